@@ -0,0 +1,29 @@
+extern crate ursa;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+/// Run the ACVP vector set in the file named by the first argument, or read from
+/// standard input, and print the response
+fn main() {
+    let request = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(&path),
+        None => {
+            let mut request = String::new();
+            io::stdin().read_to_string(&mut request).map(|_| request)
+        }
+    };
+    let request = request.unwrap_or_else(|e| {
+        eprintln!("Could not read the vector set: {}", e);
+        process::exit(2);
+    });
+    match ursa::acvp::run(&request) {
+        Ok(response) => println!("{}", response),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! `ACVP-AES-GCM`
+
+use aead::generic_array::GenericArray;
+use aead::{Aead, NewAead, Payload};
+use serde_json::Value;
+
+use super::{
+    bits_field, hex_field, hex_value, object, str_field, tests, u64_field, unsupported, Object,
+};
+use encryption::symm::prelude::{Aes128Gcm, Aes256Gcm};
+use CryptoError;
+
+const IV_BITS: u64 = 96;
+const TAG_BITS: u64 = 128;
+
+pub(super) fn group(_: &str, _: Option<&str>, group: &Value) -> Result<Object, CryptoError> {
+    let key_len = u64_field(group, "keyLen")?;
+    let iv_len = u64_field(group, "ivLen")?;
+    let tag_len = u64_field(group, "tagLen")?;
+    let iv_gen = str_field(group, "ivGen")?;
+    if iv_len != IV_BITS || tag_len != TAG_BITS || iv_gen != "external" {
+        return Err(unsupported(&format!(
+            "AES-GCM with {} IVs of {} bits and tags of {} bits",
+            iv_gen, iv_len, tag_len
+        )));
+    }
+    let encrypt = match str_field(group, "direction")? {
+        "encrypt" => true,
+        "decrypt" => false,
+        direction => return Err(unsupported(&format!("AES-GCM {}", direction))),
+    };
+    let payload_len = u64_field(group, "payloadLen")?;
+    let aad_len = u64_field(group, "aadLen")?;
+    tests(group, |test| {
+        let key = hex_field(test, "key")?;
+        let iv = hex_field(test, "iv")?;
+        let aad = bits_field(test, "aad", aad_len)?;
+        if key.len() as u64 * 8 != key_len || iv.len() as u64 * 8 != iv_len {
+            return Err(CryptoError::ParseError(
+                "AES-GCM key or IV doesn't match its group".to_string(),
+            ));
+        }
+        if encrypt {
+            let plaintext = bits_field(test, "pt", payload_len)?;
+            let mut ciphertext = seal(key_len, &key, &iv, &aad, &plaintext)?;
+            let tag = ciphertext.split_off(plaintext.len());
+            Ok(object(vec![
+                ("ct", hex_value(&ciphertext)),
+                ("tag", hex_value(&tag)),
+            ]))
+        } else {
+            let ciphertext = [
+                bits_field(test, "ct", payload_len)?,
+                bits_field(test, "tag", tag_len)?,
+            ]
+            .concat();
+            Ok(match open(key_len, &key, &iv, &aad, &ciphertext)? {
+                Some(plaintext) => object(vec![("pt", hex_value(&plaintext))]),
+                None => object(vec![("testPassed", Value::from(false))]),
+            })
+        }
+    })
+}
+
+fn seal(
+    key_len: u64,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    let iv = GenericArray::from_slice(iv);
+    match key_len {
+        128 => Aes128Gcm::new(GenericArray::from_slice(key)).encrypt(iv, payload),
+        256 => Aes256Gcm::new(GenericArray::from_slice(key)).encrypt(iv, payload),
+        _ => return Err(unsupported(&format!("AES-{}-GCM", key_len))),
+    }
+    .map_err(|_| CryptoError::GeneralError("AES-GCM encryption failed".to_string()))
+}
+
+/// The plaintext, or `None` if the tag is wrong
+fn open(
+    key_len: u64,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Option<Vec<u8>>, CryptoError> {
+    let payload = Payload {
+        msg: ciphertext,
+        aad,
+    };
+    let iv = GenericArray::from_slice(iv);
+    Ok(match key_len {
+        128 => Aes128Gcm::new(GenericArray::from_slice(key)).decrypt(iv, payload),
+        256 => Aes256Gcm::new(GenericArray::from_slice(key)).decrypt(iv, payload),
+        _ => return Err(unsupported(&format!("AES-{}-GCM", key_len))),
+    }
+    .ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(direction: &str, key_len: u64, test: Value) -> Result<Value, CryptoError> {
+        let group = serde_json::json!({
+            "direction": direction, "keyLen": key_len, "ivLen": 96, "ivGen": "external",
+            "tagLen": 128, "payloadLen": 128, "aadLen": 0, "tests": [test]
+        });
+        super::group("ACVP-AES-GCM", None, &group).map(|r| r["tests"][0].clone())
+    }
+
+    #[test]
+    fn known_answers() {
+        // Test cases 2 and 14 of the GCM specification
+        let zero_key = "00000000000000000000000000000000";
+        let zero_iv = "000000000000000000000000";
+        let zero_block = "00000000000000000000000000000000";
+        let response = run(
+            "encrypt",
+            128,
+            serde_json::json!({"tcId": 1, "key": zero_key, "iv": zero_iv, "aad": "", "pt": zero_block}),
+        )
+        .unwrap();
+        assert_eq!(response["ct"], "0388DACE60B6A392F328C2B971B2FE78");
+        assert_eq!(response["tag"], "AB6E47D42CEC13BDF53A67B21257BDDF");
+        let key = [zero_key, zero_key].concat();
+        let response = run(
+            "encrypt",
+            256,
+            serde_json::json!({"tcId": 1, "key": key, "iv": zero_iv, "aad": "", "pt": zero_block}),
+        )
+        .unwrap();
+        assert_eq!(response["ct"], "CEA7403D4D606B6E074EC5D3BAF39D18");
+        assert_eq!(response["tag"], "D0D1C8A799996BF0265B98B5D48AB919");
+
+        let decrypt = |tag: &str| {
+            run(
+                "decrypt",
+                128,
+                serde_json::json!({"tcId": 1, "key": zero_key, "iv": zero_iv, "aad": "",
+                    "ct": "0388DACE60B6A392F328C2B971B2FE78", "tag": tag}),
+            )
+            .unwrap()
+        };
+        let response = decrypt("AB6E47D42CEC13BDF53A67B21257BDDF");
+        assert_eq!(response["pt"], zero_block.to_uppercase());
+        assert!(response.get("testPassed").is_none());
+        let response = decrypt("AB6E47D42CEC13BDF53A67B21257BDDE");
+        assert_eq!(response["testPassed"], false);
+        assert!(response.get("pt").is_none());
+    }
+
+    #[test]
+    fn unsupported_groups() {
+        let test = serde_json::json!({"tcId": 1, "key": "00".repeat(24),
+            "iv": "000000000000000000000000", "aad": "", "pt": "00".repeat(16)});
+        assert!(matches!(
+            run("encrypt", 192, test.clone()),
+            Err(CryptoError::NoSuchAlgorithm(_))
+        ));
+        let group = serde_json::json!({
+            "direction": "encrypt", "keyLen": 128, "ivLen": 96, "ivGen": "internal",
+            "tagLen": 128, "payloadLen": 0, "aadLen": 0, "tests": [test]
+        });
+        assert!(matches!(
+            super::group("ACVP-AES-GCM", None, &group),
+            Err(CryptoError::NoSuchAlgorithm(_))
+        ));
+    }
+}
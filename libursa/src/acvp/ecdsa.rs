@@ -0,0 +1,149 @@
+//! `ECDSA` key and signature verification on P-256
+
+use serde_json::Value;
+
+use super::{hex_field, object, sha, str_field, tests, unsupported, Object};
+use signatures::ecdsa_p256::{p256_point, verify_es256_digest};
+use CryptoError;
+
+const FIELD_SIZE: usize = 32;
+
+pub(super) fn group(_: &str, mode: Option<&str>, group: &Value) -> Result<Object, CryptoError> {
+    let curve = str_field(group, "curve")?;
+    if curve != "P-256" {
+        return Err(unsupported(&format!("ECDSA on {}", curve)));
+    }
+    match mode {
+        Some("keyVer") => tests(group, |test| {
+            let valid = point(test)?.is_some_and(|q| p256_point(&q).is_some());
+            Ok(object(vec![("testPassed", Value::from(valid))]))
+        }),
+        Some("sigVer") => {
+            let hash_alg = str_field(group, "hashAlg")?;
+            if !sha::is_supported(hash_alg) {
+                return Err(unsupported(&format!("ECDSA with {}", hash_alg)));
+            }
+            if group.get("conformance").is_some() {
+                return Err(unsupported("randomized message hashing"));
+            }
+            tests(group, |test| {
+                let digest = sha::digest(hash_alg, &hex_field(test, "message")?)
+                    .expect("the hash is supported");
+                let signature = match (scalar(test, "r")?, scalar(test, "s")?) {
+                    (Some(r), Some(s)) => [r, s].concat(),
+                    _ => Vec::new(),
+                };
+                let valid = point(test)?
+                    .is_some_and(|q| verify_es256_digest(&q, &field_digest(&digest), &signature));
+                Ok(object(vec![("testPassed", Value::from(valid))]))
+            })
+        }
+        mode => Err(unsupported(&format!("ECDSA {}", mode.unwrap_or("")))),
+    }
+}
+
+/// The uncompressed encoding of the test's `qx` and `qy`, if they fit in the field size
+fn point(test: &Value) -> Result<Option<Vec<u8>>, CryptoError> {
+    Ok(match (scalar(test, "qx")?, scalar(test, "qy")?) {
+        (Some(x), Some(y)) => Some([&[4u8][..], &x, &y].concat()),
+        _ => None,
+    })
+}
+
+/// The hex number `name` of `test` as `FIELD_SIZE` big endian bytes, if it fits
+fn scalar(test: &Value, name: &str) -> Result<Option<Vec<u8>>, CryptoError> {
+    let bytes = hex_field(test, name)?;
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    if bytes.len() > FIELD_SIZE {
+        return Ok(None);
+    }
+    let mut padded = vec![0u8; FIELD_SIZE - bytes.len()];
+    padded.extend_from_slice(bytes);
+    Ok(Some(padded))
+}
+
+/// The leftmost bits of `digest` that fit the order of the curve, as ECDSA uses them
+fn field_digest(digest: &[u8]) -> Vec<u8> {
+    if digest.len() >= FIELD_SIZE {
+        digest[..FIELD_SIZE].to_vec()
+    } else {
+        let mut padded = vec![0u8; FIELD_SIZE - digest.len()];
+        padded.extend_from_slice(digest);
+        padded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    use p256::{ProjectivePoint, Scalar};
+    use sha2::{Digest, Sha384};
+    use signatures::ecdsa_p256::{field_bytes, p256_scalar};
+
+    // The key of the first P-256 vector of the FIPS 186-4 SigGen test vectors
+    const D: &str = "519b423d715f8b581f4fa8ee59f4771a5b44c8130b4e3eacca54a56dda72b464";
+    const Q_X: &str = "1ccbe91c075fc7f4f033bfa248db8fccd3565de94bbfb12f3c59ff46c271bf83";
+    const Q_Y: &str = "ce4014c68811f9a21a1fdb2c0e6113e06db7ca93b7404e78dc7ccd5ca89a4ca9";
+
+    /// Sign the SHA-384 hash of `message` with a fixed nonce
+    fn sign(message: &[u8]) -> (String, String) {
+        let d = p256_scalar(&hex::decode(D).unwrap()).unwrap();
+        let k = Scalar::from(0x1234_5678u64);
+        let digest = Sha384::digest(message);
+        let e = Scalar::from_bytes_reduced(&field_bytes(&digest[..FIELD_SIZE]));
+        let r = Scalar::from_bytes_reduced(
+            (ProjectivePoint::generator() * k)
+                .to_affine()
+                .to_encoded_point(false)
+                .x(),
+        );
+        let s = k.invert().unwrap() * (e + r * d);
+        (hex::encode(r.to_bytes()), hex::encode(s.to_bytes()))
+    }
+
+    fn run(mode: &str, group: Value) -> Vec<Value> {
+        let response = super::group("ECDSA", Some(mode), &group).unwrap();
+        response["tests"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["testPassed"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn key_verification() {
+        let (long, padded) = (["01", Q_X].concat(), ["00", Q_X].concat());
+        let results = run(
+            "keyVer",
+            serde_json::json!({"curve": "P-256", "tests": [
+                {"tcId": 1, "qx": Q_X, "qy": Q_Y},
+                {"tcId": 2, "qx": Q_X, "qy": Q_X},
+                {"tcId": 3, "qx": long, "qy": Q_Y},
+                {"tcId": 4, "qx": padded, "qy": Q_Y}
+            ]}),
+        );
+        assert_eq!(results, vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn signature_verification() {
+        let (r, s) = sign(b"message");
+        let results = run(
+            "sigVer",
+            serde_json::json!({"curve": "P-256", "hashAlg": "SHA2-384", "tests": [
+                {"tcId": 1, "message": "6d657373616765", "qx": Q_X, "qy": Q_Y, "r": r, "s": s},
+                {"tcId": 2, "message": "6d657373616766", "qx": Q_X, "qy": Q_Y, "r": r, "s": s},
+                {"tcId": 3, "message": "6d657373616765", "qx": Q_X, "qy": Q_Y, "r": s, "s": r}
+            ]}),
+        );
+        assert_eq!(results, vec![true, false, false]);
+
+        let group = serde_json::json!({"curve": "P-384", "tests": []});
+        assert!(super::group("ECDSA", Some("sigVer"), &group).is_err());
+        let group = serde_json::json!({"curve": "P-256", "tests": []});
+        assert!(super::group("ECDSA", Some("sigGen"), &group).is_err());
+    }
+}
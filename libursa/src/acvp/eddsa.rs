@@ -0,0 +1,146 @@
+//! `EDDSA` with pure Ed25519
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use serde_json::Value;
+
+use super::{hex_field, hex_value, object, str_field, tests, unsupported, Object};
+use keys::PublicKey;
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+const SEED_SIZE: usize = 32;
+
+pub(super) fn group(_: &str, mode: Option<&str>, group: &Value) -> Result<Object, CryptoError> {
+    let curve = str_field(group, "curve")?;
+    if curve != "ED-25519" {
+        return Err(unsupported(&format!("EdDSA on {}", curve)));
+    }
+    if group.get("preHash").and_then(Value::as_bool) == Some(true) {
+        return Err(unsupported("Ed25519ph"));
+    }
+    let scheme = Ed25519Sha512::new();
+    match mode {
+        Some("keyGen") => tests(group, |_| {
+            let (public_key, private_key) = scheme.keypair(None)?;
+            Ok(object(vec![
+                ("d", hex_value(&private_key[..SEED_SIZE])),
+                ("q", hex_value(&public_key[..])),
+            ]))
+        }),
+        Some("keyVer") => tests(group, |test| {
+            let valid = is_valid_point(&hex_field(test, "q")?);
+            Ok(object(vec![("testPassed", Value::from(valid))]))
+        }),
+        Some("sigGen") => {
+            // One key signs all the tests of the group, and the response gives its public key
+            let (public_key, private_key) = scheme.keypair(None)?;
+            let mut response = tests(group, |test| {
+                if !test.get("context").is_none_or(|c| c == "") {
+                    return Err(unsupported("Ed25519 with a context"));
+                }
+                let signature = scheme.sign(&hex_field(test, "message")?, &private_key)?;
+                Ok(object(vec![("signature", hex_value(&signature))]))
+            })?;
+            response.insert("q".to_string(), hex_value(&public_key[..]));
+            Ok(response)
+        }
+        Some("sigVer") => tests(group, |test| {
+            let q = hex_field(test, "q")?;
+            let valid = is_valid_point(&q)
+                && scheme
+                    .verify(
+                        &hex_field(test, "message")?,
+                        &hex_field(test, "signature")?,
+                        &PublicKey(q),
+                    )
+                    .unwrap_or(false);
+            Ok(object(vec![("testPassed", Value::from(valid))]))
+        }),
+        mode => Err(unsupported(&format!("EdDSA {}", mode.unwrap_or("")))),
+    }
+}
+
+/// Whether `q` is the canonical encoding of a point on the curve
+fn is_valid_point(q: &[u8]) -> bool {
+    if q.len() != 32 {
+        return false;
+    }
+    let compressed = CompressedEdwardsY::from_slice(q);
+    compressed
+        .decompress()
+        .is_some_and(|p| p.compress() == compressed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 8032 section 7.1, test 1
+    const Q: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+                             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+    fn run(mode: &str, group: Value) -> Value {
+        Value::Object(super::group("EDDSA", Some(mode), &group).unwrap())
+    }
+
+    #[test]
+    fn verification() {
+        let response = run(
+            "sigVer",
+            serde_json::json!({"curve": "ED-25519", "preHash": false, "tests": [
+                {"tcId": 1, "message": "", "q": Q, "signature": SIGNATURE},
+                {"tcId": 2, "message": "00", "q": Q, "signature": SIGNATURE}
+            ]}),
+        );
+        assert_eq!(response["tests"][0]["testPassed"], true);
+        assert_eq!(response["tests"][1]["testPassed"], false);
+
+        // The y coordinate of the second point is not reduced
+        let response = run(
+            "keyVer",
+            serde_json::json!({"curve": "ED-25519", "tests": [
+                {"tcId": 1, "q": Q},
+                {"tcId": 2, "q": "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f"},
+                {"tcId": 3, "q": "d75a"}
+            ]}),
+        );
+        let results = response["tests"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["testPassed"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![true, false, false]);
+    }
+
+    #[test]
+    fn generation() {
+        let response = run(
+            "keyGen",
+            serde_json::json!({"curve": "ED-25519", "tests": [{"tcId": 1}]}),
+        );
+        let d = hex::decode(response["tests"][0]["d"].as_str().unwrap()).unwrap();
+        let q = hex::decode(response["tests"][0]["q"].as_str().unwrap()).unwrap();
+        assert_eq!(d.len(), SEED_SIZE);
+        assert!(is_valid_point(&q));
+
+        let response = run(
+            "sigGen",
+            serde_json::json!({"curve": "ED-25519", "preHash": false, "tests": [
+                {"tcId": 1, "message": "6d657373616765"}
+            ]}),
+        );
+        let q = PublicKey(hex::decode(response["q"].as_str().unwrap()).unwrap());
+        let signature = hex::decode(response["tests"][0]["signature"].as_str().unwrap()).unwrap();
+        assert!(Ed25519Sha512::new()
+            .verify(b"message", &signature, &q)
+            .unwrap());
+
+        let group = serde_json::json!({"curve": "ED-25519", "preHash": true, "tests": []});
+        assert!(super::group("EDDSA", Some("sigGen"), &group).is_err());
+        let group = serde_json::json!({"curve": "ED-448", "tests": []});
+        assert!(super::group("EDDSA", Some("keyGen"), &group).is_err());
+    }
+}
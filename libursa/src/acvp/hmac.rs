@@ -0,0 +1,90 @@
+//! `HMAC-SHA2-*` and `HMAC-SHA3-*`
+
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512Trunc224, Sha512Trunc256};
+use sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
+
+use super::{hex_field, hex_value, object, str_field, tests, u64_field, unsupported, Object};
+use CryptoError;
+
+macro_rules! mac {
+    ($hash:ty, $key:expr, $message:expr) => {{
+        let mut mac = Hmac::<$hash>::new_varkey($key).expect("HMAC takes keys of any size");
+        mac.input($message);
+        mac.result().code().to_vec()
+    }};
+}
+
+fn hmac(algorithm: &str, key: &[u8], message: &[u8]) -> Option<Vec<u8>> {
+    Some(match algorithm {
+        "HMAC-SHA2-224" => mac!(Sha224, key, message),
+        "HMAC-SHA2-256" => mac!(Sha256, key, message),
+        "HMAC-SHA2-384" => mac!(Sha384, key, message),
+        "HMAC-SHA2-512" => mac!(Sha512, key, message),
+        "HMAC-SHA2-512/224" => mac!(Sha512Trunc224, key, message),
+        "HMAC-SHA2-512/256" => mac!(Sha512Trunc256, key, message),
+        "HMAC-SHA3-224" => mac!(Sha3_224, key, message),
+        "HMAC-SHA3-256" => mac!(Sha3_256, key, message),
+        "HMAC-SHA3-384" => mac!(Sha3_384, key, message),
+        "HMAC-SHA3-512" => mac!(Sha3_512, key, message),
+        _ => return None,
+    })
+}
+
+pub(super) fn group(
+    algorithm: &str,
+    _: Option<&str>,
+    group: &Value,
+) -> Result<Object, CryptoError> {
+    if hmac(algorithm, b"", b"").is_none() {
+        return Err(unsupported(algorithm));
+    }
+    let test_type = str_field(group, "testType")?;
+    if test_type != "AFT" {
+        return Err(unsupported(&format!("{} {}", algorithm, test_type)));
+    }
+    let mac_len = u64_field(group, "macLen")?;
+    tests(group, |test| {
+        let mut mac = hmac(
+            algorithm,
+            &hex_field(test, "key")?,
+            &hex_field(test, "msg")?,
+        )
+        .expect("the algorithm is supported");
+        if !mac_len.is_multiple_of(8) || mac_len / 8 > mac.len() as u64 {
+            return Err(unsupported(&format!("MACs of {} bits", mac_len)));
+        }
+        mac.truncate((mac_len / 8) as usize);
+        Ok(object(vec![("mac", hex_value(&mac))]))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_answers() {
+        // RFC 4231 test case 2, also truncated to 128 bits
+        let test = |mac_len| {
+            let group = serde_json::json!({"testType": "AFT", "macLen": mac_len, "tests": [
+                {"tcId": 1, "key": "4a656665",
+                 "msg": "7768617420646f2079612077616e7420666f72206e6f7468696e673f"}
+            ]});
+            Value::Object(super::group("HMAC-SHA2-256", None, &group).unwrap())["tests"][0]["mac"]
+                .clone()
+        };
+        assert_eq!(
+            test(256),
+            "5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843"
+        );
+        assert_eq!(test(128), "5BDCC146BF60754E6A042426089575C7");
+
+        let group = serde_json::json!({"testType": "AFT", "macLen": 520, "tests": [
+            {"tcId": 1, "key": "00", "msg": "00"}
+        ]});
+        assert!(super::group("HMAC-SHA2-256", None, &group).is_err());
+        assert!(super::group("HMAC-SHA1", None, &group).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! A client for the test vectors of NIST's Automated Cryptographic Validation Protocol.
+//!
+//! FIPS 140-3 validation exercises an implementation with vector sets downloaded from an
+//! ACVP server and checks the responses it sends back. [`run`] takes a vector set as
+//! the server returns it, i.e. a JSON array of the version header and the vector set,
+//! or just the vector set, runs each test against the implementations in this crate and
+//! returns the response in the same form. Talking to the server, and the registration
+//! and authentication that entails, is left to the tools of the validation lab.
+//!
+//! The supported algorithms and test types are
+//!
+//! * `SHA2-224` to `SHA2-512/256` and `SHA3-224` to `SHA3-512`: AFT and the standard
+//!   MCT, for byte oriented messages
+//! * `HMAC-SHA2-*` and `HMAC-SHA3-*`: AFT
+//! * `ACVP-AES-GCM`: AES-128 and AES-256 with external 96 bit IVs and 128 bit tags
+//! * `ECDSA`: `keyVer` and `sigVer` on P-256
+//! * `EDDSA`: `keyGen`, `keyVer`, `sigGen` and `sigVer` of pure Ed25519
+//!
+//! A vector set using anything else is an error rather than a partial response, so a
+//! response is never missing tests. `bin/acvp.rs` runs a vector set from a file.
+
+mod aes_gcm;
+mod ecdsa;
+mod eddsa;
+mod hmac;
+mod sha;
+
+use serde_json::{Map, Value};
+
+use CryptoError;
+
+type Object = Map<String, Value>;
+
+/// Run the ACVP vector set `request` and return the response as JSON
+pub fn run(request: &str) -> Result<String, CryptoError> {
+    let request: Value = serde_json::from_str(request)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid ACVP request: {}", e)))?;
+    let response = match request {
+        Value::Array(parts) => {
+            let mut response = Vec::with_capacity(parts.len());
+            for part in parts {
+                if part.get("acvVersion").is_some() {
+                    response.push(part);
+                } else {
+                    response.push(Value::Object(run_vector_set(&part)?));
+                }
+            }
+            Value::Array(response)
+        }
+        vector_set => Value::Object(run_vector_set(&vector_set)?),
+    };
+    serde_json::to_string_pretty(&response)
+        .map_err(|e| CryptoError::GeneralError(format!("Response not serialized: {}", e)))
+}
+
+/// Run the tests of one vector set
+pub fn run_vector_set(vector_set: &Value) -> Result<Object, CryptoError> {
+    let algorithm = str_field(vector_set, "algorithm")?;
+    let mode = vector_set.get("mode").and_then(Value::as_str);
+    let handler: fn(&str, Option<&str>, &Value) -> Result<Object, CryptoError> =
+        if sha::is_supported(algorithm) {
+            sha::group
+        } else if algorithm.starts_with("HMAC-") {
+            hmac::group
+        } else if algorithm == "ACVP-AES-GCM" {
+            aes_gcm::group
+        } else if algorithm == "ECDSA" {
+            ecdsa::group
+        } else if algorithm == "EDDSA" {
+            eddsa::group
+        } else {
+            return Err(unsupported(algorithm));
+        };
+
+    let mut groups = Vec::new();
+    for group in array_field(vector_set, "testGroups")? {
+        let mut response = Object::new();
+        response.insert("tgId".to_string(), field(group, "tgId")?.clone());
+        response.extend(handler(algorithm, mode, group)?);
+        groups.push(Value::Object(response));
+    }
+
+    let mut response = Object::new();
+    response.insert("vsId".to_string(), field(vector_set, "vsId")?.clone());
+    response.insert("algorithm".to_string(), Value::from(algorithm));
+    if let Some(mode) = mode {
+        response.insert("mode".to_string(), Value::from(mode));
+    }
+    if let Some(revision) = vector_set.get("revision") {
+        response.insert("revision".to_string(), revision.clone());
+    }
+    response.insert("testGroups".to_string(), Value::Array(groups));
+    Ok(response)
+}
+
+/// The responses to the tests of `group`, each computed by `f` and given its `tcId`
+fn tests<F>(group: &Value, mut f: F) -> Result<Object, CryptoError>
+where
+    F: FnMut(&Value) -> Result<Object, CryptoError>,
+{
+    let mut responses = Vec::new();
+    for test in array_field(group, "tests")? {
+        let mut response = Object::new();
+        response.insert("tcId".to_string(), field(test, "tcId")?.clone());
+        response.extend(f(test)?);
+        responses.push(Value::Object(response));
+    }
+    let mut tests = Object::new();
+    tests.insert("tests".to_string(), Value::Array(responses));
+    Ok(tests)
+}
+
+fn object(entries: Vec<(&str, Value)>) -> Object {
+    entries
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, CryptoError> {
+    value
+        .get(name)
+        .ok_or_else(|| CryptoError::ParseError(format!("ACVP field {} is missing", name)))
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, CryptoError> {
+    field(value, name)?
+        .as_str()
+        .ok_or_else(|| CryptoError::ParseError(format!("ACVP field {} is not a string", name)))
+}
+
+fn u64_field(value: &Value, name: &str) -> Result<u64, CryptoError> {
+    field(value, name)?
+        .as_u64()
+        .ok_or_else(|| CryptoError::ParseError(format!("ACVP field {} is not a number", name)))
+}
+
+fn array_field<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, CryptoError> {
+    field(value, name)?
+        .as_array()
+        .ok_or_else(|| CryptoError::ParseError(format!("ACVP field {} is not an array", name)))
+}
+
+fn hex_field(value: &Value, name: &str) -> Result<Vec<u8>, CryptoError> {
+    hex::decode(str_field(value, name)?)
+        .map_err(|_| CryptoError::ParseError(format!("ACVP field {} is not hex", name)))
+}
+
+/// A hex field holding `bits` bits, which must be a whole number of bytes
+fn bits_field(value: &Value, name: &str, bits: u64) -> Result<Vec<u8>, CryptoError> {
+    let mut bytes = hex_field(value, name)?;
+    if !bits.is_multiple_of(8) || bytes.len() < (bits / 8) as usize {
+        return Err(unsupported(&format!("{} of {} bits", name, bits)));
+    }
+    bytes.truncate((bits / 8) as usize);
+    Ok(bytes)
+}
+
+fn hex_value(bytes: &[u8]) -> Value {
+    Value::from(hex::encode_upper(bytes))
+}
+
+fn unsupported(what: &str) -> CryptoError {
+    CryptoError::NoSuchAlgorithm(format!("ACVP tests of {} are not supported", what))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_the_header() {
+        let request = r#"[
+            {"acvVersion": "1.0"},
+            {"vsId": 7, "algorithm": "SHA2-256", "revision": "1.0", "testGroups": [
+                {"tgId": 1, "testType": "AFT", "tests": [
+                    {"tcId": 1, "msg": "616263", "len": 24}
+                ]}
+            ]}
+        ]"#;
+        let response: Value = serde_json::from_str(&run(request).unwrap()).unwrap();
+        assert_eq!(response[0]["acvVersion"], "1.0");
+        assert_eq!(response[1]["vsId"], 7);
+        assert_eq!(response[1]["testGroups"][0]["tgId"], 1);
+        assert_eq!(response[1]["testGroups"][0]["tests"][0]["tcId"], 1);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_run() {
+        let vector_set = serde_json::json!({
+            "vsId": 1, "algorithm": "ACVP-TDES-ECB", "testGroups": []
+        });
+        assert!(matches!(
+            run_vector_set(&vector_set),
+            Err(CryptoError::NoSuchAlgorithm(_))
+        ));
+        let vector_set = serde_json::json!({
+            "vsId": 1, "algorithm": "SHA2-256", "testGroups": [
+                {"tgId": 1, "testType": "AFT", "tests": [{"tcId": 1, "msg": "00", "len": 7}]}
+            ]
+        });
+        assert!(run_vector_set(&vector_set).is_err());
+        assert!(run("{").is_err());
+        assert!(run(r#"{"vsId": 1, "algorithm": "SHA2-256"}"#).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! `SHA2-*` and `SHA3-*`
+
+use serde_json::Value;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512, Sha512Trunc224, Sha512Trunc256};
+use sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
+
+use super::{bits_field, hex_value, object, str_field, tests, u64_field, unsupported, Object};
+use CryptoError;
+
+const MCT_ROUNDS: usize = 100;
+const MCT_ITERATIONS: usize = 1000;
+
+pub(super) fn is_supported(algorithm: &str) -> bool {
+    digest(algorithm, b"").is_some()
+}
+
+/// Hash `message` with the hash ACVP calls `algorithm`
+pub(super) fn digest(algorithm: &str, message: &[u8]) -> Option<Vec<u8>> {
+    Some(match algorithm {
+        "SHA2-224" => Sha224::digest(message).to_vec(),
+        "SHA2-256" => Sha256::digest(message).to_vec(),
+        "SHA2-384" => Sha384::digest(message).to_vec(),
+        "SHA2-512" => Sha512::digest(message).to_vec(),
+        "SHA2-512/224" => Sha512Trunc224::digest(message).to_vec(),
+        "SHA2-512/256" => Sha512Trunc256::digest(message).to_vec(),
+        "SHA3-224" => Sha3_224::digest(message).to_vec(),
+        "SHA3-256" => Sha3_256::digest(message).to_vec(),
+        "SHA3-384" => Sha3_384::digest(message).to_vec(),
+        "SHA3-512" => Sha3_512::digest(message).to_vec(),
+        _ => return None,
+    })
+}
+
+pub(super) fn group(
+    algorithm: &str,
+    _: Option<&str>,
+    group: &Value,
+) -> Result<Object, CryptoError> {
+    let hash = |message: &[u8]| digest(algorithm, message).expect("the algorithm is supported");
+    match str_field(group, "testType")? {
+        "AFT" => tests(group, |test| {
+            let message = bits_field(test, "msg", u64_field(test, "len")?)?;
+            Ok(object(vec![("md", hex_value(&hash(&message)))]))
+        }),
+        "MCT" => {
+            if group
+                .get("mctVersion")
+                .is_some_and(|v| v.as_str() != Some("standard"))
+            {
+                return Err(unsupported("the alternate MCT"));
+            }
+            tests(group, |test| {
+                let seed = bits_field(test, "msg", u64_field(test, "len")?)?;
+                let results = if algorithm.starts_with("SHA2-") {
+                    sha2_mct(&hash, seed)
+                } else {
+                    sha3_mct(&hash, seed)
+                };
+                let results = results
+                    .iter()
+                    .map(|md| Value::Object(object(vec![("md", hex_value(md))])))
+                    .collect();
+                Ok(object(vec![("resultsArray", Value::Array(results))]))
+            })
+        }
+        test_type => Err(unsupported(&format!("{} {}", algorithm, test_type))),
+    }
+}
+
+/// Each digest of a round hashes the three before it, starting from three copies of the
+/// seed, and each round starts from its last digest
+fn sha2_mct<F: Fn(&[u8]) -> Vec<u8>>(hash: &F, mut seed: Vec<u8>) -> Vec<Vec<u8>> {
+    (0..MCT_ROUNDS)
+        .map(|_| {
+            let (mut a, mut b, mut c) = (seed.clone(), seed.clone(), seed.clone());
+            for _ in 0..MCT_ITERATIONS {
+                let md = hash(&[&a[..], &b[..], &c[..]].concat());
+                a = b;
+                b = c;
+                c = md;
+            }
+            seed = c;
+            seed.clone()
+        })
+        .collect()
+}
+
+/// Each digest of a round hashes the one before it
+fn sha3_mct<F: Fn(&[u8]) -> Vec<u8>>(hash: &F, mut seed: Vec<u8>) -> Vec<Vec<u8>> {
+    (0..MCT_ROUNDS)
+        .map(|_| {
+            for _ in 0..MCT_ITERATIONS {
+                seed = hash(&seed);
+            }
+            seed.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_answers() {
+        let group = serde_json::json!({"tgId": 1, "testType": "AFT", "tests": [
+            {"tcId": 1, "msg": "616263", "len": 24},
+            {"tcId": 2, "msg": "61626364", "len": 24}
+        ]});
+        let response = Value::Object(super::group("SHA2-256", None, &group).unwrap());
+        // The FIPS 180-2 example, and the message is cut to its length
+        for i in 0..2 {
+            assert_eq!(
+                response["tests"][i]["md"],
+                "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD"
+            );
+        }
+        let response = Value::Object(super::group("SHA3-256", None, &group).unwrap());
+        assert_eq!(
+            response["tests"][0]["md"],
+            "3A985DA74FE225B2045C172D6BD390BD855F086E3E9D525B46BFE24511431532"
+        );
+        assert!(super::group("SHA2-256", None, &serde_json::json!({"testType": "LDT"})).is_err());
+    }
+
+    #[test]
+    fn monte_carlo() {
+        let hash = |m: &[u8]| Sha256::digest(m).to_vec();
+        let seed = vec![0u8; 32];
+        let results = sha2_mct(&hash, seed.clone());
+        assert_eq!(results.len(), MCT_ROUNDS);
+        // The first round by hand
+        let (mut a, mut b, mut c) = (seed.clone(), seed.clone(), seed);
+        for _ in 0..MCT_ITERATIONS {
+            let md = hash(&[&a[..], &b[..], &c[..]].concat());
+            a = b;
+            b = c;
+            c = md;
+        }
+        assert_eq!(results[0], c);
+        assert_ne!(results[1], results[0]);
+
+        let results = sha3_mct(&hash, vec![0u8; 32]);
+        let mut md = vec![0u8; 32];
+        for _ in 0..MCT_ITERATIONS {
+            md = hash(&md);
+        }
+        assert_eq!(results[0], md);
+    }
+}
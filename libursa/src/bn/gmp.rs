@@ -0,0 +1,753 @@
+use errors::prelude::*;
+
+use gmp::mpz::{Mpz, ParseMpzError, ProbabPrimeResult};
+use gmp::sign::Sign;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{self, Digest};
+
+#[cfg(feature = "serde")]
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::cmp::Ord;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Miller-Rabin rounds after GMP's Baillie-PSW test
+const PRIME_CHECKS: i32 = 25;
+
+/// Candidates sieved from one random starting point before choosing another
+const SIEVE_RANGE: u64 = 1 << 20;
+
+pub struct BigNumberContext;
+
+pub struct BigNumber {
+    bn: Mpz,
+}
+
+impl BigNumber {
+    pub fn new_context() -> UrsaCryptoResult<BigNumberContext> {
+        Ok(BigNumberContext {})
+    }
+
+    pub fn new() -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber { bn: Mpz::new() })
+    }
+
+    pub fn generate_prime(size: usize) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: BigNumber::_search_prime(size, false)?,
+        })
+    }
+
+    pub fn generate_safe_prime(size: usize) -> UrsaCryptoResult<BigNumber> {
+        // Like OpenSSL, (p - 1) / 2 has `size` bits
+        let q = BigNumber::_search_prime(size, true)?;
+        Ok(BigNumber {
+            bn: (q << 1) + 1u64,
+        })
+    }
+
+    /// A prime of exactly `size` bits, or one where `2 * q + 1` is also prime,
+    /// found by sieving odd candidates from a random start with the small primes
+    fn _search_prime(size: usize, safe: bool) -> UrsaCryptoResult<Mpz> {
+        if size < 2 {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                format!("Cannot generate a prime of {} bits", size),
+            ));
+        }
+        // Candidates are above every prime sieved with so divisibility means composite
+        let sieve = SMALL_PRIMES
+            .iter()
+            .filter(|p| 64 - p.leading_zeros() < (size - 1) as u32)
+            .collect::<Vec<_>>();
+
+        let mut iteration = 0;
+        loop {
+            let mut start = BigNumber::_random_bits(size)?;
+            start.setbit(size - 1);
+            start.setbit(size - 2);
+            start.setbit(0);
+            let residues = sieve
+                .iter()
+                .map(|p| Option::<u64>::from(&(&start % **p)).unwrap_or(0))
+                .collect::<Vec<_>>();
+
+            for delta in (0..SIEVE_RANGE).step_by(2) {
+                let composite = sieve.iter().zip(residues.iter()).any(|(p, r)| {
+                    let r = (r + delta) % **p;
+                    r == 0 || (safe && (2 * r + 1) % **p == 0)
+                });
+                if composite {
+                    continue;
+                }
+                iteration += 1;
+
+                let candidate = &start + delta;
+                if candidate.bit_length() != size {
+                    break;
+                }
+                if !safe {
+                    if candidate.probab_prime(PRIME_CHECKS) != ProbabPrimeResult::NotPrime {
+                        debug!("Found prime in {} iteration", iteration);
+                        return Ok(candidate);
+                    }
+                    continue;
+                }
+                // One cheap round for both before the full tests
+                let safe_prime = (&candidate << 1) + 1u64;
+                if candidate.probab_prime(1) != ProbabPrimeResult::NotPrime
+                    && safe_prime.probab_prime(1) != ProbabPrimeResult::NotPrime
+                    && candidate.probab_prime(PRIME_CHECKS) != ProbabPrimeResult::NotPrime
+                    && safe_prime.probab_prime(PRIME_CHECKS) != ProbabPrimeResult::NotPrime
+                {
+                    debug!("Found safe prime in {} iteration", iteration);
+                    return Ok(candidate);
+                }
+            }
+        }
+    }
+
+    fn _random_bits(size: usize) -> UrsaCryptoResult<Mpz> {
+        let mut bytes = vec![0u8; size.div_ceil(8)];
+        OsRng.try_fill_bytes(&mut bytes)?;
+        let excess = bytes.len() * 8 - size;
+        if let Some(top) = bytes.first_mut() {
+            *top &= 0xff >> excess;
+        }
+        Ok(Mpz::from(&bytes[..]))
+    }
+
+    pub fn generate_prime_in_range(
+        start: &BigNumber,
+        end: &BigNumber,
+    ) -> UrsaCryptoResult<BigNumber> {
+        let (start, end) = if start > end {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        if start.is_negative() {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                format!("Invalid number for 'start': {:?}", start),
+            ));
+        }
+        let range = end.sub(start)?;
+
+        let mut iteration = 0;
+        loop {
+            let mut prime = range.rand_range()?.add(start)?;
+            prime.bn.setbit(0);
+
+            if prime < *end && prime.is_prime(None)? {
+                debug!("Found prime in {} iteration", iteration);
+                return Ok(prime);
+            }
+            iteration += 1;
+        }
+    }
+
+    pub fn is_prime(&self, _ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<bool> {
+        Ok(
+            !self.is_negative()
+                && self.bn.probab_prime(PRIME_CHECKS) != ProbabPrimeResult::NotPrime,
+        )
+    }
+
+    pub fn is_safe_prime(&self, ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<bool> {
+        // A safe prime above 7 is congruent to 2 mod 3, like OpenSSL this rejects 7
+        Ok(
+            self.modulus(&BigNumber::from_u32(3)?, None)? == BigNumber::from_u32(2)?
+                && self.is_prime(ctx)?
+                && self.rshift1()?.is_prime(None)?,
+        )
+    }
+
+    pub fn rand(size: usize) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: BigNumber::_random_bits(size)?,
+        })
+    }
+
+    pub fn rand_range(&self) -> UrsaCryptoResult<BigNumber> {
+        if self.bn.sign() != Sign::Positive {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "An error in rand_range".to_string(),
+            ));
+        }
+        // Rejection sampling takes fewer than two tries on average
+        let size = self.bn.bit_length();
+        loop {
+            let bn = BigNumber::_random_bits(size)?;
+            if bn < self.bn {
+                return Ok(BigNumber { bn });
+            }
+        }
+    }
+
+    pub fn num_bits(&self) -> UrsaCryptoResult<i32> {
+        if self.bn.is_zero() {
+            Ok(0)
+        } else {
+            Ok(self.bn.bit_length() as i32)
+        }
+    }
+
+    pub fn is_bit_set(&self, n: i32) -> UrsaCryptoResult<bool> {
+        Ok(self.bn.abs().tstbit(n as usize))
+    }
+
+    pub fn set_bit(&mut self, n: i32) -> UrsaCryptoResult<&mut BigNumber> {
+        // Of the absolute value, like OpenSSL
+        let mut abs = self.bn.abs();
+        abs.setbit(n as usize);
+        self.bn = if self.is_negative() { -abs } else { abs };
+        Ok(self)
+    }
+
+    pub fn from_u32(n: usize) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: Mpz::from(n as u64),
+        })
+    }
+
+    pub fn from_dec(dec: &str) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: Mpz::from_str_radix(dec, 10)?,
+        })
+    }
+
+    pub fn from_hex(hex: &str) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: Mpz::from_str_radix(hex, 16)?,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: Mpz::from(bytes),
+        })
+    }
+
+    pub fn to_dec(&self) -> UrsaCryptoResult<String> {
+        Ok(self.bn.to_str_radix(10))
+    }
+
+    pub fn to_hex(&self) -> UrsaCryptoResult<String> {
+        Ok(self.bn.to_str_radix(16).to_uppercase())
+    }
+
+    pub fn to_bytes(&self) -> UrsaCryptoResult<Vec<u8>> {
+        // Zero has no bytes, like OpenSSL
+        if self.bn.is_zero() {
+            Ok(Vec::new())
+        } else {
+            Ok(Vec::from(&self.bn))
+        }
+    }
+
+    pub fn hash(data: &[u8]) -> UrsaCryptoResult<Vec<u8>> {
+        Ok(sha2::Sha256::digest(data).as_slice().to_vec())
+    }
+
+    pub fn add(&self, a: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn + &a.bn,
+        })
+    }
+
+    pub fn sub(&self, a: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn - &a.bn,
+        })
+    }
+
+    pub fn sqr(&self, _ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn * &self.bn,
+        })
+    }
+
+    pub fn mul(
+        &self,
+        a: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn * &a.bn,
+        })
+    }
+
+    pub fn mod_mul(
+        &self,
+        a: &BigNumber,
+        n: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        self.mul(a, None)?.modulus(n, None)
+    }
+
+    pub fn mod_sub(
+        &self,
+        a: &BigNumber,
+        n: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        self.sub(a)?.modulus(n, None)
+    }
+
+    pub fn div(
+        &self,
+        a: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        if a.bn.is_zero() {
+            Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "a cannot be zero".to_string(),
+            ))
+        } else {
+            Ok(BigNumber {
+                bn: &self.bn / &a.bn,
+            })
+        }
+    }
+
+    pub fn gcd(
+        a: &BigNumber,
+        b: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: a.bn.gcd(&b.bn),
+        })
+    }
+
+    pub fn add_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        self.bn += u64::from(w);
+        Ok(self)
+    }
+
+    pub fn sub_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        self.bn -= u64::from(w);
+        Ok(self)
+    }
+
+    pub fn mul_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        self.bn *= u64::from(w);
+        Ok(self)
+    }
+
+    pub fn div_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        if w == 0 {
+            Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "a cannot be zero".to_string(),
+            ))
+        } else {
+            self.bn /= u64::from(w);
+            Ok(self)
+        }
+    }
+
+    pub fn mod_exp(
+        &self,
+        a: &BigNumber,
+        b: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        let modulus = BigNumber::_get_modulus(&b.bn)?;
+        if modulus == Mpz::one() {
+            return BigNumber::new();
+        }
+
+        if a.is_negative() {
+            let res = self.inverse(b, None)?;
+            Ok(BigNumber {
+                bn: res.bn.powm(&a.bn.abs(), &modulus),
+            })
+        } else {
+            Ok(BigNumber {
+                bn: self.bn.powm(&a.bn, &modulus),
+            })
+        }
+    }
+
+    pub fn modulus(
+        &self,
+        a: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        // Non-negative for either sign of `a`
+        Ok(BigNumber {
+            bn: self.bn.modulus(&BigNumber::_get_modulus(&a.bn)?),
+        })
+    }
+
+    /// The absolute value of a modulus, GMP aborts the process when dividing by zero
+    fn _get_modulus(bn: &Mpz) -> UrsaCryptoResult<Mpz> {
+        if bn.is_zero() {
+            Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Cannot have modulus==0".to_string(),
+            ))
+        } else {
+            Ok(bn.abs())
+        }
+    }
+
+    pub fn exp(
+        &self,
+        a: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        match Option::<u64>::from(&a.bn) {
+            Some(num) if !a.is_negative() && num <= u64::from(u32::MAX) => Ok(BigNumber {
+                bn: self.bn.pow(num as u32),
+            }),
+            _ => Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "'a' cannot be u32".to_string(),
+            )),
+        }
+    }
+
+    pub fn inverse(
+        &self,
+        n: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        let n = BigNumber::_get_modulus(&n.bn)?;
+        if n == Mpz::one() {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Invalid modulus".to_string(),
+            ));
+        }
+        match self.bn.invert(&n) {
+            Some(bn) => Ok(BigNumber { bn }),
+            None => Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Not invertible".to_string(),
+            )),
+        }
+    }
+
+    pub fn set_negative(&self, negative: bool) -> UrsaCryptoResult<BigNumber> {
+        let abs = self.bn.abs();
+        Ok(BigNumber {
+            bn: if negative { -abs } else { abs },
+        })
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.bn.sign() == Sign::Negative
+    }
+
+    pub fn increment(&self) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn + 1u64,
+        })
+    }
+
+    pub fn decrement(&self) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: &self.bn - 1u64,
+        })
+    }
+
+    pub fn lshift1(&self) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber { bn: &self.bn << 1 })
+    }
+
+    pub fn rshift1(&self) -> UrsaCryptoResult<BigNumber> {
+        self.rshift(1)
+    }
+
+    pub fn rshift(&self, n: u32) -> UrsaCryptoResult<BigNumber> {
+        // Of the absolute value, like OpenSSL, where GMP would round towards minus infinity
+        let abs = self.bn.abs() >> n as usize;
+        Ok(BigNumber {
+            bn: if self.is_negative() { -abs } else { abs },
+        })
+    }
+
+    pub fn mod_div(
+        &self,
+        b: &BigNumber,
+        p: &BigNumber,
+        _ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        //(a * (1/b mod p) mod p)
+        self.mul(&b.inverse(p, None)?, None)?.modulus(p, None)
+    }
+
+    pub fn random_qr(n: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        let qr = n.rand_range()?.sqr(None)?.modulus(n, None)?;
+        Ok(qr)
+    }
+
+    pub fn try_clone(&self) -> UrsaCryptoResult<BigNumber> {
+        Ok(BigNumber {
+            bn: self.bn.clone(),
+        })
+    }
+
+    pub fn hash_array(nums: &[Vec<u8>]) -> UrsaCryptoResult<Vec<u8>> {
+        let mut hasher = sha2::Sha256::new();
+
+        for num in nums.iter() {
+            hasher.input(num);
+        }
+
+        Ok(hasher.result().as_slice().to_vec())
+    }
+}
+
+impl fmt::Debug for BigNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BigNumber {{ bn: {} }}", self.bn.to_str_radix(10))
+    }
+}
+
+impl fmt::Display for BigNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BigNumber {{ bn: {} }}", self.bn.to_str_radix(10))
+    }
+}
+
+impl Ord for BigNumber {
+    fn cmp(&self, other: &BigNumber) -> Ordering {
+        self.bn.cmp(&other.bn)
+    }
+}
+
+impl Eq for BigNumber {}
+
+impl PartialOrd for BigNumber {
+    fn partial_cmp(&self, other: &BigNumber) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for BigNumber {
+    fn eq(&self, other: &BigNumber) -> bool {
+        self.bn == other.bn
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for BigNumber {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(
+            "BigNumber",
+            &self.to_dec().map_err(serde::ser::Error::custom)?,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Deserialize<'a> for BigNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        struct BigNumberVisitor;
+
+        impl<'a> Visitor<'a> for BigNumberVisitor {
+            type Value = BigNumber;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("expected BigNumber")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<BigNumber, E>
+            where
+                E: serde::de::Error,
+            {
+                BigNumber::from_dec(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(BigNumberVisitor)
+    }
+}
+
+impl From<rand::Error> for UrsaCryptoError {
+    fn from(err: rand::Error) -> UrsaCryptoError {
+        UrsaCryptoError::from_msg(
+            UrsaCryptoErrorKind::InvalidState,
+            format!("Internal Random Number error: {}", err),
+        )
+    }
+}
+
+impl From<ParseMpzError> for UrsaCryptoError {
+    fn from(err: ParseMpzError) -> UrsaCryptoError {
+        UrsaCryptoError::from_msg(
+            UrsaCryptoErrorKind::InvalidState,
+            format!("Internal Parse Mpz error: {}", err),
+        )
+    }
+}
+
+impl Default for BigNumber {
+    fn default() -> BigNumber {
+        BigNumber { bn: Mpz::new() }
+    }
+}
+
+lazy_static! {
+    /// The odd primes below 2^12, for sieving prime candidates
+    static ref SMALL_PRIMES: Vec<u64> = {
+        let mut composite = vec![false; 1 << 12];
+        let mut primes = Vec::new();
+        for n in 3..composite.len() {
+            if !composite[n] {
+                primes.push(n as u64);
+                (n * n..composite.len())
+                    .step_by(n)
+                    .for_each(|m| composite[m] = true);
+            }
+        }
+        primes
+    };
+}
+
+// Constants that are used throughout the code, so avoiding recomputation.
+lazy_static! {
+    pub static ref BIGNUMBER_1: BigNumber = BigNumber::from_u32(1).unwrap();
+    pub static ref BIGNUMBER_2: BigNumber = BigNumber::from_u32(2).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_prime_works() {
+        for size in [2, 3, 10, 64, 512].iter() {
+            let prime = BigNumber::generate_prime(*size).unwrap();
+            assert_eq!(prime.num_bits().unwrap(), *size as i32);
+            assert!(prime.is_prime(None).unwrap());
+        }
+        assert!(BigNumber::generate_prime(1).is_err());
+    }
+
+    #[test]
+    fn generate_safe_prime_works() {
+        for size in [10, 64, 512].iter() {
+            let prime = BigNumber::generate_safe_prime(*size).unwrap();
+            assert_eq!(prime.num_bits().unwrap(), *size as i32 + 1);
+            assert!(prime.is_prime(None).unwrap());
+            assert!(prime.rshift1().unwrap().is_prime(None).unwrap());
+        }
+    }
+
+    #[test]
+    fn generate_prime_in_range_works() {
+        let start = BigNumber::from_u32(1000).unwrap();
+        let end = BigNumber::from_u32(1020).unwrap();
+        for _ in 0..20 {
+            let prime = BigNumber::generate_prime_in_range(&end, &start).unwrap();
+            assert!([1009, 1013, 1019]
+                .iter()
+                .any(|p| prime == BigNumber::from_u32(*p).unwrap()));
+        }
+    }
+
+    #[test]
+    fn modulus_works() {
+        let base = BigNumber::from_u32(6).unwrap();
+        assert!(base.modulus(&BigNumber::new().unwrap(), None).is_err());
+        assert!(base
+            .mod_exp(&base, &BigNumber::new().unwrap(), None)
+            .is_err());
+        assert!(base.inverse(&BigNumber::new().unwrap(), None).is_err());
+
+        for (modulus, expected) in [(1, 0), (2, 0), (5, 1), (7, 6)].iter() {
+            let modulus = BigNumber::from_u32(*modulus).unwrap();
+            let expected = BigNumber::from_u32(*expected).unwrap();
+            assert_eq!(expected, base.modulus(&modulus, None).unwrap());
+            assert_eq!(
+                expected,
+                base.modulus(&modulus.set_negative(true).unwrap(), None)
+                    .unwrap()
+            );
+        }
+        let negative = base.set_negative(true).unwrap();
+        assert_eq!(
+            BigNumber::from_u32(4).unwrap(),
+            negative
+                .modulus(&BigNumber::from_u32(5).unwrap(), None)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_modular_exponentiation() {
+        let base = BigNumber::from_u32(6).unwrap();
+        let exp = BigNumber::from_u32(5).unwrap().set_negative(true).unwrap();
+        let modulus = BigNumber::from_u32(13).unwrap();
+        assert_eq!(
+            BigNumber::from_u32(7).unwrap(),
+            base.mod_exp(&exp, &modulus, None).unwrap()
+        );
+
+        let modulus = BigNumber::from_u32(1).unwrap().set_negative(true).unwrap();
+        assert_eq!(
+            BigNumber::new().unwrap(),
+            base.mod_exp(&exp, &modulus, None).unwrap()
+        );
+
+        let modulus = BigNumber::from_u32(5).unwrap().set_negative(true).unwrap();
+        assert_eq!(
+            BigNumber::from_u32(1).unwrap(),
+            base.mod_exp(&exp, &modulus, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn signed_bits_work() {
+        let mut num = BigNumber::from_u32(1001)
+            .unwrap()
+            .set_negative(true)
+            .unwrap();
+        assert_eq!(num.rshift1().unwrap().to_dec().unwrap(), "-500");
+        assert_eq!(num.rshift(3).unwrap().to_dec().unwrap(), "-125");
+        assert!(num.is_bit_set(0).unwrap());
+        assert!(!num.is_bit_set(1).unwrap());
+        num.set_bit(1).unwrap();
+        assert_eq!(num.to_dec().unwrap(), "-1003");
+        assert_eq!(num.to_bytes().unwrap(), vec![3, 235]);
+
+        assert_eq!(BigNumber::new().unwrap().num_bits().unwrap(), 0);
+        assert!(BigNumber::new().unwrap().to_bytes().unwrap().is_empty());
+        assert!(BigNumber::new().unwrap().rand_range().is_err());
+        assert!(BigNumber::from_dec("12a").is_err());
+        assert!(BigNumber::from_hex("12G").is_err());
+    }
+
+    #[test]
+    fn exp_works() {
+        let answer = BigNumber::from_dec("259344723055062059907025491480697571938277889515152306249728583105665800713306759149981690559193987143012367913206299323899696942213235956742929677132122730441323862712594345230336").unwrap();
+        let test = BigNumber::from_u32(2)
+            .unwrap()
+            .exp(&BigNumber::from_u32(596).unwrap(), None)
+            .unwrap();
+        assert_eq!(answer, test);
+        assert!(test.exp(&test, None).is_err());
+        assert!(test
+            .exp(
+                &BigNumber::from_u32(2).unwrap().set_negative(true).unwrap(),
+                None
+            )
+            .is_err());
+    }
+}
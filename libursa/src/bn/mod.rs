@@ -0,0 +1,615 @@
+//! Arbitrary precision integers for CL signatures and secret sharing.
+//!
+//! The backend is chosen at compile time by exactly one of the big number features:
+//!
+//! * `cl_native` / `sharing_native` wrap OpenSSL's `BIGNUM`
+//! * `cl_gmp` / `sharing_gmp` wrap GMP with `rust-gmp`
+//! * `cl` / `sharing` are pure Rust with `num-bigint` and `glass_pumpkin`, for targets
+//!   without a C toolchain or system libraries such as wasm and iOS
+//!
+//! Every backend exports the same [`BigNumber`] and [`BigNumberContext`], so code using
+//! them compiles unchanged against any of them. [`BigNumberBackend`] spells that interface
+//! out as a trait, so it is checked for every backend and generic code can rely on it.
+
+use errors::prelude::*;
+
+use std::fmt::Debug;
+
+#[cfg(any(feature = "cl_gmp", feature = "sharing_gmp"))]
+mod gmp;
+#[cfg(any(feature = "cl_gmp", feature = "sharing_gmp"))]
+pub use self::gmp::*;
+
+#[cfg(any(feature = "cl_native", feature = "sharing_native"))]
+mod openssl;
+#[cfg(any(feature = "cl_native", feature = "sharing_native"))]
+pub use self::openssl::*;
+
+#[cfg(any(feature = "cl", feature = "sharing"))]
+mod rust;
+#[cfg(any(feature = "cl", feature = "sharing"))]
+pub use self::rust::*;
+
+/// The operations every big number backend provides.
+///
+/// Numbers are signed. Operations taking a context use it for scratch space when the
+/// backend needs one and create a temporary one when given `None`.
+pub trait BigNumberBackend: Sized + Debug + Default + Ord + Send + Sync {
+    /// Scratch space reused across operations
+    type Context;
+
+    fn new_context() -> UrsaCryptoResult<Self::Context>;
+    /// Zero
+    fn new() -> UrsaCryptoResult<Self>;
+    /// A random prime of at most `size` bits, exactly `size` with OpenSSL and GMP
+    fn generate_prime(size: usize) -> UrsaCryptoResult<Self>;
+    /// A random prime `p` where `(p - 1) / 2` is also prime and has at most `size` bits,
+    /// exactly `size` with OpenSSL and GMP
+    fn generate_safe_prime(size: usize) -> UrsaCryptoResult<Self>;
+    /// A random prime between `start` and `end`
+    fn generate_prime_in_range(start: &Self, end: &Self) -> UrsaCryptoResult<Self>;
+    fn is_prime(&self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<bool>;
+    fn is_safe_prime(&self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<bool>;
+    /// A random number of at most `size` bits
+    fn rand(size: usize) -> UrsaCryptoResult<Self>;
+    /// A random number in `[0, self)`
+    fn rand_range(&self) -> UrsaCryptoResult<Self>;
+    fn num_bits(&self) -> UrsaCryptoResult<i32>;
+    fn is_bit_set(&self, n: i32) -> UrsaCryptoResult<bool>;
+    fn set_bit(&mut self, n: i32) -> UrsaCryptoResult<&mut Self>;
+    fn from_u32(n: usize) -> UrsaCryptoResult<Self>;
+    fn from_dec(dec: &str) -> UrsaCryptoResult<Self>;
+    fn from_hex(hex: &str) -> UrsaCryptoResult<Self>;
+    /// The non-negative number with these big endian bytes
+    fn from_bytes(bytes: &[u8]) -> UrsaCryptoResult<Self>;
+    fn to_dec(&self) -> UrsaCryptoResult<String>;
+    /// Upper case hexadecimal
+    fn to_hex(&self) -> UrsaCryptoResult<String>;
+    /// The big endian bytes of the absolute value
+    fn to_bytes(&self) -> UrsaCryptoResult<Vec<u8>>;
+    /// SHA-256 of `data`
+    fn hash(data: &[u8]) -> UrsaCryptoResult<Vec<u8>>;
+    fn add(&self, a: &Self) -> UrsaCryptoResult<Self>;
+    fn sub(&self, a: &Self) -> UrsaCryptoResult<Self>;
+    fn sqr(&self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn mul(&self, a: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn mod_mul(
+        &self,
+        a: &Self,
+        n: &Self,
+        ctx: Option<&mut Self::Context>,
+    ) -> UrsaCryptoResult<Self>;
+    fn mod_sub(
+        &self,
+        a: &Self,
+        n: &Self,
+        ctx: Option<&mut Self::Context>,
+    ) -> UrsaCryptoResult<Self>;
+    /// The quotient rounded towards zero
+    fn div(&self, a: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn gcd(a: &Self, b: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn add_word(&mut self, w: u32) -> UrsaCryptoResult<&mut Self>;
+    fn sub_word(&mut self, w: u32) -> UrsaCryptoResult<&mut Self>;
+    fn mul_word(&mut self, w: u32) -> UrsaCryptoResult<&mut Self>;
+    fn div_word(&mut self, w: u32) -> UrsaCryptoResult<&mut Self>;
+    /// `self^a mod b`, with the inverse of `self` for a negative `a`
+    fn mod_exp(
+        &self,
+        a: &Self,
+        b: &Self,
+        ctx: Option<&mut Self::Context>,
+    ) -> UrsaCryptoResult<Self>;
+    /// The non-negative remainder of dividing by `a`
+    fn modulus(&self, a: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn exp(&self, a: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn inverse(&self, n: &Self, ctx: Option<&mut Self::Context>) -> UrsaCryptoResult<Self>;
+    fn set_negative(&self, negative: bool) -> UrsaCryptoResult<Self>;
+    fn is_negative(&self) -> bool;
+    fn increment(&self) -> UrsaCryptoResult<Self>;
+    fn decrement(&self) -> UrsaCryptoResult<Self>;
+    fn lshift1(&self) -> UrsaCryptoResult<Self>;
+    fn rshift1(&self) -> UrsaCryptoResult<Self>;
+    fn rshift(&self, n: u32) -> UrsaCryptoResult<Self>;
+    /// `self * (1 / b mod p) mod p`
+    fn mod_div(
+        &self,
+        b: &Self,
+        p: &Self,
+        ctx: Option<&mut Self::Context>,
+    ) -> UrsaCryptoResult<Self>;
+    /// A random quadratic residue modulo `n`
+    fn random_qr(n: &Self) -> UrsaCryptoResult<Self>;
+    fn try_clone(&self) -> UrsaCryptoResult<Self>;
+    /// SHA-256 of the concatenation of `nums`
+    fn hash_array(nums: &[Vec<u8>]) -> UrsaCryptoResult<Vec<u8>>;
+}
+
+impl BigNumberBackend for BigNumber {
+    type Context = BigNumberContext;
+
+    fn new_context() -> UrsaCryptoResult<BigNumberContext> {
+        BigNumber::new_context()
+    }
+
+    fn new() -> UrsaCryptoResult<BigNumber> {
+        BigNumber::new()
+    }
+
+    fn generate_prime(size: usize) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::generate_prime(size)
+    }
+
+    fn generate_safe_prime(size: usize) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::generate_safe_prime(size)
+    }
+
+    fn generate_prime_in_range(start: &BigNumber, end: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::generate_prime_in_range(start, end)
+    }
+
+    fn is_prime(&self, ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<bool> {
+        BigNumber::is_prime(self, ctx)
+    }
+
+    fn is_safe_prime(&self, ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<bool> {
+        BigNumber::is_safe_prime(self, ctx)
+    }
+
+    fn rand(size: usize) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::rand(size)
+    }
+
+    fn rand_range(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::rand_range(self)
+    }
+
+    fn num_bits(&self) -> UrsaCryptoResult<i32> {
+        BigNumber::num_bits(self)
+    }
+
+    fn is_bit_set(&self, n: i32) -> UrsaCryptoResult<bool> {
+        BigNumber::is_bit_set(self, n)
+    }
+
+    fn set_bit(&mut self, n: i32) -> UrsaCryptoResult<&mut BigNumber> {
+        BigNumber::set_bit(self, n)
+    }
+
+    fn from_u32(n: usize) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::from_u32(n)
+    }
+
+    fn from_dec(dec: &str) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::from_dec(dec)
+    }
+
+    fn from_hex(hex: &str) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::from_hex(hex)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::from_bytes(bytes)
+    }
+
+    fn to_dec(&self) -> UrsaCryptoResult<String> {
+        BigNumber::to_dec(self)
+    }
+
+    fn to_hex(&self) -> UrsaCryptoResult<String> {
+        BigNumber::to_hex(self)
+    }
+
+    fn to_bytes(&self) -> UrsaCryptoResult<Vec<u8>> {
+        BigNumber::to_bytes(self)
+    }
+
+    fn hash(data: &[u8]) -> UrsaCryptoResult<Vec<u8>> {
+        BigNumber::hash(data)
+    }
+
+    fn add(&self, a: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::add(self, a)
+    }
+
+    fn sub(&self, a: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::sub(self, a)
+    }
+
+    fn sqr(&self, ctx: Option<&mut BigNumberContext>) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::sqr(self, ctx)
+    }
+
+    fn mul(
+        &self,
+        a: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::mul(self, a, ctx)
+    }
+
+    fn mod_mul(
+        &self,
+        a: &BigNumber,
+        n: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::mod_mul(self, a, n, ctx)
+    }
+
+    fn mod_sub(
+        &self,
+        a: &BigNumber,
+        n: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::mod_sub(self, a, n, ctx)
+    }
+
+    fn div(
+        &self,
+        a: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::div(self, a, ctx)
+    }
+
+    fn gcd(
+        a: &BigNumber,
+        b: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::gcd(a, b, ctx)
+    }
+
+    fn add_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        BigNumber::add_word(self, w)
+    }
+
+    fn sub_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        BigNumber::sub_word(self, w)
+    }
+
+    fn mul_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        BigNumber::mul_word(self, w)
+    }
+
+    fn div_word(&mut self, w: u32) -> UrsaCryptoResult<&mut BigNumber> {
+        BigNumber::div_word(self, w)
+    }
+
+    fn mod_exp(
+        &self,
+        a: &BigNumber,
+        b: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::mod_exp(self, a, b, ctx)
+    }
+
+    fn modulus(
+        &self,
+        a: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::modulus(self, a, ctx)
+    }
+
+    fn exp(
+        &self,
+        a: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::exp(self, a, ctx)
+    }
+
+    fn inverse(
+        &self,
+        n: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::inverse(self, n, ctx)
+    }
+
+    fn set_negative(&self, negative: bool) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::set_negative(self, negative)
+    }
+
+    fn is_negative(&self) -> bool {
+        BigNumber::is_negative(self)
+    }
+
+    fn increment(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::increment(self)
+    }
+
+    fn decrement(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::decrement(self)
+    }
+
+    fn lshift1(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::lshift1(self)
+    }
+
+    fn rshift1(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::rshift1(self)
+    }
+
+    fn rshift(&self, n: u32) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::rshift(self, n)
+    }
+
+    fn mod_div(
+        &self,
+        b: &BigNumber,
+        p: &BigNumber,
+        ctx: Option<&mut BigNumberContext>,
+    ) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::mod_div(self, b, p, ctx)
+    }
+
+    fn random_qr(n: &BigNumber) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::random_qr(n)
+    }
+
+    fn try_clone(&self) -> UrsaCryptoResult<BigNumber> {
+        BigNumber::try_clone(self)
+    }
+
+    fn hash_array(nums: &[Vec<u8>]) -> UrsaCryptoResult<Vec<u8>> {
+        BigNumber::hash_array(nums)
+    }
+}
+
+/// The inverses modulo `n` of all `values` with a single modular inversion and three
+/// modular multiplications per value, Montgomery's trick. Fails like
+/// [`BigNumberBackend::inverse`] if any value has no inverse
+pub fn batch_inverse<B: BigNumberBackend>(
+    values: &[B],
+    n: &B,
+    ctx: Option<&mut B::Context>,
+) -> UrsaCryptoResult<Vec<B>> {
+    let mut owned;
+    let ctx = match ctx {
+        Some(ctx) => ctx,
+        None => {
+            owned = B::new_context()?;
+            &mut owned
+        }
+    };
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // products[i] is the product of the values before i
+    let mut products = Vec::with_capacity(values.len());
+    let mut acc = B::from_u32(1)?;
+    for v in values {
+        let next = acc.mod_mul(v, n, Some(ctx))?;
+        products.push(acc);
+        acc = next;
+    }
+    let mut inverse = acc.inverse(n, Some(ctx))?;
+    for (v, p) in values.iter().zip(products.iter_mut()).rev() {
+        // inverse is the inverse of the product up to and including v
+        let next = inverse.mod_mul(v, n, Some(ctx))?;
+        *p = p.mod_mul(&inverse, n, Some(ctx))?;
+        inverse = next;
+    }
+    Ok(products)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num<B: BigNumberBackend>(dec: &str) -> B {
+        B::from_dec(dec).unwrap()
+    }
+
+    /// The same answers from every backend, through the trait only
+    fn arithmetic<B: BigNumberBackend>() {
+        let a: B = num("340282366920938463463374607431768211457");
+        let b: B = num("18446744073709551629");
+        let mut ctx = B::new_context().unwrap();
+
+        assert_eq!(
+            a.add(&b).unwrap(),
+            num("340282366920938463481821351505477763086")
+        );
+        assert_eq!(
+            b.sub(&a).unwrap(),
+            num("-340282366920938463444927863358058659828")
+        );
+        assert_eq!(
+            a.mul(&b, Some(&mut ctx)).unwrap(),
+            num("6277101735386680768259460193179866441144672085150730813453")
+        );
+        assert_eq!(b.sqr(None).unwrap(), b.mul(&b, None).unwrap());
+        assert_eq!(
+            a.div(&b, Some(&mut ctx)).unwrap(),
+            num("18446744073709551603")
+        );
+        assert_eq!(
+            a.set_negative(true).unwrap().div(&b, None).unwrap(),
+            num("-18446744073709551603")
+        );
+        assert!(a.div(&B::new().unwrap(), None).is_err());
+        assert_eq!(a.modulus(&b, None).unwrap(), num("170"));
+        assert_eq!(
+            a.set_negative(true).unwrap().modulus(&b, None).unwrap(),
+            num("18446744073709551459")
+        );
+        assert_eq!(a.mod_mul(&a, &b, Some(&mut ctx)).unwrap(), num("28900"));
+        assert_eq!(
+            b.mod_sub(&a, &b, None).unwrap(),
+            num("18446744073709551459")
+        );
+        assert_eq!(
+            B::gcd(&a.mul(&b, None).unwrap(), &b.sqr(None).unwrap(), None).unwrap(),
+            b
+        );
+
+        let inverse = a.inverse(&b, Some(&mut ctx)).unwrap();
+        assert_eq!(
+            inverse.mod_mul(&a, &b, None).unwrap(),
+            B::from_u32(1).unwrap()
+        );
+        assert_eq!(
+            B::from_u32(1).unwrap().mod_div(&a, &b, None).unwrap(),
+            inverse
+        );
+        assert!(b.inverse(&b, None).is_err());
+
+        // Fermat's little theorem for the prime b
+        assert_eq!(
+            a.mod_exp(&b.decrement().unwrap(), &b, Some(&mut ctx))
+                .unwrap(),
+            B::from_u32(1).unwrap()
+        );
+        assert_eq!(
+            a.mod_exp(
+                &B::from_u32(1).unwrap().set_negative(true).unwrap(),
+                &b,
+                None
+            )
+            .unwrap(),
+            inverse
+        );
+        assert_eq!(
+            B::from_u32(3)
+                .unwrap()
+                .exp(&B::from_u32(40).unwrap(), None)
+                .unwrap(),
+            num("12157665459056928801")
+        );
+
+        let mut c = B::from_u32(1000).unwrap();
+        c.add_word(24)
+            .unwrap()
+            .mul_word(3)
+            .unwrap()
+            .sub_word(2)
+            .unwrap();
+        assert_eq!(c, B::from_u32(3070).unwrap());
+        c.div_word(7).unwrap();
+        assert_eq!(c, B::from_u32(438).unwrap());
+        assert_eq!(c.increment().unwrap(), B::from_u32(439).unwrap());
+        assert_eq!(c.lshift1().unwrap(), B::from_u32(876).unwrap());
+        assert_eq!(c.rshift1().unwrap(), B::from_u32(219).unwrap());
+        assert_eq!(c.rshift(3).unwrap(), B::from_u32(54).unwrap());
+    }
+
+    fn conversions<B: BigNumberBackend>() {
+        let a: B = num("340282366920938463463374607431768211457");
+        assert_eq!(
+            a.to_dec().unwrap(),
+            "340282366920938463463374607431768211457"
+        );
+        // OpenSSL pads to whole bytes
+        assert_eq!(
+            a.to_hex().unwrap().trim_start_matches('0'),
+            "100000000000000000000000000000001"
+        );
+        assert_eq!(
+            B::from_hex("1000000000000000000000000000000A1").unwrap(),
+            a.add(&B::from_u32(160).unwrap()).unwrap()
+        );
+        let bytes = a.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 17);
+        assert_eq!((bytes[0], bytes[16]), (1, 1));
+        assert_eq!(B::from_bytes(&bytes).unwrap(), a);
+        assert_eq!(a.num_bits().unwrap(), 129);
+        assert!(a.is_bit_set(128).unwrap());
+        assert!(!a.is_bit_set(127).unwrap());
+
+        let negative = a.set_negative(true).unwrap();
+        assert!(negative.is_negative());
+        assert!(negative < B::new().unwrap());
+        assert_eq!(
+            negative.to_dec().unwrap(),
+            "-340282366920938463463374607431768211457"
+        );
+        assert_eq!(negative.set_negative(false).unwrap(), a);
+        assert_eq!(negative.try_clone().unwrap(), negative);
+
+        let mut b = B::new().unwrap();
+        assert_eq!(b, B::default());
+        b.set_bit(70).unwrap().set_bit(0).unwrap();
+        assert_eq!(b, num("1180591620717411303425"));
+
+        assert_eq!(
+            B::hash(b"abc").unwrap(),
+            B::hash_array(&[b"a".to_vec(), b"bc".to_vec()]).unwrap()
+        );
+    }
+
+    fn randomness<B: BigNumberBackend>() {
+        let mut ctx = B::new_context().unwrap();
+        let p = B::generate_prime(128).unwrap();
+        assert!(p.num_bits().unwrap() <= 128);
+        assert!(p.is_prime(Some(&mut ctx)).unwrap());
+        assert!(!p.mul(&p, None).unwrap().is_prime(None).unwrap());
+
+        let safe = B::generate_safe_prime(128).unwrap();
+        assert!(safe.num_bits().unwrap() <= 129);
+        assert!(safe.is_safe_prime(Some(&mut ctx)).unwrap());
+
+        let start: B = num("1000000000000");
+        let end = start.add(&B::from_u32(1000000).unwrap()).unwrap();
+        let p = B::generate_prime_in_range(&start, &end).unwrap();
+        assert!(start <= p && p < end && p.is_prime(None).unwrap());
+
+        for _ in 0..20 {
+            assert!(B::rand(100).unwrap().num_bits().unwrap() <= 100);
+            let r = p.rand_range().unwrap();
+            assert!(!r.is_negative() && r < p);
+            let qr = B::random_qr(&p).unwrap();
+            // Euler's criterion
+            let legendre = qr
+                .mod_exp(&p.decrement().unwrap().rshift1().unwrap(), &p, None)
+                .unwrap();
+            assert!(qr == B::new().unwrap() || legendre == B::from_u32(1).unwrap());
+        }
+    }
+
+    fn batch_inversion<B: BigNumberBackend>() {
+        let n: B = num("18446744073709551629");
+        let values: Vec<B> = ["2", "340282366920938463463374607431768211457", "-5", "1"]
+            .iter()
+            .map(|v| num(v))
+            .collect();
+        let mut ctx = B::new_context().unwrap();
+        let inverses = batch_inverse(&values, &n, Some(&mut ctx)).unwrap();
+        assert_eq!(inverses.len(), values.len());
+        for (v, inverse) in values.iter().zip(inverses.iter()) {
+            assert_eq!(
+                inverse.mod_mul(v, &n, None).unwrap(),
+                B::from_u32(1).unwrap()
+            );
+        }
+        assert!(batch_inverse::<B>(&[], &n, None).unwrap().is_empty());
+
+        let with_multiple = vec![num("3"), n.lshift1().unwrap()];
+        assert!(batch_inverse(&with_multiple, &n, None).is_err());
+    }
+
+    #[test]
+    fn backend_arithmetic_works() {
+        arithmetic::<BigNumber>();
+    }
+
+    #[test]
+    fn backend_conversions_work() {
+        conversions::<BigNumber>();
+    }
+
+    #[test]
+    fn backend_randomness_works() {
+        randomness::<BigNumber>();
+    }
+
+    #[test]
+    fn backend_batch_inversion_works() {
+        batch_inversion::<BigNumber>();
+    }
+}
@@ -0,0 +1,538 @@
+//! Discovery of what the linked build of the crate supports.
+//!
+//! Which algorithms exist, and which implementation backs each of them, is decided by
+//! the features the crate was compiled with. [`capabilities`] describes the build an
+//! application actually linked against, so it can negotiate only algorithms the peer
+//! and the library both support, refuse to start without one it needs, or log the
+//! build next to the results of the [`self_test`](crate::self_test). FFI consumers get
+//! the same description as JSON from `ursa_capabilities`.
+//!
+//! Whole categories of algorithms are included with the `symmetric_encryption`,
+//! `legacy_ciphers` and `asymmetric_encryption` features and the per-algorithm ones. The
+//! constants here say which categories a build has, and
+//! [`assert_capabilities`](crate::assert_capabilities) turns them into compile-time
+//! checks for builds that must leave some out.
+//!
+//! Security levels are estimates of classical security in bits: the collision
+//! resistance of a hash, the key size of a cipher, and the cost of the best known
+//! attack on a curve or modulus. The pairing friendly curves are rated below their
+//! original targets because of the number field sieve improvements of Kim and
+//! Barbulescu. None of the algorithms resist a quantum computer.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// What an algorithm is used for
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum AlgorithmKind {
+    /// A cryptographic hash function
+    Hash,
+    /// Authenticated encryption with associated data
+    Aead,
+    /// A digital signature scheme
+    Signature,
+    /// A key agreement scheme
+    KeyExchange,
+}
+
+/// The implementation behind an algorithm
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Backend {
+    /// Portable Rust
+    Rust,
+    /// Rust with assembly or SIMD optimizations
+    Asm,
+    /// The system OpenSSL
+    OpenSsl,
+    /// The system libsodium
+    Libsodium,
+    /// Bitcoin Core's libsecp256k1
+    Libsecp256k1,
+    /// GMP for big number arithmetic
+    Gmp,
+}
+
+/// An algorithm compiled into the crate
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Algorithm {
+    /// The algorithm's standard name, e.g. `Ed25519`
+    pub name: &'static str,
+    /// What the algorithm is used for
+    pub kind: AlgorithmKind,
+    /// The implementation behind it
+    pub backend: Backend,
+    /// The estimated classical security level in bits
+    pub security_bits: u16,
+}
+
+/// A description of the linked build
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Capabilities {
+    /// The crate version
+    pub version: &'static str,
+    /// The enabled crate features, sorted by name
+    pub features: Vec<&'static str>,
+    /// The enabled algorithms
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl Capabilities {
+    /// Whether the crate was compiled with `feature`
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// The algorithm called `name`, if it is enabled
+    pub fn algorithm(&self, name: &str) -> Option<&Algorithm> {
+        self.algorithms.iter().find(|a| a.name == name)
+    }
+
+    /// The enabled algorithms of `kind`
+    pub fn algorithms_of(&self, kind: AlgorithmKind) -> impl Iterator<Item = &Algorithm> {
+        self.algorithms.iter().filter(move |a| a.kind == kind)
+    }
+}
+
+/// Whether any symmetric cipher is compiled in
+pub const SYMMETRIC_ENCRYPTION: bool = cfg!(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+));
+/// Whether encryption to a public key is compiled in: HPKE, the envelopes, sealed
+/// boxes and MLS
+pub const ASYMMETRIC_ENCRYPTION: bool = cfg!(any(
+    feature = "cryptobox",
+    feature = "envelope",
+    feature = "hpke",
+    feature = "mls"
+));
+/// Whether any signature scheme is compiled in
+pub const SIGNATURES: bool = cfg!(any(
+    feature = "ed25519",
+    feature = "ed25519_asm",
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm",
+    feature = "bls_bls12381",
+    feature = "bls_bn254",
+    feature = "bls_bn254_asm",
+    feature = "cl",
+    feature = "cl_native",
+    feature = "cl_gmp"
+));
+/// Whether any key agreement scheme is compiled in
+pub const KEY_EXCHANGE: bool = cfg!(any(
+    feature = "ecdh_secp256k1",
+    feature = "ecdh_secp256k1_native",
+    feature = "ecdh_secp256k1_asm",
+    feature = "x25519",
+    feature = "x25519_asm"
+));
+/// Whether a post-quantum algorithm is compiled in. None are implemented yet, this lets
+/// a build assert that it stays so
+pub const POST_QUANTUM: bool = false;
+/// Whether a legacy cipher is compiled in, i.e. AES-CBC with HMAC
+pub const LEGACY_CIPHERS: bool = cfg!(any(feature = "aescbc", feature = "aescbc_native"));
+/// Whether SHA-1 is compiled in, for HOTP, SRP and OpenPGP fingerprints
+pub const LEGACY_HASHES: bool = cfg!(any(feature = "openpgp", feature = "otp", feature = "srp"));
+
+/// Fail to compile unless the linked build of ursa has the given categories of
+/// algorithms, or doesn't have those wrapped in `not(..)`.
+///
+/// The categories are the constants of [`capabilities`](crate::capabilities), e.g.
+/// `ASYMMETRIC_ENCRYPTION`. Cargo features only ever add algorithms, and any crate in
+/// the dependency graph can turn one on, so a build shipping into a jurisdiction with
+/// algorithm restrictions should assert what it excludes where the binary is built:
+///
+/// ```
+/// ursa::assert_capabilities!(not(POST_QUANTUM));
+/// ```
+///
+/// ```compile_fail
+/// ursa::assert_capabilities!(POST_QUANTUM);
+/// ```
+///
+/// The `export_restricted` feature is such a profile: hashes, signatures and the
+/// AEADs without CBC, and no asymmetric encryption.
+#[macro_export]
+macro_rules! assert_capabilities {
+    () => {};
+    (not($category:ident) $(, $($rest:tt)*)?) => {
+        const _: () = assert!(
+            !$crate::capabilities::$category,
+            concat!("ursa was built with ", stringify!($category))
+        );
+        $crate::assert_capabilities!($($($rest)*)?);
+    };
+    ($category:ident $(, $($rest:tt)*)?) => {
+        const _: () = assert!(
+            $crate::capabilities::$category,
+            concat!("ursa was built without ", stringify!($category))
+        );
+        $crate::assert_capabilities!($($($rest)*)?);
+    };
+}
+
+macro_rules! enabled_features {
+    ($($feature:tt),* $(,)?) => {{
+        let mut features = Vec::new();
+        $(if cfg!(feature = $feature) {
+            features.push($feature);
+        })*
+        features
+    }};
+}
+
+/// Describe the algorithms, backends and features of the linked build
+pub fn capabilities() -> Capabilities {
+    use self::AlgorithmKind::*;
+    use self::Backend::*;
+
+    // Any of these turns on the assembly implementation of SHA-2 for every user
+    let sha2_asm = cfg!(any(
+        feature = "hashes_asm",
+        feature = "bls_bn254_asm",
+        feature = "ecdh_secp256k1_asm",
+        feature = "ecdsa_secp256k1_asm",
+        feature = "ed25519_asm",
+        feature = "x25519_asm"
+    ));
+    let sha2 = if sha2_asm { Asm } else { Rust };
+    let blake2 = if cfg!(feature = "hashes_asm") {
+        Asm
+    } else {
+        Rust
+    };
+    let aescbc = if cfg!(feature = "aescbc_native") {
+        OpenSsl
+    } else {
+        Rust
+    };
+    let aesgcm = if cfg!(feature = "aesgcm_native") {
+        OpenSsl
+    } else {
+        Rust
+    };
+    let chacha = if cfg!(feature = "chacha20poly1305_native") {
+        Libsodium
+    } else {
+        Rust
+    };
+    let ed25519 = if cfg!(feature = "ed25519_asm") {
+        Asm
+    } else {
+        Rust
+    };
+    let x25519 = if cfg!(feature = "x25519_asm") {
+        Asm
+    } else {
+        Rust
+    };
+    let ecdsa = if cfg!(any(
+        feature = "ecdsa_secp256k1_native",
+        feature = "ecdsa_secp256k1_asm"
+    )) {
+        Libsecp256k1
+    } else {
+        Rust
+    };
+    let ecdh = if cfg!(any(
+        feature = "ecdh_secp256k1_native",
+        feature = "ecdh_secp256k1_asm"
+    )) {
+        Libsecp256k1
+    } else {
+        Rust
+    };
+    let cl = if cfg!(feature = "cl_native") {
+        OpenSsl
+    } else if cfg!(feature = "cl_gmp") {
+        Gmp
+    } else {
+        Rust
+    };
+
+    let hashes = cfg!(feature = "sha2");
+    let sha3 = cfg!(feature = "sha3");
+    let aescbc_enabled = cfg!(any(feature = "aescbc", feature = "aescbc_native"));
+    let aesgcm_enabled = cfg!(any(feature = "aesgcm", feature = "aesgcm_native"));
+    let chacha_enabled = cfg!(any(
+        feature = "chacha20poly1305",
+        feature = "chacha20poly1305_native"
+    ));
+    let table = [
+        (hashes, "SHA-256", Hash, sha2, 128),
+        (hashes, "SHA-384", Hash, sha2, 192),
+        (hashes, "SHA-512", Hash, sha2, 256),
+        (sha3, "SHA3-256", Hash, Rust, 128),
+        (sha3, "SHA3-384", Hash, Rust, 192),
+        (sha3, "SHA3-512", Hash, Rust, 256),
+        (sha3, "Keccak-256", Hash, Rust, 128),
+        (cfg!(feature = "blake2"), "BLAKE2b-512", Hash, blake2, 256),
+        (aescbc_enabled, "AES-128-CBC-HMAC-SHA256", Aead, aescbc, 128),
+        (aescbc_enabled, "AES-256-CBC-HMAC-SHA512", Aead, aescbc, 256),
+        (aesgcm_enabled, "AES-128-GCM", Aead, aesgcm, 128),
+        (aesgcm_enabled, "AES-256-GCM", Aead, aesgcm, 256),
+        (chacha_enabled, "ChaCha20-Poly1305", Aead, chacha, 256),
+        (chacha_enabled, "XChaCha20-Poly1305", Aead, chacha, 256),
+        (
+            cfg!(any(feature = "ed25519", feature = "ed25519_asm")),
+            "Ed25519",
+            Signature,
+            ed25519,
+            128,
+        ),
+        (
+            cfg!(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            )),
+            "ECDSA-secp256k1",
+            Signature,
+            ecdsa,
+            128,
+        ),
+        (
+            cfg!(feature = "bls_bls12381"),
+            "BLS-BLS12-381",
+            Signature,
+            Rust,
+            117,
+        ),
+        (
+            cfg!(any(feature = "bls_bn254", feature = "bls_bn254_asm")),
+            "BLS-BN254",
+            Signature,
+            Rust,
+            100,
+        ),
+        (
+            cfg!(any(
+                feature = "cl",
+                feature = "cl_native",
+                feature = "cl_gmp"
+            )),
+            "CL",
+            Signature,
+            cl,
+            112,
+        ),
+        (
+            cfg!(any(feature = "x25519", feature = "x25519_asm")),
+            "X25519",
+            KeyExchange,
+            x25519,
+            128,
+        ),
+        (
+            cfg!(any(
+                feature = "ecdh_secp256k1",
+                feature = "ecdh_secp256k1_native",
+                feature = "ecdh_secp256k1_asm"
+            )),
+            "ECDH-secp256k1",
+            KeyExchange,
+            ecdh,
+            128,
+        ),
+    ];
+
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        features: enabled_features![
+            "acvp",
+            "aescbc",
+            "aescbc_native",
+            "aesgcm",
+            "aesgcm_native",
+            "asm",
+            "asymmetric_encryption",
+            "attenuable",
+            "aws_kms",
+            "benchmarkaescbc",
+            "benchmarkaesgcm",
+            "benchmarked25519",
+            "benchmarksecp256k1",
+            "benchmarkxchacha20poly1305",
+            "blind_index",
+            "bls_bls12381",
+            "bls_bn254",
+            "bls_bn254_asm",
+            "chacha20poly1305",
+            "chacha20poly1305_native",
+            "cl",
+            "cl_gmp",
+            "cl_native",
+            "committing_aead",
+            "cryptobox",
+            "default",
+            "ecdh_secp256k1",
+            "ecdh_secp256k1_asm",
+            "ecdh_secp256k1_native",
+            "ecdsa_secp256k1",
+            "ecdsa_secp256k1_asm",
+            "ecdsa_secp256k1_native",
+            "ed25519",
+            "ed25519_asm",
+            "encryption",
+            "encryption_asm",
+            "envelope",
+            "expert",
+            "export_restricted",
+            "ffi",
+            "gcp_kms",
+            "group_messaging",
+            "hash_commitment",
+            "hashes",
+            "hashes_asm",
+            "hd",
+            "hpke",
+            "kex",
+            "kex_asm",
+            "kex_kk",
+            "kex_native",
+            "key_encoding",
+            "key_transparency",
+            "key_usage",
+            "keyring",
+            "kmip",
+            "kvac",
+            "leaky",
+            "legacy_ciphers",
+            "logger",
+            "merkle",
+            "message_recovery",
+            "minisign",
+            "mls",
+            "mmr",
+            "openpgp",
+            "otp",
+            "parallel",
+            "paseto",
+            "password_file",
+            "portable",
+            "portable_wasm",
+            "privacy_pass",
+            "ratchet",
+            "sd_jwt",
+            "sharing",
+            "sharing_gmp",
+            "sharing_native",
+            "signature_policy",
+            "signatures",
+            "signatures_asm",
+            "signatures_native",
+            "sparse_merkle",
+            "srp",
+            "status_list",
+            "streaming",
+            "symmetric_encryption",
+            "uprove",
+            "wasm",
+            "webauthn",
+            "x25519",
+            "x25519_asm",
+        ],
+        algorithms: table
+            .iter()
+            .filter(|(enabled, ..)| *enabled)
+            .map(|&(_, name, kind, backend, security_bits)| Algorithm {
+                name,
+                kind,
+                backend,
+                security_bits,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_match_features() {
+        let capabilities = capabilities();
+        assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+        let mut sorted = capabilities.features.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, capabilities.features);
+        assert_eq!(
+            capabilities.has_feature("ed25519"),
+            cfg!(feature = "ed25519")
+        );
+        assert!(!capabilities.has_feature("no_such_feature"));
+
+        let ed25519 = capabilities.algorithm("Ed25519");
+        assert_eq!(
+            ed25519.is_some(),
+            cfg!(any(feature = "ed25519", feature = "ed25519_asm"))
+        );
+        if let Some(ed25519) = ed25519 {
+            assert_eq!(ed25519.kind, AlgorithmKind::Signature);
+            assert_eq!(ed25519.security_bits, 128);
+        }
+        assert_eq!(
+            capabilities.algorithm("SHA-256").is_some(),
+            cfg!(feature = "sha2")
+        );
+        assert!(capabilities.algorithm("MD5").is_none());
+        assert!(capabilities
+            .algorithms_of(AlgorithmKind::Aead)
+            .all(|a| a.security_bits >= 128));
+    }
+
+    assert_capabilities!(not(POST_QUANTUM));
+
+    #[test]
+    fn categories_match_algorithms() {
+        let capabilities = capabilities();
+        assert_eq!(
+            LEGACY_CIPHERS,
+            capabilities.algorithm("AES-128-CBC-HMAC-SHA256").is_some()
+        );
+        assert_eq!(
+            SYMMETRIC_ENCRYPTION,
+            capabilities.algorithms_of(AlgorithmKind::Aead).count() > 0
+        );
+        assert_eq!(
+            SIGNATURES,
+            capabilities.algorithms_of(AlgorithmKind::Signature).count() > 0
+        );
+        assert_eq!(
+            KEY_EXCHANGE,
+            capabilities
+                .algorithms_of(AlgorithmKind::KeyExchange)
+                .count()
+                > 0
+        );
+        if cfg!(feature = "export_restricted") {
+            assert!(SIGNATURES && SYMMETRIC_ENCRYPTION);
+        }
+        if cfg!(feature = "asymmetric_encryption") {
+            assert!(ASYMMETRIC_ENCRYPTION);
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn capabilities_to_json() {
+        let json = serde_json::to_value(capabilities()).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["features"].is_array());
+        assert!(json["algorithms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|a| a["name"].is_string() && a["security_bits"].is_u64()));
+    }
+}
@@ -0,0 +1,182 @@
+//! Canonical JSON for credential artifacts.
+//!
+//! `serde_json::to_string` writes struct fields in declaration order and maps in their
+//! iteration order, which for the `HashMap`s in proofs changes from run to run. Hashes and
+//! signatures over that output cannot be reproduced by another implementation or even by
+//! the same program twice.
+//!
+//! The canonical form follows the JSON Canonicalization Scheme (RFC 8785) for the values
+//! these artifacts contain: object keys are sorted by their UTF-16 code units, there is no
+//! whitespace, strings use the shortest escapes and numbers are written as plain integers.
+//! Big numbers and group elements are already strings so no precision is lost.
+//! Floating point numbers have no single encoding across languages and are rejected.
+//!
+//! Credential offers (`CredentialKeyCorrectnessProof` and `Nonce`), requests
+//! (`BlindedCredentialSecrets` and its correctness proof), credentials (`CredentialSignature`
+//! and `SignatureCorrectnessProof`) and presentations (`Proof`) can all be written this way,
+//! as can any other serializable value.
+//!
+//! # Example
+//! ```
+//! use ursa::cl::canonical::to_canonical_json;
+//! use ursa::cl::new_nonce;
+//!
+//! let nonce = new_nonce().unwrap();
+//! let json = to_canonical_json(&nonce).unwrap();
+//! assert_eq!(json, format!("\"{}\"", nonce.to_dec().unwrap()));
+//! ```
+
+use errors::prelude::*;
+use serde::Serialize;
+use serde_json::{self, Map, Number, Value};
+
+/// Serialize `value` to canonical JSON
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> UrsaCryptoResult<String> {
+    let value = serde_json::to_value(value).map_err(|err| {
+        err_msg(
+            UrsaCryptoErrorKind::InvalidStructure,
+            format!("Cannot serialize to JSON: {}", err),
+        )
+    })?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// Serialize `value` to the UTF-8 bytes of its canonical JSON, ready for hashing or signing
+pub fn to_canonical_json_bytes<T: Serialize + ?Sized>(value: &T) -> UrsaCryptoResult<Vec<u8>> {
+    Ok(to_canonical_json(value)?.into_bytes())
+}
+
+fn write_value(value: &Value, out: &mut String) -> UrsaCryptoResult<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out)?,
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => write_object(map, out)?,
+    }
+    Ok(())
+}
+
+fn write_object(map: &Map<String, Value>, out: &mut String) -> UrsaCryptoResult<()> {
+    let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+    out.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(key, out);
+        out.push(':');
+        write_value(value, out)?;
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_number(n: &Number, out: &mut String) -> UrsaCryptoResult<()> {
+    if let Some(u) = n.as_u64() {
+        out.push_str(&u.to_string());
+    } else if let Some(i) = n.as_i64() {
+        out.push_str(&i.to_string());
+    } else {
+        return Err(err_msg(
+            UrsaCryptoErrorKind::InvalidStructure,
+            format!("Floating point number {} has no canonical encoding", n),
+        ));
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cl::{issuer, prover, Proof, SubProof};
+    use std::collections::HashMap;
+
+    #[test]
+    fn canonical_json_values() {
+        let value = json!({
+            "b": [1, -2, true, null],
+            "a": {"\u{ff61}": "x", "\u{1f600}": "y", "A\"\n\u{1}": ""},
+            "aa": 18446744073709551615u64
+        });
+        // Sorted by UTF-16 code units, U+1F600 is a surrogate pair starting with 0xD83D
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            "{\"a\":{\"A\\\"\\n\\u0001\":\"\",\"\u{1f600}\":\"y\",\"\u{ff61}\":\"x\"},\
+             \"aa\":18446744073709551615,\"b\":[1,-2,true,null]}"
+        );
+        assert!(to_canonical_json(&json!({ "f": 1.5 })).is_err());
+
+        // Map iteration order does not matter
+        let mut one = HashMap::new();
+        let mut two = HashMap::new();
+        for i in 0..32 {
+            one.insert(format!("attr{}", i), i);
+            two.insert(format!("attr{}", 31 - i), 31 - i);
+        }
+        assert_eq!(
+            to_canonical_json(&one).unwrap(),
+            to_canonical_json(&two).unwrap()
+        );
+    }
+
+    fn assert_canonical<T: Serialize + ::serde::de::DeserializeOwned>(artifact: &T) {
+        let json = to_canonical_json(artifact).unwrap();
+        // Parsing fills the maps in a new order
+        let parsed: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(to_canonical_json(&parsed).unwrap(), json);
+        assert_eq!(to_canonical_json_bytes(&parsed).unwrap(), json.into_bytes());
+    }
+
+    #[test]
+    fn canonical_json_artifacts() {
+        // Offer
+        assert_canonical(&issuer::mocks::credential_key_correctness_proof());
+        assert_canonical(&issuer::mocks::credential_nonce());
+        // Request
+        assert_canonical(&prover::mocks::blinded_credential_secrets());
+        assert_canonical(&prover::mocks::blinded_credential_secrets_correctness_proof());
+        // Credential
+        assert_canonical(&issuer::mocks::credential());
+        assert_canonical(&issuer::mocks::signature_correctness_proof());
+        // Presentation, without the non-revocation part of the mock
+        assert_canonical(&Proof {
+            proofs: vec![SubProof {
+                primary_proof: prover::mocks::primary_proof(),
+                non_revoc_proof: None,
+            }],
+            aggregated_proof: prover::mocks::aggregated_proof(),
+        });
+    }
+}
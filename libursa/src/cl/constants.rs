@@ -1,4 +1,8 @@
 use bn::{BigNumber, BIGNUMBER_2};
+use errors::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 pub const LARGE_MASTER_SECRET: usize = 256;
 pub const LARGE_E_START: usize = 596;
@@ -36,3 +40,263 @@ lazy_static! {
         .exp(&BigNumber::from_u32(LARGE_VPRIME_PRIME - 1).unwrap(), None)
         .unwrap();
 }
+
+/// Size of attribute values and the master secret
+const MESSAGE_BITS: usize = LARGE_MASTER_SECRET;
+/// Size of the challenge hash
+const HASH_BITS: usize = 256;
+/// Statistical hiding of the 2048-bit parameters
+const STATISTICAL_BITS: usize = 80;
+
+/// Bit sizes of the CL signature parameters.
+///
+/// The defaults are the constants in this module, which use a 2048-bit RSA modulus.
+/// All other sizes follow from the modulus, the statistical hiding of the blinding
+/// factors and the range of the signature prime `e`.
+/// The level is stored in the issuer's primary public key so provers and verifiers
+/// use the same sizes as the issuer.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SecurityLevel {
+    modulus_bits: usize,
+    statistical_bits: usize,
+    e_start_bits: usize,
+    e_range_bits: usize,
+}
+
+impl SecurityLevel {
+    /// Create a level after checking the sizes are consistent
+    ///
+    /// # Arguments
+    /// * `modulus_bits` - Size of the RSA modulus, at least 2048 and even
+    /// * `statistical_bits` - Statistical hiding of the blinding factors, at least 80
+    /// * `e_start_bits` - Signature primes are at least `2^e_start_bits`
+    /// * `e_range_bits` - Signature primes are less than `2^e_start_bits + 2^e_range_bits`
+    pub fn new(
+        modulus_bits: usize,
+        statistical_bits: usize,
+        e_start_bits: usize,
+        e_range_bits: usize,
+    ) -> UrsaCryptoResult<SecurityLevel> {
+        if modulus_bits < 2048 || !modulus_bits.is_multiple_of(2) {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Modulus must be an even number of at least 2048 bits, found {}",
+                    modulus_bits
+                ),
+            ));
+        }
+        if statistical_bits < STATISTICAL_BITS {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Statistical hiding must be at least {} bits, found {}",
+                    STATISTICAL_BITS, statistical_bits
+                ),
+            ));
+        }
+        // e must exceed the messages with their blindings and e' = e - 2^e_start
+        // with its blinding must not reach 2^e_start
+        if e_start_bits <= MESSAGE_BITS + statistical_bits + HASH_BITS + 2
+            || e_range_bits == 0
+            || e_range_bits + statistical_bits + HASH_BITS + 2 >= e_start_bits
+            || e_start_bits >= modulus_bits / 2
+        {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Invalid range for the signature prime, start {} and range {} bits",
+                    e_start_bits, e_range_bits
+                ),
+            ));
+        }
+        Ok(SecurityLevel {
+            modulus_bits,
+            statistical_bits,
+            e_start_bits,
+            e_range_bits,
+        })
+    }
+
+    /// The level of the constants in this module
+    pub fn modulus_2048() -> SecurityLevel {
+        SecurityLevel {
+            modulus_bits: 2 * LARGE_PRIME,
+            statistical_bits: STATISTICAL_BITS,
+            e_start_bits: LARGE_E_START,
+            e_range_bits: LARGE_E_END_RANGE,
+        }
+    }
+
+    /// A 3072-bit modulus with the default hiding and prime range
+    pub fn modulus_3072() -> SecurityLevel {
+        SecurityLevel {
+            modulus_bits: 3072,
+            ..SecurityLevel::modulus_2048()
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == SecurityLevel::default()
+    }
+
+    pub fn modulus_bits(&self) -> usize {
+        self.modulus_bits
+    }
+
+    pub fn statistical_bits(&self) -> usize {
+        self.statistical_bits
+    }
+
+    pub fn e_start_bits(&self) -> usize {
+        self.e_start_bits
+    }
+
+    pub fn e_range_bits(&self) -> usize {
+        self.e_range_bits
+    }
+
+    /// Size of each of the safe primes of the modulus
+    pub fn prime_bits(&self) -> usize {
+        self.modulus_bits / 2
+    }
+
+    /// Size of the prover's blinding factor `v'`
+    pub fn vprime_bits(&self) -> usize {
+        self.modulus_bits + self.statistical_bits
+    }
+
+    /// Size of the issuer's part `v''` of the signature blinding factor
+    pub fn vprime_prime_bits(&self) -> usize {
+        self.modulus_bits + self.e_start_bits + self.statistical_bits
+    }
+
+    /// Size of the blinding of hidden attributes
+    pub fn mvect_bits(&self) -> usize {
+        MESSAGE_BITS + self.statistical_bits + HASH_BITS
+    }
+
+    /// Size of the blinding of hidden attributes in the blinded secrets proof
+    pub fn mtilde_bits(&self) -> usize {
+        self.mvect_bits() + 1
+    }
+
+    /// Size of the blinding of `e'`
+    pub fn etilde_bits(&self) -> usize {
+        self.e_range_bits + 1 + self.statistical_bits + HASH_BITS
+    }
+
+    /// Size of the blinding of `v`
+    pub fn vtilde_bits(&self) -> usize {
+        self.vprime_prime_bits() + self.statistical_bits + HASH_BITS
+    }
+
+    /// Size of the blinding of the four squares of a predicate
+    pub fn utilde_bits(&self) -> usize {
+        self.mvect_bits()
+    }
+
+    /// Size of the blinding of the randomness of the commitments of a predicate
+    pub fn rtilde_bits(&self) -> usize {
+        2 * (self.statistical_bits + HASH_BITS)
+    }
+
+    /// Size of the blinding of `v'` in the blinded secrets proof
+    pub fn vprime_tilde_bits(&self) -> usize {
+        self.rtilde_bits() + 1
+    }
+
+    /// Size of the blinding of `alpha` of a predicate,
+    /// it keeps the distance to `v'` of the 2048-bit level
+    pub fn alphatilde_bits(&self) -> usize {
+        self.vprime_bits() + LARGE_ALPHATILDE - LARGE_VPRIME
+    }
+
+    /// 2^e_start
+    pub fn e_start_value(&self) -> UrsaCryptoResult<BigNumber> {
+        BIGNUMBER_2.exp(&BigNumber::from_u32(self.e_start_bits)?, None)
+    }
+
+    /// 2^e_start + 2^e_range
+    pub fn e_end_range_value(&self) -> UrsaCryptoResult<BigNumber> {
+        BIGNUMBER_2
+            .exp(&BigNumber::from_u32(self.e_range_bits)?, None)?
+            .add(&self.e_start_value()?)
+    }
+
+    /// 2^(v''_bits - 1)
+    pub fn vprime_prime_value(&self) -> UrsaCryptoResult<BigNumber> {
+        BIGNUMBER_2.exp(&BigNumber::from_u32(self.vprime_prime_bits() - 1)?, None)
+    }
+}
+
+impl Default for SecurityLevel {
+    fn default() -> SecurityLevel {
+        SecurityLevel::modulus_2048()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> ::serde::de::Deserialize<'a> for SecurityLevel {
+    fn deserialize<D: ::serde::de::Deserializer<'a>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct SecurityLevelV1 {
+            modulus_bits: usize,
+            statistical_bits: usize,
+            e_start_bits: usize,
+            e_range_bits: usize,
+        }
+
+        let helper = SecurityLevelV1::deserialize(deserializer)?;
+        SecurityLevel::new(
+            helper.modulus_bits,
+            helper.statistical_bits,
+            helper.e_start_bits,
+            helper.e_range_bits,
+        )
+        .map_err(::serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_security_level_matches_constants() {
+        let level = SecurityLevel::default();
+        assert_eq!(level.prime_bits(), LARGE_PRIME);
+        assert_eq!(level.vprime_bits(), LARGE_VPRIME);
+        assert_eq!(level.vprime_prime_bits(), LARGE_VPRIME_PRIME);
+        assert_eq!(level.mvect_bits(), LARGE_MVECT);
+        assert_eq!(level.mtilde_bits(), LARGE_MTILDE);
+        assert_eq!(level.etilde_bits(), LARGE_ETILDE);
+        assert_eq!(level.vtilde_bits(), LARGE_VTILDE);
+        assert_eq!(level.utilde_bits(), LARGE_UTILDE);
+        assert_eq!(level.rtilde_bits(), LARGE_RTILDE);
+        assert_eq!(level.vprime_tilde_bits(), LARGE_VPRIME_TILDE);
+        assert_eq!(level.alphatilde_bits(), LARGE_ALPHATILDE);
+        assert_eq!(level.e_start_value().unwrap(), *LARGE_E_START_VALUE);
+        assert_eq!(level.e_end_range_value().unwrap(), *LARGE_E_END_RANGE_VALUE);
+        assert_eq!(
+            level.vprime_prime_value().unwrap(),
+            *LARGE_VPRIME_PRIME_VALUE
+        );
+        assert_eq!(
+            SecurityLevel::new(2048, 80, LARGE_E_START, LARGE_E_END_RANGE).unwrap(),
+            level
+        );
+    }
+
+    #[test]
+    fn security_level_is_validated() {
+        assert!(SecurityLevel::new(3072, 128, 700, 119).is_ok());
+        assert!(SecurityLevel::new(1024, 80, 596, 119).is_err());
+        assert!(SecurityLevel::new(3071, 80, 596, 119).is_err());
+        assert!(SecurityLevel::new(2048, 64, 596, 119).is_err());
+        assert!(SecurityLevel::new(2048, 128, 596, 119).is_err());
+        assert!(SecurityLevel::new(2048, 80, 596, 0).is_err());
+        assert!(SecurityLevel::new(2048, 80, 596, 300).is_err());
+    }
+}
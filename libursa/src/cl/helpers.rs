@@ -6,6 +6,14 @@ use pair::GroupOrderElement;
 
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often `generate_safe_prime_pair` reports progress
+pub const SAFE_PRIME_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
 
 #[cfg(test)]
 use std::cell::RefCell;
@@ -122,24 +130,27 @@ pub fn encode_attribute(attribute: &str, byte_order: ByteOrder) -> UrsaCryptoRes
 }
 
 #[cfg(test)]
-pub fn generate_v_prime_prime() -> UrsaCryptoResult<BigNumber> {
+pub fn generate_v_prime_prime(security_level: &SecurityLevel) -> UrsaCryptoResult<BigNumber> {
     if MockHelper::is_injected() {
         return BigNumber::from_dec("6620937836014079781509458870800001917950459774302786434315639456568768602266735503527631640833663968617512880802104566048179854406925811731340920442625764155409951969854303612644125623549271204625894424804352003689903192473464433927658013251120302922648839652919662117216521257876025436906282750361355336367533874548955283776610021309110505377492806210342214471251451681722267655419075635703240258044336607001296052867746675049720589092355650996711033859489737240617860392914314205277920274997312351322125481593636904917159990500837822414761512231315313922792934655437808723096823124948039695324591344458785345326611693414625458359651738188933757751726392220092781991665483583988703321457480411992304516676385323318285847376271589157730040526123521479652961899368891914982347831632139045838008837541334927738208491424027");
     }
-    _generate_v_prime_prime()
+    _generate_v_prime_prime(security_level)
 }
 
 #[cfg(not(test))]
-pub fn generate_v_prime_prime() -> UrsaCryptoResult<BigNumber> {
-    _generate_v_prime_prime()
+pub fn generate_v_prime_prime(security_level: &SecurityLevel) -> UrsaCryptoResult<BigNumber> {
+    _generate_v_prime_prime(security_level)
 }
 
-pub fn _generate_v_prime_prime() -> UrsaCryptoResult<BigNumber> {
-    trace!("Helpers::generate_v_prime_prime: >>>");
+pub fn _generate_v_prime_prime(security_level: &SecurityLevel) -> UrsaCryptoResult<BigNumber> {
+    trace!(
+        "Helpers::generate_v_prime_prime: >>> security_level: {:?}",
+        security_level
+    );
 
-    let a = bn_rand(LARGE_VPRIME_PRIME)?;
+    let a = bn_rand(security_level.vprime_prime_bits())?;
 
-    let v_prime_prime = bitwise_or_big_int(&a, &LARGE_VPRIME_PRIME_VALUE)?;
+    let v_prime_prime = bitwise_or_big_int(&a, &security_level.vprime_prime_value()?)?;
 
     trace!(
         "Helpers::generate_v_prime_prime: <<< v_prime_prime: {:?}",
@@ -210,6 +221,81 @@ pub fn _generate_safe_prime(size: usize) -> UrsaCryptoResult<BigNumber> {
     Ok(safe_prime)
 }
 
+/// Search for two distinct safe primes of `size` bits on `threads` threads.
+///
+/// Each thread runs its own search and the first two results are used
+/// so the expected time drops with the number of threads.
+/// `progress` is called from the calling thread at least every `SAFE_PRIME_PROGRESS_INTERVAL`
+/// with how many primes are found and the time since the search started.
+/// Returns an error as soon as `cancellation` is set; threads still searching
+/// exit once their current candidate is finished.
+pub fn generate_safe_prime_pair(
+    size: usize,
+    threads: usize,
+    progress: &mut dyn FnMut(u8, Duration),
+    cancellation: &CancellationToken,
+) -> UrsaCryptoResult<(BigNumber, BigNumber)> {
+    trace!(
+        "Helpers::generate_safe_prime_pair: >>> size: {:?}, threads: {:?}",
+        size,
+        threads
+    );
+
+    let (sender, receiver) = channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    for _ in 0..max(threads, 1) {
+        let sender = sender.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                let safe_prime = _generate_safe_prime(size);
+                let failed = safe_prime.is_err();
+                if sender.send(safe_prime).is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let start = Instant::now();
+    let mut safe_primes: Vec<BigNumber> = Vec::with_capacity(2);
+    let res = loop {
+        if let Err(err) = cancellation.check() {
+            break Err(err);
+        }
+        match receiver.recv_timeout(SAFE_PRIME_PROGRESS_INTERVAL) {
+            Ok(Ok(safe_prime)) => {
+                if safe_primes.iter().all(|p| *p != safe_prime) {
+                    safe_primes.push(safe_prime);
+                }
+                if safe_primes.len() == 2 {
+                    let q_safe = safe_primes.pop().unwrap();
+                    let p_safe = safe_primes.pop().unwrap();
+                    break Ok((p_safe, q_safe));
+                }
+            }
+            Ok(Err(err)) => break Err(err),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                break Err(err_msg(
+                    UrsaCryptoErrorKind::InvalidState,
+                    "Safe prime search stopped unexpectedly",
+                ))
+            }
+        }
+        progress(safe_primes.len() as u8, start.elapsed());
+    };
+    stop.store(true, Ordering::SeqCst);
+
+    trace!(
+        "Helpers::generate_safe_prime_pair: <<< res: {:?}",
+        secret!(&res)
+    );
+
+    res
+}
+
 #[cfg(test)]
 pub fn gen_x(p: &BigNumber, q: &BigNumber) -> UrsaCryptoResult<BigNumber> {
     if MockHelper::is_injected() {
@@ -295,6 +381,7 @@ pub fn transform_u32_to_array_of_u8(x: u32) -> Vec<u8> {
 pub fn get_mtilde<S: ::std::hash::BuildHasher>(
     unrevealed_attrs: &HashSet<String, S>,
     mtilde: &mut HashMap<String, BigNumber, S>,
+    security_level: &SecurityLevel,
 ) -> UrsaCryptoResult<()> {
     trace!(
         "Helpers::get_mtilde: >>> unrevealed_attrs: {:?}",
@@ -303,7 +390,7 @@ pub fn get_mtilde<S: ::std::hash::BuildHasher>(
 
     for attr in unrevealed_attrs {
         if !mtilde.contains_key(attr) {
-            mtilde.insert(attr.clone(), bn_rand(LARGE_MVECT)?);
+            mtilde.insert(attr.clone(), bn_rand(security_level.mvect_bits())?);
         }
     }
 
@@ -669,6 +756,32 @@ mod tests {
     use super::*;
     use cl::{issuer, prover};
 
+    #[test]
+    fn generate_safe_prime_pair_works() {
+        let mut reports = 0;
+        let (p_safe, q_safe) = generate_safe_prime_pair(
+            256,
+            2,
+            &mut |found, _| {
+                assert!(found < 2);
+                reports += 1;
+            },
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert!(reports > 0);
+        assert_ne!(p_safe, q_safe);
+        assert!(p_safe.is_safe_prime(None).unwrap());
+        assert!(q_safe.is_safe_prime(None).unwrap());
+    }
+
+    #[test]
+    fn generate_safe_prime_pair_can_be_cancelled() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        assert!(generate_safe_prime_pair(LARGE_PRIME, 2, &mut |_, _| {}, &cancellation).is_err());
+    }
+
     #[test]
     fn encode_attribute_works() {
         let test_str = "5435";
@@ -688,7 +801,10 @@ mod tests {
         MockHelper::inject();
 
         let result = BigNumber::from_dec("6620937836014079781509458870800001917950459774302786434315639456568768602266735503527631640833663968617512880802104566048179854406925811731340920442625764155409951969854303612644125623549271204625894424804352003689903192473464433927658013251120302922648839652919662117216521257876025436906282750361355336367533874548955283776610021309110505377492806210342214471251451681722267655419075635703240258044336607001296052867746675049720589092355650996711033859489737240617860392914314205277920274997312351322125481593636904917159990500837822414761512231315313922792934655437808723096823124948039695324591344458785345326611693414625458359651738188933757751726392220092781991665483583988703321457480411992304516676385323318285847376271589157730040526123521479652961899368891914982347831632139045838008837541334927738208491424027").unwrap();
-        assert_eq!(generate_v_prime_prime().unwrap(), result);
+        assert_eq!(
+            generate_v_prime_prime(&SecurityLevel::default()).unwrap(),
+            result
+        );
     }
 
     #[test]
@@ -73,10 +73,184 @@ impl Issuer {
             support_revocation
         );
 
-        let (p_pub_key, p_priv_key, p_key_meta) =
-            Issuer::_new_credential_primary_keys(credential_schema, non_credential_schema)?;
+        let res = Issuer::new_credential_def_with_security_level(
+            credential_schema,
+            non_credential_schema,
+            support_revocation,
+            &SecurityLevel::default(),
+        )?;
 
+        trace!("Issuer::new_credential_def: <<< cred_pub_key: {:?}, cred_priv_key: {:?}, cred_key_correctness_proof: {:?}",
+               res.0, secret!(&res.1), res.2);
+
+        Ok(res)
+    }
+
+    /// Same as `new_credential_def` but with the parameter sizes in `security_level`.
+    ///
+    /// The level is kept in the public key so provers and verifiers use the same sizes,
+    /// larger moduli make key generation, issuance and proofs slower.
+    ///
+    /// # Arguments
+    /// * `credential_schema` - Credential schema entity.
+    /// * `non_credential_schema` - Non credential schema entity.
+    /// * `support_revocation` - If true non revocation part of keys will be generated.
+    /// * `security_level` - Sizes of the modulus, blinding factors and signature primes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ursa::cl::constants::SecurityLevel;
+    /// use ursa::cl::issuer::Issuer;
+    ///
+    /// let mut credential_schema_builder = Issuer::new_credential_schema_builder().unwrap();
+    /// credential_schema_builder.add_attr("name").unwrap();
+    /// let credential_schema = credential_schema_builder.finalize().unwrap();
+    ///
+    /// let mut non_credential_schema_builder = Issuer::new_non_credential_schema_builder().unwrap();
+    /// non_credential_schema_builder.add_attr("master_secret").unwrap();
+    /// let non_credential_schema = non_credential_schema_builder.finalize().unwrap();
+    ///
+    /// let (_cred_pub_key, _cred_priv_key, _cred_key_correctness_proof) =
+    ///     Issuer::new_credential_def_with_security_level(&credential_schema, &non_credential_schema, false,
+    ///         &SecurityLevel::modulus_3072()).unwrap();
+    /// ```
+    pub fn new_credential_def_with_security_level(
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        support_revocation: bool,
+        security_level: &SecurityLevel,
+    ) -> UrsaCryptoResult<(
+        CredentialPublicKey,
+        CredentialPrivateKey,
+        CredentialKeyCorrectnessProof,
+    )> {
+        trace!(
+            "Issuer::new_credential_def_with_security_level: >>> credential_schema: {:?}, support_revocation: {:?}, security_level: {:?}",
+            credential_schema,
+            support_revocation,
+            security_level
+        );
+
+        let p_keys = Issuer::_new_credential_primary_keys(
+            credential_schema,
+            non_credential_schema,
+            security_level,
+        )?;
+
+        let res = Issuer::_new_credential_def(
+            p_keys,
+            support_revocation,
+            &mut |_| {},
+            &CancellationToken::new(),
+        )?;
+
+        trace!("Issuer::new_credential_def_with_security_level: <<< cred_pub_key: {:?}, cred_priv_key: {:?}, cred_key_correctness_proof: {:?}",
+               res.0, secret!(&res.1), res.2);
+
+        Ok(res)
+    }
+
+    /// Same as `new_credential_def` but searches for the safe primes on several threads,
+    /// reports progress and can be aborted.
+    ///
+    /// Generating the safe primes takes most of the time and can take minutes.
+    ///
+    /// # Arguments
+    /// * `credential_schema` - Credential schema entity.
+    /// * `non_credential_schema` - Non credential schema entity.
+    /// * `support_revocation` - If true non revocation part of keys will be generated.
+    /// * `threads` - Number of threads searching for safe primes, usually the number of cores.
+    /// * `progress` - Called from the calling thread as key generation proceeds.
+    /// * `cancellation` - Cancel this token from another thread to abort with an error.
+    ///
+    /// # Example
+    /// ```
+    /// use ursa::cl::issuer::Issuer;
+    /// use ursa::cl::{CancellationToken, KeyGenerationProgress};
+    ///
+    /// let mut credential_schema_builder = Issuer::new_credential_schema_builder().unwrap();
+    /// credential_schema_builder.add_attr("name").unwrap();
+    /// let credential_schema = credential_schema_builder.finalize().unwrap();
+    ///
+    /// let mut non_credential_schema_builder = Issuer::new_non_credential_schema_builder().unwrap();
+    /// non_credential_schema_builder.add_attr("master_secret").unwrap();
+    /// let non_credential_schema = non_credential_schema_builder.finalize().unwrap();
+    ///
+    /// let cancellation = CancellationToken::new();
+    /// let (_cred_pub_key, _cred_priv_key, _cred_key_correctness_proof) =
+    ///     Issuer::new_credential_def_with_progress(&credential_schema, &non_credential_schema, true, 4,
+    ///         &mut |progress| {
+    ///             if let KeyGenerationProgress::SafePrimeSearch { found, elapsed } = progress {
+    ///                 println!("found {} of 2 safe primes after {:?}", found, elapsed);
+    ///             }
+    ///         },
+    ///         &cancellation).unwrap();
+    /// ```
+    pub fn new_credential_def_with_progress(
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        support_revocation: bool,
+        threads: usize,
+        progress: &mut dyn FnMut(KeyGenerationProgress),
+        cancellation: &CancellationToken,
+    ) -> UrsaCryptoResult<(
+        CredentialPublicKey,
+        CredentialPrivateKey,
+        CredentialKeyCorrectnessProof,
+    )> {
+        trace!(
+            "Issuer::new_credential_def_with_progress: >>> credential_schema: {:?}, support_revocation: {:?}, threads: {:?}",
+            credential_schema,
+            support_revocation,
+            threads
+        );
+
+        Issuer::_check_credential_schema(credential_schema)?;
+
+        let security_level = SecurityLevel::default();
+        let (p_safe, q_safe) = generate_safe_prime_pair(
+            security_level.prime_bits(),
+            threads,
+            &mut |found, elapsed| {
+                progress(KeyGenerationProgress::SafePrimeSearch { found, elapsed })
+            },
+            cancellation,
+        )?;
+
+        progress(KeyGenerationProgress::PrimaryKey);
+        let p_keys = Issuer::_new_credential_primary_keys_from_primes(
+            credential_schema,
+            non_credential_schema,
+            &p_safe,
+            &q_safe,
+            &security_level,
+        )?;
+
+        let res = Issuer::_new_credential_def(p_keys, support_revocation, progress, cancellation)?;
+
+        trace!("Issuer::new_credential_def_with_progress: <<< cred_pub_key: {:?}, cred_priv_key: {:?}, cred_key_correctness_proof: {:?}",
+               res.0, secret!(&res.1), res.2);
+
+        Ok(res)
+    }
+
+    fn _new_credential_def(
+        (p_pub_key, p_priv_key, p_key_meta): (
+            CredentialPrimaryPublicKey,
+            CredentialPrimaryPrivateKey,
+            CredentialPrimaryPublicKeyMetadata,
+        ),
+        support_revocation: bool,
+        progress: &mut dyn FnMut(KeyGenerationProgress),
+        cancellation: &CancellationToken,
+    ) -> UrsaCryptoResult<(
+        CredentialPublicKey,
+        CredentialPrivateKey,
+        CredentialKeyCorrectnessProof,
+    )> {
         let (r_pub_key, r_priv_key) = if support_revocation {
+            cancellation.check()?;
+            progress(KeyGenerationProgress::RevocationKey);
             Issuer::_new_credential_revocation_keys()
                 .map(|(r_pub_key, r_priv_key)| (Some(r_pub_key), Some(r_priv_key)))?
         } else {
@@ -91,15 +265,14 @@ impl Issuer {
             p_key: p_priv_key,
             r_key: r_priv_key,
         };
+        cancellation.check()?;
+        progress(KeyGenerationProgress::KeyCorrectnessProof);
         let cred_key_correctness_proof = Issuer::_new_credential_key_correctness_proof(
             &cred_pub_key.p_key,
             &cred_priv_key.p_key,
             &p_key_meta,
         )?;
 
-        trace!("Issuer::new_credential_def: <<< cred_pub_key: {:?}, cred_priv_key: {:?}, cred_key_correctness_proof: {:?}",
-               cred_pub_key, secret!(&cred_priv_key), cred_key_correctness_proof);
-
         Ok((cred_pub_key, cred_priv_key, cred_key_correctness_proof))
     }
 
@@ -774,6 +947,7 @@ impl Issuer {
     fn _new_credential_primary_keys(
         credential_schema: &CredentialSchema,
         non_credential_schema: &NonCredentialSchema,
+        security_level: &SecurityLevel,
     ) -> UrsaCryptoResult<(
         CredentialPrimaryPublicKey,
         CredentialPrimaryPrivateKey,
@@ -784,22 +958,47 @@ impl Issuer {
             credential_schema
         );
 
-        let mut ctx = BigNumber::new_context()?;
+        Issuer::_check_credential_schema(credential_schema)?;
+
+        let p_safe = generate_safe_prime(security_level.prime_bits())?;
+        let q_safe = generate_safe_prime(security_level.prime_bits())?;
+
+        Issuer::_new_credential_primary_keys_from_primes(
+            credential_schema,
+            non_credential_schema,
+            &p_safe,
+            &q_safe,
+            security_level,
+        )
+    }
 
+    fn _check_credential_schema(credential_schema: &CredentialSchema) -> UrsaCryptoResult<()> {
         if credential_schema.attrs.is_empty() {
             return Err(err_msg(
                 UrsaCryptoErrorKind::InvalidStructure,
                 "List of attributes is empty",
             ));
         }
+        Ok(())
+    }
 
-        let p_safe = generate_safe_prime(LARGE_PRIME)?;
-        let q_safe = generate_safe_prime(LARGE_PRIME)?;
+    fn _new_credential_primary_keys_from_primes(
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        p_safe: &BigNumber,
+        q_safe: &BigNumber,
+        security_level: &SecurityLevel,
+    ) -> UrsaCryptoResult<(
+        CredentialPrimaryPublicKey,
+        CredentialPrimaryPrivateKey,
+        CredentialPrimaryPublicKeyMetadata,
+    )> {
+        let mut ctx = BigNumber::new_context()?;
 
         let p = p_safe.rshift1()?;
         let q = q_safe.rshift1()?;
 
-        let n = p_safe.mul(&q_safe, Some(&mut ctx))?;
+        let n = p_safe.mul(q_safe, Some(&mut ctx))?;
         let s = random_qr(&n)?;
         let xz = gen_x(&p, &q)?;
 
@@ -821,7 +1020,14 @@ impl Issuer {
 
         let rctxt = s.mod_exp(&gen_x(&p, &q)?, &n, Some(&mut ctx))?;
 
-        let cred_pr_pub_key = CredentialPrimaryPublicKey { n, s, rctxt, r, z };
+        let cred_pr_pub_key = CredentialPrimaryPublicKey {
+            n,
+            s,
+            rctxt,
+            r,
+            z,
+            security_level: *security_level,
+        };
         let cred_pr_priv_key = CredentialPrimaryPrivateKey { p, q };
         let cred_pr_pub_key_metadata = CredentialPrimaryPublicKeyMetadata { xz, xr };
 
@@ -1140,9 +1346,13 @@ impl Issuer {
         trace!("Issuer::_new_primary_credential: >>> credential_context: {:?}, cred_pub_key: {:?}, cred_priv_key: {:?}, blinded_ms: {:?},\
          cred_values: {:?}", secret!(credential_context), cred_pub_key, secret!(cred_priv_key), blinded_credential_secrets, secret!(cred_values));
 
-        let v = generate_v_prime_prime()?;
+        let security_level = cred_pub_key.p_key.security_level();
+        let v = generate_v_prime_prime(security_level)?;
 
-        let e = generate_prime_in_range(&LARGE_E_START_VALUE, &LARGE_E_END_RANGE_VALUE)?;
+        let e = generate_prime_in_range(
+            &security_level.e_start_value()?,
+            &security_level.e_end_range_value()?,
+        )?;
         let (a, q) = Issuer::_sign_primary_credential(
             cred_pub_key,
             cred_priv_key,
@@ -1419,6 +1629,61 @@ mod tests {
     use cl::helpers::MockHelper;
     use cl::issuer::{mocks, Issuer};
 
+    #[test]
+    fn new_credential_def_with_progress_works() {
+        let mut stages = Vec::new();
+        let (cred_pub_key, _, cred_key_correctness_proof) =
+            Issuer::new_credential_def_with_progress(
+                &mocks::credential_schema(),
+                &mocks::non_credential_schema(),
+                true,
+                2,
+                &mut |progress| stages.push(progress),
+                &CancellationToken::new(),
+            )
+            .unwrap();
+        assert!(cred_pub_key.r_key.is_some());
+        assert!(Prover::blind_credential_secrets(
+            &cred_pub_key,
+            &cred_key_correctness_proof,
+            &mocks::credential_values(),
+            &mocks::credential_nonce(),
+        )
+        .is_ok());
+
+        assert_eq!(
+            stages[stages.len() - 3..],
+            [
+                KeyGenerationProgress::PrimaryKey,
+                KeyGenerationProgress::RevocationKey,
+                KeyGenerationProgress::KeyCorrectnessProof,
+            ]
+        );
+        for stage in &stages[..stages.len() - 3] {
+            match stage {
+                KeyGenerationProgress::SafePrimeSearch { .. } => {}
+                _ => panic!("Unexpected stage {:?}", stage),
+            }
+        }
+    }
+
+    #[test]
+    fn new_credential_def_with_progress_can_be_cancelled() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let mut stages = Vec::new();
+        assert!(Issuer::new_credential_def_with_progress(
+            &mocks::credential_schema(),
+            &mocks::non_credential_schema(),
+            false,
+            2,
+            &mut |progress| stages.push(progress),
+            &cancellation,
+        )
+        .is_err());
+        assert!(stages.is_empty());
+    }
+
     #[test]
     fn generate_context_attribute_works() {
         let rev_idx = 110;
@@ -1818,6 +2083,7 @@ pub mod mocks {
             ],
             rctxt: BigNumber::from_dec("22367649113891905664593367589756927154620026002870686791425116899113166102463385255777947612590272326902876607965930393299017708388456014672833098517510402725906562714517383519224241769370097436360213271801024664973101516459676759121006263327545857171301256844849290876113986609209526369774492299815377779730250971480247123999361231894462657785201833140206882164481738440445907028661962175780038926095996356731476561447556285865588500666880748440388241988576483428813710093676464103155200711556185738545216528962065908814210434956734336781475483267248489836659903340870985489551641891702996597499832133432061498821350").unwrap(),
             z: BigNumber::from_dec("20971049306556516416548411855462653126934915528788169742105904685171526036021814020308366595378985697473160298612279628754632434933759095053014742445453246869014501318132129164954281672366894792411718693685773560773966579052996993259737028689495198784560422879504530423473348349585086897461177376910543665826129373202987768115430007889968052288637875214108680986123834214768628273585410552488075439001161273207000954506399869209972102566538554006252214727260705838993631349254893430895487478655362331032373744785458381443406082435300178682616238581378757588795672662888045672364001684986862571709608524646032002755410").unwrap(),
+            security_level: SecurityLevel::default(),
         }
     }
 
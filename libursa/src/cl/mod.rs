@@ -1,3 +1,5 @@
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+pub mod canonical;
 pub mod constants;
 #[macro_use]
 mod datastructures;
@@ -7,9 +9,13 @@ pub mod helpers;
 pub mod hash;
 pub mod issuer;
 pub mod prover;
+#[cfg(feature = "status_list")]
+pub mod status_list;
+pub mod tails;
 pub mod verifier;
 
 use bn::BigNumber;
+use cl::constants::SecurityLevel;
 use errors::prelude::*;
 use pair::*;
 
@@ -18,6 +24,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 use std::iter::FromIterator;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Creates random nonce
 ///
@@ -38,6 +47,13 @@ pub struct CredentialSchema {
     attrs: BTreeSet<String>, /* attr names */
 }
 
+impl CredentialSchema {
+    /// The attribute names in the schema
+    pub fn attrs(&self) -> &BTreeSet<String> {
+        &self.attrs
+    }
+}
+
 /// A Builder of `Credential Schema`.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug)]
@@ -68,6 +84,13 @@ pub struct NonCredentialSchema {
     attrs: BTreeSet<String>,
 }
 
+impl NonCredentialSchema {
+    /// The attribute names that are not part of the credential schema
+    pub fn attrs(&self) -> &BTreeSet<String> {
+        &self.attrs
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug)]
 pub struct NonCredentialSchemaBuilder {
@@ -170,6 +193,11 @@ impl CredentialValues {
             attrs_values: clone_credential_value_map(&self.attrs_values)?,
         })
     }
+
+    /// The value of `attr` if it is in the set
+    pub fn get(&self, attr: &str) -> Option<&CredentialValue> {
+        self.attrs_values.get(attr)
+    }
 }
 
 /// A Builder of `Credential Values`.
@@ -321,6 +349,11 @@ pub struct CredentialPrimaryPublicKey {
     r: HashMap<String /* attr_name */, BigNumber>,
     rctxt: BigNumber,
     z: BigNumber,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "SecurityLevel::is_default")
+    )]
+    security_level: SecurityLevel,
 }
 
 impl CredentialPrimaryPublicKey {
@@ -331,8 +364,14 @@ impl CredentialPrimaryPublicKey {
             r: clone_bignum_map(&self.r)?,
             rctxt: self.rctxt.try_clone()?,
             z: self.z.try_clone()?,
+            security_level: self.security_level,
         })
     }
+
+    /// The parameter sizes the issuer created this key with
+    pub fn security_level(&self) -> &SecurityLevel {
+        &self.security_level
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -347,6 +386,8 @@ impl<'a> ::serde::de::Deserialize<'a> for CredentialPrimaryPublicKey {
             #[serde(default)]
             rms: BigNumber,
             z: BigNumber,
+            #[serde(default)]
+            security_level: SecurityLevel,
         }
 
         let mut helper = CredentialPrimaryPublicKeyV1::deserialize(deserializer)?;
@@ -359,6 +400,7 @@ impl<'a> ::serde::de::Deserialize<'a> for CredentialPrimaryPublicKey {
             rctxt: helper.rctxt,
             z: helper.z,
             r: helper.r,
+            security_level: helper.security_level,
         })
     }
 }
@@ -425,6 +467,49 @@ pub struct CredentialRevocationPrivateKey {
     sk: GroupOrderElement,
 }
 
+/// Stages reported by `Issuer::new_credential_def_with_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyGenerationProgress {
+    /// Still searching for the two safe primes. Sent periodically with how many have been found.
+    SafePrimeSearch { found: u8, elapsed: Duration },
+    /// Computing the primary public key from the safe primes
+    PrimaryKey,
+    /// Generating the non revocation keys
+    RevocationKey,
+    /// Proving the primary public key is well formed
+    KeyCorrectnessProof,
+}
+
+/// Flag shared between threads to abort a long running operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request the operation to stop as soon as possible
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn check(&self) -> UrsaCryptoResult<()> {
+        if self.is_cancelled() {
+            Err(err_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Operation cancelled",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 pub type Accumulator = PointG2;
 
 /// `Revocation Registry` contains accumulator.
@@ -502,6 +587,55 @@ impl RevocationRegistryDelta {
 
         Ok(())
     }
+
+    /// Merge a chain of consecutive deltas into a single delta.
+    ///
+    /// Each delta must start from the accumulator the previous one ends with.
+    /// Indices that are revoked and later issued again (or the reverse) cancel out
+    /// so the result only contains the net changes and witnesses only need to be updated once.
+    pub fn merge_all(
+        deltas: &[RevocationRegistryDelta],
+    ) -> UrsaCryptoResult<RevocationRegistryDelta> {
+        let (first, rest) = deltas
+            .split_first()
+            .ok_or_else(|| err_msg(UrsaCryptoErrorKind::InvalidStructure, "No deltas to merge."))?;
+        let mut merged = first.clone();
+        for delta in rest {
+            merged.merge(delta)?;
+        }
+        Ok(merged)
+    }
+
+    /// Produce the minimal delta between two registry states from a history of deltas.
+    ///
+    /// `deltas` must be in order but may extend before `rev_reg_from` and after `rev_reg_to`.
+    /// A `rev_reg_from` of `None` starts from the first delta that has no previous accumulator.
+    pub fn compact(
+        deltas: &[RevocationRegistryDelta],
+        rev_reg_from: Option<&RevocationRegistry>,
+        rev_reg_to: &RevocationRegistry,
+    ) -> UrsaCryptoResult<RevocationRegistryDelta> {
+        let prev_accum = rev_reg_from.map(|rev_reg| rev_reg.accum);
+        let start = deltas
+            .iter()
+            .position(|delta| delta.prev_accum == prev_accum)
+            .ok_or_else(|| {
+                err_msg(
+                    UrsaCryptoErrorKind::InvalidStructure,
+                    "No delta starts from the given registry.",
+                )
+            })?;
+        let end = deltas[start..]
+            .iter()
+            .position(|delta| delta.accum == rev_reg_to.accum)
+            .ok_or_else(|| {
+                err_msg(
+                    UrsaCryptoErrorKind::InvalidStructure,
+                    "No delta ends at the given registry.",
+                )
+            })?;
+        RevocationRegistryDelta::merge_all(&deltas[start..=start + end])
+    }
 }
 
 /// `Revocation Key Public` Accumulator public key.
@@ -1706,6 +1840,25 @@ mod test {
         assert_eq!(two, one);
     }
 
+    #[test]
+    fn credential_primary_public_key_security_level_conversion_works() {
+        let mut key = issuer::mocks::credential_primary_public_key();
+        let json = serde_json::to_string(&key).unwrap();
+        assert!(!json.contains("security_level"));
+        assert_eq!(
+            serde_json::from_str::<CredentialPrimaryPublicKey>(&json).unwrap(),
+            key
+        );
+
+        key.security_level = SecurityLevel::modulus_3072();
+        let json = serde_json::to_string(&key).unwrap();
+        let parsed = serde_json::from_str::<CredentialPrimaryPublicKey>(&json).unwrap();
+        assert_eq!(parsed.security_level(), &SecurityLevel::modulus_3072());
+
+        let invalid = json.replace("\"statistical_bits\":80", "\"statistical_bits\":40");
+        assert!(serde_json::from_str::<CredentialPrimaryPublicKey>(&invalid).is_err());
+    }
+
     #[test]
     fn primary_equal_proof_conversion_works() {
         let string1 = r#"{
@@ -2006,4 +2159,60 @@ mod test {
             proof_verifier.verify(&proof, &proof_request_nonce).unwrap()
         );
     }
+
+    #[test]
+    fn revocation_registry_delta_merge_all_and_compact_work() {
+        let cred_pub_key = issuer::mocks::credential_public_key();
+        let max_cred_num = 5;
+        let (_, _, mut rev_reg, mut rev_tails_generator) =
+            Issuer::new_revocation_registry_def(&cred_pub_key, max_cred_num, true).unwrap();
+        let simple_tail_accessor = SimpleTailsAccessor::new(&mut rev_tails_generator).unwrap();
+        let rev_reg_start = rev_reg.clone();
+
+        let mut deltas = Vec::new();
+        for rev_idx in 1..=4 {
+            deltas.push(
+                Issuer::revoke_credential(
+                    &mut rev_reg,
+                    max_cred_num,
+                    rev_idx,
+                    &simple_tail_accessor,
+                )
+                .unwrap(),
+            );
+        }
+        let rev_reg_middle = rev_reg.clone();
+        deltas.push(
+            Issuer::recovery_credential(&mut rev_reg, max_cred_num, 2, &simple_tail_accessor)
+                .unwrap(),
+        );
+        deltas.push(
+            Issuer::recovery_credential(&mut rev_reg, max_cred_num, 3, &simple_tail_accessor)
+                .unwrap(),
+        );
+        let rev_reg_end = rev_reg.clone();
+        deltas.push(
+            Issuer::revoke_credential(&mut rev_reg, max_cred_num, 5, &simple_tail_accessor)
+                .unwrap(),
+        );
+
+        let merged = RevocationRegistryDelta::merge_all(&deltas).unwrap();
+        assert_eq!(merged.prev_accum, Some(rev_reg_start.accum));
+        assert_eq!(merged.accum, rev_reg.accum);
+        assert!(merged.issued.is_empty());
+        assert_eq!(merged.revoked, HashSet::from_iter(vec![1, 4, 5]));
+
+        let compacted =
+            RevocationRegistryDelta::compact(&deltas, Some(&rev_reg_middle), &rev_reg_end).unwrap();
+        assert_eq!(compacted.prev_accum, Some(rev_reg_middle.accum));
+        assert_eq!(compacted.accum, rev_reg_end.accum);
+        assert_eq!(compacted.issued, HashSet::from_iter(vec![2, 3]));
+        assert!(compacted.revoked.is_empty());
+
+        assert!(RevocationRegistryDelta::merge_all(&[]).is_err());
+        assert!(
+            RevocationRegistryDelta::merge_all(&[deltas[0].clone(), deltas[2].clone()]).is_err()
+        );
+        assert!(RevocationRegistryDelta::compact(&deltas, None, &rev_reg_end).is_err());
+    }
 }
@@ -407,7 +407,7 @@ impl Prover {
         );
 
         let mut ctx = BigNumber::new_context()?;
-        let v_prime = bn_rand(LARGE_VPRIME)?;
+        let v_prime = bn_rand(p_pub_key.security_level().vprime_bits())?;
 
         //Hidden attributes are combined in this value
         let hidden_attributes = credential_values
@@ -510,7 +510,8 @@ impl Prover {
 
         let mut ctx = BigNumber::new_context()?;
 
-        let v_dash_tilde = bn_rand(LARGE_VPRIME_TILDE)?;
+        let security_level = p_pub_key.security_level();
+        let v_dash_tilde = bn_rand(security_level.vprime_tilde_bits())?;
 
         let mut m_tildes = BTreeMap::new();
         let mut r_tildes = BTreeMap::new();
@@ -525,7 +526,7 @@ impl Prover {
             .iter()
             .filter(|&(_, v)| v.is_hidden() || v.is_commitment())
         {
-            let m_tilde = bn_rand(LARGE_MTILDE)?;
+            let m_tilde = bn_rand(security_level.mtilde_bits())?;
             let pk_r = p_pub_key.r.get(attr).ok_or_else(|| {
                 err_msg(
                     UrsaCryptoErrorKind::InvalidStructure,
@@ -542,7 +543,7 @@ impl Prover {
                     )?;
                 }
                 CredentialValue::Commitment { .. } => {
-                    let r_tilde = bn_rand(LARGE_MTILDE)?;
+                    let r_tilde = bn_rand(security_level.mtilde_bits())?;
                     let commitment_tilde = get_pedersen_commitment(
                         &p_pub_key.z,
                         &m_tilde,
@@ -850,8 +851,18 @@ pub struct ProofBuilder {
 impl ProofBuilder {
     /// Creates m_tildes for attributes that will be the same across all subproofs
     pub fn add_common_attribute(&mut self, attr_name: &str) -> UrsaCryptoResult<()> {
+        self.add_common_attribute_with_security_level(attr_name, &SecurityLevel::default())
+    }
+
+    /// Same as `add_common_attribute` for sub proofs whose keys have the statistical hiding
+    /// of `security_level`, use the level of the key with the most hiding
+    pub fn add_common_attribute_with_security_level(
+        &mut self,
+        attr_name: &str,
+        security_level: &SecurityLevel,
+    ) -> UrsaCryptoResult<()> {
         self.common_attributes
-            .insert(attr_name.to_owned(), bn_rand(LARGE_MVECT)?);
+            .insert(attr_name.to_owned(), bn_rand(security_level.mvect_bits())?);
         Ok(())
     }
     /// Adds sub proof request to proof builder which will be used fo building of proof.
@@ -1336,11 +1347,12 @@ impl ProofBuilder {
 
         let mut ctx = BigNumber::new_context()?;
 
-        let m2_tilde = m2_t.unwrap_or(bn_rand(LARGE_MVECT)?);
+        let security_level = cred_pub_key.security_level();
+        let m2_tilde = m2_t.unwrap_or(bn_rand(security_level.mvect_bits())?);
 
-        let r = bn_rand(LARGE_VPRIME)?;
-        let e_tilde = bn_rand(LARGE_ETILDE)?;
-        let v_tilde = bn_rand(LARGE_VTILDE)?;
+        let r = bn_rand(security_level.vprime_bits())?;
+        let e_tilde = bn_rand(security_level.etilde_bits())?;
+        let v_tilde = bn_rand(security_level.vtilde_bits())?;
 
         let unrevealed_attrs = non_cred_schema_elems
             .attrs
@@ -1352,14 +1364,14 @@ impl ProofBuilder {
             .collect::<HashSet<String>>();
 
         let mut m_tilde = clone_bignum_map(&common_attributes)?;
-        get_mtilde(&unrevealed_attrs, &mut m_tilde)?;
+        get_mtilde(&unrevealed_attrs, &mut m_tilde, security_level)?;
 
         let a_prime = cred_pub_key
             .s
             .mod_exp(&r, &cred_pub_key.n, Some(&mut ctx))?
             .mod_mul(&c1.a, &cred_pub_key.n, Some(&mut ctx))?;
 
-        let e_prime = c1.e.sub(&LARGE_E_START_VALUE)?;
+        let e_prime = c1.e.sub(&security_level.e_start_value()?)?;
 
         let v_prime = c1.v.sub(&c1.e.mul(&r, Some(&mut ctx))?)?;
 
@@ -1403,6 +1415,7 @@ impl ProofBuilder {
                p_pub_key, m_tilde, cred_values, predicate);
 
         let mut ctx = BigNumber::new_context()?;
+        let security_level = p_pub_key.security_level();
 
         let attr_value = cred_values
             .attrs_values
@@ -1449,7 +1462,7 @@ impl ProofBuilder {
                 )
             })?;
 
-            let cur_r = bn_rand(LARGE_VPRIME)?;
+            let cur_r = bn_rand(security_level.vprime_bits())?;
             let cut_t = get_pedersen_commitment(
                 &p_pub_key.z,
                 &cur_u,
@@ -1464,7 +1477,7 @@ impl ProofBuilder {
             c_list.push(cut_t)
         }
 
-        let r_delta = bn_rand(LARGE_VPRIME)?;
+        let r_delta = bn_rand(security_level.vprime_bits())?;
 
         let t_delta = get_pedersen_commitment(
             &p_pub_key.z,
@@ -1483,12 +1496,12 @@ impl ProofBuilder {
         let mut r_tilde = HashMap::new();
 
         for i in 0..ITERATION {
-            u_tilde.insert(i.to_string(), bn_rand(LARGE_UTILDE)?);
-            r_tilde.insert(i.to_string(), bn_rand(LARGE_RTILDE)?);
+            u_tilde.insert(i.to_string(), bn_rand(security_level.utilde_bits())?);
+            r_tilde.insert(i.to_string(), bn_rand(security_level.rtilde_bits())?);
         }
 
-        r_tilde.insert("DELTA".to_string(), bn_rand(LARGE_RTILDE)?);
-        let alpha_tilde = bn_rand(LARGE_ALPHATILDE)?;
+        r_tilde.insert("DELTA".to_string(), bn_rand(security_level.rtilde_bits())?);
+        let alpha_tilde = bn_rand(security_level.alphatilde_bits())?;
 
         let mj = m_tilde.get(&predicate.attr_name).ok_or_else(|| {
             err_msg(
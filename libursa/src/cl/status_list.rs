@@ -0,0 +1,439 @@
+//! Bitstring status lists, an alternative to revocation accumulators.
+//!
+//! The issuer keeps one bit per credential, set when the credential is revoked, and
+//! publishes the whole list compressed with GZIP and signed with any `SignatureScheme`.
+//! Verifiers only check a signature and read a bit instead of a non-revocation proof,
+//! but they learn the index the holder presents. Lists hold at least `MIN_LENGTH`
+//! entries and indices should be assigned at random so an index alone says little
+//! about the holder.
+//!
+//! The encoding follows the W3C Bitstring Status List: bit 0 is the most significant bit
+//! of the first byte and the encoded list is the multibase base64url of the GZIP output.
+//!
+//! The index of a credential is given to the holder as a `StatusEntry`, typically as a
+//! revealed attribute of the credential so the holder cannot present another index.
+//! The holder fetches the current `SignedStatusList` and builds a `StatusProof` which the
+//! verifier checks against the issuer's key and the entry.
+//!
+//! # Example
+//! ```
+//! use ursa::cl::status_list::{StatusEntry, StatusList, MIN_LENGTH};
+//! use ursa::signatures::{ed25519::Ed25519Sha512, SignatureScheme};
+//!
+//! let scheme = Ed25519Sha512::new();
+//! let (pk, sk) = scheme.keypair(None).unwrap();
+//!
+//! // Issuer
+//! let mut list = StatusList::new(MIN_LENGTH).unwrap();
+//! list.revoke(7).unwrap();
+//! let signed = list.sign("https://example.com/status/1", 1_600_000_000, &scheme, &sk).unwrap();
+//!
+//! // Holder of the credential at index 42
+//! let entry = StatusEntry::new("https://example.com/status/1", 42);
+//! let proof = signed.prove_status(&entry).unwrap();
+//!
+//! // Verifier
+//! proof.verify(&scheme, &pk, &entry).unwrap();
+//! ```
+
+use errors::prelude::*;
+use keys::{PrivateKey, PublicKey};
+use signatures::SignatureScheme;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// The smallest number of entries in a list, 16KiB of bits
+pub const MIN_LENGTH: usize = 131_072;
+
+/// Multibase prefix for base64url without padding
+const MULTIBASE_BASE64URL: char = 'u';
+const SIGNATURE_DOMAIN: &[u8] = b"ursa cl status list v1";
+
+/// The revocation status of every credential of an issuer, kept by the issuer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusList {
+    bits: Vec<u8>,
+}
+
+impl StatusList {
+    /// Create a list of `length` entries that are all unrevoked.
+    /// `length` must be a multiple of 8 and at least `MIN_LENGTH`
+    pub fn new(length: usize) -> UrsaCryptoResult<StatusList> {
+        if length < MIN_LENGTH || length % 8 != 0 {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Status list length must be a multiple of 8 and at least {}, got {}",
+                    MIN_LENGTH, length
+                ),
+            ));
+        }
+        Ok(StatusList {
+            bits: vec![0u8; length / 8],
+        })
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// A list always has entries
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// Whether the credential at `index` is revoked
+    pub fn is_revoked(&self, index: usize) -> UrsaCryptoResult<bool> {
+        self.check_index(index)?;
+        Ok(self.bits[index / 8] & Self::mask(index) != 0)
+    }
+
+    /// Revoke the credential at `index`
+    pub fn revoke(&mut self, index: usize) -> UrsaCryptoResult<()> {
+        self.check_index(index)?;
+        self.bits[index / 8] |= Self::mask(index);
+        Ok(())
+    }
+
+    /// Undo the revocation of the credential at `index`
+    pub fn unrevoke(&mut self, index: usize) -> UrsaCryptoResult<()> {
+        self.check_index(index)?;
+        self.bits[index / 8] &= !Self::mask(index);
+        Ok(())
+    }
+
+    /// Compress and encode the list for publishing
+    pub fn encode(&self) -> UrsaCryptoResult<String> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&self.bits).map_err(io_error)?;
+        let compressed = encoder.finish().map_err(io_error)?;
+        let mut encoded = MULTIBASE_BASE64URL.to_string();
+        encoded.push_str(&base64::encode_config(&compressed, base64::URL_SAFE_NO_PAD));
+        Ok(encoded)
+    }
+
+    /// Decode a list of `length` entries encoded with `encode`.
+    /// Decompression stops after `length` entries so oversized input is rejected
+    /// without being inflated in full
+    pub fn decode(encoded: &str, length: usize) -> UrsaCryptoResult<StatusList> {
+        let mut list = StatusList::new(length)?;
+        if !encoded.starts_with(MULTIBASE_BASE64URL) {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                "Status list is not multibase base64url encoded",
+            ));
+        }
+        let compressed =
+            base64::decode_config(&encoded[1..], base64::URL_SAFE_NO_PAD).map_err(|e| {
+                err_msg(
+                    UrsaCryptoErrorKind::InvalidStructure,
+                    format!("Invalid base64url in status list: {}", e),
+                )
+            })?;
+        let mut bits = Vec::with_capacity(list.bits.len());
+        GzDecoder::new(compressed.as_slice())
+            .take(list.bits.len() as u64 + 1)
+            .read_to_end(&mut bits)
+            .map_err(io_error)?;
+        if bits.len() != list.bits.len() {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Status list has {} entries, expected {}",
+                    bits.len() * 8,
+                    length
+                ),
+            ));
+        }
+        list.bits = bits;
+        Ok(list)
+    }
+
+    /// Sign the current list as `id` issued at `issued`, in seconds since the Unix epoch
+    pub fn sign<T: SignatureScheme>(
+        &self,
+        id: &str,
+        issued: u64,
+        scheme: &T,
+        issuer_key: &PrivateKey,
+    ) -> UrsaCryptoResult<SignedStatusList> {
+        let mut signed = SignedStatusList {
+            id: id.to_string(),
+            issued,
+            length: self.len(),
+            encoded_list: self.encode()?,
+            signature: Vec::new(),
+        };
+        signed.signature = scheme
+            .sign(&signed.signed_bytes(), issuer_key)
+            .map_err(|e| {
+                err_msg(
+                    UrsaCryptoErrorKind::InvalidState,
+                    format!("Cannot sign status list: {}", e),
+                )
+            })?;
+        Ok(signed)
+    }
+
+    fn check_index(&self, index: usize) -> UrsaCryptoResult<()> {
+        if index >= self.len() {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidRevocationAccumulatorIndex,
+                format!(
+                    "Index {} is outside of the status list of {} entries",
+                    index,
+                    self.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn mask(index: usize) -> u8 {
+        0x80 >> (index % 8)
+    }
+}
+
+/// A published status list with the issuer's signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignedStatusList {
+    id: String,
+    issued: u64,
+    length: usize,
+    encoded_list: String,
+    signature: Vec<u8>,
+}
+
+impl SignedStatusList {
+    /// The identifier of the list, usually the URL it is published at
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    /// When the list was signed, in seconds since the Unix epoch.
+    /// Verifiers decide how old a list they accept
+    pub fn issued(&self) -> u64 {
+        self.issued
+    }
+
+    /// The number of entries
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The compressed and encoded list
+    pub fn encoded_list(&self) -> &str {
+        self.encoded_list.as_str()
+    }
+
+    /// Check the issuer's signature and decode the list
+    pub fn verify<T: SignatureScheme>(
+        &self,
+        scheme: &T,
+        issuer_key: &PublicKey,
+    ) -> UrsaCryptoResult<StatusList> {
+        let valid = scheme
+            .verify(&self.signed_bytes(), &self.signature, issuer_key)
+            .unwrap_or(false);
+        if !valid {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::ProofRejected,
+                "Invalid status list signature",
+            ));
+        }
+        StatusList::decode(&self.encoded_list, self.length)
+    }
+
+    /// Prove the credential with `entry` is not revoked in this list.
+    /// The signature is not checked here, the verifier does that
+    pub fn prove_status(&self, entry: &StatusEntry) -> UrsaCryptoResult<StatusProof> {
+        if entry.list_id != self.id {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!(
+                    "Status entry is for list {}, not {}",
+                    entry.list_id, self.id
+                ),
+            ));
+        }
+        let list = StatusList::decode(&self.encoded_list, self.length)?;
+        if list.is_revoked(entry.index)? {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::CredentialRevoked,
+                "Credential is revoked",
+            ));
+        }
+        Ok(StatusProof {
+            list: self.clone(),
+            index: entry.index,
+        })
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = SIGNATURE_DOMAIN.to_vec();
+        bytes.extend_from_slice(&(self.id.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(self.id.as_bytes());
+        bytes.extend_from_slice(&self.issued.to_be_bytes());
+        bytes.extend_from_slice(&(self.length as u64).to_be_bytes());
+        bytes.extend_from_slice(self.encoded_list.as_bytes());
+        bytes
+    }
+}
+
+/// Where the status of a credential is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StatusEntry {
+    /// The identifier of the status list
+    pub list_id: String,
+    /// The index of the credential in the list
+    pub index: usize,
+}
+
+impl StatusEntry {
+    /// Create an entry for the credential at `index` of the list `list_id`
+    pub fn new(list_id: &str, index: usize) -> StatusEntry {
+        StatusEntry {
+            list_id: list_id.to_string(),
+            index,
+        }
+    }
+}
+
+/// A holder's proof that a credential is not revoked: the signed list and the index in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StatusProof {
+    list: SignedStatusList,
+    index: usize,
+}
+
+impl StatusProof {
+    /// The signed list the proof was made from
+    pub fn list(&self) -> &SignedStatusList {
+        &self.list
+    }
+
+    /// The index of the credential
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Check the proof is for `entry`, the list is signed by the issuer
+    /// and the credential is not revoked
+    pub fn verify<T: SignatureScheme>(
+        &self,
+        scheme: &T,
+        issuer_key: &PublicKey,
+        entry: &StatusEntry,
+    ) -> UrsaCryptoResult<()> {
+        if entry.list_id != self.list.id || entry.index != self.index {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::ProofRejected,
+                "Status proof is for another status entry",
+            ));
+        }
+        if self
+            .list
+            .verify(scheme, issuer_key)?
+            .is_revoked(self.index)?
+        {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::CredentialRevoked,
+                "Credential is revoked",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn io_error(e: ::std::io::Error) -> UrsaCryptoError {
+    err_msg(
+        UrsaCryptoErrorKind::IOError,
+        format!("Status list compression failed: {}", e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::ed25519::Ed25519Sha512;
+
+    const ID: &str = "https://example.com/status/1";
+
+    #[test]
+    fn status_list_bits_and_encoding() {
+        assert!(StatusList::new(MIN_LENGTH - 8).is_err());
+        assert!(StatusList::new(MIN_LENGTH + 1).is_err());
+
+        let mut list = StatusList::new(MIN_LENGTH).unwrap();
+        assert_eq!(list.len(), MIN_LENGTH);
+        list.revoke(0).unwrap();
+        list.revoke(9).unwrap();
+        list.revoke(MIN_LENGTH - 1).unwrap();
+        assert!(list.revoke(MIN_LENGTH).is_err());
+        assert_eq!(list.bits[0], 0x80);
+        assert_eq!(list.bits[1], 0x40);
+        assert!(list.is_revoked(9).unwrap());
+        assert!(!list.is_revoked(10).unwrap());
+        list.unrevoke(9).unwrap();
+        assert!(!list.is_revoked(9).unwrap());
+
+        let encoded = list.encode().unwrap();
+        assert!(encoded.starts_with('u'));
+        // Mostly zeros compress well
+        assert!(encoded.len() < 1024);
+        assert_eq!(StatusList::decode(&encoded, MIN_LENGTH).unwrap(), list);
+        // The length must match
+        assert!(StatusList::decode(&encoded, MIN_LENGTH * 2).is_err());
+        let bigger = StatusList::new(MIN_LENGTH * 2).unwrap().encode().unwrap();
+        assert!(StatusList::decode(&bigger, MIN_LENGTH).is_err());
+        assert!(StatusList::decode(&encoded[1..], MIN_LENGTH).is_err());
+    }
+
+    #[test]
+    fn status_list_proofs() {
+        let scheme = Ed25519Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let (other_pk, _) = scheme.keypair(None).unwrap();
+
+        let mut list = StatusList::new(MIN_LENGTH).unwrap();
+        list.revoke(7).unwrap();
+        let signed = list.sign(ID, 1_600_000_000, &scheme, &sk).unwrap();
+        assert_eq!(signed.verify(&scheme, &pk).unwrap(), list);
+        assert!(signed.verify(&scheme, &other_pk).is_err());
+
+        let entry = StatusEntry::new(ID, 42);
+        let proof = signed.prove_status(&entry).unwrap();
+        proof.verify(&scheme, &pk, &entry).unwrap();
+        assert!(proof.verify(&scheme, &other_pk, &entry).is_err());
+        // A proof for one entry doesn't verify for another
+        assert!(proof
+            .verify(&scheme, &pk, &StatusEntry::new(ID, 43))
+            .is_err());
+
+        // Revoked credentials have no proof and old proofs fail against new lists
+        assert!(signed.prove_status(&StatusEntry::new(ID, 7)).is_err());
+        assert!(signed
+            .prove_status(&StatusEntry::new("https://example.com/status/2", 42))
+            .is_err());
+        list.revoke(42).unwrap();
+        let updated = list.sign(ID, 1_600_000_100, &scheme, &sk).unwrap();
+        assert!(updated.prove_status(&entry).is_err());
+
+        // Changing the signed fields breaks the signature
+        let mut tampered = proof.clone();
+        tampered.list.issued += 1;
+        assert!(tampered.verify(&scheme, &pk, &entry).is_err());
+        let mut tampered = proof.clone();
+        tampered.list.encoded_list = StatusList::new(MIN_LENGTH).unwrap().encode().unwrap();
+        assert!(tampered.verify(&scheme, &pk, &entry).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! Revocation tails stored outside of memory.
+//!
+//! Tails files reach hundreds of megabytes for large registries but a witness
+//! update only touches the tails for the changed indices.
+//! `TailsFileAccessor` reads each tail on demand from any `TailsReader` so
+//! files can be read from disk, from a memory map or fetched by ranges from a server.
+//!
+//! The layout is the one used by Indy: a two byte version header followed by
+//! every tail serialized with `PointG2::to_bytes`.
+use cl::*;
+use errors::prelude::*;
+use pair::PointG2;
+
+use std::cmp::{max, min};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Version header at the start of every tails file
+pub const TAILS_FILE_VERSION: [u8; 2] = [0, 2];
+
+/// Number of tails kept in each block of `CachedTailsReader` by default
+pub const DEFAULT_CACHE_BLOCK_TAILS: usize = 64;
+
+/// Random access to the bytes of a tails file.
+pub trait TailsReader {
+    /// Total length in bytes
+    fn size(&self) -> UrsaCryptoResult<u64>;
+    /// Fill `buf` with the bytes starting at `offset`
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> UrsaCryptoResult<()>;
+}
+
+/// Read tails from bytes that are already addressable.
+///
+/// Wrap a memory map of the tails file here, e.g. `SliceTailsReader(memmap::Mmap)`,
+/// so the operating system pages in only the parts that are used.
+#[derive(Debug, Clone)]
+pub struct SliceTailsReader<T: AsRef<[u8]>>(pub T);
+
+impl<T: AsRef<[u8]>> TailsReader for SliceTailsReader<T> {
+    fn size(&self) -> UrsaCryptoResult<u64> {
+        Ok(self.0.as_ref().len() as u64)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> UrsaCryptoResult<()> {
+        let data = self.0.as_ref();
+        let start = offset as usize;
+        if offset > data.len() as u64 || data.len() - start < buf.len() {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::IOError,
+                "Read past the end of the tails",
+            ));
+        }
+        buf.copy_from_slice(&data[start..start + buf.len()]);
+        Ok(())
+    }
+}
+
+/// Read tails from a file with seeks instead of loading the whole file.
+#[derive(Debug)]
+pub struct FileTailsReader {
+    file: Mutex<File>,
+    size: u64,
+}
+
+impl FileTailsReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> UrsaCryptoResult<FileTailsReader> {
+        let file = File::open(path).map_err(io_error)?;
+        let size = file.metadata().map_err(io_error)?.len();
+        Ok(FileTailsReader {
+            file: Mutex::new(file),
+            size,
+        })
+    }
+}
+
+impl TailsReader for FileTailsReader {
+    fn size(&self) -> UrsaCryptoResult<u64> {
+        Ok(self.size)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> UrsaCryptoResult<()> {
+        let mut file = self.file.lock().map_err(|_| {
+            err_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Tails file lock poisoned",
+            )
+        })?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        file.read_exact(buf).map_err(io_error)
+    }
+}
+
+/// Read tails with a caller supplied ranged fetch such as an HTTP `Range` request.
+///
+/// `fetch(offset, length)` must return exactly `length` bytes starting at `offset`.
+/// Combine with `CachedTailsReader` to avoid a round trip for every tail.
+pub struct RangeTailsReader<F>
+where
+    F: Fn(u64, usize) -> UrsaCryptoResult<Vec<u8>>,
+{
+    size: u64,
+    fetch: F,
+}
+
+impl<F> RangeTailsReader<F>
+where
+    F: Fn(u64, usize) -> UrsaCryptoResult<Vec<u8>>,
+{
+    /// `size` is the total length of the remote tails file
+    pub fn new(size: u64, fetch: F) -> RangeTailsReader<F> {
+        RangeTailsReader { size, fetch }
+    }
+}
+
+impl<F> TailsReader for RangeTailsReader<F>
+where
+    F: Fn(u64, usize) -> UrsaCryptoResult<Vec<u8>>,
+{
+    fn size(&self) -> UrsaCryptoResult<u64> {
+        Ok(self.size)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> UrsaCryptoResult<()> {
+        let data = (self.fetch)(offset, buf.len())?;
+        if data.len() != buf.len() {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::IOError,
+                format!(
+                    "Expected {} bytes of tails but received {}",
+                    buf.len(),
+                    data.len()
+                ),
+            ));
+        }
+        buf.copy_from_slice(&data);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct BlockCache {
+    blocks: HashMap<u64, Vec<u8>>,
+    /// Least recently used block first
+    order: VecDeque<u64>,
+}
+
+/// Keep the most recently used blocks of another reader in memory.
+#[derive(Debug)]
+pub struct CachedTailsReader<R: TailsReader> {
+    reader: R,
+    block_size: usize,
+    capacity: usize,
+    cache: Mutex<BlockCache>,
+}
+
+impl<R: TailsReader> CachedTailsReader<R> {
+    /// Cache at most `capacity` blocks of `DEFAULT_CACHE_BLOCK_TAILS` tails each
+    pub fn new(reader: R, capacity: usize) -> CachedTailsReader<R> {
+        CachedTailsReader::with_block_size(
+            reader,
+            DEFAULT_CACHE_BLOCK_TAILS * PointG2::BYTES_REPR_SIZE,
+            capacity,
+        )
+    }
+
+    /// Cache at most `capacity` blocks of `block_size` bytes each
+    pub fn with_block_size(reader: R, block_size: usize, capacity: usize) -> CachedTailsReader<R> {
+        CachedTailsReader {
+            reader,
+            block_size: max(block_size, 1),
+            capacity: max(capacity, 1),
+            cache: Mutex::new(BlockCache::default()),
+        }
+    }
+
+    fn read_block(&self, cache: &mut BlockCache, block: u64) -> UrsaCryptoResult<()> {
+        if cache.blocks.contains_key(&block) {
+            cache.order.retain(|b| *b != block);
+            cache.order.push_back(block);
+            return Ok(());
+        }
+
+        let start = block * self.block_size as u64;
+        let size = self.reader.size()?;
+        if start >= size {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::IOError,
+                "Read past the end of the tails",
+            ));
+        }
+        let mut data = vec![0u8; min(self.block_size as u64, size - start) as usize];
+        self.reader.read_at(start, &mut data)?;
+
+        if cache.order.len() >= self.capacity {
+            if let Some(evicted) = cache.order.pop_front() {
+                cache.blocks.remove(&evicted);
+            }
+        }
+        cache.blocks.insert(block, data);
+        cache.order.push_back(block);
+        Ok(())
+    }
+}
+
+impl<R: TailsReader> TailsReader for CachedTailsReader<R> {
+    fn size(&self) -> UrsaCryptoResult<u64> {
+        self.reader.size()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> UrsaCryptoResult<()> {
+        let mut cache = self.cache.lock().map_err(|_| {
+            err_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                "Tails cache lock poisoned",
+            )
+        })?;
+        let mut written = 0;
+        while written < buf.len() {
+            let position = offset + written as u64;
+            let block = position / self.block_size as u64;
+            let block_offset = (position % self.block_size as u64) as usize;
+            self.read_block(&mut cache, block)?;
+
+            let data = &cache.blocks[&block];
+            if block_offset >= data.len() {
+                return Err(err_msg(
+                    UrsaCryptoErrorKind::IOError,
+                    "Read past the end of the tails",
+                ));
+            }
+            let count = min(data.len() - block_offset, buf.len() - written);
+            buf[written..written + count]
+                .copy_from_slice(&data[block_offset..block_offset + count]);
+            written += count;
+        }
+        Ok(())
+    }
+}
+
+/// `RevocationTailsAccessor` that reads each tail on demand from a `TailsReader`.
+#[derive(Debug)]
+pub struct TailsFileAccessor<R: TailsReader> {
+    reader: R,
+}
+
+impl<R: TailsReader> TailsFileAccessor<R> {
+    /// Check the version header of the tails in `reader`
+    pub fn new(reader: R) -> UrsaCryptoResult<TailsFileAccessor<R>> {
+        let mut version = [0u8; 2];
+        reader.read_at(0, &mut version)?;
+        if version != TAILS_FILE_VERSION {
+            return Err(err_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                format!("Unsupported tails file version {:?}", version),
+            ));
+        }
+        Ok(TailsFileAccessor { reader })
+    }
+
+    /// Number of tails available
+    pub fn count(&self) -> UrsaCryptoResult<u32> {
+        let size = self.reader.size()?;
+        Ok(((size - TAILS_FILE_VERSION.len() as u64) / PointG2::BYTES_REPR_SIZE as u64) as u32)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: TailsReader> RevocationTailsAccessor for TailsFileAccessor<R> {
+    fn access_tail(&self, tail_id: u32, accessor: &mut dyn FnMut(&Tail)) -> UrsaCryptoResult<()> {
+        let offset =
+            TAILS_FILE_VERSION.len() as u64 + u64::from(tail_id) * PointG2::BYTES_REPR_SIZE as u64;
+        let mut bytes = vec![0u8; PointG2::BYTES_REPR_SIZE];
+        self.reader.read_at(offset, &mut bytes)?;
+        let tail = PointG2::from_bytes(&bytes)?;
+        accessor(&tail);
+        Ok(())
+    }
+}
+
+/// Write every tail from `rev_tails_generator` in the tails file layout.
+/// Returns the number of bytes written.
+pub fn write_tails<W: Write>(
+    rev_tails_generator: &mut RevocationTailsGenerator,
+    writer: &mut W,
+) -> UrsaCryptoResult<u64> {
+    writer.write_all(&TAILS_FILE_VERSION).map_err(io_error)?;
+    let mut written = TAILS_FILE_VERSION.len() as u64;
+    while let Some(tail) = rev_tails_generator.try_next()? {
+        let bytes = tail.to_bytes()?;
+        writer.write_all(&bytes).map_err(io_error)?;
+        written += bytes.len() as u64;
+    }
+    Ok(written)
+}
+
+fn io_error(err: ::std::io::Error) -> UrsaCryptoError {
+    err_msg(
+        UrsaCryptoErrorKind::IOError,
+        format!("Unable to access tails: {}", err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cl::issuer::{mocks, Issuer};
+    use std::cell::Cell;
+
+    fn tails() -> (Vec<u8>, SimpleTailsAccessor) {
+        let cred_pub_key = mocks::credential_public_key();
+        let (_, _, _, mut rev_tails_generator) =
+            Issuer::new_revocation_registry_def(&cred_pub_key, 5, false).unwrap();
+        let mut bytes = Vec::new();
+        let written = write_tails(&mut rev_tails_generator.clone(), &mut bytes).unwrap();
+        assert_eq!(written, bytes.len() as u64);
+        (
+            bytes,
+            SimpleTailsAccessor::new(&mut rev_tails_generator).unwrap(),
+        )
+    }
+
+    fn assert_same_tails<RTA: RevocationTailsAccessor>(
+        accessor: &RTA,
+        expected: &SimpleTailsAccessor,
+    ) {
+        for tail_id in 0..11 {
+            let mut tail = None;
+            accessor
+                .access_tail(tail_id, &mut |t| tail = Some(*t))
+                .unwrap();
+            let mut expected_tail = None;
+            expected
+                .access_tail(tail_id, &mut |t| expected_tail = Some(*t))
+                .unwrap();
+            assert_eq!(tail, expected_tail);
+        }
+    }
+
+    #[test]
+    fn tails_file_accessor_works() {
+        let (bytes, expected) = tails();
+        let accessor = TailsFileAccessor::new(SliceTailsReader(bytes.clone())).unwrap();
+        assert_eq!(accessor.count().unwrap(), 11);
+        assert_same_tails(&accessor, &expected);
+        assert!(accessor.access_tail(11, &mut |_| {}).is_err());
+
+        let mut bad_version = bytes;
+        bad_version[1] = 1;
+        assert!(TailsFileAccessor::new(SliceTailsReader(bad_version)).is_err());
+    }
+
+    #[test]
+    fn file_tails_reader_works() {
+        let (bytes, expected) = tails();
+        let path = ::std::env::temp_dir().join("ursa_file_tails_reader_works.tails");
+        File::create(&path).unwrap().write_all(&bytes).unwrap();
+
+        let accessor = TailsFileAccessor::new(FileTailsReader::open(&path).unwrap()).unwrap();
+        assert_same_tails(&accessor, &expected);
+        ::std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cached_range_tails_reader_works() {
+        let (bytes, expected) = tails();
+        let fetches = Cell::new(0);
+        let reader = RangeTailsReader::new(bytes.len() as u64, |offset, length| {
+            fetches.set(fetches.get() + 1);
+            let start = offset as usize;
+            Ok(bytes[start..min(start + length, bytes.len())].to_vec())
+        });
+        // Blocks that do not line up with the tails
+        let cached = CachedTailsReader::with_block_size(reader, 300, 2);
+        let accessor = TailsFileAccessor::new(cached).unwrap();
+        assert_same_tails(&accessor, &expected);
+        // Reading in order fetches every block once
+        let count = fetches.get();
+        assert_eq!(count, bytes.len() / 300 + 1);
+
+        // The most recent blocks are served from the cache
+        accessor.access_tail(10, &mut |_| {}).unwrap();
+        assert_eq!(fetches.get(), count);
+    }
+}
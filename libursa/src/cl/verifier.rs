@@ -1,5 +1,5 @@
 use bn::BigNumber;
-use cl::constants::{ITERATION, LARGE_E_START_VALUE};
+use cl::constants::ITERATION;
 use cl::hash::get_hash_as_int;
 use cl::helpers::*;
 use cl::*;
@@ -133,6 +133,11 @@ impl ProofVerifier {
 
     /// Verifies proof.
     ///
+    /// CL proofs only carry the challenge and the responses, so the verifier has to
+    /// recompute every T value to hash it and compare the challenge. Unlike BBS+ proofs,
+    /// which carry their commitments, they can't be batched with a random linear
+    /// combination and each proof is verified on its own.
+    ///
     /// # Arguments
     /// * `proof_verifier` - Proof verifier.
     /// * `proof` - Proof generated by Prover.
@@ -475,9 +480,11 @@ impl ProofVerifier {
 
         let mut ctx = BigNumber::new_context()?;
 
-        let mut rar = proof
-            .a_prime
-            .mod_exp(&LARGE_E_START_VALUE, &p_pub_key.n, Some(&mut ctx))?;
+        let mut rar = proof.a_prime.mod_exp(
+            &p_pub_key.security_level().e_start_value()?,
+            &p_pub_key.n,
+            Some(&mut ctx),
+        )?;
 
         for (attr, encoded_value) in &proof.revealed_attrs {
             let cur_r = p_pub_key.r.get(attr).ok_or_else(|| {
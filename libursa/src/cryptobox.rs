@@ -0,0 +1,885 @@
+//! Encryption and signatures with the algorithms chosen for you.
+//!
+//! For applications that just need to encrypt to someone, sign or encrypt with a
+//! password and don't want to pick algorithms or parameters:
+//!
+//! * [`seal_to`] encrypts to an X25519 public key from [`box_keypair`] with an
+//!   ephemeral key, HKDF-SHA256 and XChaCha20-Poly1305. [`open`] decrypts.
+//! * [`sign`] signs with an Ed25519 key from [`signing_keypair`], [`verify`] checks.
+//!   [`countersign`] adds a notary's signature of a signature and [`timestamp`] a time
+//!   stamping authority's token of when it existed. A [`SignatureBundle`] carries a
+//!   signature with its counter-signatures and timestamps in one envelope.
+//! * [`password_encrypt`] encrypts under a key derived from a password with Argon2id,
+//!   [`password_decrypt`] decrypts. The cipher commits to the key, so a ciphertext
+//!   can't be crafted to decrypt under many passwords and test them all at once.
+//!
+//! Every output is an [`Envelope`] naming the version of the format, the [`Algorithm`]
+//! and the id of the key it's for, which are authenticated with it. Outputs can be
+//! routed to the right key and algorithm without agreeing on a format beforehand, and
+//! new algorithms like post-quantum ones can be added under new ids while the old
+//! ones are still read. Nonces and salts are generated here.
+//!
+//! Signatures sign the envelope header and the message, so they are not plain Ed25519
+//! signatures of the message. Use `signatures::ed25519` to interoperate.
+//!
+//! Counter-signatures and timestamps sign the whole signature envelope, so they can't be
+//! moved to another signature, and hold up even if the signer later disowns or loses its
+//! key: a notary vouches it saw the signature and a timestamp that it existed before the
+//! key was revoked. Tokens follow the model of RFC 3161: the authority signs a hash of
+//! the signature with the time and a serial number. They are envelopes like the rest,
+//! not the ASN.1 tokens of RFC 3161 authorities.
+//!
+//! The envelope is encoded as
+//!
+//! ```text
+//! version (1) | algorithm id (2, big endian) | key id length (1) | key id | payload
+//! ```
+
+use argon2::{self, Argon2, Params};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use encryption::symm::prelude::*;
+use kex::x25519::X25519Sha256;
+use kex::KeyExchangeScheme;
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// The version of the envelope format created here
+pub const VERSION: u8 = 1;
+/// The length of the key ids from [`key_id`]
+pub const KEY_ID_SIZE: usize = 8;
+
+const HEADER_SIZE: usize = 4;
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+const SALT_SIZE: usize = 16;
+const SIGNATURE_SIZE: usize = 64;
+const TIMESTAMP_SIZE: usize = 16;
+const SEAL_INFO: &[u8] = b"URSA_CRYPTOBOX_SEAL_V1";
+// Argon2id with the parameters recommended by OWASP
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// The algorithms of the payloads in an [`Envelope`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Sealed to an X25519 key with an ephemeral key, HKDF-SHA256 and XChaCha20-Poly1305
+    X25519XChaCha20Poly1305,
+    /// Signed with Ed25519 over the envelope header and the message
+    Ed25519,
+    /// Encrypted under a key derived from a password with Argon2id and XChaCha20-Poly1305.
+    /// No longer decrypted, as it doesn't commit to the key
+    Argon2idXChaCha20Poly1305,
+    /// Signed with Ed25519 over the envelope header and a signature envelope
+    Ed25519Countersignature,
+    /// A time and serial number signed with Ed25519 over the envelope header and the
+    /// hash of a signature envelope
+    Ed25519Timestamp,
+    /// A signature envelope with its counter-signatures and timestamps
+    SignatureBundle,
+    /// Encrypted under a key derived from a password with Argon2id and key-committing
+    /// XChaCha20-Poly1305
+    Argon2idXChaCha20Poly1305Committing,
+}
+
+impl Algorithm {
+    /// The id of the algorithm in envelopes
+    pub fn id(self) -> u16 {
+        match self {
+            Algorithm::X25519XChaCha20Poly1305 => 1,
+            Algorithm::Ed25519 => 2,
+            Algorithm::Argon2idXChaCha20Poly1305 => 3,
+            Algorithm::Ed25519Countersignature => 4,
+            Algorithm::Ed25519Timestamp => 5,
+            Algorithm::SignatureBundle => 6,
+            Algorithm::Argon2idXChaCha20Poly1305Committing => 7,
+        }
+    }
+
+    /// The algorithm with `id`, if it's supported
+    pub fn from_id(id: u16) -> Option<Self> {
+        match id {
+            1 => Some(Algorithm::X25519XChaCha20Poly1305),
+            2 => Some(Algorithm::Ed25519),
+            3 => Some(Algorithm::Argon2idXChaCha20Poly1305),
+            4 => Some(Algorithm::Ed25519Countersignature),
+            5 => Some(Algorithm::Ed25519Timestamp),
+            6 => Some(Algorithm::SignatureBundle),
+            7 => Some(Algorithm::Argon2idXChaCha20Poly1305Committing),
+            _ => None,
+        }
+    }
+}
+
+/// The self-describing wrapper of every output, borrowing from the encoded bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    /// The version of the envelope format
+    pub version: u8,
+    /// The id of the algorithm of the payload, which may be one this version of
+    /// the library doesn't support
+    pub algorithm_id: u16,
+    /// The id of the key from [`key_id`]. Empty for passwords.
+    pub key_id: &'a [u8],
+    /// The ciphertext or signature
+    pub payload: &'a [u8],
+    header: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// Parse an envelope of any algorithm so it can be routed to the right key
+    pub fn parse(data: &'a [u8]) -> Result<Self, CryptoError> {
+        if data.is_empty() {
+            return Err(CryptoError::ParseError("Input is too short".to_string()));
+        }
+        if data[0] != VERSION {
+            return Err(CryptoError::NoSuchAlgorithm(format!(
+                "Envelope version {} is not supported",
+                data[0]
+            )));
+        }
+        if data.len() < HEADER_SIZE || data.len() < HEADER_SIZE + data[3] as usize {
+            return Err(CryptoError::ParseError("Input is too short".to_string()));
+        }
+        let header_size = HEADER_SIZE + data[3] as usize;
+        Ok(Envelope {
+            version: data[0],
+            algorithm_id: u16::from(data[1]) << 8 | u16::from(data[2]),
+            key_id: &data[HEADER_SIZE..header_size],
+            payload: &data[header_size..],
+            header: &data[..header_size],
+        })
+    }
+
+    /// The algorithm of the payload, or `None` if it isn't supported
+    pub fn algorithm(&self) -> Option<Algorithm> {
+        Algorithm::from_id(self.algorithm_id)
+    }
+
+    /// Parse an envelope and check it's for `algorithm`
+    fn parse_for(data: &'a [u8], algorithm: Algorithm) -> Result<Self, CryptoError> {
+        let envelope = Envelope::parse(data)?;
+        match envelope.algorithm() {
+            Some(a) if a == algorithm => Ok(envelope),
+            Some(a) => Err(CryptoError::ParseError(format!(
+                "Expected an envelope of {:?} but found {:?}",
+                algorithm, a
+            ))),
+            None => Err(CryptoError::NoSuchAlgorithm(format!(
+                "Algorithm {} is not supported",
+                envelope.algorithm_id
+            ))),
+        }
+    }
+
+    /// Check the envelope is for the key with id `key_id`
+    fn check_key_id(&self, key_id: &[u8]) -> Result<(), CryptoError> {
+        if self.key_id == key_id {
+            Ok(())
+        } else {
+            Err(CryptoError::GeneralError(
+                "Envelope is for another key".to_string(),
+            ))
+        }
+    }
+}
+
+/// The id of `public_key` in envelopes, the first [`KEY_ID_SIZE`] bytes of its SHA-256 hash
+pub fn key_id(public_key: &PublicKey) -> Vec<u8> {
+    Sha256::digest(&public_key[..])[..KEY_ID_SIZE].to_vec()
+}
+
+/// Generate a key pair for [`seal_to`] and [`open`]
+pub fn box_keypair() -> Result<(PublicKey, PrivateKey), CryptoError> {
+    X25519Sha256::new().keypair(None)
+}
+
+/// Generate a key pair for [`sign`] and [`verify`]
+pub fn signing_keypair() -> Result<(PublicKey, PrivateKey), CryptoError> {
+    Ed25519Sha512::new().keypair(None)
+}
+
+/// Encrypt `message` so only the holder of the private key of `public_key` can read it.
+/// The sender is anonymous, sign the message too if the recipient must know who sent it.
+pub fn seal_to(public_key: &PublicKey, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    check_size(
+        &public_key[..],
+        X25519Sha256::public_key_size(),
+        "public key",
+    )?;
+    let scheme = X25519Sha256::new();
+    let (ephemeral_public, ephemeral_private) = scheme.keypair(None)?;
+    let shared_secret = scheme.compute_shared_secret(&ephemeral_private, public_key)?;
+    let mut output = header(Algorithm::X25519XChaCha20Poly1305, &key_id(public_key));
+    let encryptor = seal_encryptor(&shared_secret[..], &ephemeral_public, public_key)?;
+    let ciphertext = encrypt(&encryptor, &output, message)?;
+    output.extend_from_slice(&ephemeral_public[..]);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// Decrypt a message from [`seal_to`] with the recipient's private key
+pub fn open(private_key: &PrivateKey, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    check_size(
+        &private_key[..],
+        X25519Sha256::private_key_size(),
+        "private key",
+    )?;
+    let envelope = Envelope::parse_for(sealed, Algorithm::X25519XChaCha20Poly1305)?;
+    let scheme = X25519Sha256::new();
+    let (public_key, _) = scheme.keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(
+        private_key[..].to_vec(),
+    ))))?;
+    envelope.check_key_id(&key_id(&public_key))?;
+    let public_key_size = X25519Sha256::public_key_size();
+    if envelope.payload.len() < public_key_size {
+        return Err(CryptoError::ParseError(
+            "Sealed message is too short".to_string(),
+        ));
+    }
+    let (ephemeral_public, ciphertext) = envelope.payload.split_at(public_key_size);
+    let ephemeral_public = PublicKey(ephemeral_public.to_vec());
+
+    let shared_secret = scheme.compute_shared_secret(private_key, &ephemeral_public)?;
+    let encryptor = seal_encryptor(&shared_secret[..], &ephemeral_public, &public_key)?;
+    decrypt(&encryptor, envelope.header, ciphertext)
+        .map_err(|_| CryptoError::GeneralError("Failed to open the sealed message".to_string()))
+}
+
+/// Sign `message` with a private key from [`signing_keypair`]
+pub fn sign(private_key: &PrivateKey, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut output = signed_header(private_key, Algorithm::Ed25519)?;
+    let signature = Ed25519Sha512::new().sign(&concat(&output, message), private_key)?;
+    output.extend_from_slice(&signature);
+    Ok(output)
+}
+
+/// Check a signature from [`sign`] of `message` by the holder of `public_key`
+pub fn verify(public_key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    let envelope = Envelope::parse_for(signature, Algorithm::Ed25519)?;
+    envelope.check_key_id(&key_id(public_key))?;
+    check_signature(
+        public_key,
+        &concat(envelope.header, message),
+        envelope.payload,
+        "Signature is invalid",
+    )
+}
+
+/// Counter-sign a signature from [`sign`] with a notary's private key from
+/// [`signing_keypair`]
+pub fn countersign(private_key: &PrivateKey, signature: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    Envelope::parse_for(signature, Algorithm::Ed25519)?;
+    let mut output = signed_header(private_key, Algorithm::Ed25519Countersignature)?;
+    let countersignature = Ed25519Sha512::new().sign(&concat(&output, signature), private_key)?;
+    output.extend_from_slice(&countersignature);
+    Ok(output)
+}
+
+/// Check a counter-signature from [`countersign`] of `signature` by the notary holding
+/// `public_key`
+pub fn verify_countersignature(
+    public_key: &PublicKey,
+    signature: &[u8],
+    countersignature: &[u8],
+) -> Result<(), CryptoError> {
+    let envelope = Envelope::parse_for(countersignature, Algorithm::Ed25519Countersignature)?;
+    envelope.check_key_id(&key_id(public_key))?;
+    check_signature(
+        public_key,
+        &concat(envelope.header, signature),
+        envelope.payload,
+        "Counter-signature is invalid",
+    )
+}
+
+/// When a timestamp token says a signature existed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    /// The time in seconds since the Unix epoch
+    pub time: u64,
+    /// The authority's serial number of the token
+    pub serial: u64,
+}
+
+/// Issue a token that `signature` existed at `timestamp`, as a time stamping authority
+/// with a private key from [`signing_keypair`]. The authority takes the time from its
+/// own clock and never issues two tokens with the same serial number
+pub fn timestamp(
+    private_key: &PrivateKey,
+    signature: &[u8],
+    timestamp: Timestamp,
+) -> Result<Vec<u8>, CryptoError> {
+    Envelope::parse_for(signature, Algorithm::Ed25519)?;
+    let mut output = signed_header(private_key, Algorithm::Ed25519Timestamp)?;
+    output.extend_from_slice(&timestamp.time.to_be_bytes());
+    output.extend_from_slice(&timestamp.serial.to_be_bytes());
+    let token = Ed25519Sha512::new().sign(&timestamped(&output, signature), private_key)?;
+    output.extend_from_slice(&token);
+    Ok(output)
+}
+
+/// Check a token from [`timestamp`] of `signature` by the authority holding
+/// `public_key` and return the time it gives
+pub fn verify_timestamp(
+    public_key: &PublicKey,
+    signature: &[u8],
+    token: &[u8],
+) -> Result<Timestamp, CryptoError> {
+    let envelope = Envelope::parse_for(token, Algorithm::Ed25519Timestamp)?;
+    envelope.check_key_id(&key_id(public_key))?;
+    if envelope.payload.len() != TIMESTAMP_SIZE + SIGNATURE_SIZE {
+        return Err(CryptoError::ParseError(
+            "Timestamp token has the wrong length".to_string(),
+        ));
+    }
+    let signed = &token[..token.len() - SIGNATURE_SIZE];
+    check_signature(
+        public_key,
+        &timestamped(signed, signature),
+        &envelope.payload[TIMESTAMP_SIZE..],
+        "Timestamp token is invalid",
+    )?;
+    let mut time = [0u8; 8];
+    time.copy_from_slice(&envelope.payload[..8]);
+    let mut serial = [0u8; 8];
+    serial.copy_from_slice(&envelope.payload[8..TIMESTAMP_SIZE]);
+    Ok(Timestamp {
+        time: u64::from_be_bytes(time),
+        serial: u64::from_be_bytes(serial),
+    })
+}
+
+/// A signature from [`sign`] with the counter-signatures and timestamps of it, so a
+/// document can carry the proofs of its issuer, notaries and time stamping authorities
+/// together
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignatureBundle {
+    /// The signature of the document
+    pub signature: Vec<u8>,
+    /// Counter-signatures from [`countersign`]
+    pub countersignatures: Vec<Vec<u8>>,
+    /// Timestamp tokens from [`timestamp`]
+    pub timestamps: Vec<Vec<u8>>,
+}
+
+impl SignatureBundle {
+    /// A bundle of `signature` without counter-signatures or timestamps
+    pub fn new(signature: Vec<u8>) -> Self {
+        SignatureBundle {
+            signature,
+            ..Default::default()
+        }
+    }
+
+    /// Check the signature of `message` by the holder of `public_key`
+    pub fn verify(&self, public_key: &PublicKey, message: &[u8]) -> Result<(), CryptoError> {
+        verify(public_key, message, &self.signature)
+    }
+
+    /// Check the bundle has a counter-signature by the notary holding `public_key`
+    pub fn verify_countersignature(&self, public_key: &PublicKey) -> Result<(), CryptoError> {
+        for_key(&self.countersignatures, public_key)
+            .find_map(|c| verify_countersignature(public_key, &self.signature, c).ok())
+            .ok_or_else(|| {
+                CryptoError::GeneralError("No valid counter-signature by the key".to_string())
+            })
+    }
+
+    /// Check the bundle has a timestamp token by the authority holding `public_key`
+    /// and return the earliest time its tokens give
+    pub fn verify_timestamp(&self, public_key: &PublicKey) -> Result<Timestamp, CryptoError> {
+        for_key(&self.timestamps, public_key)
+            .filter_map(|t| verify_timestamp(public_key, &self.signature, t).ok())
+            .min_by_key(|t| t.time)
+            .ok_or_else(|| {
+                CryptoError::GeneralError("No valid timestamp token by the key".to_string())
+            })
+    }
+
+    /// Encode as an envelope without a key id whose payload is the length of the
+    /// signature as 2 bytes and the signature, then the number of counter-signatures
+    /// as 1 byte and each one length prefixed like the signature, then the timestamps
+    /// the same way
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CryptoError> {
+        let mut output = header(Algorithm::SignatureBundle, &[]);
+        push_part(&mut output, &self.signature)?;
+        for parts in &[&self.countersignatures, &self.timestamps] {
+            if parts.len() > u8::MAX as usize {
+                return Err(CryptoError::GeneralError(
+                    "Too many parts for a signature bundle".to_string(),
+                ));
+            }
+            output.push(parts.len() as u8);
+            for part in parts.iter() {
+                push_part(&mut output, part)?;
+            }
+        }
+        Ok(output)
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn parse(data: &[u8]) -> Result<Self, CryptoError> {
+        let envelope = Envelope::parse_for(data, Algorithm::SignatureBundle)?;
+        let mut rest = envelope.payload;
+        let signature = take_part(&mut rest)?;
+        let mut lists = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let (count, tail) = rest.split_first().ok_or_else(bundle_truncated)?;
+            rest = tail;
+            lists.push(
+                (0..*count)
+                    .map(|_| take_part(&mut rest))
+                    .collect::<Result<Vec<_>, _>>()?,
+            );
+        }
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after signature bundle".to_string(),
+            ));
+        }
+        let timestamps = lists.pop().unwrap_or_default();
+        let countersignatures = lists.pop().unwrap_or_default();
+        Ok(SignatureBundle {
+            signature,
+            countersignatures,
+            timestamps,
+        })
+    }
+}
+
+/// Encrypt `message` under `password`. Deriving the key takes time and memory on
+/// purpose to slow down guessing, so don't call this in a loop.
+pub fn password_encrypt(password: &[u8], message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut output = header(Algorithm::Argon2idXChaCha20Poly1305Committing, &[]);
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    let encryptor = password_encryptor(password, &salt)?;
+    let ciphertext = encrypt(&encryptor, &concat(&output, &salt), message)?;
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// Decrypt a message from [`password_encrypt`]
+pub fn password_decrypt(password: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let envelope = Envelope::parse_for(ciphertext, Algorithm::Argon2idXChaCha20Poly1305Committing)?;
+    if envelope.payload.len() < SALT_SIZE {
+        return Err(CryptoError::ParseError(
+            "Encrypted message is too short".to_string(),
+        ));
+    }
+    let (salt, encrypted) = envelope.payload.split_at(SALT_SIZE);
+    let encryptor = password_encryptor(password, salt)?;
+    decrypt(&encryptor, &concat(envelope.header, salt), encrypted).map_err(|_| {
+        CryptoError::GeneralError(
+            "Failed to decrypt the message, the password may be wrong".to_string(),
+        )
+    })
+}
+
+/// The header of an envelope signed by `private_key`
+fn signed_header(private_key: &PrivateKey, algorithm: Algorithm) -> Result<Vec<u8>, CryptoError> {
+    let (public_key, _) = Ed25519Sha512::new().keypair(Some(KeyGenOption::FromSecretKey(
+        PrivateKey(private_key[..].to_vec()),
+    )))?;
+    Ok(header(algorithm, &key_id(&public_key)))
+}
+
+fn check_signature(
+    public_key: &PublicKey,
+    data: &[u8],
+    signature: &[u8],
+    error: &str,
+) -> Result<(), CryptoError> {
+    if signature.len() != SIGNATURE_SIZE {
+        return Err(CryptoError::ParseError(
+            "Signature has the wrong length".to_string(),
+        ));
+    }
+    if Ed25519Sha512::new()
+        .verify(data, signature, public_key)
+        .unwrap_or(false)
+    {
+        Ok(())
+    } else {
+        Err(CryptoError::GeneralError(error.to_string()))
+    }
+}
+
+/// What a timestamp token signs: its header, time and serial and the hash of the signature
+fn timestamped(token: &[u8], signature: &[u8]) -> Vec<u8> {
+    concat(token, &Sha256::digest(signature))
+}
+
+/// The envelopes for `public_key`
+fn for_key<'a>(
+    envelopes: &'a [Vec<u8>],
+    public_key: &PublicKey,
+) -> impl Iterator<Item = &'a Vec<u8>> {
+    let id = key_id(public_key);
+    envelopes.iter().filter(move |e| {
+        Envelope::parse(e)
+            .map(|e| e.key_id == &id[..])
+            .unwrap_or(false)
+    })
+}
+
+fn push_part(output: &mut Vec<u8>, part: &[u8]) -> Result<(), CryptoError> {
+    if part.len() > u16::MAX as usize {
+        return Err(CryptoError::GeneralError(
+            "Part is too long for a signature bundle".to_string(),
+        ));
+    }
+    output.extend_from_slice(&(part.len() as u16).to_be_bytes());
+    output.extend_from_slice(part);
+    Ok(())
+}
+
+fn take_part(rest: &mut &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if rest.len() < 2 {
+        return Err(bundle_truncated());
+    }
+    let len = (usize::from(rest[0]) << 8) | usize::from(rest[1]);
+    if rest.len() - 2 < len {
+        return Err(bundle_truncated());
+    }
+    let part = rest[2..2 + len].to_vec();
+    *rest = &rest[2 + len..];
+    Ok(part)
+}
+
+fn bundle_truncated() -> CryptoError {
+    CryptoError::ParseError("Signature bundle is truncated".to_string())
+}
+
+/// Encode the envelope header
+fn header(algorithm: Algorithm, key_id: &[u8]) -> Vec<u8> {
+    let id = algorithm.id();
+    let mut header = vec![VERSION, (id >> 8) as u8, id as u8, key_id.len() as u8];
+    header.extend_from_slice(key_id);
+    header
+}
+
+fn check_size(key: &[u8], size: usize, name: &str) -> Result<(), CryptoError> {
+    if key.len() == size {
+        Ok(())
+    } else {
+        Err(CryptoError::ParseError(format!(
+            "Expected a {} byte {}",
+            size, name
+        )))
+    }
+}
+
+/// The key bound to both public keys so the sealed message can't be moved to another
+fn seal_encryptor(
+    shared_secret: &[u8],
+    ephemeral_public: &PublicKey,
+    public_key: &PublicKey,
+) -> Result<SymmetricEncryptor<XChaCha20Poly1305>, CryptoError> {
+    let mut salt = ephemeral_public[..].to_vec();
+    salt.extend_from_slice(&public_key[..]);
+    let mut key = [0u8; KEY_SIZE];
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(SEAL_INFO, &mut key)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    let encryptor = SymmetricEncryptor::<XChaCha20Poly1305>::new_with_key(&key[..]);
+    key.zeroize();
+    encryptor.map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+fn password_encryptor(
+    password: &[u8],
+    salt: &[u8],
+) -> Result<SymmetricEncryptor<KeyCommitting<XChaCha20Poly1305>>, CryptoError> {
+    let params = Params::new(
+        ARGON2_MEMORY_KIB,
+        ARGON2_ITERATIONS,
+        ARGON2_PARALLELISM,
+        Some(KEY_SIZE),
+    )
+    .map_err(|e| CryptoError::GeneralError(e.to_string()))?;
+    let mut key = [0u8; KEY_SIZE];
+    Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(password, salt, &mut key)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    let encryptor = SymmetricEncryptor::<KeyCommitting<XChaCha20Poly1305>>::new_with_key(&key[..]);
+    key.zeroize();
+    encryptor.map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+/// Encrypt with a fresh nonce and prepend it
+fn encrypt<E: Encryptor>(
+    encryptor: &SymmetricEncryptor<E>,
+    aad: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let mut output = vec![0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut output);
+    let ciphertext = encryptor
+        .encrypt(&output[..], aad, message)
+        .map_err(|_| CryptoError::GeneralError("Failed to encrypt the message".to_string()))?;
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// Decrypt the output of `encrypt`. `decrypt_easy` would reject empty messages.
+fn decrypt<E: Encryptor>(
+    encryptor: &SymmetricEncryptor<E>,
+    aad: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(CryptoError::ParseError("Input is too short".to_string()));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+    encryptor
+        .decrypt(nonce, aad, ciphertext)
+        .map_err(|_| CryptoError::GeneralError("Failed to decrypt the message".to_string()))
+}
+
+fn concat(header: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut output = header.to_vec();
+    output.extend_from_slice(data);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seal_open() {
+        let (public_key, private_key) = box_keypair().unwrap();
+        let sealed = seal_to(&public_key, b"for your eyes only").unwrap();
+        let envelope = Envelope::parse(&sealed).unwrap();
+        assert_eq!(envelope.version, VERSION);
+        assert_eq!(
+            envelope.algorithm(),
+            Some(Algorithm::X25519XChaCha20Poly1305)
+        );
+        assert_eq!(envelope.key_id, &key_id(&public_key)[..]);
+        assert_ne!(sealed, seal_to(&public_key, b"for your eyes only").unwrap());
+        assert_eq!(open(&private_key, &sealed).unwrap(), b"for your eyes only");
+        assert_eq!(
+            open(&private_key, &seal_to(&public_key, b"").unwrap()).unwrap(),
+            b""
+        );
+
+        let (_, other) = box_keypair().unwrap();
+        assert!(open(&other, &sealed).is_err());
+        for i in &[2, 5, 12, 50, sealed.len() - 1] {
+            let mut modified = sealed.clone();
+            modified[*i] ^= 1;
+            assert!(open(&private_key, &modified).is_err());
+        }
+        assert!(open(&private_key, &sealed[..50]).is_err());
+        assert!(seal_to(&PublicKey(vec![1u8; 31]), b"message").is_err());
+        assert!(open(&PrivateKey(vec![1u8; 64]), &sealed).is_err());
+    }
+
+    #[test]
+    fn sign_verify() {
+        let (public_key, private_key) = signing_keypair().unwrap();
+        let signature = sign(&private_key, b"transfer 10").unwrap();
+        assert_eq!(signature.len(), HEADER_SIZE + KEY_ID_SIZE + SIGNATURE_SIZE);
+        assert_eq!(
+            Envelope::parse(&signature).unwrap().key_id,
+            &key_id(&public_key)[..]
+        );
+        assert!(verify(&public_key, b"transfer 10", &signature).is_ok());
+        assert!(verify(&public_key, b"transfer 11", &signature).is_err());
+        let (other, _) = signing_keypair().unwrap();
+        assert!(verify(&other, b"transfer 10", &signature).is_err());
+
+        // The plain Ed25519 signature of the message isn't accepted
+        let plain = Ed25519Sha512::new()
+            .sign(b"transfer 10", &private_key)
+            .unwrap();
+        let relabeled = concat(&header(Algorithm::Ed25519, &key_id(&public_key)), &plain);
+        assert!(verify(&public_key, b"transfer 10", &relabeled).is_err());
+        let truncated = &signature[..signature.len() - 1];
+        assert!(verify(&public_key, b"transfer 10", truncated).is_err());
+    }
+
+    #[test]
+    fn countersign_timestamp() {
+        let (public_key, private_key) = signing_keypair().unwrap();
+        let (notary, notary_key) = signing_keypair().unwrap();
+        let (tsa, tsa_key) = signing_keypair().unwrap();
+        let signature = sign(&private_key, b"deed of sale").unwrap();
+        let other = sign(&private_key, b"deed of gift").unwrap();
+
+        let countersignature = countersign(&notary_key, &signature).unwrap();
+        assert!(verify_countersignature(&notary, &signature, &countersignature).is_ok());
+        assert!(verify_countersignature(&notary, &other, &countersignature).is_err());
+        assert!(verify_countersignature(&public_key, &signature, &countersignature).is_err());
+        // Neither is a signature of the other's content
+        assert!(verify(&notary, &signature, &countersignature).is_err());
+        assert!(verify_countersignature(&public_key, b"deed of sale", &signature).is_err());
+        assert!(countersign(&notary_key, &countersignature).is_err());
+
+        let issued = Timestamp {
+            time: 1_700_000_000,
+            serial: 42,
+        };
+        let token = timestamp(&tsa_key, &signature, issued).unwrap();
+        assert_eq!(verify_timestamp(&tsa, &signature, &token).unwrap(), issued);
+        assert!(verify_timestamp(&tsa, &other, &token).is_err());
+        assert!(verify_timestamp(&notary, &signature, &token).is_err());
+        for i in &[
+            HEADER_SIZE + KEY_ID_SIZE + 7,
+            HEADER_SIZE + KEY_ID_SIZE + 15,
+        ] {
+            let mut backdated = token.clone();
+            backdated[*i] ^= 1;
+            assert!(verify_timestamp(&tsa, &signature, &backdated).is_err());
+        }
+        assert!(verify_timestamp(&tsa, &signature, &token[..token.len() - 1]).is_err());
+        assert!(timestamp(&tsa_key, b"not an envelope", issued).is_err());
+    }
+
+    #[test]
+    fn signature_bundle() {
+        let (public_key, private_key) = signing_keypair().unwrap();
+        let (notary, notary_key) = signing_keypair().unwrap();
+        let (tsa, tsa_key) = signing_keypair().unwrap();
+        let (other_notary, other_notary_key) = signing_keypair().unwrap();
+        let signature = sign(&private_key, b"deed of sale").unwrap();
+
+        let mut bundle = SignatureBundle::new(signature.clone());
+        bundle
+            .countersignatures
+            .push(countersign(&notary_key, &signature).unwrap());
+        for (time, serial) in &[(1_700_000_500, 8), (1_700_000_000, 7)] {
+            let stamp = Timestamp {
+                time: *time,
+                serial: *serial,
+            };
+            bundle
+                .timestamps
+                .push(timestamp(&tsa_key, &signature, stamp).unwrap());
+        }
+        // A counter-signature of another signature doesn't count
+        let other = sign(&private_key, b"deed of gift").unwrap();
+        bundle
+            .countersignatures
+            .push(countersign(&other_notary_key, &other).unwrap());
+
+        let encoded = bundle.to_bytes().unwrap();
+        assert_eq!(
+            Envelope::parse(&encoded).unwrap().algorithm(),
+            Some(Algorithm::SignatureBundle)
+        );
+        let parsed = SignatureBundle::parse(&encoded).unwrap();
+        assert_eq!(parsed, bundle);
+        assert!(parsed.verify(&public_key, b"deed of sale").is_ok());
+        assert!(parsed.verify(&public_key, b"deed of gift").is_err());
+        assert!(parsed.verify_countersignature(&notary).is_ok());
+        assert!(parsed.verify_countersignature(&other_notary).is_err());
+        assert!(parsed.verify_countersignature(&tsa).is_err());
+        assert_eq!(
+            parsed.verify_timestamp(&tsa).unwrap(),
+            Timestamp {
+                time: 1_700_000_000,
+                serial: 7
+            }
+        );
+        assert!(parsed.verify_timestamp(&notary).is_err());
+
+        // Moving the proofs to another signature
+        let mut moved = parsed.clone();
+        moved.signature = other;
+        assert!(moved.verify_countersignature(&notary).is_err());
+        assert!(moved.verify_timestamp(&tsa).is_err());
+
+        assert!(SignatureBundle::parse(&encoded[..encoded.len() - 1]).is_err());
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(SignatureBundle::parse(&trailing).is_err());
+        assert!(SignatureBundle::parse(&signature).is_err());
+        let empty = SignatureBundle::new(signature);
+        assert_eq!(
+            SignatureBundle::parse(&empty.to_bytes().unwrap()).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn password_round_trip() {
+        let encrypted = password_encrypt(b"correct horse", b"seed words").unwrap();
+        assert!(Envelope::parse(&encrypted).unwrap().key_id.is_empty());
+        assert_eq!(
+            password_decrypt(b"correct horse", &encrypted).unwrap(),
+            b"seed words"
+        );
+        assert!(password_decrypt(b"battery staple", &encrypted).is_err());
+        let mut modified = encrypted.clone();
+        modified[HEADER_SIZE] ^= 1;
+        assert!(password_decrypt(b"correct horse", &modified).is_err());
+
+        // Ciphertexts that don't commit to the key aren't decrypted
+        let mut legacy = encrypted.clone();
+        legacy[2] = Algorithm::Argon2idXChaCha20Poly1305.id() as u8;
+        assert!(password_decrypt(b"correct horse", &legacy).is_err());
+    }
+
+    #[test]
+    fn envelopes() {
+        let (public_key, private_key) = box_keypair().unwrap();
+        let sealed = seal_to(&public_key, b"message").unwrap();
+
+        let mut future = sealed.clone();
+        future[0] = VERSION + 1;
+        match open(&private_key, &future) {
+            Err(CryptoError::NoSuchAlgorithm(_)) => {}
+            r => panic!("expected an unsupported version, found {:?}", r),
+        }
+
+        // A new algorithm can be recognized and routed without being supported
+        let mut post_quantum = sealed.clone();
+        post_quantum[1] = 0x10;
+        let envelope = Envelope::parse(&post_quantum).unwrap();
+        assert_eq!(envelope.algorithm_id, 0x1001);
+        assert_eq!(envelope.algorithm(), None);
+        assert_eq!(envelope.key_id, &key_id(&public_key)[..]);
+        match open(&private_key, &post_quantum) {
+            Err(CryptoError::NoSuchAlgorithm(_)) => {}
+            r => panic!("expected an unsupported algorithm, found {:?}", r),
+        }
+
+        for a in &[
+            Algorithm::X25519XChaCha20Poly1305,
+            Algorithm::Ed25519,
+            Algorithm::Argon2idXChaCha20Poly1305,
+            Algorithm::Ed25519Countersignature,
+            Algorithm::Ed25519Timestamp,
+            Algorithm::SignatureBundle,
+            Algorithm::Argon2idXChaCha20Poly1305Committing,
+        ] {
+            assert_eq!(Algorithm::from_id(a.id()), Some(*a));
+        }
+        let mut relabeled = sealed.clone();
+        relabeled[2] = Algorithm::Argon2idXChaCha20Poly1305Committing.id() as u8;
+        match password_decrypt(b"password", &relabeled) {
+            Err(CryptoError::GeneralError(_)) => {}
+            r => panic!("expected a failed decryption, found {:?}", r),
+        }
+        assert!(open(&private_key, &relabeled).is_err());
+
+        let mut long_key_id = sealed.clone();
+        long_key_id[3] = 255;
+        assert!(Envelope::parse(&long_key_id).is_err());
+        assert!(Envelope::parse(&[VERSION, 0, 1]).is_err());
+        assert!(open(&private_key, &[]).is_err());
+    }
+}
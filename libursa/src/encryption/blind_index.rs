@@ -0,0 +1,257 @@
+//! Blind indexes for exact-match lookups over encrypted records.
+//!
+//! A blind index is a truncated HMAC-SHA256 of a normalized field value. It is
+//! stored next to the AEAD ciphertext of the record and the application queries
+//! by computing the index of the value it is looking for.
+//!
+//! Every field gets its own key derived from a root key and the field name, so
+//! equal values in different fields don't produce equal indexes. The index is also
+//! truncated: with fewer bits than the number of distinct values, many values share
+//! one index, a lookup returns a few false positives that are filtered out after
+//! decryption, and the index no longer identifies a value on its own. Use
+//! `recommended_bits` to pick the length.
+//!
+//! Blind indexes still reveal which records have equal values in a field. They
+//! should not be used for fields with very few possible values, such as booleans.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use CryptoError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const KEY_SIZE: usize = 32;
+pub const MAX_BITS: usize = 256;
+/// Fewer bits than this make a lookup return a large part of the table
+pub const MIN_BITS: usize = 8;
+
+const FIELD_KEY_CONTEXT: &[u8] = b"ursa blind index field key";
+
+/// The root key that every field key is derived from. Keep it apart from the keys
+/// that encrypt the records.
+pub struct BlindIndexKey([u8; KEY_SIZE]);
+
+impl BlindIndexKey {
+    pub fn new(key: [u8; KEY_SIZE]) -> Self {
+        BlindIndexKey(key)
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        BlindIndexKey(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl ::std::fmt::Debug for BlindIndexKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("BlindIndexKey(..)")
+    }
+}
+
+impl Drop for BlindIndexKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// How values are made canonical before they are indexed, so that values the
+/// application considers equal get the same index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Normalization {
+    /// Index the bytes as they are
+    None,
+    /// Remove leading and trailing whitespace
+    Trim,
+    /// Trim and lowercase, for email addresses and user names
+    CaseInsensitive,
+    /// Keep only the ASCII digits, for phone and card numbers
+    Digits,
+}
+
+impl Normalization {
+    pub fn apply(self, value: &str) -> String {
+        match self {
+            Normalization::None => value.to_string(),
+            Normalization::Trim => value.trim().to_string(),
+            Normalization::CaseInsensitive => value.trim().to_lowercase(),
+            Normalization::Digits => value.chars().filter(char::is_ascii_digit).collect(),
+        }
+    }
+}
+
+/// A blind index value. Only indexes made by the same field are comparable.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlindIndex(Vec<u8>);
+
+impl BlindIndex {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+}
+
+/// Computes the blind indexes of one field
+pub struct BlindIndexField {
+    name: String,
+    key: [u8; KEY_SIZE],
+    bits: usize,
+    normalization: Normalization,
+}
+
+impl BlindIndexField {
+    /// `name` must stay the same for the lifetime of the stored indexes, changing
+    /// it changes the field key
+    pub fn new(
+        root_key: &BlindIndexKey,
+        name: &str,
+        bits: usize,
+        normalization: Normalization,
+    ) -> Result<Self, CryptoError> {
+        if !(MIN_BITS..=MAX_BITS).contains(&bits) {
+            return Err(CryptoError::GeneralError(format!(
+                "Blind indexes must have between {} and {} bits",
+                MIN_BITS, MAX_BITS
+            )));
+        }
+        if name.is_empty() {
+            return Err(CryptoError::GeneralError(
+                "Blind index fields need a name".to_string(),
+            ));
+        }
+        let mut hmac = HmacSha256::new_varkey(&root_key.0)
+            .map_err(|e| CryptoError::KeyGenError(format!("{:?}", e)))?;
+        hmac.input(FIELD_KEY_CONTEXT);
+        hmac.input(&(name.len() as u64).to_be_bytes());
+        hmac.input(name.as_bytes());
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&hmac.result().code());
+        Ok(BlindIndexField {
+            name: name.to_string(),
+            key,
+            bits,
+            normalization,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// The index of a text value, after normalization
+    pub fn index(&self, value: &str) -> BlindIndex {
+        let mut normalized = self.normalization.apply(value);
+        let index = self.index_bytes(normalized.as_bytes());
+        normalized.zeroize();
+        index
+    }
+
+    /// The index of a binary value, which isn't normalized
+    pub fn index_bytes(&self, value: &[u8]) -> BlindIndex {
+        // A 32 byte key is always accepted
+        let mut hmac = HmacSha256::new_varkey(&self.key).unwrap();
+        hmac.input(value);
+        let mut index = hmac.result().code().to_vec();
+        index.truncate(self.bits.div_ceil(8));
+        if !self.bits.is_multiple_of(8) {
+            let last = index.len() - 1;
+            index[last] &= 0xffu8 << (8 - self.bits % 8);
+        }
+        BlindIndex(index)
+    }
+}
+
+impl Drop for BlindIndexField {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// The index length that leaves about `matches_per_index` distinct values behind
+/// every index of a field with `distinct_values` possible values, clamped to the
+/// allowed range. Two to four matches are usual, more for sensitive fields.
+pub fn recommended_bits(distinct_values: u64, matches_per_index: u64) -> usize {
+    let buckets = distinct_values / matches_per_index.max(1);
+    let bits = if buckets == 0 {
+        0
+    } else {
+        63 - buckets.leading_zeros() as usize
+    };
+    bits.clamp(MIN_BITS, MAX_BITS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indexes() {
+        let root = BlindIndexKey::generate();
+        let email =
+            BlindIndexField::new(&root, "email", 32, Normalization::CaseInsensitive).unwrap();
+        let a = email.index(" Alice@Example.com ");
+        assert_eq!(a, email.index("alice@example.com"));
+        assert_ne!(a, email.index("bob@example.com"));
+        assert_eq!(a.as_bytes().len(), 4);
+
+        let backup =
+            BlindIndexField::new(&root, "backup_email", 32, Normalization::CaseInsensitive)
+                .unwrap();
+        assert_ne!(a, backup.index("alice@example.com"));
+
+        let other_root = BlindIndexField::new(
+            &BlindIndexKey::generate(),
+            "email",
+            32,
+            Normalization::CaseInsensitive,
+        )
+        .unwrap();
+        assert_ne!(a, other_root.index("alice@example.com"));
+
+        let phone = BlindIndexField::new(&root, "phone", 12, Normalization::Digits).unwrap();
+        let p = phone.index("+1 (555) 010-9999");
+        assert_eq!(p, phone.index("15550109999"));
+        assert_eq!(p.as_bytes().len(), 2);
+        assert_eq!(p.as_bytes()[1] & 0x0f, 0);
+    }
+
+    #[test]
+    fn known_answer() {
+        let root = BlindIndexKey::new([7u8; KEY_SIZE]);
+        let field = BlindIndexField::new(&root, "ssn", 256, Normalization::Digits).unwrap();
+        let first = field.index("123-45-6789");
+        assert_eq!(first, field.index_bytes(b"123456789"));
+        let truncated = BlindIndexField::new(&root, "ssn", 64, Normalization::Digits).unwrap();
+        let second = truncated.index("123456789");
+        assert_eq!(second.as_bytes(), &first.as_bytes()[..8]);
+        assert_eq!(second.to_hex(), "3485326729c32117");
+    }
+
+    #[test]
+    fn guidance() {
+        let root = BlindIndexKey::generate();
+        assert!(BlindIndexField::new(&root, "flag", 1, Normalization::None).is_err());
+        assert!(BlindIndexField::new(&root, "wide", 257, Normalization::None).is_err());
+        assert!(BlindIndexField::new(&root, "", 32, Normalization::None).is_err());
+
+        assert_eq!(recommended_bits(1 << 20, 4), 18);
+        assert_eq!(recommended_bits(1_000_000, 2), 18);
+        assert_eq!(recommended_bits(10, 2), MIN_BITS);
+        assert_eq!(recommended_bits(u64::MAX, 0), 63);
+    }
+}
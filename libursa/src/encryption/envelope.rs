@@ -0,0 +1,1276 @@
+//! Encryption of one message to many recipients.
+//!
+//! `seal` encrypts the message once with key-committing ChaCha20-Poly1305 under a fresh
+//! content key,
+//! then wraps that key for each recipient: with HPKE for X25519 keys, with ECIES over
+//! P-256 (ephemeral ECDH, HKDF-SHA256 and ChaCha20-Poly1305) for P-256 keys, and with
+//! RSA-OAEP-SHA256 for RSA keys. Every wrapped key is labelled with the key id of the
+//! recipient's public key, so a recipient holding several keys finds the one an
+//! envelope is for with `recipients` and opens only that stanza with
+//! `Identity::open`.
+//!
+//! The content is authenticated together with the whole header, so no recipient can
+//! be added, removed or swapped without opening failing. The envelope says nothing
+//! about its sender; sign it if the recipients need to know who sent it.
+//!
+//! A recipient can also be a t-of-n committee of P-256 key shares. The content key is
+//! wrapped to the committee's group key as for a P-256 recipient, but nobody holds the
+//! whole private key: each `CommitteeMember` computes a `PartialDecryption` of the
+//! stanza with a proof that it used its share, and `Committee::open` checks the proofs
+//! and combines any t of them. Sealing to a person and to a committee at once gives
+//! one envelope that either can open.
+//!
+//! Because the content cipher commits to its key, a sender can't wrap different content
+//! keys for different recipients so that they read different messages from the same
+//! envelope. Version 1 envelopes, which didn't commit, are no longer opened.
+//!
+//! An envelope is encoded as
+//!
+//! ```text
+//! version (1) | recipient count (2, big endian) | stanzas | content ciphertext
+//! stanza = key type (1) | key id (16) | wrapped key length (2, big endian) | wrapped key
+//! ```
+
+use openssl::encrypt::{Decrypter, Encrypter};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private, Public};
+use openssl::rsa::{Padding, Rsa};
+use p256::elliptic_curve::ff::PrimeField;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::{AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use super::hpke::{self, AeadId};
+use super::symm::chacha20poly1305::ChaCha20Poly1305;
+use super::symm::committing::{KeyCommitting, COMMITMENT_SIZE};
+use aead::generic_array::GenericArray;
+use aead::{Aead, NewAead, Payload};
+use hkdf::Hkdf;
+use keys::{PrivateKey, PublicKey};
+use CryptoError;
+
+/// The version of the envelope format
+pub const VERSION: u8 = 2;
+/// The length of a key id
+pub const KEY_ID_SIZE: usize = 16;
+/// The smallest RSA modulus accepted, in bits
+pub const MIN_RSA_BITS: u32 = 2048;
+/// The RSA modulus of generated keys, in bits
+pub const RSA_BITS: u32 = 3072;
+
+const KEY_ID_LABEL: &[u8] = b"ursa envelope key id v1";
+const PROOF_LABEL: &[u8] = b"ursa envelope partial decryption v1";
+const WRAP_INFO: &[u8] = b"ursa envelope key wrap v1";
+const CONTENT_KEY_SIZE: usize = 32;
+const NONCE: [u8; 12] = [0u8; 12];
+const HEADER_SIZE: usize = 3;
+const STANZA_HEADER_SIZE: usize = 1 + KEY_ID_SIZE + 2;
+const X25519_KEY_SIZE: usize = 32;
+const P256_POINT_SIZE: usize = 65;
+const P256_SCALAR_SIZE: usize = 32;
+const COMMITTEE_HEADER_SIZE: usize = 2 + P256_POINT_SIZE;
+const PARTIAL_DECRYPTION_SIZE: usize = 1 + P256_POINT_SIZE + 2 * P256_SCALAR_SIZE;
+
+/// The most members a committee can have
+pub const MAX_COMMITTEE_MEMBERS: usize = 255;
+
+/// The id of a recipient's public key, which labels the content key wrapped for it
+pub type KeyId = [u8; KEY_ID_SIZE];
+
+/// The type of a recipient's key.
+///
+/// X25519 keys are 32 bytes each. P-256 public keys are SEC1 encoded points, either
+/// compressed or not, and private keys are 32 byte scalars. RSA public keys are DER
+/// encoded SubjectPublicKeyInfo, and private keys are DER encoded PKCS#1. Committee
+/// public keys are encoded by `Committee::to_public_key` and have no private key,
+/// only the shares of their members.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyType {
+    X25519,
+    P256,
+    Rsa,
+    Committee,
+}
+
+impl KeyType {
+    fn id(self) -> u8 {
+        match self {
+            KeyType::X25519 => 1,
+            KeyType::P256 => 2,
+            KeyType::Rsa => 3,
+            KeyType::Committee => 4,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, CryptoError> {
+        match id {
+            1 => Ok(KeyType::X25519),
+            2 => Ok(KeyType::P256),
+            3 => Ok(KeyType::Rsa),
+            4 => Ok(KeyType::Committee),
+            _ => Err(CryptoError::ParseError(format!(
+                "Unsupported envelope key type {}",
+                id
+            ))),
+        }
+    }
+
+    /// Generate a key pair of this type to receive envelopes with
+    pub fn generate_key_pair(self) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        match self {
+            KeyType::X25519 => hpke::generate_key_pair(),
+            KeyType::P256 => {
+                let secret = p256_random_scalar();
+                let public = p256_public_key(&secret);
+                Ok((
+                    PublicKey(p256_encode(&public)),
+                    PrivateKey(secret.to_repr().to_vec()),
+                ))
+            }
+            KeyType::Rsa => {
+                let rsa = Rsa::generate(RSA_BITS)?;
+                Ok((
+                    PublicKey(rsa.public_key_to_der()?),
+                    PrivateKey(rsa.private_key_to_der()?),
+                ))
+            }
+            KeyType::Committee => Err(CryptoError::KeyGenError(
+                "Committee keys are dealt with Committee::generate".to_string(),
+            )),
+        }
+    }
+
+    /// The id of `public_key`, the first 16 bytes of SHA-256 over its canonical
+    /// encoding
+    pub fn key_id(self, public_key: &PublicKey) -> Result<KeyId, CryptoError> {
+        Ok(self.canonical(&public_key[..])?.1)
+    }
+
+    /// The canonical encoding of a public key and its key id
+    fn canonical(self, public_key: &[u8]) -> Result<(Vec<u8>, KeyId), CryptoError> {
+        let canonical = match self {
+            KeyType::X25519 => {
+                if public_key.len() != X25519_KEY_SIZE {
+                    return Err(CryptoError::ParseError(
+                        "Public key has the wrong length".to_string(),
+                    ));
+                }
+                public_key.to_vec()
+            }
+            KeyType::P256 => p256_encode(&p256_point(public_key)?),
+            KeyType::Rsa => rsa_public_key(public_key)?.public_key_to_der()?,
+            KeyType::Committee => Committee::from_bytes(public_key)?.to_bytes(),
+        };
+        let key_id = self.canonical_key_id(&canonical);
+        Ok((canonical, key_id))
+    }
+
+    fn canonical_key_id(self, canonical: &[u8]) -> KeyId {
+        let hash = Sha256::new()
+            .chain(KEY_ID_LABEL)
+            .chain([self.id()])
+            .chain(canonical)
+            .result();
+        let mut key_id = [0u8; KEY_ID_SIZE];
+        key_id.copy_from_slice(&hash[..KEY_ID_SIZE]);
+        key_id
+    }
+}
+
+/// A public key an envelope is sealed to
+#[derive(Clone, Debug)]
+pub struct Recipient {
+    key_type: KeyType,
+    public_key: Vec<u8>,
+    key_id: KeyId,
+}
+
+impl Recipient {
+    /// Check `public_key` and compute its key id
+    pub fn new(key_type: KeyType, public_key: &PublicKey) -> Result<Self, CryptoError> {
+        let (public_key, key_id) = key_type.canonical(&public_key[..])?;
+        Ok(Recipient {
+            key_type,
+            public_key,
+            key_id,
+        })
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    pub fn key_id(&self) -> &KeyId {
+        &self.key_id
+    }
+
+    fn wrap(&self, content_key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let aad = stanza_aad(self.key_type, &self.key_id);
+        match self.key_type {
+            KeyType::X25519 => {
+                let (mut wrapped, ciphertext) = hpke::seal_base(
+                    AeadId::ChaCha20Poly1305,
+                    &PublicKey(self.public_key.clone()),
+                    WRAP_INFO,
+                    &aad,
+                    content_key,
+                )?;
+                wrapped.extend_from_slice(&ciphertext);
+                Ok(wrapped)
+            }
+            KeyType::P256 | KeyType::Committee => {
+                let ephemeral = p256_random_scalar();
+                let mut wrapped = p256_encode(&p256_public_key(&ephemeral));
+                // A committee is wrapped to its group key, bound to all of its members
+                let point = if self.key_type == KeyType::Committee {
+                    Committee::from_bytes(&self.public_key)?.public_key
+                } else {
+                    p256_point(&self.public_key)?
+                };
+                let mut kek = p256_kek(&ephemeral, &point, &wrapped, &self.public_key)?;
+                let ciphertext = AeadId::ChaCha20Poly1305.seal(&kek, &NONCE, &aad, content_key);
+                kek.zeroize();
+                wrapped.extend_from_slice(&ciphertext.map_err(|_| {
+                    CryptoError::GeneralError("Failed to wrap the content key".to_string())
+                })?);
+                Ok(wrapped)
+            }
+            KeyType::Rsa => {
+                let pkey = PKey::from_rsa(rsa_public_key(&self.public_key)?)?;
+                let mut encrypter = Encrypter::new(&pkey)?;
+                encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+                encrypter.set_rsa_oaep_md(MessageDigest::sha256())?;
+                encrypter.set_rsa_mgf1_md(MessageDigest::sha256())?;
+                let mut wrapped = vec![0u8; encrypter.encrypt_len(content_key)?];
+                let length = encrypter.encrypt(content_key, &mut wrapped)?;
+                wrapped.truncate(length);
+                Ok(wrapped)
+            }
+        }
+    }
+}
+
+/// A private key to open envelopes with
+pub struct Identity {
+    key_type: KeyType,
+    private_key: PrivateKey,
+    key_id: KeyId,
+}
+
+impl Identity {
+    /// Check `private_key` and compute the key id of its public key
+    pub fn new(key_type: KeyType, private_key: &PrivateKey) -> Result<Self, CryptoError> {
+        let public_key = match key_type {
+            KeyType::X25519 => {
+                if private_key.len() != X25519_KEY_SIZE {
+                    return Err(CryptoError::ParseError(
+                        "Private key has the wrong length".to_string(),
+                    ));
+                }
+                let secret = StaticSecret::from(*array_ref!(&private_key[..], 0, X25519_KEY_SIZE));
+                X25519PublicKey::from(&secret).as_bytes().to_vec()
+            }
+            KeyType::P256 => p256_encode(&p256_public_key(&p256_scalar(&private_key[..])?)),
+            KeyType::Rsa => {
+                let rsa = rsa_private_key(&private_key[..])?;
+                Rsa::from_public_components(rsa.n().to_owned()?, rsa.e().to_owned()?)?
+                    .public_key_to_der()?
+            }
+            KeyType::Committee => {
+                return Err(CryptoError::GeneralError(
+                    "A committee has no single private key, use its members".to_string(),
+                ))
+            }
+        };
+        Ok(Identity {
+            key_type,
+            private_key: PrivateKey(private_key[..].to_vec()),
+            key_id: key_type.canonical(&public_key)?.1,
+        })
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    pub fn key_id(&self) -> &KeyId {
+        &self.key_id
+    }
+
+    /// Open an envelope sealed to this key with the same `aad`
+    pub fn open(&self, aad: &[u8], envelope: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let (stanzas, header_size) = parse(envelope)?;
+        let mut found = false;
+        for stanza in stanzas
+            .iter()
+            .filter(|s| s.key_type == self.key_type && s.key_id == self.key_id)
+        {
+            found = true;
+            // Another key with a colliding id fails to unwrap, so keep looking
+            if let Ok(content_key) = self.unwrap(stanza.wrapped) {
+                return open_content(aad, envelope, header_size, content_key);
+            }
+        }
+        Err(CryptoError::GeneralError(
+            if found {
+                "Unable to unwrap the content key"
+            } else {
+                "The envelope isn't sealed to this key"
+            }
+            .to_string(),
+        ))
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let aad = stanza_aad(self.key_type, &self.key_id);
+        let content_key = match self.key_type {
+            KeyType::X25519 => {
+                if wrapped.len() < hpke::ENC_SIZE {
+                    return Err(CryptoError::ParseError(
+                        "Wrapped key is too short".to_string(),
+                    ));
+                }
+                let (enc, ciphertext) = wrapped.split_at(hpke::ENC_SIZE);
+                hpke::open_base(
+                    AeadId::ChaCha20Poly1305,
+                    enc,
+                    &self.private_key,
+                    WRAP_INFO,
+                    &aad,
+                    ciphertext,
+                )?
+            }
+            KeyType::P256 => {
+                if wrapped.len() < P256_POINT_SIZE {
+                    return Err(CryptoError::ParseError(
+                        "Wrapped key is too short".to_string(),
+                    ));
+                }
+                let (ephemeral, ciphertext) = wrapped.split_at(P256_POINT_SIZE);
+                let secret = p256_scalar(&self.private_key[..])?;
+                let public_key = p256_encode(&p256_public_key(&secret));
+                let kek = p256_kek(&secret, &p256_point(ephemeral)?, ephemeral, &public_key)?;
+                unwrap_with_kek(kek, &aad, ciphertext)?
+            }
+            KeyType::Rsa => {
+                let pkey = PKey::from_rsa(rsa_private_key(&self.private_key[..])?)?;
+                let mut decrypter = Decrypter::new(&pkey)?;
+                decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+                decrypter.set_rsa_oaep_md(MessageDigest::sha256())?;
+                decrypter.set_rsa_mgf1_md(MessageDigest::sha256())?;
+                let mut content_key = vec![0u8; decrypter.decrypt_len(wrapped)?];
+                let length = decrypter.decrypt(wrapped, &mut content_key);
+                match length {
+                    Ok(length) => content_key.truncate(length),
+                    Err(e) => {
+                        content_key.zeroize();
+                        return Err(e.into());
+                    }
+                }
+                content_key
+            }
+            // `Identity::new` refuses committees
+            KeyType::Committee => unreachable!(),
+        };
+        check_content_key(content_key)
+    }
+}
+
+impl ::std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Identity")
+            .field("key_type", &self.key_type)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+/// A t-of-n committee of P-256 key shares that opens envelopes together.
+///
+/// The shares are points of a polynomial of degree t - 1 whose value at zero is the
+/// private key of the group key. The committee is public: its threshold, group key and
+/// the verification key of each member, which partial decryptions are checked against.
+#[derive(Clone)]
+pub struct Committee {
+    threshold: usize,
+    public_key: AffinePoint,
+    verification_keys: Vec<AffinePoint>,
+}
+
+impl Committee {
+    /// Deal a new committee of `members`, any `threshold` of which can open envelopes
+    /// sealed to it. The dealer sees the whole private key, so it should run where
+    /// the shares can be handed out and then forgotten. Share `i` belongs to member
+    /// `i + 1`
+    pub fn generate(
+        threshold: usize,
+        members: usize,
+    ) -> Result<(Self, Vec<PrivateKey>), CryptoError> {
+        check_committee_size(threshold, members)?;
+        let coefficients = (0..threshold)
+            .map(|_| p256_random_scalar())
+            .collect::<Vec<_>>();
+        let shares = (1..=members as u64)
+            .map(|x| {
+                let x = Scalar::from(x);
+                coefficients
+                    .iter()
+                    .rev()
+                    .fold(Scalar::zero(), |acc, c| acc * x + *c)
+            })
+            .collect::<Vec<_>>();
+        let committee = Committee {
+            threshold,
+            public_key: p256_public_key(&coefficients[0]),
+            verification_keys: shares.iter().map(p256_public_key).collect(),
+        };
+        Ok((
+            committee,
+            shares
+                .iter()
+                .map(|s| PrivateKey(s.to_repr().to_vec()))
+                .collect(),
+        ))
+    }
+
+    /// The number of partial decryptions needed to open an envelope
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of members, indexed from 1
+    pub fn members(&self) -> usize {
+        self.verification_keys.len()
+    }
+
+    /// The public key to seal envelopes to, with `KeyType::Committee`
+    pub fn to_public_key(&self) -> PublicKey {
+        PublicKey(self.to_bytes())
+    }
+
+    /// Parse a committee from its `to_public_key` form
+    pub fn from_public_key(public_key: &PublicKey) -> Result<Self, CryptoError> {
+        Self::from_bytes(&public_key[..])
+    }
+
+    /// The id of the committee's public key in envelope stanzas
+    pub fn key_id(&self) -> KeyId {
+        KeyType::Committee.canonical_key_id(&self.to_bytes())
+    }
+
+    /// Open an envelope sealed to this committee with the same `aad`, from the partial
+    /// decryptions of at least `threshold` members. Partial decryptions with a wrong
+    /// proof are skipped, and named in the error if too few valid ones remain
+    pub fn open(
+        &self,
+        aad: &[u8],
+        envelope: &[u8],
+        partials: &[PartialDecryption],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let key_id = self.key_id();
+        let (stanzas, header_size) = parse(envelope)?;
+        let (ephemeral, ciphertext) = committee_stanza(&stanzas, &key_id)?;
+        let point = p256_point(ephemeral)?;
+
+        let mut indices = Vec::with_capacity(self.threshold);
+        let mut shares = Vec::with_capacity(self.threshold);
+        let mut invalid = Vec::new();
+        for partial in partials {
+            if indices.len() == self.threshold {
+                break;
+            }
+            if indices.contains(&partial.index) {
+                continue;
+            }
+            if !self.verify(&key_id, &point, partial) {
+                if !invalid.contains(&partial.index) {
+                    invalid.push(partial.index);
+                }
+                continue;
+            }
+            indices.push(partial.index);
+            shares.push(partial.share);
+        }
+        if indices.len() < self.threshold && !invalid.is_empty() {
+            let members = invalid
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(CryptoError::GeneralError(format!(
+                "Opening needs partial decryptions from {} members, found {}, invalid from members {}",
+                self.threshold,
+                indices.len(),
+                members
+            )));
+        }
+        if indices.len() < self.threshold {
+            return Err(CryptoError::GeneralError(format!(
+                "Opening needs partial decryptions from {} members, found {}",
+                self.threshold,
+                indices.len()
+            )));
+        }
+
+        let shared = lagrange_combine(&indices, &shares, 0);
+        let kek = p256_shared_kek(&shared, ephemeral, &self.to_bytes())?;
+        let aad_stanza = stanza_aad(KeyType::Committee, &key_id);
+        let content_key = check_content_key(unwrap_with_kek(kek, &aad_stanza, ciphertext)?)?;
+        open_content(aad, envelope, header_size, content_key)
+    }
+
+    /// Check the proof that `partial` is the share of its member times `point`
+    fn verify(&self, key_id: &KeyId, point: &AffinePoint, partial: &PartialDecryption) -> bool {
+        let index = partial.index as usize;
+        if index == 0 || index > self.members() {
+            return false;
+        }
+        let verification_key = ProjectivePoint::from(self.verification_keys[index - 1]);
+        let commitment_g = (ProjectivePoint::generator() * partial.response
+            - verification_key * partial.challenge)
+            .to_affine();
+        let commitment_r = (ProjectivePoint::from(*point) * partial.response
+            - ProjectivePoint::from(partial.share) * partial.challenge)
+            .to_affine();
+        let challenge = proof_challenge(
+            key_id,
+            partial.index,
+            &self.verification_keys[index - 1],
+            point,
+            &partial.share,
+            &commitment_g,
+            &commitment_r,
+        );
+        challenge == partial.challenge
+    }
+
+    /// threshold (1) | members (1) | group key | verification keys, all uncompressed
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.threshold as u8, self.members() as u8];
+        bytes.extend_from_slice(&p256_encode(&self.public_key));
+        for key in &self.verification_keys {
+            bytes.extend_from_slice(&p256_encode(key));
+        }
+        bytes
+    }
+
+    /// Parse a committee, refusing one whose verification keys aren't all on one
+    /// polynomial of degree t - 1 through the group key
+    fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = |m: &str| CryptoError::ParseError(format!("Invalid committee: {}", m));
+        if bytes.len() < COMMITTEE_HEADER_SIZE {
+            return Err(invalid("too short"));
+        }
+        let threshold = bytes[0] as usize;
+        let members = bytes[1] as usize;
+        check_committee_size(threshold, members).map_err(|_| invalid("wrong size"))?;
+        if bytes.len() != COMMITTEE_HEADER_SIZE + members * P256_POINT_SIZE {
+            return Err(invalid("wrong length"));
+        }
+        let public_key = p256_point(&bytes[2..COMMITTEE_HEADER_SIZE])?;
+        let verification_keys = bytes[COMMITTEE_HEADER_SIZE..]
+            .chunks(P256_POINT_SIZE)
+            .map(p256_point)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let indices = (1..=threshold as u8).collect::<Vec<_>>();
+        let points = &verification_keys[..threshold];
+        let consistent = p256_encode(&lagrange_combine(&indices, points, 0))
+            == p256_encode(&public_key)
+            && (threshold + 1..=members).all(|x| {
+                p256_encode(&lagrange_combine(&indices, points, x as u8))
+                    == p256_encode(&verification_keys[x - 1])
+            });
+        if !consistent {
+            return Err(invalid("the verification keys don't match the group key"));
+        }
+        Ok(Committee {
+            threshold,
+            public_key,
+            verification_keys,
+        })
+    }
+}
+
+impl ::std::fmt::Debug for Committee {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Committee")
+            .field("threshold", &self.threshold)
+            .field("members", &self.members())
+            .field("key_id", &self.key_id())
+            .finish()
+    }
+}
+
+/// A member of a committee with its share of the private key
+pub struct CommitteeMember {
+    committee: Committee,
+    key_id: KeyId,
+    index: u8,
+    share: PrivateKey,
+}
+
+impl CommitteeMember {
+    /// Check that `share` is the share of member `index` of `committee`
+    pub fn new(
+        committee: &Committee,
+        index: usize,
+        share: &PrivateKey,
+    ) -> Result<Self, CryptoError> {
+        if index == 0 || index > committee.members() {
+            return Err(CryptoError::GeneralError(format!(
+                "The committee has no member {}",
+                index
+            )));
+        }
+        let secret = p256_scalar(&share[..])?;
+        if p256_encode(&p256_public_key(&secret))
+            != p256_encode(&committee.verification_keys[index - 1])
+        {
+            return Err(CryptoError::GeneralError(format!(
+                "Not the share of member {}",
+                index
+            )));
+        }
+        Ok(CommitteeMember {
+            committee: committee.clone(),
+            key_id: committee.key_id(),
+            index: index as u8,
+            share: PrivateKey(share[..].to_vec()),
+        })
+    }
+
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// This member's part of opening an envelope sealed to its committee, with a
+    /// proof that it used its share. It reveals nothing about the content on its own
+    pub fn partial_decrypt(&self, envelope: &[u8]) -> Result<PartialDecryption, CryptoError> {
+        let (stanzas, _) = parse(envelope)?;
+        let (ephemeral, _) = committee_stanza(&stanzas, &self.key_id)?;
+        let point = p256_point(ephemeral)?;
+        let secret = p256_scalar(&self.share[..])?;
+        let share = (ProjectivePoint::from(point) * secret).to_affine();
+
+        // A Chaum-Pedersen proof that the share and the verification key have the same
+        // discrete log to the ephemeral key and the generator
+        let nonce = p256_random_scalar();
+        let challenge = proof_challenge(
+            &self.key_id,
+            self.index,
+            &self.committee.verification_keys[self.index as usize - 1],
+            &point,
+            &share,
+            &p256_public_key(&nonce),
+            &(ProjectivePoint::from(point) * nonce).to_affine(),
+        );
+        Ok(PartialDecryption {
+            index: self.index,
+            share,
+            challenge,
+            response: nonce + challenge * secret,
+        })
+    }
+}
+
+impl ::std::fmt::Debug for CommitteeMember {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("CommitteeMember")
+            .field("key_id", &self.key_id)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A committee member's share of opening one envelope, with the proof it's correct
+#[derive(Clone, Debug)]
+pub struct PartialDecryption {
+    index: u8,
+    share: AffinePoint,
+    challenge: Scalar,
+    response: Scalar,
+}
+
+impl PartialDecryption {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// index (1) | share (65) | challenge (32) | response (32)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.index];
+        bytes.extend_from_slice(&p256_encode(&self.share));
+        bytes.extend_from_slice(&self.challenge.to_repr());
+        bytes.extend_from_slice(&self.response.to_repr());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != PARTIAL_DECRYPTION_SIZE {
+            return Err(CryptoError::ParseError(
+                "Partial decryption has the wrong length".to_string(),
+            ));
+        }
+        let scalar = |bytes: &[u8]| {
+            let mut field = FieldBytes::default();
+            field.copy_from_slice(bytes);
+            Scalar::from_repr(field)
+                .ok_or_else(|| CryptoError::ParseError("Invalid partial decryption".to_string()))
+        };
+        let offset = 1 + P256_POINT_SIZE;
+        Ok(PartialDecryption {
+            index: bytes[0],
+            share: p256_point(&bytes[1..offset])?,
+            challenge: scalar(&bytes[offset..offset + P256_SCALAR_SIZE])?,
+            response: scalar(&bytes[offset + P256_SCALAR_SIZE..])?,
+        })
+    }
+}
+
+/// Encrypt `plaintext` once to every recipient, binding it to `aad`, which must be
+/// given again to open it
+pub fn seal(
+    recipients: &[Recipient],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if recipients.is_empty() || recipients.len() > u16::MAX as usize {
+        return Err(CryptoError::GeneralError(format!(
+            "An envelope has between 1 and {} recipients",
+            u16::MAX
+        )));
+    }
+    let mut content_key = vec![0u8; CONTENT_KEY_SIZE];
+    OsRng.fill_bytes(&mut content_key);
+    let mut envelope = vec![VERSION];
+    envelope.extend_from_slice(&(recipients.len() as u16).to_be_bytes());
+    for recipient in recipients {
+        let wrapped = match recipient.wrap(&content_key) {
+            Ok(wrapped) => wrapped,
+            Err(e) => {
+                content_key.zeroize();
+                return Err(e);
+            }
+        };
+        envelope.push(recipient.key_type.id());
+        envelope.extend_from_slice(&recipient.key_id);
+        envelope.extend_from_slice(&(wrapped.len() as u16).to_be_bytes());
+        envelope.extend_from_slice(&wrapped);
+    }
+    let mut content_aad = envelope.clone();
+    content_aad.extend_from_slice(aad);
+    let ciphertext = content_cipher(&content_key).encrypt(
+        GenericArray::from_slice(&NONCE),
+        Payload {
+            msg: plaintext,
+            aad: &content_aad,
+        },
+    );
+    content_key.zeroize();
+    envelope.extend_from_slice(
+        &ciphertext
+            .map_err(|_| CryptoError::GeneralError("Failed to seal the envelope".to_string()))?,
+    );
+    Ok(envelope)
+}
+
+/// The key type and key id of every recipient of an envelope, in order
+pub fn recipients(envelope: &[u8]) -> Result<Vec<(KeyType, KeyId)>, CryptoError> {
+    Ok(parse(envelope)?
+        .0
+        .iter()
+        .map(|s| (s.key_type, s.key_id))
+        .collect())
+}
+
+struct Stanza<'a> {
+    key_type: KeyType,
+    key_id: KeyId,
+    wrapped: &'a [u8],
+}
+
+/// The stanzas of an envelope and the length of its header
+fn parse<'a>(envelope: &'a [u8]) -> Result<(Vec<Stanza<'a>>, usize), CryptoError> {
+    let truncated = || CryptoError::ParseError("Envelope is truncated".to_string());
+    if envelope.len() < HEADER_SIZE {
+        return Err(truncated());
+    }
+    if envelope[0] != VERSION {
+        return Err(CryptoError::ParseError(format!(
+            "Unsupported envelope version {}",
+            envelope[0]
+        )));
+    }
+    let count = u16::from_be_bytes([envelope[1], envelope[2]]) as usize;
+    if count == 0 {
+        return Err(CryptoError::ParseError(
+            "Envelope has no recipients".to_string(),
+        ));
+    }
+    let mut stanzas = Vec::with_capacity(count);
+    let mut offset = HEADER_SIZE;
+    for _ in 0..count {
+        if envelope.len() < offset + STANZA_HEADER_SIZE {
+            return Err(truncated());
+        }
+        let key_type = KeyType::from_id(envelope[offset])?;
+        let mut key_id = [0u8; KEY_ID_SIZE];
+        key_id.copy_from_slice(&envelope[offset + 1..offset + 1 + KEY_ID_SIZE]);
+        let length = u16::from_be_bytes([
+            envelope[offset + 1 + KEY_ID_SIZE],
+            envelope[offset + 2 + KEY_ID_SIZE],
+        ]) as usize;
+        offset += STANZA_HEADER_SIZE;
+        if envelope.len() < offset + length {
+            return Err(truncated());
+        }
+        stanzas.push(Stanza {
+            key_type,
+            key_id,
+            wrapped: &envelope[offset..offset + length],
+        });
+        offset += length;
+    }
+    if envelope.len() < offset + COMMITMENT_SIZE + hpke::TAG_SIZE {
+        return Err(truncated());
+    }
+    Ok((stanzas, offset))
+}
+
+/// The cipher of the content, which commits to the content key
+fn content_cipher(content_key: &[u8]) -> KeyCommitting<ChaCha20Poly1305> {
+    KeyCommitting::new(GenericArray::from_slice(content_key))
+}
+
+/// Decrypt the content of an envelope with its unwrapped content key
+fn open_content(
+    aad: &[u8],
+    envelope: &[u8],
+    header_size: usize,
+    mut content_key: Vec<u8>,
+) -> Result<Vec<u8>, CryptoError> {
+    let mut content_aad = envelope[..header_size].to_vec();
+    content_aad.extend_from_slice(aad);
+    let plaintext = content_cipher(&content_key).decrypt(
+        GenericArray::from_slice(&NONCE),
+        Payload {
+            msg: &envelope[header_size..],
+            aad: &content_aad,
+        },
+    );
+    content_key.zeroize();
+    plaintext.map_err(|_| CryptoError::GeneralError("Unable to decrypt the envelope".to_string()))
+}
+
+fn unwrap_with_kek(
+    mut kek: Vec<u8>,
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let content_key = AeadId::ChaCha20Poly1305.open(&kek, &NONCE, aad, ciphertext);
+    kek.zeroize();
+    content_key
+        .map_err(|_| CryptoError::GeneralError("Unable to unwrap the content key".to_string()))
+}
+
+fn check_content_key(mut content_key: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+    if content_key.len() != CONTENT_KEY_SIZE {
+        content_key.zeroize();
+        return Err(CryptoError::GeneralError(
+            "Content key has the wrong length".to_string(),
+        ));
+    }
+    Ok(content_key)
+}
+
+/// The ephemeral key and wrapped content key of the stanza for committee `key_id`
+fn committee_stanza<'a>(
+    stanzas: &[Stanza<'a>],
+    key_id: &KeyId,
+) -> Result<(&'a [u8], &'a [u8]), CryptoError> {
+    let stanza = stanzas
+        .iter()
+        .find(|s| s.key_type == KeyType::Committee && &s.key_id == key_id)
+        .ok_or_else(|| {
+            CryptoError::GeneralError("The envelope isn't sealed to this committee".to_string())
+        })?;
+    if stanza.wrapped.len() < P256_POINT_SIZE {
+        return Err(CryptoError::ParseError(
+            "Wrapped key is too short".to_string(),
+        ));
+    }
+    Ok(stanza.wrapped.split_at(P256_POINT_SIZE))
+}
+
+fn check_committee_size(threshold: usize, members: usize) -> Result<(), CryptoError> {
+    if threshold == 0 || threshold > members || members > MAX_COMMITTEE_MEMBERS {
+        return Err(CryptoError::GeneralError(format!(
+            "A committee has a threshold between 1 and its size, at most {} members",
+            MAX_COMMITTEE_MEMBERS
+        )));
+    }
+    Ok(())
+}
+
+/// The value at `x` of the polynomial through `points` at `indices`, in the exponent
+fn lagrange_combine(indices: &[u8], points: &[AffinePoint], x: u8) -> AffinePoint {
+    let x = Scalar::from(u64::from(x));
+    let mut sum = ProjectivePoint::identity();
+    for (i, point) in indices.iter().zip(points) {
+        let xi = Scalar::from(u64::from(*i));
+        let mut numerator = Scalar::one();
+        let mut denominator = Scalar::one();
+        for j in indices.iter().filter(|j| *j != i) {
+            let xj = Scalar::from(u64::from(*j));
+            numerator *= x - xj;
+            denominator *= xi - xj;
+        }
+        // The indices are distinct, so the denominator isn't zero
+        sum += ProjectivePoint::from(*point) * (numerator * denominator.invert().unwrap());
+    }
+    sum.to_affine()
+}
+
+fn proof_challenge(
+    key_id: &KeyId,
+    index: u8,
+    verification_key: &AffinePoint,
+    point: &AffinePoint,
+    share: &AffinePoint,
+    commitment_g: &AffinePoint,
+    commitment_r: &AffinePoint,
+) -> Scalar {
+    let mut hash = Sha256::new()
+        .chain(PROOF_LABEL)
+        .chain(key_id)
+        .chain([index]);
+    for p in &[verification_key, point, share, commitment_g, commitment_r] {
+        hash = hash.chain(p256_encode(p));
+    }
+    let mut field = FieldBytes::default();
+    field.copy_from_slice(&hash.result());
+    Scalar::from_bytes_reduced(&field)
+}
+
+fn stanza_aad(key_type: KeyType, key_id: &KeyId) -> Vec<u8> {
+    let mut aad = vec![key_type.id()];
+    aad.extend_from_slice(key_id);
+    aad
+}
+
+fn p256_random_scalar() -> Scalar {
+    let mut field = FieldBytes::default();
+    loop {
+        OsRng.fill_bytes(&mut field);
+        let scalar = Scalar::from_repr(field).filter(|s| !bool::from(s.is_zero()));
+        if let Some(scalar) = scalar {
+            field.as_mut_slice().zeroize();
+            return scalar;
+        }
+    }
+}
+
+fn p256_scalar(bytes: &[u8]) -> Result<Scalar, CryptoError> {
+    let invalid = || CryptoError::ParseError("Invalid P-256 private key".to_string());
+    if bytes.len() != P256_SCALAR_SIZE {
+        return Err(invalid());
+    }
+    let mut field = FieldBytes::default();
+    field.copy_from_slice(bytes);
+    let scalar = Scalar::from_repr(field).filter(|s| !bool::from(s.is_zero()));
+    field.as_mut_slice().zeroize();
+    scalar.ok_or_else(invalid)
+}
+
+fn p256_point(bytes: &[u8]) -> Result<AffinePoint, CryptoError> {
+    EncodedPoint::from_bytes(bytes)
+        .ok()
+        .and_then(|point| Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&point)))
+        .ok_or_else(|| CryptoError::ParseError("Invalid P-256 public key".to_string()))
+}
+
+fn p256_public_key(secret: &Scalar) -> AffinePoint {
+    (ProjectivePoint::generator() * *secret).to_affine()
+}
+
+fn p256_encode(point: &AffinePoint) -> Vec<u8> {
+    point.to_encoded_point(false).as_bytes().to_vec()
+}
+
+/// The key wrapping key of an ECDH between `secret` and `point`, salted with the
+/// ephemeral public key and the recipient's
+fn p256_kek(
+    secret: &Scalar,
+    point: &AffinePoint,
+    ephemeral: &[u8],
+    recipient: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    p256_shared_kek(
+        &(ProjectivePoint::from(*point) * *secret).to_affine(),
+        ephemeral,
+        recipient,
+    )
+}
+
+/// The key wrapping key of the shared point of an ECDH, or of a committee's partial
+/// decryptions combined
+fn p256_shared_kek(
+    shared: &AffinePoint,
+    ephemeral: &[u8],
+    recipient: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let mut salt = ephemeral.to_vec();
+    salt.extend_from_slice(recipient);
+    let mut z = shared.to_encoded_point(false).as_bytes()[1..1 + P256_SCALAR_SIZE].to_vec();
+    let mut kek = vec![0u8; CONTENT_KEY_SIZE];
+    let result = Hkdf::<Sha256>::new(Some(&salt), &z).expand(WRAP_INFO, &mut kek);
+    z.zeroize();
+    result.map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    Ok(kek)
+}
+
+fn rsa_public_key(der: &[u8]) -> Result<Rsa<Public>, CryptoError> {
+    let rsa = Rsa::public_key_from_der(der)
+        .map_err(|_| CryptoError::ParseError("Invalid RSA public key".to_string()))?;
+    check_rsa_size(rsa.size() * 8)?;
+    Ok(rsa)
+}
+
+fn rsa_private_key(der: &[u8]) -> Result<Rsa<Private>, CryptoError> {
+    let rsa = Rsa::private_key_from_der(der)
+        .map_err(|_| CryptoError::ParseError("Invalid RSA private key".to_string()))?;
+    check_rsa_size(rsa.size() * 8)?;
+    Ok(rsa)
+}
+
+fn check_rsa_size(bits: u32) -> Result<(), CryptoError> {
+    if bits < MIN_RSA_BITS {
+        return Err(CryptoError::ParseError(format!(
+            "RSA keys must have at least {} bits",
+            MIN_RSA_BITS
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn identity(key_type: KeyType) -> (Recipient, Identity) {
+        let (public_key, private_key) = key_type.generate_key_pair().unwrap();
+        (
+            Recipient::new(key_type, &public_key).unwrap(),
+            Identity::new(key_type, &private_key).unwrap(),
+        )
+    }
+
+    fn team() -> Vec<(Recipient, Identity)> {
+        vec![
+            identity(KeyType::X25519),
+            identity(KeyType::P256),
+            identity(KeyType::Rsa),
+        ]
+    }
+
+    #[test]
+    fn seal_to_many() {
+        let team = team();
+        let keys = team.iter().map(|(r, _)| r.clone()).collect::<Vec<_>>();
+        let envelope = seal(&keys, b"header", b"for the team").unwrap();
+        for (recipient, identity) in &team {
+            assert_eq!(recipient.key_id(), identity.key_id());
+            assert_eq!(
+                identity.open(b"header", &envelope).unwrap(),
+                b"for the team".to_vec()
+            );
+            assert!(identity.open(b"other header", &envelope).is_err());
+        }
+        assert_eq!(
+            recipients(&envelope).unwrap(),
+            team.iter()
+                .map(|(r, _)| (r.key_type(), *r.key_id()))
+                .collect::<Vec<_>>()
+        );
+
+        let (_, outsider) = identity(KeyType::X25519);
+        match outsider.open(b"header", &envelope) {
+            Err(CryptoError::GeneralError(m)) => {
+                assert_eq!(m, "The envelope isn't sealed to this key")
+            }
+            _ => panic!("Opened an envelope sealed to others"),
+        }
+        assert!(seal(&[], b"", b"").is_err());
+        let empty = seal(&keys[..1], b"", b"").unwrap();
+        assert_eq!(team[0].1.open(b"", &empty).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let team = team();
+        let keys = team.iter().map(|(r, _)| r.clone()).collect::<Vec<_>>();
+        let envelope = seal(&keys, b"", b"secret").unwrap();
+        for i in 0..envelope.len() {
+            let mut tampered = envelope.clone();
+            tampered[i] ^= 1;
+            for (_, identity) in &team {
+                assert!(identity.open(b"", &tampered).is_err());
+            }
+        }
+        for length in 0..envelope.len() {
+            assert!(team[0].1.open(b"", &envelope[..length]).is_err());
+        }
+
+        // Dropping another recipient's stanza changes the authenticated header
+        let (stanzas, header_size) = parse(&envelope).unwrap();
+        let mut stripped = vec![VERSION, 0, 1];
+        stripped.push(stanzas[0].key_type.id());
+        stripped.extend_from_slice(&stanzas[0].key_id);
+        stripped.extend_from_slice(&(stanzas[0].wrapped.len() as u16).to_be_bytes());
+        stripped.extend_from_slice(stanzas[0].wrapped);
+        stripped.extend_from_slice(&envelope[header_size..]);
+        assert_eq!(recipients(&stripped).unwrap().len(), 1);
+        assert!(team[0].1.open(b"", &stripped).is_err());
+
+        // Envelopes without key commitment are refused
+        let mut old = envelope.clone();
+        old[0] = 1;
+        assert!(recipients(&old).is_err());
+        assert!(team[0].1.open(b"", &old).is_err());
+    }
+
+    #[test]
+    fn key_ids() {
+        let (public_key, private_key) = KeyType::P256.generate_key_pair().unwrap();
+        let point = p256_point(&public_key[..]).unwrap();
+        let compressed = PublicKey(point.to_encoded_point(true).as_bytes().to_vec());
+        let recipient = Recipient::new(KeyType::P256, &compressed).unwrap();
+        assert_eq!(
+            recipient.key_id(),
+            &KeyType::P256.key_id(&public_key).unwrap()
+        );
+        let identity = Identity::new(KeyType::P256, &private_key).unwrap();
+        let envelope = seal(&[recipient], b"", b"compressed").unwrap();
+        assert_eq!(
+            identity.open(b"", &envelope).unwrap(),
+            b"compressed".to_vec()
+        );
+
+        // The same bytes under another type are another key
+        let (x25519, _) = KeyType::X25519.generate_key_pair().unwrap();
+        let mut other = [0u8; 33];
+        other[0] = 2;
+        other[1..].copy_from_slice(&x25519[..]);
+        if let Ok(id) = KeyType::P256.key_id(&PublicKey(other.to_vec())) {
+            assert_ne!(id, KeyType::X25519.key_id(&x25519).unwrap());
+        }
+
+        assert!(Recipient::new(KeyType::X25519, &PublicKey(vec![0u8; 31])).is_err());
+        assert!(Recipient::new(KeyType::P256, &PublicKey(vec![4u8; 65])).is_err());
+        assert!(Recipient::new(KeyType::Rsa, &PublicKey(vec![0u8; 64])).is_err());
+        assert!(Identity::new(KeyType::P256, &PrivateKey(vec![0u8; 32])).is_err());
+        let small = Rsa::generate(1024).unwrap();
+        assert!(
+            Recipient::new(KeyType::Rsa, &PublicKey(small.public_key_to_der().unwrap())).is_err()
+        );
+        assert!(Identity::new(
+            KeyType::Rsa,
+            &PrivateKey(small.private_key_to_der().unwrap())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn committee() {
+        let (committee, shares) = Committee::generate(2, 3).unwrap();
+        assert_eq!((committee.threshold(), committee.members()), (2, 3));
+        let members = shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| CommitteeMember::new(&committee, i + 1, share).unwrap())
+            .collect::<Vec<_>>();
+        let public_key = committee.to_public_key();
+        assert_eq!(
+            Committee::from_public_key(&public_key).unwrap().key_id(),
+            committee.key_id()
+        );
+        let quorum = Recipient::new(KeyType::Committee, &public_key).unwrap();
+        assert_eq!(quorum.key_id(), &committee.key_id());
+
+        // Either the custodian alone or any two members open the same envelope
+        let (custodian, custodian_identity) = identity(KeyType::X25519);
+        let envelope = seal(&[custodian, quorum.clone()], b"aad", b"shared").unwrap();
+        assert_eq!(
+            custodian_identity.open(b"aad", &envelope).unwrap(),
+            b"shared".to_vec()
+        );
+        for pair in &[[0, 1], [1, 2], [2, 0]] {
+            let partials = pair
+                .iter()
+                .map(|i| members[*i].partial_decrypt(&envelope).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                committee.open(b"aad", &envelope, &partials).unwrap(),
+                b"shared".to_vec()
+            );
+            assert!(committee.open(b"other", &envelope, &partials).is_err());
+        }
+
+        // The same member twice isn't a quorum
+        let partial = members[0].partial_decrypt(&envelope).unwrap();
+        let parsed = PartialDecryption::from_bytes(&partial.to_bytes()).unwrap();
+        assert_eq!(parsed.index(), 1);
+        match committee.open(b"aad", &envelope, &[partial.clone(), parsed.clone()]) {
+            Err(CryptoError::GeneralError(m)) => assert_eq!(
+                m,
+                "Opening needs partial decryptions from 2 members, found 1"
+            ),
+            _ => panic!("Opened with one member"),
+        }
+
+        // A partial decryption of another envelope fails its proof and is skipped
+        let other = seal(&[quorum], b"aad", b"other").unwrap();
+        let wrong = members[1].partial_decrypt(&other).unwrap();
+        match committee.open(b"aad", &envelope, &[parsed.clone(), wrong.clone()]) {
+            Err(CryptoError::GeneralError(m)) => assert_eq!(
+                m,
+                "Opening needs partial decryptions from 2 members, found 1, invalid from members 2"
+            ),
+            _ => panic!("Accepted a wrong partial decryption"),
+        }
+        let third = members[2].partial_decrypt(&envelope).unwrap();
+        assert_eq!(
+            committee
+                .open(b"aad", &envelope, &[wrong, parsed, third])
+                .unwrap(),
+            b"shared".to_vec()
+        );
+        let (stranger, _) = Committee::generate(2, 3).unwrap();
+        assert!(stranger.open(b"aad", &envelope, &[partial]).is_err());
+        let (individual, _) = identity(KeyType::X25519);
+        let unrelated = seal(&[individual], b"", b"").unwrap();
+        assert!(members[0].partial_decrypt(&unrelated).is_err());
+
+        // Shares are checked against the verification keys
+        assert!(CommitteeMember::new(&committee, 1, &shares[1]).is_err());
+        assert!(CommitteeMember::new(&committee, 4, &shares[0]).is_err());
+        assert!(Identity::new(KeyType::Committee, &shares[0]).is_err());
+        assert!(KeyType::Committee.generate_key_pair().is_err());
+        assert!(Committee::generate(0, 3).is_err());
+        assert!(Committee::generate(4, 3).is_err());
+
+        // Verification keys that don't lie on one polynomial are refused
+        let mut swapped = public_key[..].to_vec();
+        let first = COMMITTEE_HEADER_SIZE..COMMITTEE_HEADER_SIZE + P256_POINT_SIZE;
+        let key = swapped[first.clone()].to_vec();
+        swapped.copy_within(first.end..first.end + P256_POINT_SIZE, first.start);
+        swapped[first.end..first.end + P256_POINT_SIZE].copy_from_slice(&key);
+        assert!(Committee::from_public_key(&PublicKey(swapped)).is_err());
+        assert!(Recipient::new(KeyType::Committee, &PublicKey(public_key[1..].to_vec())).is_err());
+    }
+}
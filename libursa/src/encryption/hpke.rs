@@ -0,0 +1,588 @@
+//! Hybrid public key encryption (RFC 9180) in base mode.
+//!
+//! HPKE encrypts to an X25519 public key by encapsulating a fresh shared secret with
+//! DHKEM(X25519, HKDF-SHA256) and deriving an AEAD key, nonce and exporter secret
+//! from it with HKDF-SHA256. The AEAD is one of AES-128-GCM, AES-256-GCM and
+//! ChaCha20-Poly1305, and the output is bit for bit that of other RFC 9180
+//! implementations with the same algorithms.
+//!
+//! `seal_base` and `open_base` encrypt a single message. `setup_base_sender` and
+//! `setup_base_receiver` return a `Context` for a sequence of messages, which must be
+//! opened in the order they were sealed, and for exporting secrets bound to the
+//! encapsulation. Only the base mode is provided, the PSK and authenticated modes
+//! aren't.
+
+use aead::Error;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use super::symm::prelude::*;
+use kex::x25519::{is_all_zero, validate_public_key};
+use keys::{PrivateKey, PublicKey};
+use CryptoError;
+
+/// DHKEM(X25519, HKDF-SHA256)
+pub const KEM_ID: u16 = 0x0020;
+/// HKDF-SHA256
+pub const KDF_ID: u16 = 0x0001;
+/// The length of an encapsulated key
+pub const ENC_SIZE: usize = 32;
+/// The length an AEAD adds to its plaintext
+pub const TAG_SIZE: usize = 16;
+
+const VERSION_LABEL: &[u8] = b"HPKE-v1";
+const KEY_SIZE: usize = 32;
+const HASH_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const MODE_BASE: u8 = 0x00;
+
+/// The AEAD a message is encrypted with, by its RFC 9180 identifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AeadId {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadId {
+    pub fn id(self) -> u16 {
+        match self {
+            AeadId::Aes128Gcm => 0x0001,
+            AeadId::Aes256Gcm => 0x0002,
+            AeadId::ChaCha20Poly1305 => 0x0003,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Result<Self, CryptoError> {
+        match id {
+            0x0001 => Ok(AeadId::Aes128Gcm),
+            0x0002 => Ok(AeadId::Aes256Gcm),
+            0x0003 => Ok(AeadId::ChaCha20Poly1305),
+            _ => Err(CryptoError::ParseError(format!(
+                "Unsupported HPKE AEAD {:#06x}",
+                id
+            ))),
+        }
+    }
+
+    pub fn key_size(self) -> usize {
+        match self {
+            AeadId::Aes128Gcm => 16,
+            AeadId::Aes256Gcm | AeadId::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    pub fn nonce_size(self) -> usize {
+        NONCE_SIZE
+    }
+
+    pub(crate) fn seal(
+        self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            AeadId::Aes128Gcm => {
+                SymmetricEncryptor::<Aes128Gcm>::new_with_key(key)?.encrypt(nonce, aad, plaintext)
+            }
+            AeadId::Aes256Gcm => {
+                SymmetricEncryptor::<Aes256Gcm>::new_with_key(key)?.encrypt(nonce, aad, plaintext)
+            }
+            AeadId::ChaCha20Poly1305 => SymmetricEncryptor::<ChaCha20Poly1305>::new_with_key(key)?
+                .encrypt(nonce, aad, plaintext),
+        }
+    }
+
+    pub(crate) fn open(
+        self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            AeadId::Aes128Gcm => {
+                SymmetricEncryptor::<Aes128Gcm>::new_with_key(key)?.decrypt(nonce, aad, ciphertext)
+            }
+            AeadId::Aes256Gcm => {
+                SymmetricEncryptor::<Aes256Gcm>::new_with_key(key)?.decrypt(nonce, aad, ciphertext)
+            }
+            AeadId::ChaCha20Poly1305 => SymmetricEncryptor::<ChaCha20Poly1305>::new_with_key(key)?
+                .decrypt(nonce, aad, ciphertext),
+        }
+    }
+}
+
+/// The keys of one encapsulation, for sealing or opening its messages in order and
+/// exporting secrets
+pub struct Context {
+    aead: AeadId,
+    key: Vec<u8>,
+    base_nonce: Vec<u8>,
+    exporter_secret: Vec<u8>,
+    sequence: u64,
+}
+
+impl Context {
+    /// Encrypt the next message
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let nonce = self.next_nonce()?;
+        let ciphertext = self
+            .aead
+            .seal(&self.key, &nonce, aad, plaintext)
+            .map_err(|_| CryptoError::GeneralError("Failed to seal the message".to_string()))?;
+        self.sequence += 1;
+        Ok(ciphertext)
+    }
+
+    /// Decrypt the next message
+    pub fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext.len() < TAG_SIZE {
+            return Err(CryptoError::ParseError(
+                "Ciphertext is too short".to_string(),
+            ));
+        }
+        let nonce = self.next_nonce()?;
+        let plaintext = self
+            .aead
+            .open(&self.key, &nonce, aad, ciphertext)
+            .map_err(|_| CryptoError::GeneralError("Failed to open the message".to_string()))?;
+        self.sequence += 1;
+        Ok(plaintext)
+    }
+
+    /// Derive a `length` byte secret for `exporter_context`. Both sides of an
+    /// encapsulation export the same secrets
+    pub fn export(&self, exporter_context: &[u8], length: usize) -> Result<Vec<u8>, CryptoError> {
+        labeled_expand(
+            &self.exporter_secret,
+            &hpke_suite_id(self.aead),
+            b"sec",
+            exporter_context,
+            length,
+        )
+    }
+
+    fn next_nonce(&self) -> Result<Vec<u8>, CryptoError> {
+        if self.sequence == u64::MAX {
+            return Err(CryptoError::GeneralError(
+                "The context has sealed its last message".to_string(),
+            ));
+        }
+        let mut nonce = self.base_nonce.clone();
+        for (n, s) in nonce[NONCE_SIZE - 8..]
+            .iter_mut()
+            .zip(self.sequence.to_be_bytes().iter())
+        {
+            *n ^= s;
+        }
+        Ok(nonce)
+    }
+}
+
+impl ::std::fmt::Debug for Context {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Context")
+            .field("aead", &self.aead)
+            .field("sequence", &self.sequence)
+            .finish()
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.base_nonce.zeroize();
+        self.exporter_secret.zeroize();
+    }
+}
+
+/// Generate an X25519 key pair to receive with
+pub fn generate_key_pair() -> Result<(PublicKey, PrivateKey), CryptoError> {
+    let mut ikm = vec![0u8; KEY_SIZE];
+    OsRng.fill_bytes(&mut ikm);
+    let keys = derive_key_pair(&ikm);
+    ikm.zeroize();
+    keys
+}
+
+/// Derive an X25519 key pair from at least 32 bytes of key material, as
+/// `DeriveKeyPair` of the KEM does
+pub fn derive_key_pair(ikm: &[u8]) -> Result<(PublicKey, PrivateKey), CryptoError> {
+    if ikm.len() < KEY_SIZE {
+        return Err(CryptoError::KeyGenError(
+            "Input key material is too short".to_string(),
+        ));
+    }
+    let suite_id = kem_suite_id();
+    let mut prk = labeled_extract(&[], &suite_id, b"dkp_prk", ikm);
+    let secret = labeled_expand(&prk, &suite_id, b"sk", &[], KEY_SIZE);
+    prk.zeroize();
+    // The private key is kept unclamped as RFC 9180 serializes it
+    let secret = secret?;
+    let pk = X25519PublicKey::from(&StaticSecret::from(*array_ref!(&secret[..], 0, KEY_SIZE)));
+    Ok((PublicKey(pk.as_bytes().to_vec()), PrivateKey(secret)))
+}
+
+/// Encapsulate a shared secret to `recipient`. Returns the encapsulated key to send
+/// with the messages and the context to seal them with
+pub fn setup_base_sender(
+    aead: AeadId,
+    recipient: &PublicKey,
+    info: &[u8],
+) -> Result<(Vec<u8>, Context), CryptoError> {
+    let (_, ephemeral) = generate_key_pair()?;
+    setup_sender_with_ephemeral(aead, recipient, info, &ephemeral)
+}
+
+/// Decapsulate the shared secret of `enc` with the private key it was encapsulated to,
+/// returning the context to open the messages with
+pub fn setup_base_receiver(
+    aead: AeadId,
+    enc: &[u8],
+    private_key: &PrivateKey,
+    info: &[u8],
+) -> Result<Context, CryptoError> {
+    if enc.len() != ENC_SIZE {
+        return Err(CryptoError::ParseError(
+            "Encapsulated key has the wrong length".to_string(),
+        ));
+    }
+    validate_public_key(&PublicKey(enc.to_vec()))?;
+    let sk = secret_key(private_key)?;
+    let public_key = X25519PublicKey::from(&sk);
+    let mut dh = sk.diffie_hellman(&X25519PublicKey::from(*array_ref!(enc, 0, ENC_SIZE)));
+    let shared_secret = extract_and_expand(dh.as_bytes(), enc, public_key.as_bytes());
+    dh.zeroize();
+    let mut shared_secret = shared_secret?;
+    let context = key_schedule(aead, &shared_secret, info);
+    shared_secret.zeroize();
+    context
+}
+
+/// Encrypt one message to `recipient`, returning the encapsulated key and the
+/// ciphertext
+pub fn seal_base(
+    aead: AeadId,
+    recipient: &PublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+    let (enc, mut context) = setup_base_sender(aead, recipient, info)?;
+    Ok((enc, context.seal(aad, plaintext)?))
+}
+
+/// Decrypt a message of `seal_base`
+pub fn open_base(
+    aead: AeadId,
+    enc: &[u8],
+    private_key: &PrivateKey,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    setup_base_receiver(aead, enc, private_key, info)?.open(aad, ciphertext)
+}
+
+fn setup_sender_with_ephemeral(
+    aead: AeadId,
+    recipient: &PublicKey,
+    info: &[u8],
+    ephemeral: &PrivateKey,
+) -> Result<(Vec<u8>, Context), CryptoError> {
+    validate_public_key(recipient)?;
+    let sk = secret_key(ephemeral)?;
+    let enc = X25519PublicKey::from(&sk).as_bytes().to_vec();
+    let mut dh = sk.diffie_hellman(&X25519PublicKey::from(*array_ref!(
+        &recipient[..],
+        0,
+        KEY_SIZE
+    )));
+    let shared_secret = extract_and_expand(dh.as_bytes(), &enc, &recipient[..]);
+    dh.zeroize();
+    let mut shared_secret = shared_secret?;
+    let context = key_schedule(aead, &shared_secret, info);
+    shared_secret.zeroize();
+    Ok((enc, context?))
+}
+
+fn secret_key(private_key: &PrivateKey) -> Result<StaticSecret, CryptoError> {
+    if private_key.len() != KEY_SIZE {
+        return Err(CryptoError::ParseError(
+            "Private key has the wrong length".to_string(),
+        ));
+    }
+    Ok(StaticSecret::from(*array_ref!(
+        &private_key[..],
+        0,
+        KEY_SIZE
+    )))
+}
+
+fn extract_and_expand(dh: &[u8], enc: &[u8], recipient: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    // A low order public key gives the all zero output, which RFC 9180 7.1.4 rejects
+    if is_all_zero(dh) {
+        return Err(CryptoError::GeneralError(
+            "Public key has a low order".to_string(),
+        ));
+    }
+    let suite_id = kem_suite_id();
+    let mut kem_context = enc.to_vec();
+    kem_context.extend_from_slice(recipient);
+    let mut prk = labeled_extract(&[], &suite_id, b"eae_prk", dh);
+    let shared_secret = labeled_expand(&prk, &suite_id, b"shared_secret", &kem_context, KEY_SIZE);
+    prk.zeroize();
+    shared_secret
+}
+
+fn key_schedule(aead: AeadId, shared_secret: &[u8], info: &[u8]) -> Result<Context, CryptoError> {
+    let suite_id = hpke_suite_id(aead);
+    let mut context = vec![MODE_BASE];
+    context.extend_from_slice(&labeled_extract(&[], &suite_id, b"psk_id_hash", &[]));
+    context.extend_from_slice(&labeled_extract(&[], &suite_id, b"info_hash", info));
+    let mut secret = labeled_extract(shared_secret, &suite_id, b"secret", &[]);
+    let keys = (
+        labeled_expand(&secret, &suite_id, b"key", &context, aead.key_size()),
+        labeled_expand(&secret, &suite_id, b"base_nonce", &context, NONCE_SIZE),
+        labeled_expand(&secret, &suite_id, b"exp", &context, HASH_SIZE),
+    );
+    secret.zeroize();
+    Ok(Context {
+        aead,
+        key: keys.0?,
+        base_nonce: keys.1?,
+        exporter_secret: keys.2?,
+        sequence: 0,
+    })
+}
+
+fn kem_suite_id() -> Vec<u8> {
+    let mut id = b"KEM".to_vec();
+    id.extend_from_slice(&KEM_ID.to_be_bytes());
+    id
+}
+
+fn hpke_suite_id(aead: AeadId) -> Vec<u8> {
+    let mut id = b"HPKE".to_vec();
+    id.extend_from_slice(&KEM_ID.to_be_bytes());
+    id.extend_from_slice(&KDF_ID.to_be_bytes());
+    id.extend_from_slice(&aead.id().to_be_bytes());
+    id
+}
+
+fn labeled_extract(salt: &[u8], suite_id: &[u8], label: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let mut labeled_ikm = VERSION_LABEL.to_vec();
+    labeled_ikm.extend_from_slice(suite_id);
+    labeled_ikm.extend_from_slice(label);
+    labeled_ikm.extend_from_slice(ikm);
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), &labeled_ikm);
+    labeled_ikm.zeroize();
+    prk.to_vec()
+}
+
+fn labeled_expand(
+    prk: &[u8],
+    suite_id: &[u8],
+    label: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, CryptoError> {
+    if length > 0xffff {
+        return Err(CryptoError::GeneralError(
+            "Requested too many bytes".to_string(),
+        ));
+    }
+    let mut labeled_info = (length as u16).to_be_bytes().to_vec();
+    labeled_info.extend_from_slice(VERSION_LABEL);
+    labeled_info.extend_from_slice(suite_id);
+    labeled_info.extend_from_slice(label);
+    labeled_info.extend_from_slice(info);
+    let mut output = vec![0u8; length];
+    Hkdf::<Sha256>::from_prk(prk)
+        .map_err(|_| CryptoError::GeneralError("Invalid pseudorandom key".to_string()))?
+        .expand(&labeled_info, &mut output)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex::decode;
+
+    // RFC 9180 appendix A.1.1, DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM
+    #[test]
+    fn rfc9180_base_vector() {
+        let info = decode("4f6465206f6e2061204772656369616e2055726e").unwrap();
+        let ikm_e =
+            decode("7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234").unwrap();
+        let ikm_r =
+            decode("6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037").unwrap();
+        let (pk_e, sk_e) = derive_key_pair(&ikm_e).unwrap();
+        let (pk_r, sk_r) = derive_key_pair(&ikm_r).unwrap();
+        assert_eq!(
+            pk_e[..],
+            decode("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431").unwrap()[..]
+        );
+        assert_eq!(
+            sk_e[..],
+            decode("52c4a758a802cd8b936eceea314432798d5baf2d7e9235dc084ab1b9cfa2f736").unwrap()[..]
+        );
+        assert_eq!(
+            pk_r[..],
+            decode("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d").unwrap()[..]
+        );
+        assert_eq!(
+            sk_r[..],
+            decode("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8").unwrap()[..]
+        );
+
+        let (enc, mut sender) =
+            setup_sender_with_ephemeral(AeadId::Aes128Gcm, &pk_r, &info, &sk_e).unwrap();
+        assert_eq!(enc, pk_e[..].to_vec());
+        assert_eq!(
+            sender.key,
+            decode("4531685d41d65f03dc48f6b8302c05b0").unwrap()
+        );
+        assert_eq!(
+            sender.base_nonce,
+            decode("56d890e5accaaf011cff4b7d").unwrap()
+        );
+        assert_eq!(
+            sender.exporter_secret,
+            decode("45ff1c2e220db587171952c0592d5f5ebe103f1561a2614e38f2ffd47e99e3f8").unwrap()
+        );
+
+        let plaintext =
+            decode("4265617574792069732074727574682c20747275746820626561757479").unwrap();
+        let aad = decode("436f756e742d30").unwrap();
+        let ciphertext = sender.seal(&aad, &plaintext).unwrap();
+        assert_eq!(
+            ciphertext,
+            decode(
+                "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a9\
+                 6d8770ac83d07bea87e13c512a"
+            )
+            .unwrap()
+        );
+        let mut receiver = setup_base_receiver(AeadId::Aes128Gcm, &enc, &sk_r, &info).unwrap();
+        assert_eq!(receiver.open(&aad, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn contexts() {
+        for aead in &[
+            AeadId::Aes128Gcm,
+            AeadId::Aes256Gcm,
+            AeadId::ChaCha20Poly1305,
+        ] {
+            assert_eq!(AeadId::from_id(aead.id()).unwrap(), *aead);
+            let (pk, sk) = generate_key_pair().unwrap();
+            let (enc, mut sender) = setup_base_sender(*aead, &pk, b"info").unwrap();
+            let mut receiver = setup_base_receiver(*aead, &enc, &sk, b"info").unwrap();
+            let messages = (0..3)
+                .map(|i| sender.seal(b"aad", &[i as u8; 40]).unwrap())
+                .collect::<Vec<_>>();
+            assert_ne!(messages[0], messages[1]);
+            for (i, m) in messages.iter().enumerate() {
+                assert_eq!(receiver.open(b"aad", m).unwrap(), vec![i as u8; 40]);
+            }
+            assert_eq!(
+                sender.export(b"context", 64).unwrap(),
+                receiver.export(b"context", 64).unwrap()
+            );
+            assert_ne!(
+                sender.export(b"context", 32).unwrap(),
+                sender.export(b"other", 32).unwrap()
+            );
+
+            // Out of order, with other info or another key
+            let (enc, mut sender) = setup_base_sender(*aead, &pk, b"info").unwrap();
+            let first = sender.seal(b"", b"first").unwrap();
+            let second = sender.seal(b"", b"second").unwrap();
+            let mut receiver = setup_base_receiver(*aead, &enc, &sk, b"info").unwrap();
+            assert!(receiver.open(b"", &second).is_err());
+            let mut receiver = setup_base_receiver(*aead, &enc, &sk, b"other").unwrap();
+            assert!(receiver.open(b"", &first).is_err());
+            let (_, other) = generate_key_pair().unwrap();
+            let mut receiver = setup_base_receiver(*aead, &enc, &other, b"info").unwrap();
+            assert!(receiver.open(b"", &first).is_err());
+        }
+        assert!(AeadId::from_id(0xffff).is_err());
+    }
+
+    #[test]
+    fn single_shot() {
+        let (pk, sk) = generate_key_pair().unwrap();
+        let (enc, ciphertext) =
+            seal_base(AeadId::ChaCha20Poly1305, &pk, b"info", b"aad", b"message").unwrap();
+        assert_eq!(enc.len(), ENC_SIZE);
+        assert_eq!(ciphertext.len(), 7 + TAG_SIZE);
+        let plaintext = open_base(
+            AeadId::ChaCha20Poly1305,
+            &enc,
+            &sk,
+            b"info",
+            b"aad",
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(plaintext, b"message");
+        assert!(open_base(
+            AeadId::ChaCha20Poly1305,
+            &enc,
+            &sk,
+            b"info",
+            b"other",
+            &ciphertext
+        )
+        .is_err());
+        assert!(open_base(
+            AeadId::ChaCha20Poly1305,
+            &enc[1..],
+            &sk,
+            b"info",
+            b"aad",
+            &ciphertext
+        )
+        .is_err());
+
+        // The identity point is rejected
+        assert!(seal_base(AeadId::Aes128Gcm, &PublicKey(vec![0u8; 32]), b"", b"", b"").is_err());
+        assert!(setup_base_receiver(AeadId::Aes128Gcm, &[0u8; 32], &sk, b"").is_err());
+        assert!(derive_key_pair(&[0u8; 31]).is_err());
+    }
+
+    #[test]
+    fn low_order_keys() {
+        let (_, sk) = generate_key_pair().unwrap();
+        // A point of order 8, with and without the ignored top bit
+        let mut point =
+            decode("e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800").unwrap();
+        for _ in 0..2 {
+            match setup_base_receiver(AeadId::ChaCha20Poly1305, &point, &sk, b"") {
+                Err(CryptoError::GeneralError(m)) => assert_eq!(m, "Public key has a low order"),
+                _ => panic!("Accepted a low order encapsulated key"),
+            }
+            assert!(
+                open_base(AeadId::ChaCha20Poly1305, &point, &sk, b"", b"", &[0u8; 16]).is_err()
+            );
+            assert!(seal_base(
+                AeadId::ChaCha20Poly1305,
+                &PublicKey(point.clone()),
+                b"",
+                b"",
+                b""
+            )
+            .is_err());
+            point[31] |= 0x80;
+        }
+    }
+}
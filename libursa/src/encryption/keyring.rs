@@ -0,0 +1,348 @@
+//! Per-tenant and per-record encryption keys derived from one master key.
+//!
+//! Encrypting every record of a table under its own key limits what one leaked key
+//! exposes, but storing millions of keys is its own problem. A `DerivedKeyring`
+//! stores nothing but the master key: each tenant key is derived with HKDF-SHA256
+//! from the master key, the tenant name and a key version, and each record key from
+//! the tenant key and a `KeyContext` naming the table, column and optionally the
+//! record. The same inputs always give the same key, so keys are derived again when
+//! a record is read instead of being looked up.
+//!
+//! A tenant is re-keyed by moving its records from one key version to the next with
+//! `TenantKeyring::reencrypt`. Only the current version of each tenant has to be
+//! stored. Retiring a version makes every record still under it unreadable.
+//!
+//! Every field of a context is length prefixed before it is used, so no two different
+//! contexts produce the same key.
+
+use aead::generic_array::GenericArray;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use super::symm::{Encryptor, SymmetricEncryptor};
+use CryptoError;
+
+pub const KEY_SIZE: usize = 32;
+
+const KEYRING_SALT: &[u8] = b"URSA_KEYRING_V1";
+const TENANT_KEY_CONTEXT: &[u8] = b"ursa keyring tenant key";
+const RECORD_KEY_CONTEXT: &[u8] = b"ursa keyring record key";
+
+/// The root of the key hierarchy. Keep it in a key vault or KMS, every record
+/// key can be derived from it.
+pub struct MasterKey([u8; KEY_SIZE]);
+
+impl MasterKey {
+    pub fn new(key: [u8; KEY_SIZE]) -> Self {
+        MasterKey(key)
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        MasterKey(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl ::std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Where a key is used: a column of a table, and optionally one record in it.
+/// The names must stay the same for the lifetime of the stored ciphertexts,
+/// changing them changes the key.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyContext {
+    table: String,
+    column: String,
+    record: Option<Vec<u8>>,
+}
+
+impl KeyContext {
+    /// The context of a whole column
+    pub fn new(table: &str, column: &str) -> Self {
+        KeyContext {
+            table: table.to_string(),
+            column: column.to_string(),
+            record: None,
+        }
+    }
+
+    /// The context of the record with `id` in this column
+    pub fn with_record<A: AsRef<[u8]>>(mut self, id: A) -> Self {
+        self.record = Some(id.as_ref().to_vec());
+        self
+    }
+
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    pub fn record(&self) -> Option<&[u8]> {
+        self.record.as_deref()
+    }
+
+    fn to_info(&self) -> Result<Vec<u8>, CryptoError> {
+        if self.table.is_empty() || self.column.is_empty() {
+            return Err(CryptoError::GeneralError(
+                "Key contexts need a table and a column".to_string(),
+            ));
+        }
+        let mut info = RECORD_KEY_CONTEXT.to_vec();
+        push_field(&mut info, self.table.as_bytes());
+        push_field(&mut info, self.column.as_bytes());
+        match &self.record {
+            Some(id) => {
+                info.push(1);
+                push_field(&mut info, id);
+            }
+            None => info.push(0),
+        }
+        Ok(info)
+    }
+}
+
+/// Derives the keys of every tenant from a master key
+#[derive(Debug)]
+pub struct DerivedKeyring {
+    master: MasterKey,
+}
+
+impl DerivedKeyring {
+    pub fn new(master: MasterKey) -> Self {
+        DerivedKeyring { master }
+    }
+
+    /// The keys of `tenant` at key `version`. Each version gives unrelated keys
+    pub fn tenant(&self, tenant: &str, version: u32) -> Result<TenantKeyring, CryptoError> {
+        if tenant.is_empty() {
+            return Err(CryptoError::GeneralError("Tenants need a name".to_string()));
+        }
+        let mut info = TENANT_KEY_CONTEXT.to_vec();
+        push_field(&mut info, tenant.as_bytes());
+        info.extend_from_slice(&version.to_be_bytes());
+        let mut key = [0u8; KEY_SIZE];
+        expand(&self.master.0, &info, &mut key)?;
+        Ok(TenantKeyring {
+            tenant: tenant.to_string(),
+            version,
+            key,
+        })
+    }
+}
+
+/// The keys of one tenant at one key version
+pub struct TenantKeyring {
+    tenant: String,
+    version: u32,
+    key: [u8; KEY_SIZE],
+}
+
+impl TenantKeyring {
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Fill `key` with the key for `context`
+    pub fn derive_key(&self, context: &KeyContext, key: &mut [u8]) -> Result<(), CryptoError> {
+        expand(&self.key, &context.to_info()?, key)
+    }
+
+    /// An encryptor keyed for `context`
+    pub fn encryptor<E: Encryptor>(
+        &self,
+        context: &KeyContext,
+    ) -> Result<SymmetricEncryptor<E>, CryptoError> {
+        let mut key = GenericArray::<u8, E::KeySize>::default();
+        self.derive_key(context, key.as_mut_slice())?;
+        let encryptor = SymmetricEncryptor::new(E::new(&key));
+        key.as_mut_slice().zeroize();
+        Ok(encryptor)
+    }
+
+    /// Decrypt `ciphertext` of `context` made by `encrypt_easy` under these keys and
+    /// encrypt it again under the keys of `next`, usually the next version of the tenant
+    pub fn reencrypt<E: Encryptor>(
+        &self,
+        next: &TenantKeyring,
+        context: &KeyContext,
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut plaintext = self
+            .encryptor::<E>(context)?
+            .decrypt_easy(aad, ciphertext)
+            .map_err(|_| CryptoError::GeneralError("Record could not be decrypted".to_string()))?;
+        let res = next
+            .encryptor::<E>(context)?
+            .encrypt_easy(aad, plaintext.as_slice())
+            .map_err(|_| CryptoError::GeneralError("Record could not be encrypted".to_string()));
+        plaintext.zeroize();
+        res
+    }
+}
+
+impl ::std::fmt::Debug for TenantKeyring {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("TenantKeyring")
+            .field("tenant", &self.tenant)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl Drop for TenantKeyring {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+fn push_field(info: &mut Vec<u8>, field: &[u8]) {
+    info.extend_from_slice(&(field.len() as u64).to_be_bytes());
+    info.extend_from_slice(field);
+}
+
+fn expand(ikm: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), CryptoError> {
+    Hkdf::<Sha256>::new(Some(KEYRING_SALT), ikm)
+        .expand(info, okm)
+        .map_err(|e| CryptoError::KeyGenError(format!("Failed to derive key: {}", e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use encryption::symm::prelude::*;
+
+    #[test]
+    fn derivation() {
+        let keyring = DerivedKeyring::new(MasterKey::generate());
+        let acme = keyring.tenant("acme", 1).unwrap();
+        let email = KeyContext::new("users", "email").with_record(42u64.to_be_bytes());
+
+        let mut key = [0u8; KEY_SIZE];
+        acme.derive_key(&email, &mut key).unwrap();
+        let mut again = [0u8; KEY_SIZE];
+        keyring
+            .tenant("acme", 1)
+            .unwrap()
+            .derive_key(&email, &mut again)
+            .unwrap();
+        assert_eq!(key, again);
+
+        let others = [
+            (keyring.tenant("acme", 2).unwrap(), email.clone()),
+            (keyring.tenant("globex", 1).unwrap(), email.clone()),
+            (
+                keyring.tenant("acme", 1).unwrap(),
+                KeyContext::new("users", "email").with_record(43u64.to_be_bytes()),
+            ),
+            (
+                keyring.tenant("acme", 1).unwrap(),
+                KeyContext::new("users", "phone").with_record(42u64.to_be_bytes()),
+            ),
+            (
+                keyring.tenant("acme", 1).unwrap(),
+                KeyContext::new("users", "email"),
+            ),
+            // The same bytes split differently between the fields
+            (
+                keyring.tenant("acme", 1).unwrap(),
+                KeyContext::new("user", "semail").with_record(42u64.to_be_bytes()),
+            ),
+        ];
+        for (tenant, context) in others.iter() {
+            let mut other = [0u8; KEY_SIZE];
+            tenant.derive_key(context, &mut other).unwrap();
+            assert_ne!(key, other);
+        }
+
+        assert!(keyring.tenant("", 1).is_err());
+        assert!(acme
+            .derive_key(&KeyContext::new("", "email"), &mut key)
+            .is_err());
+        assert!(acme
+            .derive_key(&KeyContext::new("users", ""), &mut key)
+            .is_err());
+    }
+
+    #[test]
+    fn known_answer() {
+        let keyring = DerivedKeyring::new(MasterKey::new([7u8; KEY_SIZE]));
+        let tenant = keyring.tenant("acme", 1).unwrap();
+        let mut key = [0u8; KEY_SIZE];
+        tenant
+            .derive_key(
+                &KeyContext::new("users", "email").with_record(b"42"),
+                &mut key,
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(key),
+            "eb157097dc1a4a9043a7567fbc297e66be3297863cbd086f2472154a6a639c13"
+        );
+    }
+
+    #[test]
+    fn encrypt_and_rekey() {
+        let keyring = DerivedKeyring::new(MasterKey::generate());
+        let current = keyring.tenant("acme", 1).unwrap();
+        let next = keyring.tenant("acme", 2).unwrap();
+        let context = KeyContext::new("users", "email").with_record(b"42");
+        let aad = b"users.email.42";
+        let message = b"alice@example.com";
+
+        let ciphertext = current
+            .encryptor::<XChaCha20Poly1305>(&context)
+            .unwrap()
+            .encrypt_easy(aad.as_ref(), message.as_ref())
+            .unwrap();
+        let rekeyed = current
+            .reencrypt::<XChaCha20Poly1305>(&next, &context, aad, &ciphertext)
+            .unwrap();
+        let decrypted = next
+            .encryptor::<XChaCha20Poly1305>(&context)
+            .unwrap()
+            .decrypt_easy(aad.as_ref(), rekeyed.as_slice())
+            .unwrap();
+        assert_eq!(decrypted.as_slice(), message);
+
+        // The old version and other records can't read it
+        assert!(current
+            .encryptor::<XChaCha20Poly1305>(&context)
+            .unwrap()
+            .decrypt_easy(aad.as_ref(), rekeyed.as_slice())
+            .is_err());
+        let other = KeyContext::new("users", "email").with_record(b"43");
+        assert!(next
+            .encryptor::<XChaCha20Poly1305>(&other)
+            .unwrap()
+            .decrypt_easy(aad.as_ref(), rekeyed.as_slice())
+            .is_err());
+        assert!(next
+            .reencrypt::<XChaCha20Poly1305>(&current, &context, aad, &ciphertext)
+            .is_err());
+    }
+}
@@ -1,9 +1,36 @@
 //! Encryption is categorized as public key encryption or symmetric encryption
 //! `symm` provides symmetric AEAD cryptographic algorithms.
+//! `blind_index` provides exact-match lookups over records encrypted with them.
+//! `envelope` encrypts one message to many X25519, P-256, RSA and threshold committee
+//! recipients with them.
+//! `hpke` encrypts to an X25519 public key with them, as RFC 9180 does.
+//! `keyring` derives per-tenant and per-record keys for them from one master key.
+//! `ore` provides range queries over them, at the cost of revealing the order of the values.
+//! `password_file` encrypts files of any size under a passphrase with them.
 use aead::generic_array::{ArrayLength, GenericArray};
 use aead::Error;
 use rand::{rngs::OsRng, RngCore};
 
+#[cfg(feature = "blind_index")]
+pub mod blind_index;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+#[cfg(feature = "hpke")]
+pub mod hpke;
+#[cfg(feature = "keyring")]
+pub mod keyring;
+#[cfg(feature = "leaky")]
+pub mod ore;
+#[cfg(feature = "password_file")]
+pub mod password_file;
+#[cfg(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+))]
 pub mod symm;
 
 // Helpful for generating bytes using the operating system random number generator
@@ -0,0 +1,287 @@
+//! Order-revealing encryption of 64 bit integers (Lewi and Wu, CCS 2016).
+//!
+//! **This scheme leaks information by design.** It is only built with the `leaky`
+//! feature and should only be used for range queries that can't be answered any
+//! other way.
+//!
+//! Values are encrypted into a left and a right ciphertext. Comparing a left
+//! ciphertext with a right ciphertext reveals the order of the two values and the
+//! index of the first byte, from the most significant, in which they differ.
+//! Right ciphertexts on their own reveal nothing, two right ciphertexts can't be
+//! compared with each other. A database should therefore store only right
+//! ciphertexts and the client sends a left ciphertext with every query, so order is
+//! only revealed for the values that are queried. Storing left ciphertexts as well
+//! reveals the order of the whole column.
+//!
+//! Each value is split into eight one byte blocks and the right ciphertext holds
+//! one comparison result for every possible value of every block, hidden under a
+//! PRF, which makes it 528 bytes long.
+
+use std::cmp::Ordering;
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use CryptoError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const KEY_SIZE: usize = 32;
+const BLOCKS: usize = 8;
+const BLOCK_VALUES: usize = 256;
+const NONCE_SIZE: usize = 16;
+const LEFT_BLOCK_SIZE: usize = 33;
+pub const LEFT_CIPHERTEXT_SIZE: usize = BLOCKS * LEFT_BLOCK_SIZE;
+/// Four results of two bits each are packed into every byte
+pub const RIGHT_CIPHERTEXT_SIZE: usize = NONCE_SIZE + BLOCKS * BLOCK_VALUES / 4;
+
+/// The key for both halves of the ciphertexts
+pub struct OreKey {
+    prf_key: [u8; KEY_SIZE],
+    permutation_key: [u8; KEY_SIZE],
+}
+
+/// What the client sends to compare against stored values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeftCiphertext {
+    /// The PRF output and the permuted value of every block
+    blocks: Vec<([u8; 32], u8)>,
+}
+
+/// What is stored
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RightCiphertext {
+    nonce: [u8; NONCE_SIZE],
+    /// The comparison results, held as 0 for equal, 1 for greater and 2 for less
+    /// plus a PRF output, modulo 3
+    results: Vec<u8>,
+}
+
+impl OreKey {
+    pub fn generate() -> Self {
+        let mut key = OreKey {
+            prf_key: [0u8; KEY_SIZE],
+            permutation_key: [0u8; KEY_SIZE],
+        };
+        OsRng.fill_bytes(&mut key.prf_key);
+        OsRng.fill_bytes(&mut key.permutation_key);
+        key
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != 2 * KEY_SIZE {
+            return Err(CryptoError::ParseError(format!(
+                "ORE keys are {} bytes",
+                2 * KEY_SIZE
+            )));
+        }
+        Ok(OreKey {
+            prf_key: *array_ref!(bytes, 0, KEY_SIZE),
+            permutation_key: *array_ref!(bytes, KEY_SIZE, KEY_SIZE),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.prf_key.to_vec();
+        bytes.extend_from_slice(&self.permutation_key);
+        bytes
+    }
+
+    pub fn encrypt_left(&self, value: u64) -> LeftCiphertext {
+        let x = value.to_be_bytes();
+        let blocks = (0..BLOCKS)
+            .map(|i| {
+                let permuted = self.permutation(i, &x[..i])[x[i] as usize];
+                (self.prf(i, &x[..i], permuted), permuted)
+            })
+            .collect();
+        LeftCiphertext { blocks }
+    }
+
+    pub fn encrypt_right(&self, value: u64) -> RightCiphertext {
+        let y = value.to_be_bytes();
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let mut results = vec![0u8; BLOCKS * BLOCK_VALUES / 4];
+        for i in 0..BLOCKS {
+            let permutation = self.permutation(i, &y[..i]);
+            for (original, permuted) in permutation.iter().enumerate() {
+                let cmp = match (original as u8).cmp(&y[i]) {
+                    Ordering::Equal => 0,
+                    Ordering::Greater => 1,
+                    Ordering::Less => 2,
+                };
+                let mut mask = self.prf(i, &y[..i], *permuted);
+                let result = (cmp + hash_mod3(&mask, &nonce)) % 3;
+                mask.zeroize();
+                let position = i * BLOCK_VALUES + *permuted as usize;
+                results[position / 4] |= result << (2 * (position % 4));
+            }
+        }
+        RightCiphertext { nonce, results }
+    }
+
+    /// F(k, i || prefix || block), with the prefix length fixed by `i`
+    fn prf(&self, block: usize, prefix: &[u8], value: u8) -> [u8; 32] {
+        let mut hmac = HmacSha256::new_varkey(&self.prf_key).unwrap();
+        hmac.input(&[block as u8]);
+        hmac.input(prefix);
+        hmac.input(&[value]);
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&hmac.result().code());
+        out
+    }
+
+    /// The permutation of a block's values, which depends on the blocks before it.
+    /// `permutation[v]` is where value `v` goes.
+    fn permutation(&self, block: usize, prefix: &[u8]) -> Vec<u8> {
+        let mut hmac = HmacSha256::new_varkey(&self.permutation_key).unwrap();
+        hmac.input(&[block as u8]);
+        hmac.input(prefix);
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&hmac.result().code());
+        let mut rng = ChaChaRng::from_seed(seed);
+        seed.zeroize();
+        let mut permutation: Vec<u8> = (0..BLOCK_VALUES).map(|v| v as u8).collect();
+        permutation.shuffle(&mut rng);
+        permutation
+    }
+}
+
+impl Drop for OreKey {
+    fn drop(&mut self) {
+        self.prf_key.zeroize();
+        self.permutation_key.zeroize();
+    }
+}
+
+impl LeftCiphertext {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(LEFT_CIPHERTEXT_SIZE);
+        for (mask, permuted) in &self.blocks {
+            bytes.extend_from_slice(mask);
+            bytes.push(*permuted);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != LEFT_CIPHERTEXT_SIZE {
+            return Err(CryptoError::ParseError(format!(
+                "ORE left ciphertexts are {} bytes",
+                LEFT_CIPHERTEXT_SIZE
+            )));
+        }
+        Ok(LeftCiphertext {
+            blocks: bytes
+                .chunks(LEFT_BLOCK_SIZE)
+                .map(|b| (*array_ref!(b, 0, 32), b[32]))
+                .collect(),
+        })
+    }
+}
+
+impl RightCiphertext {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.nonce.to_vec();
+        bytes.extend_from_slice(&self.results);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != RIGHT_CIPHERTEXT_SIZE {
+            return Err(CryptoError::ParseError(format!(
+                "ORE right ciphertexts are {} bytes",
+                RIGHT_CIPHERTEXT_SIZE
+            )));
+        }
+        Ok(RightCiphertext {
+            nonce: *array_ref!(bytes, 0, NONCE_SIZE),
+            results: bytes[NONCE_SIZE..].to_vec(),
+        })
+    }
+
+    fn result(&self, block: usize, permuted: u8) -> u8 {
+        let position = block * BLOCK_VALUES + permuted as usize;
+        (self.results[position / 4] >> (2 * (position % 4))) & 0x03
+    }
+}
+
+/// Compare the value in `left` with the value in `right`. Both have to come from
+/// the same key, otherwise the result is meaningless.
+pub fn compare(left: &LeftCiphertext, right: &RightCiphertext) -> Ordering {
+    for (i, (mask, permuted)) in left.blocks.iter().enumerate() {
+        let result = (right.result(i, *permuted) + 3 - hash_mod3(mask, &right.nonce)) % 3;
+        match result {
+            1 => return Ordering::Greater,
+            2 => return Ordering::Less,
+            _ => continue,
+        }
+    }
+    Ordering::Equal
+}
+
+/// H(F(k, ...), r) reduced to a comparison result
+fn hash_mod3(mask: &[u8; 32], nonce: &[u8; NONCE_SIZE]) -> u8 {
+    let mut hmac = HmacSha256::new_varkey(mask).unwrap();
+    hmac.input(nonce);
+    let hash = hmac.result().code();
+    // The bias of reducing 64 bits modulo 3 is negligible
+    (u64::from_le_bytes(*array_ref!(hash, 0, 8)) % 3) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn comparisons() {
+        let key = OreKey::generate();
+        let values = [
+            0u64,
+            1,
+            255,
+            256,
+            1 << 32,
+            (1 << 32) + 1,
+            u64::MAX - 1,
+            u64::MAX,
+        ];
+        let rights: Vec<_> = values.iter().map(|v| key.encrypt_right(*v)).collect();
+        for x in values.iter() {
+            let left = key.encrypt_left(*x);
+            for (y, right) in values.iter().zip(rights.iter()) {
+                assert_eq!(compare(&left, right), x.cmp(y), "{} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn right_ciphertexts_are_randomized() {
+        let key = OreKey::generate();
+        let a = key.encrypt_right(42);
+        let b = key.encrypt_right(42);
+        assert_ne!(a, b);
+        assert_eq!(key.encrypt_left(42), key.encrypt_left(42));
+        assert_eq!(compare(&key.encrypt_left(43), &a), Ordering::Greater);
+    }
+
+    #[test]
+    fn serialization() {
+        let key = OreKey::from_bytes(&OreKey::generate().to_bytes()).unwrap();
+        let left = key.encrypt_left(1000);
+        let right = key.encrypt_right(999);
+        let left = LeftCiphertext::from_bytes(&left.to_bytes()).unwrap();
+        let bytes = right.to_bytes();
+        assert_eq!(bytes.len(), RIGHT_CIPHERTEXT_SIZE);
+        let right = RightCiphertext::from_bytes(&bytes).unwrap();
+        assert_eq!(compare(&left, &right), Ordering::Greater);
+        assert!(RightCiphertext::from_bytes(&bytes[1..]).is_err());
+        assert!(LeftCiphertext::from_bytes(&[0u8; 10]).is_err());
+    }
+}
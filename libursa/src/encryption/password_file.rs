@@ -0,0 +1,483 @@
+//! Encryption of files under a passphrase.
+//!
+//! `encrypt` and `decrypt` are the whole recipe, so command line tools and applications
+//! don't have to put one together from a password hash, a cipher and a file format:
+//!
+//! * The key is derived from the passphrase with Argon2id. Its parameters are stored in
+//!   the header, so they can be raised later and older files still decrypt.
+//! * `KeyCommitting::derive` turns that key into the XChaCha20-Poly1305 key of the
+//!   content and a commitment kept in the header. A wrong passphrase is detected before
+//!   anything is decrypted, and no file can be crafted to open under several passphrases.
+//! * The content is sealed in chunks with [`stream`](super::symm::stream), so files of
+//!   any size are encrypted without holding them in memory. The header is authenticated
+//!   with every chunk.
+//! * With `Params::armor` the file is base64 encoded between BEGIN and END lines, to be
+//!   pasted into email or a terminal. `decrypt` reads both forms.
+//!
+//! As with any stream, plaintext written out before `decrypt` fails must be discarded.
+//!
+//! A file is encoded as
+//!
+//! ```text
+//! magic "URSAPWF" (7) | version (1) | Argon2id memory in KiB (4, big endian) |
+//! iterations (4, big endian) | parallelism (4, big endian) | salt (16) | commitment (32) |
+//! stream
+//! ```
+
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+
+use aead::generic_array::GenericArray;
+use aead::NewAead;
+use argon2::{self, Argon2};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use super::symm::committing::{KeyCommitting, COMMITMENT_SIZE};
+use super::symm::stream::{DecryptorStream, EncryptorStream};
+use super::symm::xchacha20poly1305::XChaCha20Poly1305;
+use CryptoError;
+
+/// The version of the file format
+pub const VERSION: u8 = 1;
+/// The most memory a file may ask Argon2id for, 1 GiB
+pub const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+/// The most Argon2id iterations a file may ask for
+pub const MAX_ITERATIONS: u32 = 64;
+/// The most Argon2id lanes a file may ask for
+pub const MAX_PARALLELISM: u32 = 16;
+
+const MAGIC: &[u8] = b"URSAPWF";
+const PARAMS_OFFSET: usize = 8;
+const SALT_OFFSET: usize = PARAMS_OFFSET + 12;
+const SALT_SIZE: usize = 16;
+const COMMITMENT_OFFSET: usize = SALT_OFFSET + SALT_SIZE;
+const HEADER_SIZE: usize = COMMITMENT_OFFSET + COMMITMENT_SIZE;
+const KEY_SIZE: usize = 32;
+const ARMOR_BEGIN: &str = "-----BEGIN URSA ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END URSA ENCRYPTED FILE-----";
+/// Encoded as 64 characters
+const ARMOR_LINE_BYTES: usize = 48;
+const ARMOR_MAX_LINE: usize = 128;
+
+/// How the key is derived and the file written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// The memory Argon2id uses, in KiB, at most `MAX_MEMORY_KIB`
+    pub memory_kib: u32,
+    /// The Argon2id iterations over memory, at most `MAX_ITERATIONS`
+    pub iterations: u32,
+    /// The Argon2id lanes, at most `MAX_PARALLELISM`
+    pub parallelism: u32,
+    /// Whether the file is ASCII armored
+    pub armor: bool,
+}
+
+impl Default for Params {
+    /// Argon2id with the parameters recommended by OWASP and a binary file
+    fn default() -> Self {
+        Params {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+            armor: false,
+        }
+    }
+}
+
+/// Encrypt everything read from `plaintext` under `passphrase` and write the file to
+/// `output`, which is returned. Deriving the key takes time and memory on purpose to
+/// slow down guessing
+pub fn encrypt<R: Read, W: Write>(
+    passphrase: &[u8],
+    params: Params,
+    mut plaintext: R,
+    output: W,
+) -> Result<W, CryptoError> {
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+    header.extend_from_slice(&params.memory_kib.to_be_bytes());
+    header.extend_from_slice(&params.iterations.to_be_bytes());
+    header.extend_from_slice(&params.parallelism.to_be_bytes());
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    header.extend_from_slice(&salt);
+    let (cipher, commitment) = derive(passphrase, &params, &header)?;
+    header.extend_from_slice(&commitment);
+
+    let mut output = if params.armor {
+        Output::Armored(ArmorWriter::new(output).map_err(io_error)?)
+    } else {
+        Output::Binary(output)
+    };
+    output.write_all(&header).map_err(io_error)?;
+    let mut stream = EncryptorStream::new(&cipher, &header, output)
+        .map_err(|_| CryptoError::GeneralError("Failed to start the stream".to_string()))?;
+    io::copy(&mut plaintext, &mut stream).map_err(io_error)?;
+    stream
+        .finish()
+        .map_err(|_| CryptoError::GeneralError("Failed to seal the last chunk".to_string()))?
+        .finish()
+        .map_err(io_error)
+}
+
+/// Decrypt a file from `encrypt`, binary or armored, and write the plaintext to
+/// `plaintext`, which is returned
+pub fn decrypt<R: Read, W: Write>(
+    passphrase: &[u8],
+    mut input: R,
+    mut plaintext: W,
+) -> Result<W, CryptoError> {
+    // Look at the start to tell armored files from binary ones
+    let mut start = Vec::with_capacity(ARMOR_BEGIN.len());
+    (&mut input)
+        .take(ARMOR_BEGIN.len() as u64)
+        .read_to_end(&mut start)
+        .map_err(io_error)?;
+    let armor = start == ARMOR_BEGIN.as_bytes();
+    let input = BufReader::new(Cursor::new(start).chain(input));
+    let mut input = if armor {
+        Input::Armored(ArmorReader::new(input).map_err(io_error)?)
+    } else {
+        Input::Binary(input)
+    };
+
+    let mut header = [0u8; HEADER_SIZE];
+    input
+        .read_exact(&mut header)
+        .map_err(|_| CryptoError::ParseError("File is too short".to_string()))?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(CryptoError::ParseError(
+            "Not a passphrase encrypted file".to_string(),
+        ));
+    }
+    if header[MAGIC.len()] != VERSION {
+        return Err(CryptoError::ParseError(format!(
+            "Unsupported version {}",
+            header[MAGIC.len()]
+        )));
+    }
+    let params = Params {
+        memory_kib: be32(&header[PARAMS_OFFSET..]),
+        iterations: be32(&header[PARAMS_OFFSET + 4..]),
+        parallelism: be32(&header[PARAMS_OFFSET + 8..]),
+        armor,
+    };
+    let (cipher, commitment) = derive(passphrase, &params, &header[..COMMITMENT_OFFSET])?;
+    if commitment.ct_eq(&header[COMMITMENT_OFFSET..]).unwrap_u8() != 1 {
+        return Err(CryptoError::GeneralError(
+            "The passphrase is wrong or the file is corrupted".to_string(),
+        ));
+    }
+    let mut stream = DecryptorStream::new(&cipher, &header[..], input)
+        .map_err(|_| CryptoError::ParseError("File is truncated".to_string()))?;
+    io::copy(&mut stream, &mut plaintext).map_err(io_error)?;
+    plaintext.flush().map_err(io_error)?;
+    Ok(plaintext)
+}
+
+/// The content cipher and the commitment for the header up to the commitment
+fn derive(
+    passphrase: &[u8],
+    params: &Params,
+    header: &[u8],
+) -> Result<(XChaCha20Poly1305, [u8; COMMITMENT_SIZE]), CryptoError> {
+    if params.memory_kib > MAX_MEMORY_KIB
+        || params.iterations > MAX_ITERATIONS
+        || params.parallelism > MAX_PARALLELISM
+    {
+        return Err(CryptoError::GeneralError(format!(
+            "Argon2id parameters {:?} exceed the limits",
+            params
+        )));
+    }
+    let argon2_params = argon2::Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(KEY_SIZE),
+    )
+    .map_err(|e| CryptoError::GeneralError(e.to_string()))?;
+    let mut key = [0u8; KEY_SIZE];
+    Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2_params,
+    )
+    .hash_password_into(
+        passphrase,
+        &header[SALT_OFFSET..COMMITMENT_OFFSET],
+        &mut key,
+    )
+    .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    let committing = KeyCommitting::<XChaCha20Poly1305>::new(GenericArray::from_slice(&key));
+    key.zeroize();
+    committing
+        .derive(header)
+        .map_err(|_| CryptoError::GeneralError("Failed to derive the key".to_string()))
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn io_error(e: io::Error) -> CryptoError {
+    CryptoError::GeneralError(format!("I/O error: {}", e))
+}
+
+fn invalid_armor(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+enum Output<W: Write> {
+    Binary(W),
+    Armored(ArmorWriter<W>),
+}
+
+impl<W: Write> Output<W> {
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Output::Binary(mut writer) => writer.flush().map(|_| writer),
+            Output::Armored(writer) => writer.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Binary(writer) => writer.write(buf),
+            Output::Armored(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Binary(writer) => writer.flush(),
+            Output::Armored(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Base64 encodes everything written to it in lines of 64 characters
+struct ArmorWriter<W: Write> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> ArmorWriter<W> {
+    fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(ARMOR_BEGIN.as_bytes())?;
+        writer.write_all(b"\n")?;
+        Ok(ArmorWriter {
+            writer,
+            buffer: Vec::with_capacity(ARMOR_LINE_BYTES),
+        })
+    }
+
+    /// Write the last, possibly short, line and the END line
+    fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            self.write_line()?;
+        }
+        self.writer.write_all(ARMOR_END.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let n = self.buffer.len().min(ARMOR_LINE_BYTES);
+        let mut line = base64::encode(&self.buffer[..n]);
+        line.push('\n');
+        self.buffer.drain(..n);
+        self.writer.write_all(line.as_bytes())
+    }
+}
+
+impl<W: Write> Write for ArmorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while self.buffer.len() >= ARMOR_LINE_BYTES {
+            self.write_line()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+enum Input<R: BufRead> {
+    Binary(R),
+    Armored(ArmorReader<R>),
+}
+
+impl<R: BufRead> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Binary(reader) => reader.read(buf),
+            Input::Armored(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Decodes the lines of armor written by `ArmorWriter`. Fails if the END line is missing
+struct ArmorReader<R: BufRead> {
+    reader: R,
+    decoded: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ArmorReader<R> {
+    fn new(mut reader: R) -> io::Result<Self> {
+        if next_line(&mut reader)? != ARMOR_BEGIN {
+            return Err(invalid_armor("Armor doesn't start with the BEGIN line"));
+        }
+        Ok(ArmorReader {
+            reader,
+            decoded: Vec::new(),
+            position: 0,
+            done: false,
+        })
+    }
+}
+
+impl<R: BufRead> Read for ArmorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            let line = next_line(&mut self.reader)?;
+            if line == ARMOR_END {
+                self.done = true;
+            } else {
+                self.decoded = base64::decode(&line).map_err(|_| invalid_armor("Invalid armor"))?;
+                self.position = 0;
+            }
+        }
+        let n = buf.len().min(self.decoded.len() - self.position);
+        buf[..n].copy_from_slice(&self.decoded[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// The next line without its line ending
+fn next_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    reader
+        .take(ARMOR_MAX_LINE as u64)
+        .read_line(&mut line)
+        .map_err(|_| invalid_armor("Invalid armor"))?;
+    if line.is_empty() {
+        return Err(invalid_armor("Armor is truncated"));
+    }
+    if !line.ends_with('\n') && line.len() == ARMOR_MAX_LINE {
+        return Err(invalid_armor("Armor line is too long"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::symm::stream::CHUNK_SIZE;
+    use super::*;
+
+    // Cheap parameters so the tests run quickly
+    const FAST: Params = Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+        armor: false,
+    };
+
+    fn round_trip(params: Params, plaintext: &[u8]) -> Vec<u8> {
+        let encrypted = encrypt(b"correct horse", params, plaintext, Vec::new()).unwrap();
+        assert_eq!(
+            decrypt(b"correct horse", encrypted.as_slice(), Vec::new()).unwrap(),
+            plaintext
+        );
+        encrypted
+    }
+
+    #[test]
+    fn binary_and_armored() {
+        for size in &[0, 1, 47, 48, CHUNK_SIZE, 2 * CHUNK_SIZE + 3] {
+            let plaintext = (0..*size).map(|i| i as u8).collect::<Vec<_>>();
+            let binary = round_trip(FAST, &plaintext);
+            assert_eq!(&binary[..MAGIC.len()], MAGIC);
+
+            let armored = round_trip(
+                Params {
+                    armor: true,
+                    ..FAST
+                },
+                &plaintext,
+            );
+            let text = String::from_utf8(armored).unwrap();
+            let lines = text.lines().collect::<Vec<_>>();
+            assert_eq!(lines[0], ARMOR_BEGIN);
+            assert_eq!(lines[lines.len() - 1], ARMOR_END);
+            assert!(lines.iter().all(|l| l.len() <= 64));
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase_and_tampering() {
+        let encrypted = encrypt(b"correct horse", FAST, &b"secret"[..], Vec::new()).unwrap();
+        match decrypt(b"battery staple", encrypted.as_slice(), Vec::new()) {
+            Err(CryptoError::GeneralError(m)) => {
+                assert_eq!(m, "The passphrase is wrong or the file is corrupted")
+            }
+            r => panic!("expected a wrong passphrase, found {:?}", r.map(|_| ())),
+        }
+
+        for i in 0..encrypted.len() {
+            let mut tampered = encrypted.clone();
+            tampered[i] ^= 1;
+            assert!(decrypt(b"correct horse", tampered.as_slice(), Vec::new()).is_err());
+        }
+        for length in 0..encrypted.len() {
+            assert!(decrypt(b"correct horse", &encrypted[..length], Vec::new()).is_err());
+        }
+
+        // Armor without its END line is truncated
+        let armored = encrypt(
+            b"correct horse",
+            Params {
+                armor: true,
+                ..FAST
+            },
+            &b"secret"[..],
+            Vec::new(),
+        )
+        .unwrap();
+        let end = armored.len() - ARMOR_END.len() - 1;
+        assert!(decrypt(b"correct horse", &armored[..end], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn parameter_limits() {
+        let greedy = Params {
+            memory_kib: MAX_MEMORY_KIB + 1,
+            ..FAST
+        };
+        assert!(encrypt(b"passphrase", greedy, &b""[..], Vec::new()).is_err());
+
+        // A header asking for too much memory is refused before deriving the key
+        let mut encrypted = encrypt(b"passphrase", FAST, &b""[..], Vec::new()).unwrap();
+        encrypted[PARAMS_OFFSET..PARAMS_OFFSET + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        match decrypt(b"passphrase", encrypted.as_slice(), Vec::new()) {
+            Err(CryptoError::GeneralError(m)) => assert!(m.contains("exceed the limits")),
+            r => panic!(
+                "expected the limits to be enforced, found {:?}",
+                r.map(|_| ())
+            ),
+        }
+    }
+}
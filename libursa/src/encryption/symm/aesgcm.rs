@@ -1,4 +1,4 @@
-use super::Encryptor;
+use super::{seal_batch, stream::StreamEncryptor, Encryptor};
 use aead::{
     generic_array::{
         typenum::{Unsigned, U0, U12, U16, U32},
@@ -20,8 +20,18 @@ macro_rules! aes_gcm_impl {
 
         impl Encryptor for $name {
             type MinSize = U32;
+
+            fn encrypt_batch<M: AsRef<[u8]> + Sync>(
+                &self,
+                records: &[(M, M)],
+            ) -> Result<Vec<Vec<u8>>, Error> {
+                let aes = $algoname::new(&self.key);
+                seal_batch(records, |nonce, payload| aes.encrypt(nonce, payload))
+            }
         }
 
+        impl StreamEncryptor for $name {}
+
         impl NewAead for $name {
             type KeySize = $keysize;
 
@@ -52,7 +62,7 @@ macro_rules! aes_gcm_impl {
             ) -> Result<Vec<u8>, Error> {
                 let payload = ciphertext.into();
 
-                if payload.msg.len() < Self::TagSize::to_usize() + Self::NonceSize::to_usize() {
+                if payload.msg.len() < Self::TagSize::to_usize() {
                     return Err(Error);
                 }
 
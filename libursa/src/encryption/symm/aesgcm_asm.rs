@@ -1,4 +1,4 @@
-use super::Encryptor;
+use super::{stream::StreamEncryptor, Encryptor};
 use aead::{
     generic_array::{
         typenum::{Unsigned, U0, U12, U16, U32},
@@ -24,6 +24,8 @@ macro_rules! aes_gcm_impl {
             type MinSize = U32;
         }
 
+        impl StreamEncryptor for $name {}
+
         impl NewAead for $name {
             type KeySize = $keysize;
 
@@ -65,7 +67,7 @@ macro_rules! aes_gcm_impl {
             ) -> Result<Vec<u8>, Error> {
                 let payload = ciphertext.into();
 
-                if payload.msg.len() < Self::TagSize::to_usize() + Self::NonceSize::to_usize() {
+                if payload.msg.len() < Self::TagSize::to_usize() {
                     return Err(Error);
                 }
 
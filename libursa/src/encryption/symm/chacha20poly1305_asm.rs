@@ -1,4 +1,4 @@
-use super::Encryptor;
+use super::{stream::StreamEncryptor, Encryptor};
 use aead::{
     generic_array::{
         typenum::{Unsigned, U0, U12, U16, U32, U36},
@@ -28,6 +28,8 @@ impl Encryptor for ChaCha20Poly1305 {
     type MinSize = U36;
 }
 
+impl StreamEncryptor for ChaCha20Poly1305 {}
+
 impl NewAead for ChaCha20Poly1305 {
     type KeySize = U32;
 
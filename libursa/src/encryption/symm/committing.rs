@@ -0,0 +1,210 @@
+//! Key-committing AEAD.
+//!
+//! AES-GCM, ChaCha20-Poly1305 and their relatives don't commit to their key: anyone who
+//! picks the keys can make one ciphertext that decrypts without error under several
+//! of them. When the key comes from something the attacker can guess, like a password,
+//! or when the same ciphertext goes to many recipients who each unwrap their own key,
+//! that enables partitioning oracle attacks, which test many guesses with a single
+//! decryption, and invisible salamanders, where recipients read different messages.
+//!
+//! `KeyCommitting` wraps any `Encryptor` so it does commit. The key and the nonce are
+//! run through HKDF-SHA256, salted with the nonce, to derive both the key of the wrapped
+//! cipher and a 32 byte commitment, which is prepended to the ciphertext and checked
+//! before decrypting. Finding two keys with the same commitment means finding a
+//! collision in HMAC-SHA256.
+//!
+//! The ciphertexts aren't compatible with those of the wrapped cipher.
+
+use super::Encryptor;
+use aead::{
+    generic_array::{typenum::Unsigned, GenericArray},
+    Aead, Error, NewAead, Payload,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// The size of the commitment at the start of every ciphertext
+pub const COMMITMENT_SIZE: usize = 32;
+
+const KEY_INFO: &[u8] = b"ursa key committing aead key";
+const COMMITMENT_INFO: &[u8] = b"ursa key committing aead commitment";
+
+/// An AEAD whose ciphertexts only decrypt under the key they were made with
+pub trait CommittingAead: Encryptor {
+    /// The commitment to the key for `nonce`, which starts every ciphertext made with it
+    fn commitment(&self, nonce: &GenericArray<u8, Self::NonceSize>) -> Result<Vec<u8>, Error>;
+}
+
+/// `E` with a commitment to the key
+pub struct KeyCommitting<E: Encryptor> {
+    key: GenericArray<u8, E::KeySize>,
+}
+
+impl<E: Encryptor> KeyCommitting<E> {
+    /// The wrapped cipher and the commitment for `salt`, for formats that check the
+    /// commitment once and then seal many messages, like the chunks of a stream, with
+    /// the wrapped cipher. `encrypt` and `decrypt` use the nonce as the salt
+    pub fn derive(&self, salt: &[u8]) -> Result<(E, [u8; COMMITMENT_SIZE]), Error> {
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), self.key.as_slice());
+        let mut key = GenericArray::<u8, E::KeySize>::default();
+        let mut commitment = [0u8; COMMITMENT_SIZE];
+        hkdf.expand(KEY_INFO, key.as_mut_slice())
+            .and_then(|_| hkdf.expand(COMMITMENT_INFO, &mut commitment))
+            .map_err(|_| Error)?;
+        let cipher = E::new(&key);
+        key.as_mut_slice().zeroize();
+        Ok((cipher, commitment))
+    }
+}
+
+impl<E: Encryptor> Encryptor for KeyCommitting<E> {
+    type MinSize = E::MinSize;
+}
+
+impl<E: Encryptor> CommittingAead for KeyCommitting<E> {
+    fn commitment(&self, nonce: &GenericArray<u8, Self::NonceSize>) -> Result<Vec<u8>, Error> {
+        Ok(self.derive(nonce.as_slice())?.1.to_vec())
+    }
+}
+
+impl<E: Encryptor> NewAead for KeyCommitting<E> {
+    type KeySize = E::KeySize;
+
+    fn new(key: &GenericArray<u8, Self::KeySize>) -> Self {
+        Self { key: key.clone() }
+    }
+}
+
+impl<E: Encryptor> Aead for KeyCommitting<E> {
+    type NonceSize = E::NonceSize;
+    type TagSize = E::TagSize;
+    type CiphertextOverhead = E::CiphertextOverhead;
+
+    fn encrypt<'msg, 'aad>(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        plaintext: impl Into<Payload<'msg, 'aad>>,
+    ) -> Result<Vec<u8>, Error> {
+        let (cipher, commitment) = self.derive(nonce.as_slice())?;
+        let mut ciphertext = commitment.to_vec();
+        ciphertext.extend_from_slice(&cipher.encrypt(nonce, plaintext)?);
+        Ok(ciphertext)
+    }
+
+    fn decrypt<'msg, 'aad>(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        ciphertext: impl Into<Payload<'msg, 'aad>>,
+    ) -> Result<Vec<u8>, Error> {
+        let payload = ciphertext.into();
+        if payload.msg.len() < COMMITMENT_SIZE + Self::TagSize::to_usize() {
+            return Err(Error);
+        }
+        let (cipher, commitment) = self.derive(nonce.as_slice())?;
+        let (expected, msg) = payload.msg.split_at(COMMITMENT_SIZE);
+        if commitment.ct_eq(expected).unwrap_u8() != 1 {
+            return Err(Error);
+        }
+        cipher.decrypt(
+            nonce,
+            Payload {
+                msg,
+                aad: payload.aad,
+            },
+        )
+    }
+}
+
+impl<E: Encryptor> Default for KeyCommitting<E> {
+    fn default() -> Self {
+        Self::new(&Self::key_gen().unwrap())
+    }
+}
+
+impl<E: Encryptor> ::std::fmt::Debug for KeyCommitting<E> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("KeyCommitting(..)")
+    }
+}
+
+impl<E: Encryptor> Zeroize for KeyCommitting<E> {
+    fn zeroize(&mut self) {
+        self.key.as_mut_slice().zeroize();
+    }
+}
+
+impl<E: Encryptor> Drop for KeyCommitting<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::chacha20poly1305::ChaCha20Poly1305;
+    use super::super::SymmetricEncryptor;
+    use super::*;
+
+    type Committing = KeyCommitting<ChaCha20Poly1305>;
+
+    #[test]
+    fn round_trip() {
+        let encryptor = SymmetricEncryptor::<Committing>::default();
+        let ciphertext = encryptor
+            .encrypt_easy(&b"aad"[..], &b"message"[..])
+            .unwrap();
+        // Nonce, commitment, ciphertext and tag
+        assert_eq!(ciphertext.len(), 12 + COMMITMENT_SIZE + 7 + 16);
+        assert_eq!(
+            encryptor
+                .decrypt_easy(&b"aad"[..], &ciphertext[..])
+                .unwrap(),
+            b"message"
+        );
+        assert!(encryptor
+            .decrypt_easy(&b"other"[..], &ciphertext[..])
+            .is_err());
+
+        let mut tampered = ciphertext.clone();
+        tampered[12] ^= 1;
+        assert!(encryptor.decrypt_easy(&b"aad"[..], &tampered[..]).is_err());
+        assert!(encryptor
+            .decrypt_easy(&b"aad"[..], &ciphertext[..12 + COMMITMENT_SIZE])
+            .is_err());
+    }
+
+    #[test]
+    fn commits_to_the_key() {
+        let cipher = Committing::default();
+        let other = Committing::default();
+        let nonce = Committing::nonce_gen().unwrap();
+        let commitment = cipher.commitment(&nonce).unwrap();
+        assert_eq!(commitment, cipher.commitment(&nonce).unwrap());
+        assert_ne!(commitment, other.commitment(&nonce).unwrap());
+        assert_ne!(
+            commitment,
+            cipher
+                .commitment(&Committing::nonce_gen().unwrap())
+                .unwrap()
+        );
+
+        let ciphertext = cipher.encrypt(&nonce, &b"message"[..]).unwrap();
+        assert_eq!(&ciphertext[..COMMITMENT_SIZE], &commitment[..]);
+        assert!(other.decrypt(&nonce, ciphertext.as_slice()).is_err());
+
+        // A ciphertext of the wrapped cipher under the derived key is rejected
+        // without the commitment of the right key
+        let hkdf = Hkdf::<Sha256>::new(Some(nonce.as_slice()), cipher.key.as_slice());
+        let mut key = [0u8; 32];
+        hkdf.expand(KEY_INFO, &mut key).unwrap();
+        let inner = ChaCha20Poly1305::new(GenericArray::from_slice(&key))
+            .encrypt(&nonce, &b"message"[..])
+            .unwrap();
+        assert_eq!(&ciphertext[COMMITMENT_SIZE..], &inner[..]);
+        let mut forged = other.commitment(&nonce).unwrap();
+        forged.extend_from_slice(&inner);
+        assert!(cipher.decrypt(&nonce, forged.as_slice()).is_err());
+    }
+}
@@ -14,6 +14,18 @@
 //! The `encrypt_easy` prepends the nonce to the front of the ciphertext and `decrypt_easy` expects
 //! the nonce to be prepended to the front of the ciphertext.
 //!
+//! `encrypt_batch` seals many `(aad, plaintext)` records at once, such as rows of a database table,
+//! each with its own nonce as `encrypt_easy` would. AES-GCM expands its key once per batch instead of
+//! once per record, and with the `parallel` feature the records are spread over rayon's thread pool.
+//! All records count towards the number of messages a single key can safely encrypt with random nonces.
+//!
+//! `encrypt_stream` and `decrypt_stream` encrypt data too large to hold in memory, such as
+//! multi-gigabyte files, in chunks through `Write` and `Read` wrappers. They're available for
+//! AES-GCM, ChaCha20-Poly1305 and XCHACHA20-POLY1305, see the `stream` module.
+//!
+//! `committing::KeyCommitting` wraps any of them so a ciphertext only decrypts under the key it
+//! was made with, which matters when keys come from passwords or are unwrapped by many recipients.
+//!
 //! More advanced users may use `encrypt` and `decrypt` directly. These two methods require the
 //! caller to supply a nonce with sufficient entropy and should never be reused when encrypting
 //! with the same `key`.
@@ -34,11 +46,14 @@
 //! More ciphers will added as needed like AES-GCM-SIV or [XCHACHA20POLY1305-SIV](https://tools.ietf.org/id/draft-madden-generalised-siv-00.html)
 //! where using poly1305 instead of HMAC might be appropriate.
 
-use super::random_bytes;
+use self::stream::{DecryptorStream, EncryptorStream, StreamEncryptor};
+use super::{random_bytes, random_vec};
 use aead::{
     generic_array::{typenum::Unsigned, ArrayLength, GenericArray},
     Aead, Error, NewAead, Payload,
 };
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
@@ -173,6 +188,18 @@ impl<E: Encryptor> SymmetricEncryptor<E> {
         self.encryptor.encrypt(nonce, payload)
     }
 
+    // Encrypt each `(aad, plaintext)` record as `encrypt_easy` does. The result is the ciphertexts
+    // in the same order, each with its nonce prepended
+    pub fn encrypt_batch<A: AsRef<[u8]> + Sync>(
+        &self,
+        records: &[(A, A)],
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        E: Sync,
+    {
+        self.encryptor.encrypt_batch(records)
+    }
+
     // Decrypt `ciphertext` using integrity protected `aad`. The result is the plaintext if successful
     // or an error if the `ciphetext` cannot be decrypted due to tampering, an incorrect `aad` value,
     // or incorrect key.
@@ -221,6 +248,28 @@ impl<E: Encryptor> SymmetricEncryptor<E> {
     }
 }
 
+impl<E: StreamEncryptor> SymmetricEncryptor<E> {
+    // Start encrypting a stream to `ciphertext` in chunks, integrity protecting `aad` with each.
+    // `EncryptorStream::finish` must be called after the last write
+    pub fn encrypt_stream<A: AsRef<[u8]>, O: Write>(
+        &self,
+        aad: A,
+        ciphertext: O,
+    ) -> Result<EncryptorStream<'_, E, O>, Error> {
+        EncryptorStream::new(&self.encryptor, aad, ciphertext)
+    }
+
+    // Start decrypting a stream from `ciphertext` made by `encrypt_stream` with the same `aad`.
+    // Reading fails if the stream was modified or truncated
+    pub fn decrypt_stream<A: AsRef<[u8]>, I: Read>(
+        &self,
+        aad: A,
+        ciphertext: I,
+    ) -> Result<DecryptorStream<'_, E, I>, Error> {
+        DecryptorStream::new(&self.encryptor, aad, ciphertext)
+    }
+}
+
 impl<E: Encryptor + Default> Default for SymmetricEncryptor<E> {
     fn default() -> Self {
         SymmetricEncryptor {
@@ -246,6 +295,18 @@ pub trait Encryptor: Aead + NewAead {
         Ok(result)
     }
 
+    /// Encrypt each `(aad, plaintext)` record as `encrypt_easy` does, in the same order.
+    /// Ciphers with a key schedule override this to expand the key once for the batch.
+    fn encrypt_batch<M: AsRef<[u8]> + Sync>(
+        &self,
+        records: &[(M, M)],
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        Self: Sync,
+    {
+        seal_batch(records, |nonce, payload| self.encrypt(nonce, payload))
+    }
+
     fn decrypt_easy<M: AsRef<[u8]>>(&self, aad: M, ciphertext: M) -> Result<Vec<u8>, Error> {
         let ciphertext = ciphertext.as_ref();
         if ciphertext.len() < Self::MinSize::to_usize() {
@@ -457,6 +518,40 @@ impl std::fmt::Display for EncryptorType {
     }
 }
 
+/// Seal each record with `seal` under a random nonce, prepended to its ciphertext.
+/// The nonces for the whole batch are read from the operating system at once.
+fn seal_batch<N, M, F>(records: &[(M, M)], seal: F) -> Result<Vec<Vec<u8>>, Error>
+where
+    N: ArrayLength<u8>,
+    M: AsRef<[u8]> + Sync,
+    F: Fn(&GenericArray<u8, N>, Payload) -> Result<Vec<u8>, Error> + Sync,
+{
+    let nonces = random_vec(records.len() * N::to_usize())?;
+    let seal_one = |((aad, plaintext), nonce): (&(M, M), &[u8])| {
+        let payload = Payload {
+            msg: plaintext.as_ref(),
+            aad: aad.as_ref(),
+        };
+        let ciphertext = seal(GenericArray::from_slice(nonce), payload)?;
+        let mut result = nonce.to_vec();
+        result.extend_from_slice(ciphertext.as_slice());
+        Ok(result)
+    };
+    #[cfg(feature = "parallel")]
+    let results = records
+        .par_iter()
+        .zip(nonces.par_chunks(N::to_usize()))
+        .map(seal_one)
+        .collect();
+    #[cfg(not(feature = "parallel"))]
+    let results = records
+        .iter()
+        .zip(nonces.chunks(N::to_usize()))
+        .map(seal_one)
+        .collect();
+    results
+}
+
 fn read_buffer<I: Read>(buffer: &mut I) -> Result<Vec<u8>, Error> {
     let mut v = Vec::new();
     let bytes_read = buffer.read_to_end(&mut v).map_err(|_| Error)?;
@@ -534,6 +629,26 @@ macro_rules! tests_impl {
             assert_eq!(dummytext.to_vec(), plaintext);
         }
 
+        #[test]
+        fn encrypt_batch_works() {
+            let aes = $name::default();
+            let records = (0..64u8)
+                .map(|i| (vec![i], vec![i; 16 + i as usize]))
+                .collect::<Vec<_>>();
+            let ciphertexts = aes.encrypt_batch(&records).unwrap();
+            assert_eq!(ciphertexts.len(), records.len());
+            for ((aad, message), ciphertext) in records.iter().zip(ciphertexts.iter()) {
+                assert_eq!(&aes.decrypt_easy(aad, ciphertext).unwrap(), message);
+            }
+            // Every record gets its own nonce
+            let nonce_size = $name::nonce_gen().unwrap().len();
+            assert_ne!(ciphertexts[1][..nonce_size], ciphertexts[2][..nonce_size]);
+            assert!(aes.decrypt_easy(&records[1].0, &ciphertexts[2]).is_err());
+
+            let empty: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+            assert!(aes.encrypt_batch(&empty).unwrap().is_empty());
+        }
+
         #[cfg(feature = "serde")]
         #[test]
         fn serialization() {
@@ -592,6 +707,11 @@ pub mod xchacha20poly1305;
 #[path = "xchacha20poly1305.rs"]
 pub mod xchacha20poly1305;
 
+#[cfg(feature = "committing_aead")]
+pub mod committing;
+
+pub mod stream;
+
 pub mod prelude {
     #[cfg(any(feature = "aescbc", feature = "aescbc_native"))]
     pub use super::aescbc::{Aes128CbcHmac256, Aes256CbcHmac512};
@@ -599,6 +719,9 @@ pub mod prelude {
     pub use super::aesgcm::{Aes128Gcm, Aes256Gcm};
     #[cfg(any(feature = "chacha20poly1305", feature = "chacha20poly1305_native"))]
     pub use super::chacha20poly1305::ChaCha20Poly1305;
+    #[cfg(feature = "committing_aead")]
+    pub use super::committing::{CommittingAead, KeyCommitting};
+    pub use super::stream::{DecryptorStream, EncryptorStream, StreamEncryptor};
     #[cfg(any(feature = "chacha20poly1305", feature = "chacha20poly1305_native"))]
     pub use super::xchacha20poly1305::XChaCha20Poly1305;
     pub use super::{DynEncryptor, Encryptor, EncryptorType, SymmetricEncryptor};
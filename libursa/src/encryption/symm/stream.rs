@@ -0,0 +1,348 @@
+//! Chunked encryption of streams too large to hold in memory.
+//!
+//! `EncryptorStream` wraps a `Write` and `DecryptorStream` wraps a `Read`. The plaintext is
+//! split into chunks of `CHUNK_SIZE` bytes, each sealed on its own with the STREAM
+//! construction of [Hoang, Reyhanitabar, Rogaway and Vizár](https://eprint.iacr.org/2015/189.pdf).
+//! The nonce of each chunk is a random prefix chosen for the stream, a 32 bit big endian
+//! chunk counter and a byte that is 1 for the last chunk and 0 otherwise. The stream is
+//! the prefix followed by the sealed chunks.
+//!
+//! The counter stops chunks from being reordered, dropped or copied between positions,
+//! and the last chunk flag stops the stream from being truncated: a stream that ends without
+//! a last chunk is rejected. The last chunk is the only one shorter than `CHUNK_SIZE`, and may
+//! be empty. `EncryptorStream::finish` must be called to seal it.
+//!
+//! A chunk is only returned once it has been authenticated, but the stream as a whole is only
+//! authentic once `DecryptorStream` has returned the end of the stream. Anything read before
+//! an error must be discarded.
+//!
+//! The random prefix is 19 bytes with XChaCha20-Poly1305 but only 7 bytes with AES-GCM and
+//! ChaCha20-Poly1305, so a key used with those should seal far fewer than 2^28 streams.
+
+use super::super::random_vec;
+use super::Encryptor;
+use aead::{
+    generic_array::{typenum::Unsigned, GenericArray},
+    Aead, Error, Payload,
+};
+use std::io::{self, Read, Write};
+
+/// The size of each plaintext chunk but the last
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The counter and last chunk flag at the end of the nonce
+const NONCE_SUFFIX_SIZE: usize = 5;
+
+/// AEADs whose ciphertext is the plaintext followed by a tag of a fixed size,
+/// so every sealed chunk but the last has the same length
+pub trait StreamEncryptor: Encryptor {}
+
+/// The nonce sequence of one stream
+struct NonceSequence {
+    prefix: Vec<u8>,
+    counter: u32,
+    done: bool,
+}
+
+impl NonceSequence {
+    fn prefix_size<E: StreamEncryptor>() -> usize {
+        <E as Aead>::NonceSize::to_usize() - NONCE_SUFFIX_SIZE
+    }
+
+    fn new(prefix: Vec<u8>) -> Self {
+        NonceSequence {
+            prefix,
+            counter: 0,
+            done: false,
+        }
+    }
+
+    /// The nonce of the next chunk. Fails once the last chunk has been
+    /// sealed or the counter is exhausted
+    fn next<E: StreamEncryptor>(
+        &mut self,
+        last: bool,
+    ) -> Result<GenericArray<u8, <E as Aead>::NonceSize>, Error> {
+        if self.done {
+            return Err(Error);
+        }
+        let mut nonce = GenericArray::default();
+        let prefix_size = self.prefix.len();
+        nonce[..prefix_size].copy_from_slice(&self.prefix);
+        nonce[prefix_size..prefix_size + 4].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[prefix_size + 4] = last as u8;
+        if last {
+            self.done = true;
+        } else {
+            self.counter = self.counter.checked_add(1).ok_or(Error)?;
+        }
+        Ok(nonce)
+    }
+}
+
+/// Encrypts everything written to it and writes the sealed stream to `W`
+pub struct EncryptorStream<'a, E: 'a + StreamEncryptor, W: Write> {
+    encryptor: &'a E,
+    aad: Vec<u8>,
+    nonces: NonceSequence,
+    buffer: Vec<u8>,
+    writer: W,
+}
+
+impl<'a, E: StreamEncryptor, W: Write> EncryptorStream<'a, E, W> {
+    /// Start a stream under a new random prefix and write the prefix to `writer`.
+    /// `aad` is integrity protected with every chunk
+    pub fn new<A: AsRef<[u8]>>(encryptor: &'a E, aad: A, mut writer: W) -> Result<Self, Error> {
+        let prefix = random_vec(NonceSequence::prefix_size::<E>())?;
+        writer.write_all(&prefix).map_err(|_| Error)?;
+        Ok(EncryptorStream {
+            encryptor,
+            aad: aad.as_ref().to_vec(),
+            nonces: NonceSequence::new(prefix),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            writer,
+        })
+    }
+
+    /// Seal the last chunk and return the writer. Without this the stream is truncated
+    /// and can't be decrypted
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.seal_chunk(true)?;
+        self.writer.flush().map_err(|_| Error)?;
+        Ok(self.writer)
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> Result<(), Error> {
+        let nonce = self.nonces.next::<E>(last)?;
+        let ciphertext = self.encryptor.encrypt(
+            &nonce,
+            Payload {
+                msg: self.buffer.as_slice(),
+                aad: self.aad.as_slice(),
+            },
+        )?;
+        self.buffer.clear();
+        self.writer
+            .write_all(ciphertext.as_slice())
+            .map_err(|_| Error)
+    }
+}
+
+impl<'a, E: StreamEncryptor, W: Write> Write for EncryptorStream<'a, E, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.seal_chunk(false)
+                .map_err(|_| io::Error::other("Chunk could not be encrypted"))?;
+        }
+        Ok(n)
+    }
+
+    /// Flushes the writer. The buffered part of a chunk is only written once it is full
+    /// or the stream is finished
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads a stream sealed by `EncryptorStream` from `R` and decrypts it
+pub struct DecryptorStream<'a, E: 'a + StreamEncryptor, R: Read> {
+    encryptor: &'a E,
+    aad: Vec<u8>,
+    nonces: NonceSequence,
+    plaintext: Vec<u8>,
+    position: usize,
+    reader: R,
+}
+
+impl<'a, E: StreamEncryptor, R: Read> DecryptorStream<'a, E, R> {
+    /// Read the prefix of a stream from `reader`.
+    /// `aad` must be the value the stream was encrypted with
+    pub fn new<A: AsRef<[u8]>>(encryptor: &'a E, aad: A, mut reader: R) -> Result<Self, Error> {
+        let mut prefix = vec![0u8; NonceSequence::prefix_size::<E>()];
+        reader.read_exact(&mut prefix).map_err(|_| Error)?;
+        Ok(DecryptorStream {
+            encryptor,
+            aad: aad.as_ref().to_vec(),
+            nonces: NonceSequence::new(prefix),
+            plaintext: Vec::new(),
+            position: 0,
+            reader,
+        })
+    }
+
+    /// Read and open the next chunk. A chunk shorter than a full one is the last
+    fn open_chunk(&mut self) -> io::Result<()> {
+        let chunk_size = CHUNK_SIZE + <E as Aead>::TagSize::to_usize();
+        let mut ciphertext = vec![0u8; chunk_size];
+        let mut read = 0;
+        while read < chunk_size {
+            match self.reader.read(&mut ciphertext[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        ciphertext.truncate(read);
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Stream is corrupted or truncated",
+            )
+        };
+        let nonce = self
+            .nonces
+            .next::<E>(read < chunk_size)
+            .map_err(|_| invalid())?;
+        self.plaintext = self
+            .encryptor
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: ciphertext.as_slice(),
+                    aad: self.aad.as_slice(),
+                },
+            )
+            .map_err(|_| invalid())?;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<'a, E: StreamEncryptor, R: Read> Read for DecryptorStream<'a, E, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.nonces.done || buf.is_empty() {
+                return Ok(0);
+            }
+            self.open_chunk()?;
+        }
+        let n = buf.len().min(self.plaintext.len() - self.position);
+        buf[..n].copy_from_slice(&self.plaintext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use encryption::symm::prelude::*;
+
+    fn encrypt<E: StreamEncryptor>(encryptor: &E, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut stream = EncryptorStream::new(encryptor, aad, Vec::new()).unwrap();
+        // Odd sized writes so chunks are filled across calls
+        for part in plaintext.chunks(10_007) {
+            stream.write_all(part).unwrap();
+        }
+        stream.finish().unwrap()
+    }
+
+    fn decrypt<E: StreamEncryptor>(
+        encryptor: &E,
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let mut stream = DecryptorStream::new(encryptor, aad, ciphertext)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Stream has no prefix"))?;
+        let mut plaintext = Vec::new();
+        stream.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    fn round_trip<E: StreamEncryptor + Default>() {
+        let encryptor = E::default();
+        let aad = b"stream round trip";
+        let sizes = [
+            0,
+            1,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            CHUNK_SIZE + 1,
+            3 * CHUNK_SIZE + 17,
+        ];
+        for size in sizes.iter() {
+            let plaintext = (0..*size).map(|i| i as u8).collect::<Vec<u8>>();
+            let ciphertext = encrypt(&encryptor, aad, &plaintext);
+            let chunks = size / CHUNK_SIZE + 1;
+            assert_eq!(
+                ciphertext.len(),
+                NonceSequence::prefix_size::<E>()
+                    + size
+                    + chunks * <E as Aead>::TagSize::to_usize()
+            );
+            assert_eq!(decrypt(&encryptor, aad, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    fn tampering<E: StreamEncryptor + Default>() {
+        let encryptor = E::default();
+        let aad = b"stream tampering";
+        let plaintext = vec![7u8; 2 * CHUNK_SIZE + 100];
+        let ciphertext = encrypt(&encryptor, aad, &plaintext);
+        let prefix = NonceSequence::prefix_size::<E>();
+        let chunk = CHUNK_SIZE + <E as Aead>::TagSize::to_usize();
+
+        // Truncated at a chunk boundary, mid chunk and inside the prefix
+        for len in [prefix + chunk, prefix + 2 * chunk, prefix + chunk + 10, 3].iter() {
+            assert!(decrypt(&encryptor, aad, &ciphertext[..*len]).is_err());
+        }
+        // Chunks swapped
+        let mut swapped = ciphertext[..prefix].to_vec();
+        swapped.extend_from_slice(&ciphertext[prefix + chunk..prefix + 2 * chunk]);
+        swapped.extend_from_slice(&ciphertext[prefix..prefix + chunk]);
+        swapped.extend_from_slice(&ciphertext[prefix + 2 * chunk..]);
+        assert!(decrypt(&encryptor, aad, &swapped).is_err());
+        // Data appended after the last chunk
+        let mut extended = ciphertext.clone();
+        extended.extend_from_slice(&[0u8; 32]);
+        assert!(decrypt(&encryptor, aad, &extended).is_err());
+        // Flipped bits in the prefix and a chunk
+        for i in [0, prefix + chunk + 5].iter() {
+            let mut corrupted = ciphertext.clone();
+            corrupted[*i] ^= 1;
+            assert!(decrypt(&encryptor, aad, &corrupted).is_err());
+        }
+        // Another aad or key
+        assert!(decrypt(&encryptor, b"another aad", &ciphertext).is_err());
+        assert!(decrypt(&E::default(), aad, &ciphertext).is_err());
+        // Each stream gets its own prefix
+        assert_ne!(
+            encrypt(&encryptor, aad, &plaintext)[..prefix],
+            ciphertext[..prefix]
+        );
+    }
+
+    #[cfg(any(feature = "aesgcm", feature = "aesgcm_native"))]
+    #[test]
+    fn aes_gcm_stream() {
+        round_trip::<Aes128Gcm>();
+        round_trip::<Aes256Gcm>();
+        tampering::<Aes256Gcm>();
+    }
+
+    #[cfg(any(feature = "chacha20poly1305", feature = "chacha20poly1305_native"))]
+    #[test]
+    fn xchacha20poly1305_stream() {
+        round_trip::<XChaCha20Poly1305>();
+        tampering::<XChaCha20Poly1305>();
+    }
+
+    #[cfg(any(feature = "aesgcm", feature = "aesgcm_native"))]
+    #[test]
+    fn symmetric_encryptor_stream() {
+        let encryptor = SymmetricEncryptor::<Aes128Gcm>::default();
+        let mut stream = encryptor.encrypt_stream(b"file", Vec::new()).unwrap();
+        stream.write_all(b"Hello and Goodbye!").unwrap();
+        let ciphertext = stream.finish().unwrap();
+        let mut plaintext = Vec::new();
+        encryptor
+            .decrypt_stream(b"file", ciphertext.as_slice())
+            .unwrap()
+            .read_to_end(&mut plaintext)
+            .unwrap();
+        assert_eq!(plaintext.as_slice(), b"Hello and Goodbye!");
+    }
+}
@@ -1,4 +1,4 @@
-use super::Encryptor;
+use super::{stream::StreamEncryptor, Encryptor};
 use aead::{
     generic_array::{
         typenum::{Unsigned, U0, U16, U24, U32, U48},
@@ -28,6 +28,8 @@ impl Encryptor for XChaCha20Poly1305 {
     type MinSize = U48;
 }
 
+impl StreamEncryptor for XChaCha20Poly1305 {}
+
 impl NewAead for XChaCha20Poly1305 {
     type KeySize = U32;
 
@@ -0,0 +1,303 @@
+//! Raw stream ciphers for implementing protocols.
+//!
+//! **Nothing here is authenticated.** Anyone can flip bits of the output and the
+//! change goes unnoticed, and encrypting two messages with the same key and nonce
+//! reveals their XOR. This is only built with the `expert` feature and is meant for
+//! the pieces of a protocol that are authenticated some other way or must not grow,
+//! such as masking headers with a sample of the AEAD protected payload. Data at rest
+//! and messages belong in [`symm`](crate::encryption::symm) or
+//! [`stream`](crate::encryption::symm::stream).
+//!
+//! To keep these out of code that just wants to encrypt something, the ciphers only
+//! XOR a keystream into a buffer in place, they never return a ciphertext to store.
+//! Their keys are a type of their own, [`StreamKey`], that can't be serialized or
+//! printed and that the AEAD keys don't convert to. A cipher can't be cloned, so a
+//! position in the keystream can't be used twice by accident, only by seeking back
+//! to it.
+//!
+//! `ChaCha20` is the RFC 8439 cipher with a 96 bit nonce and a 32 bit block counter,
+//! so one nonce covers 256 GiB. `Aes128Ctr` and `Aes256Ctr` are counter mode as in
+//! NIST SP 800-38A, incrementing the whole 16 byte counter block as a big endian
+//! number.
+
+use std::fmt;
+
+use aes::cipher::generic_array::GenericArray;
+use aes::{Aes128, Aes256, BlockCipher, NewBlockCipher};
+use chacha20::cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+use zeroize::Zeroize;
+
+use CryptoError;
+
+pub const CHACHA20_NONCE_SIZE: usize = 12;
+pub const AES_CTR_NONCE_SIZE: usize = 16;
+
+const AES_BLOCK_SIZE: usize = 16;
+const CHACHA20_BLOCK_SIZE: u64 = 64;
+
+/// A key for the raw stream ciphers. It can't be serialized, printed or used as the
+/// key of anything else here, and is zeroed when dropped
+pub struct StreamKey(Vec<u8>);
+
+impl StreamKey {
+    pub fn new(key: &[u8]) -> Self {
+        StreamKey(key.to_vec())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for StreamKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StreamKey(..)")
+    }
+}
+
+impl Drop for StreamKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// A keystream XORed into buffers in place
+pub trait Keystream {
+    /// XOR the keystream from the current position into `data` and advance past it.
+    /// Applying it again at the same position undoes it
+    fn apply_keystream(&mut self, data: &mut [u8]);
+    /// Move to byte `position` of the keystream
+    fn seek(&mut self, position: u64) -> Result<(), CryptoError>;
+    /// The byte position of the keystream
+    fn position(&self) -> u64;
+}
+
+/// ChaCha20 as in RFC 8439
+pub struct ChaCha20 {
+    cipher: chacha20::ChaCha20,
+}
+
+impl ChaCha20 {
+    /// Start the keystream of `key` and `nonce` at block `counter`. QUIC header
+    /// protection, for one, takes both the counter and the nonce from a sample
+    pub fn new(key: &StreamKey, nonce: &[u8], counter: u32) -> Result<Self, CryptoError> {
+        if key.len() != 32 {
+            return Err(CryptoError::KeyGenError(
+                "ChaCha20 keys are 32 bytes".to_string(),
+            ));
+        }
+        if nonce.len() != CHACHA20_NONCE_SIZE {
+            return Err(CryptoError::GeneralError(
+                "ChaCha20 nonces are 12 bytes".to_string(),
+            ));
+        }
+        let mut cipher = chacha20::ChaCha20::new(
+            GenericArray::from_slice(&key.0),
+            GenericArray::from_slice(nonce),
+        );
+        cipher.seek(u64::from(counter) * CHACHA20_BLOCK_SIZE);
+        Ok(ChaCha20 { cipher })
+    }
+}
+
+impl Keystream for ChaCha20 {
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        SyncStreamCipher::apply_keystream(&mut self.cipher, data);
+    }
+
+    fn seek(&mut self, position: u64) -> Result<(), CryptoError> {
+        if position > CHACHA20_BLOCK_SIZE << 32 {
+            return Err(CryptoError::GeneralError(
+                "Position past the end of the ChaCha20 keystream".to_string(),
+            ));
+        }
+        SyncStreamCipherSeek::seek(&mut self.cipher, position);
+        Ok(())
+    }
+
+    fn position(&self) -> u64 {
+        self.cipher.current_pos()
+    }
+}
+
+impl fmt::Debug for ChaCha20 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChaCha20")
+            .field("position", &self.position())
+            .finish()
+    }
+}
+
+macro_rules! aes_ctr_impl {
+    ($name:ident, $cipher:ident, $keysize:expr) => {
+        /// AES in counter mode as in NIST SP 800-38A
+        pub struct $name {
+            cipher: $cipher,
+            nonce: [u8; AES_CTR_NONCE_SIZE],
+            /// The current block of keystream
+            block: [u8; AES_BLOCK_SIZE],
+            position: u64,
+        }
+
+        impl $name {
+            /// Start the keystream at the initial counter block `nonce`
+            pub fn new(key: &StreamKey, nonce: &[u8]) -> Result<Self, CryptoError> {
+                if key.len() != $keysize {
+                    return Err(CryptoError::KeyGenError(format!(
+                        "{} keys are {} bytes",
+                        stringify!($name),
+                        $keysize
+                    )));
+                }
+                if nonce.len() != AES_CTR_NONCE_SIZE {
+                    return Err(CryptoError::GeneralError(format!(
+                        "{} nonces are 16 bytes",
+                        stringify!($name)
+                    )));
+                }
+                let mut ctr = $name {
+                    cipher: $cipher::new(GenericArray::from_slice(&key.0)),
+                    nonce: *array_ref!(nonce, 0, AES_CTR_NONCE_SIZE),
+                    block: [0u8; AES_BLOCK_SIZE],
+                    position: 0,
+                };
+                ctr.refill();
+                Ok(ctr)
+            }
+
+            /// Encrypt the counter block of the current position
+            fn refill(&mut self) {
+                let counter = u128::from_be_bytes(self.nonce)
+                    .wrapping_add(u128::from(self.position / AES_BLOCK_SIZE as u64));
+                let mut block = GenericArray::clone_from_slice(&counter.to_be_bytes());
+                self.cipher.encrypt_block(&mut block);
+                self.block.copy_from_slice(&block);
+                block.as_mut_slice().zeroize();
+            }
+        }
+
+        impl Keystream for $name {
+            fn apply_keystream(&mut self, data: &mut [u8]) {
+                for byte in data.iter_mut() {
+                    *byte ^= self.block[(self.position % AES_BLOCK_SIZE as u64) as usize];
+                    self.position += 1;
+                    if self.position % AES_BLOCK_SIZE as u64 == 0 {
+                        self.refill();
+                    }
+                }
+            }
+
+            fn seek(&mut self, position: u64) -> Result<(), CryptoError> {
+                self.position = position;
+                self.refill();
+                Ok(())
+            }
+
+            fn position(&self) -> u64 {
+                self.position
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("position", &self.position)
+                    .finish()
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                self.block.zeroize();
+            }
+        }
+    };
+}
+
+aes_ctr_impl!(Aes128Ctr, Aes128, 16);
+aes_ctr_impl!(Aes256Ctr, Aes256, 32);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chacha20_rfc8439() {
+        // RFC 8439 section 2.4.2
+        let key = StreamKey::new(&(0u8..32).collect::<Vec<_>>());
+        let nonce = hex::decode("000000000000004a00000000").unwrap();
+        let mut data = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec();
+        let plaintext = data.clone();
+        let mut cipher = ChaCha20::new(&key, &nonce, 1).unwrap();
+        assert_eq!(cipher.position(), 64);
+        cipher.apply_keystream(&mut data);
+        assert_eq!(
+            hex::encode(&data),
+            "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d"
+        );
+
+        // Seeking back and applying it again decrypts
+        cipher.seek(64).unwrap();
+        cipher.apply_keystream(&mut data);
+        assert_eq!(data, plaintext);
+        assert!(cipher.seek((64 << 32) + 1).is_err());
+
+        assert!(ChaCha20::new(&StreamKey::new(&[0u8; 16]), &nonce, 0).is_err());
+        assert!(ChaCha20::new(&key, &nonce[1..], 0).is_err());
+    }
+
+    #[test]
+    fn aes_ctr_sp800_38a() {
+        // NIST SP 800-38A F.5.1 and F.5.5
+        let nonce = hex::decode("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+        let plaintext = hex::decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710").unwrap();
+
+        let key = StreamKey::new(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap());
+        let mut data = plaintext.clone();
+        Aes128Ctr::new(&key, &nonce)
+            .unwrap()
+            .apply_keystream(&mut data);
+        assert_eq!(
+            hex::encode(&data),
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+        );
+
+        let key = StreamKey::new(
+            &hex::decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4")
+                .unwrap(),
+        );
+        let mut data = plaintext.clone();
+        let mut cipher = Aes256Ctr::new(&key, &nonce).unwrap();
+        // Uneven pieces cross the block boundaries
+        for piece in data.chunks_mut(7) {
+            cipher.apply_keystream(piece);
+        }
+        assert_eq!(
+            hex::encode(&data),
+            "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c52b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6"
+        );
+        assert_eq!(cipher.position(), 64);
+
+        // Only the second half, from the middle of a block
+        cipher.seek(37).unwrap();
+        cipher.apply_keystream(&mut data[37..]);
+        assert_eq!(&data[37..], &plaintext[37..]);
+
+        // The counter block wraps around to zero
+        let mut cipher =
+            Aes128Ctr::new(&StreamKey::new(&[7u8; 16]), &[0xffu8; AES_CTR_NONCE_SIZE]).unwrap();
+        let mut wrapped = [0u8; 32];
+        cipher.apply_keystream(&mut wrapped);
+        let mut zero = [0u8; 16];
+        let mut start = Aes128Ctr::new(&StreamKey::new(&[7u8; 16]), &[0u8; 16]).unwrap();
+        start.apply_keystream(&mut zero);
+        assert_eq!(&wrapped[16..], &zero[..]);
+
+        assert!(Aes256Ctr::new(&StreamKey::new(&[0u8; 16]), &nonce).is_err());
+        assert!(Aes128Ctr::new(&StreamKey::new(&[0u8; 16]), &nonce[1..]).is_err());
+    }
+}
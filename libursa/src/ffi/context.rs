@@ -0,0 +1,514 @@
+// FFI contexts that restrict which primitives the bound code can invoke
+//
+// An embedder creates a context with the algorithms and operations it allows and
+// hands only the context to the code it binds, usually a scripting or UI layer of a
+// mobile wallet. Every `ursa_context_*` call checks the context's policy before it
+// runs, so code that only holds a signing context can't decrypt, and code that only
+// holds an AES-GCM context can't use any other cipher. The policy is fixed when the
+// context is created and can't be widened afterwards.
+//
+// The policy is JSON with the algorithms and the operations allowed on them:
+//
+// {
+//     "algorithms": ["aes-256-gcm", "ed25519"],
+//     "operations": ["encrypt", "decrypt", "sign"]
+// }
+//
+// Algorithms use the names `ursa_encrypt` accepts and `ed25519`. Operations are
+// `keygen`, `sign`, `verify`, `encrypt`, `decrypt` and `random`, which needs no
+// algorithm. Unknown names are rejected so that a typo can't silently deny a call.
+//
+// The unscoped functions like `ursa_encrypt` stay available to code that's linked
+// against them directly, so the restriction only holds for code that's given nothing
+// but a context.
+
+use super::ByteArray;
+use ffi_support::{ByteBuffer, ErrorCode, ExternError, FfiStr};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::os::raw::c_void;
+use std::str::FromStr;
+
+#[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+use encryption::symm::prelude::EncryptorType;
+#[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+use ffi::encryption::{random_bytes, ursa_decrypt, ursa_encrypt};
+#[cfg(any(
+    feature = "signatures",
+    feature = "signatures_native",
+    feature = "signatures_asm"
+))]
+use ffi::signatures::ed25519::{ursa_ed25519_keypair_new, ursa_ed25519_sign, ursa_ed25519_verify};
+
+pub mod context_error_codes {
+    pub const INVALID_POLICY: i32 = 12;
+    pub const INVALID_CONTEXT: i32 = 13;
+    pub const NOT_PERMITTED: i32 = 14;
+}
+
+/// The name of Ed25519 signatures in a policy
+pub const ED25519: &str = "ed25519";
+
+/// The operations a policy can allow
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    KeyGen,
+    Sign,
+    Verify,
+    Encrypt,
+    Decrypt,
+    Random,
+}
+
+impl FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keygen" => Ok(Operation::KeyGen),
+            "sign" => Ok(Operation::Sign),
+            "verify" => Ok(Operation::Verify),
+            "encrypt" => Ok(Operation::Encrypt),
+            "decrypt" => Ok(Operation::Decrypt),
+            "random" => Ok(Operation::Random),
+            _ => Err(format!("Invalid operation: {}", s)),
+        }
+    }
+}
+
+/// The JSON policy a context is created from
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub algorithms: Vec<String>,
+    #[serde(default)]
+    pub operations: Vec<String>,
+}
+
+/// The algorithms and operations the holder of a context may use
+#[derive(Debug)]
+pub struct Context {
+    algorithms: HashSet<String>,
+    operations: HashSet<Operation>,
+}
+
+impl Context {
+    pub fn new(policy: &Policy) -> Result<Self, String> {
+        let algorithms = policy
+            .algorithms
+            .iter()
+            .map(|a| {
+                if is_known_algorithm(a) {
+                    Ok(a.clone())
+                } else {
+                    Err(format!("Invalid algorithm: {}", a))
+                }
+            })
+            .collect::<Result<HashSet<String>, String>>()?;
+        let operations = policy
+            .operations
+            .iter()
+            .map(|o| Operation::from_str(o))
+            .collect::<Result<HashSet<Operation>, String>>()?;
+        Ok(Context {
+            algorithms,
+            operations,
+        })
+    }
+
+    /// Whether the policy allows `operation`, and `algorithm` if the operation uses one
+    pub fn allows(&self, algorithm: Option<&str>, operation: Operation) -> bool {
+        self.operations.contains(&operation)
+            && match algorithm {
+                Some(a) => self.algorithms.contains(a),
+                None => true,
+            }
+    }
+}
+
+fn is_known_algorithm(name: &str) -> bool {
+    #[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+    {
+        if EncryptorType::from_str(name).is_ok() {
+            return true;
+        }
+    }
+    name == ED25519
+}
+
+/// Create a context from a JSON `policy`.
+/// Caller will need to call `ursa_context_free` on `context` to free the memory.
+/// If an error occurs, caller will need to call `ursa_string_free`
+/// on `err.message` to free the memory.
+#[no_mangle]
+pub extern "C" fn ursa_context_new(
+    policy: &FfiStr<'_>,
+    context: &mut *const c_void,
+    err: &mut ExternError,
+) -> i32 {
+    let res = serde_json::from_str::<Policy>(policy.as_str())
+        .map_err(|e| e.to_string())
+        .and_then(|p| Context::new(&p));
+    match res {
+        Ok(c) => {
+            *err = ExternError::success();
+            *context = Box::into_raw(Box::new(c)) as *const c_void;
+            1
+        }
+        Err(e) => {
+            *err = ExternError::new_error(ErrorCode::new(context_error_codes::INVALID_POLICY), e);
+            0
+        }
+    }
+}
+
+/// Free a context created by `ursa_context_new`
+#[no_mangle]
+pub extern "C" fn ursa_context_free(context: *const c_void) {
+    if !context.is_null() {
+        drop(unsafe { Box::from_raw(context as *mut Context) });
+    }
+}
+
+/// Return 1 if the context allows `operation` with `algorithm`, which may be null for
+/// operations that don't use one, and 0 otherwise
+#[no_mangle]
+pub extern "C" fn ursa_context_is_allowed(
+    context: *const c_void,
+    algorithm: &FfiStr<'_>,
+    operation: &FfiStr<'_>,
+) -> i32 {
+    let mut err = ExternError::success();
+    match Operation::from_str(operation.as_str()) {
+        Ok(op) => check(context, algorithm.as_opt_str(), op, &mut err),
+        Err(_) => 0,
+    }
+}
+
+/// Encrypt like `ursa_encrypt` if the context allows `encrypt` with `algorithm`
+#[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+#[no_mangle]
+pub extern "C" fn ursa_context_encrypt(
+    context: *const c_void,
+    output: &mut ByteBuffer,
+    algorithm: &FfiStr<'_>,
+    key: &ByteArray,
+    nonce: &ByteArray,
+    aad: &ByteArray,
+    input: &ByteArray,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, Some(algorithm.as_str()), Operation::Encrypt, err) == 0 {
+        return 0;
+    }
+    ursa_encrypt(output, algorithm, key, nonce, aad, input, err)
+}
+
+/// Decrypt like `ursa_decrypt` if the context allows `decrypt` with `algorithm`
+#[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+#[no_mangle]
+pub extern "C" fn ursa_context_decrypt(
+    context: *const c_void,
+    output: &mut ByteBuffer,
+    algorithm: &FfiStr<'_>,
+    key: &ByteArray,
+    nonce: &ByteArray,
+    aad: &ByteArray,
+    input: &ByteArray,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, Some(algorithm.as_str()), Operation::Decrypt, err) == 0 {
+        return 0;
+    }
+    ursa_decrypt(output, algorithm, key, nonce, aad, input, err)
+}
+
+/// Generate random bytes like `random_bytes` if the context allows `random`
+#[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+#[no_mangle]
+pub extern "C" fn ursa_context_random_bytes(
+    context: *const c_void,
+    output: &mut ByteBuffer,
+    bytes: usize,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, None, Operation::Random, err) == 0 {
+        return 0;
+    }
+    random_bytes(output, bytes, err)
+}
+
+/// Create a keypair like `ursa_ed25519_keypair_new` if the context allows `keygen`
+/// with `ed25519`
+#[cfg(any(
+    feature = "signatures",
+    feature = "signatures_native",
+    feature = "signatures_asm"
+))]
+#[no_mangle]
+pub extern "C" fn ursa_context_ed25519_keypair_new(
+    context: *const c_void,
+    public_key: &mut ByteBuffer,
+    private_key: &mut ByteBuffer,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, Some(ED25519), Operation::KeyGen, err) == 0 {
+        return 0;
+    }
+    ursa_ed25519_keypair_new(public_key, private_key, err)
+}
+
+/// Sign like `ursa_ed25519_sign` if the context allows `sign` with `ed25519`
+#[cfg(any(
+    feature = "signatures",
+    feature = "signatures_native",
+    feature = "signatures_asm"
+))]
+#[no_mangle]
+pub extern "C" fn ursa_context_ed25519_sign(
+    context: *const c_void,
+    message: &ByteArray,
+    private_key: &ByteArray,
+    signature: &mut ByteBuffer,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, Some(ED25519), Operation::Sign, err) == 0 {
+        return 0;
+    }
+    ursa_ed25519_sign(message, private_key, signature, err)
+}
+
+/// Verify like `ursa_ed25519_verify` if the context allows `verify` with `ed25519`
+#[cfg(any(
+    feature = "signatures",
+    feature = "signatures_native",
+    feature = "signatures_asm"
+))]
+#[no_mangle]
+pub extern "C" fn ursa_context_ed25519_verify(
+    context: *const c_void,
+    message: &ByteArray,
+    signature: &ByteArray,
+    public_key: &ByteArray,
+    err: &mut ExternError,
+) -> i32 {
+    if check(context, Some(ED25519), Operation::Verify, err) == 0 {
+        return 0;
+    }
+    ursa_ed25519_verify(message, signature, public_key, err)
+}
+
+fn check(
+    context: *const c_void,
+    algorithm: Option<&str>,
+    operation: Operation,
+    err: &mut ExternError,
+) -> i32 {
+    let context = match unsafe { (context as *const Context).as_ref() } {
+        Some(c) => c,
+        None => {
+            *err = ExternError::new_error(
+                ErrorCode::new(context_error_codes::INVALID_CONTEXT),
+                "Invalid context",
+            );
+            return 0;
+        }
+    };
+    if context.allows(algorithm, operation) {
+        *err = ExternError::success();
+        1
+    } else {
+        *err = ExternError::new_error(
+            ErrorCode::new(context_error_codes::NOT_PERMITTED),
+            format!(
+                "{:?} with {} is not permitted by this context",
+                operation,
+                algorithm.unwrap_or("no algorithm")
+            ),
+        );
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::ptr;
+
+    fn new_context(policy: &str, err: &mut ExternError) -> *const c_void {
+        let policy = CString::new(policy).unwrap();
+        let policy = unsafe { FfiStr::from_raw(policy.as_ptr()) };
+        let mut context = ptr::null();
+        ursa_context_new(&policy, &mut context, err);
+        context
+    }
+
+    fn is_allowed(context: *const c_void, algorithm: Option<&str>, operation: &str) -> i32 {
+        let algorithm = algorithm.map(|a| CString::new(a).unwrap());
+        let algorithm_ptr = algorithm.as_ref().map_or(ptr::null(), |a| a.as_ptr());
+        let operation = CString::new(operation).unwrap();
+        unsafe {
+            ursa_context_is_allowed(
+                context,
+                &FfiStr::from_raw(algorithm_ptr),
+                &FfiStr::from_raw(operation.as_ptr()),
+            )
+        }
+    }
+
+    #[test]
+    fn policy() {
+        let mut error = ExternError::success();
+        let context = new_context(
+            r#"{"algorithms": ["ed25519"], "operations": ["sign", "random"]}"#,
+            &mut error,
+        );
+        assert!(error.get_code().is_success());
+        assert_eq!(is_allowed(context, Some(ED25519), "sign"), 1);
+        assert_eq!(is_allowed(context, Some(ED25519), "verify"), 0);
+        assert_eq!(is_allowed(context, Some("aes-256-gcm"), "sign"), 0);
+        assert_eq!(is_allowed(context, None, "random"), 1);
+        assert_eq!(is_allowed(context, None, "unknown"), 0);
+        assert_eq!(is_allowed(ptr::null(), None, "random"), 0);
+        ursa_context_free(context);
+
+        for policy in &[
+            "not json",
+            r#"{"algorithms": ["rot13"], "operations": []}"#,
+            r#"{"algorithms": [], "operations": ["delete"]}"#,
+            r#"{"algorithms": [], "operations": [], "admin": true}"#,
+        ] {
+            let context = new_context(policy, &mut error);
+            assert!(context.is_null(), "{}", policy);
+            assert_eq!(
+                error.get_code(),
+                ErrorCode::new(context_error_codes::INVALID_POLICY)
+            );
+        }
+    }
+
+    #[cfg(any(feature = "encryption", feature = "encryption_asm"))]
+    #[test]
+    fn scoped_encryption() {
+        let mut error = ExternError::success();
+        let context = new_context(
+            r#"{"algorithms": ["aes-256-gcm"], "operations": ["encrypt"]}"#,
+            &mut error,
+        );
+        let key = ByteArray::from(vec![7u8; 32]);
+        let nonce = ByteArray::default();
+        let aad = ByteArray::from(b"ffi/context".to_vec());
+        let msg = ByteArray::from(b"Scoped message".to_vec());
+        let alg = CString::new("aes-256-gcm").unwrap();
+        let alg = unsafe { FfiStr::from_raw(alg.as_ptr()) };
+
+        let mut ciphertext = ByteBuffer::default();
+        let res = ursa_context_encrypt(
+            context,
+            &mut ciphertext,
+            &alg,
+            &key,
+            &nonce,
+            &aad,
+            &msg,
+            &mut error,
+        );
+        assert_eq!(res, 1);
+        let ciphertext = ByteArray::from(ciphertext);
+
+        let mut plaintext = ByteBuffer::default();
+        let res = ursa_context_decrypt(
+            context,
+            &mut plaintext,
+            &alg,
+            &key,
+            &nonce,
+            &aad,
+            &ciphertext,
+            &mut error,
+        );
+        assert_eq!(res, 0);
+        assert_eq!(
+            error.get_code(),
+            ErrorCode::new(context_error_codes::NOT_PERMITTED)
+        );
+
+        let other = CString::new("xchacha20poly1305").unwrap();
+        let other = unsafe { FfiStr::from_raw(other.as_ptr()) };
+        let mut output = ByteBuffer::default();
+        let res = ursa_context_encrypt(
+            context,
+            &mut output,
+            &other,
+            &key,
+            &nonce,
+            &aad,
+            &msg,
+            &mut error,
+        );
+        assert_eq!(res, 0);
+
+        let res = ursa_context_random_bytes(context, &mut output, 16, &mut error);
+        assert_eq!(res, 0);
+
+        // The unscoped function decrypts what the context encrypted
+        let res = ursa_decrypt(
+            &mut plaintext,
+            &alg,
+            &key,
+            &nonce,
+            &aad,
+            &ciphertext,
+            &mut error,
+        );
+        assert_eq!(res, 1);
+        assert_eq!(plaintext.destroy_into_vec(), msg.to_vec());
+        ursa_context_free(context);
+    }
+
+    #[cfg(any(
+        feature = "signatures",
+        feature = "signatures_native",
+        feature = "signatures_asm"
+    ))]
+    #[test]
+    fn scoped_signatures() {
+        let mut error = ExternError::success();
+        let context = new_context(
+            r#"{"algorithms": ["ed25519"], "operations": ["keygen", "sign"]}"#,
+            &mut error,
+        );
+        let mut public_key = ByteBuffer::default();
+        let mut private_key = ByteBuffer::default();
+        let res = ursa_context_ed25519_keypair_new(
+            context,
+            &mut public_key,
+            &mut private_key,
+            &mut error,
+        );
+        assert_eq!(res, 1);
+        let pk = public_key.destroy_into_vec();
+        let sk = private_key.destroy_into_vec();
+
+        let message = ByteArray::from(&b"Scoped message"[..]);
+        let sk_wrapper = ByteArray::from(&sk);
+        let mut signature = ByteBuffer::default();
+        let res =
+            ursa_context_ed25519_sign(context, &message, &sk_wrapper, &mut signature, &mut error);
+        assert_eq!(res, 1);
+
+        let sig = signature.destroy_into_vec();
+        let sig_wrapper = ByteArray::from(&sig);
+        let pk_wrapper = ByteArray::from(&pk);
+        let res =
+            ursa_context_ed25519_verify(context, &message, &sig_wrapper, &pk_wrapper, &mut error);
+        assert_eq!(res, 0);
+        assert_eq!(
+            error.get_code(),
+            ErrorCode::new(context_error_codes::NOT_PERMITTED)
+        );
+        ursa_context_free(context);
+    }
+}
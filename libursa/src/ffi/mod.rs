@@ -1,7 +1,8 @@
 #[cfg(any(feature = "bls_bn254", feature = "bls_bn254_asm"))]
 pub mod bls;
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 pub mod cl;
+pub mod context;
 #[cfg(any(feature = "encryption", feature = "encryption_asm"))]
 pub mod encryption;
 pub mod logger;
@@ -12,9 +13,12 @@ pub mod logger;
 ))]
 pub mod signatures;
 
+use capabilities::capabilities;
 use errors::prelude::*;
 use ffi_support::ByteBuffer;
+use serde_json;
 use std::os::raw::c_char;
+use utils::ctypes::string_to_cstring;
 
 /// Used for receiving a ByteBuffer from C that was allocated by either C or Rust.
 /// If Rust allocated, then the outgoing struct is ffi_support::ByteBuffer
@@ -188,7 +192,58 @@ pub extern "C" fn ursa_get_current_error(error_json_p: *mut *const c_char) {
     trace!("ursa_get_current_error: <<<");
 }
 
-#[cfg(all(test, any(feature = "cl", feature = "cl_native")))]
+/// Get a description of the algorithms, backends and features of the linked library.
+///
+/// NOTE: The returned string must be freed with `ursa_string_free`.
+///
+/// #Params
+/// * `capabilities_json_p` - Reference that will contain the description in the format:
+/// {
+///     "version": str - library version
+///     "features": [str] - enabled crate features
+///     "algorithms": [{
+///         "name": str - algorithm name, e.g. "Ed25519"
+///         "kind": str - one of "Hash", "Aead", "Signature" or "KeyExchange"
+///         "backend": str - one of "Rust", "Asm", "OpenSsl", "Libsodium", "Libsecp256k1" or "Gmp"
+///         "security_bits": int - estimated classical security level
+///     }]
+/// }
+///
+#[no_mangle]
+pub extern "C" fn ursa_capabilities(capabilities_json_p: *mut *const c_char) -> ErrorCode {
+    trace!(
+        "ursa_capabilities >>> capabilities_json_p: {:?}",
+        capabilities_json_p
+    );
+
+    if capabilities_json_p.is_null() {
+        set_current_error(&err_msg(
+            ErrorCode::CommonInvalidParam1.into(),
+            "Invalid pointer has been passed",
+        ));
+        return ErrorCode::CommonInvalidParam1;
+    }
+
+    let res = match serde_json::to_string(&capabilities()) {
+        Ok(capabilities_json) => {
+            unsafe {
+                *capabilities_json_p = string_to_cstring(capabilities_json).into_raw();
+            }
+            ErrorCode::Success
+        }
+        Err(err) => err
+            .to_ursa(
+                UrsaCryptoErrorKind::InvalidState,
+                "Unable to serialize capabilities as json",
+            )
+            .into(),
+    };
+
+    trace!("ursa_capabilities: <<< res: {:?}", res);
+    res
+}
+
+#[cfg(all(test, any(feature = "cl", feature = "cl_native", feature = "cl_gmp")))]
 mod tests {
     use super::*;
 
@@ -216,4 +271,25 @@ mod tests {
 
         assert_ne!(error_json_1.unwrap(), error_json_2.unwrap());
     }
+
+    #[test]
+    fn ursa_capabilities_works() {
+        let mut capabilities_json_p: *const c_char = ptr::null();
+        assert_eq!(
+            ursa_capabilities(&mut capabilities_json_p),
+            ErrorCode::Success
+        );
+        let capabilities_json = c_str_to_string(capabilities_json_p).unwrap().unwrap();
+        unsafe { ursa_string_free(capabilities_json_p as *mut c_char) };
+        let capabilities: serde_json::Value = serde_json::from_str(&capabilities_json).unwrap();
+        assert!(capabilities["features"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("ffi")));
+
+        assert_eq!(
+            ursa_capabilities(ptr::null_mut()),
+            ErrorCode::CommonInvalidParam1
+        );
+    }
 }
@@ -146,7 +146,7 @@
 // }
 
 use super::super::ByteArray;
-use keys::{KeyGenOption, PrivateKey, PublicKey};
+use keys::{KeyGenOption, PrivateKey, PublicKey, SecretBytes};
 use signatures::ed25519;
 use signatures::prelude::*;
 
@@ -205,7 +205,7 @@ pub extern "C" fn ursa_ed25519_keypair_from_seed(
     err: &mut ExternError,
 ) -> i32 {
     ursa_ed25519_keypair_gen(
-        Some(KeyGenOption::UseSeed(seed.to_vec())),
+        Some(KeyGenOption::UseSeed(SecretBytes::new(seed.to_vec()))),
         public_key,
         Some(private_key),
         err,
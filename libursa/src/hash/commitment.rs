@@ -0,0 +1,445 @@
+//! Hash commitments to byte strings.
+//!
+//! A commitment is an HMAC over the domain, the parameters, the value and fresh
+//! randomness. It can be published before the value is revealed: the committer then
+//! sends the [`Opening`] and anyone holding the same scheme checks it.
+//!
+//! The strength of both properties is chosen explicitly instead of being implied by an
+//! ad hoc `sha256(value || nonce)`:
+//!
+//! * [`Binding`] picks the hash. A committer who can find a collision can open one
+//!   commitment to two values, so binding is half the output length in bits.
+//! * [`Hiding`] picks the randomness. Without randomness anyone can test guesses of
+//!   the value against the commitment, which is only acceptable for values with high
+//!   entropy or with a [`CommitmentKey`].
+//!
+//! Every scheme has a domain, usually the name of the protocol and the purpose of the
+//! commitment, so a commitment made for one purpose can't be opened in another. With a
+//! key only its holders can create or check commitments, a plain commitment is checked
+//! by anyone.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use CryptoError;
+
+pub const KEY_SIZE: usize = 32;
+pub const RANDOMNESS_SIZE: usize = 32;
+
+const CONTEXT: &[u8] = b"ursa hash commitment v1";
+
+/// How hard it is to open a commitment to a different value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    /// HMAC-SHA256, 32 byte commitments
+    Bits128,
+    /// HMAC-SHA512, 64 byte commitments
+    Bits256,
+}
+
+impl Binding {
+    /// The length of the commitments in bytes
+    pub fn size(self) -> usize {
+        match self {
+            Binding::Bits128 => 32,
+            Binding::Bits256 => 64,
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            Binding::Bits128 => 1,
+            Binding::Bits256 => 2,
+        }
+    }
+}
+
+/// How hard it is to learn the value from a commitment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hiding {
+    /// No randomness, equal values give equal commitments. Only hides values that
+    /// can't be guessed
+    None,
+    /// `RANDOMNESS_SIZE` bytes of fresh randomness for every commitment
+    Randomized,
+}
+
+impl Hiding {
+    /// The length of the randomness in bytes
+    pub fn size(self) -> usize {
+        match self {
+            Hiding::None => 0,
+            Hiding::Randomized => RANDOMNESS_SIZE,
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            Hiding::None => 0,
+            Hiding::Randomized => 1,
+        }
+    }
+}
+
+/// A secret key that restricts creating and checking commitments to its holders
+pub struct CommitmentKey([u8; KEY_SIZE]);
+
+impl CommitmentKey {
+    pub fn new(key: [u8; KEY_SIZE]) -> Self {
+        CommitmentKey(key)
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        CommitmentKey(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl ::std::fmt::Debug for CommitmentKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("CommitmentKey(..)")
+    }
+}
+
+impl Drop for CommitmentKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The randomness of one commitment. Keep it secret until the commitment is opened
+/// and never use it twice
+#[derive(Clone, PartialEq, Eq)]
+pub struct Randomness(Vec<u8>);
+
+impl Randomness {
+    /// Fresh randomness for `hiding`, empty for `Hiding::None`
+    pub fn generate(hiding: Hiding) -> Self {
+        let mut randomness = vec![0u8; hiding.size()];
+        OsRng.fill_bytes(&mut randomness);
+        Randomness(randomness)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Randomness(bytes.to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl ::std::fmt::Debug for Randomness {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("Randomness(..)")
+    }
+}
+
+impl Drop for Randomness {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// A commitment that can be published
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitment(Vec<u8>);
+
+impl Commitment {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != Binding::Bits128.size() && bytes.len() != Binding::Bits256.size() {
+            return Err(CryptoError::ParseError(format!(
+                "Invalid commitment length {}",
+                bytes.len()
+            )));
+        }
+        Ok(Commitment(bytes.to_vec()))
+    }
+}
+
+/// The value and randomness of a commitment, sent to open it
+#[derive(Clone, Debug)]
+pub struct Opening {
+    value: Vec<u8>,
+    randomness: Randomness,
+}
+
+impl Opening {
+    pub fn new(value: &[u8], randomness: Randomness) -> Self {
+        Opening {
+            value: value.to_vec(),
+            randomness,
+        }
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    pub fn randomness(&self) -> &Randomness {
+        &self.randomness
+    }
+
+    /// The length of the value as 8 bytes big endian, the value, then the randomness
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + self.value.len() + self.randomness.0.len());
+        out.extend_from_slice(&(self.value.len() as u64).to_be_bytes());
+        out.extend_from_slice(&self.value);
+        out.extend_from_slice(&self.randomness.0);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() < 8 {
+            return Err(CryptoError::ParseError("Opening is too short".to_string()));
+        }
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&bytes[..8]);
+        let len = u64::from_be_bytes(len);
+        if len > (bytes.len() - 8) as u64 {
+            return Err(CryptoError::ParseError(
+                "Opening value length is out of bounds".to_string(),
+            ));
+        }
+        let (value, randomness) = bytes[8..].split_at(len as usize);
+        Ok(Opening::new(value, Randomness::from_bytes(randomness)))
+    }
+}
+
+impl Drop for Opening {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// The parameters that commitments are made and checked with. Both sides must use the
+/// same domain, binding, hiding and key
+#[derive(Debug)]
+pub struct CommitmentScheme {
+    domain: Vec<u8>,
+    binding: Binding,
+    hiding: Hiding,
+    key: Option<CommitmentKey>,
+}
+
+impl CommitmentScheme {
+    pub fn new(domain: &[u8], binding: Binding, hiding: Hiding) -> Result<Self, CryptoError> {
+        if domain.is_empty() {
+            return Err(CryptoError::GeneralError(
+                "Commitment schemes need a domain".to_string(),
+            ));
+        }
+        Ok(CommitmentScheme {
+            domain: domain.to_vec(),
+            binding,
+            hiding,
+            key: None,
+        })
+    }
+
+    /// Make and check commitments keyed with `key`
+    pub fn with_key(mut self, key: CommitmentKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    pub fn binding(&self) -> Binding {
+        self.binding
+    }
+
+    pub fn hiding(&self) -> Hiding {
+        self.hiding
+    }
+
+    pub fn is_keyed(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Commit to `value` with the caller's randomness, which must have the length
+    /// required by the hiding of this scheme
+    pub fn commit(&self, value: &[u8], randomness: &Randomness) -> Result<Commitment, CryptoError> {
+        if randomness.0.len() != self.hiding.size() {
+            return Err(CryptoError::GeneralError(format!(
+                "Expected {} bytes of randomness, found {}",
+                self.hiding.size(),
+                randomness.0.len()
+            )));
+        }
+        Ok(Commitment(self.mac(value, randomness)))
+    }
+
+    /// Commit to `value` with fresh randomness. Publish the commitment and keep the
+    /// opening until the value is revealed
+    pub fn commit_random(&self, value: &[u8]) -> (Commitment, Opening) {
+        let randomness = Randomness::generate(self.hiding);
+        let commitment = Commitment(self.mac(value, &randomness));
+        (commitment, Opening::new(value, randomness))
+    }
+
+    /// Check an opening against a commitment and return the committed value
+    pub fn open<'a>(
+        &self,
+        commitment: &Commitment,
+        opening: &'a Opening,
+    ) -> Result<&'a [u8], CryptoError> {
+        if self.verify(commitment, &opening.value, &opening.randomness) {
+            Ok(&opening.value)
+        } else {
+            Err(CryptoError::GeneralError(
+                "The opening doesn't match the commitment".to_string(),
+            ))
+        }
+    }
+
+    /// Whether `commitment` was made to `value` with `randomness`
+    pub fn verify(&self, commitment: &Commitment, value: &[u8], randomness: &Randomness) -> bool {
+        if commitment.0.len() != self.binding.size() || randomness.0.len() != self.hiding.size() {
+            return false;
+        }
+        self.mac(value, randomness).ct_eq(&commitment.0).into()
+    }
+
+    fn mac(&self, value: &[u8], randomness: &Randomness) -> Vec<u8> {
+        // An unkeyed scheme uses HMAC with an empty key, which is as collision
+        // resistant as the plain hash
+        let key = self.key.as_ref().map(|k| &k.0[..]).unwrap_or(&[]);
+        match self.binding {
+            Binding::Bits128 => self.mac_with::<Hmac<Sha256>>(key, value, randomness),
+            Binding::Bits256 => self.mac_with::<Hmac<Sha512>>(key, value, randomness),
+        }
+    }
+
+    fn mac_with<M: Mac>(&self, key: &[u8], value: &[u8], randomness: &Randomness) -> Vec<u8> {
+        // HMAC accepts keys of any length
+        let mut mac = M::new_varkey(key).unwrap();
+        mac.input(CONTEXT);
+        mac.input(&[
+            self.binding.tag(),
+            self.hiding.tag(),
+            self.key.is_some() as u8,
+        ]);
+        mac.input(&(self.domain.len() as u64).to_be_bytes());
+        mac.input(&self.domain);
+        mac.input(&(value.len() as u64).to_be_bytes());
+        mac.input(value);
+        mac.input(&randomness.0);
+        mac.result().code().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commit_and_open() {
+        for &binding in &[Binding::Bits128, Binding::Bits256] {
+            for &hiding in &[Hiding::None, Hiding::Randomized] {
+                let scheme = CommitmentScheme::new(b"test bids", binding, hiding).unwrap();
+                let (commitment, opening) = scheme.commit_random(b"42 coins");
+                assert_eq!(commitment.as_bytes().len(), binding.size());
+                assert_eq!(scheme.open(&commitment, &opening).unwrap(), b"42 coins");
+
+                let opening = Opening::from_bytes(&opening.to_bytes()).unwrap();
+                let commitment = Commitment::from_bytes(&commitment.to_bytes()).unwrap();
+                assert!(scheme.verify(&commitment, opening.value(), opening.randomness()));
+                assert!(!scheme.verify(&commitment, b"43 coins", opening.randomness()));
+
+                let other = Opening::new(b"43 coins", opening.randomness().clone());
+                assert!(scheme.open(&commitment, &other).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn hiding() {
+        let plain = CommitmentScheme::new(b"test", Binding::Bits128, Hiding::None).unwrap();
+        let randomness = Randomness::generate(Hiding::None);
+        assert!(randomness.as_bytes().is_empty());
+        assert_eq!(
+            plain.commit(b"yes", &randomness).unwrap(),
+            plain.commit(b"yes", &randomness).unwrap()
+        );
+        assert!(plain
+            .commit(b"yes", &Randomness::generate(Hiding::Randomized))
+            .is_err());
+
+        let hiding = CommitmentScheme::new(b"test", Binding::Bits128, Hiding::Randomized).unwrap();
+        assert_ne!(
+            hiding.commit_random(b"yes").0,
+            hiding.commit_random(b"yes").0
+        );
+        assert!(hiding.commit(b"yes", &randomness).is_err());
+    }
+
+    #[test]
+    fn domain_separation() {
+        let randomness = Randomness::from_bytes(&[7u8; RANDOMNESS_SIZE]);
+        let a = CommitmentScheme::new(b"a", Binding::Bits128, Hiding::Randomized).unwrap();
+        let b = CommitmentScheme::new(b"b", Binding::Bits128, Hiding::Randomized).unwrap();
+        let commitment = a.commit(b"value", &randomness).unwrap();
+        assert!(a.verify(&commitment, b"value", &randomness));
+        assert!(!b.verify(&commitment, b"value", &randomness));
+        assert!(CommitmentScheme::new(b"", Binding::Bits128, Hiding::None).is_err());
+
+        // The domain length is framed, so moving bytes between the domain and the
+        // value changes the commitment
+        let ab = CommitmentScheme::new(b"ab", Binding::Bits128, Hiding::Randomized).unwrap();
+        assert_ne!(
+            ab.commit(b"c", &randomness).unwrap(),
+            a.commit(b"bc", &randomness).unwrap()
+        );
+    }
+
+    #[test]
+    fn keyed() {
+        let key = [3u8; KEY_SIZE];
+        let scheme = CommitmentScheme::new(b"test", Binding::Bits256, Hiding::None)
+            .unwrap()
+            .with_key(CommitmentKey::new(key));
+        let other = CommitmentScheme::new(b"test", Binding::Bits256, Hiding::None)
+            .unwrap()
+            .with_key(CommitmentKey::generate());
+        let plain = CommitmentScheme::new(b"test", Binding::Bits256, Hiding::None).unwrap();
+        assert!(scheme.is_keyed());
+
+        let (commitment, opening) = scheme.commit_random(b"123-45-6789");
+        assert!(scheme.open(&commitment, &opening).is_ok());
+        assert!(other.open(&commitment, &opening).is_err());
+        assert!(plain.open(&commitment, &opening).is_err());
+        assert_eq!(
+            format!("{:?}", CommitmentKey::new(key)),
+            "CommitmentKey(..)"
+        );
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        assert!(Commitment::from_bytes(&[0u8; 31]).is_err());
+        assert!(Opening::from_bytes(&[0u8; 7]).is_err());
+        let mut bytes = 10u64.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"short");
+        assert!(Opening::from_bytes(&bytes).is_err());
+    }
+}
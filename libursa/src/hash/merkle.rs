@@ -0,0 +1,566 @@
+//! Merkle trees of transparency logs as specified by RFC 6962.
+//!
+//! A Certificate Transparency log commits to its entries with the Merkle Tree Hash of
+//! [RFC 6962](https://tools.ietf.org/html/rfc6962#section-2.1). A client holding the
+//! root of one tree size checks two things against it without the entries:
+//!
+//! * An [`AuditProof`] shows an entry is in the tree of a given size, about `log2(n)` hashes.
+//! * A [`ConsistencyProof`] shows the tree of a larger size is the tree of the smaller size
+//!   with entries appended, so the log never rewrote or removed what it already published.
+//!
+//! Replicated state machines can use the same proofs to show their logs only grow.
+//! The proofs are checked with the algorithms of
+//! [RFC 9162](https://tools.ietf.org/html/rfc9162#section-2.1.3.2) and have the same
+//! encoding of hashes as the log's, so proofs from other RFC 6962 logs can be checked.
+//!
+//! Unlike the root of a [`mmr`](super::mmr), which only this crate computes, the root here
+//! is the one RFC 6962 defines. The tree is generic over the hash, RFC 6962 logs use
+//! `sha2::Sha256`.
+
+use sha2::Digest;
+use subtle::ConstantTimeEq;
+
+use CryptoError;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// The hash of the leaf holding `data`
+pub fn leaf_hash<D: Digest>(data: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([LEAF_PREFIX]);
+    hasher.input(data);
+    hasher.result().to_vec()
+}
+
+fn node_hash<D: Digest>(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([NODE_PREFIX]);
+    hasher.input(left);
+    hasher.input(right);
+    hasher.result().to_vec()
+}
+
+/// The largest power of two less than `n`, where the tree of `n > 1` leaves is split
+fn split(n: u64) -> u64 {
+    debug_assert!(n > 1);
+    1 << (63 - (n - 1).leading_zeros())
+}
+
+/// An RFC 6962 Merkle tree over the hash `D` that entries are appended to
+#[derive(Clone, Debug)]
+pub struct MerkleTree<D: Digest> {
+    // columns[h][k] is the root of the perfect tree over leaves k * 2^h..(k + 1) * 2^h
+    columns: Vec<Vec<Vec<u8>>>,
+    leaves: u64,
+    digest: ::std::marker::PhantomData<D>,
+}
+
+impl<D: Digest> Default for MerkleTree<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest> MerkleTree<D> {
+    pub fn new() -> Self {
+        MerkleTree {
+            columns: Vec::new(),
+            leaves: 0,
+            digest: ::std::marker::PhantomData,
+        }
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> u64 {
+        self.leaves
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves == 0
+    }
+
+    /// Append `data` as the next leaf and return its index
+    pub fn append(&mut self, data: &[u8]) -> u64 {
+        self.append_hash(leaf_hash::<D>(data))
+    }
+
+    /// Append a leaf already hashed with `leaf_hash` and return its index
+    pub fn append_hash(&mut self, hash: Vec<u8>) -> u64 {
+        let index = self.leaves;
+        let mut node = hash;
+        let mut height = 0;
+        loop {
+            if self.columns.len() == height {
+                self.columns.push(Vec::new());
+            }
+            self.columns[height].push(node);
+            let column = &self.columns[height];
+            // An even column ends with a pair of siblings that form a new parent
+            if column.len() % 2 == 1 {
+                break;
+            }
+            node = node_hash::<D>(&column[column.len() - 2], &column[column.len() - 1]);
+            height += 1;
+        }
+        self.leaves += 1;
+        index
+    }
+
+    /// The root of the tree of every leaf
+    pub fn root(&self) -> Vec<u8> {
+        self.subtree(0, self.leaves)
+    }
+
+    /// The root of the tree of the first `size` leaves, as the log published it at that size
+    pub fn root_at(&self, size: u64) -> Result<Vec<u8>, CryptoError> {
+        self.check_size(size)?;
+        Ok(self.subtree(0, size))
+    }
+
+    /// The proof that the leaf at `index` is in the tree of the first `size` leaves
+    pub fn prove_inclusion(&self, index: u64, size: u64) -> Result<AuditProof, CryptoError> {
+        self.check_size(size)?;
+        if index >= size {
+            return Err(CryptoError::GeneralError(format!(
+                "Leaf {} is not in a tree of {} leaves",
+                index, size
+            )));
+        }
+        let mut path = Vec::new();
+        self.audit_path(index, 0, size, &mut path);
+        Ok(AuditProof { index, size, path })
+    }
+
+    /// The proof that the tree of the first `new_size` leaves extends the tree of
+    /// the first `old_size` leaves
+    pub fn prove_consistency(
+        &self,
+        old_size: u64,
+        new_size: u64,
+    ) -> Result<ConsistencyProof, CryptoError> {
+        self.check_size(new_size)?;
+        if old_size == 0 || old_size > new_size {
+            return Err(CryptoError::GeneralError(format!(
+                "No consistency proof from {} to {} leaves",
+                old_size, new_size
+            )));
+        }
+        let mut path = Vec::new();
+        self.subproof(old_size, 0, new_size, true, &mut path);
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), CryptoError> {
+        if size > self.leaves {
+            return Err(CryptoError::GeneralError(format!(
+                "The tree has {} leaves, not {}",
+                self.leaves, size
+            )));
+        }
+        Ok(())
+    }
+
+    /// MTH(D[start:end])
+    fn subtree(&self, start: u64, end: u64) -> Vec<u8> {
+        let n = end - start;
+        if n == 0 {
+            return D::digest(&[]).to_vec();
+        }
+        // Perfect trees starting at a multiple of their size are stored
+        if n.is_power_of_two() && start & (n - 1) == 0 {
+            let height = n.trailing_zeros() as usize;
+            return self.columns[height][(start >> height) as usize].clone();
+        }
+        let k = split(n);
+        node_hash::<D>(
+            &self.subtree(start, start + k),
+            &self.subtree(start + k, end),
+        )
+    }
+
+    /// PATH(index, D[start:end]), from the leaf up
+    fn audit_path(&self, index: u64, start: u64, end: u64, path: &mut Vec<Vec<u8>>) {
+        let n = end - start;
+        if n == 1 {
+            return;
+        }
+        let k = split(n);
+        if index < k {
+            self.audit_path(index, start, start + k, path);
+            path.push(self.subtree(start + k, end));
+        } else {
+            self.audit_path(index - k, start + k, end, path);
+            path.push(self.subtree(start, start + k));
+        }
+    }
+
+    /// SUBPROOF(m, D[start:end], complete)
+    fn subproof(&self, m: u64, start: u64, end: u64, complete: bool, path: &mut Vec<Vec<u8>>) {
+        let n = end - start;
+        if m == n {
+            if !complete {
+                path.push(self.subtree(start, end));
+            }
+            return;
+        }
+        let k = split(n);
+        if m <= k {
+            self.subproof(m, start, start + k, complete, path);
+            path.push(self.subtree(start + k, end));
+        } else {
+            self.subproof(m - k, start + k, end, false, path);
+            path.push(self.subtree(start, start + k));
+        }
+    }
+}
+
+/// The proof that a leaf is in the tree of a given size
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditProof {
+    index: u64,
+    size: u64,
+    path: Vec<Vec<u8>>,
+}
+
+impl AuditProof {
+    /// The index of the leaf
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The number of leaves of the tree the proof is for
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Whether `data` is the leaf at `index()` of the tree of `size()` leaves with `root`
+    pub fn verify<D: Digest>(&self, root: &[u8], data: &[u8]) -> bool {
+        self.verify_hash::<D>(root, &leaf_hash::<D>(data))
+    }
+
+    /// Whether the leaf hashed with `leaf_hash` is at `index()` of the tree of `size()`
+    /// leaves with `root`
+    pub fn verify_hash<D: Digest>(&self, root: &[u8], leaf: &[u8]) -> bool {
+        if self.index >= self.size || self.path.iter().any(|p| p.len() != D::output_size()) {
+            return false;
+        }
+        let mut fnode = self.index;
+        let mut snode = self.size - 1;
+        let mut node = leaf.to_vec();
+        for sibling in &self.path {
+            if snode == 0 {
+                return false;
+            }
+            if fnode & 1 == 1 || fnode == snode {
+                node = node_hash::<D>(sibling, &node);
+                // Skip the levels where this subtree has no right sibling
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            } else {
+                node = node_hash::<D>(&node, sibling);
+            }
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        snode == 0 && bool::from(node.ct_eq(root))
+    }
+
+    /// The index and tree size as 8 bytes big endian each, the number of path hashes
+    /// as 1 byte, then the hash size as 1 byte and the path
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self.index, self.size, &self.path)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let (index, size, path) = decode(bytes, "Audit proof")?;
+        Ok(AuditProof { index, size, path })
+    }
+}
+
+/// The proof that the tree of one size extends the tree of a smaller size
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    old_size: u64,
+    new_size: u64,
+    path: Vec<Vec<u8>>,
+}
+
+impl ConsistencyProof {
+    /// The number of leaves of the older tree
+    pub fn old_size(&self) -> u64 {
+        self.old_size
+    }
+
+    /// The number of leaves of the newer tree
+    pub fn new_size(&self) -> u64 {
+        self.new_size
+    }
+
+    /// Whether the tree of `new_size()` leaves with `new_root` extends the tree of
+    /// `old_size()` leaves with `old_root`
+    pub fn verify<D: Digest>(&self, old_root: &[u8], new_root: &[u8]) -> bool {
+        if self.old_size == 0
+            || self.old_size > self.new_size
+            || self.path.iter().any(|p| p.len() != D::output_size())
+        {
+            return false;
+        }
+        if self.old_size == self.new_size {
+            return self.path.is_empty()
+                && bool::from(old_root.ct_eq(new_root))
+                && old_root.len() == D::output_size();
+        }
+        let mut path = self.path.iter();
+        // The old tree is a perfect tree in the new one, its root starts the path
+        let first = if self.old_size.is_power_of_two() {
+            old_root
+        } else {
+            match path.next() {
+                Some(first) => first.as_slice(),
+                None => return false,
+            }
+        };
+        let mut fnode = self.old_size - 1;
+        let mut snode = self.new_size - 1;
+        while fnode & 1 == 1 {
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        let mut old = first.to_vec();
+        let mut new = first.to_vec();
+        for c in path {
+            if snode == 0 {
+                return false;
+            }
+            if fnode & 1 == 1 || fnode == snode {
+                old = node_hash::<D>(c, &old);
+                new = node_hash::<D>(c, &new);
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            } else {
+                new = node_hash::<D>(&new, c);
+            }
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        snode == 0 && bool::from(old.ct_eq(old_root)) & bool::from(new.ct_eq(new_root))
+    }
+
+    /// The old and new tree sizes as 8 bytes big endian each, the number of path hashes
+    /// as 1 byte, then the hash size as 1 byte and the path
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self.old_size, self.new_size, &self.path)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let (old_size, new_size, path) = decode(bytes, "Consistency proof")?;
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+}
+
+fn encode(first: u64, second: u64, path: &[Vec<u8>]) -> Vec<u8> {
+    let size = path.first().map(Vec::len).unwrap_or(0);
+    let mut out = Vec::with_capacity(18 + size * path.len());
+    out.extend_from_slice(&first.to_be_bytes());
+    out.extend_from_slice(&second.to_be_bytes());
+    out.push(path.len() as u8);
+    out.push(size as u8);
+    for hash in path {
+        out.extend_from_slice(hash);
+    }
+    out
+}
+
+fn decode(bytes: &[u8], name: &str) -> Result<(u64, u64, Vec<Vec<u8>>), CryptoError> {
+    if bytes.len() < 18 {
+        return Err(CryptoError::ParseError(format!("{} is too short", name)));
+    }
+    let mut first = [0u8; 8];
+    first.copy_from_slice(&bytes[..8]);
+    let mut second = [0u8; 8];
+    second.copy_from_slice(&bytes[8..16]);
+    let path_len = bytes[16] as usize;
+    let size = bytes[17] as usize;
+    let hashes = &bytes[18..];
+    if (size == 0 && path_len != 0) || hashes.len() != size * path_len {
+        return Err(CryptoError::ParseError(format!(
+            "{} has the wrong length",
+            name
+        )));
+    }
+    Ok((
+        u64::from_be_bytes(first),
+        u64::from_be_bytes(second),
+        hashes.chunks(size.max(1)).map(<[u8]>::to_vec).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Sha256, Sha512};
+
+    /// The entries of the RFC 6962 test log of the certificate-transparency project
+    fn entries() -> Vec<Vec<u8>> {
+        vec![
+            vec![],
+            vec![0x00],
+            vec![0x10],
+            vec![0x20, 0x21],
+            vec![0x30, 0x31],
+            vec![0x40, 0x41, 0x42, 0x43],
+            (0x50..0x58).collect(),
+            (0x60..0x70).collect(),
+        ]
+    }
+
+    #[test]
+    fn known_roots() {
+        let mut tree = MerkleTree::<Sha256>::new();
+        assert_eq!(
+            hex::encode(tree.root()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        for entry in entries() {
+            tree.append(&entry);
+        }
+        let roots = [
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+        for (size, root) in roots.iter().enumerate() {
+            assert_eq!(hex::encode(tree.root_at(size as u64 + 1).unwrap()), *root);
+        }
+        assert_eq!(tree.root(), tree.root_at(8).unwrap());
+        assert!(tree.root_at(9).is_err());
+
+        // RFC 6962 section 2.1.3, the consistency proof from 3 to 7 leaves is [c, d, g, l]
+        let leaves = entries()
+            .iter()
+            .map(|e| leaf_hash::<Sha256>(e))
+            .collect::<Vec<_>>();
+        let c = leaves[2].clone();
+        let d = leaves[3].clone();
+        let g = node_hash::<Sha256>(&leaves[0], &leaves[1]);
+        let l = tree.subtree(4, 7);
+        let proof = tree.prove_consistency(3, 7).unwrap();
+        assert_eq!(proof.path, vec![c, d, g, l]);
+    }
+
+    #[test]
+    fn inclusion_and_consistency() {
+        let mut tree = MerkleTree::<Sha256>::new();
+        for i in 0..40u32 {
+            tree.append(&i.to_be_bytes());
+        }
+        for size in 1..=40u64 {
+            let root = tree.root_at(size).unwrap();
+            for index in 0..size {
+                let data = (index as u32).to_be_bytes();
+                let proof = tree.prove_inclusion(index, size).unwrap();
+                assert!(proof.verify::<Sha256>(&root, &data));
+                assert!(!proof.verify::<Sha256>(&root, &(index as u32 + 1).to_be_bytes()));
+                assert_eq!(AuditProof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+            }
+            assert!(tree.prove_inclusion(size, size).is_err());
+
+            for old_size in 1..=size {
+                let old_root = tree.root_at(old_size).unwrap();
+                let proof = tree.prove_consistency(old_size, size).unwrap();
+                assert!(proof.verify::<Sha256>(&old_root, &root));
+                assert_eq!(
+                    ConsistencyProof::from_bytes(&proof.to_bytes()).unwrap(),
+                    proof
+                );
+                if old_size < size {
+                    // A forked log with another entry at old_size - 1
+                    let mut fork = MerkleTree::<Sha256>::new();
+                    for i in 0..size as u32 {
+                        fork.append(&(i + (i == old_size as u32 - 1) as u32 * 1000).to_be_bytes());
+                    }
+                    assert!(!proof.verify::<Sha256>(&fork.root_at(old_size).unwrap(), &root));
+                    assert!(!proof.verify::<Sha256>(&old_root, &fork.root_at(size).unwrap()));
+                }
+            }
+        }
+        assert!(tree.prove_consistency(0, 5).is_err());
+        assert!(tree.prove_consistency(6, 5).is_err());
+        assert!(tree.prove_consistency(5, 41).is_err());
+    }
+
+    #[test]
+    fn tampering() {
+        let mut tree = MerkleTree::<Sha512>::new();
+        for i in 0..13u8 {
+            tree.append(&[i]);
+        }
+        let (old_root, root) = (tree.root_at(6).unwrap(), tree.root());
+
+        let proof = tree.prove_inclusion(9, 13).unwrap();
+        assert!(proof.verify::<Sha512>(&root, &[9]));
+        assert!(!proof.verify::<Sha256>(&root, &[9]));
+        let mut bytes = proof.to_bytes();
+        // Another index or tree size
+        for i in [7, 15].iter() {
+            bytes[*i] ^= 1;
+            let moved = AuditProof::from_bytes(&bytes).unwrap();
+            assert!(!moved.verify::<Sha512>(&root, &[9]));
+            bytes[*i] ^= 1;
+        }
+        // A changed path hash
+        bytes[20] ^= 1;
+        let changed = AuditProof::from_bytes(&bytes).unwrap();
+        assert!(!changed.verify::<Sha512>(&root, &[9]));
+        assert!(AuditProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(AuditProof::from_bytes(&bytes[..17]).is_err());
+
+        let proof = tree.prove_consistency(6, 13).unwrap();
+        assert!(proof.verify::<Sha512>(&old_root, &root));
+        assert!(!proof.verify::<Sha512>(&root, &old_root));
+        // The sizes are authenticated by the signed tree heads with the roots, a new size
+        // that gives the same path structure can verify, but not another old size
+        let mut bytes = proof.to_bytes();
+        for i in [7, 20].iter() {
+            bytes[*i] ^= 1;
+            let changed = ConsistencyProof::from_bytes(&bytes).unwrap();
+            assert!(!changed.verify::<Sha512>(&old_root, &root));
+            bytes[*i] ^= 1;
+        }
+        // Dropping or adding a hash
+        let mut short = proof.clone();
+        short.path.pop();
+        assert!(!short.verify::<Sha512>(&old_root, &root));
+        let mut long = proof.clone();
+        long.path.push(root.clone());
+        assert!(!long.verify::<Sha512>(&old_root, &root));
+
+        // Equal sizes only need equal roots
+        let same = tree.prove_consistency(13, 13).unwrap();
+        assert!(same.verify::<Sha512>(&root, &root));
+        assert!(!same.verify::<Sha512>(&old_root, &root));
+
+        // A node can't be proven as a leaf
+        let node = node_hash::<Sha512>(&leaf_hash::<Sha512>(&[8]), &leaf_hash::<Sha512>(&[9]));
+        let proof = tree.prove_inclusion(8, 13).unwrap();
+        assert!(!proof.verify_hash::<Sha512>(&root, &node));
+    }
+}
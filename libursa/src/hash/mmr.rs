@@ -0,0 +1,372 @@
+//! Append-only Merkle Mountain Ranges.
+//!
+//! A Merkle Mountain Range commits to an ever-growing sequence of entries, like the
+//! records of a transparency log. Appending never changes a stored node: the range is
+//! a list of perfect binary trees, the mountains, one for each bit set in the number
+//! of leaves, and a new leaf only merges the equal sized mountains at the end. The
+//! nodes are kept one column per height, so the sibling of a node is the neighbour in
+//! its column.
+//!
+//! The root bags the peaks of all the mountains together with the number of leaves.
+//! An [`InclusionProof`] holds the path from a leaf to its peak and all the peaks,
+//! about `log2(n)` hashes, and is checked against a root without the range.
+//!
+//! Leaves, nodes and the root are hashed with different prefixes, as in RFC 6962, so
+//! a leaf can't be passed off as a node. The range is generic over the hash, any
+//! [`Digest`] such as `sha2::Sha256` works.
+
+use sha2::Digest;
+use subtle::ConstantTimeEq;
+
+use CryptoError;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// The hash of the leaf holding `data`
+pub fn leaf_hash<D: Digest>(data: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([LEAF_PREFIX]);
+    hasher.input(data);
+    hasher.result().to_vec()
+}
+
+fn node_hash<D: Digest>(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([NODE_PREFIX]);
+    hasher.input(left);
+    hasher.input(right);
+    hasher.result().to_vec()
+}
+
+fn bag_peaks<D: Digest>(leaves: u64, peaks: &[Vec<u8>]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([ROOT_PREFIX]);
+    hasher.input(leaves.to_be_bytes());
+    for peak in peaks {
+        hasher.input(peak);
+    }
+    hasher.result().to_vec()
+}
+
+/// The mountain holding leaf `index` of a range of `leaves` leaves, as the position
+/// of its peak, its height and its first leaf
+fn mountain(index: u64, leaves: u64) -> (usize, usize, u64) {
+    let mut start = 0u64;
+    let mut position = 0;
+    for height in (0..64usize).rev() {
+        let size = 1u64 << height;
+        if leaves & size == 0 {
+            continue;
+        }
+        if index < start + size {
+            return (position, height, start);
+        }
+        start += size;
+        position += 1;
+    }
+    unreachable!("index is less than the number of leaves")
+}
+
+/// An append-only Merkle Mountain Range over the hash `D`
+#[derive(Clone, Debug)]
+pub struct MerkleMountainRange<D: Digest> {
+    // columns[h][k] is the root of the perfect tree over leaves k * 2^h..(k + 1) * 2^h
+    columns: Vec<Vec<Vec<u8>>>,
+    leaves: u64,
+    digest: ::std::marker::PhantomData<D>,
+}
+
+impl<D: Digest> Default for MerkleMountainRange<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest> MerkleMountainRange<D> {
+    pub fn new() -> Self {
+        MerkleMountainRange {
+            columns: Vec::new(),
+            leaves: 0,
+            digest: ::std::marker::PhantomData,
+        }
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> u64 {
+        self.leaves
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves == 0
+    }
+
+    /// Append `data` as the next leaf and return its index
+    pub fn append(&mut self, data: &[u8]) -> u64 {
+        self.append_hash(leaf_hash::<D>(data))
+    }
+
+    /// Append a leaf already hashed with `leaf_hash` and return its index
+    pub fn append_hash(&mut self, hash: Vec<u8>) -> u64 {
+        let index = self.leaves;
+        let mut node = hash;
+        let mut height = 0;
+        loop {
+            if self.columns.len() == height {
+                self.columns.push(Vec::new());
+            }
+            self.columns[height].push(node);
+            let column = &self.columns[height];
+            // An even column ends with a pair of siblings that form a new parent
+            if column.len() % 2 == 1 {
+                break;
+            }
+            node = node_hash::<D>(&column[column.len() - 2], &column[column.len() - 1]);
+            height += 1;
+        }
+        self.leaves += 1;
+        index
+    }
+
+    /// The peaks of the mountains from the highest to the lowest
+    pub fn peaks(&self) -> Vec<Vec<u8>> {
+        let mut peaks = Vec::new();
+        let mut start = 0u64;
+        for height in (0..self.columns.len()).rev() {
+            if self.leaves & (1 << height) != 0 {
+                peaks.push(self.columns[height][(start >> height) as usize].clone());
+                start += 1 << height;
+            }
+        }
+        peaks
+    }
+
+    /// The commitment to every leaf and their order
+    pub fn root(&self) -> Vec<u8> {
+        bag_peaks::<D>(self.leaves, &self.peaks())
+    }
+
+    /// The proof that the leaf at `index` is in the range with the current root
+    pub fn prove(&self, index: u64) -> Result<InclusionProof, CryptoError> {
+        if index >= self.leaves {
+            return Err(CryptoError::GeneralError(format!(
+                "Leaf {} is not in a range of {} leaves",
+                index, self.leaves
+            )));
+        }
+        let (_, height, _) = mountain(index, self.leaves);
+        let mut path = Vec::with_capacity(height);
+        let mut k = index;
+        for column in &self.columns[..height] {
+            path.push(column[(k ^ 1) as usize].clone());
+            k >>= 1;
+        }
+        Ok(InclusionProof {
+            index,
+            leaves: self.leaves,
+            path,
+            peaks: self.peaks(),
+        })
+    }
+}
+
+/// The proof that a leaf is in a Merkle Mountain Range with a given root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    index: u64,
+    leaves: u64,
+    path: Vec<Vec<u8>>,
+    peaks: Vec<Vec<u8>>,
+}
+
+impl InclusionProof {
+    /// The index of the leaf
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The number of leaves of the range the proof is for
+    pub fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    /// Whether `data` is the leaf at `index()` of the range with `root`
+    pub fn verify<D: Digest>(&self, root: &[u8], data: &[u8]) -> bool {
+        self.verify_hash::<D>(root, &leaf_hash::<D>(data))
+    }
+
+    /// Whether the leaf hashed with `leaf_hash` is at `index()` of the range with `root`
+    pub fn verify_hash<D: Digest>(&self, root: &[u8], leaf: &[u8]) -> bool {
+        if self.index >= self.leaves
+            || self.peaks.len() != self.leaves.count_ones() as usize
+            || self.peaks.iter().any(|p| p.len() != D::output_size())
+        {
+            return false;
+        }
+        let (position, height, start) = mountain(self.index, self.leaves);
+        if self.path.len() != height {
+            return false;
+        }
+        let mut node = leaf.to_vec();
+        let mut k = self.index - start;
+        for sibling in &self.path {
+            node = if k & 1 == 0 {
+                node_hash::<D>(&node, sibling)
+            } else {
+                node_hash::<D>(sibling, &node)
+            };
+            k >>= 1;
+        }
+        if !bool::from(node.ct_eq(&self.peaks[position])) {
+            return false;
+        }
+        bag_peaks::<D>(self.leaves, &self.peaks).ct_eq(root).into()
+    }
+
+    /// The index and number of leaves as 8 bytes big endian each, the number of path
+    /// hashes as 1 byte, then the hash size as 1 byte, the path and the peaks
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self.peaks.first().map(Vec::len).unwrap_or(0);
+        let mut out = Vec::with_capacity(18 + size * (self.path.len() + self.peaks.len()));
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&self.leaves.to_be_bytes());
+        out.push(self.path.len() as u8);
+        out.push(size as u8);
+        for hash in self.path.iter().chain(self.peaks.iter()) {
+            out.extend_from_slice(hash);
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() < 18 {
+            return Err(CryptoError::ParseError(
+                "Inclusion proof is too short".to_string(),
+            ));
+        }
+        let mut index = [0u8; 8];
+        index.copy_from_slice(&bytes[..8]);
+        let mut leaves = [0u8; 8];
+        leaves.copy_from_slice(&bytes[8..16]);
+        let leaves = u64::from_be_bytes(leaves);
+        let path_len = bytes[16] as usize;
+        let size = bytes[17] as usize;
+        let peaks_len = leaves.count_ones() as usize;
+        let hashes = &bytes[18..];
+        if size == 0 || hashes.len() != size * (path_len + peaks_len) {
+            return Err(CryptoError::ParseError(
+                "Inclusion proof has the wrong length".to_string(),
+            ));
+        }
+        let mut hashes = hashes.chunks(size).map(<[u8]>::to_vec);
+        Ok(InclusionProof {
+            index: u64::from_be_bytes(index),
+            leaves,
+            path: hashes.by_ref().take(path_len).collect(),
+            peaks: hashes.collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Sha256, Sha512};
+
+    #[test]
+    fn append_and_prove() {
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        assert!(mmr.is_empty());
+        let mut roots = Vec::new();
+        for i in 0..37u32 {
+            assert_eq!(mmr.append(&i.to_be_bytes()), i as u64);
+            assert_eq!(mmr.peaks().len(), mmr.len().count_ones() as usize);
+            roots.push(mmr.root());
+        }
+        roots.sort();
+        roots.dedup();
+        assert_eq!(roots.len(), 37);
+
+        let root = mmr.root();
+        for i in 0..37u32 {
+            let proof = mmr.prove(i as u64).unwrap();
+            assert!(proof.verify::<Sha256>(&root, &i.to_be_bytes()));
+            assert!(!proof.verify::<Sha256>(&root, &(i + 1).to_be_bytes()));
+            assert!(!proof.verify::<Sha256>(&roots[0], &i.to_be_bytes()));
+            let decoded = InclusionProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert_eq!(decoded, proof);
+        }
+        assert!(mmr.prove(37).is_err());
+
+        // Proofs stay valid against the root they were made for
+        let proof = mmr.prove(5).unwrap();
+        mmr.append(b"more");
+        assert!(proof.verify::<Sha256>(&root, &5u32.to_be_bytes()));
+        assert!(!proof.verify::<Sha256>(&mmr.root(), &5u32.to_be_bytes()));
+    }
+
+    #[test]
+    fn structure() {
+        // Seven leaves are mountains of four, two and one leaves
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        let leaves = (0..7u8)
+            .map(|i| leaf_hash::<Sha256>(&[i]))
+            .collect::<Vec<_>>();
+        for leaf in &leaves {
+            mmr.append_hash(leaf.clone());
+        }
+        let n01 = node_hash::<Sha256>(&leaves[0], &leaves[1]);
+        let n23 = node_hash::<Sha256>(&leaves[2], &leaves[3]);
+        let n45 = node_hash::<Sha256>(&leaves[4], &leaves[5]);
+        let n0123 = node_hash::<Sha256>(&n01, &n23);
+        assert_eq!(
+            mmr.peaks(),
+            vec![n0123.clone(), n45.clone(), leaves[6].clone()]
+        );
+        let mut hasher = Sha256::new();
+        hasher.input([ROOT_PREFIX]);
+        hasher.input(7u64.to_be_bytes());
+        hasher.input(&n0123);
+        hasher.input(&n45);
+        hasher.input(&leaves[6]);
+        assert_eq!(mmr.root(), hasher.result().to_vec());
+
+        let proof = mmr.prove(2).unwrap();
+        assert_eq!(proof.path, vec![leaves[3].clone(), n01]);
+        assert!(mmr.prove(6).unwrap().path.is_empty());
+    }
+
+    #[test]
+    fn tampering() {
+        let mut mmr = MerkleMountainRange::<Sha512>::new();
+        for i in 0..12u8 {
+            mmr.append(&[i]);
+        }
+        let root = mmr.root();
+        let proof = mmr.prove(9).unwrap();
+        assert!(proof.verify::<Sha512>(&root, &[9]));
+        assert!(!proof.verify::<Sha256>(&root, &[9]));
+
+        let mut bytes = proof.to_bytes();
+        // Another index
+        bytes[7] ^= 1;
+        let moved = InclusionProof::from_bytes(&bytes).unwrap();
+        assert!(!moved.verify::<Sha512>(&root, &[9]));
+        bytes[7] ^= 1;
+        // A changed path hash
+        bytes[20] ^= 1;
+        let changed = InclusionProof::from_bytes(&bytes).unwrap();
+        assert!(!changed.verify::<Sha512>(&root, &[9]));
+        bytes[20] ^= 1;
+        // Another number of leaves changes the number of peaks
+        bytes[15] ^= 1;
+        assert!(InclusionProof::from_bytes(&bytes).is_err());
+        assert!(InclusionProof::from_bytes(&bytes[..17]).is_err());
+
+        // A node can't be proven as a leaf
+        let node = node_hash::<Sha512>(&leaf_hash::<Sha512>(&[8]), &leaf_hash::<Sha512>(&[9]));
+        let proof = mmr.prove(8).unwrap();
+        assert!(!proof.verify_hash::<Sha512>(&root, &node));
+    }
+}
@@ -8,3 +8,11 @@ pub use sha2::Digest;
 
 #[cfg(feature = "blake2")]
 pub mod blake2;
+#[cfg(feature = "hash_commitment")]
+pub mod commitment;
+#[cfg(feature = "merkle")]
+pub mod merkle;
+#[cfg(feature = "mmr")]
+pub mod mmr;
+#[cfg(feature = "sparse_merkle")]
+pub mod sparse_merkle;
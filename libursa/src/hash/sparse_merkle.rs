@@ -0,0 +1,494 @@
+//! Sparse Merkle trees with proofs of inclusion and non-inclusion.
+//!
+//! A sparse Merkle tree maps 256 bit keys to values, each key has its own leaf in a
+//! tree of depth 256 and the leaves of all the keys with no value are empty. Only the
+//! subtrees holding two or more values are stored: a subtree holding one value is
+//! replaced by its leaf, so a tree of `n` values has `2n - 1` nodes and its proofs have
+//! about `log2(n)` hashes, as in CONIKS. The path of a key ends at its leaf if it has a
+//! value, otherwise at an empty subtree or at the leaf of another key with the same
+//! prefix, which a [`SparseMerkleProof`] checks to show the key has no value.
+//!
+//! ```text
+//! empty        = 0^n
+//! leaf(k, v)   = H(0x00 | k | H(v))
+//! node(l, r)   = H(0x01 | l | r)
+//! ```
+//!
+//! Leaves hold the hash of their value, so a proof that a key has no value doesn't
+//! reveal the value of the key it ends at. The hashes of the stored subtrees are kept,
+//! and [`SparseMerkleTree::update`] changes many keys at once and rehashes each subtree
+//! they share once. The tree is generic over the hash, any [`Digest`] such as
+//! `sha2::Sha256` works. Keys should be hashes of the application's identifiers, so they
+//! are spread over the tree and its depth stays about `log2(n)`.
+//!
+//! A proof is encoded as
+//!
+//! ```text
+//! count (2, big endian) | hash size (1) | count * sibling | 0x00
+//! count (2, big endian) | hash size (1) | count * sibling | 0x01 | key (32) | value hash
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use sha2::Digest;
+use subtle::ConstantTimeEq;
+
+use CryptoError;
+
+/// The size of keys
+pub const KEY_SIZE: usize = 32;
+
+/// A key of the tree, the bits from the most significant bit of the first byte on give
+/// its path from the root
+pub type Key = [u8; KEY_SIZE];
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const DEPTH: usize = 8 * KEY_SIZE;
+
+type Nodes = Vec<((usize, Key), Vec<u8>)>;
+
+/// The hash of the leaf of `key` holding the value with `value_hash`
+pub fn leaf_hash<D: Digest>(key: &Key, value_hash: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([LEAF_PREFIX]);
+    hasher.input(key);
+    hasher.input(value_hash);
+    hasher.result().to_vec()
+}
+
+fn node_hash<D: Digest>(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.input([NODE_PREFIX]);
+    hasher.input(left);
+    hasher.input(right);
+    hasher.result().to_vec()
+}
+
+fn empty<D: Digest>() -> Vec<u8> {
+    vec![0u8; D::output_size()]
+}
+
+/// A sparse Merkle tree over the hash `D`
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<D: Digest> {
+    // The value and its hash of each key
+    leaves: BTreeMap<Key, (Vec<u8>, Vec<u8>)>,
+    // The hash of each subtree holding two or more values, by its depth and the
+    // prefix of its keys with the other bits cleared
+    nodes: HashMap<(usize, Key), Vec<u8>>,
+    root: Vec<u8>,
+    digest: ::std::marker::PhantomData<D>,
+}
+
+impl<D: Digest> Default for SparseMerkleTree<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest> SparseMerkleTree<D> {
+    pub fn new() -> Self {
+        SparseMerkleTree {
+            leaves: BTreeMap::new(),
+            nodes: HashMap::new(),
+            root: empty::<D>(),
+            digest: ::std::marker::PhantomData,
+        }
+    }
+
+    /// The number of keys with a value
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// The value of `key`
+    pub fn get(&self, key: &Key) -> Option<&[u8]> {
+        self.leaves.get(key).map(|(value, _)| value.as_slice())
+    }
+
+    /// The root hash
+    pub fn root(&self) -> &[u8] {
+        &self.root
+    }
+
+    /// Set the value of `key`, returning the previous one
+    pub fn insert(&mut self, key: Key, value: &[u8]) -> Option<Vec<u8>> {
+        let previous = self.set(key, Some(value.to_vec()));
+        self.rehash();
+        previous
+    }
+
+    /// Remove the value of `key`, returning it
+    pub fn remove(&mut self, key: &Key) -> Option<Vec<u8>> {
+        let previous = self.set(*key, None);
+        self.rehash();
+        previous
+    }
+
+    /// Set or, for `None`, remove the values of many keys and rehash the tree once.
+    /// A key changed more than once ends with its last value.
+    pub fn update<I>(&mut self, changes: I)
+    where
+        I: IntoIterator<Item = (Key, Option<Vec<u8>>)>,
+    {
+        for (key, value) in changes {
+            self.set(key, value);
+        }
+        self.rehash();
+    }
+
+    /// The proof of the value of `key`, or that it has none
+    pub fn prove(&self, key: &Key) -> SparseMerkleProof {
+        let mut siblings = Vec::new();
+        let mut prefix = [0u8; KEY_SIZE];
+        let mut depth = 0;
+        while self.nodes.contains_key(&(depth, prefix)) {
+            let mut sibling = prefix;
+            if bit(key, depth) {
+                set_bit(&mut prefix, depth);
+            } else {
+                set_bit(&mut sibling, depth);
+            }
+            siblings.push(self.subtree(depth + 1, sibling, &mut Vec::new()));
+            depth += 1;
+        }
+        let terminal = self
+            .leaves
+            .range(prefix..=last(&prefix, depth))
+            .next()
+            .map(|(key, (_, hash))| (*key, hash.clone()));
+        SparseMerkleProof { siblings, terminal }
+    }
+
+    fn set(&mut self, key: Key, value: Option<Vec<u8>>) -> Option<Vec<u8>> {
+        // Every subtree on the path of the key changes, the ones above it in a batch
+        // may already be removed by other keys
+        let mut prefix = [0u8; KEY_SIZE];
+        for depth in 0..DEPTH {
+            self.nodes.remove(&(depth, prefix));
+            if bit(&key, depth) {
+                set_bit(&mut prefix, depth);
+            }
+        }
+        let previous = match value {
+            Some(value) => {
+                let hash = D::digest(&value).to_vec();
+                self.leaves.insert(key, (value, hash))
+            }
+            None => self.leaves.remove(&key),
+        };
+        previous.map(|(value, _)| value)
+    }
+
+    fn rehash(&mut self) {
+        let mut computed = Vec::new();
+        self.root = self.subtree(0, [0u8; KEY_SIZE], &mut computed);
+        self.nodes.extend(computed);
+    }
+
+    /// The hash of the subtree at `depth` with `prefix`, adding the subtrees whose hashes
+    /// weren't kept to `computed`
+    fn subtree(&self, depth: usize, prefix: Key, computed: &mut Nodes) -> Vec<u8> {
+        let mut leaves = self.leaves.range(prefix..=last(&prefix, depth));
+        let (key, hash) = match (leaves.next(), leaves.next()) {
+            (None, _) => return empty::<D>(),
+            (Some((key, (_, hash))), None) => return leaf_hash::<D>(key, hash),
+            _ => match self.nodes.get(&(depth, prefix)) {
+                Some(hash) => return hash.clone(),
+                None => {
+                    let mut right = prefix;
+                    set_bit(&mut right, depth);
+                    let left = self.subtree(depth + 1, prefix, computed);
+                    let right = self.subtree(depth + 1, right, computed);
+                    ((depth, prefix), node_hash::<D>(&left, &right))
+                }
+            },
+        };
+        computed.push((key, hash.clone()));
+        hash
+    }
+}
+
+/// The siblings on the path of a key and the key and value hash of the leaf it ends at,
+/// if any
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseMerkleProof {
+    siblings: Vec<Vec<u8>>,
+    terminal: Option<(Key, Vec<u8>)>,
+}
+
+impl SparseMerkleProof {
+    /// Whether `key` has `value` in the tree with `root`, or has no value if `value`
+    /// is `None`
+    pub fn verify<D: Digest>(&self, root: &[u8], key: &Key, value: Option<&[u8]>) -> bool {
+        let hash = value.map(|v| D::digest(v).to_vec());
+        self.verify_hash::<D>(root, key, hash.as_deref())
+    }
+
+    /// Whether `key` has a value with `value_hash` in the tree with `root`, or has no
+    /// value if `value_hash` is `None`
+    pub fn verify_hash<D: Digest>(
+        &self,
+        root: &[u8],
+        key: &Key,
+        value_hash: Option<&[u8]>,
+    ) -> bool {
+        let depth = self.siblings.len();
+        if depth > DEPTH || self.siblings.iter().any(|s| s.len() != D::output_size()) {
+            return false;
+        }
+        let mut hash = match (self.terminal.as_ref(), value_hash) {
+            (Some((k, h)), Some(value_hash)) if k == key && h[..] == value_hash[..] => {
+                leaf_hash::<D>(k, h)
+            }
+            // The only leaf in the subtree of the key is another key's
+            (Some((k, h)), None) if k != key && (0..depth).all(|d| bit(k, d) == bit(key, d)) => {
+                leaf_hash::<D>(k, h)
+            }
+            (None, None) => empty::<D>(),
+            _ => return false,
+        };
+        for (d, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = if bit(key, d) {
+                node_hash::<D>(sibling, &hash)
+            } else {
+                node_hash::<D>(&hash, sibling)
+            };
+        }
+        hash.ct_eq(root).into()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self
+            .siblings
+            .first()
+            .or_else(|| self.terminal.as_ref().map(|(_, h)| h))
+            .map_or(0, Vec::len);
+        let mut output = Vec::with_capacity(4 + KEY_SIZE + (self.siblings.len() + 1) * size);
+        output.extend_from_slice(&(self.siblings.len() as u16).to_be_bytes());
+        output.push(size as u8);
+        for sibling in &self.siblings {
+            output.extend_from_slice(sibling);
+        }
+        match self.terminal {
+            None => output.push(0),
+            Some((ref key, ref hash)) => {
+                output.push(1);
+                output.extend_from_slice(key);
+                output.extend_from_slice(hash);
+            }
+        }
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::ParseError("Invalid sparse Merkle proof".to_string());
+        if data.len() < 4 {
+            return Err(invalid());
+        }
+        let count = u16::from_be_bytes([data[0], data[1]]) as usize;
+        let size = data[2] as usize;
+        let end = 3 + count * size;
+        if count > DEPTH || (size == 0 && count != 0) || data.len() <= end {
+            return Err(invalid());
+        }
+        let siblings = data[3..end]
+            .chunks(size.max(1))
+            .map(<[u8]>::to_vec)
+            .collect();
+        let rest = &data[end + 1..];
+        let terminal = match data[end] {
+            0 if rest.is_empty() => None,
+            1 if size != 0 && rest.len() == KEY_SIZE + size => {
+                let mut key = [0u8; KEY_SIZE];
+                key.copy_from_slice(&rest[..KEY_SIZE]);
+                Some((key, rest[KEY_SIZE..].to_vec()))
+            }
+            _ => return Err(invalid()),
+        };
+        Ok(SparseMerkleProof { siblings, terminal })
+    }
+}
+
+fn bit(key: &Key, depth: usize) -> bool {
+    (key[depth >> 3] >> (7 - (depth & 7))) & 1 == 1
+}
+
+fn set_bit(key: &mut Key, depth: usize) {
+    key[depth >> 3] |= 0x80 >> (depth & 7);
+}
+
+/// The largest key with `prefix`, whose bits from `depth` on are cleared
+fn last(prefix: &Key, depth: usize) -> Key {
+    let mut key = *prefix;
+    for d in depth..DEPTH {
+        set_bit(&mut key, d);
+    }
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Sha256, Sha512};
+
+    fn key(i: u32) -> Key {
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&Sha256::digest(&i.to_be_bytes()));
+        key
+    }
+
+    #[test]
+    fn proofs() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        assert_eq!(tree.root(), &[0u8; 32][..]);
+        let proof = tree.prove(&key(0));
+        assert!(proof.verify::<Sha256>(tree.root(), &key(0), None));
+
+        for i in 0..100u32 {
+            assert_eq!(tree.insert(key(i), &i.to_be_bytes()), None);
+        }
+        assert_eq!(tree.len(), 100);
+        assert_eq!(tree.get(&key(7)), Some(&7u32.to_be_bytes()[..]));
+        let root = tree.root().to_vec();
+        for i in 0..100u32 {
+            let value = i.to_be_bytes();
+            let proof = tree.prove(&key(i));
+            assert!(proof.siblings.len() < 20);
+            assert!(proof.verify::<Sha256>(&root, &key(i), Some(&value)));
+            assert!(proof.verify_hash::<Sha256>(&root, &key(i), Some(&Sha256::digest(&value))));
+            assert!(!proof.verify::<Sha256>(&root, &key(i), Some(b"other")));
+            assert!(!proof.verify::<Sha256>(&root, &key(i), None));
+            assert!(!proof.verify::<Sha512>(&root, &key(i), Some(&value)));
+            assert_eq!(
+                SparseMerkleProof::from_bytes(&proof.to_bytes()).unwrap(),
+                proof
+            );
+        }
+        for i in 100..200u32 {
+            let proof = tree.prove(&key(i));
+            assert!(proof.verify::<Sha256>(&root, &key(i), None));
+            assert!(!proof.verify::<Sha256>(&root, &key(i), Some(&i.to_be_bytes())));
+            assert!(!proof.verify::<Sha256>(&root, &key(i + 1), None));
+            assert_eq!(
+                SparseMerkleProof::from_bytes(&proof.to_bytes()).unwrap(),
+                proof
+            );
+        }
+
+        // A proof of one key shows the keys in its subtree have no value, no others
+        let proof = tree.prove(&key(5));
+        let mut other = key(5);
+        other[31] ^= 1;
+        assert!(proof.verify::<Sha256>(&root, &other, None));
+        let mut stray = key(5);
+        stray[0] ^= 0x80;
+        assert!(!proof.verify::<Sha256>(&root, &stray, None));
+
+        assert_eq!(tree.remove(&key(5)), Some(5u32.to_be_bytes().to_vec()));
+        assert_eq!(tree.remove(&key(5)), None);
+        let updated = tree.root().to_vec();
+        assert_ne!(updated, root);
+        assert!(tree
+            .prove(&key(5))
+            .verify::<Sha256>(&updated, &key(5), None));
+        tree.insert(key(5), &5u32.to_be_bytes());
+        assert_eq!(tree.root(), &root[..]);
+        assert_eq!(tree.insert(key(5), b"6"), Some(5u32.to_be_bytes().to_vec()));
+        assert_ne!(tree.root(), &root[..]);
+    }
+
+    #[test]
+    fn batches() {
+        // Keys sharing long prefixes, so subtrees become and stop being stored
+        let keys = (0..64u8)
+            .map(|i| {
+                let mut k = [0u8; KEY_SIZE];
+                k[0] = i & 0xf0;
+                k[31] = i;
+                k
+            })
+            .collect::<Vec<_>>();
+        let mut single = SparseMerkleTree::<Sha512>::new();
+        for k in &keys {
+            single.insert(*k, &k[31..]);
+        }
+        let mut batched = SparseMerkleTree::<Sha512>::new();
+        batched.update(keys.iter().map(|k| (*k, Some(k[31..].to_vec()))));
+        assert_eq!(batched.root(), single.root());
+        assert_eq!(batched.root().len(), 64);
+
+        let changes = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                (
+                    *k,
+                    if i % 3 == 0 {
+                        None
+                    } else {
+                        Some(vec![i as u8; i])
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        batched.update(changes.clone());
+        for (k, v) in &changes {
+            match v {
+                Some(v) => single.insert(*k, v),
+                None => single.remove(k),
+            };
+        }
+        assert_eq!(batched.root(), single.root());
+        let mut fresh = SparseMerkleTree::<Sha512>::new();
+        fresh.update(changes.iter().filter(|(_, v)| v.is_some()).cloned());
+        assert_eq!(fresh.root(), batched.root());
+        assert_eq!(fresh.len(), batched.len());
+        for (k, v) in &changes {
+            let proof = batched.prove(k);
+            assert!(proof.verify::<Sha512>(batched.root(), k, v.as_deref()));
+        }
+
+        // The last change of a key wins, and removing everything empties the tree
+        batched.update(vec![(keys[1], Some(vec![1])), (keys[1], Some(vec![2]))]);
+        assert_eq!(batched.get(&keys[1]), Some(&[2u8][..]));
+        batched.update(keys.iter().map(|k| (*k, None)));
+        assert!(batched.is_empty());
+        assert!(batched.nodes.is_empty());
+        assert_eq!(batched.root(), &[0u8; 64][..]);
+    }
+
+    #[test]
+    fn encoding() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        tree.insert(key(1), b"one");
+        let lone = tree.prove(&key(1));
+        assert!(lone.siblings.is_empty());
+        assert_eq!(
+            SparseMerkleProof::from_bytes(&lone.to_bytes()).unwrap(),
+            lone
+        );
+        tree.insert(key(3), b"three");
+        let proof = tree.prove(&key(1));
+        let bytes = proof.to_bytes();
+        let tag = 3 + proof.siblings.len() * 32;
+        assert_eq!(bytes.len(), tag + 1 + KEY_SIZE + 32);
+        assert!(SparseMerkleProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SparseMerkleProof::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        let mut bad = bytes.clone();
+        bad[tag] = 2;
+        assert!(SparseMerkleProof::from_bytes(&bad).is_err());
+        let mut bad = bytes;
+        bad[2] = 0;
+        assert!(SparseMerkleProof::from_bytes(&bad).is_err());
+        let empty = SparseMerkleTree::<Sha256>::new().prove(&key(9)).to_bytes();
+        assert_eq!(empty, vec![0, 0, 0, 0]);
+        assert!(SparseMerkleProof::from_bytes(&empty).is_ok());
+        assert!(SparseMerkleProof::from_bytes(&[0, 1, 32]).is_err());
+    }
+}
@@ -0,0 +1,990 @@
+//! Group key agreement with TreeKEM for encrypting to a changing group.
+//!
+//! A [`Group`] shares an epoch secret between its members that every message of the
+//! epoch is encrypted under. A member changes the group with a [`Commit`] that adds
+//! members from their [`KeyPackage`]s, removes members or just refreshes its own keys,
+//! and every commit starts a new epoch with a new secret. Removed members can't
+//! decrypt messages of later epochs and added members can't decrypt earlier ones.
+//!
+//! The members are the leaves of a left-balanced binary tree as in MLS (RFC 9420),
+//! and every node of the tree other than a blank one has an X25519 key pair whose
+//! private key is known to the members below it. A commit replaces the keys on the
+//! path from its sender's leaf to the root, sealing each new path secret to the nodes
+//! that cover the other half of the subtree below it, so a commit has `O(log n)`
+//! ciphertexts instead of one per member. Removing or adding a member blanks the path
+//! above its leaf. Members added by a commit join the new epoch with a [`Welcome`].
+//!
+//! Secrets are sealed to X25519 keys like HPKE base mode, with an ephemeral key,
+//! HKDF-SHA256 and XChaCha20-Poly1305. Commits carry an HMAC-SHA256 tag under a key
+//! of the epoch, which shows they come from a member. They don't say which member,
+//! and welcomes aren't authenticated at all, so applications should sign both with
+//! the members' identity keys. Messages are sealed with XChaCha20-Poly1305 under a
+//! key of the epoch, which only decrypts messages of the current epoch.
+//!
+//! Messages are encoded as
+//!
+//! ```text
+//! epoch (8, big endian) | sender (4, big endian) | nonce (24) | ciphertext
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use encryption::symm::prelude::*;
+use kex::x25519::X25519Sha256;
+use kex::KeyExchangeScheme;
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+use mls::tree::{common_ancestor, copath, direct_path, leaf_to_node, left, right};
+use CryptoError;
+
+/// The length a message adds to its plaintext
+pub const OVERHEAD: usize = HEADER_SIZE + NONCE_SIZE + TAG_SIZE;
+
+const KEY_SIZE: usize = 32;
+const SECRET_SIZE: usize = 32;
+const HEADER_SIZE: usize = 8 + 4;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+const SEALED_SECRET_SIZE: usize = KEY_SIZE + SECRET_SIZE + TAG_SIZE;
+const SEAL_INFO: &[u8] = b"URSA_GROUP_SEAL_V1";
+const EPOCH_INFO: &[u8] = b"URSA_GROUP_EPOCH_V1";
+const PATH_LABEL: &[u8] = b"URSA_GROUP_PATH_V1";
+const NODE_LABEL: &[u8] = b"URSA_GROUP_NODE_V1";
+const COMMIT_LABEL: &[u8] = b"URSA_GROUP_COMMIT_V1";
+const MEMBERSHIP_LABEL: &[u8] = b"URSA_GROUP_MEMBERSHIP_V1";
+const APPLICATION_LABEL: &[u8] = b"URSA_GROUP_APPLICATION_V1";
+
+/// The key a prospective member publishes so a member can add it
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct KeyPackage {
+    pub public_key: PublicKey,
+    private_key: PrivateKey,
+}
+
+impl KeyPackage {
+    /// Generate a key package. Publish its `public_key` and keep it to join with
+    pub fn generate() -> Result<Self, CryptoError> {
+        let (public_key, private_key) = X25519Sha256::new().keypair(None)?;
+        Ok(KeyPackage {
+            public_key,
+            private_key,
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+struct Node {
+    public_key: PublicKey,
+    private_key: Option<PrivateKey>,
+}
+
+/// One member's state of a group. Store it between messages, serialized with the
+/// `serde` feature, as securely as a private key.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct Group {
+    group_id: Vec<u8>,
+    epoch: u64,
+    epoch_secret: Vec<u8>,
+    own_leaf: u32,
+    nodes: Vec<Option<Node>>,
+}
+
+/// A change to a group that starts its next epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    epoch: u64,
+    sender: u32,
+    removes: Vec<u32>,
+    adds: Vec<PublicKey>,
+    leaf_key: PublicKey,
+    /// The new public key of every node on the sender's path to the root, with its
+    /// path secret sealed to each node of the resolution of the other child
+    path: Vec<(PublicKey, Vec<Vec<u8>>)>,
+    membership_tag: Vec<u8>,
+}
+
+/// The secrets a member added by a commit joins the next epoch with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Welcome {
+    /// The key package public key it is sealed to
+    pub public_key: PublicKey,
+    sealed: Vec<u8>,
+}
+
+impl Group {
+    /// Create a group with its creator as the only member
+    pub fn create(group_id: &[u8]) -> Result<Self, CryptoError> {
+        let (public_key, private_key) = X25519Sha256::new().keypair(None)?;
+        let mut epoch_secret = vec![0u8; SECRET_SIZE];
+        OsRng.fill_bytes(&mut epoch_secret);
+        Ok(Group {
+            group_id: group_id.to_vec(),
+            epoch: 0,
+            epoch_secret,
+            own_leaf: 0,
+            nodes: vec![Some(Node {
+                public_key,
+                private_key: Some(private_key),
+            })],
+        })
+    }
+
+    /// Join the group from the welcome of the commit that added `key_package`
+    pub fn join(welcome: &Welcome, key_package: &KeyPackage) -> Result<Self, CryptoError> {
+        if welcome.public_key != key_package.public_key {
+            return Err(CryptoError::GeneralError(
+                "Welcome is for another key package".to_string(),
+            ));
+        }
+        let mut plaintext = open_secret(
+            &key_package.private_key,
+            &key_package.public_key,
+            b"welcome",
+            &welcome.sealed,
+        )?;
+        let result = Self::from_welcome(&plaintext, key_package);
+        plaintext.zeroize();
+        result
+    }
+
+    fn from_welcome(plaintext: &[u8], key_package: &KeyPackage) -> Result<Self, CryptoError> {
+        let mut reader = Reader(plaintext);
+        let group_id = reader.field()?.to_vec();
+        let epoch = reader.u64()?;
+        let epoch_secret = reader.take(SECRET_SIZE)?.to_vec();
+        let own_leaf = reader.u32()?;
+        let committer = reader.u32()?;
+        let path_secret = reader.take(SECRET_SIZE)?.to_vec();
+        let count = reader.u32()? as usize;
+        let mut nodes = Vec::new();
+        for _ in 0..count {
+            nodes.push(match reader.take(1)?[0] {
+                0 => None,
+                1 => Some(Node {
+                    public_key: PublicKey(reader.take(KEY_SIZE)?.to_vec()),
+                    private_key: None,
+                }),
+                _ => return Err(parse_error("Welcome has an invalid node")),
+            });
+        }
+        reader.finish()?;
+
+        let mut group = Group {
+            group_id,
+            epoch,
+            epoch_secret,
+            own_leaf,
+            nodes,
+        };
+        let leaves = group.leaf_count() as u32;
+        let own = leaf_to_node(own_leaf);
+        if group.nodes.len() & 1 == 0
+            || committer == own_leaf
+            || !group.is_member(committer)
+            || group.nodes[own].as_ref().map(|n| &n.public_key) != Some(&key_package.public_key)
+        {
+            return Err(parse_error("Welcome has an invalid tree"));
+        }
+        group.nodes[own].as_mut().unwrap().private_key = Some(key_package.private_key.clone());
+
+        // The keys from the lowest common ancestor with the committer up
+        let path = direct_path(own, leaves);
+        let ancestor = common_ancestor(own, leaf_to_node(committer));
+        let start = path.iter().position(|p| *p == ancestor).unwrap();
+        group.install_path(&path[start..], &path_secret, None)?;
+        Ok(group)
+    }
+
+    /// The group identifier
+    pub fn group_id(&self) -> &[u8] {
+        &self.group_id
+    }
+
+    /// The current epoch, which starts at zero and counts the commits
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// This member's leaf index
+    pub fn own_leaf(&self) -> u32 {
+        self.own_leaf
+    }
+
+    /// The leaf indices of the members
+    pub fn members(&self) -> Vec<u32> {
+        (0..self.leaf_count() as u32)
+            .filter(|l| self.is_member(*l))
+            .collect()
+    }
+
+    /// Start the next epoch, removing the members at `removes` and adding the holders
+    /// of `adds`, or only refreshing this member's keys when both are empty. The commit
+    /// goes to every other current member and the welcomes to the added ones, in order.
+    /// This member moves to the new epoch straight away, so keep the previous state if
+    /// the commit might be rejected
+    pub fn commit(
+        &mut self,
+        adds: &[PublicKey],
+        removes: &[u32],
+    ) -> Result<(Commit, Vec<Welcome>), CryptoError> {
+        if removes.contains(&self.own_leaf) {
+            return Err(CryptoError::GeneralError(
+                "A member can't remove itself".to_string(),
+            ));
+        }
+        let mut next = self.clone();
+        let added = next.apply_changes(adds, removes)?;
+
+        let own = leaf_to_node(self.own_leaf);
+        let leaves = next.leaf_count() as u32;
+        let path = direct_path(own, leaves);
+        let copath = copath(own, leaves);
+        let mut path_secrets = vec![vec![0u8; SECRET_SIZE]];
+        OsRng.fill_bytes(&mut path_secrets[0]);
+        for i in 0..path.len() {
+            let secret = expand(&path_secrets[i], PATH_LABEL)?;
+            path_secrets.push(secret);
+        }
+
+        let leaf_key = node_keypair(&path_secrets[0])?;
+        next.nodes[own] = Some(Node {
+            public_key: leaf_key.0.clone(),
+            private_key: Some(leaf_key.1),
+        });
+        let context = self.seal_context();
+        let mut update_path = Vec::with_capacity(path.len());
+        for (i, (node, other)) in path.iter().zip(copath.iter()).enumerate() {
+            let (public_key, private_key) = node_keypair(&path_secrets[i + 1])?;
+            let mut sealed = Vec::new();
+            for r in next.resolution(*other, &added) {
+                let recipient = &next.nodes[r].as_ref().unwrap().public_key;
+                sealed.push(seal_secret(recipient, &context, &path_secrets[i + 1])?);
+            }
+            next.nodes[*node] = Some(Node {
+                public_key: public_key.clone(),
+                private_key: Some(private_key),
+            });
+            update_path.push((public_key, sealed));
+        }
+
+        let mut commit = Commit {
+            epoch: self.epoch,
+            sender: self.own_leaf,
+            removes: removes.to_vec(),
+            adds: adds.to_vec(),
+            leaf_key: leaf_key.0,
+            path: update_path,
+            membership_tag: Vec::new(),
+        };
+        commit.membership_tag = self.membership_tag(&commit.content())?;
+        let commit_secret = expand(path_secrets.last().unwrap(), COMMIT_LABEL)?;
+        next.advance(&commit, &commit_secret)?;
+
+        let welcomes = added
+            .iter()
+            .zip(adds.iter())
+            .map(|(leaf, public_key)| {
+                // The path secret of the lowest node the new member shares with us
+                let ancestor = common_ancestor(own, leaf_to_node(*leaf));
+                let index = path.iter().position(|p| *p == ancestor).unwrap();
+                next.welcome(*leaf, public_key, &path_secrets[index + 1])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        path_secrets.iter_mut().for_each(|s| s.zeroize());
+        *self = next;
+        Ok((commit, welcomes))
+    }
+
+    /// Move to the next epoch with a commit from another member. Fails if the commit
+    /// is for another epoch, isn't from a member or removes this member
+    pub fn process(&mut self, commit: &Commit) -> Result<(), CryptoError> {
+        if commit.epoch != self.epoch {
+            return Err(CryptoError::GeneralError(
+                "Commit is for another epoch".to_string(),
+            ));
+        }
+        if commit.sender == self.own_leaf || !self.is_member(commit.sender) {
+            return Err(CryptoError::GeneralError(
+                "Commit has an invalid sender".to_string(),
+            ));
+        }
+        let mut mac = self.membership_mac()?;
+        mac.input(&commit.content());
+        mac.verify(&commit.membership_tag)
+            .map_err(|_| CryptoError::GeneralError("Commit is not from a member".to_string()))?;
+        if commit.removes.contains(&self.own_leaf) {
+            return Err(CryptoError::GeneralError(
+                "This member was removed from the group".to_string(),
+            ));
+        }
+
+        let mut next = self.clone();
+        let added = next.apply_changes(&commit.adds, &commit.removes)?;
+        let leaves = next.leaf_count() as u32;
+        let sender = leaf_to_node(commit.sender);
+        let path = direct_path(sender, leaves);
+        let copath = copath(sender, leaves);
+        if commit.path.len() != path.len() {
+            return Err(parse_error("Commit has a path of the wrong length"));
+        }
+
+        // Open the path secret of the lowest node above both leaves
+        let own = leaf_to_node(self.own_leaf);
+        let ancestor = common_ancestor(own, sender);
+        let index = path.iter().position(|p| *p == ancestor).unwrap();
+        let resolution = next.resolution(copath[index], &added);
+        if resolution.len() != commit.path[index].1.len() {
+            return Err(parse_error("Commit has the wrong number of secrets"));
+        }
+        let (position, private_key) = resolution
+            .iter()
+            .enumerate()
+            .find_map(|(i, r)| {
+                next.nodes[*r]
+                    .as_ref()
+                    .and_then(|n| n.private_key.clone())
+                    .map(|k| (i, (*r, k)))
+            })
+            .ok_or_else(|| {
+                CryptoError::GeneralError("No key to open the commit with".to_string())
+            })?;
+        let recipient = &next.nodes[private_key.0].as_ref().unwrap().public_key;
+        let path_secret = open_secret(
+            &private_key.1,
+            recipient,
+            &self.seal_context(),
+            &commit.path[index].1[position],
+        )?;
+
+        next.nodes[sender] = Some(Node {
+            public_key: commit.leaf_key.clone(),
+            private_key: None,
+        });
+        for (node, (public_key, _)) in path.iter().zip(commit.path.iter()).take(index) {
+            next.nodes[*node] = Some(Node {
+                public_key: public_key.clone(),
+                private_key: None,
+            });
+        }
+        let expected = commit.path[index..]
+            .iter()
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        let root_secret = next.install_path(&path[index..], &path_secret, Some(&expected))?;
+        let commit_secret = expand(&root_secret, COMMIT_LABEL)?;
+        next.advance(commit, &commit_secret)?;
+        *self = next;
+        Ok(())
+    }
+
+    /// Encrypt a message to the members of the current epoch
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut header = self.epoch.to_be_bytes().to_vec();
+        header.extend_from_slice(&self.own_leaf.to_be_bytes());
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .application_encryptor()?
+            .encrypt(&nonce[..], &self.aad(&header), plaintext)
+            .map_err(|_| CryptoError::GeneralError("Failed to encrypt the message".to_string()))?;
+        let mut output = header;
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&ciphertext);
+        Ok(output)
+    }
+
+    /// Decrypt a message of the current epoch, returning the leaf index the sender
+    /// claims with the plaintext
+    pub fn decrypt(&self, message: &[u8]) -> Result<(u32, Vec<u8>), CryptoError> {
+        if message.len() < OVERHEAD {
+            return Err(parse_error("Message is too short"));
+        }
+        let mut reader = Reader(message);
+        let epoch = reader.u64()?;
+        let sender = reader.u32()?;
+        if epoch != self.epoch {
+            return Err(CryptoError::GeneralError(
+                "Message is from another epoch".to_string(),
+            ));
+        }
+        let aad = self.aad(&message[..HEADER_SIZE]);
+        let (nonce, ciphertext) = message[HEADER_SIZE..].split_at(NONCE_SIZE);
+        let plaintext = self
+            .application_encryptor()?
+            .decrypt(nonce, &aad, ciphertext)
+            .map_err(|_| CryptoError::GeneralError("Failed to decrypt the message".to_string()))?;
+        Ok((sender, plaintext))
+    }
+
+    fn leaf_count(&self) -> usize {
+        self.nodes.len() / 2 + 1
+    }
+
+    fn is_member(&self, leaf: u32) -> bool {
+        (leaf as usize) < self.leaf_count() && self.nodes[leaf_to_node(leaf)].is_some()
+    }
+
+    /// Blank the removed leaves and put the added ones in the leftmost blank leaves,
+    /// blanking the paths above all of them. Returns the leaves of the added members
+    fn apply_changes(
+        &mut self,
+        adds: &[PublicKey],
+        removes: &[u32],
+    ) -> Result<Vec<u32>, CryptoError> {
+        for (i, leaf) in removes.iter().enumerate() {
+            if !self.is_member(*leaf) || removes[..i].contains(leaf) {
+                return Err(CryptoError::GeneralError(format!(
+                    "Leaf {} is not a member",
+                    leaf
+                )));
+            }
+            self.blank_path(leaf_to_node(*leaf));
+            self.nodes[leaf_to_node(*leaf)] = None;
+        }
+        let mut added = Vec::with_capacity(adds.len());
+        for public_key in adds {
+            if public_key.len() != KEY_SIZE {
+                return Err(parse_error("Key package has an invalid key"));
+            }
+            let leaf = match (0..self.leaf_count() as u32).find(|l| !self.is_member(*l)) {
+                Some(leaf) => leaf,
+                None => {
+                    self.nodes.push(None);
+                    self.nodes.push(None);
+                    self.leaf_count() as u32 - 1
+                }
+            };
+            let node = leaf_to_node(leaf);
+            self.nodes[node] = Some(Node {
+                public_key: public_key.clone(),
+                private_key: None,
+            });
+            self.blank_path(node);
+            added.push(leaf);
+        }
+        Ok(added)
+    }
+
+    fn blank_path(&mut self, node: usize) {
+        for p in direct_path(node, self.leaf_count() as u32) {
+            self.nodes[p] = None;
+        }
+    }
+
+    /// The non-blank nodes that cover the subtree of `node`, without the added leaves
+    fn resolution(&self, node: usize, added: &[u32]) -> Vec<usize> {
+        match self.nodes[node] {
+            Some(_) if node & 1 == 0 && added.contains(&((node / 2) as u32)) => Vec::new(),
+            Some(_) => vec![node],
+            None if node & 1 == 0 => Vec::new(),
+            None => {
+                let mut r = self.resolution(left(node), added);
+                r.extend(self.resolution(right(node, self.leaf_count() as u32), added));
+                r
+            }
+        }
+    }
+
+    /// Derive the keys of `path` from the secret of its first node, checking them
+    /// against `expected`, and return the secret of the last one
+    fn install_path(
+        &mut self,
+        path: &[usize],
+        path_secret: &[u8],
+        expected: Option<&[PublicKey]>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut secret = path_secret.to_vec();
+        for (i, node) in path.iter().enumerate() {
+            if i > 0 {
+                let next = expand(&secret, PATH_LABEL)?;
+                secret.zeroize();
+                secret = next;
+            }
+            let (public_key, private_key) = node_keypair(&secret)?;
+            let matches = match expected {
+                Some(keys) => keys[i] == public_key,
+                None => self.nodes[*node].as_ref().map(|n| &n.public_key) == Some(&public_key),
+            };
+            if !matches {
+                secret.zeroize();
+                return Err(CryptoError::GeneralError(
+                    "Path secret doesn't match the tree".to_string(),
+                ));
+            }
+            self.nodes[*node] = Some(Node {
+                public_key,
+                private_key: Some(private_key),
+            });
+        }
+        Ok(secret)
+    }
+
+    /// Derive the next epoch's secret from the commit
+    fn advance(&mut self, commit: &Commit, commit_secret: &[u8]) -> Result<(), CryptoError> {
+        self.epoch = self
+            .epoch
+            .checked_add(1)
+            .ok_or_else(|| CryptoError::GeneralError("The group has no epochs left".to_string()))?;
+        let mut info = EPOCH_INFO.to_vec();
+        info.extend_from_slice(&(self.group_id.len() as u32).to_be_bytes());
+        info.extend_from_slice(&self.group_id);
+        info.extend_from_slice(&self.epoch.to_be_bytes());
+        info.extend_from_slice(&Sha256::digest(&commit.to_bytes()));
+        let mut epoch_secret = vec![0u8; SECRET_SIZE];
+        Hkdf::<Sha256>::new(Some(&self.epoch_secret), commit_secret)
+            .expand(&info, &mut epoch_secret)
+            .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+        self.epoch_secret.zeroize();
+        self.epoch_secret = epoch_secret;
+        Ok(())
+    }
+
+    fn welcome(
+        &self,
+        leaf: u32,
+        public_key: &PublicKey,
+        path_secret: &[u8],
+    ) -> Result<Welcome, CryptoError> {
+        let mut plaintext = (self.group_id.len() as u32).to_be_bytes().to_vec();
+        plaintext.extend_from_slice(&self.group_id);
+        plaintext.extend_from_slice(&self.epoch.to_be_bytes());
+        plaintext.extend_from_slice(&self.epoch_secret);
+        plaintext.extend_from_slice(&leaf.to_be_bytes());
+        plaintext.extend_from_slice(&self.own_leaf.to_be_bytes());
+        plaintext.extend_from_slice(path_secret);
+        plaintext.extend_from_slice(&(self.nodes.len() as u32).to_be_bytes());
+        for node in &self.nodes {
+            match node {
+                Some(n) => {
+                    plaintext.push(1);
+                    plaintext.extend_from_slice(&n.public_key[..]);
+                }
+                None => plaintext.push(0),
+            }
+        }
+        let sealed = seal_secret(public_key, b"welcome", &plaintext);
+        plaintext.zeroize();
+        Ok(Welcome {
+            public_key: public_key.clone(),
+            sealed: sealed?,
+        })
+    }
+
+    /// What the path secrets of a commit in this epoch are bound to
+    fn seal_context(&self) -> Vec<u8> {
+        let mut context = (self.group_id.len() as u32).to_be_bytes().to_vec();
+        context.extend_from_slice(&self.group_id);
+        context.extend_from_slice(&self.epoch.to_be_bytes());
+        context
+    }
+
+    fn membership_mac(&self) -> Result<Hmac<Sha256>, CryptoError> {
+        let mut key = expand(&self.epoch_secret, MEMBERSHIP_LABEL)?;
+        let mac = Hmac::<Sha256>::new_varkey(&key)
+            .map_err(|_| CryptoError::GeneralError("Invalid membership key".to_string()));
+        key.zeroize();
+        mac
+    }
+
+    fn membership_tag(&self, content: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut mac = self.membership_mac()?;
+        mac.input(content);
+        Ok(mac.result().code().to_vec())
+    }
+
+    fn application_encryptor(&self) -> Result<SymmetricEncryptor<XChaCha20Poly1305>, CryptoError> {
+        let mut key = expand(&self.epoch_secret, APPLICATION_LABEL)?;
+        let encryptor = encryptor(&key);
+        key.zeroize();
+        encryptor
+    }
+
+    fn aad(&self, header: &[u8]) -> Vec<u8> {
+        let mut aad = (self.group_id.len() as u32).to_be_bytes().to_vec();
+        aad.extend_from_slice(&self.group_id);
+        aad.extend_from_slice(header);
+        aad
+    }
+}
+
+impl Commit {
+    /// The sender's leaf index
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The epoch the commit ends
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Everything but the membership tag
+    fn content(&self) -> Vec<u8> {
+        let mut output = self.epoch.to_be_bytes().to_vec();
+        output.extend_from_slice(&self.sender.to_be_bytes());
+        output.extend_from_slice(&(self.removes.len() as u32).to_be_bytes());
+        for leaf in &self.removes {
+            output.extend_from_slice(&leaf.to_be_bytes());
+        }
+        output.extend_from_slice(&(self.adds.len() as u32).to_be_bytes());
+        for key in &self.adds {
+            output.extend_from_slice(&key[..]);
+        }
+        output.extend_from_slice(&self.leaf_key[..]);
+        output.extend_from_slice(&(self.path.len() as u32).to_be_bytes());
+        for (key, sealed) in &self.path {
+            output.extend_from_slice(&key[..]);
+            output.extend_from_slice(&(sealed.len() as u32).to_be_bytes());
+            for s in sealed {
+                output.extend_from_slice(s);
+            }
+        }
+        output
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.content();
+        output.extend_from_slice(&self.membership_tag);
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(data);
+        let epoch = reader.u64()?;
+        let sender = reader.u32()?;
+        let mut removes = Vec::new();
+        for _ in 0..reader.u32()? {
+            removes.push(reader.u32()?);
+        }
+        let mut adds = Vec::new();
+        for _ in 0..reader.u32()? {
+            adds.push(PublicKey(reader.take(KEY_SIZE)?.to_vec()));
+        }
+        let leaf_key = PublicKey(reader.take(KEY_SIZE)?.to_vec());
+        let mut path = Vec::new();
+        for _ in 0..reader.u32()? {
+            let key = PublicKey(reader.take(KEY_SIZE)?.to_vec());
+            let mut sealed = Vec::new();
+            for _ in 0..reader.u32()? {
+                sealed.push(reader.take(SEALED_SECRET_SIZE)?.to_vec());
+            }
+            path.push((key, sealed));
+        }
+        let membership_tag = reader.take(SECRET_SIZE)?.to_vec();
+        reader.finish()?;
+        Ok(Commit {
+            epoch,
+            sender,
+            removes,
+            adds,
+            leaf_key,
+            path,
+            membership_tag,
+        })
+    }
+}
+
+impl Welcome {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.public_key[..].to_vec();
+        output.extend_from_slice(&self.sealed);
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() < KEY_SIZE + KEY_SIZE + TAG_SIZE {
+            return Err(parse_error("Welcome is too short"));
+        }
+        Ok(Welcome {
+            public_key: PublicKey(data[..KEY_SIZE].to_vec()),
+            sealed: data[KEY_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// Seal `secret` to `recipient` with an ephemeral key
+fn seal_secret(
+    recipient: &PublicKey,
+    context: &[u8],
+    secret: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let scheme = X25519Sha256::new();
+    let (ephemeral, ephemeral_private) = scheme.keypair(None)?;
+    let mut key = seal_key(
+        &scheme.compute_shared_secret(&ephemeral_private, recipient)?[..],
+        &ephemeral,
+        recipient,
+    )?;
+    // Every key seals one secret so the nonce can be fixed
+    let sealed = encryptor(&key)?
+        .encrypt(&[0u8; NONCE_SIZE][..], context, secret)
+        .map_err(|_| CryptoError::GeneralError("Failed to seal the secret".to_string()));
+    key.zeroize();
+    let mut output = ephemeral[..].to_vec();
+    output.extend_from_slice(&sealed?);
+    Ok(output)
+}
+
+fn open_secret(
+    private_key: &PrivateKey,
+    public_key: &PublicKey,
+    context: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < KEY_SIZE + TAG_SIZE {
+        return Err(parse_error("Sealed secret is too short"));
+    }
+    let scheme = X25519Sha256::new();
+    let ephemeral = PublicKey(sealed[..KEY_SIZE].to_vec());
+    let mut key = seal_key(
+        &scheme.compute_shared_secret(private_key, &ephemeral)?[..],
+        &ephemeral,
+        public_key,
+    )?;
+    let secret = encryptor(&key)?
+        .decrypt(&[0u8; NONCE_SIZE][..], context, &sealed[KEY_SIZE..])
+        .map_err(|_| CryptoError::GeneralError("Failed to open the secret".to_string()));
+    key.zeroize();
+    secret
+}
+
+fn seal_key(
+    dh: &[u8],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<Vec<u8>, CryptoError> {
+    let mut info = SEAL_INFO.to_vec();
+    info.extend_from_slice(&ephemeral[..]);
+    info.extend_from_slice(&recipient[..]);
+    let mut key = vec![0u8; KEY_SIZE];
+    Hkdf::<Sha256>::new(None, dh)
+        .expand(&info, &mut key)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    Ok(key)
+}
+
+fn expand(secret: &[u8], label: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut output = vec![0u8; SECRET_SIZE];
+    Hkdf::<Sha256>::new(None, secret)
+        .expand(label, &mut output)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    Ok(output)
+}
+
+/// The key pair of a node with this path secret
+fn node_keypair(path_secret: &[u8]) -> Result<(PublicKey, PrivateKey), CryptoError> {
+    let secret = expand(path_secret, NODE_LABEL)?;
+    X25519Sha256::new().keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(secret))))
+}
+
+fn encryptor(key: &[u8]) -> Result<SymmetricEncryptor<XChaCha20Poly1305>, CryptoError> {
+    SymmetricEncryptor::<XChaCha20Poly1305>::new_with_key(key)
+        .map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+fn parse_error(message: &str) -> CryptoError {
+    CryptoError::ParseError(message.to_string())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CryptoError> {
+        if self.0.len() < len {
+            return Err(parse_error("Input is too short"));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, CryptoError> {
+        Ok(u32::from_be_bytes(*array_ref!(self.take(4)?, 0, 4)))
+    }
+
+    fn u64(&mut self) -> Result<u64, CryptoError> {
+        Ok(u64::from_be_bytes(*array_ref!(self.take(8)?, 0, 8)))
+    }
+
+    fn field(&mut self) -> Result<&'a [u8], CryptoError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn finish(&self) -> Result<(), CryptoError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(parse_error("Input has trailing bytes"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A group of `n` members where the creator added everyone else in one commit
+    fn group(n: usize) -> Vec<Group> {
+        let mut creator = Group::create(b"group").unwrap();
+        let packages = (1..n)
+            .map(|_| KeyPackage::generate().unwrap())
+            .collect::<Vec<_>>();
+        let adds = packages
+            .iter()
+            .map(|p| p.public_key.clone())
+            .collect::<Vec<_>>();
+        let (_, welcomes) = creator.commit(&adds, &[]).unwrap();
+        let mut members = vec![creator];
+        for (welcome, package) in welcomes.iter().zip(packages.iter()) {
+            members.push(Group::join(welcome, package).unwrap());
+        }
+        members
+    }
+
+    /// Commit with member `sender` and process it with every other current member
+    fn commit(members: &mut Vec<Group>, sender: usize, adds: &[KeyPackage], removes: &[u32]) {
+        let keys = adds
+            .iter()
+            .map(|p| p.public_key.clone())
+            .collect::<Vec<_>>();
+        let (commit, welcomes) = members[sender].commit(&keys, removes).unwrap();
+        let commit = Commit::from_bytes(&commit.to_bytes()).unwrap();
+        for (i, member) in members.iter_mut().enumerate() {
+            if i != sender && !removes.contains(&member.own_leaf()) {
+                member.process(&commit).unwrap();
+            }
+        }
+        members.retain(|m| !removes.contains(&m.own_leaf()) || m.epoch() == commit.epoch() + 1);
+        for (welcome, package) in welcomes.iter().zip(adds.iter()) {
+            let welcome = Welcome::from_bytes(&welcome.to_bytes()).unwrap();
+            members.push(Group::join(&welcome, package).unwrap());
+        }
+    }
+
+    fn all_agree(members: &[Group]) {
+        for sender in members {
+            let message = sender.encrypt(b"hello group").unwrap();
+            assert_eq!(message.len(), OVERHEAD + 11);
+            for member in members {
+                assert_eq!(member.epoch(), sender.epoch());
+                assert_eq!(member.members(), sender.members());
+                assert_eq!(
+                    member.decrypt(&message).unwrap(),
+                    (sender.own_leaf(), b"hello group".to_vec())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn membership_changes() {
+        let mut members = group(5);
+        all_agree(&members);
+
+        // Refresh keys, then remove and add members from different leaves
+        commit(&mut members, 3, &[], &[]);
+        all_agree(&members);
+        commit(&mut members, 1, &[], &[0, 4]);
+        assert_eq!(members.len(), 3);
+        all_agree(&members);
+        let packages = (0..3)
+            .map(|_| KeyPackage::generate().unwrap())
+            .collect::<Vec<_>>();
+        commit(&mut members, 2, &packages, &[]);
+        assert_eq!(members[0].members(), vec![0, 1, 2, 3, 4, 5]);
+        all_agree(&members);
+        for sender in 0..members.len() {
+            commit(&mut members, sender, &[], &[]);
+        }
+        all_agree(&members);
+        assert_eq!(members[0].epoch(), 10);
+    }
+
+    #[test]
+    fn removed_members_are_locked_out() {
+        let mut members = group(4);
+        let removed = members[2].clone();
+        let (commit, _) = members[0].commit(&[], &[2]).unwrap();
+        assert!(removed.clone().process(&commit).is_err());
+        members[1].process(&commit).unwrap();
+        let message = members[0].encrypt(b"secret").unwrap();
+        assert!(removed.decrypt(&message).is_err());
+        assert_eq!(members[1].decrypt(&message).unwrap().1, b"secret");
+        assert!(members[0].commit(&[], &[0]).is_err());
+        assert!(members[0].commit(&[], &[2]).is_err());
+    }
+
+    #[test]
+    fn new_members_cant_read_old_epochs() {
+        let mut members = group(2);
+        let old = members[0].encrypt(b"before").unwrap();
+        let package = KeyPackage::generate().unwrap();
+        commit(&mut members, 1, &[package], &[]);
+        assert!(members[2].decrypt(&old).is_err());
+        assert!(members[0].decrypt(&old).is_err());
+    }
+
+    #[test]
+    fn forged_commits_are_rejected() {
+        let mut members = group(3);
+        let (commit, _) = members[0].commit(&[], &[]).unwrap();
+        let bytes = commit.to_bytes();
+        for i in &[0, 8, 20, 60, bytes.len() - 1] {
+            let mut forged = bytes.clone();
+            forged[*i] ^= 1;
+            if let Ok(forged) = Commit::from_bytes(&forged) {
+                assert!(members[1].process(&forged).is_err());
+            }
+        }
+        assert!(Commit::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // Another group's member can't commit
+        let mut outsider = group(3).remove(0);
+        let (other, _) = outsider.commit(&[], &[]).unwrap();
+        assert!(members[1].process(&other).is_err());
+
+        members[1].process(&commit).unwrap();
+        assert!(members[1].process(&commit).is_err());
+        members[2].process(&commit).unwrap();
+        all_agree(&members);
+    }
+
+    #[test]
+    fn welcomes() {
+        let mut creator = Group::create(b"group").unwrap();
+        let package = KeyPackage::generate().unwrap();
+        let other = KeyPackage::generate().unwrap();
+        let adds = vec![package.public_key.clone()];
+        let (_, welcomes) = creator.commit(&adds, &[]).unwrap();
+        assert!(Group::join(&welcomes[0], &other).is_err());
+        let mut forged = welcomes[0].clone();
+        let last = forged.sealed.len() - 1;
+        forged.sealed[last] ^= 1;
+        assert!(Group::join(&forged, &package).is_err());
+        let joined = Group::join(&welcomes[0], &package).unwrap();
+        assert_eq!(joined.group_id(), b"group");
+        assert_eq!(joined.own_leaf(), 1);
+        all_agree(&[creator, joined]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization() {
+        let members = group(3);
+        let stored = serde_json::to_string(&members[1]).unwrap();
+        let restored: Group = serde_json::from_str(&stored).unwrap();
+        let message = members[2].encrypt(b"hi").unwrap();
+        assert_eq!(restored.decrypt(&message).unwrap().1, b"hi");
+    }
+}
@@ -0,0 +1,990 @@
+//! One round trip authenticated key exchange between parties that know each other's
+//! static keys.
+//!
+//! The exchange follows the KK pattern of the Noise framework, so a device and a
+//! server that were provisioned with each other's X25519 keys agree on a key with a
+//! single request and response:
+//!
+//! ```text
+//! -> e, es, ss, payload
+//! <- e, ee, se, payload
+//! ```
+//!
+//! The initiator [`initiate`]s with its static private key and the responder's public
+//! key and sends the [`Request`] with its first payload, e.g. a sensor reading. The
+//! responder [`respond`]s to decrypt it, [`Responder::reply`]s with its own payload
+//! and both [`Initiator::finish`] with the same [`Session`]. The response can only be
+//! produced by the holder of the responder's static key and the session key can only
+//! be computed by the holder of the initiator's, so both are authenticated after one
+//! round trip. [`Session::channel_binding`] is the hash of the whole transcript, for
+//! binding application level signatures or tokens to the channel.
+//!
+//! The request payload is encrypted before the responder has contributed a fresh key:
+//! it isn't forward secret if the responder's static key is compromised and it can be
+//! replayed, so it must only carry requests that are safe to repeat. The response
+//! payload and the session key have neither weakness.
+//!
+//! A responder can hand a reconnecting initiator a resumption ticket so it skips the
+//! Diffie-Hellman exchanges next time. A [`TicketIssuer`] encrypts the initiator's key
+//! and the [`Session::resumption_secret`] both parties derive into a self-contained
+//! ticket, sent over the session, so the responder keeps no state per client. The
+//! initiator [`resume`]s with the ticket and the secret, and [`TicketIssuer::accept`]
+//! checks the ticket is within its lifetime and hasn't been used before. A resumed
+//! session isn't forward secret: it's only as secret as the ticket key and the session
+//! the ticket came from, so keep lifetimes short and rotate the ticket key. Every
+//! resumed session can issue the next ticket.
+//!
+//! The payload of a [`ResumeRequest`] is early data, sent before the responder has
+//! answered, to upload telemetry without waiting a round trip. The issuer only hands
+//! it to the application after [`TicketIssuer::set_max_early_data`] allows it, and
+//! [`ResumeResponse::early_data_accepted`] tells the initiator to send it again over the
+//! session otherwise. Early data can be replayed to other servers sharing the ticket
+//! key or after the issuer's record of used tickets is lost, e.g. on a restart. The
+//! [`ReplayFilter`] that keeps the record is pluggable so servers can share one, and
+//! early data should still only carry requests that are harmless to repeat.
+//!
+//! Keys are those of `X25519Sha256`, whose hashed Diffie-Hellman outputs don't match
+//! Noise's, so this doesn't interoperate with other Noise implementations.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+use encryption::symm::prelude::*;
+use kex::x25519::X25519Sha256;
+use kex::KeyExchangeScheme;
+use keys::{KeyGenOption, PrivateKey, PublicKey, SessionKey};
+use CryptoError;
+
+/// The length of the session key
+pub const SESSION_KEY_SIZE: usize = 32;
+/// The length of the channel binding value
+pub const CHANNEL_BINDING_SIZE: usize = 32;
+
+const PROTOCOL: &[u8] = b"URSA_KK_X25519_CHACHAPOLY_SHA256_V1";
+const RESUMPTION_PROTOCOL: &[u8] = b"URSA_KK_PSK_CHACHAPOLY_SHA256_V1";
+const RESUMPTION_INFO: &[u8] = b"URSA_KK_RESUMPTION_V1";
+const TICKET_AAD: &[u8] = b"URSA_KK_TICKET_V1";
+const NONCE_SIZE: usize = 32;
+const TICKET_NONCE_SIZE: usize = 24;
+const TICKET_SIZE: usize = TICKET_NONCE_SIZE + 8 + 2 * KEY_SIZE + TAG_SIZE;
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+
+/// The first message, from the initiator
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub ephemeral_key: PublicKey,
+    /// The encrypted payload
+    pub ciphertext: Vec<u8>,
+}
+
+/// The reply from the responder
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub ephemeral_key: PublicKey,
+    /// The encrypted payload
+    pub ciphertext: Vec<u8>,
+}
+
+macro_rules! impl_message_bytes {
+    ($name:ident) => {
+        impl $name {
+            /// Encode as `ephemeral key | ciphertext`
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut out = self.ephemeral_key[..].to_vec();
+                out.extend_from_slice(&self.ciphertext);
+                out
+            }
+
+            pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+                if data.len() < KEY_SIZE + TAG_SIZE {
+                    return Err(CryptoError::ParseError(format!(
+                        "Invalid {} length",
+                        stringify!($name)
+                    )));
+                }
+                Ok($name {
+                    ephemeral_key: PublicKey(data[..KEY_SIZE].to_vec()),
+                    ciphertext: data[KEY_SIZE..].to_vec(),
+                })
+            }
+        }
+    };
+}
+
+impl_message_bytes!(Request);
+impl_message_bytes!(Response);
+
+/// The agreed key and the value that identifies the channel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub key: SessionKey,
+    /// The hash of the transcript, the same for both parties and unique to the session
+    pub channel_binding: Vec<u8>,
+}
+
+impl Session {
+    /// The secret to resume the session with a ticket, which both parties derive
+    pub fn resumption_secret(&self) -> Result<SessionKey, CryptoError> {
+        let mut secret = vec![0u8; KEY_SIZE];
+        Hkdf::<Sha256>::new(Some(&self.channel_binding), &self.key[..])
+            .expand(RESUMPTION_INFO, &mut secret)
+            .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+        Ok(SessionKey(secret))
+    }
+}
+
+/// The symmetric state both parties keep through the handshake
+struct State {
+    chaining_key: SessionKey,
+    hash: Vec<u8>,
+}
+
+impl State {
+    fn new(prologue: &[u8], initiator: &PublicKey, responder: &PublicKey) -> Self {
+        let hash = Sha256::digest(PROTOCOL).to_vec();
+        let mut state = State {
+            chaining_key: SessionKey(hash.clone()),
+            hash,
+        };
+        state.mix_hash(prologue);
+        state.mix_hash(&initiator[..]);
+        state.mix_hash(&responder[..]);
+        state
+    }
+
+    fn resumption(prologue: &[u8], ticket: &[u8]) -> Self {
+        let hash = Sha256::digest(RESUMPTION_PROTOCOL).to_vec();
+        let mut state = State {
+            chaining_key: SessionKey(hash.clone()),
+            hash,
+        };
+        state.mix_hash(prologue);
+        state.mix_hash(ticket);
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.hash = Sha256::new()
+            .chain(&self.hash)
+            .chain((data.len() as u32).to_be_bytes())
+            .chain(data)
+            .result()
+            .to_vec();
+    }
+
+    /// Mix two Diffie-Hellman outputs into the chaining key and return the key of the next payload
+    fn mix_keys(
+        &mut self,
+        first: &SessionKey,
+        second: &SessionKey,
+    ) -> Result<SessionKey, CryptoError> {
+        let mut ikm = first[..].to_vec();
+        ikm.extend_from_slice(&second[..]);
+        let mut okm = [0u8; 2 * KEY_SIZE];
+        let res =
+            Hkdf::<Sha256>::new(Some(&self.chaining_key[..]), &ikm).expand(&self.hash, &mut okm);
+        ikm.zeroize();
+        res.map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+        self.chaining_key = SessionKey(okm[..KEY_SIZE].to_vec());
+        let key = SessionKey(okm[KEY_SIZE..].to_vec());
+        okm.zeroize();
+        Ok(key)
+    }
+
+    /// Encrypt a payload bound to the transcript so far and add it to the transcript
+    fn seal(&mut self, key: &SessionKey, payload: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        // Every key encrypts once so the nonce is fixed
+        let ciphertext = cipher(key)?
+            .encrypt(&[0u8; 12][..], &self.hash[..], payload)
+            .map_err(|_| CryptoError::GeneralError("Failed to encrypt the payload".to_string()))?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn open(&mut self, key: &SessionKey, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let payload = cipher(key)?
+            .decrypt(&[0u8; 12][..], &self.hash[..], ciphertext)
+            .map_err(|_| CryptoError::GeneralError("Failed to decrypt the payload".to_string()))?;
+        self.mix_hash(ciphertext);
+        Ok(payload)
+    }
+
+    fn finish(self) -> Result<Session, CryptoError> {
+        let mut key = vec![0u8; SESSION_KEY_SIZE];
+        Hkdf::<Sha256>::new(Some(&self.chaining_key[..]), &[])
+            .expand(&self.hash, &mut key)
+            .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+        Ok(Session {
+            key: SessionKey(key),
+            channel_binding: self.hash,
+        })
+    }
+}
+
+/// The initiator waiting for the response
+pub struct Initiator {
+    state: State,
+    static_key: PrivateKey,
+    ephemeral_key: PrivateKey,
+}
+
+/// Start an exchange with the owner of `remote` using the X25519 `local` key.
+/// `prologue` is any context both parties must agree on, like a protocol version
+pub fn initiate(
+    local: &PrivateKey,
+    remote: &PublicKey,
+    prologue: &[u8],
+    payload: &[u8],
+) -> Result<(Initiator, Request), CryptoError> {
+    let local_public = public_key(local)?;
+    check_public_key(remote)?;
+    let scheme = X25519Sha256::new();
+    let (ephemeral_public, ephemeral_key) = scheme.keypair(None)?;
+
+    let mut state = State::new(prologue, &local_public, remote);
+    state.mix_hash(&ephemeral_public[..]);
+    let key = state.mix_keys(
+        &scheme.compute_shared_secret(&ephemeral_key, remote)?,
+        &scheme.compute_shared_secret(local, remote)?,
+    )?;
+    let ciphertext = state.seal(&key, payload)?;
+    let initiator = Initiator {
+        state,
+        static_key: local.clone(),
+        ephemeral_key,
+    };
+    Ok((
+        initiator,
+        Request {
+            ephemeral_key: ephemeral_public,
+            ciphertext,
+        },
+    ))
+}
+
+impl Initiator {
+    /// Complete the exchange with the response and return the session and the
+    /// responder's payload. Fails if the response wasn't made by the responder for this request
+    pub fn finish(mut self, response: &Response) -> Result<(Session, Vec<u8>), CryptoError> {
+        check_public_key(&response.ephemeral_key)?;
+        let scheme = X25519Sha256::new();
+        self.state.mix_hash(&response.ephemeral_key[..]);
+        let key = self.state.mix_keys(
+            &scheme.compute_shared_secret(&self.ephemeral_key, &response.ephemeral_key)?,
+            &scheme.compute_shared_secret(&self.static_key, &response.ephemeral_key)?,
+        )?;
+        let payload = self.state.open(&key, &response.ciphertext)?;
+        Ok((self.state.finish()?, payload))
+    }
+}
+
+/// The responder after reading the request
+pub struct Responder {
+    state: State,
+    ephemeral_key: PrivateKey,
+    initiator_ephemeral: PublicKey,
+    remote: PublicKey,
+}
+
+/// Read a request from the owner of `remote` using the X25519 `local` key and return the
+/// initiator's payload. Fails if the request wasn't made by `remote` to `local`
+pub fn respond(
+    local: &PrivateKey,
+    remote: &PublicKey,
+    prologue: &[u8],
+    request: &Request,
+) -> Result<(Responder, Vec<u8>), CryptoError> {
+    let local_public = public_key(local)?;
+    check_public_key(remote)?;
+    check_public_key(&request.ephemeral_key)?;
+    let scheme = X25519Sha256::new();
+
+    let mut state = State::new(prologue, remote, &local_public);
+    state.mix_hash(&request.ephemeral_key[..]);
+    let key = state.mix_keys(
+        &scheme.compute_shared_secret(local, &request.ephemeral_key)?,
+        &scheme.compute_shared_secret(local, remote)?,
+    )?;
+    let payload = state.open(&key, &request.ciphertext)?;
+    let (_, ephemeral_key) = scheme.keypair(None)?;
+    Ok((
+        Responder {
+            state,
+            ephemeral_key,
+            initiator_ephemeral: request.ephemeral_key.clone(),
+            remote: remote.clone(),
+        },
+        payload,
+    ))
+}
+
+impl Responder {
+    /// Reply with `payload` and complete the exchange
+    pub fn reply(mut self, payload: &[u8]) -> Result<(Session, Response), CryptoError> {
+        let scheme = X25519Sha256::new();
+        let ephemeral_public = public_key(&self.ephemeral_key)?;
+        self.state.mix_hash(&ephemeral_public[..]);
+        let key = self.state.mix_keys(
+            &scheme.compute_shared_secret(&self.ephemeral_key, &self.initiator_ephemeral)?,
+            &scheme.compute_shared_secret(&self.ephemeral_key, &self.remote)?,
+        )?;
+        let ciphertext = self.state.seal(&key, payload)?;
+        Ok((
+            self.state.finish()?,
+            Response {
+                ephemeral_key: ephemeral_public,
+                ciphertext,
+            },
+        ))
+    }
+}
+
+/// The first message of a resumed session, from the initiator
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumeRequest {
+    pub ticket: Vec<u8>,
+    pub nonce: Vec<u8>,
+    /// The encrypted payload
+    pub ciphertext: Vec<u8>,
+}
+
+impl ResumeRequest {
+    /// Encode as `ticket | nonce | ciphertext`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.ticket.clone();
+        out.extend_from_slice(&self.nonce);
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() < TICKET_SIZE + NONCE_SIZE + TAG_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid ResumeRequest length".to_string(),
+            ));
+        }
+        Ok(ResumeRequest {
+            ticket: data[..TICKET_SIZE].to_vec(),
+            nonce: data[TICKET_SIZE..TICKET_SIZE + NONCE_SIZE].to_vec(),
+            ciphertext: data[TICKET_SIZE + NONCE_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// The reply to a [`ResumeRequest`]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumeResponse {
+    /// Whether the responder accepted the early data, authenticated by the ciphertext
+    pub early_data_accepted: bool,
+    pub nonce: Vec<u8>,
+    /// The encrypted payload
+    pub ciphertext: Vec<u8>,
+}
+
+impl ResumeResponse {
+    /// Encode as `early data accepted (1) | nonce | ciphertext`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.early_data_accepted as u8];
+        out.extend_from_slice(&self.nonce);
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() < 1 + NONCE_SIZE + TAG_SIZE || data[0] > 1 {
+            return Err(CryptoError::ParseError(
+                "Invalid ResumeResponse length".to_string(),
+            ));
+        }
+        Ok(ResumeResponse {
+            early_data_accepted: data[0] == 1,
+            nonce: data[1..1 + NONCE_SIZE].to_vec(),
+            ciphertext: data[1 + NONCE_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// The record of used tickets that stops resumption requests and their early data
+/// from being accepted twice.
+///
+/// Servers that share a ticket key should share the filter too, e.g. in a database
+/// with an atomic insert. A filter that loses its record, e.g. on a restart, should
+/// refuse every ticket issued before then, by rotating the ticket key. A filter may
+/// also refuse ids it can't check, which makes initiators fall back to a full handshake.
+pub trait ReplayFilter {
+    /// Record the ticket `id` at `time` until `expiry`, both in seconds.
+    /// Returns false if the id was already recorded and hasn't expired
+    fn insert(&mut self, id: &[u8], expiry: u64, time: u64) -> bool;
+}
+
+/// A [`ReplayFilter`] in memory, for a single server
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReplayFilter {
+    /// The ids and when they expire
+    used: BTreeMap<Vec<u8>, u64>,
+}
+
+impl MemoryReplayFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of ids recorded
+    pub fn len(&self) -> usize {
+        self.used.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+}
+
+impl ReplayFilter for MemoryReplayFilter {
+    fn insert(&mut self, id: &[u8], expiry: u64, time: u64) -> bool {
+        self.used.retain(|_, e| *e > time);
+        if self.used.contains_key(id) {
+            return false;
+        }
+        self.used.insert(id.to_vec(), expiry);
+        true
+    }
+}
+
+/// Issues and accepts the resumption tickets of a responder.
+/// Tickets are single use: the replay filter remembers the ones accepted until they expire
+pub struct TicketIssuer<F: ReplayFilter = MemoryReplayFilter> {
+    key: SessionKey,
+    lifetime: u64,
+    max_early_data: usize,
+    filter: F,
+}
+
+impl TicketIssuer {
+    /// An issuer with a random ticket key whose tickets are valid for `lifetime` seconds
+    pub fn new(lifetime: u64) -> Result<Self, CryptoError> {
+        let mut key = vec![0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        Self::with_key(SessionKey(key), lifetime)
+    }
+
+    /// An issuer with the given 32 byte ticket key, e.g. shared by the servers behind
+    /// a load balancer. Each server keeps its own record of used tickets, so a ticket
+    /// can be used once per server unless they share a filter with [`TicketIssuer::with_filter`]
+    pub fn with_key(key: SessionKey, lifetime: u64) -> Result<Self, CryptoError> {
+        Self::with_filter(key, lifetime, MemoryReplayFilter::new())
+    }
+}
+
+impl<F: ReplayFilter> TicketIssuer<F> {
+    /// An issuer with the given 32 byte ticket key that records used tickets in `filter`
+    pub fn with_filter(key: SessionKey, lifetime: u64, filter: F) -> Result<Self, CryptoError> {
+        if key.len() != KEY_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid ticket key provided".to_string(),
+            ));
+        }
+        Ok(TicketIssuer {
+            key,
+            lifetime,
+            max_early_data: 0,
+            filter,
+        })
+    }
+
+    /// Accept early data of up to `max` bytes, 0 to refuse all early data, the default
+    pub fn set_max_early_data(&mut self, max: usize) {
+        self.max_early_data = max;
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// Issue a ticket at `time`, in seconds, to resume `session` with the initiator `remote`
+    pub fn issue(
+        &self,
+        session: &Session,
+        remote: &PublicKey,
+        time: u64,
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_public_key(remote)?;
+        let mut plaintext = time.to_be_bytes().to_vec();
+        plaintext.extend_from_slice(&remote[..]);
+        plaintext.extend_from_slice(&session.resumption_secret()?[..]);
+        let ticket = ticket_cipher(&self.key)?.encrypt_easy(TICKET_AAD, &plaintext);
+        plaintext.zeroize();
+        ticket.map_err(|_| CryptoError::GeneralError("Failed to encrypt the ticket".to_string()))
+    }
+
+    /// Accept a resumption request at `time`, in seconds, and return its early data if
+    /// it is accepted. Fails if the ticket wasn't issued by this issuer, has expired or
+    /// was used before
+    pub fn accept(
+        &mut self,
+        prologue: &[u8],
+        request: &ResumeRequest,
+        time: u64,
+    ) -> Result<(ResumedResponder, Option<Vec<u8>>), CryptoError> {
+        let invalid = || CryptoError::GeneralError("Invalid resumption ticket".to_string());
+        if request.ticket.len() != TICKET_SIZE || request.nonce.len() != NONCE_SIZE {
+            return Err(invalid());
+        }
+        let mut plaintext = ticket_cipher(&self.key)?
+            .decrypt_easy(TICKET_AAD, &request.ticket[..])
+            .map_err(|_| invalid())?;
+        let mut issued = [0u8; 8];
+        issued.copy_from_slice(&plaintext[..8]);
+        let issued = u64::from_be_bytes(issued);
+        let remote = PublicKey(plaintext[8..8 + KEY_SIZE].to_vec());
+        let secret = SessionKey(plaintext[8 + KEY_SIZE..].to_vec());
+        plaintext.zeroize();
+
+        let expiry = issued.saturating_add(self.lifetime);
+        if time < issued || time >= expiry {
+            return Err(CryptoError::GeneralError(
+                "The resumption ticket has expired".to_string(),
+            ));
+        }
+
+        let mut state = State::resumption(prologue, &request.ticket);
+        state.mix_hash(&request.nonce);
+        let key = state.mix_keys(&secret, &SessionKey(request.nonce.clone()))?;
+        let mut early_data = state.open(&key, &request.ciphertext)?;
+        // Only a request that decrypts uses up the ticket
+        if !self
+            .filter
+            .insert(&Sha256::digest(&request.ticket), expiry, time)
+        {
+            early_data.zeroize();
+            return Err(CryptoError::GeneralError(
+                "The resumption ticket was already used".to_string(),
+            ));
+        }
+        let early_data_accepted = early_data.len() <= self.max_early_data;
+        let early_data = if early_data_accepted {
+            Some(early_data)
+        } else {
+            early_data.zeroize();
+            None
+        };
+        Ok((
+            ResumedResponder {
+                state,
+                secret,
+                remote,
+                early_data_accepted,
+            },
+            early_data,
+        ))
+    }
+}
+
+/// The initiator of a resumed session waiting for the response
+pub struct ResumingInitiator {
+    state: State,
+    secret: SessionKey,
+}
+
+/// Resume a session with a ticket from the responder and the session's
+/// [`Session::resumption_secret`], sending `early_data` the responder may accept.
+/// A ticket resumes once, use a new one from the resumed session next time
+pub fn resume(
+    ticket: &[u8],
+    secret: &SessionKey,
+    prologue: &[u8],
+    early_data: &[u8],
+) -> Result<(ResumingInitiator, ResumeRequest), CryptoError> {
+    if ticket.len() != TICKET_SIZE || secret.len() != KEY_SIZE {
+        return Err(CryptoError::ParseError(
+            "Invalid resumption ticket".to_string(),
+        ));
+    }
+    let mut nonce = vec![0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let mut state = State::resumption(prologue, ticket);
+    state.mix_hash(&nonce);
+    let key = state.mix_keys(secret, &SessionKey(nonce.clone()))?;
+    let ciphertext = state.seal(&key, early_data)?;
+    Ok((
+        ResumingInitiator {
+            state,
+            secret: secret.clone(),
+        },
+        ResumeRequest {
+            ticket: ticket.to_vec(),
+            nonce,
+            ciphertext,
+        },
+    ))
+}
+
+impl ResumingInitiator {
+    /// Complete the resumption with the response and return the session and the
+    /// responder's payload. Send the early data again over the session if the response
+    /// didn't accept it
+    pub fn finish(mut self, response: &ResumeResponse) -> Result<(Session, Vec<u8>), CryptoError> {
+        if response.nonce.len() != NONCE_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid ResumeResponse nonce".to_string(),
+            ));
+        }
+        self.state.mix_hash(&[response.early_data_accepted as u8]);
+        self.state.mix_hash(&response.nonce);
+        let key = self
+            .state
+            .mix_keys(&self.secret, &SessionKey(response.nonce.clone()))?;
+        let payload = self.state.open(&key, &response.ciphertext)?;
+        Ok((self.state.finish()?, payload))
+    }
+}
+
+/// The responder after accepting a resumption request
+pub struct ResumedResponder {
+    state: State,
+    secret: SessionKey,
+    remote: PublicKey,
+    early_data_accepted: bool,
+}
+
+impl ResumedResponder {
+    /// The static key of the initiator the ticket was issued to
+    pub fn remote(&self) -> &PublicKey {
+        &self.remote
+    }
+
+    /// Reply with `payload` and complete the resumption
+    pub fn reply(mut self, payload: &[u8]) -> Result<(Session, ResumeResponse), CryptoError> {
+        let mut nonce = vec![0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        self.state.mix_hash(&[self.early_data_accepted as u8]);
+        self.state.mix_hash(&nonce);
+        let key = self
+            .state
+            .mix_keys(&self.secret, &SessionKey(nonce.clone()))?;
+        let ciphertext = self.state.seal(&key, payload)?;
+        Ok((
+            self.state.finish()?,
+            ResumeResponse {
+                early_data_accepted: self.early_data_accepted,
+                nonce,
+                ciphertext,
+            },
+        ))
+    }
+}
+
+fn public_key(private_key: &PrivateKey) -> Result<PublicKey, CryptoError> {
+    if private_key.len() != KEY_SIZE {
+        return Err(CryptoError::ParseError(
+            "Invalid private key provided".to_string(),
+        ));
+    }
+    let (public_key, _) =
+        X25519Sha256::new().keypair(Some(KeyGenOption::FromSecretKey(private_key.clone())))?;
+    Ok(public_key)
+}
+
+fn check_public_key(public_key: &PublicKey) -> Result<(), CryptoError> {
+    if public_key.len() != KEY_SIZE {
+        return Err(CryptoError::ParseError(
+            "Invalid public key provided".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn ticket_cipher(key: &SessionKey) -> Result<SymmetricEncryptor<XChaCha20Poly1305>, CryptoError> {
+    SymmetricEncryptor::<XChaCha20Poly1305>::new_with_key(&key[..])
+        .map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+fn cipher(key: &SessionKey) -> Result<SymmetricEncryptor<ChaCha20Poly1305>, CryptoError> {
+    SymmetricEncryptor::<ChaCha20Poly1305>::new_with_key(&key[..])
+        .map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn keys() -> ((PublicKey, PrivateKey), (PublicKey, PrivateKey)) {
+        let scheme = X25519Sha256::new();
+        (scheme.keypair(None).unwrap(), scheme.keypair(None).unwrap())
+    }
+
+    #[test]
+    fn agreement() {
+        let ((device_pk, device_sk), (server_pk, server_sk)) = keys();
+        let (initiator, request) = initiate(&device_sk, &server_pk, b"v1", b"temp=21.5").unwrap();
+        assert_eq!(request.ciphertext.len(), 9 + TAG_SIZE);
+        let (responder, payload) = respond(&server_sk, &device_pk, b"v1", &request).unwrap();
+        assert_eq!(payload, b"temp=21.5");
+        let (server, response) = responder.reply(b"ack").unwrap();
+        let (device, payload) = initiator.finish(&response).unwrap();
+        assert_eq!(payload, b"ack");
+        assert_eq!(device, server);
+        assert_eq!(device.key.len(), SESSION_KEY_SIZE);
+        assert_eq!(device.channel_binding.len(), CHANNEL_BINDING_SIZE);
+        assert_ne!(device.key[..], device.channel_binding[..]);
+
+        // Every exchange gets a fresh key and channel binding
+        let (initiator, request) = initiate(&device_sk, &server_pk, b"v1", b"").unwrap();
+        let (responder, payload) = respond(&server_sk, &device_pk, b"v1", &request).unwrap();
+        assert!(payload.is_empty());
+        let (server_again, response) = responder.reply(b"").unwrap();
+        let (device_again, _) = initiator.finish(&response).unwrap();
+        assert_eq!(device_again, server_again);
+        assert_ne!(device_again.key, device.key);
+        assert_ne!(device_again.channel_binding, device.channel_binding);
+    }
+
+    #[test]
+    fn wrong_keys_fail() {
+        let ((device_pk, device_sk), (server_pk, server_sk)) = keys();
+        let ((mallory_pk, mallory_sk), _) = keys();
+        let (initiator, request) = initiate(&device_sk, &server_pk, b"v1", b"hi").unwrap();
+
+        // Only the responder reads the request, only from the expected initiator
+        assert!(respond(&mallory_sk, &device_pk, b"v1", &request).is_err());
+        assert!(respond(&server_sk, &mallory_pk, b"v1", &request).is_err());
+        assert!(respond(&server_sk, &device_pk, b"v2", &request).is_err());
+
+        // A response to another request is refused
+        let (_, other_request) = initiate(&device_sk, &server_pk, b"v1", b"hi").unwrap();
+        let (responder, _) = respond(&server_sk, &device_pk, b"v1", &other_request).unwrap();
+        let (_, other_response) = responder.reply(b"ack").unwrap();
+        assert!(initiator.finish(&other_response).is_err());
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let ((device_pk, device_sk), (server_pk, server_sk)) = keys();
+        let (initiator, request) = initiate(&device_sk, &server_pk, b"", b"open").unwrap();
+        let bytes = request.to_bytes();
+        assert_eq!(Request::from_bytes(&bytes).unwrap(), request);
+        for i in [0, 31, 32, bytes.len() - 1].iter() {
+            let mut bad = bytes.clone();
+            bad[*i] ^= 1;
+            let bad = Request::from_bytes(&bad).unwrap();
+            assert!(respond(&server_sk, &device_pk, b"", &bad).is_err());
+        }
+        assert!(Request::from_bytes(&bytes[..KEY_SIZE + TAG_SIZE - 1]).is_err());
+
+        // A replayed request is read again but gets a different session
+        let (responder, _) = respond(&server_sk, &device_pk, b"", &request).unwrap();
+        let (server, response) = responder.reply(b"opened").unwrap();
+        let (replayed, _) = respond(&server_sk, &device_pk, b"", &request).unwrap();
+        let (replayed, _) = replayed.reply(b"opened").unwrap();
+        assert_ne!(replayed, server);
+
+        let mut bad = response.to_bytes();
+        bad[KEY_SIZE] ^= 1;
+        assert!(initiator
+            .finish(&Response::from_bytes(&bad).unwrap())
+            .is_err());
+
+        assert!(initiate(&PrivateKey(vec![0u8; 31]), &server_pk, b"", b"").is_err());
+        assert!(initiate(&device_sk, &PublicKey(vec![0u8; 33]), b"", b"").is_err());
+    }
+
+    fn handshake() -> (PublicKey, Session, Session) {
+        let ((device_pk, device_sk), (server_pk, server_sk)) = keys();
+        let (initiator, request) = initiate(&device_sk, &server_pk, b"v1", b"").unwrap();
+        let (responder, _) = respond(&server_sk, &device_pk, b"v1", &request).unwrap();
+        let (server, response) = responder.reply(b"").unwrap();
+        let (device, _) = initiator.finish(&response).unwrap();
+        (device_pk, device, server)
+    }
+
+    #[test]
+    fn resumption() {
+        let (device_pk, device, server) = handshake();
+        let mut issuer = TicketIssuer::new(3600).unwrap();
+        issuer.set_max_early_data(64);
+        let ticket = issuer.issue(&server, &device_pk, 100).unwrap();
+        assert_eq!(ticket.len(), TICKET_SIZE);
+        let secret = device.resumption_secret().unwrap();
+        assert_eq!(secret, server.resumption_secret().unwrap());
+
+        let (initiator, request) = resume(&ticket, &secret, b"v1", b"temp=22.0").unwrap();
+        let (responder, payload) = issuer.accept(b"v1", &request, 200).unwrap();
+        assert_eq!(payload.unwrap(), b"temp=22.0");
+        assert_eq!(responder.remote(), &device_pk);
+        let (server_resumed, response) = responder.reply(b"ack").unwrap();
+        assert!(response.early_data_accepted);
+        let (device_resumed, payload) = initiator.finish(&response).unwrap();
+        assert_eq!(payload, b"ack");
+        assert_eq!(device_resumed, server_resumed);
+        assert_ne!(device_resumed.key, device.key);
+        assert_ne!(device_resumed.channel_binding, device.channel_binding);
+
+        // Resumed sessions issue the next ticket
+        let next = issuer.issue(&server_resumed, &device_pk, 300).unwrap();
+        let secret = device_resumed.resumption_secret().unwrap();
+        let (initiator, request) = resume(&next, &secret, b"v1", b"").unwrap();
+        let (responder, _) = issuer.accept(b"v1", &request, 400).unwrap();
+        let (server_again, response) = responder.reply(b"").unwrap();
+        let (device_again, _) = initiator.finish(&response).unwrap();
+        assert_eq!(device_again, server_again);
+
+        let bytes = request.to_bytes();
+        assert_eq!(ResumeRequest::from_bytes(&bytes).unwrap(), request);
+        assert!(ResumeRequest::from_bytes(&bytes[..TICKET_SIZE + NONCE_SIZE]).is_err());
+        let bytes = response.to_bytes();
+        assert_eq!(ResumeResponse::from_bytes(&bytes).unwrap(), response);
+        assert!(ResumeResponse::from_bytes(&bytes[..1 + NONCE_SIZE]).is_err());
+        let mut bad = bytes.clone();
+        bad[0] = 2;
+        assert!(ResumeResponse::from_bytes(&bad).is_err());
+    }
+
+    #[test]
+    fn tickets_are_single_use() {
+        let (device_pk, device, server) = handshake();
+        let mut issuer = TicketIssuer::new(60).unwrap();
+        let ticket = issuer.issue(&server, &device_pk, 1000).unwrap();
+        let secret = device.resumption_secret().unwrap();
+
+        // A request without the secret doesn't use up the ticket
+        let wrong = SessionKey(vec![7u8; KEY_SIZE]);
+        let (_, forged) = resume(&ticket, &wrong, b"v1", b"").unwrap();
+        assert!(issuer.accept(b"v1", &forged, 1001).is_err());
+
+        let (_, request) = resume(&ticket, &secret, b"v1", b"unlock").unwrap();
+        assert!(issuer.accept(b"v2", &request, 1001).is_err());
+        issuer.accept(b"v1", &request, 1001).unwrap();
+        // Neither the request nor the ticket can be replayed while the ticket is valid
+        assert!(issuer.accept(b"v1", &request, 1002).is_err());
+        let (_, again) = resume(&ticket, &secret, b"v1", b"unlock").unwrap();
+        assert!(issuer.accept(b"v1", &again, 1059).is_err());
+        // and refused once expired
+        assert!(issuer.accept(b"v1", &again, 1060).is_err());
+        assert_eq!(issuer.filter().len(), 1);
+
+        // The memory filter forgets ids once they expire
+        let mut filter = MemoryReplayFilter::new();
+        assert!(filter.insert(b"a", 10, 0));
+        assert!(!filter.insert(b"a", 10, 9));
+        assert!(filter.insert(b"b", 20, 10));
+        assert_eq!(filter.len(), 1);
+        assert!(filter.insert(b"a", 30, 10));
+    }
+
+    #[test]
+    fn invalid_tickets_fail() {
+        let (device_pk, device, server) = handshake();
+        let mut issuer = TicketIssuer::new(60).unwrap();
+        let ticket = issuer.issue(&server, &device_pk, 1000).unwrap();
+        let secret = device.resumption_secret().unwrap();
+        let (initiator, request) = resume(&ticket, &secret, b"", b"").unwrap();
+
+        // Outside the lifetime
+        assert!(issuer.accept(b"", &request, 999).is_err());
+        assert!(issuer.accept(b"", &request, 1060).is_err());
+        // From another issuer or modified
+        let mut other = TicketIssuer::with_key(SessionKey(vec![1u8; KEY_SIZE]), 60).unwrap();
+        assert!(other.accept(b"", &request, 1001).is_err());
+        for i in [0, 30, TICKET_SIZE - 1].iter() {
+            let mut bad = request.clone();
+            bad.ticket[*i] ^= 1;
+            assert!(issuer.accept(b"", &bad, 1001).is_err());
+        }
+        let mut bad = request.clone();
+        bad.nonce[0] ^= 1;
+        assert!(issuer.accept(b"", &bad, 1001).is_err());
+
+        let (responder, _) = issuer.accept(b"", &request, 1001).unwrap();
+        let (_, response) = responder.reply(b"").unwrap();
+        let mut bad = response.clone();
+        bad.nonce[0] ^= 1;
+        assert!(initiator.finish(&bad).is_err());
+
+        assert!(resume(&ticket[1..], &secret, b"", b"").is_err());
+        assert!(TicketIssuer::with_key(SessionKey(vec![0u8; 16]), 60).is_err());
+        assert!(issuer
+            .issue(&server, &PublicKey(vec![0u8; 31]), 1000)
+            .is_err());
+    }
+
+    /// A filter shared by several servers
+    #[derive(Clone, Default)]
+    struct SharedFilter(Rc<RefCell<MemoryReplayFilter>>);
+
+    impl ReplayFilter for SharedFilter {
+        fn insert(&mut self, id: &[u8], expiry: u64, time: u64) -> bool {
+            self.0.borrow_mut().insert(id, expiry, time)
+        }
+    }
+
+    #[test]
+    fn early_data() {
+        let (device_pk, device, server) = handshake();
+        let ticket_key = SessionKey(vec![3u8; KEY_SIZE]);
+        let filter = SharedFilter::default();
+        let mut first = TicketIssuer::with_filter(ticket_key.clone(), 60, filter.clone()).unwrap();
+        let mut second = TicketIssuer::with_filter(ticket_key, 60, filter.clone()).unwrap();
+        let secret = device.resumption_secret().unwrap();
+        let ticket =
+            |issuer: &TicketIssuer<SharedFilter>| issuer.issue(&server, &device_pk, 0).unwrap();
+
+        // Refused by default, the initiator learns to send it over the session
+        let (initiator, request) = resume(&ticket(&first), &secret, b"", b"reading").unwrap();
+        let (responder, early) = first.accept(b"", &request, 1).unwrap();
+        assert!(early.is_none());
+        let (server_resumed, response) = responder.reply(b"").unwrap();
+        assert!(!response.early_data_accepted);
+        let (device_resumed, _) = initiator.finish(&response).unwrap();
+        assert_eq!(device_resumed, server_resumed);
+
+        // Accepted up to the limit
+        first.set_max_early_data(7);
+        let (_, request) = resume(&ticket(&first), &secret, b"", b"reading").unwrap();
+        assert_eq!(
+            first.accept(b"", &request, 1).unwrap().1.unwrap(),
+            b"reading"
+        );
+        let (initiator, request) = resume(&ticket(&first), &secret, b"", b"readings").unwrap();
+        let (responder, early) = first.accept(b"", &request, 1).unwrap();
+        assert!(early.is_none());
+        let (_, response) = responder.reply(b"").unwrap();
+        assert!(!response.early_data_accepted);
+        let mut forged = response.clone();
+        forged.early_data_accepted = true;
+        assert!(initiator.finish(&forged).is_err());
+
+        // A request accepted by one server is refused by the others sharing the filter
+        let (_, request) = resume(&ticket(&first), &secret, b"", b"reading").unwrap();
+        first.accept(b"", &request, 1).unwrap();
+        assert!(second.accept(b"", &request, 2).is_err());
+        assert_eq!(filter.0.borrow().len(), 4);
+        // but not by a server with a filter of its own
+        let mut other = TicketIssuer::with_key(SessionKey(vec![3u8; KEY_SIZE]), 60).unwrap();
+        assert!(other.accept(b"", &request, 2).is_ok());
+
+        // A filter that refuses everything makes every resumption fail
+        struct Refuse;
+        impl ReplayFilter for Refuse {
+            fn insert(&mut self, _: &[u8], _: u64, _: u64) -> bool {
+                false
+            }
+        }
+        let mut refusing =
+            TicketIssuer::with_filter(SessionKey(vec![3u8; KEY_SIZE]), 60, Refuse).unwrap();
+        let (_, request) = resume(&ticket(&first), &secret, b"", b"").unwrap();
+        assert!(refusing.accept(b"", &request, 1).is_err());
+    }
+}
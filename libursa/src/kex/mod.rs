@@ -32,8 +32,16 @@ pub trait KeyExchangeScheme {
     fn private_key_size() -> usize;
 }
 
+#[cfg(feature = "group_messaging")]
+pub mod group;
+#[cfg(feature = "kex_kk")]
+pub mod kk;
+#[cfg(feature = "ratchet")]
+pub mod ratchet;
 #[cfg(any(feature = "x25519", feature = "x25519_asm"))]
 pub mod x25519;
+#[cfg(feature = "ratchet")]
+pub mod x3dh;
 
 #[cfg(any(
     feature = "ecdh_secp256k1",
@@ -0,0 +1,471 @@
+//! Double Ratchet sessions for encrypting a conversation.
+//!
+//! A [`Session`] starts from the [`SharedSecret`] of an X3DH agreement and derives a
+//! new key for every message. Each message key comes from a hash chain, so a stolen
+//! session can't decrypt earlier messages, and whenever the other party replies both
+//! sides mix in a fresh X25519 exchange, so it stops decrypting later ones too.
+//!
+//! The initiator starts with [`Session::initiator`] and the bundle's signed prekey and
+//! can send straight away, with the X3DH `InitialMessage` alongside its first message.
+//! The responder starts with [`Session::responder`] and the private signed prekey, and
+//! can send once it has decrypted a message.
+//!
+//! Messages can arrive out of order. The keys of up to [`MAX_SKIP`] messages skipped
+//! in a chain are kept until those messages arrive, the oldest are dropped after
+//! that. A message that fails to decrypt leaves the session as it was.
+//!
+//! Messages are encoded as
+//!
+//! ```text
+//! ratchet key (32) | previous chain length (4, big endian) | index (4, big endian) | nonce (24) | ciphertext
+//! ```
+//!
+//! and sealed with XChaCha20-Poly1305 under the message key, authenticating the
+//! identities from X3DH and the header. Chain keys are stepped with HMAC-SHA256 and
+//! the root key with HKDF-SHA256, as in the Signal specification.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use encryption::symm::prelude::*;
+use kex::x25519::X25519Sha256;
+use kex::x3dh::{SharedSecret, SignedPreKey};
+use kex::KeyExchangeScheme;
+use keys::{PrivateKey, PublicKey, SessionKey};
+use CryptoError;
+
+/// The most message keys skipped in one chain
+pub const MAX_SKIP: u32 = 1000;
+/// The length a message adds to its plaintext
+pub const OVERHEAD: usize = HEADER_SIZE + NONCE_SIZE + TAG_SIZE;
+
+const MAX_STORED_SKIPPED: usize = 2 * MAX_SKIP as usize;
+const KEY_SIZE: usize = 32;
+const HEADER_SIZE: usize = KEY_SIZE + 8;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+const ROOT_INFO: &[u8] = b"URSA_RATCHET_ROOT_V1";
+const MESSAGE_KEY_INPUT: u8 = 1;
+const CHAIN_KEY_INPUT: u8 = 2;
+
+/// The key of a message that hasn't arrived yet
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+struct SkippedKey {
+    ratchet_key: PublicKey,
+    index: u32,
+    message_key: SessionKey,
+}
+
+/// One side of a conversation. Store it between messages, serialized with the
+/// `serde` feature, as securely as a private key.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct Session {
+    associated_data: Vec<u8>,
+    root_key: SessionKey,
+    sending_ratchet: (PublicKey, PrivateKey),
+    receiving_ratchet: Option<PublicKey>,
+    sending_chain: Option<SessionKey>,
+    receiving_chain: Option<SessionKey>,
+    sent: u32,
+    received: u32,
+    previous_sent: u32,
+    skipped: Vec<SkippedKey>,
+}
+
+impl Session {
+    /// Start the initiator's session with the X3DH secret and the signed prekey of
+    /// the responder's bundle
+    pub fn initiator(
+        shared: &SharedSecret,
+        signed_prekey: &PublicKey,
+    ) -> Result<Self, CryptoError> {
+        let scheme = X25519Sha256::new();
+        let (public_key, private_key) = scheme.keypair(None)?;
+        let (root_key, sending_chain) = kdf_root(
+            &shared.key,
+            &scheme.compute_shared_secret(&private_key, signed_prekey)?,
+        )?;
+        Ok(Session {
+            associated_data: shared.associated_data.clone(),
+            root_key,
+            sending_ratchet: (public_key, private_key),
+            receiving_ratchet: Some(signed_prekey.clone()),
+            sending_chain: Some(sending_chain),
+            receiving_chain: None,
+            sent: 0,
+            received: 0,
+            previous_sent: 0,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Start the responder's session with the X3DH secret and the signed prekey the
+    /// initial message used
+    pub fn responder(shared: &SharedSecret, signed_prekey: &SignedPreKey) -> Self {
+        Session {
+            associated_data: shared.associated_data.clone(),
+            root_key: shared.key.clone(),
+            sending_ratchet: (
+                signed_prekey.public_key.clone(),
+                signed_prekey.private_key().clone(),
+            ),
+            receiving_ratchet: None,
+            sending_chain: None,
+            receiving_chain: None,
+            sent: 0,
+            received: 0,
+            previous_sent: 0,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Encrypt the next message
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let (chain, message_key) = match self.sending_chain {
+            Some(ref chain) => kdf_chain(chain)?,
+            None => {
+                return Err(CryptoError::GeneralError(
+                    "The responder can't send before it has received a message".to_string(),
+                ))
+            }
+        };
+        let header = Header {
+            ratchet_key: self.sending_ratchet.0.clone(),
+            previous: self.previous_sent,
+            index: self.sent,
+        }
+        .to_bytes();
+        self.sent = self.sent.checked_add(1).ok_or_else(|| {
+            CryptoError::GeneralError("The sending chain is exhausted".to_string())
+        })?;
+        self.sending_chain = Some(chain);
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = encryptor(&message_key)?
+            .encrypt(&nonce[..], &self.aad(&header), plaintext)
+            .map_err(|_| CryptoError::GeneralError("Failed to encrypt the message".to_string()))?;
+        let mut output = header;
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&ciphertext);
+        Ok(output)
+    }
+
+    /// Decrypt a message from the other side, ratcheting forward if it starts a new
+    /// chain
+    pub fn decrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if message.len() < OVERHEAD {
+            return Err(CryptoError::ParseError("Message is too short".to_string()));
+        }
+        let (header_bytes, sealed) = message.split_at(HEADER_SIZE);
+        let header = Header::from_bytes(header_bytes);
+        let aad = self.aad(header_bytes);
+
+        if let Some(i) = self
+            .skipped
+            .iter()
+            .position(|s| s.ratchet_key == header.ratchet_key && s.index == header.index)
+        {
+            let plaintext = open(&self.skipped[i].message_key, &aad, sealed)?;
+            self.skipped.remove(i);
+            return Ok(plaintext);
+        }
+
+        // Work on a copy so a forged message can't advance the session
+        let mut next = self.clone();
+        if next.receiving_ratchet.as_ref() != Some(&header.ratchet_key) {
+            next.skip_message_keys(header.previous)?;
+            next.ratchet(&header.ratchet_key)?;
+        }
+        if header.index < next.received {
+            return Err(CryptoError::GeneralError(
+                "Message was already decrypted or its key was dropped".to_string(),
+            ));
+        }
+        next.skip_message_keys(header.index)?;
+        let (chain, message_key) = match next.receiving_chain {
+            Some(ref chain) => kdf_chain(chain)?,
+            // Only a forged message reuses the responder's prekey as its ratchet key
+            None => {
+                return Err(CryptoError::GeneralError(
+                    "Failed to decrypt the message".to_string(),
+                ))
+            }
+        };
+        next.receiving_chain = Some(chain);
+        next.received += 1;
+
+        let plaintext = open(&message_key, &aad, sealed)?;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    /// The public ratchet key currently sent, which changes after each reply
+    pub fn ratchet_key(&self) -> &PublicKey {
+        &self.sending_ratchet.0
+    }
+
+    fn aad(&self, header: &[u8]) -> Vec<u8> {
+        let mut aad = self.associated_data.clone();
+        aad.extend_from_slice(header);
+        aad
+    }
+
+    /// Store the keys of the messages of the receiving chain before `until`
+    fn skip_message_keys(&mut self, until: u32) -> Result<(), CryptoError> {
+        if u64::from(until) > u64::from(self.received) + u64::from(MAX_SKIP) {
+            return Err(CryptoError::GeneralError(format!(
+                "Message skips more than {} messages",
+                MAX_SKIP
+            )));
+        }
+        let ratchet_key = match (&self.receiving_chain, &self.receiving_ratchet) {
+            (Some(_), Some(key)) => key.clone(),
+            _ => return Ok(()),
+        };
+        while self.received < until {
+            let (chain, message_key) = match self.receiving_chain {
+                Some(ref chain) => kdf_chain(chain)?,
+                None => break,
+            };
+            self.receiving_chain = Some(chain);
+            self.skipped.push(SkippedKey {
+                ratchet_key: ratchet_key.clone(),
+                index: self.received,
+                message_key,
+            });
+            self.received += 1;
+        }
+        if self.skipped.len() > MAX_STORED_SKIPPED {
+            let excess = self.skipped.len() - MAX_STORED_SKIPPED;
+            self.skipped.drain(..excess);
+        }
+        Ok(())
+    }
+
+    /// Mix in a new exchange with the other side's `ratchet_key`
+    fn ratchet(&mut self, ratchet_key: &PublicKey) -> Result<(), CryptoError> {
+        let scheme = X25519Sha256::new();
+        self.previous_sent = self.sent;
+        self.sent = 0;
+        self.received = 0;
+        self.receiving_ratchet = Some(ratchet_key.clone());
+
+        let (root_key, receiving_chain) = kdf_root(
+            &self.root_key,
+            &scheme.compute_shared_secret(&self.sending_ratchet.1, ratchet_key)?,
+        )?;
+        self.sending_ratchet = scheme.keypair(None)?;
+        let (root_key, sending_chain) = kdf_root(
+            &root_key,
+            &scheme.compute_shared_secret(&self.sending_ratchet.1, ratchet_key)?,
+        )?;
+        self.root_key = root_key;
+        self.receiving_chain = Some(receiving_chain);
+        self.sending_chain = Some(sending_chain);
+        Ok(())
+    }
+}
+
+struct Header {
+    ratchet_key: PublicKey,
+    previous: u32,
+    index: u32,
+}
+
+impl Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.ratchet_key[..].to_vec();
+        output.extend_from_slice(&self.previous.to_be_bytes());
+        output.extend_from_slice(&self.index.to_be_bytes());
+        output
+    }
+
+    /// Parse the `HEADER_SIZE` bytes of a header
+    fn from_bytes(data: &[u8]) -> Self {
+        let mut previous = [0u8; 4];
+        previous.copy_from_slice(&data[KEY_SIZE..KEY_SIZE + 4]);
+        let mut index = [0u8; 4];
+        index.copy_from_slice(&data[KEY_SIZE + 4..HEADER_SIZE]);
+        Header {
+            ratchet_key: PublicKey(data[..KEY_SIZE].to_vec()),
+            previous: u32::from_be_bytes(previous),
+            index: u32::from_be_bytes(index),
+        }
+    }
+}
+
+/// The next root key and a new chain key
+fn kdf_root(
+    root_key: &SessionKey,
+    dh: &SessionKey,
+) -> Result<(SessionKey, SessionKey), CryptoError> {
+    let mut okm = [0u8; 2 * KEY_SIZE];
+    Hkdf::<Sha256>::new(Some(&root_key[..]), &dh[..])
+        .expand(ROOT_INFO, &mut okm)
+        .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    let keys = (
+        SessionKey(okm[..KEY_SIZE].to_vec()),
+        SessionKey(okm[KEY_SIZE..].to_vec()),
+    );
+    okm.zeroize();
+    Ok(keys)
+}
+
+/// The next chain key and a message key
+fn kdf_chain(chain_key: &SessionKey) -> Result<(SessionKey, SessionKey), CryptoError> {
+    let hmac = |input: u8| -> Result<SessionKey, CryptoError> {
+        let mut mac = Hmac::<Sha256>::new_varkey(&chain_key[..])
+            .map_err(|_| CryptoError::GeneralError("Invalid chain key".to_string()))?;
+        mac.input(&[input]);
+        Ok(SessionKey(mac.result().code().to_vec()))
+    };
+    Ok((hmac(CHAIN_KEY_INPUT)?, hmac(MESSAGE_KEY_INPUT)?))
+}
+
+fn encryptor(
+    message_key: &SessionKey,
+) -> Result<SymmetricEncryptor<XChaCha20Poly1305>, CryptoError> {
+    SymmetricEncryptor::<XChaCha20Poly1305>::new_with_key(&message_key[..])
+        .map_err(|_| CryptoError::GeneralError("Failed to create the cipher".to_string()))
+}
+
+fn open(message_key: &SessionKey, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+    encryptor(message_key)?
+        .decrypt(nonce, aad, ciphertext)
+        .map_err(|_| CryptoError::GeneralError("Failed to decrypt the message".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kex::x3dh::{initiate, respond, OneTimePreKey, PreKeyBundle};
+    use signatures::ed25519::Ed25519Sha512;
+    use signatures::SignatureScheme;
+
+    fn sessions() -> (Session, Session) {
+        let (_, alice_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let (bob_pk, bob_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let signed = SignedPreKey::generate(1, &bob_sk).unwrap();
+        let one_time = OneTimePreKey::generate(1).unwrap();
+        let bundle = PreKeyBundle::new(&bob_pk, &signed, Some(&one_time));
+
+        let (shared, initial) = initiate(&alice_sk, &bundle).unwrap();
+        let alice = Session::initiator(&shared, &bundle.signed_prekey).unwrap();
+        let shared = respond(&bob_sk, &signed, Some(&one_time), &initial).unwrap();
+        let bob = Session::responder(&shared, &signed);
+        (alice, bob)
+    }
+
+    #[test]
+    fn conversation() {
+        let (mut alice, mut bob) = sessions();
+        assert!(bob.encrypt(b"too early").is_err());
+
+        let alice_key = alice.ratchet_key().clone();
+        let bob_key = bob.ratchet_key().clone();
+        let first = alice.encrypt(b"hi bob").unwrap();
+        assert_eq!(first.len(), OVERHEAD + 6);
+        assert_eq!(bob.decrypt(&first).unwrap(), b"hi bob");
+
+        for i in 0..3 {
+            let m = bob.encrypt(format!("reply {}", i).as_bytes()).unwrap();
+            assert_eq!(
+                alice.decrypt(&m).unwrap(),
+                format!("reply {}", i).as_bytes()
+            );
+        }
+        // Both sides moved to new ratchet keys
+        assert_ne!(bob.ratchet_key(), &bob_key);
+        assert_ne!(alice.ratchet_key(), &alice_key);
+
+        let m = alice.encrypt(b"").unwrap();
+        assert_eq!(bob.decrypt(&m).unwrap(), b"");
+        // The same plaintext never encrypts the same way
+        assert_ne!(alice.encrypt(b"x").unwrap(), alice.encrypt(b"x").unwrap());
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (mut alice, mut bob) = sessions();
+        let m0 = alice.encrypt(b"zero").unwrap();
+        let m1 = alice.encrypt(b"one").unwrap();
+        let m2 = alice.encrypt(b"two").unwrap();
+        assert_eq!(bob.decrypt(&m2).unwrap(), b"two");
+        let r = bob.encrypt(b"reply").unwrap();
+        assert_eq!(alice.decrypt(&r).unwrap(), b"reply");
+        let m3 = alice.encrypt(b"three").unwrap();
+
+        // Skipped messages of the old chain still decrypt after a ratchet
+        assert_eq!(bob.decrypt(&m3).unwrap(), b"three");
+        assert_eq!(bob.decrypt(&m0).unwrap(), b"zero");
+        assert_eq!(bob.decrypt(&m1).unwrap(), b"one");
+
+        // But only once
+        assert!(bob.decrypt(&m1).is_err());
+        assert!(bob.decrypt(&m3).is_err());
+    }
+
+    #[test]
+    fn too_many_skipped() {
+        let (mut alice, mut bob) = sessions();
+        for _ in 0..MAX_SKIP + 1 {
+            alice.encrypt(b"lost").unwrap();
+        }
+        let m = alice.encrypt(b"too far").unwrap();
+        assert!(bob.decrypt(&m).is_err());
+
+        let (mut alice, mut bob) = sessions();
+        for _ in 0..MAX_SKIP {
+            alice.encrypt(b"lost").unwrap();
+        }
+        let m = alice.encrypt(b"just far enough").unwrap();
+        assert_eq!(bob.decrypt(&m).unwrap(), b"just far enough");
+    }
+
+    #[test]
+    fn forgeries_leave_the_session_unchanged() {
+        let (mut alice, mut bob) = sessions();
+        let m = alice.encrypt(b"hi bob").unwrap();
+        for i in &[0, 31, 35, 39, 40, 63, 64, m.len() - 1] {
+            let mut forged = m.clone();
+            forged[*i] ^= 1;
+            assert!(bob.decrypt(&forged).is_err());
+        }
+        assert!(bob.decrypt(&m[..OVERHEAD - 1]).is_err());
+        // Still receiving and unable to send
+        assert!(bob.encrypt(b"too early").is_err());
+        assert_eq!(bob.decrypt(&m).unwrap(), b"hi bob");
+
+        // Nor does one claiming the responder's prekey as its ratchet key
+        let mut forged = alice.encrypt(b"hi again").unwrap();
+        forged[..32].copy_from_slice(&alice.receiving_ratchet.clone().unwrap()[..]);
+        assert!(alice.decrypt(&forged).is_err());
+
+        // Another conversation's messages don't decrypt
+        let (mut carol, _) = sessions();
+        assert!(bob.decrypt(&carol.encrypt(b"hi bob").unwrap()).is_err());
+        let r = bob.encrypt(b"reply").unwrap();
+        assert_eq!(alice.decrypt(&r).unwrap(), b"reply");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization() {
+        let (mut alice, bob) = sessions();
+        let stored = serde_json::to_string(&bob).unwrap();
+        let mut bob: Session = serde_json::from_str(&stored).unwrap();
+        let m = alice.encrypt(b"hi bob").unwrap();
+        assert_eq!(bob.decrypt(&m).unwrap(), b"hi bob");
+    }
+}
@@ -70,7 +70,7 @@ mod ecdh_secp256k1 {
                 Some(mut o) => match o {
                     KeyGenOption::UseSeed(ref mut seed) => {
                         let mut s = [0u8; PRIVATE_KEY_SIZE];
-                        let mut rng = ChaChaRng::from_seed(*array_ref!(seed.as_slice(), 0, 32));
+                        let mut rng = ChaChaRng::from_seed(*array_ref!(seed.expose_secret(), 0, 32));
                         seed.zeroize();
                         rng.fill_bytes(&mut s);
                         let k = D::digest(&s);
@@ -152,7 +152,7 @@ mod ecdh_secp256k1 {
                 Some(mut o) => match o {
                     KeyGenOption::UseSeed(ref mut seed) => {
                         let mut rng =
-                            ChaChaRng::from_seed(*array_ref!(seed.as_slice(), 0, PRIVATE_KEY_SIZE));
+                            ChaChaRng::from_seed(*array_ref!(seed.expose_secret(), 0, PRIVATE_KEY_SIZE));
                         seed.zeroize();
                         rng.fill_bytes(&mut sk);
 
@@ -1,6 +1,6 @@
 use super::*;
 use rand::rngs::OsRng;
-use rand::SeedableRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 use sha2::Digest;
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
@@ -8,11 +8,136 @@ use zeroize::Zeroize;
 
 use CryptoError;
 
-pub struct X25519Sha256;
+/// The u-coordinates of the points of order 1, 2, 4 and 8, and their encodings that
+/// aren't reduced mod p. The top bit is ignored when comparing, as X25519 does
+const LOW_ORDER_POINTS: [[u8; 32]; 7] = [
+    [0u8; 32],
+    [
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    [
+        0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae, 0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4,
+        0x6a, 0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd, 0x86, 0x62, 0x05, 0x16, 0x5f, 0x49,
+        0xb8, 0x00,
+    ],
+    [
+        0x5f, 0x9c, 0x95, 0xbc, 0xa3, 0x50, 0x8c, 0x24, 0xb1, 0xd0, 0xb1, 0x55, 0x9c, 0x83, 0xef,
+        0x5b, 0x04, 0x44, 0x5c, 0xc4, 0x58, 0x1c, 0x8e, 0x86, 0xd8, 0x22, 0x4e, 0xdd, 0xd0, 0x9f,
+        0x11, 0x57,
+    ],
+    // p - 1
+    [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    // p, the same point as 0
+    [
+        0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    // p + 1, the same point as 1
+    [
+        0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+];
+
+/// Whether `public_key` is an X25519 point of small order, including the identity.
+/// Every private key gives the same shared secret with such a point, all zeros,
+/// so a peer sending one fixes the outcome of the exchange.
+///
+/// Runs in constant time for 32 byte keys.
+pub fn is_low_order(public_key: &[u8]) -> bool {
+    if public_key.len() != 32 {
+        return false;
+    }
+    let mut found = 0u8;
+    for point in LOW_ORDER_POINTS.iter() {
+        let mut diff = (public_key[31] & 0x7f) ^ point[31];
+        for i in 0..31 {
+            diff |= public_key[i] ^ point[i];
+        }
+        found |= (diff == 0) as u8;
+    }
+    found != 0
+}
+
+/// Whether the raw output of X25519 is all zeros, which is what a low order public
+/// key produces. Runs in constant time
+pub fn is_all_zero(shared_secret: &[u8]) -> bool {
+    shared_secret.iter().fold(0u8, |acc, b| acc | b) == 0
+}
+
+/// Check `public_key` is 32 bytes and not of small order
+pub fn validate_public_key(public_key: &PublicKey) -> Result<(), CryptoError> {
+    if public_key.len() != 32 {
+        return Err(CryptoError::ParseError(
+            "Public key has the wrong length".to_string(),
+        ));
+    }
+    if is_low_order(&public_key[..]) {
+        return Err(CryptoError::GeneralError(
+            "Public key has a low order".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// X25519 with the shared secret hashed with SHA-256.
+///
+/// `compute_shared_secret` rejects low order public keys and all zero shared secrets
+/// unless `with_low_order_checks(false)` opts out, for protocols that must accept
+/// any point on the wire and handle the result themselves.
+pub struct X25519Sha256 {
+    check_low_order: bool,
+}
+
+impl X25519Sha256 {
+    /// Enable or disable the low order and all zero checks, enabled by default
+    pub fn with_low_order_checks(mut self, enabled: bool) -> Self {
+        self.check_low_order = enabled;
+        self
+    }
+
+    /// Generate `count` keypairs from `rng`.
+    ///
+    /// Draws the secrets for every key in one call to `rng` and derives each
+    /// public key with the precomputed basepoint table. Meant for tests and
+    /// services that create many ephemeral keys.
+    pub fn generate_keypairs<R: RngCore + CryptoRng>(
+        &self,
+        count: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(PublicKey, PrivateKey)>, CryptoError> {
+        let mut seeds = vec![0u8; count * 32];
+        rng.try_fill_bytes(&mut seeds)
+            .map_err(|e| CryptoError::KeyGenError(e.to_string()))?;
+        let keys = seeds
+            .chunks(32)
+            .map(|seed| {
+                let sk = StaticSecret::from(*array_ref!(seed, 0, 32));
+                let pk = X25519PublicKey::from(&sk);
+                (
+                    PublicKey(pk.as_bytes().to_vec()),
+                    PrivateKey(sk.to_bytes().to_vec()),
+                )
+            })
+            .collect();
+        seeds.zeroize();
+        Ok(keys)
+    }
+}
 
 impl KeyExchangeScheme for X25519Sha256 {
     fn new() -> Self {
-        Self
+        X25519Sha256 {
+            check_low_order: true,
+        }
     }
 
     fn keypair(
@@ -22,7 +147,7 @@ impl KeyExchangeScheme for X25519Sha256 {
         let (pk, sk) = match option {
             Some(mut o) => match o {
                 KeyGenOption::UseSeed(ref mut s) => {
-                    let hash = sha2::Sha256::digest(s.as_slice());
+                    let hash = sha2::Sha256::digest(s.expose_secret());
                     s.zeroize();
                     let mut rng = ChaChaRng::from_seed(*array_ref!(hash.as_slice(), 0, 32));
                     let sk = StaticSecret::new(&mut rng);
@@ -53,9 +178,17 @@ impl KeyExchangeScheme for X25519Sha256 {
         local_private_key: &PrivateKey,
         remote_public_key: &PublicKey,
     ) -> Result<SessionKey, CryptoError> {
+        if self.check_low_order {
+            validate_public_key(remote_public_key)?;
+        }
         let sk = StaticSecret::from(*array_ref!(&local_private_key[..], 0, 32));
         let pk = X25519PublicKey::from(*array_ref!(&remote_public_key[..], 0, 32));
         let shared_secret = sk.diffie_hellman(&pk);
+        if self.check_low_order && is_all_zero(shared_secret.as_bytes()) {
+            return Err(CryptoError::GeneralError(
+                "Shared secret is all zeros".to_string(),
+            ));
+        }
         let hash = sha2::Sha256::digest(shared_secret.as_bytes());
         Ok(SessionKey(hash.as_slice().to_vec()))
     }
@@ -111,4 +244,54 @@ mod tests {
         assert_eq!(pk1, pk);
         assert_eq!(sk1, sk);
     }
+
+    #[test]
+    fn generate_keypairs() {
+        let scheme = X25519Sha256::new();
+        let keys = scheme.generate_keypairs(20, &mut OsRng).unwrap();
+        assert_eq!(keys.len(), 20);
+        for (i, (pk, sk)) in keys.iter().enumerate() {
+            let (pk1, sk1) = scheme
+                .keypair(Some(KeyGenOption::FromSecretKey(sk.clone())))
+                .unwrap();
+            assert_eq!(&pk1, pk);
+            assert_eq!(&sk1, sk);
+            assert!(keys[i + 1..].iter().all(|(pk2, _)| pk2 != pk));
+        }
+        let (pk, sk) = &keys[0];
+        let (pk1, sk1) = &keys[1];
+        assert_eq!(
+            scheme.compute_shared_secret(sk, pk1).unwrap(),
+            scheme.compute_shared_secret(sk1, pk).unwrap()
+        );
+    }
+
+    #[test]
+    fn low_order_points() {
+        let scheme = X25519Sha256::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        assert!(!is_low_order(&pk[..]));
+        validate_public_key(&pk).unwrap();
+        assert!(validate_public_key(&PublicKey(pk[1..].to_vec())).is_err());
+
+        for point in LOW_ORDER_POINTS.iter() {
+            let mut high_bit = *point;
+            high_bit[31] |= 0x80;
+            for bytes in &[*point, high_bit] {
+                assert!(is_low_order(bytes));
+                let low = PublicKey(bytes.to_vec());
+                assert!(validate_public_key(&low).is_err());
+                assert!(scheme.compute_shared_secret(&sk, &low).is_err());
+
+                // Opting out gives the hash of the all zero output
+                let secret = X25519Sha256::new()
+                    .with_low_order_checks(false)
+                    .compute_shared_secret(&sk, &low)
+                    .unwrap();
+                assert_eq!(&secret[..], &sha2::Sha256::digest(&[0u8; 32])[..]);
+            }
+        }
+        assert!(is_all_zero(&[0u8; 32]));
+        assert!(!is_all_zero(&pk[..]));
+    }
 }
@@ -0,0 +1,325 @@
+//! Asynchronous key agreement with X3DH over prekey bundles.
+//!
+//! X3DH lets Alice agree on a secret with Bob while he's offline. Bob publishes a
+//! [`PreKeyBundle`]: his Ed25519 identity key, an X25519 signed prekey with his
+//! signature over it and optionally a one-time prekey. Alice checks the signature,
+//! [`initiate`]s with an ephemeral key and sends the [`InitialMessage`] along with her
+//! first ciphertext. Bob [`respond`]s with the private prekeys it names, then deletes
+//! the one-time prekey so the message can't be replayed to him.
+//!
+//! Both sides end up with the same [`SharedSecret`], which starts a
+//! `kex::ratchet::Session`. The shared secret derives from three or four
+//! Diffie-Hellman outputs as in the Signal specification: identity keys authenticate
+//! both parties, and the ephemeral and one-time keys give forward secrecy.
+//!
+//! Identity keys are Ed25519 keys from `Ed25519Sha512`, converted to X25519 for the
+//! key agreement, and the Diffie-Hellman outputs are the hashed ones of
+//! `X25519Sha256`. Neither matches Signal's XEdDSA encoding, so this doesn't talk to
+//! libsignal.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use kex::x25519::X25519Sha256;
+use kex::KeyExchangeScheme;
+use keys::{KeyGenOption, PrivateKey, PublicKey, SessionKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// The length of the shared secret
+pub const SHARED_SECRET_SIZE: usize = 32;
+
+const SIGNED_PREKEY_CONTEXT: &[u8] = b"URSA_X3DH_SIGNED_PREKEY_V1";
+const INFO: &[u8] = b"URSA_X3DH_V1";
+
+/// A medium-term prekey signed by the identity key. Replace it every week or so and
+/// keep the old private key a little longer for messages still in flight.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct SignedPreKey {
+    pub id: u32,
+    pub public_key: PublicKey,
+    pub signature: Vec<u8>,
+    private_key: PrivateKey,
+}
+
+impl SignedPreKey {
+    /// Generate a prekey and sign it with the Ed25519 `identity` key
+    pub fn generate(id: u32, identity: &PrivateKey) -> Result<Self, CryptoError> {
+        let (public_key, private_key) = X25519Sha256::new().keypair(None)?;
+        let signature = Ed25519Sha512::new().sign(&signed_prekey_message(&public_key), identity)?;
+        Ok(SignedPreKey {
+            id,
+            public_key,
+            signature,
+            private_key,
+        })
+    }
+
+    pub(crate) fn private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+}
+
+/// A prekey for a single use. Delete it once a message has used it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct OneTimePreKey {
+    pub id: u32,
+    pub public_key: PublicKey,
+    private_key: PrivateKey,
+}
+
+impl OneTimePreKey {
+    pub fn generate(id: u32) -> Result<Self, CryptoError> {
+        let (public_key, private_key) = X25519Sha256::new().keypair(None)?;
+        Ok(OneTimePreKey {
+            id,
+            public_key,
+            private_key,
+        })
+    }
+}
+
+/// The public keys published for initiators
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreKeyBundle {
+    /// The Ed25519 identity key
+    pub identity_key: PublicKey,
+    pub signed_prekey_id: u32,
+    pub signed_prekey: PublicKey,
+    pub signed_prekey_signature: Vec<u8>,
+    /// The id and public key of a one-time prekey, if any are left
+    pub one_time_prekey: Option<(u32, PublicKey)>,
+}
+
+impl PreKeyBundle {
+    pub fn new(
+        identity_key: &PublicKey,
+        signed_prekey: &SignedPreKey,
+        one_time_prekey: Option<&OneTimePreKey>,
+    ) -> Self {
+        PreKeyBundle {
+            identity_key: identity_key.clone(),
+            signed_prekey_id: signed_prekey.id,
+            signed_prekey: signed_prekey.public_key.clone(),
+            signed_prekey_signature: signed_prekey.signature.clone(),
+            one_time_prekey: one_time_prekey.map(|k| (k.id, k.public_key.clone())),
+        }
+    }
+
+    /// Check the signed prekey was signed by the identity key
+    pub fn verify(&self) -> Result<(), CryptoError> {
+        let valid = Ed25519Sha512::new()
+            .verify(
+                &signed_prekey_message(&self.signed_prekey),
+                &self.signed_prekey_signature,
+                &self.identity_key,
+            )
+            .unwrap_or(false);
+        if valid {
+            Ok(())
+        } else {
+            Err(CryptoError::GeneralError(
+                "Signed prekey signature is invalid".to_string(),
+            ))
+        }
+    }
+}
+
+/// What the initiator sends with its first message so the responder can agree
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitialMessage {
+    /// The initiator's Ed25519 identity key
+    pub identity_key: PublicKey,
+    pub ephemeral_key: PublicKey,
+    pub signed_prekey_id: u32,
+    pub one_time_prekey_id: Option<u32>,
+}
+
+/// The agreed secret, and the identities it's bound to which every message
+/// must authenticate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedSecret {
+    pub key: SessionKey,
+    /// The initiator's identity key followed by the responder's
+    pub associated_data: Vec<u8>,
+}
+
+/// Agree on a secret with the owner of `bundle` using the Ed25519 `identity` key
+pub fn initiate(
+    identity: &PrivateKey,
+    bundle: &PreKeyBundle,
+) -> Result<(SharedSecret, InitialMessage), CryptoError> {
+    bundle.verify()?;
+    let (identity_key, identity_dh) = identity_keys(identity)?;
+    let remote_identity_dh = Ed25519Sha512::ver_key_to_key_exchange(&bundle.identity_key)?;
+    let scheme = X25519Sha256::new();
+    let (ephemeral_key, ephemeral_private) = scheme.keypair(None)?;
+
+    let mut dh = vec![
+        scheme.compute_shared_secret(&identity_dh, &bundle.signed_prekey)?,
+        scheme.compute_shared_secret(&ephemeral_private, &remote_identity_dh)?,
+        scheme.compute_shared_secret(&ephemeral_private, &bundle.signed_prekey)?,
+    ];
+    if let Some((_, ref one_time_prekey)) = bundle.one_time_prekey {
+        dh.push(scheme.compute_shared_secret(&ephemeral_private, one_time_prekey)?);
+    }
+    let shared = derive(&dh, &identity_key, &bundle.identity_key)?;
+    let message = InitialMessage {
+        identity_key,
+        ephemeral_key,
+        signed_prekey_id: bundle.signed_prekey_id,
+        one_time_prekey_id: bundle.one_time_prekey.as_ref().map(|(id, _)| *id),
+    };
+    Ok((shared, message))
+}
+
+/// Agree on the secret of an [`InitialMessage`] using the Ed25519 `identity` key and
+/// the private prekeys it names
+pub fn respond(
+    identity: &PrivateKey,
+    signed_prekey: &SignedPreKey,
+    one_time_prekey: Option<&OneTimePreKey>,
+    message: &InitialMessage,
+) -> Result<SharedSecret, CryptoError> {
+    if message.signed_prekey_id != signed_prekey.id
+        || message.one_time_prekey_id != one_time_prekey.map(|k| k.id)
+    {
+        return Err(CryptoError::GeneralError(
+            "The prekeys don't match the ones used by the initial message".to_string(),
+        ));
+    }
+    let (identity_key, identity_dh) = identity_keys(identity)?;
+    let remote_identity_dh = Ed25519Sha512::ver_key_to_key_exchange(&message.identity_key)?;
+    let scheme = X25519Sha256::new();
+
+    let mut dh = vec![
+        scheme.compute_shared_secret(&signed_prekey.private_key, &remote_identity_dh)?,
+        scheme.compute_shared_secret(&identity_dh, &message.ephemeral_key)?,
+        scheme.compute_shared_secret(&signed_prekey.private_key, &message.ephemeral_key)?,
+    ];
+    if let Some(k) = one_time_prekey {
+        dh.push(scheme.compute_shared_secret(&k.private_key, &message.ephemeral_key)?);
+    }
+    derive(&dh, &message.identity_key, &identity_key)
+}
+
+/// The Ed25519 public key of `identity` and its X25519 private key
+fn identity_keys(identity: &PrivateKey) -> Result<(PublicKey, PrivateKey), CryptoError> {
+    let (public_key, _) =
+        Ed25519Sha512::new().keypair(Some(KeyGenOption::FromSecretKey(identity.clone())))?;
+    Ok((
+        public_key,
+        Ed25519Sha512::sign_key_to_key_exchange(identity)?,
+    ))
+}
+
+fn signed_prekey_message(public_key: &PublicKey) -> Vec<u8> {
+    let mut message = SIGNED_PREKEY_CONTEXT.to_vec();
+    message.extend_from_slice(&public_key[..]);
+    message
+}
+
+fn derive(
+    dh: &[SessionKey],
+    initiator: &PublicKey,
+    responder: &PublicKey,
+) -> Result<SharedSecret, CryptoError> {
+    // Prefixed with 32 0xFF bytes as in the specification
+    let mut ikm = vec![0xFFu8; 32];
+    for d in dh {
+        ikm.extend_from_slice(&d[..]);
+    }
+    let mut key = vec![0u8; SHARED_SECRET_SIZE];
+    let res = Hkdf::<Sha256>::new(Some(&[0u8; 32]), &ikm).expand(INFO, &mut key);
+    ikm.zeroize();
+    res.map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+    let mut associated_data = initiator[..].to_vec();
+    associated_data.extend_from_slice(&responder[..]);
+    Ok(SharedSecret {
+        key: SessionKey(key),
+        associated_data,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bob() -> (PublicKey, PrivateKey, SignedPreKey, OneTimePreKey) {
+        let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let signed = SignedPreKey::generate(1, &sk).unwrap();
+        let one_time = OneTimePreKey::generate(7).unwrap();
+        (pk, sk, signed, one_time)
+    }
+
+    #[test]
+    fn agreement() {
+        let (alice_pk, alice_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let (bob_pk, bob_sk, signed, one_time) = bob();
+
+        let bundle = PreKeyBundle::new(&bob_pk, &signed, Some(&one_time));
+        let (alice, message) = initiate(&alice_sk, &bundle).unwrap();
+        assert_eq!(message.identity_key, alice_pk);
+        assert_eq!(message.signed_prekey_id, 1);
+        assert_eq!(message.one_time_prekey_id, Some(7));
+        let bob = respond(&bob_sk, &signed, Some(&one_time), &message).unwrap();
+        assert_eq!(alice, bob);
+        assert_eq!(alice.key.len(), SHARED_SECRET_SIZE);
+        assert_eq!(alice.associated_data[..32], alice_pk[..]);
+        assert_eq!(alice.associated_data[32..], bob_pk[..]);
+
+        // Without a one-time prekey
+        let bundle = PreKeyBundle::new(&bob_pk, &signed, None);
+        let (alice_again, message) = initiate(&alice_sk, &bundle).unwrap();
+        assert_eq!(message.one_time_prekey_id, None);
+        let bob_again = respond(&bob_sk, &signed, None, &message).unwrap();
+        assert_eq!(alice_again, bob_again);
+        assert_ne!(alice_again.key, alice.key);
+    }
+
+    #[test]
+    fn mismatches_fail() {
+        let (_, alice_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let (bob_pk, bob_sk, signed, one_time) = bob();
+        let bundle = PreKeyBundle::new(&bob_pk, &signed, Some(&one_time));
+        let (alice, message) = initiate(&alice_sk, &bundle).unwrap();
+
+        assert!(respond(&bob_sk, &signed, None, &message).is_err());
+        let other = OneTimePreKey::generate(8).unwrap();
+        assert!(respond(&bob_sk, &signed, Some(&other), &message).is_err());
+        let newer = SignedPreKey::generate(2, &bob_sk).unwrap();
+        assert!(respond(&bob_sk, &newer, Some(&one_time), &message).is_err());
+
+        // Someone else's identity key gets a different secret
+        let (_, mallory_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let mallory = respond(&mallory_sk, &signed, Some(&one_time), &message).unwrap();
+        assert_ne!(alice, mallory);
+    }
+
+    #[test]
+    fn bundle_signature_is_checked() {
+        let (_, alice_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let (bob_pk, _, signed, _) = bob();
+        let mut bundle = PreKeyBundle::new(&bob_pk, &signed, None);
+        bundle.verify().unwrap();
+
+        // A prekey swapped in by the server is refused
+        bundle.signed_prekey = OneTimePreKey::generate(1).unwrap().public_key;
+        assert!(bundle.verify().is_err());
+        assert!(initiate(&alice_sk, &bundle).is_err());
+
+        let (mallory_pk, _) = Ed25519Sha512::new().keypair(None).unwrap();
+        let mut bundle = PreKeyBundle::new(&bob_pk, &signed, None);
+        bundle.identity_key = mallory_pk;
+        assert!(initiate(&alice_sk, &bundle).is_err());
+    }
+}
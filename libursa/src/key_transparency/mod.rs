@@ -0,0 +1,558 @@
+//! Key transparency: a verifiable directory of users' public keys in the style of
+//! CONIKS and Key Transparency.
+//!
+//! A [`Directory`] maps names, such as email addresses, to keys. Each epoch it commits to
+//! the whole map with the root of a [`SparseMerkleTree`] over SHA-256 and signs it in a
+//! [`SignedTreeHead`] that also holds the hash of the previous head, so the heads form a
+//! chain auditors check with [`DirectoryVerifier::verify_successor`], and the directory
+//! can't show different users different histories without signing two heads of an epoch.
+//!
+//! A name's position in the tree is its index, the first 32 bytes of the [`vrf`] output
+//! of the name under the directory's VRF key. The leaf holds a commitment to the name
+//! and key with a random opening, so a tree proof reveals neither which names are in the
+//! directory nor their keys. A [`LookupProof`] proves the index with the VRF proof and
+//! the key in the tree, or that the name has no key, and is checked with
+//! [`DirectoryVerifier::verify_lookup`]. Users monitor their own names by looking them up
+//! every epoch.
+//!
+//! The VRF and signing keys are Ed25519 keys of `Ed25519Sha512`. A signed head is encoded as
+//!
+//! ```text
+//! epoch (8) | timestamp (8) | root (32) | previous (32) | signature (64)
+//! ```
+//!
+//! and a lookup proof as
+//!
+//! ```text
+//! vrf proof (80) | 0x00 | tree proof
+//! vrf proof (80) | 0x01 | opening (32) | key length (2, big endian) | key | tree proof
+//! ```
+//!
+//! with the tree proof encoded as a [`SparseMerkleProof`].
+
+pub mod vrf;
+
+use std::collections::BTreeMap;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use hash::sparse_merkle::{SparseMerkleProof, SparseMerkleTree};
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::{Ed25519Sha512, SIGNATURE_SIZE};
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// The size of roots, openings and commitments
+pub const HASH_SIZE: usize = 32;
+/// The size of an encoded signed tree head
+pub const HEAD_SIZE: usize = 16 + 2 * HASH_SIZE + SIGNATURE_SIZE;
+
+const HEAD_LABEL: &[u8] = b"ursa key transparency v1 tree head";
+const COMMITMENT_LABEL: &[u8] = b"ursa key transparency v1 commitment";
+
+type Hash = [u8; HASH_SIZE];
+
+/// The directory's signature on the root of an epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedTreeHead {
+    epoch: u64,
+    timestamp: u64,
+    root: Hash,
+    previous: Hash,
+    signature: Vec<u8>,
+}
+
+impl SignedTreeHead {
+    /// The epoch, counting from 0
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The time the head was published, as given by the directory
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// The root of the prefix tree
+    pub fn root(&self) -> &[u8] {
+        &self.root
+    }
+
+    /// The hash of the previous head, zero in epoch 0
+    pub fn previous(&self) -> &[u8] {
+        &self.previous
+    }
+
+    /// The hash the next head links to
+    pub fn hash(&self) -> Vec<u8> {
+        Sha256::digest(&self.to_bytes()).to_vec()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.signed_bytes();
+        output.extend_from_slice(&self.signature);
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() != HEAD_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid signed tree head length".to_string(),
+            ));
+        }
+        let mut epoch = [0u8; 8];
+        epoch.copy_from_slice(&data[..8]);
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&data[8..16]);
+        let mut root = [0u8; HASH_SIZE];
+        root.copy_from_slice(&data[16..16 + HASH_SIZE]);
+        let mut previous = [0u8; HASH_SIZE];
+        previous.copy_from_slice(&data[16 + HASH_SIZE..16 + 2 * HASH_SIZE]);
+        Ok(SignedTreeHead {
+            epoch: u64::from_be_bytes(epoch),
+            timestamp: u64::from_be_bytes(timestamp),
+            root,
+            previous,
+            signature: data[16 + 2 * HASH_SIZE..].to_vec(),
+        })
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(HEAD_SIZE);
+        output.extend_from_slice(&self.epoch.to_be_bytes());
+        output.extend_from_slice(&self.timestamp.to_be_bytes());
+        output.extend_from_slice(&self.root);
+        output.extend_from_slice(&self.previous);
+        output
+    }
+
+    fn message(&self) -> Vec<u8> {
+        let mut message = HEAD_LABEL.to_vec();
+        message.extend_from_slice(&self.signed_bytes());
+        message
+    }
+}
+
+/// The proof of a name's key, or that it has none, in the tree of one epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LookupProof {
+    vrf_proof: Vec<u8>,
+    entry: Option<(Hash, Vec<u8>)>,
+    tree: SparseMerkleProof,
+}
+
+impl LookupProof {
+    /// The key the proof shows, if any
+    pub fn key(&self) -> Option<&[u8]> {
+        self.entry.as_ref().map(|(_, key)| key.as_slice())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.vrf_proof.clone();
+        match self.entry {
+            None => output.push(0),
+            Some((ref opening, ref key)) => {
+                output.push(1);
+                output.extend_from_slice(opening);
+                output.extend_from_slice(&(key.len() as u16).to_be_bytes());
+                output.extend_from_slice(key);
+            }
+        }
+        output.extend_from_slice(&self.tree.to_bytes());
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::ParseError("Invalid lookup proof".to_string());
+        if data.len() <= vrf::PROOF_SIZE {
+            return Err(invalid());
+        }
+        let vrf_proof = data[..vrf::PROOF_SIZE].to_vec();
+        let rest = &data[vrf::PROOF_SIZE + 1..];
+        let (entry, rest) = match data[vrf::PROOF_SIZE] {
+            0 => (None, rest),
+            1 if rest.len() >= HASH_SIZE + 2 => {
+                let mut opening = [0u8; HASH_SIZE];
+                opening.copy_from_slice(&rest[..HASH_SIZE]);
+                let length = u16::from_be_bytes([rest[HASH_SIZE], rest[HASH_SIZE + 1]]) as usize;
+                let rest = &rest[HASH_SIZE + 2..];
+                if rest.len() < length {
+                    return Err(invalid());
+                }
+                (Some((opening, rest[..length].to_vec())), &rest[length..])
+            }
+            _ => return Err(invalid()),
+        };
+        Ok(LookupProof {
+            vrf_proof,
+            entry,
+            tree: SparseMerkleProof::from_bytes(rest)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    index: Hash,
+    key: Vec<u8>,
+    opening: Hash,
+}
+
+/// A key directory run by an identity provider
+#[derive(Debug)]
+pub struct Directory {
+    vrf_key: PrivateKey,
+    signing_key: PrivateKey,
+    entries: BTreeMap<Vec<u8>, Entry>,
+    pending: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    tree: SparseMerkleTree<Sha256>,
+    head: Option<SignedTreeHead>,
+}
+
+impl Directory {
+    /// Create an empty directory with the Ed25519 private keys of its VRF and signatures
+    pub fn new(vrf_key: PrivateKey, signing_key: PrivateKey) -> Result<Self, CryptoError> {
+        if vrf_key.len() != 64 || signing_key.len() != 64 {
+            return Err(CryptoError::ParseError(
+                "Invalid private key provided".to_string(),
+            ));
+        }
+        Ok(Directory {
+            vrf_key,
+            signing_key,
+            entries: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            tree: SparseMerkleTree::new(),
+            head: None,
+        })
+    }
+
+    /// The VRF and signing public keys clients verify the directory with
+    pub fn verifier(&self) -> DirectoryVerifier {
+        DirectoryVerifier {
+            vrf_key: PublicKey(self.vrf_key[32..].to_vec()),
+            signing_key: PublicKey(self.signing_key[32..].to_vec()),
+        }
+    }
+
+    /// The number of names with a key in the latest epoch
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The latest signed tree head, if one was published
+    pub fn head(&self) -> Option<&SignedTreeHead> {
+        self.head.as_ref()
+    }
+
+    /// Set the key of `name` in the next epoch
+    pub fn set(&mut self, name: &[u8], key: &[u8]) -> Result<(), CryptoError> {
+        if key.len() > u16::MAX as usize {
+            return Err(CryptoError::GeneralError(
+                "Keys are limited to 65535 bytes".to_string(),
+            ));
+        }
+        self.pending.insert(name.to_vec(), Some(key.to_vec()));
+        Ok(())
+    }
+
+    /// Remove the key of `name` in the next epoch
+    pub fn remove(&mut self, name: &[u8]) {
+        self.pending.insert(name.to_vec(), None);
+    }
+
+    /// Apply the changes since the last epoch and sign the head of the next one
+    pub fn publish(&mut self, timestamp: u64) -> Result<SignedTreeHead, CryptoError> {
+        let pending = ::std::mem::take(&mut self.pending);
+        let mut changes = Vec::with_capacity(pending.len());
+        for (name, key) in pending {
+            let index = match self.entries.get(&name) {
+                Some(entry) => entry.index,
+                None => to_hash(&vrf::proof_to_hash(&vrf::prove(&self.vrf_key, &name)?)?),
+            };
+            match key {
+                Some(key) => {
+                    let mut opening = [0u8; HASH_SIZE];
+                    OsRng.fill_bytes(&mut opening);
+                    changes.push((index, Some(commitment(&opening, &name, &key).to_vec())));
+                    self.entries.insert(
+                        name,
+                        Entry {
+                            index,
+                            key,
+                            opening,
+                        },
+                    );
+                }
+                None => {
+                    changes.push((index, None));
+                    self.entries.remove(&name);
+                }
+            }
+        }
+        self.tree.update(changes);
+        let mut head = SignedTreeHead {
+            epoch: self.head.as_ref().map_or(0, |h| h.epoch + 1),
+            timestamp,
+            root: to_hash(self.tree.root()),
+            previous: self
+                .head
+                .as_ref()
+                .map_or([0u8; HASH_SIZE], |h| to_hash(&h.hash())),
+            signature: Vec::new(),
+        };
+        head.signature = Ed25519Sha512::new().sign(&head.message(), &self.signing_key)?;
+        self.head = Some(head.clone());
+        Ok(head)
+    }
+
+    /// Prove the key of `name` in the latest epoch, or that it has none
+    pub fn lookup(&self, name: &[u8]) -> Result<LookupProof, CryptoError> {
+        if self.head.is_none() {
+            return Err(CryptoError::GeneralError(
+                "No tree head was published".to_string(),
+            ));
+        }
+        let vrf_proof = vrf::prove(&self.vrf_key, name)?;
+        let (index, entry) = match self.entries.get(name) {
+            Some(entry) => (entry.index, Some((entry.opening, entry.key.clone()))),
+            None => (to_hash(&vrf::proof_to_hash(&vrf_proof)?), None),
+        };
+        Ok(LookupProof {
+            vrf_proof,
+            entry,
+            tree: self.tree.prove(&index),
+        })
+    }
+}
+
+/// The public keys of a directory, for clients and auditors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectoryVerifier {
+    vrf_key: PublicKey,
+    signing_key: PublicKey,
+}
+
+impl DirectoryVerifier {
+    pub fn new(vrf_key: PublicKey, signing_key: PublicKey) -> Self {
+        DirectoryVerifier {
+            vrf_key,
+            signing_key,
+        }
+    }
+
+    /// Check the directory's signature on a head
+    pub fn verify_head(&self, head: &SignedTreeHead) -> Result<(), CryptoError> {
+        if Ed25519Sha512::new().verify(&head.message(), &head.signature, &self.signing_key)? {
+            Ok(())
+        } else {
+            Err(CryptoError::GeneralError(
+                "Invalid signed tree head".to_string(),
+            ))
+        }
+    }
+
+    /// Check that `next` is signed and directly follows `previous`,
+    /// so the directory didn't fork or rewrite its history
+    pub fn verify_successor(
+        &self,
+        previous: &SignedTreeHead,
+        next: &SignedTreeHead,
+    ) -> Result<(), CryptoError> {
+        self.verify_head(next)?;
+        if next.epoch != previous.epoch.wrapping_add(1)
+            || next.previous[..] != previous.hash()[..]
+            || next.timestamp < previous.timestamp
+        {
+            return Err(CryptoError::GeneralError(
+                "Signed tree head doesn't follow the previous one".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check a lookup of `name` against a signed head and return its key,
+    /// or `None` if the proof shows it has none
+    pub fn verify_lookup(
+        &self,
+        head: &SignedTreeHead,
+        name: &[u8],
+        proof: &LookupProof,
+    ) -> Result<Option<Vec<u8>>, CryptoError> {
+        self.verify_head(head)?;
+        let index = to_hash(&vrf::verify(&self.vrf_key, name, &proof.vrf_proof)?);
+        let value = proof
+            .entry
+            .as_ref()
+            .map(|(opening, key)| commitment(opening, name, key));
+        if !proof
+            .tree
+            .verify::<Sha256>(&head.root, &index, value.as_ref().map(|v| &v[..]))
+        {
+            return Err(CryptoError::GeneralError(
+                "Invalid lookup proof".to_string(),
+            ));
+        }
+        Ok(proof.entry.as_ref().map(|(_, key)| key.clone()))
+    }
+}
+
+/// `SHA-256(label | opening | len(name) | name | key)`
+fn commitment(opening: &Hash, name: &[u8], key: &[u8]) -> Hash {
+    to_hash(
+        &Sha256::new()
+            .chain(COMMITMENT_LABEL)
+            .chain(opening)
+            .chain((name.len() as u64).to_be_bytes())
+            .chain(name)
+            .chain(key)
+            .result(),
+    )
+}
+
+fn to_hash(data: &[u8]) -> Hash {
+    let mut hash = [0u8; HASH_SIZE];
+    hash.copy_from_slice(&data[..HASH_SIZE]);
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn directory() -> Directory {
+        let scheme = Ed25519Sha512::new();
+        let (_, vrf_key) = scheme.keypair(None).unwrap();
+        let (_, signing_key) = scheme.keypair(None).unwrap();
+        Directory::new(vrf_key, signing_key).unwrap()
+    }
+
+    #[test]
+    fn lookups() {
+        let mut directory = directory();
+        let verifier = directory.verifier();
+        assert!(directory.lookup(b"alice").is_err());
+        directory.set(b"alice", b"alice key 1").unwrap();
+        directory.set(b"bob", b"bob key").unwrap();
+        directory.set(b"carol", b"carol key").unwrap();
+        let first = directory.publish(100).unwrap();
+        assert_eq!(first.epoch(), 0);
+        assert_eq!(directory.len(), 3);
+        verifier.verify_head(&first).unwrap();
+
+        let proof = directory.lookup(b"alice").unwrap();
+        assert_eq!(
+            verifier.verify_lookup(&first, b"alice", &proof).unwrap(),
+            Some(b"alice key 1".to_vec())
+        );
+        assert!(verifier.verify_lookup(&first, b"bob", &proof).is_err());
+        let missing = directory.lookup(b"dave").unwrap();
+        assert_eq!(missing.key(), None);
+        assert_eq!(
+            verifier.verify_lookup(&first, b"dave", &missing).unwrap(),
+            None
+        );
+        assert!(verifier.verify_lookup(&first, b"erin", &missing).is_err());
+
+        // Changes apply in the next epoch
+        directory.set(b"alice", b"alice key 2").unwrap();
+        directory.remove(b"bob");
+        let stale = directory.lookup(b"alice").unwrap();
+        assert_eq!(stale.key(), Some(&b"alice key 1"[..]));
+        let second = directory.publish(200).unwrap();
+        verifier.verify_successor(&first, &second).unwrap();
+        assert!(verifier.verify_lookup(&second, b"alice", &stale).is_err());
+        let proof = directory.lookup(b"alice").unwrap();
+        assert_eq!(
+            verifier.verify_lookup(&second, b"alice", &proof).unwrap(),
+            Some(b"alice key 2".to_vec())
+        );
+        let removed = directory.lookup(b"bob").unwrap();
+        assert_eq!(
+            verifier.verify_lookup(&second, b"bob", &removed).unwrap(),
+            None
+        );
+
+        // A proof of a key can't be passed off as a proof that there is none
+        let mut hidden = proof.clone();
+        hidden.entry = None;
+        assert!(verifier.verify_lookup(&second, b"alice", &hidden).is_err());
+        let mut swapped = proof;
+        swapped.entry = Some((swapped.entry.unwrap().0, b"mallory key".to_vec()));
+        assert!(verifier.verify_lookup(&second, b"alice", &swapped).is_err());
+
+        // Other directories' heads don't verify
+        let other = directory_head();
+        assert!(verifier.verify_head(&other).is_err());
+    }
+
+    fn directory_head() -> SignedTreeHead {
+        let mut other = directory();
+        other.set(b"alice", b"alice key 1").unwrap();
+        other.publish(100).unwrap()
+    }
+
+    #[test]
+    fn head_chain() {
+        let mut directory = directory();
+        let verifier = directory.verifier();
+        let first = directory.publish(10).unwrap();
+        assert_eq!(first.previous(), &[0u8; HASH_SIZE][..]);
+        let second = directory.publish(20).unwrap();
+        let third = directory.publish(30).unwrap();
+        verifier.verify_successor(&second, &third).unwrap();
+        assert_eq!(third.previous(), &second.hash()[..]);
+        assert!(verifier.verify_successor(&first, &third).is_err());
+        assert!(verifier.verify_successor(&third, &second).is_err());
+
+        // A fork: another head of the same epoch
+        let mut forked = third.clone();
+        forked.timestamp = 31;
+        forked.signature = Ed25519Sha512::new()
+            .sign(&forked.message(), &directory.signing_key)
+            .unwrap();
+        verifier.verify_successor(&second, &forked).unwrap();
+        assert_ne!(forked.hash(), third.hash());
+        let fourth = directory.publish(40).unwrap();
+        assert!(verifier.verify_successor(&forked, &fourth).is_err());
+
+        let mut tampered = third.clone();
+        tampered.root[0] ^= 1;
+        assert!(verifier.verify_head(&tampered).is_err());
+        let earlier = directory.publish(5).unwrap();
+        assert!(verifier.verify_successor(&fourth, &earlier).is_err());
+    }
+
+    #[test]
+    fn encoding() {
+        let mut directory = directory();
+        directory.set(b"alice", b"alice key").unwrap();
+        let head = directory.publish(1).unwrap();
+        let bytes = head.to_bytes();
+        assert_eq!(bytes.len(), HEAD_SIZE);
+        assert_eq!(SignedTreeHead::from_bytes(&bytes).unwrap(), head);
+        assert!(SignedTreeHead::from_bytes(&bytes[1..]).is_err());
+
+        for name in [&b"alice"[..], &b"bob"[..]].iter() {
+            let proof = directory.lookup(name).unwrap();
+            let bytes = proof.to_bytes();
+            let parsed = LookupProof::from_bytes(&bytes).unwrap();
+            assert_eq!(parsed, proof);
+            assert!(directory
+                .verifier()
+                .verify_lookup(&head, name, &parsed)
+                .is_ok());
+            assert!(LookupProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+            assert!(LookupProof::from_bytes(&bytes[..vrf::PROOF_SIZE]).is_err());
+        }
+        let mut bad = directory.lookup(b"alice").unwrap().to_bytes();
+        bad[vrf::PROOF_SIZE] = 2;
+        assert!(LookupProof::from_bytes(&bad).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! ECVRF-EDWARDS25519-SHA512-TAI of [RFC 9381](https://www.rfc-editor.org/rfc/rfc9381).
+//!
+//! The directory proves with the VRF of its key that an index was computed from a name,
+//! without anyone who doesn't ask for the name learning which index it has. Keys are
+//! Ed25519 keys, the private key is the 64 byte seed and public key of
+//! `Ed25519Sha512`.
+
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_TABLE,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::IsIdentity,
+};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use keys::{PrivateKey, PublicKey};
+use CryptoError;
+
+/// The size of a proof `Gamma | c | s`
+pub const PROOF_SIZE: usize = 80;
+/// The size of a VRF output
+pub const OUTPUT_SIZE: usize = 64;
+
+const SUITE: u8 = 0x03;
+const CHALLENGE_SIZE: usize = 16;
+
+/// Prove the VRF output of `alpha` with the Ed25519 private key `sk`
+pub fn prove(sk: &PrivateKey, alpha: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if sk.len() != 64 {
+        return Err(CryptoError::ParseError(
+            "Invalid private key provided".to_string(),
+        ));
+    }
+    let mut hashed = Sha512::digest(&sk[..32]);
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&hashed[..32]);
+    bytes[0] &= 248;
+    bytes[31] &= 127;
+    bytes[31] |= 64;
+    let mut x = Scalar::from_bits(bytes);
+    bytes.zeroize();
+    let y = &x * &ED25519_BASEPOINT_TABLE;
+    let y_bytes = y.compress().to_bytes();
+    if y_bytes.ct_eq(&sk[32..]).unwrap_u8() != 1 {
+        hashed.as_mut_slice().zeroize();
+        x.zeroize();
+        return Err(CryptoError::ParseError(
+            "Invalid private key provided".to_string(),
+        ));
+    }
+
+    let h = hash_to_curve(&y_bytes, alpha)?;
+    let h_bytes = h.compress().to_bytes();
+    let gamma = h * x;
+    // The nonce of RFC 8032 from the second half of the hashed key
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&Sha512::new().chain(&hashed[32..]).chain(h_bytes).result());
+    hashed.as_mut_slice().zeroize();
+    let mut k = Scalar::from_bytes_mod_order_wide(&wide);
+    wide.zeroize();
+    let c = challenge(&[
+        &y_bytes,
+        &h_bytes,
+        gamma.compress().as_bytes(),
+        (&k * &ED25519_BASEPOINT_TABLE).compress().as_bytes(),
+        (h * k).compress().as_bytes(),
+    ]);
+    let s = k + c * x;
+    k.zeroize();
+    x.zeroize();
+
+    let mut proof = Vec::with_capacity(PROOF_SIZE);
+    proof.extend_from_slice(gamma.compress().as_bytes());
+    proof.extend_from_slice(&c.as_bytes()[..CHALLENGE_SIZE]);
+    proof.extend_from_slice(s.as_bytes());
+    Ok(proof)
+}
+
+/// The VRF output of a proof, without checking it
+pub fn proof_to_hash(proof: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (gamma, _, _) = decode_proof(proof)?;
+    Ok(output(&gamma))
+}
+
+/// Check a proof of `alpha` under the public key `pk` and return the VRF output
+pub fn verify(pk: &PublicKey, alpha: &[u8], proof: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let invalid = || CryptoError::GeneralError("Invalid VRF proof".to_string());
+    if pk.len() != 32 {
+        return Err(CryptoError::ParseError(
+            "Invalid public key provided".to_string(),
+        ));
+    }
+    let y = match CompressedEdwardsY::from_slice(&pk[..]).decompress() {
+        Some(y) if !y.is_small_order() => y,
+        _ => {
+            return Err(CryptoError::ParseError(
+                "Invalid public key provided".to_string(),
+            ))
+        }
+    };
+    let (gamma, c, s) = decode_proof(proof).map_err(|_| invalid())?;
+    let h = hash_to_curve(&pk[..], alpha)?;
+    let u = &s * &ED25519_BASEPOINT_TABLE - y * c;
+    let v = h * s - gamma * c;
+    let expected = challenge(&[
+        &pk[..],
+        h.compress().as_bytes(),
+        &proof[..32],
+        u.compress().as_bytes(),
+        v.compress().as_bytes(),
+    ]);
+    if expected.as_bytes()[..CHALLENGE_SIZE]
+        .ct_eq(&proof[32..32 + CHALLENGE_SIZE])
+        .unwrap_u8()
+        != 1
+    {
+        return Err(invalid());
+    }
+    Ok(output(&gamma))
+}
+
+/// Try and increment: the first hash of `pk | alpha | ctr` that decodes to a point,
+/// multiplied by the cofactor
+fn hash_to_curve(pk: &[u8], alpha: &[u8]) -> Result<EdwardsPoint, CryptoError> {
+    for ctr in 0..=255u8 {
+        let hash = Sha512::new()
+            .chain([SUITE, 0x01])
+            .chain(pk)
+            .chain(alpha)
+            .chain([ctr, 0x00])
+            .result();
+        if let Some(p) = CompressedEdwardsY::from_slice(&hash[..32]).decompress() {
+            let p = p.mul_by_cofactor();
+            if !p.is_identity() {
+                return Ok(p);
+            }
+        }
+    }
+    Err(CryptoError::GeneralError(
+        "Input could not be hashed to the curve".to_string(),
+    ))
+}
+
+/// The first 16 bytes of the hash of the points, as a scalar
+fn challenge(points: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new().chain([SUITE, 0x02]);
+    for p in points {
+        hasher = hasher.chain(p);
+    }
+    let hash = hasher.chain([0x00]).result();
+    let mut c = [0u8; 32];
+    c[..CHALLENGE_SIZE].copy_from_slice(&hash[..CHALLENGE_SIZE]);
+    Scalar::from_bits(c)
+}
+
+fn decode_proof(proof: &[u8]) -> Result<(EdwardsPoint, Scalar, Scalar), CryptoError> {
+    let invalid = || CryptoError::ParseError("Invalid VRF proof".to_string());
+    if proof.len() != PROOF_SIZE {
+        return Err(invalid());
+    }
+    let gamma = CompressedEdwardsY::from_slice(&proof[..32])
+        .decompress()
+        .ok_or_else(invalid)?;
+    let mut c = [0u8; 32];
+    c[..CHALLENGE_SIZE].copy_from_slice(&proof[32..32 + CHALLENGE_SIZE]);
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&proof[32 + CHALLENGE_SIZE..]);
+    let s = Scalar::from_canonical_bytes(s).ok_or_else(invalid)?;
+    Ok((gamma, Scalar::from_bits(c), s))
+}
+
+fn output(gamma: &EdwardsPoint) -> Vec<u8> {
+    Sha512::new()
+        .chain([SUITE, 0x03])
+        .chain(gamma.mul_by_cofactor().compress().as_bytes())
+        .chain([0x00])
+        .result()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use signatures::ed25519::Ed25519Sha512;
+    use signatures::SignatureScheme;
+
+    fn key(seed: &str, pk: &str) -> (PublicKey, PrivateKey) {
+        let pk = hex::decode(pk).unwrap();
+        let mut sk = hex::decode(seed).unwrap();
+        sk.extend_from_slice(&pk);
+        (PublicKey(pk), PrivateKey(sk))
+    }
+
+    #[test]
+    fn rfc9381_vector() {
+        let cases = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+            ),
+        ];
+        for (seed, pk, alpha, pi, beta) in cases.iter() {
+            let (pk, sk) = key(seed, pk);
+            let alpha = hex::decode(alpha).unwrap();
+            let proof = prove(&sk, &alpha).unwrap();
+            assert_eq!(hex::encode(&proof), *pi);
+            assert_eq!(hex::encode(verify(&pk, &alpha, &proof).unwrap()), *beta);
+            assert_eq!(hex::encode(proof_to_hash(&proof).unwrap()), *beta);
+        }
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let proof = prove(&sk, b"alice@example.com").unwrap();
+        assert_eq!(proof.len(), PROOF_SIZE);
+        let output = verify(&pk, b"alice@example.com", &proof).unwrap();
+        assert_eq!(output.len(), OUTPUT_SIZE);
+        assert_eq!(prove(&sk, b"alice@example.com").unwrap(), proof);
+
+        assert!(verify(&pk, b"bob@example.com", &proof).is_err());
+        let (other, _) = Ed25519Sha512::new().keypair(None).unwrap();
+        assert!(verify(&other, b"alice@example.com", &proof).is_err());
+        for i in [0, 40, 79].iter() {
+            let mut bad = proof.clone();
+            bad[*i] ^= 1;
+            assert!(verify(&pk, b"alice@example.com", &bad).is_err());
+        }
+        assert!(verify(&pk, b"alice@example.com", &proof[1..]).is_err());
+        assert!(verify(&PublicKey(vec![0u8; 32]), b"", &proof).is_err());
+        let mut wrong = sk[..].to_vec();
+        wrong[40] ^= 1;
+        assert!(prove(&PrivateKey(wrong), b"").is_err());
+    }
+}
@@ -0,0 +1,627 @@
+//! Hierarchical deterministic key derivation with SLIP-0010 and BIP32.
+//!
+//! A wallet keeps one seed and derives every key it needs from it along a
+//! [`DerivationPath`] like `m/44'/0'/0'/0/1`, so backing up the seed backs up all the
+//! keys. [`ExtendedPrivateKey::new_master`] turns the seed into the root of the tree
+//! and each step derives a child with HMAC-SHA512 over the parent's chain code.
+//!
+//! Hardened children, written with `'` or `h`, are derived from the parent's private
+//! key, so a leaked child and chain code can't be used to recover the parent. Normal
+//! children of a secp256k1 key can also be derived from the [`ExtendedPublicKey`]
+//! alone, which lets a server hand out fresh receiving keys without holding any
+//! private key. SLIP-0010 only defines hardened derivation for Ed25519.
+//!
+//! Secp256k1 keys follow BIP32 exactly and Ed25519 keys follow SLIP-0010, so both
+//! reproduce the keys of other wallets from the same seed and path. [`keypair`]
+//! returns keys in the format of `Ed25519Sha512` or `EcdsaSecp256k1Sha256`. The
+//! Base58 `xprv` and `xpub` encodings are not supported.
+//!
+//! [`keypair`]: ExtendedPrivateKey::keypair
+
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroize;
+
+#[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+use bitcoinsecp256k1;
+use ed25519_dalek;
+
+use keys::{PrivateKey, PublicKey};
+use CryptoError;
+
+/// The flag set on the index of hardened children
+pub const HARDENED: u32 = 1 << 31;
+/// The shortest seed BIP32 accepts
+pub const MIN_SEED_SIZE: usize = 16;
+/// The longest seed BIP32 accepts
+pub const MAX_SEED_SIZE: usize = 64;
+
+const CHAIN_CODE_SIZE: usize = 32;
+
+/// The curves keys can be derived on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// Keys for `Ed25519Sha512`, hardened children only
+    Ed25519,
+    /// Keys for `EcdsaSecp256k1Sha256`
+    #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+    Secp256k1,
+}
+
+impl Curve {
+    /// The HMAC key of the master key derivation
+    fn seed_key(self) -> &'static [u8] {
+        match self {
+            Curve::Ed25519 => b"ed25519 seed",
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => b"Bitcoin seed",
+        }
+    }
+}
+
+/// The index of a child, with [`HARDENED`] set for hardened children
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChildNumber(pub u32);
+
+impl ChildNumber {
+    /// The hardened child at `index`, which must be below 2^31
+    pub fn hardened(index: u32) -> Result<Self, CryptoError> {
+        ChildNumber::normal(index).map(|c| ChildNumber(c.0 | HARDENED))
+    }
+
+    /// The normal child at `index`, which must be below 2^31
+    pub fn normal(index: u32) -> Result<Self, CryptoError> {
+        if index & HARDENED == 0 {
+            Ok(ChildNumber(index))
+        } else {
+            Err(CryptoError::ParseError(format!(
+                "Child index {} is too large",
+                index
+            )))
+        }
+    }
+
+    pub fn is_hardened(self) -> bool {
+        self.0 & HARDENED != 0
+    }
+
+    /// The index without the hardened flag
+    pub fn index(self) -> u32 {
+        self.0 & !HARDENED
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_hardened() {
+            write!(f, "{}'", self.index())
+        } else {
+            write!(f, "{}", self.index())
+        }
+    }
+}
+
+/// The children to step through from a master key, written `m/44'/0'/0'/0/1`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+    pub fn new(children: Vec<ChildNumber>) -> Self {
+        DerivationPath(children)
+    }
+
+    pub fn children(&self) -> &[ChildNumber] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = CryptoError;
+
+    fn from_str(path: &str) -> Result<Self, CryptoError> {
+        let mut parts = path.split('/');
+        if parts.next() != Some("m") {
+            return Err(CryptoError::ParseError(
+                "Derivation path must start with m".to_string(),
+            ));
+        }
+        parts
+            .map(|part| {
+                let (index, hardened) = match part.chars().last() {
+                    Some('\'') | Some('h') => (&part[..part.len() - 1], true),
+                    _ => (part, false),
+                };
+                let index = if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) {
+                    index.parse::<u32>().ok()
+                } else {
+                    None
+                }
+                .ok_or_else(|| {
+                    CryptoError::ParseError(format!("Invalid derivation path element {}", part))
+                })?;
+                if hardened {
+                    ChildNumber::hardened(index)
+                } else {
+                    ChildNumber::normal(index)
+                }
+            })
+            .collect::<Result<Vec<ChildNumber>, CryptoError>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+/// A private key with the chain code to derive its children
+#[derive(Clone)]
+pub struct ExtendedPrivateKey {
+    curve: Curve,
+    depth: u8,
+    child_number: ChildNumber,
+    chain_code: [u8; CHAIN_CODE_SIZE],
+    private_key: PrivateKey,
+}
+
+impl ExtendedPrivateKey {
+    /// Derive the master key of `curve` from a seed of 16 to 64 bytes, such as
+    /// the output of a BIP39 mnemonic
+    pub fn new_master(curve: Curve, seed: &[u8]) -> Result<Self, CryptoError> {
+        if seed.len() < MIN_SEED_SIZE || seed.len() > MAX_SEED_SIZE {
+            return Err(CryptoError::KeyGenError(format!(
+                "Seed must be between {} and {} bytes",
+                MIN_SEED_SIZE, MAX_SEED_SIZE
+            )));
+        }
+        let mut i = hmac_sha512(curve.seed_key(), &[seed])?;
+        // Secp256k1 retries the vanishingly rare seeds that aren't a valid key
+        while !curve.is_valid_private_key(&i[..32]) {
+            i = hmac_sha512(curve.seed_key(), &[&i[..]])?;
+        }
+        Ok(ExtendedPrivateKey::from_hmac(curve, 0, ChildNumber(0), i))
+    }
+
+    /// Derive the child at `child_number`
+    pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, CryptoError> {
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or_else(|| CryptoError::KeyGenError("Derivation path is too deep".to_string()))?;
+        let index = child_number.0.to_be_bytes();
+        let mut i = if child_number.is_hardened() {
+            hmac_sha512(&self.chain_code, &[&[0u8], &self.private_key[..], &index])?
+        } else {
+            let public_key = self.curve.normal_child_public_key(&self.private_key)?;
+            hmac_sha512(&self.chain_code, &[&public_key[..], &index])?
+        };
+        loop {
+            if let Some(private_key) = self.curve.add_private_key(&self.private_key, &i[..32]) {
+                let mut child = ExtendedPrivateKey::from_hmac(self.curve, depth, child_number, i);
+                child.private_key = private_key;
+                return Ok(child);
+            }
+            i = hmac_sha512(&self.chain_code, &[&[1u8], &i[32..], &index])?;
+        }
+    }
+
+    /// Derive the descendant at `path` below this key
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, CryptoError> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    /// The public key and chain code, to share with whoever derives normal
+    /// children's public keys
+    pub fn public_key(&self) -> Result<ExtendedPublicKey, CryptoError> {
+        let (public_key, _) = self.keypair()?;
+        Ok(ExtendedPublicKey {
+            curve: self.curve,
+            depth: self.depth,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key,
+        })
+    }
+
+    /// The key pair for signing with `Ed25519Sha512` or `EcdsaSecp256k1Sha256`
+    pub fn keypair(&self) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        match self.curve {
+            Curve::Ed25519 => {
+                let secret = ed25519_dalek::SecretKey::from_bytes(&self.private_key[..])
+                    .map_err(|e| CryptoError::KeyGenError(e.to_string()))?;
+                let public = ed25519_dalek::PublicKey::from(&secret);
+                let mut keypair = self.private_key[..].to_vec();
+                keypair.extend_from_slice(public.as_bytes());
+                Ok((PublicKey(public.as_bytes().to_vec()), PrivateKey(keypair)))
+            }
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => Ok((
+                secp256k1_public_key(&self.private_key)?,
+                self.private_key.clone(),
+            )),
+        }
+    }
+
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// The number of derivations from the master key
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The child number this key was derived at, 0 for the master key
+    pub fn child_number(&self) -> ChildNumber {
+        self.child_number
+    }
+
+    pub fn chain_code(&self) -> &[u8] {
+        &self.chain_code
+    }
+
+    fn from_hmac(curve: Curve, depth: u8, child_number: ChildNumber, mut i: Vec<u8>) -> Self {
+        let mut chain_code = [0u8; CHAIN_CODE_SIZE];
+        chain_code.copy_from_slice(&i[32..]);
+        let key = ExtendedPrivateKey {
+            curve,
+            depth,
+            child_number,
+            chain_code,
+            private_key: PrivateKey(i[..32].to_vec()),
+        };
+        i.zeroize();
+        key
+    }
+}
+
+impl fmt::Debug for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExtendedPrivateKey")
+            .field("curve", &self.curve)
+            .field("depth", &self.depth)
+            .field("child_number", &self.child_number)
+            .finish()
+    }
+}
+
+impl Drop for ExtendedPrivateKey {
+    fn drop(&mut self) {
+        self.chain_code.zeroize();
+    }
+}
+
+/// A public key with the chain code to derive its normal children
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    curve: Curve,
+    depth: u8,
+    child_number: ChildNumber,
+    chain_code: [u8; CHAIN_CODE_SIZE],
+    public_key: PublicKey,
+}
+
+impl ExtendedPublicKey {
+    /// Derive the public key of the normal child at `child_number`. Hardened
+    /// children and Ed25519 keys need the [`ExtendedPrivateKey`].
+    pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, CryptoError> {
+        if child_number.is_hardened() {
+            return Err(CryptoError::KeyGenError(
+                "Hardened children can't be derived from a public key".to_string(),
+            ));
+        }
+        match self.curve {
+            Curve::Ed25519 => Err(CryptoError::KeyGenError(
+                "Ed25519 children can't be derived from a public key".to_string(),
+            )),
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => {
+                let depth = self.depth.checked_add(1).ok_or_else(|| {
+                    CryptoError::KeyGenError("Derivation path is too deep".to_string())
+                })?;
+                let index = child_number.0.to_be_bytes();
+                let mut i = hmac_sha512(&self.chain_code, &[&self.public_key[..], &index])?;
+                loop {
+                    if let Some(public_key) = secp256k1_add_public_key(&self.public_key, &i[..32])?
+                    {
+                        let mut chain_code = [0u8; CHAIN_CODE_SIZE];
+                        chain_code.copy_from_slice(&i[32..]);
+                        return Ok(ExtendedPublicKey {
+                            curve: self.curve,
+                            depth,
+                            child_number,
+                            chain_code,
+                            public_key,
+                        });
+                    }
+                    i = hmac_sha512(&self.chain_code, &[&[1u8], &i[32..], &index])?;
+                }
+            }
+        }
+    }
+
+    /// Derive the public key at `path` below this key, which must have no
+    /// hardened children
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, CryptoError> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    /// The public key for verifying with `Ed25519Sha512` or `EcdsaSecp256k1Sha256`
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn child_number(&self) -> ChildNumber {
+        self.child_number
+    }
+
+    pub fn chain_code(&self) -> &[u8] {
+        &self.chain_code
+    }
+}
+
+impl Curve {
+    #[cfg_attr(
+        not(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm")),
+        allow(unused_variables)
+    )]
+    fn is_valid_private_key(self, key: &[u8]) -> bool {
+        match self {
+            Curve::Ed25519 => true,
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => bitcoinsecp256k1::SecretKey::from_slice(key).is_ok(),
+        }
+    }
+
+    /// The compressed public key hashed into a normal child
+    #[cfg_attr(
+        not(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm")),
+        allow(unused_variables)
+    )]
+    fn normal_child_public_key(self, private_key: &PrivateKey) -> Result<PublicKey, CryptoError> {
+        match self {
+            Curve::Ed25519 => Err(CryptoError::KeyGenError(
+                "Ed25519 only has hardened children".to_string(),
+            )),
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => secp256k1_public_key(private_key),
+        }
+    }
+
+    /// The child private key from the parent's and the left half of the HMAC, or
+    /// `None` if they don't make a valid key
+    #[cfg_attr(
+        not(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm")),
+        allow(unused_variables)
+    )]
+    fn add_private_key(self, parent: &PrivateKey, tweak: &[u8]) -> Option<PrivateKey> {
+        match self {
+            // Ed25519 children are the HMAC alone
+            Curve::Ed25519 => Some(PrivateKey(tweak.to_vec())),
+            #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+            Curve::Secp256k1 => {
+                let mut key = bitcoinsecp256k1::SecretKey::from_slice(&parent[..]).ok()?;
+                key.add_assign(tweak).ok()?;
+                Some(PrivateKey(key[..].to_vec()))
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+fn secp256k1_public_key(private_key: &PrivateKey) -> Result<PublicKey, CryptoError> {
+    let secret = bitcoinsecp256k1::SecretKey::from_slice(&private_key[..])?;
+    let context = bitcoinsecp256k1::Secp256k1::signing_only();
+    let public = bitcoinsecp256k1::PublicKey::from_secret_key(&context, &secret);
+    Ok(PublicKey(public.serialize().to_vec()))
+}
+
+/// The parent public key plus the left half of the HMAC times the generator, or
+/// `None` if the HMAC doesn't make a valid key
+#[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+fn secp256k1_add_public_key(
+    parent: &PublicKey,
+    tweak: &[u8],
+) -> Result<Option<PublicKey>, CryptoError> {
+    let mut key = bitcoinsecp256k1::PublicKey::from_slice(&parent[..])?;
+    let context = bitcoinsecp256k1::Secp256k1::verification_only();
+    Ok(key
+        .add_exp_assign(&context, tweak)
+        .ok()
+        .map(|_| PublicKey(key.serialize().to_vec())))
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Result<Vec<u8>, CryptoError> {
+    let mut mac = Hmac::<Sha512>::new_varkey(key)
+        .map_err(|_| CryptoError::KeyGenError("Invalid chain code".to_string()))?;
+    for d in data {
+        mac.input(d);
+    }
+    Ok(mac.result().code().to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use signatures::ed25519::Ed25519Sha512;
+    use signatures::SignatureScheme;
+
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn derive(curve: Curve, path: &str) -> ExtendedPrivateKey {
+        ExtendedPrivateKey::new_master(curve, &hex::decode(SEED).unwrap())
+            .unwrap()
+            .derive_path(&path.parse().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn paths() {
+        let path: DerivationPath = "m/44'/0h/1/2147483647'".parse().unwrap();
+        assert_eq!(
+            path.children(),
+            &[
+                ChildNumber::hardened(44).unwrap(),
+                ChildNumber(HARDENED),
+                ChildNumber::normal(1).unwrap(),
+                ChildNumber(u32::MAX),
+            ]
+        );
+        assert_eq!(path.to_string(), "m/44'/0'/1/2147483647'");
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::default()
+        );
+        for bad in &[
+            "",
+            "44'/0'",
+            "m/",
+            "m/x",
+            "m/-1",
+            "m/+1",
+            "m/1''",
+            "m/2147483648",
+        ] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{}", bad);
+        }
+        assert!(ChildNumber::hardened(HARDENED).is_err());
+    }
+
+    /// SLIP-0010 test vector 1 for ed25519
+    #[test]
+    fn ed25519_vectors() {
+        let master = derive(Curve::Ed25519, "m");
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+        let (pk, sk) = master.keypair().unwrap();
+        assert_eq!(
+            hex::encode(&sk[..32]),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(&pk[..]),
+            "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed"
+        );
+
+        let key = derive(Curve::Ed25519, "m/0'/1'/2'/2'/1000000000'");
+        assert_eq!(key.depth(), 5);
+        assert_eq!(
+            key.child_number(),
+            ChildNumber::hardened(1000000000).unwrap()
+        );
+        assert_eq!(
+            hex::encode(key.chain_code()),
+            "68789923a0cac2cd5a29172a475fe9e0fb14cd6adb5ad98a3fa70333e7afa230"
+        );
+        let (pk, sk) = key.keypair().unwrap();
+        assert_eq!(
+            hex::encode(&sk[..32]),
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+        );
+        assert_eq!(
+            hex::encode(&pk[..]),
+            "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a"
+        );
+
+        // The derived keys sign with Ed25519Sha512
+        let scheme = Ed25519Sha512::new();
+        let signature = scheme.sign(b"derived", &sk).unwrap();
+        assert!(scheme.verify(b"derived", &signature, &pk).unwrap());
+        assert_eq!(key.public_key().unwrap().public_key(), &pk);
+    }
+
+    #[test]
+    fn ed25519_is_hardened_only() {
+        let master = derive(Curve::Ed25519, "m");
+        assert!(master.derive_child(ChildNumber(0)).is_err());
+        let public = master.public_key().unwrap();
+        assert!(public.derive_child(ChildNumber(0)).is_err());
+        assert!(public.derive_child(ChildNumber(HARDENED)).is_err());
+    }
+
+    #[test]
+    fn seed_length() {
+        assert!(ExtendedPrivateKey::new_master(Curve::Ed25519, &[0u8; 15]).is_err());
+        assert!(ExtendedPrivateKey::new_master(Curve::Ed25519, &[0u8; 16]).is_ok());
+        assert!(ExtendedPrivateKey::new_master(Curve::Ed25519, &[0u8; 64]).is_ok());
+        assert!(ExtendedPrivateKey::new_master(Curve::Ed25519, &[0u8; 65]).is_err());
+    }
+
+    /// BIP32 test vector 1
+    #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+    #[test]
+    fn secp256k1_vectors() {
+        use signatures::secp256k1::EcdsaSecp256k1Sha256;
+
+        let master = derive(Curve::Secp256k1, "m");
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508"
+        );
+        let (pk, sk) = master.keypair().unwrap();
+        assert_eq!(
+            hex::encode(&sk[..]),
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"
+        );
+        assert_eq!(
+            hex::encode(&pk[..]),
+            "0339a36013301597daef41fbe593a02cc513d0b55527ec2df1050e2e8ff49c85c2"
+        );
+
+        let key = derive(Curve::Secp256k1, "m/0'/1/2'/2/1000000000");
+        assert_eq!(
+            hex::encode(key.chain_code()),
+            "c783e67b921d2beb8f6b389cc646d7263b4145701dadd2161548a8b078e65e9e"
+        );
+        let (pk, sk) = key.keypair().unwrap();
+        assert_eq!(
+            hex::encode(&sk[..]),
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"
+        );
+        assert_eq!(
+            hex::encode(&pk[..]),
+            "022a471424da5e657499d1ff51cb43c47481a03b1e77f951fe64cec9f5a48f7011"
+        );
+
+        let scheme = EcdsaSecp256k1Sha256::new();
+        let signature = scheme.sign(b"derived", &sk).unwrap();
+        assert!(scheme.verify(b"derived", &signature, &pk).unwrap());
+    }
+
+    #[cfg(any(feature = "ecdsa_secp256k1_native", feature = "ecdsa_secp256k1_asm"))]
+    #[test]
+    fn secp256k1_public_derivation() {
+        // Normal children of the public key match the private derivation
+        let account = derive(Curve::Secp256k1, "m/0'/1/2'");
+        let path: DerivationPath = "m/2/1000000000".parse().unwrap();
+        let public = account.public_key().unwrap().derive_path(&path).unwrap();
+        let private = account.derive_path(&path).unwrap();
+        assert_eq!(public, private.public_key().unwrap());
+        assert_eq!(public.depth(), 5);
+
+        let public = account.public_key().unwrap();
+        assert!(public.derive_child(ChildNumber(HARDENED)).is_err());
+    }
+}
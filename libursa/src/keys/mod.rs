@@ -0,0 +1,168 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::Drop;
+use zeroize::Zeroize;
+
+#[cfg(feature = "key_encoding")]
+pub mod encoding;
+#[cfg(feature = "hd")]
+pub mod hd;
+#[cfg(feature = "key_usage")]
+pub mod usage;
+
+// A private key instance.
+/// The underlying content is dependent on implementation.
+pub struct PrivateKey(pub Vec<u8>);
+impl_secret_bytearray!(PrivateKey);
+
+pub struct PublicKey(pub Vec<u8>);
+impl_bytearray!(PublicKey);
+
+pub struct SessionKey(pub Vec<u8>);
+impl_secret_bytearray!(SessionKey);
+
+pub struct MacKey(pub Vec<u8>);
+impl_secret_bytearray!(MacKey);
+
+/// Secret bytes such as a seed.
+/// The contents are only reachable through `expose_secret`, are never printed,
+/// are compared in constant time and are zeroed when dropped.
+#[derive(Clone)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    /// Returns the secret bytes
+    pub fn expose_secret(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+}
+
+impl<'a> From<&'a [u8]> for SecretBytes {
+    fn from(bytes: &'a [u8]) -> Self {
+        SecretBytes(bytes.to_vec())
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "SecretBytes {{ .. }}")
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &SecretBytes) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl Zeroize for SecretBytes {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SecretBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Deserialize<'a> for SecretBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        Vec::<u8>::deserialize(deserializer).map(SecretBytes)
+    }
+}
+
+/// Compare two byte strings in time that depends only on their lengths
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyGenOption {
+    UseSeed(SecretBytes),
+    FromSecretKey(PrivateKey),
+}
+
+impl Drop for KeyGenOption {
+    fn drop(&mut self) {
+        match self {
+            KeyGenOption::UseSeed(ref mut v) => v.zeroize(),
+            KeyGenOption::FromSecretKey(ref mut s) => s.zeroize(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_tests() {
+    let t = vec![1u8, 1u8, 2u8, 2u8, 3u8, 3u8, 4u8, 4u8];
+    let e = KeyGenOption::UseSeed(SecretBytes::from(&t[..]));
+    let s = serde_json::to_string(&e).unwrap();
+    assert_eq!(r#"{"UseSeed":[1,1,2,2,3,3,4,4]}"#, s);
+    let f: KeyGenOption = serde_json::from_str(&s).unwrap();
+    assert_eq!(KeyGenOption::UseSeed(SecretBytes::new(t)), f);
+    let sk = PrivateKey(vec![1u8, 1u8, 1u8, 1u8, 1u8, 1u8, 2u8]);
+    let e = KeyGenOption::FromSecretKey(sk);
+    assert_eq!(
+        r#"{"FromSecretKey":"01010101010102"}"#,
+        serde_json::to_string(&e).unwrap()
+    );
+}
+
+#[test]
+fn secret_bytes_tests() {
+    let seed = SecretBytes::new(vec![7u8; 4]);
+    assert_eq!(format!("{:?}", seed), "SecretBytes { .. }");
+    assert_eq!(seed.expose_secret(), &[7u8; 4]);
+    assert_eq!(seed, SecretBytes::from(&[7u8; 4][..]));
+    assert_ne!(seed, SecretBytes::new(vec![7u8, 7u8, 7u8, 8u8]));
+    assert_ne!(seed, SecretBytes::new(vec![7u8; 5]));
+
+    let sk = PrivateKey(vec![1u8, 2u8, 3u8]);
+    assert_eq!(format!("{:?}", sk), "PrivateKey { .. }");
+    assert_eq!(sk.expose_secret(), &[1u8, 2u8, 3u8]);
+    assert_eq!(sk, PrivateKey(vec![1u8, 2u8, 3u8]));
+}
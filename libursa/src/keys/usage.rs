@@ -0,0 +1,479 @@
+//! Usage accounting and quotas for key handles.
+//!
+//! A `UsageMeter` counts what a key has been used for: the signatures it produced and
+//! the bytes it decrypted, along with the operations it refused and the ones that
+//! failed. Before each operation it asks its `UsagePolicy` callbacks, which see the
+//! operation and the counts so far and can deny it, so embedders can enforce per-key
+//! quotas, rate limits or their own anomaly detection, e.g. alerting when a key that
+//! signs a few times a day signs a hundred times in a minute.
+//!
+//! `MeteredKeyProvider` puts a meter in front of any `KeyProvider`, and
+//! `MeteredDecryptor` in front of a `SymmetricEncryptor`. `Quota` and `RateLimit` are
+//! the common policies, and any `Fn(&str, &KeyOperation, &KeyUsage) -> Result<(), CryptoError>`
+//! is one too.
+//!
+//! The counts are checked and updated under one lock, so concurrent callers can't
+//! together go over a quota. They are kept in memory for the life of the meter, a
+//! quota that has to survive restarts has to be persisted by the embedder.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[cfg(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+))]
+use encryption::symm::{Encryptor, SymmetricEncryptor};
+use keys::PublicKey;
+use signatures::provider::KeyProvider;
+use CryptoError;
+
+/// An operation a key is asked to perform
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyOperation {
+    /// Sign a message of `bytes` bytes
+    Sign { bytes: usize },
+    /// Decrypt a ciphertext of `bytes` bytes
+    Decrypt { bytes: usize },
+}
+
+/// What a key has been used for
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyUsage {
+    /// The signatures the key was allowed to produce
+    pub signatures: u64,
+    /// The bytes of the messages it was allowed to sign
+    pub bytes_signed: u64,
+    /// The decryptions it was allowed to perform
+    pub decryptions: u64,
+    /// The bytes of the ciphertexts it was allowed to decrypt
+    pub bytes_decrypted: u64,
+    /// The operations a policy denied
+    pub denied: u64,
+    /// The allowed operations that then failed, e.g. ciphertexts that didn't decrypt
+    pub failures: u64,
+}
+
+/// A callback deciding whether a key may perform an operation.
+///
+/// `usage` is what the key `key_id` was used for before `operation`. Returning an error
+/// denies the operation and the error is returned to the caller.
+pub trait UsagePolicy: Send + Sync {
+    fn check(
+        &self,
+        key_id: &str,
+        operation: &KeyOperation,
+        usage: &KeyUsage,
+    ) -> Result<(), CryptoError>;
+}
+
+impl<F> UsagePolicy for F
+where
+    F: Fn(&str, &KeyOperation, &KeyUsage) -> Result<(), CryptoError> + Send + Sync,
+{
+    fn check(
+        &self,
+        key_id: &str,
+        operation: &KeyOperation,
+        usage: &KeyUsage,
+    ) -> Result<(), CryptoError> {
+        self(key_id, operation, usage)
+    }
+}
+
+/// Limits on the total use of a key. Limits that aren't set don't apply
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    signatures: Option<u64>,
+    bytes_decrypted: Option<u64>,
+}
+
+impl Quota {
+    /// A quota without limits
+    pub fn new() -> Self {
+        Quota::default()
+    }
+
+    /// Allow at most `max` signatures
+    pub fn signatures(mut self, max: u64) -> Self {
+        self.signatures = Some(max);
+        self
+    }
+
+    /// Allow decrypting at most `max` bytes of ciphertext
+    pub fn bytes_decrypted(mut self, max: u64) -> Self {
+        self.bytes_decrypted = Some(max);
+        self
+    }
+}
+
+impl UsagePolicy for Quota {
+    fn check(
+        &self,
+        key_id: &str,
+        operation: &KeyOperation,
+        usage: &KeyUsage,
+    ) -> Result<(), CryptoError> {
+        let exceeded = match *operation {
+            KeyOperation::Sign { .. } => self.signatures.is_some_and(|max| usage.signatures >= max),
+            KeyOperation::Decrypt { bytes } => self
+                .bytes_decrypted
+                .is_some_and(|max| usage.bytes_decrypted + bytes as u64 > max),
+        };
+        if exceeded {
+            return Err(CryptoError::GeneralError(format!(
+                "Key {} has exceeded its quota",
+                key_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Allow at most `max` operations of a key in any period of `window`.
+///
+/// Every operation the rate limit allows is counted, even if a later policy denies it.
+pub struct RateLimit {
+    max: usize,
+    window: Duration,
+    recent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimit {
+    pub fn new(max: usize, window: Duration) -> Self {
+        RateLimit {
+            max,
+            window,
+            recent: Mutex::new(VecDeque::with_capacity(max)),
+        }
+    }
+}
+
+impl UsagePolicy for RateLimit {
+    fn check(&self, key_id: &str, _: &KeyOperation, _: &KeyUsage) -> Result<(), CryptoError> {
+        let now = Instant::now();
+        let mut recent = lock(&self.recent);
+        while matches!(recent.front(), Some(t) if now.duration_since(*t) >= self.window) {
+            recent.pop_front();
+        }
+        if recent.len() >= self.max {
+            return Err(CryptoError::GeneralError(format!(
+                "Key {} is used too often",
+                key_id
+            )));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+}
+
+/// Counts the use of one key and applies its policies
+pub struct UsageMeter {
+    key_id: String,
+    usage: Mutex<KeyUsage>,
+    policies: Vec<Box<dyn UsagePolicy>>,
+}
+
+impl UsageMeter {
+    /// A meter without policies for the key named `key_id`, which policies are told
+    pub fn new(key_id: &str) -> Self {
+        UsageMeter {
+            key_id: key_id.to_string(),
+            usage: Mutex::new(KeyUsage::default()),
+            policies: Vec::new(),
+        }
+    }
+
+    /// Also ask `policy` before each operation. Policies are asked in the order they
+    /// were added and the first denial stops the operation
+    pub fn with_policy<P: UsagePolicy + 'static>(mut self, policy: P) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// What the key has been used for so far
+    pub fn usage(&self) -> KeyUsage {
+        *lock(&self.usage)
+    }
+
+    /// Start counting again, e.g. at the start of a quota period. Returns the old counts
+    pub fn reset(&self) -> KeyUsage {
+        ::std::mem::take(&mut *lock(&self.usage))
+    }
+
+    /// Ask the policies whether the key may perform `operation` and count it if so
+    pub fn authorize(&self, operation: KeyOperation) -> Result<(), CryptoError> {
+        let mut usage = lock(&self.usage);
+        for policy in &self.policies {
+            if let Err(e) = policy.check(&self.key_id, &operation, &usage) {
+                usage.denied += 1;
+                return Err(e);
+            }
+        }
+        match operation {
+            KeyOperation::Sign { bytes } => {
+                usage.signatures += 1;
+                usage.bytes_signed += bytes as u64;
+            }
+            KeyOperation::Decrypt { bytes } => {
+                usage.decryptions += 1;
+                usage.bytes_decrypted += bytes as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count an allowed operation that then failed
+    pub fn record_failure(&self) {
+        lock(&self.usage).failures += 1;
+    }
+
+    /// Perform `f` as `operation` if the policies allow it
+    fn run<T, E>(
+        &self,
+        operation: KeyOperation,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Result<T, E>, CryptoError> {
+        self.authorize(operation)?;
+        let res = f();
+        if res.is_err() {
+            self.record_failure();
+        }
+        Ok(res)
+    }
+}
+
+impl ::std::fmt::Debug for UsageMeter {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("UsageMeter")
+            .field("key_id", &self.key_id)
+            .field("usage", &self.usage())
+            .field("policies", &self.policies.len())
+            .finish()
+    }
+}
+
+/// A `KeyProvider` whose signatures are counted and subject to the meter's policies
+pub struct MeteredKeyProvider<P: KeyProvider> {
+    provider: P,
+    meter: UsageMeter,
+}
+
+impl<P: KeyProvider> MeteredKeyProvider<P> {
+    pub fn new(provider: P, meter: UsageMeter) -> Self {
+        MeteredKeyProvider { provider, meter }
+    }
+
+    pub fn meter(&self) -> &UsageMeter {
+        &self.meter
+    }
+
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+}
+
+impl<P: KeyProvider> KeyProvider for MeteredKeyProvider<P> {
+    fn public_key(&self) -> Result<PublicKey, CryptoError> {
+        self.provider.public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.meter.run(
+            KeyOperation::Sign {
+                bytes: message.len(),
+            },
+            || self.provider.sign(message),
+        )?
+    }
+}
+
+/// A `SymmetricEncryptor` whose decryptions are counted and subject to the meter's
+/// policies. Encryption isn't metered
+#[cfg(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+))]
+pub struct MeteredDecryptor<E: Encryptor> {
+    encryptor: SymmetricEncryptor<E>,
+    meter: UsageMeter,
+}
+
+#[cfg(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+))]
+impl<E: Encryptor> MeteredDecryptor<E> {
+    pub fn new(encryptor: SymmetricEncryptor<E>, meter: UsageMeter) -> Self {
+        MeteredDecryptor { encryptor, meter }
+    }
+
+    pub fn meter(&self) -> &UsageMeter {
+        &self.meter
+    }
+
+    /// The encryptor, for the operations that aren't metered
+    pub fn encryptor(&self) -> &SymmetricEncryptor<E> {
+        &self.encryptor
+    }
+
+    /// `SymmetricEncryptor::decrypt_easy` if the policies allow decrypting `ciphertext`
+    pub fn decrypt_easy(&self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.meter
+            .run(
+                KeyOperation::Decrypt {
+                    bytes: ciphertext.len(),
+                },
+                || self.encryptor.decrypt_easy(aad, ciphertext),
+            )?
+            .map_err(|_| CryptoError::GeneralError("Ciphertext could not be decrypted".to_string()))
+    }
+
+    /// `SymmetricEncryptor::decrypt` if the policies allow decrypting `ciphertext`
+    pub fn decrypt(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.meter
+            .run(
+                KeyOperation::Decrypt {
+                    bytes: ciphertext.len(),
+                },
+                || self.encryptor.decrypt(nonce, aad, ciphertext),
+            )?
+            .map_err(|_| CryptoError::GeneralError("Ciphertext could not be decrypted".to_string()))
+    }
+}
+
+/// Counts stay meaningful if a policy panicked while holding the lock, so the
+/// poisoning is ignored
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::*;
+    use signatures::ed25519::Ed25519Sha512;
+    use signatures::provider::SoftwareKeyProvider;
+    use signatures::SignatureScheme;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn signing_quota() {
+        let meter = UsageMeter::new("release key").with_policy(Quota::new().signatures(2));
+        let provider = MeteredKeyProvider::new(
+            SoftwareKeyProvider::<Ed25519Sha512>::generate().unwrap(),
+            meter,
+        );
+        let public_key = provider.public_key().unwrap();
+        for message in &[&b"v1.0"[..], b"v1.1"] {
+            let signature = provider.sign(message).unwrap();
+            assert!(Ed25519Sha512::new()
+                .verify(message, &signature, &public_key)
+                .unwrap());
+        }
+        assert!(provider.sign(b"v1.2").is_err());
+        assert_eq!(
+            provider.meter().usage(),
+            KeyUsage {
+                signatures: 2,
+                bytes_signed: 8,
+                denied: 1,
+                ..KeyUsage::default()
+            }
+        );
+
+        // A new period gives a new quota
+        assert_eq!(provider.meter().reset().signatures, 2);
+        provider.sign(b"v1.2").unwrap();
+    }
+
+    #[test]
+    fn callbacks() {
+        let alerts = Arc::new(AtomicUsize::new(0));
+        let seen = alerts.clone();
+        let meter = UsageMeter::new("payments")
+            .with_policy(move |key_id: &str, op: &KeyOperation, usage: &KeyUsage| {
+                assert_eq!(key_id, "payments");
+                match op {
+                    KeyOperation::Sign { bytes } if *bytes > 16 => {
+                        seen.fetch_add(1, Ordering::SeqCst);
+                        Err(CryptoError::GeneralError("Message too long".to_string()))
+                    }
+                    _ if usage.denied >= 2 => {
+                        Err(CryptoError::GeneralError("Key is locked".to_string()))
+                    }
+                    _ => Ok(()),
+                }
+            })
+            .with_policy(RateLimit::new(3, Duration::from_secs(3600)));
+        assert!(meter.authorize(KeyOperation::Sign { bytes: 8 }).is_ok());
+        assert!(meter.authorize(KeyOperation::Sign { bytes: 32 }).is_err());
+        assert_eq!(alerts.load(Ordering::SeqCst), 1);
+        assert!(meter.authorize(KeyOperation::Decrypt { bytes: 8 }).is_ok());
+        assert!(meter.authorize(KeyOperation::Decrypt { bytes: 8 }).is_ok());
+        // The rate limit is reached
+        assert!(meter.authorize(KeyOperation::Sign { bytes: 8 }).is_err());
+        // and two denials lock the key
+        assert!(meter.authorize(KeyOperation::Sign { bytes: 8 }).is_err());
+        let usage = meter.usage();
+        assert_eq!(
+            (usage.signatures, usage.decryptions, usage.denied),
+            (1, 2, 3)
+        );
+
+        let limit = RateLimit::new(1, Duration::from_millis(0));
+        assert!(limit
+            .check("k", &KeyOperation::Sign { bytes: 0 }, &usage)
+            .is_ok());
+        assert!(limit
+            .check("k", &KeyOperation::Sign { bytes: 0 }, &usage)
+            .is_ok());
+    }
+
+    #[cfg(feature = "chacha20poly1305")]
+    #[test]
+    fn decryption_quota() {
+        use encryption::symm::prelude::*;
+
+        let encryptor = SymmetricEncryptor::<ChaCha20Poly1305>::default();
+        let ciphertext = encryptor
+            .encrypt_easy(&b"aad"[..], &[7u8; 100][..])
+            .unwrap();
+        let meter = UsageMeter::new("records")
+            .with_policy(Quota::new().bytes_decrypted(2 * ciphertext.len() as u64));
+        let decryptor = MeteredDecryptor::new(encryptor, meter);
+
+        assert_eq!(
+            decryptor.decrypt_easy(b"aad", &ciphertext).unwrap(),
+            vec![7u8; 100]
+        );
+        assert!(decryptor.decrypt_easy(b"other", &ciphertext).is_err());
+        assert!(decryptor.decrypt_easy(b"aad", &ciphertext).is_err());
+        let usage = decryptor.meter().usage();
+        assert_eq!(usage.decryptions, 2);
+        assert_eq!(usage.bytes_decrypted, 2 * ciphertext.len() as u64);
+        assert_eq!((usage.denied, usage.failures), (1, 1));
+    }
+}
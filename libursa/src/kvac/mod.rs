@@ -0,0 +1,809 @@
+//! Keyed-verification anonymous credentials over Ristretto (Chase, Meiklejohn and
+//! Zaverucha, CCS 2014).
+//!
+//! When the party that verifies credentials is the one that issued them, credentials
+//! don't need to be publicly verifiable and can use an algebraic MAC instead of a
+//! signature. Issuance and presentation then take a few group operations in a prime
+//! order group and no pairings, which is much faster than CL or BBS+ credentials.
+//!
+//! The issuer publishes `IssuerParameters`, a commitment to its secret key. Every
+//! credential comes with a proof that it was made with that key, so the issuer
+//! can't tag a credential to recognize the holder later. Presentations reveal some
+//! attributes and hide the others, and two presentations of one credential can't be
+//! linked to each other or to the issuance.
+//!
+//! Only the issuer can verify presentations. Attributes are scalars, use
+//! `attribute_from_bytes` to hash other values into one.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, MultiscalarMul, VartimeMultiscalarMul},
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use CryptoError;
+
+pub const POINT_SIZE: usize = 32;
+pub const SCALAR_SIZE: usize = 32;
+/// No single credential can hold more attributes than this
+pub const MAX_ATTRIBUTES: usize = 255;
+
+const GENERATOR_H_DOMAIN: &[u8] = b"URSA_KVAC_RISTRETTO_H";
+const ATTRIBUTE_DOMAIN: &[u8] = b"URSA_KVAC_RISTRETTO_ATTRIBUTE";
+const ISSUANCE_DOMAIN: &[u8] = b"URSA_KVAC_RISTRETTO_ISSUANCE";
+const PRESENTATION_DOMAIN: &[u8] = b"URSA_KVAC_RISTRETTO_PRESENTATION";
+
+/// Hash a value to an attribute
+pub fn attribute_from_bytes(value: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.input(ATTRIBUTE_DOMAIN);
+    hasher.input(value);
+    hash_to_scalar(hasher)
+}
+
+/// The issuer's secret key, which is also what verifies presentations
+pub struct IssuerSecretKey {
+    x0: Scalar,
+    x0_blinding: Scalar,
+    x: Vec<Scalar>,
+}
+
+/// The commitment to the issuer's key that holders check credentials against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuerParameters {
+    /// x0 G + x0_blinding H
+    cx0: RistrettoPoint,
+    /// x_i H
+    x: Vec<RistrettoPoint>,
+}
+
+/// The MAC on a credential's attributes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mac {
+    u: RistrettoPoint,
+    /// (x0 + sum x_i m_i) u
+    u_prime: RistrettoPoint,
+}
+
+/// What the issuer sends the holder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuedCredential {
+    mac: Mac,
+    proof: IssuanceProof,
+}
+
+/// Proof that a MAC was made with the key behind `IssuerParameters`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuanceProof {
+    challenge: Scalar,
+    /// For x0, x0_blinding and every x_i
+    responses: Vec<Scalar>,
+}
+
+/// A credential held by its holder
+#[derive(Clone, Debug)]
+pub struct Credential {
+    mac: Mac,
+    attributes: Vec<Scalar>,
+}
+
+/// A presentation of a credential that reveals some of its attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Presentation {
+    u: RistrettoPoint,
+    /// u' + r G
+    c_u_prime: RistrettoPoint,
+    revealed: BTreeMap<usize, Scalar>,
+    /// m_j u + z_j H for every hidden attribute j
+    hidden: BTreeMap<usize, RistrettoPoint>,
+    challenge: Scalar,
+    /// For m_j and z_j of every hidden attribute, then r
+    responses: Vec<Scalar>,
+}
+
+impl IssuerSecretKey {
+    /// A key for credentials with `attribute_count` attributes
+    pub fn generate(attribute_count: usize) -> Result<Self, CryptoError> {
+        if attribute_count == 0 || attribute_count > MAX_ATTRIBUTES {
+            return Err(CryptoError::KeyGenError(format!(
+                "Credentials need between 1 and {} attributes",
+                MAX_ATTRIBUTES
+            )));
+        }
+        let mut rng = OsRng;
+        Ok(IssuerSecretKey {
+            x0: Scalar::random(&mut rng),
+            x0_blinding: Scalar::random(&mut rng),
+            x: (0..attribute_count)
+                .map(|_| Scalar::random(&mut rng))
+                .collect(),
+        })
+    }
+
+    pub fn attribute_count(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn parameters(&self) -> IssuerParameters {
+        let h = generator_h();
+        IssuerParameters {
+            cx0: RistrettoPoint::multiscalar_mul(
+                &[self.x0, self.x0_blinding],
+                &[RISTRETTO_BASEPOINT_POINT, h],
+            ),
+            x: self.x.iter().map(|x| x * h).collect(),
+        }
+    }
+
+    /// Issue a credential on `attributes`
+    pub fn issue(&self, attributes: &[Scalar]) -> Result<IssuedCredential, CryptoError> {
+        self.check_attributes(attributes)?;
+        let mut rng = OsRng;
+        let u = RistrettoPoint::random(&mut rng);
+        let mut exponent = self.exponent(attributes);
+        let mac = Mac {
+            u,
+            u_prime: exponent * u,
+        };
+        exponent.zeroize();
+
+        let params = self.parameters();
+        let h = generator_h();
+        // Commitments for Cx0, every X_i and u'
+        let mut blindings: Vec<Scalar> = (0..self.x.len() + 2)
+            .map(|_| Scalar::random(&mut rng))
+            .collect();
+        let mut commitments = Vec::with_capacity(self.x.len() + 2);
+        commitments.push(RistrettoPoint::multiscalar_mul(
+            &blindings[..2],
+            &[RISTRETTO_BASEPOINT_POINT, h],
+        ));
+        commitments.extend(blindings[2..].iter().map(|k| k * h));
+        commitments.push(RistrettoPoint::multiscalar_mul(
+            blindings.iter().take(1).chain(blindings[2..].iter()),
+            attribute_bases(&u, attributes).iter(),
+        ));
+
+        let challenge = issuance_challenge(&params, &mac, attributes, &commitments);
+        let mut secrets = vec![self.x0, self.x0_blinding];
+        secrets.extend_from_slice(&self.x);
+        let responses = blindings
+            .iter()
+            .zip(secrets.iter())
+            .map(|(k, x)| k - challenge * x)
+            .collect();
+        blindings.zeroize();
+        secrets.zeroize();
+        Ok(IssuedCredential {
+            mac,
+            proof: IssuanceProof {
+                challenge,
+                responses,
+            },
+        })
+    }
+
+    /// Check a presentation made with `context`, the same bytes the holder used.
+    /// The revealed attributes are in `Presentation::revealed`.
+    pub fn verify_presentation(
+        &self,
+        presentation: &Presentation,
+        context: &[u8],
+    ) -> Result<(), CryptoError> {
+        let params = self.parameters();
+        presentation.check_shape(self.x.len())?;
+        let u = presentation.u;
+        if u.is_identity() {
+            return Err(invalid_presentation());
+        }
+        // V = x0 u + sum x_i m_i u + sum x_j C_j - C_u'
+        let mut scalars = vec![
+            self.x0
+                + presentation
+                    .revealed
+                    .iter()
+                    .fold(Scalar::zero(), |acc, (i, m)| acc + self.x[*i] * m),
+        ];
+        let mut points = vec![u];
+        for (j, c) in &presentation.hidden {
+            scalars.push(self.x[*j]);
+            points.push(*c);
+        }
+        let v = RistrettoPoint::multiscalar_mul(&scalars, &points) - presentation.c_u_prime;
+        scalars.zeroize();
+
+        let commitments = presentation.commitments(&params, &v);
+        if presentation_challenge(&params, presentation, &commitments, context)
+            == presentation.challenge
+        {
+            Ok(())
+        } else {
+            Err(invalid_presentation())
+        }
+    }
+
+    /// x0 + sum x_i m_i
+    fn exponent(&self, attributes: &[Scalar]) -> Scalar {
+        self.x
+            .iter()
+            .zip(attributes.iter())
+            .fold(self.x0, |acc, (x, m)| acc + x * m)
+    }
+
+    fn check_attributes(&self, attributes: &[Scalar]) -> Result<(), CryptoError> {
+        if attributes.len() != self.x.len() {
+            return Err(CryptoError::GeneralError(format!(
+                "Expected {} attributes, found {}",
+                self.x.len(),
+                attributes.len()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SCALAR_SIZE * (self.x.len() + 2));
+        bytes.extend_from_slice(self.x0.as_bytes());
+        bytes.extend_from_slice(self.x0_blinding.as_bytes());
+        for x in &self.x {
+            bytes.extend_from_slice(x.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let scalars = read_scalars(bytes)?;
+        if scalars.len() < 3 || scalars.len() > MAX_ATTRIBUTES + 2 {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer secret key length".to_string(),
+            ));
+        }
+        Ok(IssuerSecretKey {
+            x0: scalars[0],
+            x0_blinding: scalars[1],
+            x: scalars[2..].to_vec(),
+        })
+    }
+}
+
+impl ::std::fmt::Debug for IssuerSecretKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("IssuerSecretKey(..)")
+    }
+}
+
+impl Drop for IssuerSecretKey {
+    fn drop(&mut self) {
+        self.x0.zeroize();
+        self.x0_blinding.zeroize();
+        self.x.zeroize();
+    }
+}
+
+impl IssuerParameters {
+    pub fn attribute_count(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(POINT_SIZE * (self.x.len() + 1));
+        bytes.extend_from_slice(self.cx0.compress().as_bytes());
+        for x in &self.x {
+            bytes.extend_from_slice(x.compress().as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let points = read_points(bytes)?;
+        if points.len() < 2 || points.len() > MAX_ATTRIBUTES + 1 {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer parameters length".to_string(),
+            ));
+        }
+        Ok(IssuerParameters {
+            cx0: points[0],
+            x: points[1..].to_vec(),
+        })
+    }
+}
+
+impl IssuedCredential {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(2 * POINT_SIZE + SCALAR_SIZE * (self.proof.responses.len() + 1));
+        bytes.extend_from_slice(self.mac.u.compress().as_bytes());
+        bytes.extend_from_slice(self.mac.u_prime.compress().as_bytes());
+        bytes.extend_from_slice(self.proof.challenge.as_bytes());
+        for s in &self.proof.responses {
+            bytes.extend_from_slice(s.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() < 2 * POINT_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid issued credential length".to_string(),
+            ));
+        }
+        let points = read_points(&bytes[..2 * POINT_SIZE])?;
+        let scalars = read_scalars(&bytes[2 * POINT_SIZE..])?;
+        if scalars.len() < 4 {
+            return Err(CryptoError::ParseError(
+                "Invalid issued credential length".to_string(),
+            ));
+        }
+        Ok(IssuedCredential {
+            mac: Mac {
+                u: points[0],
+                u_prime: points[1],
+            },
+            proof: IssuanceProof {
+                challenge: scalars[0],
+                responses: scalars[1..].to_vec(),
+            },
+        })
+    }
+}
+
+impl Credential {
+    /// Check an issued credential on `attributes` against the issuer's parameters
+    pub fn new(
+        params: &IssuerParameters,
+        attributes: &[Scalar],
+        issued: &IssuedCredential,
+    ) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::GeneralError("Invalid issued credential".to_string());
+        let mac = issued.mac;
+        let proof = &issued.proof;
+        if attributes.len() != params.x.len() || proof.responses.len() != params.x.len() + 2 {
+            return Err(invalid());
+        }
+        if mac.u.is_identity() {
+            return Err(invalid());
+        }
+        let h = generator_h();
+        let c = proof.challenge;
+        let s = &proof.responses;
+        let mut commitments = Vec::with_capacity(params.x.len() + 2);
+        commitments.push(RistrettoPoint::vartime_multiscalar_mul(
+            &[s[0], s[1], c],
+            &[RISTRETTO_BASEPOINT_POINT, h, params.cx0],
+        ));
+        commitments.extend(
+            s[2..]
+                .iter()
+                .zip(params.x.iter())
+                .map(|(s, x)| RistrettoPoint::vartime_multiscalar_mul(&[*s, c], &[h, *x])),
+        );
+        let mut bases = attribute_bases(&mac.u, attributes);
+        bases.push(mac.u_prime);
+        commitments.push(RistrettoPoint::vartime_multiscalar_mul(
+            s.iter()
+                .take(1)
+                .chain(s[2..].iter())
+                .chain(::std::iter::once(&c)),
+            bases.iter(),
+        ));
+        if issuance_challenge(params, &mac, attributes, &commitments) != c {
+            return Err(invalid());
+        }
+        Ok(Credential {
+            mac,
+            attributes: attributes.to_vec(),
+        })
+    }
+
+    pub fn attributes(&self) -> &[Scalar] {
+        &self.attributes
+    }
+
+    /// Present the credential revealing the attributes at `revealed`. `context`
+    /// binds the presentation to a session and should contain a fresh nonce from
+    /// the verifier.
+    pub fn present(
+        &self,
+        params: &IssuerParameters,
+        revealed: &BTreeSet<usize>,
+        context: &[u8],
+    ) -> Result<Presentation, CryptoError> {
+        if params.x.len() != self.attributes.len() {
+            return Err(CryptoError::GeneralError(
+                "The parameters don't match the credential".to_string(),
+            ));
+        }
+        if revealed.iter().any(|i| *i >= self.attributes.len()) {
+            return Err(CryptoError::GeneralError(
+                "Revealed attribute index is out of range".to_string(),
+            ));
+        }
+        let mut rng = OsRng;
+        let h = generator_h();
+        // Randomize the MAC so presentations can't be linked
+        let t = Scalar::random(&mut rng);
+        let u = t * self.mac.u;
+        let u_prime = t * self.mac.u_prime;
+        let mut r = Scalar::random(&mut rng);
+        let c_u_prime = u_prime + r * RISTRETTO_BASEPOINT_POINT;
+
+        let mut revealed_attributes = BTreeMap::new();
+        let mut hidden = BTreeMap::new();
+        // m_j and z_j of every hidden attribute, then r
+        let mut secrets = Vec::new();
+        let mut z_x = Vec::new();
+        for (i, m) in self.attributes.iter().enumerate() {
+            if revealed.contains(&i) {
+                revealed_attributes.insert(i, *m);
+            } else {
+                let z = Scalar::random(&mut rng);
+                hidden.insert(i, RistrettoPoint::multiscalar_mul(&[*m, z], &[u, h]));
+                secrets.push(*m);
+                secrets.push(z);
+                z_x.push(params.x[i]);
+            }
+        }
+        r = -r;
+        secrets.push(r);
+        let mut blindings: Vec<Scalar> = (0..secrets.len())
+            .map(|_| Scalar::random(&mut rng))
+            .collect();
+
+        let mut presentation = Presentation {
+            u,
+            c_u_prime,
+            revealed: revealed_attributes,
+            hidden,
+            challenge: Scalar::zero(),
+            responses: Vec::new(),
+        };
+        // Commitments for every C_j, then V = sum z_j X_j - r G
+        let mut commitments: Vec<RistrettoPoint> = blindings
+            .chunks(2)
+            .take(z_x.len())
+            .map(|k| RistrettoPoint::multiscalar_mul(k, &[u, h]))
+            .collect();
+        z_x.push(RISTRETTO_BASEPOINT_POINT);
+        let (pairs, k_r) = blindings.split_at(blindings.len() - 1);
+        commitments.push(RistrettoPoint::multiscalar_mul(
+            pairs.iter().skip(1).step_by(2).chain(k_r.iter()),
+            z_x.iter(),
+        ));
+        let challenge = presentation_challenge(params, &presentation, &commitments, context);
+        presentation.responses = blindings
+            .iter()
+            .zip(secrets.iter())
+            .map(|(k, x)| k - challenge * x)
+            .collect();
+        presentation.challenge = challenge;
+        blindings.zeroize();
+        secrets.zeroize();
+        r.zeroize();
+        Ok(presentation)
+    }
+}
+
+impl Drop for Credential {
+    fn drop(&mut self) {
+        self.attributes.zeroize();
+    }
+}
+
+impl Presentation {
+    pub fn revealed(&self) -> &BTreeMap<usize, Scalar> {
+        &self.revealed
+    }
+
+    fn check_shape(&self, attribute_count: usize) -> Result<(), CryptoError> {
+        let indices: BTreeSet<usize> = self
+            .revealed
+            .keys()
+            .chain(self.hidden.keys())
+            .copied()
+            .collect();
+        if indices.len() != attribute_count
+            || indices.len() != self.revealed.len() + self.hidden.len()
+            || indices.iter().any(|i| *i >= attribute_count)
+            || self.responses.len() != 2 * self.hidden.len() + 1
+        {
+            return Err(invalid_presentation());
+        }
+        Ok(())
+    }
+
+    /// The prover's commitments, recomputed from the responses
+    fn commitments(&self, params: &IssuerParameters, v: &RistrettoPoint) -> Vec<RistrettoPoint> {
+        let h = generator_h();
+        let c = self.challenge;
+        let mut commitments: Vec<RistrettoPoint> = self
+            .hidden
+            .values()
+            .zip(self.responses.chunks(2))
+            .map(|(cj, s)| {
+                RistrettoPoint::vartime_multiscalar_mul(&[s[0], s[1], c], &[self.u, h, *cj])
+            })
+            .collect();
+        let mut points: Vec<RistrettoPoint> = self.hidden.keys().map(|j| params.x[*j]).collect();
+        points.push(RISTRETTO_BASEPOINT_POINT);
+        points.push(*v);
+        let (pairs, s_r) = self.responses.split_at(self.responses.len() - 1);
+        commitments.push(RistrettoPoint::vartime_multiscalar_mul(
+            pairs
+                .iter()
+                .skip(1)
+                .step_by(2)
+                .chain(s_r.iter())
+                .chain(::std::iter::once(&c)),
+            points.iter(),
+        ));
+        commitments
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.u.compress().as_bytes());
+        bytes.extend_from_slice(self.c_u_prime.compress().as_bytes());
+        bytes.extend_from_slice(self.challenge.as_bytes());
+        bytes.push(self.revealed.len() as u8);
+        for (i, m) in &self.revealed {
+            bytes.push(*i as u8);
+            bytes.extend_from_slice(m.as_bytes());
+        }
+        bytes.push(self.hidden.len() as u8);
+        for (j, c) in &self.hidden {
+            bytes.push(*j as u8);
+            bytes.extend_from_slice(c.compress().as_bytes());
+        }
+        for s in &self.responses {
+            bytes.extend_from_slice(s.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::ParseError("Invalid presentation length".to_string());
+        let mut reader = Reader(bytes);
+        let u = reader.point().ok_or_else(invalid)??;
+        let c_u_prime = reader.point().ok_or_else(invalid)??;
+        let challenge = reader.scalar().ok_or_else(invalid)??;
+        let mut revealed = BTreeMap::new();
+        for _ in 0..reader.byte().ok_or_else(invalid)? {
+            let i = reader.byte().ok_or_else(invalid)? as usize;
+            revealed.insert(i, reader.scalar().ok_or_else(invalid)??);
+        }
+        let mut hidden = BTreeMap::new();
+        let hidden_count = reader.byte().ok_or_else(invalid)?;
+        for _ in 0..hidden_count {
+            let j = reader.byte().ok_or_else(invalid)? as usize;
+            hidden.insert(j, reader.point().ok_or_else(invalid)??);
+        }
+        let mut responses = Vec::with_capacity(2 * hidden_count as usize + 1);
+        for _ in 0..2 * hidden_count as usize + 1 {
+            responses.push(reader.scalar().ok_or_else(invalid)??);
+        }
+        if !reader.0.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Presentation {
+            u,
+            c_u_prime,
+            revealed,
+            hidden,
+            challenge,
+            responses,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn point(&mut self) -> Option<Result<RistrettoPoint, CryptoError>> {
+        self.take(POINT_SIZE).map(decompress)
+    }
+
+    fn scalar(&mut self) -> Option<Result<Scalar, CryptoError>> {
+        self.take(SCALAR_SIZE).map(canonical_scalar)
+    }
+}
+
+/// The second generator, with no known discrete log relative to G
+fn generator_h() -> RistrettoPoint {
+    let mut hasher = Sha512::new();
+    hasher.input(GENERATOR_H_DOMAIN);
+    let hash = hasher.result();
+    RistrettoPoint::from_uniform_bytes(array_ref!(hash.as_slice(), 0, 64))
+}
+
+/// u and m_i u for every attribute
+fn attribute_bases(u: &RistrettoPoint, attributes: &[Scalar]) -> Vec<RistrettoPoint> {
+    let mut bases = Vec::with_capacity(attributes.len() + 1);
+    bases.push(*u);
+    bases.extend(attributes.iter().map(|m| m * u));
+    bases
+}
+
+fn issuance_challenge(
+    params: &IssuerParameters,
+    mac: &Mac,
+    attributes: &[Scalar],
+    commitments: &[RistrettoPoint],
+) -> Scalar {
+    let mut transcript = Sha512::new();
+    transcript.input(ISSUANCE_DOMAIN);
+    transcript.input(params.to_bytes());
+    transcript.input(mac.u.compress().as_bytes());
+    transcript.input(mac.u_prime.compress().as_bytes());
+    for m in attributes {
+        transcript.input(m.as_bytes());
+    }
+    for t in commitments {
+        transcript.input(t.compress().as_bytes());
+    }
+    hash_to_scalar(transcript)
+}
+
+fn presentation_challenge(
+    params: &IssuerParameters,
+    presentation: &Presentation,
+    commitments: &[RistrettoPoint],
+    context: &[u8],
+) -> Scalar {
+    let mut transcript = Sha512::new();
+    transcript.input(PRESENTATION_DOMAIN);
+    transcript.input(params.to_bytes());
+    transcript.input(presentation.u.compress().as_bytes());
+    transcript.input(presentation.c_u_prime.compress().as_bytes());
+    for (i, m) in &presentation.revealed {
+        transcript.input((*i as u64).to_be_bytes());
+        transcript.input(m.as_bytes());
+    }
+    for (j, c) in &presentation.hidden {
+        transcript.input((*j as u64).to_be_bytes());
+        transcript.input(c.compress().as_bytes());
+    }
+    for t in commitments {
+        transcript.input(t.compress().as_bytes());
+    }
+    transcript.input((context.len() as u64).to_be_bytes());
+    transcript.input(context);
+    hash_to_scalar(transcript)
+}
+
+fn hash_to_scalar(hasher: Sha512) -> Scalar {
+    let hash = hasher.result();
+    Scalar::from_bytes_mod_order_wide(array_ref!(hash.as_slice(), 0, 64))
+}
+
+fn decompress(bytes: &[u8]) -> Result<RistrettoPoint, CryptoError> {
+    CompressedRistretto::from_slice(bytes)
+        .decompress()
+        .ok_or_else(|| CryptoError::ParseError("Invalid Ristretto point".to_string()))
+}
+
+fn canonical_scalar(bytes: &[u8]) -> Result<Scalar, CryptoError> {
+    Scalar::from_canonical_bytes(*array_ref!(bytes, 0, SCALAR_SIZE))
+        .ok_or_else(|| CryptoError::ParseError("Invalid scalar".to_string()))
+}
+
+fn read_points(bytes: &[u8]) -> Result<Vec<RistrettoPoint>, CryptoError> {
+    if !bytes.len().is_multiple_of(POINT_SIZE) {
+        return Err(CryptoError::ParseError("Invalid length".to_string()));
+    }
+    bytes.chunks(POINT_SIZE).map(decompress).collect()
+}
+
+fn read_scalars(bytes: &[u8]) -> Result<Vec<Scalar>, CryptoError> {
+    if !bytes.len().is_multiple_of(SCALAR_SIZE) {
+        return Err(CryptoError::ParseError("Invalid length".to_string()));
+    }
+    bytes.chunks(SCALAR_SIZE).map(canonical_scalar).collect()
+}
+
+fn invalid_presentation() -> CryptoError {
+    CryptoError::GeneralError("Invalid presentation".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attributes() -> Vec<Scalar> {
+        vec![
+            attribute_from_bytes(b"alice"),
+            attribute_from_bytes(b"1990-01-01"),
+            attribute_from_bytes(b"premium"),
+        ]
+    }
+
+    #[test]
+    fn issue_and_present() {
+        let sk = IssuerSecretKey::generate(3).unwrap();
+        let params = sk.parameters();
+        let issued = sk.issue(&attributes()).unwrap();
+        let credential = Credential::new(&params, &attributes(), &issued).unwrap();
+
+        let revealed: BTreeSet<usize> = [2].iter().copied().collect();
+        let p1 = credential.present(&params, &revealed, b"nonce 1").unwrap();
+        assert!(sk.verify_presentation(&p1, b"nonce 1").is_ok());
+        assert_eq!(p1.revealed().len(), 1);
+        assert_eq!(p1.revealed()[&2], attributes()[2]);
+        assert!(sk.verify_presentation(&p1, b"nonce 2").is_err());
+
+        // Presentations are randomized
+        let p2 = credential.present(&params, &revealed, b"nonce 1").unwrap();
+        assert_ne!(p1.u, p2.u);
+
+        // Everything revealed and nothing revealed
+        let all: BTreeSet<usize> = (0..3).collect();
+        let p = credential.present(&params, &all, b"").unwrap();
+        assert!(sk.verify_presentation(&p, b"").is_ok());
+        let p = credential.present(&params, &BTreeSet::new(), b"").unwrap();
+        assert!(sk.verify_presentation(&p, b"").is_ok());
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let sk = IssuerSecretKey::generate(3).unwrap();
+        let params = sk.parameters();
+        let issued = sk.issue(&attributes()).unwrap();
+        let credential = Credential::new(&params, &attributes(), &issued).unwrap();
+
+        // Changing a revealed attribute
+        let revealed: BTreeSet<usize> = [0].iter().copied().collect();
+        let mut p = credential.present(&params, &revealed, b"ctx").unwrap();
+        p.revealed.insert(0, attribute_from_bytes(b"mallory"));
+        assert!(sk.verify_presentation(&p, b"ctx").is_err());
+
+        // A credential from another issuer, or for other attributes
+        let other = IssuerSecretKey::generate(3).unwrap();
+        let p = credential.present(&params, &revealed, b"ctx").unwrap();
+        assert!(other.verify_presentation(&p, b"ctx").is_err());
+        let mut wrong = attributes();
+        wrong[1] = Scalar::one();
+        assert!(Credential::new(&params, &wrong, &issued).is_err());
+        assert!(Credential::new(&other.parameters(), &attributes(), &issued).is_err());
+
+        assert!(sk.issue(&attributes()[..2]).is_err());
+        assert!(IssuerSecretKey::generate(0).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let sk =
+            IssuerSecretKey::from_bytes(&IssuerSecretKey::generate(3).unwrap().to_bytes()).unwrap();
+        let params = IssuerParameters::from_bytes(&sk.parameters().to_bytes()).unwrap();
+        assert_eq!(params, sk.parameters());
+        let issued =
+            IssuedCredential::from_bytes(&sk.issue(&attributes()).unwrap().to_bytes()).unwrap();
+        let credential = Credential::new(&params, &attributes(), &issued).unwrap();
+
+        let revealed: BTreeSet<usize> = [1].iter().copied().collect();
+        let p = credential.present(&params, &revealed, b"ctx").unwrap();
+        let bytes = p.to_bytes();
+        let q = Presentation::from_bytes(&bytes).unwrap();
+        assert_eq!(p, q);
+        assert!(sk.verify_presentation(&q, b"ctx").is_ok());
+        assert!(Presentation::from_bytes(&bytes[1..]).is_err());
+        assert!(Presentation::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -10,6 +10,16 @@ compile_error!("Cannot compile both features 'ecdsa_sepc256k1_native' and 'ecdsa
 compile_error!("Cannot compile both features 'ed25519' and 'ed25519_asm'");
 #[cfg(all(feature = "cl", feature = "cl_native"))]
 compile_error!("Cannot compile both features 'cl' and 'cl_native'");
+#[cfg(all(feature = "cl", feature = "cl_gmp"))]
+compile_error!("Cannot compile both features 'cl' and 'cl_gmp'");
+#[cfg(all(feature = "cl_native", feature = "cl_gmp"))]
+compile_error!("Cannot compile both features 'cl_native' and 'cl_gmp'");
+#[cfg(all(feature = "sharing", feature = "sharing_native"))]
+compile_error!("Cannot compile both features 'sharing' and 'sharing_native'");
+#[cfg(all(feature = "sharing", feature = "sharing_gmp"))]
+compile_error!("Cannot compile both features 'sharing' and 'sharing_gmp'");
+#[cfg(all(feature = "sharing_native", feature = "sharing_gmp"))]
+compile_error!("Cannot compile both features 'sharing_native' and 'sharing_gmp'");
 
 #[cfg(feature = "aead")]
 extern crate aead;
@@ -19,6 +29,8 @@ extern crate aes;
 extern crate aes_gcm;
 #[cfg(feature = "amcl")]
 extern crate amcl;
+#[cfg(feature = "argon2")]
+extern crate argon2;
 #[cfg(feature = "block-modes")]
 extern crate block_modes;
 #[cfg(feature = "block-padding")]
@@ -31,8 +43,12 @@ extern crate libsodium_ffi;
 extern crate openssl;
 #[cfg(feature = "rand")]
 extern crate rand;
+#[cfg(feature = "rust-gmp")]
+extern crate gmp;
 #[cfg(feature = "rand_chacha")]
 extern crate rand_chacha;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 #[cfg(feature = "rustchacha20poly1305")]
 extern crate rustchacha20poly1305;
 #[cfg(feature = "subtle")]
@@ -40,12 +56,18 @@ extern crate subtle;
 #[cfg(feature = "lazy_static")]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "base64")]
+extern crate base64;
 #[cfg(feature = "blake2")]
 pub extern crate blake2;
 #[cfg(test)]
 extern crate bytebuffer;
+#[cfg(feature = "chacha20")]
+extern crate chacha20;
 #[cfg(feature = "ed25519-dalek")]
 extern crate ed25519_dalek;
+#[cfg(feature = "sha1_smol")]
+extern crate sha1_smol;
 #[cfg(feature = "sha2")]
 pub extern crate sha2;
 #[cfg(feature = "sha3")]
@@ -59,6 +81,8 @@ extern crate arrayref;
 extern crate amcl_wrapper;
 #[cfg(feature = "failure")]
 extern crate failure;
+#[cfg(feature = "flate2")]
+extern crate flate2;
 #[cfg(feature = "glass_pumpkin")]
 extern crate glass_pumpkin;
 #[cfg(feature = "int_traits")]
@@ -68,8 +92,10 @@ extern crate int_traits;
     any(
         feature = "cl",
         feature = "cl_native",
+        feature = "cl_gmp",
         feature = "sharing",
         feature = "sharing_native",
+        feature = "sharing_gmp",
         feature = "ffi"
     ),
     macro_use
@@ -87,14 +113,29 @@ extern crate k256;
 #[cfg(any(test, feature = "bitcoinsecp256k1"))]
 extern crate bitcoinsecp256k1;
 
+#[cfg(feature = "p256")]
+extern crate p256;
 #[cfg(feature = "serde")]
 extern crate serde;
-#[cfg(any(test, feature = "ffi"))]
+#[cfg(feature = "serde_cbor")]
+extern crate serde_cbor;
+#[cfg(any(
+    test,
+    feature = "ffi",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "key_encoding",
+    feature = "serde_json",
+    feature = "webauthn"
+))]
 #[cfg_attr(
     any(
+        feature = "aws_kms",
         feature = "cl",
         feature = "cl_native",
+        feature = "cl_gmp",
         feature = "ffi",
+        feature = "gcp_kms",
         feature = "wasm"
     ),
     macro_use
@@ -122,6 +163,7 @@ extern crate x25519_dalek;
     feature = "bls_bls12381",
     feature = "cl",
     feature = "cl_native",
+    feature = "cl_gmp",
     feature = "ecdh_secp256k1",
     feature = "ecdh_secp256k1_native",
     feature = "ecdh_secp256k1_asm",
@@ -133,28 +175,42 @@ extern crate x25519_dalek;
     feature = "ffi",
     feature = "x25519",
     feature = "x25519_asm",
-    feature = "wasm"
+    feature = "wasm",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
 ))]
 #[macro_use]
 pub mod utils;
 
+#[cfg(feature = "acvp")]
+pub mod acvp;
 #[cfg(any(feature = "bls_bn254", feature = "bls_bn254_asm"))]
 pub mod bls;
-#[cfg(any(feature = "cl_native", feature = "sharing_native"))]
-#[path = "bn/openssl.rs"]
-pub mod bn;
-#[cfg(any(feature = "cl", feature = "sharing"))]
-#[path = "bn/rust.rs"]
+#[cfg(any(
+    feature = "cl",
+    feature = "cl_native",
+    feature = "cl_gmp",
+    feature = "sharing",
+    feature = "sharing_native",
+    feature = "sharing_gmp"
+))]
 pub mod bn;
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+pub mod capabilities;
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 pub mod cl;
+#[cfg(feature = "cryptobox")]
+pub mod cryptobox;
 #[cfg(any(
     feature = "aescbc",
     feature = "aescbc_native",
     feature = "aesgcm",
     feature = "aesgcm_native",
+    feature = "blind_index",
     feature = "chacha20poly1305",
-    feature = "chacha20poly1305_native"
+    feature = "chacha20poly1305_native",
+    feature = "keyring",
+    feature = "leaky"
 ))]
 pub mod encryption;
 #[cfg(any(
@@ -164,12 +220,16 @@ pub mod encryption;
     feature = "ecdsa_secp256k1_asm",
     feature = "cl",
     feature = "cl_native",
+    feature = "cl_gmp",
     feature = "sharing",
     feature = "sharing_native",
+    feature = "sharing_gmp",
     feature = "ffi",
     feature = "wasm"
 ))]
 pub mod errors;
+#[cfg(feature = "expert")]
+pub mod expert;
 #[cfg(feature = "ffi")]
 pub mod ffi;
 #[cfg(any(feature = "blake2", feature = "sha2", feature = "sha3"))]
@@ -182,6 +242,8 @@ pub mod hash;
     feature = "x25519_asm"
 ))]
 pub mod kex;
+#[cfg(feature = "key_transparency")]
+pub mod key_transparency;
 #[cfg(any(
     feature = "bls_bls12381",
     feature = "ecdh_secp256k1",
@@ -194,18 +256,33 @@ pub mod kex;
     feature = "ed25519_asm",
     feature = "x25519",
     feature = "x25519_asm",
-    feature = "wasm"
+    feature = "wasm",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
 ))]
 pub mod keys;
+#[cfg(feature = "kvac")]
+pub mod kvac;
+#[cfg(feature = "mls")]
+pub mod mls;
+#[cfg(feature = "otp")]
+pub mod otp;
 #[cfg(any(
     feature = "bls_bn254",
     feature = "bls_bn254_asm",
     feature = "cl",
-    feature = "cl_native"
+    feature = "cl_native",
+    feature = "cl_gmp"
 ))]
 #[path = "pair/amcl.rs"]
 pub mod pair;
-#[cfg(any(feature = "sharing", feature = "sharing_native"))]
+pub mod self_test;
+#[cfg(any(
+    feature = "sharing",
+    feature = "sharing_native",
+    feature = "sharing_gmp"
+))]
 pub mod sharing;
 #[cfg(any(
     feature = "ed25519",
@@ -213,12 +290,24 @@ pub mod sharing;
     feature = "ecdsa_secp256k1",
     feature = "ecdsa_secp256k1_native",
     feature = "ecdsa_secp256k1_asm",
-    feature = "bls_bls12381"
+    feature = "bls_bls12381",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
 ))]
 pub mod signatures;
+#[cfg(feature = "srp")]
+pub mod srp;
+#[cfg(any(feature = "paseto", feature = "privacy_pass"))]
+pub mod tokens;
+#[cfg(feature = "uprove")]
+pub mod uprove;
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
+pub use capabilities::capabilities;
+pub use self_test::self_test;
+
 pub type CryptoResult<T> = Result<T, CryptoError>;
 
 #[derive(Debug)]
@@ -283,6 +372,13 @@ impl From<bitcoinsecp256k1::Error> for CryptoError {
     }
 }
 
+#[cfg(feature = "openssl")]
+impl From<openssl::error::ErrorStack> for CryptoError {
+    fn from(error: openssl::error::ErrorStack) -> CryptoError {
+        CryptoError::GeneralError(format!("OpenSSL error: {}", error))
+    }
+}
+
 #[cfg(any(
     feature = "bls_bn254",
     feature = "bls_bn254_asm",
@@ -290,8 +386,10 @@ impl From<bitcoinsecp256k1::Error> for CryptoError {
     feature = "ecdsa_secp256k1_asm",
     feature = "cl",
     feature = "cl_native",
+    feature = "cl_gmp",
     feature = "sharing",
     feature = "sharing_native",
+    feature = "sharing_gmp",
     feature = "ffi",
     feature = "wasm"
 ))]
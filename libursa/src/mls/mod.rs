@@ -0,0 +1,805 @@
+//! The cryptographic provider of an MLS (RFC 9420) stack.
+//!
+//! MLS implementations such as openmls leave the cryptography to a provider that
+//! implements the primitives of each ciphersuite, and build the protocol on that.
+//! `CryptoProvider` is that interface: the hash, MAC, KDF, AEAD, HPKE and signature
+//! algorithms of a `Ciphersuite`, with the labeled functions of RFC 9420 section 5
+//! and the TreeKEM key derivation of section 7 as provided methods on top of them.
+//! `UrsaCryptoProvider` implements it with ursa's own primitives, so an MLS stack
+//! needs no other backend.
+//!
+//! The ciphersuites are the two mandatory-to-implement X25519 suites,
+//! `MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519` and
+//! `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`. `tree` has the array
+//! arithmetic of the ratchet tree.
+//!
+//! Signature keys are ursa Ed25519 keys, so a private key is the 64 byte keypair.
+//! HPKE private keys are the 32 byte X25519 scalars of RFC 9180.
+
+pub mod tree;
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use encryption::hpke::{self, AeadId};
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// The prefix of every label
+pub const LABEL_PREFIX: &[u8] = b"MLS 1.0 ";
+/// The `SignatureScheme` code point of Ed25519
+pub const ED25519: u16 = 0x0807;
+
+const HASH_SIZE: usize = 32;
+
+/// An MLS ciphersuite
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ciphersuite {
+    /// `MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519`
+    Mls128DhkemX25519Aes128GcmSha256Ed25519,
+    /// `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`
+    Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519,
+}
+
+impl Ciphersuite {
+    /// Every supported ciphersuite, by identifier
+    pub const ALL: [Ciphersuite; 2] = [
+        Ciphersuite::Mls128DhkemX25519Aes128GcmSha256Ed25519,
+        Ciphersuite::Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519,
+    ];
+
+    /// The identifier in the MLS ciphersuites registry
+    pub fn id(self) -> u16 {
+        match self {
+            Ciphersuite::Mls128DhkemX25519Aes128GcmSha256Ed25519 => 0x0001,
+            Ciphersuite::Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519 => 0x0003,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Result<Self, CryptoError> {
+        Self::ALL
+            .iter()
+            .find(|s| s.id() == id)
+            .copied()
+            .ok_or_else(|| {
+                CryptoError::ParseError(format!("Unsupported MLS ciphersuite {:#06x}", id))
+            })
+    }
+
+    /// The name in the MLS ciphersuites registry
+    pub fn name(self) -> &'static str {
+        match self {
+            Ciphersuite::Mls128DhkemX25519Aes128GcmSha256Ed25519 => {
+                "MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519"
+            }
+            Ciphersuite::Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519 => {
+                "MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519"
+            }
+        }
+    }
+
+    /// The AEAD of both the HPKE suite and the message protection
+    pub fn aead(self) -> AeadId {
+        match self {
+            Ciphersuite::Mls128DhkemX25519Aes128GcmSha256Ed25519 => AeadId::Aes128Gcm,
+            Ciphersuite::Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519 => AeadId::ChaCha20Poly1305,
+        }
+    }
+
+    /// The HPKE KEM, KDF and AEAD identifiers
+    pub fn hpke_suite(self) -> (u16, u16, u16) {
+        (hpke::KEM_ID, hpke::KDF_ID, self.aead().id())
+    }
+
+    /// The `SignatureScheme` code point
+    pub fn signature_scheme(self) -> u16 {
+        ED25519
+    }
+
+    /// `Nh`, the output length of the hash and the KDF
+    pub fn hash_size(self) -> usize {
+        HASH_SIZE
+    }
+
+    /// `Nk`
+    pub fn aead_key_size(self) -> usize {
+        self.aead().key_size()
+    }
+
+    /// `Nn`
+    pub fn aead_nonce_size(self) -> usize {
+        self.aead().nonce_size()
+    }
+}
+
+/// An HPKE ciphertext as MLS encodes it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HpkeCiphertext {
+    pub kem_output: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl HpkeCiphertext {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CryptoError> {
+        let mut output = Vec::new();
+        write_vector(&mut output, &self.kem_output)?;
+        write_vector(&mut output, &self.ciphertext)?;
+        Ok(output)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut data = data;
+        let kem_output = read_vector(&mut data)?.to_vec();
+        let ciphertext = read_vector(&mut data)?.to_vec();
+        if !data.is_empty() {
+            return Err(CryptoError::ParseError(
+                "HPKE ciphertext has trailing bytes".to_string(),
+            ));
+        }
+        Ok(HpkeCiphertext {
+            kem_output,
+            ciphertext,
+        })
+    }
+}
+
+/// The cryptographic primitives an MLS stack runs on.
+///
+/// Implementations provide the primitives of each ciphersuite they support. The
+/// provided methods derive everything else RFC 9420 needs from them and shouldn't
+/// be overridden, other implementations depend on their exact output.
+pub trait CryptoProvider {
+    /// Whether the provider implements `suite`
+    fn supports(&self, suite: Ciphersuite) -> bool;
+
+    /// `length` bytes from a cryptographically secure generator
+    fn random_bytes(&self, length: usize) -> Result<Vec<u8>, CryptoError>;
+
+    fn hash(&self, suite: Ciphersuite, data: &[u8]) -> Result<Vec<u8>, CryptoError>;
+
+    fn mac(&self, suite: Ciphersuite, key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError>;
+
+    fn kdf_extract(
+        &self,
+        suite: Ciphersuite,
+        salt: &[u8],
+        ikm: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    fn kdf_expand(
+        &self,
+        suite: Ciphersuite,
+        prk: &[u8],
+        info: &[u8],
+        length: usize,
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    fn aead_seal(
+        &self,
+        suite: Ciphersuite,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    fn aead_open(
+        &self,
+        suite: Ciphersuite,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Generate a signature key pair
+    fn signature_key_pair(
+        &self,
+        suite: Ciphersuite,
+    ) -> Result<(PublicKey, PrivateKey), CryptoError>;
+
+    fn sign(
+        &self,
+        suite: Ciphersuite,
+        private_key: &PrivateKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Whether `signature` is one of `message` by `public_key`
+    fn verify(
+        &self,
+        suite: Ciphersuite,
+        public_key: &PublicKey,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, CryptoError>;
+
+    /// `SealBase` of RFC 9180
+    fn hpke_seal(
+        &self,
+        suite: Ciphersuite,
+        public_key: &PublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<HpkeCiphertext, CryptoError>;
+
+    /// `OpenBase` of RFC 9180
+    fn hpke_open(
+        &self,
+        suite: Ciphersuite,
+        private_key: &PrivateKey,
+        ciphertext: &HpkeCiphertext,
+        info: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// `DeriveKeyPair` of the HPKE KEM
+    fn derive_hpke_key_pair(
+        &self,
+        suite: Ciphersuite,
+        ikm: &[u8],
+    ) -> Result<(PublicKey, PrivateKey), CryptoError>;
+
+    /// Generate an HPKE key pair
+    fn hpke_key_pair(&self, suite: Ciphersuite) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        let ikm = self.random_bytes(suite.hash_size())?;
+        self.derive_hpke_key_pair(suite, &ikm)
+    }
+
+    /// `ExpandWithLabel`
+    fn expand_with_label(
+        &self,
+        suite: Ciphersuite,
+        secret: &[u8],
+        label: &[u8],
+        context: &[u8],
+        length: usize,
+    ) -> Result<Vec<u8>, CryptoError> {
+        if length > 0xffff {
+            return Err(CryptoError::GeneralError(
+                "Requested too many bytes".to_string(),
+            ));
+        }
+        let mut info = (length as u16).to_be_bytes().to_vec();
+        write_vector(&mut info, &labeled(label))?;
+        write_vector(&mut info, context)?;
+        self.kdf_expand(suite, secret, &info, length)
+    }
+
+    /// `DeriveSecret`
+    fn derive_secret(
+        &self,
+        suite: Ciphersuite,
+        secret: &[u8],
+        label: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.expand_with_label(suite, secret, label, &[], suite.hash_size())
+    }
+
+    /// `DeriveTreeSecret` of the secret tree
+    fn derive_tree_secret(
+        &self,
+        suite: Ciphersuite,
+        secret: &[u8],
+        label: &[u8],
+        generation: u32,
+        length: usize,
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.expand_with_label(suite, secret, label, &generation.to_be_bytes(), length)
+    }
+
+    /// `RefHash`, which names key packages and proposals
+    fn ref_hash(
+        &self,
+        suite: Ciphersuite,
+        label: &[u8],
+        value: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut input = Vec::new();
+        write_vector(&mut input, label)?;
+        write_vector(&mut input, value)?;
+        self.hash(suite, &input)
+    }
+
+    /// `SignWithLabel`
+    fn sign_with_label(
+        &self,
+        suite: Ciphersuite,
+        private_key: &PrivateKey,
+        label: &[u8],
+        content: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.sign(suite, private_key, &labeled_content(label, content)?)
+    }
+
+    /// `VerifyWithLabel`
+    fn verify_with_label(
+        &self,
+        suite: Ciphersuite,
+        public_key: &PublicKey,
+        label: &[u8],
+        content: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, CryptoError> {
+        self.verify(
+            suite,
+            public_key,
+            &labeled_content(label, content)?,
+            signature,
+        )
+    }
+
+    /// `EncryptWithLabel`
+    fn encrypt_with_label(
+        &self,
+        suite: Ciphersuite,
+        public_key: &PublicKey,
+        label: &[u8],
+        context: &[u8],
+        plaintext: &[u8],
+    ) -> Result<HpkeCiphertext, CryptoError> {
+        let info = labeled_content(label, context)?;
+        self.hpke_seal(suite, public_key, &info, &[], plaintext)
+    }
+
+    /// `DecryptWithLabel`
+    fn decrypt_with_label(
+        &self,
+        suite: Ciphersuite,
+        private_key: &PrivateKey,
+        label: &[u8],
+        context: &[u8],
+        ciphertext: &HpkeCiphertext,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let info = labeled_content(label, context)?;
+        self.hpke_open(suite, private_key, ciphertext, &info, &[])
+    }
+
+    /// The path secret of the parent of a node with `path_secret`
+    fn derive_path_secret(
+        &self,
+        suite: Ciphersuite,
+        path_secret: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.derive_secret(suite, path_secret, b"path")
+    }
+
+    /// The HPKE key pair of a node with `path_secret`
+    fn derive_node_key_pair(
+        &self,
+        suite: Ciphersuite,
+        path_secret: &[u8],
+    ) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        let node_secret = self.derive_secret(suite, path_secret, b"node")?;
+        self.derive_hpke_key_pair(suite, &node_secret)
+    }
+}
+
+/// The provider built on ursa's primitives
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UrsaCryptoProvider;
+
+impl CryptoProvider for UrsaCryptoProvider {
+    fn supports(&self, suite: Ciphersuite) -> bool {
+        Ciphersuite::ALL.contains(&suite)
+    }
+
+    fn random_bytes(&self, length: usize) -> Result<Vec<u8>, CryptoError> {
+        let mut output = vec![0u8; length];
+        OsRng
+            .try_fill_bytes(&mut output)
+            .map_err(|e| CryptoError::GeneralError(e.to_string()))?;
+        Ok(output)
+    }
+
+    fn hash(&self, _suite: Ciphersuite, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        Ok(Sha256::digest(data).to_vec())
+    }
+
+    fn mac(&self, _suite: Ciphersuite, key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut mac = Hmac::<Sha256>::new_varkey(key)
+            .map_err(|_| CryptoError::GeneralError("Invalid MAC key".to_string()))?;
+        mac.input(data);
+        Ok(mac.result().code().to_vec())
+    }
+
+    fn kdf_extract(
+        &self,
+        _suite: Ciphersuite,
+        salt: &[u8],
+        ikm: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), ikm);
+        Ok(prk.to_vec())
+    }
+
+    fn kdf_expand(
+        &self,
+        _suite: Ciphersuite,
+        prk: &[u8],
+        info: &[u8],
+        length: usize,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut output = vec![0u8; length];
+        Hkdf::<Sha256>::from_prk(prk)
+            .map_err(|_| CryptoError::GeneralError("Invalid pseudorandom key".to_string()))?
+            .expand(info, &mut output)
+            .map_err(|e| CryptoError::GeneralError(format!("Failed to derive the key: {}", e)))?;
+        Ok(output)
+    }
+
+    fn aead_seal(
+        &self,
+        suite: Ciphersuite,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_aead_inputs(suite, key, nonce)?;
+        suite
+            .aead()
+            .seal(key, nonce, aad, plaintext)
+            .map_err(|_| CryptoError::GeneralError("Failed to encrypt".to_string()))
+    }
+
+    fn aead_open(
+        &self,
+        suite: Ciphersuite,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_aead_inputs(suite, key, nonce)?;
+        if ciphertext.len() < hpke::TAG_SIZE {
+            return Err(CryptoError::ParseError(
+                "Ciphertext is too short".to_string(),
+            ));
+        }
+        suite
+            .aead()
+            .open(key, nonce, aad, ciphertext)
+            .map_err(|_| CryptoError::GeneralError("Failed to decrypt".to_string()))
+    }
+
+    fn signature_key_pair(
+        &self,
+        _suite: Ciphersuite,
+    ) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        Ed25519Sha512::new().keypair(None)
+    }
+
+    fn sign(
+        &self,
+        _suite: Ciphersuite,
+        private_key: &PrivateKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        Ed25519Sha512::new().sign(message, private_key)
+    }
+
+    fn verify(
+        &self,
+        _suite: Ciphersuite,
+        public_key: &PublicKey,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, CryptoError> {
+        match Ed25519Sha512::new().verify(message, signature, public_key) {
+            Err(CryptoError::SigningError(_)) => Ok(false),
+            result => result,
+        }
+    }
+
+    fn hpke_seal(
+        &self,
+        suite: Ciphersuite,
+        public_key: &PublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<HpkeCiphertext, CryptoError> {
+        let (kem_output, ciphertext) =
+            hpke::seal_base(suite.aead(), public_key, info, aad, plaintext)?;
+        Ok(HpkeCiphertext {
+            kem_output,
+            ciphertext,
+        })
+    }
+
+    fn hpke_open(
+        &self,
+        suite: Ciphersuite,
+        private_key: &PrivateKey,
+        ciphertext: &HpkeCiphertext,
+        info: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        hpke::open_base(
+            suite.aead(),
+            &ciphertext.kem_output,
+            private_key,
+            info,
+            aad,
+            &ciphertext.ciphertext,
+        )
+    }
+
+    fn derive_hpke_key_pair(
+        &self,
+        _suite: Ciphersuite,
+        ikm: &[u8],
+    ) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        hpke::derive_key_pair(ikm)
+    }
+}
+
+/// Append `value` as an MLS `opaque value<V>`, with its length as a variable-length
+/// integer
+pub fn write_vector(output: &mut Vec<u8>, value: &[u8]) -> Result<(), CryptoError> {
+    let len = value.len();
+    if len < 1 << 6 {
+        output.push(len as u8);
+    } else if len < 1 << 14 {
+        output.extend_from_slice(&(len as u16 | 0x4000).to_be_bytes());
+    } else if len < 1 << 30 {
+        output.extend_from_slice(&(len as u32 | 0x8000_0000).to_be_bytes());
+    } else {
+        return Err(CryptoError::GeneralError(
+            "Vector is too long to encode".to_string(),
+        ));
+    }
+    output.extend_from_slice(value);
+    Ok(())
+}
+
+/// Read an MLS `opaque value<V>` from the front of `input`, advancing it
+pub fn read_vector<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], CryptoError> {
+    let short = || CryptoError::ParseError("Vector is truncated".to_string());
+    let first = *input.first().ok_or_else(short)?;
+    let (size, len) = match first >> 6 {
+        0 => (1, first as usize),
+        1 if input.len() >= 2 => (
+            2,
+            (u16::from_be_bytes(*array_ref!(input, 0, 2)) & 0x3fff) as usize,
+        ),
+        2 if input.len() >= 4 => (
+            4,
+            (u32::from_be_bytes(*array_ref!(input, 0, 4)) & 0x3fff_ffff) as usize,
+        ),
+        3 => {
+            return Err(CryptoError::ParseError(
+                "Vector length has an invalid prefix".to_string(),
+            ))
+        }
+        _ => return Err(short()),
+    };
+    // Lengths must use the shortest encoding
+    if (size == 2 && len < 1 << 6) || (size == 4 && len < 1 << 14) {
+        return Err(CryptoError::ParseError(
+            "Vector length isn't minimally encoded".to_string(),
+        ));
+    }
+    if input.len() < size + len {
+        return Err(short());
+    }
+    let value = &input[size..size + len];
+    *input = &input[size + len..];
+    Ok(value)
+}
+
+fn labeled(label: &[u8]) -> Vec<u8> {
+    let mut output = LABEL_PREFIX.to_vec();
+    output.extend_from_slice(label);
+    output
+}
+
+/// `SignContent` and `EncryptContext`, which share their encoding
+fn labeled_content(label: &[u8], content: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut output = Vec::new();
+    write_vector(&mut output, &labeled(label))?;
+    write_vector(&mut output, content)?;
+    Ok(output)
+}
+
+fn check_aead_inputs(suite: Ciphersuite, key: &[u8], nonce: &[u8]) -> Result<(), CryptoError> {
+    if key.len() != suite.aead_key_size() || nonce.len() != suite.aead_nonce_size() {
+        return Err(CryptoError::GeneralError(
+            "AEAD key or nonce has the wrong length".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROVIDER: UrsaCryptoProvider = UrsaCryptoProvider;
+
+    #[test]
+    fn registry() {
+        for suite in &Ciphersuite::ALL {
+            assert!(PROVIDER.supports(*suite));
+            assert_eq!(Ciphersuite::from_id(suite.id()).unwrap(), *suite);
+            assert_eq!(suite.hpke_suite().0, 0x0020);
+            assert_eq!(suite.signature_scheme(), ED25519);
+        }
+        let suite = Ciphersuite::from_id(1).unwrap();
+        assert_eq!(suite.name(), "MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519");
+        assert_eq!(suite.hpke_suite(), (0x0020, 0x0001, 0x0001));
+        assert_eq!(suite.aead_key_size(), 16);
+        assert_eq!(
+            Ciphersuite::from_id(3).unwrap().hpke_suite(),
+            (0x0020, 0x0001, 0x0003)
+        );
+        assert!(Ciphersuite::from_id(2).is_err());
+    }
+
+    #[test]
+    fn vectors() {
+        // RFC 9000 section A.1, without the encodings MLS doesn't allow
+        for (bytes, len) in &[
+            (&[0x25u8][..], 37usize),
+            (&[0x7b, 0xbd], 15293),
+            (&[0x80, 0x00, 0x40, 0x00], 1 << 14),
+        ] {
+            let mut input = *bytes;
+            let short = read_vector(&mut input).unwrap_err();
+            assert!(matches!(short, CryptoError::ParseError(_)));
+            let mut encoded = Vec::new();
+            write_vector(&mut encoded, &vec![7u8; *len]).unwrap();
+            assert_eq!(&encoded[..bytes.len()], *bytes);
+            let mut input = &encoded[..];
+            assert_eq!(read_vector(&mut input).unwrap().len(), *len);
+            assert!(input.is_empty());
+        }
+        for invalid in &[&[0x40u8, 0x25][..], &[0xc2, 0x19, 0x7c, 0x5e], &[0x41]] {
+            assert!(read_vector(&mut &invalid[..]).is_err());
+        }
+
+        let ciphertext = HpkeCiphertext {
+            kem_output: vec![1; 32],
+            ciphertext: vec![2; 100],
+        };
+        let bytes = ciphertext.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 1 + 32 + 2 + 100);
+        assert_eq!(HpkeCiphertext::from_bytes(&bytes).unwrap(), ciphertext);
+        assert!(HpkeCiphertext::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn labeled_derivation() {
+        let suite = Ciphersuite::Mls128DhkemX25519Aes128GcmSha256Ed25519;
+        let secret = PROVIDER.kdf_extract(suite, &[], b"input").unwrap();
+
+        // KDFLabel is the length, the prefixed label and the context
+        let mut info = vec![0, 16, 14];
+        info.extend_from_slice(b"MLS 1.0 secret");
+        info.extend_from_slice(&[3, 1, 2, 3]);
+        assert_eq!(
+            PROVIDER
+                .expand_with_label(suite, &secret, b"secret", &[1, 2, 3], 16)
+                .unwrap(),
+            PROVIDER.kdf_expand(suite, &secret, &info, 16).unwrap()
+        );
+        let derived = PROVIDER.derive_secret(suite, &secret, b"epoch").unwrap();
+        assert_eq!(derived.len(), 32);
+        assert_eq!(
+            derived,
+            PROVIDER
+                .expand_with_label(suite, &secret, b"epoch", &[], 32)
+                .unwrap()
+        );
+        assert_ne!(
+            PROVIDER
+                .derive_tree_secret(suite, &secret, b"key", 0, 16)
+                .unwrap(),
+            PROVIDER
+                .derive_tree_secret(suite, &secret, b"key", 1, 16)
+                .unwrap()
+        );
+
+        let reference = PROVIDER
+            .ref_hash(suite, b"MLS 1.0 KeyPackage Reference", b"package")
+            .unwrap();
+        let mut input = vec![28];
+        input.extend_from_slice(b"MLS 1.0 KeyPackage Reference");
+        input.push(7);
+        input.extend_from_slice(b"package");
+        assert_eq!(reference, PROVIDER.hash(suite, &input).unwrap());
+    }
+
+    #[test]
+    fn signatures_and_encryption() {
+        for suite in &Ciphersuite::ALL {
+            let suite = *suite;
+            let (pk, sk) = PROVIDER.signature_key_pair(suite).unwrap();
+            let signature = PROVIDER
+                .sign_with_label(suite, &sk, b"LeafNodeTBS", b"content")
+                .unwrap();
+            assert!(PROVIDER
+                .verify_with_label(suite, &pk, b"LeafNodeTBS", b"content", &signature)
+                .unwrap());
+            assert!(!PROVIDER
+                .verify_with_label(suite, &pk, b"KeyPackageTBS", b"content", &signature)
+                .unwrap());
+            assert!(!PROVIDER.verify(suite, &pk, b"content", &signature).unwrap());
+
+            let (pk, sk) = PROVIDER.hpke_key_pair(suite).unwrap();
+            let ciphertext = PROVIDER
+                .encrypt_with_label(suite, &pk, b"Welcome", b"context", b"group secrets")
+                .unwrap();
+            assert_eq!(
+                PROVIDER
+                    .decrypt_with_label(suite, &sk, b"Welcome", b"context", &ciphertext)
+                    .unwrap(),
+                b"group secrets"
+            );
+            assert!(PROVIDER
+                .decrypt_with_label(suite, &sk, b"UpdatePathNode", b"context", &ciphertext)
+                .is_err());
+
+            let key = PROVIDER.random_bytes(suite.aead_key_size()).unwrap();
+            let nonce = PROVIDER.random_bytes(suite.aead_nonce_size()).unwrap();
+            let sealed = PROVIDER
+                .aead_seal(suite, &key, &nonce, b"aad", b"message")
+                .unwrap();
+            assert_eq!(
+                PROVIDER
+                    .aead_open(suite, &key, &nonce, b"aad", &sealed)
+                    .unwrap(),
+                b"message"
+            );
+            assert!(PROVIDER
+                .aead_open(suite, &key, &nonce, b"other", &sealed)
+                .is_err());
+            assert!(PROVIDER
+                .aead_seal(suite, &key[1..], &nonce, b"", b"")
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn tree_key_derivation() {
+        // The members below a node derive the same keys up to the root
+        let suite = Ciphersuite::Mls128DhkemX25519ChaCha20Poly1305Sha256Ed25519;
+        let n = 8;
+        let leaf_secret = PROVIDER.random_bytes(32).unwrap();
+        let path = tree::direct_path(tree::leaf_to_node(2), n);
+        assert_eq!(path, vec![5, 3, 7]);
+        let mut secrets = vec![leaf_secret];
+        for _ in &path {
+            let next = PROVIDER
+                .derive_path_secret(suite, secrets.last().unwrap())
+                .unwrap();
+            secrets.push(next);
+        }
+        let keys = secrets
+            .iter()
+            .map(|s| PROVIDER.derive_node_key_pair(suite, s).unwrap())
+            .collect::<Vec<_>>();
+
+        // Leaf 0 is given the path secret of node 3, sealed to node 1
+        let (pk1, sk1) = PROVIDER.hpke_key_pair(suite).unwrap();
+        let sealed = PROVIDER
+            .encrypt_with_label(suite, &pk1, b"UpdatePathNode", b"group", &secrets[2])
+            .unwrap();
+        let opened = PROVIDER
+            .decrypt_with_label(suite, &sk1, b"UpdatePathNode", b"group", &sealed)
+            .unwrap();
+        let root_secret = PROVIDER.derive_path_secret(suite, &opened).unwrap();
+        assert_eq!(
+            PROVIDER.derive_node_key_pair(suite, &root_secret).unwrap(),
+            keys[3]
+        );
+        assert_ne!(keys[0].0, keys[1].0);
+    }
+}
@@ -0,0 +1,187 @@
+//! Tree arithmetic for TreeKEM (RFC 9420 appendix C).
+//!
+//! The ratchet tree is stored as an array with the leaves at the even indices and
+//! the parents between their children, so node `2i` is leaf `i` and the level of a
+//! node is the number of trailing one bits of its index. Every function takes the
+//! number of leaves `n` of the tree.
+//!
+//! RFC 9420 trees always have a power of two leaves, and for those these are the
+//! functions of the RFC. Other sizes give the left-balanced trees of the earlier
+//! drafts, where nodes past the end of the array are skipped, so a group can also
+//! keep its tree as small as its membership.
+
+/// The level of a node, zero for leaves
+pub fn level(x: usize) -> u32 {
+    (!x).trailing_zeros()
+}
+
+/// The node index of a leaf
+pub fn leaf_to_node(leaf: u32) -> usize {
+    2 * leaf as usize
+}
+
+/// The leaf index of a node at level zero
+pub fn node_to_leaf(x: usize) -> u32 {
+    (x / 2) as u32
+}
+
+pub fn is_leaf(x: usize) -> bool {
+    x & 1 == 0
+}
+
+/// The number of nodes of a tree with `n` leaves
+pub fn node_width(n: u32) -> usize {
+    if n == 0 {
+        0
+    } else {
+        2 * (n as usize - 1) + 1
+    }
+}
+
+/// The root of a tree with `n` leaves, which must be at least one
+pub fn root(n: u32) -> usize {
+    let width = node_width(n);
+    (1 << (usize::BITS - 1 - width.leading_zeros())) - 1
+}
+
+/// The left child of a parent node
+pub fn left(x: usize) -> usize {
+    x ^ (1 << (level(x) - 1))
+}
+
+/// The right child of a parent node
+pub fn right(x: usize, n: u32) -> usize {
+    let width = node_width(n);
+    let mut r = x ^ (3 << (level(x) - 1));
+    while r >= width {
+        r = left(r);
+    }
+    r
+}
+
+/// The parent of a node other than the root
+pub fn parent(x: usize, n: u32) -> usize {
+    let width = node_width(n);
+    let step = |x: usize| {
+        let k = level(x);
+        let b = (x >> (k + 1)) & 1;
+        (x | (1 << k)) ^ (b << (k + 1))
+    };
+    let mut p = step(x);
+    while p >= width {
+        p = step(p);
+    }
+    p
+}
+
+/// The other child of the parent of a node other than the root
+pub fn sibling(x: usize, n: u32) -> usize {
+    let p = parent(x, n);
+    if x < p {
+        right(p, n)
+    } else {
+        left(p)
+    }
+}
+
+/// The nodes from the parent of `x` up to the root
+pub fn direct_path(x: usize, n: u32) -> Vec<usize> {
+    let root = root(n);
+    let mut path = Vec::new();
+    let mut x = x;
+    while x != root {
+        x = parent(x, n);
+        path.push(x);
+    }
+    path
+}
+
+/// The siblings of `x` and of its ancestors below the root
+pub fn copath(x: usize, n: u32) -> Vec<usize> {
+    let mut nodes = vec![x];
+    nodes.extend(direct_path(x, n));
+    nodes.pop();
+    nodes.iter().map(|y| sibling(*y, n)).collect()
+}
+
+/// The lowest node that is `x`, `y` or an ancestor of both
+pub fn common_ancestor(x: usize, y: usize) -> usize {
+    let (mut xn, mut yn) = (x, y);
+    let lx = level(x) + 1;
+    let ly = level(y) + 1;
+    if lx <= ly && x >> ly == y >> ly {
+        return y;
+    }
+    if ly <= lx && x >> lx == y >> lx {
+        return x;
+    }
+    let mut k = 0;
+    while xn != yn {
+        xn >>= 1;
+        yn >>= 1;
+        k += 1;
+    }
+    (xn << k) + (1 << (k - 1)) - 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn full_trees() {
+        // Eight leaves, nodes 0 to 14 with the root at 7
+        let n = 8;
+        assert_eq!(node_width(n), 15);
+        assert_eq!(root(n), 7);
+        assert_eq!(left(7), 3);
+        assert_eq!(right(7, n), 11);
+        assert_eq!(parent(11, n), 7);
+        assert_eq!(parent(12, n), 13);
+        assert_eq!(sibling(9, n), 13);
+        assert_eq!(direct_path(4, n), vec![5, 3, 7]);
+        assert_eq!(copath(4, n), vec![6, 1, 11]);
+        assert_eq!(common_ancestor(0, 14), 7);
+        assert_eq!(common_ancestor(8, 10), 9);
+        assert_eq!(common_ancestor(1, 5), 3);
+        assert_eq!(common_ancestor(5, 3), 3);
+        assert_eq!(common_ancestor(6, 6), 6);
+        assert_eq!(leaf_to_node(6), 12);
+        assert_eq!(node_to_leaf(12), 6);
+        assert!(is_leaf(12));
+        assert!(!is_leaf(13));
+    }
+
+    #[test]
+    fn truncated_trees() {
+        // Five leaves, nodes 0 to 8 with the root at 7
+        let n = 5;
+        assert_eq!(node_width(n), 9);
+        assert_eq!(root(n), 7);
+        assert_eq!(right(7, n), 8);
+        assert_eq!(parent(8, n), 7);
+        assert_eq!(direct_path(0, n), vec![1, 3, 7]);
+        assert_eq!(direct_path(8, n), vec![7]);
+        assert_eq!(copath(0, n), vec![2, 5, 8]);
+        assert_eq!(copath(8, n), vec![3]);
+        assert_eq!(common_ancestor(4, 6), 5);
+        assert_eq!(common_ancestor(2, 8), 7);
+
+        assert_eq!(node_width(1), 1);
+        assert_eq!(root(1), 0);
+        assert!(direct_path(0, 1).is_empty());
+        assert!(copath(0, 1).is_empty());
+
+        // Every node of every tree is reached from the root
+        for n in 1..40u32 {
+            for x in 0..node_width(n) {
+                if x != root(n) {
+                    let p = parent(x, n);
+                    assert!(left(p) == x || right(p, n) == x);
+                    assert_eq!(direct_path(x, n).last(), Some(&root(n)));
+                    assert_eq!(common_ancestor(x, sibling(x, n)), p);
+                }
+            }
+        }
+    }
+}
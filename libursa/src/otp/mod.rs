@@ -0,0 +1,378 @@
+//! One-time passwords for second factor authentication.
+//!
+//! [`Hotp`] is the counter based scheme of RFC 4226 and [`Totp`] the time based one of
+//! RFC 6238, which count periods since the Unix epoch instead. Both are compatible with
+//! the usual authenticator apps when used with SHA-1, six digits and 30 second steps.
+//!
+//! Verification accepts a window of counters around the expected one, for HOTP tokens
+//! that were pressed without logging in and for TOTP clocks that drift. Every code in
+//! the window is computed and compared in constant time, and the matching counter is
+//! returned: the caller has to store it and reject it and everything before it next
+//! time, or a code can be replayed while it is in the window.
+//!
+//! [`recovery`] has the single use codes that let a user who lost the device holding
+//! the secret log in.
+
+pub mod recovery;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha1_smol::Sha1;
+use sha2::{Sha256, Sha512};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
+
+use CryptoError;
+
+/// The smallest number of digits RFC 4226 allows
+pub const MIN_DIGITS: u32 = 6;
+/// The largest number of digits, as a code is a 31 bit number
+pub const MAX_DIGITS: u32 = 10;
+/// The step of RFC 6238 and of the authenticator apps
+pub const DEFAULT_STEP: u64 = 30;
+
+/// The HMAC hash function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OtpAlgorithm {
+    /// The algorithm of RFC 4226 and the only one some apps support
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl OtpAlgorithm {
+    /// The name in `otpauth` URIs
+    pub fn name(self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// The recommended length of the shared secret, that of the hash output
+    pub fn secret_size(self) -> usize {
+        match self {
+            OtpAlgorithm::Sha1 => 20,
+            OtpAlgorithm::Sha256 => 32,
+            OtpAlgorithm::Sha512 => 64,
+        }
+    }
+
+    fn hmac(self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        match self {
+            OtpAlgorithm::Sha1 => hmac_sha1(key, message),
+            OtpAlgorithm::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any size");
+                mac.input(message);
+                mac.result().code().to_vec()
+            }
+            OtpAlgorithm::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC takes keys of any size");
+                mac.input(message);
+                mac.result().code().to_vec()
+            }
+        }
+    }
+}
+
+/// Counter based one-time passwords (RFC 4226)
+#[derive(Clone)]
+pub struct Hotp {
+    secret: Vec<u8>,
+    algorithm: OtpAlgorithm,
+    digits: u32,
+}
+
+impl Hotp {
+    /// Codes of `digits` digits under the shared `secret`
+    pub fn new(secret: &[u8], algorithm: OtpAlgorithm, digits: u32) -> Result<Self, CryptoError> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&digits) {
+            return Err(CryptoError::GeneralError(format!(
+                "Codes must have {} to {} digits",
+                MIN_DIGITS, MAX_DIGITS
+            )));
+        }
+        // RFC 4226 requires 128 bits
+        if secret.len() < 16 {
+            return Err(CryptoError::KeyGenError(
+                "Secret must be at least 16 bytes".to_string(),
+            ));
+        }
+        Ok(Hotp {
+            secret: secret.to_vec(),
+            algorithm,
+            digits,
+        })
+    }
+
+    pub fn algorithm(&self) -> OtpAlgorithm {
+        self.algorithm
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    /// The code for `counter`, with leading zeros
+    pub fn generate(&self, counter: u64) -> String {
+        format!(
+            "{:0width$}",
+            self.truncate(counter),
+            width = self.digits as usize
+        )
+    }
+
+    /// Check `code` against the counters from `counter` to `counter + look_ahead`.
+    /// Returns the counter that matched, after which the next one is expected
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+        self.verify_range(code, counter, counter.saturating_add(look_ahead))
+    }
+
+    /// Constant time search of `first` to `last`, which must not be a wide range
+    fn verify_range(&self, code: &str, first: u64, last: u64) -> Option<u64> {
+        let code = parse(code, self.digits)?;
+        let mut found = Choice::from(0);
+        let mut matched = 0u64;
+        for counter in first..=last {
+            let hit = self.truncate(counter).ct_eq(&code) & !found;
+            matched |= counter & 0u64.wrapping_sub(hit.unwrap_u8() as u64);
+            found |= hit;
+        }
+        if bool::from(found) {
+            Some(matched)
+        } else {
+            None
+        }
+    }
+
+    /// Dynamic truncation of RFC 4226 section 5.3
+    fn truncate(&self, counter: u64) -> u32 {
+        let mac = self.algorithm.hmac(&self.secret, &counter.to_be_bytes());
+        let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+        let code = u32::from_be_bytes([
+            mac[offset] & 0x7f,
+            mac[offset + 1],
+            mac[offset + 2],
+            mac[offset + 3],
+        ]);
+        (code as u64 % 10u64.pow(self.digits)) as u32
+    }
+}
+
+impl Drop for Hotp {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// Time based one-time passwords (RFC 6238)
+#[derive(Clone)]
+pub struct Totp {
+    hotp: Hotp,
+    step: u64,
+}
+
+impl Totp {
+    /// Codes of `digits` digits under the shared `secret` that change every `step`
+    /// seconds
+    pub fn new(
+        secret: &[u8],
+        algorithm: OtpAlgorithm,
+        digits: u32,
+        step: u64,
+    ) -> Result<Self, CryptoError> {
+        if step == 0 {
+            return Err(CryptoError::GeneralError(
+                "Step must be at least a second".to_string(),
+            ));
+        }
+        Ok(Totp {
+            hotp: Hotp::new(secret, algorithm, digits)?,
+            step,
+        })
+    }
+
+    pub fn algorithm(&self) -> OtpAlgorithm {
+        self.hotp.algorithm
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.hotp.digits
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// The time step of `time`, in seconds since the Unix epoch
+    pub fn time_step(&self, time: u64) -> u64 {
+        time / self.step
+    }
+
+    /// The code at `time`, in seconds since the Unix epoch
+    pub fn generate(&self, time: u64) -> String {
+        self.hotp.generate(self.time_step(time))
+    }
+
+    /// The code now
+    pub fn generate_now(&self) -> Result<String, CryptoError> {
+        Ok(self.generate(now()?))
+    }
+
+    /// Check `code` against the time steps up to `skew` steps before and after that of
+    /// `time`. Returns the time step that matched, which the caller must not accept
+    /// again
+    pub fn verify(&self, code: &str, time: u64, skew: u64) -> Option<u64> {
+        let current = self.time_step(time);
+        self.hotp.verify_range(
+            code,
+            current.saturating_sub(skew),
+            current.saturating_add(skew),
+        )
+    }
+
+    /// Check `code` against the time steps around now
+    pub fn verify_now(&self, code: &str, skew: u64) -> Result<Option<u64>, CryptoError> {
+        Ok(self.verify(code, now()?, skew))
+    }
+}
+
+fn now() -> Result<u64, CryptoError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .map_err(|_| CryptoError::GeneralError("System time is before 1970".to_string()))
+}
+
+/// The value of a code of exactly `digits` decimal digits
+fn parse(code: &str, digits: u32) -> Option<u32> {
+    if code.len() != digits as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    code.parse().ok()
+}
+
+/// HMAC-SHA1 (RFC 2104), as `sha1_smol` doesn't implement the digest traits
+fn hmac_sha1(key: &[u8], message: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..20].copy_from_slice(&Sha1::from(key).digest().bytes());
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut pad = [0u8; BLOCK_SIZE];
+    pad.iter_mut()
+        .zip(block.iter())
+        .for_each(|(p, k)| *p = k ^ 0x36);
+    let mut inner = Sha1::new();
+    inner.update(&pad);
+    inner.update(message);
+    let inner = inner.digest().bytes();
+
+    pad.iter_mut()
+        .zip(block.iter())
+        .for_each(|(p, k)| *p = k ^ 0x5c);
+    let mut outer = Sha1::new();
+    outer.update(&pad);
+    outer.update(&inner);
+    block.zeroize();
+    pad.zeroize();
+    outer.digest().bytes().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    // RFC 4226 appendix D
+    #[test]
+    fn hotp_vectors() {
+        let hotp = Hotp::new(SECRET, OtpAlgorithm::Sha1, 6).unwrap();
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in codes.iter().enumerate() {
+            assert_eq!(hotp.generate(counter as u64), *code);
+            assert_eq!(hotp.verify(code, counter as u64, 0), Some(counter as u64));
+        }
+        assert_eq!(hotp.verify("969429", 0, 2), None);
+        assert_eq!(hotp.verify("969429", 0, 3), Some(3));
+        assert_eq!(hotp.verify("969429", 4, 10), None);
+    }
+
+    // RFC 6238 appendix B
+    #[test]
+    fn totp_vectors() {
+        let sha1 = Totp::new(SECRET, OtpAlgorithm::Sha1, 8, DEFAULT_STEP).unwrap();
+        let sha256 = Totp::new(
+            b"12345678901234567890123456789012",
+            OtpAlgorithm::Sha256,
+            8,
+            DEFAULT_STEP,
+        )
+        .unwrap();
+        let sha512 = Totp::new(
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+            OtpAlgorithm::Sha512,
+            8,
+            DEFAULT_STEP,
+        )
+        .unwrap();
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, code1, code256, code512) in vectors.iter() {
+            assert_eq!(sha1.generate(*time), *code1);
+            assert_eq!(sha256.generate(*time), *code256);
+            assert_eq!(sha512.generate(*time), *code512);
+            assert_eq!(sha512.verify(code512, *time, 0), Some(time / 30));
+        }
+    }
+
+    #[test]
+    fn totp_drift() {
+        let totp = Totp::new(SECRET, OtpAlgorithm::Sha1, 6, DEFAULT_STEP).unwrap();
+        let code = totp.generate(1_000_000);
+        assert_eq!(totp.verify(&code, 1_000_000 + 30, 0), None);
+        assert_eq!(totp.verify(&code, 1_000_000 + 30, 1), Some(33_333));
+        assert_eq!(totp.verify(&code, 1_000_000 - 30, 1), Some(33_333));
+        assert_eq!(totp.verify(&code, 1_000_000 + 90, 2), None);
+        assert_eq!(totp.verify(&totp.generate(10), 10, 5), Some(0));
+
+        let now = totp.generate_now().unwrap();
+        assert!(totp.verify_now(&now, 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Hotp::new(SECRET, OtpAlgorithm::Sha1, 5).is_err());
+        assert!(Hotp::new(SECRET, OtpAlgorithm::Sha1, 11).is_err());
+        assert!(Hotp::new(&SECRET[..15], OtpAlgorithm::Sha1, 6).is_err());
+        assert!(Totp::new(SECRET, OtpAlgorithm::Sha1, 6, 0).is_err());
+
+        let hotp = Hotp::new(SECRET, OtpAlgorithm::Sha1, 6).unwrap();
+        for code in &["", "75522", "0755224", "+55224", " 55224", "75522a"] {
+            assert_eq!(hotp.verify(code, 0, 0), None);
+        }
+
+        // HMAC-SHA1 with a key longer than the block (RFC 2202 test case 6)
+        let mac = hmac_sha1(
+            &[0xaa; 80],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+        );
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "aa4ae5e15272d00e95705637ce8a3b55ed402112");
+    }
+}
@@ -0,0 +1,282 @@
+//! Single use recovery codes.
+//!
+//! `RecoveryCodes::generate` creates a batch of random codes to show to the user once
+//! and the record the server stores, which holds only a hash of each code and which
+//! ones were used. A code is redeemed once: `redeem` marks it used, after which it is
+//! rejected, and the caller must store the updated record before logging the user in.
+//!
+//! Codes are 16 characters of Crockford's base32, 80 bits, written as four groups of
+//! four. With that much entropy a leaked record can't be searched, so the hash is an
+//! HMAC-SHA256 under a random key of the batch instead of a password hash. Codes are
+//! read case insensitively and ignoring spaces and dashes, with `O` read as `0` and
+//! `I` and `L` as `1`. Every stored hash is compared in constant time.
+//!
+//! A record is encoded as
+//!
+//! ```text
+//! version (1) | key (32) | count (2, big endian) | count * (used (1) | hash (32))
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
+
+use CryptoError;
+
+/// The number of codes a batch usually has
+pub const DEFAULT_COUNT: usize = 10;
+/// The length of a code without separators
+pub const CODE_LENGTH: usize = 16;
+
+const KEY_SIZE: usize = 32;
+const HASH_SIZE: usize = 32;
+const VERSION: u8 = 1;
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The stored record of a batch of recovery codes
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecoveryCodes {
+    key: Vec<u8>,
+    hashes: Vec<Vec<u8>>,
+    used: Vec<bool>,
+}
+
+impl RecoveryCodes {
+    /// A batch of `count` new codes, formatted for display, and their record
+    pub fn generate(count: usize) -> Result<(Vec<String>, Self), CryptoError> {
+        if count == 0 || count > u16::MAX as usize {
+            return Err(CryptoError::GeneralError(
+                "Invalid number of recovery codes".to_string(),
+            ));
+        }
+        let mut key = vec![0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        let mut codes = Vec::with_capacity(count);
+        let mut hashes = Vec::with_capacity(count);
+        let mut random = [0u8; CODE_LENGTH];
+        for _ in 0..count {
+            OsRng.fill_bytes(&mut random);
+            let mut code: Vec<u8> = random.iter().map(|b| ALPHABET[(b & 31) as usize]).collect();
+            hashes.push(hash(&key, &code));
+            codes.push(
+                code.chunks(4)
+                    .map(|group| String::from_utf8_lossy(group).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("-"),
+            );
+            code.zeroize();
+        }
+        random.zeroize();
+        Ok((
+            codes,
+            RecoveryCodes {
+                key,
+                hashes,
+                used: vec![false; count],
+            },
+        ))
+    }
+
+    /// The number of codes in the batch
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// The number of codes that haven't been used
+    pub fn remaining(&self) -> usize {
+        self.used.iter().filter(|u| !**u).count()
+    }
+
+    /// Whether the code at `index` of the batch was used
+    pub fn is_used(&self, index: usize) -> bool {
+        self.used.get(index).cloned().unwrap_or(false)
+    }
+
+    /// The index of `code` in the batch if it is valid and unused
+    pub fn verify(&self, code: &str) -> Option<usize> {
+        match self.find(code) {
+            Some(index) if !self.used[index] => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Check `code` and mark it used. Returns its index, or an error if it isn't in the
+    /// batch or was used before, which may mean the codes have leaked
+    pub fn redeem(&mut self, code: &str) -> Result<usize, CryptoError> {
+        match self.find(code) {
+            None => Err(CryptoError::GeneralError(
+                "Invalid recovery code".to_string(),
+            )),
+            Some(index) if self.used[index] => Err(CryptoError::GeneralError(
+                "Recovery code was already used".to_string(),
+            )),
+            Some(index) => {
+                self.used[index] = true;
+                Ok(index)
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(3 + KEY_SIZE + self.len() * (1 + HASH_SIZE));
+        output.push(VERSION);
+        output.extend_from_slice(&self.key);
+        output.extend_from_slice(&(self.len() as u16).to_be_bytes());
+        for (hash, used) in self.hashes.iter().zip(self.used.iter()) {
+            output.push(*used as u8);
+            output.extend_from_slice(hash);
+        }
+        output
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() < 3 + KEY_SIZE || data[0] != VERSION {
+            return Err(CryptoError::ParseError(
+                "Invalid recovery code record".to_string(),
+            ));
+        }
+        let count = u16::from_be_bytes([data[1 + KEY_SIZE], data[2 + KEY_SIZE]]) as usize;
+        let entries = &data[3 + KEY_SIZE..];
+        if entries.len() != count * (1 + HASH_SIZE) {
+            return Err(CryptoError::ParseError(
+                "Invalid recovery code record length".to_string(),
+            ));
+        }
+        let mut hashes = Vec::with_capacity(count);
+        let mut used = Vec::with_capacity(count);
+        for entry in entries.chunks(1 + HASH_SIZE) {
+            match entry[0] {
+                0 => used.push(false),
+                1 => used.push(true),
+                _ => {
+                    return Err(CryptoError::ParseError(
+                        "Invalid recovery code usage flag".to_string(),
+                    ))
+                }
+            }
+            hashes.push(entry[1..].to_vec());
+        }
+        Ok(RecoveryCodes {
+            key: data[1..1 + KEY_SIZE].to_vec(),
+            hashes,
+            used,
+        })
+    }
+
+    /// The index of `code` in the batch, used or not, comparing every hash
+    fn find(&self, code: &str) -> Option<usize> {
+        let mut code = normalize(code)?;
+        let candidate = hash(&self.key, &code);
+        code.zeroize();
+        let mut found = Choice::from(0);
+        let mut matched = 0usize;
+        for (index, stored) in self.hashes.iter().enumerate() {
+            let hit = stored.ct_eq(&candidate) & !found;
+            matched |= index & 0usize.wrapping_sub(hit.unwrap_u8() as usize);
+            found |= hit;
+        }
+        if bool::from(found) {
+            Some(matched)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for RecoveryCodes {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// The canonical characters of a code as typed, if it has the right length
+fn normalize(code: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(CODE_LENGTH);
+    for c in code.bytes() {
+        let c = match c.to_ascii_uppercase() {
+            b' ' | b'-' => continue,
+            b'O' => b'0',
+            b'I' | b'L' => b'1',
+            c if ALPHABET.contains(&c) => c,
+            _ => return None,
+        };
+        output.push(c);
+    }
+    if output.len() == CODE_LENGTH {
+        Some(output)
+    } else {
+        output.zeroize();
+        None
+    }
+}
+
+fn hash(key: &[u8], code: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any size");
+    mac.input(code);
+    mac.result().code().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redeem() {
+        let (codes, mut record) = RecoveryCodes::generate(DEFAULT_COUNT).unwrap();
+        assert_eq!(codes.len(), DEFAULT_COUNT);
+        assert_eq!(record.len(), DEFAULT_COUNT);
+        assert_eq!(codes[0].len(), CODE_LENGTH + 3);
+        assert!(codes[0].bytes().all(|c| c == b'-' || ALPHABET.contains(&c)));
+
+        assert_eq!(record.verify(&codes[3]), Some(3));
+        assert_eq!(record.redeem(&codes[3]).unwrap(), 3);
+        assert!(record.is_used(3));
+        assert_eq!(record.remaining(), DEFAULT_COUNT - 1);
+        assert_eq!(record.verify(&codes[3]), None);
+        assert!(record.redeem(&codes[3]).is_err());
+
+        // Typing variations
+        let typed = codes[5].to_lowercase().replace('-', " ").replace('0', "o");
+        assert_eq!(record.redeem(&typed).unwrap(), 5);
+
+        let (other, _) = RecoveryCodes::generate(1).unwrap();
+        assert!(record.redeem(&other[0]).is_err());
+        assert!(record.redeem("").is_err());
+        assert!(record.redeem(&codes[0][..18]).is_err());
+        assert!(record.redeem(&format!("{}U", &codes[0][..18])).is_err());
+        assert_eq!(record.remaining(), DEFAULT_COUNT - 2);
+    }
+
+    #[test]
+    fn encoding() {
+        let (codes, mut record) = RecoveryCodes::generate(4).unwrap();
+        record.redeem(&codes[1]).unwrap();
+        let bytes = record.to_bytes();
+        assert_eq!(bytes.len(), 3 + KEY_SIZE + 4 * (1 + HASH_SIZE));
+
+        let mut restored = RecoveryCodes::from_bytes(&bytes).unwrap();
+        assert_eq!(restored, record);
+        assert!(restored.redeem(&codes[1]).is_err());
+        assert_eq!(restored.redeem(&codes[2]).unwrap(), 2);
+
+        assert!(RecoveryCodes::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut bad = bytes.clone();
+        bad[0] = 2;
+        assert!(RecoveryCodes::from_bytes(&bad).is_err());
+        let mut bad = bytes;
+        bad[3 + KEY_SIZE] = 2;
+        assert!(RecoveryCodes::from_bytes(&bad).is_err());
+        assert!(RecoveryCodes::generate(0).is_err());
+    }
+}
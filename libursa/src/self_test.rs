@@ -0,0 +1,479 @@
+//! Power-on self-tests.
+//!
+//! Certification regimes such as FIPS 140-3 expect a module to check its algorithms
+//! before first use, and a miscompiled or mislinked backend is caught the same way.
+//! [`self_test`] runs a known-answer test for every algorithm enabled by the crate's
+//! features, then health tests over a sample of the operating system's random number
+//! generator, and returns the first failure. Call it once at startup and refuse to
+//! continue if it fails.
+//!
+//! The known answers come from the standards defining each algorithm: RFC 8439 for
+//! ChaCha20-Poly1305, the XChaCha20-Poly1305 draft, the GCM specification, RFC 8032
+//! for Ed25519 and RFC 7748 for X25519. AES-CBC-HMAC is this crate's own composition,
+//! its answer was computed with an independent implementation. Each AEAD answer is
+//! also decrypted, and decrypted again with a flipped tag bit, which must fail.
+//!
+//! The health tests are the repetition count and adaptive proportion tests of
+//! SP 800-90B section 4.4, on byte samples with a claimed min-entropy of 8 bits and a
+//! false positive probability of 2^-40 per sample. The OS generator is a conditioned
+//! source, not raw noise, so these only catch catastrophic failures like stuck or
+//! repeating output; they say nothing about its statistical quality. [`HealthTests`]
+//! can run the same tests continuously over every byte drawn.
+
+#[cfg(feature = "rand")]
+use rand::{rngs::OsRng, RngCore};
+
+use CryptoError;
+
+/// Samples a value may repeat in a row before the repetition count test fails,
+/// `1 + ceil(40 / 8)`
+pub const REPETITION_CUTOFF: usize = 6;
+/// Samples in each window of the adaptive proportion test
+pub const PROPORTION_WINDOW: usize = 512;
+/// Occurrences of a window's first sample that fail the adaptive proportion test,
+/// `1 + CRITBINOM(512, 2^-8, 1 - 2^-40)`
+pub const PROPORTION_CUTOFF: usize = 19;
+/// Samples drawn by [`rng_health_test`], the minimum SP 800-90B asks for at startup
+pub const STARTUP_SAMPLES: usize = 1024;
+
+/// Run every known-answer test, then the startup health tests on `OsRng`
+pub fn self_test() -> Result<(), CryptoError> {
+    known_answer_tests()?;
+    #[cfg(feature = "rand")]
+    rng_health_test(&mut OsRng)?;
+    Ok(())
+}
+
+/// Run the known-answer test of each enabled algorithm
+pub fn known_answer_tests() -> Result<(), CryptoError> {
+    #[cfg(feature = "sha2")]
+    sha2_kat()?;
+    #[cfg(feature = "sha3")]
+    sha3_kat()?;
+    #[cfg(feature = "blake2")]
+    blake2_kat()?;
+    #[cfg(any(feature = "aescbc", feature = "aescbc_native"))]
+    aescbc_kat()?;
+    #[cfg(any(feature = "aesgcm", feature = "aesgcm_native"))]
+    aesgcm_kat()?;
+    #[cfg(any(feature = "chacha20poly1305", feature = "chacha20poly1305_native"))]
+    chacha20poly1305_kat()?;
+    #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+    ed25519_kat()?;
+    #[cfg(any(feature = "x25519", feature = "x25519_asm"))]
+    x25519_kat()?;
+    Ok(())
+}
+
+/// Feed [`STARTUP_SAMPLES`] bytes from `rng` through the health tests
+#[cfg(feature = "rand")]
+pub fn rng_health_test<R: RngCore>(rng: &mut R) -> Result<(), CryptoError> {
+    let mut samples = [0u8; STARTUP_SAMPLES];
+    rng.fill_bytes(&mut samples);
+    let mut tests = HealthTests::new();
+    samples.iter().try_for_each(|s| tests.feed(*s))
+}
+
+/// The SP 800-90B repetition count and adaptive proportion tests over a stream of
+/// byte samples. Once a test fails every later sample is refused too.
+#[derive(Clone, Debug, Default)]
+pub struct HealthTests {
+    repeated: u8,
+    repetitions: usize,
+    window_first: u8,
+    window_count: usize,
+    window_index: usize,
+    failed: bool,
+}
+
+impl HealthTests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the next sample
+    pub fn feed(&mut self, sample: u8) -> Result<(), CryptoError> {
+        if self.failed {
+            return Err(health_error("source has already failed"));
+        }
+
+        if self.repetitions > 0 && sample == self.repeated {
+            self.repetitions += 1;
+        } else {
+            self.repeated = sample;
+            self.repetitions = 1;
+        }
+
+        if self.window_index == 0 {
+            self.window_first = sample;
+            self.window_count = 1;
+        } else if sample == self.window_first {
+            self.window_count += 1;
+        }
+        self.window_index = (self.window_index + 1) % PROPORTION_WINDOW;
+
+        if self.repetitions >= REPETITION_CUTOFF {
+            self.failed = true;
+            return Err(health_error("repetition count test failed"));
+        }
+        if self.window_count >= PROPORTION_CUTOFF {
+            self.failed = true;
+            return Err(health_error("adaptive proportion test failed"));
+        }
+        Ok(())
+    }
+
+    /// Whether a test has failed
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+}
+
+fn health_error(reason: &str) -> CryptoError {
+    CryptoError::GeneralError(format!("RNG health test: {}", reason))
+}
+
+// The known-answer helpers are unused when no algorithm is enabled
+#[allow(dead_code)]
+fn kat_error(algorithm: &str) -> CryptoError {
+    CryptoError::GeneralError(format!("Known-answer test failed for {}", algorithm))
+}
+
+#[allow(dead_code)]
+fn check(algorithm: &str, output: &[u8], expected: &str) -> Result<(), CryptoError> {
+    if output == unhex(expected).as_slice() {
+        Ok(())
+    } else {
+        Err(kat_error(algorithm))
+    }
+}
+
+#[allow(dead_code)]
+fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("known answers are hex"))
+        .collect()
+}
+
+#[cfg(feature = "sha2")]
+fn sha2_kat() -> Result<(), CryptoError> {
+    use sha2::{Digest, Sha256, Sha512};
+
+    check(
+        "SHA-256",
+        &Sha256::digest(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    )?;
+    check(
+        "SHA-512",
+        &Sha512::digest(b"abc"),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+    )
+}
+
+#[cfg(feature = "sha3")]
+fn sha3_kat() -> Result<(), CryptoError> {
+    use sha3::{Digest, Sha3_256, Sha3_512};
+
+    check(
+        "SHA3-256",
+        &Sha3_256::digest(b"abc"),
+        "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+    )?;
+    check(
+        "SHA3-512",
+        &Sha3_512::digest(b"abc"),
+        "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+         10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+    )
+}
+
+#[cfg(feature = "blake2")]
+fn blake2_kat() -> Result<(), CryptoError> {
+    use blake2::{Blake2b, Blake2s, Digest};
+
+    check(
+        "BLAKE2b",
+        &Blake2b::digest(b"abc"),
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+    )?;
+    check(
+        "BLAKE2s",
+        &Blake2s::digest(b"abc"),
+        "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+    )
+}
+
+/// Check `E` encrypts to `expected`, decrypts it back and rejects a flipped tag bit
+#[cfg(any(
+    feature = "aescbc",
+    feature = "aescbc_native",
+    feature = "aesgcm",
+    feature = "aesgcm_native",
+    feature = "chacha20poly1305",
+    feature = "chacha20poly1305_native"
+))]
+fn aead_kat<E: aead::Aead + aead::NewAead>(
+    algorithm: &str,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    expected: &str,
+) -> Result<(), CryptoError> {
+    use aead::{generic_array::GenericArray, Payload};
+
+    let cipher = E::new(GenericArray::from_slice(key));
+    let nonce = GenericArray::from_slice(nonce);
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| kat_error(algorithm))?;
+    check(algorithm, &ciphertext, expected)?;
+
+    match cipher.decrypt(
+        nonce,
+        Payload {
+            msg: &ciphertext,
+            aad,
+        },
+    ) {
+        Ok(ref p) if p.as_slice() == plaintext => {}
+        _ => return Err(kat_error(algorithm)),
+    }
+    let mut tampered = ciphertext;
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    match cipher.decrypt(
+        nonce,
+        Payload {
+            msg: &tampered,
+            aad,
+        },
+    ) {
+        Err(_) => Ok(()),
+        Ok(_) => Err(kat_error(algorithm)),
+    }
+}
+
+#[cfg(any(feature = "aescbc", feature = "aescbc_native"))]
+fn aescbc_kat() -> Result<(), CryptoError> {
+    use encryption::symm::prelude::Aes128CbcHmac256;
+
+    let key = (0u8..32).collect::<Vec<u8>>();
+    let nonce = (0u8..16).collect::<Vec<u8>>();
+    aead_kat::<Aes128CbcHmac256>(
+        "AES-128-CBC-HMAC-SHA256",
+        &key,
+        &nonce,
+        b"self test",
+        b"The quick brown fox jumps over the lazy dog",
+        "d5f239401cf82914474428e33d8b1c9e380a8f51f8800d23a97775715faf4ba9\
+         184136f0641c6aa50af279b95be10423\
+         3ae79d8eb5b7fb3477048ef078123e919cdde9b65f9e4ccdd8b6784378145244",
+    )
+}
+
+#[cfg(any(feature = "aesgcm", feature = "aesgcm_native"))]
+fn aesgcm_kat() -> Result<(), CryptoError> {
+    use encryption::symm::prelude::{Aes128Gcm, Aes256Gcm};
+
+    // Test cases 2 and 14 of the GCM specification
+    aead_kat::<Aes128Gcm>(
+        "AES-128-GCM",
+        &[0u8; 16],
+        &[0u8; 12],
+        &[],
+        &[0u8; 16],
+        "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf",
+    )?;
+    aead_kat::<Aes256Gcm>(
+        "AES-256-GCM",
+        &[0u8; 32],
+        &[0u8; 12],
+        &[],
+        &[0u8; 16],
+        "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919",
+    )
+}
+
+#[cfg(any(feature = "chacha20poly1305", feature = "chacha20poly1305_native"))]
+fn chacha20poly1305_kat() -> Result<(), CryptoError> {
+    use encryption::symm::prelude::{ChaCha20Poly1305, XChaCha20Poly1305};
+
+    let key = (0x80u8..0xa0).collect::<Vec<u8>>();
+    let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: \
+        If I could offer you only one tip for the future, sunscreen would be it.";
+    // RFC 8439 section 2.8.2
+    aead_kat::<ChaCha20Poly1305>(
+        "ChaCha20-Poly1305",
+        &key,
+        &unhex("070000004041424344454647"),
+        &aad,
+        plaintext,
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+         3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+         92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+         3ff4def08e4b7a9de576d26586cec64b6116\
+         1ae10b594f09e26a7e902ecbd0600691",
+    )?;
+    // draft-irtf-cfrg-xchacha section A.3.1
+    aead_kat::<XChaCha20Poly1305>(
+        "XChaCha20-Poly1305",
+        &key,
+        &(0x40u8..0x58).collect::<Vec<u8>>(),
+        &aad,
+        plaintext,
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+         731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+         2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+         21f9664c97637da9768812f615c68b13b52e\
+         c0875924c1c7987947deafd8780acf49",
+    )
+}
+
+/// RFC 8032 section 7.1, test 1
+#[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+fn ed25519_kat() -> Result<(), CryptoError> {
+    use keys::{KeyGenOption, PrivateKey};
+    use signatures::{ed25519::Ed25519Sha512, SignatureScheme};
+
+    let scheme = Ed25519Sha512::new();
+    let secret = unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+    let (pk, sk) = scheme.keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(
+        [
+            secret,
+            unhex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"),
+        ]
+        .concat(),
+    ))))?;
+    let signature = scheme.sign(b"", &sk)?;
+    check(
+        "Ed25519",
+        &signature,
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+         5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    )?;
+    // A bad signature is an error rather than `Ok(false)`
+    match (
+        scheme.verify(b"", &signature, &pk),
+        scheme.verify(b"\0", &signature, &pk),
+    ) {
+        (Ok(true), Ok(false)) | (Ok(true), Err(_)) => Ok(()),
+        _ => Err(kat_error("Ed25519")),
+    }
+}
+
+/// RFC 7748 section 6.1, hashed with SHA-256 as `X25519Sha256` does
+#[cfg(any(feature = "x25519", feature = "x25519_asm"))]
+fn x25519_kat() -> Result<(), CryptoError> {
+    use kex::{x25519::X25519Sha256, KeyExchangeScheme};
+    use keys::{KeyGenOption, PrivateKey};
+
+    let scheme = X25519Sha256::new();
+    let (alice_pk, alice_sk) = scheme.keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(
+        unhex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"),
+    ))))?;
+    let (bob_pk, bob_sk) = scheme.keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(unhex(
+        "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+    )))))?;
+    check(
+        "X25519",
+        &alice_pk[..],
+        "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a",
+    )?;
+    check(
+        "X25519",
+        &bob_pk[..],
+        "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f",
+    )?;
+    let shared = "dead45a1d43d6902aa9240b43c0d75a0b5fc750660590d6d45461cbfc4010684";
+    check(
+        "X25519",
+        &scheme.compute_shared_secret(&alice_sk, &bob_pk)?[..],
+        shared,
+    )?;
+    check(
+        "X25519",
+        &scheme.compute_shared_secret(&bob_sk, &alice_pk)?[..],
+        shared,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_answers_pass() {
+        known_answer_tests().unwrap();
+        self_test().unwrap();
+    }
+
+    #[test]
+    fn check_catches_wrong_answer() {
+        assert!(check("test", &[0xab, 0xcd], "abcd").is_ok());
+        assert!(check("test", &[0xab, 0xce], "abcd").is_err());
+        assert!(check("test", &[0xab], "abcd").is_err());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn rng_health() {
+        use rand::rngs::mock::StepRng;
+
+        rng_health_test(&mut OsRng).unwrap();
+        // A stuck generator fails
+        assert!(rng_health_test(&mut StepRng::new(0, 0)).is_err());
+    }
+
+    #[test]
+    fn repetition_count() {
+        let mut tests = HealthTests::new();
+        for _ in 0..REPETITION_CUTOFF - 1 {
+            tests.feed(7).unwrap();
+        }
+        assert!(tests.feed(7).is_err());
+        assert!(tests.has_failed());
+        // A failure sticks
+        assert!(tests.feed(8).is_err());
+    }
+
+    #[test]
+    fn adaptive_proportion() {
+        // Every value once per 256 samples passes both tests
+        let mut tests = HealthTests::new();
+        for i in 0..4 * PROPORTION_WINDOW {
+            tests.feed(i as u8).unwrap();
+        }
+
+        // Half the samples the same, never twice in a row
+        let mut tests = HealthTests::new();
+        let res = (0..PROPORTION_WINDOW)
+            .map(|i| if i % 2 == 0 { 0 } else { i as u8 })
+            .try_for_each(|s| tests.feed(s));
+        assert!(res.is_err());
+        assert!(tests.has_failed());
+
+        // Only the window's first sample is counted
+        let mut tests = HealthTests::new();
+        tests.feed(1).unwrap();
+        for i in 1..PROPORTION_WINDOW {
+            let s = if i % 2 == 0 {
+                0
+            } else {
+                (i / 2 % 254 + 2) as u8
+            };
+            tests.feed(s).unwrap();
+        }
+    }
+}
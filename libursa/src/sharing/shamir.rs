@@ -9,7 +9,7 @@
 //! Future work would be to use pedersen commitments or reed-solomon
 //! codes to check for corrupted shares.
 
-use bn::BigNumber;
+use bn::{batch_inverse, BigNumber};
 use std::{cmp::Ordering, collections::BTreeSet};
 
 use {CryptoError, CryptoResult};
@@ -76,16 +76,6 @@ impl Element {
         })
     }
 
-    /// Compute `self.value` * `b.value^-1` mod n
-    pub fn div(&self, b: &Self) -> CryptoResult<Self> {
-        self.validate(b)?;
-        let value = self.value.mod_div(&b.value, &b.modulus, None)?;
-        Ok(Self {
-            modulus: self.modulus.try_clone()?,
-            value,
-        })
-    }
-
     /// Computes 0 - `self.value`
     pub fn neg(&self) -> CryptoResult<Self> {
         let zero = BigNumber::new()?;
@@ -183,30 +173,44 @@ impl Polynomial {
         debug_assert_eq!(x_coordinates.len(), y_coordinates.len());
 
         let limit = x_coordinates.len();
-        // Initialize to zero
-        let mut result = Element {
-            modulus: x_coordinates[0].modulus.try_clone()?,
-            value: BigNumber::new()?,
+        let modulus = &x_coordinates[0].modulus;
+        let one = Element {
+            modulus: modulus.try_clone()?,
+            value: BigNumber::from_u32(1)?,
         };
-
+        // The basis polynomial at zero is the product of -x_m / (x_j - x_m), so
+        // multiply out the numerators and denominators and invert these all at once
+        let mut numerators = Vec::with_capacity(limit);
+        let mut denominators = Vec::with_capacity(limit);
         for i in 0..limit {
-            let mut basis = Element {
-                modulus: x_coordinates[0].modulus.try_clone()?,
-                value: BigNumber::from_u32(1)?,
-            };
+            let mut num = one.try_clone()?;
+            let mut denom = one.try_clone()?;
             for j in 0..limit {
                 if i == j {
                     continue;
                 }
 
                 // -x_m
-                let num = x_coordinates[j].neg()?;
+                num = num.mul(&x_coordinates[j].neg()?)?;
                 // x_j - x_m
-                let denom = x_coordinates[i].sub(&x_coordinates[j])?;
-                // -x_m / (x_j - x_m) * ...
-                basis = basis.mul(&num.div(&denom)?)?;
+                denom = denom.mul(&x_coordinates[i].sub(&x_coordinates[j])?)?;
             }
-            let group = y_coordinates[i].mul(&basis)?;
+            numerators.push(num);
+            denominators.push(denom.value);
+        }
+        let inverses = batch_inverse(&denominators, modulus, None)?;
+
+        // Initialize to zero
+        let mut result = Element {
+            modulus: modulus.try_clone()?,
+            value: BigNumber::new()?,
+        };
+        for ((num, inverse), y) in numerators.iter().zip(inverses).zip(y_coordinates.iter()) {
+            let basis = num.mul(&Element {
+                modulus: modulus.try_clone()?,
+                value: inverse,
+            })?;
+            let group = y.mul(&basis)?;
             result = result.add(&group)?;
         }
         Ok(result)
@@ -0,0 +1,333 @@
+//! Offline attenuable bearer tokens in the style of Biscuit.
+//!
+//! A token is a chain of blocks. The issuer signs the first block, the authority
+//! block, with its root key. Every block carries the public half of a fresh
+//! Ed25519 key and the token carries the secret half of the last one as its proof.
+//! Whoever holds a token can therefore append a block with more caveats and sign it
+//! with that key, without talking to the issuer, but can't remove or change the
+//! blocks before it. Sealing a token replaces the proof with a signature so no more
+//! blocks can be added.
+//!
+//! The contents of the blocks are opaque here. The verifier is expected to check
+//! that every caveat in every block holds, so appending a block can only ever
+//! restrict what the token allows.
+
+use ed25519_dalek::{PublicKey as PK, SecretKey as SK};
+use zeroize::Zeroize;
+
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+const VERSION: u8 = 1;
+const PUBLIC_KEY_SIZE: usize = 32;
+const PRIVATE_KEY_SIZE: usize = 64;
+const SIGNATURE_SIZE: usize = 64;
+const BLOCK_CONTEXT: &[u8] = b"ursa attenuable token block";
+const SEAL_CONTEXT: &[u8] = b"ursa attenuable token seal";
+
+/// One link in the chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// The caveats, in whatever encoding the application uses
+    pub data: Vec<u8>,
+    /// The key that signs the next block or the seal
+    pub next_key: PublicKey,
+    /// Made by the root key for the authority block and by the previous block's
+    /// `next_key` for the others
+    pub signature: Vec<u8>,
+}
+
+/// What lets the holder of a token extend it, or shows that it can't be extended
+#[derive(Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// The secret key for the last block's `next_key`
+    NextSecret(PrivateKey),
+    /// A signature by the last block's `next_key` over that block
+    Sealed(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AttenuableToken {
+    pub blocks: Vec<Block>,
+    pub proof: Proof,
+}
+
+impl AttenuableToken {
+    /// Issue a token whose authority block holds `authority`
+    pub fn new(root_key: &PrivateKey, authority: &[u8]) -> Result<Self, CryptoError> {
+        let (block, next_secret) = sign_block(root_key, authority, &[])?;
+        Ok(AttenuableToken {
+            blocks: vec![block],
+            proof: Proof::NextSecret(next_secret),
+        })
+    }
+
+    /// Append a block, which only needs the token itself
+    pub fn attenuate(&self, caveats: &[u8]) -> Result<Self, CryptoError> {
+        let secret = match self.proof {
+            Proof::NextSecret(ref secret) => secret,
+            Proof::Sealed(_) => {
+                return Err(CryptoError::SigningError(
+                    "Sealed tokens can't be attenuated".to_string(),
+                ))
+            }
+        };
+        let previous = self.last_block();
+        let (block, next_secret) = sign_block(secret, caveats, &previous.signature)?;
+        let mut blocks = self.blocks.clone();
+        blocks.push(block);
+        Ok(AttenuableToken {
+            blocks,
+            proof: Proof::NextSecret(next_secret),
+        })
+    }
+
+    /// Stop the token from being attenuated any further
+    pub fn seal(&self) -> Result<Self, CryptoError> {
+        match self.proof {
+            Proof::NextSecret(ref secret) => {
+                let signature =
+                    Ed25519Sha512::new().sign(&seal_message(self.last_block()), secret)?;
+                Ok(AttenuableToken {
+                    blocks: self.blocks.clone(),
+                    proof: Proof::Sealed(signature),
+                })
+            }
+            Proof::Sealed(_) => Err(CryptoError::SigningError(
+                "Token is already sealed".to_string(),
+            )),
+        }
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        match self.proof {
+            Proof::Sealed(_) => true,
+            Proof::NextSecret(_) => false,
+        }
+    }
+
+    /// Check the chain of signatures from `root_key` to the proof. The caveats in
+    /// the blocks still have to be checked by the caller.
+    pub fn verify(&self, root_key: &PublicKey) -> Result<bool, CryptoError> {
+        if self.blocks.is_empty() {
+            return Ok(false);
+        }
+        let scheme = Ed25519Sha512::new();
+        let mut key = root_key;
+        let mut previous_signature: &[u8] = &[];
+        for block in &self.blocks {
+            let message = block_message(&block.data, &block.next_key, previous_signature);
+            if !scheme
+                .verify(&message, &block.signature, key)
+                .unwrap_or(false)
+            {
+                return Ok(false);
+            }
+            key = &block.next_key;
+            previous_signature = &block.signature;
+        }
+        match self.proof {
+            Proof::NextSecret(ref secret) => Ok(&public_key_of(secret)? == key),
+            Proof::Sealed(ref signature) => Ok(scheme
+                .verify(&seal_message(self.last_block()), signature, key)
+                .unwrap_or(false)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        out.extend_from_slice(&(self.blocks.len() as u32).to_le_bytes());
+        for block in &self.blocks {
+            out.extend_from_slice(&(block.data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&block.data);
+            out.extend_from_slice(&block.next_key[..]);
+            out.extend_from_slice(&block.signature);
+        }
+        match self.proof {
+            Proof::NextSecret(ref secret) => {
+                out.push(0);
+                out.extend_from_slice(&secret[..]);
+            }
+            Proof::Sealed(ref signature) => {
+                out.push(1);
+                out.extend_from_slice(signature);
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(bytes);
+        if reader.take(1)? != [VERSION] {
+            return Err(parse_error("Unsupported attenuable token version"));
+        }
+        let count = reader.u32()?;
+        if count == 0 {
+            return Err(parse_error("Attenuable token has no blocks"));
+        }
+        let mut blocks = Vec::new();
+        for _ in 0..count {
+            let length = reader.u32()? as usize;
+            blocks.push(Block {
+                data: reader.take(length)?.to_vec(),
+                next_key: PublicKey(reader.take(PUBLIC_KEY_SIZE)?.to_vec()),
+                signature: reader.take(SIGNATURE_SIZE)?.to_vec(),
+            });
+        }
+        let proof = match reader.take(1)?[0] {
+            0 => Proof::NextSecret(PrivateKey(reader.take(PRIVATE_KEY_SIZE)?.to_vec())),
+            1 => Proof::Sealed(reader.take(SIGNATURE_SIZE)?.to_vec()),
+            _ => return Err(parse_error("Invalid attenuable token proof")),
+        };
+        if !reader.0.is_empty() {
+            return Err(parse_error("Trailing bytes after attenuable token"));
+        }
+        Ok(AttenuableToken { blocks, proof })
+    }
+
+    fn last_block(&self) -> &Block {
+        // Tokens made here and by `from_bytes` have at least the authority block
+        &self.blocks[self.blocks.len() - 1]
+    }
+}
+
+/// Sign a new block with `key` and return it with the secret key for its `next_key`
+fn sign_block(
+    key: &PrivateKey,
+    data: &[u8],
+    previous_signature: &[u8],
+) -> Result<(Block, PrivateKey), CryptoError> {
+    let scheme = Ed25519Sha512::new();
+    let (next_key, next_secret) = scheme.keypair(None)?;
+    let signature = scheme.sign(&block_message(data, &next_key, previous_signature), key)?;
+    Ok((
+        Block {
+            data: data.to_vec(),
+            next_key,
+            signature,
+        },
+        next_secret,
+    ))
+}
+
+/// A block's signature also covers the previous block's, which ties every block to
+/// the one it was appended to
+fn block_message(data: &[u8], next_key: &PublicKey, previous_signature: &[u8]) -> Vec<u8> {
+    let mut message = BLOCK_CONTEXT.to_vec();
+    message.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    message.extend_from_slice(data);
+    message.extend_from_slice(&next_key[..]);
+    message.extend_from_slice(previous_signature);
+    message
+}
+
+fn seal_message(block: &Block) -> Vec<u8> {
+    let mut message = SEAL_CONTEXT.to_vec();
+    message.extend_from_slice(&block.signature);
+    message
+}
+
+/// Derive the public key from the seed rather than trusting the copy stored in
+/// the second half of the secret key
+fn public_key_of(secret: &PrivateKey) -> Result<PublicKey, CryptoError> {
+    if secret.len() != PRIVATE_KEY_SIZE {
+        return Err(parse_error("Invalid attenuable token proof"));
+    }
+    let mut seed = SK::from_bytes(&secret[..32]).map_err(|e| parse_error(&e.to_string()))?;
+    let public_key = PublicKey(PK::from(&seed).to_bytes().to_vec());
+    seed.zeroize();
+    Ok(public_key)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], CryptoError> {
+        if self.0.len() < length {
+            return Err(parse_error("Attenuable token is truncated"));
+        }
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, CryptoError> {
+        Ok(u32::from_le_bytes(*array_ref!(self.take(4)?, 0, 4)))
+    }
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn attenuate_and_seal() {
+        let (root_pk, root_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let token = AttenuableToken::new(&root_sk, b"user(\"alice\")").unwrap();
+        assert!(token.verify(&root_pk).unwrap());
+
+        let attenuated = token.attenuate(b"check if resource(\"file1\")").unwrap();
+        let attenuated = attenuated.attenuate(b"check if time < 2030").unwrap();
+        assert_eq!(attenuated.blocks.len(), 3);
+        assert_eq!(attenuated.blocks[0], token.blocks[0]);
+        assert!(attenuated.verify(&root_pk).unwrap());
+
+        let sealed = attenuated.seal().unwrap();
+        assert!(sealed.is_sealed());
+        assert!(sealed.verify(&root_pk).unwrap());
+        assert!(sealed.attenuate(b"more").is_err());
+        assert!(sealed.seal().is_err());
+
+        let bytes = sealed.to_bytes();
+        assert_eq!(AttenuableToken::from_bytes(&bytes).unwrap(), sealed);
+        let bytes = attenuated.to_bytes();
+        assert_eq!(AttenuableToken::from_bytes(&bytes).unwrap(), attenuated);
+        assert!(AttenuableToken::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let (other_pk, _) = Ed25519Sha512::new().keypair(None).unwrap();
+        assert!(!sealed.verify(&other_pk).unwrap());
+    }
+
+    #[test]
+    fn tampering() {
+        let (root_pk, root_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let token = AttenuableToken::new(&root_sk, b"admin")
+            .unwrap()
+            .attenuate(b"read only")
+            .unwrap();
+
+        // Dropping the caveat block and reusing its proof doesn't work
+        let truncated = AttenuableToken::from_bytes(&token.to_bytes()).unwrap();
+        let truncated = AttenuableToken {
+            blocks: truncated.blocks[..1].to_vec(),
+            proof: truncated.proof,
+        };
+        assert!(!truncated.verify(&root_pk).unwrap());
+
+        let mut changed = AttenuableToken::from_bytes(&token.to_bytes()).unwrap();
+        changed.blocks[1].data = b"read write".to_vec();
+        assert!(!changed.verify(&root_pk).unwrap());
+
+        // Neither does swapping in a block signed for a different token
+        let other = AttenuableToken::new(&root_sk, b"guest")
+            .unwrap()
+            .attenuate(b"read only")
+            .unwrap();
+        let mut spliced = AttenuableToken::from_bytes(&token.to_bytes()).unwrap();
+        spliced.blocks[1] = other.blocks[1].clone();
+        assert!(!spliced.verify(&root_pk).unwrap());
+
+        // A proof key whose stored public half matches but whose seed doesn't
+        let mut forged = AttenuableToken::from_bytes(&token.to_bytes()).unwrap();
+        let (_, mut secret) = Ed25519Sha512::new().keypair(None).unwrap();
+        secret.0[32..].copy_from_slice(&token.blocks[1].next_key[..]);
+        forged.proof = Proof::NextSecret(secret);
+        assert!(!forged.verify(&root_pk).unwrap());
+    }
+}
@@ -0,0 +1,166 @@
+//! Attestation that a key pair was generated inside approved hardware.
+//!
+//! A TPM, secure enclave or HSM that generates a key can produce evidence, such as a
+//! TPM quote or an enclave report, that it did so. The evidence has to commit to the
+//! public key so it can't be replayed for another key, which is done by placing the
+//! value from `attestation_binding` in the field the hardware signs over
+//! (the qualifying data of a TPM quote or the report data of an SGX report).
+//!
+//! This module doesn't parse any hardware formats itself. Generators implement
+//! `AttestedKeyGenerator` and relying parties register an `AttestationVerifier`
+//! for each evidence format they accept.
+
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+use CryptoError;
+
+const BINDING_DOMAIN: &[u8] = b"URSA_KEY_ATTESTATION_V1";
+
+/// Evidence produced by the hardware that generated a key pair
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    /// Identifies the evidence format, for example `"tpm2-quote"` or `"sgx-dcap"`
+    pub format: String,
+    /// The evidence as produced by the hardware
+    pub evidence: Vec<u8>,
+}
+
+/// Key generation inside hardware that can attest to the keys it creates
+pub trait AttestedKeyGenerator {
+    /// Generate a key pair and evidence that binds the public key and `nonce`.
+    /// `nonce` is chosen by the relying party to show the evidence is fresh.
+    fn keypair_attested(
+        &self,
+        options: Option<KeyGenOption>,
+        nonce: &[u8],
+    ) -> Result<(PublicKey, PrivateKey, Attestation), CryptoError>;
+}
+
+/// Checks the evidence for one attestation format
+pub trait AttestationVerifier {
+    /// The format this verifier accepts
+    fn format(&self) -> &str;
+
+    /// Return true iff `evidence` was produced by approved hardware and commits to `binding`
+    fn verify_evidence(&self, evidence: &[u8], binding: &[u8; 32]) -> Result<bool, CryptoError>;
+}
+
+/// The value attestation evidence must commit to for `public_key` and `nonce`
+pub fn attestation_binding(public_key: &PublicKey, nonce: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(BINDING_DOMAIN);
+    hasher.input((public_key.len() as u64).to_be_bytes());
+    hasher.input(&public_key[..]);
+    hasher.input((nonce.len() as u64).to_be_bytes());
+    hasher.input(nonce);
+    let mut binding = [0u8; 32];
+    binding.copy_from_slice(hasher.result().as_slice());
+    binding
+}
+
+/// Check `attestation` shows `public_key` was generated by approved hardware for `nonce`.
+///
+/// The verifier registered for the attestation's format is used. Returns an error if
+/// no verifier accepts the format and `Ok(false)` if the evidence doesn't verify.
+pub fn verify_attestation(
+    public_key: &PublicKey,
+    nonce: &[u8],
+    attestation: &Attestation,
+    verifiers: &[&dyn AttestationVerifier],
+) -> Result<bool, CryptoError> {
+    let verifier = verifiers
+        .iter()
+        .find(|v| v.format() == attestation.format)
+        .ok_or_else(|| {
+            CryptoError::NoSuchAlgorithm(format!(
+                "No verifier for attestation format {}",
+                attestation.format
+            ))
+        })?;
+    verifier.verify_evidence(
+        &attestation.evidence,
+        &attestation_binding(public_key, nonce),
+    )
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::super::SignatureScheme;
+    use super::*;
+
+    const FORMAT: &str = "test-device";
+
+    /// Stands in for a device that signs its reports with a key the relying party trusts
+    struct Device {
+        report_key: (PublicKey, PrivateKey),
+    }
+
+    impl AttestedKeyGenerator for Device {
+        fn keypair_attested(
+            &self,
+            options: Option<KeyGenOption>,
+            nonce: &[u8],
+        ) -> Result<(PublicKey, PrivateKey, Attestation), CryptoError> {
+            let scheme = Ed25519Sha512::new();
+            let (pk, sk) = scheme.keypair(options)?;
+            let evidence = scheme.sign(&attestation_binding(&pk, nonce), &self.report_key.1)?;
+            Ok((
+                pk,
+                sk,
+                Attestation {
+                    format: FORMAT.to_string(),
+                    evidence,
+                },
+            ))
+        }
+    }
+
+    struct DeviceVerifier {
+        report_key: PublicKey,
+    }
+
+    impl AttestationVerifier for DeviceVerifier {
+        fn format(&self) -> &str {
+            FORMAT
+        }
+
+        fn verify_evidence(
+            &self,
+            evidence: &[u8],
+            binding: &[u8; 32],
+        ) -> Result<bool, CryptoError> {
+            Ok(Ed25519Sha512::new()
+                .verify(binding, evidence, &self.report_key)
+                .unwrap_or(false))
+        }
+    }
+
+    #[test]
+    fn attested_keys_verify() {
+        let (report_pk, report_sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let device = Device {
+            report_key: (PublicKey(report_pk[..].to_vec()), report_sk),
+        };
+        let verifier = DeviceVerifier {
+            report_key: report_pk,
+        };
+        let verifiers: [&dyn AttestationVerifier; 1] = [&verifier];
+
+        let (pk, _sk, attestation) = device.keypair_attested(None, b"nonce 1").unwrap();
+        assert!(verify_attestation(&pk, b"nonce 1", &attestation, &verifiers).unwrap());
+
+        // Replayed for another nonce or key
+        assert!(!verify_attestation(&pk, b"nonce 2", &attestation, &verifiers).unwrap());
+        let (other, _, _) = device.keypair_attested(None, b"nonce 1").unwrap();
+        assert!(!verify_attestation(&other, b"nonce 1", &attestation, &verifiers).unwrap());
+
+        // Unknown formats are rejected
+        let unknown = Attestation {
+            format: "other-device".to_string(),
+            evidence: attestation.evidence.clone(),
+        };
+        assert!(verify_attestation(&pk, b"nonce 1", &unknown, &verifiers).is_err());
+    }
+}
@@ -7,8 +7,8 @@ use amcl_wrapper::{
     extension_field_gt::GT,
     field_elem::FieldElement,
     group_elem::GroupElement,
-    group_elem_g1::G1,
-    group_elem_g2::G2,
+    group_elem_g1::{G1Vector, G1},
+    group_elem_g2::{G2Vector, G2},
     types_g2::GroupG2_SIZE,
 };
 use keys::{KeyGenOption, PrivateKey as UrsaPrivateKey, PublicKey as UrsaPublicKey};
@@ -23,8 +23,57 @@ pub const PRIVATE_KEY_SIZE: usize = MODBYTES;
 /// instead of wrapping it as a private field
 pub type PrivateKey = FieldElement;
 
+/// Computes the multi-scalar multiplications and pairings of batch verification.
+/// Implement it to run them on a GPU, FPGA or another accelerator
+/// and pass it to `batch_verify_with_offload`.
+/// Everything passed in is public so implementations may be variable time.
+pub trait Offload: Send + Sync {
+    /// Return `scalars[0] * points[0] + scalars[1] * points[1] + ...`
+    fn multi_scalar_mul_g1(
+        &self,
+        points: &[G1],
+        scalars: &[FieldElement],
+    ) -> Result<G1, CryptoError>;
+    /// Return `scalars[0] * points[0] + scalars[1] * points[1] + ...`
+    fn multi_scalar_mul_g2(
+        &self,
+        points: &[G2],
+        scalars: &[FieldElement],
+    ) -> Result<G2, CryptoError>;
+    /// Is the product of the pairings of `pairs` the identity in GT
+    fn pairing_product_is_one(&self, pairs: Vec<(&G1, &G2)>) -> bool;
+}
+
+/// Computes everything on the CPU, this is what `batch_verify` uses
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CpuOffload;
+
+impl Offload for CpuOffload {
+    fn multi_scalar_mul_g1(
+        &self,
+        points: &[G1],
+        scalars: &[FieldElement],
+    ) -> Result<G1, CryptoError> {
+        G1Vector::multi_scalar_mul_var_time_without_precomputation(points.iter(), scalars.iter())
+            .map_err(|e| CryptoError::GeneralError(format!("{:?}", e)))
+    }
+
+    fn multi_scalar_mul_g2(
+        &self,
+        points: &[G2],
+        scalars: &[FieldElement],
+    ) -> Result<G2, CryptoError> {
+        G2Vector::multi_scalar_mul_var_time_without_precomputation(points.iter(), scalars.iter())
+            .map_err(|e| CryptoError::GeneralError(format!("{:?}", e)))
+    }
+
+    fn pairing_product_is_one(&self, pairs: Vec<(&G1, &G2)>) -> bool {
+        GT::ate_multi_pairing(pairs).is_one()
+    }
+}
+
 macro_rules! bls_impl {
-    ($pk_size:expr, $sig_size:expr, $pk_group:ident, $sig_group:ident, $ate_2_pairing_is_one:ident, $set_pairs:ident) => {
+    ($pk_size:expr, $sig_size:expr, $pk_group:ident, $sig_group:ident, $ate_2_pairing_is_one:ident, $set_pairs:ident, $sig_multi_scalar_mul:ident) => {
         pub const PUBLIC_KEY_SIZE: usize = $pk_size;
         pub const SIGNATURE_SIZE: usize = $sig_size;
 
@@ -75,6 +124,7 @@ macro_rules! bls_impl {
                         KeyGenOption::UseSeed(ref seed) => {
                             let salt = b"BLS-SIG-KEYGEN-SALT-";
                             let info = [0u8, PRIVATE_KEY_SIZE as u8]; // key_info || I2OSP(L, 2)
+                            let seed = seed.expose_secret();
                             let mut ikm = vec![0u8; seed.len() + 1];
                             ikm[..seed.len()].copy_from_slice(seed); // IKM || I2OSP(0, 1)
                             let mut okm = [0u8; PRIVATE_KEY_SIZE];
@@ -163,7 +213,8 @@ macro_rules! bls_impl {
                 PublicKey(g * sk)
             }
 
-            // Create an combined public key without rogue key mitigation
+            // Create an combined public key without rogue key mitigation.
+            // Prefer `AggregatedPublicKey::musig` or `AggregatedPublicKey::from_proofs_of_possession`
             pub fn combine(&mut self, pks: &[PublicKey]) {
                 for pk in pks {
                     self.0 += &pk.0;
@@ -193,26 +244,98 @@ macro_rules! bls_impl {
                 // compute (t_1,…,t_n)←H1(pk_1,…,pk_n) ∈ R_n
                 // output the aggregated public key
                 // as described in section 3.1 from https://eprint.iacr.org/2018/483
-                let mut bytes = Vec::new();
-                for k in keys {
-                    bytes.extend_from_slice(k.to_bytes().as_slice());
-                }
+                let bytes = key_list_bytes(keys);
                 AggregatedPublicKey(keys.iter().fold(Generator::identity(), |apk, k| {
-                    // The position of the ith public key in the byte array
-                    // of the hash doesn't matter as much as its included twice.
-                    // For convenience, its appended to the end
-                    let mut h = bytes.clone();
-                    h.extend_from_slice(k.0.to_bytes(false).as_slice());
-                    apk + &k.0 * &FieldElement::from_msg_hash(h.as_slice())
+                    apk + &k.0 * &rk_coefficient(&bytes, k)
                 }))
             }
         }
 
+        fn key_list_bytes(keys: &[PublicKey]) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            for k in keys {
+                bytes.extend_from_slice(k.to_bytes().as_slice());
+            }
+            bytes
+        }
+
+        fn rk_coefficient(key_list: &[u8], pk: &PublicKey) -> FieldElement {
+            // The position of the ith public key in the byte array
+            // of the hash doesn't matter as much as its included twice.
+            // For convenience, its appended to the end
+            let mut h = key_list.to_vec();
+            h.extend_from_slice(pk.to_bytes().as_slice());
+            FieldElement::from_msg_hash(h.as_slice())
+        }
+
+        /// Keys that can't be aggregated safely: none at all, the identity
+        /// which signs anything with the identity signature, or a key listed twice
+        fn check_aggregation_keys<'a, I: Iterator<Item = &'a PublicKey>>(
+            keys: I,
+        ) -> Result<(), CryptoError> {
+            let mut seen = ::std::collections::HashSet::new();
+            for (i, k) in keys.enumerate() {
+                if k.0.is_identity() {
+                    return Err(CryptoError::GeneralError(format!(
+                        "Public key {} is the identity",
+                        i
+                    )));
+                }
+                if !seen.insert(k.to_bytes()) {
+                    return Err(CryptoError::GeneralError(format!(
+                        "Public key {} is a duplicate",
+                        i
+                    )));
+                }
+            }
+            if seen.is_empty() {
+                return Err(CryptoError::GeneralError(
+                    "No public keys to aggregate".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
         impl AggregatedPublicKey {
             pub fn new(keys: &[PublicKey]) -> Self {
                 keys.into()
             }
 
+            /// Aggregate `keys` with a hash derived coefficient for each key as in `new`
+            /// so no key can be chosen to cancel out the others.
+            /// Signers must sign with `Signature::new_with_rk_mitigation` over the same `keys`
+            /// in the same order. Fails if there are no keys, a key is the identity
+            /// or a key is repeated
+            pub fn musig(keys: &[PublicKey]) -> Result<Self, CryptoError> {
+                check_aggregation_keys(keys.iter())?;
+                Ok(keys.into())
+            }
+
+            /// Aggregate keys whose holders proved possession of the secret key.
+            /// Every proof is checked before the keys are added so the aggregate verifies
+            /// signatures created by `Signature::new` and combined with `AggregatedSignature::new`.
+            /// Fails naming the first key whose proof is invalid, and like `musig`
+            /// if there are no keys, a key is the identity or a key is repeated
+            pub fn from_proofs_of_possession(
+                keys: &[(PublicKey, ProofOfPossession)],
+                context: Option<&'static [u8]>,
+                g: &Generator,
+            ) -> Result<Self, CryptoError> {
+                check_aggregation_keys(keys.iter().map(|(k, _)| k))?;
+                for (i, (k, proof)) in keys.iter().enumerate() {
+                    if !proof.verify(context, k, g) {
+                        return Err(CryptoError::GeneralError(format!(
+                            "Invalid proof of possession for public key {}",
+                            i
+                        )));
+                    }
+                }
+                Ok(AggregatedPublicKey(
+                    keys.iter()
+                        .fold(Generator::identity(), |apk, (k, _)| apk + &k.0),
+                ))
+            }
+
             pub fn to_bytes(&self) -> Vec<u8> {
                 self.0.to_bytes(false)
             }
@@ -262,12 +385,7 @@ macro_rules! bls_impl {
                 // compute (t_1,…,t_n)←H1(pk_1,…,pk_n) ∈ R_n
                 // output the aggregated public key
                 // as described in section 3.1 from https://eprint.iacr.org/2018/483
-                let mut bytes = Vec::new();
-                for k in pks {
-                    bytes.extend_from_slice(k.to_bytes().as_slice());
-                }
-                bytes.extend_from_slice(pks[pk_index].to_bytes().as_slice());
-                let a = FieldElement::from_msg_hash(bytes.as_slice());
+                let a = rk_coefficient(&key_list_bytes(pks), &pks[pk_index]);
                 Signature(hash * sk * &a)
             }
 
@@ -323,23 +441,40 @@ macro_rules! bls_impl {
                 inputs: &[(&[u8], &Signature, &PublicKey)],
                 context: Option<&'static [u8]>,
                 g: &Generator,
+            ) -> bool {
+                Self::batch_verify_with_offload(inputs, context, g, &CpuOffload)
+            }
+
+            /// Same as `batch_verify` but the multi-scalar multiplication and pairings
+            /// are computed by `offload`
+            pub fn batch_verify_with_offload(
+                inputs: &[(&[u8], &Signature, &PublicKey)],
+                context: Option<&'static [u8]>,
+                g: &Generator,
+                offload: &dyn Offload,
             ) -> bool {
                 // To avoid rogue key attacks, you must use proof of possession or `AggregateSignature::batch_verify`
                 // This function just avoids checking for distinct messages and
                 // uses batch verification as described in the end of section 3.1 from https://eprint.iacr.org/2018/483
-                let mut pairs = Vec::new();
-                let mut sig = SignatureGroup::identity();
+                let mut pairs = Vec::with_capacity(inputs.len() + 1);
+                let mut sigs = Vec::with_capacity(inputs.len());
+                let mut random_exponents = Vec::with_capacity(inputs.len());
                 for (msg, asg, apk) in inputs {
                     let random_exponent = FieldElement::random();
                     let hash = hash_msg(msg, context);
-                    sig += &asg.0 * &random_exponent;
                     pairs.push((&apk.0 * &random_exponent, hash));
+                    sigs.push(asg.0.clone());
+                    random_exponents.push(random_exponent);
                 }
 
+                let sig = match $sig_multi_scalar_mul(offload, &sigs, &random_exponents) {
+                    Ok(sig) => sig,
+                    Err(_) => return false,
+                };
                 pairs.push((-g, sig));
 
                 let ate_pairs = pairs.iter().map($set_pairs).collect();
-                GT::ate_multi_pairing(ate_pairs).is_one()
+                offload.pairing_product_is_one(ate_pairs)
             }
 
             pub fn to_bytes(&self) -> Vec<u8> {
@@ -443,22 +578,43 @@ macro_rules! bls_impl {
                 )],
                 context: Option<&'static [u8]>,
                 g: &Generator,
+            ) -> bool {
+                Self::batch_verify_with_offload(inputs, context, g, &CpuOffload)
+            }
+
+            /// Same as `batch_verify` but the multi-scalar multiplication and pairings
+            /// are computed by `offload`
+            pub fn batch_verify_with_offload(
+                inputs: &[(
+                    &[u8], /* message */
+                    &AggregatedSignature,
+                    &AggregatedPublicKey,
+                )],
+                context: Option<&'static [u8]>,
+                g: &Generator,
+                offload: &dyn Offload,
             ) -> bool {
                 // To combat the rogue key attack and avoid checking for distinct messages
                 // use batch verification as described in the end of section 3.1 from https://eprint.iacr.org/2018/483
-                let mut pairs = Vec::new();
-                let mut sig = SignatureGroup::identity();
+                let mut pairs = Vec::with_capacity(inputs.len() + 1);
+                let mut sigs = Vec::with_capacity(inputs.len());
+                let mut random_exponents = Vec::with_capacity(inputs.len());
                 for (msg, asg, apk) in inputs {
                     let random_exponent = FieldElement::random();
                     let hash = hash_msg(msg, context);
-                    sig += &asg.0 * &random_exponent;
                     pairs.push((&apk.0 * &random_exponent, hash));
+                    sigs.push(asg.0.clone());
+                    random_exponents.push(random_exponent);
                 }
 
+                let sig = match $sig_multi_scalar_mul(offload, &sigs, &random_exponents) {
+                    Ok(sig) => sig,
+                    Err(_) => return false,
+                };
                 pairs.push((-g, sig));
 
                 let ate_pairs = pairs.iter().map($set_pairs).collect();
-                GT::ate_multi_pairing(ate_pairs).is_one()
+                offload.pairing_product_is_one(ate_pairs)
             }
 
             pub fn to_bytes(&self) -> Vec<u8> {
@@ -472,6 +628,114 @@ macro_rules! bls_impl {
                 ))
             }
         }
+
+        /// Folds signatures and public keys over the same message into an aggregate
+        /// as they arrive so memory use doesn't grow with the number of signers.
+        /// Relays can checkpoint the partial aggregate with `to_bytes` and
+        /// combine aggregates from other relays with `merge`.
+        ///
+        /// Rogue key mitigation by `new_with_rk_mitigation` needs every public key
+        /// up front so can't be used here. Signers should be checked with
+        /// `ProofOfPossession` before their signatures are added.
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[derive(Debug, Clone)]
+        pub struct AggregateAccumulator {
+            signature: SignatureGroup,
+            public_key: Generator,
+            count: u64,
+        }
+
+        impl Default for AggregateAccumulator {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl AggregateAccumulator {
+            pub const BYTES: usize = 8 + SIGNATURE_SIZE + PUBLIC_KEY_SIZE;
+
+            /// Create an empty accumulator
+            pub fn new() -> Self {
+                AggregateAccumulator {
+                    signature: SignatureGroup::identity(),
+                    public_key: Generator::identity(),
+                    count: 0,
+                }
+            }
+
+            /// Add a signature created by `Signature::new` and the signer's public key
+            pub fn add(&mut self, signature: &Signature, pk: &PublicKey) {
+                self.signature += &signature.0;
+                self.public_key += &pk.0;
+                self.count += 1;
+            }
+
+            /// Add everything accumulated by `other`
+            pub fn merge(&mut self, other: &AggregateAccumulator) {
+                self.signature += &other.signature;
+                self.public_key += &other.public_key;
+                self.count += other.count;
+            }
+
+            /// The number of signatures accumulated
+            pub fn count(&self) -> u64 {
+                self.count
+            }
+
+            /// The aggregate of the signatures so far
+            pub fn signature(&self) -> AggregatedSignature {
+                AggregatedSignature(self.signature.clone())
+            }
+
+            /// The sum of the public keys so far
+            pub fn public_key(&self) -> PublicKey {
+                PublicKey(self.public_key.clone())
+            }
+
+            /// Verify the aggregate signature against the accumulated public key
+            pub fn verify<A: AsRef<[u8]>>(
+                &self,
+                message: A,
+                context: Option<&'static [u8]>,
+                g: &Generator,
+            ) -> bool {
+                if self.count == 0 {
+                    return false;
+                }
+                let hash = hash_msg(message, context);
+                $ate_2_pairing_is_one(&g, &self.signature, &self.public_key, &hash)
+            }
+
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut out = Vec::with_capacity(Self::BYTES);
+                out.extend_from_slice(&self.count.to_be_bytes()[..]);
+                out.extend_from_slice(self.signature.to_bytes(false).as_slice());
+                out.extend_from_slice(self.public_key.to_bytes(false).as_slice());
+                out
+            }
+
+            pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+                if bytes.len() != Self::BYTES {
+                    return Err(CryptoError::ParseError(format!(
+                        "Expected {} bytes, found {}",
+                        Self::BYTES,
+                        bytes.len()
+                    )));
+                }
+                let mut count = [0u8; 8];
+                count.copy_from_slice(&bytes[..8]);
+                let count = u64::from_be_bytes(count);
+                let signature = SignatureGroup::from_bytes(&bytes[8..8 + SIGNATURE_SIZE])
+                    .map_err(|e| CryptoError::ParseError(format!("{:?}", e)))?;
+                let public_key = Generator::from_bytes(&bytes[8 + SIGNATURE_SIZE..])
+                    .map_err(|e| CryptoError::ParseError(format!("{:?}", e)))?;
+                Ok(AggregateAccumulator {
+                    signature,
+                    public_key,
+                    count,
+                })
+            }
+        }
     };
 }
 
@@ -488,10 +752,10 @@ macro_rules! bls_tests_impl {
             #[test]
             fn signature_generation_from_seed() {
                 let keypair_1 = Bls
-                    .keypair(Some(KeyGenOption::UseSeed(SEED.to_vec())))
+                    .keypair(Some(KeyGenOption::UseSeed(SEED[..].into())))
                     .unwrap();
                 let keypair_2 = Bls
-                    .keypair(Some(KeyGenOption::UseSeed(SEED.to_vec())))
+                    .keypair(Some(KeyGenOption::UseSeed(SEED[..].into())))
                     .unwrap();
                 assert_eq!(keypair_1, keypair_2);
             }
@@ -600,6 +864,141 @@ macro_rules! bls_tests_impl {
                 }
             }
 
+            #[test]
+            fn aggregate_public_key_musig() {
+                let g = Generator::generator();
+                let (pks, sks): (Vec<_>, Vec<_>) = (0..5).map(|_| generate(&g)).unzip();
+                let sigs = (0..5)
+                    .map(|i| {
+                        Signature::new_with_rk_mitigation(&MESSAGE_1[..], None, &sks[i], i, &pks)
+                    })
+                    .collect::<Vec<_>>();
+                let asg = AggregatedSignature::new(&sigs);
+                let apk = AggregatedPublicKey::musig(&pks).unwrap();
+                assert_eq!(apk.to_bytes(), AggregatedPublicKey::new(&pks).to_bytes());
+                assert!(asg.verify(&MESSAGE_1[..], None, &apk, &g));
+                assert!(!asg.verify(&MESSAGE_2[..], None, &apk, &g));
+
+                // A rogue key cancelling the others doesn't cancel after weighting
+                let (rogue, rogue_sk) = generate(&g);
+                let mut rogue_pk = rogue.clone();
+                rogue_pk.0 = pks.iter().fold(rogue.0.clone(), |r, k| r - &k.0);
+                let mut keys = pks.clone();
+                keys.push(rogue_pk);
+                let forged =
+                    AggregatedSignature::new(&[Signature::new(&MESSAGE_1[..], None, &rogue_sk)]);
+                let naive =
+                    AggregatedPublicKey(keys.iter().fold(Generator::identity(), |a, k| a + &k.0));
+                assert!(forged.verify(&MESSAGE_1[..], None, &naive, &g));
+                let apk = AggregatedPublicKey::musig(&keys).unwrap();
+                assert!(!forged.verify(&MESSAGE_1[..], None, &apk, &g));
+
+                assert!(AggregatedPublicKey::musig(&[]).is_err());
+                assert!(AggregatedPublicKey::musig(&[pks[0].clone(), pks[0].clone()]).is_err());
+                assert!(AggregatedPublicKey::musig(&[
+                    pks[0].clone(),
+                    PublicKey(Generator::identity())
+                ])
+                .is_err());
+            }
+
+            #[test]
+            fn aggregate_public_key_proof_of_possession() {
+                let g = Generator::generator();
+                let (pks, sks): (Vec<_>, Vec<_>) = (0..5).map(|_| generate(&g)).unzip();
+                let mut keys = pks
+                    .iter()
+                    .zip(sks.iter())
+                    .map(|(pk, sk)| (pk.clone(), ProofOfPossession::new(pk, None, sk)))
+                    .collect::<Vec<_>>();
+                let sigs = sks
+                    .iter()
+                    .map(|sk| Signature::new(&MESSAGE_1[..], None, sk))
+                    .collect::<Vec<_>>();
+                let asg = AggregatedSignature::new(&sigs);
+                let apk = AggregatedPublicKey::from_proofs_of_possession(&keys, None, &g).unwrap();
+                assert!(asg.verify(&MESSAGE_1[..], None, &apk, &g));
+                assert!(asg.verify_no_rk(&MESSAGE_1[..], None, &pks, &g));
+                assert!(!asg.verify(&MESSAGE_2[..], None, &apk, &g));
+
+                // Proofs under another context or for another key are rejected
+                let ctx = b"another domain separator";
+                assert!(
+                    AggregatedPublicKey::from_proofs_of_possession(&keys, Some(ctx), &g).is_err()
+                );
+                let (rogue, _) = generate(&g);
+                keys[3].0 = rogue;
+                match AggregatedPublicKey::from_proofs_of_possession(&keys, None, &g) {
+                    Err(CryptoError::GeneralError(e)) => assert!(e.contains("public key 3")),
+                    r => panic!("expected an invalid proof, found {:?}", r),
+                }
+
+                let identity = PublicKey(Generator::identity());
+                let proof = ProofOfPossession::new(&identity, None, &sks[0]);
+                assert!(AggregatedPublicKey::from_proofs_of_possession(
+                    &[(identity, proof)],
+                    None,
+                    &g
+                )
+                .is_err());
+                assert!(AggregatedPublicKey::from_proofs_of_possession(&[], None, &g).is_err());
+                let dup = [keys[0].clone(), keys[0].clone()];
+                assert!(AggregatedPublicKey::from_proofs_of_possession(&dup, None, &g).is_err());
+            }
+
+            #[test]
+            fn aggregate_accumulator() {
+                const KEY_COUNT: usize = 10;
+
+                let g = Generator::generator();
+                let mut acc = AggregateAccumulator::new();
+                let mut other = AggregateAccumulator::default();
+                assert!(!acc.verify(&MESSAGE_1[..], None, &g));
+                let mut pks = Vec::new();
+                let mut sigs = Vec::new();
+                for i in 0..KEY_COUNT {
+                    let (pk, sk) = generate(&g);
+                    let sig = Signature::new(&MESSAGE_1[..], None, &sk);
+                    if i % 2 == 0 {
+                        acc.add(&sig, &pk);
+                    } else {
+                        other.add(&sig, &pk);
+                    }
+                    pks.push(pk);
+                    sigs.push(sig);
+
+                    // Checkpoint and resume
+                    if i == KEY_COUNT / 2 {
+                        let bytes = acc.to_bytes();
+                        assert_eq!(bytes.len(), AggregateAccumulator::BYTES);
+                        acc = AggregateAccumulator::from_bytes(&bytes).unwrap();
+                    }
+                }
+                assert_eq!(acc.count(), (KEY_COUNT / 2) as u64);
+                assert!(acc.verify(&MESSAGE_1[..], None, &g));
+                assert!(!acc.verify(&MESSAGE_2[..], None, &g));
+
+                acc.merge(&other);
+                assert_eq!(acc.count(), KEY_COUNT as u64);
+                assert!(acc.verify(&MESSAGE_1[..], None, &g));
+                assert_eq!(
+                    acc.signature().to_bytes(),
+                    AggregatedSignature::new(sigs.as_slice()).to_bytes()
+                );
+                assert!(acc
+                    .signature()
+                    .verify_no_rk(&MESSAGE_1[..], None, pks.as_slice(), &g));
+                assert!(acc.signature().verify_no_rk(
+                    &MESSAGE_1[..],
+                    None,
+                    &[acc.public_key()],
+                    &g
+                ));
+
+                let bytes = acc.to_bytes();
+                assert!(AggregateAccumulator::from_bytes(&bytes[1..]).is_err());
+            }
+
             #[test]
             fn batch_signature_verification() {
                 const KEY_COUNT: usize = 10;
@@ -691,6 +1090,66 @@ macro_rules! bls_tests_impl {
                 ));
             }
 
+            #[test]
+            fn batch_verification_with_offload() {
+                struct CountingOffload(::std::sync::atomic::AtomicUsize);
+
+                impl Offload for CountingOffload {
+                    fn multi_scalar_mul_g1(
+                        &self,
+                        points: &[G1],
+                        scalars: &[FieldElement],
+                    ) -> Result<G1, CryptoError> {
+                        self.0.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+                        CpuOffload.multi_scalar_mul_g1(points, scalars)
+                    }
+
+                    fn multi_scalar_mul_g2(
+                        &self,
+                        points: &[G2],
+                        scalars: &[FieldElement],
+                    ) -> Result<G2, CryptoError> {
+                        self.0.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+                        CpuOffload.multi_scalar_mul_g2(points, scalars)
+                    }
+
+                    fn pairing_product_is_one(&self, pairs: Vec<(&G1, &G2)>) -> bool {
+                        self.0.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+                        CpuOffload.pairing_product_is_one(pairs)
+                    }
+                }
+
+                let g = Generator::generator();
+                let mut groups = Vec::new();
+                for i in 0..3 {
+                    let (pk, sk) = generate(&g);
+                    let msg = vec![i as u8; 8];
+                    let sig = Signature::new(msg.as_slice(), None, &sk);
+                    groups.push((msg, sig, pk));
+                }
+                let mut refs = groups
+                    .iter()
+                    .map(|(m, s, p)| (m.as_slice(), s, p))
+                    .collect::<Vec<(&[u8], &Signature, &PublicKey)>>();
+
+                let offload = CountingOffload(::std::sync::atomic::AtomicUsize::new(0));
+                assert!(Signature::batch_verify_with_offload(
+                    refs.as_slice(),
+                    None,
+                    &g,
+                    &offload
+                ));
+                assert_eq!(offload.0.load(::std::sync::atomic::Ordering::SeqCst), 2);
+
+                refs[0].0 = MESSAGE_1;
+                assert!(!Signature::batch_verify_with_offload(
+                    refs.as_slice(),
+                    None,
+                    &g,
+                    &offload
+                ));
+            }
+
             #[test]
             fn multi_signature_verification() {
                 const KEY_COUNT: usize = 10;
@@ -736,12 +1195,13 @@ pub mod prelude {
     pub use super::{
         normal::*,
         small::{
-            generate as small_generate, AggregatedPublicKey as SmallAggregatedPublicKey,
+            generate as small_generate, AggregateAccumulator as SmallAggregateAccumulator,
+            AggregatedPublicKey as SmallAggregatedPublicKey,
             AggregatedSignature as SmallAggregatedSignature, Generator as SmallGenerator,
             ProofOfPossession as SmallProofOfPossession, PublicKey as SmallPublicKey,
             Signature as SmallSignature, SignatureGroup as SmallSignatureGroup,
         },
-        PrivateKey,
+        CpuOffload, Offload, PrivateKey,
     };
 }
 
@@ -757,7 +1217,8 @@ pub mod normal {
         G1,
         G2,
         ate_2_pairing_g1_g2_is_one,
-        set_pairs_g1_g2
+        set_pairs_g1_g2,
+        multi_scalar_mul_g2
     );
 
     bls_tests_impl!();
@@ -778,7 +1239,8 @@ pub mod small {
         G2,
         G1,
         ate_2_pairing_g2_g1_is_one,
-        set_pairs_g2_g1
+        set_pairs_g2_g1,
+        multi_scalar_mul_g1
     );
 
     bls_tests_impl!();
@@ -794,6 +1256,15 @@ fn set_pairs_g1_g2(t: &(G1, G2)) -> (&G1, &G2) {
     (&t.0, &t.1)
 }
 
+#[inline(always)]
+fn multi_scalar_mul_g2(
+    offload: &dyn Offload,
+    points: &[G2],
+    scalars: &[FieldElement],
+) -> Result<G2, CryptoError> {
+    offload.multi_scalar_mul_g2(points, scalars)
+}
+
 #[inline(always)]
 fn ate_2_pairing_g2_g1_is_one(p1: &G2, g1: &G1, p2: &G2, g2: &G1) -> bool {
     GT::ate_2_pairing(g1, &-p1, g2, p2).is_one()
@@ -804,6 +1275,15 @@ fn set_pairs_g2_g1(t: &(G2, G1)) -> (&G1, &G2) {
     (&t.1, &t.0)
 }
 
+#[inline(always)]
+fn multi_scalar_mul_g1(
+    offload: &dyn Offload,
+    points: &[G1],
+    scalars: &[FieldElement],
+) -> Result<G1, CryptoError> {
+    offload.multi_scalar_mul_g1(points, scalars)
+}
+
 #[cfg(test)]
 mod tests {
     use super::normal::{
@@ -0,0 +1,144 @@
+//! AWS KMS signing keys.
+
+use sha2::{Digest, Sha256};
+
+use super::{json_base64_field, HttpRequest, HttpTransport, KeyAlgorithm};
+use keys::PublicKey;
+use signatures::vault::{check_underived, KeyVault};
+use CryptoError;
+
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// Signs with asymmetric keys in AWS KMS. Seed ids are key ids, ARNs or aliases.
+pub struct AwsKms<T: HttpTransport> {
+    transport: T,
+    region: String,
+    algorithm: KeyAlgorithm,
+}
+
+impl<T: HttpTransport> AwsKms<T> {
+    /// Use keys for `algorithm` in `region`, for example `"eu-west-1"`
+    pub fn new(transport: T, region: &str, algorithm: KeyAlgorithm) -> Self {
+        AwsKms {
+            transport,
+            region: region.to_string(),
+            algorithm,
+        }
+    }
+
+    /// Fetch the public key of `key_id`
+    pub fn public_key(&self, key_id: &str) -> Result<PublicKey, CryptoError> {
+        let response = self.call("GetPublicKey", json!({ "KeyId": key_id }))?;
+        self.algorithm
+            .normalize_public_key(json_base64_field(&response, "PublicKey")?)
+    }
+
+    /// Sign the SHA-256 digest of `message` with `key_id`
+    pub fn sign(&self, key_id: &str, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let response = self.call(
+            "Sign",
+            json!({
+                "KeyId": key_id,
+                "Message": base64::encode(Sha256::digest(message)),
+                "MessageType": "DIGEST",
+                "SigningAlgorithm": signing_algorithm(self.algorithm),
+            }),
+        )?;
+        self.algorithm
+            .normalize_signature(json_base64_field(&response, "Signature")?)
+    }
+
+    fn call(&self, action: &str, body: ::serde_json::Value) -> Result<Vec<u8>, CryptoError> {
+        self.transport.send(&HttpRequest {
+            method: "POST",
+            url: format!("https://kms.{}.amazonaws.com/", self.region),
+            headers: vec![
+                ("Content-Type", CONTENT_TYPE.to_string()),
+                ("X-Amz-Target", format!("TrentService.{}", action)),
+            ],
+            body: body.to_string().into_bytes(),
+        })
+    }
+}
+
+impl<T: HttpTransport> KeyVault for AwsKms<T> {
+    fn derive_public_key(&self, seed_id: &str, path: &[u32]) -> Result<PublicKey, CryptoError> {
+        check_underived(path, "AWS KMS")?;
+        self.public_key(seed_id)
+    }
+
+    fn sign_derived(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_underived(path, "AWS KMS")?;
+        self.sign(seed_id, message)
+    }
+}
+
+fn signing_algorithm(algorithm: KeyAlgorithm) -> &'static str {
+    match algorithm {
+        KeyAlgorithm::EcdsaP256Sha256 => "ECDSA_SHA_256",
+        KeyAlgorithm::RsaPkcs1Sha256 => "RSASSA_PKCS1_V1_5_SHA_256",
+        KeyAlgorithm::RsaPssSha256 => "RSASSA_PSS_SHA_256",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+    use signatures::der::test::{p256_der_signature, p256_spki};
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct MockAws {
+        requests: RefCell<Vec<HttpRequest>>,
+    }
+
+    impl HttpTransport for MockAws {
+        fn send(&self, request: &HttpRequest) -> Result<Vec<u8>, CryptoError> {
+            self.requests.borrow_mut().push(request.clone());
+            let target = &request.headers[1].1;
+            let response = match target.as_str() {
+                "TrentService.GetPublicKey" => json!({
+                    "KeyId": "key",
+                    "PublicKey": base64::encode(p256_spki()),
+                    "KeySpec": "ECC_NIST_P256",
+                }),
+                "TrentService.Sign" => json!({
+                    "KeyId": "key",
+                    "Signature": base64::encode(p256_der_signature()),
+                    "SigningAlgorithm": "ECDSA_SHA_256",
+                }),
+                _ => return Err(CryptoError::GeneralError("Unknown action".to_string())),
+            };
+            Ok(response.to_string().into_bytes())
+        }
+    }
+
+    #[test]
+    fn sign_with_aws_kms() {
+        let kms = AwsKms::new(
+            MockAws::default(),
+            "eu-west-1",
+            KeyAlgorithm::EcdsaP256Sha256,
+        );
+
+        assert_eq!(kms.derive_public_key("key", &[]).unwrap().len(), 65);
+        assert_eq!(kms.sign_derived("key", &[], b"message").unwrap().len(), 64);
+        assert!(kms.sign_derived("key", &[0], b"message").is_err());
+
+        let requests = kms.transport.requests.borrow();
+        assert_eq!(requests[1].url, "https://kms.eu-west-1.amazonaws.com/");
+        let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body["MessageType"], "DIGEST");
+        assert_eq!(body["SigningAlgorithm"], "ECDSA_SHA_256");
+        assert_eq!(
+            base64::decode(body["Message"].as_str().unwrap()).unwrap(),
+            Sha256::digest(b"message").to_vec()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Google Cloud KMS signing keys.
+
+use sha2::{Digest, Sha256};
+
+use super::{
+    json_base64_field, json_str_field, pem_to_der, HttpRequest, HttpTransport, KeyAlgorithm,
+};
+use keys::PublicKey;
+use signatures::vault::{check_underived, KeyVault};
+use CryptoError;
+
+const ENDPOINT: &str = "https://cloudkms.googleapis.com/v1/";
+
+/// Signs with asymmetric keys in Google Cloud KMS. Seed ids are key version resource
+/// names, `projects/*/locations/*/keyRings/*/cryptoKeys/*/cryptoKeyVersions/*`.
+pub struct GcpKms<T: HttpTransport> {
+    transport: T,
+    algorithm: KeyAlgorithm,
+}
+
+impl<T: HttpTransport> GcpKms<T> {
+    /// Use key versions for `algorithm`
+    pub fn new(transport: T, algorithm: KeyAlgorithm) -> Self {
+        GcpKms {
+            transport,
+            algorithm,
+        }
+    }
+
+    /// Fetch the public key of the key version `name`
+    pub fn public_key(&self, name: &str) -> Result<PublicKey, CryptoError> {
+        let response = self.transport.send(&HttpRequest {
+            method: "GET",
+            url: format!("{}{}/publicKey", ENDPOINT, name),
+            headers: Vec::new(),
+            body: Vec::new(),
+        })?;
+        let algorithm = json_str_field(&response, "algorithm")?;
+        if !matches_algorithm(self.algorithm, &algorithm) {
+            return Err(CryptoError::NoSuchAlgorithm(format!(
+                "Key version {} is for {}",
+                name, algorithm
+            )));
+        }
+        self.algorithm
+            .normalize_public_key(pem_to_der(&json_str_field(&response, "pem")?)?)
+    }
+
+    /// Sign the SHA-256 digest of `message` with the key version `name`
+    pub fn sign(&self, name: &str, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let body = json!({
+            "digest": { "sha256": base64::encode(Sha256::digest(message)) }
+        });
+        let response = self.transport.send(&HttpRequest {
+            method: "POST",
+            url: format!("{}{}:asymmetricSign", ENDPOINT, name),
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+        })?;
+        self.algorithm
+            .normalize_signature(json_base64_field(&response, "signature")?)
+    }
+}
+
+impl<T: HttpTransport> KeyVault for GcpKms<T> {
+    fn derive_public_key(&self, seed_id: &str, path: &[u32]) -> Result<PublicKey, CryptoError> {
+        check_underived(path, "Cloud KMS")?;
+        self.public_key(seed_id)
+    }
+
+    fn sign_derived(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_underived(path, "Cloud KMS")?;
+        self.sign(seed_id, message)
+    }
+}
+
+/// Whether the Cloud KMS algorithm name `name` is `algorithm` with any key size
+fn matches_algorithm(algorithm: KeyAlgorithm, name: &str) -> bool {
+    match algorithm {
+        KeyAlgorithm::EcdsaP256Sha256 => name == "EC_SIGN_P256_SHA256",
+        KeyAlgorithm::RsaPkcs1Sha256 => {
+            name.starts_with("RSA_SIGN_PKCS1_") && name.ends_with("_SHA256")
+        }
+        KeyAlgorithm::RsaPssSha256 => {
+            name.starts_with("RSA_SIGN_PSS_") && name.ends_with("_SHA256")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use signatures::der::test::{p256_der_signature, p256_spki};
+
+    const NAME: &str = "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1";
+
+    struct MockGcp;
+
+    impl HttpTransport for MockGcp {
+        fn send(&self, request: &HttpRequest) -> Result<Vec<u8>, CryptoError> {
+            let response = if request.url == format!("{}{}/publicKey", ENDPOINT, NAME) {
+                json!({
+                    "pem": format!(
+                        "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+                        base64::encode(p256_spki())
+                    ),
+                    "algorithm": "EC_SIGN_P256_SHA256",
+                })
+            } else if request.url == format!("{}{}:asymmetricSign", ENDPOINT, NAME) {
+                json!({ "signature": base64::encode(p256_der_signature()), "name": NAME })
+            } else {
+                return Err(CryptoError::GeneralError("Not found".to_string()));
+            };
+            Ok(response.to_string().into_bytes())
+        }
+    }
+
+    #[test]
+    fn sign_with_cloud_kms() {
+        let kms = GcpKms::new(MockGcp, KeyAlgorithm::EcdsaP256Sha256);
+        assert_eq!(kms.derive_public_key(NAME, &[]).unwrap().len(), 65);
+        assert_eq!(kms.sign_derived(NAME, &[], b"message").unwrap().len(), 64);
+        assert!(kms.sign_derived(NAME, &[0], b"message").is_err());
+
+        let rsa = GcpKms::new(MockGcp, KeyAlgorithm::RsaPssSha256);
+        assert!(rsa.public_key(NAME).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Signing with keys held by cloud key management services.
+//!
+//! `aws::AwsKms` and `gcp::GcpKms` implement `KeyVault` for AWS KMS and Google
+//! Cloud KMS. The seed id is the service's name for the key and, as the keys are
+//! used as they are, the derivation path must be empty.
+//!
+//! Requests go through an `HttpTransport` supplied by the caller, which is also
+//! responsible for authenticating them (SigV4 for AWS, an OAuth bearer token for
+//! Google Cloud) so credentials never pass through this crate. Messages are hashed
+//! with SHA-256 locally and only the digest is sent. Like the rest of `KeyVault`
+//! the calls block, so async services should make them from a blocking task.
+//!
+//! Results are normalized into the forms the rest of ursa uses: ECDSA signatures
+//! become the 64 byte `r || s` form instead of DER and P-256 public keys become the
+//! 65 byte uncompressed point. RSA signatures are returned as they are and RSA public
+//! keys as DER encoded SubjectPublicKeyInfo.
+
+#[cfg(feature = "aws_kms")]
+pub mod aws;
+#[cfg(feature = "gcp_kms")]
+pub mod gcp;
+
+pub use signatures::der::{ecdsa_der_to_raw, p256_spki_to_point, pem_to_der};
+
+use keys::PublicKey;
+use serde_json::Value;
+use signatures::der::P256_FIELD_SIZE;
+use CryptoError;
+
+/// An HTTP request to a key management service
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Authenticates and sends requests to a key management service.
+/// Returns the response body, or an error if the service didn't answer with success.
+pub trait HttpTransport {
+    fn send(&self, request: &HttpRequest) -> Result<Vec<u8>, CryptoError>;
+}
+
+/// The signing algorithms supported for cloud keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    EcdsaP256Sha256,
+    RsaPkcs1Sha256,
+    RsaPssSha256,
+}
+
+impl KeyAlgorithm {
+    fn normalize_signature(self, signature: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            KeyAlgorithm::EcdsaP256Sha256 => ecdsa_der_to_raw(&signature, P256_FIELD_SIZE),
+            KeyAlgorithm::RsaPkcs1Sha256 | KeyAlgorithm::RsaPssSha256 => Ok(signature),
+        }
+    }
+
+    fn normalize_public_key(self, spki: Vec<u8>) -> Result<PublicKey, CryptoError> {
+        match self {
+            KeyAlgorithm::EcdsaP256Sha256 => p256_spki_to_point(&spki).map(PublicKey),
+            KeyAlgorithm::RsaPkcs1Sha256 | KeyAlgorithm::RsaPssSha256 => Ok(PublicKey(spki)),
+        }
+    }
+}
+
+/// Parse a JSON response and return the base64 field `name` decoded
+fn json_base64_field(response: &[u8], name: &str) -> Result<Vec<u8>, CryptoError> {
+    let field = json_str_field(response, name)?;
+    base64::decode(&field)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid base64 in {}: {}", name, e)))
+}
+
+/// Parse a JSON response and return the string field `name`
+fn json_str_field(response: &[u8], name: &str) -> Result<String, CryptoError> {
+    let value: Value = serde_json::from_slice(response)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid JSON response: {}", e)))?;
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| CryptoError::ParseError(format!("Response has no {}", name)))
+}
@@ -0,0 +1,409 @@
+//! Short lived certificates that let one key act for another.
+//!
+//! A `DelegationCertificate` is signed by a parent key and authorizes a child key to
+//! perform a list of operations between two times. Services keep the root key cold,
+//! sign a certificate for an ephemeral operational key and sign with that instead, so
+//! losing the operational key only exposes the listed operations until the certificate
+//! expires. An operational key can in turn delegate to another key, but only a subset
+//! of its own operations.
+//!
+//! A verifier that trusts the root key takes the signer's certificates and calls
+//! `verify_delegation_chain` with the operation and the current time, which walks them
+//! from the root and returns the key allowed to sign for that operation. Times are in
+//! seconds and the operations are names chosen by the application, e.g. `"sign:invoice"`.
+//! Every key of a chain uses the same `SignatureScheme`.
+
+use keys::{PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+use super::provider::KeyProvider;
+use super::SignatureScheme;
+use CryptoError;
+
+const CERTIFICATE_DOMAIN: &[u8] = b"URSA_KEY_DELEGATION_V1";
+
+/// An authorization of `subject` by `issuer` to perform `operations` from `not_before`
+/// until `not_after`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelegationCertificate {
+    /// The key delegating its authority
+    pub issuer: PublicKey,
+    /// The key receiving it
+    pub subject: PublicKey,
+    /// The operations the subject may perform
+    pub operations: Vec<String>,
+    /// The first second the certificate is valid
+    pub not_before: u64,
+    /// The first second the certificate is no longer valid
+    pub not_after: u64,
+    /// The issuer's signature
+    pub signature: Vec<u8>,
+}
+
+impl DelegationCertificate {
+    /// Authorize `subject` to perform `operations` from `not_before` until `not_after`
+    pub fn issue<T: SignatureScheme>(
+        scheme: &T,
+        issuer: (&PublicKey, &PrivateKey),
+        subject: &PublicKey,
+        operations: &[&str],
+        not_before: u64,
+        not_after: u64,
+    ) -> Result<Self, CryptoError> {
+        let mut certificate =
+            Self::unsigned(issuer.0.clone(), subject, operations, not_before, not_after)?;
+        certificate.signature = scheme.sign(&certificate.message(), issuer.1)?;
+        Ok(certificate)
+    }
+
+    /// Like `issue` with the issuer's key held by a `KeyProvider`, e.g. in an HSM
+    pub fn issue_with_provider<T: SignatureScheme>(
+        scheme: &T,
+        issuer: &dyn KeyProvider,
+        subject: &PublicKey,
+        operations: &[&str],
+        not_before: u64,
+        not_after: u64,
+    ) -> Result<Self, CryptoError> {
+        let mut certificate =
+            Self::unsigned(issuer.public_key()?, subject, operations, not_before, not_after)?;
+        certificate.signature = scheme.sign_with_provider(&certificate.message(), issuer)?;
+        Ok(certificate)
+    }
+
+    fn unsigned(
+        issuer: PublicKey,
+        subject: &PublicKey,
+        operations: &[&str],
+        not_before: u64,
+        not_after: u64,
+    ) -> Result<Self, CryptoError> {
+        if &issuer == subject {
+            return Err(CryptoError::KeyGenError(
+                "A key can't delegate to itself".to_string(),
+            ));
+        }
+        if not_before >= not_after {
+            return Err(CryptoError::KeyGenError(
+                "The certificate must expire after it becomes valid".to_string(),
+            ));
+        }
+        if operations.is_empty() {
+            return Err(CryptoError::KeyGenError(
+                "The certificate must authorize at least one operation".to_string(),
+            ));
+        }
+        let mut operations = operations
+            .iter()
+            .map(|o| o.to_string())
+            .collect::<Vec<_>>();
+        operations.sort();
+        operations.dedup();
+        Ok(DelegationCertificate {
+            issuer,
+            subject: subject.clone(),
+            operations,
+            not_before,
+            not_after,
+            signature: Vec::new(),
+        })
+    }
+
+    /// Return true iff the issuer's signature is valid
+    pub fn verify<T: SignatureScheme>(&self, scheme: &T) -> Result<bool, CryptoError> {
+        if self.issuer == self.subject {
+            return Ok(false);
+        }
+        Ok(scheme
+            .verify(&self.message(), &self.signature, &self.issuer)
+            .unwrap_or(false))
+    }
+
+    /// Return true iff the certificate is valid at `time`
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.not_before <= time && time < self.not_after
+    }
+
+    /// Return true iff the subject may perform `operation`
+    pub fn allows(&self, operation: &str) -> bool {
+        self.operations.iter().any(|o| o == operation)
+    }
+
+    /// Serialize as the length prefixed keys, the number of operations followed by each
+    /// length prefixed operation, the validity period and the length prefixed signature.
+    /// Integers are big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        push_field(&mut out, &self.issuer[..]);
+        push_field(&mut out, &self.subject[..]);
+        out.extend_from_slice(&(self.operations.len() as u32).to_be_bytes());
+        for operation in &self.operations {
+            push_field(&mut out, operation.as_bytes());
+        }
+        out.extend_from_slice(&self.not_before.to_be_bytes());
+        out.extend_from_slice(&self.not_after.to_be_bytes());
+        push_field(&mut out, &self.signature);
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut rest = data;
+        let issuer = PublicKey(take_field(&mut rest)?);
+        let subject = PublicKey(take_field(&mut rest)?);
+        let count = u32::from_be_bytes(take_array(&mut rest)?);
+        let mut operations = Vec::new();
+        for _ in 0..count {
+            let operation = String::from_utf8(take_field(&mut rest)?).map_err(|_| {
+                CryptoError::ParseError("Delegated operation is not UTF-8".to_string())
+            })?;
+            operations.push(operation);
+        }
+        let not_before = u64::from_be_bytes(take_array(&mut rest)?);
+        let not_after = u64::from_be_bytes(take_array(&mut rest)?);
+        let signature = take_field(&mut rest)?;
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after delegation certificate".to_string(),
+            ));
+        }
+        Ok(DelegationCertificate {
+            issuer,
+            subject,
+            operations,
+            not_before,
+            not_after,
+            signature,
+        })
+    }
+
+    /// The message signed by the issuer
+    fn message(&self) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.input(CERTIFICATE_DOMAIN);
+        hasher.input((self.issuer.len() as u64).to_be_bytes());
+        hasher.input(&self.issuer[..]);
+        hasher.input((self.subject.len() as u64).to_be_bytes());
+        hasher.input(&self.subject[..]);
+        hasher.input((self.operations.len() as u64).to_be_bytes());
+        for operation in &self.operations {
+            hasher.input((operation.len() as u64).to_be_bytes());
+            hasher.input(operation.as_bytes());
+        }
+        hasher.input(self.not_before.to_be_bytes());
+        hasher.input(self.not_after.to_be_bytes());
+        hasher.result().to_vec()
+    }
+}
+
+/// Check the certificates that delegate from `root` for `operation` at `time` and
+/// return the key allowed to perform it.
+///
+/// The certificates must be in order from the one issued by `root`: each one's issuer
+/// is the previous one's subject, every one is valid at `time` and only authorizes
+/// operations its issuer was authorized for. The last one must allow `operation`.
+/// Returns an error naming the first certificate that is out of place, doesn't verify
+/// or has expired.
+pub fn verify_delegation_chain<T: SignatureScheme>(
+    scheme: &T,
+    root: &PublicKey,
+    certificates: &[DelegationCertificate],
+    operation: &str,
+    time: u64,
+) -> Result<PublicKey, CryptoError> {
+    let mut current = root;
+    let mut parent: Option<&DelegationCertificate> = None;
+    for (index, certificate) in certificates.iter().enumerate() {
+        if &certificate.issuer != current {
+            return Err(CryptoError::GeneralError(format!(
+                "Delegation {} isn't issued by the previous subject",
+                index
+            )));
+        }
+        if !certificate.verify(scheme)? {
+            return Err(CryptoError::GeneralError(format!(
+                "Delegation {} is not signed by its issuer",
+                index
+            )));
+        }
+        if !certificate.is_valid_at(time) {
+            return Err(CryptoError::GeneralError(format!(
+                "Delegation {} is not valid at {}",
+                index, time
+            )));
+        }
+        if let Some(parent) = parent {
+            if !certificate.operations.iter().all(|o| parent.allows(o)) {
+                return Err(CryptoError::GeneralError(format!(
+                    "Delegation {} authorizes operations its issuer wasn't authorized for",
+                    index
+                )));
+            }
+        }
+        current = &certificate.subject;
+        parent = Some(certificate);
+    }
+    match parent {
+        Some(last) if last.allows(operation) => Ok(current.clone()),
+        Some(_) => Err(CryptoError::GeneralError(format!(
+            "The delegated key is not authorized for {}",
+            operation
+        ))),
+        None => Err(CryptoError::GeneralError(
+            "No delegation certificates".to_string(),
+        )),
+    }
+}
+
+fn push_field(out: &mut Vec<u8>, field: &[u8]) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+fn take_array<A: Default + AsMut<[u8]>>(rest: &mut &[u8]) -> Result<A, CryptoError> {
+    let mut array = A::default();
+    let len = array.as_mut().len();
+    if rest.len() < len {
+        return Err(CryptoError::ParseError(
+            "Delegation certificate is truncated".to_string(),
+        ));
+    }
+    array.as_mut().copy_from_slice(&rest[..len]);
+    *rest = &rest[len..];
+    Ok(array)
+}
+
+fn take_field(rest: &mut &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let len = u32::from_be_bytes(take_array(rest)?) as usize;
+    if rest.len() < len {
+        return Err(CryptoError::ParseError(
+            "Delegation certificate is truncated".to_string(),
+        ));
+    }
+    let field = rest[..len].to_vec();
+    *rest = &rest[len..];
+    Ok(field)
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+
+    fn keys(n: usize) -> Vec<(PublicKey, PrivateKey)> {
+        let scheme = Ed25519Sha512::new();
+        (0..n).map(|_| scheme.keypair(None).unwrap()).collect()
+    }
+
+    fn delegate(
+        issuer: &(PublicKey, PrivateKey),
+        subject: &(PublicKey, PrivateKey),
+        operations: &[&str],
+    ) -> DelegationCertificate {
+        DelegationCertificate::issue(
+            &Ed25519Sha512::new(),
+            (&issuer.0, &issuer.1),
+            &subject.0,
+            operations,
+            1000,
+            2000,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn certificate_verifies() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(3);
+        let certificate = delegate(&keys[0], &keys[1], &["sign", "decrypt", "sign"]);
+        assert!(certificate.verify(&scheme).unwrap());
+        assert_eq!(certificate.operations, ["decrypt", "sign"]);
+        assert!(certificate.allows("sign"));
+        assert!(!certificate.allows("rotate"));
+        assert!(!certificate.is_valid_at(999));
+        assert!(certificate.is_valid_at(1000));
+        assert!(!certificate.is_valid_at(2000));
+
+        let parsed = DelegationCertificate::from_bytes(&certificate.to_bytes()).unwrap();
+        assert_eq!(parsed, certificate);
+        let bytes = certificate.to_bytes();
+        assert!(DelegationCertificate::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(DelegationCertificate::from_bytes(&trailing).is_err());
+
+        let mut extended = certificate.clone();
+        extended.not_after = 3000;
+        assert!(!extended.verify(&scheme).unwrap());
+        let mut widened = certificate.clone();
+        widened.operations.push("rotate".to_string());
+        assert!(!widened.verify(&scheme).unwrap());
+        let mut redirected = certificate;
+        redirected.subject = keys[2].0.clone();
+        assert!(!redirected.verify(&scheme).unwrap());
+
+        let (pk, sk) = &keys[0];
+        assert!(DelegationCertificate::issue(&scheme, (pk, sk), pk, &["sign"], 0, 1).is_err());
+        let other = &keys[1].0;
+        assert!(DelegationCertificate::issue(&scheme, (pk, sk), other, &["sign"], 1, 1).is_err());
+        assert!(DelegationCertificate::issue(&scheme, (pk, sk), other, &[], 0, 1).is_err());
+    }
+
+    #[test]
+    fn provider_certificate() {
+        use signatures::provider::SoftwareKeyProvider;
+
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(2);
+        let issuer = SoftwareKeyProvider::<Ed25519Sha512>::new(keys[0].1.clone()).unwrap();
+        let certificate = DelegationCertificate::issue_with_provider(
+            &scheme,
+            &issuer,
+            &keys[1].0,
+            &["sign"],
+            1000,
+            2000,
+        )
+        .unwrap();
+        assert!(certificate.verify(&scheme).unwrap());
+        assert_eq!(certificate, delegate(&keys[0], &keys[1], &["sign"]));
+    }
+
+    #[test]
+    fn chain_verifies() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(4);
+        let chain = vec![
+            delegate(&keys[0], &keys[1], &["sign", "decrypt"]),
+            delegate(&keys[1], &keys[2], &["sign"]),
+        ];
+        assert_eq!(
+            verify_delegation_chain(&scheme, &keys[0].0, &chain, "sign", 1500).unwrap(),
+            keys[2].0
+        );
+        assert_eq!(
+            verify_delegation_chain(&scheme, &keys[0].0, &chain[..1], "decrypt", 1500).unwrap(),
+            keys[1].0
+        );
+        assert!(verify_delegation_chain(&scheme, &keys[0].0, &chain, "decrypt", 1500).is_err());
+        assert!(verify_delegation_chain(&scheme, &keys[0].0, &chain, "sign", 2000).is_err());
+        assert!(verify_delegation_chain(&scheme, &keys[0].0, &[], "sign", 1500).is_err());
+        assert!(verify_delegation_chain(&scheme, &keys[1].0, &chain, "sign", 1500).is_err());
+
+        let reordered = [chain[1].clone(), chain[0].clone()];
+        assert!(verify_delegation_chain(&scheme, &keys[0].0, &reordered, "sign", 1500).is_err());
+
+        // A delegated key can't grant more than it was given
+        let widening = [
+            chain[1].clone(),
+            delegate(&keys[2], &keys[3], &["sign", "decrypt"]),
+        ];
+        assert!(verify_delegation_chain(&scheme, &keys[1].0, &widening, "sign", 1500).is_err());
+
+        // Nor can a key outside the chain
+        let mut forged = chain;
+        forged[1] = delegate(&keys[3], &keys[2], &["sign"]);
+        forged[1].issuer = keys[1].0.clone();
+        assert!(verify_delegation_chain(&scheme, &keys[0].0, &forged, "sign", 1500).is_err());
+    }
+}
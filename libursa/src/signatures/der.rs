@@ -0,0 +1,270 @@
+//! Just enough DER to read ECDSA signatures, public keys and the keys in certificates,
+//! and to write the keys of `keys::encoding`.
+#![cfg_attr(
+    not(any(feature = "aws_kms", feature = "gcp_kms", feature = "webauthn")),
+    allow(dead_code)
+)]
+
+use CryptoError;
+
+pub(crate) const P256_FIELD_SIZE: usize = 32;
+/// The AlgorithmIdentifier of a P-256 SubjectPublicKeyInfo, id-ecPublicKey with prime256v1
+pub(crate) const P256_ALGORITHM_ID: &[u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d,
+    0x03, 0x01, 0x07,
+];
+
+pub(crate) const DER_INTEGER: u8 = 0x02;
+pub(crate) const DER_BIT_STRING: u8 = 0x03;
+#[cfg(feature = "key_encoding")]
+pub(crate) const DER_OCTET_STRING: u8 = 0x04;
+pub(crate) const DER_SEQUENCE: u8 = 0x30;
+/// The explicit version tag at the start of a TBSCertificate
+#[cfg(feature = "webauthn")]
+const DER_VERSION: u8 = 0xa0;
+
+/// Convert a DER encoded ECDSA signature into `r || s` with each half `size` bytes
+pub fn ecdsa_der_to_raw(der: &[u8], size: usize) -> Result<Vec<u8>, CryptoError> {
+    let (signature, rest) = der_read(der, DER_SEQUENCE)?;
+    if !rest.is_empty() {
+        return Err(parse_error("Trailing bytes after ECDSA signature"));
+    }
+    let (r, signature) = der_read(signature, DER_INTEGER)?;
+    let (s, signature) = der_read(signature, DER_INTEGER)?;
+    if !signature.is_empty() {
+        return Err(parse_error("Trailing bytes in ECDSA signature"));
+    }
+    let mut raw = vec![0u8; 2 * size];
+    for (i, n) in [r, s].iter().enumerate() {
+        let start = n.iter().position(|b| *b != 0).unwrap_or(n.len());
+        let n = &n[start..];
+        if n.len() > size {
+            return Err(parse_error("ECDSA signature value is too large"));
+        }
+        raw[(i + 1) * size - n.len()..(i + 1) * size].copy_from_slice(n);
+    }
+    Ok(raw)
+}
+
+/// Extract the uncompressed point from a DER encoded P-256 SubjectPublicKeyInfo
+pub fn p256_spki_to_point(spki: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (info, rest) = der_read(spki, DER_SEQUENCE)?;
+    if !rest.is_empty() {
+        return Err(parse_error("Trailing bytes after public key"));
+    }
+    let (algorithm, info) = der_read(info, DER_SEQUENCE)?;
+    if algorithm != P256_ALGORITHM_ID {
+        return Err(CryptoError::ParseError(
+            "Public key is not a P-256 key".to_string(),
+        ));
+    }
+    let (bits, info) = der_read(info, DER_BIT_STRING)?;
+    if !info.is_empty() || bits.len() != 2 + 2 * P256_FIELD_SIZE || bits[0] != 0 || bits[1] != 4 {
+        return Err(parse_error("Public key is not an uncompressed point"));
+    }
+    Ok(bits[1..].to_vec())
+}
+
+/// Decode the first PEM block in `pem`
+#[cfg(any(feature = "aws_kms", feature = "gcp_kms"))]
+pub fn pem_to_der(pem: &str) -> Result<Vec<u8>, CryptoError> {
+    pem_decode(pem).map(|(_, der)| der)
+}
+
+/// Decode the first PEM block in `pem` into its label and contents
+#[cfg(any(feature = "aws_kms", feature = "gcp_kms", feature = "key_encoding"))]
+pub fn pem_decode(pem: &str) -> Result<(String, Vec<u8>), CryptoError> {
+    let mut lines = pem
+        .lines()
+        .map(str::trim)
+        .skip_while(|l| !l.starts_with("-----BEGIN"));
+    let label = lines
+        .next()
+        .map(|l| l.trim_start_matches("-----BEGIN ").trim_end_matches('-'))
+        .ok_or_else(|| parse_error("No PEM block"))?
+        .to_string();
+    let body: String = lines.take_while(|l| !l.starts_with("-----END")).collect();
+    let der = base64::decode(&body)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid PEM: {}", e)))?;
+    Ok((label, der))
+}
+
+/// Encode `der` as a PEM block with `label`, in lines of 64 characters
+#[cfg(feature = "key_encoding")]
+pub fn pem_encode(label: &str, der: &[u8]) -> String {
+    let body = base64::encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Return the SubjectPublicKeyInfo of a DER encoded X.509 certificate
+#[cfg(feature = "webauthn")]
+pub fn certificate_spki(certificate: &[u8]) -> Result<&[u8], CryptoError> {
+    let (certificate, _) = der_read(certificate, DER_SEQUENCE)?;
+    let (mut tbs, _) = der_read(certificate, DER_SEQUENCE)?;
+    if tbs.first() == Some(&DER_VERSION) {
+        tbs = der_split(tbs)?.2;
+    }
+    // Skip the serial number, signature algorithm, issuer, validity and subject
+    for _ in 0..5 {
+        tbs = der_split(tbs)?.2;
+    }
+    let (_, spki, _) = der_split(tbs)?;
+    if spki.first() != Some(&DER_SEQUENCE) {
+        return Err(parse_error("Certificate has no public key"));
+    }
+    Ok(spki)
+}
+
+/// Read one DER item with `tag` and return its contents and the bytes after it
+pub(crate) fn der_read(data: &[u8], tag: u8) -> Result<(&[u8], &[u8]), CryptoError> {
+    if data.first() != Some(&tag) {
+        return Err(parse_error("Unexpected DER item"));
+    }
+    let (contents, _, rest) = der_split(data)?;
+    Ok((contents, rest))
+}
+
+/// Encode `contents` as one DER item with `tag`
+#[cfg(feature = "key_encoding")]
+pub(crate) fn der_write(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut item = vec![tag];
+    match contents.len() {
+        l if l < 0x80 => item.push(l as u8),
+        l if l < 0x100 => item.extend_from_slice(&[0x81, l as u8]),
+        l => item.extend_from_slice(&[0x82, (l >> 8) as u8, l as u8]),
+    }
+    item.extend_from_slice(contents);
+    item
+}
+
+/// The contents of a DER item, the whole item and the bytes after it
+type DerSplit<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Split the first DER item off `data`
+fn der_split(data: &[u8]) -> Result<DerSplit<'_>, CryptoError> {
+    if data.len() < 2 {
+        return Err(parse_error("DER item is truncated"));
+    }
+    let (length, header) = match data[1] {
+        l if l < 0x80 => (l as usize, 2),
+        0x81 if data.len() > 2 => (data[2] as usize, 3),
+        0x82 if data.len() > 3 => (((data[2] as usize) << 8) | data[3] as usize, 4),
+        _ => return Err(parse_error("Unsupported DER length")),
+    };
+    if data.len() - header < length {
+        return Err(parse_error("DER item is truncated"));
+    }
+    Ok((
+        &data[header..header + length],
+        &data[..header + length],
+        &data[header + length..],
+    ))
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// The P-256 SubjectPublicKeyInfo for the uncompressed `point`
+    pub fn spki_for_point(point: &[u8]) -> Vec<u8> {
+        let mut spki = vec![0x30, 0x59, 0x30, 0x13];
+        spki.extend_from_slice(P256_ALGORITHM_ID);
+        spki.extend_from_slice(&[0x03, 0x42, 0x00]);
+        spki.extend_from_slice(point);
+        spki
+    }
+
+    /// A SubjectPublicKeyInfo for the point `04 01 .. 01`
+    pub fn p256_spki() -> Vec<u8> {
+        let mut point = vec![4u8];
+        point.extend_from_slice(&[1u8; 64]);
+        spki_for_point(&point)
+    }
+
+    /// A DER signature where r has a leading zero for its sign bit and s is short
+    pub fn p256_der_signature() -> Vec<u8> {
+        let mut der = vec![0x30, 0x26, 0x02, 0x21, 0x00, 0xff];
+        der.extend_from_slice(&[0u8; 30]);
+        der.extend_from_slice(&[0x01, 0x02, 0x01, 0x01]);
+        der
+    }
+
+    #[test]
+    fn ecdsa_der_normalization() {
+        let der = p256_der_signature();
+        let raw = ecdsa_der_to_raw(&der, 32).unwrap();
+        assert_eq!(raw.len(), 64);
+        assert_eq!(raw[0], 0xff);
+        assert_eq!(raw[31], 0x01);
+        assert_eq!(&raw[32..63], &[0u8; 31][..]);
+        assert_eq!(raw[63], 0x01);
+
+        assert!(ecdsa_der_to_raw(&der[..der.len() - 1], 32).is_err());
+        let mut long = der.clone();
+        long.push(0);
+        assert!(ecdsa_der_to_raw(&long, 32).is_err());
+    }
+
+    #[test]
+    fn p256_public_key_normalization() {
+        let point = p256_spki_to_point(&p256_spki()).unwrap();
+        assert_eq!(point.len(), 65);
+        assert_eq!(point[0], 4);
+
+        let mut other_curve = p256_spki();
+        other_curve[22] = 0x08;
+        assert!(p256_spki_to_point(&other_curve).is_err());
+        assert!(p256_spki_to_point(&p256_spki()[..80]).is_err());
+    }
+
+    /// A certificate for `spki` with empty names and an empty signature
+    #[cfg(feature = "webauthn")]
+    pub fn certificate_for(spki: &[u8]) -> Vec<u8> {
+        fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+            let mut item = vec![tag, contents.len() as u8];
+            item.extend_from_slice(contents);
+            item
+        }
+        let mut tbs = tlv(DER_VERSION, &tlv(DER_INTEGER, &[2]));
+        tbs.extend_from_slice(&tlv(DER_INTEGER, &[1]));
+        for _ in 0..4 {
+            tbs.extend_from_slice(&tlv(DER_SEQUENCE, &[]));
+        }
+        tbs.extend_from_slice(spki);
+        let mut certificate = tlv(DER_SEQUENCE, &tbs);
+        certificate.extend_from_slice(&tlv(DER_SEQUENCE, &[]));
+        certificate.extend_from_slice(&tlv(DER_BIT_STRING, &[0]));
+        tlv(DER_SEQUENCE, &certificate)
+    }
+
+    #[cfg(feature = "webauthn")]
+    #[test]
+    fn certificate_public_key() {
+        assert_eq!(
+            certificate_spki(&certificate_for(&p256_spki())).unwrap(),
+            &p256_spki()[..]
+        );
+        assert!(certificate_spki(&p256_spki()).is_err());
+    }
+
+    #[cfg(any(feature = "aws_kms", feature = "gcp_kms"))]
+    #[test]
+    fn pem_decoding() {
+        let pem = format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n{}\n-----END PUBLIC KEY-----\n",
+            &base64::encode(p256_spki())[..64],
+            &base64::encode(p256_spki())[64..]
+        );
+        assert_eq!(pem_to_der(&pem).unwrap(), p256_spki());
+    }
+}
@@ -0,0 +1,51 @@
+//! ECDSA verification over P-256 with the `p256` arithmetic, for WebAuthn authenticators
+//! and P-256 keys in signature policies, e.g. in HSMs.
+
+use p256::elliptic_curve::ff::PrimeField;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::{AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar};
+
+const FIELD_SIZE: usize = 32;
+
+pub(crate) fn p256_point(point: &[u8]) -> Option<AffinePoint> {
+    EncodedPoint::from_bytes(point)
+        .ok()
+        .and_then(|p| AffinePoint::from_encoded_point(&p).into())
+}
+
+pub(crate) fn field_bytes(bytes: &[u8]) -> FieldBytes {
+    let mut field = FieldBytes::default();
+    field.copy_from_slice(bytes);
+    field
+}
+
+pub(crate) fn p256_scalar(bytes: &[u8]) -> Option<Scalar> {
+    Scalar::from_repr(field_bytes(bytes)).filter(|s| !bool::from(s.is_zero()))
+}
+
+/// Check the `r || s` signature on `digest` with the uncompressed P-256 `point`
+pub(crate) fn verify_es256_digest(point: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    if signature.len() != 2 * FIELD_SIZE {
+        return false;
+    }
+    let q = match p256_point(point) {
+        Some(q) => q,
+        None => return false,
+    };
+    let (r, s) = match (
+        p256_scalar(&signature[..FIELD_SIZE]),
+        p256_scalar(&signature[FIELD_SIZE..]),
+    ) {
+        (Some(r), Some(s)) => (r, s),
+        _ => return false,
+    };
+    let e = Scalar::from_bytes_reduced(&field_bytes(digest));
+    let s_inv = s.invert().unwrap();
+    let point = (ProjectivePoint::generator() * (e * s_inv)
+        + ProjectivePoint::from(q) * (r * s_inv))
+        .to_affine();
+    if bool::from(point.is_identity()) {
+        return false;
+    }
+    Scalar::from_bytes_reduced(point.to_encoded_point(false).x()) == r
+}
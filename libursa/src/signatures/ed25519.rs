@@ -1,16 +1,25 @@
 pub const ALGORITHM_NAME: &str = "ED25519_SHA2_512";
+const HALF_AGGREGATE_DOMAIN: &[u8] = b"ED25519_SHA2_512_HALF_AGGREGATE";
+const HEDGED_DOMAIN: &[u8] = b"ED25519_SHA2_512_HEDGED";
+const POINT_SIZE: usize = 32;
 
-use super::{KeyGenOption, SignatureScheme};
-#[cfg(any(feature = "x25519", feature = "x25519_asm"))]
-use ed25519_dalek::SecretKey as SK;
-use ed25519_dalek::{Keypair, PublicKey as PK, Signature, Signer, Verifier};
+use super::{HedgedSignatureScheme, KeyGenOption, SignatureScheme};
+use curve25519_dalek::{
+    constants::{ED25519_BASEPOINT_POINT, ED25519_BASEPOINT_TABLE},
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use ed25519_dalek::{
+    Keypair, PublicKey as PK, SecretKey as SK, Signature, Signer, Verifier, SECRET_KEY_LENGTH,
+};
 pub use ed25519_dalek::{
     EXPANDED_SECRET_KEY_LENGTH as PRIVATE_KEY_SIZE, PUBLIC_KEY_LENGTH as PUBLIC_KEY_SIZE,
     SIGNATURE_LENGTH as SIGNATURE_SIZE,
 };
 use keys::{PrivateKey, PublicKey};
 use rand::rngs::OsRng;
-use rand::SeedableRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 use sha2::Digest;
 use std::convert::TryFrom;
@@ -36,8 +45,6 @@ impl Ed25519Sha512 {
     /// let curve_sk = Ed25519Sha512::sign_key_to_key_exchange(&sk).unwrap();
     /// ```
     pub fn ver_key_to_key_exchange(pk: &PublicKey) -> Result<PublicKey, CryptoError> {
-        use curve25519_dalek::edwards::CompressedEdwardsY;
-
         // Verify it's a valid public key
         PK::from_bytes(&pk[..]).map_err(|e| CryptoError::ParseError(e.to_string()))?;
         // PublicKey is a CompressedEdwardsY in dalek. So we decompress it to get the
@@ -106,6 +113,186 @@ impl Ed25519Sha512 {
     }
 }
 
+impl Ed25519Sha512 {
+    /// Generate `count` keypairs from `rng`.
+    ///
+    /// Draws the secrets for every key in one call to `rng` and derives each
+    /// public key with the precomputed basepoint table. Meant for tests and
+    /// services that create many ephemeral keys.
+    ///
+    /// # Example
+    /// ```
+    /// use rand::rngs::OsRng;
+    /// use ursa::signatures::ed25519::Ed25519Sha512;
+    /// use ursa::signatures::SignatureScheme;
+    ///
+    /// let keys = Ed25519Sha512::new().generate_keypairs(100, &mut OsRng).unwrap();
+    /// assert_eq!(keys.len(), 100);
+    /// ```
+    pub fn generate_keypairs<R: RngCore + CryptoRng>(
+        &self,
+        count: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(PublicKey, PrivateKey)>, CryptoError> {
+        let mut seeds = vec![0u8; count * SECRET_KEY_LENGTH];
+        rng.try_fill_bytes(&mut seeds)
+            .map_err(|e| CryptoError::KeyGenError(e.to_string()))?;
+        let keys = seeds
+            .chunks(SECRET_KEY_LENGTH)
+            .map(|seed| {
+                let sk =
+                    SK::from_bytes(seed).map_err(|e| CryptoError::KeyGenError(e.to_string()))?;
+                let pk = PK::from(&sk);
+                let mut private = Vec::with_capacity(PRIVATE_KEY_SIZE);
+                private.extend_from_slice(sk.as_bytes());
+                private.extend_from_slice(pk.as_bytes());
+                Ok((PublicKey(pk.to_bytes().to_vec()), PrivateKey(private)))
+            })
+            .collect();
+        seeds.zeroize();
+        keys
+    }
+
+    /// Half-aggregate signatures over distinct messages into one value.
+    ///
+    /// The aggregate keeps every signature's `R` and folds the `s` values into one
+    /// scalar so it is `32 * (n + 1)` bytes instead of `64 * n`.
+    /// Unlike interactive aggregation the signers don't need to cooperate.
+    /// `messages`, `signatures` and `pks` must be in the same order and the aggregate
+    /// only verifies with that order.
+    /// (see <https://eprint.iacr.org/2021/350>)
+    ///
+    /// # Example
+    /// ```
+    /// use ursa::signatures::ed25519::Ed25519Sha512;
+    /// use ursa::signatures::SignatureScheme;
+    ///
+    /// let scheme = Ed25519Sha512::new();
+    /// let (pk1, sk1) = scheme.keypair(None).unwrap();
+    /// let (pk2, sk2) = scheme.keypair(None).unwrap();
+    /// let sig1 = scheme.sign(b"block 1", &sk1).unwrap();
+    /// let sig2 = scheme.sign(b"block 2", &sk2).unwrap();
+    /// let messages: &[&[u8]] = &[b"block 1", b"block 2"];
+    /// let aggregate = scheme
+    ///     .aggregate_signatures(messages, &[&sig1[..], &sig2[..]], &[pk1.clone(), pk2.clone()])
+    ///     .unwrap();
+    /// assert!(scheme.verify_aggregate(messages, &aggregate, &[pk1, pk2]).unwrap());
+    /// ```
+    pub fn aggregate_signatures(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        pks: &[PublicKey],
+    ) -> Result<Vec<u8>, CryptoError> {
+        if messages.is_empty() || messages.len() != signatures.len() || messages.len() != pks.len()
+        {
+            return Err(CryptoError::ParseError(format!(
+                "Expected the same non-zero number of messages, signatures and public keys"
+            )));
+        }
+        let mut aggregate = Vec::with_capacity(POINT_SIZE * (signatures.len() + 1));
+        for sig in signatures {
+            if sig.len() != SIGNATURE_SIZE {
+                return Err(CryptoError::ParseError(format!("Invalid signature length")));
+            }
+            aggregate.extend_from_slice(&sig[..POINT_SIZE]);
+        }
+        let weights = aggregate_weights(messages, &aggregate, pks);
+        let mut s = Scalar::zero();
+        for (sig, z) in signatures.iter().zip(weights.iter()) {
+            let s_i = Scalar::from_canonical_bytes(*array_ref!(sig, POINT_SIZE, POINT_SIZE))
+                .ok_or_else(|| CryptoError::ParseError(format!("Invalid signature")))?;
+            s += z * s_i;
+        }
+        aggregate.extend_from_slice(s.as_bytes());
+        Ok(aggregate)
+    }
+
+    /// Verify an aggregate created by `aggregate_signatures`.
+    ///
+    /// All signatures are checked at once with a single multiscalar multiplication.
+    /// Like batch verification the check is cofactored.
+    pub fn verify_aggregate(
+        &self,
+        messages: &[&[u8]],
+        aggregate: &[u8],
+        pks: &[PublicKey],
+    ) -> Result<bool, CryptoError> {
+        let n = messages.len();
+        if n == 0 || n != pks.len() || aggregate.len() != POINT_SIZE * (n + 1) {
+            return Err(CryptoError::ParseError(format!(
+                "Aggregate does not match the number of messages and public keys"
+            )));
+        }
+        let (rs, s) = aggregate.split_at(POINT_SIZE * n);
+        let s = match Scalar::from_canonical_bytes(*array_ref!(s, 0, POINT_SIZE)) {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+        let weights = aggregate_weights(messages, rs, pks);
+
+        // s * B - sum(z_i * R_i) - sum(z_i * c_i * A_i) == identity
+        let mut scalars = Vec::with_capacity(2 * n + 1);
+        let mut points = Vec::with_capacity(2 * n + 1);
+        scalars.push(s);
+        points.push(ED25519_BASEPOINT_POINT);
+        for (((m, r), pk), z) in messages
+            .iter()
+            .zip(rs.chunks(POINT_SIZE))
+            .zip(pks.iter())
+            .zip(weights.iter())
+        {
+            if pk.len() != PUBLIC_KEY_SIZE {
+                return Err(CryptoError::ParseError(format!(
+                    "Invalid public key provided"
+                )));
+            }
+            let a = match CompressedEdwardsY::from_slice(&pk[..]).decompress() {
+                Some(a) => a,
+                None => {
+                    return Err(CryptoError::ParseError(format!(
+                        "Invalid public key provided"
+                    )))
+                }
+            };
+            let r_point = match CompressedEdwardsY::from_slice(r).decompress() {
+                Some(r) => r,
+                None => return Ok(false),
+            };
+            let c = hash_to_scalar(sha2::Sha512::new().chain(r).chain(&pk[..]).chain(m));
+            scalars.push(-z);
+            points.push(r_point);
+            scalars.push(-(z * c));
+            points.push(a);
+        }
+        Ok(EdwardsPoint::vartime_multiscalar_mul(scalars, points)
+            .mul_by_cofactor()
+            .is_identity())
+    }
+}
+
+fn hash_to_scalar(hasher: sha2::Sha512) -> Scalar {
+    let hash = hasher.result();
+    Scalar::from_bytes_mod_order_wide(array_ref!(hash.as_slice(), 0, 64))
+}
+
+/// Derive the weight for each signature from everything being aggregated
+/// so a signer can't choose its signature to cancel out another's.
+fn aggregate_weights(messages: &[&[u8]], rs: &[u8], pks: &[PublicKey]) -> Vec<Scalar> {
+    let mut transcript = sha2::Sha512::new();
+    transcript.input(HALF_AGGREGATE_DOMAIN);
+    transcript.input(&(messages.len() as u64).to_be_bytes()[..]);
+    for ((m, r), pk) in messages.iter().zip(rs.chunks(POINT_SIZE)).zip(pks.iter()) {
+        transcript.input(r);
+        transcript.input(&pk[..]);
+        transcript.input(&(m.len() as u64).to_be_bytes()[..]);
+        transcript.input(m);
+    }
+    (0..messages.len() as u64)
+        .map(|i| hash_to_scalar(transcript.clone().chain(&i.to_be_bytes()[..])))
+        .collect()
+}
+
 impl SignatureScheme for Ed25519Sha512 {
     fn new() -> Self {
         Self
@@ -117,7 +304,7 @@ impl SignatureScheme for Ed25519Sha512 {
         let kp = match option {
             Some(mut o) => match o {
                 KeyGenOption::UseSeed(ref mut s) => {
-                    let hash = sha2::Sha256::digest(s.as_slice());
+                    let hash = sha2::Sha256::digest(s.expose_secret());
                     s.zeroize();
                     let mut rng = ChaChaRng::from_seed(*array_ref!(hash.as_slice(), 0, 32));
                     Keypair::generate(&mut rng)
@@ -164,10 +351,62 @@ impl SignatureScheme for Ed25519Sha512 {
     }
 }
 
+impl HedgedSignatureScheme for Ed25519Sha512 {
+    /// Sign like RFC 8032 except that the nonce hash also covers `noise`.
+    /// The signature is a standard Ed25519 signature.
+    fn sign_with_noise(
+        &self,
+        message: &[u8],
+        sk: &PrivateKey,
+        noise: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        if sk.len() != PRIVATE_KEY_SIZE {
+            return Err(CryptoError::KeyGenError(format!(
+                "Invalid private key provided"
+            )));
+        }
+        let mut h = sha2::Sha512::digest(&sk[..SECRET_KEY_LENGTH]);
+        let mut a_bytes = *array_ref!(h.as_slice(), 0, 32);
+        a_bytes[0] &= 248;
+        a_bytes[31] &= 127;
+        a_bytes[31] |= 64;
+        let mut a = Scalar::from_bits(a_bytes);
+        // The public key is derived again so a corrupted copy in `sk` can't leak `a`
+        let public = (&a * &ED25519_BASEPOINT_TABLE).compress();
+
+        let mut r = hash_to_scalar(
+            sha2::Sha512::new()
+                .chain(HEDGED_DOMAIN)
+                .chain(&(noise.len() as u64).to_le_bytes()[..])
+                .chain(noise)
+                .chain(&h[32..])
+                .chain(message),
+        );
+        let big_r = (&r * &ED25519_BASEPOINT_TABLE).compress();
+        let k = hash_to_scalar(
+            sha2::Sha512::new()
+                .chain(big_r.as_bytes())
+                .chain(public.as_bytes())
+                .chain(message),
+        );
+        let s = r + k * a;
+
+        h.as_mut_slice().zeroize();
+        a_bytes.zeroize();
+        a.zeroize();
+        r.zeroize();
+
+        let mut signature = Vec::with_capacity(SIGNATURE_SIZE);
+        signature.extend_from_slice(big_r.as_bytes());
+        signature.extend_from_slice(s.as_bytes());
+        Ok(signature)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use self::Ed25519Sha512;
-    use super::super::{SignatureScheme, Signer};
+    use super::super::{HedgedSignatureScheme, SignatureScheme, Signer};
     use super::*;
     use keys::{KeyGenOption, PrivateKey, PublicKey};
     use libsodium_ffi as ffi;
@@ -204,6 +443,78 @@ mod test {
         assert_eq!(p1, PublicKey(hex::decode(PUBLIC_KEY).unwrap()));
     }
 
+    #[test]
+    fn ed25519_generate_keypairs() {
+        let scheme = Ed25519Sha512::new();
+        let keys = scheme.generate_keypairs(20, &mut OsRng).unwrap();
+        assert_eq!(keys.len(), 20);
+        for (i, (p, s)) in keys.iter().enumerate() {
+            assert_eq!(p.len(), PUBLIC_KEY_SIZE);
+            assert_eq!(s.len(), PRIVATE_KEY_SIZE);
+            let (p1, s1) = scheme
+                .keypair(Some(KeyGenOption::FromSecretKey(s.clone())))
+                .unwrap();
+            assert_eq!(&p1, p);
+            assert_eq!(&s1, s);
+            let sig = scheme.sign(MESSAGE_1, s).unwrap();
+            assert!(scheme.verify(MESSAGE_1, &sig, p).unwrap());
+            assert!(keys[i + 1..].iter().all(|(p2, _)| p2 != p));
+        }
+        assert!(scheme.generate_keypairs(0, &mut OsRng).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ed25519_half_aggregate() {
+        let scheme = Ed25519Sha512::new();
+        let keys = scheme.generate_keypairs(5, &mut OsRng).unwrap();
+        let messages = (0..5)
+            .map(|i| format!("block {}", i).into_bytes())
+            .collect::<Vec<_>>();
+        let messages = messages.iter().map(|m| m.as_slice()).collect::<Vec<_>>();
+        let signatures = keys
+            .iter()
+            .zip(messages.iter())
+            .map(|((_, s), m)| scheme.sign(m, s).unwrap())
+            .collect::<Vec<_>>();
+        let signatures = signatures.iter().map(|s| s.as_slice()).collect::<Vec<_>>();
+        let pks = keys.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+
+        let aggregate = scheme
+            .aggregate_signatures(&messages, &signatures, &pks)
+            .unwrap();
+        assert_eq!(aggregate.len(), 32 * 6);
+        assert!(scheme
+            .verify_aggregate(&messages, &aggregate, &pks)
+            .unwrap());
+
+        // Reordered messages
+        let mut swapped = messages.clone();
+        swapped.swap(0, 1);
+        assert!(!scheme.verify_aggregate(&swapped, &aggregate, &pks).unwrap());
+
+        // Tampered aggregate
+        let mut tampered = aggregate.clone();
+        tampered[0] ^= 1;
+        assert!(!scheme.verify_aggregate(&messages, &tampered, &pks).unwrap());
+
+        // One bad signature spoils the aggregate
+        let mut bad = signatures[2].to_vec();
+        bad[40] ^= 1;
+        let mut with_bad = signatures.clone();
+        with_bad[2] = &bad;
+        let aggregate_bad = scheme.aggregate_signatures(&messages, &with_bad, &pks);
+        assert!(aggregate_bad
+            .map(|a| !scheme.verify_aggregate(&messages, &a, &pks).unwrap())
+            .unwrap_or(true));
+
+        assert!(scheme
+            .verify_aggregate(&messages[..4], &aggregate, &pks[..4])
+            .is_err());
+        assert!(scheme
+            .aggregate_signatures(&messages[..4], &signatures, &pks)
+            .is_err());
+    }
+
     #[test]
     fn ed25519_verify() {
         let scheme = Ed25519Sha512::new();
@@ -275,6 +586,33 @@ mod test {
         }
     }
 
+    #[test]
+    fn ed25519_hedged_sign() {
+        let scheme = Ed25519Sha512::new();
+        let s = PrivateKey(hex::decode(PRIVATE_KEY).unwrap());
+        let p = PublicKey(hex::decode(PUBLIC_KEY).unwrap());
+
+        let first = scheme.sign_hedged(&MESSAGE_1, &s).unwrap();
+        let second = scheme.sign_hedged(&MESSAGE_1, &s).unwrap();
+        assert_eq!(first.len(), SIGNATURE_SIZE);
+        assert_ne!(first, second);
+        assert!(scheme.verify(&MESSAGE_1, &first, &p).unwrap());
+        assert!(scheme.verify(&MESSAGE_1, &second, &p).unwrap());
+
+        let noisy = scheme.sign_with_noise(&MESSAGE_1, &s, b"noise").unwrap();
+        assert_eq!(
+            noisy,
+            scheme.sign_with_noise(&MESSAGE_1, &s, b"noise").unwrap()
+        );
+        assert_ne!(hex::encode(&noisy), SIGNATURE_1);
+        assert!(scheme.verify(&MESSAGE_1, &noisy, &p).unwrap());
+        assert!(scheme.verify(b"another message", &noisy, &p).is_err());
+
+        let host = super::super::nonce::NonceSecret::generate().derive_child(b"signer-1");
+        let hosted = scheme.sign_hedged_on_host(&MESSAGE_1, &s, &host).unwrap();
+        assert!(scheme.verify(&MESSAGE_1, &hosted, &p).unwrap());
+    }
+
     #[cfg(any(feature = "x25519", feature = "x25519_asm"))]
     #[test]
     fn ed25519_to_x25519_default() {
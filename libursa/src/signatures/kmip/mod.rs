@@ -0,0 +1,496 @@
+//! A minimal KMIP 2.0 client.
+//!
+//! Supports creating key pairs, fetching keys, signing and destroying keys on an
+//! enterprise key manager so services can use keys that never leave it.
+//! Messages are sent in the TTLV encoding over a `KmipTransport` supplied by the
+//! caller, normally a mutually authenticated TLS connection to the server.
+//! `KmipKeyVault` maps the client onto the `KeyVault` trait.
+
+pub mod ttlv;
+
+use self::ttlv::*;
+use keys::PublicKey;
+use signatures::vault::{check_underived, KeyVault};
+use std::collections::HashMap;
+use CryptoError;
+
+const PROTOCOL_VERSION_MAJOR: i32 = 2;
+const PROTOCOL_VERSION_MINOR: i32 = 0;
+
+const OPERATION_CREATE_KEY_PAIR: u32 = 0x02;
+const OPERATION_GET: u32 = 0x0A;
+const OPERATION_DESTROY: u32 = 0x14;
+const OPERATION_SIGN: u32 = 0x21;
+
+const RESULT_STATUS_SUCCESS: u32 = 0x00;
+
+const USAGE_MASK_SIGN: i32 = 0x01;
+const USAGE_MASK_VERIFY: i32 = 0x02;
+
+/// Values of the KMIP Cryptographic Algorithm enumeration
+pub mod algorithm {
+    pub const RSA: u32 = 0x04;
+    pub const ECDSA: u32 = 0x06;
+    pub const EC: u32 = 0x18;
+}
+
+/// Values of the KMIP Recommended Curve enumeration
+pub mod curve {
+    pub const P_256: u32 = 0x07;
+    pub const P_384: u32 = 0x0A;
+}
+
+/// Values of the KMIP Digital Signature Algorithm enumeration
+pub mod signature_algorithm {
+    pub const SHA256_WITH_RSA: u32 = 0x05;
+    pub const ECDSA_WITH_SHA256: u32 = 0x0E;
+    pub const ECDSA_WITH_SHA384: u32 = 0x0F;
+}
+
+/// Values of the KMIP Key Format Type enumeration
+pub mod key_format {
+    pub const RAW: u32 = 0x01;
+    pub const PKCS1: u32 = 0x03;
+    pub const X509: u32 = 0x05;
+}
+
+/// Sends an encoded request to a KMIP server and returns the encoded response
+pub trait KmipTransport {
+    fn send(&self, request: &[u8]) -> Result<Vec<u8>, CryptoError>;
+}
+
+/// The attributes of a key pair to create
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPairSpec {
+    /// A value from `algorithm`
+    pub algorithm: u32,
+    /// The key length in bits
+    pub length: i32,
+    /// A value from `curve` for elliptic curve keys
+    pub curve: Option<u32>,
+}
+
+impl KeyPairSpec {
+    pub fn ec_p256() -> Self {
+        KeyPairSpec {
+            algorithm: algorithm::EC,
+            length: 256,
+            curve: Some(curve::P_256),
+        }
+    }
+
+    pub fn rsa(length: i32) -> Self {
+        KeyPairSpec {
+            algorithm: algorithm::RSA,
+            length,
+            curve: None,
+        }
+    }
+}
+
+/// The unique identifiers the server gave a new key pair
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyPairIds {
+    pub private_key: String,
+    pub public_key: String,
+}
+
+pub struct KmipClient<T: KmipTransport> {
+    transport: T,
+}
+
+impl<T: KmipTransport> KmipClient<T> {
+    pub fn new(transport: T) -> Self {
+        KmipClient { transport }
+    }
+
+    /// Create a signing key pair on the server
+    pub fn create_key_pair(&self, spec: &KeyPairSpec) -> Result<KeyPairIds, CryptoError> {
+        let mut common = vec![
+            Item::new(
+                TAG_CRYPTOGRAPHIC_ALGORITHM,
+                Value::Enumeration(spec.algorithm),
+            ),
+            Item::new(TAG_CRYPTOGRAPHIC_LENGTH, Value::Integer(spec.length)),
+        ];
+        if let Some(curve) = spec.curve {
+            common.push(Item::structure(
+                TAG_CRYPTOGRAPHIC_DOMAIN_PARAMETERS,
+                vec![Item::new(TAG_RECOMMENDED_CURVE, Value::Enumeration(curve))],
+            ));
+        }
+        let payload = self.call(
+            OPERATION_CREATE_KEY_PAIR,
+            vec![
+                Item::structure(TAG_COMMON_ATTRIBUTES, common),
+                Item::structure(
+                    TAG_PRIVATE_KEY_ATTRIBUTES,
+                    vec![usage_mask(USAGE_MASK_SIGN)],
+                ),
+                Item::structure(
+                    TAG_PUBLIC_KEY_ATTRIBUTES,
+                    vec![usage_mask(USAGE_MASK_VERIFY)],
+                ),
+            ],
+        )?;
+        Ok(KeyPairIds {
+            private_key: text(&payload, TAG_PRIVATE_KEY_UNIQUE_IDENTIFIER)?,
+            public_key: text(&payload, TAG_PUBLIC_KEY_UNIQUE_IDENTIFIER)?,
+        })
+    }
+
+    /// Fetch the key material of the object `id` in `format`, a value from `key_format`
+    pub fn get(&self, id: &str, format: u32) -> Result<Vec<u8>, CryptoError> {
+        let payload = self.call(
+            OPERATION_GET,
+            vec![
+                unique_identifier(id),
+                Item::new(TAG_KEY_FORMAT_TYPE, Value::Enumeration(format)),
+            ],
+        )?;
+        match payload.find_nested(TAG_KEY_MATERIAL) {
+            Some(Item {
+                value: Value::ByteString(material),
+                ..
+            }) => Ok(material.clone()),
+            _ => Err(CryptoError::ParseError(
+                "KMIP response has no key material".to_string(),
+            )),
+        }
+    }
+
+    /// Sign `data` with the private key `id` using `algorithm`, a value from `signature_algorithm`
+    pub fn sign(&self, id: &str, algorithm: u32, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let payload = self.call(
+            OPERATION_SIGN,
+            vec![
+                unique_identifier(id),
+                Item::structure(
+                    TAG_CRYPTOGRAPHIC_PARAMETERS,
+                    vec![Item::new(
+                        TAG_DIGITAL_SIGNATURE_ALGORITHM,
+                        Value::Enumeration(algorithm),
+                    )],
+                ),
+                Item::new(TAG_DATA, Value::ByteString(data.to_vec())),
+            ],
+        )?;
+        match payload.find(TAG_SIGNATURE_DATA) {
+            Some(Item {
+                value: Value::ByteString(signature),
+                ..
+            }) => Ok(signature.clone()),
+            _ => Err(CryptoError::ParseError(
+                "KMIP response has no signature".to_string(),
+            )),
+        }
+    }
+
+    /// Destroy the object `id` on the server
+    pub fn destroy(&self, id: &str) -> Result<(), CryptoError> {
+        self.call(OPERATION_DESTROY, vec![unique_identifier(id)])
+            .map(|_| ())
+    }
+
+    /// Send one operation and return the response payload
+    fn call(&self, operation: u32, payload: Vec<Item>) -> Result<Item, CryptoError> {
+        let request = Item::structure(
+            TAG_REQUEST_MESSAGE,
+            vec![
+                Item::structure(
+                    TAG_REQUEST_HEADER,
+                    vec![
+                        Item::structure(
+                            TAG_PROTOCOL_VERSION,
+                            vec![
+                                Item::new(
+                                    TAG_PROTOCOL_VERSION_MAJOR,
+                                    Value::Integer(PROTOCOL_VERSION_MAJOR),
+                                ),
+                                Item::new(
+                                    TAG_PROTOCOL_VERSION_MINOR,
+                                    Value::Integer(PROTOCOL_VERSION_MINOR),
+                                ),
+                            ],
+                        ),
+                        Item::new(TAG_BATCH_COUNT, Value::Integer(1)),
+                    ],
+                ),
+                Item::structure(
+                    TAG_BATCH_ITEM,
+                    vec![
+                        Item::new(TAG_OPERATION, Value::Enumeration(operation)),
+                        Item::structure(TAG_REQUEST_PAYLOAD, payload),
+                    ],
+                ),
+            ],
+        );
+        let response = Item::from_bytes(&self.transport.send(&request.to_bytes())?)?;
+        if response.tag != TAG_RESPONSE_MESSAGE {
+            return Err(CryptoError::ParseError(
+                "KMIP response is not a response message".to_string(),
+            ));
+        }
+        let batch_item = response.find(TAG_BATCH_ITEM).ok_or_else(|| {
+            CryptoError::ParseError("KMIP response has no batch item".to_string())
+        })?;
+        match batch_item.find(TAG_RESULT_STATUS) {
+            Some(Item {
+                value: Value::Enumeration(RESULT_STATUS_SUCCESS),
+                ..
+            }) => {}
+            _ => {
+                let reason = match batch_item.find(TAG_RESULT_REASON) {
+                    Some(Item {
+                        value: Value::Enumeration(r),
+                        ..
+                    }) => *r,
+                    _ => 0,
+                };
+                let message = text(batch_item, TAG_RESULT_MESSAGE).unwrap_or_default();
+                return Err(CryptoError::GeneralError(format!(
+                    "KMIP operation {:02X} failed with reason {:02X}: {}",
+                    operation, reason, message
+                )));
+            }
+        }
+        Ok(batch_item
+            .find(TAG_RESPONSE_PAYLOAD)
+            .cloned()
+            .unwrap_or_else(|| Item::structure(TAG_RESPONSE_PAYLOAD, Vec::new())))
+    }
+}
+
+/// Presents keys held by a KMIP server as a `KeyVault`.
+///
+/// The seed id is the unique identifier of a private key the vault knows the public
+/// key for, either because it created the pair or because it was added with
+/// `add_key_pair`. KMIP keys are used as they are, so the derivation path must be empty.
+pub struct KmipKeyVault<T: KmipTransport> {
+    client: KmipClient<T>,
+    signature_algorithm: u32,
+    public_key_format: u32,
+    public_keys: HashMap<String, String>,
+}
+
+impl<T: KmipTransport> KmipKeyVault<T> {
+    /// `signature_algorithm` is used for all signatures and public keys are fetched
+    /// in `public_key_format`
+    pub fn new(client: KmipClient<T>, signature_algorithm: u32, public_key_format: u32) -> Self {
+        KmipKeyVault {
+            client,
+            signature_algorithm,
+            public_key_format,
+            public_keys: HashMap::new(),
+        }
+    }
+
+    pub fn client(&self) -> &KmipClient<T> {
+        &self.client
+    }
+
+    /// Create a key pair on the server and return the private key's identifier
+    pub fn create_key_pair(&mut self, spec: &KeyPairSpec) -> Result<String, CryptoError> {
+        let ids = self.client.create_key_pair(spec)?;
+        self.add_key_pair(ids.clone());
+        Ok(ids.private_key)
+    }
+
+    /// Use a key pair that already exists on the server
+    pub fn add_key_pair(&mut self, ids: KeyPairIds) {
+        self.public_keys.insert(ids.private_key, ids.public_key);
+    }
+
+    /// Destroy both halves of a key pair on the server
+    pub fn destroy_key_pair(&mut self, private_key_id: &str) -> Result<(), CryptoError> {
+        self.client.destroy(private_key_id)?;
+        if let Some(public_key_id) = self.public_keys.remove(private_key_id) {
+            self.client.destroy(&public_key_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: KmipTransport> KeyVault for KmipKeyVault<T> {
+    fn derive_public_key(&self, seed_id: &str, path: &[u32]) -> Result<PublicKey, CryptoError> {
+        check_underived(path, "KMIP")?;
+        let public_key_id = self.public_keys.get(seed_id).ok_or_else(|| {
+            CryptoError::GeneralError(format!("No public key known for {}", seed_id))
+        })?;
+        Ok(PublicKey(
+            self.client.get(public_key_id, self.public_key_format)?,
+        ))
+    }
+
+    fn sign_derived(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_underived(path, "KMIP")?;
+        self.client.sign(seed_id, self.signature_algorithm, message)
+    }
+}
+
+fn unique_identifier(id: &str) -> Item {
+    Item::new(TAG_UNIQUE_IDENTIFIER, Value::TextString(id.to_string()))
+}
+
+fn usage_mask(mask: i32) -> Item {
+    Item::new(TAG_CRYPTOGRAPHIC_USAGE_MASK, Value::Integer(mask))
+}
+
+fn text(item: &Item, tag: u32) -> Result<String, CryptoError> {
+    match item.find(tag) {
+        Some(Item {
+            value: Value::TextString(s),
+            ..
+        }) => Ok(s.clone()),
+        _ => Err(CryptoError::ParseError(format!(
+            "KMIP response has no text item {:06X}",
+            tag
+        ))),
+    }
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::super::SignatureScheme;
+    use super::*;
+    use keys::PrivateKey;
+    use std::cell::RefCell;
+
+    /// A server that keeps Ed25519 keys in memory and answers the four supported operations
+    #[derive(Default)]
+    struct MockServer {
+        keys: RefCell<HashMap<String, Vec<u8>>>,
+        next_id: RefCell<u32>,
+    }
+
+    impl MockServer {
+        fn store(&self, material: Vec<u8>) -> String {
+            let mut next_id = self.next_id.borrow_mut();
+            *next_id += 1;
+            let id = format!("key-{}", next_id);
+            self.keys.borrow_mut().insert(id.clone(), material);
+            id
+        }
+
+        fn handle(&self, operation: u32, payload: &Item) -> Result<Vec<Item>, u32> {
+            let id = text(payload, TAG_UNIQUE_IDENTIFIER).ok();
+            let material = |id: &Option<String>| {
+                id.as_ref()
+                    .and_then(|id| self.keys.borrow().get(id).cloned())
+                    .ok_or(0x01u32)
+            };
+            match operation {
+                OPERATION_CREATE_KEY_PAIR => {
+                    let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+                    Ok(vec![
+                        Item::new(
+                            TAG_PRIVATE_KEY_UNIQUE_IDENTIFIER,
+                            Value::TextString(self.store(sk[..].to_vec())),
+                        ),
+                        Item::new(
+                            TAG_PUBLIC_KEY_UNIQUE_IDENTIFIER,
+                            Value::TextString(self.store(pk[..].to_vec())),
+                        ),
+                    ])
+                }
+                OPERATION_GET => Ok(vec![Item::structure(
+                    0x42_006D,
+                    vec![Item::structure(
+                        0x42_0040,
+                        vec![Item::structure(
+                            0x42_0045,
+                            vec![Item::new(
+                                TAG_KEY_MATERIAL,
+                                Value::ByteString(material(&id)?),
+                            )],
+                        )],
+                    )],
+                )]),
+                OPERATION_SIGN => {
+                    let data = match payload.find(TAG_DATA) {
+                        Some(Item {
+                            value: Value::ByteString(d),
+                            ..
+                        }) => d.clone(),
+                        _ => return Err(0x04),
+                    };
+                    let sk = PrivateKey(material(&id)?);
+                    Ok(vec![Item::new(
+                        TAG_SIGNATURE_DATA,
+                        Value::ByteString(Ed25519Sha512::new().sign(&data, &sk).unwrap()),
+                    )])
+                }
+                OPERATION_DESTROY => {
+                    material(&id)?;
+                    self.keys.borrow_mut().remove(id.as_ref().unwrap());
+                    Ok(Vec::new())
+                }
+                _ => Err(0x04),
+            }
+        }
+    }
+
+    impl KmipTransport for &MockServer {
+        fn send(&self, request: &[u8]) -> Result<Vec<u8>, CryptoError> {
+            let request = Item::from_bytes(request)?;
+            let batch_item = request.find(TAG_BATCH_ITEM).unwrap();
+            let operation = match batch_item.find(TAG_OPERATION) {
+                Some(Item {
+                    value: Value::Enumeration(op),
+                    ..
+                }) => *op,
+                _ => panic!("request has no operation"),
+            };
+            let payload = batch_item.find(TAG_REQUEST_PAYLOAD).unwrap();
+            let mut items = vec![Item::new(TAG_OPERATION, Value::Enumeration(operation))];
+            match self.handle(operation, payload) {
+                Ok(payload) => {
+                    items.push(Item::new(TAG_RESULT_STATUS, Value::Enumeration(0)));
+                    items.push(Item::structure(TAG_RESPONSE_PAYLOAD, payload));
+                }
+                Err(reason) => {
+                    items.push(Item::new(TAG_RESULT_STATUS, Value::Enumeration(1)));
+                    items.push(Item::new(TAG_RESULT_REASON, Value::Enumeration(reason)));
+                    items.push(Item::new(
+                        TAG_RESULT_MESSAGE,
+                        Value::TextString("failed".to_string()),
+                    ));
+                }
+            }
+            Ok(Item::structure(
+                TAG_RESPONSE_MESSAGE,
+                vec![
+                    Item::structure(TAG_RESPONSE_HEADER, Vec::new()),
+                    Item::structure(TAG_BATCH_ITEM, items),
+                ],
+            )
+            .to_bytes())
+        }
+    }
+
+    #[test]
+    fn key_vault_over_kmip() {
+        let server = MockServer::default();
+        // The mock signs with Ed25519 whatever algorithm is asked for
+        let mut vault = KmipKeyVault::new(KmipClient::new(&server), 0, key_format::RAW);
+        let id = vault.create_key_pair(&KeyPairSpec::ec_p256()).unwrap();
+
+        let pk = vault.derive_public_key(&id, &[]).unwrap();
+        let signature = vault.sign_derived(&id, &[], b"message").unwrap();
+        assert!(Ed25519Sha512::new()
+            .verify(b"message", &signature, &pk)
+            .unwrap());
+        assert!(vault.sign_derived(&id, &[1], b"message").is_err());
+
+        vault.destroy_key_pair(&id).unwrap();
+        assert!(server.keys.borrow().is_empty());
+        assert!(vault.sign_derived(&id, &[], b"message").is_err());
+        assert!(vault.client().destroy(&id).is_err());
+    }
+}
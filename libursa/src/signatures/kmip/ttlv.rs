@@ -0,0 +1,291 @@
+//! The KMIP tag-type-length-value encoding.
+//!
+//! Every item is a 3 byte tag, a 1 byte type and a 4 byte big endian length followed
+//! by the value, which is padded with zeros to a multiple of 8 bytes.
+
+use CryptoError;
+
+pub const TAG_BATCH_COUNT: u32 = 0x42_000D;
+pub const TAG_BATCH_ITEM: u32 = 0x42_000F;
+pub const TAG_COMMON_ATTRIBUTES: u32 = 0x42_0126;
+pub const TAG_CRYPTOGRAPHIC_ALGORITHM: u32 = 0x42_0028;
+pub const TAG_CRYPTOGRAPHIC_DOMAIN_PARAMETERS: u32 = 0x42_0029;
+pub const TAG_CRYPTOGRAPHIC_LENGTH: u32 = 0x42_002A;
+pub const TAG_CRYPTOGRAPHIC_PARAMETERS: u32 = 0x42_002B;
+pub const TAG_CRYPTOGRAPHIC_USAGE_MASK: u32 = 0x42_002C;
+pub const TAG_DATA: u32 = 0x42_00C2;
+pub const TAG_DIGITAL_SIGNATURE_ALGORITHM: u32 = 0x42_00AE;
+pub const TAG_KEY_FORMAT_TYPE: u32 = 0x42_0042;
+pub const TAG_KEY_MATERIAL: u32 = 0x42_0043;
+pub const TAG_OPERATION: u32 = 0x42_005C;
+pub const TAG_PRIVATE_KEY_ATTRIBUTES: u32 = 0x42_0127;
+pub const TAG_PRIVATE_KEY_UNIQUE_IDENTIFIER: u32 = 0x42_0066;
+pub const TAG_PROTOCOL_VERSION: u32 = 0x42_0069;
+pub const TAG_PROTOCOL_VERSION_MAJOR: u32 = 0x42_006A;
+pub const TAG_PROTOCOL_VERSION_MINOR: u32 = 0x42_006B;
+pub const TAG_PUBLIC_KEY_ATTRIBUTES: u32 = 0x42_0128;
+pub const TAG_PUBLIC_KEY_UNIQUE_IDENTIFIER: u32 = 0x42_006F;
+pub const TAG_RECOMMENDED_CURVE: u32 = 0x42_0075;
+pub const TAG_REQUEST_HEADER: u32 = 0x42_0077;
+pub const TAG_REQUEST_MESSAGE: u32 = 0x42_0078;
+pub const TAG_REQUEST_PAYLOAD: u32 = 0x42_0079;
+pub const TAG_RESPONSE_HEADER: u32 = 0x42_007A;
+pub const TAG_RESPONSE_MESSAGE: u32 = 0x42_007B;
+pub const TAG_RESPONSE_PAYLOAD: u32 = 0x42_007C;
+pub const TAG_RESULT_MESSAGE: u32 = 0x42_007D;
+pub const TAG_RESULT_REASON: u32 = 0x42_007E;
+pub const TAG_RESULT_STATUS: u32 = 0x42_007F;
+pub const TAG_SIGNATURE_DATA: u32 = 0x42_00C3;
+pub const TAG_UNIQUE_IDENTIFIER: u32 = 0x42_0094;
+
+const TYPE_STRUCTURE: u8 = 0x01;
+const TYPE_INTEGER: u8 = 0x02;
+const TYPE_LONG_INTEGER: u8 = 0x03;
+const TYPE_ENUMERATION: u8 = 0x05;
+const TYPE_BOOLEAN: u8 = 0x06;
+const TYPE_TEXT_STRING: u8 = 0x07;
+const TYPE_BYTE_STRING: u8 = 0x08;
+const TYPE_DATE_TIME: u8 = 0x09;
+
+const HEADER_SIZE: usize = 8;
+
+/// The value of an item. Big integers and intervals aren't used by the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Structure(Vec<Item>),
+    Integer(i32),
+    LongInteger(i64),
+    Enumeration(u32),
+    Boolean(bool),
+    TextString(String),
+    ByteString(Vec<u8>),
+    /// Seconds since the unix epoch
+    DateTime(i64),
+}
+
+/// A tagged value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub tag: u32,
+    pub value: Value,
+}
+
+impl Item {
+    pub fn new(tag: u32, value: Value) -> Self {
+        Item { tag, value }
+    }
+
+    pub fn structure(tag: u32, items: Vec<Item>) -> Self {
+        Item::new(tag, Value::Structure(items))
+    }
+
+    /// Returns the first child with `tag` if this is a structure
+    pub fn find(&self, tag: u32) -> Option<&Item> {
+        match self.value {
+            Value::Structure(ref items) => items.iter().find(|i| i.tag == tag),
+            _ => None,
+        }
+    }
+
+    /// Returns the first item with `tag` anywhere below this one
+    pub fn find_nested(&self, tag: u32) -> Option<&Item> {
+        match self.value {
+            Value::Structure(ref items) => items.iter().find_map(|i| {
+                if i.tag == tag {
+                    Some(i)
+                } else {
+                    i.find_nested(tag)
+                }
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// Parse a single item that takes up all of `data`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let (item, used) = Item::decode(data)?;
+        if used != data.len() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after TTLV item".to_string(),
+            ));
+        }
+        Ok(item)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.tag.to_be_bytes()[1..]);
+        let start = out.len();
+        out.extend_from_slice(&[0u8; 5]);
+        let item_type = match self.value {
+            Value::Structure(ref items) => {
+                for item in items {
+                    item.encode(out);
+                }
+                TYPE_STRUCTURE
+            }
+            Value::Integer(v) => {
+                out.extend_from_slice(&v.to_be_bytes());
+                TYPE_INTEGER
+            }
+            Value::LongInteger(v) => {
+                out.extend_from_slice(&v.to_be_bytes());
+                TYPE_LONG_INTEGER
+            }
+            Value::Enumeration(v) => {
+                out.extend_from_slice(&v.to_be_bytes());
+                TYPE_ENUMERATION
+            }
+            Value::Boolean(v) => {
+                out.extend_from_slice(&(v as u64).to_be_bytes());
+                TYPE_BOOLEAN
+            }
+            Value::TextString(ref v) => {
+                out.extend_from_slice(v.as_bytes());
+                TYPE_TEXT_STRING
+            }
+            Value::ByteString(ref v) => {
+                out.extend_from_slice(v);
+                TYPE_BYTE_STRING
+            }
+            Value::DateTime(v) => {
+                out.extend_from_slice(&v.to_be_bytes());
+                TYPE_DATE_TIME
+            }
+        };
+        let length = (out.len() - start - 5) as u32;
+        out[start] = item_type;
+        out[start + 1..start + 5].copy_from_slice(&length.to_be_bytes());
+        out.resize(out.len() + padding(length as usize), 0);
+    }
+
+    fn decode(data: &[u8]) -> Result<(Self, usize), CryptoError> {
+        if data.len() < HEADER_SIZE {
+            return Err(CryptoError::ParseError(
+                "TTLV item is truncated".to_string(),
+            ));
+        }
+        let tag = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+        let item_type = data[3];
+        let length = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let end = HEADER_SIZE
+            .checked_add(length)
+            .and_then(|e| e.checked_add(padding(length)))
+            .filter(|e| *e <= data.len())
+            .ok_or_else(|| CryptoError::ParseError("TTLV item is truncated".to_string()))?;
+        let body = &data[HEADER_SIZE..HEADER_SIZE + length];
+        let fixed = |size: usize| {
+            if length == size {
+                Ok(body)
+            } else {
+                Err(CryptoError::ParseError(format!(
+                    "TTLV item {:06X} has length {}, expected {}",
+                    tag, length, size
+                )))
+            }
+        };
+        let value = match item_type {
+            TYPE_STRUCTURE => {
+                let mut items = Vec::new();
+                let mut offset = 0;
+                while offset < body.len() {
+                    let (item, used) = Item::decode(&body[offset..])?;
+                    items.push(item);
+                    offset += used;
+                }
+                Value::Structure(items)
+            }
+            TYPE_INTEGER => Value::Integer(i32::from_be_bytes(*array_ref!(fixed(4)?, 0, 4))),
+            TYPE_LONG_INTEGER => {
+                Value::LongInteger(i64::from_be_bytes(*array_ref!(fixed(8)?, 0, 8)))
+            }
+            TYPE_ENUMERATION => {
+                Value::Enumeration(u32::from_be_bytes(*array_ref!(fixed(4)?, 0, 4)))
+            }
+            TYPE_BOOLEAN => match u64::from_be_bytes(*array_ref!(fixed(8)?, 0, 8)) {
+                0 => Value::Boolean(false),
+                1 => Value::Boolean(true),
+                _ => {
+                    return Err(CryptoError::ParseError(
+                        "TTLV boolean is not 0 or 1".to_string(),
+                    ))
+                }
+            },
+            TYPE_TEXT_STRING => Value::TextString(
+                String::from_utf8(body.to_vec())
+                    .map_err(|_| CryptoError::ParseError("TTLV text is not UTF-8".to_string()))?,
+            ),
+            TYPE_BYTE_STRING => Value::ByteString(body.to_vec()),
+            TYPE_DATE_TIME => Value::DateTime(i64::from_be_bytes(*array_ref!(fixed(8)?, 0, 8))),
+            t => {
+                return Err(CryptoError::ParseError(format!(
+                    "Unsupported TTLV type {:02X}",
+                    t
+                )))
+            }
+        };
+        Ok((Item { tag, value }, end))
+    }
+}
+
+fn padding(length: usize) -> usize {
+    (8 - length % 8) % 8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_spec_examples() {
+        // From section 9.1.2 of the KMIP 1.0 specification
+        assert_eq!(
+            Item::new(0x42_0020, Value::Integer(8)).to_bytes(),
+            hex::decode("42002002000000040000000800000000").unwrap()
+        );
+        assert_eq!(
+            Item::new(0x42_0020, Value::TextString("Hello World".to_string())).to_bytes(),
+            hex::decode("420020070000000B48656C6C6F20576F726C640000000000").unwrap()
+        );
+        assert_eq!(
+            Item::structure(
+                0x42_0020,
+                vec![
+                    Item::new(0x42_0004, Value::Enumeration(254)),
+                    Item::new(0x42_0005, Value::Integer(255)),
+                ]
+            )
+            .to_bytes(),
+            hex::decode(
+                "42002001000000204200040500000004000000FE000000004200050200000004000000FF00000000"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let item = Item::structure(
+            TAG_REQUEST_MESSAGE,
+            vec![
+                Item::new(
+                    TAG_UNIQUE_IDENTIFIER,
+                    Value::TextString("key-1".to_string()),
+                ),
+                Item::new(TAG_DATA, Value::ByteString(vec![1, 2, 3])),
+                Item::new(TAG_BATCH_COUNT, Value::LongInteger(-5)),
+                Item::new(TAG_RESULT_STATUS, Value::Boolean(true)),
+                Item::new(TAG_RESULT_REASON, Value::DateTime(1_600_000_000)),
+            ],
+        );
+        let bytes = item.to_bytes();
+        assert_eq!(bytes.len() % 8, 0);
+        assert_eq!(Item::from_bytes(&bytes).unwrap(), item);
+        assert!(Item::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+    }
+}
@@ -0,0 +1,531 @@
+//! Signed manifests of firmware images and other artifacts.
+//!
+//! Secure boot and update clients decide what to install from a `Manifest`: the name,
+//! size and SHA-256 digest of each artifact of a release, a version and free form
+//! metadata such as the hardware revision it is built for. A `SignerPolicy` names the
+//! keys allowed to sign releases and how many of them must, so no single build machine
+//! or release manager can ship an update alone. Each key has a slot in the policy and
+//! signs the manifest separately, e.g. on its own offline machine.
+//!
+//! The device keeps the policy, not the manifest, as its trust anchor, so a manifest
+//! can't name its own signers. Signatures are bound to the policy they were made for.
+//! `Manifest::verify_update` also rejects versions that aren't newer than the installed
+//! one, so an old, correctly signed release with known bugs can't be rolled back to.
+//! Each artifact is checked with `verify_artifact` once it is downloaded.
+//!
+//! All keys of a policy use the same `SignatureScheme`.
+
+use std::collections::BTreeMap;
+
+use keys::{PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+use super::provider::KeyProvider;
+use super::SignatureScheme;
+use CryptoError;
+
+const MANIFEST_DOMAIN: &[u8] = b"URSA_ARTIFACT_MANIFEST_V1";
+const POLICY_DOMAIN: &[u8] = b"URSA_ARTIFACT_SIGNER_POLICY_V1";
+
+/// The keys allowed to sign manifests and how many signatures a manifest needs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignerPolicy {
+    threshold: usize,
+    signers: Vec<PublicKey>,
+}
+
+impl SignerPolicy {
+    /// A policy requiring `threshold` of the distinct keys in `signers`, whose
+    /// positions are their slots
+    pub fn new(threshold: usize, signers: Vec<PublicKey>) -> Result<Self, CryptoError> {
+        if threshold == 0 || threshold > signers.len() {
+            return Err(CryptoError::GeneralError(format!(
+                "A threshold of {} is not possible with {} signers",
+                threshold,
+                signers.len()
+            )));
+        }
+        for (i, signer) in signers.iter().enumerate() {
+            if signers[..i].contains(signer) {
+                return Err(CryptoError::GeneralError(format!(
+                    "Signer {} appears twice",
+                    i
+                )));
+            }
+        }
+        Ok(SignerPolicy { threshold, signers })
+    }
+
+    /// The number of signatures a manifest needs
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The keys allowed to sign, in slot order
+    pub fn signers(&self) -> &[PublicKey] {
+        &self.signers
+    }
+
+    /// The digest identifying the policy, covered by every signature made for it
+    pub fn id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.input(POLICY_DOMAIN);
+        hasher.input((self.threshold as u64).to_be_bytes());
+        hasher.input((self.signers.len() as u64).to_be_bytes());
+        for signer in &self.signers {
+            hasher.input((signer.len() as u64).to_be_bytes());
+            hasher.input(&signer[..]);
+        }
+        let mut id = [0u8; 32];
+        id.copy_from_slice(hasher.result().as_slice());
+        id
+    }
+}
+
+/// An artifact of a release
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Artifact {
+    /// The name the artifact is installed under, e.g. `"bootloader"`
+    pub name: String,
+    /// The size in bytes
+    pub size: u64,
+    /// The SHA-256 digest
+    pub digest: [u8; 32],
+}
+
+/// The artifacts of a release with the signatures of the keys that approved it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    version: u64,
+    artifacts: Vec<Artifact>,
+    metadata: BTreeMap<String, String>,
+    // (slot, signature), sorted by slot
+    signatures: Vec<(u32, Vec<u8>)>,
+}
+
+impl Manifest {
+    /// An unsigned manifest of release `version` without artifacts
+    pub fn new(version: u64) -> Self {
+        Manifest {
+            version,
+            artifacts: Vec::new(),
+            metadata: BTreeMap::new(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// The release version, which only increases
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn artifacts(&self) -> &[Artifact] {
+        &self.artifacts
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// The slots that have signed
+    pub fn signed_slots(&self) -> Vec<usize> {
+        self.signatures.iter().map(|(s, _)| *s as usize).collect()
+    }
+
+    /// Add the artifact `name` with the content `data`
+    pub fn add_artifact(&mut self, name: &str, data: &[u8]) -> Result<(), CryptoError> {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(Sha256::digest(data).as_slice());
+        self.add_artifact_digest(name, data.len() as u64, digest)
+    }
+
+    /// Add the artifact `name` of `size` bytes hashed elsewhere, e.g. while streaming it
+    pub fn add_artifact_digest(
+        &mut self,
+        name: &str,
+        size: u64,
+        digest: [u8; 32],
+    ) -> Result<(), CryptoError> {
+        if self.artifacts.iter().any(|a| a.name == name) {
+            return Err(CryptoError::GeneralError(format!(
+                "The manifest already has an artifact {}",
+                name
+            )));
+        }
+        self.artifacts.push(Artifact {
+            name: name.to_string(),
+            size,
+            digest,
+        });
+        Ok(())
+    }
+
+    /// Set the metadata `key` to `value`
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.insert(key.to_string(), value.to_string());
+    }
+
+    /// Sign the manifest for `policy` with the private key of `slot`, replacing any
+    /// signature the slot made before. Changing the manifest afterwards invalidates the
+    /// signatures
+    pub fn sign<T: SignatureScheme>(
+        &mut self,
+        scheme: &T,
+        policy: &SignerPolicy,
+        slot: usize,
+        private_key: &PrivateKey,
+    ) -> Result<(), CryptoError> {
+        let message = self.message(policy);
+        let signature = scheme.sign(&message, private_key)?;
+        self.insert_signature(scheme, policy, slot, &message, signature)
+    }
+
+    /// Like `sign` with the key held by a `KeyProvider`, e.g. in an HSM
+    pub fn sign_with_provider<T: SignatureScheme>(
+        &mut self,
+        scheme: &T,
+        policy: &SignerPolicy,
+        slot: usize,
+        provider: &dyn KeyProvider,
+    ) -> Result<(), CryptoError> {
+        let message = self.message(policy);
+        let signature = scheme.sign_with_provider(&message, provider)?;
+        self.insert_signature(scheme, policy, slot, &message, signature)
+    }
+
+    fn insert_signature<T: SignatureScheme>(
+        &mut self,
+        scheme: &T,
+        policy: &SignerPolicy,
+        slot: usize,
+        message: &[u8],
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        let key = policy.signers.get(slot).ok_or_else(|| {
+            CryptoError::GeneralError(format!("The policy has no signer slot {}", slot))
+        })?;
+        if !scheme.verify(message, &signature, key).unwrap_or(false) {
+            return Err(CryptoError::SigningError(format!(
+                "The key is not the signer of slot {}",
+                slot
+            )));
+        }
+        let slot = slot as u32;
+        match self.signatures.binary_search_by_key(&slot, |(s, _)| *s) {
+            Ok(i) => self.signatures[i].1 = signature,
+            Err(i) => self.signatures.insert(i, (slot, signature)),
+        }
+        Ok(())
+    }
+
+    /// Check the manifest has the signatures `policy` requires. Returns an error
+    /// naming how many valid signatures it has if not
+    pub fn verify<T: SignatureScheme>(
+        &self,
+        scheme: &T,
+        policy: &SignerPolicy,
+    ) -> Result<(), CryptoError> {
+        let message = self.message(policy);
+        let mut valid = 0;
+        for (i, (slot, signature)) in self.signatures.iter().enumerate() {
+            // Slots are counted once however often they appear
+            if self.signatures[..i].iter().any(|(s, _)| s == slot) {
+                continue;
+            }
+            let key = match policy.signers.get(*slot as usize) {
+                Some(key) => key,
+                None => continue,
+            };
+            if scheme.verify(&message, signature, key).unwrap_or(false) {
+                valid += 1;
+            }
+        }
+        if valid < policy.threshold {
+            return Err(CryptoError::SigningError(format!(
+                "The manifest has {} of the {} signatures the policy requires",
+                valid, policy.threshold
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the manifest is signed as `policy` requires and is newer than the
+    /// `installed` version
+    pub fn verify_update<T: SignatureScheme>(
+        &self,
+        scheme: &T,
+        policy: &SignerPolicy,
+        installed: u64,
+    ) -> Result<(), CryptoError> {
+        self.verify(scheme, policy)?;
+        if self.version <= installed {
+            return Err(CryptoError::GeneralError(format!(
+                "Version {} is not newer than the installed version {}",
+                self.version, installed
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check `data` is the artifact `name` of the manifest
+    pub fn verify_artifact(&self, name: &str, data: &[u8]) -> Result<(), CryptoError> {
+        let artifact = self
+            .artifacts
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| {
+                CryptoError::GeneralError(format!("The manifest has no artifact {}", name))
+            })?;
+        if artifact.size != data.len() as u64
+            || artifact.digest[..] != Sha256::digest(data).as_slice()[..]
+        {
+            return Err(CryptoError::GeneralError(format!(
+                "The content of {} doesn't match the manifest",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Serialize as the version as 8 bytes, then the number of artifacts as 4 bytes and
+    /// each one's length prefixed name, size as 8 bytes and digest, then the number of
+    /// metadata entries and their length prefixed keys and values, then the number of
+    /// signatures and each one's slot as 4 bytes and the length prefixed signature.
+    /// Integers are big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.body();
+        out.extend_from_slice(&(self.signatures.len() as u32).to_be_bytes());
+        for (slot, signature) in &self.signatures {
+            out.extend_from_slice(&slot.to_be_bytes());
+            push_field(&mut out, signature);
+        }
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut rest = data;
+        let mut manifest = Manifest::new(take_u64(&mut rest)?);
+        for _ in 0..take_u32(&mut rest)? {
+            let name = take_string(&mut rest)?;
+            let size = take_u64(&mut rest)?;
+            if rest.len() < 32 {
+                return Err(truncated());
+            }
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(&rest[..32]);
+            rest = &rest[32..];
+            manifest.add_artifact_digest(&name, size, digest)?;
+        }
+        for _ in 0..take_u32(&mut rest)? {
+            let key = take_string(&mut rest)?;
+            let value = take_string(&mut rest)?;
+            if manifest.metadata.insert(key, value).is_some() {
+                return Err(CryptoError::ParseError(
+                    "Manifest repeats a metadata key".to_string(),
+                ));
+            }
+        }
+        for _ in 0..take_u32(&mut rest)? {
+            let slot = take_u32(&mut rest)?;
+            let signature = take_field(&mut rest)?;
+            if matches!(manifest.signatures.last(), Some((s, _)) if *s >= slot) {
+                return Err(CryptoError::ParseError(
+                    "Manifest signatures are not in slot order".to_string(),
+                ));
+            }
+            manifest.signatures.push((slot, signature));
+        }
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after manifest".to_string(),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// Everything but the signatures, in the encoding of `to_bytes`
+    fn body(&self) -> Vec<u8> {
+        let mut out = self.version.to_be_bytes().to_vec();
+        out.extend_from_slice(&(self.artifacts.len() as u32).to_be_bytes());
+        for artifact in &self.artifacts {
+            push_field(&mut out, artifact.name.as_bytes());
+            out.extend_from_slice(&artifact.size.to_be_bytes());
+            out.extend_from_slice(&artifact.digest);
+        }
+        out.extend_from_slice(&(self.metadata.len() as u32).to_be_bytes());
+        for (key, value) in &self.metadata {
+            push_field(&mut out, key.as_bytes());
+            push_field(&mut out, value.as_bytes());
+        }
+        out
+    }
+
+    /// The message signed for `policy`
+    fn message(&self, policy: &SignerPolicy) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.input(MANIFEST_DOMAIN);
+        hasher.input(policy.id());
+        hasher.input(self.body());
+        hasher.result().to_vec()
+    }
+}
+
+fn truncated() -> CryptoError {
+    CryptoError::ParseError("Manifest is truncated".to_string())
+}
+
+fn push_field(out: &mut Vec<u8>, field: &[u8]) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+fn take_u32(rest: &mut &[u8]) -> Result<u32, CryptoError> {
+    if rest.len() < 4 {
+        return Err(truncated());
+    }
+    let mut n = [0u8; 4];
+    n.copy_from_slice(&rest[..4]);
+    *rest = &rest[4..];
+    Ok(u32::from_be_bytes(n))
+}
+
+fn take_u64(rest: &mut &[u8]) -> Result<u64, CryptoError> {
+    if rest.len() < 8 {
+        return Err(truncated());
+    }
+    let mut n = [0u8; 8];
+    n.copy_from_slice(&rest[..8]);
+    *rest = &rest[8..];
+    Ok(u64::from_be_bytes(n))
+}
+
+fn take_field(rest: &mut &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let len = take_u32(rest)? as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let field = rest[..len].to_vec();
+    *rest = &rest[len..];
+    Ok(field)
+}
+
+fn take_string(rest: &mut &[u8]) -> Result<String, CryptoError> {
+    String::from_utf8(take_field(rest)?)
+        .map_err(|_| CryptoError::ParseError("Manifest has a string that isn't UTF-8".to_string()))
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+
+    fn keys(n: usize) -> Vec<(PublicKey, PrivateKey)> {
+        let scheme = Ed25519Sha512::new();
+        (0..n).map(|_| scheme.keypair(None).unwrap()).collect()
+    }
+
+    fn release(version: u64) -> Manifest {
+        let mut manifest = Manifest::new(version);
+        manifest.add_artifact("bootloader", b"boot code").unwrap();
+        manifest.add_artifact("kernel", &[7u8; 5000]).unwrap();
+        manifest.set_metadata("hardware", "rev-b");
+        manifest
+    }
+
+    #[test]
+    fn threshold_signatures() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(4);
+        let policy = SignerPolicy::new(2, keys[..3].iter().map(|k| k.0.clone()).collect()).unwrap();
+        let mut manifest = release(3);
+        manifest.sign(&scheme, &policy, 0, &keys[0].1).unwrap();
+        assert!(manifest.verify(&scheme, &policy).is_err());
+        // Signing a slot again doesn't count twice
+        manifest.sign(&scheme, &policy, 0, &keys[0].1).unwrap();
+        assert!(manifest.verify(&scheme, &policy).is_err());
+        manifest.sign(&scheme, &policy, 2, &keys[2].1).unwrap();
+        manifest.verify(&scheme, &policy).unwrap();
+        assert_eq!(manifest.signed_slots(), vec![0, 2]);
+
+        let parsed = Manifest::from_bytes(&manifest.to_bytes()).unwrap();
+        assert_eq!(parsed, manifest);
+        parsed.verify(&scheme, &policy).unwrap();
+        parsed.verify_artifact("kernel", &[7u8; 5000]).unwrap();
+        assert!(parsed.verify_artifact("kernel", &[7u8; 4999]).is_err());
+        assert!(parsed.verify_artifact("bootloader", b"boot codf").is_err());
+        assert!(parsed.verify_artifact("initrd", b"").is_err());
+
+        // Keys outside the policy or in the wrong slot can't sign
+        assert!(manifest.sign(&scheme, &policy, 1, &keys[3].1).is_err());
+        assert!(manifest.sign(&scheme, &policy, 1, &keys[0].1).is_err());
+        assert!(manifest.sign(&scheme, &policy, 3, &keys[3].1).is_err());
+
+        // Signatures are bound to the manifest and the policy
+        let mut changed = manifest.clone();
+        changed.set_metadata("hardware", "rev-c");
+        assert!(changed.verify(&scheme, &policy).is_err());
+        let mut added = manifest.clone();
+        added.add_artifact("initrd", b"").unwrap();
+        assert!(added.verify(&scheme, &policy).is_err());
+        assert!(manifest.add_artifact("kernel", b"").is_err());
+        let other = SignerPolicy::new(1, policy.signers().to_vec()).unwrap();
+        assert!(manifest.verify(&scheme, &other).is_err());
+
+        assert!(SignerPolicy::new(0, vec![keys[0].0.clone()]).is_err());
+        assert!(SignerPolicy::new(2, vec![keys[0].0.clone()]).is_err());
+        assert!(SignerPolicy::new(1, vec![keys[0].0.clone(), keys[0].0.clone()]).is_err());
+    }
+
+    #[test]
+    fn updates() {
+        use signatures::provider::SoftwareKeyProvider;
+
+        let scheme = Ed25519Sha512::new();
+        let provider = SoftwareKeyProvider::<Ed25519Sha512>::generate().unwrap();
+        let policy = SignerPolicy::new(1, vec![provider.public_key().unwrap()]).unwrap();
+        let mut manifest = release(5);
+        assert!(manifest.verify_update(&scheme, &policy, 4).is_err());
+        manifest
+            .sign_with_provider(&scheme, &policy, 0, &provider)
+            .unwrap();
+        manifest.verify_update(&scheme, &policy, 4).unwrap();
+        // No rollback to the same or an older version
+        assert!(manifest.verify_update(&scheme, &policy, 5).is_err());
+        assert!(manifest.verify_update(&scheme, &policy, 9).is_err());
+    }
+
+    #[test]
+    fn encoding() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(2);
+        let policy = SignerPolicy::new(2, keys.iter().map(|k| k.0.clone()).collect()).unwrap();
+        let mut manifest = release(1);
+        for (slot, (_, sk)) in keys.iter().enumerate() {
+            manifest.sign(&scheme, &policy, slot, sk).unwrap();
+        }
+        let bytes = manifest.to_bytes();
+        assert_eq!(Manifest::from_bytes(&bytes).unwrap(), manifest);
+        assert!(Manifest::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Manifest::from_bytes(&trailing).is_err());
+        assert!(Manifest::from_bytes(&[]).is_err());
+
+        // A changed version or digest no longer verifies
+        for i in [7, 30].iter() {
+            let mut changed = bytes.clone();
+            changed[*i] ^= 1;
+            let changed = Manifest::from_bytes(&changed).unwrap();
+            assert!(changed.verify(&scheme, &policy).is_err());
+        }
+
+        // Signatures must be in slot order without repeats
+        let mut repeated = manifest.clone();
+        repeated.signatures[1].0 = 0;
+        assert!(Manifest::from_bytes(&repeated.to_bytes()).is_err());
+        repeated.signatures.swap(0, 1);
+        repeated.signatures[0].0 = 1;
+        assert!(Manifest::from_bytes(&repeated.to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,444 @@
+//! Keys and detached signatures in the formats of minisign and signify.
+//!
+//! Both tools use Ed25519 and an 8 byte key id. New signatures are made the way
+//! minisign makes them by default: over the BLAKE2b-512 hash of the file, with a
+//! trusted comment covered by a second signature. Signatures over the file itself,
+//! which signify makes and older minisign versions made with `-l`, are verified too.
+//!
+//! Secret keys are written and read without a password, like `minisign -W` keys.
+//! Keys encrypted with a password have to be decrypted with minisign first.
+
+use std::fmt;
+
+use blake2::digest::{Update, VariableOutput};
+use blake2::{Blake2b, Digest, VarBlake2b};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+pub const KEY_ID_SIZE: usize = 8;
+const PUBLIC_KEY_SIZE: usize = 32;
+/// The seed followed by the public key
+const PRIVATE_KEY_SIZE: usize = 64;
+const SIGNATURE_SIZE: usize = 64;
+
+/// Signatures over the message itself
+const ALG_ED25519: &[u8; 2] = b"Ed";
+/// Signatures over the BLAKE2b-512 hash of the message
+const ALG_ED25519_PREHASHED: &[u8; 2] = b"ED";
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const CHECKSUM_BLAKE2B: &[u8; 2] = b"B2";
+const CHECKSUM_SIZE: usize = 32;
+/// The KDF salt, opslimit and memlimit, which are unused without a password
+const KDF_PARAMETERS_SIZE: usize = 48;
+const SECRET_KEY_FILE_SIZE: usize =
+    6 + KDF_PARAMETERS_SIZE + KEY_ID_SIZE + PRIVATE_KEY_SIZE + CHECKSUM_SIZE;
+
+const UNTRUSTED_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_PREFIX: &str = "trusted comment: ";
+
+/// A public key, as in a `minisign.pub` or signify `.pub` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinisignPublicKey {
+    pub key_id: [u8; KEY_ID_SIZE],
+    pub public_key: PublicKey,
+}
+
+impl MinisignPublicKey {
+    /// Parse a public key file
+    pub fn from_file(text: &str) -> Result<Self, CryptoError> {
+        let (_, line) = read_comment_and_line(&mut text.lines())?;
+        MinisignPublicKey::from_base64(&line)
+    }
+
+    /// Parse the base64 line of a public key file, which is what `minisign -P` takes
+    pub fn from_base64(line: &str) -> Result<Self, CryptoError> {
+        let data = decode_base64(line)?;
+        if data.len() != 2 + KEY_ID_SIZE + PUBLIC_KEY_SIZE || &data[..2] != ALG_ED25519 {
+            return Err(parse_error("Not an Ed25519 minisign public key"));
+        }
+        Ok(MinisignPublicKey {
+            key_id: *array_ref!(data, 2, KEY_ID_SIZE),
+            public_key: PublicKey(data[2 + KEY_ID_SIZE..].to_vec()),
+        })
+    }
+
+    pub fn to_base64(&self) -> String {
+        let mut data = ALG_ED25519.to_vec();
+        data.extend_from_slice(&self.key_id);
+        data.extend_from_slice(&self.public_key[..]);
+        base64::encode(data)
+    }
+}
+
+/// Writes the public key file minisign writes
+impl fmt::Display for MinisignPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}minisign public key {}",
+            UNTRUSTED_PREFIX,
+            key_id_hex(&self.key_id)
+        )?;
+        writeln!(f, "{}", self.to_base64())
+    }
+}
+
+/// A secret key and its key id
+#[derive(Debug, PartialEq, Eq)]
+pub struct MinisignSecretKey {
+    pub key_id: [u8; KEY_ID_SIZE],
+    /// The Ed25519 secret key followed by the public key, as ursa stores them
+    pub secret_key: PrivateKey,
+}
+
+impl MinisignSecretKey {
+    /// Generate a key pair with a random key id
+    pub fn generate(
+        options: Option<KeyGenOption>,
+    ) -> Result<(MinisignPublicKey, MinisignSecretKey), CryptoError> {
+        let (public_key, secret_key) = Ed25519Sha512::new().keypair(options)?;
+        let mut key_id = [0u8; KEY_ID_SIZE];
+        OsRng.fill_bytes(&mut key_id);
+        Ok((
+            MinisignPublicKey { key_id, public_key },
+            MinisignSecretKey { key_id, secret_key },
+        ))
+    }
+
+    /// Parse an unencrypted secret key file
+    pub fn from_file(text: &str) -> Result<Self, CryptoError> {
+        let (_, line) = read_comment_and_line(&mut text.lines())?;
+        let mut data = decode_base64(&line)?;
+        let key = MinisignSecretKey::from_bytes(&data);
+        data.zeroize();
+        key
+    }
+
+    /// The public key, which is the second half of the secret key
+    pub fn public_key(&self) -> MinisignPublicKey {
+        MinisignPublicKey {
+            key_id: self.key_id,
+            public_key: PublicKey(self.secret_key[PRIVATE_KEY_SIZE - PUBLIC_KEY_SIZE..].to_vec()),
+        }
+    }
+
+    /// Write an unencrypted secret key file. It should only ever be stored with
+    /// permissions that keep other users from reading it.
+    pub fn to_file(&self) -> String {
+        let mut data = ALG_ED25519.to_vec();
+        data.extend_from_slice(KDF_NONE);
+        data.extend_from_slice(CHECKSUM_BLAKE2B);
+        data.extend_from_slice(&[0u8; KDF_PARAMETERS_SIZE]);
+        data.extend_from_slice(&self.key_id);
+        data.extend_from_slice(&self.secret_key[..]);
+        data.extend_from_slice(&self.checksum());
+        let file = format!(
+            "{}minisign secret key\n{}\n",
+            UNTRUSTED_PREFIX,
+            base64::encode(&data)
+        );
+        data.zeroize();
+        file
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() != SECRET_KEY_FILE_SIZE || &data[..2] != ALG_ED25519 {
+            return Err(parse_error("Not an Ed25519 minisign secret key"));
+        }
+        if &data[2..4] != KDF_NONE {
+            return Err(CryptoError::NoSuchAlgorithm(
+                "Secret key is encrypted, remove the password with minisign first".to_string(),
+            ));
+        }
+        if &data[4..6] != CHECKSUM_BLAKE2B {
+            return Err(parse_error("Unsupported minisign secret key checksum"));
+        }
+        let start = 6 + KDF_PARAMETERS_SIZE;
+        let key = MinisignSecretKey {
+            key_id: *array_ref!(data, start, KEY_ID_SIZE),
+            secret_key: PrivateKey(
+                data[start + KEY_ID_SIZE..start + KEY_ID_SIZE + PRIVATE_KEY_SIZE].to_vec(),
+            ),
+        };
+        if key.checksum()[..] != data[data.len() - CHECKSUM_SIZE..] {
+            return Err(parse_error("Minisign secret key checksum doesn't match"));
+        }
+        Ok(key)
+    }
+
+    fn checksum(&self) -> [u8; CHECKSUM_SIZE] {
+        let mut hasher = VarBlake2b::new(CHECKSUM_SIZE).unwrap();
+        hasher.update(ALG_ED25519);
+        hasher.update(self.key_id);
+        hasher.update(&self.secret_key[..]);
+        let mut checksum = [0u8; CHECKSUM_SIZE];
+        hasher.finalize_variable(|h| checksum.copy_from_slice(h));
+        checksum
+    }
+}
+
+/// A signature file, as minisign writes to `<file>.minisig` and signify to `<file>.sig`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinisignSignature {
+    pub untrusted_comment: String,
+    pub key_id: [u8; KEY_ID_SIZE],
+    /// Whether the signature is over the BLAKE2b-512 hash of the file
+    pub prehashed: bool,
+    pub signature: Vec<u8>,
+    /// The trusted comment and the signature over it, which signify doesn't write.
+    /// The comment can only be relied on once `verify` has returned true.
+    pub trusted_comment: Option<(String, Vec<u8>)>,
+}
+
+impl MinisignSignature {
+    pub fn from_file(text: &str) -> Result<Self, CryptoError> {
+        let mut lines = text.lines();
+        let (untrusted_comment, line) = read_comment_and_line(&mut lines)?;
+        let data = decode_base64(&line)?;
+        if data.len() != 2 + KEY_ID_SIZE + SIGNATURE_SIZE {
+            return Err(parse_error("Not an Ed25519 minisign signature"));
+        }
+        let prehashed = match *array_ref!(data, 0, 2) {
+            ref a if a == ALG_ED25519 => false,
+            ref a if a == ALG_ED25519_PREHASHED => true,
+            _ => return Err(parse_error("Not an Ed25519 minisign signature")),
+        };
+        let trusted_comment = match lines.next() {
+            Some(l) if l.starts_with(TRUSTED_PREFIX) => {
+                let signature = lines
+                    .next()
+                    .ok_or_else(|| parse_error("Trusted comment has no signature"))?;
+                Some((
+                    l[TRUSTED_PREFIX.len()..].to_string(),
+                    decode_base64(signature)?,
+                ))
+            }
+            Some(l) if !l.trim().is_empty() => {
+                return Err(parse_error("Expected a trusted comment"));
+            }
+            _ => None,
+        };
+        Ok(MinisignSignature {
+            untrusted_comment,
+            key_id: *array_ref!(data, 2, KEY_ID_SIZE),
+            prehashed,
+            signature: data[2 + KEY_ID_SIZE..].to_vec(),
+            trusted_comment,
+        })
+    }
+}
+
+impl fmt::Display for MinisignSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut data = if self.prehashed {
+            ALG_ED25519_PREHASHED.to_vec()
+        } else {
+            ALG_ED25519.to_vec()
+        };
+        data.extend_from_slice(&self.key_id);
+        data.extend_from_slice(&self.signature);
+        writeln!(f, "{}{}", UNTRUSTED_PREFIX, self.untrusted_comment)?;
+        writeln!(f, "{}", base64::encode(data))?;
+        if let Some((ref comment, ref signature)) = self.trusted_comment {
+            writeln!(f, "{}{}", TRUSTED_PREFIX, comment)?;
+            writeln!(f, "{}", base64::encode(signature))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sign `data` like `minisign -S -t <trusted_comment>`. minisign's own trusted
+/// comment is `timestamp:<unix time>\tfile:<file name>`.
+pub fn sign(
+    key: &MinisignSecretKey,
+    data: &[u8],
+    trusted_comment: &str,
+) -> Result<MinisignSignature, CryptoError> {
+    if trusted_comment.contains('\n') {
+        return Err(CryptoError::SigningError(
+            "Trusted comments have to fit on one line".to_string(),
+        ));
+    }
+    let scheme = Ed25519Sha512::new();
+    let signature = scheme.sign(&Blake2b::digest(data), &key.secret_key)?;
+    let mut global = signature.clone();
+    global.extend_from_slice(trusted_comment.as_bytes());
+    let global = scheme.sign(&global, &key.secret_key)?;
+    Ok(MinisignSignature {
+        untrusted_comment: "signature from minisign secret key".to_string(),
+        key_id: key.key_id,
+        prehashed: true,
+        signature,
+        trusted_comment: Some((trusted_comment.to_string(), global)),
+    })
+}
+
+/// Check that `signature` was made over `data` by `key`, including the trusted
+/// comment if there is one
+pub fn verify(
+    key: &MinisignPublicKey,
+    data: &[u8],
+    signature: &MinisignSignature,
+) -> Result<bool, CryptoError> {
+    if signature.key_id != key.key_id {
+        return Ok(false);
+    }
+    let scheme = Ed25519Sha512::new();
+    let verified = if signature.prehashed {
+        scheme.verify(
+            &Blake2b::digest(data),
+            &signature.signature,
+            &key.public_key,
+        )
+    } else {
+        scheme.verify(data, &signature.signature, &key.public_key)
+    };
+    if !verified.unwrap_or(false) {
+        return Ok(false);
+    }
+    match signature.trusted_comment {
+        Some((ref comment, ref global)) => {
+            let mut signed = signature.signature.clone();
+            signed.extend_from_slice(comment.as_bytes());
+            Ok(scheme
+                .verify(&signed, global, &key.public_key)
+                .unwrap_or(false))
+        }
+        None => Ok(true),
+    }
+}
+
+/// minisign shows the key id as a little endian number
+fn key_id_hex(key_id: &[u8; KEY_ID_SIZE]) -> String {
+    key_id.iter().rev().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Read the untrusted comment line and the base64 line after it
+fn read_comment_and_line<'a, I: Iterator<Item = &'a str>>(
+    lines: &mut I,
+) -> Result<(String, String), CryptoError> {
+    let comment = match lines.next() {
+        Some(l) if l.starts_with(UNTRUSTED_PREFIX) => l[UNTRUSTED_PREFIX.len()..].to_string(),
+        _ => return Err(parse_error("Expected an untrusted comment")),
+    };
+    let line = lines
+        .next()
+        .ok_or_else(|| parse_error("Missing base64 line"))?;
+    Ok((comment, line.trim().to_string()))
+}
+
+fn decode_base64(line: &str) -> Result<Vec<u8>, CryptoError> {
+    base64::decode(line.trim())
+        .map_err(|e| CryptoError::ParseError(format!("Invalid base64: {}", e)))
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let (pk, sk) = MinisignSecretKey::generate(None).unwrap();
+        let signature = sign(
+            &sk,
+            b"release artifact",
+            "timestamp:1600000000\tfile:a.tar.gz",
+        )
+        .unwrap();
+        let parsed = MinisignSignature::from_file(&signature.to_string()).unwrap();
+        assert_eq!(parsed, signature);
+        assert!(verify(&pk, b"release artifact", &parsed).unwrap());
+        assert!(!verify(&pk, b"another artifact", &parsed).unwrap());
+
+        let mut tampered = parsed.clone();
+        tampered.trusted_comment.as_mut().unwrap().0 = "timestamp:0".to_string();
+        assert!(!verify(&pk, b"release artifact", &tampered).unwrap());
+
+        let (other, _) = MinisignSecretKey::generate(None).unwrap();
+        assert!(!verify(&other, b"release artifact", &parsed).unwrap());
+        assert!(sign(&sk, b"", "two\nlines").is_err());
+    }
+
+    #[test]
+    fn key_files() {
+        let (pk, sk) = MinisignSecretKey::generate(None).unwrap();
+        let text = pk.to_string();
+        assert!(text.starts_with("untrusted comment: minisign public key "));
+        assert_eq!(MinisignPublicKey::from_file(&text).unwrap(), pk);
+        assert_eq!(sk.public_key(), pk);
+
+        let file = sk.to_file();
+        assert_eq!(MinisignSecretKey::from_file(&file).unwrap(), sk);
+
+        let mut data = base64::decode(file.lines().nth(1).unwrap()).unwrap();
+        data[2..4].copy_from_slice(b"Sc");
+        let encrypted = format!("untrusted comment: x\n{}\n", base64::encode(&data));
+        assert!(MinisignSecretKey::from_file(&encrypted).is_err());
+        data[2..4].copy_from_slice(KDF_NONE);
+        data[100] ^= 1;
+        let corrupted = format!("untrusted comment: x\n{}\n", base64::encode(&data));
+        assert!(MinisignSecretKey::from_file(&corrupted).is_err());
+    }
+
+    #[test]
+    fn known_signature() {
+        let pk = MinisignPublicKey::from_base64(
+            "RWQBAgMEBQYHCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4",
+        )
+        .unwrap();
+        assert!(pk
+            .to_string()
+            .starts_with("untrusted comment: minisign public key 0807060504030201\n"));
+        let file = "untrusted comment: signature from minisign secret key\n\
+RUQBAgMEBQYHCGW+pIg6GYXXG3/tNvX3E/fS07sQrL+vZJSK8kuMPJcEZmgeorOzoi7CR0GrnItC6ormFZEW4WIcaZpvIhMiFQg=\n\
+trusted comment: timestamp:1700000000\tfile:release.txt\n\
+AynpTquLMVCO30O+hPjx5vCWPJYzrKZ9+jfTG/cqwIO9112NQMFtOwUQtZs/+Npwa44rKaQSbcklNfi2OGqVCQ==\n";
+        let signature = MinisignSignature::from_file(file).unwrap();
+        assert!(verify(&pk, b"ursa release\n", &signature).unwrap());
+        assert_eq!(signature.to_string(), file);
+
+        let mut secret_key: Vec<u8> = (0..32).collect();
+        secret_key.extend_from_slice(&pk.public_key[..]);
+        let sk = MinisignSecretKey {
+            key_id: pk.key_id,
+            secret_key: PrivateKey(secret_key),
+        };
+        let signed = sign(
+            &sk,
+            b"ursa release\n",
+            "timestamp:1700000000\tfile:release.txt",
+        )
+        .unwrap();
+        assert_eq!(signed, signature);
+    }
+
+    #[test]
+    fn signify_signatures() {
+        let (pk, sk) = MinisignSecretKey::generate(None).unwrap();
+        let signature = Ed25519Sha512::new()
+            .sign(b"release artifact", &sk.secret_key)
+            .unwrap();
+        let mut data = ALG_ED25519.to_vec();
+        data.extend_from_slice(&pk.key_id);
+        data.extend_from_slice(&signature);
+        let file = format!(
+            "untrusted comment: verify with release.pub\n{}\n",
+            base64::encode(&data)
+        );
+        let signature = MinisignSignature::from_file(&file).unwrap();
+        assert!(!signature.prehashed);
+        assert_eq!(signature.trusted_comment, None);
+        assert!(verify(&pk, b"release artifact", &signature).unwrap());
+        assert!(!verify(&pk, b"another artifact", &signature).unwrap());
+    }
+}
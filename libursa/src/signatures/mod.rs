@@ -1,17 +1,60 @@
+#[cfg(feature = "attenuable")]
+pub mod attenuable;
+#[cfg(feature = "sha2")]
+pub mod attestation;
 #[cfg(feature = "bls_bls12381")]
 pub mod bls;
+#[cfg(any(feature = "aws_kms", feature = "gcp_kms"))]
+pub mod cloud_kms;
+#[cfg(feature = "sha2")]
+pub mod delegation;
+#[cfg(any(
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "key_encoding",
+    feature = "webauthn"
+))]
+pub(crate) mod der;
+#[cfg(any(feature = "acvp", feature = "signature_policy", feature = "webauthn"))]
+pub(crate) mod ecdsa_p256;
 #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
 pub mod ed25519;
+#[cfg(feature = "kmip")]
+pub mod kmip;
+#[cfg(feature = "sha2")]
+pub mod manifest;
+#[cfg(feature = "minisign")]
+pub mod minisign;
+#[cfg(all(feature = "hmac", feature = "sha2"))]
+pub mod nonce;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+#[cfg(feature = "signature_policy")]
+pub mod policy;
+pub mod provider;
+#[cfg(feature = "message_recovery")]
+pub mod recovery;
+#[cfg(feature = "sha2")]
+pub mod rotation;
 #[cfg(any(
     feature = "ecdsa_secp256k1",
     feature = "ecdsa_secp256k1_native",
     feature = "ecdsa_secp256k1_asm"
 ))]
 pub mod secp256k1;
+#[cfg(feature = "streaming")]
+pub mod streaming;
+#[cfg(all(feature = "hkdf", feature = "sha2"))]
+pub mod vault;
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
 
 pub mod prelude {
     #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
     pub use super::ed25519::Ed25519Sha512;
+    pub use super::provider::KeyProvider;
+    #[cfg(all(feature = "hmac", feature = "sha2"))]
+    pub use super::HedgedSignatureScheme;
     #[cfg(any(
         feature = "ecdsa_secp256k1",
         feature = "ecdsa_secp256k1_native",
@@ -22,6 +65,11 @@ pub mod prelude {
 }
 
 use keys::{KeyGenOption, PrivateKey, PublicKey};
+#[cfg(all(feature = "hmac", feature = "sha2"))]
+use rand::{rngs::OsRng, RngCore};
+#[cfg(all(feature = "hmac", feature = "sha2"))]
+use signatures::nonce::NonceSecret;
+use signatures::provider::KeyProvider;
 use CryptoError;
 
 pub trait SignatureScheme {
@@ -36,6 +84,24 @@ pub trait SignatureScheme {
     fn signature_size() -> usize;
     fn private_key_size() -> usize;
     fn public_key_size() -> usize;
+
+    /// Sign with a key held by `provider` instead of its bytes. The signature is checked
+    /// against the provider's public key, so a backend signing with another key or in
+    /// another encoding is caught before the signature is used
+    fn sign_with_provider(
+        &self,
+        message: &[u8],
+        provider: &dyn KeyProvider,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let signature = provider.sign(message)?;
+        let pk = provider.public_key()?;
+        if signature.len() != Self::signature_size() || !self.verify(message, &signature, &pk)? {
+            return Err(CryptoError::SigningError(
+                "Key provider returned an invalid signature".to_string(),
+            ));
+        }
+        Ok(signature)
+    }
 }
 
 pub struct Signer<'a, 'b, T: 'a + SignatureScheme> {
@@ -82,6 +148,48 @@ impl<'a, 'b, T: 'a + SignatureScheme> Signer<'a, 'b, T> {
     }
 }
 
+/// Signing with nonces derived from fresh randomness as well as from the key and
+/// the message.
+///
+/// Deterministic nonces protect against a broken RNG but a fault injected while
+/// a signature is computed can leak the key when the correct signature for the same
+/// message is known. Hedged signatures mix random noise into the deterministic
+/// derivation, so repeated signatures differ and a failing RNG only makes them as
+/// good as deterministic ones. Hedged signatures verify like any other.
+#[cfg(all(feature = "hmac", feature = "sha2"))]
+pub trait HedgedSignatureScheme: SignatureScheme {
+    /// Sign with a nonce derived from the key, the message and `noise`. The same
+    /// noise gives the same signature, so it should be fresh for every signature.
+    fn sign_with_noise(
+        &self,
+        message: &[u8],
+        sk: &PrivateKey,
+        noise: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Sign with 32 bytes from the operating system's RNG as noise
+    fn sign_hedged(&self, message: &[u8], sk: &PrivateKey) -> Result<Vec<u8>, CryptoError> {
+        let mut noise = [0u8; 32];
+        // Without randomness this falls back to a deterministic signature
+        let _ = OsRng.try_fill_bytes(&mut noise);
+        self.sign_with_noise(message, sk, &noise)
+    }
+
+    /// Sign with noise derived from the RNG and the host's `NonceSecret`, so replicas
+    /// that share a key never share nonces, even when their RNGs fail the same way
+    fn sign_hedged_on_host(
+        &self,
+        message: &[u8],
+        sk: &PrivateKey,
+        host: &NonceSecret,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut random = [0u8; 32];
+        let _ = OsRng.try_fill_bytes(&mut random);
+        let noise = host.derive(&sk[..], message, &random, 32);
+        self.sign_with_noise(message, sk, noise.expose_secret())
+    }
+}
+
 #[cfg(any(
     feature = "ecdsa_secp256k1",
     feature = "ecdsa_secp256k1_native",
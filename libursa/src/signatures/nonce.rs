@@ -0,0 +1,210 @@
+//! Signing nonces derived from the key, the message and a per-host secret.
+//!
+//! RFC 6979 makes nonces a function of the key and the message so a broken RNG
+//! can't leak the key. In a fleet of replicas that share a signing key, purely
+//! deterministic nonces have another problem: a fault injected on one replica while
+//! it computes a signature can be combined with the correct signature another
+//! replica makes for the same message. Mixing in a secret that is different on
+//! every host gives every replica its own nonces for the same key and message, and
+//! adding fresh randomness, when there is some, makes repeated signatures on one
+//! host differ as well.
+//!
+//! Host secrets form a hierarchy. A fleet secret is provisioned once and every
+//! region, host and process derives its own child from its parent and a label, so
+//! no replica needs its own provisioning step and one child reveals nothing about
+//! its siblings.
+//!
+//! Nonces are HMAC-SHA512 outputs, first over the inputs and then expanded like
+//! HKDF to as many candidates of as many bytes as the scheme needs.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha512;
+use zeroize::Zeroize;
+
+use keys::SecretBytes;
+use CryptoError;
+
+type HmacSha512 = Hmac<Sha512>;
+
+pub const NONCE_SECRET_SIZE: usize = 32;
+
+const CHILD_CONTEXT: &[u8] = b"URSA_NONCE_CHILD_V1";
+const NONCE_CONTEXT: &[u8] = b"URSA_NONCE_V1";
+const HASH_SIZE: usize = 64;
+
+/// A secret that is mixed into every nonce a host derives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceSecret(SecretBytes);
+
+impl NonceSecret {
+    pub fn new(secret: SecretBytes) -> Result<Self, CryptoError> {
+        if secret.len() < NONCE_SECRET_SIZE {
+            return Err(CryptoError::KeyGenError(format!(
+                "Nonce secrets need at least {} bytes",
+                NONCE_SECRET_SIZE
+            )));
+        }
+        Ok(NonceSecret(secret))
+    }
+
+    pub fn generate() -> Self {
+        let mut secret = vec![0u8; NONCE_SECRET_SIZE];
+        OsRng.fill_bytes(&mut secret);
+        NonceSecret(SecretBytes::new(secret))
+    }
+
+    /// The secret of the child named `label`, such as a region, a host name or a
+    /// process id
+    pub fn derive_child(&self, label: &[u8]) -> NonceSecret {
+        let mut mac = self.mac();
+        mac.input(CHILD_CONTEXT);
+        mac.input(label);
+        let mut child = mac.result().code().to_vec();
+        child.truncate(NONCE_SECRET_SIZE);
+        NonceSecret(SecretBytes::new(child))
+    }
+
+    /// Derive the child at the end of `labels`
+    pub fn derive_path(&self, labels: &[&[u8]]) -> NonceSecret {
+        labels
+            .iter()
+            .fold(self.clone(), |secret, label| secret.derive_child(label))
+    }
+
+    /// Derive a nonce of `length` bytes. `randomness` may be empty, which makes the
+    /// nonce deterministic for this host.
+    pub fn derive(
+        &self,
+        secret_key: &[u8],
+        message: &[u8],
+        randomness: &[u8],
+        length: usize,
+    ) -> SecretBytes {
+        self.candidates(secret_key, message, randomness, length)
+            .next()
+            .unwrap()
+    }
+
+    /// An endless series of nonces of `length` bytes, for schemes that have to
+    /// reject values outside their scalar range and try the next one
+    pub fn candidates(
+        &self,
+        secret_key: &[u8],
+        message: &[u8],
+        randomness: &[u8],
+        length: usize,
+    ) -> NonceCandidates {
+        let mut mac = self.mac();
+        mac.input(NONCE_CONTEXT);
+        for input in [secret_key, randomness, message].iter() {
+            mac.input(&(input.len() as u64).to_be_bytes());
+            mac.input(input);
+        }
+        let mut prk = [0u8; HASH_SIZE];
+        prk.copy_from_slice(&mac.result().code());
+        NonceCandidates {
+            prk,
+            previous: Vec::new(),
+            counter: 0,
+            length,
+        }
+    }
+
+    fn mac(&self) -> HmacSha512 {
+        // HMAC accepts keys of any length
+        HmacSha512::new_varkey(self.0.expose_secret()).unwrap()
+    }
+}
+
+/// See `NonceSecret::candidates`
+pub struct NonceCandidates {
+    prk: [u8; HASH_SIZE],
+    previous: Vec<u8>,
+    counter: u32,
+    length: usize,
+}
+
+impl NonceCandidates {
+    fn block(&mut self) -> Vec<u8> {
+        self.counter += 1;
+        let mut mac = HmacSha512::new_varkey(&self.prk).unwrap();
+        mac.input(&self.previous);
+        mac.input(&self.counter.to_be_bytes());
+        let block = mac.result().code().to_vec();
+        self.previous.zeroize();
+        self.previous = block.clone();
+        block
+    }
+}
+
+impl Iterator for NonceCandidates {
+    type Item = SecretBytes;
+
+    fn next(&mut self) -> Option<SecretBytes> {
+        let mut nonce = Vec::with_capacity(self.length + HASH_SIZE);
+        while nonce.len() < self.length {
+            let mut block = self.block();
+            nonce.extend_from_slice(&block);
+            block.zeroize();
+        }
+        nonce.truncate(self.length);
+        Some(SecretBytes::new(nonce))
+    }
+}
+
+impl Drop for NonceCandidates {
+    fn drop(&mut self) {
+        self.prk.zeroize();
+        self.previous.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn derivation() {
+        let fleet = NonceSecret::generate();
+        let host_a = fleet.derive_path(&[b"eu-west", b"signer-1"]);
+        let host_b = fleet.derive_path(&[b"eu-west", b"signer-2"]);
+        assert_eq!(
+            host_a,
+            fleet.derive_child(b"eu-west").derive_child(b"signer-1")
+        );
+
+        let a = host_a.derive(b"key", b"message", b"", 32);
+        assert_eq!(a.len(), 32);
+        assert_eq!(a, host_a.derive(b"key", b"message", b"", 32));
+        assert_ne!(a, host_b.derive(b"key", b"message", b"", 32));
+        assert_ne!(a, host_a.derive(b"key", b"message2", b"", 32));
+        assert_ne!(a, host_a.derive(b"key2", b"message", b"", 32));
+        assert_ne!(a, host_a.derive(b"key", b"message", b"noise", 32));
+        // Length prefixes keep the inputs apart
+        assert_ne!(
+            host_a.derive(b"keym", b"essage", b"", 32),
+            host_a.derive(b"key", b"message", b"", 32)
+        );
+    }
+
+    #[test]
+    fn candidates() {
+        let host = NonceSecret::new(SecretBytes::new(vec![3u8; 32])).unwrap();
+        let candidates: Vec<_> = host
+            .candidates(b"key", b"message", b"", 100)
+            .take(3)
+            .collect();
+        assert_eq!(candidates[0], host.derive(b"key", b"message", b"", 100));
+        assert_eq!(candidates[0].len(), 100);
+        assert_ne!(candidates[0], candidates[1]);
+        assert_ne!(candidates[1], candidates[2]);
+        // Shorter nonces are prefixes of the first candidate
+        assert_eq!(
+            host.derive(b"key", b"message", b"", 32).expose_secret(),
+            &candidates[0].expose_secret()[..32]
+        );
+        assert!(NonceSecret::new(SecretBytes::new(vec![3u8; 16])).is_err());
+    }
+}
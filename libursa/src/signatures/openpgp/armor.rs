@@ -0,0 +1,127 @@
+//! OpenPGP ASCII armor.
+
+use super::packet::parse_error;
+use CryptoError;
+
+const LINE_LENGTH: usize = 64;
+const CRC24_INIT: u32 = 0x00b7_04ce;
+const CRC24_POLY: u32 = 0x0186_4cfb;
+
+/// What an armored block holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArmorKind {
+    Signature,
+    PublicKey,
+}
+
+impl ArmorKind {
+    fn label(self) -> &'static str {
+        match self {
+            ArmorKind::Signature => "PGP SIGNATURE",
+            ArmorKind::PublicKey => "PGP PUBLIC KEY BLOCK",
+        }
+    }
+}
+
+/// Armor `data` the way `gpg --armor` does
+pub fn armor(kind: ArmorKind, data: &[u8]) -> String {
+    let mut text = format!("-----BEGIN {}-----\n\n", kind.label());
+    let encoded = base64::encode(data);
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        text.push_str(::std::str::from_utf8(line).unwrap());
+        text.push('\n');
+    }
+    text.push('=');
+    text.push_str(&base64::encode(&crc24(data).to_be_bytes()[1..]));
+    text.push_str(&format!("\n-----END {}-----\n", kind.label()));
+    text
+}
+
+/// Decode the first armored block in `text` and return what it holds
+pub fn dearmor(text: &str) -> Result<(ArmorKind, Vec<u8>), CryptoError> {
+    let mut lines = text
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|l| !l.starts_with("-----BEGIN PGP "));
+    let kind = match lines.next() {
+        Some("-----BEGIN PGP SIGNATURE-----") => ArmorKind::Signature,
+        Some("-----BEGIN PGP PUBLIC KEY BLOCK-----") => ArmorKind::PublicKey,
+        Some(l) => return Err(CryptoError::ParseError(format!("Unsupported armor {}", l))),
+        None => return Err(parse_error("No OpenPGP armor found")),
+    };
+    // Skip the armor headers, which end at the first empty line
+    let mut lines = lines.skip_while(|l| !l.is_empty()).skip(1);
+    let mut body = String::new();
+    let mut checksum = None;
+    loop {
+        match lines.next() {
+            Some(l) if l.starts_with("-----END ") => break,
+            Some(l) if l.starts_with('=') => checksum = Some(l[1..].to_string()),
+            Some(l) => body.push_str(l),
+            None => return Err(parse_error("OpenPGP armor has no end line")),
+        }
+    }
+    let data = base64::decode(&body)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid armor: {}", e)))?;
+    if let Some(checksum) = checksum {
+        if base64::decode(&checksum).ok() != Some(crc24(&data).to_be_bytes()[1..].to_vec()) {
+            return Err(parse_error("OpenPGP armor checksum doesn't match"));
+        }
+    }
+    Ok((kind, data))
+}
+
+/// Read binary OpenPGP data or the first armored block of `kind` in it
+pub(super) fn binary(data: &[u8], kind: ArmorKind) -> Result<Vec<u8>, CryptoError> {
+    if !data.starts_with(b"-----BEGIN PGP ") {
+        return Ok(data.to_vec());
+    }
+    let text = ::std::str::from_utf8(data).map_err(|_| parse_error("Armor is not UTF-8"))?;
+    match dearmor(text)? {
+        (k, data) if k == kind => Ok(data),
+        _ => Err(CryptoError::ParseError(format!(
+            "Expected {} armor",
+            kind.label()
+        ))),
+    }
+}
+
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for b in data {
+        crc ^= (*b as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0x00ff_ffff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn armor_round_trip() {
+        let data: Vec<u8> = (0..100).collect();
+        let text = armor(ArmorKind::Signature, &data);
+        assert!(text.starts_with("-----BEGIN PGP SIGNATURE-----\n\n"));
+        assert_eq!(
+            dearmor(&text).unwrap(),
+            (ArmorKind::Signature, data.clone())
+        );
+        assert_eq!(binary(text.as_bytes(), ArmorKind::Signature).unwrap(), data);
+        assert!(binary(text.as_bytes(), ArmorKind::PublicKey).is_err());
+
+        let tampered = text.replace("AAEC", "AAED");
+        assert!(dearmor(&tampered).is_err());
+    }
+
+    #[test]
+    fn crc24_check_value() {
+        assert_eq!(crc24(b"123456789"), 0x0021_cf02);
+    }
+}
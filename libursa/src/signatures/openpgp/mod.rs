@@ -0,0 +1,520 @@
+//! OpenPGP v4 keys and detached signatures as described in RFC 4880, for artifacts
+//! that have to be checked with gpg or signed by it.
+//!
+//! Ed25519 keys use the EdDSA algorithm gpg uses for them and secp256k1 keys use
+//! ECDSA when one of the `ecdsa_secp256k1` features is enabled. Signatures made here
+//! are over binary documents with SHA-256. Verification also accepts SHA-384 and
+//! SHA-512 for Ed25519 keys. ursa's ECDSA hashes with SHA-256 itself, so ECDSA
+//! signatures have to use SHA-256.
+//!
+//! `PgpKey::to_transferable` binds a user id to a key with a self signature, which
+//! gpg needs before it imports a key. Keys and signatures are read as binary data or
+//! in the armor `armor::armor` writes.
+
+pub mod armor;
+mod packet;
+
+use sha1_smol::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use self::armor::{binary, ArmorKind};
+use self::packet::{
+    decode_mpi, decode_packets, decode_subpackets, encode_mpi, encode_packet, encode_subpacket,
+    mpi_to_fixed, parse_error, TAG_PUBLIC_KEY, TAG_SIGNATURE, TAG_USER_ID,
+};
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+#[cfg(any(
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm"
+))]
+use signatures::{secp256k1::EcdsaSecp256k1Sha256, EcdsaPublicKeyHandler};
+use CryptoError;
+
+const VERSION: u8 = 4;
+
+#[cfg(any(
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm"
+))]
+const ALG_ECDSA: u8 = 19;
+const ALG_EDDSA: u8 = 22;
+
+const HASH_SHA256: u8 = 8;
+const HASH_SHA384: u8 = 9;
+const HASH_SHA512: u8 = 10;
+
+const OID_ED25519: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+#[cfg(any(
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm"
+))]
+const OID_SECP256K1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x0a];
+/// The prefix of a native EdDSA point
+const EDDSA_POINT_PREFIX: u8 = 0x40;
+
+const SIG_BINARY: u8 = 0x00;
+const SIG_POSITIVE_CERTIFICATION: u8 = 0x13;
+
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_ISSUER: u8 = 16;
+const SUBPACKET_KEY_FLAGS: u8 = 27;
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+const KEY_FLAGS_CERTIFY_SIGN: u8 = 0x03;
+const SCALAR_SIZE: usize = 32;
+
+/// The key types OpenPGP keys can be made from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    Ed25519,
+    #[cfg(any(
+        feature = "ecdsa_secp256k1",
+        feature = "ecdsa_secp256k1_native",
+        feature = "ecdsa_secp256k1_asm"
+    ))]
+    EcdsaSecp256k1,
+}
+
+impl KeyAlgorithm {
+    fn id(self) -> u8 {
+        match self {
+            KeyAlgorithm::Ed25519 => ALG_EDDSA,
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            KeyAlgorithm::EcdsaSecp256k1 => ALG_ECDSA,
+        }
+    }
+}
+
+/// An OpenPGP public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgpKey {
+    pub algorithm: KeyAlgorithm,
+    pub public_key: PublicKey,
+    /// Seconds since the unix epoch. It's part of the fingerprint, so a key has to
+    /// keep the time it was first exported with.
+    pub created: u32,
+}
+
+impl PgpKey {
+    pub fn new(algorithm: KeyAlgorithm, public_key: PublicKey, created: u32) -> Self {
+        PgpKey {
+            algorithm,
+            public_key,
+            created,
+        }
+    }
+
+    /// Read the first public key in a binary or armored key, such as the output of
+    /// `gpg --export`
+    pub fn parse(data: &[u8]) -> Result<Self, CryptoError> {
+        let data = binary(data, ArmorKind::PublicKey)?;
+        let body = decode_packets(&data)?
+            .into_iter()
+            .find(|(tag, _)| *tag == TAG_PUBLIC_KEY)
+            .map(|(_, body)| body)
+            .ok_or_else(|| parse_error("No OpenPGP public key found"))?;
+        PgpKey::from_body(body)
+    }
+
+    /// The public key packet
+    pub fn to_packet(&self) -> Vec<u8> {
+        encode_packet(TAG_PUBLIC_KEY, &self.body())
+    }
+
+    /// The v4 fingerprint
+    pub fn fingerprint(&self) -> [u8; 20] {
+        let mut sha1 = Sha1::new();
+        sha1.update(&key_hash_prefix(&self.body()));
+        sha1.digest().bytes()
+    }
+
+    /// The key id, which is the low 64 bits of the fingerprint
+    pub fn key_id(&self) -> [u8; 8] {
+        let fingerprint = self.fingerprint();
+        *array_ref!(fingerprint, 12, 8)
+    }
+
+    /// The key packet followed by `user_id` and a self signature made at `created`,
+    /// which can be imported with `gpg --import`
+    pub fn to_transferable(
+        &self,
+        user_id: &str,
+        sk: &PrivateKey,
+        created: u32,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut signed = key_hash_prefix(&self.body());
+        signed.push(0xb4);
+        signed.extend_from_slice(&(user_id.len() as u32).to_be_bytes());
+        signed.extend_from_slice(user_id.as_bytes());
+
+        let mut subpackets = Vec::new();
+        encode_subpacket(
+            SUBPACKET_CREATION_TIME,
+            &created.to_be_bytes(),
+            &mut subpackets,
+        );
+        encode_subpacket(
+            SUBPACKET_KEY_FLAGS,
+            &[KEY_FLAGS_CERTIFY_SIGN],
+            &mut subpackets,
+        );
+        let signature = self.sign(sk, SIG_POSITIVE_CERTIFICATION, subpackets, signed)?;
+
+        let mut transferable = self.to_packet();
+        transferable.extend_from_slice(&encode_packet(TAG_USER_ID, user_id.as_bytes()));
+        transferable.extend_from_slice(&signature);
+        Ok(transferable)
+    }
+
+    fn body(&self) -> Vec<u8> {
+        let mut body = vec![VERSION];
+        body.extend_from_slice(&self.created.to_be_bytes());
+        body.push(self.algorithm.id());
+        let (oid, point) = match self.algorithm {
+            KeyAlgorithm::Ed25519 => {
+                let mut point = vec![EDDSA_POINT_PREFIX];
+                point.extend_from_slice(&self.public_key[..]);
+                (OID_ED25519, point)
+            }
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            KeyAlgorithm::EcdsaSecp256k1 => (
+                OID_SECP256K1,
+                EcdsaSecp256k1Sha256::new().public_key_uncompressed(&self.public_key),
+            ),
+        };
+        body.push(oid.len() as u8);
+        body.extend_from_slice(oid);
+        encode_mpi(&point, &mut body);
+        body
+    }
+
+    fn from_body(body: &[u8]) -> Result<Self, CryptoError> {
+        if body.len() < 7 || body[0] != VERSION {
+            return Err(CryptoError::NoSuchAlgorithm(
+                "Only v4 OpenPGP keys are supported".to_string(),
+            ));
+        }
+        let created = u32::from_be_bytes(*array_ref!(body, 1, 4));
+        let algorithm = body[5];
+        let oid_length = body[6] as usize;
+        if body.len() < 7 + oid_length {
+            return Err(parse_error("OpenPGP key is truncated"));
+        }
+        let oid = &body[7..7 + oid_length];
+        let (point, rest) = decode_mpi(&body[7 + oid_length..])?;
+        if !rest.is_empty() {
+            return Err(parse_error("Trailing bytes after OpenPGP key"));
+        }
+        let (algorithm, public_key) = match (algorithm, oid) {
+            (ALG_EDDSA, OID_ED25519) => {
+                if point.len() != 33 || point[0] != EDDSA_POINT_PREFIX {
+                    return Err(parse_error("Invalid Ed25519 key"));
+                }
+                (KeyAlgorithm::Ed25519, PublicKey(point[1..].to_vec()))
+            }
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            (ALG_ECDSA, OID_SECP256K1) => (
+                KeyAlgorithm::EcdsaSecp256k1,
+                EcdsaSecp256k1Sha256::new().parse(point)?,
+            ),
+            (a, _) => {
+                return Err(CryptoError::NoSuchAlgorithm(format!(
+                    "Unsupported OpenPGP key algorithm {}",
+                    a
+                )))
+            }
+        };
+        Ok(PgpKey::new(algorithm, public_key, created))
+    }
+
+    /// Make a signature packet over `signed` followed by the hashed part of the packet
+    fn sign(
+        &self,
+        sk: &PrivateKey,
+        signature_type: u8,
+        mut subpackets: Vec<u8>,
+        mut signed: Vec<u8>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut fingerprint = vec![VERSION];
+        fingerprint.extend_from_slice(&self.fingerprint());
+        encode_subpacket(SUBPACKET_ISSUER_FINGERPRINT, &fingerprint, &mut subpackets);
+
+        let mut body = vec![VERSION, signature_type, self.algorithm.id(), HASH_SHA256];
+        body.extend_from_slice(&(subpackets.len() as u16).to_be_bytes());
+        body.extend_from_slice(&subpackets);
+        append_trailer(&mut signed, &body);
+
+        let digest = Sha256::digest(&signed);
+        let signature = match self.algorithm {
+            KeyAlgorithm::Ed25519 => Ed25519Sha512::new().sign(&digest, sk)?,
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            KeyAlgorithm::EcdsaSecp256k1 => EcdsaSecp256k1Sha256::new().sign(&signed, sk)?,
+        };
+
+        let mut unhashed = Vec::new();
+        encode_subpacket(SUBPACKET_ISSUER, &self.key_id(), &mut unhashed);
+        body.extend_from_slice(&(unhashed.len() as u16).to_be_bytes());
+        body.extend_from_slice(&unhashed);
+        body.extend_from_slice(&digest[..2]);
+        encode_mpi(&signature[..SCALAR_SIZE], &mut body);
+        encode_mpi(&signature[SCALAR_SIZE..], &mut body);
+        Ok(encode_packet(TAG_SIGNATURE, &body))
+    }
+}
+
+/// Make a binary detached signature over `data` at `created`, like `gpg --detach-sign`
+pub fn sign_detached(
+    key: &PgpKey,
+    sk: &PrivateKey,
+    data: &[u8],
+    created: u32,
+) -> Result<Vec<u8>, CryptoError> {
+    let mut subpackets = Vec::new();
+    encode_subpacket(
+        SUBPACKET_CREATION_TIME,
+        &created.to_be_bytes(),
+        &mut subpackets,
+    );
+    key.sign(sk, SIG_BINARY, subpackets, data.to_vec())
+}
+
+/// Check a binary or armored detached signature over `data` made by `key`
+pub fn verify_detached(key: &PgpKey, data: &[u8], signature: &[u8]) -> Result<bool, CryptoError> {
+    let signature = binary(signature, ArmorKind::Signature)?;
+    let body = match decode_packets(&signature)?.as_slice() {
+        [(TAG_SIGNATURE, body)] => body.to_vec(),
+        _ => return Err(parse_error("Expected a single OpenPGP signature")),
+    };
+    if body.len() < 6 || body[0] != VERSION {
+        return Err(CryptoError::NoSuchAlgorithm(
+            "Only v4 OpenPGP signatures are supported".to_string(),
+        ));
+    }
+    if body[1] != SIG_BINARY {
+        return Err(CryptoError::NoSuchAlgorithm(format!(
+            "Unsupported OpenPGP signature type {}",
+            body[1]
+        )));
+    }
+    if body[2] != key.algorithm.id() {
+        return Ok(false);
+    }
+    let hash = body[3];
+
+    let hashed_end = 6 + u16::from_be_bytes([body[4], body[5]]) as usize;
+    if body.len() < hashed_end + 2 {
+        return Err(parse_error("OpenPGP signature is truncated"));
+    }
+    let unhashed_end =
+        hashed_end + 2 + u16::from_be_bytes([body[hashed_end], body[hashed_end + 1]]) as usize;
+    if body.len() < unhashed_end + 2 {
+        return Err(parse_error("OpenPGP signature is truncated"));
+    }
+    for (kind, value) in decode_subpackets(&body[6..hashed_end])?
+        .into_iter()
+        .chain(decode_subpackets(&body[hashed_end + 2..unhashed_end])?)
+    {
+        let issued_by_key = match kind {
+            SUBPACKET_ISSUER => value == key.key_id(),
+            SUBPACKET_ISSUER_FINGERPRINT => value.get(1..) == Some(&key.fingerprint()[..]),
+            _ => true,
+        };
+        if !issued_by_key {
+            return Ok(false);
+        }
+    }
+    let (r, rest) = decode_mpi(&body[unhashed_end + 2..])?;
+    let (s, rest) = decode_mpi(rest)?;
+    if !rest.is_empty() {
+        return Err(parse_error("Trailing bytes after OpenPGP signature"));
+    }
+    let mut raw = mpi_to_fixed(r, SCALAR_SIZE)?;
+    raw.extend_from_slice(&mpi_to_fixed(s, SCALAR_SIZE)?);
+
+    let mut signed = data.to_vec();
+    append_trailer(&mut signed, &body[..hashed_end]);
+    let digest = match hash {
+        HASH_SHA256 => Sha256::digest(&signed).to_vec(),
+        HASH_SHA384 => Sha384::digest(&signed).to_vec(),
+        HASH_SHA512 => Sha512::digest(&signed).to_vec(),
+        h => {
+            return Err(CryptoError::NoSuchAlgorithm(format!(
+                "Unsupported OpenPGP hash algorithm {}",
+                h
+            )))
+        }
+    };
+    if digest[..2] != body[unhashed_end..unhashed_end + 2] {
+        return Ok(false);
+    }
+    match key.algorithm {
+        KeyAlgorithm::Ed25519 => Ok(Ed25519Sha512::new()
+            .verify(&digest, &raw, &key.public_key)
+            .unwrap_or(false)),
+        #[cfg(any(
+            feature = "ecdsa_secp256k1",
+            feature = "ecdsa_secp256k1_native",
+            feature = "ecdsa_secp256k1_asm"
+        ))]
+        KeyAlgorithm::EcdsaSecp256k1 => {
+            if hash != HASH_SHA256 {
+                return Err(CryptoError::NoSuchAlgorithm(
+                    "ECDSA OpenPGP signatures have to use SHA-256".to_string(),
+                ));
+            }
+            // gpg doesn't normalize s like ursa's verifier expects
+            let scheme = EcdsaSecp256k1Sha256::new();
+            scheme.normalize_s(&mut raw)?;
+            scheme.verify(&signed, &raw, &key.public_key)
+        }
+    }
+}
+
+/// The key packet as it's hashed for fingerprints and certifications
+fn key_hash_prefix(body: &[u8]) -> Vec<u8> {
+    let mut prefix = vec![0x99];
+    prefix.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    prefix.extend_from_slice(body);
+    prefix
+}
+
+/// Append the hashed part of a signature packet and the v4 trailer
+fn append_trailer(signed: &mut Vec<u8>, hashed: &[u8]) {
+    signed.extend_from_slice(hashed);
+    signed.extend_from_slice(&[VERSION, 0xff]);
+    signed.extend_from_slice(&(hashed.len() as u32).to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ARTIFACT: &[u8] = b"release artifact";
+
+    // Made with `gpg --quick-gen-key "Ursa Test <test@example.com>" ed25519 sign`,
+    // `gpg --armor --export` and `gpg --armor --detach-sign`
+    const GPG_ED25519_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEas8rUBYJKwYBBAHaRw8BAQdA+NCwI+c+pDG/dVBu+cal9StjJHekjQQFl5eP
+cdC97sy0HFVyc2EgVGVzdCA8dGVzdEBleGFtcGxlLmNvbT6IkAQTFggAOBYhBJoF
+/6QRDWad7oVXrNUFtktfzmvEBQJqzytQAhsDBQsJCAcCBhUKCQgLAgQWAgMBAh4B
+AheAAAoJENUFtktfzmvEuS8A/AmbAeuuDtH1Iw88jC+8yDk7hsWDaCUGZrwm7DIm
+aU1bAQDvG/KCpCdoRhkOWRteEiXcB/xVc1U+wLnSkDeLMdMaAQ==
+=ylUP
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const GPG_ED25519_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----
+
+iHUEABYIAB0WIQSaBf+kEQ1mne6FV6zVBbZLX85rxAUCas8rUAAKCRDVBbZLX85r
+xBt5AP9Y6fAmgZ4sq1rnvh4fie2QNpPrcVlZNYxRKRAUUGNNfgEAk2lVLy4W4+0m
+ZjI61mOoZaCX1PsaG5ORQfptrO9jOQU=
+=hMRK
+-----END PGP SIGNATURE-----
+";
+    // The same with `--digest-algo SHA512`
+    const GPG_ED25519_SHA512_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----
+
+iHUEABYKAB0WIQSaBf+kEQ1mne6FV6zVBbZLX85rxAUCas8rVAAKCRDVBbZLX85r
+xFUeAQDbE9ZAlFIudsxleb1W/EYoyxXn7GbVFEr/U66eNeqNIwEAwyL72yoOJGBJ
+70VE3JHDRkBo7H1VKlkDhzSdbCfIxwM=
+=9kpm
+-----END PGP SIGNATURE-----
+";
+
+    #[test]
+    fn verify_gpg_signatures() {
+        let key = PgpKey::parse(GPG_ED25519_KEY.as_bytes()).unwrap();
+        assert_eq!(key.algorithm, KeyAlgorithm::Ed25519);
+        assert_eq!(
+            hex::encode_upper(key.fingerprint()),
+            "9A05FFA4110D669DEE8557ACD505B64B5FCE6BC4"
+        );
+        for signature in [GPG_ED25519_SIGNATURE, GPG_ED25519_SHA512_SIGNATURE].iter() {
+            assert!(verify_detached(&key, ARTIFACT, signature.as_bytes()).unwrap());
+            assert!(!verify_detached(&key, b"another artifact", signature.as_bytes()).unwrap());
+        }
+    }
+
+    #[test]
+    fn ed25519_round_trip() {
+        let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let key = PgpKey::new(KeyAlgorithm::Ed25519, pk, 1_600_000_000);
+
+        let signature = sign_detached(&key, &sk, ARTIFACT, 1_600_000_100).unwrap();
+        assert!(verify_detached(&key, ARTIFACT, &signature).unwrap());
+        let armored = armor::armor(ArmorKind::Signature, &signature);
+        assert!(verify_detached(&key, ARTIFACT, armored.as_bytes()).unwrap());
+        assert!(!verify_detached(&key, b"another artifact", &signature).unwrap());
+
+        let gpg_key = PgpKey::parse(GPG_ED25519_KEY.as_bytes()).unwrap();
+        assert!(!verify_detached(&gpg_key, ARTIFACT, &signature).unwrap());
+        assert!(!verify_detached(&key, ARTIFACT, GPG_ED25519_SIGNATURE.as_bytes()).unwrap());
+
+        let transferable = key.to_transferable("Ursa <ursa@example.com>", &sk, 1_600_000_000);
+        assert_eq!(PgpKey::parse(&transferable.unwrap()).unwrap(), key);
+    }
+
+    #[cfg(any(
+        feature = "ecdsa_secp256k1",
+        feature = "ecdsa_secp256k1_native",
+        feature = "ecdsa_secp256k1_asm"
+    ))]
+    #[test]
+    fn ecdsa_secp256k1() {
+        // Made like the Ed25519 vectors with the secp256k1 curve
+        let gpg_key = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mE8Eas8rVBMFK4EEAAoCAwS9l+/BaO+D1ghxIq2VCIfCkZem+h6KgMtTLHhzBfhJ
+WxlW0eAT4Z2H7yUbJZbVbU4gfa2Hjv9Hz1LZc14UP2KetBhVcnNhIEsxIDxrMUBl
+eGFtcGxlLmNvbT6IkAQTEwgAOBYhBIK8IQ+HW3mwRVmFqM4px/7AbBW5BQJqzytU
+AhsDBQsJCAcCBhUKCQgLAgQWAgMBAh4BAheAAAoJEM4px/7AbBW58V8BAJSfPVc+
+Jb1LXDqEk0qBjbMQ+llSRmbNEjD3K7pvCiDeAQCCSCgrajkpaxw1l5AG80fEmB36
+UnnE1eCW/a2yWhh6gA==
+=nG/C
+-----END PGP PUBLIC KEY BLOCK-----
+";
+        let gpg_signature = "-----BEGIN PGP SIGNATURE-----
+
+iIUEABMIAC0WIQSCvCEPh1t5sEVZhajOKcf+wGwVuQUCas8rVA8cazFAZXhhbXBs
+ZS5jb20ACgkQzinH/sBsFbkyOwEAhdwHdONg7bzFEJPLEwErsfsoAiAkOFgMIyUE
+eewAHH8A/ibiSqvx+D9r/Y4vDwipc8QVeC01rF2P23MR5DR3Swni
+=dsJG
+-----END PGP SIGNATURE-----
+";
+        let key = PgpKey::parse(gpg_key.as_bytes()).unwrap();
+        assert_eq!(
+            hex::encode_upper(key.fingerprint()),
+            "82BC210F875B79B0455985A8CE29C7FEC06C15B9"
+        );
+        assert!(verify_detached(&key, ARTIFACT, gpg_signature.as_bytes()).unwrap());
+        assert!(!verify_detached(&key, b"another artifact", gpg_signature.as_bytes()).unwrap());
+
+        let (pk, sk) = EcdsaSecp256k1Sha256::new().keypair(None).unwrap();
+        let key = PgpKey::new(KeyAlgorithm::EcdsaSecp256k1, pk, 1_600_000_000);
+        let signature = sign_detached(&key, &sk, ARTIFACT, 1_600_000_100).unwrap();
+        assert!(verify_detached(&key, ARTIFACT, &signature).unwrap());
+        let transferable = key.to_transferable("Ursa <ursa@example.com>", &sk, 1_600_000_000);
+        assert_eq!(PgpKey::parse(&transferable.unwrap()).unwrap(), key);
+    }
+}
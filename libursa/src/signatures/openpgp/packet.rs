@@ -0,0 +1,188 @@
+//! OpenPGP packet framing, multiprecision integers and signature subpackets.
+
+use CryptoError;
+
+pub const TAG_SIGNATURE: u8 = 2;
+pub const TAG_PUBLIC_KEY: u8 = 6;
+pub const TAG_USER_ID: u8 = 13;
+
+/// Frame `body` as a new format packet
+pub fn encode_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | tag];
+    match body.len() {
+        l if l < 192 => packet.push(l as u8),
+        l if l < 8384 => {
+            let l = l - 192;
+            packet.push((l >> 8) as u8 + 192);
+            packet.push(l as u8);
+        }
+        l => {
+            packet.push(0xff);
+            packet.extend_from_slice(&(l as u32).to_be_bytes());
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Split `data` into packets, returning the tag and body of each.
+/// Both header formats are read. Partial body lengths, which are only used for
+/// streamed data packets, are not supported.
+pub fn decode_packets(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>, CryptoError> {
+    let mut packets = Vec::new();
+    while !data.is_empty() {
+        let header = data[0];
+        if header & 0x80 == 0 {
+            return Err(parse_error("Invalid OpenPGP packet header"));
+        }
+        let (tag, length, used) = if header & 0x40 != 0 {
+            let (length, used) = match data.get(1) {
+                Some(&l) if l < 192 => (l as usize, 2),
+                Some(&l) if l < 224 => match data.get(2) {
+                    Some(&l2) => ((((l as usize) - 192) << 8) + l2 as usize + 192, 3),
+                    None => return Err(truncated()),
+                },
+                Some(0xff) if data.len() >= 6 => (be_length(&data[2..6]), 6),
+                Some(0xff) => return Err(truncated()),
+                Some(_) => return Err(parse_error("Partial OpenPGP packets are not supported")),
+                None => return Err(truncated()),
+            };
+            (header & 0x3f, length, used)
+        } else {
+            let size = match header & 0x03 {
+                0 => 1,
+                1 => 2,
+                2 => 4,
+                _ => {
+                    return Err(parse_error(
+                        "Indeterminate OpenPGP packet lengths are not supported",
+                    ))
+                }
+            };
+            if data.len() < 1 + size {
+                return Err(truncated());
+            }
+            ((header >> 2) & 0x0f, be_length(&data[1..=size]), 1 + size)
+        };
+        if data.len() - used < length {
+            return Err(truncated());
+        }
+        packets.push((tag, &data[used..used + length]));
+        data = &data[used + length..];
+    }
+    Ok(packets)
+}
+
+/// Append `value` as a multiprecision integer, big endian without leading zeros
+pub fn encode_mpi(value: &[u8], out: &mut Vec<u8>) {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    let bits = match value.first() {
+        Some(b) => value.len() * 8 - b.leading_zeros() as usize,
+        None => 0,
+    };
+    out.extend_from_slice(&(bits as u16).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Read a multiprecision integer and return its bytes and the rest of `data`
+pub fn decode_mpi(data: &[u8]) -> Result<(&[u8], &[u8]), CryptoError> {
+    if data.len() < 2 {
+        return Err(truncated());
+    }
+    let length = (u16::from_be_bytes([data[0], data[1]]) as usize).div_ceil(8);
+    if data.len() - 2 < length {
+        return Err(truncated());
+    }
+    Ok((&data[2..2 + length], &data[2 + length..]))
+}
+
+/// Left pad a multiprecision integer to `size` bytes
+pub fn mpi_to_fixed(value: &[u8], size: usize) -> Result<Vec<u8>, CryptoError> {
+    if value.len() > size {
+        return Err(parse_error("OpenPGP integer is too large"));
+    }
+    let mut fixed = vec![0u8; size - value.len()];
+    fixed.extend_from_slice(value);
+    Ok(fixed)
+}
+
+/// Append a signature subpacket
+pub fn encode_subpacket(kind: u8, data: &[u8], out: &mut Vec<u8>) {
+    // Every subpacket written here is shorter than the one byte length limit
+    out.push(data.len() as u8 + 1);
+    out.push(kind);
+    out.extend_from_slice(data);
+}
+
+/// Split a subpacket area, returning the type without the critical bit and the data
+/// of each subpacket
+pub fn decode_subpackets(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>, CryptoError> {
+    let mut subpackets = Vec::new();
+    while !data.is_empty() {
+        let (length, used) = match data[0] {
+            l if l < 192 => (l as usize, 1),
+            l if l < 255 && data.len() >= 2 => {
+                ((((l as usize) - 192) << 8) + data[1] as usize + 192, 2)
+            }
+            255 if data.len() >= 5 => (be_length(&data[1..5]), 5),
+            _ => return Err(truncated()),
+        };
+        if length == 0 || data.len() - used < length {
+            return Err(truncated());
+        }
+        let subpacket = &data[used..used + length];
+        subpackets.push((subpacket[0] & 0x7f, &subpacket[1..]));
+        data = &data[used + length..];
+    }
+    Ok(subpackets)
+}
+
+fn be_length(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |l, b| (l << 8) | *b as usize)
+}
+
+fn truncated() -> CryptoError {
+    parse_error("OpenPGP data is truncated")
+}
+
+pub(super) fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn packet_lengths() {
+        for length in [0, 191, 192, 8383, 8384, 70_000].iter() {
+            let body = vec![7u8; *length];
+            let packet = encode_packet(TAG_SIGNATURE, &body);
+            assert_eq!(
+                decode_packets(&packet).unwrap(),
+                vec![(TAG_SIGNATURE, &body[..])]
+            );
+        }
+        // An old format signature packet with a two byte length, as gpg writes them
+        let mut old = vec![0x89, 0x00, 0x03, 1, 2, 3];
+        old.extend_from_slice(&encode_packet(TAG_USER_ID, b"id"));
+        assert_eq!(
+            decode_packets(&old).unwrap(),
+            vec![(TAG_SIGNATURE, &[1u8, 2, 3][..]), (TAG_USER_ID, &b"id"[..])]
+        );
+        assert!(decode_packets(&old[..5]).is_err());
+    }
+
+    #[test]
+    fn mpis() {
+        let mut out = Vec::new();
+        encode_mpi(&[0, 0, 0x01, 0xff], &mut out);
+        assert_eq!(out, vec![0x00, 0x09, 0x01, 0xff]);
+        let (value, rest) = decode_mpi(&out).unwrap();
+        assert_eq!(value, &[0x01, 0xff]);
+        assert!(rest.is_empty());
+        assert_eq!(mpi_to_fixed(value, 4).unwrap(), vec![0, 0, 0x01, 0xff]);
+        assert!(decode_mpi(&out[..3]).is_err());
+    }
+}
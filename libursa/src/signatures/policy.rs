@@ -0,0 +1,734 @@
+//! M-of-N signature policies over keys of different schemes.
+//!
+//! Custody and governance often need the approval of several parties whose keys live in
+//! different places: an Ed25519 key on a laptop, a P-256 key in an HSM and a BLS key of a
+//! validator. A `Policy` is a tree of such keys and thresholds, e.g. 2 of those 3 keys,
+//! or 2 of the board's keys and 1 of the auditors'. Each key signs on its own with its
+//! own scheme, or through a `KeyProvider`, and the signatures are collected in
+//! `PolicySignatures`, which is serialized as an envelope and checked with a single
+//! `verify_policy` call.
+//!
+//! Keys sign `Policy::signing_message`, which binds the message to the policy, so a
+//! signature collected for one policy can't be counted towards another. The envelope
+//! names its policy by `Policy::id` but doesn't carry it: the verifier holds the policy
+//! it trusts, so an envelope can't bring its own signers.
+//!
+//! ECDSA signatures are `r || s` over the SHA-256 hash of the message, as providers
+//! return them. A policy is encoded as
+//!
+//! ```text
+//! key:       0 | algorithm (1) | public key length (2, big endian) | public key
+//! threshold: 1 | threshold (1) | number of policies (1) | policies
+//! ```
+//!
+//! and the keys are numbered from 0 in the order they appear, which is how signatures
+//! name their key.
+
+use keys::PublicKey;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "bls_bls12381")]
+use super::bls;
+use super::ecdsa_p256::verify_es256_digest;
+#[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+use super::ed25519::Ed25519Sha512;
+use super::provider::KeyProvider;
+#[cfg(any(
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm"
+))]
+use super::secp256k1::EcdsaSecp256k1Sha256;
+use super::SignatureScheme;
+use CryptoError;
+
+/// The version of the signature envelope created here
+pub const VERSION: u8 = 1;
+
+const POLICY_DOMAIN: &[u8] = b"URSA_SIGNATURE_POLICY_V1";
+const MESSAGE_DOMAIN: &[u8] = b"URSA_SIGNATURE_POLICY_MESSAGE_V1";
+const TAG_KEY: u8 = 0;
+const TAG_THRESHOLD: u8 = 1;
+/// How deeply thresholds may nest, which bounds the recursion of parsing
+const MAX_DEPTH: usize = 16;
+
+/// The schemes of the keys in a policy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SignerAlgorithm {
+    /// Keys of `Ed25519Sha512`
+    #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+    Ed25519,
+    /// Keys of `EcdsaSecp256k1Sha256`
+    #[cfg(any(
+        feature = "ecdsa_secp256k1",
+        feature = "ecdsa_secp256k1_native",
+        feature = "ecdsa_secp256k1_asm"
+    ))]
+    EcdsaSecp256k1,
+    /// P-256 keys as their uncompressed point, signing with ECDSA and SHA-256
+    EcdsaP256,
+    /// Keys of `bls::normal`, with the public key in G1
+    #[cfg(feature = "bls_bls12381")]
+    Bls12381G1,
+    /// Keys of `bls::small`, with the public key in G2
+    #[cfg(feature = "bls_bls12381")]
+    Bls12381G2,
+}
+
+impl SignerAlgorithm {
+    /// The id of the algorithm in encoded policies
+    pub fn id(self) -> u8 {
+        match self {
+            #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+            SignerAlgorithm::Ed25519 => 1,
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            SignerAlgorithm::EcdsaSecp256k1 => 2,
+            SignerAlgorithm::EcdsaP256 => 3,
+            #[cfg(feature = "bls_bls12381")]
+            SignerAlgorithm::Bls12381G1 => 4,
+            #[cfg(feature = "bls_bls12381")]
+            SignerAlgorithm::Bls12381G2 => 5,
+        }
+    }
+
+    /// The algorithm with `id`, if it's compiled in
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+            1 => Some(SignerAlgorithm::Ed25519),
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            2 => Some(SignerAlgorithm::EcdsaSecp256k1),
+            3 => Some(SignerAlgorithm::EcdsaP256),
+            #[cfg(feature = "bls_bls12381")]
+            4 => Some(SignerAlgorithm::Bls12381G1),
+            #[cfg(feature = "bls_bls12381")]
+            5 => Some(SignerAlgorithm::Bls12381G2),
+            _ => None,
+        }
+    }
+
+    /// Return true iff `signature` of `message` is valid for `public_key`
+    pub fn verify(self, message: &[u8], signature: &[u8], public_key: &PublicKey) -> bool {
+        match self {
+            #[cfg(any(feature = "ed25519", feature = "ed25519_asm"))]
+            SignerAlgorithm::Ed25519 => Ed25519Sha512::new()
+                .verify(message, signature, public_key)
+                .unwrap_or(false),
+            #[cfg(any(
+                feature = "ecdsa_secp256k1",
+                feature = "ecdsa_secp256k1_native",
+                feature = "ecdsa_secp256k1_asm"
+            ))]
+            SignerAlgorithm::EcdsaSecp256k1 => EcdsaSecp256k1Sha256::new()
+                .verify(message, signature, public_key)
+                .unwrap_or(false),
+            SignerAlgorithm::EcdsaP256 => verify_es256_digest(
+                &public_key[..],
+                Sha256::digest(message).as_slice(),
+                signature,
+            ),
+            #[cfg(feature = "bls_bls12381")]
+            SignerAlgorithm::Bls12381G1 => bls::normal::Bls::new()
+                .verify(message, signature, public_key)
+                .unwrap_or(false),
+            #[cfg(feature = "bls_bls12381")]
+            SignerAlgorithm::Bls12381G2 => bls::small::Bls::new()
+                .verify(message, signature, public_key)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// A tree of keys and thresholds that decides whose signatures are enough
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Satisfied by a signature of the key
+    Key {
+        algorithm: SignerAlgorithm,
+        public_key: PublicKey,
+    },
+    /// Satisfied when `threshold` of `policies` are
+    Threshold {
+        threshold: usize,
+        policies: Vec<Policy>,
+    },
+}
+
+impl Policy {
+    /// A policy satisfied by a signature of `public_key`
+    pub fn key(algorithm: SignerAlgorithm, public_key: PublicKey) -> Self {
+        Policy::Key {
+            algorithm,
+            public_key,
+        }
+    }
+
+    /// A policy satisfied when `threshold` of `policies` are
+    pub fn threshold(threshold: usize, policies: Vec<Policy>) -> Result<Self, CryptoError> {
+        if threshold == 0 || threshold > policies.len() || policies.len() > u8::MAX as usize {
+            return Err(CryptoError::GeneralError(format!(
+                "A threshold of {} is not possible with {} policies",
+                threshold,
+                policies.len()
+            )));
+        }
+        let policy = Policy::Threshold {
+            threshold,
+            policies,
+        };
+        if policy.depth() > MAX_DEPTH {
+            return Err(CryptoError::GeneralError(format!(
+                "Policies can nest at most {} thresholds",
+                MAX_DEPTH
+            )));
+        }
+        Ok(policy)
+    }
+
+    /// The keys of the policy in the order signatures number them
+    pub fn keys(&self) -> Vec<(SignerAlgorithm, &PublicKey)> {
+        let mut keys = Vec::new();
+        self.collect_keys(&mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut Vec<(SignerAlgorithm, &'a PublicKey)>) {
+        match self {
+            Policy::Key {
+                algorithm,
+                public_key,
+            } => keys.push((*algorithm, public_key)),
+            Policy::Threshold { policies, .. } => {
+                for policy in policies {
+                    policy.collect_keys(keys);
+                }
+            }
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match self {
+            Policy::Key { .. } => 0,
+            Policy::Threshold { policies, .. } => {
+                1 + policies.iter().map(Policy::depth).max().unwrap_or(0)
+            }
+        }
+    }
+
+    /// Whether the keys with `signed[i]` set satisfy the policy, starting from key `next`
+    fn is_satisfied(&self, signed: &[bool], next: &mut usize) -> bool {
+        match self {
+            Policy::Key { .. } => {
+                *next += 1;
+                signed[*next - 1]
+            }
+            Policy::Threshold {
+                threshold,
+                policies,
+            } => {
+                // Every subtree is walked to keep the key numbering
+                let satisfied = policies
+                    .iter()
+                    .filter(|p| p.is_satisfied(signed, next))
+                    .count();
+                satisfied >= *threshold
+            }
+        }
+    }
+
+    /// The digest identifying the policy, which envelopes name and signatures cover
+    pub fn id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.input(POLICY_DOMAIN);
+        hasher.input(self.to_bytes());
+        let mut id = [0u8; 32];
+        id.copy_from_slice(hasher.result().as_slice());
+        id
+    }
+
+    /// What the keys sign to approve `message` under the policy
+    pub fn signing_message(&self, message: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.input(MESSAGE_DOMAIN);
+        hasher.input(self.id());
+        hasher.input((message.len() as u64).to_be_bytes());
+        hasher.input(message);
+        hasher.result().to_vec()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Policy::Key {
+                algorithm,
+                public_key,
+            } => {
+                out.push(TAG_KEY);
+                out.push(algorithm.id());
+                out.extend_from_slice(&(public_key.len() as u16).to_be_bytes());
+                out.extend_from_slice(&public_key[..]);
+            }
+            Policy::Threshold {
+                threshold,
+                policies,
+            } => {
+                out.push(TAG_THRESHOLD);
+                out.push(*threshold as u8);
+                out.push(policies.len() as u8);
+                for policy in policies {
+                    policy.encode(out);
+                }
+            }
+        }
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut rest = data;
+        let policy = Policy::decode(&mut rest, 0)?;
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after policy".to_string(),
+            ));
+        }
+        Ok(policy)
+    }
+
+    fn decode(rest: &mut &[u8], depth: usize) -> Result<Self, CryptoError> {
+        let header = take(rest, 3)?;
+        match header[0] {
+            TAG_KEY => {
+                let algorithm = SignerAlgorithm::from_id(header[1]).ok_or_else(|| {
+                    CryptoError::NoSuchAlgorithm(format!(
+                        "Signer algorithm {} is not supported",
+                        header[1]
+                    ))
+                })?;
+                // The header holds the high byte of the key length
+                let len = (header[2] as usize) << 8 | take(rest, 1)?[0] as usize;
+                let public_key = PublicKey(take(rest, len)?.to_vec());
+                Ok(Policy::key(algorithm, public_key))
+            }
+            TAG_THRESHOLD if depth < MAX_DEPTH => {
+                let policies = (0..header[2])
+                    .map(|_| Policy::decode(rest, depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Policy::threshold(header[1] as usize, policies)
+                    .map_err(|e| CryptoError::ParseError(e.to_string()))
+            }
+            _ => Err(CryptoError::ParseError(
+                "Policy is malformed or nests too deeply".to_string(),
+            )),
+        }
+    }
+}
+
+/// The signatures collected for a message under a policy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicySignatures {
+    policy_id: [u8; 32],
+    // (key number, signature), sorted by key number
+    signatures: Vec<(u16, Vec<u8>)>,
+}
+
+impl PolicySignatures {
+    /// An envelope without signatures for `policy`
+    pub fn new(policy: &Policy) -> Self {
+        PolicySignatures {
+            policy_id: policy.id(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// The id of the policy the signatures are for
+    pub fn policy_id(&self) -> &[u8; 32] {
+        &self.policy_id
+    }
+
+    /// The numbers of the keys that have signed
+    pub fn signed_keys(&self) -> Vec<usize> {
+        self.signatures.iter().map(|(k, _)| *k as usize).collect()
+    }
+
+    /// Add the signature of key number `key` of `policy` over `policy.signing_message(message)`,
+    /// replacing any it made before. Returns an error if the signature isn't valid
+    pub fn add(
+        &mut self,
+        policy: &Policy,
+        key: usize,
+        message: &[u8],
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if policy.id() != self.policy_id {
+            return Err(CryptoError::GeneralError(
+                "The signatures are for another policy".to_string(),
+            ));
+        }
+        let keys = policy.keys();
+        let (algorithm, public_key) = keys
+            .get(key)
+            .ok_or_else(|| CryptoError::GeneralError(format!("The policy has no key {}", key)))?;
+        if !algorithm.verify(&policy.signing_message(message), &signature, public_key) {
+            return Err(CryptoError::SigningError(format!(
+                "The signature is not valid for key {}",
+                key
+            )));
+        }
+        let key = key as u16;
+        match self.signatures.binary_search_by_key(&key, |(k, _)| *k) {
+            Ok(i) => self.signatures[i].1 = signature,
+            Err(i) => self.signatures.insert(i, (key, signature)),
+        }
+        Ok(())
+    }
+
+    /// Sign `message` with the private key of key number `key` of `policy`
+    pub fn sign<T: SignatureScheme>(
+        &mut self,
+        scheme: &T,
+        policy: &Policy,
+        key: usize,
+        message: &[u8],
+        private_key: &::keys::PrivateKey,
+    ) -> Result<(), CryptoError> {
+        let signature = scheme.sign(&policy.signing_message(message), private_key)?;
+        self.add(policy, key, message, signature)
+    }
+
+    /// Sign `message` as key number `key` of `policy` with a key held by a `KeyProvider`,
+    /// e.g. a P-256 key in an HSM
+    pub fn sign_with_provider(
+        &mut self,
+        provider: &dyn KeyProvider,
+        policy: &Policy,
+        key: usize,
+        message: &[u8],
+    ) -> Result<(), CryptoError> {
+        let signature = provider.sign(&policy.signing_message(message))?;
+        self.add(policy, key, message, signature)
+    }
+
+    /// Encode as the version as 1 byte, the policy id, the number of signatures as
+    /// 2 bytes, then each one's key number as 2 bytes and the length prefixed signature.
+    /// Integers are big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        out.extend_from_slice(&self.policy_id);
+        out.extend_from_slice(&(self.signatures.len() as u16).to_be_bytes());
+        for (key, signature) in &self.signatures {
+            out.extend_from_slice(&key.to_be_bytes());
+            out.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+            out.extend_from_slice(signature);
+        }
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut rest = data;
+        let version = take(&mut rest, 1)?[0];
+        if version != VERSION {
+            return Err(CryptoError::NoSuchAlgorithm(format!(
+                "Signature envelope version {} is not supported",
+                version
+            )));
+        }
+        let mut policy_id = [0u8; 32];
+        policy_id.copy_from_slice(take(&mut rest, 32)?);
+        let count = take_u16(&mut rest)?;
+        let mut signatures: Vec<(u16, Vec<u8>)> = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key = take_u16(&mut rest)?;
+            let len = take_u16(&mut rest)? as usize;
+            if matches!(signatures.last(), Some((k, _)) if *k >= key) {
+                return Err(CryptoError::ParseError(
+                    "Signatures are not in key order".to_string(),
+                ));
+            }
+            signatures.push((key, take(&mut rest, len)?.to_vec()));
+        }
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after signature envelope".to_string(),
+            ));
+        }
+        Ok(PolicySignatures {
+            policy_id,
+            signatures,
+        })
+    }
+}
+
+/// Check the signature envelope `signatures` from `PolicySignatures::to_bytes` satisfies
+/// `policy` for `message`. Invalid signatures are ignored, the rest must be enough
+pub fn verify_policy(
+    policy: &Policy,
+    message: &[u8],
+    signatures: &[u8],
+) -> Result<(), CryptoError> {
+    let envelope = PolicySignatures::from_bytes(signatures)?;
+    if envelope.policy_id != policy.id() {
+        return Err(CryptoError::GeneralError(
+            "The signatures are for another policy".to_string(),
+        ));
+    }
+    let keys = policy.keys();
+    let signing_message = policy.signing_message(message);
+    let mut signed = vec![false; keys.len()];
+    for (key, signature) in &envelope.signatures {
+        if let Some((algorithm, public_key)) = keys.get(*key as usize) {
+            signed[*key as usize] = algorithm.verify(&signing_message, signature, public_key);
+        }
+    }
+    if policy.is_satisfied(&signed, &mut 0) {
+        Ok(())
+    } else {
+        Err(CryptoError::SigningError(format!(
+            "The {} valid signatures don't satisfy the policy",
+            signed.iter().filter(|s| **s).count()
+        )))
+    }
+}
+
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], CryptoError> {
+    if rest.len() < len {
+        return Err(CryptoError::ParseError("Input is truncated".to_string()));
+    }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Ok(head)
+}
+
+fn take_u16(rest: &mut &[u8]) -> Result<u16, CryptoError> {
+    let bytes = take(rest, 2)?;
+    Ok(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::*;
+    use keys::PrivateKey;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    use p256::{ProjectivePoint, Scalar};
+    use signatures::ecdsa_p256::field_bytes;
+
+    /// A P-256 key in an HSM
+    struct Hsm(Scalar);
+
+    impl KeyProvider for Hsm {
+        fn public_key(&self) -> Result<PublicKey, CryptoError> {
+            let point = (ProjectivePoint::generator() * self.0).to_affine();
+            Ok(PublicKey(point.to_encoded_point(false).as_bytes().to_vec()))
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+            let digest = Sha256::digest(message);
+            let e = Scalar::from_bytes_reduced(&field_bytes(&digest));
+            // Deterministic but not RFC 6979, which is fine for a test
+            let k = Scalar::from_bytes_reduced(&field_bytes(&Sha256::digest(
+                &[&self.0.to_bytes()[..], &digest[..]].concat(),
+            )));
+            let r = Scalar::from_bytes_reduced(
+                (ProjectivePoint::generator() * k)
+                    .to_affine()
+                    .to_encoded_point(false)
+                    .x(),
+            );
+            let s = k.invert().unwrap() * (e + r * self.0);
+            Ok([&r.to_bytes()[..], &s.to_bytes()[..]].concat())
+        }
+    }
+
+    fn custody() -> (Policy, Vec<(PublicKey, PrivateKey)>, Hsm) {
+        let scheme = Ed25519Sha512::new();
+        let keys = (0..2)
+            .map(|_| scheme.keypair(None).unwrap())
+            .collect::<Vec<_>>();
+        let hsm = Hsm(Scalar::from(0x5eed_u64));
+        let policy = Policy::threshold(
+            2,
+            vec![
+                Policy::key(SignerAlgorithm::Ed25519, keys[0].0.clone()),
+                Policy::key(SignerAlgorithm::EcdsaP256, hsm.public_key().unwrap()),
+                Policy::key(SignerAlgorithm::Ed25519, keys[1].0.clone()),
+            ],
+        )
+        .unwrap();
+        (policy, keys, hsm)
+    }
+
+    #[test]
+    fn two_of_three() {
+        let scheme = Ed25519Sha512::new();
+        let (policy, keys, hsm) = custody();
+        let message = b"withdraw 10 BTC";
+        let mut signatures = PolicySignatures::new(&policy);
+        signatures
+            .sign(&scheme, &policy, 0, message, &keys[0].1)
+            .unwrap();
+        assert!(verify_policy(&policy, message, &signatures.to_bytes()).is_err());
+        signatures
+            .sign_with_provider(&hsm, &policy, 1, message)
+            .unwrap();
+        assert_eq!(signatures.signed_keys(), vec![0, 1]);
+        let envelope = signatures.to_bytes();
+        verify_policy(&policy, message, &envelope).unwrap();
+        assert!(verify_policy(&policy, b"withdraw 11 BTC", &envelope).is_err());
+        assert_eq!(PolicySignatures::from_bytes(&envelope).unwrap(), signatures);
+
+        // Keys can't sign in another key's place or twice
+        assert!(signatures
+            .sign(&scheme, &policy, 2, message, &keys[0].1)
+            .is_err());
+        assert!(signatures
+            .sign_with_provider(&hsm, &policy, 0, message)
+            .is_err());
+        assert!(signatures
+            .sign(&scheme, &policy, 3, message, &keys[0].1)
+            .is_err());
+        let mut repeated = PolicySignatures::new(&policy);
+        repeated
+            .sign(&scheme, &policy, 0, message, &keys[0].1)
+            .unwrap();
+        repeated
+            .sign(&scheme, &policy, 0, message, &keys[0].1)
+            .unwrap();
+        assert!(verify_policy(&policy, message, &repeated.to_bytes()).is_err());
+
+        // A plain signature of the message, without the policy, doesn't count
+        let mut plain = PolicySignatures::new(&policy);
+        plain.signatures = vec![
+            (0, scheme.sign(message, &keys[0].1).unwrap()),
+            (2, scheme.sign(message, &keys[1].1).unwrap()),
+        ];
+        assert!(verify_policy(&policy, message, &plain.to_bytes()).is_err());
+
+        // Signatures for another policy with the same keys
+        let other = Policy::threshold(1, vec![policy.clone()]).unwrap();
+        assert!(verify_policy(&other, message, &envelope).is_err());
+        assert!(PolicySignatures::new(&other)
+            .sign(&scheme, &policy, 0, message, &keys[0].1)
+            .is_err());
+    }
+
+    #[test]
+    fn nested_policies() {
+        let scheme = Ed25519Sha512::new();
+        let board = (0..3)
+            .map(|_| scheme.keypair(None).unwrap())
+            .collect::<Vec<_>>();
+        let auditors = (0..2)
+            .map(|_| scheme.keypair(None).unwrap())
+            .collect::<Vec<_>>();
+        let group = |keys: &[(PublicKey, PrivateKey)], m| {
+            Policy::threshold(
+                m,
+                keys.iter()
+                    .map(|k| Policy::key(SignerAlgorithm::Ed25519, k.0.clone()))
+                    .collect(),
+            )
+            .unwrap()
+        };
+        // 2 of the board and 1 of the auditors
+        let policy = Policy::threshold(2, vec![group(&board, 2), group(&auditors, 1)]).unwrap();
+        assert_eq!(policy.keys().len(), 5);
+        let message = b"approve budget";
+        let sign = |signers: &[(usize, &PrivateKey)]| {
+            let mut signatures = PolicySignatures::new(&policy);
+            for (key, sk) in signers {
+                signatures
+                    .sign(&scheme, &policy, *key, message, sk)
+                    .unwrap();
+            }
+            verify_policy(&policy, message, &signatures.to_bytes())
+        };
+        assert!(sign(&[(0, &board[0].1), (2, &board[2].1), (4, &auditors[1].1)]).is_ok());
+        assert!(sign(&[(0, &board[0].1), (1, &board[1].1), (2, &board[2].1)]).is_err());
+        assert!(sign(&[(1, &board[1].1), (3, &auditors[0].1), (4, &auditors[1].1)]).is_err());
+
+        let parsed = Policy::from_bytes(&policy.to_bytes()).unwrap();
+        assert_eq!(parsed, policy);
+        assert_eq!(parsed.id(), policy.id());
+    }
+
+    #[cfg(feature = "bls_bls12381")]
+    #[test]
+    fn bls_signer() {
+        let scheme = Ed25519Sha512::new();
+        let bls_scheme = bls::normal::Bls::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let (bls_pk, bls_sk) = bls_scheme.keypair(None).unwrap();
+        let policy = Policy::threshold(
+            2,
+            vec![
+                Policy::key(SignerAlgorithm::Ed25519, pk),
+                Policy::key(SignerAlgorithm::Bls12381G1, bls_pk),
+            ],
+        )
+        .unwrap();
+        let mut signatures = PolicySignatures::new(&policy);
+        signatures.sign(&scheme, &policy, 0, b"m", &sk).unwrap();
+        assert!(signatures.sign(&scheme, &policy, 1, b"m", &sk).is_err());
+        signatures
+            .sign(&bls_scheme, &policy, 1, b"m", &bls_sk)
+            .unwrap();
+        verify_policy(&policy, b"m", &signatures.to_bytes()).unwrap();
+    }
+
+    #[test]
+    fn encoding() {
+        let (policy, keys, _) = custody();
+        let bytes = policy.to_bytes();
+        assert_eq!(Policy::from_bytes(&bytes).unwrap(), policy);
+        assert!(Policy::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Policy::from_bytes(&trailing).is_err());
+        let mut impossible = bytes.clone();
+        impossible[1] = 4;
+        assert!(Policy::from_bytes(&impossible).is_err());
+        let mut unknown = bytes.clone();
+        unknown[4] = 0xff;
+        assert!(matches!(
+            Policy::from_bytes(&unknown),
+            Err(CryptoError::NoSuchAlgorithm(_))
+        ));
+        assert!(Policy::threshold(0, vec![]).is_err());
+
+        // Thresholds can only nest so deep
+        let mut deep = Policy::key(SignerAlgorithm::Ed25519, keys[0].0.clone());
+        for _ in 0..MAX_DEPTH {
+            deep = Policy::threshold(1, vec![deep]).unwrap();
+        }
+        assert!(Policy::threshold(1, vec![deep.clone()]).is_err());
+        let mut too_deep = vec![TAG_THRESHOLD, 1, 1];
+        too_deep.extend_from_slice(&deep.to_bytes());
+        assert!(Policy::from_bytes(&too_deep).is_err());
+
+        let envelope = PolicySignatures::new(&policy).to_bytes();
+        assert!(PolicySignatures::from_bytes(&envelope[..envelope.len() - 1]).is_err());
+        let mut future = envelope.clone();
+        future[0] = VERSION + 1;
+        assert!(matches!(
+            PolicySignatures::from_bytes(&future),
+            Err(CryptoError::NoSuchAlgorithm(_))
+        ));
+        // Signatures out of key order
+        let mut unordered = PolicySignatures::new(&policy);
+        unordered.signatures = vec![(1, vec![1]), (0, vec![0])];
+        assert!(PolicySignatures::from_bytes(&unordered.to_bytes()).is_err());
+    }
+}
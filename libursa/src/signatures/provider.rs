@@ -0,0 +1,246 @@
+//! Signing keys that are used through a handle instead of their bytes.
+//!
+//! Keys kept in an HSM, a PKCS#11 token or a cloud KMS never leave it, so they can't
+//! be passed as a `PrivateKey`. A `KeyProvider` is a handle to one such key: it reports
+//! the public key and signs on request, and `SignatureScheme::sign_with_provider` accepts
+//! it wherever a scheme would sign with a private key. Backends implement the trait for
+//! their own handles and the rest of an application is written against it unchanged.
+//!
+//! `SoftwareKeyProvider` is the reference implementation holding the key in memory, and
+//! `VaultKeyProvider` names a key of any `KeyVault`, such as the AWS, Google Cloud and
+//! KMIP backends. A provider must sign exactly as the scheme's `sign` would, e.g. an
+//! ECDSA provider hashes the message itself and returns `r || s`.
+//!
+//! `AsyncKeyProvider` is the same interface for backends reached over the network,
+//! returning boxed futures so the calls can be awaited instead of blocking a thread.
+
+use std::future::{self, Future};
+use std::pin::Pin;
+
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+#[cfg(all(feature = "hkdf", feature = "sha2"))]
+use signatures::vault::KeyVault;
+use signatures::{SignatureScheme, Signer};
+use CryptoError;
+
+/// The result of an `AsyncKeyProvider` call
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, CryptoError>> + Send + 'a>>;
+
+/// A handle to a signing key that may be held outside of application memory
+pub trait KeyProvider {
+    /// The public key of the signing key
+    fn public_key(&self) -> Result<PublicKey, CryptoError>;
+
+    /// Sign `message` with the key
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError>;
+}
+
+/// A `KeyProvider` whose calls complete asynchronously
+pub trait AsyncKeyProvider {
+    /// The public key of the signing key
+    fn public_key<'a>(&'a self) -> ProviderFuture<'a, PublicKey>;
+
+    /// Sign `message` with the key
+    fn sign<'a>(&'a self, message: &'a [u8]) -> ProviderFuture<'a, Vec<u8>>;
+}
+
+/// A provider holding the private key in process memory.
+///
+/// This gives no more protection than using the key directly. It is for tests and for
+/// hosts without a hardware backend, so code written against `KeyProvider` can move to
+/// one unchanged.
+pub struct SoftwareKeyProvider<T: SignatureScheme> {
+    scheme: T,
+    public_key: PublicKey,
+    private_key: PrivateKey,
+}
+
+impl<T: SignatureScheme> SoftwareKeyProvider<T> {
+    /// Hold `private_key`, computing its public key with the scheme
+    pub fn new(private_key: PrivateKey) -> Result<Self, CryptoError> {
+        let scheme = T::new();
+        let (public_key, _) =
+            scheme.keypair(Some(KeyGenOption::FromSecretKey(private_key.clone())))?;
+        Ok(SoftwareKeyProvider {
+            scheme,
+            public_key,
+            private_key,
+        })
+    }
+
+    /// Generate a new key
+    pub fn generate() -> Result<Self, CryptoError> {
+        let scheme = T::new();
+        let (public_key, private_key) = scheme.keypair(None)?;
+        Ok(SoftwareKeyProvider {
+            scheme,
+            public_key,
+            private_key,
+        })
+    }
+}
+
+impl<T: SignatureScheme> KeyProvider for SoftwareKeyProvider<T> {
+    fn public_key(&self) -> Result<PublicKey, CryptoError> {
+        Ok(self.public_key.clone())
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.scheme.sign(message, &self.private_key)
+    }
+}
+
+impl<T: SignatureScheme> AsyncKeyProvider for SoftwareKeyProvider<T> {
+    fn public_key<'a>(&'a self) -> ProviderFuture<'a, PublicKey> {
+        Box::pin(future::ready(KeyProvider::public_key(self)))
+    }
+
+    fn sign<'a>(&'a self, message: &'a [u8]) -> ProviderFuture<'a, Vec<u8>> {
+        Box::pin(future::ready(KeyProvider::sign(self, message)))
+    }
+}
+
+impl<'a, 'b, T: 'a + SignatureScheme> KeyProvider for Signer<'a, 'b, T> {
+    fn public_key(&self) -> Result<PublicKey, CryptoError> {
+        self.get_public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        Signer::sign(self, message)
+    }
+}
+
+/// The key at `path` under the seed named `seed_id` of a `KeyVault`
+#[cfg(all(feature = "hkdf", feature = "sha2"))]
+pub struct VaultKeyProvider<'a, V: KeyVault> {
+    vault: &'a V,
+    seed_id: String,
+    path: Vec<u32>,
+}
+
+#[cfg(all(feature = "hkdf", feature = "sha2"))]
+impl<'a, V: KeyVault> VaultKeyProvider<'a, V> {
+    /// Name a key of `vault`. Backends whose keys are used as they are need an empty path
+    pub fn new(vault: &'a V, seed_id: &str, path: &[u32]) -> Self {
+        VaultKeyProvider {
+            vault,
+            seed_id: seed_id.to_string(),
+            path: path.to_vec(),
+        }
+    }
+}
+
+#[cfg(all(feature = "hkdf", feature = "sha2"))]
+impl<'a, V: KeyVault> KeyProvider for VaultKeyProvider<'a, V> {
+    fn public_key(&self) -> Result<PublicKey, CryptoError> {
+        self.vault.derive_public_key(&self.seed_id, &self.path)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.vault.sign_derived(&self.seed_id, &self.path, message)
+    }
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    /// Poll a future that is ready on its first poll
+    fn poll_ready<T>(mut f: ProviderFuture<T>) -> Result<T, CryptoError> {
+        fn raw() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                raw()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(raw()) };
+        match f.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(r) => r,
+            Poll::Pending => panic!("software provider futures are ready at once"),
+        }
+    }
+
+    #[test]
+    fn software_provider() {
+        let scheme = Ed25519Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let provider = SoftwareKeyProvider::<Ed25519Sha512>::new(sk.clone()).unwrap();
+        assert_eq!(KeyProvider::public_key(&provider).unwrap(), pk);
+
+        let signature = scheme.sign_with_provider(b"message", &provider).unwrap();
+        assert!(scheme.verify(b"message", &signature, &pk).unwrap());
+        // The reference provider signs the same as the scheme
+        assert_eq!(signature, scheme.sign(b"message", &sk).unwrap());
+        // and so does the borrowed Signer
+        let signer = Signer::new(&scheme, &sk);
+        assert_eq!(
+            scheme.sign_with_provider(b"message", &signer).unwrap(),
+            signature
+        );
+
+        assert_eq!(
+            poll_ready(AsyncKeyProvider::public_key(&provider)).unwrap(),
+            pk
+        );
+        let signature = poll_ready(AsyncKeyProvider::sign(&provider, b"message")).unwrap();
+        assert!(scheme.verify(b"message", &signature, &pk).unwrap());
+    }
+
+    /// A faulty backend that signs with another key or returns another encoding
+    struct Faulty<'a>(&'a SoftwareKeyProvider<Ed25519Sha512>, PublicKey, bool);
+
+    impl<'a> KeyProvider for Faulty<'a> {
+        fn public_key(&self) -> Result<PublicKey, CryptoError> {
+            Ok(self.1.clone())
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+            let mut signature = KeyProvider::sign(self.0, message)?;
+            if self.2 {
+                signature.push(0);
+            }
+            Ok(signature)
+        }
+    }
+
+    #[test]
+    fn faulty_provider() {
+        let scheme = Ed25519Sha512::new();
+        let provider = SoftwareKeyProvider::<Ed25519Sha512>::generate().unwrap();
+        let other = SoftwareKeyProvider::<Ed25519Sha512>::generate().unwrap();
+        let pk = KeyProvider::public_key(&provider).unwrap();
+
+        let wrong_key = Faulty(&other, pk.clone(), false);
+        assert!(scheme.sign_with_provider(b"message", &wrong_key).is_err());
+        let wrong_size = Faulty(&provider, pk, true);
+        assert!(scheme.sign_with_provider(b"message", &wrong_size).is_err());
+    }
+
+    #[cfg(all(feature = "hkdf", feature = "sha2"))]
+    #[test]
+    fn vault_provider() {
+        use keys::SecretBytes;
+        use signatures::vault::SoftwareKeyVault;
+
+        let scheme = Ed25519Sha512::new();
+        let mut vault = SoftwareKeyVault::<Ed25519Sha512>::new();
+        vault.import_seed("master", SecretBytes::new(vec![3u8; 32]));
+        let provider = VaultKeyProvider::new(&vault, "master", &[44, 0]);
+        assert_eq!(
+            provider.public_key().unwrap(),
+            vault.derive_public_key("master", &[44, 0]).unwrap()
+        );
+        let signature = scheme.sign_with_provider(b"message", &provider).unwrap();
+        assert!(scheme
+            .verify(b"message", &signature, &provider.public_key().unwrap())
+            .unwrap());
+
+        assert!(scheme
+            .sign_with_provider(b"message", &VaultKeyProvider::new(&vault, "other", &[]))
+            .is_err());
+    }
+}
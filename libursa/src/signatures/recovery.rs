@@ -0,0 +1,365 @@
+//! Schnorr signatures with partial message recovery over Ristretto255.
+//!
+//! This follows the Pintsov-Vanstone construction of ISO/IEC 9796-3 and IEEE 1363a.
+//! A message is split into a recoverable part of at most `MAX_RECOVERABLE_SIZE` bytes
+//! and a clear part. The recoverable part is padded with `REDUNDANCY_SIZE` zero bytes
+//! and encrypted with a key stream derived from the nonce point, the clear part is sent
+//! next to the signature. The verifier recomputes the nonce point from the signature,
+//! decrypts the recoverable part and accepts it only if the redundancy is intact.
+//! A signature is `SIGNATURE_OVERHEAD` bytes plus the recoverable part, so a short
+//! telemetry reading travels as 48 bytes more than itself where an ordinary Schnorr
+//! signature would add 64.
+//!
+//! `RecoverySchnorrRistretto255Sha512` also implements `SignatureScheme`, recovering as
+//! much of the message as fits and comparing it with the message given to `verify`.
+
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use keys::{KeyGenOption, PrivateKey, PublicKey};
+use signatures::SignatureScheme;
+use CryptoError;
+
+pub const ALGORITHM_NAME: &str = "RECOVERY_SCHNORR_RISTRETTO255_SHA2_512";
+/// The most bytes of a message a signature recovers
+pub const MAX_RECOVERABLE_SIZE: usize = 128;
+/// The zero bytes appended to the recoverable part before it is encrypted
+pub const REDUNDANCY_SIZE: usize = 16;
+/// The size of a signature without its recoverable part
+pub const SIGNATURE_OVERHEAD: usize = SCALAR_SIZE + REDUNDANCY_SIZE;
+pub const PRIVATE_KEY_SIZE: usize = SCALAR_SIZE;
+pub const PUBLIC_KEY_SIZE: usize = 32;
+
+const SCALAR_SIZE: usize = 32;
+const KEYGEN_DOMAIN: &[u8] = b"RECOVERY_SCHNORR_RISTRETTO255_SHA2_512_KEYGEN";
+const NONCE_DOMAIN: &[u8] = b"RECOVERY_SCHNORR_RISTRETTO255_SHA2_512_NONCE";
+const KEYSTREAM_DOMAIN: &[u8] = b"RECOVERY_SCHNORR_RISTRETTO255_SHA2_512_KEYSTREAM";
+const CHALLENGE_DOMAIN: &[u8] = b"RECOVERY_SCHNORR_RISTRETTO255_SHA2_512_CHALLENGE";
+
+pub struct RecoverySchnorrRistretto255Sha512;
+
+impl RecoverySchnorrRistretto255Sha512 {
+    /// Sign `recoverable`, which the signature will carry, and `clear`, which the
+    /// verifier must be given. The signature is `SIGNATURE_OVERHEAD` bytes plus the
+    /// length of `recoverable`
+    pub fn sign_with_recovery(
+        &self,
+        recoverable: &[u8],
+        clear: &[u8],
+        sk: &PrivateKey,
+    ) -> Result<Vec<u8>, CryptoError> {
+        if recoverable.len() > MAX_RECOVERABLE_SIZE {
+            return Err(CryptoError::SigningError(format!(
+                "At most {} bytes can be recovered",
+                MAX_RECOVERABLE_SIZE
+            )));
+        }
+        let mut x = parse_secret_key(sk)?;
+        let pk = (&x * &RISTRETTO_BASEPOINT_TABLE).compress();
+
+        let mut noise = [0u8; 32];
+        OsRng.fill_bytes(&mut noise);
+        let mut hasher = Sha512::new();
+        hasher.input(NONCE_DOMAIN);
+        hasher.input(x.as_bytes());
+        hasher.input(noise);
+        hasher.input((recoverable.len() as u64).to_be_bytes());
+        hasher.input(recoverable);
+        hasher.input(clear);
+        let mut k = wide_scalar(hasher);
+        let r = (&k * &RISTRETTO_BASEPOINT_TABLE).compress();
+
+        let mut encrypted = recoverable.to_vec();
+        encrypted.resize(recoverable.len() + REDUNDANCY_SIZE, 0);
+        apply_keystream(&r, &mut encrypted);
+        let d = challenge(&pk, &encrypted, clear);
+        let s = k - d * x;
+        k.zeroize();
+        x.zeroize();
+
+        let mut signature = s.to_bytes().to_vec();
+        signature.extend_from_slice(&encrypted);
+        Ok(signature)
+    }
+
+    /// Check `signature` over `clear` and return the recoverable part it carries.
+    /// Fails if the signature isn't valid for `pk` and `clear`
+    pub fn recover(
+        &self,
+        signature: &[u8],
+        clear: &[u8],
+        pk: &PublicKey,
+    ) -> Result<Vec<u8>, CryptoError> {
+        if signature.len() < SIGNATURE_OVERHEAD
+            || signature.len() > SIGNATURE_OVERHEAD + MAX_RECOVERABLE_SIZE
+        {
+            return Err(CryptoError::ParseError(format!(
+                "Signature must be between {} and {} bytes",
+                SIGNATURE_OVERHEAD,
+                SIGNATURE_OVERHEAD + MAX_RECOVERABLE_SIZE
+            )));
+        }
+        let y = parse_public_key(pk)?;
+        let s = Scalar::from_canonical_bytes(*array_ref!(signature, 0, SCALAR_SIZE))
+            .ok_or_else(|| CryptoError::ParseError("Invalid signature scalar".to_string()))?;
+        let encrypted = &signature[SCALAR_SIZE..];
+
+        let d = challenge(
+            &CompressedRistretto(*array_ref!(&pk[..], 0, PUBLIC_KEY_SIZE)),
+            encrypted,
+            clear,
+        );
+        let r = RistrettoPoint::vartime_multiscalar_mul(&[s, d], &[RISTRETTO_BASEPOINT_POINT, y]);
+        if r.is_identity() {
+            return Err(CryptoError::SigningError(
+                "Signature is not valid".to_string(),
+            ));
+        }
+        let mut decrypted = encrypted.to_vec();
+        apply_keystream(&r.compress(), &mut decrypted);
+        let (recovered, redundancy) = decrypted.split_at(decrypted.len() - REDUNDANCY_SIZE);
+        if redundancy.iter().any(|b| *b != 0) {
+            return Err(CryptoError::SigningError(
+                "Signature is not valid".to_string(),
+            ));
+        }
+        Ok(recovered.to_vec())
+    }
+}
+
+impl SignatureScheme for RecoverySchnorrRistretto255Sha512 {
+    fn new() -> Self {
+        RecoverySchnorrRistretto255Sha512
+    }
+
+    fn keypair(
+        &self,
+        options: Option<KeyGenOption>,
+    ) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        let x = match options {
+            Some(mut o) => match o {
+                KeyGenOption::UseSeed(ref mut s) => {
+                    let mut hasher = Sha512::new();
+                    hasher.input(KEYGEN_DOMAIN);
+                    hasher.input(s.expose_secret());
+                    s.zeroize();
+                    wide_scalar(hasher)
+                }
+                KeyGenOption::FromSecretKey(ref s) => parse_secret_key(s)?,
+            },
+            None => Scalar::random(&mut OsRng),
+        };
+        if x == Scalar::zero() {
+            return Err(CryptoError::KeyGenError("Secret key is zero".to_string()));
+        }
+        let pk = (&x * &RISTRETTO_BASEPOINT_TABLE).compress();
+        Ok((
+            PublicKey(pk.to_bytes().to_vec()),
+            PrivateKey(x.to_bytes().to_vec()),
+        ))
+    }
+
+    /// Sign with as much of `message` as fits recoverable and the rest in the clear
+    fn sign(&self, message: &[u8], sk: &PrivateKey) -> Result<Vec<u8>, CryptoError> {
+        let split = message.len().min(MAX_RECOVERABLE_SIZE);
+        self.sign_with_recovery(&message[..split], &message[split..], sk)
+    }
+
+    /// The recoverable part of `message` is the part the signature's length covers
+    fn verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        pk: &PublicKey,
+    ) -> Result<bool, CryptoError> {
+        if signature.len() < SIGNATURE_OVERHEAD {
+            return Err(CryptoError::ParseError(format!(
+                "Signature must be at least {} bytes",
+                SIGNATURE_OVERHEAD
+            )));
+        }
+        let split = signature.len() - SIGNATURE_OVERHEAD;
+        if split > message.len() {
+            return Ok(false);
+        }
+        match self.recover(signature, &message[split..], pk) {
+            Ok(recovered) => Ok(recovered[..] == message[..split]),
+            Err(CryptoError::SigningError(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The size of a signature that recovers nothing
+    fn signature_size() -> usize {
+        SIGNATURE_OVERHEAD
+    }
+
+    fn private_key_size() -> usize {
+        PRIVATE_KEY_SIZE
+    }
+
+    fn public_key_size() -> usize {
+        PUBLIC_KEY_SIZE
+    }
+}
+
+fn parse_secret_key(sk: &PrivateKey) -> Result<Scalar, CryptoError> {
+    if sk.len() != PRIVATE_KEY_SIZE {
+        return Err(CryptoError::ParseError(format!(
+            "Private key must be {} bytes",
+            PRIVATE_KEY_SIZE
+        )));
+    }
+    match Scalar::from_canonical_bytes(*array_ref!(&sk[..], 0, PRIVATE_KEY_SIZE)) {
+        Some(x) if x != Scalar::zero() => Ok(x),
+        _ => Err(CryptoError::ParseError("Invalid private key".to_string())),
+    }
+}
+
+fn parse_public_key(pk: &PublicKey) -> Result<RistrettoPoint, CryptoError> {
+    if pk.len() != PUBLIC_KEY_SIZE {
+        return Err(CryptoError::ParseError(format!(
+            "Public key must be {} bytes",
+            PUBLIC_KEY_SIZE
+        )));
+    }
+    match CompressedRistretto(*array_ref!(&pk[..], 0, PUBLIC_KEY_SIZE)).decompress() {
+        Some(y) if !y.is_identity() => Ok(y),
+        _ => Err(CryptoError::ParseError("Invalid public key".to_string())),
+    }
+}
+
+/// XOR `data` with the SHA-512 key stream of the nonce point `r`
+fn apply_keystream(r: &CompressedRistretto, data: &mut [u8]) {
+    for (counter, chunk) in data.chunks_mut(64).enumerate() {
+        let mut hasher = Sha512::new();
+        hasher.input(KEYSTREAM_DOMAIN);
+        hasher.input(r.as_bytes());
+        hasher.input((counter as u32).to_be_bytes());
+        let block = hasher.result();
+        for (b, k) in chunk.iter_mut().zip(block.iter()) {
+            *b ^= *k;
+        }
+    }
+}
+
+/// The challenge binds the public key, the encrypted recoverable part and the clear part
+fn challenge(pk: &CompressedRistretto, encrypted: &[u8], clear: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.input(CHALLENGE_DOMAIN);
+    hasher.input(pk.as_bytes());
+    hasher.input((encrypted.len() as u64).to_be_bytes());
+    hasher.input(encrypted);
+    hasher.input(clear);
+    wide_scalar(hasher)
+}
+
+fn wide_scalar(hasher: Sha512) -> Scalar {
+    let hash = hasher.result();
+    Scalar::from_bytes_mod_order_wide(array_ref!(hash.as_slice(), 0, 64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::SecretBytes;
+
+    #[test]
+    fn recovers_message() {
+        let scheme = RecoverySchnorrRistretto255Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let reading = b"t=21.5C h=40% id=7";
+        let signature = scheme.sign_with_recovery(reading, b"", &sk).unwrap();
+        assert_eq!(signature.len(), SIGNATURE_OVERHEAD + reading.len());
+        assert_eq!(scheme.recover(&signature, b"", &pk).unwrap(), &reading[..]);
+
+        // A clear part is bound too
+        let signature = scheme
+            .sign_with_recovery(reading, b"sensor 7", &sk)
+            .unwrap();
+        assert_eq!(
+            scheme.recover(&signature, b"sensor 7", &pk).unwrap(),
+            &reading[..]
+        );
+        assert!(scheme.recover(&signature, b"sensor 8", &pk).is_err());
+        let empty = scheme.sign_with_recovery(b"", b"sensor 7", &sk).unwrap();
+        assert_eq!(empty.len(), SIGNATURE_OVERHEAD);
+        assert!(scheme.recover(&empty, b"sensor 7", &pk).unwrap().is_empty());
+
+        // Tampering with either half, or another key
+        for i in &[0, SCALAR_SIZE, signature.len() - 1] {
+            let mut tampered = signature.clone();
+            tampered[*i] ^= 1;
+            assert!(scheme.recover(&tampered, b"sensor 7", &pk).is_err());
+        }
+        let (other, _) = scheme.keypair(None).unwrap();
+        assert!(scheme.recover(&signature, b"sensor 7", &other).is_err());
+        assert!(scheme
+            .recover(&signature[..SIGNATURE_OVERHEAD - 1], b"", &pk)
+            .is_err());
+        assert!(scheme
+            .sign_with_recovery(&[0u8; MAX_RECOVERABLE_SIZE + 1], b"", &sk)
+            .is_err());
+    }
+
+    #[test]
+    fn signature_scheme() {
+        let scheme = RecoverySchnorrRistretto255Sha512::new();
+        let seed = SecretBytes::from(&[3u8; 32][..]);
+        let (pk, sk) = scheme.keypair(Some(KeyGenOption::UseSeed(seed))).unwrap();
+        let (pk_2, sk_2) = scheme
+            .keypair(Some(KeyGenOption::FromSecretKey(sk.clone())))
+            .unwrap();
+        assert_eq!(pk, pk_2);
+        assert_eq!(sk, sk_2);
+        assert_eq!(
+            pk.len(),
+            RecoverySchnorrRistretto255Sha512::public_key_size()
+        );
+        assert_eq!(
+            sk.len(),
+            RecoverySchnorrRistretto255Sha512::private_key_size()
+        );
+
+        // Short messages are recovered whole, long ones partly
+        for message in &[b"short".to_vec(), vec![5u8; 300]] {
+            let signature = scheme.sign(message, &sk).unwrap();
+            let recoverable = message.len().min(MAX_RECOVERABLE_SIZE);
+            assert_eq!(signature.len(), SIGNATURE_OVERHEAD + recoverable);
+            assert!(scheme.verify(message, &signature, &pk).unwrap());
+            assert_eq!(
+                scheme
+                    .recover(&signature, &message[recoverable..], &pk)
+                    .unwrap(),
+                &message[..recoverable]
+            );
+            let mut other = message.clone();
+            other[0] ^= 1;
+            assert!(!scheme.verify(&other, &signature, &pk).unwrap());
+        }
+        let signature = scheme.sign(b"short", &sk).unwrap();
+        assert!(!scheme.verify(b"shor", &signature, &pk).unwrap());
+        assert!(!scheme.verify(b"short!", &signature, &pk).unwrap());
+        assert!(scheme.verify(b"short", &signature[..10], &pk).is_err());
+
+        assert!(scheme
+            .keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(vec![0u8; 32]))))
+            .is_err());
+        assert!(scheme
+            .keypair(Some(KeyGenOption::FromSecretKey(PrivateKey(vec![
+                0xffu8;
+                32
+            ]))))
+            .is_err());
+        assert!(scheme
+            .verify(b"short", &signature, &PublicKey(vec![0u8; 32]))
+            .is_err());
+    }
+}
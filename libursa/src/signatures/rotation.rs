@@ -0,0 +1,342 @@
+//! Statements that control of an identity moved from one key to another.
+//!
+//! A `RotationStatement` binds an old public key to its replacement and is signed
+//! by both. The old key's signature shows its holder authorized the new key and the
+//! new key's signature shows its holder accepted it, so neither can be attached to a
+//! rotation the other didn't take part in. Each statement carries its position in the
+//! identity's history and arbitrary application context, e.g. a timestamp.
+//!
+//! A long lived identity publishes its first key and the statements of every rotation
+//! since. `verify_rotation_chain` walks them from the first key and returns the key
+//! currently in control, proving continuity of control without a trusted third party.
+//! Both keys of a statement use the same `SignatureScheme`.
+
+use keys::{PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+use super::provider::KeyProvider;
+use super::SignatureScheme;
+use CryptoError;
+
+const STATEMENT_DOMAIN: &[u8] = b"URSA_KEY_ROTATION_V1";
+const ROLE_OLD: u8 = 1;
+const ROLE_NEW: u8 = 2;
+
+/// A rotation from `old_key` to `new_key` signed by both keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RotationStatement {
+    /// The key being retired
+    pub old_key: PublicKey,
+    /// The key taking over
+    pub new_key: PublicKey,
+    /// The position of this rotation in the identity's history, starting at 0
+    pub sequence: u64,
+    /// Application data bound into the statement
+    pub context: Vec<u8>,
+    /// The old key's signature authorizing the new key
+    pub old_signature: Vec<u8>,
+    /// The new key's signature accepting control
+    pub new_signature: Vec<u8>,
+}
+
+impl RotationStatement {
+    /// Create the statement for rotation number `sequence` from `old` to `new`
+    pub fn new<T: SignatureScheme>(
+        scheme: &T,
+        old: (&PublicKey, &PrivateKey),
+        new: (&PublicKey, &PrivateKey),
+        sequence: u64,
+        context: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let mut statement = Self::unsigned(old.0.clone(), new.0.clone(), sequence, context)?;
+        statement.old_signature = scheme.sign(&statement.message(ROLE_OLD), old.1)?;
+        statement.new_signature = scheme.sign(&statement.message(ROLE_NEW), new.1)?;
+        Ok(statement)
+    }
+
+    /// Like `new` with both keys held by `KeyProvider`s, e.g. in an HSM
+    pub fn new_with_providers<T: SignatureScheme>(
+        scheme: &T,
+        old: &dyn KeyProvider,
+        new: &dyn KeyProvider,
+        sequence: u64,
+        context: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let mut statement =
+            Self::unsigned(old.public_key()?, new.public_key()?, sequence, context)?;
+        statement.old_signature = scheme.sign_with_provider(&statement.message(ROLE_OLD), old)?;
+        statement.new_signature = scheme.sign_with_provider(&statement.message(ROLE_NEW), new)?;
+        Ok(statement)
+    }
+
+    fn unsigned(
+        old_key: PublicKey,
+        new_key: PublicKey,
+        sequence: u64,
+        context: &[u8],
+    ) -> Result<Self, CryptoError> {
+        if old_key == new_key {
+            return Err(CryptoError::KeyGenError(
+                "The new key must differ from the old key".to_string(),
+            ));
+        }
+        Ok(RotationStatement {
+            old_key,
+            new_key,
+            sequence,
+            context: context.to_vec(),
+            old_signature: Vec::new(),
+            new_signature: Vec::new(),
+        })
+    }
+
+    /// Return true iff both signatures are valid
+    pub fn verify<T: SignatureScheme>(&self, scheme: &T) -> Result<bool, CryptoError> {
+        if self.old_key == self.new_key {
+            return Ok(false);
+        }
+        let old = scheme
+            .verify(&self.message(ROLE_OLD), &self.old_signature, &self.old_key)
+            .unwrap_or(false);
+        let new = scheme
+            .verify(&self.message(ROLE_NEW), &self.new_signature, &self.new_key)
+            .unwrap_or(false);
+        Ok(old && new)
+    }
+
+    /// Serialize as the length prefixed keys, the sequence, the length prefixed
+    /// context and the length prefixed signatures. Integers are big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for field in &[&self.old_key[..], &self.new_key[..]] {
+            push_field(&mut out, field);
+        }
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        for field in &[&self.context, &self.old_signature, &self.new_signature] {
+            push_field(&mut out, field);
+        }
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut rest = data;
+        let old_key = PublicKey(take_field(&mut rest)?);
+        let new_key = PublicKey(take_field(&mut rest)?);
+        if rest.len() < 8 {
+            return Err(CryptoError::ParseError(
+                "Rotation statement is truncated".to_string(),
+            ));
+        }
+        let mut sequence = [0u8; 8];
+        sequence.copy_from_slice(&rest[..8]);
+        rest = &rest[8..];
+        let context = take_field(&mut rest)?;
+        let old_signature = take_field(&mut rest)?;
+        let new_signature = take_field(&mut rest)?;
+        if !rest.is_empty() {
+            return Err(CryptoError::ParseError(
+                "Trailing bytes after rotation statement".to_string(),
+            ));
+        }
+        Ok(RotationStatement {
+            old_key,
+            new_key,
+            sequence: u64::from_be_bytes(sequence),
+            context,
+            old_signature,
+            new_signature,
+        })
+    }
+
+    /// The message signed by the key in `role`
+    fn message(&self, role: u8) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.input(STATEMENT_DOMAIN);
+        hasher.input([role]);
+        hasher.input((self.old_key.len() as u64).to_be_bytes());
+        hasher.input(&self.old_key[..]);
+        hasher.input((self.new_key.len() as u64).to_be_bytes());
+        hasher.input(&self.new_key[..]);
+        hasher.input(self.sequence.to_be_bytes());
+        hasher.input((self.context.len() as u64).to_be_bytes());
+        hasher.input(&self.context);
+        hasher.result().to_vec()
+    }
+}
+
+/// Check the rotations of an identity that started with `first_key` and return
+/// the key currently in control.
+///
+/// The statements must be in order: each one's sequence is its index and its old key
+/// is the previous statement's new key. Returns an error naming the first statement
+/// that is out of place or doesn't verify.
+pub fn verify_rotation_chain<T: SignatureScheme>(
+    scheme: &T,
+    first_key: &PublicKey,
+    statements: &[RotationStatement],
+) -> Result<PublicKey, CryptoError> {
+    let mut current = first_key;
+    for (index, statement) in statements.iter().enumerate() {
+        if statement.sequence != index as u64 {
+            return Err(CryptoError::GeneralError(format!(
+                "Rotation {} has sequence {}",
+                index, statement.sequence
+            )));
+        }
+        if &statement.old_key != current {
+            return Err(CryptoError::GeneralError(format!(
+                "Rotation {} doesn't start from the key in control",
+                index
+            )));
+        }
+        if !statement.verify(scheme)? {
+            return Err(CryptoError::GeneralError(format!(
+                "Rotation {} is not signed by both keys",
+                index
+            )));
+        }
+        current = &statement.new_key;
+    }
+    Ok(current.clone())
+}
+
+fn push_field(out: &mut Vec<u8>, field: &[u8]) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+fn take_field(rest: &mut &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if rest.len() < 4 {
+        return Err(CryptoError::ParseError(
+            "Rotation statement is truncated".to_string(),
+        ));
+    }
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&rest[..4]);
+    let len = u32::from_be_bytes(len) as usize;
+    if rest.len() - 4 < len {
+        return Err(CryptoError::ParseError(
+            "Rotation statement is truncated".to_string(),
+        ));
+    }
+    let field = rest[4..4 + len].to_vec();
+    *rest = &rest[4 + len..];
+    Ok(field)
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+
+    fn keys(n: usize) -> Vec<(PublicKey, PrivateKey)> {
+        let scheme = Ed25519Sha512::new();
+        (0..n).map(|_| scheme.keypair(None).unwrap()).collect()
+    }
+
+    fn chain(keys: &[(PublicKey, PrivateKey)]) -> Vec<RotationStatement> {
+        let scheme = Ed25519Sha512::new();
+        keys.windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                RotationStatement::new(
+                    &scheme,
+                    (&pair[0].0, &pair[0].1),
+                    (&pair[1].0, &pair[1].1),
+                    i as u64,
+                    format!("rotation {}", i).as_bytes(),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn statement_verifies() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(3);
+        let statement = chain(&keys[..2]).remove(0);
+        assert!(statement.verify(&scheme).unwrap());
+
+        let parsed = RotationStatement::from_bytes(&statement.to_bytes()).unwrap();
+        assert_eq!(parsed, statement);
+        assert!(parsed.verify(&scheme).unwrap());
+        let bytes = statement.to_bytes();
+        assert!(RotationStatement::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(RotationStatement::from_bytes(&trailing).is_err());
+
+        let mut tampered = statement.clone();
+        tampered.context = b"rotation 7".to_vec();
+        assert!(!tampered.verify(&scheme).unwrap());
+
+        // The new key's signature is needed too
+        let mut unaccepted = statement.clone();
+        unaccepted.new_key = keys[2].0.clone();
+        assert!(!unaccepted.verify(&scheme).unwrap());
+
+        // Signatures are bound to their role
+        let mut swapped = statement;
+        std::mem::swap(&mut swapped.old_signature, &mut swapped.new_signature);
+        assert!(!swapped.verify(&scheme).unwrap());
+
+        let (pk, sk) = &keys[0];
+        assert!(RotationStatement::new(&scheme, (pk, sk), (pk, sk), 0, b"").is_err());
+    }
+
+    #[test]
+    fn provider_statement() {
+        use signatures::provider::SoftwareKeyProvider;
+
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(2);
+        let old = SoftwareKeyProvider::<Ed25519Sha512>::new(keys[0].1.clone()).unwrap();
+        let new = SoftwareKeyProvider::<Ed25519Sha512>::new(keys[1].1.clone()).unwrap();
+        let statement =
+            RotationStatement::new_with_providers(&scheme, &old, &new, 0, b"rotation 0").unwrap();
+        assert!(statement.verify(&scheme).unwrap());
+        assert_eq!(statement, chain(&keys).remove(0));
+        assert!(RotationStatement::new_with_providers(&scheme, &old, &old, 0, b"").is_err());
+    }
+
+    #[test]
+    fn chain_verifies() {
+        let scheme = Ed25519Sha512::new();
+        let keys = keys(4);
+        let statements = chain(&keys);
+        assert_eq!(
+            verify_rotation_chain(&scheme, &keys[0].0, &statements).unwrap(),
+            keys[3].0
+        );
+        assert_eq!(
+            verify_rotation_chain(&scheme, &keys[0].0, &[]).unwrap(),
+            keys[0].0
+        );
+        assert!(verify_rotation_chain(&scheme, &keys[1].0, &statements).is_err());
+
+        // Missing, reordered or tampered links
+        let missing = [statements[0].clone(), statements[2].clone()];
+        assert!(verify_rotation_chain(&scheme, &keys[0].0, &missing).is_err());
+        let reordered = [statements[1].clone(), statements[0].clone()];
+        assert!(verify_rotation_chain(&scheme, &keys[1].0, &reordered).is_err());
+        let mut tampered = statements.clone();
+        tampered[1].sequence = 5;
+        assert!(verify_rotation_chain(&scheme, &keys[0].0, &tampered).is_err());
+
+        // A rotation signed by someone else's new key
+        let intruder = self::keys(1).remove(0);
+        let mut forged = statements;
+        forged[2] = RotationStatement::new(
+            &scheme,
+            (&intruder.0, &intruder.1),
+            (&keys[3].0, &keys[3].1),
+            2,
+            b"rotation 2",
+        )
+        .unwrap();
+        forged[2].old_key = keys[2].0.clone();
+        assert!(verify_rotation_chain(&scheme, &keys[0].0, &forged).is_err());
+    }
+}
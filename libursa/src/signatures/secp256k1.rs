@@ -3,6 +3,7 @@ use sha2::digest::generic_array::typenum::U32;
 use CryptoError;
 
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 
 #[cfg(feature = "serde")]
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
@@ -20,6 +21,19 @@ impl EcdsaSecp256k1Sha256 {
     pub fn normalize_s(&self, signature: &mut [u8]) -> Result<(), CryptoError> {
         self.0.normalize_s(signature)
     }
+
+    /// Generate `count` keypairs from `rng`.
+    ///
+    /// Draws the secrets for every key in one call to `rng` and reuses this
+    /// scheme's precomputed context for every public key. Meant for tests and
+    /// services that create many ephemeral keys.
+    pub fn generate_keypairs<R: RngCore + CryptoRng>(
+        &self,
+        count: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(PublicKey, PrivateKey)>, CryptoError> {
+        self.0.generate_keypairs(count, rng)
+    }
 }
 
 impl SignatureScheme for EcdsaSecp256k1Sha256 {
@@ -54,6 +68,19 @@ impl SignatureScheme for EcdsaSecp256k1Sha256 {
     }
 }
 
+impl HedgedSignatureScheme for EcdsaSecp256k1Sha256 {
+    /// Sign with an RFC 6979 nonce that also covers `noise`, as its section 3.6
+    /// allows
+    fn sign_with_noise(
+        &self,
+        message: &[u8],
+        sk: &PrivateKey,
+        noise: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.0.sign_with_noise::<sha2::Sha256>(message, sk, noise)
+    }
+}
+
 impl EcdsaPublicKeyHandler for EcdsaSecp256k1Sha256 {
     /// Returns the compressed bytes
     fn public_key_compressed(&self, pk: &PublicKey) -> Vec<u8> {
@@ -113,6 +140,7 @@ impl<'a> Deserialize<'a> for EcdsaSecp256k1Sha256 {
 mod ecdsa_secp256k1 {
     use super::*;
     use bitcoinsecp256k1;
+    use bitcoinsecp256k1::ffi::{self, CPtr};
     use sha2::Digest;
     use zeroize::Zeroize;
 
@@ -149,7 +177,7 @@ mod ecdsa_secp256k1 {
                 Some(mut o) => match o {
                     KeyGenOption::UseSeed(ref mut seed) => {
                         let mut s = [0u8; PRIVATE_KEY_SIZE];
-                        let mut rng = ChaChaRng::from_seed(*array_ref!(seed.as_slice(), 0, 32));
+                        let mut rng = ChaChaRng::from_seed(*array_ref!(seed.expose_secret(), 0, 32));
                         seed.zeroize();
                         rng.fill_bytes(&mut s);
                         let k = D::digest(&s);
@@ -174,6 +202,32 @@ mod ecdsa_secp256k1 {
                 PrivateKey(sk[..].to_vec()),
             ))
         }
+        pub fn generate_keypairs<R: RngCore + CryptoRng>(
+            &self,
+            count: usize,
+            rng: &mut R,
+        ) -> Result<Vec<(PublicKey, PrivateKey)>, CryptoError> {
+            let mut seeds = vec![0u8; count * PRIVATE_KEY_SIZE];
+            rng.try_fill_bytes(&mut seeds)
+                .map_err(|e| CryptoError::KeyGenError(e.to_string()))?;
+            let mut keys = Vec::with_capacity(count);
+            for seed in seeds.chunks_mut(PRIVATE_KEY_SIZE) {
+                // Out of range values are so unlikely it's fine to draw again
+                let sk = loop {
+                    match bitcoinsecp256k1::key::SecretKey::from_slice(seed) {
+                        Ok(sk) => break sk,
+                        Err(_) => rng.fill_bytes(seed),
+                    }
+                };
+                let pk = bitcoinsecp256k1::key::PublicKey::from_secret_key(&self.0, &sk);
+                keys.push((
+                    PublicKey(pk.serialize().to_vec()),
+                    PrivateKey(sk[..].to_vec()),
+                ));
+            }
+            seeds.zeroize();
+            Ok(keys)
+        }
         pub fn sign<D>(&self, message: &[u8], sk: &PrivateKey) -> Result<Vec<u8>, CryptoError>
         where
             D: Digest<OutputSize = U32>,
@@ -184,6 +238,41 @@ mod ecdsa_secp256k1 {
             let sig = self.0.sign(&msg, &s);
             Ok(sig.serialize_compact().to_vec())
         }
+        pub fn sign_with_noise<D>(
+            &self,
+            message: &[u8],
+            sk: &PrivateKey,
+            noise: &[u8],
+        ) -> Result<Vec<u8>, CryptoError>
+        where
+            D: Digest<OutputSize = U32>,
+        {
+            let h = D::digest(message);
+            let msg = bitcoinsecp256k1::Message::from_slice(h.as_slice())?;
+            let s = bitcoinsecp256k1::key::SecretKey::from_slice(&sk[..])?;
+            // The RFC 6979 nonce function takes 32 bytes of extra data
+            let mut extra = sha2::Sha256::digest(noise);
+            let mut sig = ffi::Signature::new();
+            let ret = unsafe {
+                ffi::secp256k1_ecdsa_sign(
+                    *self.0.ctx(),
+                    &mut sig,
+                    msg.as_c_ptr(),
+                    s.as_c_ptr(),
+                    ffi::secp256k1_nonce_function_rfc6979,
+                    extra.as_ptr() as *const ffi::types::c_void,
+                )
+            };
+            extra.as_mut_slice().zeroize();
+            if ret != 1 {
+                return Err(CryptoError::SigningError(format!(
+                    "Unable to create a hedged signature"
+                )));
+            }
+            Ok(bitcoinsecp256k1::Signature::from(sig)
+                .serialize_compact()
+                .to_vec())
+        }
         pub fn verify<D>(
             &self,
             message: &[u8],
@@ -218,6 +307,7 @@ mod ecdsa_secp256k1 {
 mod ecdsa_secp256k1 {
     use super::*;
     use k256;
+    use sha2::Digest;
 
     use rand::{RngCore, SeedableRng};
     use rand_chacha::ChaChaRng;
@@ -263,12 +353,49 @@ mod ecdsa_secp256k1 {
             let compressed = verify_key::to_bytes(); //serialized as compressed point
             Ok((PublicKey(compressed.to_vec()), PrivateKey(sk.to_vec())))
         }
+        pub fn generate_keypairs<R: RngCore + CryptoRng>(
+            &self,
+            count: usize,
+            rng: &mut R,
+        ) -> Result<Vec<(PublicKey, PrivateKey)>, CryptoError> {
+            let mut keys = Vec::with_capacity(count);
+            for _ in 0..count {
+                let signing_key = k256::SigningKey::random(&mut *rng);
+                let sk = signing_key.to_bytes();
+                let compressed = signing_key.verify_key().to_bytes();
+                keys.push((PublicKey(compressed.to_vec()), PrivateKey(sk.to_vec())));
+            }
+            Ok(keys)
+        }
         pub fn sign(&self, message: &[u8], sk: &PrivateKey) -> Result<Vec<u8>, CryptoError> {
             let signing_key = k256::SigningKey::new(&sk)
                 .map_err(|e| CryptoError::SigningError(format!("{:?}", e)))?;
             let (sig, _) = k256::signing_key.sign(&message);
             Ok(sig.serialize().to_vec())
         }
+        pub fn sign_with_noise<D>(
+            &self,
+            message: &[u8],
+            sk: &PrivateKey,
+            noise: &[u8],
+        ) -> Result<Vec<u8>, CryptoError>
+        where
+            D: Digest<OutputSize = U32>,
+        {
+            use k256::ecdsa::signature::RandomizedDigestSigner;
+
+            let signing_key = k256::ecdsa::SigningKey::new(&sk[..])
+                .map_err(|e| CryptoError::SigningError(format!("{:?}", e)))?;
+            // k256 mixes the RNG output into its RFC 6979 nonce
+            let mut seed = [0u8; 32];
+            seed.copy_from_slice(sha2::Sha256::digest(noise).as_slice());
+            let rng = ChaChaRng::from_seed(seed);
+            seed.zeroize();
+            let sig: k256::ecdsa::Signature = signing_key
+                .try_sign_digest_with_rng(rng, D::new().chain(message))
+                .map_err(|e| CryptoError::SigningError(format!("{:?}", e)))?;
+            Ok(sig.as_ref().to_vec())
+        }
         pub fn verify(
             &self,
             message: &[u8],
@@ -316,6 +443,24 @@ mod test {
         println!("{:?}", p);
     }
 
+    #[test]
+    fn secp256k1_generate_keypairs() {
+        let scheme = EcdsaSecp256k1Sha256::new();
+        let keys = scheme.generate_keypairs(20, &mut OsRng).unwrap();
+        assert_eq!(keys.len(), 20);
+        for (i, (p, s)) in keys.iter().enumerate() {
+            assert_eq!(p.len(), PUBLIC_KEY_SIZE);
+            assert_eq!(s.len(), PRIVATE_KEY_SIZE);
+            let (p1, _) = scheme
+                .keypair(Some(KeyGenOption::FromSecretKey(s.clone())))
+                .unwrap();
+            assert_eq!(&p1, p);
+            let sig = scheme.sign(MESSAGE_1, s).unwrap();
+            assert!(scheme.verify(MESSAGE_1, &sig, p).unwrap());
+            assert!(keys[i + 1..].iter().all(|(p2, _)| p2 != p));
+        }
+    }
+
     #[test]
     fn secp256k1_load_keys() {
         let scheme = EcdsaSecp256k1Sha256::new();
@@ -489,6 +634,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn secp256k1_hedged_sign() {
+        let scheme = EcdsaSecp256k1Sha256::new();
+        let s = PrivateKey(hex::decode(PRIVATE_KEY).unwrap());
+        let p = PublicKey(hex::decode(PUBLIC_KEY).unwrap());
+
+        let first = scheme.sign_hedged(MESSAGE_1, &s).unwrap();
+        let second = scheme.sign_hedged(MESSAGE_1, &s).unwrap();
+        assert_eq!(first.len(), SIGNATURE_SIZE);
+        assert_ne!(first, second);
+        assert!(scheme.verify(MESSAGE_1, &first, &p).unwrap());
+        assert!(scheme.verify(MESSAGE_1, &second, &p).unwrap());
+
+        let noisy = scheme.sign_with_noise(MESSAGE_1, &s, b"noise").unwrap();
+        assert_eq!(
+            noisy,
+            scheme.sign_with_noise(MESSAGE_1, &s, b"noise").unwrap()
+        );
+        assert_ne!(hex::encode(&noisy), SIGNATURE_1);
+        assert!(!scheme.verify(b"another message", &noisy, &p).unwrap());
+    }
+
     #[test]
     fn secp256k1_publickey_compression() {
         let scheme = EcdsaSecp256k1Sha256::new();
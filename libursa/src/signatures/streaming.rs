@@ -0,0 +1,392 @@
+//! Signatures over streamed content that verify chunk by chunk.
+//!
+//! Checking an ordinary signature over a firmware image needs the whole image, so a
+//! device would have to buffer or write out a corrupted or malicious download before
+//! finding out. Here the content is cut into chunks of a fixed size, the chunks are the
+//! leaves of an RFC 6962 [`MerkleTree`](hash::merkle::MerkleTree) over SHA-256, and only
+//! its root is signed, in a `StreamManifest` along with the chunk size and total length.
+//!
+//! The sender signs with a `StreamSigner` fed the content in pieces of any size and
+//! sends the manifest first, then each chunk with its `AuditProof` from
+//! `SignedStream::prove`. A `StreamVerifier` checks the manifest's signature before any
+//! chunk, then each chunk against the signed root as it arrives, so a device can write
+//! a chunk to flash once it is authenticated and reject the stream at the first bad one.
+//! Chunks must come in order and have the signed size, only the last may be shorter,
+//! and `StreamVerifier::finish` checks none are missing.
+//!
+//! A proof is about `log2(n)` hashes, for 4 KiB chunks of a 16 MiB image 12 hashes
+//! per chunk.
+
+use hash::merkle::{leaf_hash, AuditProof, MerkleTree};
+use keys::{PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+use super::SignatureScheme;
+use CryptoError;
+
+const MANIFEST_DOMAIN: &[u8] = b"URSA_STREAM_MANIFEST_V1";
+const ROOT_SIZE: usize = 32;
+
+/// The signed root of the chunks of a stream
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamManifest {
+    /// The size of every chunk but the last
+    pub chunk_size: u32,
+    /// The length of the content in bytes
+    pub length: u64,
+    /// The Merkle tree root of the chunks
+    pub root: Vec<u8>,
+    /// The signature over the chunk size, length and root
+    pub signature: Vec<u8>,
+}
+
+impl StreamManifest {
+    /// The number of chunks the content is cut into
+    pub fn chunks(&self) -> u64 {
+        chunks(self.length, self.chunk_size)
+    }
+
+    /// Return true iff the manifest is signed by `public_key`
+    pub fn verify<T: SignatureScheme>(
+        &self,
+        scheme: &T,
+        public_key: &PublicKey,
+    ) -> Result<bool, CryptoError> {
+        if self.chunk_size == 0 || self.root.len() != ROOT_SIZE {
+            return Ok(false);
+        }
+        Ok(scheme
+            .verify(
+                &message(self.chunk_size, self.length, &self.root),
+                &self.signature,
+                public_key,
+            )
+            .unwrap_or(false))
+    }
+
+    /// Serialize as the chunk size as 4 bytes and the length as 8 bytes big endian,
+    /// then the root and the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12 + ROOT_SIZE + self.signature.len());
+        out.extend_from_slice(&self.chunk_size.to_be_bytes());
+        out.extend_from_slice(&self.length.to_be_bytes());
+        out.extend_from_slice(&self.root);
+        out.extend_from_slice(&self.signature);
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        if data.len() < 12 + ROOT_SIZE {
+            return Err(CryptoError::ParseError(
+                "Stream manifest is truncated".to_string(),
+            ));
+        }
+        let mut chunk_size = [0u8; 4];
+        chunk_size.copy_from_slice(&data[..4]);
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&data[4..12]);
+        let chunk_size = u32::from_be_bytes(chunk_size);
+        if chunk_size == 0 {
+            return Err(CryptoError::ParseError(
+                "Stream manifest has no chunk size".to_string(),
+            ));
+        }
+        Ok(StreamManifest {
+            chunk_size,
+            length: u64::from_be_bytes(length),
+            root: data[12..12 + ROOT_SIZE].to_vec(),
+            signature: data[12 + ROOT_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// Cuts content fed in pieces of any size into chunks and signs their root
+#[derive(Clone, Debug)]
+pub struct StreamSigner {
+    chunk_size: u32,
+    length: u64,
+    pending: Vec<u8>,
+    tree: MerkleTree<Sha256>,
+}
+
+impl StreamSigner {
+    /// A signer cutting the content into chunks of `chunk_size` bytes
+    pub fn new(chunk_size: u32) -> Result<Self, CryptoError> {
+        if chunk_size == 0 {
+            return Err(CryptoError::GeneralError(
+                "The chunk size must not be zero".to_string(),
+            ));
+        }
+        Ok(StreamSigner {
+            chunk_size,
+            length: 0,
+            pending: Vec::with_capacity(chunk_size as usize),
+            tree: MerkleTree::new(),
+        })
+    }
+
+    /// Append `data` to the content
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        let chunk_size = self.chunk_size as usize;
+        while !data.is_empty() {
+            let take = (chunk_size - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() == chunk_size {
+                self.tree.append(&self.pending);
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// Sign the root of the content's chunks with `private_key`
+    pub fn finish<T: SignatureScheme>(
+        mut self,
+        scheme: &T,
+        private_key: &PrivateKey,
+    ) -> Result<SignedStream, CryptoError> {
+        if !self.pending.is_empty() {
+            self.tree.append(&self.pending);
+        }
+        let root = self.tree.root();
+        let signature = scheme.sign(&message(self.chunk_size, self.length, &root), private_key)?;
+        Ok(SignedStream {
+            manifest: StreamManifest {
+                chunk_size: self.chunk_size,
+                length: self.length,
+                root,
+                signature,
+            },
+            tree: self.tree,
+        })
+    }
+}
+
+/// A signed stream's manifest and the tree its chunk proofs come from
+#[derive(Clone, Debug)]
+pub struct SignedStream {
+    manifest: StreamManifest,
+    tree: MerkleTree<Sha256>,
+}
+
+impl SignedStream {
+    /// The manifest to send before the chunks
+    pub fn manifest(&self) -> &StreamManifest {
+        &self.manifest
+    }
+
+    /// The proof to send with the chunk at `index`
+    pub fn prove(&self, index: u64) -> Result<AuditProof, CryptoError> {
+        self.tree.prove_inclusion(index, self.tree.len())
+    }
+}
+
+/// Authenticates the chunks of a stream as they arrive
+#[derive(Clone, Debug)]
+pub struct StreamVerifier {
+    manifest: StreamManifest,
+    next: u64,
+}
+
+impl StreamVerifier {
+    /// Start verifying the stream of `manifest`, after checking it is signed by
+    /// `public_key`
+    pub fn new<T: SignatureScheme>(
+        scheme: &T,
+        manifest: StreamManifest,
+        public_key: &PublicKey,
+    ) -> Result<Self, CryptoError> {
+        if !manifest.verify(scheme, public_key)? {
+            return Err(CryptoError::SigningError(
+                "Stream manifest is not signed by the key".to_string(),
+            ));
+        }
+        Ok(StreamVerifier { manifest, next: 0 })
+    }
+
+    /// The index of the chunk expected next
+    pub fn next_chunk(&self) -> u64 {
+        self.next
+    }
+
+    /// Check `chunk` is the next chunk of the signed content, with the proof the sender
+    /// sent along. Returns an error without advancing if it isn't
+    pub fn verify_chunk(&mut self, chunk: &[u8], proof: &AuditProof) -> Result<(), CryptoError> {
+        let chunks = self.manifest.chunks();
+        if self.next == chunks {
+            return Err(CryptoError::GeneralError(format!(
+                "The stream has only {} chunks",
+                chunks
+            )));
+        }
+        let expected = if self.next + 1 == chunks {
+            self.manifest.length - self.next * u64::from(self.manifest.chunk_size)
+        } else {
+            u64::from(self.manifest.chunk_size)
+        };
+        if chunk.len() as u64 != expected {
+            return Err(CryptoError::GeneralError(format!(
+                "Chunk {} has {} bytes, not {}",
+                self.next,
+                chunk.len(),
+                expected
+            )));
+        }
+        if proof.index() != self.next
+            || proof.size() != chunks
+            || !proof.verify_hash::<Sha256>(&self.manifest.root, &leaf_hash::<Sha256>(chunk))
+        {
+            return Err(CryptoError::GeneralError(format!(
+                "Chunk {} is not part of the signed content",
+                self.next
+            )));
+        }
+        self.next += 1;
+        Ok(())
+    }
+
+    /// Check every chunk was verified
+    pub fn finish(self) -> Result<(), CryptoError> {
+        let chunks = self.manifest.chunks();
+        if self.next != chunks {
+            return Err(CryptoError::GeneralError(format!(
+                "The stream ended after {} of {} chunks",
+                self.next, chunks
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn chunks(length: u64, chunk_size: u32) -> u64 {
+    length.div_ceil(u64::from(chunk_size))
+}
+
+/// The message the manifest's signature is over
+fn message(chunk_size: u32, length: u64, root: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.input(MANIFEST_DOMAIN);
+    hasher.input(chunk_size.to_be_bytes());
+    hasher.input(length.to_be_bytes());
+    hasher.input(root);
+    hasher.result().to_vec()
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+
+    fn content(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 7 + i / 256) as u8).collect()
+    }
+
+    fn sign(data: &[u8], chunk_size: u32, sk: &PrivateKey) -> SignedStream {
+        let mut signer = StreamSigner::new(chunk_size).unwrap();
+        // Pieces that don't line up with the chunks
+        for piece in data.chunks(37) {
+            signer.update(piece);
+        }
+        signer.finish(&Ed25519Sha512::new(), sk).unwrap()
+    }
+
+    #[test]
+    fn stream_verifies() {
+        let scheme = Ed25519Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        for length in [0usize, 1, 255, 256, 257, 4096, 5000].iter() {
+            let data = content(*length);
+            let signed = sign(&data, 256, &sk);
+            let mut whole = StreamSigner::new(256).unwrap();
+            whole.update(&data);
+            assert_eq!(
+                whole.finish(&scheme, &sk).unwrap().manifest().root,
+                signed.manifest().root
+            );
+
+            let manifest = StreamManifest::from_bytes(&signed.manifest().to_bytes()).unwrap();
+            assert_eq!(&manifest, signed.manifest());
+            assert_eq!(manifest.chunks(), (*length as u64 + 255) / 256);
+            let mut verifier = StreamVerifier::new(&scheme, manifest, &pk).unwrap();
+            for (i, chunk) in data.chunks(256).enumerate() {
+                assert_eq!(verifier.next_chunk(), i as u64);
+                let proof = signed.prove(i as u64).unwrap();
+                let proof = AuditProof::from_bytes(&proof.to_bytes()).unwrap();
+                verifier.verify_chunk(chunk, &proof).unwrap();
+            }
+            verifier.finish().unwrap();
+        }
+        assert!(StreamSigner::new(0).is_err());
+    }
+
+    #[test]
+    fn rejects_early() {
+        let scheme = Ed25519Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let data = content(1000);
+        let signed = sign(&data, 100, &sk);
+        let chunks = data.chunks(100).collect::<Vec<_>>();
+        let start = || StreamVerifier::new(&scheme, signed.manifest().clone(), &pk).unwrap();
+
+        // A manifest signed by another key or changed after signing
+        let (other, _) = scheme.keypair(None).unwrap();
+        assert!(StreamVerifier::new(&scheme, signed.manifest().clone(), &other).is_err());
+        let mut longer = signed.manifest().clone();
+        longer.length += 1;
+        assert!(StreamVerifier::new(&scheme, longer, &pk).is_err());
+        let bytes = signed.manifest().to_bytes();
+        assert!(StreamManifest::from_bytes(&bytes[..43]).is_err());
+        assert!(StreamManifest::from_bytes(&[0u8; 44]).is_err());
+
+        // A corrupted chunk is rejected when it arrives and can be retried
+        let mut verifier = start();
+        verifier
+            .verify_chunk(chunks[0], &signed.prove(0).unwrap())
+            .unwrap();
+        let mut corrupted = chunks[1].to_vec();
+        corrupted[50] ^= 1;
+        assert!(verifier
+            .verify_chunk(&corrupted, &signed.prove(1).unwrap())
+            .is_err());
+        assert_eq!(verifier.next_chunk(), 1);
+        verifier
+            .verify_chunk(chunks[1], &signed.prove(1).unwrap())
+            .unwrap();
+
+        // Chunks out of order, repeated, truncated or past the end
+        assert!(verifier
+            .verify_chunk(chunks[3], &signed.prove(3).unwrap())
+            .is_err());
+        assert!(verifier
+            .verify_chunk(chunks[1], &signed.prove(1).unwrap())
+            .is_err());
+        assert!(verifier
+            .verify_chunk(&chunks[2][..99], &signed.prove(2).unwrap())
+            .is_err());
+        for i in 2..10 {
+            verifier
+                .verify_chunk(chunks[i], &signed.prove(i as u64).unwrap())
+                .unwrap();
+        }
+        assert!(verifier
+            .verify_chunk(chunks[9], &signed.prove(9).unwrap())
+            .is_err());
+        verifier.finish().unwrap();
+
+        // A stream cut short
+        let mut verifier = start();
+        verifier
+            .verify_chunk(chunks[0], &signed.prove(0).unwrap())
+            .unwrap();
+        assert!(verifier.finish().is_err());
+
+        // A chunk of another stream signed by the same key
+        let other = sign(&content(999), 100, &sk);
+        let mut verifier = start();
+        assert!(verifier
+            .verify_chunk(&content(999)[..100], &other.prove(0).unwrap())
+            .is_err());
+    }
+}
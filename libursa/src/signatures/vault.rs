@@ -0,0 +1,160 @@
+//! Keys derived in place from a seed sealed inside a key vault.
+//!
+//! A vault such as an HSM holds master seeds that are never exported. Callers name
+//! a seed and a derivation path, and the vault derives the child key, hands back only
+//! its public key and signs with it on request, so neither the master seed nor the
+//! derived private key has to enter application memory.
+//!
+//! Each step of a path derives a 32 byte child seed from its parent with HKDF-SHA256
+//! and the step's index, and the scheme's keypair is then generated from the final
+//! seed with `KeyGenOption::UseSeed`. A child seed reveals nothing about its parent or
+//! its siblings.
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::collections::HashMap;
+use zeroize::Zeroize;
+
+use keys::{KeyGenOption, PrivateKey, PublicKey, SecretBytes};
+use signatures::SignatureScheme;
+use CryptoError;
+
+const DERIVE_SALT: &[u8] = b"URSA_VAULT_DERIVE_V1";
+const CHILD_SEED_SIZE: usize = 32;
+
+/// Key generation and signing against seeds held by a vault
+pub trait KeyVault {
+    /// Derive the key at `path` under the seed named `seed_id` and return its public key
+    fn derive_public_key(&self, seed_id: &str, path: &[u32]) -> Result<PublicKey, CryptoError>;
+
+    /// Sign `message` with the key at `path` under the seed named `seed_id`
+    fn sign_derived(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+}
+
+/// Derive the child seed at `path` from `seed`
+pub fn derive_seed(seed: &SecretBytes, path: &[u32]) -> Result<SecretBytes, CryptoError> {
+    let mut current = seed.clone();
+    for index in path {
+        let mut okm = [0u8; CHILD_SEED_SIZE];
+        Hkdf::<Sha256>::new(Some(DERIVE_SALT), current.expose_secret())
+            .expand(&index.to_be_bytes(), &mut okm)
+            .map_err(|e| CryptoError::KeyGenError(format!("Failed to derive seed: {}", e)))?;
+        current = SecretBytes::from(&okm[..]);
+        okm.zeroize();
+    }
+    Ok(current)
+}
+
+/// For backends whose keys are used as they are, check no derivation was asked for
+#[cfg(any(feature = "aws_kms", feature = "gcp_kms", feature = "kmip"))]
+pub(crate) fn check_underived(path: &[u32], backend: &str) -> Result<(), CryptoError> {
+    if path.is_empty() {
+        Ok(())
+    } else {
+        Err(CryptoError::NoSuchAlgorithm(format!(
+            "{} keys can't be derived",
+            backend
+        )))
+    }
+}
+
+/// A vault that keeps its seeds in process memory.
+///
+/// This gives no more protection than holding the seed directly. It is for tests and
+/// for hosts without a hardware vault, so code written against `KeyVault` can move to
+/// one unchanged.
+pub struct SoftwareKeyVault<T: SignatureScheme> {
+    scheme: T,
+    seeds: HashMap<String, SecretBytes>,
+}
+
+impl<T: SignatureScheme> SoftwareKeyVault<T> {
+    pub fn new() -> Self {
+        SoftwareKeyVault {
+            scheme: T::new(),
+            seeds: HashMap::new(),
+        }
+    }
+
+    /// Seal `seed` in the vault under `seed_id`, replacing any seed already there
+    pub fn import_seed(&mut self, seed_id: &str, seed: SecretBytes) {
+        self.seeds.insert(seed_id.to_string(), seed);
+    }
+
+    fn derive_keypair(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+    ) -> Result<(PublicKey, PrivateKey), CryptoError> {
+        let seed = self
+            .seeds
+            .get(seed_id)
+            .ok_or_else(|| CryptoError::KeyGenError(format!("No seed named {}", seed_id)))?;
+        self.scheme
+            .keypair(Some(KeyGenOption::UseSeed(derive_seed(seed, path)?)))
+    }
+}
+
+impl<T: SignatureScheme> Default for SoftwareKeyVault<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: SignatureScheme> KeyVault for SoftwareKeyVault<T> {
+    fn derive_public_key(&self, seed_id: &str, path: &[u32]) -> Result<PublicKey, CryptoError> {
+        Ok(self.derive_keypair(seed_id, path)?.0)
+    }
+
+    fn sign_derived(
+        &self,
+        seed_id: &str,
+        path: &[u32],
+        message: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let (_, sk) = self.derive_keypair(seed_id, path)?;
+        self.scheme.sign(message, &sk)
+    }
+}
+
+#[cfg(all(test, any(feature = "ed25519", feature = "ed25519_asm")))]
+mod test {
+    use super::super::ed25519::Ed25519Sha512;
+    use super::*;
+
+    #[test]
+    fn derive_in_place() {
+        let mut vault = SoftwareKeyVault::<Ed25519Sha512>::new();
+        vault.import_seed("master", SecretBytes::new(vec![3u8; 32]));
+
+        let pk = vault.derive_public_key("master", &[44, 0, 1]).unwrap();
+        assert_eq!(pk, vault.derive_public_key("master", &[44, 0, 1]).unwrap());
+        assert_ne!(pk, vault.derive_public_key("master", &[44, 0, 2]).unwrap());
+        assert_ne!(pk, vault.derive_public_key("master", &[44, 0]).unwrap());
+
+        let signature = vault
+            .sign_derived("master", &[44, 0, 1], b"message")
+            .unwrap();
+        assert!(Ed25519Sha512::new()
+            .verify(b"message", &signature, &pk)
+            .unwrap());
+
+        assert!(vault.derive_public_key("other", &[44, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn derive_seed_steps() {
+        let seed = SecretBytes::new(vec![5u8; 32]);
+        assert_eq!(derive_seed(&seed, &[]).unwrap(), seed);
+        let child = derive_seed(&seed, &[7]).unwrap();
+        assert_eq!(
+            derive_seed(&child, &[9]).unwrap(),
+            derive_seed(&seed, &[7, 9]).unwrap()
+        );
+    }
+}
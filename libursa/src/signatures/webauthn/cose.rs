@@ -0,0 +1,212 @@
+//! COSE_Key parsing and signature verification for the algorithms authenticators use.
+
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+
+use super::cbor_get;
+use keys::PublicKey;
+use signatures::der::{ecdsa_der_to_raw, P256_FIELD_SIZE};
+use signatures::ecdsa_p256::{p256_point, verify_es256_digest};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// ECDSA with P-256 and SHA-256
+pub const ALG_ES256: i128 = -7;
+/// EdDSA, which authenticators only use with Ed25519
+pub const ALG_EDDSA: i128 = -8;
+
+const KEY_KTY: i128 = 1;
+const KEY_ALG: i128 = 3;
+const KEY_CRV: i128 = -1;
+const KEY_X: i128 = -2;
+const KEY_Y: i128 = -3;
+
+const KTY_OKP: i128 = 1;
+const KTY_EC2: i128 = 2;
+const CRV_P256: i128 = 1;
+const CRV_ED25519: i128 = 6;
+
+/// A credential public key
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoseKey {
+    /// A P-256 key as its 65 byte uncompressed point
+    Es256(Vec<u8>),
+    /// An Ed25519 key
+    EdDsa(Vec<u8>),
+}
+
+impl CoseKey {
+    /// Parse a CBOR encoded COSE_Key
+    pub fn from_cbor(data: &[u8]) -> Result<Self, CryptoError> {
+        let value: Value = serde_cbor::from_slice(data)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid COSE key: {}", e)))?;
+        CoseKey::from_value(&value)
+    }
+
+    pub(crate) fn from_value(value: &Value) -> Result<Self, CryptoError> {
+        let int = |label: i128| match cbor_get(value, &Value::Integer(label)) {
+            Some(Value::Integer(i)) => Ok(*i),
+            _ => Err(CryptoError::ParseError(format!(
+                "COSE key has no integer {}",
+                label
+            ))),
+        };
+        let coordinate = |label: i128| match cbor_get(value, &Value::Integer(label)) {
+            Some(Value::Bytes(b)) if b.len() == P256_FIELD_SIZE => Ok(b.clone()),
+            _ => Err(CryptoError::ParseError(format!(
+                "COSE key has no 32 byte coordinate {}",
+                label
+            ))),
+        };
+        match (int(KEY_KTY)?, int(KEY_ALG)?, int(KEY_CRV)?) {
+            (KTY_EC2, ALG_ES256, CRV_P256) => {
+                let mut point = vec![4u8];
+                point.extend_from_slice(&coordinate(KEY_X)?);
+                point.extend_from_slice(&coordinate(KEY_Y)?);
+                if p256_point(&point).is_none() {
+                    return Err(CryptoError::ParseError(
+                        "COSE key is not a P-256 point".to_string(),
+                    ));
+                }
+                Ok(CoseKey::Es256(point))
+            }
+            (KTY_OKP, ALG_EDDSA, CRV_ED25519) => Ok(CoseKey::EdDsa(coordinate(KEY_X)?)),
+            (kty, alg, crv) => Err(CryptoError::NoSuchAlgorithm(format!(
+                "Unsupported COSE key type {} algorithm {} curve {}",
+                kty, alg, crv
+            ))),
+        }
+    }
+
+    /// The COSE algorithm identifier of the key
+    pub fn alg(&self) -> i128 {
+        match self {
+            CoseKey::Es256(_) => ALG_ES256,
+            CoseKey::EdDsa(_) => ALG_EDDSA,
+        }
+    }
+
+    /// The key in the form the rest of ursa uses
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            CoseKey::Es256(point) | CoseKey::EdDsa(point) => PublicKey(point.clone()),
+        }
+    }
+
+    /// Check `signature` on `message`. ES256 signatures are DER encoded as
+    /// authenticators produce them.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool, CryptoError> {
+        match self {
+            CoseKey::Es256(point) => {
+                let signature = ecdsa_der_to_raw(signature, P256_FIELD_SIZE)?;
+                Ok(verify_es256_digest(
+                    point,
+                    Sha256::digest(message).as_slice(),
+                    &signature,
+                ))
+            }
+            CoseKey::EdDsa(key) => Ok(Ed25519Sha512::new()
+                .verify(message, signature, &PublicKey(key.clone()))
+                .unwrap_or(false)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    use p256::{ProjectivePoint, Scalar};
+    use signatures::ecdsa_p256::{field_bytes, p256_scalar};
+    use std::collections::BTreeMap;
+
+    // The first P-256 SHA-256 vector from the FIPS 186-4 SigGen test vectors
+    const D: &str = "519b423d715f8b581f4fa8ee59f4771a5b44c8130b4e3eacca54a56dda72b464";
+    const Q_X: &str = "1ccbe91c075fc7f4f033bfa248db8fccd3565de94bbfb12f3c59ff46c271bf83";
+    const Q_Y: &str = "ce4014c68811f9a21a1fdb2c0e6113e06db7ca93b7404e78dc7ccd5ca89a4ca9";
+    const DIGEST: &str = "44acf6b7e36c1342c2c5897204fe09504e1e2efb1a900377dbc4e7a6a133ec56";
+    const R: &str = "f3ac8061b514795b8843e3d6629527ed2afd6b1f6a555a7acabb5e6f79c8c2ac";
+    const S: &str = "8bf77819ca05a6b2786c76262bf7371cef97b218e96f175a3ccdda2acc058903";
+
+    pub fn es256_cose_key() -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(KEY_KTY), Value::Integer(KTY_EC2));
+        map.insert(Value::Integer(KEY_ALG), Value::Integer(ALG_ES256));
+        map.insert(Value::Integer(KEY_CRV), Value::Integer(CRV_P256));
+        map.insert(
+            Value::Integer(KEY_X),
+            Value::Bytes(hex::decode(Q_X).unwrap()),
+        );
+        map.insert(
+            Value::Integer(KEY_Y),
+            Value::Bytes(hex::decode(Q_Y).unwrap()),
+        );
+        Value::Map(map)
+    }
+
+    pub fn eddsa_cose_key(public_key: &PublicKey) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(KEY_KTY), Value::Integer(KTY_OKP));
+        map.insert(Value::Integer(KEY_ALG), Value::Integer(ALG_EDDSA));
+        map.insert(Value::Integer(KEY_CRV), Value::Integer(CRV_ED25519));
+        map.insert(Value::Integer(KEY_X), Value::Bytes(public_key[..].to_vec()));
+        Value::Map(map)
+    }
+
+    /// Sign the way an authenticator would: hash, sign with a fixed nonce and DER encode
+    pub fn es256_sign(message: &[u8]) -> Vec<u8> {
+        let d = p256_scalar(&hex::decode(D).unwrap()).unwrap();
+        let k = Scalar::from(0x1234_5678u64);
+        let e = Scalar::from_bytes_reduced(&field_bytes(&Sha256::digest(message)));
+        let r = Scalar::from_bytes_reduced(
+            (ProjectivePoint::generator() * k)
+                .to_affine()
+                .to_encoded_point(false)
+                .x(),
+        );
+        let s = k.invert().unwrap() * (e + r * d);
+        let mut der = vec![0x30, 0];
+        for n in [r, s].iter() {
+            let bytes = n.to_bytes();
+            der.extend_from_slice(&[0x02, 33, 0]);
+            der.extend_from_slice(&bytes);
+        }
+        der[1] = (der.len() - 2) as u8;
+        der
+    }
+
+    #[test]
+    fn es256_test_vector() {
+        let key = CoseKey::from_value(&es256_cose_key()).unwrap();
+        let point = key.public_key();
+        let mut signature = hex::decode(R).unwrap();
+        signature.extend_from_slice(&hex::decode(S).unwrap());
+        let digest = hex::decode(DIGEST).unwrap();
+        assert!(verify_es256_digest(&point[..], &digest, &signature));
+
+        signature[63] ^= 1;
+        assert!(!verify_es256_digest(&point[..], &digest, &signature));
+    }
+
+    #[test]
+    fn cose_keys_verify() {
+        let key = CoseKey::from_value(&es256_cose_key()).unwrap();
+        assert_eq!(key.alg(), ALG_ES256);
+        assert!(key.verify(b"message", &es256_sign(b"message")).unwrap());
+        assert!(!key.verify(b"other", &es256_sign(b"message")).unwrap());
+
+        let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let key = CoseKey::from_cbor(&serde_cbor::to_vec(&eddsa_cose_key(&pk)).unwrap()).unwrap();
+        let signature = Ed25519Sha512::new().sign(b"message", &sk).unwrap();
+        assert!(key.verify(b"message", &signature).unwrap());
+        assert!(!key.verify(b"other", &signature).unwrap());
+
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(KEY_KTY), Value::Integer(KTY_EC2));
+        map.insert(Value::Integer(KEY_ALG), Value::Integer(-47));
+        map.insert(Value::Integer(KEY_CRV), Value::Integer(8));
+        assert!(CoseKey::from_value(&Value::Map(map)).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! WebAuthn verification for relying party servers, the part of a passkey login
+//! that runs on the server.
+//!
+//! `RelyingParty::verify_registration` checks the attestation object a browser
+//! returns from `navigator.credentials.create` and gives back the `Credential` to
+//! store for the user. `RelyingParty::verify_assertion` checks what
+//! `navigator.credentials.get` returns against that credential.
+//!
+//! The `none` and `packed` attestation formats are supported, with ES256 and EdDSA
+//! credential keys. For packed attestation with a certificate chain only the
+//! statement signature is checked here. The certificates are returned so the caller
+//! can validate the chain against the roots it trusts.
+
+pub mod cose;
+
+pub use self::cose::CoseKey;
+
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+
+use self::cose::ALG_ES256;
+use signatures::der::{certificate_spki, p256_spki_to_point};
+use CryptoError;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+const FLAG_EXTENSIONS: u8 = 0x80;
+
+/// The rpIdHash, flags and signature counter
+const AUTHENTICATOR_DATA_SIZE: usize = 37;
+const AAGUID_SIZE: usize = 16;
+
+/// The relying party credentials are registered with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelyingParty {
+    /// The RP ID, usually the domain name of the site
+    pub id: String,
+    /// The origin the browser must report, for example `"https://example.com"`
+    pub origin: String,
+    /// Reject authenticators that didn't verify the user with a PIN or biometric
+    pub require_user_verification: bool,
+}
+
+/// A registered credential
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credential {
+    pub id: Vec<u8>,
+    pub public_key: CoseKey,
+    /// The last signature counter seen from the authenticator
+    pub sign_count: u32,
+    /// Identifies the authenticator model
+    pub aaguid: [u8; AAGUID_SIZE],
+}
+
+/// How the authenticator vouched for a new credential
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttestationType {
+    /// No attestation was given
+    None,
+    /// The statement is signed with the credential key itself
+    SelfAttestation,
+    /// The statement is signed by the first of these DER encoded certificates, which
+    /// still have to be validated by the caller
+    Basic { certificates: Vec<Vec<u8>> },
+}
+
+/// The fields of the client data the browser builds
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(default, rename = "crossOrigin")]
+    cross_origin: bool,
+}
+
+impl RelyingParty {
+    pub fn new(id: &str, origin: &str) -> Self {
+        RelyingParty {
+            id: id.to_string(),
+            origin: origin.to_string(),
+            require_user_verification: false,
+        }
+    }
+
+    /// Check a new credential created for `challenge`
+    pub fn verify_registration(
+        &self,
+        attestation_object: &[u8],
+        client_data_json: &[u8],
+        challenge: &[u8],
+    ) -> Result<(Credential, AttestationType), CryptoError> {
+        self.check_client_data(client_data_json, "webauthn.create", challenge)?;
+        let object: Value = serde_cbor::from_slice(attestation_object)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid attestation object: {}", e)))?;
+        let format = match cbor_get(&object, &text("fmt")) {
+            Some(Value::Text(format)) => format,
+            _ => return Err(parse_error("Attestation object has no format")),
+        };
+        let statement = match cbor_get(&object, &text("attStmt")) {
+            Some(statement @ Value::Map(_)) => statement,
+            _ => return Err(parse_error("Attestation object has no statement")),
+        };
+        let authenticator_data = match cbor_get(&object, &text("authData")) {
+            Some(Value::Bytes(data)) => data,
+            _ => return Err(parse_error("Attestation object has no authenticator data")),
+        };
+
+        let (flags, sign_count) = self.check_authenticator_data(authenticator_data)?;
+        if flags & FLAG_ATTESTED_CREDENTIAL == 0 {
+            return Err(parse_error("Authenticator data has no credential"));
+        }
+        let credential = parse_attested_credential(
+            &authenticator_data[AUTHENTICATOR_DATA_SIZE..],
+            flags,
+            sign_count,
+        )?;
+
+        let attestation = match format.as_str() {
+            "none" => {
+                if statement != &Value::Map(Default::default()) {
+                    return Err(parse_error("None attestation has a statement"));
+                }
+                AttestationType::None
+            }
+            "packed" => {
+                let mut signed = authenticator_data.clone();
+                signed.extend_from_slice(Sha256::digest(client_data_json).as_slice());
+                verify_packed(statement, &signed, &credential)?
+            }
+            f => {
+                return Err(CryptoError::NoSuchAlgorithm(format!(
+                    "Unsupported attestation format {}",
+                    f
+                )))
+            }
+        };
+        Ok((credential, attestation))
+    }
+
+    /// Check an assertion made with `credential` for `challenge` and return the new
+    /// signature counter, which should be stored in place of the old one
+    pub fn verify_assertion(
+        &self,
+        credential: &Credential,
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        signature: &[u8],
+        challenge: &[u8],
+    ) -> Result<u32, CryptoError> {
+        self.check_client_data(client_data_json, "webauthn.get", challenge)?;
+        let (_, sign_count) = self.check_authenticator_data(authenticator_data)?;
+
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(Sha256::digest(client_data_json).as_slice());
+        if !credential.public_key.verify(&signed, signature)? {
+            return Err(CryptoError::GeneralError(
+                "Invalid assertion signature".to_string(),
+            ));
+        }
+        // Authenticators without a counter always report zero
+        if (sign_count != 0 || credential.sign_count != 0) && sign_count <= credential.sign_count {
+            return Err(CryptoError::GeneralError(
+                "Signature counter didn't increase, the authenticator may have been cloned"
+                    .to_string(),
+            ));
+        }
+        Ok(sign_count)
+    }
+
+    fn check_client_data(
+        &self,
+        client_data_json: &[u8],
+        kind: &str,
+        challenge: &[u8],
+    ) -> Result<(), CryptoError> {
+        let client_data: ClientData = serde_json::from_slice(client_data_json)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid client data: {}", e)))?;
+        if client_data.kind != kind {
+            return Err(CryptoError::GeneralError(format!(
+                "Client data is for {}, expected {}",
+                client_data.kind, kind
+            )));
+        }
+        let received = base64::decode_config(&client_data.challenge, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid challenge: {}", e)))?;
+        if received != challenge {
+            return Err(CryptoError::GeneralError(
+                "Client data is for another challenge".to_string(),
+            ));
+        }
+        if client_data.origin != self.origin || client_data.cross_origin {
+            return Err(CryptoError::GeneralError(format!(
+                "Client data is from origin {}",
+                client_data.origin
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the fixed part of authenticator data and return its flags and counter
+    fn check_authenticator_data(&self, data: &[u8]) -> Result<(u8, u32), CryptoError> {
+        if data.len() < AUTHENTICATOR_DATA_SIZE {
+            return Err(parse_error("Authenticator data is truncated"));
+        }
+        if data[..32] != Sha256::digest(self.id.as_bytes())[..] {
+            return Err(CryptoError::GeneralError(
+                "Authenticator data is for another relying party".to_string(),
+            ));
+        }
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err(CryptoError::GeneralError("User wasn't present".to_string()));
+        }
+        if self.require_user_verification && flags & FLAG_USER_VERIFIED == 0 {
+            return Err(CryptoError::GeneralError(
+                "User wasn't verified".to_string(),
+            ));
+        }
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+        Ok((flags, sign_count))
+    }
+}
+
+/// Parse the attested credential data that follows the fixed part of authenticator data
+fn parse_attested_credential(
+    data: &[u8],
+    flags: u8,
+    sign_count: u32,
+) -> Result<Credential, CryptoError> {
+    if data.len() < AAGUID_SIZE + 2 {
+        return Err(parse_error("Attested credential data is truncated"));
+    }
+    let mut aaguid = [0u8; AAGUID_SIZE];
+    aaguid.copy_from_slice(&data[..AAGUID_SIZE]);
+    let id_length = u16::from_be_bytes([data[AAGUID_SIZE], data[AAGUID_SIZE + 1]]) as usize;
+    let data = &data[AAGUID_SIZE + 2..];
+    if data.len() < id_length {
+        return Err(parse_error("Attested credential data is truncated"));
+    }
+    let (id, data) = data.split_at(id_length);
+
+    // The key is followed by the extensions, so read exactly one CBOR item
+    let mut deserializer = serde_cbor::Deserializer::from_slice(data);
+    let key = Value::deserialize(&mut deserializer)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid COSE key: {}", e)))?;
+    if deserializer.byte_offset() != data.len() && flags & FLAG_EXTENSIONS == 0 {
+        return Err(parse_error("Trailing bytes after credential"));
+    }
+    Ok(Credential {
+        id: id.to_vec(),
+        public_key: CoseKey::from_value(&key)?,
+        sign_count,
+        aaguid,
+    })
+}
+
+fn verify_packed(
+    statement: &Value,
+    signed: &[u8],
+    credential: &Credential,
+) -> Result<AttestationType, CryptoError> {
+    let alg = match cbor_get(statement, &text("alg")) {
+        Some(Value::Integer(alg)) => *alg,
+        _ => return Err(parse_error("Packed attestation has no algorithm")),
+    };
+    let signature = match cbor_get(statement, &text("sig")) {
+        Some(Value::Bytes(signature)) => signature,
+        _ => return Err(parse_error("Packed attestation has no signature")),
+    };
+    let (key, attestation) = match cbor_get(statement, &text("x5c")) {
+        Some(Value::Array(chain)) => {
+            let certificates = chain
+                .iter()
+                .map(|c| match c {
+                    Value::Bytes(c) => Ok(c.clone()),
+                    _ => Err(parse_error("Attestation certificate is not a byte string")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let leaf = certificates
+                .first()
+                .ok_or_else(|| parse_error("Attestation certificate chain is empty"))?;
+            if alg != ALG_ES256 {
+                return Err(CryptoError::NoSuchAlgorithm(format!(
+                    "Unsupported attestation algorithm {}",
+                    alg
+                )));
+            }
+            let key = CoseKey::Es256(p256_spki_to_point(certificate_spki(leaf)?)?);
+            (key, AttestationType::Basic { certificates })
+        }
+        None => {
+            if alg != credential.public_key.alg() {
+                return Err(CryptoError::GeneralError(
+                    "Self attestation algorithm doesn't match the credential".to_string(),
+                ));
+            }
+            (
+                credential.public_key.clone(),
+                AttestationType::SelfAttestation,
+            )
+        }
+        Some(_) => return Err(parse_error("Invalid attestation certificate chain")),
+    };
+    if !key.verify(signed, signature)? {
+        return Err(CryptoError::GeneralError(
+            "Invalid attestation signature".to_string(),
+        ));
+    }
+    Ok(attestation)
+}
+
+/// Look up `key` if `map` is a CBOR map
+fn cbor_get<'a>(map: &'a Value, key: &Value) -> Option<&'a Value> {
+    match map {
+        Value::Map(map) => map.get(key),
+        _ => None,
+    }
+}
+
+fn text(s: &str) -> Value {
+    Value::Text(s.to_string())
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::cose::test::{eddsa_cose_key, es256_cose_key, es256_sign};
+    use super::*;
+    use signatures::der::test::{certificate_for, spki_for_point};
+    use signatures::ed25519::Ed25519Sha512;
+    use signatures::SignatureScheme;
+    use std::collections::BTreeMap;
+
+    const ORIGIN: &str = "https://example.com";
+    const CHALLENGE: &[u8] = b"a challenge from the server";
+
+    fn relying_party() -> RelyingParty {
+        RelyingParty::new("example.com", ORIGIN)
+    }
+
+    fn client_data(kind: &str, challenge: &[u8]) -> Vec<u8> {
+        format!(
+            r#"{{"type":"{}","challenge":"{}","origin":"{}","crossOrigin":false}}"#,
+            kind,
+            base64::encode_config(challenge, base64::URL_SAFE_NO_PAD),
+            ORIGIN
+        )
+        .into_bytes()
+    }
+
+    fn authenticator_data(flags: u8, sign_count: u32, key: Option<&Value>) -> Vec<u8> {
+        let mut data = Sha256::digest(b"example.com").to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        if let Some(key) = key {
+            data.extend_from_slice(&[7u8; AAGUID_SIZE]);
+            data.extend_from_slice(&[0, 4, 1, 2, 3, 4]);
+            data.extend_from_slice(&serde_cbor::to_vec(key).unwrap());
+        }
+        data
+    }
+
+    fn attestation_object(format: &str, statement: Vec<(&str, Value)>, data: Vec<u8>) -> Vec<u8> {
+        let statement = statement.into_iter().map(|(k, v)| (text(k), v)).collect();
+        let mut object = BTreeMap::new();
+        object.insert(text("fmt"), text(format));
+        object.insert(text("attStmt"), Value::Map(statement));
+        object.insert(text("authData"), Value::Bytes(data));
+        serde_cbor::to_vec(&Value::Map(object)).unwrap()
+    }
+
+    fn signed_data(data: &[u8], client_data: &[u8]) -> Vec<u8> {
+        let mut signed = data.to_vec();
+        signed.extend_from_slice(Sha256::digest(client_data).as_slice());
+        signed
+    }
+
+    #[test]
+    fn eddsa_registration_and_assertion() {
+        let rp = relying_party();
+        let scheme = Ed25519Sha512::new();
+        let (pk, sk) = scheme.keypair(None).unwrap();
+        let data = authenticator_data(0x41, 0, Some(&eddsa_cose_key(&pk)));
+        let create = client_data("webauthn.create", CHALLENGE);
+
+        let (credential, attestation) = rp
+            .verify_registration(
+                &attestation_object("none", Vec::new(), data.clone()),
+                &create,
+                CHALLENGE,
+            )
+            .unwrap();
+        assert_eq!(attestation, AttestationType::None);
+        assert_eq!(credential.id, vec![1, 2, 3, 4]);
+        assert_eq!(credential.aaguid, [7u8; AAGUID_SIZE]);
+        assert_eq!(credential.public_key, CoseKey::EdDsa(pk[..].to_vec()));
+        assert!(rp
+            .verify_registration(
+                &attestation_object("none", Vec::new(), data.clone()),
+                &create,
+                b"another challenge",
+            )
+            .is_err());
+        assert!(rp
+            .verify_registration(
+                &attestation_object("fido-u2f", Vec::new(), data),
+                &create,
+                CHALLENGE,
+            )
+            .is_err());
+
+        let get = client_data("webauthn.get", CHALLENGE);
+        let data = authenticator_data(0x01, 5, None);
+        let signature = scheme.sign(&signed_data(&data, &get), &sk).unwrap();
+        assert_eq!(
+            rp.verify_assertion(&credential, &data, &get, &signature, CHALLENGE)
+                .unwrap(),
+            5
+        );
+        assert!(rp
+            .verify_assertion(&credential, &data, &create, &signature, CHALLENGE)
+            .is_err());
+
+        let used = Credential {
+            sign_count: 5,
+            ..credential.clone()
+        };
+        assert!(rp
+            .verify_assertion(&used, &data, &get, &signature, CHALLENGE)
+            .is_err());
+
+        let strict = RelyingParty {
+            require_user_verification: true,
+            ..relying_party()
+        };
+        assert!(strict
+            .verify_assertion(&credential, &data, &get, &signature, CHALLENGE)
+            .is_err());
+        let other = RelyingParty::new("example.org", ORIGIN);
+        assert!(other
+            .verify_assertion(&credential, &data, &get, &signature, CHALLENGE)
+            .is_err());
+    }
+
+    #[test]
+    fn packed_attestation() {
+        let rp = relying_party();
+        let create = client_data("webauthn.create", CHALLENGE);
+        let data = authenticator_data(0x45, 1, Some(&es256_cose_key()));
+        let signature = Value::Bytes(es256_sign(&signed_data(&data, &create)));
+
+        let statement = vec![
+            ("alg", Value::Integer(ALG_ES256)),
+            ("sig", signature.clone()),
+        ];
+        let (credential, attestation) = rp
+            .verify_registration(
+                &attestation_object("packed", statement, data.clone()),
+                &create,
+                CHALLENGE,
+            )
+            .unwrap();
+        assert_eq!(attestation, AttestationType::SelfAttestation);
+        assert_eq!(credential.sign_count, 1);
+
+        let certificate = certificate_for(&spki_for_point(&credential.public_key.public_key()[..]));
+        let statement = vec![
+            ("alg", Value::Integer(ALG_ES256)),
+            ("sig", signature),
+            ("x5c", Value::Array(vec![Value::Bytes(certificate.clone())])),
+        ];
+        let (_, attestation) = rp
+            .verify_registration(
+                &attestation_object("packed", statement, data.clone()),
+                &create,
+                CHALLENGE,
+            )
+            .unwrap();
+        assert_eq!(
+            attestation,
+            AttestationType::Basic {
+                certificates: vec![certificate]
+            }
+        );
+
+        let statement = vec![
+            ("alg", Value::Integer(ALG_ES256)),
+            ("sig", Value::Bytes(es256_sign(b"something else"))),
+        ];
+        assert!(rp
+            .verify_registration(
+                &attestation_object("packed", statement, data),
+                &create,
+                CHALLENGE,
+            )
+            .is_err());
+
+        let get = client_data("webauthn.get", CHALLENGE);
+        let data = authenticator_data(0x05, 2, None);
+        let signature = es256_sign(&signed_data(&data, &get));
+        assert_eq!(
+            rp.verify_assertion(&credential, &data, &get, &signature, CHALLENGE)
+                .unwrap(),
+            2
+        );
+    }
+}
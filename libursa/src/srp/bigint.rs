@@ -0,0 +1,333 @@
+//! Constant-time arithmetic modulo an odd public modulus.
+//!
+//! Numbers are little-endian `u64` limbs, as many as the modulus has, and are kept in
+//! Montgomery form between operations. No operation branches on or indexes memory by
+//! a secret value: reductions are conditional moves and exponentiation processes
+//! every bit of the exponent with a fixed window, reading the whole table for each
+//! window. Only the lengths of the inputs, which the protocol fixes, affect timing.
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// A number modulo `Modulus`, in Montgomery form
+#[derive(Clone, Debug)]
+pub struct Residue(Vec<u64>);
+
+impl Drop for Residue {
+    fn drop(&mut self) {
+        for limb in self.0.iter_mut() {
+            *limb = 0;
+        }
+    }
+}
+
+/// An odd modulus with its Montgomery constants
+#[derive(Clone, Debug)]
+pub struct Modulus {
+    n: Vec<u64>,
+    n0: u64,
+    r2: Vec<u64>,
+    one: Vec<u64>,
+    bytes: usize,
+}
+
+impl Modulus {
+    /// The modulus with these big endian bytes, which must be odd and above one
+    pub fn new(modulus: &[u8]) -> Self {
+        let n = limbs(modulus, (modulus.len() + 7) >> 3);
+        assert!(n[0] & 1 == 1, "the modulus must be odd");
+
+        // -n^-1 mod 2^64 by Newton's iteration
+        let mut inverse = 1u64;
+        for _ in 0..6 {
+            inverse = inverse.wrapping_mul(2u64.wrapping_sub(n[0].wrapping_mul(inverse)));
+        }
+
+        // R mod n and R^2 mod n by doubling, where R is 2^64 per limb
+        let mut modulus = Modulus {
+            n0: inverse.wrapping_neg(),
+            r2: Vec::new(),
+            one: Vec::new(),
+            bytes: modulus.len(),
+            n,
+        };
+        let mut x = vec![0u64; modulus.n.len()];
+        x[0] = 1;
+        for i in 0..128 * modulus.n.len() {
+            if i == 64 * modulus.n.len() {
+                modulus.one = x.clone();
+            }
+            x = modulus.add_limbs(&x, &x);
+        }
+        modulus.r2 = x;
+        modulus
+    }
+
+    /// The length of the modulus in bytes
+    pub fn byte_len(&self) -> usize {
+        self.bytes
+    }
+
+    /// Reduce big endian `value`, which may be up to `8 * limbs` bytes long
+    pub fn residue(&self, value: &[u8]) -> Residue {
+        Residue(self.mul_limbs(&limbs(value, self.n.len()), &self.r2))
+    }
+
+    /// The value as big endian bytes of the length of the modulus
+    pub fn to_bytes(&self, x: &Residue) -> Vec<u8> {
+        let mut one = vec![0u64; self.n.len()];
+        one[0] = 1;
+        let mut value = self.mul_limbs(&x.0, &one);
+        let bytes = to_bytes(&value, self.bytes);
+        value.iter_mut().for_each(|l| *l = 0);
+        bytes
+    }
+
+    pub fn one(&self) -> Residue {
+        Residue(self.one.clone())
+    }
+
+    pub fn is_zero(&self, x: &Residue) -> Choice {
+        x.0.iter().fold(Choice::from(1), |acc, l| acc & l.ct_eq(&0))
+    }
+
+    pub fn add(&self, a: &Residue, b: &Residue) -> Residue {
+        Residue(self.add_limbs(&a.0, &b.0))
+    }
+
+    pub fn sub(&self, a: &Residue, b: &Residue) -> Residue {
+        let (difference, borrow) = sub_limbs(&a.0, &b.0);
+        let (wrapped, _) = add_limbs(&difference, &self.n);
+        Residue(select(&difference, &wrapped, Choice::from(borrow as u8)))
+    }
+
+    pub fn mul(&self, a: &Residue, b: &Residue) -> Residue {
+        Residue(self.mul_limbs(&a.0, &b.0))
+    }
+
+    /// `base` to the power of big endian `exponent`
+    pub fn pow(&self, base: &Residue, exponent: &[u8]) -> Residue {
+        let mut table = vec![self.one()];
+        for i in 1..16 {
+            let next = self.mul(&table[i - 1], base);
+            table.push(next);
+        }
+        let mut result = self.one();
+        for byte in exponent {
+            for nibble in &[byte >> 4, byte & 0x0f] {
+                for _ in 0..4 {
+                    result = self.mul(&result, &result);
+                }
+                let mut entry = vec![0u64; self.n.len()];
+                for (i, t) in table.iter().enumerate() {
+                    let hit = (i as u8).ct_eq(nibble);
+                    for (e, l) in entry.iter_mut().zip(t.0.iter()) {
+                        e.conditional_assign(l, hit);
+                    }
+                }
+                result = self.mul(&result, &Residue(entry));
+            }
+        }
+        result
+    }
+
+    fn add_limbs(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let (sum, carry) = add_limbs(a, b);
+        let (reduced, borrow) = sub_limbs(&sum, &self.n);
+        select(&sum, &reduced, Choice::from((carry | (borrow ^ 1)) as u8))
+    }
+
+    /// Montgomery multiplication, `a * b / R mod n`
+    fn mul_limbs(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let len = self.n.len();
+        let mut t = vec![0u64; len + 2];
+        for &bi in b {
+            let mut carry = 0u64;
+            for j in 0..len {
+                let (v, c) = mac(t[j], a[j], bi, carry);
+                t[j] = v;
+                carry = c;
+            }
+            let (v, c) = adc(t[len], carry, 0);
+            t[len] = v;
+            t[len + 1] = c;
+
+            let m = t[0].wrapping_mul(self.n0);
+            let (_, mut carry) = mac(t[0], m, self.n[0], 0);
+            for j in 1..len {
+                let (v, c) = mac(t[j], m, self.n[j], carry);
+                t[j - 1] = v;
+                carry = c;
+            }
+            let (v, c) = adc(t[len], carry, 0);
+            t[len - 1] = v;
+            t[len] = t[len + 1] + c;
+        }
+        let (reduced, borrow) = sub_limbs(&t[..len], &self.n);
+        let result = select(
+            &t[..len],
+            &reduced,
+            Choice::from((t[len] as u8) | (borrow as u8 ^ 1)),
+        );
+        t.iter_mut().for_each(|l| *l = 0);
+        result
+    }
+}
+
+/// `a * b + c` of big endian numbers, as big endian bytes of the combined length
+pub fn mul_add(a: &[u8], b: &[u8], c: &[u8]) -> Vec<u8> {
+    let len = (a.len() + b.len() + 8) / 8 + 1;
+    let (a, b) = (limbs(a, len), limbs(b, len));
+    let mut result = limbs(c, 2 * len);
+    for (i, &bi) in b.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &aj) in a.iter().enumerate() {
+            let (v, c) = mac(result[i + j], aj, bi, carry);
+            result[i + j] = v;
+            carry = c;
+        }
+        for r in result[i + len..].iter_mut() {
+            let (v, c) = adc(*r, carry, 0);
+            *r = v;
+            carry = c;
+        }
+    }
+    let bytes = to_bytes(&result, 16 * len);
+    result.iter_mut().for_each(|l| *l = 0);
+    bytes
+}
+
+fn limbs(bytes: &[u8], len: usize) -> Vec<u64> {
+    assert!(bytes.len() <= 8 * len, "value is longer than the modulus");
+    let mut output = vec![0u64; len];
+    for (i, byte) in bytes.iter().rev().enumerate() {
+        output[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    output
+}
+
+fn to_bytes(limbs: &[u64], len: usize) -> Vec<u8> {
+    let mut output = vec![0u8; len];
+    for (i, byte) in output.iter_mut().rev().enumerate() {
+        if i / 8 < limbs.len() {
+            *byte = (limbs[i / 8] >> (8 * (i % 8))) as u8;
+        }
+    }
+    output
+}
+
+fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (t as u64, (t >> 127) as u64)
+}
+
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+fn add_limbs(a: &[u64], b: &[u64]) -> (Vec<u64>, u64) {
+    let mut carry = 0;
+    let sum = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| {
+            let (v, c) = adc(*x, *y, carry);
+            carry = c;
+            v
+        })
+        .collect();
+    (sum, carry)
+}
+
+fn sub_limbs(a: &[u64], b: &[u64]) -> (Vec<u64>, u64) {
+    let mut borrow = 0;
+    let difference = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| {
+            let (v, c) = sbb(*x, *y, borrow);
+            borrow = c;
+            v
+        })
+        .collect();
+    (difference, borrow)
+}
+
+/// `b` where `choice` is set, otherwise `a`
+fn select(a: &[u64], b: &[u64], choice: Choice) -> Vec<u64> {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| u64::conditional_select(x, y, choice))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn small_modulus() {
+        // 2^127 - 1 spans two limbs
+        let p = (1u128 << 127) - 1;
+        let modulus = Modulus::new(&p.to_be_bytes());
+        let value = |x: u128| modulus.residue(&x.to_be_bytes());
+        let read = |x: &Residue| {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&modulus.to_bytes(x));
+            u128::from_be_bytes(bytes)
+        };
+
+        let (a, b) = (p - 5, 123_456_789_012_345_678_901u128);
+        assert_eq!(read(&value(a)), a);
+        assert_eq!(read(&value(p + 3)), 3);
+        assert_eq!(read(&modulus.add(&value(a), &value(b))), b - 5);
+        assert_eq!(read(&modulus.sub(&value(b), &value(a))), b + 5);
+        assert_eq!(read(&modulus.sub(&value(a), &value(a))), 0);
+        assert!(bool::from(modulus.is_zero(&value(p))));
+        assert_eq!(read(&modulus.mul(&value(a), &value(a))), 25);
+        assert_eq!(read(&modulus.pow(&value(3), &[1, 0])), pow(3, 256, p));
+        assert_eq!(read(&modulus.pow(&value(b), &[])), 1);
+
+        // Fermat's little theorem
+        let exponent = (p - 1).to_be_bytes();
+        assert_eq!(read(&modulus.pow(&value(b), &exponent)), 1);
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(mul_add(&[3], &[5], &[7]), {
+            let mut v = vec![0u8; 32];
+            v[31] = 22;
+            v
+        });
+        let max = [0xffu8; 20];
+        let product = mul_add(&max, &max, &max);
+        // (2^160 - 1)^2 + 2^160 - 1 = 2^320 - 2^160
+        let mut expected = vec![0u8; product.len()];
+        let start = expected.len() - 40;
+        expected[start..start + 20].copy_from_slice(&max);
+        assert_eq!(product, expected);
+    }
+
+    fn pow(base: u128, exponent: u32, modulus: u128) -> u128 {
+        (0..exponent).fold(1, |acc, _| {
+            // Shift and add, as the product doesn't fit
+            let mut result = 0u128;
+            let mut x = acc;
+            let mut y = base;
+            while y > 0 {
+                if y & 1 == 1 {
+                    result = (result + x) % modulus;
+                }
+                x = (x << 1) % modulus;
+                y >>= 1;
+            }
+            result
+        })
+    }
+}
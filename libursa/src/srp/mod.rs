@@ -0,0 +1,669 @@
+//! SRP-6a password authenticated key exchange (RFC 5054).
+//!
+//! SRP lets a client prove it knows a password to a server that only stores a
+//! verifier derived from it, and gives both a shared key. The verifier doesn't
+//! reveal the password except to a dictionary attack, and an eavesdropper or a
+//! party impersonating either side learns nothing to test guesses offline with.
+//! This implementation is for systems that have to talk to existing SRP
+//! deployments: new designs should prefer OPAQUE, which doesn't hand the salt to
+//! anyone who asks.
+//!
+//! The flow, with `I` the username and `P` the password, is
+//!
+//! 1. At registration the client picks a salt `s` and sends `I`, `s` and the
+//!    verifier from `compute_verifier` to the server, which stores them.
+//! 2. To log in the client sends `I`. The server looks up `s` and `v`, creates an
+//!    `SrpServer` and returns `s` and its public key `B`.
+//! 3. The client creates an `SrpClient`, processes `s` and `B` with the password,
+//!    and sends its public key `A` and the proof `M1` of the session.
+//! 4. The server checks `M1` with `verify_client` and returns its proof `M2`,
+//!    which the client checks with `verify_server`.
+//!
+//! Either side must abort on any error and use the session key only after the
+//! proofs have been checked. The groups, `k`, `x`, `u`, the public keys and the
+//! premaster secret `S` are those of RFC 5054 and match its test vectors. The
+//! session key is `K = H(S)` and the proofs are
+//! `M1 = H(H(N) xor H(g) | H(I) | s | A | B | K)` and `M2 = H(A | M1 | K)` as in
+//! RFC 2945, with `N`, `g`, `A`, `B` and `S` hashed as big endian numbers without
+//! leading zero bytes, the convention of the common SRP-6a libraries.
+//!
+//! All arithmetic on secrets is constant time, see `bigint`.
+
+mod bigint;
+pub mod groups;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha1_smol::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use self::bigint::{Modulus, Residue};
+use CryptoError;
+
+/// The length of the salts `generate_salt` returns
+pub const SALT_SIZE: usize = 32;
+
+/// The length of the private exponents, as RFC 5054 requires at least 256 bits
+const EXPONENT_SIZE: usize = 32;
+
+/// An SRP group of RFC 5054
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SrpGroup {
+    G1024,
+    G1536,
+    G2048,
+    G3072,
+    G4096,
+    G6144,
+    G8192,
+}
+
+impl SrpGroup {
+    /// The size of the prime in bits
+    pub fn bits(self) -> usize {
+        match self {
+            SrpGroup::G1024 => 1024,
+            SrpGroup::G1536 => 1536,
+            SrpGroup::G2048 => 2048,
+            SrpGroup::G3072 => 3072,
+            SrpGroup::G4096 => 4096,
+            SrpGroup::G6144 => 6144,
+            SrpGroup::G8192 => 8192,
+        }
+    }
+
+    /// The prime `N` as big endian bytes
+    pub fn prime(self) -> Vec<u8> {
+        let hex = match self {
+            SrpGroup::G1024 => groups::N1024,
+            SrpGroup::G1536 => groups::N1536,
+            SrpGroup::G2048 => groups::N2048,
+            SrpGroup::G3072 => groups::N3072,
+            SrpGroup::G4096 => groups::N4096,
+            SrpGroup::G6144 => groups::N6144,
+            SrpGroup::G8192 => groups::N8192,
+        };
+        hex::decode(hex).expect("the groups are valid hex")
+    }
+
+    /// The generator `g`
+    pub fn generator(self) -> u8 {
+        match self {
+            SrpGroup::G1024 | SrpGroup::G1536 | SrpGroup::G2048 => 2,
+            SrpGroup::G3072 | SrpGroup::G4096 | SrpGroup::G6144 => 5,
+            SrpGroup::G8192 => 19,
+        }
+    }
+}
+
+/// The hash function `H`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SrpHash {
+    /// SHA-1, which RFC 5054 specifies. Only for existing deployments that use it
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl SrpHash {
+    pub fn output_size(self) -> usize {
+        match self {
+            SrpHash::Sha1 => 20,
+            SrpHash::Sha256 => 32,
+            SrpHash::Sha512 => 64,
+        }
+    }
+
+    fn hash(self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            SrpHash::Sha1 => {
+                let mut hasher = Sha1::new();
+                parts.iter().for_each(|p| hasher.update(p));
+                hasher.digest().bytes().to_vec()
+            }
+            SrpHash::Sha256 => {
+                let mut hasher = Sha256::new();
+                parts.iter().for_each(|p| hasher.input(p));
+                hasher.result().to_vec()
+            }
+            SrpHash::Sha512 => {
+                let mut hasher = Sha512::new();
+                parts.iter().for_each(|p| hasher.input(p));
+                hasher.result().to_vec()
+            }
+        }
+    }
+}
+
+/// A group and hash both sides have agreed on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SrpParameters {
+    pub group: SrpGroup,
+    pub hash: SrpHash,
+}
+
+impl SrpParameters {
+    pub fn new(group: SrpGroup, hash: SrpHash) -> Self {
+        SrpParameters { group, hash }
+    }
+}
+
+/// The arithmetic of one set of parameters
+struct Context {
+    params: SrpParameters,
+    modulus: Modulus,
+    prime: Vec<u8>,
+    generator: Residue,
+    /// `g` padded to the length of `N`
+    padded_generator: Vec<u8>,
+}
+
+impl Context {
+    fn new(params: SrpParameters) -> Self {
+        let prime = params.group.prime();
+        let modulus = Modulus::new(&prime);
+        let generator = modulus.residue(&[params.group.generator()]);
+        let padded_generator = modulus.to_bytes(&generator);
+        Context {
+            params,
+            modulus,
+            prime,
+            generator,
+            padded_generator,
+        }
+    }
+
+    fn hash(&self, parts: &[&[u8]]) -> Vec<u8> {
+        self.params.hash.hash(parts)
+    }
+
+    /// `k = H(N | PAD(g))`
+    fn multiplier(&self) -> Residue {
+        self.modulus
+            .residue(&self.hash(&[&self.prime, &self.padded_generator]))
+    }
+
+    /// `x = H(s | H(I | ":" | P))`
+    fn private_key(&self, username: &[u8], password: &[u8], salt: &[u8]) -> Vec<u8> {
+        let mut inner = self.hash(&[username, b":", password]);
+        let x = self.hash(&[salt, &inner]);
+        inner.zeroize();
+        x
+    }
+
+    /// `u = H(PAD(A) | PAD(B))`
+    fn scrambler(&self, client: &[u8], server: &[u8]) -> Vec<u8> {
+        self.hash(&[client, server])
+    }
+
+    /// Parse a public key, which must be of the length of `N` at most and not be a
+    /// multiple of it. Returns it padded to the length of `N` with its residue
+    fn public_key(&self, key: &[u8]) -> Result<(Vec<u8>, Residue), CryptoError> {
+        if key.len() > self.modulus.byte_len() {
+            return Err(CryptoError::ParseError(
+                "Public key is longer than the prime".to_string(),
+            ));
+        }
+        let residue = self.modulus.residue(key);
+        if bool::from(self.modulus.is_zero(&residue)) {
+            return Err(CryptoError::GeneralError(
+                "Public key is a multiple of the prime".to_string(),
+            ));
+        }
+        let mut padded = vec![0u8; self.modulus.byte_len() - key.len()];
+        padded.extend_from_slice(key);
+        Ok((padded, residue))
+    }
+
+    /// `K`, `M1` and `M2` from the premaster secret
+    fn proofs(
+        &self,
+        username: &[u8],
+        salt: &[u8],
+        client: &[u8],
+        server: &[u8],
+        premaster: &[u8],
+    ) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let key = self.hash(&[unpadded(premaster)]);
+        let mut group = self.hash(&[unpadded(&self.prime)]);
+        let generator = self.hash(&[unpadded(&self.padded_generator)]);
+        group
+            .iter_mut()
+            .zip(generator.iter())
+            .for_each(|(n, g)| *n ^= g);
+        let client_proof = self.hash(&[
+            &group,
+            &self.hash(&[username]),
+            salt,
+            unpadded(client),
+            unpadded(server),
+            &key,
+        ]);
+        let server_proof = self.hash(&[unpadded(client), &client_proof, &key]);
+        (key, client_proof, server_proof)
+    }
+}
+
+/// Generate a random salt for a new verifier
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// The verifier `v = g^x` the server stores for `username`, padded to the length
+/// of the prime
+pub fn compute_verifier(
+    params: SrpParameters,
+    username: &[u8],
+    password: &[u8],
+    salt: &[u8],
+) -> Vec<u8> {
+    let context = Context::new(params);
+    let mut x = context.private_key(username, password, salt);
+    let verifier = context
+        .modulus
+        .to_bytes(&context.modulus.pow(&context.generator, &x));
+    x.zeroize();
+    verifier
+}
+
+/// The client side of a login
+pub struct SrpClient {
+    context: Context,
+    username: Vec<u8>,
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl SrpClient {
+    /// Start a login as `username` with a fresh private exponent
+    pub fn new(params: SrpParameters, username: &[u8]) -> Self {
+        let mut private_key = vec![0u8; EXPONENT_SIZE];
+        OsRng.fill_bytes(&mut private_key);
+        Self::with_private_key(params, username, private_key)
+    }
+
+    fn with_private_key(params: SrpParameters, username: &[u8], private_key: Vec<u8>) -> Self {
+        let context = Context::new(params);
+        let public_key = context
+            .modulus
+            .to_bytes(&context.modulus.pow(&context.generator, &private_key));
+        SrpClient {
+            context,
+            username: username.to_vec(),
+            private_key,
+            public_key,
+        }
+    }
+
+    /// `A`, padded to the length of the prime
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Derive the session from the server's salt and public key `B`
+    pub fn process_challenge(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+        server_public_key: &[u8],
+    ) -> Result<SrpClientSession, CryptoError> {
+        let context = &self.context;
+        let modulus = &context.modulus;
+        let (server, b) = context.public_key(server_public_key)?;
+        let u = context.scrambler(&self.public_key, &server);
+        if u.iter().all(|b| *b == 0) {
+            return Err(CryptoError::GeneralError(
+                "Public keys give a zero scrambler".to_string(),
+            ));
+        }
+
+        // S = (B - k * g^x)^(a + u * x)
+        let mut x = context.private_key(&self.username, password, salt);
+        let kgx = modulus.mul(&context.multiplier(), &modulus.pow(&context.generator, &x));
+        let mut exponent = bigint::mul_add(&u, &x, &self.private_key);
+        x.zeroize();
+        let mut premaster = modulus.to_bytes(&modulus.pow(&modulus.sub(&b, &kgx), &exponent));
+        exponent.zeroize();
+
+        let (key, proof, server_proof) =
+            context.proofs(&self.username, salt, &self.public_key, &server, &premaster);
+        premaster.zeroize();
+        Ok(SrpClientSession {
+            key,
+            proof,
+            server_proof,
+        })
+    }
+}
+
+impl Drop for SrpClient {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+/// A client's session after processing the challenge
+pub struct SrpClientSession {
+    key: Vec<u8>,
+    proof: Vec<u8>,
+    server_proof: Vec<u8>,
+}
+
+impl SrpClientSession {
+    /// `M1`, to send to the server with `A`
+    pub fn proof(&self) -> &[u8] {
+        &self.proof
+    }
+
+    /// Check the server's proof `M2`. A failure means the server doesn't hold the
+    /// verifier, or the password was wrong and the server would have rejected `M1`
+    pub fn verify_server(&self, server_proof: &[u8]) -> Result<(), CryptoError> {
+        if bool::from(self.server_proof.ct_eq(server_proof)) {
+            Ok(())
+        } else {
+            Err(CryptoError::GeneralError(
+                "Server proof doesn't match".to_string(),
+            ))
+        }
+    }
+
+    /// `K`, to use once the server's proof has been checked
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Drop for SrpClientSession {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// The server side of a login
+pub struct SrpServer {
+    context: Context,
+    username: Vec<u8>,
+    salt: Vec<u8>,
+    verifier: Residue,
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl SrpServer {
+    /// Answer a login as `username` with its stored salt and verifier
+    pub fn new(
+        params: SrpParameters,
+        username: &[u8],
+        salt: &[u8],
+        verifier: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let mut private_key = vec![0u8; EXPONENT_SIZE];
+        OsRng.fill_bytes(&mut private_key);
+        Self::with_private_key(params, username, salt, verifier, private_key)
+    }
+
+    fn with_private_key(
+        params: SrpParameters,
+        username: &[u8],
+        salt: &[u8],
+        verifier: &[u8],
+        private_key: Vec<u8>,
+    ) -> Result<Self, CryptoError> {
+        let context = Context::new(params);
+        let modulus = &context.modulus;
+        let (_, verifier) = context.public_key(verifier)?;
+
+        // B = k * v + g^b
+        let public_key = modulus.to_bytes(&modulus.add(
+            &modulus.mul(&context.multiplier(), &verifier),
+            &modulus.pow(&context.generator, &private_key),
+        ));
+        Ok(SrpServer {
+            context,
+            username: username.to_vec(),
+            salt: salt.to_vec(),
+            verifier,
+            private_key,
+            public_key,
+        })
+    }
+
+    /// `B`, padded to the length of the prime
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Check the client's public key `A` and proof `M1`
+    pub fn verify_client(
+        &self,
+        client_public_key: &[u8],
+        client_proof: &[u8],
+    ) -> Result<SrpServerSession, CryptoError> {
+        let context = &self.context;
+        let modulus = &context.modulus;
+        let (client, a) = context.public_key(client_public_key)?;
+        let u = context.scrambler(&client, &self.public_key);
+        if u.iter().all(|b| *b == 0) {
+            return Err(CryptoError::GeneralError(
+                "Public keys give a zero scrambler".to_string(),
+            ));
+        }
+
+        // S = (A * v^u)^b
+        let base = modulus.mul(&a, &modulus.pow(&self.verifier, &u));
+        let mut premaster = modulus.to_bytes(&modulus.pow(&base, &self.private_key));
+        let (mut key, expected, proof) = context.proofs(
+            &self.username,
+            &self.salt,
+            &client,
+            &self.public_key,
+            &premaster,
+        );
+        premaster.zeroize();
+        if !bool::from(expected.ct_eq(client_proof)) {
+            key.zeroize();
+            return Err(CryptoError::GeneralError(
+                "Client proof doesn't match".to_string(),
+            ));
+        }
+        Ok(SrpServerSession { key, proof })
+    }
+}
+
+impl Drop for SrpServer {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+/// A server's session after checking the client's proof
+pub struct SrpServerSession {
+    key: Vec<u8>,
+    proof: Vec<u8>,
+}
+
+impl SrpServerSession {
+    /// `M2`, to send to the client
+    pub fn proof(&self) -> &[u8] {
+        &self.proof
+    }
+
+    /// `K`
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Drop for SrpServerSession {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+fn unpadded(value: &[u8]) -> &[u8] {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    &value[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PARAMS: SrpParameters = SrpParameters {
+        group: SrpGroup::G1024,
+        hash: SrpHash::Sha1,
+    };
+
+    fn hex(value: &str) -> Vec<u8> {
+        hex::decode(value.replace(' ', "")).unwrap()
+    }
+
+    // RFC 5054 appendix B
+    #[test]
+    fn rfc5054_vector() {
+        let salt = hex("BEB25379 D1A8581E B5A72767 3A2441EE");
+        let context = Context::new(PARAMS);
+        assert_eq!(
+            context.modulus.to_bytes(&context.multiplier())[108..],
+            hex("7556AA04 5AEF2CDD 07ABAF0F 665C3E81 8913186F")[..]
+        );
+        assert_eq!(
+            context.private_key(b"alice", b"password123", &salt),
+            hex("94B7555A ABE9127C C58CCF49 93DB6CF8 4D16C124")
+        );
+        let verifier = compute_verifier(PARAMS, b"alice", b"password123", &salt);
+        assert_eq!(
+            verifier,
+            hex(
+                "7E273DE8 696FFC4F 4E337D05 B4B375BE B0DDE156 9E8FA00A 9886D812 9BADA1F1 \
+                 822223CA 1A605B53 0E379BA4 729FDC59 F105B478 7E5186F5 C671085A 1447B52A \
+                 48CF1970 B4FB6F84 00BBF4CE BFBB1681 52E08AB5 EA53D15C 1AFF87B2 B9DA6E04 \
+                 E058AD51 CC72BFC9 033B564E 26480D78 E955A5E2 9E7AB245 DB2BE315 E2099AFB"
+            )
+        );
+
+        let a = hex("60975527 035CF2AD 1989806F 0407210B C81EDC04 E2762A56 AFD529DD DA2D4393");
+        let b = hex("E487CB59 D31AC550 471E81F0 0F6928E0 1DDA08E9 74A004F4 9E61F5D1 05284D20");
+        let client = SrpClient::with_private_key(PARAMS, b"alice", a);
+        let server = SrpServer::with_private_key(PARAMS, b"alice", &salt, &verifier, b).unwrap();
+        assert_eq!(
+            client.public_key(),
+            &hex(
+                "61D5E490 F6F1B795 47B0704C 436F523D D0E560F0 C64115BB 72557EC4 4352E890 \
+                  3211C046 92272D8B 2D1A5358 A2CF1B6E 0BFCF99F 921530EC 8E393561 79EAE45E \
+                  42BA92AE ACED8251 71E1E8B9 AF6D9C03 E1327F44 BE087EF0 6530E69F 66615261 \
+                  EEF54073 CA11CF58 58F0EDFD FE15EFEA B349EF5D 76988A36 72FAC47B 0769447B"
+            )[..]
+        );
+        assert_eq!(
+            server.public_key(),
+            &hex(
+                "BD0C6151 2C692C0C B6D041FA 01BB152D 4916A1E7 7AF46AE1 05393011 BAF38964 \
+                  DC46A067 0DD125B9 5A981652 236F99D9 B681CBF8 7837EC99 6C6DA044 53728610 \
+                  D0C6DDB5 8B318885 D7D82C7F 8DEB75CE 7BD4FBAA 37089E6F 9C6059F3 88838E7A \
+                  00030B33 1EB76840 910440B1 B27AAEAE EB4012B7 D7665238 A8E3FB00 4B117B58"
+            )[..]
+        );
+        assert_eq!(
+            context.scrambler(client.public_key(), server.public_key()),
+            hex("CE38B959 3487DA98 554ED47D 70A7AE5F 462EF019")
+        );
+
+        let premaster = hex(
+            "B0DC82BA BCF30674 AE450C02 87745E79 90A3381F 63B387AA F271A10D 233861E3 \
+             59B48220 F7C4693C 9AE12B0A 6F67809F 0876E2D0 13800D6C 41BB59B6 D5979B5C \
+             00A172B4 A2A5903A 0BDCAF8A 709585EB 2AFAFA8F 3499B200 210DCC1F 10EB3394 \
+             3CD67FC8 8A2F39A4 BE5BEC4E C0A3212D C346D7E4 74B29EDE 8A469FFE CA686E5A",
+        );
+        let session = client
+            .process_challenge(b"password123", &salt, server.public_key())
+            .unwrap();
+        assert_eq!(session.key(), &PARAMS.hash.hash(&[&premaster])[..]);
+        let server_session = server
+            .verify_client(client.public_key(), session.proof())
+            .unwrap();
+        assert_eq!(server_session.key(), session.key());
+        session.verify_server(server_session.proof()).unwrap();
+    }
+
+    #[test]
+    fn logins() {
+        for (group, hash) in &[
+            (SrpGroup::G1536, SrpHash::Sha256),
+            (SrpGroup::G2048, SrpHash::Sha256),
+            (SrpGroup::G3072, SrpHash::Sha512),
+            (SrpGroup::G4096, SrpHash::Sha1),
+        ] {
+            let params = SrpParameters::new(*group, *hash);
+            assert_eq!(group.prime().len() * 8, group.bits());
+            let salt = generate_salt();
+            let verifier = compute_verifier(params, b"bob", b"correct horse", &salt);
+
+            let server = SrpServer::new(params, b"bob", &salt, &verifier).unwrap();
+            let client = SrpClient::new(params, b"bob");
+            let session = client
+                .process_challenge(b"correct horse", &salt, server.public_key())
+                .unwrap();
+            let server_session = server
+                .verify_client(client.public_key(), session.proof())
+                .unwrap();
+            session.verify_server(server_session.proof()).unwrap();
+            assert_eq!(session.key(), server_session.key());
+            assert_eq!(session.key().len(), hash.output_size());
+
+            // A wrong password fails at the server
+            let client = SrpClient::new(params, b"bob");
+            let session = client
+                .process_challenge(b"wrong horse", &salt, server.public_key())
+                .unwrap();
+            assert!(server
+                .verify_client(client.public_key(), session.proof())
+                .is_err());
+            assert!(session.verify_server(server_session.proof()).is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_public_keys() {
+        let salt = generate_salt();
+        let verifier = compute_verifier(PARAMS, b"alice", b"password", &salt);
+        let server = SrpServer::new(PARAMS, b"alice", &salt, &verifier).unwrap();
+        let client = SrpClient::new(PARAMS, b"alice");
+        let prime = SrpGroup::G1024.prime();
+        for key in &[vec![0u8; 128], prime.clone(), vec![0u8], vec![1u8; 129]] {
+            assert!(server.verify_client(key, &[0u8; 20]).is_err());
+            assert!(client.process_challenge(b"password", &salt, key).is_err());
+        }
+
+        // Unpadded keys are accepted
+        let session = client
+            .process_challenge(b"password", &salt, unpadded(server.public_key()))
+            .unwrap();
+        assert!(server
+            .verify_client(unpadded(client.public_key()), session.proof())
+            .is_ok());
+    }
+
+    #[test]
+    fn primes() {
+        // The primes are what other implementations use
+        assert!(groups::N1024.starts_with("EEAF0AB9ADB38DD6"));
+        assert!(groups::N1536.starts_with("9DEF3CAFB939277A"));
+        assert!(groups::N2048.starts_with("AC6BDB41324A9A9B"));
+        for group in &[
+            SrpGroup::G3072,
+            SrpGroup::G4096,
+            SrpGroup::G6144,
+            SrpGroup::G8192,
+        ] {
+            let prime = group.prime();
+            assert_eq!(prime.len() * 8, group.bits());
+            assert_eq!(&prime[..12], &hex("FFFFFFFF FFFFFFFF C90FDAA2")[..]);
+            assert!(prime[prime.len() - 8..].iter().all(|b| *b == 0xff));
+        }
+    }
+}
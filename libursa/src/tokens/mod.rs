@@ -0,0 +1,8 @@
+//! Bearer tokens built on ursa's ciphers and signatures.
+
+#[cfg(feature = "paseto")]
+pub mod paseto;
+#[cfg(feature = "privacy_pass")]
+pub mod privacy_pass;
+#[cfg(feature = "sd_jwt")]
+pub mod sd_jwt;
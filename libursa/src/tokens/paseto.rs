@@ -0,0 +1,378 @@
+//! PASETO version 4 tokens.
+//!
+//! `v4.local` tokens are encrypted with XChaCha20 and authenticated with a keyed
+//! BLAKE2b MAC, `v4.public` tokens are signed with Ed25519. Both carry an optional
+//! footer, which is authenticated but not encrypted, and can be bound to an implicit
+//! assertion that the verifier has to supply but that isn't part of the token.
+//!
+//! The payload is opaque here. Services usually put JSON claims in it and check
+//! `exp` and friends after the token has been validated.
+
+use std::fmt;
+
+use blake2::digest::{Update, VariableOutput};
+use blake2::VarBlake2b;
+use chacha20::cipher::{NewStreamCipher, SyncStreamCipher};
+use chacha20::XChaCha20;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+pub const LOCAL_HEADER: &str = "v4.local.";
+pub const PUBLIC_HEADER: &str = "v4.public.";
+pub const LOCAL_KEY_SIZE: usize = 32;
+
+const NONCE_SIZE: usize = 32;
+const TAG_SIZE: usize = 32;
+const SIGNATURE_SIZE: usize = 64;
+const ENCRYPTION_KEY_INFO: &[u8] = b"paseto-encryption-key";
+const AUTHENTICATION_KEY_INFO: &[u8] = b"paseto-auth-key-for-aead";
+
+/// A key for `v4.local` tokens. It can't be used for anything else.
+pub struct LocalKey([u8; LOCAL_KEY_SIZE]);
+
+impl LocalKey {
+    pub fn new(key: [u8; LOCAL_KEY_SIZE]) -> Self {
+        LocalKey(key)
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; LOCAL_KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        LocalKey(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for LocalKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LocalKey(..)")
+    }
+}
+
+impl Drop for LocalKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The validated contents of a token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub payload: Vec<u8>,
+    pub footer: Vec<u8>,
+}
+
+/// Create a `v4.local` token
+pub fn encrypt_local(
+    key: &LocalKey,
+    payload: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+) -> Result<String, CryptoError> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    Ok(encrypt_local_with_nonce(
+        key,
+        &nonce,
+        payload,
+        footer,
+        implicit_assertion,
+    ))
+}
+
+/// Validate and decrypt a `v4.local` token
+pub fn decrypt_local(
+    key: &LocalKey,
+    token: &str,
+    implicit_assertion: &[u8],
+) -> Result<Token, CryptoError> {
+    let (body, footer) = split(token, LOCAL_HEADER)?;
+    if body.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(parse_error("PASETO token is too short"));
+    }
+    let (nonce, rest) = body.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+    let (mut encryption_key, mut counter_nonce, mut authentication_key) = split_key(key, nonce);
+    let expected = blake2b_mac(
+        &authentication_key,
+        &pae(&[
+            LOCAL_HEADER.as_bytes(),
+            nonce,
+            ciphertext,
+            &footer,
+            implicit_assertion,
+        ]),
+    );
+    authentication_key.zeroize();
+    if !bool::from(expected.ct_eq(tag)) {
+        encryption_key.zeroize();
+        counter_nonce.zeroize();
+        return Err(CryptoError::GeneralError(
+            "PASETO token authentication failed".to_string(),
+        ));
+    }
+    let mut payload = ciphertext.to_vec();
+    apply_keystream(&encryption_key, &counter_nonce, &mut payload);
+    encryption_key.zeroize();
+    counter_nonce.zeroize();
+    Ok(Token { payload, footer })
+}
+
+/// Create a `v4.public` token. `secret_key` is an Ed25519 key from `Ed25519Sha512`.
+pub fn sign_public(
+    secret_key: &PrivateKey,
+    payload: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+) -> Result<String, CryptoError> {
+    let signature = Ed25519Sha512::new().sign(
+        &pae(&[
+            PUBLIC_HEADER.as_bytes(),
+            payload,
+            footer,
+            implicit_assertion,
+        ]),
+        secret_key,
+    )?;
+    let mut body = payload.to_vec();
+    body.extend_from_slice(&signature);
+    Ok(assemble(PUBLIC_HEADER, &body, footer))
+}
+
+/// Verify a `v4.public` token and return its payload and footer
+pub fn verify_public(
+    public_key: &PublicKey,
+    token: &str,
+    implicit_assertion: &[u8],
+) -> Result<Token, CryptoError> {
+    let (body, footer) = split(token, PUBLIC_HEADER)?;
+    if body.len() < SIGNATURE_SIZE {
+        return Err(parse_error("PASETO token is too short"));
+    }
+    let (payload, signature) = body.split_at(body.len() - SIGNATURE_SIZE);
+    let message = pae(&[
+        PUBLIC_HEADER.as_bytes(),
+        payload,
+        &footer,
+        implicit_assertion,
+    ]);
+    if !Ed25519Sha512::new()
+        .verify(&message, signature, public_key)
+        .unwrap_or(false)
+    {
+        return Err(CryptoError::GeneralError(
+            "PASETO token signature is invalid".to_string(),
+        ));
+    }
+    Ok(Token {
+        payload: payload.to_vec(),
+        footer,
+    })
+}
+
+/// Read the footer of a token before validating it, for example to find the key
+/// it names. Nothing in it can be trusted until the token has been validated.
+pub fn untrusted_footer(token: &str) -> Result<Vec<u8>, CryptoError> {
+    match token.splitn(4, '.').nth(3) {
+        Some(footer) => decode(footer),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn encrypt_local_with_nonce(
+    key: &LocalKey,
+    nonce: &[u8; NONCE_SIZE],
+    payload: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+) -> String {
+    let (mut encryption_key, mut counter_nonce, mut authentication_key) = split_key(key, nonce);
+    let mut ciphertext = payload.to_vec();
+    apply_keystream(&encryption_key, &counter_nonce, &mut ciphertext);
+    let tag = blake2b_mac(
+        &authentication_key,
+        &pae(&[
+            LOCAL_HEADER.as_bytes(),
+            nonce,
+            &ciphertext,
+            footer,
+            implicit_assertion,
+        ]),
+    );
+    encryption_key.zeroize();
+    counter_nonce.zeroize();
+    authentication_key.zeroize();
+
+    let mut body = nonce.to_vec();
+    body.extend_from_slice(&ciphertext);
+    body.extend_from_slice(&tag);
+    assemble(LOCAL_HEADER, &body, footer)
+}
+
+/// Derive the encryption key, the XChaCha20 nonce and the authentication key
+/// for one token from the local key and the token's nonce
+fn split_key(key: &LocalKey, nonce: &[u8]) -> ([u8; 32], [u8; 24], [u8; 32]) {
+    let mut derived = [0u8; 56];
+    let mut hasher = VarBlake2b::new_keyed(&key.0, derived.len());
+    hasher.update(ENCRYPTION_KEY_INFO);
+    hasher.update(nonce);
+    hasher.finalize_variable(|h| derived.copy_from_slice(h));
+    let encryption_key = *array_ref!(derived, 0, 32);
+    let counter_nonce = *array_ref!(derived, 32, 24);
+    derived.zeroize();
+
+    let mut info = AUTHENTICATION_KEY_INFO.to_vec();
+    info.extend_from_slice(nonce);
+    let authentication_key = blake2b_mac(&key.0, &info);
+    (encryption_key, counter_nonce, authentication_key)
+}
+
+fn blake2b_mac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut hasher = VarBlake2b::new_keyed(key, 32);
+    hasher.update(data);
+    let mut mac = [0u8; 32];
+    hasher.finalize_variable(|h| mac.copy_from_slice(h));
+    mac
+}
+
+fn apply_keystream(key: &[u8; 32], nonce: &[u8; 24], data: &mut [u8]) {
+    XChaCha20::new(&(*key).into(), &(*nonce).into()).apply_keystream(data);
+}
+
+/// Pre-authentication encoding, which makes the pieces of a token unambiguous
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&le64(pieces.len()));
+    for piece in pieces {
+        out.extend_from_slice(&le64(piece.len()));
+        out.extend_from_slice(piece);
+    }
+    out
+}
+
+fn le64(n: usize) -> [u8; 8] {
+    (n as u64 & 0x7fff_ffff_ffff_ffff).to_le_bytes()
+}
+
+fn assemble(header: &str, body: &[u8], footer: &[u8]) -> String {
+    let mut token = header.to_string();
+    token.push_str(&base64::encode_config(body, base64::URL_SAFE_NO_PAD));
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&base64::encode_config(footer, base64::URL_SAFE_NO_PAD));
+    }
+    token
+}
+
+/// Check the header and decode the body and footer
+fn split(token: &str, header: &str) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+    if !token.starts_with(header) {
+        return Err(CryptoError::ParseError(format!(
+            "Expected a {} token",
+            header.trim_end_matches('.')
+        )));
+    }
+    let mut parts = token[header.len()..].split('.');
+    let body = decode(parts.next().unwrap_or(""))?;
+    let footer = match parts.next() {
+        Some(f) if !f.is_empty() => decode(f)?,
+        Some(_) => return Err(parse_error("PASETO footer is empty")),
+        None => Vec::new(),
+    };
+    if parts.next().is_some() {
+        return Err(parse_error("PASETO token has too many parts"));
+    }
+    Ok((body, footer))
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, CryptoError> {
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid PASETO base64: {}", e)))
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOCAL_PAYLOAD: &[u8] =
+        br#"{"data":"this is a secret message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const PUBLIC_PAYLOAD: &[u8] =
+        br#"{"data":"this is a signed message","exp":"2022-01-01T00:00:00+00:00"}"#;
+
+    #[test]
+    fn local_test_vector() {
+        // Test vector 4-E-1 from the PASETO specification
+        let key = hex::decode("707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f")
+            .unwrap();
+        let key = LocalKey::new(*array_ref!(key, 0, LOCAL_KEY_SIZE));
+        let token = encrypt_local_with_nonce(&key, &[0u8; 32], LOCAL_PAYLOAD, b"", b"");
+        assert_eq!(token, "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvSwscFlAl1pk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XJ5hOb_4v9RmDkneN0S92dx0OW4pgy7omxgf3S8c3LlQg");
+        let decrypted = decrypt_local(&key, &token, b"").unwrap();
+        assert_eq!(decrypted.payload, LOCAL_PAYLOAD);
+        assert!(decrypted.footer.is_empty());
+    }
+
+    #[test]
+    fn local_round_trip() {
+        let key = LocalKey::generate();
+        let footer = br#"{"kid":"local-1"}"#;
+        let token = encrypt_local(&key, b"claims", footer, b"user-42").unwrap();
+        assert_eq!(untrusted_footer(&token).unwrap(), footer);
+        let decrypted = decrypt_local(&key, &token, b"user-42").unwrap();
+        assert_eq!(decrypted.payload, b"claims");
+        assert_eq!(decrypted.footer, footer);
+
+        assert!(decrypt_local(&key, &token, b"user-43").is_err());
+        assert!(decrypt_local(&LocalKey::generate(), &token, b"user-42").is_err());
+        let (body, _) = token.split_at(token.rfind('.').unwrap());
+        let other_footer = format!(
+            "{}.{}",
+            body,
+            base64::encode_config(b"{}", base64::URL_SAFE_NO_PAD)
+        );
+        assert!(decrypt_local(&key, &other_footer, b"user-42").is_err());
+        let public = token.replacen(LOCAL_HEADER, PUBLIC_HEADER, 1);
+        assert!(decrypt_local(&key, &public, b"user-42").is_err());
+    }
+
+    #[test]
+    fn public_test_vector() {
+        // Test vector 4-S-1 from the PASETO specification
+        let secret_key = PrivateKey(hex::decode("b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a37741eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2").unwrap());
+        let public_key = PublicKey(secret_key[32..].to_vec());
+        let token = sign_public(&secret_key, PUBLIC_PAYLOAD, b"", b"").unwrap();
+        assert_eq!(token, "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9bg_XBBzds8lTZShVlwwKSgeKpLT3yukTw6JUz3W4h_ExsQV-P0V54zemZDcAxFaSeef1QlXEFtkqxT1ciiQEDA");
+        let verified = verify_public(&public_key, &token, b"").unwrap();
+        assert_eq!(verified.payload, PUBLIC_PAYLOAD);
+    }
+
+    #[test]
+    fn public_round_trip() {
+        let (public_key, secret_key) = Ed25519Sha512::new().keypair(None).unwrap();
+        let token = sign_public(&secret_key, b"claims", b"kid-1", b"tenant-7").unwrap();
+        let verified = verify_public(&public_key, &token, b"tenant-7").unwrap();
+        assert_eq!(verified.payload, b"claims");
+        assert_eq!(verified.footer, b"kid-1");
+
+        assert!(verify_public(&public_key, &token, b"").is_err());
+        let (other, _) = Ed25519Sha512::new().keypair(None).unwrap();
+        assert!(verify_public(&other, &token, b"tenant-7").is_err());
+        let local = token.replacen(PUBLIC_HEADER, LOCAL_HEADER, 1);
+        assert!(verify_public(&public_key, &local, b"tenant-7").is_err());
+        assert!(verify_public(&public_key, "v4.public.AAAA", b"").is_err());
+    }
+}
@@ -0,0 +1,685 @@
+//! Privacy Pass tokens issued with a verifiable OPRF (RFC 9576 architecture).
+//!
+//! A client that passed some check, a CAPTCHA or a login, asks the issuer for a batch of
+//! tokens and later spends one per request to an origin instead of repeating the check.
+//! Tokens are blinded during issuance so the issuer can't link a redemption to the
+//! issuance it came from, which is what makes them useful for rate limiting without
+//! tracking.
+//!
+//! Issuance uses the VOPRF of RFC 9497 with ristretto255 and SHA-512. The client sends
+//! blinded inputs in a `TokenRequest`, the issuer evaluates them with its key and proves
+//! with a single batched DLEQ proof that every evaluation used the key behind its
+//! `IssuerPublicKey`, so it can't tag one client's tokens with another key. Tokens are
+//! privately verifiable like RFC 9578 type 0x0001 tokens: redemption needs the issuer
+//! key, so the origin is the issuer or shares its key. The token type here is not a
+//! registered one, both sides must use this module.
+//!
+//! A token is bound to the `challenge` of the origin it is spent at. The origin must keep
+//! the nonces of spent tokens and reject a token whose nonce it has seen before.
+
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use CryptoError;
+
+/// The token type in every token, request and response
+pub const TOKEN_TYPE: u16 = 0xFF01;
+pub const POINT_SIZE: usize = 32;
+pub const SCALAR_SIZE: usize = 32;
+pub const NONCE_SIZE: usize = 32;
+pub const KEY_ID_SIZE: usize = 32;
+pub const AUTHENTICATOR_SIZE: usize = 64;
+pub const TOKEN_SIZE: usize = 2 + NONCE_SIZE + 32 + KEY_ID_SIZE + AUTHENTICATOR_SIZE;
+/// No single request can ask for more tokens than this
+pub const MAX_BATCH_SIZE: usize = 1024;
+
+/// "OPRFV1-" || I2OSP(modeVOPRF, 1) || "-ristretto255-SHA512"
+const CONTEXT_STRING: &[u8] = b"OPRFV1-\x01-ristretto255-SHA512";
+
+/// The issuer's secret key, needed to issue and to redeem tokens
+pub struct IssuerKey(Scalar);
+
+/// The key clients check issued tokens against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IssuerPublicKey(RistrettoPoint);
+
+/// A client's request for a batch of tokens
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenRequest {
+    truncated_key_id: u8,
+    blinded: Vec<RistrettoPoint>,
+}
+
+/// What the client keeps until the issuer answers its request
+pub struct PendingTokens {
+    public_key: IssuerPublicKey,
+    inputs: Vec<Vec<u8>>,
+    blinds: Vec<Scalar>,
+    blinded: Vec<RistrettoPoint>,
+}
+
+/// The issuer's answer to a `TokenRequest`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenResponse {
+    evaluated: Vec<RistrettoPoint>,
+    proof: DleqProof,
+}
+
+/// Proof that every evaluated element was made with the same key as the public key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DleqProof {
+    challenge: Scalar,
+    response: Scalar,
+}
+
+/// A token ready to be spent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    nonce: [u8; NONCE_SIZE],
+    challenge_digest: [u8; 32],
+    key_id: [u8; KEY_ID_SIZE],
+    authenticator: [u8; AUTHENTICATOR_SIZE],
+}
+
+impl IssuerKey {
+    pub fn generate() -> Self {
+        IssuerKey(Scalar::random(&mut OsRng))
+    }
+
+    pub fn public_key(&self) -> IssuerPublicKey {
+        IssuerPublicKey(self.0 * RISTRETTO_BASEPOINT_POINT)
+    }
+
+    /// Evaluate the blinded inputs of `request` and prove the evaluations correct
+    pub fn issue(&self, request: &TokenRequest) -> Result<TokenResponse, CryptoError> {
+        let public_key = self.public_key();
+        if request.truncated_key_id != public_key.key_id()[KEY_ID_SIZE - 1] {
+            return Err(CryptoError::GeneralError(
+                "The token request is for another issuer key".to_string(),
+            ));
+        }
+        let evaluated: Vec<RistrettoPoint> = request.blinded.iter().map(|b| self.0 * b).collect();
+
+        // GenerateProof, Z = k M since the issuer knows k
+        let weights = composite_weights(&public_key, &request.blinded, &evaluated);
+        let m = RistrettoPoint::vartime_multiscalar_mul(&weights, &request.blinded);
+        let z = self.0 * m;
+        let mut r = Scalar::random(&mut OsRng);
+        let challenge = proof_challenge(
+            &public_key,
+            &m,
+            &z,
+            &(r * RISTRETTO_BASEPOINT_POINT),
+            &(r * m),
+        );
+        let response = r - challenge * self.0;
+        r.zeroize();
+        Ok(TokenResponse {
+            evaluated,
+            proof: DleqProof {
+                challenge,
+                response,
+            },
+        })
+    }
+
+    /// Check a token spent at an origin that sent `challenge`. The caller must also
+    /// check that `token.nonce()` wasn't spent before.
+    pub fn verify(&self, token: &Token, challenge: &[u8]) -> Result<(), CryptoError> {
+        let invalid = || CryptoError::GeneralError("Invalid token".to_string());
+        if token.key_id != self.public_key().key_id()
+            || token.challenge_digest != challenge_digest(challenge)
+        {
+            return Err(invalid());
+        }
+        let input = token.input();
+        let expected = finalize(&input, &(self.0 * hash_to_group(&input)));
+        if bool::from(expected.ct_eq(&token.authenticator[..])) {
+            Ok(())
+        } else {
+            Err(invalid())
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; SCALAR_SIZE] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != SCALAR_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer key length".to_string(),
+            ));
+        }
+        Ok(IssuerKey(canonical_scalar(bytes)?))
+    }
+}
+
+impl ::std::fmt::Debug for IssuerKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("IssuerKey(..)")
+    }
+}
+
+impl Drop for IssuerKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl IssuerPublicKey {
+    /// SHA-256 of the serialized key, which identifies the key in tokens
+    pub fn key_id(&self) -> [u8; KEY_ID_SIZE] {
+        let mut id = [0u8; KEY_ID_SIZE];
+        id.copy_from_slice(Sha256::digest(&self.to_bytes()).as_slice());
+        id
+    }
+
+    pub fn to_bytes(&self) -> [u8; POINT_SIZE] {
+        self.0.compress().to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != POINT_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer public key length".to_string(),
+            ));
+        }
+        let point = decompress(bytes)?;
+        if point.is_identity() {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer public key".to_string(),
+            ));
+        }
+        Ok(IssuerPublicKey(point))
+    }
+}
+
+impl TokenRequest {
+    /// Ask the issuer with `public_key` for `count` tokens that can be spent at an
+    /// origin that sends `challenge`
+    pub fn new(
+        public_key: &IssuerPublicKey,
+        challenge: &[u8],
+        count: usize,
+    ) -> Result<(TokenRequest, PendingTokens), CryptoError> {
+        if count == 0 || count > MAX_BATCH_SIZE {
+            return Err(CryptoError::GeneralError(format!(
+                "A request needs between 1 and {} tokens",
+                MAX_BATCH_SIZE
+            )));
+        }
+        let mut rng = OsRng;
+        let key_id = public_key.key_id();
+        let digest = challenge_digest(challenge);
+        let mut inputs = Vec::with_capacity(count);
+        let mut blinds = Vec::with_capacity(count);
+        let mut blinded = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut nonce = [0u8; NONCE_SIZE];
+            rng.fill_bytes(&mut nonce);
+            let input = token_input(&nonce, &digest, &key_id);
+            let blind = Scalar::random(&mut rng);
+            blinded.push(blind * hash_to_group(&input));
+            blinds.push(blind);
+            inputs.push(input);
+        }
+        Ok((
+            TokenRequest {
+                truncated_key_id: key_id[KEY_ID_SIZE - 1],
+                blinded: blinded.clone(),
+            },
+            PendingTokens {
+                public_key: *public_key,
+                inputs,
+                blinds,
+                blinded,
+            },
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.blinded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blinded.is_empty()
+    }
+
+    /// token_type || truncated_key_id || count || blinded elements
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + POINT_SIZE * self.blinded.len());
+        bytes.extend_from_slice(&TOKEN_TYPE.to_be_bytes());
+        bytes.push(self.truncated_key_id);
+        bytes.extend_from_slice(&(self.blinded.len() as u16).to_be_bytes());
+        for b in &self.blinded {
+            bytes.extend_from_slice(b.compress().as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::ParseError("Invalid token request".to_string());
+        if bytes.len() < 5 || *array_ref!(bytes, 0, 2) != TOKEN_TYPE.to_be_bytes() {
+            return Err(invalid());
+        }
+        let count = u16::from_be_bytes(*array_ref!(bytes, 3, 2)) as usize;
+        if count == 0 || count > MAX_BATCH_SIZE || bytes.len() != 5 + POINT_SIZE * count {
+            return Err(invalid());
+        }
+        let blinded = read_points(&bytes[5..])?;
+        if blinded.iter().any(|b| b.is_identity()) {
+            return Err(invalid());
+        }
+        Ok(TokenRequest {
+            truncated_key_id: bytes[2],
+            blinded,
+        })
+    }
+}
+
+impl PendingTokens {
+    /// Check the issuer's proof and unblind the tokens
+    pub fn finalize(self, response: &TokenResponse) -> Result<Vec<Token>, CryptoError> {
+        let invalid = || CryptoError::GeneralError("Invalid token response".to_string());
+        if response.evaluated.len() != self.blinded.len() {
+            return Err(invalid());
+        }
+        // VerifyProof
+        let weights = composite_weights(&self.public_key, &self.blinded, &response.evaluated);
+        let m = RistrettoPoint::vartime_multiscalar_mul(&weights, &self.blinded);
+        let z = RistrettoPoint::vartime_multiscalar_mul(&weights, &response.evaluated);
+        let DleqProof {
+            challenge,
+            response: s,
+        } = response.proof;
+        let t2 = RistrettoPoint::vartime_multiscalar_mul(
+            &[s, challenge],
+            &[RISTRETTO_BASEPOINT_POINT, self.public_key.0],
+        );
+        let t3 = RistrettoPoint::vartime_multiscalar_mul(&[s, challenge], &[m, z]);
+        if proof_challenge(&self.public_key, &m, &z, &t2, &t3) != challenge {
+            return Err(invalid());
+        }
+
+        Ok(self
+            .inputs
+            .iter()
+            .zip(self.blinds.iter())
+            .zip(response.evaluated.iter())
+            .map(|((input, blind), evaluated)| {
+                let authenticator = finalize(input, &(blind.invert() * evaluated));
+                Token::from_input(input, authenticator)
+            })
+            .collect())
+    }
+}
+
+impl Drop for PendingTokens {
+    fn drop(&mut self) {
+        self.blinds.zeroize();
+    }
+}
+
+impl TokenResponse {
+    pub fn len(&self) -> usize {
+        self.evaluated.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.evaluated.is_empty()
+    }
+
+    /// count || evaluated elements || challenge || response
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + POINT_SIZE * self.evaluated.len() + 2 * SCALAR_SIZE);
+        bytes.extend_from_slice(&(self.evaluated.len() as u16).to_be_bytes());
+        for e in &self.evaluated {
+            bytes.extend_from_slice(e.compress().as_bytes());
+        }
+        bytes.extend_from_slice(self.proof.challenge.as_bytes());
+        bytes.extend_from_slice(self.proof.response.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::ParseError("Invalid token response".to_string());
+        if bytes.len() < 2 {
+            return Err(invalid());
+        }
+        let count = u16::from_be_bytes(*array_ref!(bytes, 0, 2)) as usize;
+        let points_end = 2 + POINT_SIZE * count;
+        if count == 0 || count > MAX_BATCH_SIZE || bytes.len() != points_end + 2 * SCALAR_SIZE {
+            return Err(invalid());
+        }
+        Ok(TokenResponse {
+            evaluated: read_points(&bytes[2..points_end])?,
+            proof: DleqProof {
+                challenge: canonical_scalar(&bytes[points_end..points_end + SCALAR_SIZE])?,
+                response: canonical_scalar(&bytes[points_end + SCALAR_SIZE..])?,
+            },
+        })
+    }
+}
+
+impl Token {
+    /// Unique per token, the origin rejects a nonce it has seen before
+    pub fn nonce(&self) -> &[u8; NONCE_SIZE] {
+        &self.nonce
+    }
+
+    pub fn key_id(&self) -> &[u8; KEY_ID_SIZE] {
+        &self.key_id
+    }
+
+    /// token_type || nonce || challenge_digest || token_key_id || authenticator
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = token_input(&self.nonce, &self.challenge_digest, &self.key_id);
+        bytes.extend_from_slice(&self.authenticator);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != TOKEN_SIZE || *array_ref!(bytes, 0, 2) != TOKEN_TYPE.to_be_bytes() {
+            return Err(CryptoError::ParseError("Invalid token".to_string()));
+        }
+        let (input, authenticator) = bytes.split_at(TOKEN_SIZE - AUTHENTICATOR_SIZE);
+        Ok(Token::from_input(
+            input,
+            *array_ref!(authenticator, 0, AUTHENTICATOR_SIZE),
+        ))
+    }
+
+    fn from_input(input: &[u8], authenticator: [u8; AUTHENTICATOR_SIZE]) -> Self {
+        Token {
+            nonce: *array_ref!(input, 2, NONCE_SIZE),
+            challenge_digest: *array_ref!(input, 2 + NONCE_SIZE, 32),
+            key_id: *array_ref!(input, 2 + NONCE_SIZE + 32, KEY_ID_SIZE),
+            authenticator,
+        }
+    }
+
+    fn input(&self) -> Vec<u8> {
+        token_input(&self.nonce, &self.challenge_digest, &self.key_id)
+    }
+}
+
+fn token_input(nonce: &[u8], challenge_digest: &[u8], key_id: &[u8]) -> Vec<u8> {
+    let mut input = Vec::with_capacity(TOKEN_SIZE - AUTHENTICATOR_SIZE);
+    input.extend_from_slice(&TOKEN_TYPE.to_be_bytes());
+    input.extend_from_slice(nonce);
+    input.extend_from_slice(challenge_digest);
+    input.extend_from_slice(key_id);
+    input
+}
+
+fn challenge_digest(challenge: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(Sha256::digest(challenge).as_slice());
+    digest
+}
+
+/// The weights of ComputeComposites in RFC 9497, M and Z are the weighted sums of
+/// the blinded and evaluated elements
+fn composite_weights(
+    public_key: &IssuerPublicKey,
+    blinded: &[RistrettoPoint],
+    evaluated: &[RistrettoPoint],
+) -> Vec<Scalar> {
+    let mut seed_dst = b"Seed-".to_vec();
+    seed_dst.extend_from_slice(CONTEXT_STRING);
+    let mut seed_transcript = Sha512::new();
+    length_prefixed(&mut seed_transcript, &public_key.to_bytes());
+    length_prefixed(&mut seed_transcript, &seed_dst);
+    let seed = seed_transcript.result();
+
+    blinded
+        .iter()
+        .zip(evaluated.iter())
+        .enumerate()
+        .map(|(i, (c, d))| {
+            let mut transcript = Vec::with_capacity(seed.len() + 2 * POINT_SIZE + 17);
+            transcript.extend_from_slice(&(seed.len() as u16).to_be_bytes());
+            transcript.extend_from_slice(seed.as_slice());
+            transcript.extend_from_slice(&(i as u16).to_be_bytes());
+            transcript.extend_from_slice(&(POINT_SIZE as u16).to_be_bytes());
+            transcript.extend_from_slice(c.compress().as_bytes());
+            transcript.extend_from_slice(&(POINT_SIZE as u16).to_be_bytes());
+            transcript.extend_from_slice(d.compress().as_bytes());
+            transcript.extend_from_slice(b"Composite");
+            hash_to_scalar(&transcript)
+        })
+        .collect()
+}
+
+fn proof_challenge(
+    public_key: &IssuerPublicKey,
+    m: &RistrettoPoint,
+    z: &RistrettoPoint,
+    t2: &RistrettoPoint,
+    t3: &RistrettoPoint,
+) -> Scalar {
+    let mut transcript = Vec::with_capacity(5 * (POINT_SIZE + 2) + 9);
+    for p in &[public_key.0, *m, *z, *t2, *t3] {
+        transcript.extend_from_slice(&(POINT_SIZE as u16).to_be_bytes());
+        transcript.extend_from_slice(p.compress().as_bytes());
+    }
+    transcript.extend_from_slice(b"Challenge");
+    hash_to_scalar(&transcript)
+}
+
+/// The VOPRF output for `input`, from the unblinded evaluation
+fn finalize(input: &[u8], unblinded: &RistrettoPoint) -> [u8; AUTHENTICATOR_SIZE] {
+    let mut hasher = Sha512::new();
+    length_prefixed(&mut hasher, input);
+    length_prefixed(&mut hasher, unblinded.compress().as_bytes());
+    hasher.input(b"Finalize");
+    let mut output = [0u8; AUTHENTICATOR_SIZE];
+    output.copy_from_slice(hasher.result().as_slice());
+    output
+}
+
+fn length_prefixed(hasher: &mut Sha512, data: &[u8]) {
+    hasher.input((data.len() as u16).to_be_bytes());
+    hasher.input(data);
+}
+
+fn hash_to_group(input: &[u8]) -> RistrettoPoint {
+    let mut dst = b"HashToGroup-".to_vec();
+    dst.extend_from_slice(CONTEXT_STRING);
+    RistrettoPoint::from_uniform_bytes(&expand_message_xmd(input, &dst))
+}
+
+fn hash_to_scalar(input: &[u8]) -> Scalar {
+    let mut dst = b"HashToScalar-".to_vec();
+    dst.extend_from_slice(CONTEXT_STRING);
+    Scalar::from_bytes_mod_order_wide(&expand_message_xmd(input, &dst))
+}
+
+/// expand_message_xmd of RFC 9380 with SHA-512 for 64 bytes, a single block
+fn expand_message_xmd(msg: &[u8], dst: &[u8]) -> [u8; 64] {
+    let mut b0 = Sha512::new();
+    b0.input(&[0u8; 128][..]);
+    b0.input(msg);
+    b0.input(64u16.to_be_bytes());
+    b0.input([0u8]);
+    b0.input(dst);
+    b0.input([dst.len() as u8]);
+    let b0 = b0.result();
+
+    let mut b1 = Sha512::new();
+    b1.input(b0.as_slice());
+    b1.input([1u8]);
+    b1.input(dst);
+    b1.input([dst.len() as u8]);
+    let mut output = [0u8; 64];
+    output.copy_from_slice(b1.result().as_slice());
+    output
+}
+
+fn decompress(bytes: &[u8]) -> Result<RistrettoPoint, CryptoError> {
+    CompressedRistretto::from_slice(bytes)
+        .decompress()
+        .ok_or_else(|| CryptoError::ParseError("Invalid Ristretto point".to_string()))
+}
+
+fn canonical_scalar(bytes: &[u8]) -> Result<Scalar, CryptoError> {
+    Scalar::from_canonical_bytes(*array_ref!(bytes, 0, SCALAR_SIZE))
+        .ok_or_else(|| CryptoError::ParseError("Invalid scalar".to_string()))
+}
+
+fn read_points(bytes: &[u8]) -> Result<Vec<RistrettoPoint>, CryptoError> {
+    bytes.chunks(POINT_SIZE).map(decompress).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rfc9497_vector() {
+        // VOPRF(ristretto255, SHA-512) test vector 1 of RFC 9497 appendix A.1.2
+        let key = IssuerKey::from_bytes(&hex(
+            "e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909",
+        ))
+        .unwrap();
+        let public_key = key.public_key();
+        assert_eq!(
+            public_key.to_bytes().to_vec(),
+            hex("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e")
+        );
+        let input = hex("00");
+        let blind = canonical_scalar(&hex(
+            "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706",
+        ))
+        .unwrap();
+        let blinded = blind * hash_to_group(&input);
+        assert_eq!(
+            blinded.compress().as_bytes().to_vec(),
+            hex("863f330cc1a1259ed5a5998a23acfd37fb4351a793a5b3c090b642ddc439b945")
+        );
+        let evaluated = key.0 * blinded;
+        assert_eq!(
+            evaluated.compress().as_bytes().to_vec(),
+            hex("aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e")
+        );
+
+        let r = canonical_scalar(&hex(
+            "222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e",
+        ))
+        .unwrap();
+        let weights = composite_weights(&public_key, &[blinded], &[evaluated]);
+        let m = weights[0] * blinded;
+        let challenge = proof_challenge(
+            &public_key,
+            &m,
+            &(key.0 * m),
+            &(r * RISTRETTO_BASEPOINT_POINT),
+            &(r * m),
+        );
+        let mut proof = challenge.to_bytes().to_vec();
+        proof.extend_from_slice((r - challenge * key.0).as_bytes());
+        assert_eq!(
+            proof,
+            hex(
+                "ddef93772692e535d1a53903db24367355cc2cc78de93b3be5a8ffcc6985dd06\
+                 6d4346421d17bf5117a2a1ff0fcb2a759f58a539dfbe857a40bce4cf49ec600d"
+            )
+        );
+        assert_eq!(
+            finalize(&input, &(blind.invert() * evaluated)).to_vec(),
+            hex(
+                "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+                 a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c"
+            )
+        );
+    }
+
+    #[test]
+    fn issue_and_redeem() {
+        let key = IssuerKey::generate();
+        let public_key = key.public_key();
+        let (request, pending) =
+            TokenRequest::new(&public_key, b"origin.example challenge", 8).unwrap();
+        let response = key.issue(&request).unwrap();
+        let tokens = pending.finalize(&response).unwrap();
+        assert_eq!(tokens.len(), 8);
+        for token in &tokens {
+            assert!(key.verify(token, b"origin.example challenge").is_ok());
+            assert!(key.verify(token, b"other challenge").is_err());
+            assert_eq!(token.key_id(), &public_key.key_id());
+        }
+        assert_ne!(tokens[0].nonce(), tokens[1].nonce());
+
+        // Tokens from one issuer key don't redeem with another
+        let other = IssuerKey::generate();
+        assert!(other
+            .verify(&tokens[0], b"origin.example challenge")
+            .is_err());
+        assert!(other.issue(&request).is_err());
+
+        // A forged authenticator
+        let mut forged = tokens[0].clone();
+        forged.authenticator[0] ^= 1;
+        assert!(key.verify(&forged, b"origin.example challenge").is_err());
+    }
+
+    #[test]
+    fn issuer_must_use_its_public_key() {
+        let key = IssuerKey::generate();
+        let (request, pending) = TokenRequest::new(&key.public_key(), b"ctx", 3).unwrap();
+        let mut response = key.issue(&request).unwrap();
+        // Evaluating one element with a different key to tag a client
+        let tag = IssuerKey::generate();
+        response.evaluated[1] = tag.0 * request.blinded[1];
+        assert!(pending.finalize(&response).is_err());
+
+        let (request, pending) = TokenRequest::new(&key.public_key(), b"ctx", 3).unwrap();
+        let mut response = key.issue(&request).unwrap();
+        response.evaluated.pop();
+        assert!(pending.finalize(&response).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let key = IssuerKey::from_bytes(&IssuerKey::generate().to_bytes()).unwrap();
+        let public_key = IssuerPublicKey::from_bytes(&key.public_key().to_bytes()).unwrap();
+        assert_eq!(public_key, key.public_key());
+
+        let (request, pending) = TokenRequest::new(&public_key, b"ctx", 2).unwrap();
+        let bytes = request.to_bytes();
+        assert_eq!(TokenRequest::from_bytes(&bytes).unwrap(), request);
+        assert!(TokenRequest::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let response = key.issue(&request).unwrap();
+        let bytes = response.to_bytes();
+        let response = TokenResponse::from_bytes(&bytes).unwrap();
+        assert!(TokenResponse::from_bytes(&bytes[1..]).is_err());
+
+        let tokens = pending.finalize(&response).unwrap();
+        let bytes = tokens[0].to_bytes();
+        assert_eq!(bytes.len(), TOKEN_SIZE);
+        let token = Token::from_bytes(&bytes).unwrap();
+        assert_eq!(token, tokens[0]);
+        assert!(key.verify(&token, b"ctx").is_ok());
+
+        assert!(TokenRequest::new(&public_key, b"ctx", 0).is_err());
+        assert!(TokenRequest::new(&public_key, b"ctx", MAX_BATCH_SIZE + 1).is_err());
+    }
+}
@@ -0,0 +1,559 @@
+//! Selective disclosure for JWTs with salted claim digests, following SD-JWT.
+//!
+//! The issuer replaces each claim the holder may withhold with the digest of a
+//! `Disclosure`, the base64url encoded JSON array `[salt, name, value]`, and lists the
+//! digests in the object's `_sd` array. Array elements are replaced in place by
+//! `{"...": digest}` with a `[salt, value]` disclosure. The payload is signed as an
+//! EdDSA JWS and the issued `SdJwt` is the JWS followed by every disclosure, each
+//! terminated by `~`.
+//!
+//! The holder presents the JWS with only the disclosures it chooses to reveal. The
+//! verifier checks the signature, hashes every disclosure it received and puts the
+//! claims back in place of their digests, so it learns nothing about the withheld
+//! claims except how many there may be. The salts keep a withheld claim from being
+//! guessed by hashing candidate values, which is also why `add_decoys` can hide how
+//! many claims were concealed.
+//!
+//! Digests are SHA-256, `_sd_alg` is always `sha-256`. Key binding JWTs aren't
+//! produced or checked here; a presentation that ends with one is rejected.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use keys::{PrivateKey, PublicKey};
+use signatures::ed25519::Ed25519Sha512;
+use signatures::SignatureScheme;
+use CryptoError;
+
+/// The claim listing the digests of an object's concealed claims
+pub const SD_CLAIM: &str = "_sd";
+/// The claim naming the digest algorithm
+pub const SD_ALG_CLAIM: &str = "_sd_alg";
+/// The key of a concealed array element
+pub const ARRAY_ELEMENT_KEY: &str = "...";
+
+const SD_ALG: &str = "sha-256";
+const SALT_SIZE: usize = 16;
+const JWS_HEADER: &str = r#"{"alg":"EdDSA","typ":"sd+jwt"}"#;
+
+/// A concealed claim or array element with its salt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disclosure {
+    pub salt: String,
+    /// `None` for array elements
+    pub name: Option<String>,
+    pub value: Value,
+    encoded: String,
+}
+
+impl Disclosure {
+    /// A disclosure for the claim `name` with a fresh salt
+    pub fn new(name: &str, value: Value) -> Result<Self, CryptoError> {
+        if name == SD_CLAIM || name == ARRAY_ELEMENT_KEY {
+            return Err(CryptoError::GeneralError(format!(
+                "{} can't be concealed",
+                name
+            )));
+        }
+        Ok(Self::encode(random_salt(), Some(name.to_string()), value))
+    }
+
+    /// A disclosure for an array element with a fresh salt
+    pub fn for_array_element(value: Value) -> Self {
+        Self::encode(random_salt(), None, value)
+    }
+
+    fn encode(salt: String, name: Option<String>, value: Value) -> Self {
+        let mut array = vec![Value::String(salt.clone())];
+        if let Some(ref name) = name {
+            array.push(Value::String(name.clone()));
+        }
+        array.push(value.clone());
+        let json = Value::Array(array).to_string();
+        Disclosure {
+            salt,
+            name,
+            value,
+            encoded: base64url(json.as_bytes()),
+        }
+    }
+
+    /// Parse a disclosure as received, keeping its encoding for the digest
+    pub fn from_encoded(encoded: &str) -> Result<Self, CryptoError> {
+        let json = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid disclosure base64: {}", e)))?;
+        let mut array = match serde_json::from_slice(&json) {
+            Ok(Value::Array(array)) => array,
+            _ => return Err(parse_error("A disclosure must be a JSON array")),
+        };
+        let (salt, name, value) = match array.len() {
+            2 => (array.remove(0), None, array.remove(0)),
+            3 => (array.remove(0), Some(array.remove(0)), array.remove(0)),
+            _ => return Err(parse_error("A disclosure must have two or three elements")),
+        };
+        let salt = match salt {
+            Value::String(salt) => salt,
+            _ => return Err(parse_error("The disclosure salt must be a string")),
+        };
+        let name = match name {
+            Some(Value::String(name)) => {
+                if name == SD_CLAIM || name == ARRAY_ELEMENT_KEY {
+                    return Err(parse_error("The disclosure claim name is reserved"));
+                }
+                Some(name)
+            }
+            Some(_) => return Err(parse_error("The disclosure claim name must be a string")),
+            None => None,
+        };
+        Ok(Disclosure {
+            salt,
+            name,
+            value,
+            encoded: encoded.to_string(),
+        })
+    }
+
+    /// The base64url encoding sent to the verifier
+    pub fn encoded(&self) -> &str {
+        &self.encoded
+    }
+
+    /// The digest the issuer puts in place of the claim, base64url encoded
+    pub fn digest(&self) -> String {
+        base64url(&Sha256::digest(self.encoded.as_bytes()))
+    }
+}
+
+/// Replace the claims `names` of `claims` with their digests.
+/// Returns the disclosures, in the same order as `names`
+pub fn conceal(
+    claims: &mut Map<String, Value>,
+    names: &[&str],
+) -> Result<Vec<Disclosure>, CryptoError> {
+    let mut disclosures = Vec::with_capacity(names.len());
+    for name in names {
+        let value = claims
+            .remove(*name)
+            .ok_or_else(|| CryptoError::GeneralError(format!("No claim named {}", name)))?;
+        let disclosure = Disclosure::new(name, value)?;
+        push_digest(claims, disclosure.digest())?;
+        disclosures.push(disclosure);
+    }
+    Ok(disclosures)
+}
+
+/// Replace the elements of `array` at `indices` with their digests.
+/// Returns the disclosures, in the same order as `indices`
+pub fn conceal_elements(
+    array: &mut Vec<Value>,
+    indices: &[usize],
+) -> Result<Vec<Disclosure>, CryptoError> {
+    let mut disclosures = Vec::with_capacity(indices.len());
+    for index in indices {
+        let element = array
+            .get_mut(*index)
+            .ok_or_else(|| CryptoError::GeneralError(format!("No array element {}", index)))?;
+        let disclosure = Disclosure::for_array_element(element.take());
+        let mut placeholder = Map::new();
+        placeholder.insert(
+            ARRAY_ELEMENT_KEY.to_string(),
+            Value::String(disclosure.digest()),
+        );
+        *element = Value::Object(placeholder);
+        disclosures.push(disclosure);
+    }
+    Ok(disclosures)
+}
+
+/// Add `count` random digests to the `_sd` array of `claims` that no disclosure matches
+pub fn add_decoys(claims: &mut Map<String, Value>, count: usize) -> Result<(), CryptoError> {
+    for _ in 0..count {
+        let mut random = [0u8; 32];
+        OsRng.fill_bytes(&mut random);
+        push_digest(claims, base64url(&Sha256::digest(&random)))?;
+    }
+    Ok(())
+}
+
+/// Add a digest to `_sd`, keeping it sorted so the order doesn't reveal which claim
+/// each digest is for
+fn push_digest(claims: &mut Map<String, Value>, digest: String) -> Result<(), CryptoError> {
+    let digests = claims
+        .entry(SD_CLAIM)
+        .or_insert_with(|| Value::Array(Vec::new()));
+    match digests {
+        Value::Array(digests) => {
+            digests.push(Value::String(digest));
+            digests.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+            Ok(())
+        }
+        _ => Err(CryptoError::GeneralError(format!(
+            "{} must be an array",
+            SD_CLAIM
+        ))),
+    }
+}
+
+/// An issued SD-JWT or a presentation of one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SdJwt {
+    /// The compact JWS over the payload with the digests
+    pub jws: String,
+    pub disclosures: Vec<Disclosure>,
+}
+
+impl SdJwt {
+    /// Sign `payload`, whose concealed claims have been replaced with the digests of
+    /// `disclosures`, with the issuer's Ed25519 key
+    pub fn issue(
+        mut payload: Map<String, Value>,
+        disclosures: Vec<Disclosure>,
+        issuer_key: &PrivateKey,
+    ) -> Result<Self, CryptoError> {
+        payload.insert(SD_ALG_CLAIM.to_string(), Value::String(SD_ALG.to_string()));
+        let mut jws = base64url(JWS_HEADER.as_bytes());
+        jws.push('.');
+        jws.push_str(&base64url(Value::Object(payload).to_string().as_bytes()));
+        let signature = Ed25519Sha512::new().sign(jws.as_bytes(), issuer_key)?;
+        jws.push('.');
+        jws.push_str(&base64url(&signature));
+        Ok(SdJwt { jws, disclosures })
+    }
+
+    /// Parse the `~` separated serialization
+    pub fn parse(serialized: &str) -> Result<Self, CryptoError> {
+        if !serialized.ends_with('~') {
+            return Err(parse_error(
+                "An SD-JWT must end with ~, key binding JWTs aren't supported",
+            ));
+        }
+        let mut parts = serialized[..serialized.len() - 1].split('~');
+        let jws = parts.next().unwrap_or("").to_string();
+        let disclosures = parts
+            .map(Disclosure::from_encoded)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SdJwt { jws, disclosures })
+    }
+
+    /// A presentation revealing only the disclosures of the claims `names`.
+    /// Disclosures of array elements are kept if `keep_elements` is true
+    pub fn present(&self, names: &[&str], keep_elements: bool) -> Self {
+        SdJwt {
+            jws: self.jws.clone(),
+            disclosures: self
+                .disclosures
+                .iter()
+                .filter(|d| match d.name {
+                    Some(ref name) => names.contains(&name.as_str()),
+                    None => keep_elements,
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Check the issuer's signature and return the payload with the disclosed claims
+    /// put back and the digests removed. Fails if a disclosure doesn't match a digest
+    /// exactly once or would overwrite a claim
+    pub fn verify(&self, issuer_key: &PublicKey) -> Result<Map<String, Value>, CryptoError> {
+        let parts = self.jws.split('.').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            return Err(parse_error("The JWS must have three parts"));
+        }
+        let header: Value = serde_json::from_slice(&decode(parts[0])?)
+            .map_err(|e| CryptoError::ParseError(format!("Invalid JWS header: {}", e)))?;
+        if header.get("alg").and_then(Value::as_str) != Some("EdDSA") {
+            return Err(parse_error("The JWS must be signed with EdDSA"));
+        }
+        let signature = decode(parts[2])?;
+        let signed = &self.jws[..parts[0].len() + 1 + parts[1].len()];
+        if !Ed25519Sha512::new()
+            .verify(signed.as_bytes(), &signature, issuer_key)
+            .unwrap_or(false)
+        {
+            return Err(CryptoError::GeneralError(
+                "The SD-JWT signature is not valid".to_string(),
+            ));
+        }
+        let mut payload = match serde_json::from_slice(&decode(parts[1])?) {
+            Ok(Value::Object(payload)) => payload,
+            _ => return Err(parse_error("The JWS payload must be a JSON object")),
+        };
+        match payload.remove(SD_ALG_CLAIM) {
+            None => {}
+            Some(Value::String(ref alg)) if alg == SD_ALG => {}
+            Some(_) => return Err(parse_error("Only sha-256 digests are supported")),
+        }
+
+        let mut disclosures = BTreeMap::new();
+        for disclosure in &self.disclosures {
+            if disclosures
+                .insert(disclosure.digest(), Some(disclosure))
+                .is_some()
+            {
+                return Err(parse_error("A disclosure is repeated"));
+            }
+        }
+        let mut seen = Vec::new();
+        disclose_object(&mut payload, &mut disclosures, &mut seen)?;
+        if disclosures.values().any(Option::is_some) {
+            return Err(CryptoError::GeneralError(
+                "A disclosure doesn't match any digest".to_string(),
+            ));
+        }
+        Ok(payload)
+    }
+}
+
+impl fmt::Display for SdJwt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.jws)?;
+        f.write_str("~")?;
+        for disclosure in &self.disclosures {
+            f.write_str(disclosure.encoded())?;
+            f.write_str("~")?;
+        }
+        Ok(())
+    }
+}
+
+/// The disclosures not used yet by digest, each taken when its digest is found
+type Pending<'a> = BTreeMap<String, Option<&'a Disclosure>>;
+
+fn disclose_object(
+    object: &mut Map<String, Value>,
+    disclosures: &mut Pending,
+    seen: &mut Vec<String>,
+) -> Result<(), CryptoError> {
+    let digests = match object.remove(SD_CLAIM) {
+        None => Vec::new(),
+        Some(Value::Array(digests)) => digests,
+        Some(_) => return Err(parse_error("_sd must be an array")),
+    };
+    for value in object.values_mut() {
+        disclose_value(value, disclosures, seen)?;
+    }
+    for digest in digests {
+        let digest = check_digest(digest, seen)?;
+        if let Some(disclosure) = disclosures.get_mut(&digest).and_then(Option::take) {
+            let name = match disclosure.name {
+                Some(ref name) => name,
+                None => return Err(parse_error("An array element disclosure is in _sd")),
+            };
+            if object.contains_key(name) {
+                return Err(parse_error("A disclosed claim is already in the payload"));
+            }
+            let mut value = disclosure.value.clone();
+            disclose_value(&mut value, disclosures, seen)?;
+            object.insert(name.clone(), value);
+        }
+    }
+    Ok(())
+}
+
+fn disclose_value(
+    value: &mut Value,
+    disclosures: &mut Pending,
+    seen: &mut Vec<String>,
+) -> Result<(), CryptoError> {
+    match value {
+        Value::Object(object) => disclose_object(object, disclosures, seen),
+        Value::Array(array) => {
+            let mut disclosed = Vec::with_capacity(array.len());
+            for mut element in array.drain(..) {
+                let digest = match element {
+                    Value::Object(ref object)
+                        if object.len() == 1 && object.contains_key(ARRAY_ELEMENT_KEY) =>
+                    {
+                        Some(check_digest(object[ARRAY_ELEMENT_KEY].clone(), seen)?)
+                    }
+                    _ => None,
+                };
+                match digest {
+                    Some(digest) => {
+                        if let Some(disclosure) =
+                            disclosures.get_mut(&digest).and_then(Option::take)
+                        {
+                            if disclosure.name.is_some() {
+                                return Err(parse_error(
+                                    "A claim disclosure is in place of an array element",
+                                ));
+                            }
+                            let mut value = disclosure.value.clone();
+                            disclose_value(&mut value, disclosures, seen)?;
+                            disclosed.push(value);
+                        }
+                    }
+                    None => {
+                        disclose_value(&mut element, disclosures, seen)?;
+                        disclosed.push(element);
+                    }
+                }
+            }
+            *array = disclosed;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Every digest must be a string and appear once in the whole payload
+fn check_digest(digest: Value, seen: &mut Vec<String>) -> Result<String, CryptoError> {
+    let digest = match digest {
+        Value::String(digest) => digest,
+        _ => return Err(parse_error("A digest must be a string")),
+    };
+    if seen.contains(&digest) {
+        return Err(parse_error("A digest is repeated"));
+    }
+    seen.push(digest.clone());
+    Ok(digest)
+}
+
+fn random_salt() -> String {
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    base64url(&salt)
+}
+
+fn base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, CryptoError> {
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| CryptoError::ParseError(format!("Invalid JWS base64: {}", e)))
+}
+
+fn parse_error(msg: &str) -> CryptoError {
+    CryptoError::ParseError(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn issue() -> (SdJwt, PublicKey) {
+        let (pk, sk) = Ed25519Sha512::new().keypair(None).unwrap();
+        let mut claims = match json!({
+            "iss": "https://issuer.example",
+            "given_name": "Erika",
+            "family_name": "Mustermann",
+            "address": {"locality": "Berlin", "country": "DE"},
+            "nationalities": ["DE", "FR"],
+        }) {
+            Value::Object(claims) => claims,
+            _ => unreachable!(),
+        };
+        let mut disclosures = match claims.get_mut("nationalities") {
+            Some(Value::Array(array)) => conceal_elements(array, &[1]).unwrap(),
+            _ => unreachable!(),
+        };
+        match claims.get_mut("address") {
+            Some(Value::Object(address)) => {
+                disclosures.extend(conceal(address, &["locality"]).unwrap())
+            }
+            _ => unreachable!(),
+        }
+        disclosures
+            .extend(conceal(&mut claims, &["given_name", "family_name", "address"]).unwrap());
+        add_decoys(&mut claims, 2).unwrap();
+        (SdJwt::issue(claims, disclosures, &sk).unwrap(), pk)
+    }
+
+    #[test]
+    fn disclosure_test_vector() {
+        // The family_name disclosure from the examples of the SD-JWT specification
+        let disclosure = Disclosure::from_encoded(
+            "WyJfMjZiYzRMVC1hYzZxMktJNmNCVzVlcyIsICJmYW1pbHlfbmFtZSIsICJNw7ZiaXVzIl0",
+        )
+        .unwrap();
+        assert_eq!(disclosure.salt, "_26bc4LT-ac6q2KI6cBW5es");
+        assert_eq!(disclosure.name.as_deref(), Some("family_name"));
+        assert_eq!(disclosure.value, json!("Möbius"));
+        assert_eq!(
+            disclosure.digest(),
+            "X9yH0Ajrdm1Oij4tWso9UzzKJvPoDxwmuEcO3XAdRC0"
+        );
+
+        let element = Disclosure::for_array_element(json!("FR"));
+        let parsed = Disclosure::from_encoded(element.encoded()).unwrap();
+        assert_eq!(parsed, element);
+        assert!(Disclosure::new("_sd", json!(1)).is_err());
+        assert!(Disclosure::from_encoded("eyJhIjoxfQ").is_err());
+    }
+
+    #[test]
+    fn issue_and_present() {
+        let (issued, pk) = issue();
+        assert_eq!(issued.disclosures.len(), 5);
+
+        let all = SdJwt::parse(&issued.to_string()).unwrap();
+        assert_eq!(all, issued);
+        let claims = all.verify(&pk).unwrap();
+        assert_eq!(
+            Value::Object(claims),
+            json!({
+                "iss": "https://issuer.example",
+                "given_name": "Erika",
+                "family_name": "Mustermann",
+                "address": {"locality": "Berlin", "country": "DE"},
+                "nationalities": ["DE", "FR"],
+            })
+        );
+
+        let presented = issued.present(&["given_name", "address"], false);
+        let claims = SdJwt::parse(&presented.to_string())
+            .unwrap()
+            .verify(&pk)
+            .unwrap();
+        assert_eq!(
+            Value::Object(claims),
+            json!({
+                "iss": "https://issuer.example",
+                "given_name": "Erika",
+                "address": {"country": "DE"},
+                "nationalities": ["DE"],
+            })
+        );
+
+        let (other, _) = Ed25519Sha512::new().keypair(None).unwrap();
+        assert!(issued.verify(&other).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_disclosures() {
+        let (issued, pk) = issue();
+
+        // A disclosure the issuer didn't sign
+        let mut forged = issued.present(&["given_name"], false);
+        forged
+            .disclosures
+            .push(Disclosure::new("age_over_21", json!(true)).unwrap());
+        assert!(forged.verify(&pk).is_err());
+
+        // A disclosure sent twice
+        let mut repeated = issued.present(&["given_name"], false);
+        repeated.disclosures.push(repeated.disclosures[0].clone());
+        assert!(repeated.verify(&pk).is_err());
+
+        // A disclosure with the same salt and name but another value
+        let mut changed = issued.present(&["family_name"], false);
+        let original = changed.disclosures.remove(0);
+        changed.disclosures.push(Disclosure::encode(
+            original.salt.clone(),
+            original.name.clone(),
+            json!("Musterfrau"),
+        ));
+        assert!(changed.verify(&pk).is_err());
+
+        // Key binding JWTs and truncated serializations
+        let serialized = issued.to_string();
+        assert!(SdJwt::parse(&format!("{}eyJhbGciOiJFZERTQSJ9.e30.sig", serialized)).is_err());
+        assert!(SdJwt::parse(&serialized[..serialized.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,885 @@
+//! One-show credential tokens in the style of U-Prove (Brands, 2000) over Ristretto.
+//!
+//! The issuer signs the holder's attributes with a blind signature, so it never sees
+//! the token it issued and can't link a presentation back to the issuance. A token
+//! is shown together with its public key and signature, which makes every
+//! presentation of the same token linkable. Holders that don't want that obtain a
+//! batch of tokens and show each one once. Presentations are publicly verifiable
+//! with the issuer's parameters and reveal the chosen attributes, the others stay
+//! hidden.
+//!
+//! Issuance takes three messages:
+//!
+//! 1. The issuer calls `IssuerKey::start_issuance` and sends the `FirstMessage`.
+//! 2. The holder calls `ProverSession::new` and sends back the `SecondMessage`.
+//! 3. The issuer calls `IssuerSession::respond` and sends the `ThirdMessage`, which
+//!    the holder turns into a token with `ProverSession::finish`.
+//!
+//! Each issuer session must only be answered once. Answering two second messages
+//! from one session reveals the issuer's private key.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, MultiscalarMul, VartimeMultiscalarMul},
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use CryptoError;
+
+pub const POINT_SIZE: usize = 32;
+pub const SCALAR_SIZE: usize = 32;
+/// No single token can hold more attributes than this
+pub const MAX_ATTRIBUTES: usize = 255;
+
+const GENERATOR_DOMAIN: &[u8] = b"URSA_UPROVE_RISTRETTO_GENERATOR";
+const ATTRIBUTE_DOMAIN: &[u8] = b"URSA_UPROVE_RISTRETTO_ATTRIBUTE";
+const TOKEN_INFORMATION_DOMAIN: &[u8] = b"URSA_UPROVE_RISTRETTO_TOKEN_INFORMATION";
+const ISSUANCE_DOMAIN: &[u8] = b"URSA_UPROVE_RISTRETTO_ISSUANCE";
+const PRESENTATION_DOMAIN: &[u8] = b"URSA_UPROVE_RISTRETTO_PRESENTATION";
+
+/// Hash a value to an attribute
+pub fn attribute_from_bytes(value: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.input(ATTRIBUTE_DOMAIN);
+    hasher.input(value);
+    hash_to_scalar(hasher)
+}
+
+/// The issuer's public parameters, which is all a verifier needs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuerParameters {
+    /// Names the parameters, every generator is derived from it
+    uid: Vec<u8>,
+    /// g^y0
+    g0: RistrettoPoint,
+    /// One generator for every attribute
+    generators: Vec<RistrettoPoint>,
+    /// The generator for the token information
+    gt: RistrettoPoint,
+}
+
+/// The issuer's private key together with its parameters
+pub struct IssuerKey {
+    y0: Scalar,
+    params: IssuerParameters,
+}
+
+/// The issuer's state between the first and third message
+pub struct IssuerSession {
+    y0: Scalar,
+    w: Scalar,
+}
+
+/// The holder's state between the second and third message
+pub struct ProverSession {
+    params: IssuerParameters,
+    attributes: Vec<Scalar>,
+    token_information: Vec<u8>,
+    prover_information: Vec<u8>,
+    alpha: Scalar,
+    beta2: Scalar,
+    h: RistrettoPoint,
+    sigma_z_prime: RistrettoPoint,
+    sigma_a_prime: RistrettoPoint,
+    sigma_b_prime: RistrettoPoint,
+    sigma_c_prime: Scalar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FirstMessage {
+    /// gamma^y0
+    sigma_z: RistrettoPoint,
+    /// g^w
+    sigma_a: RistrettoPoint,
+    /// gamma^w
+    sigma_b: RistrettoPoint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecondMessage {
+    sigma_c: Scalar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThirdMessage {
+    sigma_r: Scalar,
+}
+
+/// The public part of a token, shown with every presentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// The token public key, gamma^alpha
+    h: RistrettoPoint,
+    /// Set by the issuer and always shown, such as an expiry date
+    token_information: Vec<u8>,
+    /// Set by the holder and hidden from the issuer
+    prover_information: Vec<u8>,
+    sigma_z_prime: RistrettoPoint,
+    sigma_c_prime: Scalar,
+    sigma_r_prime: Scalar,
+}
+
+/// A token with the private key and attributes that the holder keeps
+pub struct HeldToken {
+    token: Token,
+    /// 1 / alpha
+    private_key: Scalar,
+    attributes: Vec<Scalar>,
+}
+
+/// A token shown to a verifier with some of its attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Presentation {
+    token: Token,
+    disclosed: BTreeMap<usize, Scalar>,
+    challenge: Scalar,
+    /// For the private key, then every hidden attribute in order
+    responses: Vec<Scalar>,
+}
+
+impl IssuerKey {
+    /// A key for tokens with `attribute_count` attributes. `uid` names the
+    /// parameters and has to be unique among the issuer's keys.
+    pub fn generate(uid: &[u8], attribute_count: usize) -> Result<Self, CryptoError> {
+        if attribute_count == 0 || attribute_count > MAX_ATTRIBUTES {
+            return Err(CryptoError::KeyGenError(format!(
+                "Tokens need between 1 and {} attributes",
+                MAX_ATTRIBUTES
+            )));
+        }
+        let y0 = Scalar::random(&mut OsRng);
+        let params = IssuerParameters::new(uid, y0 * RISTRETTO_BASEPOINT_POINT, attribute_count);
+        Ok(IssuerKey { y0, params })
+    }
+
+    pub fn parameters(&self) -> &IssuerParameters {
+        &self.params
+    }
+
+    /// Start issuing a token on `attributes` with `token_information`
+    pub fn start_issuance(
+        &self,
+        attributes: &[Scalar],
+        token_information: &[u8],
+    ) -> Result<(IssuerSession, FirstMessage), CryptoError> {
+        let gamma = self.params.gamma(attributes, token_information)?;
+        let w = Scalar::random(&mut OsRng);
+        Ok((
+            IssuerSession { y0: self.y0, w },
+            FirstMessage {
+                sigma_z: self.y0 * gamma,
+                sigma_a: w * RISTRETTO_BASEPOINT_POINT,
+                sigma_b: w * gamma,
+            },
+        ))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.y0.as_bytes().to_vec();
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() < SCALAR_SIZE {
+            return Err(CryptoError::ParseError(
+                "Invalid issuer key length".to_string(),
+            ));
+        }
+        let y0 = canonical_scalar(&bytes[..SCALAR_SIZE])?;
+        let params = IssuerParameters::from_bytes(&bytes[SCALAR_SIZE..])?;
+        if y0 * RISTRETTO_BASEPOINT_POINT != params.g0 {
+            return Err(CryptoError::ParseError(
+                "The issuer key doesn't match its parameters".to_string(),
+            ));
+        }
+        Ok(IssuerKey { y0, params })
+    }
+}
+
+impl ::std::fmt::Debug for IssuerKey {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("IssuerKey(..)")
+    }
+}
+
+impl Drop for IssuerKey {
+    fn drop(&mut self) {
+        self.y0.zeroize();
+    }
+}
+
+impl IssuerSession {
+    /// Answer the holder's second message. Consumes the session since answering
+    /// twice reveals the private key.
+    pub fn respond(self, second: &SecondMessage) -> ThirdMessage {
+        ThirdMessage {
+            sigma_r: second.sigma_c * self.y0 + self.w,
+        }
+    }
+}
+
+impl Drop for IssuerSession {
+    fn drop(&mut self) {
+        self.y0.zeroize();
+        self.w.zeroize();
+    }
+}
+
+impl ProverSession {
+    /// Blind the issuer's first message. `prover_information` is bound to the token
+    /// without the issuer seeing it.
+    pub fn new(
+        params: &IssuerParameters,
+        attributes: &[Scalar],
+        token_information: &[u8],
+        prover_information: &[u8],
+        first: &FirstMessage,
+    ) -> Result<(Self, SecondMessage), CryptoError> {
+        let gamma = params.gamma(attributes, token_information)?;
+        let mut rng = OsRng;
+        let alpha = Scalar::random(&mut rng);
+        let mut beta1 = Scalar::random(&mut rng);
+        let beta2 = Scalar::random(&mut rng);
+        let g = RISTRETTO_BASEPOINT_POINT;
+
+        let h = alpha * gamma;
+        let sigma_z_prime = alpha * first.sigma_z;
+        let sigma_a_prime =
+            RistrettoPoint::multiscalar_mul(&[beta1, beta2], &[params.g0, g]) + first.sigma_a;
+        let sigma_b_prime = RistrettoPoint::multiscalar_mul(
+            &[beta1, beta2, alpha],
+            &[sigma_z_prime, h, first.sigma_b],
+        );
+        let sigma_c_prime = issuance_challenge(
+            &h,
+            prover_information,
+            &sigma_z_prime,
+            &sigma_a_prime,
+            &sigma_b_prime,
+        );
+        let second = SecondMessage {
+            sigma_c: sigma_c_prime + beta1,
+        };
+        beta1.zeroize();
+        Ok((
+            ProverSession {
+                params: params.clone(),
+                attributes: attributes.to_vec(),
+                token_information: token_information.to_vec(),
+                prover_information: prover_information.to_vec(),
+                alpha,
+                beta2,
+                h,
+                sigma_z_prime,
+                sigma_a_prime,
+                sigma_b_prime,
+                sigma_c_prime,
+            },
+            second,
+        ))
+    }
+
+    /// Unblind the issuer's answer and check the token's signature
+    pub fn finish(self, third: &ThirdMessage) -> Result<HeldToken, CryptoError> {
+        let token = Token {
+            h: self.h,
+            token_information: self.token_information.clone(),
+            prover_information: self.prover_information.clone(),
+            sigma_z_prime: self.sigma_z_prime,
+            sigma_c_prime: self.sigma_c_prime,
+            sigma_r_prime: third.sigma_r + self.beta2,
+        };
+        let (sigma_a_prime, sigma_b_prime) = token.commitments(&self.params);
+        if sigma_a_prime != self.sigma_a_prime || sigma_b_prime != self.sigma_b_prime {
+            return Err(CryptoError::GeneralError(
+                "Invalid issuer response".to_string(),
+            ));
+        }
+        Ok(HeldToken {
+            token,
+            private_key: self.alpha.invert(),
+            attributes: self.attributes.clone(),
+        })
+    }
+}
+
+impl Drop for ProverSession {
+    fn drop(&mut self) {
+        self.attributes.zeroize();
+        self.alpha.zeroize();
+        self.beta2.zeroize();
+    }
+}
+
+impl IssuerParameters {
+    fn new(uid: &[u8], g0: RistrettoPoint, attribute_count: usize) -> Self {
+        IssuerParameters {
+            uid: uid.to_vec(),
+            g0,
+            generators: (1..=attribute_count as u64)
+                .map(|i| generator(uid, i))
+                .collect(),
+            gt: generator(uid, 0),
+        }
+    }
+
+    pub fn uid(&self) -> &[u8] {
+        &self.uid
+    }
+
+    pub fn attribute_count(&self) -> usize {
+        self.generators.len()
+    }
+
+    /// Check a presentation of a token for `message`, which binds it to a session and
+    /// should contain a fresh nonce from the verifier. The disclosed attributes are
+    /// in `Presentation::disclosed`. Keep the token's public key to notice tokens
+    /// shown more than once.
+    pub fn verify_presentation(
+        &self,
+        presentation: &Presentation,
+        message: &[u8],
+    ) -> Result<(), CryptoError> {
+        let invalid = || CryptoError::GeneralError("Invalid presentation".to_string());
+        let token = &presentation.token;
+        if token.h.is_identity() {
+            return Err(invalid());
+        }
+        let (sigma_a_prime, sigma_b_prime) = token.commitments(self);
+        if issuance_challenge(
+            &token.h,
+            &token.prover_information,
+            &token.sigma_z_prime,
+            &sigma_a_prime,
+            &sigma_b_prime,
+        ) != token.sigma_c_prime
+        {
+            return Err(invalid());
+        }
+
+        let hidden = self.hidden_indices(&presentation.disclosed)?;
+        if presentation.responses.len() != hidden.len() + 1 {
+            return Err(invalid());
+        }
+        // (g0 gt^xt prod g_i^x_i for disclosed i)^-c h^r0 prod g_i^r_i for hidden i
+        let c = presentation.challenge;
+        let mut scalars = vec![
+            -c,
+            -c * hash_token_information(&self.uid, &token.token_information),
+        ];
+        let mut points = vec![self.g0, self.gt];
+        for (i, x) in &presentation.disclosed {
+            scalars.push(-c * x);
+            points.push(self.generators[*i]);
+        }
+        scalars.extend_from_slice(&presentation.responses);
+        points.push(token.h);
+        points.extend(hidden.iter().map(|i| self.generators[*i]));
+        let a = RistrettoPoint::vartime_multiscalar_mul(&scalars, &points);
+        if presentation_challenge(self, presentation, &a, message) == c {
+            Ok(())
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// g0 gt^xt prod g_i^x_i
+    fn gamma(
+        &self,
+        attributes: &[Scalar],
+        token_information: &[u8],
+    ) -> Result<RistrettoPoint, CryptoError> {
+        if attributes.len() != self.generators.len() {
+            return Err(CryptoError::GeneralError(format!(
+                "Expected {} attributes, found {}",
+                self.generators.len(),
+                attributes.len()
+            )));
+        }
+        let xt = hash_token_information(&self.uid, token_information);
+        Ok(self.g0
+            + RistrettoPoint::multiscalar_mul(
+                ::std::iter::once(&xt).chain(attributes.iter()),
+                ::std::iter::once(&self.gt).chain(self.generators.iter()),
+            ))
+    }
+
+    fn hidden_indices(
+        &self,
+        disclosed: &BTreeMap<usize, Scalar>,
+    ) -> Result<Vec<usize>, CryptoError> {
+        if disclosed.keys().any(|i| *i >= self.generators.len()) {
+            return Err(CryptoError::GeneralError(
+                "Disclosed attribute index is out of range".to_string(),
+            ));
+        }
+        Ok((0..self.generators.len())
+            .filter(|i| !disclosed.contains_key(i))
+            .collect())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(6 + self.uid.len() + POINT_SIZE);
+        bytes.push(self.generators.len() as u8);
+        write_bytes(&mut bytes, &self.uid);
+        bytes.extend_from_slice(self.g0.compress().as_bytes());
+        bytes
+    }
+
+    /// The generators are derived again from the uid
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(bytes);
+        let attribute_count = reader.byte()? as usize;
+        let uid = reader.bytes()?.to_vec();
+        let g0 = reader.point()?;
+        reader.end()?;
+        if attribute_count == 0 {
+            return Err(CryptoError::ParseError(
+                "Tokens need at least one attribute".to_string(),
+            ));
+        }
+        Ok(IssuerParameters::new(&uid, g0, attribute_count))
+    }
+}
+
+impl Token {
+    /// The token's public key, which verifiers store to reject tokens shown twice
+    pub fn public_key(&self) -> [u8; POINT_SIZE] {
+        self.h.compress().to_bytes()
+    }
+
+    pub fn token_information(&self) -> &[u8] {
+        &self.token_information
+    }
+
+    pub fn prover_information(&self) -> &[u8] {
+        &self.prover_information
+    }
+
+    /// sigma_a' and sigma_b' recomputed from the signature
+    fn commitments(&self, params: &IssuerParameters) -> (RistrettoPoint, RistrettoPoint) {
+        let r = self.sigma_r_prime;
+        let c = -self.sigma_c_prime;
+        (
+            RistrettoPoint::vartime_multiscalar_mul(
+                &[r, c],
+                &[RISTRETTO_BASEPOINT_POINT, params.g0],
+            ),
+            RistrettoPoint::vartime_multiscalar_mul(&[r, c], &[self.h, self.sigma_z_prime]),
+        )
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.h.compress().as_bytes());
+        write_bytes(&mut bytes, &self.token_information);
+        write_bytes(&mut bytes, &self.prover_information);
+        bytes.extend_from_slice(self.sigma_z_prime.compress().as_bytes());
+        bytes.extend_from_slice(self.sigma_c_prime.as_bytes());
+        bytes.extend_from_slice(self.sigma_r_prime.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(bytes);
+        let token = Token::read(&mut reader)?;
+        reader.end()?;
+        Ok(token)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, CryptoError> {
+        Ok(Token {
+            h: reader.point()?,
+            token_information: reader.bytes()?.to_vec(),
+            prover_information: reader.bytes()?.to_vec(),
+            sigma_z_prime: reader.point()?,
+            sigma_c_prime: reader.scalar()?,
+            sigma_r_prime: reader.scalar()?,
+        })
+    }
+}
+
+impl HeldToken {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn attributes(&self) -> &[Scalar] {
+        &self.attributes
+    }
+
+    /// Show the token with the attributes at `disclosed` for `message`. Each token
+    /// should only be shown once, presentations of one token are linkable.
+    pub fn present(
+        &self,
+        params: &IssuerParameters,
+        disclosed: &BTreeSet<usize>,
+        message: &[u8],
+    ) -> Result<Presentation, CryptoError> {
+        if params.attribute_count() != self.attributes.len() {
+            return Err(CryptoError::GeneralError(
+                "The parameters don't match the token".to_string(),
+            ));
+        }
+        let disclosed: BTreeMap<usize, Scalar> = disclosed
+            .iter()
+            .map(|i| self.attributes.get(*i).map(|x| (*i, *x)))
+            .collect::<Option<_>>()
+            .ok_or_else(|| {
+                CryptoError::GeneralError("Disclosed attribute index is out of range".to_string())
+            })?;
+        let hidden = params.hidden_indices(&disclosed)?;
+
+        let mut rng = OsRng;
+        let mut blindings: Vec<Scalar> = (0..=hidden.len())
+            .map(|_| Scalar::random(&mut rng))
+            .collect();
+        let mut points = vec![self.token.h];
+        points.extend(hidden.iter().map(|i| params.generators[*i]));
+        let a = RistrettoPoint::multiscalar_mul(&blindings, &points);
+
+        let mut presentation = Presentation {
+            token: self.token.clone(),
+            disclosed,
+            challenge: Scalar::zero(),
+            responses: Vec::new(),
+        };
+        let c = presentation_challenge(params, &presentation, &a, message);
+        let mut responses = vec![c * self.private_key + blindings[0]];
+        responses.extend(
+            hidden
+                .iter()
+                .zip(blindings[1..].iter())
+                .map(|(i, w)| w - c * self.attributes[*i]),
+        );
+        blindings.zeroize();
+        presentation.challenge = c;
+        presentation.responses = responses;
+        Ok(presentation)
+    }
+}
+
+impl Drop for HeldToken {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+        self.attributes.zeroize();
+    }
+}
+
+impl Presentation {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn disclosed(&self) -> &BTreeMap<usize, Scalar> {
+        &self.disclosed
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.token.to_bytes();
+        bytes.push(self.disclosed.len() as u8);
+        for (i, x) in &self.disclosed {
+            bytes.push(*i as u8);
+            bytes.extend_from_slice(x.as_bytes());
+        }
+        bytes.extend_from_slice(self.challenge.as_bytes());
+        for r in &self.responses {
+            bytes.extend_from_slice(r.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(bytes);
+        let token = Token::read(&mut reader)?;
+        let mut disclosed = BTreeMap::new();
+        for _ in 0..reader.byte()? {
+            let i = reader.byte()? as usize;
+            disclosed.insert(i, reader.scalar()?);
+        }
+        let challenge = reader.scalar()?;
+        // The responses take up the rest
+        let mut responses = Vec::new();
+        while !reader.0.is_empty() {
+            responses.push(reader.scalar()?);
+        }
+        Ok(Presentation {
+            token,
+            disclosed,
+            challenge,
+            responses,
+        })
+    }
+}
+
+macro_rules! message_bytes {
+    ($name:ident, $field:ident, $kind:ident) => {
+        impl $name {
+            pub fn to_bytes(&self) -> Vec<u8> {
+                self.$field.as_bytes().to_vec()
+            }
+
+            pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+                let mut reader = Reader(bytes);
+                let $field = reader.$kind()?;
+                reader.end()?;
+                Ok($name { $field })
+            }
+        }
+    };
+}
+
+message_bytes!(SecondMessage, sigma_c, scalar);
+message_bytes!(ThirdMessage, sigma_r, scalar);
+
+impl FirstMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 * POINT_SIZE);
+        bytes.extend_from_slice(self.sigma_z.compress().as_bytes());
+        bytes.extend_from_slice(self.sigma_a.compress().as_bytes());
+        bytes.extend_from_slice(self.sigma_b.compress().as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = Reader(bytes);
+        let message = FirstMessage {
+            sigma_z: reader.point()?,
+            sigma_a: reader.point()?,
+            sigma_b: reader.point()?,
+        };
+        reader.end()?;
+        Ok(message)
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CryptoError> {
+        if self.0.len() < n {
+            return Err(CryptoError::ParseError(
+                "Unexpected end of input".to_string(),
+            ));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, CryptoError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], CryptoError> {
+        let length = u32::from_be_bytes(*array_ref!(self.take(4)?, 0, 4));
+        self.take(length as usize)
+    }
+
+    fn point(&mut self) -> Result<RistrettoPoint, CryptoError> {
+        CompressedRistretto::from_slice(self.take(POINT_SIZE)?)
+            .decompress()
+            .ok_or_else(|| CryptoError::ParseError("Invalid Ristretto point".to_string()))
+    }
+
+    fn scalar(&mut self) -> Result<Scalar, CryptoError> {
+        canonical_scalar(self.take(SCALAR_SIZE)?)
+    }
+
+    fn end(&self) -> Result<(), CryptoError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(CryptoError::ParseError(
+                "Unexpected trailing bytes".to_string(),
+            ))
+        }
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// The generator at `index` of the parameters named `uid`, 0 is for the token
+/// information
+fn generator(uid: &[u8], index: u64) -> RistrettoPoint {
+    let mut hasher = Sha512::new();
+    hasher.input(GENERATOR_DOMAIN);
+    hasher.input((uid.len() as u64).to_be_bytes());
+    hasher.input(uid);
+    hasher.input(index.to_be_bytes());
+    let hash = hasher.result();
+    RistrettoPoint::from_uniform_bytes(array_ref!(hash.as_slice(), 0, 64))
+}
+
+fn hash_token_information(uid: &[u8], token_information: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.input(TOKEN_INFORMATION_DOMAIN);
+    hasher.input((uid.len() as u64).to_be_bytes());
+    hasher.input(uid);
+    hasher.input(token_information);
+    hash_to_scalar(hasher)
+}
+
+fn issuance_challenge(
+    h: &RistrettoPoint,
+    prover_information: &[u8],
+    sigma_z_prime: &RistrettoPoint,
+    sigma_a_prime: &RistrettoPoint,
+    sigma_b_prime: &RistrettoPoint,
+) -> Scalar {
+    let mut transcript = Sha512::new();
+    transcript.input(ISSUANCE_DOMAIN);
+    transcript.input(h.compress().as_bytes());
+    transcript.input((prover_information.len() as u64).to_be_bytes());
+    transcript.input(prover_information);
+    transcript.input(sigma_z_prime.compress().as_bytes());
+    transcript.input(sigma_a_prime.compress().as_bytes());
+    transcript.input(sigma_b_prime.compress().as_bytes());
+    hash_to_scalar(transcript)
+}
+
+fn presentation_challenge(
+    params: &IssuerParameters,
+    presentation: &Presentation,
+    a: &RistrettoPoint,
+    message: &[u8],
+) -> Scalar {
+    let mut transcript = Sha512::new();
+    transcript.input(PRESENTATION_DOMAIN);
+    transcript.input(params.to_bytes());
+    transcript.input(presentation.token.to_bytes());
+    for (i, x) in &presentation.disclosed {
+        transcript.input((*i as u64).to_be_bytes());
+        transcript.input(x.as_bytes());
+    }
+    transcript.input(a.compress().as_bytes());
+    transcript.input((message.len() as u64).to_be_bytes());
+    transcript.input(message);
+    hash_to_scalar(transcript)
+}
+
+fn hash_to_scalar(hasher: Sha512) -> Scalar {
+    let hash = hasher.result();
+    Scalar::from_bytes_mod_order_wide(array_ref!(hash.as_slice(), 0, 64))
+}
+
+fn canonical_scalar(bytes: &[u8]) -> Result<Scalar, CryptoError> {
+    Scalar::from_canonical_bytes(*array_ref!(bytes, 0, SCALAR_SIZE))
+        .ok_or_else(|| CryptoError::ParseError("Invalid scalar".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attributes() -> Vec<Scalar> {
+        vec![
+            attribute_from_bytes(b"alice"),
+            attribute_from_bytes(b"NL"),
+            attribute_from_bytes(b"1990-01-01"),
+        ]
+    }
+
+    fn issue(key: &IssuerKey, token_information: &[u8]) -> HeldToken {
+        let (issuer, first) = key
+            .start_issuance(&attributes(), token_information)
+            .unwrap();
+        let first = FirstMessage::from_bytes(&first.to_bytes()).unwrap();
+        let (prover, second) = ProverSession::new(
+            key.parameters(),
+            &attributes(),
+            token_information,
+            b"device 7",
+            &first,
+        )
+        .unwrap();
+        let second = SecondMessage::from_bytes(&second.to_bytes()).unwrap();
+        let third = ThirdMessage::from_bytes(&issuer.respond(&second).to_bytes()).unwrap();
+        prover.finish(&third).unwrap()
+    }
+
+    #[test]
+    fn issue_and_present() {
+        let key = IssuerKey::generate(b"issuer/2024", 3).unwrap();
+        let params = key.parameters();
+        let token = issue(&key, b"expires 2030");
+
+        let disclosed: BTreeSet<usize> = [1].iter().copied().collect();
+        let p = token.present(params, &disclosed, b"nonce").unwrap();
+        assert!(params.verify_presentation(&p, b"nonce").is_ok());
+        assert_eq!(p.disclosed()[&1], attributes()[1]);
+        assert_eq!(p.token().token_information(), b"expires 2030");
+        assert!(params.verify_presentation(&p, b"other").is_err());
+
+        let all: BTreeSet<usize> = (0..3).collect();
+        let p = token.present(params, &all, b"").unwrap();
+        assert!(params.verify_presentation(&p, b"").is_ok());
+        let p = token.present(params, &BTreeSet::new(), b"").unwrap();
+        assert!(params.verify_presentation(&p, b"").is_ok());
+
+        // The issuer never sees the token
+        let other = issue(&key, b"expires 2030");
+        assert_ne!(other.token().public_key(), token.token().public_key());
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let key = IssuerKey::generate(b"issuer", 3).unwrap();
+        let params = key.parameters();
+        let token = issue(&key, b"ti");
+        let disclosed: BTreeSet<usize> = [0].iter().copied().collect();
+
+        let mut p = token.present(params, &disclosed, b"m").unwrap();
+        p.disclosed.insert(0, attribute_from_bytes(b"mallory"));
+        assert!(params.verify_presentation(&p, b"m").is_err());
+
+        let mut p = token.present(params, &disclosed, b"m").unwrap();
+        p.token.token_information = b"never expires".to_vec();
+        assert!(params.verify_presentation(&p, b"m").is_err());
+
+        let other = IssuerKey::generate(b"issuer", 3).unwrap();
+        let p = token.present(params, &disclosed, b"m").unwrap();
+        assert!(other.parameters().verify_presentation(&p, b"m").is_err());
+
+        // A wrong answer from the issuer is noticed at issuance
+        let (_, first) = key.start_issuance(&attributes(), b"ti").unwrap();
+        let (prover, _) = ProverSession::new(params, &attributes(), b"ti", b"", &first).unwrap();
+        assert!(prover
+            .finish(&ThirdMessage {
+                sigma_r: Scalar::one()
+            })
+            .is_err());
+        assert!(key.start_issuance(&attributes()[..2], b"ti").is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let key =
+            IssuerKey::from_bytes(&IssuerKey::generate(b"uid", 3).unwrap().to_bytes()).unwrap();
+        let params = IssuerParameters::from_bytes(&key.parameters().to_bytes()).unwrap();
+        assert_eq!(&params, key.parameters());
+        let token = issue(&key, b"ti");
+        assert_eq!(
+            Token::from_bytes(&token.token().to_bytes()).unwrap(),
+            *token.token()
+        );
+
+        let disclosed: BTreeSet<usize> = [2].iter().copied().collect();
+        let p = token.present(&params, &disclosed, b"m").unwrap();
+        let bytes = p.to_bytes();
+        let q = Presentation::from_bytes(&bytes).unwrap();
+        assert_eq!(p, q);
+        assert!(params.verify_presentation(&q, b"m").is_ok());
+        assert!(Presentation::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -60,6 +60,7 @@ macro_rules! check_useful_opt_c_byte_array {
 #[cfg(any(
     feature = "cl",
     feature = "cl_native",
+    feature = "cl_gmp",
     feature = "bls_bn254",
     feature = "bls_bn254_asm"
 ))]
@@ -74,7 +75,7 @@ macro_rules! check_useful_c_reference {
     };
 }
 
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 macro_rules! check_useful_mut_c_reference {
     ($ptr:ident, $type:ty, $err:expr) => {
         if $ptr.is_null() {
@@ -86,7 +87,7 @@ macro_rules! check_useful_mut_c_reference {
     };
 }
 
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 macro_rules! check_useful_opt_c_reference {
     ($ptr:ident, $type:ty) => {
         let $ptr: Option<&$type> = if $ptr.is_null() {
@@ -120,7 +121,7 @@ macro_rules! check_useful_c_reference_array {
     };
 }
 
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 macro_rules! check_useful_hashset {
     ($ptr:ident, $len:expr, $err1:expr, $err2:expr) => {
         if $ptr.is_null() {
@@ -135,6 +136,7 @@ macro_rules! check_useful_hashset {
 #[cfg(any(
     feature = "cl",
     feature = "cl_native",
+    feature = "cl_gmp",
     feature = "bls_bn254",
     feature = "bls_bn254_asm"
 ))]
@@ -147,7 +149,7 @@ macro_rules! check_useful_c_ptr {
     };
 }
 
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 macro_rules! check_useful_c_str {
     ($x:ident, $e:expr) => {
         let $x = match c_str_to_string($x) {
@@ -29,9 +29,12 @@ macro_rules! array_copy {
     feature = "ecdsa_secp256k1_asm",
     feature = "x25519",
     feature = "x25519_asm",
-    feature = "wasm"
+    feature = "wasm",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
 ))]
-macro_rules! impl_bytearray {
+macro_rules! impl_bytearray_common {
     ($thing:ident) => {
         impl $thing {
             #[inline]
@@ -58,15 +61,6 @@ macro_rules! impl_bytearray {
             }
         }
 
-        impl PartialEq for $thing {
-            #[inline]
-            fn eq(&self, other: &$thing) -> bool {
-                self.0 == other.0
-            }
-        }
-
-        impl Eq for $thing {}
-
         impl Clone for $thing {
             #[inline]
             fn clone(&self) -> $thing {
@@ -118,28 +112,6 @@ macro_rules! impl_bytearray {
                 self.0.as_slice()
             }
         }
-        impl ::std::fmt::Display for $thing {
-            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-                write!(
-                    formatter,
-                    "{} {{ {} }}",
-                    stringify!($thing),
-                    hex::encode(&self.0[..])
-                )
-            }
-        }
-
-        impl ::std::fmt::Debug for $thing {
-            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-                write!(
-                    formatter,
-                    "{} {{ {} }}",
-                    stringify!($thing),
-                    hex::encode(&self.0[..])
-                )
-            }
-        }
-
         impl Zeroize for $thing {
             #[inline]
             fn zeroize(&mut self) {
@@ -198,6 +170,107 @@ macro_rules! impl_bytearray {
     };
 }
 
+#[cfg(any(
+    feature = "bls_bls12381",
+    feature = "ed25519",
+    feature = "ed25519_asm",
+    feature = "ecdh_secp256k1",
+    feature = "ecdh_secp256k1_native",
+    feature = "ecdh_secp256k1_asm",
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm",
+    feature = "x25519",
+    feature = "x25519_asm",
+    feature = "wasm",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
+))]
+macro_rules! impl_bytearray {
+    ($thing:ident) => {
+        impl_bytearray_common!($thing);
+
+        impl PartialEq for $thing {
+            #[inline]
+            fn eq(&self, other: &$thing) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for $thing {}
+
+        impl ::std::fmt::Display for $thing {
+            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                write!(
+                    formatter,
+                    "{} {{ {} }}",
+                    stringify!($thing),
+                    hex::encode(&self.0[..])
+                )
+            }
+        }
+
+        impl ::std::fmt::Debug for $thing {
+            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                write!(
+                    formatter,
+                    "{} {{ {} }}",
+                    stringify!($thing),
+                    hex::encode(&self.0[..])
+                )
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "bls_bls12381",
+    feature = "ed25519",
+    feature = "ed25519_asm",
+    feature = "ecdh_secp256k1",
+    feature = "ecdh_secp256k1_native",
+    feature = "ecdh_secp256k1_asm",
+    feature = "ecdsa_secp256k1",
+    feature = "ecdsa_secp256k1_native",
+    feature = "ecdsa_secp256k1_asm",
+    feature = "x25519",
+    feature = "x25519_asm",
+    feature = "wasm",
+    feature = "aws_kms",
+    feature = "gcp_kms",
+    feature = "kmip"
+))]
+/// Like `impl_bytearray` for secret material: `Debug` doesn't show the contents,
+/// there is no `Display` and equality is checked in constant time
+macro_rules! impl_secret_bytearray {
+    ($thing:ident) => {
+        impl_bytearray_common!($thing);
+
+        impl $thing {
+            /// Returns the secret bytes
+            pub fn expose_secret(&self) -> &[u8] {
+                self.0.as_slice()
+            }
+        }
+
+        impl PartialEq for $thing {
+            #[inline]
+            fn eq(&self, other: &$thing) -> bool {
+                ::keys::ct_eq(&self.0, &other.0)
+            }
+        }
+
+        impl Eq for $thing {}
+
+        impl ::std::fmt::Debug for $thing {
+            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                write!(formatter, "{} {{ .. }}", stringify!($thing))
+            }
+        }
+    };
+}
+
 #[cfg(debug_assertions)]
 #[macro_export]
 macro_rules! secret {
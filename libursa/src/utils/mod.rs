@@ -6,5 +6,5 @@ pub mod macros;
 #[cfg(feature = "logger")]
 #[macro_use]
 pub mod logger;
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 pub mod commitment;
@@ -26,7 +26,7 @@ impl Ed25519Sha512 {
 
     pub fn keyPairFromSeed(&self, seed: &[u8]) -> Result<KeyPair, JsValue> {
         let scheme = Ed25519Sha512Impl {};
-        let (pk, sk) = maperr!(scheme.keypair(Some(KeyGenOption::UseSeed(seed.to_vec()))));
+        let (pk, sk) = maperr!(scheme.keypair(Some(KeyGenOption::UseSeed(seed.into()))));
         Ok(KeyPair {
             pk: pk.into(),
             sk: sk.into(),
@@ -27,7 +27,7 @@ impl EcdsaSecp256k1Sha256 {
     }
 
     pub fn keyPairFromSeed(&self, seed: &[u8]) -> Result<KeyPair, JsValue> {
-        let (pk, sk) = maperr!(self.0.keypair(Some(KeyGenOption::UseSeed(seed.to_vec()))));
+        let (pk, sk) = maperr!(self.0.keypair(Some(KeyGenOption::UseSeed(seed.into()))));
         let pk = WasmPublicKey::from(&pk);
         let sk = WasmPrivateKey::from(&sk);
         Ok(KeyPair { pk, sk })
@@ -97,7 +97,7 @@ impl EcdhSecp256k1Sha256 {
     }
 
     pub fn keyPairFromSeed(&self, seed: &[u8]) -> Result<KeyPair, JsValue> {
-        let (pk, sk) = maperr!(self.0.keypair(Some(KeyGenOption::UseSeed(seed.to_vec()))));
+        let (pk, sk) = maperr!(self.0.keypair(Some(KeyGenOption::UseSeed(seed.into()))));
         let pk = WasmPublicKey::from(&pk);
         let sk = WasmPrivateKey::from(&sk);
         Ok(KeyPair { pk, sk })
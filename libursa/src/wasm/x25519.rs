@@ -16,7 +16,7 @@ impl X25519Sha256 {
     }
 
     pub fn keypair(&self) -> Result<KeyPair, JsValue> {
-        let scheme = X25519Sha256Impl {};
+        let scheme = X25519Sha256Impl::new();
         let (pk, sk) = maperr!(scheme.keypair(None));
         Ok(KeyPair {
             pk: pk.into(),
@@ -25,8 +25,8 @@ impl X25519Sha256 {
     }
 
     pub fn keyPairFromSeed(&self, seed: &[u8]) -> Result<KeyPair, JsValue> {
-        let scheme = X25519Sha256Impl {};
-        let (pk, sk) = maperr!(scheme.keypair(Some(KeyGenOption::UseSeed(seed.to_vec()))));
+        let scheme = X25519Sha256Impl::new();
+        let (pk, sk) = maperr!(scheme.keypair(Some(KeyGenOption::UseSeed(seed.into()))));
         Ok(KeyPair {
             pk: pk.into(),
             sk: sk.into(),
@@ -35,7 +35,7 @@ impl X25519Sha256 {
 
     pub fn getPublicKey(&self, sk: &WasmPrivateKey) -> Result<WasmPublicKey, JsValue> {
         let sk = sk.into();
-        let scheme = X25519Sha256Impl {};
+        let scheme = X25519Sha256Impl::new();
         let (pk, _) = maperr!(scheme.keypair(Some(KeyGenOption::FromSecretKey(sk))));
         Ok(pk.into())
     }
@@ -47,7 +47,7 @@ impl X25519Sha256 {
     ) -> Result<WasmSessionKey, JsValue> {
         let sk = PrivateKey::from(sk);
         let pk = PublicKey::from(pk);
-        let scheme = X25519Sha256Impl {};
+        let scheme = X25519Sha256Impl::new();
         let secret = maperr!(scheme.compute_shared_secret(&sk, &pk));
         let secret = WasmSessionKey::from(secret);
         Ok(secret)
@@ -1,9 +1,9 @@
 #[cfg(feature = "serde")]
 extern crate serde;
-#[cfg(any(feature = "cl_native"))]
+#[cfg(any(feature = "cl_native", feature = "cl_gmp"))]
 extern crate ursa;
 
-#[cfg(any(feature = "cl_native"))]
+#[cfg(any(feature = "cl_native", feature = "cl_gmp"))]
 mod cl_tests {
     use serde::{Deserialize, Serialize};
     use std::collections::{BTreeSet, HashSet};
@@ -1,7 +1,7 @@
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 extern crate ursa;
 
-#[cfg(any(feature = "cl", feature = "cl_native"))]
+#[cfg(any(feature = "cl", feature = "cl_native", feature = "cl_gmp"))]
 mod cl_bench_tests {
     use ursa::cl::issuer::Issuer;
     use ursa::cl::prover::Prover;
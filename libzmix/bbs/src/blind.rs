@@ -0,0 +1,404 @@
+//! Blind signature requests whose committed messages are proven equal to hidden
+//! messages of a signature the holder already has.
+//!
+//! A holder asking for a credential over their link secret usually also has to show
+//! it is the link secret of an earlier credential, otherwise they could commit to any
+//! value. The holder hides the link secret in a signature proof of the earlier credential
+//! with `HiddenMessage::ExternalBlinding` and starts a `PoKOfCommittedMessages` with the same
+//! blinding for it, so both proofs answer one challenge and give equal responses for the
+//! link secret. The issuer checks both with `BlindSignatureContext::verify_linked`, or
+//! signs right away with `Issuer::blind_sign_linked`.
+//!
+//! The result is an ordinary `BlindSignatureContext` that is unblinded with
+//! `Prover::complete_signature`, but it is only valid together with the signature proof.
+
+use crate::errors::prelude::*;
+use crate::keys::prelude::*;
+use crate::pok_sig::prelude::*;
+use crate::pok_vc::prelude::*;
+use crate::signature::prelude::*;
+use crate::{
+    BlindSignatureContext, Commitment, CommitmentBuilder, GeneratorG1, HashElem, ProofChallenge,
+    ProofNonce, ProofRequest, SignatureBlinding, SignatureMessage, SignatureProof,
+};
+use pairing_plus::serdes::SerDes;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Convenience module
+pub mod prelude {
+    pub use super::PoKOfCommittedMessages;
+}
+
+/// The holder's side of a blind signature request that shares blindings with other proofs
+#[derive(Clone, Debug)]
+pub struct PoKOfCommittedMessages {
+    commitment: Commitment,
+    committed: ProverCommittedG1,
+    secrets: Vec<SignatureMessage>,
+    blinding_factor: SignatureBlinding,
+}
+
+impl PoKOfCommittedMessages {
+    /// Commit to the hidden `messages` by their index in `verkey`. The messages in
+    /// `blindings` use the given blinding, which must be the one of the same message
+    /// in the signature proof they are linked to.
+    pub fn init(
+        verkey: &PublicKey,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        blindings: &BTreeMap<usize, ProofNonce>,
+    ) -> Result<Self, BBSError> {
+        if let Some(i) = messages
+            .keys()
+            .chain(blindings.keys())
+            .find(|i| **i >= verkey.message_count())
+        {
+            return Err(BBSErrorKind::PublicKeyGeneratorMessageCountMismatch(
+                *i,
+                verkey.message_count(),
+            )
+            .into());
+        }
+        if let Some(i) = blindings.keys().find(|i| !messages.contains_key(i)) {
+            return Err(BBSErrorKind::GeneralError {
+                msg: format!("Message {} has a blinding but isn't committed", i),
+            }
+            .into());
+        }
+        let blinding_factor = Signature::generate_blinding();
+        let mut builder = CommitmentBuilder::new();
+        builder.add(verkey.h0, blinding_factor);
+
+        let mut committing = ProverCommittingG1::new();
+        committing.commit(verkey.h0);
+        let mut secrets = vec![SignatureMessage(blinding_factor.0)];
+        for (i, m) in messages {
+            builder.add(verkey.h[*i], m);
+            match blindings.get(i) {
+                Some(b) => committing.commit_with(verkey.h[*i], b),
+                None => committing.commit(verkey.h[*i]),
+            };
+            secrets.push(*m);
+        }
+        Ok(Self {
+            commitment: builder.finalize(),
+            committed: committing.finish(),
+            secrets,
+            blinding_factor,
+        })
+    }
+
+    /// The commitment to the hidden messages
+    pub fn commitment(&self) -> Commitment {
+        self.commitment
+    }
+
+    /// Return byte representation of public elements so they can be used for challenge computation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.committed.to_bytes();
+        bytes.extend_from_slice(&self.commitment.to_bytes_uncompressed_form()[..]);
+        bytes
+    }
+
+    /// Create the challenge for this request and `pok_sig`, like `Prover::create_challenge_hash`
+    pub fn create_challenge_hash(
+        &self,
+        pok_sig: &PoKOfSignature,
+        claims: Option<&[&[u8]]>,
+        nonce: &ProofNonce,
+    ) -> ProofChallenge {
+        let mut bytes = pok_sig.to_bytes();
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        if let Some(add_claims) = claims {
+            for c in add_claims {
+                bytes.extend_from_slice(c);
+            }
+        }
+        ProofChallenge::hash(&bytes)
+    }
+
+    /// Given the challenge value, compute the request to send to the issuer
+    /// and the blinding factor to unblind the signature with
+    pub fn gen_proof(
+        self,
+        challenge: &ProofChallenge,
+    ) -> Result<(BlindSignatureContext, SignatureBlinding), BBSError> {
+        let proof_of_hidden_messages = self.committed.gen_proof(challenge, &self.secrets)?;
+        Ok((
+            BlindSignatureContext {
+                commitment: self.commitment,
+                challenge_hash: *challenge,
+                proof_of_hidden_messages,
+            },
+            self.blinding_factor,
+        ))
+    }
+}
+
+impl BlindSignatureContext {
+    /// Check a request from `PoKOfCommittedMessages` together with `signature_proof`.
+    /// `revealed_messages` are the indices the issuer signs in `verkey`, all others are committed.
+    /// `links` maps a committed index to the index of the hidden message of the
+    /// signature proof it has to equal. Returns the revealed messages of the signature proof
+    /// like `Verifier::verify_signature_pok`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_linked(
+        &self,
+        revealed_messages: &BTreeSet<usize>,
+        verkey: &PublicKey,
+        proof_request: &ProofRequest,
+        signature_proof: &SignatureProof,
+        links: &BTreeMap<usize, usize>,
+        claims: Option<&[&[u8]]>,
+        nonce: &ProofNonce,
+    ) -> Result<Vec<SignatureMessage>, BBSError> {
+        let vk = &proof_request.verification_key;
+        for (committed, hidden) in links {
+            if *committed >= verkey.message_count() || revealed_messages.contains(committed) {
+                return Err(BBSErrorKind::GeneralError {
+                    msg: format!(
+                        "Message {} has to be one of the {} committed messages",
+                        committed,
+                        verkey.message_count()
+                    ),
+                }
+                .into());
+            }
+            if *hidden >= vk.message_count() || proof_request.revealed_messages.contains(hidden) {
+                return Err(BBSErrorKind::GeneralError {
+                    msg: format!(
+                        "Message {} has to be one of the {} hidden messages",
+                        hidden,
+                        vk.message_count()
+                    ),
+                }
+                .into());
+            }
+        }
+        let committed_indices: Vec<usize> = (0..verkey.message_count())
+            .filter(|i| !revealed_messages.contains(i))
+            .collect();
+        let mut bases = vec![verkey.h0];
+        bases.extend(committed_indices.iter().map(|i| verkey.h[*i]));
+
+        let mut bytes = signature_proof
+            .proof
+            .get_bytes_for_challenge(proof_request.revealed_messages.clone(), vk);
+        bytes.extend_from_slice(&self.get_bytes_for_challenge(&bases));
+        bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        if let Some(add_claims) = claims {
+            for c in add_claims {
+                bytes.extend_from_slice(c);
+            }
+        }
+        let challenge = ProofChallenge::hash(&bytes);
+
+        if challenge != self.challenge_hash
+            || !self
+                .proof_of_hidden_messages
+                .verify(&bases, &self.commitment, &challenge)?
+        {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "Invalid proof of committed messages".to_string(),
+            }
+            .into());
+        }
+        match signature_proof
+            .proof
+            .verify(vk, &signature_proof.revealed_messages, &challenge)?
+        {
+            PoKOfSignatureProofStatus::Success => {}
+            e => return Err(BBSErrorKind::InvalidProof { status: e }.into()),
+        }
+        for (committed, hidden) in links {
+            // The first response is for the signature blinding
+            let position = 1 + committed_indices
+                .iter()
+                .take_while(|i| *i < committed)
+                .count();
+            let hidden_index = (0..*hidden)
+                .filter(|i| !proof_request.revealed_messages.contains(i))
+                .count();
+            if signature_proof.proof.get_resp_for_message(hidden_index)?.0
+                != self.proof_of_hidden_messages.responses[position]
+            {
+                return Err(BBSErrorKind::GeneralError {
+                    msg: format!(
+                        "Committed message {} is not hidden message {}",
+                        committed, hidden
+                    ),
+                }
+                .into());
+            }
+        }
+        Ok(signature_proof
+            .revealed_messages
+            .values()
+            .copied()
+            .collect())
+    }
+
+    fn get_bytes_for_challenge(&self, bases: &[GeneratorG1]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for b in bases {
+            b.0.serialize(&mut bytes, false).unwrap();
+        }
+        self.proof_of_hidden_messages
+            .commitment
+            .serialize(&mut bytes, false)
+            .unwrap();
+        bytes.extend_from_slice(&self.commitment.to_bytes_uncompressed_form()[..]);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::Issuer;
+    use crate::messages::*;
+    use crate::prover::Prover;
+    use crate::verifier::Verifier;
+    use crate::{RandomElem, ToVariableLengthBytes};
+    use std::convert::TryFrom;
+
+    /// Issue a credential where the link secret is message 0 and the rest are known
+    fn credential(count: usize, link_secret: SignatureMessage) -> (PublicKey, Signature) {
+        let (pk, sk) = Issuer::new_keys(count).unwrap();
+        let mut messages: Vec<_> = (1..count)
+            .map(|i| SignatureMessage::hash(format!("claim {}", i)))
+            .collect();
+        messages.insert(0, link_secret);
+        let signature = Signature::new(&messages, &sk, &pk).unwrap();
+        (pk, signature)
+    }
+
+    /// Request a blind signature on `committed` as message 1 of `verkey`, linked to
+    /// message 0 of `signature`, which is hidden while the others are revealed
+    fn request(
+        pk: &PublicKey,
+        signature: &Signature,
+        old_messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        committed: SignatureMessage,
+        nonce: &ProofNonce,
+    ) -> (SignatureProof, BlindSignatureContext, SignatureBlinding) {
+        let blinding = ProofNonce::random();
+        let mut proof_messages = vec![pm_hidden_raw!(old_messages[0], blinding)];
+        proof_messages.extend(old_messages[1..].iter().map(|m| pm_revealed_raw!(*m)));
+        let pok = PoKOfSignature::init(signature, pk, &proof_messages).unwrap();
+
+        let messages: BTreeMap<_, _> = vec![(1, committed)].into_iter().collect();
+        let blindings: BTreeMap<_, _> = vec![(1, blinding)].into_iter().collect();
+        let committing = PoKOfCommittedMessages::init(verkey, &messages, &blindings).unwrap();
+        let challenge = committing.create_challenge_hash(&pok, None, nonce);
+        let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let (ctx, blinding_factor) = committing.gen_proof(&challenge).unwrap();
+        (proof, ctx, blinding_factor)
+    }
+
+    #[test]
+    fn link_secret_carried_over() {
+        let link_secret = Prover::new_link_secret();
+        let (pk1, signature1) = credential(3, link_secret);
+        let old_messages = vec![
+            link_secret,
+            SignatureMessage::hash(b"claim 1"),
+            SignatureMessage::hash(b"claim 2"),
+        ];
+        let proof_request = Verifier::new_proof_request(&[1, 2], &pk1).unwrap();
+
+        let (pk2, sk2) = Issuer::new_keys(3).unwrap();
+        let nonce = Issuer::generate_signing_nonce();
+        let (proof, ctx, blinding_factor) =
+            request(&pk1, &signature1, &old_messages, &pk2, link_secret, &nonce);
+        let ctx = BlindSignatureContext::try_from(ctx.to_bytes_compressed_form()).unwrap();
+
+        let known = sm_map![0 => b"name", 2 => b"age"];
+        let links: BTreeMap<_, _> = vec![(1, 0)].into_iter().collect();
+        let (blind_signature, revealed) = Issuer::blind_sign_linked(
+            &ctx,
+            &known,
+            &sk2,
+            &pk2,
+            &proof_request,
+            &proof,
+            &links,
+            &nonce,
+        )
+        .unwrap();
+        assert_eq!(revealed, old_messages[1..].to_vec());
+        // The request isn't valid on its own
+        assert!(Issuer::blind_sign(&ctx, &known, &sk2, &pk2, &nonce).is_err());
+
+        let messages = vec![known[&0], link_secret, known[&2]];
+        let signature =
+            Prover::complete_signature(&pk2, &messages, &blind_signature, &blinding_factor)
+                .unwrap();
+        assert!(signature.verify(&messages, &pk2).unwrap());
+
+        // Links to a revealed message, to another nonce or by another index fail
+        let revealed_link: BTreeMap<_, _> = vec![(1, 1)].into_iter().collect();
+        let signed_link: BTreeMap<_, _> = vec![(0, 0)].into_iter().collect();
+        let revealed_indices = known.keys().copied().collect();
+        for (links, nonce) in &[
+            (&revealed_link, &nonce),
+            (&signed_link, &nonce),
+            (&links, &ProofNonce::random()),
+        ] {
+            assert!(ctx
+                .verify_linked(
+                    &revealed_indices,
+                    &pk2,
+                    &proof_request,
+                    &proof,
+                    links,
+                    None,
+                    nonce
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn other_committed_value_is_rejected() {
+        let link_secret = Prover::new_link_secret();
+        let (pk1, signature1) = credential(2, link_secret);
+        let old_messages = vec![link_secret, SignatureMessage::hash(b"claim 1")];
+        let proof_request = Verifier::new_proof_request(&[1], &pk1).unwrap();
+
+        let (pk2, sk2) = Issuer::new_keys(3).unwrap();
+        let nonce = Issuer::generate_signing_nonce();
+        let (proof, ctx, _) = request(
+            &pk1,
+            &signature1,
+            &old_messages,
+            &pk2,
+            Prover::new_link_secret(),
+            &nonce,
+        );
+        let known = sm_map![0 => b"name", 2 => b"age"];
+        let links: BTreeMap<_, _> = vec![(1, 0)].into_iter().collect();
+        assert!(Issuer::blind_sign_linked(
+            &ctx,
+            &known,
+            &sk2,
+            &pk2,
+            &proof_request,
+            &proof,
+            &links,
+            &nonce
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn init_checks_indices() {
+        let (pk, _) = Issuer::new_keys(2).unwrap();
+        let messages: BTreeMap<_, _> = vec![(2, Prover::new_link_secret())].into_iter().collect();
+        assert!(PoKOfCommittedMessages::init(&pk, &messages, &BTreeMap::new()).is_err());
+        let messages: BTreeMap<_, _> = vec![(0, Prover::new_link_secret())].into_iter().collect();
+        let blindings: BTreeMap<_, _> = vec![(1, ProofNonce::random())].into_iter().collect();
+        assert!(PoKOfCommittedMessages::init(&pk, &messages, &blindings).is_err());
+    }
+}
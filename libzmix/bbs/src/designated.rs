@@ -0,0 +1,415 @@
+//! Signature proofs of knowledge that only convince one designated verifier.
+//!
+//! An ordinary signature proof convinces anyone who sees it, so a verifier can pass a
+//! holder's presentation on or sell it. A designated proof is an OR proof: either the
+//! prover knows a signature on the revealed messages, or it knows the secret key of the
+//! designated verifier. The verifier knows that it didn't make the proof itself and is
+//! convinced, while anyone else knows that the verifier could have made it with
+//! `DesignatedVerifierSecretKey::simulate_signature_pok` for any messages it likes.
+//!
+//! The verifier branch is a Schnorr proof for `Y = g1^y`. The prover simulates it with a
+//! challenge `c_v` of its own choosing and answers the signature proof with `c - c_v`,
+//! where `c` is the Fiat-Shamir challenge over both branches, the nonce and `Y`.
+//!
+//! The randomized signature `A'`, `Abar` is still checked with a pairing, so a proof
+//! shows third parties that the issuer signed something for someone. A simulation
+//! reuses `A'` and `Abar` from any earlier presentation of a credential from the same
+//! issuer, so it says nothing about which messages were signed or who holds them.
+
+use crate::errors::prelude::*;
+use crate::pok_sig::prelude::*;
+use crate::pok_vc::prelude::*;
+use crate::threshold::{check_length, general_error};
+use crate::{
+    rand_non_zero_fr, Commitment, GeneratorG1, HashElem, ProofChallenge, ProofNonce, ProofRequest,
+    SignatureMessage, SignatureProof, ToVariableLengthBytes, FR_COMPRESSED_SIZE,
+    G1_COMPRESSED_SIZE,
+};
+use ff_zeroize::Field;
+use pairing_plus::{
+    bls12_381::{Fr, G1},
+    serdes::SerDes,
+    CurveProjective,
+};
+use rand::thread_rng;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use zeroize::Zeroize;
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{
+        DesignatedSignatureProof, DesignatedVerifierPublicKey, DesignatedVerifierSecretKey,
+    };
+}
+
+/// The secret key of a designated verifier. Anyone holding it can simulate proofs
+/// for its public key, which is what makes them worthless to anyone else.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DesignatedVerifierSecretKey(Fr);
+
+impl DesignatedVerifierSecretKey {
+    /// Generate a new random key
+    pub fn new() -> Self {
+        Self(rand_non_zero_fr())
+    }
+
+    /// The public key that provers designate this verifier with
+    pub fn public_key(&self) -> DesignatedVerifierPublicKey {
+        let mut y = G1::one();
+        y.mul_assign(self.0);
+        DesignatedVerifierPublicKey(y)
+    }
+
+    /// Create a proof for `request` that reveals `revealed_messages` without knowing a
+    /// signature on them. `template` is any earlier proof for a credential from the
+    /// issuer of `request`, only its randomized signature is reused.
+    ///
+    /// The result verifies for this key's public key exactly like an honest proof.
+    pub fn simulate_signature_pok(
+        &self,
+        request: &ProofRequest,
+        revealed_messages: BTreeMap<usize, SignatureMessage>,
+        template: &SignatureProof,
+        nonce: &ProofNonce,
+    ) -> Result<DesignatedSignatureProof, BBSError> {
+        let vk = &request.verification_key;
+        if revealed_messages.len() != request.revealed_messages.len()
+            || revealed_messages
+                .keys()
+                .any(|i| !request.revealed_messages.contains(i))
+        {
+            return Err(general_error(
+                "The revealed messages do not match the proof request",
+            ));
+        }
+        if revealed_messages.keys().any(|i| *i >= vk.message_count()) {
+            return Err(general_error(
+                "A revealed message index is out of range for the verification key",
+            ));
+        }
+
+        // Answer the signature proof first with random responses and derive its
+        // commitments from them
+        let mut proof = PoKOfSignatureProof {
+            a_prime: template.proof.a_prime,
+            a_bar: template.proof.a_bar,
+            d: G1::random(&mut thread_rng()),
+            proof_vc_1: ProofG1::default(),
+            proof_vc_2: ProofG1::default(),
+        };
+        let challenge = ProofChallenge(rand_non_zero_fr());
+        let ((bases_1, commitment_1), (bases_2, commitment_2)) =
+            proof.relations(vk, &revealed_messages);
+        proof.proof_vc_1 = simulate_relation(&bases_1, &commitment_1, &challenge)?;
+        proof.proof_vc_2 = simulate_relation(&bases_2, &commitment_2, &challenge)?;
+
+        // Then run the verifier branch honestly
+        let blinding = rand_non_zero_fr();
+        let mut t_v = G1::one();
+        t_v.mul_assign(blinding);
+        let mut bytes = proof.get_bytes_for_challenge(request.revealed_messages.clone(), vk);
+        let c = designated_challenge(&mut bytes, nonce, &self.public_key(), &t_v);
+        let mut verifier_challenge = c;
+        verifier_challenge.sub_assign(&challenge.0);
+        let mut verifier_response = verifier_challenge;
+        verifier_response.mul_assign(&self.0);
+        verifier_response.add_assign(&blinding);
+
+        Ok(DesignatedSignatureProof {
+            proof: SignatureProof {
+                revealed_messages,
+                proof,
+            },
+            verifier_challenge: ProofChallenge(verifier_challenge),
+            verifier_response,
+        })
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(FR_COMPRESSED_SIZE);
+        self.0.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl Default for DesignatedVerifierSecretKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<&[u8]> for DesignatedVerifierSecretKey {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, FR_COMPRESSED_SIZE)?;
+        let mut c = value;
+        Ok(Self(Fr::deserialize(&mut c, true)?))
+    }
+}
+
+impl Drop for DesignatedVerifierSecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The public key of a designated verifier, `g1^y`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DesignatedVerifierPublicKey(G1);
+
+impl DesignatedVerifierPublicKey {
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(G1_COMPRESSED_SIZE);
+        self.0.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for DesignatedVerifierPublicKey {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, G1_COMPRESSED_SIZE)?;
+        let mut c = value;
+        let y = G1::deserialize(&mut c, true)?;
+        if y.is_zero() {
+            return Err(general_error("The designated verifier key is the identity"));
+        }
+        Ok(Self(y))
+    }
+}
+
+/// A signature proof of knowledge that only convinces the verifier it was made for
+#[derive(Clone, Debug)]
+pub struct DesignatedSignatureProof {
+    proof: SignatureProof,
+    /// The challenge of the simulated verifier branch
+    verifier_challenge: ProofChallenge,
+    /// The response of the simulated verifier branch
+    verifier_response: Fr,
+}
+
+impl DesignatedSignatureProof {
+    /// Complete `pok_sig` into a proof that only convinces the holder of the secret key
+    /// for `verifier`. The challenge is computed here from `nonce` and `verifier`.
+    pub fn new(
+        pok_sig: PoKOfSignature,
+        nonce: &ProofNonce,
+        verifier: &DesignatedVerifierPublicKey,
+    ) -> Result<Self, BBSError> {
+        // Simulate the verifier branch
+        let verifier_challenge = rand_non_zero_fr();
+        let verifier_response = rand_non_zero_fr();
+        let t_v = verifier_commitment(verifier, &verifier_challenge, &verifier_response);
+
+        let mut bytes = pok_sig.to_bytes();
+        let mut challenge = designated_challenge(&mut bytes, nonce, verifier, &t_v);
+        challenge.sub_assign(&verifier_challenge);
+
+        let revealed_messages = pok_sig.revealed_messages.clone();
+        let proof = pok_sig.gen_proof(&ProofChallenge(challenge))?;
+        Ok(Self {
+            proof: SignatureProof {
+                revealed_messages,
+                proof,
+            },
+            verifier_challenge: ProofChallenge(verifier_challenge),
+            verifier_response,
+        })
+    }
+
+    /// The revealed messages
+    pub fn revealed_messages(&self) -> &BTreeMap<usize, SignatureMessage> {
+        &self.proof.revealed_messages
+    }
+
+    /// Check the proof for `request` and `nonce` as the designated `verifier`.
+    /// Returns the revealed messages.
+    pub fn verify(
+        &self,
+        request: &ProofRequest,
+        nonce: &ProofNonce,
+        verifier: &DesignatedVerifierPublicKey,
+    ) -> Result<Vec<SignatureMessage>, BBSError> {
+        let t_v = verifier_commitment(
+            verifier,
+            &self.verifier_challenge.0,
+            &self.verifier_response,
+        );
+        let mut bytes = self
+            .proof
+            .proof
+            .get_bytes_for_challenge(request.revealed_messages.clone(), &request.verification_key);
+        let mut challenge = designated_challenge(&mut bytes, nonce, verifier, &t_v);
+        challenge.sub_assign(&self.verifier_challenge.0);
+
+        match self.proof.proof.verify(
+            &request.verification_key,
+            &self.proof.revealed_messages,
+            &ProofChallenge(challenge),
+        )? {
+            PoKOfSignatureProofStatus::Success => {
+                Ok(self.proof.revealed_messages.values().copied().collect())
+            }
+            e => Err(BBSErrorKind::InvalidProof { status: e }.into()),
+        }
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.verifier_challenge.0.serialize(&mut out, true).unwrap();
+        self.verifier_response.serialize(&mut out, true).unwrap();
+        out.extend_from_slice(&self.proof.to_bytes_compressed_form());
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for DesignatedSignatureProof {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        if value.len() < 2 * FR_COMPRESSED_SIZE {
+            return Err(
+                BBSErrorKind::InvalidNumberOfBytes(2 * FR_COMPRESSED_SIZE, value.len()).into(),
+            );
+        }
+        let mut c = value;
+        let verifier_challenge = ProofChallenge(Fr::deserialize(&mut c, true)?);
+        let verifier_response = Fr::deserialize(&mut c, true)?;
+        Ok(Self {
+            proof: SignatureProof::from_bytes_compressed_form(c)?,
+            verifier_challenge,
+            verifier_response,
+        })
+    }
+}
+
+/// g1^z_v * Y^-c_v
+fn verifier_commitment(
+    verifier: &DesignatedVerifierPublicKey,
+    challenge: &Fr,
+    response: &Fr,
+) -> G1 {
+    let mut t_v = G1::one();
+    t_v.mul_assign(*response);
+    let mut y = verifier.0;
+    y.mul_assign(*challenge);
+    t_v.sub_assign(&y);
+    t_v
+}
+
+/// The challenge shared by both branches
+fn designated_challenge(
+    bytes: &mut Vec<u8>,
+    nonce: &ProofNonce,
+    verifier: &DesignatedVerifierPublicKey,
+    t_v: &G1,
+) -> Fr {
+    bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+    verifier.0.serialize(bytes, false).unwrap();
+    t_v.serialize(bytes, false).unwrap();
+    ProofChallenge::hash(bytes).0
+}
+
+/// A proof for `bases` and `commitment` that verifies with `challenge`
+/// without knowing the exponents
+fn simulate_relation(
+    bases: &[GeneratorG1],
+    commitment: &Commitment,
+    challenge: &ProofChallenge,
+) -> Result<ProofG1, BBSError> {
+    let mut proof = ProofG1 {
+        commitment: G1::zero(),
+        responses: (0..bases.len()).map(|_| rand_non_zero_fr()).collect(),
+    };
+    proof.commitment = proof
+        .get_challenge_contribution(bases, commitment, challenge)?
+        .0;
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::Issuer;
+    use crate::messages::*;
+    use crate::prover::Prover;
+    use crate::signature::Signature;
+    use crate::verifier::Verifier;
+    use crate::RandomElem;
+
+    #[test]
+    fn designated_proof() {
+        let (pk, sk) = Issuer::new_keys(4).unwrap();
+        let messages: Vec<SignatureMessage> = (0..4).map(|_| SignatureMessage::random()).collect();
+        let signature = Signature::new(&messages, &sk, &pk).unwrap();
+        let request = Verifier::new_proof_request(&[0, 2], &pk).unwrap();
+        let proof_messages = |m: &[SignatureMessage]| {
+            m.iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    if request.revealed_messages.contains(&i) {
+                        ProofMessage::Revealed(*m)
+                    } else {
+                        ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(*m))
+                    }
+                })
+                .collect::<Vec<ProofMessage>>()
+        };
+
+        let verifier = DesignatedVerifierSecretKey::new();
+        let nonce = ProofNonce::random();
+        let pok =
+            Prover::commit_signature_pok(&request, &proof_messages(&messages), &signature).unwrap();
+        let proof = DesignatedSignatureProof::new(pok, &nonce, &verifier.public_key()).unwrap();
+        let proof = DesignatedSignatureProof::try_from(proof.to_bytes().as_slice()).unwrap();
+        assert_eq!(
+            proof
+                .verify(&request, &nonce, &verifier.public_key())
+                .unwrap(),
+            vec![messages[0], messages[2]]
+        );
+
+        // Useless for anyone else or with another nonce
+        let other = DesignatedVerifierSecretKey::new();
+        assert!(proof.verify(&request, &nonce, &other.public_key()).is_err());
+        assert!(proof
+            .verify(&request, &ProofNonce::random(), &verifier.public_key())
+            .is_err());
+
+        // The verifier can make a proof for messages that were never signed,
+        // so showing one to a third party proves nothing
+        let pok =
+            Prover::commit_signature_pok(&request, &proof_messages(&messages), &signature).unwrap();
+        let challenge =
+            Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+        let template = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let mut forged = BTreeMap::new();
+        forged.insert(0, SignatureMessage::random());
+        forged.insert(2, SignatureMessage::random());
+        let simulated = verifier
+            .simulate_signature_pok(&request, forged.clone(), &template, &nonce)
+            .unwrap();
+        assert_eq!(
+            simulated
+                .verify(&request, &nonce, &verifier.public_key())
+                .unwrap(),
+            forged.values().copied().collect::<Vec<_>>()
+        );
+        assert!(simulated
+            .verify(&request, &nonce, &other.public_key())
+            .is_err());
+
+        let key = DesignatedVerifierSecretKey::try_from(verifier.to_bytes().as_slice()).unwrap();
+        assert_eq!(key.public_key(), verifier.public_key());
+        assert_eq!(
+            DesignatedVerifierPublicKey::try_from(key.public_key().to_bytes().as_slice()).unwrap(),
+            verifier.public_key()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::pok_sig::PoKOfSignatureProofStatus;
 use crate::pok_vc::PoKVCError;
+use crate::version::{ProtocolVersion, VersionRange};
 use failure::{Backtrace, Context, Fail};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -51,6 +52,42 @@ pub enum BBSErrorKind {
         /// The status of the invalid proof
         status: PoKOfSignatureProofStatus,
     },
+    /// A versioned message was written with a protocol version this crate cannot read
+    #[fail(
+        display = "Unsupported protocol version {}, supported are {}",
+        found, supported
+    )]
+    UnsupportedVersion {
+        /// The version of the message
+        found: ProtocolVersion,
+        /// The versions this crate can read
+        supported: VersionRange,
+    },
+    /// Both sides of an exchange do not share a protocol version
+    #[fail(
+        display = "No common protocol version, ours are {} and theirs are {}",
+        ours, theirs
+    )]
+    NoCommonVersion {
+        /// The versions this crate supports
+        ours: VersionRange,
+        /// The versions the other side supports
+        theirs: VersionRange,
+    },
+    /// The message does not start with a protocol version
+    #[fail(display = "Message has no protocol version header")]
+    MissingVersion,
+    /// An operation needs more scratch memory than `ResourceLimits` allows
+    #[fail(
+        display = "Operation needs {} bytes of scratch memory but the limit is {}",
+        required, limit
+    )]
+    MemoryLimitExceeded {
+        /// Bytes the operation needs
+        required: usize,
+        /// Bytes allowed
+        limit: usize,
+    },
     /// A Generic error
     #[fail(display = "{:?}", msg)]
     GeneralError {
@@ -8,7 +8,9 @@ use crate::signature::prelude::*;
 /// to the secret key. `DeterministicPublicKey` can be converted to a
 /// `PublicKey` later. The latter is primarily used for storing a shorter
 /// key and looks just like a regular ECC key.
-use crate::{BlindSignatureContext, ProofNonce, RandomElem, SignatureMessage};
+use crate::{
+    BlindSignatureContext, ProofNonce, ProofRequest, RandomElem, SignatureMessage, SignatureProof,
+};
 use std::collections::{BTreeMap, BTreeSet};
 
 /// This struct represents an Issuer of signatures or Signer.
@@ -58,6 +60,35 @@ impl Issuer {
         }
     }
 
+    /// Verify a request from `PoKOfCommittedMessages` together with the holder's
+    /// `signature_proof` and generate a blind signature. `links` maps committed indices
+    /// to the hidden messages of the signature proof they have to equal, the revealed
+    /// messages of the signature proof are returned with the signature.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blind_sign_linked(
+        ctx: &BlindSignatureContext,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+        proof_request: &ProofRequest,
+        signature_proof: &SignatureProof,
+        links: &BTreeMap<usize, usize>,
+        nonce: &ProofNonce,
+    ) -> Result<(BlindSignature, Vec<SignatureMessage>), BBSError> {
+        let revealed_messages: BTreeSet<usize> = messages.keys().copied().collect();
+        let revealed = ctx.verify_linked(
+            &revealed_messages,
+            verkey,
+            proof_request,
+            signature_proof,
+            links,
+            None,
+            nonce,
+        )?;
+        let signature = BlindSignature::new(&ctx.commitment, messages, signkey, verkey)?;
+        Ok((signature, revealed))
+    }
+
     /// Create a nonce used for the blind signing context
     pub fn generate_signing_nonce() -> ProofNonce {
         ProofNonce::random()
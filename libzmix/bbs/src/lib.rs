@@ -39,8 +39,9 @@ use blake2::digest::{generic_array::GenericArray, Input, VariableOutput};
 use errors::prelude::*;
 use ff_zeroize::{Field, PrimeField};
 use keys::prelude::*;
+use limits::ResourceLimits;
 use pairing_plus::{
-    bls12_381::{Fr, G1Affine, G1, G2},
+    bls12_381::{Fr, FrRepr, G1Affine, G1, G2},
     hash_to_curve::HashToCurve,
     hash_to_field::{BaseFromRO, ExpandMsgXmd},
     serdes::SerDes,
@@ -52,6 +53,7 @@ use rand::prelude::*;
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 use std::fmt::{Display, Formatter};
+use std::mem::size_of;
 
 use serde::{
     de::{Error as DError, Visitor},
@@ -84,6 +86,10 @@ pub mod messages;
 /// Macros and classes used for creating proofs of knowledge
 #[macro_use]
 pub mod pok_vc;
+/// Blind signature requests linked to hidden messages of other signatures
+pub mod blind;
+/// Signature proofs of knowledge that only convince a designated verifier
+pub mod designated;
 /// The errors that BBS+ throws
 pub mod errors;
 /// Represents steps taken by the issuer to create a BBS+ signature
@@ -91,16 +97,28 @@ pub mod errors;
 pub mod issuer;
 /// BBS+ key classes
 pub mod keys;
+/// Limits on the threads and memory used by parallel operations
+pub mod limits;
+/// Link secrets generated and held jointly by a holder's devices
+pub mod link_secret;
+/// Proofs that a hidden message is the value in an external Pedersen commitment
+pub mod pedersen;
 /// Methods and structs for creating signature proofs of knowledge
 pub mod pok_sig;
+/// Proof requests compiled once for many presentations
+pub mod program;
 /// Represents steps taken by the prover to receive a BBS+ signature
 /// and generate ZKPs
 pub mod prover;
 /// Methods and structs for creating signatures
 pub mod signature;
+/// Issuance by `t` of `n` issuer nodes that each hold a share of the secret key
+pub mod threshold;
 /// Represents steps taken by the verifier to request signature proofs of knowledge
 /// and selective disclosure proofs
 pub mod verifier;
+/// Protocol versions for exchanging proof requests and proofs across releases
+pub mod version;
 
 /// Trait for structs that have variable length bytes but use compressed Bls12 elements
 pub trait ToVariableLengthBytes {
@@ -327,13 +345,31 @@ pub(crate) fn hash_to_fr<I: AsRef<[u8]>>(data: I) -> Fr {
     Fr::from_okm(&res)
 }
 
+/// Scratch memory used by the multi-exponentiations for each base
+const MULTI_SCALAR_MUL_ITEM_SIZE: usize = size_of::<G1Affine>()
+    + size_of::<[u64; 4]>()
+    + size_of::<&[u64; 4]>();
+
 pub(crate) fn multi_scalar_mul_const_time_g1<G: AsRef<[G1]>, S: AsRef<[Fr]>>(
     bases: G,
     scalars: S,
 ) -> G1 {
-    let bases: Vec<_> = bases.as_ref().iter().map(|b| b.into_affine()).collect();
+    let bases = bases.as_ref();
+    let scalars = scalars.as_ref();
+    let round_size = ResourceLimits::current().items_in_memory(MULTI_SCALAR_MUL_ITEM_SIZE);
+    if bases.len() <= round_size {
+        return sum_of_products_g1(bases, scalars);
+    }
+    let mut res = G1::zero();
+    for (b, s) in bases.chunks(round_size).zip(scalars.chunks(round_size)) {
+        res.add_assign(&sum_of_products_g1(b, s));
+    }
+    res
+}
+
+fn sum_of_products_g1(bases: &[G1], scalars: &[Fr]) -> G1 {
+    let bases: Vec<_> = bases.iter().map(|b| b.into_affine()).collect();
     let scalars: Vec<[u64; 4]> = scalars
-        .as_ref()
         .iter()
         .map(|s| {
             let mut t = [0u64; 4];
@@ -354,14 +390,78 @@ pub(crate) fn multi_scalar_mul_var_time_g1<G: AsRef<[G1]>, S: AsRef<[Fr]>>(
     let scalars = scalars.as_ref();
     #[cfg(feature = "rayon")]
     {
-        bases
-            .par_iter()
-            .zip(scalars.par_iter())
-            .map(|(b, s)| {
-                let mut t = *b;
-                t.mul_assign(*s);
-                t
-            })
+        // Threads work on their chunks at the same time so a round
+        // must fit in the scratch memory of the operation
+        let round_size = ResourceLimits::current().items_in_memory(MULTI_SCALAR_MUL_ITEM_SIZE);
+        let mut res = G1::zero();
+        for (bases, scalars) in bases.chunks(round_size).zip(scalars.chunks(round_size)) {
+            // Pippenger is much faster than individual multiplications
+            // so split the bases evenly between threads instead
+            let chunk_size = std::cmp::max(
+                bases.len() / rayon::current_num_threads(),
+                PRECOMPUTED_CHUNK_SIZE,
+            );
+            let round = bases
+                .par_chunks(chunk_size)
+                .zip(scalars.par_chunks(chunk_size))
+                .map(|(b, s)| sum_of_products_g1(b, s))
+                .reduce(G1::zero, |mut acc, b| {
+                    acc.add_assign(&b);
+                    acc
+                });
+            res.add_assign(&round);
+        }
+        res
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        multi_scalar_mul_const_time_g1(bases, scalars)
+    }
+}
+
+/// Number of points in the precomputed table for each base
+pub(crate) const PRECOMPUTED_TABLE_SIZE: usize = 256;
+
+/// Number of bases handled by each task when the precomputed multi-exponentiation runs in parallel
+#[cfg(feature = "rayon")]
+const PRECOMPUTED_CHUNK_SIZE: usize = 16;
+
+/// Compute table\[i\] = (\sum_{b such that bth bit of i is 1} 2^{32b}) * base for i in 0..256.
+/// This is the layout used by `mul_precomp_256` in pairing-plus but
+/// all points are normalized with a single inversion.
+pub(crate) fn precompute_g1(base: &G1) -> Vec<G1Affine> {
+    let mut table = vec![G1::zero(); PRECOMPUTED_TABLE_SIZE];
+    let mut power_of_2_times_base = *base;
+    let mut piece_length = 1;
+    while piece_length < PRECOMPUTED_TABLE_SIZE {
+        table[piece_length] = power_of_2_times_base;
+        for i in 1..piece_length {
+            let mut t = table[i];
+            t.add_assign(&power_of_2_times_base);
+            table[i + piece_length] = t;
+        }
+        if piece_length < PRECOMPUTED_TABLE_SIZE / 2 {
+            for _ in 0..32 {
+                power_of_2_times_base.double();
+            }
+        }
+        piece_length *= 2;
+    }
+    G1::batch_normalization(table.as_mut_slice());
+    table.iter().map(|p| p.into_affine()).collect()
+}
+
+/// Multi-exponentiation using the tables from `precompute_g1`.
+/// Each scalar is split into 8 32-bit words that are processed together
+/// so the whole sum needs only 32 doublings.
+pub(crate) fn multi_scalar_mul_precomputed_g1(tables: &[&[G1Affine]], scalars: &[Fr]) -> G1 {
+    let scalars: Vec<FrRepr> = scalars.iter().map(|s| s.into_repr()).collect();
+    #[cfg(feature = "rayon")]
+    {
+        tables
+            .par_chunks(PRECOMPUTED_CHUNK_SIZE)
+            .zip(scalars.par_chunks(PRECOMPUTED_CHUNK_SIZE))
+            .map(|(t, s)| sum_of_products_precomputed(t, s))
             .reduce(G1::zero, |mut acc, b| {
                 acc.add_assign(&b);
                 acc
@@ -369,18 +469,45 @@ pub(crate) fn multi_scalar_mul_var_time_g1<G: AsRef<[G1]>, S: AsRef<[Fr]>>(
     }
     #[cfg(not(feature = "rayon"))]
     {
-        bases
-            .iter()
-            .zip(scalars.iter())
-            .map(|(b, s)| {
-                let mut t = b.clone();
-                t.mul_assign(*s);
-                t
-            })
-            .fold(G1::zero(), |mut acc, b| {
-                acc.add_assign(&b);
-                acc
-            })
+        sum_of_products_precomputed(tables, scalars.as_slice())
+    }
+}
+
+fn sum_of_products_precomputed(tables: &[&[G1Affine]], scalars: &[FrRepr]) -> G1 {
+    let mut res = G1::zero();
+    for i in (0..32).rev() {
+        res.double();
+        for (table, scalar) in tables.iter().zip(scalars.iter()) {
+            let bits = &scalar.0;
+            let mut byte = (bits[3] >> (i + 25)) & 128;
+            byte |= ((bits[3] >> i) << 6) & 64;
+            byte |= (bits[2] >> (i + 27)) & 32;
+            byte |= ((bits[2] >> i) << 4) & 16;
+            byte |= (bits[1] >> (i + 29)) & 8;
+            byte |= ((bits[1] >> i) << 2) & 4;
+            byte |= (bits[0] >> (i + 31)) & 2;
+            byte |= (bits[0] >> i) & 1;
+            res.add_assign_mixed(&table[byte as usize]);
+        }
+    }
+    res
+}
+
+/// Run both closures, in parallel when rayon is available
+pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        rayon::join(a, b)
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (a(), b())
     }
 }
 
@@ -764,8 +891,10 @@ fn rand_non_zero_fr() -> Fr {
 /// Convenience importer
 pub mod prelude {
     pub use super::{
-        errors::prelude::*, issuer::Issuer, keys::prelude::*, messages::*, pok_sig::prelude::*,
-        pok_vc::prelude::*, prover::Prover, signature::prelude::*, verifier::Verifier,
+        blind::prelude::*, designated::prelude::*, errors::prelude::*, issuer::Issuer,
+        keys::prelude::*, limits::prelude::*, link_secret::prelude::*, messages::*,
+        pok_sig::prelude::*, pok_vc::prelude::*, program::prelude::*, prover::Prover,
+        signature::prelude::*, threshold::prelude::*, verifier::Verifier, version::prelude::*,
         BlindSignatureContext, Commitment, CommitmentBuilder, GeneratorG1, GeneratorG2, HashElem,
         ProofChallenge, ProofNonce, ProofRequest, RandomElem, SignatureBlinding, SignatureMessage,
         SignatureProof, ToVariableLengthBytes, FR_COMPRESSED_SIZE, G1_COMPRESSED_SIZE,
@@ -0,0 +1,232 @@
+//! Limits on the threads and memory used by parallel operations.
+//!
+//! Key generation, preparing public keys, committing to proofs and batch verification
+//! split their work across all cores with rayon and size their scratch buffers by the
+//! number of bases. Inside a container with a small cgroup that can mean more threads
+//! than the container has cpus and spikes of memory the container does not have.
+//!
+//! `set_global_limits` applies limits to everything in the process, it must be called
+//! before any other function of this crate starts the rayon thread pool.
+//! `ResourceLimits::run` applies limits to a single operation and overrides the global ones
+//! for everything the operation does.
+//!
+//! The thread limit bounds the number of worker threads. The memory limit bounds the
+//! scratch memory an operation allocates, not the memory used by its inputs and outputs.
+//! Multi-exponentiations over more bases than fit are computed in several rounds and
+//! operations whose output alone exceeds the limit fail with
+//! `BBSErrorKind::MemoryLimitExceeded`.
+//!
+//! Without the `rayon` feature everything runs on the calling thread and only the
+//! memory limit applies.
+
+use crate::errors::prelude::*;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{global_limits, set_global_limits, ResourceLimits};
+}
+
+/// 0 means unlimited
+static GLOBAL_MAX_THREADS: AtomicUsize = AtomicUsize::new(0);
+/// 0 means unlimited
+static GLOBAL_MAX_MEMORY: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Limits of the operation running on this thread, set by `ResourceLimits::run`
+    static SCOPED: Cell<Option<ResourceLimits>> = const { Cell::new(None) };
+}
+
+/// The maximum number of threads and bytes of scratch memory
+/// used by parallel operations. `None` means unlimited.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResourceLimits {
+    /// Maximum number of worker threads
+    pub max_threads: Option<usize>,
+    /// Maximum bytes of scratch memory
+    pub max_memory: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// No limits, this is the default
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Use at most `threads` worker threads
+    pub fn with_max_threads(mut self, threads: usize) -> Self {
+        self.max_threads = Some(threads);
+        self
+    }
+
+    /// Allocate at most `bytes` of scratch memory
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Run `op` with these limits instead of the global ones
+    pub fn run<OP, R>(&self, op: OP) -> Result<R, BBSError>
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        self.check()?;
+        let limits = *self;
+        let scoped = move || {
+            let previous = SCOPED.with(|s| s.replace(Some(limits)));
+            let result = op();
+            SCOPED.with(|s| s.set(previous));
+            result
+        };
+        #[cfg(feature = "rayon")]
+        {
+            match self.max_threads {
+                Some(threads) => Ok(build_pool(threads)?.install(scoped)),
+                None => Ok(scoped()),
+            }
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            Ok(scoped())
+        }
+    }
+
+    /// The limits that apply to operations started on this thread
+    pub fn current() -> Self {
+        SCOPED.with(|s| s.get()).unwrap_or_else(global_limits)
+    }
+
+    /// How many items of `item_size` bytes fit in the scratch memory, at least one
+    pub(crate) fn items_in_memory(&self, item_size: usize) -> usize {
+        match self.max_memory {
+            Some(bytes) => std::cmp::max(bytes / item_size, 1),
+            None => usize::MAX,
+        }
+    }
+
+    /// Fail if `required` bytes of scratch memory are more than allowed
+    pub(crate) fn reserve(&self, required: usize) -> Result<(), BBSError> {
+        match self.max_memory {
+            Some(limit) if required > limit => {
+                Err(BBSErrorKind::MemoryLimitExceeded { required, limit }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check(&self) -> Result<(), BBSError> {
+        if self.max_threads == Some(0) || self.max_memory == Some(0) {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "Resource limits must be greater than zero".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Apply `limits` to all operations that do not set their own.
+/// The thread limit can only be set before the global rayon thread pool starts,
+/// otherwise this fails and nothing is changed.
+pub fn set_global_limits(limits: ResourceLimits) -> Result<(), BBSError> {
+    limits.check()?;
+    #[cfg(feature = "rayon")]
+    {
+        if let Some(threads) = limits.max_threads {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .map_err(|e| BBSErrorKind::GeneralError {
+                    msg: format!("Cannot limit the global thread pool: {}", e),
+                })?;
+        }
+    }
+    GLOBAL_MAX_THREADS.store(limits.max_threads.unwrap_or(0), Ordering::SeqCst);
+    GLOBAL_MAX_MEMORY.store(limits.max_memory.unwrap_or(0), Ordering::SeqCst);
+    Ok(())
+}
+
+/// The limits set with `set_global_limits`
+pub fn global_limits() -> ResourceLimits {
+    let to_option = |v: usize| if v == 0 { None } else { Some(v) };
+    ResourceLimits {
+        max_threads: to_option(GLOBAL_MAX_THREADS.load(Ordering::SeqCst)),
+        max_memory: to_option(GLOBAL_MAX_MEMORY.load(Ordering::SeqCst)),
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn build_pool(threads: usize) -> Result<rayon::ThreadPool, BBSError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| {
+            BBSErrorKind::GeneralError {
+                msg: format!("Cannot create a thread pool: {}", e),
+            }
+            .into()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn limits_apply_to_operation() {
+        let (pk, sk) = Issuer::new_keys(40).unwrap();
+        let messages: Vec<SignatureMessage> = (0..40)
+            .map(|i| SignatureMessage::hash(format!("message {}", i)))
+            .collect();
+        let signature = Signature::new(messages.as_slice(), &sk, &pk).unwrap();
+        let request = Verifier::new_proof_request(&[0], &pk).unwrap();
+
+        let limits = ResourceLimits::unlimited()
+            .with_max_threads(2)
+            .with_max_memory(4 * 1024 * 1024);
+        let proof = limits
+            .run(|| {
+                #[cfg(feature = "rayon")]
+                assert_eq!(rayon::current_num_threads(), 2);
+                assert_eq!(ResourceLimits::current(), limits);
+
+                let nonce = Verifier::generate_proof_nonce();
+                let program = ProofProgram::compile(&request).unwrap();
+                let pok = program
+                    .commit_signature_pok(messages.as_slice(), &signature)
+                    .unwrap();
+                let challenge =
+                    Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce)
+                        .unwrap();
+                let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+                (proof, nonce)
+            })
+            .unwrap();
+        assert_eq!(ResourceLimits::current(), global_limits());
+
+        // A multi-exponentiation over more bases than fit runs in rounds
+        let tiny = ResourceLimits::unlimited().with_max_memory(1024);
+        let revealed = tiny
+            .run(|| Verifier::verify_signature_pok(&request, &proof.0, &proof.1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(revealed, vec![messages[0]]);
+
+        // The tables of a prepared key do not fit
+        match tiny.run(|| ProofProgram::compile(&request)).unwrap() {
+            Err(e) => assert!(matches!(
+                e.kind(),
+                BBSErrorKind::MemoryLimitExceeded { limit, .. } if limit == 1024
+            )),
+            Ok(_) => panic!("tables larger than the limit were computed"),
+        }
+
+        assert!(ResourceLimits::unlimited()
+            .with_max_threads(0)
+            .run(|| ())
+            .is_err());
+    }
+}
@@ -0,0 +1,873 @@
+//! A link secret that is generated and held jointly by a holder's devices.
+//!
+//! The devices run one round of distributed key generation, after which each holds a
+//! Shamir share of the link secret and the secret itself never exists anywhere. Any
+//! `t` devices can together request blind signatures on the link secret and prove
+//! knowledge of credentials that contain it, fewer than `t` can't, so a single lost
+//! or compromised device can't impersonate the holder.
+//!
+//! The wallet that builds requests and proofs is the coordinator. For every request
+//! or proof it opens a session with `t` or more devices in two steps. First every
+//! device sends a `DeviceCommitment` and the coordinator combines them into a
+//! `SharedLinkSecret` which it uses like a hidden message. Then every device answers
+//! the proof's challenge with a `DeviceResponse` and the coordinator completes the
+//! request or proof. The proofs that come out are ordinary BBS+ proofs and issuers
+//! and verifiers handle them like any other.
+//!
+//! A device answers each session only once, and should only join sessions the
+//! holder approved on it. Contributions contain secret shares and must be sent over
+//! authenticated and encrypted channels.
+
+use crate::errors::prelude::*;
+use crate::keys::prelude::*;
+use crate::messages::*;
+use crate::pok_sig::prelude::*;
+use crate::pok_vc::prelude::*;
+use crate::signature::prelude::*;
+use crate::threshold::{
+    check_index, check_length, evaluate, general_error, index_to_fr, lagrange_at_zero,
+    random_polynomial,
+};
+use crate::{
+    multi_scalar_mul_var_time_g1, rand_non_zero_fr, BlindSignatureContext, Commitment, GeneratorG1,
+    HashElem, ProofChallenge, ProofNonce, SignatureBlinding, SignatureMessage, SignatureProof,
+    FR_COMPRESSED_SIZE, G1_COMPRESSED_SIZE,
+};
+use ff_zeroize::Field;
+use pairing_plus::{
+    bls12_381::{Fr, G1},
+    serdes::SerDes,
+    CurveProjective,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use zeroize::Zeroize;
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{
+        DeviceCommitment, DeviceResponse, DeviceSession, LinkSecretContribution, LinkSecretRound,
+        LinkSecretShare, PendingBlindSignatureContext, SharedLinkSecret, SharedPoKOfSignature,
+    };
+}
+
+/// The state a device keeps while the link secret is being generated
+#[derive(Debug)]
+pub struct LinkSecretRound {
+    index: u32,
+    threshold: usize,
+    total: usize,
+    polynomial: Vec<Fr>,
+}
+
+impl LinkSecretRound {
+    /// Start generating a link secret as device `index` of `total` devices, any
+    /// `threshold` of which can use it
+    pub fn new(index: u32, threshold: usize, total: usize) -> Result<Self, BBSError> {
+        check_device_threshold(threshold, total)?;
+        check_index(index, total)?;
+        Ok(Self {
+            index,
+            threshold,
+            total,
+            polynomial: random_polynomial(rand_non_zero_fr(), threshold - 1),
+        })
+    }
+
+    /// The contribution to send to device `receiver`. A device also sends one to itself.
+    pub fn contribution(&self, receiver: u32) -> Result<LinkSecretContribution, BBSError> {
+        check_index(receiver, self.total)?;
+        let commitments = self
+            .polynomial
+            .iter()
+            .map(|c| {
+                let mut p = G1::one();
+                p.mul_assign(*c);
+                p
+            })
+            .collect();
+        Ok(LinkSecretContribution {
+            sender: self.index,
+            receiver,
+            share: evaluate(&self.polynomial, &index_to_fr(receiver)),
+            commitments,
+        })
+    }
+
+    /// Check the contributions from every device and combine them into this
+    /// device's share
+    pub fn finish(
+        self,
+        contributions: &[LinkSecretContribution],
+    ) -> Result<LinkSecretShare, BBSError> {
+        let senders: BTreeSet<u32> = contributions.iter().map(|c| c.sender).collect();
+        if senders.len() != contributions.len() || senders.len() != self.total {
+            return Err(general_error("Expected one contribution from every device"));
+        }
+        let x = index_to_fr(self.index);
+        let mut share = Fr::zero();
+        let mut public_key = G1::zero();
+        for c in contributions {
+            check_index(c.sender, self.total)?;
+            if c.receiver != self.index {
+                return Err(general_error("Contribution is for another device"));
+            }
+            if c.commitments.len() != self.threshold {
+                return Err(general_error("Contribution has the wrong threshold"));
+            }
+            // g1^share == C_0 * C_1^x * ... * C_t-1^(x^(t-1))
+            let mut expected = c.commitments.iter().rev().fold(G1::zero(), |mut acc, p| {
+                acc.mul_assign(x);
+                acc.add_assign(p);
+                acc
+            });
+            let mut actual = G1::one();
+            actual.mul_assign(c.share);
+            expected.sub_assign(&actual);
+            if !expected.is_zero() {
+                return Err(general_error(&format!(
+                    "Invalid contribution from device {}",
+                    c.sender
+                )));
+            }
+            share.add_assign(&c.share);
+            public_key.add_assign(&c.commitments[0]);
+        }
+        Ok(LinkSecretShare {
+            index: self.index,
+            threshold: self.threshold,
+            share,
+            public_key,
+        })
+    }
+}
+
+impl Drop for LinkSecretRound {
+    fn drop(&mut self) {
+        self.polynomial.iter_mut().for_each(|c| c.zeroize());
+    }
+}
+
+/// The share one device sends another while generating the link secret together
+/// with commitments to the polynomial it was taken from
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkSecretContribution {
+    sender: u32,
+    receiver: u32,
+    share: Fr,
+    commitments: Vec<G1>,
+}
+
+impl LinkSecretContribution {
+    /// The device that created this contribution
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The device this contribution is for
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            8 + FR_COMPRESSED_SIZE + G1_COMPRESSED_SIZE * self.commitments.len(),
+        );
+        out.extend_from_slice(&self.sender.to_be_bytes());
+        out.extend_from_slice(&self.receiver.to_be_bytes());
+        self.share.serialize(&mut out, true).unwrap();
+        for c in &self.commitments {
+            c.serialize(&mut out, true).unwrap();
+        }
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for LinkSecretContribution {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        let header = 8 + FR_COMPRESSED_SIZE;
+        if value.len() < header + G1_COMPRESSED_SIZE
+            || !(value.len() - header).is_multiple_of(G1_COMPRESSED_SIZE)
+        {
+            return Err(BBSErrorKind::InvalidNumberOfBytes(
+                header + G1_COMPRESSED_SIZE,
+                value.len(),
+            )
+            .into());
+        }
+        let mut c = &value[8..];
+        let share = Fr::deserialize(&mut c, true)?;
+        let mut commitments = Vec::with_capacity(c.len() / G1_COMPRESSED_SIZE);
+        while !c.is_empty() {
+            commitments.push(G1::deserialize(&mut c, true)?);
+        }
+        Ok(Self {
+            sender: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            receiver: u32::from_be_bytes(*array_ref![value, 4, 4]),
+            share,
+            commitments,
+        })
+    }
+}
+
+impl Drop for LinkSecretContribution {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// A device's share of the link secret
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkSecretShare {
+    index: u32,
+    threshold: usize,
+    share: Fr,
+    /// g1^link_secret, the same on every device
+    public_key: G1,
+}
+
+impl LinkSecretShare {
+    const SIZE: usize = 8 + FR_COMPRESSED_SIZE + G1_COMPRESSED_SIZE;
+
+    /// The device this share belongs to, from 1 to `n`
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// How many devices are needed to use the link secret
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Identifies the link secret. Devices compare it after generation to make sure
+    /// they all hold shares of the same secret, it must not be shown to anyone else
+    /// since it links all of the holder's credentials.
+    pub fn public_key(&self) -> GeneratorG1 {
+        GeneratorG1(self.public_key)
+    }
+
+    /// Join a session for the hidden message with generator `base`, usually
+    /// `verkey.h[i]` of the issuer key where the link secret is message `i`
+    pub fn start_session(&self, base: &GeneratorG1) -> (DeviceSession, DeviceCommitment) {
+        let blinding = rand_non_zero_fr();
+        let mut public_share = base.0;
+        public_share.mul_assign(self.share);
+        let mut commitment = base.0;
+        commitment.mul_assign(blinding);
+        (
+            DeviceSession {
+                index: self.index,
+                share: self.share,
+                blinding,
+            },
+            DeviceCommitment {
+                index: self.index,
+                public_share,
+                commitment,
+            },
+        )
+    }
+
+    /// Convert to raw bytes for storage on the device
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&(self.threshold as u32).to_be_bytes());
+        self.share.serialize(&mut out, true).unwrap();
+        self.public_key.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for LinkSecretShare {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[8..];
+        Ok(Self {
+            index: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            threshold: u32::from_be_bytes(*array_ref![value, 4, 4]) as usize,
+            share: Fr::deserialize(&mut c, true)?,
+            public_key: G1::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+impl Drop for LinkSecretShare {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// The state a device keeps between its commitment and its response
+#[derive(Debug)]
+pub struct DeviceSession {
+    index: u32,
+    share: Fr,
+    blinding: Fr,
+}
+
+impl DeviceSession {
+    /// Answer the challenge of the request or proof. `participants` are the devices
+    /// of the session, from `SharedLinkSecret::participants`.
+    pub fn respond(
+        self,
+        challenge: &ProofChallenge,
+        participants: &[u32],
+    ) -> Result<DeviceResponse, BBSError> {
+        let participants: BTreeSet<u32> = participants.iter().copied().collect();
+        if !participants.contains(&self.index) {
+            return Err(general_error("This device is not part of the session"));
+        }
+        // blinding - c * lambda * share
+        let mut c = lagrange_at_zero(self.index, &participants);
+        c.mul_assign(&self.share);
+        c.mul_assign(&challenge.0);
+        let mut response = self.blinding;
+        response.sub_assign(&c);
+        Ok(DeviceResponse {
+            index: self.index,
+            response,
+        })
+    }
+}
+
+impl Drop for DeviceSession {
+    fn drop(&mut self) {
+        self.share.zeroize();
+        self.blinding.zeroize();
+    }
+}
+
+/// What a device sends to the coordinator when it joins a session
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceCommitment {
+    index: u32,
+    /// base^share
+    public_share: G1,
+    /// base^blinding
+    commitment: G1,
+}
+
+impl DeviceCommitment {
+    const SIZE: usize = 4 + 2 * G1_COMPRESSED_SIZE;
+
+    /// The device that created this commitment
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        self.public_share.serialize(&mut out, true).unwrap();
+        self.commitment.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for DeviceCommitment {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[4..];
+        Ok(Self {
+            index: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            public_share: G1::deserialize(&mut c, true)?,
+            commitment: G1::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+/// A device's answer to a session's challenge
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceResponse {
+    index: u32,
+    response: Fr,
+}
+
+impl DeviceResponse {
+    const SIZE: usize = 4 + FR_COMPRESSED_SIZE;
+
+    /// The device that created this response
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        self.response.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for DeviceResponse {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[4..];
+        Ok(Self {
+            index: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            response: Fr::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+/// The coordinator's view of the link secret for one session. It stands in for the
+/// link secret, which the coordinator never learns.
+#[derive(Clone, Debug)]
+pub struct SharedLinkSecret {
+    participants: BTreeSet<u32>,
+    /// base^link_secret
+    point: G1,
+    /// The sum of the devices' commitments
+    commitment: G1,
+}
+
+impl SharedLinkSecret {
+    /// Combine the commitments of at least `threshold` devices
+    pub fn new(threshold: usize, commitments: &[DeviceCommitment]) -> Result<Self, BBSError> {
+        let participants: BTreeSet<u32> = commitments.iter().map(|c| c.index).collect();
+        if participants.len() != commitments.len() || participants.contains(&0) {
+            return Err(general_error("Invalid device indices"));
+        }
+        if threshold < 2 || participants.len() < threshold {
+            return Err(general_error("Not enough devices"));
+        }
+        let lambdas: Vec<Fr> = commitments
+            .iter()
+            .map(|c| lagrange_at_zero(c.index, &participants))
+            .collect();
+        let shares: Vec<G1> = commitments.iter().map(|c| c.public_share).collect();
+        let point = multi_scalar_mul_var_time_g1(&shares, &lambdas);
+        let mut commitment = G1::zero();
+        for c in commitments {
+            commitment.add_assign(&c.commitment);
+        }
+        Ok(Self {
+            participants,
+            point,
+            commitment,
+        })
+    }
+
+    /// The devices in this session, which each needs for its response
+    pub fn participants(&self) -> Vec<u32> {
+        self.participants.iter().copied().collect()
+    }
+
+    /// Create the request for a blind signature where the link secret is message
+    /// `link_index` and `messages` are any other hidden messages the coordinator knows
+    pub fn new_blind_signature_context(
+        &self,
+        verkey: &PublicKey,
+        link_index: usize,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        nonce: &ProofNonce,
+    ) -> Result<(PendingBlindSignatureContext, SignatureBlinding), BBSError> {
+        check_link_index(verkey, link_index, messages.keys())?;
+        let mut hidden = messages.clone();
+        hidden.insert(link_index, SignatureMessage(Fr::zero()));
+
+        let blinding_factor = Signature::generate_blinding();
+        let mut bases = vec![verkey.h0.0];
+        let mut scalars = vec![blinding_factor.0];
+        let mut committing = ProverCommittingG1::new();
+        committing.commit(verkey.h0);
+        let mut secrets = vec![SignatureMessage(blinding_factor.0)];
+        for (i, m) in &hidden {
+            bases.push(verkey.h[*i].0);
+            scalars.push(m.0);
+            if *i == link_index {
+                // The devices' commitments are added below
+                committing.commit_with(verkey.h[*i], SignatureMessage(Fr::zero()));
+            } else {
+                committing.commit(verkey.h[*i]);
+            }
+            secrets.push(*m);
+        }
+        let mut commitment = multi_scalar_mul_var_time_g1(&bases, &scalars);
+        commitment.add_assign(&self.point);
+        let commitment = Commitment(commitment);
+
+        let mut committed = committing.finish();
+        committed.add_to_commitment(&self.commitment);
+        let mut extra = Vec::new();
+        extra.extend_from_slice(&commitment.to_bytes_uncompressed_form()[..]);
+        extra.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        let challenge_hash = committed.gen_challenge(extra);
+        let proof = committed.gen_proof(&challenge_hash, secrets.as_slice())?;
+        let position = 1 + hidden.keys().take_while(|i| **i != link_index).count();
+        Ok((
+            PendingBlindSignatureContext {
+                ctx: BlindSignatureContext {
+                    commitment,
+                    challenge_hash,
+                    proof_of_hidden_messages: proof,
+                },
+                position,
+            },
+            blinding_factor,
+        ))
+    }
+
+    /// Unblind and verify a signature where the link secret is message `link_index`
+    /// and `messages` are all the other messages in order
+    pub fn complete_signature(
+        &self,
+        verkey: &PublicKey,
+        link_index: usize,
+        messages: &[SignatureMessage],
+        blind_signature: &BlindSignature,
+        blinding_factor: &SignatureBlinding,
+    ) -> Result<Signature, BBSError> {
+        let signature = blind_signature.to_unblinded(blinding_factor);
+        signature.validate()?;
+        let b = self.b(&signature, verkey, link_index, messages)?;
+        if signature.verify_b(b, verkey) {
+            Ok(signature)
+        } else {
+            Err(general_error("Invalid signature."))
+        }
+    }
+
+    /// Start a proof of knowledge of `signature` where the link secret is message
+    /// `link_index`, which stays hidden, and `messages` are all the other messages
+    pub fn commit_signature_pok(
+        &self,
+        verkey: &PublicKey,
+        link_index: usize,
+        messages: Vec<ProofMessage>,
+        signature: &Signature,
+    ) -> Result<SharedPoKOfSignature, BBSError> {
+        verkey.validate()?;
+        signature.validate()?;
+        let others: Vec<SignatureMessage> = messages.iter().map(|m| m.get_message()).collect();
+        let b = self.b(signature, verkey, link_index, &others)?;
+        let mut messages = messages;
+        messages.insert(
+            link_index,
+            ProofMessage::Hidden(HiddenMessage::ExternalBlinding(
+                SignatureMessage(Fr::zero()),
+                ProofNonce(Fr::zero()),
+            )),
+        );
+        let position = 2 + messages[..link_index]
+            .iter()
+            .filter(|m| matches!(m, ProofMessage::Hidden(_)))
+            .count();
+        let mut pok = PoKOfSignature::init_with_b(signature, verkey, None, &messages, b)?;
+        pok.add_to_commitment_2(&self.commitment);
+        Ok(SharedPoKOfSignature { pok, position })
+    }
+
+    /// b with the link secret's point in place of the message
+    fn b(
+        &self,
+        signature: &Signature,
+        verkey: &PublicKey,
+        link_index: usize,
+        messages: &[SignatureMessage],
+    ) -> Result<G1, BBSError> {
+        if messages.len() + 1 != verkey.message_count() {
+            return Err(BBSErrorKind::PublicKeyGeneratorMessageCountMismatch(
+                verkey.message_count(),
+                messages.len() + 1,
+            )
+            .into());
+        }
+        check_link_index(verkey, link_index, std::iter::empty())?;
+        let mut all = messages.to_vec();
+        all.insert(link_index, SignatureMessage(Fr::zero()));
+        let mut b = signature.get_b(&all, verkey);
+        b.add_assign(&self.point);
+        Ok(b)
+    }
+
+    /// The sum of the devices' responses, checking every participant answered
+    fn response(&self, responses: &[DeviceResponse]) -> Result<Fr, BBSError> {
+        let indices: BTreeSet<u32> = responses.iter().map(|r| r.index).collect();
+        if indices.len() != responses.len() || indices != self.participants {
+            return Err(general_error(
+                "Expected one response from every device in the session",
+            ));
+        }
+        let mut sum = Fr::zero();
+        for r in responses {
+            sum.add_assign(&r.response);
+        }
+        Ok(sum)
+    }
+}
+
+/// A blind signature request that waits for the devices' responses
+#[derive(Debug, Clone)]
+pub struct PendingBlindSignatureContext {
+    ctx: BlindSignatureContext,
+    /// Where the link secret's response goes
+    position: usize,
+}
+
+impl PendingBlindSignatureContext {
+    /// The challenge the devices answer
+    pub fn challenge(&self) -> ProofChallenge {
+        self.ctx.challenge_hash
+    }
+
+    /// Add the devices' responses and return the request to send to the issuer
+    pub fn finish(
+        self,
+        link: &SharedLinkSecret,
+        responses: &[DeviceResponse],
+    ) -> Result<BlindSignatureContext, BBSError> {
+        let response = link.response(responses)?;
+        let mut ctx = self.ctx;
+        ctx.proof_of_hidden_messages.responses[self.position].add_assign(&response);
+        Ok(ctx)
+    }
+}
+
+/// A proof of knowledge of a signature that waits for the devices' responses
+#[derive(Debug, Clone)]
+pub struct SharedPoKOfSignature {
+    pok: PoKOfSignature,
+    /// Where the link secret's response goes
+    position: usize,
+}
+
+impl SharedPoKOfSignature {
+    /// Return byte representation of public elements so they can be used for challenge computation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pok.to_bytes()
+    }
+
+    /// Create the challenge the devices answer, like `Prover::create_challenge_hash`
+    pub fn create_challenge_hash(
+        &self,
+        claims: Option<&[&[u8]]>,
+        nonce: &ProofNonce,
+    ) -> ProofChallenge {
+        let mut bytes = self.to_bytes();
+        bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        if let Some(add_claims) = claims {
+            for c in add_claims {
+                bytes.extend_from_slice(c);
+            }
+        }
+        ProofChallenge::hash(&bytes)
+    }
+
+    /// Add the devices' responses and complete the proof
+    pub fn generate_signature_pok(
+        self,
+        challenge: &ProofChallenge,
+        link: &SharedLinkSecret,
+        responses: &[DeviceResponse],
+    ) -> Result<SignatureProof, BBSError> {
+        let response = link.response(responses)?;
+        let revealed_messages = self.pok.revealed_messages.clone();
+        let mut proof = self.pok.gen_proof(challenge)?;
+        proof.proof_vc_2.responses[self.position].add_assign(&response);
+        Ok(SignatureProof {
+            revealed_messages,
+            proof,
+        })
+    }
+}
+
+fn check_device_threshold(threshold: usize, total: usize) -> Result<(), BBSError> {
+    if threshold < 2 || total < threshold || total > u32::MAX as usize {
+        return Err(BBSErrorKind::GeneralError {
+            msg: format!(
+                "Expected a threshold of at least 2 and at least as many devices, found {} of {}",
+                threshold, total
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn check_link_index<'a, I: Iterator<Item = &'a usize>>(
+    verkey: &PublicKey,
+    link_index: usize,
+    mut others: I,
+) -> Result<(), BBSError> {
+    if link_index >= verkey.message_count() {
+        return Err(general_error("Link secret index is out of range"));
+    }
+    if others.any(|i| *i == link_index || *i >= verkey.message_count()) {
+        return Err(general_error("Hidden message index is out of range"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::Issuer;
+    use crate::verifier::Verifier;
+    use crate::{HashElem, RandomElem};
+
+    fn devices(threshold: usize, total: usize) -> Vec<LinkSecretShare> {
+        let rounds: Vec<_> = (1..=total as u32)
+            .map(|i| LinkSecretRound::new(i, threshold, total).unwrap())
+            .collect();
+        let mut inboxes: Vec<Vec<LinkSecretContribution>> = vec![Vec::new(); total];
+        for round in &rounds {
+            for (j, inbox) in inboxes.iter_mut().enumerate() {
+                let c = round.contribution(j as u32 + 1).unwrap();
+                inbox.push(LinkSecretContribution::try_from(c.to_bytes().as_slice()).unwrap());
+            }
+        }
+        rounds
+            .into_iter()
+            .zip(inboxes.iter())
+            .map(|(round, inbox)| round.finish(inbox).unwrap())
+            .collect()
+    }
+
+    fn session(
+        shares: &[&LinkSecretShare],
+        base: &GeneratorG1,
+    ) -> (SharedLinkSecret, Vec<DeviceSession>) {
+        let (sessions, commitments): (Vec<_>, Vec<_>) =
+            shares.iter().map(|s| s.start_session(base)).unzip();
+        let link = SharedLinkSecret::new(shares[0].threshold(), &commitments).unwrap();
+        (link, sessions)
+    }
+
+    fn respond(
+        sessions: Vec<DeviceSession>,
+        challenge: &ProofChallenge,
+        link: &SharedLinkSecret,
+    ) -> Vec<DeviceResponse> {
+        sessions
+            .into_iter()
+            .map(|s| s.respond(challenge, &link.participants()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn issue_and_present() {
+        let (pk, sk) = Issuer::new_keys(4).unwrap();
+        let shares = devices(2, 3);
+        assert!(shares
+            .iter()
+            .all(|s| s.public_key() == shares[0].public_key()));
+
+        // Devices 1 and 3 request a credential with the link secret as message 1
+        let nonce = ProofNonce::random();
+        let (link, sessions) = session(&[&shares[0], &shares[2]], &pk.h[1]);
+        let mut hidden = BTreeMap::new();
+        hidden.insert(3, SignatureMessage::hash(b"hidden"));
+        let (pending, blinding) = link
+            .new_blind_signature_context(&pk, 1, &hidden, &nonce)
+            .unwrap();
+        let responses = respond(sessions, &pending.challenge(), &link);
+        let ctx = pending.finish(&link, &responses).unwrap();
+
+        let mut known = BTreeMap::new();
+        known.insert(0, SignatureMessage::hash(b"name"));
+        known.insert(2, SignatureMessage::hash(b"age"));
+        let blind_signature = Issuer::blind_sign(&ctx, &known, &sk, &pk, &nonce).unwrap();
+        let others = vec![known[&0], known[&2], hidden[&3]];
+        let signature = link
+            .complete_signature(&pk, 1, &others, &blind_signature, &blinding)
+            .unwrap();
+
+        // Devices 2 and 3 present it
+        let request = Verifier::new_proof_request(&[0], &pk).unwrap();
+        let (link, sessions) = session(&[&shares[1], &shares[2]], &pk.h[1]);
+        let messages = vec![
+            ProofMessage::Revealed(others[0]),
+            ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(others[1])),
+            ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(others[2])),
+        ];
+        let pok = link
+            .commit_signature_pok(&pk, 1, messages, &signature)
+            .unwrap();
+        let nonce = ProofNonce::random();
+        let challenge = pok.create_challenge_hash(None, &nonce);
+        let responses = respond(sessions, &challenge, &link);
+        let proof = pok
+            .generate_signature_pok(&challenge, &link, &responses)
+            .unwrap();
+        assert_eq!(
+            Verifier::verify_signature_pok(&request, &proof, &nonce).unwrap(),
+            vec![others[0]]
+        );
+    }
+
+    #[test]
+    fn needs_threshold_devices() {
+        let (pk, _) = Issuer::new_keys(2).unwrap();
+        let shares = devices(3, 4);
+        let (_, commitments): (Vec<_>, Vec<_>) = shares[..2]
+            .iter()
+            .map(|s| s.start_session(&pk.h[0]))
+            .unzip();
+        assert!(SharedLinkSecret::new(3, &commitments).is_err());
+
+        // Every device that committed has to respond
+        let (link, sessions) = session(&[&shares[0], &shares[1], &shares[3]], &pk.h[0]);
+        let (pending, _) = link
+            .new_blind_signature_context(&pk, 0, &BTreeMap::new(), &ProofNonce::random())
+            .unwrap();
+        let challenge = pending.challenge();
+        let mut responses = respond(sessions, &challenge, &link);
+        responses.pop();
+        assert!(pending.finish(&link, &responses).is_err());
+
+        // A device doesn't answer for sessions it isn't part of
+        let (session, _) = shares[2].start_session(&pk.h[0]);
+        assert!(session.respond(&challenge, &link.participants()).is_err());
+    }
+
+    #[test]
+    fn wrong_shares_are_rejected() {
+        let rounds: Vec<_> = (1..=3)
+            .map(|i| LinkSecretRound::new(i, 2, 3).unwrap())
+            .collect();
+        let mut inbox: Vec<_> = rounds.iter().map(|r| r.contribution(1).unwrap()).collect();
+        inbox[1].share.add_assign(&Fr::one());
+        assert!(LinkSecretRound::new(1, 2, 3)
+            .unwrap()
+            .finish(&inbox)
+            .is_err());
+        assert!(rounds[0].contribution(4).is_err());
+        assert!(LinkSecretRound::new(1, 1, 3).is_err());
+        assert!(LinkSecretRound::new(1, 4, 3).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let (pk, _) = Issuer::new_keys(2).unwrap();
+        let share = devices(2, 2).remove(0);
+        let bytes = share.to_bytes();
+        assert_eq!(LinkSecretShare::try_from(bytes.as_slice()).unwrap(), share);
+        assert!(LinkSecretShare::try_from(&bytes[1..]).is_err());
+
+        let (session, commitment) = share.start_session(&pk.h[0]);
+        let bytes = commitment.to_bytes();
+        assert_eq!(
+            DeviceCommitment::try_from(bytes.as_slice()).unwrap(),
+            commitment
+        );
+        let response = session.respond(&ProofChallenge::random(), &[1, 2]).unwrap();
+        let bytes = response.to_bytes();
+        assert_eq!(
+            DeviceResponse::try_from(bytes.as_slice()).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Proofs that a hidden message of a signature equals the value in a Pedersen
+//! commitment created somewhere else, such as a value committed on a ledger.
+//!
+//! The commitment is `g^m * h^r` for generators `g` and `h` chosen by whoever made
+//! it. The prover opens the signature proof with `HiddenMessage::ExternalBlinding`
+//! for the message and creates a `PoKOfPedersenOpening` with the same blinding, both
+//! answer one challenge so equal messages give equal responses. The verifier checks
+//! both proofs and the responses with `PedersenOpeningProof::verify_equality`.
+//!
+//! Equality only holds if nobody knows the discrete log of `h` to base `g`, the
+//! generators should come from hashing like `PedersenGenerators::hash` does.
+
+use crate::errors::prelude::*;
+use crate::pok_sig::prelude::*;
+use crate::pok_vc::prelude::*;
+use crate::{
+    Commitment, CommitmentBuilder, GeneratorG1, HashElem, ProofChallenge, ProofNonce, ProofRequest,
+    SignatureMessage, SignatureProof, G1_COMPRESSED_SIZE,
+};
+use pairing_plus::serdes::SerDes;
+use std::convert::TryFrom;
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{PedersenGenerators, PedersenOpeningProof, PoKOfPedersenOpening};
+}
+
+/// The generators of an external Pedersen commitment `g^m * h^r`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PedersenGenerators {
+    /// The base for the message
+    pub g: GeneratorG1,
+    /// The base for the randomness
+    pub h: GeneratorG1,
+}
+
+impl PedersenGenerators {
+    /// Derive both generators from `label`, so nobody knows a relation between them
+    pub fn hash<I: AsRef<[u8]>>(label: I) -> Self {
+        let mut g_label = b"PEDERSEN_G:".to_vec();
+        g_label.extend_from_slice(label.as_ref());
+        let mut h_label = b"PEDERSEN_H:".to_vec();
+        h_label.extend_from_slice(label.as_ref());
+        Self {
+            g: GeneratorG1::hash(g_label),
+            h: GeneratorG1::hash(h_label),
+        }
+    }
+
+    /// Commit to `message` with `randomness`
+    pub fn commit(&self, message: &SignatureMessage, randomness: &SignatureMessage) -> Commitment {
+        let mut builder = CommitmentBuilder::new();
+        builder.add(self.g, message);
+        builder.add(self.h, randomness);
+        builder.finalize()
+    }
+
+    fn bases(&self) -> [GeneratorG1; 2] {
+        [self.g, self.h]
+    }
+}
+
+/// The prover's side of a proof of knowledge of the opening of a Pedersen commitment
+#[derive(Clone, Debug)]
+pub struct PoKOfPedersenOpening {
+    committed: ProverCommittedG1,
+    secrets: [SignatureMessage; 2],
+}
+
+impl PoKOfPedersenOpening {
+    /// Start the proof for the commitment to `message` with `randomness`.
+    /// `blinding` must be the one used for the message in the signature proof.
+    pub fn init(
+        generators: &PedersenGenerators,
+        message: SignatureMessage,
+        randomness: SignatureMessage,
+        blinding: &ProofNonce,
+    ) -> Self {
+        let mut committing = ProverCommittingG1::new();
+        committing.commit_with(generators.g, blinding);
+        committing.commit(generators.h);
+        Self {
+            committed: committing.finish(),
+            secrets: [message, randomness],
+        }
+    }
+
+    /// Return byte representation of public elements so they can be used for challenge computation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.committed.to_bytes()
+    }
+
+    /// Create the challenge for this proof and `pok_sig`, like `Prover::create_challenge_hash`
+    pub fn create_challenge_hash(
+        &self,
+        pok_sig: &PoKOfSignature,
+        claims: Option<&[&[u8]]>,
+        nonce: &ProofNonce,
+    ) -> ProofChallenge {
+        let mut bytes = pok_sig.to_bytes();
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        if let Some(add_claims) = claims {
+            for c in add_claims {
+                bytes.extend_from_slice(c);
+            }
+        }
+        ProofChallenge::hash(&bytes)
+    }
+
+    /// Given the challenge value, compute the responses
+    pub fn gen_proof(self, challenge: &ProofChallenge) -> Result<PedersenOpeningProof, BBSError> {
+        let proof = self.committed.gen_proof(challenge, &self.secrets)?;
+        Ok(PedersenOpeningProof { proof })
+    }
+}
+
+/// A proof of knowledge of the opening of a Pedersen commitment
+#[derive(Clone, Debug)]
+pub struct PedersenOpeningProof {
+    proof: ProofG1,
+}
+
+impl PedersenOpeningProof {
+    /// Return bytes that need to be hashed for generating challenge
+    pub fn get_bytes_for_challenge(&self, generators: &PedersenGenerators) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for b in generators.bases().iter() {
+            b.0.serialize(&mut bytes, false).unwrap();
+        }
+        self.proof.commitment.serialize(&mut bytes, false).unwrap();
+        bytes
+    }
+
+    /// The response for the committed message, equal to the signature proof's
+    /// response when both hold the same message
+    pub fn message_response(&self) -> SignatureMessage {
+        SignatureMessage(self.proof.responses[0])
+    }
+
+    /// Validate the proof on its own given the challenge
+    pub fn verify(
+        &self,
+        generators: &PedersenGenerators,
+        commitment: &Commitment,
+        challenge: &ProofChallenge,
+    ) -> Result<bool, BBSError> {
+        if self.proof.responses.len() != 2 {
+            return Ok(false);
+        }
+        Ok(self
+            .proof
+            .verify(&generators.bases(), commitment, challenge)?)
+    }
+
+    /// Check `signature_proof` and this proof together and that message
+    /// `message_index` of the signature, which has to be hidden, is the value in
+    /// `commitment`. Returns the revealed messages like `Verifier::verify_signature_pok`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_equality(
+        &self,
+        proof_request: &ProofRequest,
+        signature_proof: &SignatureProof,
+        message_index: usize,
+        generators: &PedersenGenerators,
+        commitment: &Commitment,
+        claims: Option<&[&[u8]]>,
+        nonce: &ProofNonce,
+    ) -> Result<Vec<SignatureMessage>, BBSError> {
+        let vk = &proof_request.verification_key;
+        if message_index >= vk.message_count()
+            || proof_request.revealed_messages.contains(&message_index)
+        {
+            return Err(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Message {} has to be one of the {} hidden messages",
+                    message_index,
+                    vk.message_count()
+                ),
+            }
+            .into());
+        }
+        let mut bytes = signature_proof
+            .proof
+            .get_bytes_for_challenge(proof_request.revealed_messages.clone(), vk);
+        bytes.extend_from_slice(&self.get_bytes_for_challenge(generators));
+        bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        if let Some(add_claims) = claims {
+            for c in add_claims {
+                bytes.extend_from_slice(c);
+            }
+        }
+        let challenge = ProofChallenge::hash(&bytes);
+
+        match signature_proof
+            .proof
+            .verify(vk, &signature_proof.revealed_messages, &challenge)?
+        {
+            PoKOfSignatureProofStatus::Success => {}
+            e => return Err(BBSErrorKind::InvalidProof { status: e }.into()),
+        }
+        if !self.verify(generators, commitment, &challenge)? {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "Invalid proof of the commitment opening".to_string(),
+            }
+            .into());
+        }
+        let hidden_index = (0..message_index)
+            .filter(|i| !proof_request.revealed_messages.contains(i))
+            .count();
+        if signature_proof.proof.get_resp_for_message(hidden_index)? != self.message_response() {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "The committed value is not the hidden message".to_string(),
+            }
+            .into());
+        }
+        Ok(signature_proof
+            .revealed_messages
+            .values()
+            .copied()
+            .collect())
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes(true)
+    }
+}
+
+impl TryFrom<&[u8]> for PedersenOpeningProof {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        let proof = ProofG1::from_bytes(value, G1_COMPRESSED_SIZE, true)?;
+        Ok(Self { proof })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::Issuer;
+    use crate::messages::*;
+    use crate::prover::Prover;
+    use crate::verifier::Verifier;
+    use crate::RandomElem;
+
+    #[test]
+    fn hidden_message_equals_commitment() {
+        let (pk, sk) = Issuer::new_keys(3).unwrap();
+        let messages: Vec<_> = (0..3)
+            .map(|i| SignatureMessage::hash(format!("message {}", i)))
+            .collect();
+        let signature = crate::signature::Signature::new(&messages, &sk, &pk).unwrap();
+
+        // Message 1 was committed on a ledger
+        let generators = PedersenGenerators::hash(b"ledger");
+        let randomness = SignatureMessage::random();
+        let commitment = generators.commit(&messages[1], &randomness);
+
+        let request = Verifier::new_proof_request(&[0], &pk).unwrap();
+        let blinding = ProofNonce::random();
+        let proof_messages = vec![
+            pm_revealed_raw!(messages[0]),
+            pm_hidden_raw!(messages[1], blinding),
+            pm_hidden_raw!(messages[2]),
+        ];
+        let pok = PoKOfSignature::init(&signature, &pk, &proof_messages).unwrap();
+        let opening = PoKOfPedersenOpening::init(&generators, messages[1], randomness, &blinding);
+        let nonce = ProofNonce::random();
+        let challenge = opening.create_challenge_hash(&pok, None, &nonce);
+        let signature_proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let opening_proof = opening.gen_proof(&challenge).unwrap();
+        let opening_proof =
+            PedersenOpeningProof::try_from(opening_proof.to_bytes().as_slice()).unwrap();
+
+        let revealed = opening_proof
+            .verify_equality(
+                &request,
+                &signature_proof,
+                1,
+                &generators,
+                &commitment,
+                None,
+                &nonce,
+            )
+            .unwrap();
+        assert_eq!(revealed, vec![messages[0]]);
+
+        // Another hidden message, a revealed one or another commitment don't match
+        assert!(opening_proof
+            .verify_equality(
+                &request,
+                &signature_proof,
+                2,
+                &generators,
+                &commitment,
+                None,
+                &nonce
+            )
+            .is_err());
+        assert!(opening_proof
+            .verify_equality(
+                &request,
+                &signature_proof,
+                0,
+                &generators,
+                &commitment,
+                None,
+                &nonce
+            )
+            .is_err());
+        let other = generators.commit(&messages[2], &randomness);
+        assert!(opening_proof
+            .verify_equality(
+                &request,
+                &signature_proof,
+                1,
+                &generators,
+                &other,
+                None,
+                &nonce
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn different_values_are_rejected() {
+        let (pk, sk) = Issuer::new_keys(2).unwrap();
+        let messages = vec![SignatureMessage::hash(b"a"), SignatureMessage::hash(b"b")];
+        let signature = crate::signature::Signature::new(&messages, &sk, &pk).unwrap();
+
+        // The commitment holds another value than the signature
+        let generators = PedersenGenerators::hash(b"ledger");
+        let randomness = SignatureMessage::random();
+        let wrong = SignatureMessage::hash(b"c");
+        let commitment = generators.commit(&wrong, &randomness);
+
+        let request = Verifier::new_proof_request(&[], &pk).unwrap();
+        let blinding = ProofNonce::random();
+        let proof_messages = vec![
+            pm_hidden_raw!(messages[0], blinding),
+            pm_hidden_raw!(messages[1]),
+        ];
+        let pok = PoKOfSignature::init(&signature, &pk, &proof_messages).unwrap();
+        let opening = PoKOfPedersenOpening::init(&generators, wrong, randomness, &blinding);
+        let nonce = ProofNonce::random();
+        let challenge = opening.create_challenge_hash(&pok, None, &nonce);
+        let signature_proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let opening_proof = opening.gen_proof(&challenge).unwrap();
+        assert!(opening_proof
+            .verify_equality(
+                &request,
+                &signature_proof,
+                0,
+                &generators,
+                &commitment,
+                None,
+                &nonce
+            )
+            .is_err());
+    }
+}
@@ -1,18 +1,18 @@
 use crate::errors::prelude::*;
-use crate::keys::PublicKey;
+use crate::keys::{PreparedPublicKey, PublicKey};
 use crate::messages::*;
 use crate::pok_vc::prelude::*;
-use crate::signature::Signature;
+use crate::signature::{RandomizedSignature, Signature};
 use crate::{
-    multi_scalar_mul_const_time_g1, rand_non_zero_fr, Commitment, CommitmentBuilder, GeneratorG1,
-    ProofChallenge, SignatureMessage, ToVariableLengthBytes, G1_COMPRESSED_SIZE,
-    G1_UNCOMPRESSED_SIZE,
+    join, multi_scalar_mul_const_time_g1, multi_scalar_mul_precomputed_g1,
+    multi_scalar_mul_var_time_g1, rand_non_zero_fr, Commitment, GeneratorG1, ProofChallenge,
+    SignatureMessage, ToVariableLengthBytes, G1_COMPRESSED_SIZE, G1_UNCOMPRESSED_SIZE,
 };
 
 use ff_zeroize::{Field, PrimeField};
 use pairing_plus::serdes::SerDes;
 use pairing_plus::{
-    bls12_381::{Bls12, Fq12, Fr, FrRepr, G1, G2},
+    bls12_381::{Bls12, Fq12, Fr, FrRepr, G2Prepared, G1, G2},
     CurveAffine, CurveProjective, Engine,
 };
 use serde::{
@@ -29,7 +29,9 @@ use wasm_bindgen::prelude::*;
 
 /// Convenience importing module
 pub mod prelude {
-    pub use super::{PoKOfSignature, PoKOfSignatureProof, PoKOfSignatureProofStatus};
+    pub use super::{
+        BatchVerificationItem, PoKOfSignature, PoKOfSignatureProof, PoKOfSignatureProofStatus,
+    };
 }
 
 /// Proof of Knowledge of a Signature that is used by the prover
@@ -118,6 +120,26 @@ impl PoKOfSignature {
         signature: &Signature,
         vk: &PublicKey,
         messages: &[ProofMessage],
+    ) -> Result<Self, BBSError> {
+        Self::init_with(signature, vk, None, messages)
+    }
+
+    /// Creates the initial proof data before a Fiat-Shamir calculation
+    /// using the precomputed tables in `prepared`.
+    /// Use this when creating many proofs with the same issuer key that signs many messages.
+    pub fn init_prepared(
+        signature: &Signature,
+        prepared: &PreparedPublicKey,
+        messages: &[ProofMessage],
+    ) -> Result<Self, BBSError> {
+        Self::init_with(signature, prepared.public_key(), Some(prepared), messages)
+    }
+
+    fn init_with(
+        signature: &Signature,
+        vk: &PublicKey,
+        prepared: Option<&PreparedPublicKey>,
+        messages: &[ProofMessage],
     ) -> Result<Self, BBSError> {
         if messages.len() != vk.message_count() {
             return Err(BBSError::from_kind(
@@ -127,64 +149,68 @@ impl PoKOfSignature {
                 ),
             ));
         }
+        vk.validate()?;
+        signature.validate()?;
+
         let sig_messages = messages
             .iter()
             .map(|m| m.get_message())
             .collect::<Vec<SignatureMessage>>();
-        if !signature.verify(sig_messages.as_slice(), &vk)? {
+
+        // b is needed both to check the signature and for the proof so only compute it once
+        let b = match prepared {
+            Some(p) => p.compute_b(&signature.s, sig_messages.as_slice()),
+            None => signature.get_b(sig_messages.as_slice(), vk),
+        };
+        Self::init_with_b(signature, vk, prepared, messages, b)
+    }
+
+    /// Same as `init` with `b` computed by the caller, for when a hidden message is
+    /// only known as a point
+    pub(crate) fn init_with_b(
+        signature: &Signature,
+        vk: &PublicKey,
+        prepared: Option<&PreparedPublicKey>,
+        messages: &[ProofMessage],
+        b: G1,
+    ) -> Result<Self, BBSError> {
+        // The pairings are independent of the proof commitments
+        let (valid, pok) = join(
+            || signature.verify_b(b, vk),
+            || Self::commit(signature, vk, prepared, messages, b),
+        );
+        if !valid {
             return Err(BBSErrorKind::PoKVCError {
                 msg: "The messages and signature do not match.".to_string(),
             }
             .into());
         }
+        Ok(pok)
+    }
 
-        let r1 = rand_non_zero_fr();
-        let r2 = rand_non_zero_fr();
-
-        let mut temp: Vec<SignatureMessage> = Vec::new();
-        for i in 0..messages.len() {
-            match &messages[i] {
-                ProofMessage::Revealed(r) => temp.push(*r),
-                ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(m)) => temp.push(*m),
-                ProofMessage::Hidden(HiddenMessage::ExternalBlinding(m, _)) => temp.push(*m),
-            }
-        }
-
-        let b = signature.get_b(temp.as_slice(), &vk);
-
-        let mut a_prime = signature.a;
-        a_prime.mul_assign(r1);
-
-        let mut a_bar_denom = a_prime;
-        a_bar_denom.mul_assign(signature.e);
-
-        let mut a_bar = b;
-        a_bar.mul_assign(r1);
-        a_bar.sub_assign(&a_bar_denom);
-
-        let mut r2_d = r2;
-        r2_d.negate();
-        let mut builder = CommitmentBuilder::new();
-        builder.add(&GeneratorG1(b), &SignatureMessage(r1));
-        builder.add(&vk.h0, &SignatureMessage(r2_d));
-
-        // d = b^r1 h0^-r2
-        let d = builder.finalize().0;
-
-        let r3 = r1.inverse().unwrap();
-
-        // s' = s - r2 r3
-        let mut s_prime = r2;
-        s_prime.mul_assign(&r3);
-        s_prime.negate();
-        s_prime.add_assign(&signature.s);
+    fn commit(
+        signature: &Signature,
+        vk: &PublicKey,
+        prepared: Option<&PreparedPublicKey>,
+        messages: &[ProofMessage],
+        b: G1,
+    ) -> Self {
+        let RandomizedSignature {
+            a_prime,
+            a_bar,
+            d,
+            e,
+            r2,
+            r3,
+            s_prime,
+        } = signature.randomize_b(b, vk);
 
         // For proving relation a_bar / d == a_prime^{-e} * h_0^r2
         let mut committing_1 = ProverCommittingG1::new();
         let mut secrets_1 = Vec::with_capacity(2);
         // For a_prime^{-e}
         committing_1.commit(&GeneratorG1(a_prime));
-        let mut sig_e = signature.e;
+        let mut sig_e = e;
         sig_e.negate();
         secrets_1.push(sig_e);
         // For h_0^r2
@@ -201,6 +227,8 @@ impl PoKOfSignature {
         // and can be efficiently computed as (g1 * h1^m1 * h2^m2.... * h_i^m_i)^-1 and inverse in elliptic group is a point negation which is very cheap
         let mut committing_2 = ProverCommittingG1::new();
         let mut secrets_2 = Vec::with_capacity(2 + messages.len());
+        // Tables for every base after d when the key is prepared
+        let mut tables_2 = Vec::new();
         // For d^-r3
         committing_2.commit(&GeneratorG1(d));
         let mut r3_d = r3;
@@ -209,6 +237,9 @@ impl PoKOfSignature {
         // h_0^s_prime
         committing_2.commit(&vk.h0);
         secrets_2.push(s_prime);
+        if let Some(p) = prepared {
+            tables_2.push(p.h0_table());
+        }
 
         let mut revealed_messages = BTreeMap::new();
 
@@ -216,6 +247,7 @@ impl PoKOfSignature {
             match &messages[i] {
                 ProofMessage::Revealed(r) => {
                     revealed_messages.insert(i, *r);
+                    continue;
                 }
                 ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(m)) => {
                     committing_2.commit(&vk.h[i]);
@@ -226,10 +258,26 @@ impl PoKOfSignature {
                     secrets_2.push(e.0);
                 }
             }
+            if let Some(p) = prepared {
+                tables_2.push(p.h_table(i));
+            }
         }
-        let pok_vc_2 = committing_2.finish();
+        let pok_vc_2 = if prepared.is_some() {
+            committing_2.finish_with(|bases, blinding_factors| {
+                // d is the only base without a table
+                let mut commitment = bases[0];
+                commitment.mul_assign(blinding_factors[0]);
+                commitment.add_assign(&multi_scalar_mul_precomputed_g1(
+                    tables_2.as_slice(),
+                    &blinding_factors[1..],
+                ));
+                commitment
+            })
+        } else {
+            committing_2.finish()
+        };
 
-        Ok(Self {
+        Self {
             a_prime,
             a_bar,
             d,
@@ -238,7 +286,12 @@ impl PoKOfSignature {
             pok_vc_2,
             secrets_2,
             revealed_messages,
-        })
+        }
+    }
+
+    /// Add a part of the commitment of the second relation created by other parties
+    pub(crate) fn add_to_commitment_2(&mut self, point: &G1) {
+        self.pok_vc_2.add_to_commitment(point);
     }
 
     /// Return byte representation of public elements so they can be used for challenge computation.
@@ -290,6 +343,19 @@ impl PoKOfSignature {
     }
 }
 
+/// A proof and the public values needed to check it with `PoKOfSignatureProof::verify_batch`
+#[derive(Debug, Clone, Copy)]
+pub struct BatchVerificationItem<'a> {
+    /// The proof to check
+    pub proof: &'a PoKOfSignatureProof,
+    /// The issuer's public key
+    pub vk: &'a PublicKey,
+    /// The messages revealed by the proof
+    pub revealed_msgs: &'a BTreeMap<usize, SignatureMessage>,
+    /// The challenge the proof was made for
+    pub challenge: &'a ProofChallenge,
+}
+
 impl PoKOfSignatureProof {
     /// Return bytes that need to be hashed for generating challenge. Takes `self.a_bar`,
     /// `self.a_prime` and `self.d` and commitment and instance data of the two proof of knowledge protocols.
@@ -298,22 +364,32 @@ impl PoKOfSignatureProof {
         revealed_msg_indices: BTreeSet<usize>,
         vk: &PublicKey,
     ) -> Vec<u8> {
+        let mut h0 = vec![];
+        vk.h0.0.serialize(&mut h0, false).unwrap();
+        let mut hidden_bases = vec![];
+        for i in 0..vk.message_count() {
+            if revealed_msg_indices.contains(&i) {
+                continue;
+            }
+            vk.h[i].0.serialize(&mut hidden_bases, false).unwrap();
+        }
+        self.get_bytes_for_challenge_with(&h0, &hidden_bases)
+    }
+
+    /// Same as `get_bytes_for_challenge` with the uncompressed `h0` and hidden
+    /// message bases already serialized
+    pub(crate) fn get_bytes_for_challenge_with(&self, h0: &[u8], hidden_bases: &[u8]) -> Vec<u8> {
         let mut bytes = vec![];
         self.a_bar.serialize(&mut bytes, false).unwrap();
         self.a_prime.serialize(&mut bytes, false).unwrap();
-        vk.h0.0.serialize(&mut bytes, false).unwrap();
+        bytes.extend_from_slice(h0);
         self.proof_vc_1
             .commitment
             .serialize(&mut bytes, false)
             .unwrap();
         self.d.serialize(&mut bytes, false).unwrap();
-        vk.h0.0.serialize(&mut bytes, false).unwrap();
-        for i in 0..vk.message_count() {
-            if revealed_msg_indices.contains(&i) {
-                continue;
-            }
-            vk.h[i].0.serialize(&mut bytes, false).unwrap();
-        }
+        bytes.extend_from_slice(h0);
+        bytes.extend_from_slice(hidden_bases);
         self.proof_vc_2
             .commitment
             .serialize(&mut bytes, false)
@@ -353,7 +429,25 @@ impl PoKOfSignatureProof {
                 }));
             }
         }
+        self.verify_prepared(
+            vk,
+            &vk.w.0.into_affine().prepare(),
+            &G2::one().into_affine().prepare(),
+            revealed_msgs,
+            challenge,
+        )
+    }
 
+    /// Same as `verify` with `w` and the generator of G2 already prepared for
+    /// pairings, for a `vk` that was validated and revealed indices that were checked
+    pub(crate) fn verify_prepared(
+        &self,
+        vk: &PublicKey,
+        w: &G2Prepared,
+        g2: &G2Prepared,
+        revealed_msgs: &BTreeMap<usize, SignatureMessage>,
+        challenge: &ProofChallenge,
+    ) -> Result<PoKOfSignatureProofStatus, BBSError> {
         if self.a_prime.is_zero() {
             return Ok(PoKOfSignatureProofStatus::BadSignature);
         }
@@ -361,14 +455,8 @@ impl PoKOfSignatureProof {
         let mut a_bar = self.a_bar;
         a_bar.negate();
         match Bls12::final_exponentiation(&Bls12::miller_loop(&[
-            (
-                &self.a_prime.into_affine().prepare(),
-                &vk.w.0.into_affine().prepare(),
-            ),
-            (
-                &a_bar.into_affine().prepare(),
-                &G2::one().into_affine().prepare(),
-            ),
+            (&self.a_prime.into_affine().prepare(), w),
+            (&a_bar.into_affine().prepare(), g2),
         ])) {
             None => return Ok(PoKOfSignatureProofStatus::BadSignature),
             Some(product) => {
@@ -378,19 +466,41 @@ impl PoKOfSignatureProof {
             }
         };
 
-        let mut bases = vec![];
-        bases.push(GeneratorG1(self.a_prime));
-        bases.push(vk.h0);
+        let ((bases, a_bar_d), (bases_pok_vc_2, pr)) = self.relations(vk, revealed_msgs);
+        if !self.proof_vc_1.verify(&bases, &a_bar_d, challenge)? {
+            return Ok(PoKOfSignatureProofStatus::BadHiddenMessage);
+        }
+
+        match self
+            .proof_vc_2
+            .verify(bases_pok_vc_2.as_slice(), &pr, challenge)
+        {
+            Ok(b) => {
+                if b {
+                    Ok(PoKOfSignatureProofStatus::Success)
+                } else {
+                    Ok(PoKOfSignatureProofStatus::BadRevealedMessage)
+                }
+            }
+            Err(_) => Ok(PoKOfSignatureProofStatus::BadRevealedMessage),
+        }
+    }
+
+    /// The bases and commitments of the two discrete log relations the proof is checked
+    /// against, `a_bar / d == a_prime^{-e} * h_0^r2` and
+    /// `g1 * h1^m1 * h2^m2.... for all disclosed messages m_i == d^r3 * h_0^{-s_prime} * h1^-m1 * h2^-m2....`
+    pub(crate) fn relations(
+        &self,
+        vk: &PublicKey,
+        revealed_msgs: &BTreeMap<usize, SignatureMessage>,
+    ) -> (
+        (Vec<GeneratorG1>, Commitment),
+        (Vec<GeneratorG1>, Commitment),
+    ) {
+        let bases = vec![GeneratorG1(self.a_prime), vk.h0];
         // a_bar / d
         let mut a_bar_d = self.a_bar;
         a_bar_d.sub_assign(&self.d);
-        // let a_bar_d = &self.a_bar - &self.d;
-        if !self
-            .proof_vc_1
-            .verify(&bases, &Commitment(a_bar_d), &challenge)?
-        {
-            return Ok(PoKOfSignatureProofStatus::BadHiddenMessage);
-        }
 
         let mut bases_pok_vc_2 = Vec::with_capacity(2 + vk.message_count() - revealed_msgs.len());
         bases_pok_vc_2.push(GeneratorG1(self.d));
@@ -414,19 +524,151 @@ impl PoKOfSignatureProof {
         // pr = g1 * h1^-m1 * h2^-m2.... = (g1 * h1^m1 * h2^m2....)^-1 for all disclosed messages m_i
         let mut pr = Commitment(multi_scalar_mul_const_time_g1(&bases_disclosed, &exponents));
         pr.0.negate();
-        match self
-            .proof_vc_2
-            .verify(bases_pok_vc_2.as_slice(), &pr, challenge)
-        {
-            Ok(b) => {
-                if b {
-                    Ok(PoKOfSignatureProofStatus::Success)
-                } else {
-                    Ok(PoKOfSignatureProofStatus::BadRevealedMessage)
+        ((bases, Commitment(a_bar_d)), (bases_pok_vc_2, pr))
+    }
+
+    /// Validate many proofs together.
+    ///
+    /// The pairing checks share a single final exponentiation and the discrete log
+    /// relations of all proofs are combined with random weights into one
+    /// multi-exponentiation, which is much faster than calling `verify` for each.
+    /// If the combined check fails each proof is checked with `verify` and the
+    /// status of the first bad one is returned.
+    pub fn verify_batch(
+        items: &[BatchVerificationItem],
+    ) -> Result<PoKOfSignatureProofStatus, BBSError> {
+        let mut pairs = Vec::with_capacity(items.len() + 1);
+        let mut a_bar_sum = G1::zero();
+        let mut points = Vec::new();
+        let mut scalars = Vec::new();
+        for item in items {
+            item.vk.validate()?;
+            for i in item.revealed_msgs.keys() {
+                if *i >= item.vk.message_count() {
+                    return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                        msg: format!(
+                            "Index {} should be less than {}",
+                            i,
+                            item.vk.message_count()
+                        ),
+                    }));
                 }
             }
-            Err(_) => Ok(PoKOfSignatureProofStatus::BadRevealedMessage),
+            if item.proof.a_prime.is_zero() {
+                return Ok(PoKOfSignatureProofStatus::BadSignature);
+            }
+            let hidden = item.vk.message_count() - item.revealed_msgs.len();
+            if item.proof.proof_vc_1.responses.len() != 2
+                || item.proof.proof_vc_2.responses.len() != 2 + hidden
+            {
+                return Self::verify_each(items);
+            }
+
+            // e(A', w)^r == e(A_bar, g2)^r
+            let r = rand_non_zero_fr();
+            let mut a_prime = item.proof.a_prime;
+            a_prime.mul_assign(r);
+            let mut a_bar = item.proof.a_bar;
+            a_bar.mul_assign(r);
+            a_bar_sum.add_assign(&a_bar);
+            pairs.push((
+                a_prime.into_affine().prepare(),
+                item.vk.w.0.into_affine().prepare(),
+            ));
+
+            item.proof.batch_terms(
+                item,
+                rand_non_zero_fr(),
+                rand_non_zero_fr(),
+                &mut points,
+                &mut scalars,
+            );
+        }
+        a_bar_sum.negate();
+        pairs.push((
+            a_bar_sum.into_affine().prepare(),
+            G2::one().into_affine().prepare(),
+        ));
+
+        let pairs_ref = pairs.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+        let pairing_ok = match Bls12::final_exponentiation(&Bls12::miller_loop(&pairs_ref)) {
+            None => false,
+            Some(product) => product == Fq12::one(),
+        };
+        if pairing_ok && multi_scalar_mul_var_time_g1(&points, &scalars).is_zero() {
+            Ok(PoKOfSignatureProofStatus::Success)
+        } else {
+            Self::verify_each(items)
+        }
+    }
+
+    /// Append the weighted terms of both discrete log relations for a batch, they sum to zero
+    /// for a valid proof.
+    fn batch_terms(
+        &self,
+        item: &BatchVerificationItem,
+        weight_1: Fr,
+        weight_2: Fr,
+        points: &mut Vec<G1>,
+        scalars: &mut Vec<Fr>,
+    ) {
+        let weighted = |a: &Fr, w: &Fr| {
+            let mut t = *a;
+            t.mul_assign(w);
+            t
+        };
+        let mut neg_challenge = item.challenge.0;
+        neg_challenge.negate();
+
+        // A'^r1 * h0^r2 * (A_bar / d)^c / T1
+        let mut a_bar_d = self.a_bar;
+        a_bar_d.sub_assign(&self.d);
+        points.push(self.a_prime);
+        points.push(item.vk.h0.0);
+        points.push(a_bar_d);
+        points.push(self.proof_vc_1.commitment);
+        scalars.extend(
+            self.proof_vc_1
+                .responses
+                .iter()
+                .map(|s| weighted(s, &weight_1)),
+        );
+        scalars.push(weighted(&item.challenge.0, &weight_1));
+        let mut neg_weight = weight_1;
+        neg_weight.negate();
+        scalars.push(neg_weight);
+
+        // d^-e * h0^r3 * h_i^m_i for hidden i * (g1 * h_j^m_j for revealed j)^-c / T2
+        points.push(self.d);
+        points.push(item.vk.h0.0);
+        scalars.push(weighted(&self.proof_vc_2.responses[0], &weight_2));
+        scalars.push(weighted(&self.proof_vc_2.responses[1], &weight_2));
+        let mut hidden = self.proof_vc_2.responses[2..].iter();
+        for i in 0..item.vk.message_count() {
+            points.push(item.vk.h[i].0);
+            match item.revealed_msgs.get(&i) {
+                Some(m) => scalars.push(weighted(&weighted(&m.0, &neg_challenge), &weight_2)),
+                None => scalars.push(weighted(hidden.next().unwrap(), &weight_2)),
+            }
         }
+        points.push(G1::one());
+        scalars.push(weighted(&neg_challenge, &weight_2));
+        points.push(self.proof_vc_2.commitment);
+        let mut neg_weight = weight_2;
+        neg_weight.negate();
+        scalars.push(neg_weight);
+    }
+
+    fn verify_each(items: &[BatchVerificationItem]) -> Result<PoKOfSignatureProofStatus, BBSError> {
+        for item in items {
+            let status = item
+                .proof
+                .verify(item.vk, item.revealed_msgs, item.challenge)?;
+            if status != PoKOfSignatureProofStatus::Success {
+                return Ok(status);
+            }
+        }
+        Ok(PoKOfSignatureProofStatus::Success)
     }
 
     /// Convert the proof to raw bytes
@@ -693,6 +935,78 @@ mod tests {
             .is_valid());
     }
 
+    #[test]
+    fn pok_signature_prepared_key() {
+        let message_count = 60;
+        let messages: Vec<SignatureMessage> = (0..message_count)
+            .map(|_| SignatureMessage::random())
+            .collect();
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let prepared = PreparedPublicKey::new(&verkey).unwrap();
+
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(
+            prepared.compute_b(&sig.s, messages.as_slice()),
+            sig.get_b(messages.as_slice(), &verkey)
+        );
+
+        let mut revealed_indices = BTreeSet::new();
+        let mut revealed_msgs = BTreeMap::new();
+        let mut proof_messages = Vec::with_capacity(message_count);
+        for (i, m) in messages.iter().enumerate() {
+            if i % 7 == 0 {
+                revealed_indices.insert(i);
+                revealed_msgs.insert(i, *m);
+                proof_messages.push(pm_revealed_raw!(*m));
+            } else if i % 3 == 0 {
+                proof_messages.push(pm_hidden_raw!(*m, ProofNonce::random()));
+            } else {
+                proof_messages.push(pm_hidden_raw!(*m));
+            }
+        }
+
+        let pok =
+            PoKOfSignature::init_prepared(&sig, &prepared, proof_messages.as_slice()).unwrap();
+        let challenge_prover = ProofChallenge::hash(pok.to_bytes());
+        let proof = pok.gen_proof(&challenge_prover).unwrap();
+
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_indices.clone(), &verkey);
+        let challenge_verifier = ProofChallenge::hash(&chal_bytes);
+        assert!(proof
+            .verify(&verkey, &revealed_msgs, &challenge_verifier)
+            .unwrap()
+            .is_valid());
+
+        // Wrong messages are still caught
+        proof_messages[1] = pm_hidden_raw!(SignatureMessage::random());
+        assert!(PoKOfSignature::init_prepared(&sig, &prepared, proof_messages.as_slice()).is_err());
+    }
+
+    #[ignore]
+    #[test]
+    fn speed_pok_signature_prepared_key() {
+        let message_count = 100;
+        let messages: Vec<SignatureMessage> = (0..message_count)
+            .map(|_| SignatureMessage::random())
+            .collect();
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let proof_messages: Vec<ProofMessage> =
+            messages.iter().map(|m| pm_hidden_raw!(*m)).collect();
+
+        let start = std::time::Instant::now();
+        let _ = PoKOfSignature::init(&sig, &verkey, proof_messages.as_slice()).unwrap();
+        println!("init = {:?}", std::time::Instant::now() - start);
+
+        let start = std::time::Instant::now();
+        let prepared = PreparedPublicKey::new(&verkey).unwrap();
+        println!("prepare = {:?}", std::time::Instant::now() - start);
+
+        let start = std::time::Instant::now();
+        let _ = PoKOfSignature::init_prepared(&sig, &prepared, proof_messages.as_slice()).unwrap();
+        println!("init_prepared = {:?}", std::time::Instant::now() - start);
+    }
+
     #[test]
     fn test_pok_multiple_sigs_with_same_msg() {
         // Prove knowledge of multiple signatures and the equality of a specific message under both signatures.
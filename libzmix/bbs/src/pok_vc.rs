@@ -204,7 +204,18 @@ impl ProverCommittingG1 {
 
     /// Add pairwise product of (`self.bases`, self.blindings). Uses multi-exponentiation.
     pub fn finish(self) -> ProverCommittedG1 {
-        let commitment = multi_scalar_mul_const_time_g1(&self.bases, &self.blinding_factors);
+        self.finish_with(|bases, blinding_factors| {
+            multi_scalar_mul_const_time_g1(bases, blinding_factors)
+        })
+    }
+
+    /// Same as `finish` but the pairwise product is computed by `product`
+    /// so callers can use precomputed tables for some of the bases
+    pub(crate) fn finish_with<F: FnOnce(&[G1], &[Fr]) -> G1>(
+        self,
+        product: F,
+    ) -> ProverCommittedG1 {
+        let commitment = product(&self.bases, &self.blinding_factors);
         ProverCommittedG1 {
             bases: self.bases,
             blinding_factors: self.blinding_factors,
@@ -234,6 +245,12 @@ impl Default for ProverCommittingG1 {
 }
 
 impl ProverCommittedG1 {
+    /// Add a part of the commitment created by other parties, whose blinding
+    /// factors aren't known here
+    pub(crate) fn add_to_commitment(&mut self, point: &G1) {
+        self.commitment.add_assign(point);
+    }
+
     /// Convert the committed values to a byte array. Use for generating the fiat-shamir challenge
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
@@ -0,0 +1,336 @@
+//! Proof requests compiled once and reused for many presentations.
+//!
+//! Every presentation against the same `ProofRequest` repeats the same setup:
+//! validating the verification key, multiplying its generators, serializing the
+//! generators that go into the challenge and preparing `w` and the generator of G2
+//! for pairings. A `ProofProgram` does that work once. Provers use it to commit to
+//! signature proofs and verifiers use it to check them, the proofs are the same as
+//! the ones from `Prover` and `Verifier` so both sides can mix them freely.
+//!
+//! The zmix proof spec in `zkl` has no prover yet, so programs are compiled from
+//! the BBS+ `ProofRequest` that the spec is built from.
+
+use crate::errors::prelude::*;
+use crate::keys::prelude::*;
+use crate::messages::*;
+use crate::pok_sig::prelude::*;
+use crate::signature::prelude::*;
+use crate::{HashElem, ProofChallenge, ProofNonce, ProofRequest, SignatureMessage, SignatureProof};
+use pairing_plus::{
+    bls12_381::{G2Prepared, G2},
+    serdes::SerDes,
+    CurveAffine, CurveProjective,
+};
+
+/// Convenience module
+pub mod prelude {
+    pub use super::ProofProgram;
+}
+
+/// A `ProofRequest` with everything that does not depend on the signature or nonce precomputed
+#[derive(Clone, Debug)]
+pub struct ProofProgram {
+    request: ProofRequest,
+    prepared: PreparedPublicKey,
+    /// Uncompressed `h0`, added twice to the challenge
+    h0: Vec<u8>,
+    /// Uncompressed generators of the hidden messages in order
+    hidden_bases: Vec<u8>,
+    /// Position of each message in the responses of the proof or `None` if it is revealed
+    response_indices: Vec<Option<usize>>,
+    w: G2Prepared,
+    g2: G2Prepared,
+}
+
+impl ProofProgram {
+    /// Validate `request` and precompute its generators and challenge layout
+    pub fn compile(request: &ProofRequest) -> Result<Self, BBSError> {
+        let vk = &request.verification_key;
+        let prepared = PreparedPublicKey::new(vk)?;
+        if let Some(i) = request
+            .revealed_messages
+            .iter()
+            .find(|i| **i >= vk.message_count())
+        {
+            return Err(BBSErrorKind::GeneralError {
+                msg: format!("Index {} should be less than {}", i, vk.message_count()),
+            }
+            .into());
+        }
+
+        let mut h0 = Vec::new();
+        vk.h0.0.serialize(&mut h0, false).unwrap();
+        let mut hidden_bases = Vec::new();
+        let mut response_indices = Vec::with_capacity(vk.message_count());
+        let mut hidden = 0;
+        for i in 0..vk.message_count() {
+            if request.revealed_messages.contains(&i) {
+                response_indices.push(None);
+                continue;
+            }
+            vk.h[i].0.serialize(&mut hidden_bases, false).unwrap();
+            response_indices.push(Some(hidden));
+            hidden += 1;
+        }
+
+        Ok(Self {
+            request: request.clone(),
+            prepared,
+            h0,
+            hidden_bases,
+            response_indices,
+            w: vk.w.0.into_affine().prepare(),
+            g2: G2::one().into_affine().prepare(),
+        })
+    }
+
+    /// The request this program was compiled from
+    pub fn request(&self) -> &ProofRequest {
+        &self.request
+    }
+
+    /// Which response of a proof belongs to the hidden message at `message_index`.
+    /// Returns `None` if the message is revealed or out of range.
+    /// Use it to link a hidden message to other proofs that share its blinding.
+    pub fn response_index(&self, message_index: usize) -> Option<usize> {
+        self.response_indices.get(message_index).copied().flatten()
+    }
+
+    /// Commit to a signature proof that reveals the messages in the request and hides the rest
+    /// with blindings specific to this proof
+    pub fn commit_signature_pok(
+        &self,
+        messages: &[SignatureMessage],
+        signature: &Signature,
+    ) -> Result<PoKOfSignature, BBSError> {
+        let proof_messages: Vec<ProofMessage> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                if self.request.revealed_messages.contains(&i) {
+                    ProofMessage::Revealed(*m)
+                } else {
+                    ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(*m))
+                }
+            })
+            .collect();
+        PoKOfSignature::init_prepared(signature, &self.prepared, proof_messages.as_slice())
+    }
+
+    /// Same as `commit_signature_pok` but the prover classifies the messages,
+    /// for hidden messages with external blindings.
+    /// Fails if the revealed messages are not the ones in the request.
+    pub fn commit_signature_pok_with(
+        &self,
+        proof_messages: &[ProofMessage],
+        signature: &Signature,
+    ) -> Result<PoKOfSignature, BBSError> {
+        for (i, m) in proof_messages.iter().enumerate() {
+            let revealed = match m {
+                ProofMessage::Revealed(_) => true,
+                ProofMessage::Hidden(_) => false,
+            };
+            if revealed != self.request.revealed_messages.contains(&i) {
+                return Err(BBSErrorKind::GeneralError {
+                    msg: format!("Message {} does not match the proof request", i),
+                }
+                .into());
+            }
+        }
+        PoKOfSignature::init_prepared(signature, &self.prepared, proof_messages)
+    }
+
+    /// The bytes of `proof` that go into the challenge, same as
+    /// `PoKOfSignatureProof::get_bytes_for_challenge` for the request
+    pub fn challenge_bytes(&self, proof: &SignatureProof) -> Vec<u8> {
+        proof
+            .proof
+            .get_bytes_for_challenge_with(&self.h0, &self.hidden_bases)
+    }
+
+    /// Verify a signature proof of knowledge created for the request and return the revealed messages
+    pub fn verify_signature_pok(
+        &self,
+        signature_proof: &SignatureProof,
+        nonce: &ProofNonce,
+    ) -> Result<Vec<SignatureMessage>, BBSError> {
+        let mut challenge_bytes = self.challenge_bytes(signature_proof);
+        challenge_bytes.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
+        let challenge = ProofChallenge::hash(&challenge_bytes);
+        self.verify_with_challenge(signature_proof, &challenge)
+    }
+
+    /// Verify a signature proof of knowledge whose challenge also covers other proofs
+    /// and return the revealed messages
+    pub fn verify_with_challenge(
+        &self,
+        signature_proof: &SignatureProof,
+        challenge: &ProofChallenge,
+    ) -> Result<Vec<SignatureMessage>, BBSError> {
+        if !signature_proof
+            .revealed_messages
+            .keys()
+            .eq(self.request.revealed_messages.iter())
+        {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "The revealed messages do not match the proof request".to_string(),
+            }
+            .into());
+        }
+        match signature_proof.proof.verify_prepared(
+            &self.request.verification_key,
+            &self.w,
+            &self.g2,
+            &signature_proof.revealed_messages,
+            challenge,
+        )? {
+            PoKOfSignatureProofStatus::Success => Ok(signature_proof
+                .revealed_messages
+                .values()
+                .copied()
+                .collect()),
+            e => Err(BBSErrorKind::InvalidProof { status: e }.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn setup(count: usize) -> (PublicKey, Vec<SignatureMessage>, Signature) {
+        let (pk, sk) = Issuer::new_keys(count).unwrap();
+        let messages: Vec<SignatureMessage> = (0..count)
+            .map(|i| SignatureMessage::hash(format!("message {}", i)))
+            .collect();
+        let signature = Signature::new(messages.as_slice(), &sk, &pk).unwrap();
+        (pk, messages, signature)
+    }
+
+    #[test]
+    fn program_matches_prover_and_verifier() {
+        let (pk, messages, signature) = setup(5);
+        let request = Verifier::new_proof_request(&[1, 3], &pk).unwrap();
+        let program = ProofProgram::compile(&request).unwrap();
+        assert_eq!(program.response_index(0), Some(0));
+        assert_eq!(program.response_index(1), None);
+        assert_eq!(program.response_index(4), Some(2));
+        assert_eq!(program.response_index(5), None);
+
+        for _ in 0..3 {
+            let nonce = Verifier::generate_proof_nonce();
+            let pok = program
+                .commit_signature_pok(messages.as_slice(), &signature)
+                .unwrap();
+            let challenge =
+                Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+            let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+
+            let revealed = program.verify_signature_pok(&proof, &nonce).unwrap();
+            assert_eq!(revealed, vec![messages[1], messages[3]]);
+            assert_eq!(
+                Verifier::verify_signature_pok(&request, &proof, &nonce).unwrap(),
+                revealed
+            );
+            assert_eq!(
+                program.challenge_bytes(&proof),
+                proof
+                    .proof
+                    .get_bytes_for_challenge(request.revealed_messages.clone(), &pk)
+            );
+            assert!(program
+                .verify_signature_pok(&proof, &Verifier::generate_proof_nonce())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn program_rejects_other_requests() {
+        let (pk, messages, signature) = setup(4);
+        let request = Verifier::new_proof_request(&[0], &pk).unwrap();
+        let program = ProofProgram::compile(&request).unwrap();
+
+        let mut bad_request = request.clone();
+        bad_request.revealed_messages.insert(4);
+        assert!(ProofProgram::compile(&bad_request).is_err());
+
+        let proof_messages = vec![
+            pm_hidden_raw!(messages[0]),
+            pm_hidden_raw!(messages[1]),
+            pm_hidden_raw!(messages[2]),
+            pm_hidden_raw!(messages[3]),
+        ];
+        assert!(program
+            .commit_signature_pok_with(proof_messages.as_slice(), &signature)
+            .is_err());
+
+        let other = Verifier::new_proof_request(&[1], &pk).unwrap();
+        let nonce = Verifier::generate_proof_nonce();
+        let pok = Prover::commit_signature_pok(
+            &other,
+            &[
+                pm_hidden_raw!(messages[0]),
+                pm_revealed_raw!(messages[1]),
+                pm_hidden_raw!(messages[2]),
+                pm_hidden_raw!(messages[3]),
+            ],
+            &signature,
+        )
+        .unwrap();
+        let challenge =
+            Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+        let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        assert!(program.verify_signature_pok(&proof, &nonce).is_err());
+    }
+
+    #[ignore]
+    #[test]
+    fn speed_program_test() {
+        let (pk, messages, signature) = setup(32);
+        let request = Verifier::new_proof_request(&[0, 1, 2], &pk).unwrap();
+        let runs = 20;
+
+        let start = std::time::Instant::now();
+        for _ in 0..runs {
+            let nonce = Verifier::generate_proof_nonce();
+            let proof_messages: Vec<ProofMessage> = messages
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    if i < 3 {
+                        pm_revealed_raw!(*m)
+                    } else {
+                        pm_hidden_raw!(*m)
+                    }
+                })
+                .collect();
+            let pok = Prover::commit_signature_pok(&request, proof_messages.as_slice(), &signature)
+                .unwrap();
+            let challenge =
+                Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+            let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+            Verifier::verify_signature_pok(&request, &proof, &nonce).unwrap();
+        }
+        println!(
+            "prover and verifier = {:?}",
+            std::time::Instant::now() - start
+        );
+
+        let start = std::time::Instant::now();
+        let program = ProofProgram::compile(&request).unwrap();
+        println!("compile = {:?}", std::time::Instant::now() - start);
+        let start = std::time::Instant::now();
+        for _ in 0..runs {
+            let nonce = Verifier::generate_proof_nonce();
+            let pok = program
+                .commit_signature_pok(messages.as_slice(), &signature)
+                .unwrap();
+            let challenge =
+                Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+            let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+            program.verify_signature_pok(&proof, &nonce).unwrap();
+        }
+        println!("program = {:?}", std::time::Instant::now() - start);
+    }
+}
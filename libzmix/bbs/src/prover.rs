@@ -46,7 +46,7 @@ impl Prover {
         let mut secrets = Vec::new();
         secrets.push(SignatureMessage(blinding_factor.0));
         for (i, m) in messages {
-            if *i > verkey.h.len() {
+            if *i >= verkey.h.len() {
                 return Err(BBSErrorKind::PublicKeyGeneratorMessageCountMismatch(
                     *i,
                     verkey.h.len(),
@@ -68,9 +68,7 @@ impl Prover {
         extra.extend_from_slice(&commitment.to_bytes_uncompressed_form()[..]);
         extra.extend_from_slice(&nonce.to_bytes_uncompressed_form()[..]);
         let challenge_hash = committed.gen_challenge(extra);
-        let proof_of_hidden_messages = committed
-            .gen_proof(&challenge_hash, secrets.as_slice())
-            .unwrap();
+        let proof_of_hidden_messages = committed.gen_proof(&challenge_hash, secrets.as_slice())?;
 
         Ok((
             BlindSignatureContext {
@@ -116,6 +114,28 @@ impl Prover {
         PoKOfSignature::init(&signature, &request.verification_key, proof_messages)
     }
 
+    /// Same as `commit_signature_pok` but uses the precomputed tables
+    /// of the verification key in `request` which is much faster with many messages.
+    ///
+    /// # Arguments
+    /// * `request` - Proof request from verifier
+    /// * `prepared` - The verification key in `request` with its precomputed tables
+    /// * `proof_messages` - The messages to reveal or hide
+    pub fn commit_signature_pok_prepared(
+        request: &ProofRequest,
+        prepared: &PreparedPublicKey,
+        proof_messages: &[ProofMessage],
+        signature: &Signature,
+    ) -> Result<PoKOfSignature, BBSError> {
+        if prepared.public_key() != &request.verification_key {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "The prepared key does not match the proof request".to_string(),
+            }
+            .into());
+        }
+        PoKOfSignature::init_prepared(signature, prepared, proof_messages)
+    }
+
     /// Create the challenge hash for a set of proofs
     ///
     /// # Arguments
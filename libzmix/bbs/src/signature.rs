@@ -25,7 +25,8 @@ use wasm_bindgen::JsValue;
 /// Convenience module
 pub mod prelude {
     pub use super::{
-        BlindSignature, Signature, SIGNATURE_COMPRESSED_SIZE, SIGNATURE_UNCOMPRESSED_SIZE,
+        BlindSignature, RandomizedSignature, Signature, SIGNATURE_COMPRESSED_SIZE,
+        SIGNATURE_UNCOMPRESSED_SIZE,
     };
 }
 
@@ -160,6 +161,13 @@ impl BlindSignature {
             verkey.message_count(),
             messages.len()
         );
+        if let Some(i) = messages.keys().find(|i| **i >= verkey.message_count()) {
+            return Err(BBSErrorKind::PublicKeyGeneratorMessageCountMismatch(
+                *i,
+                verkey.message_count(),
+            )
+            .into());
+        }
         signkey.validate()?;
         verkey.validate()?;
         let e = rand_non_zero_fr();
@@ -287,12 +295,17 @@ impl Signature {
         verkey.validate()?;
         self.validate()?;
 
+        let b = self.get_b(messages, verkey);
+        Ok(self.verify_b(b, verkey))
+    }
+
+    /// Check e(A, w * g2^e) == e(b, g2) where `b` was computed from the messages
+    pub(crate) fn verify_b(&self, mut b: G1, verkey: &PublicKey) -> bool {
         let mut pqz = Vec::new();
         let mut a = G2::one();
         a.mul_assign(self.e);
         a.add_assign(&verkey.w.0);
 
-        let mut b = self.get_b(messages, verkey);
         b.negate();
         let b = b.into_affine().prepare();
         let g2 = G2::one().into_affine().prepare();
@@ -302,13 +315,11 @@ impl Signature {
 
         pqz.push((&a1, &a2));
         pqz.push((&b, &g2));
-        Ok(
-            //pair(a^(1/x+e), g2^(x+e), 1/b, g2)
-            match Bls12::final_exponentiation(&Bls12::miller_loop(&pqz[..])) {
-                None => false,
-                Some(product) => product == Fq12::one(),
-            },
-        )
+        //pair(a^(1/x+e), g2^(x+e), 1/b, g2)
+        match Bls12::final_exponentiation(&Bls12::miller_loop(&pqz[..])) {
+            None => false,
+            Some(product) => product == Fq12::one(),
+        }
     }
 
     /// Check if the signature is a valid form i.e. not infinity since it will always validate
@@ -387,6 +398,170 @@ display_impl!(Signature);
 #[cfg(feature = "wasm")]
 wasm_slice_impl!(Signature);
 
+/// A signature randomized as in section 4.5 to be shown without linking it to the signature
+/// or to other randomizations of it: `A' = A^r1`, `\overline{A} = A'^{-e} * b^r1` and
+/// `d = b^r1 * h0^{-r2}` for random `r1` and `r2`.
+///
+/// `e(A', w) == e(\overline{A}, g2)` holds for every randomization of every signature, and
+/// `A'` is a random point, so the public values reveal nothing about the signature. The
+/// holder keeps the secrets `e`, `r2`, `r3 = 1 / r1` and `s' = s - r2 * r3` to prove
+/// `\overline{A} / d == A'^{-e} * h0^r2` and `g1 * h_i^m_i == d^r3 * h0^{-s'} * ...`,
+/// which `PoKOfSignature` does.
+#[derive(Debug, Clone)]
+pub struct RandomizedSignature {
+    /// A'
+    pub(crate) a_prime: G1,
+    /// \overline{A}
+    pub(crate) a_bar: G1,
+    /// d
+    pub(crate) d: G1,
+    /// e
+    pub(crate) e: Fr,
+    /// r2
+    pub(crate) r2: Fr,
+    /// r3 = 1 / r1
+    pub(crate) r3: Fr,
+    /// s' = s - r2 * r3
+    pub(crate) s_prime: Fr,
+}
+
+impl Signature {
+    /// Randomize a signature on `messages`.
+    /// Fails if the signature doesn't verify
+    pub fn randomize(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<RandomizedSignature, BBSError> {
+        if !self.verify(messages, verkey)? {
+            return Err(BBSErrorKind::GeneralError {
+                msg: "The messages and signature do not match.".to_string(),
+            }
+            .into());
+        }
+        Ok(self.randomize_b(self.get_b(messages, verkey), verkey))
+    }
+
+    /// Randomize with `b` computed and checked by the caller
+    pub(crate) fn randomize_b(&self, b: G1, verkey: &PublicKey) -> RandomizedSignature {
+        let r1 = rand_non_zero_fr();
+        let r2 = rand_non_zero_fr();
+
+        let mut a_prime = self.a;
+        a_prime.mul_assign(r1);
+
+        let mut a_bar_denom = a_prime;
+        a_bar_denom.mul_assign(self.e);
+
+        let mut a_bar = b;
+        a_bar.mul_assign(r1);
+        a_bar.sub_assign(&a_bar_denom);
+
+        // d = b^r1 h0^-r2
+        let mut r2_d = r2;
+        r2_d.negate();
+        let d = multi_scalar_mul_const_time_g1([b, verkey.h0.0], [r1, r2_d]);
+
+        let r3 = r1.inverse().unwrap();
+
+        // s' = s - r2 r3
+        let mut s_prime = r2;
+        s_prime.mul_assign(&r3);
+        s_prime.negate();
+        s_prime.add_assign(&self.s);
+
+        RandomizedSignature {
+            a_prime,
+            a_bar,
+            d,
+            e: self.e,
+            r2,
+            r3,
+            s_prime,
+        }
+    }
+}
+
+impl RandomizedSignature {
+    /// A'
+    pub fn a_prime(&self) -> Commitment {
+        Commitment(self.a_prime)
+    }
+
+    /// \overline{A}
+    pub fn a_bar(&self) -> Commitment {
+        Commitment(self.a_bar)
+    }
+
+    /// d
+    pub fn d(&self) -> Commitment {
+        Commitment(self.d)
+    }
+
+    /// Randomize again without the signature: `r1` is multiplied by a random `t`
+    /// and `r2` is replaced
+    pub fn rerandomize(&self, verkey: &PublicKey) -> Self {
+        let t = rand_non_zero_fr();
+        let r2 = rand_non_zero_fr();
+
+        let mut a_prime = self.a_prime;
+        a_prime.mul_assign(t);
+        let mut a_bar = self.a_bar;
+        a_bar.mul_assign(t);
+
+        // b^(r1 t) h0^-r2 = (d h0^r2_old)^t h0^-r2
+        let mut d_t = t;
+        d_t.mul_assign(&self.r2);
+        d_t.sub_assign(&r2);
+        let d = multi_scalar_mul_const_time_g1([self.d, verkey.h0.0], [t, d_t]);
+
+        let mut r3 = self.r3;
+        r3.mul_assign(&t.inverse().unwrap());
+
+        // s' = s - r2 r3 = s'_old + r2_old r3_old - r2 r3
+        let mut s_prime = self.r2;
+        s_prime.mul_assign(&self.r3);
+        s_prime.add_assign(&self.s_prime);
+        let mut r2_r3 = r2;
+        r2_r3.mul_assign(&r3);
+        s_prime.sub_assign(&r2_r3);
+
+        Self {
+            a_prime,
+            a_bar,
+            d,
+            e: self.e,
+            r2,
+            r3,
+            s_prime,
+        }
+    }
+
+    /// Check `e(A', w) == e(\overline{A}, g2)`, the part of a proof that doesn't need the
+    /// secrets. Anyone can check it, but only a proof of the secrets shows `A'` came from
+    /// a signature on messages
+    pub fn verify(&self, verkey: &PublicKey) -> bool {
+        if self.a_prime.is_zero() {
+            return false;
+        }
+        let mut a_bar = self.a_bar;
+        a_bar.negate();
+        match Bls12::final_exponentiation(&Bls12::miller_loop(&[
+            (
+                &self.a_prime.into_affine().prepare(),
+                &verkey.w.0.into_affine().prepare(),
+            ),
+            (
+                &a_bar.into_affine().prepare(),
+                &G2::one().into_affine().prepare(),
+            ),
+        ])) {
+            None => false,
+            Some(product) => product == Fq12::one(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -540,4 +715,61 @@ mod tests {
         badsig3.s = Fr::zero();
         assert!(badsig3.validate().is_err());
     }
+
+    #[test]
+    fn signature_randomize() {
+        let message_count = 5;
+        let mut messages = Vec::new();
+        for _ in 0..message_count {
+            messages.push(SignatureMessage::random());
+        }
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        // g1 * h_i^m_i
+        let mut h0_s = verkey.h0.0;
+        h0_s.mul_assign(sig.s);
+        let mut b = sig.get_b(messages.as_slice(), &verkey);
+        b.sub_assign(&h0_s);
+
+        let r1 = sig.randomize(messages.as_slice(), &verkey).unwrap();
+        let r2 = sig.randomize(messages.as_slice(), &verkey).unwrap();
+        let r3 = r1.rerandomize(&verkey);
+        let r4 = other.randomize(messages.as_slice(), &verkey).unwrap();
+        let all = [&r1, &r2, &r3, &r4];
+        for (i, r) in all.iter().enumerate() {
+            assert!(r.verify(&verkey));
+            // Every randomization satisfies \overline{A} = A'^x like a fresh signature
+            let mut a_bar = r.a_prime;
+            a_bar.mul_assign(signkey.0);
+            assert_eq!(a_bar, r.a_bar);
+            assert_ne!(r.a_prime, sig.a);
+            assert_ne!(r.a_prime, other.a);
+            for s in &all[i + 1..] {
+                assert_ne!(r.a_prime(), s.a_prime());
+                assert_ne!(r.a_bar(), s.a_bar());
+                assert_ne!(r.d(), s.d());
+            }
+        }
+        for r in &all[..3] {
+            // d^r3 h0^-s' = g1 * h_i^m_i
+            let mut s_prime = r.s_prime;
+            s_prime.negate();
+            let b_2 = multi_scalar_mul_const_time_g1([r.d, verkey.h0.0], [r.r3, s_prime]);
+            assert_eq!(b_2, b);
+        }
+
+        let mut bad_messages = messages.clone();
+        bad_messages[0] = SignatureMessage::random();
+        assert!(sig.randomize(bad_messages.as_slice(), &verkey).is_err());
+        let (other_verkey, _) = generate(message_count).unwrap();
+        assert!(sig.randomize(messages.as_slice(), &other_verkey).is_err());
+        assert!(!r1.verify(&other_verkey));
+        let mut bad = r1.clone();
+        bad.a_bar = G1::one();
+        assert!(!bad.verify(&verkey));
+        bad.a_prime = G1::zero();
+        bad.a_bar = G1::zero();
+        assert!(!bad.verify(&verkey));
+    }
 }
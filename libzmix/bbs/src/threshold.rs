@@ -0,0 +1,760 @@
+//! Threshold issuance splits the issuer's secret key into Shamir shares held by `n`
+//! issuer nodes. Any `2t - 1` of them jointly create a signature and fewer than `t`
+//! learn nothing about the key, so no single node can issue credentials on its own.
+//!
+//! Signing needs a presignature which the nodes create ahead of time in one round
+//! where every node sends a `PresignatureContribution` to every other node. Each
+//! presignature holds a share of a random `r` and a share of zero. The online step
+//! is then non-interactive, every node turns its presignature into a
+//! `PartialSignature` and a combiner that collects `2t - 1` of them computes
+//! `u = r(x + e)` and `B^r` and the signature `A = (B^r)^(1/u)`.
+//!
+//! A presignature must only be used **once**, using one twice for different
+//! messages reveals the secret key share. Contributions contain secret shares
+//! and must be sent over authenticated and encrypted channels. The combiner checks
+//! the resulting signature, but can't tell which node sent a bad partial signature.
+
+use crate::errors::prelude::*;
+use crate::keys::prelude::*;
+use crate::signature::prelude::*;
+use crate::{
+    hash_to_fr, multi_scalar_mul_var_time_g1, rand_non_zero_fr, BlindSignatureContext, Commitment,
+    ProofNonce, SignatureMessage, FR_COMPRESSED_SIZE, G1_COMPRESSED_SIZE,
+};
+use blake2::digest::{Input, VariableOutput};
+use ff_zeroize::{Field, PrimeField};
+use pairing_plus::{
+    bls12_381::{Fr, FrRepr, G1},
+    serdes::SerDes,
+    CurveProjective,
+};
+use rand::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use zeroize::Zeroize;
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{
+        PartialSignature, Presignature, PresignatureContribution, PresignatureRound,
+        SecretKeyShare, ThresholdIssuer, PRESIGNATURE_ID_SIZE,
+    };
+}
+
+/// The number of bytes in a presignature id
+pub const PRESIGNATURE_ID_SIZE: usize = 32;
+const PRESIGNATURE_DST: &[u8] = b"BBS+_THRESHOLD_PRESIGNATURE:1_0_0";
+const E_DST: &[u8] = b"BBS+_THRESHOLD_E:1_0_0";
+const S_DST: &[u8] = b"BBS+_THRESHOLD_S:1_0_0";
+
+/// A node's Shamir share of the issuer's secret key
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SecretKeyShare {
+    index: u32,
+    share: Fr,
+}
+
+impl SecretKeyShare {
+    /// The node this share belongs to, from 1 to `n`
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.index.to_be_bytes().to_vec();
+        self.share.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for SecretKeyShare {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, 4 + FR_COMPRESSED_SIZE)?;
+        let mut c = &value[4..];
+        Ok(Self {
+            index: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            share: Fr::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+impl Drop for SecretKeyShare {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// The state a node keeps while presignatures are being created
+#[derive(Debug)]
+pub struct PresignatureRound {
+    index: u32,
+    threshold: usize,
+    total: usize,
+    nonce: [u8; 32],
+    /// Degree `t - 1`, shares the random `r`
+    r: Vec<Fr>,
+    /// Degree `2t - 2` with a constant of zero, masks the shares of `u`
+    z: Vec<Fr>,
+}
+
+impl PresignatureRound {
+    /// Start a round as node `index` of `total` nodes
+    pub fn new(index: u32, threshold: usize, total: usize) -> Result<Self, BBSError> {
+        check_threshold(threshold, total)?;
+        check_index(index, total)?;
+        let mut rng = thread_rng();
+        let mut nonce = [0u8; 32];
+        rng.fill_bytes(&mut nonce);
+        let r = random_polynomial(rand_non_zero_fr(), threshold - 1);
+        let z = random_polynomial(Fr::zero(), 2 * threshold - 2);
+        Ok(Self {
+            index,
+            threshold,
+            total,
+            nonce,
+            r,
+            z,
+        })
+    }
+
+    /// The contribution to send to node `receiver`. A node also sends one to itself.
+    pub fn contribution(&self, receiver: u32) -> Result<PresignatureContribution, BBSError> {
+        check_index(receiver, self.total)?;
+        let x = index_to_fr(receiver);
+        Ok(PresignatureContribution {
+            sender: self.index,
+            receiver,
+            nonce: self.nonce,
+            r: evaluate(&self.r, &x),
+            z: evaluate(&self.z, &x),
+        })
+    }
+
+    /// Combine the contributions received from the other nodes into a presignature.
+    /// At least `threshold` nodes have to contribute and every node that signs with
+    /// this presignature must use contributions from the same nodes.
+    pub fn finish(
+        self,
+        contributions: &[PresignatureContribution],
+    ) -> Result<Presignature, BBSError> {
+        let mut senders = BTreeMap::new();
+        for c in contributions {
+            if c.receiver != self.index {
+                return Err(general_error("Contribution is for another node"));
+            }
+            check_index(c.sender, self.total)?;
+            if senders.insert(c.sender, c).is_some() {
+                return Err(general_error("Duplicate contribution"));
+            }
+        }
+        if senders.len() < self.threshold {
+            return Err(general_error("Not enough contributions"));
+        }
+        if senders.get(&self.index).map(|c| c.nonce) != Some(self.nonce) {
+            return Err(general_error("Missing this node's own contribution"));
+        }
+
+        let mut hasher = blake2::VarBlake2b::new(PRESIGNATURE_ID_SIZE).unwrap();
+        hasher.input(PRESIGNATURE_DST);
+        hasher.input((self.threshold as u32).to_be_bytes());
+        let mut r = Fr::zero();
+        let mut z = Fr::zero();
+        for (sender, c) in senders.iter() {
+            hasher.input(sender.to_be_bytes());
+            hasher.input(c.nonce);
+            r.add_assign(&c.r);
+            z.add_assign(&c.z);
+        }
+        let mut id = [0u8; PRESIGNATURE_ID_SIZE];
+        hasher.variable_result(|out| id.copy_from_slice(out));
+        Ok(Presignature {
+            id,
+            index: self.index,
+            threshold: self.threshold,
+            r,
+            z,
+        })
+    }
+}
+
+impl Drop for PresignatureRound {
+    fn drop(&mut self) {
+        self.r.iter_mut().for_each(|c| c.zeroize());
+        self.z.iter_mut().for_each(|c| c.zeroize());
+    }
+}
+
+/// The shares one node sends another while creating a presignature
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PresignatureContribution {
+    sender: u32,
+    receiver: u32,
+    nonce: [u8; 32],
+    r: Fr,
+    z: Fr,
+}
+
+impl PresignatureContribution {
+    const SIZE: usize = 8 + 32 + 2 * FR_COMPRESSED_SIZE;
+
+    /// The node that created this contribution
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The node this contribution is for
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.sender.to_be_bytes());
+        out.extend_from_slice(&self.receiver.to_be_bytes());
+        out.extend_from_slice(&self.nonce);
+        self.r.serialize(&mut out, true).unwrap();
+        self.z.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for PresignatureContribution {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[40..];
+        Ok(Self {
+            sender: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            receiver: u32::from_be_bytes(*array_ref![value, 4, 4]),
+            nonce: *array_ref![value, 8, 32],
+            r: Fr::deserialize(&mut c, true)?,
+            z: Fr::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+impl Drop for PresignatureContribution {
+    fn drop(&mut self) {
+        self.r.zeroize();
+        self.z.zeroize();
+    }
+}
+
+/// A node's share of the randomness for one signature. Signing consumes it.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Presignature {
+    id: [u8; PRESIGNATURE_ID_SIZE],
+    index: u32,
+    threshold: usize,
+    r: Fr,
+    z: Fr,
+}
+
+impl Presignature {
+    const SIZE: usize = PRESIGNATURE_ID_SIZE + 8 + 2 * FR_COMPRESSED_SIZE;
+
+    /// The same on every node that finished the round with the same contributions
+    pub fn id(&self) -> [u8; PRESIGNATURE_ID_SIZE] {
+        self.id
+    }
+
+    /// Convert to raw bytes for storage until the presignature is used
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.id);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&(self.threshold as u32).to_be_bytes());
+        self.r.serialize(&mut out, true).unwrap();
+        self.z.serialize(&mut out, true).unwrap();
+        out
+    }
+
+    /// `e` and `s` are derived from the id so every node uses the same values
+    fn e_and_s(&self) -> (Fr, Fr) {
+        e_and_s(&self.id)
+    }
+}
+
+impl TryFrom<&[u8]> for Presignature {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[PRESIGNATURE_ID_SIZE + 8..];
+        Ok(Self {
+            id: *array_ref![value, 0, PRESIGNATURE_ID_SIZE],
+            index: u32::from_be_bytes(*array_ref![value, PRESIGNATURE_ID_SIZE, 4]),
+            threshold: u32::from_be_bytes(*array_ref![value, PRESIGNATURE_ID_SIZE + 4, 4]) as usize,
+            r: Fr::deserialize(&mut c, true)?,
+            z: Fr::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+impl Drop for Presignature {
+    fn drop(&mut self) {
+        self.r.zeroize();
+        self.z.zeroize();
+    }
+}
+
+/// One node's part of a signature
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartialSignature {
+    index: u32,
+    presignature_id: [u8; PRESIGNATURE_ID_SIZE],
+    /// B^r_i
+    a: G1,
+    /// r_i(x_i + e) + z_i
+    u: Fr,
+}
+
+impl PartialSignature {
+    const SIZE: usize = 4 + PRESIGNATURE_ID_SIZE + G1_COMPRESSED_SIZE + FR_COMPRESSED_SIZE;
+
+    /// The node that created this partial signature
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Convert to raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&self.presignature_id);
+        self.a.serialize(&mut out, true).unwrap();
+        self.u.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for PartialSignature {
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, BBSError> {
+        check_length(value, Self::SIZE)?;
+        let mut c = &value[4 + PRESIGNATURE_ID_SIZE..];
+        Ok(Self {
+            index: u32::from_be_bytes(*array_ref![value, 0, 4]),
+            presignature_id: *array_ref![value, 4, PRESIGNATURE_ID_SIZE],
+            a: G1::deserialize(&mut c, true)?,
+            u: Fr::deserialize(&mut c, true)?,
+        })
+    }
+}
+
+/// Steps taken by the issuer nodes and the combiner
+pub struct ThresholdIssuer;
+
+impl ThresholdIssuer {
+    /// Create a keypair capable of signing `message_count` messages and split the
+    /// secret key among `total` nodes. The key is never needed again.
+    pub fn new_keys(
+        message_count: usize,
+        threshold: usize,
+        total: usize,
+    ) -> Result<(PublicKey, Vec<SecretKeyShare>), BBSError> {
+        check_threshold(threshold, total)?;
+        let (pk, sk) = generate(message_count)?;
+        let shares = Self::share_secret_key(&sk, threshold, total)?;
+        Ok((pk, shares))
+    }
+
+    /// Split an existing secret key among `total` nodes. Any `threshold` shares
+    /// reconstruct it, and signing needs `2 * threshold - 1` nodes.
+    pub fn share_secret_key(
+        signkey: &SecretKey,
+        threshold: usize,
+        total: usize,
+    ) -> Result<Vec<SecretKeyShare>, BBSError> {
+        check_threshold(threshold, total)?;
+        signkey.validate()?;
+        let mut polynomial = random_polynomial(signkey.0, threshold - 1);
+        let shares = (1..=total as u32)
+            .map(|index| SecretKeyShare {
+                index,
+                share: evaluate(&polynomial, &index_to_fr(index)),
+            })
+            .collect();
+        polynomial.iter_mut().for_each(|c| c.zeroize());
+        Ok(shares)
+    }
+
+    /// Create this node's part of a signature where all messages are known
+    pub fn partial_sign(
+        messages: &[SignatureMessage],
+        share: &SecretKeyShare,
+        presignature: Presignature,
+        verkey: &PublicKey,
+    ) -> Result<PartialSignature, BBSError> {
+        let messages = messages.iter().copied().enumerate().collect();
+        partial(None, &messages, share, presignature, verkey)
+    }
+
+    /// Verify a proof of committed messages and create this node's part of a blind
+    /// signature. Every node checks the proof itself.
+    pub fn partial_blind_sign(
+        ctx: &BlindSignatureContext,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        share: &SecretKeyShare,
+        presignature: Presignature,
+        verkey: &PublicKey,
+        nonce: &ProofNonce,
+    ) -> Result<PartialSignature, BBSError> {
+        let revealed_messages: BTreeSet<usize> = messages.keys().copied().collect();
+        if !ctx.verify(&revealed_messages, verkey, nonce)? {
+            return Err(general_error("Invalid proof of committed messages"));
+        }
+        partial(Some(&ctx.commitment), messages, share, presignature, verkey)
+    }
+
+    /// Combine at least `2 * threshold - 1` partial signatures on the same messages
+    pub fn combine(
+        partials: &[PartialSignature],
+        threshold: usize,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<Signature, BBSError> {
+        let messages = messages.iter().copied().enumerate().collect();
+        let (a, e, s) = combine(partials, threshold, None, &messages, verkey)?;
+        Ok(Signature { a, e, s })
+    }
+
+    /// Combine at least `2 * threshold - 1` partial blind signatures. The holder
+    /// unblinds the result like any other blind signature.
+    pub fn combine_blind(
+        partials: &[PartialSignature],
+        threshold: usize,
+        commitment: &Commitment,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        verkey: &PublicKey,
+    ) -> Result<BlindSignature, BBSError> {
+        let (a, e, s) = combine(partials, threshold, Some(commitment), messages, verkey)?;
+        Ok(BlindSignature { a, e, s })
+    }
+}
+
+fn partial(
+    commitment: Option<&Commitment>,
+    messages: &BTreeMap<usize, SignatureMessage>,
+    share: &SecretKeyShare,
+    presignature: Presignature,
+    verkey: &PublicKey,
+) -> Result<PartialSignature, BBSError> {
+    if share.index != presignature.index {
+        return Err(general_error("Presignature belongs to another node"));
+    }
+    let (e, s) = presignature.e_and_s();
+    let mut a = compute_b(commitment, &s, messages, verkey)?;
+    a.mul_assign(presignature.r);
+    let mut u = share.share;
+    u.add_assign(&e);
+    u.mul_assign(&presignature.r);
+    u.add_assign(&presignature.z);
+    Ok(PartialSignature {
+        index: share.index,
+        presignature_id: presignature.id,
+        a,
+        u,
+    })
+}
+
+fn combine(
+    partials: &[PartialSignature],
+    threshold: usize,
+    commitment: Option<&Commitment>,
+    messages: &BTreeMap<usize, SignatureMessage>,
+    verkey: &PublicKey,
+) -> Result<(G1, Fr, Fr), BBSError> {
+    if threshold < 2 || partials.len() < 2 * threshold - 1 {
+        return Err(general_error("Not enough partial signatures"));
+    }
+    let id = partials[0].presignature_id;
+    if partials.iter().any(|p| p.presignature_id != id) {
+        return Err(general_error(
+            "Partial signatures use different presignatures",
+        ));
+    }
+    let indices: BTreeSet<u32> = partials.iter().map(|p| p.index).collect();
+    if indices.len() != partials.len() || indices.contains(&0) {
+        return Err(general_error("Invalid partial signature indices"));
+    }
+
+    let mut points = Vec::with_capacity(partials.len());
+    let mut scalars = Vec::with_capacity(partials.len());
+    let mut u = Fr::zero();
+    for p in partials {
+        let mut lambda = lagrange_at_zero(p.index, &indices);
+        points.push(p.a);
+        scalars.push(lambda);
+        lambda.mul_assign(&p.u);
+        u.add_assign(&lambda);
+    }
+    let u_inv = u
+        .inverse()
+        .ok_or_else(|| general_error("Partial signatures combine to zero"))?;
+    let mut a = multi_scalar_mul_var_time_g1(&points, &scalars);
+    a.mul_assign(u_inv);
+
+    let (e, s) = e_and_s(&id);
+    let signature = Signature { a, e, s };
+    signature.validate()?;
+    if !signature.verify_b(compute_b(commitment, &s, messages, verkey)?, verkey) {
+        return Err(general_error(
+            "Partial signatures do not combine into a valid signature",
+        ));
+    }
+    Ok((a, e, s))
+}
+
+fn compute_b(
+    commitment: Option<&Commitment>,
+    s: &Fr,
+    messages: &BTreeMap<usize, SignatureMessage>,
+    verkey: &PublicKey,
+) -> Result<G1, BBSError> {
+    if messages.len() > verkey.message_count() {
+        return Err(BBSErrorKind::PublicKeyGeneratorMessageCountMismatch(
+            verkey.message_count(),
+            messages.len(),
+        )
+        .into());
+    }
+    verkey.validate()?;
+    let mut bases = Vec::with_capacity(messages.len() + 3);
+    let mut scalars = Vec::with_capacity(messages.len() + 3);
+    let one = Fr::from_repr(FrRepr::from(1)).unwrap();
+    if let Some(commitment) = commitment {
+        bases.push(commitment.0);
+        scalars.push(one);
+    }
+    bases.push(G1::one());
+    scalars.push(one);
+    bases.push(verkey.h0.0);
+    scalars.push(*s);
+    for (i, m) in messages {
+        let h = verkey
+            .h
+            .get(*i)
+            .ok_or_else(|| general_error("Message index is out of range"))?;
+        bases.push(h.0);
+        scalars.push(m.0);
+    }
+    Ok(multi_scalar_mul_var_time_g1(&bases, &scalars))
+}
+
+fn e_and_s(id: &[u8; PRESIGNATURE_ID_SIZE]) -> (Fr, Fr) {
+    let mut e_input = E_DST.to_vec();
+    e_input.extend_from_slice(id);
+    let mut s_input = S_DST.to_vec();
+    s_input.extend_from_slice(id);
+    (hash_to_fr(e_input), hash_to_fr(s_input))
+}
+
+/// A polynomial of `degree` with the `constant` and random coefficients
+pub(crate) fn random_polynomial(constant: Fr, degree: usize) -> Vec<Fr> {
+    let mut rng = thread_rng();
+    let mut polynomial = Vec::with_capacity(degree + 1);
+    polynomial.push(constant);
+    for _ in 0..degree {
+        polynomial.push(Fr::random(&mut rng));
+    }
+    polynomial
+}
+
+pub(crate) fn evaluate(polynomial: &[Fr], x: &Fr) -> Fr {
+    polynomial.iter().rev().fold(Fr::zero(), |mut acc, c| {
+        acc.mul_assign(x);
+        acc.add_assign(c);
+        acc
+    })
+}
+
+/// The Lagrange coefficient of `index` for interpolating at zero from `indices`
+pub(crate) fn lagrange_at_zero(index: u32, indices: &BTreeSet<u32>) -> Fr {
+    let x_i = index_to_fr(index);
+    let mut numerator = Fr::from_repr(FrRepr::from(1)).unwrap();
+    let mut denominator = numerator;
+    for j in indices.iter().filter(|j| **j != index) {
+        let x_j = index_to_fr(*j);
+        numerator.mul_assign(&x_j);
+        let mut d = x_j;
+        d.sub_assign(&x_i);
+        denominator.mul_assign(&d);
+    }
+    // Indices are distinct so the denominator isn't zero
+    numerator.mul_assign(&denominator.inverse().unwrap());
+    numerator
+}
+
+pub(crate) fn index_to_fr(index: u32) -> Fr {
+    Fr::from_repr(FrRepr::from(index as u64)).unwrap()
+}
+
+fn check_threshold(threshold: usize, total: usize) -> Result<(), BBSError> {
+    if threshold < 2 || total < 2 * threshold - 1 || total > u32::MAX as usize {
+        return Err(BBSErrorKind::GeneralError {
+            msg: format!(
+                "Expected a threshold of at least 2 and 2 * threshold - 1 or more nodes, found {} of {}",
+                threshold, total
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+pub(crate) fn check_index(index: u32, total: usize) -> Result<(), BBSError> {
+    if index == 0 || index as usize > total {
+        return Err(general_error("Node index is out of range"));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_length(value: &[u8], expected: usize) -> Result<(), BBSError> {
+    if value.len() != expected {
+        return Err(BBSErrorKind::InvalidNumberOfBytes(expected, value.len()).into());
+    }
+    Ok(())
+}
+
+pub(crate) fn general_error(msg: &str) -> BBSError {
+    BBSErrorKind::GeneralError {
+        msg: msg.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::{HashElem, RandomElem};
+
+    fn presignatures(threshold: usize, total: usize) -> Vec<Presignature> {
+        let rounds: Vec<_> = (1..=total as u32)
+            .map(|i| PresignatureRound::new(i, threshold, total).unwrap())
+            .collect();
+        let mut inboxes: Vec<Vec<PresignatureContribution>> = vec![Vec::new(); total];
+        for round in &rounds {
+            for (j, inbox) in inboxes.iter_mut().enumerate() {
+                let c = round.contribution(j as u32 + 1).unwrap();
+                inbox.push(PresignatureContribution::try_from(c.to_bytes().as_slice()).unwrap());
+            }
+        }
+        rounds
+            .into_iter()
+            .zip(inboxes.iter())
+            .map(|(round, inbox)| round.finish(inbox).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn threshold_sign() {
+        let (pk, shares) = ThresholdIssuer::new_keys(4, 2, 4).unwrap();
+        let messages: Vec<_> = (0..4)
+            .map(|i| SignatureMessage::hash(format!("message {}", i)))
+            .collect();
+
+        let presigs = presignatures(2, 4);
+        assert!(presigs.iter().all(|p| p.id() == presigs[0].id()));
+        // Nodes 1, 3 and 4 sign, node 2 is offline
+        let partials: Vec<_> = presigs
+            .into_iter()
+            .zip(shares.iter())
+            .filter(|(_, share)| share.index() != 2)
+            .map(|(presig, share)| {
+                ThresholdIssuer::partial_sign(&messages, share, presig, &pk).unwrap()
+            })
+            .collect();
+        let partials: Vec<_> = partials
+            .iter()
+            .map(|p| PartialSignature::try_from(p.to_bytes().as_slice()).unwrap())
+            .collect();
+
+        let signature = ThresholdIssuer::combine(&partials, 2, &messages, &pk).unwrap();
+        assert!(signature.verify(&messages, &pk).unwrap());
+
+        // Too few partial signatures
+        assert!(ThresholdIssuer::combine(&partials[..2], 2, &messages, &pk).is_err());
+        // Different messages than the nodes signed
+        let mut other = messages.clone();
+        other[0] = SignatureMessage::hash(b"forged");
+        assert!(ThresholdIssuer::combine(&partials, 2, &other, &pk).is_err());
+    }
+
+    #[test]
+    fn threshold_blind_sign() {
+        let (pk, shares) = ThresholdIssuer::new_keys(5, 2, 3).unwrap();
+        let nonce = ProofNonce::random();
+        let link_secret = Prover::new_link_secret();
+        let mut hidden = BTreeMap::new();
+        hidden.insert(0, link_secret);
+        let (ctx, blinding) = Prover::new_blind_signature_context(&pk, &hidden, &nonce).unwrap();
+
+        let mut messages = BTreeMap::new();
+        for i in 1..5 {
+            messages.insert(i, SignatureMessage::hash(format!("message {}", i)));
+        }
+        let partials: Vec<_> = presignatures(2, 3)
+            .into_iter()
+            .zip(shares.iter())
+            .map(|(presig, share)| {
+                ThresholdIssuer::partial_blind_sign(&ctx, &messages, share, presig, &pk, &nonce)
+                    .unwrap()
+            })
+            .collect();
+        let blind_signature =
+            ThresholdIssuer::combine_blind(&partials, 2, &ctx.commitment, &messages, &pk).unwrap();
+
+        let mut all = vec![link_secret];
+        all.extend(messages.values().copied());
+        let signature = Prover::complete_signature(&pk, &all, &blind_signature, &blinding).unwrap();
+        assert!(signature.verify(&all, &pk).unwrap());
+    }
+
+    #[test]
+    fn shares_reconstruct_key() {
+        let (_, sk) = generate(1).unwrap();
+        let shares = ThresholdIssuer::share_secret_key(&sk, 3, 5).unwrap();
+        let subset: BTreeSet<u32> = [1, 4, 5].iter().copied().collect();
+        let mut x = Fr::zero();
+        for share in shares.iter().filter(|s| subset.contains(&s.index())) {
+            let mut term = lagrange_at_zero(share.index(), &subset);
+            term.mul_assign(&share.share);
+            x.add_assign(&term);
+        }
+        assert_eq!(x, sk.0);
+        let share = SecretKeyShare::try_from(shares[0].to_bytes().as_slice()).unwrap();
+        assert_eq!(share, shares[0]);
+
+        // Signing needs 2 * threshold - 1 nodes
+        assert!(ThresholdIssuer::share_secret_key(&sk, 3, 4).is_err());
+        assert!(ThresholdIssuer::share_secret_key(&sk, 1, 4).is_err());
+    }
+
+    #[test]
+    fn presignature_rounds() {
+        let round = PresignatureRound::new(1, 2, 3).unwrap();
+        assert!(round.contribution(4).is_err());
+        let other = PresignatureRound::new(2, 2, 3).unwrap();
+        // A node must include its own contribution
+        let contributions = vec![other.contribution(1).unwrap()];
+        assert!(PresignatureRound::new(1, 2, 3)
+            .unwrap()
+            .finish(&contributions)
+            .is_err());
+        let contributions = vec![
+            round.contribution(1).unwrap(),
+            other.contribution(1).unwrap(),
+        ];
+        let presig = round.finish(&contributions).unwrap();
+        let restored = Presignature::try_from(presig.to_bytes().as_slice()).unwrap();
+        assert_eq!(presig, restored);
+    }
+}
@@ -67,6 +67,66 @@ impl Verifier {
         }
     }
 
+    /// Check many signature proofs of knowledge together.
+    ///
+    /// `proof_requests`, `signature_proofs` and `nonces` must have the same length,
+    /// the proof at index `i` is checked against the request and nonce at `i`.
+    /// This is much faster than calling `verify_signature_pok` for each proof
+    /// and returns the revealed messages of every proof in the same order.
+    pub fn verify_signature_pok_batch(
+        proof_requests: &[ProofRequest],
+        signature_proofs: &[SignatureProof],
+        nonces: &[ProofNonce],
+    ) -> Result<Vec<Vec<SignatureMessage>>, BBSError> {
+        if proof_requests.len() != signature_proofs.len() || nonces.len() != signature_proofs.len()
+        {
+            return Err(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Expected the same number of proof requests, proofs and nonces, found {}, {} and {}",
+                    proof_requests.len(),
+                    signature_proofs.len(),
+                    nonces.len()
+                ),
+            }
+            .into());
+        }
+        let challenges = signature_proofs
+            .iter()
+            .zip(proof_requests.iter())
+            .zip(nonces.iter())
+            .map(|((p, r), n)| {
+                let mut challenge_bytes = p
+                    .proof
+                    .get_bytes_for_challenge(r.revealed_messages.clone(), &r.verification_key);
+                challenge_bytes.extend_from_slice(&n.to_bytes_uncompressed_form()[..]);
+                ProofChallenge::hash(&challenge_bytes)
+            })
+            .collect::<Vec<ProofChallenge>>();
+        let items = signature_proofs
+            .iter()
+            .zip(proof_requests.iter())
+            .zip(challenges.iter())
+            .map(|((p, r), c)| BatchVerificationItem {
+                proof: &p.proof,
+                vk: &r.verification_key,
+                revealed_msgs: &p.revealed_messages,
+                challenge: c,
+            })
+            .collect::<Vec<BatchVerificationItem>>();
+        match PoKOfSignatureProof::verify_batch(items.as_slice())? {
+            PoKOfSignatureProofStatus::Success => Ok(signature_proofs
+                .iter()
+                .map(|p| {
+                    p.revealed_messages
+                        .iter()
+                        .map(|(_, m)| *m)
+                        .collect::<Vec<SignatureMessage>>()
+                })
+                .collect()),
+            e => Err(BBSErrorKind::InvalidProof { status: e }.into()),
+        }
+    }
+
     /// Create a nonce used for the proof request context
     pub fn generate_proof_nonce() -> ProofNonce {
         ProofNonce::random()
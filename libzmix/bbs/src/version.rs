@@ -0,0 +1,306 @@
+//! Protocol versions for the messages provers and verifiers exchange.
+//!
+//! `ProofRequest` and `SignatureProof` bytes carry no version, so a peer on another
+//! release of this crate only notices a change in their layout when parsing fails
+//! somewhere in the middle. Wrapping them in `Versioned` puts a header in front that says
+//! which version wrote the payload and which versions the sender can read in reply.
+//! Readers check the header before touching the payload and fail with
+//! `BBSErrorKind::UnsupportedVersion` or `BBSErrorKind::MissingVersion` instead.
+//!
+//! A verifier wraps its request with `Versioned::new`, the prover answers with
+//! `Versioned::reply` which picks the highest version both sides support.
+//!
+//! The header is the tag `bbsv` followed by the version of the payload and the lowest and
+//! highest version the sender accepts, each as a big endian `u16` major and minor.
+//! Unversioned requests and proofs start with a small big endian length so they
+//! never begin with the tag.
+
+use crate::errors::prelude::*;
+use crate::ToVariableLengthBytes;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Convenience module
+pub mod prelude {
+    pub use super::{
+        ProtocolVersion, VersionRange, Versioned, CURRENT_VERSION, SUPPORTED_VERSIONS,
+    };
+}
+
+/// The version this crate writes
+pub const CURRENT_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+
+/// The versions this crate can read and write
+pub const SUPPORTED_VERSIONS: VersionRange = VersionRange {
+    min: CURRENT_VERSION,
+    max: CURRENT_VERSION,
+};
+
+const VERSION_TAG: &[u8; 4] = b"bbsv";
+const VERSION_SIZE: usize = 4;
+const HEADER_SIZE: usize = VERSION_TAG.len() + 3 * VERSION_SIZE;
+
+/// A version of the exchange protocol, the major version changes when
+/// the layout of a message changes
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    /// Incremented for incompatible changes
+    pub major: u16,
+    /// Incremented for additions older readers can ignore
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    fn to_bytes(self) -> [u8; VERSION_SIZE] {
+        let mut bytes = [0u8; VERSION_SIZE];
+        bytes[..2].copy_from_slice(&self.major.to_be_bytes());
+        bytes[2..].copy_from_slice(&self.minor.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(data: &[u8; VERSION_SIZE]) -> Self {
+        Self {
+            major: u16::from_be_bytes(*array_ref![data, 0, 2]),
+            minor: u16::from_be_bytes(*array_ref![data, 2, 2]),
+        }
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The lowest and highest protocol version a party supports
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VersionRange {
+    /// The lowest supported version
+    pub min: ProtocolVersion,
+    /// The highest supported version
+    pub max: ProtocolVersion,
+}
+
+impl VersionRange {
+    /// Is `version` in this range
+    pub fn contains(&self, version: &ProtocolVersion) -> bool {
+        self.min <= *version && *version <= self.max
+    }
+
+    /// The highest version in both ranges
+    pub fn negotiate(&self, other: &VersionRange) -> Result<ProtocolVersion, BBSError> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        if min > max {
+            return Err(BBSErrorKind::NoCommonVersion {
+                ours: *self,
+                theirs: *other,
+            }
+            .into());
+        }
+        Ok(max)
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} to {}", self.min, self.max)
+    }
+}
+
+/// A message with the version of its layout and the versions its sender can read
+#[derive(Debug, Clone)]
+pub struct Versioned<T> {
+    /// The version that wrote `payload`
+    pub version: ProtocolVersion,
+    /// The versions the sender accepts in a reply
+    pub accepts: VersionRange,
+    /// The wrapped message
+    pub payload: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wrap `payload` with the current version
+    pub fn new(payload: T) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            accepts: SUPPORTED_VERSIONS,
+            payload,
+        }
+    }
+
+    /// Wrap the answer to this message with the highest version both sides support
+    pub fn reply<U>(&self, payload: U) -> Result<Versioned<U>, BBSError> {
+        let version = SUPPORTED_VERSIONS.negotiate(&self.accepts)?;
+        Ok(Versioned {
+            version,
+            accepts: SUPPORTED_VERSIONS,
+            payload,
+        })
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(HEADER_SIZE);
+        output.extend_from_slice(VERSION_TAG);
+        output.extend_from_slice(&self.version.to_bytes());
+        output.extend_from_slice(&self.accepts.min.to_bytes());
+        output.extend_from_slice(&self.accepts.max.to_bytes());
+        output
+    }
+
+    /// Read the header from `data` and return it with the rest of the bytes
+    fn read_header(data: &[u8]) -> Result<(ProtocolVersion, VersionRange, &[u8]), BBSError> {
+        if data.len() < HEADER_SIZE || &data[..VERSION_TAG.len()] != VERSION_TAG {
+            return Err(BBSErrorKind::MissingVersion.into());
+        }
+        let version = ProtocolVersion::from_bytes(array_ref![data, 4, VERSION_SIZE]);
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(BBSErrorKind::UnsupportedVersion {
+                found: version,
+                supported: SUPPORTED_VERSIONS,
+            }
+            .into());
+        }
+        let accepts = VersionRange {
+            min: ProtocolVersion::from_bytes(array_ref![data, 8, VERSION_SIZE]),
+            max: ProtocolVersion::from_bytes(array_ref![data, 12, VERSION_SIZE]),
+        };
+        Ok((version, accepts, &data[HEADER_SIZE..]))
+    }
+}
+
+impl<T> ToVariableLengthBytes for Versioned<T>
+where
+    T: ToVariableLengthBytes<Output = T, Error = BBSError>,
+{
+    type Output = Versioned<T>;
+    type Error = BBSError;
+
+    fn to_bytes_compressed_form(&self) -> Vec<u8> {
+        let mut output = self.header();
+        output.append(&mut self.payload.to_bytes_compressed_form());
+        output
+    }
+
+    fn from_bytes_compressed_form<I: AsRef<[u8]>>(data: I) -> Result<Self::Output, Self::Error> {
+        let (version, accepts, data) = Self::read_header(data.as_ref())?;
+        Ok(Self {
+            version,
+            accepts,
+            payload: T::from_bytes_compressed_form(data)?,
+        })
+    }
+
+    fn to_bytes_uncompressed_form(&self) -> Vec<u8> {
+        let mut output = self.header();
+        output.append(&mut self.payload.to_bytes_uncompressed_form());
+        output
+    }
+
+    fn from_bytes_uncompressed_form<I: AsRef<[u8]>>(data: I) -> Result<Self::Output, Self::Error> {
+        let (version, accepts, data) = Self::read_header(data.as_ref())?;
+        Ok(Self {
+            version,
+            accepts,
+            payload: T::from_bytes_uncompressed_form(data)?,
+        })
+    }
+}
+
+impl<T> TryFrom<&[u8]> for Versioned<T>
+where
+    T: ToVariableLengthBytes<Output = T, Error = BBSError>,
+{
+    type Error = BBSError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes_compressed_form(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn versioned_exchange() {
+        let (pk, sk) = Issuer::new_keys(3).unwrap();
+        let messages = vec![
+            SignatureMessage::hash(b"message 1"),
+            SignatureMessage::hash(b"message 2"),
+            SignatureMessage::hash(b"message 3"),
+        ];
+        let signature = Signature::new(messages.as_slice(), &sk, &pk).unwrap();
+
+        let request = Versioned::new(Verifier::new_proof_request(&[1], &pk).unwrap());
+        let bytes = request.to_bytes_compressed_form();
+        let received = Versioned::<ProofRequest>::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(received.version, CURRENT_VERSION);
+        assert_eq!(received.accepts, SUPPORTED_VERSIONS);
+
+        let nonce = Verifier::generate_proof_nonce();
+        let proof_messages = vec![
+            pm_hidden_raw!(messages[0]),
+            pm_revealed_raw!(messages[1]),
+            pm_hidden_raw!(messages[2]),
+        ];
+        let pok =
+            Prover::commit_signature_pok(&received.payload, proof_messages.as_slice(), &signature)
+                .unwrap();
+        let challenge =
+            Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &nonce).unwrap();
+        let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let reply = received.reply(proof).unwrap();
+
+        let bytes = reply.to_bytes_uncompressed_form();
+        let proof = Versioned::<SignatureProof>::from_bytes_uncompressed_form(bytes).unwrap();
+        assert_eq!(proof.version, CURRENT_VERSION);
+        let revealed =
+            Verifier::verify_signature_pok(&request.payload, &proof.payload, &nonce).unwrap();
+        assert_eq!(revealed, vec![messages[1]]);
+    }
+
+    #[test]
+    fn versioned_incompatible() {
+        let (pk, _) = Issuer::new_keys(2).unwrap();
+        let request = Verifier::new_proof_request(&[0], &pk).unwrap();
+
+        // Unversioned bytes
+        match Versioned::<ProofRequest>::try_from(request.to_bytes_compressed_form().as_slice()) {
+            Err(e) => assert!(matches!(e.kind(), BBSErrorKind::MissingVersion)),
+            Ok(_) => panic!("unversioned bytes were accepted"),
+        }
+
+        // A later major version
+        let future = ProtocolVersion { major: 2, minor: 0 };
+        let mut versioned = Versioned::new(request);
+        versioned.version = future;
+        versioned.accepts = VersionRange {
+            min: future,
+            max: future,
+        };
+        match Versioned::<ProofRequest>::try_from(versioned.to_bytes_compressed_form().as_slice()) {
+            Err(e) => assert!(matches!(
+                e.kind(),
+                BBSErrorKind::UnsupportedVersion { found, .. } if found == future
+            )),
+            Ok(_) => panic!("unsupported version was accepted"),
+        }
+        match versioned.reply(SignatureProof::default()) {
+            Err(e) => assert!(matches!(e.kind(), BBSErrorKind::NoCommonVersion { .. })),
+            Ok(_) => panic!("negotiated without a common version"),
+        }
+
+        let ours = VersionRange {
+            min: CURRENT_VERSION,
+            max: ProtocolVersion { major: 1, minor: 3 },
+        };
+        let theirs = VersionRange {
+            min: CURRENT_VERSION,
+            max: ProtocolVersion { major: 1, minor: 2 },
+        };
+        assert_eq!(ours.negotiate(&theirs).unwrap(), theirs.max);
+        assert_eq!(theirs.negotiate(&ours).unwrap(), theirs.max);
+    }
+}
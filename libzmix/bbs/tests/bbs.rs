@@ -129,6 +129,23 @@ fn blind_sign_simple() {
     assert!(res.is_ok());
 }
 
+#[test]
+fn blind_sign_index_out_of_range() {
+    let (pk, sk) = Issuer::new_keys(2).unwrap();
+    let signing_nonce = Issuer::generate_signing_nonce();
+
+    // There is no message 2 in a key for two messages
+    let mut messages = BTreeMap::new();
+    messages.insert(2, Prover::new_link_secret());
+    assert!(Prover::new_blind_signature_context(&pk, &messages, &signing_nonce).is_err());
+
+    let mut messages = BTreeMap::new();
+    messages.insert(0, Prover::new_link_secret());
+    let (ctx, _) = Prover::new_blind_signature_context(&pk, &messages, &signing_nonce).unwrap();
+    let messages = sm_map![2 => b"message_2"];
+    assert!(BlindSignature::new(&ctx.commitment, &messages, &sk, &pk).is_err());
+}
+
 #[test]
 fn pok_sig() {
     let (pk, sk) = Issuer::new_keys(5).unwrap();
@@ -292,6 +309,67 @@ fn pok_sig_bad_message() {
     };
 }
 
+#[test]
+fn pok_sig_batch() {
+    let mut requests = Vec::new();
+    let mut proofs = Vec::new();
+    let mut nonces = Vec::new();
+    let mut expected = Vec::new();
+    for (count, revealed) in [(5usize, vec![1, 3]), (3, vec![]), (4, vec![0, 1, 2, 3])].iter() {
+        let (pk, sk) = Issuer::new_keys(*count).unwrap();
+        let messages = (0..*count)
+            .map(|i| SignatureMessage::hash(format!("message_{}", i)))
+            .collect::<Vec<SignatureMessage>>();
+        let signature = Signature::new(messages.as_slice(), &sk, &pk).unwrap();
+
+        let nonce = Verifier::generate_proof_nonce();
+        let proof_request = Verifier::new_proof_request(revealed.as_slice(), &pk).unwrap();
+        let proof_messages = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                if revealed.contains(&i) {
+                    pm_revealed_raw!(*m)
+                } else {
+                    pm_hidden_raw!(*m)
+                }
+            })
+            .collect::<Vec<ProofMessage>>();
+        let pok =
+            Prover::commit_signature_pok(&proof_request, proof_messages.as_slice(), &signature)
+                .unwrap();
+        let challenge = Prover::create_challenge_hash(&[pok.clone()], None, &nonce).unwrap();
+        let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+
+        expected.push(
+            revealed
+                .iter()
+                .map(|i| messages[*i])
+                .collect::<Vec<SignatureMessage>>(),
+        );
+        requests.push(proof_request);
+        proofs.push(proof);
+        nonces.push(nonce);
+    }
+
+    let revealed = Verifier::verify_signature_pok_batch(&requests, &proofs, &nonces).unwrap();
+    assert_eq!(revealed, expected);
+
+    // One proof made for another nonce fails the whole batch
+    let mut bad_nonces = nonces.clone();
+    bad_nonces[1] = Verifier::generate_proof_nonce();
+    assert!(Verifier::verify_signature_pok_batch(&requests, &proofs, &bad_nonces).is_err());
+
+    // As does a changed revealed message
+    let mut bad_proofs = proofs.clone();
+    bad_proofs[0]
+        .revealed_messages
+        .insert(1, SignatureMessage::hash(b"message_0"));
+    assert!(Verifier::verify_signature_pok_batch(&requests, &bad_proofs, &nonces).is_err());
+
+    assert!(Verifier::verify_signature_pok_batch(&requests[..2], &proofs, &nonces).is_err());
+}
+
 #[test]
 fn test_challenge_hash_with_prover_claims() {
     //issue credential
@@ -23,4 +23,6 @@ mod transcript;
 
 pub mod ipp;
 
+pub mod offload;
+
 pub mod r1cs;
@@ -0,0 +1,115 @@
+/*
+    SPDX-License-Identifier: Apache-2.0 OR MIT
+*/
+
+//! Hook for computing the multi-scalar multiplications of verification on a GPU, FPGA or
+//! another accelerator. Verifying an R1CS proof ends with one multi-scalar multiplication
+//! over all generators, which takes most of the time for large circuits.
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+
+use crate::errors::{R1CSError, R1CSErrorKind};
+
+/// Computes multi-scalar multiplications for verifiers. Implementations must return the
+/// same result as `CpuOffload`, the points and scalars are public so they may be variable time.
+pub trait Offload: Sync {
+    /// Return `scalars[0] * points[0] + scalars[1] * points[1] + ...`.
+    /// `points` and `scalars` have the same length.
+    fn multi_scalar_mul(
+        &self,
+        points: Vec<&G1>,
+        scalars: Vec<&FieldElement>,
+    ) -> Result<G1, R1CSError>;
+}
+
+/// Computes everything on the CPU, this is what `Verifier::verify` uses
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CpuOffload;
+
+impl Offload for CpuOffload {
+    fn multi_scalar_mul(
+        &self,
+        points: Vec<&G1>,
+        scalars: Vec<&FieldElement>,
+    ) -> Result<G1, R1CSError> {
+        G1Vector::inner_product_var_time_with_ref_vecs(points, scalars)
+            .map_err(|_| R1CSErrorKind::FormatError.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r1cs::{ConstraintSystem, LinearCombination, Prover, Verifier};
+    use crate::utils::get_generators;
+    use amcl_wrapper::group_elem::GroupElement;
+    use merlin::Transcript;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingOffload(AtomicUsize);
+
+    impl Offload for CountingOffload {
+        fn multi_scalar_mul(
+            &self,
+            points: Vec<&G1>,
+            scalars: Vec<&FieldElement>,
+        ) -> Result<G1, R1CSError> {
+            self.0.fetch_add(points.len(), Ordering::SeqCst);
+            CpuOffload.multi_scalar_mul(points, scalars)
+        }
+    }
+
+    struct FailingOffload;
+
+    impl Offload for FailingOffload {
+        fn multi_scalar_mul(&self, _: Vec<&G1>, _: Vec<&FieldElement>) -> Result<G1, R1CSError> {
+            Err(R1CSErrorKind::FormatError.into())
+        }
+    }
+
+    fn verifier_for<'a>(
+        transcript: &'a mut Transcript,
+        commitments: &[G1],
+        product: &FieldElement,
+    ) -> Verifier<'a> {
+        let mut verifier = Verifier::new(transcript);
+        let var_p = verifier.commit(commitments[0].clone());
+        let var_q = verifier.commit(commitments[1].clone());
+        let (_, _, o) = verifier.multiply(var_p.into(), var_q.into());
+        verifier.constrain(o - LinearCombination::from(product.clone()));
+        verifier
+    }
+
+    #[test]
+    fn test_verify_with_offload() {
+        let big_g: G1Vector = get_generators("G", 2).into();
+        let big_h: G1Vector = get_generators("H", 2).into();
+        let g = G1::from_msg_hash("g".as_bytes());
+        let h = G1::from_msg_hash("h".as_bytes());
+        let product = FieldElement::from(323u32);
+
+        let mut transcript = Transcript::new(b"Offload");
+        let mut prover = Prover::new(&g, &h, &mut transcript);
+        let (com_p, var_p) = prover.commit(FieldElement::from(17u32), FieldElement::random());
+        let (com_q, var_q) = prover.commit(FieldElement::from(19u32), FieldElement::random());
+        let (_, _, o) = prover.multiply(var_p.into(), var_q.into());
+        prover.constrain(o - LinearCombination::from(product.clone()));
+        let proof = prover.prove(&big_g, &big_h).unwrap();
+        let commitments = vec![com_p, com_q];
+
+        let offload = CountingOffload(AtomicUsize::new(0));
+        let mut transcript = Transcript::new(b"Offload");
+        let verifier = verifier_for(&mut transcript, &commitments, &product);
+        assert!(verifier
+            .verify_with_offload(&proof, &g, &h, &big_g, &big_h, &offload)
+            .is_ok());
+        assert!(offload.0.load(Ordering::SeqCst) > 0);
+
+        let mut transcript = Transcript::new(b"Offload");
+        let verifier = verifier_for(&mut transcript, &commitments, &product);
+        assert!(verifier
+            .verify_with_offload(&proof, &g, &h, &big_g, &big_h, &FailingOffload)
+            .is_err());
+    }
+}
@@ -12,6 +12,7 @@ use merlin::Transcript;
 
 use crate::errors::{R1CSError, R1CSErrorKind};
 use crate::ipp::IPP;
+use crate::offload::{CpuOffload, Offload};
 use crate::r1cs::constraint_system::ConstraintSystem;
 use crate::r1cs::constraint_system::RandomizedConstraintSystem;
 use crate::r1cs::linear_combination::LinearCombination;
@@ -272,12 +273,25 @@ impl<'a> Verifier<'a> {
 
     /// Consume this `Verifier` and attempt to verify the supplied `proof`.
     pub fn verify(
+        self,
+        proof: &R1CSProof,
+        g: &G1,
+        h: &G1,
+        G: &G1Vector,
+        H: &G1Vector,
+    ) -> Result<(), R1CSError> {
+        self.verify_with_offload(proof, g, h, G, H, &CpuOffload)
+    }
+
+    /// Same as `verify` but the final multi-scalar multiplication is computed by `offload`
+    pub fn verify_with_offload(
         mut self,
         proof: &R1CSProof,
         g: &G1,
         h: &G1,
         G: &G1Vector,
         H: &G1Vector,
+        offload: &dyn Offload,
     ) -> Result<(), R1CSError> {
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
@@ -457,7 +471,7 @@ impl<'a> Verifier<'a> {
         arg2.extend(proof.ipp_proof.L.as_slice());
         arg2.extend(proof.ipp_proof.R.as_slice());
 
-        let res = G1Vector::inner_product_var_time_with_ref_vecs(arg2, arg1).unwrap();
+        let res = offload.multi_scalar_mul(arg2, arg1)?;
         if !res.is_identity() {
             return Err(R1CSErrorKind::VerificationError.into());
         }
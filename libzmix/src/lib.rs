@@ -1,5 +1,5 @@
-//#[macro_use]
-//extern crate lazy_static;
+#[macro_use]
+extern crate lazy_static;
 pub extern crate amcl_wrapper;
 extern crate failure;
 #[macro_use]
@@ -8,6 +8,8 @@ extern crate arrayref;
 extern crate serde;
 extern crate serde_json;
 
+#[cfg(feature = "bbs")]
+extern crate bbs;
 extern crate bulletproofs_amcl as bulletproofs;
 #[cfg(feature = "hash2curve")]
 pub extern crate hash2curve;
@@ -26,6 +28,10 @@ pub extern crate ursa;
 pub mod commitments;
 #[macro_use]
 pub mod errors;
+pub mod membership;
+#[cfg(all(feature = "bbs", feature = "ursa"))]
+pub mod migration;
 pub mod signatures;
 #[cfg(feature = "ver_enc")]
 pub mod verifiable_encryption;
+pub mod zkl;
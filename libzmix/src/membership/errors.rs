@@ -0,0 +1,47 @@
+use bulletproofs::errors::{BulletproofError, R1CSError};
+use failure::{Backtrace, Context, Fail};
+use std::fmt;
+
+#[derive(Clone, Eq, PartialEq, Debug, Fail)]
+pub enum MembershipErrorKind {
+    #[fail(display = "Tree depth {} is not between 1 and {}", depth, max)]
+    InvalidDepth { depth: usize, max: usize },
+
+    #[fail(
+        display = "Index {} is not less than the set capacity {}",
+        index, capacity
+    )]
+    IndexOutOfRange { index: u64, capacity: u64 },
+
+    #[fail(display = "Zero marks empty leaves and cannot be an element")]
+    ZeroElement,
+
+    #[fail(display = "No element at index {}", index)]
+    NoElement { index: u64 },
+
+    #[fail(display = "Membership proof is invalid")]
+    InvalidProof,
+
+    #[fail(display = "Error from Bulletproofs {:?}", msg)]
+    BulletproofError { msg: String },
+}
+
+impl_Errors!(MembershipErrorKind, MembershipError);
+
+impl From<BulletproofError> for MembershipError {
+    fn from(err: BulletproofError) -> Self {
+        MembershipErrorKind::BulletproofError {
+            msg: err.to_string(),
+        }
+        .into()
+    }
+}
+
+impl From<R1CSError> for MembershipError {
+    fn from(err: R1CSError) -> Self {
+        MembershipErrorKind::BulletproofError {
+            msg: err.to_string(),
+        }
+        .into()
+    }
+}
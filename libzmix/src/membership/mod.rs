@@ -0,0 +1,503 @@
+// Proofs that a hidden value is in a large public set.
+//
+// The set is kept as a sparse 4-ary Merkle tree hashed with Poseidon, with an empty leaf
+// being zero. A proof shows in zero knowledge that a leaf of the tree with the given root
+// holds the committed value, so its size and the work to verify it grow with the depth of
+// the tree and not with the size of the set. A tree of depth 16 holds 2^32 elements.
+//
+// The value is committed as g^value * h^blinding with the generators of `MembershipParams`.
+// Choosing the blinding lets the commitment be used in another sigma protocol. A credential
+// attribute is proven to be in the set by proving knowledge of the committed value with the
+// same blinding and challenge as the credential proof, and comparing the responses.
+
+#![allow(non_snake_case)]
+
+pub mod errors;
+
+use self::errors::{MembershipError, MembershipErrorKind};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+use bulletproofs::r1cs::gadgets::helper_constraints::non_zero::is_nonzero_gadget;
+use bulletproofs::r1cs::gadgets::helper_constraints::poseidon::{
+    PoseidonParams, SboxType, CAP_CONST_W_5,
+};
+use bulletproofs::r1cs::gadgets::helper_constraints::sparse_merkle_tree_4_ary::{
+    vanilla_merkle_merkle_tree_4_verif_gadget, DbVal4ary, ProofNode4ary, VanillaSparseMerkleTree4,
+};
+use bulletproofs::r1cs::gadgets::helper_constraints::LeafValueType;
+use bulletproofs::r1cs::gadgets::merkle_tree_hash::{
+    Arity4MerkleTreeHashConstraints, PoseidonHash4, PoseidonHashConstraints,
+};
+use bulletproofs::r1cs::linear_combination::AllocatedQuantity;
+use bulletproofs::r1cs::{Prover, R1CSProof, Verifier};
+use bulletproofs::utils::get_generators;
+use bulletproofs::utils::hash_db::InMemoryHashDb;
+use merlin::Transcript;
+
+/// The deepest supported tree
+pub const MAX_DEPTH: usize = 16;
+
+const TRANSCRIPT_LABEL: &[u8] = b"zmix set membership";
+
+static SBOX: SboxType = SboxType::Quint;
+
+lazy_static! {
+    static ref POSEIDON_PARAMS: PoseidonParams = PoseidonParams::new(5, 4, 4, 56).unwrap();
+    static ref HASH: PoseidonHash4<'static> = PoseidonHash4 {
+        params: &POSEIDON_PARAMS,
+        sbox: &SBOX,
+    };
+}
+
+/// Map arbitrary bytes to a set element
+pub fn hash_to_element(bytes: &[u8]) -> FieldElement {
+    FieldElement::from_msg_hash(bytes)
+}
+
+fn check_depth(depth: usize) -> Result<(), MembershipError> {
+    if depth == 0 || depth > MAX_DEPTH {
+        return Err(MembershipErrorKind::InvalidDepth {
+            depth,
+            max: MAX_DEPTH,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Generators for proofs on trees of one depth. Prover and verifier must use the same.
+#[derive(Clone, Debug)]
+pub struct MembershipParams {
+    pub depth: usize,
+    /// Base of the committed values
+    pub g: G1,
+    /// Base of the blindings
+    pub h: G1,
+    pub G: G1Vector,
+    pub H: G1Vector,
+}
+
+impl MembershipParams {
+    /// Derive the generators from `label`
+    pub fn new(label: &str, depth: usize) -> Result<Self, MembershipError> {
+        check_depth(depth)?;
+        let n = Self::multipliers(depth).next_power_of_two();
+        Ok(Self {
+            depth,
+            g: G1::from_msg_hash(format!("{}g", label).as_bytes()),
+            h: G1::from_msg_hash(format!("{}h", label).as_bytes()),
+            G: get_generators(&format!("{}G", label), n).into(),
+            H: get_generators(&format!("{}H", label), n).into(),
+        })
+    }
+
+    /// The multipliers of the circuit: two for the nonzero check and one Poseidon hash
+    /// and the index bits of each level
+    fn multipliers(depth: usize) -> usize {
+        2 + depth * 301
+    }
+}
+
+/// The path from a leaf to the root, kept private by the prover
+#[derive(Clone, Debug)]
+pub struct MembershipWitness {
+    pub index: u64,
+    pub element: FieldElement,
+    pub path: Vec<ProofNode4ary>,
+}
+
+/// A set of up to `4^depth` elements, each at an index
+pub struct MembershipSet {
+    tree: VanillaSparseMerkleTree4<'static, PoseidonHash4<'static>>,
+    db: InMemoryHashDb<DbVal4ary>,
+}
+
+impl MembershipSet {
+    /// An empty set
+    pub fn new(depth: usize) -> Result<Self, MembershipError> {
+        check_depth(depth)?;
+        let mut db = InMemoryHashDb::new();
+        let tree = VanillaSparseMerkleTree4::new(&*HASH, depth, &mut db)?;
+        Ok(Self { tree, db })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.tree.depth
+    }
+
+    /// The number of indices
+    pub fn capacity(&self) -> u64 {
+        1 << (2 * self.tree.depth)
+    }
+
+    /// The value proofs are verified against
+    pub fn root(&self) -> &FieldElement {
+        &self.tree.root
+    }
+
+    /// Put `element` at `index`, replacing any element there
+    pub fn insert(&mut self, index: u64, element: FieldElement) -> Result<(), MembershipError> {
+        if element.is_zero() {
+            return Err(MembershipErrorKind::ZeroElement.into());
+        }
+        self.update(index, element)
+    }
+
+    /// Empty `index`
+    pub fn remove(&mut self, index: u64) -> Result<(), MembershipError> {
+        self.update(index, FieldElement::zero())
+    }
+
+    /// The element at `index`, if any
+    pub fn get(&self, index: u64) -> Result<Option<FieldElement>, MembershipError> {
+        self.check_index(index)?;
+        let element = self
+            .tree
+            .get(&FieldElement::from(index), &mut None, &self.db)?;
+        Ok(if element.is_zero() {
+            None
+        } else {
+            Some(element)
+        })
+    }
+
+    /// What the holder of the element at `index` needs to prove membership
+    pub fn witness(&self, index: u64) -> Result<MembershipWitness, MembershipError> {
+        self.check_index(index)?;
+        let mut path = Some(Vec::new());
+        let element = self
+            .tree
+            .get(&FieldElement::from(index), &mut path, &self.db)?;
+        if element.is_zero() {
+            return Err(MembershipErrorKind::NoElement { index }.into());
+        }
+        Ok(MembershipWitness {
+            index,
+            element,
+            path: path.unwrap(),
+        })
+    }
+
+    fn update(&mut self, index: u64, element: FieldElement) -> Result<(), MembershipError> {
+        self.check_index(index)?;
+        self.tree
+            .update(&FieldElement::from(index), element, &mut self.db)?;
+        Ok(())
+    }
+
+    fn check_index(&self, index: u64) -> Result<(), MembershipError> {
+        if index >= self.capacity() {
+            return Err(MembershipErrorKind::IndexOutOfRange {
+                index,
+                capacity: self.capacity(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Proof that the committed element is in the set with a given root
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MembershipProof {
+    pub proof: R1CSProof,
+    /// Commitments to the index, the element, its inverse and the nodes of the path
+    pub commitments: Vec<G1>,
+}
+
+impl MembershipProof {
+    /// Prove the element of `witness` is in the set with `root`. The proof is bound to
+    /// `context`, which is usually the nonce of the verifier.
+    pub fn new(
+        params: &MembershipParams,
+        root: &FieldElement,
+        witness: &MembershipWitness,
+        element_blinding: &FieldElement,
+        context: &[u8],
+    ) -> Result<Self, MembershipError> {
+        if witness.path.len() != params.depth {
+            return Err(MembershipErrorKind::InvalidDepth {
+                depth: witness.path.len(),
+                max: MAX_DEPTH,
+            }
+            .into());
+        }
+        if witness.element.is_zero() {
+            return Err(MembershipErrorKind::ZeroElement.into());
+        }
+
+        let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+        transcript.append_message(b"context", context);
+        let mut prover = Prover::new(&params.g, &params.h, &mut transcript);
+        let commitments = Self::constrain(&mut prover, params, root, witness, element_blinding)?;
+        let proof = prover.prove(&params.G, &params.H)?;
+        Ok(Self { proof, commitments })
+    }
+
+    /// Commit to the witness and add the constraints of the circuit
+    fn constrain(
+        prover: &mut Prover,
+        params: &MembershipParams,
+        root: &FieldElement,
+        witness: &MembershipWitness,
+        element_blinding: &FieldElement,
+    ) -> Result<Vec<G1>, MembershipError> {
+        let index = FieldElement::from(witness.index);
+        let mut commitments = Vec::with_capacity(3 + 3 * params.depth);
+        let (c, index_var) = prover.commit(index.clone(), FieldElement::random());
+        commitments.push(c);
+        let (c, element_var) = prover.commit(witness.element.clone(), element_blinding.clone());
+        commitments.push(c);
+        let (c, inverse_var) = prover.commit(witness.element.inverse(), FieldElement::random());
+        commitments.push(c);
+        let mut path_vars = Vec::with_capacity(3 * params.depth);
+        for node in &witness.path {
+            for n in node.iter() {
+                let (c, v) = prover.commit(n.clone(), FieldElement::random());
+                commitments.push(c);
+                path_vars.push(v);
+            }
+        }
+
+        // An empty leaf of any index would open to zero
+        is_nonzero_gadget(prover, element_var, inverse_var)?;
+        let mut hash = PoseidonHashConstraints::new(&POSEIDON_PARAMS, &SBOX, CAP_CONST_W_5);
+        hash.prover_setup(prover)?;
+        vanilla_merkle_merkle_tree_4_verif_gadget(
+            prover,
+            params.depth,
+            root,
+            LeafValueType::Hidden(element_var),
+            AllocatedQuantity {
+                variable: index_var,
+                assignment: Some(index),
+            },
+            path_vars,
+            &mut hash,
+        )?;
+        Ok(commitments)
+    }
+
+    /// The commitment g^element * h^blinding
+    pub fn element_commitment(&self) -> &G1 {
+        &self.commitments[1]
+    }
+
+    pub fn verify(
+        &self,
+        params: &MembershipParams,
+        root: &FieldElement,
+        context: &[u8],
+    ) -> Result<(), MembershipError> {
+        if self.commitments.len() != 3 + 3 * params.depth {
+            return Err(MembershipErrorKind::InvalidProof.into());
+        }
+
+        let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+        transcript.append_message(b"context", context);
+        let mut verifier = Verifier::new(&mut transcript);
+
+        let index_var = verifier.commit(self.commitments[0].clone());
+        let element_var = verifier.commit(self.commitments[1].clone());
+        let inverse_var = verifier.commit(self.commitments[2].clone());
+        let path_vars = self.commitments[3..]
+            .iter()
+            .map(|c| verifier.commit(c.clone()))
+            .collect();
+
+        is_nonzero_gadget(&mut verifier, element_var, inverse_var)?;
+        let mut hash = PoseidonHashConstraints::new(&POSEIDON_PARAMS, &SBOX, CAP_CONST_W_5);
+        hash.verifier_setup(&mut verifier, Some(&params.g), Some(&params.h))?;
+        vanilla_merkle_merkle_tree_4_verif_gadget(
+            &mut verifier,
+            params.depth,
+            root,
+            LeafValueType::Hidden(element_var),
+            AllocatedQuantity {
+                variable: index_var,
+                assignment: None,
+            },
+            path_vars,
+            &mut hash,
+        )?;
+
+        verifier
+            .verify(&self.proof, &params.g, &params.h, &params.G, &params.H)
+            .map_err(|_| MembershipErrorKind::InvalidProof.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commitments::pok_vc::ProverCommittingG1;
+    use signatures::ps::keys::{keygen, Params};
+    use signatures::ps::pok_sig::PoKOfSignature;
+    use signatures::ps::signature::Signature;
+    use std::collections::{HashMap, HashSet};
+
+    const DEPTH: usize = 2;
+
+    fn set() -> MembershipSet {
+        let mut set = MembershipSet::new(DEPTH).unwrap();
+        for i in 0..5 {
+            set.insert(i * 3, hash_to_element(&[i as u8])).unwrap();
+        }
+        set
+    }
+
+    #[test]
+    fn generator_count() {
+        for depth in 1..=3 {
+            let params = MembershipParams::new("test", depth).unwrap();
+            let mut set = MembershipSet::new(depth).unwrap();
+            set.insert(1, FieldElement::one()).unwrap();
+            let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+            let mut prover = Prover::new(&params.g, &params.h, &mut transcript);
+            MembershipProof::constrain(
+                &mut prover,
+                &params,
+                set.root(),
+                &set.witness(1).unwrap(),
+                &FieldElement::random(),
+            )
+            .unwrap();
+            assert_eq!(
+                prover.num_multipliers(),
+                MembershipParams::multipliers(depth)
+            );
+        }
+    }
+
+    #[test]
+    fn prove_membership() {
+        let params = MembershipParams::new("test", DEPTH).unwrap();
+        let set = set();
+        let witness = set.witness(9).unwrap();
+        assert_eq!(witness.element, hash_to_element(&[3]));
+        let blinding = FieldElement::random();
+        let proof =
+            MembershipProof::new(&params, set.root(), &witness, &blinding, b"nonce").unwrap();
+        proof.verify(&params, set.root(), b"nonce").unwrap();
+        assert_eq!(
+            *proof.element_commitment(),
+            params
+                .g
+                .binary_scalar_mul(&params.h, &witness.element, &blinding)
+        );
+
+        assert!(proof.verify(&params, set.root(), b"other").is_err());
+        let mut other = self::set();
+        other.insert(1, hash_to_element(b"new")).unwrap();
+        assert!(proof.verify(&params, other.root(), b"nonce").is_err());
+        let mut tampered = proof.clone();
+        tampered.commitments[1] = G1::random();
+        assert!(tampered.verify(&params, set.root(), b"nonce").is_err());
+        tampered.commitments.pop();
+        assert!(tampered.verify(&params, set.root(), b"nonce").is_err());
+
+        // A removed element has no witness and its old one is stale
+        let mut removed = self::set();
+        removed.remove(9).unwrap();
+        assert_eq!(removed.get(9).unwrap(), None);
+        assert!(removed.witness(9).is_err());
+        let proof =
+            MembershipProof::new(&params, removed.root(), &witness, &blinding, b"nonce").unwrap();
+        assert!(proof.verify(&params, removed.root(), b"nonce").is_err());
+    }
+
+    #[test]
+    fn empty_leaves() {
+        let params = MembershipParams::new("test", DEPTH).unwrap();
+        let mut set = set();
+        assert!(set.insert(1, FieldElement::zero()).is_err());
+        assert!(set.insert(set.capacity(), FieldElement::one()).is_err());
+        assert!(set.witness(1).is_err());
+        assert!(MembershipSet::new(0).is_err());
+        assert!(MembershipSet::new(MAX_DEPTH + 1).is_err());
+
+        // The path of an empty leaf doesn't prove zero is in the set
+        let mut witness = set.witness(0).unwrap();
+        witness.index = 1;
+        witness.element = FieldElement::zero();
+        assert!(
+            MembershipProof::new(&params, set.root(), &witness, &FieldElement::random(), b"")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn hidden_attribute_in_set() {
+        let count = 3;
+        let ps_params = Params::new(b"test");
+        let (vk, sk) = keygen(count, &ps_params);
+        let attribute = hash_to_element(b"member");
+        let msgs = vec![
+            FieldElement::random(),
+            attribute.clone(),
+            FieldElement::random(),
+        ];
+        let sig = Signature::new(msgs.as_slice(), &sk, &ps_params).unwrap();
+
+        let mut set = set();
+        set.insert(5, attribute.clone()).unwrap();
+        let params = MembershipParams::new("test", DEPTH).unwrap();
+        let element_blinding = FieldElement::random();
+        let proof = MembershipProof::new(
+            &params,
+            set.root(),
+            &set.witness(5).unwrap(),
+            &element_blinding,
+            b"nonce",
+        )
+        .unwrap();
+
+        // The attribute and the committed element share a blinding in both sigma protocols
+        let attribute_blinding = FieldElement::random();
+        let blindings = vec![
+            FieldElement::random(),
+            attribute_blinding.clone(),
+            FieldElement::random(),
+        ];
+        let pok_sig = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &ps_params,
+            msgs.as_slice(),
+            Some(blindings.as_slice()),
+            HashSet::new(),
+        )
+        .unwrap();
+        let mut committing = ProverCommittingG1::new();
+        committing.commit(&params.g, Some(&attribute_blinding));
+        committing.commit(&params.h, None);
+        let pok_element = committing.finish();
+
+        let mut challenge_bytes = pok_sig.to_bytes();
+        challenge_bytes.append(&mut pok_element.to_bytes());
+        challenge_bytes.append(&mut proof.element_commitment().to_bytes());
+        let challenge = FieldElement::from_msg_hash(&challenge_bytes);
+        let sig_proof = pok_sig.gen_proof(&challenge).unwrap();
+        let element_proof = pok_element
+            .gen_proof(&challenge, &[attribute, element_blinding])
+            .unwrap();
+
+        // The verifier checks the three proofs and that the responses match
+        proof.verify(&params, set.root(), b"nonce").unwrap();
+        assert!(sig_proof
+            .verify(&vk, &ps_params, HashMap::new(), &challenge)
+            .unwrap());
+        assert!(element_proof
+            .verify(
+                &[params.g.clone(), params.h.clone()],
+                proof.element_commitment(),
+                &challenge
+            )
+            .unwrap());
+        assert_eq!(
+            sig_proof.get_resp_for_message(1).unwrap(),
+            element_proof.responses[0]
+        );
+    }
+}
@@ -0,0 +1,498 @@
+// Copyright contributors to Hyperledger Ursa.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Migration of CL credentials to BBS+ credentials.
+//!
+//! An issuer moving from CL signatures to BBS+ can re-issue every credential under
+//! its new BBS+ key without checking the holder's attributes again from scratch:
+//!
+//! 1. The issuer sends a fresh nonce from `MigrationIssuer::new_nonce`.
+//! 2. The holder creates a `LinkingProof` with `MigrationProver::new_linking_proof`.
+//!    It is a CL proof of the existing credential that reveals every attribute in the
+//!    credential schema, together with a BBS+ blind signature context that commits to
+//!    the attributes in the non credential schema such as the link secret.
+//!    The nonce of the CL proof is derived from the blind signature context, the BBS+
+//!    public key and the issuer's nonce so neither half can be reused with another.
+//! 3. The issuer checks the proof and blindly signs the revealed values with
+//!    `MigrationIssuer::reissue`. Since the values come out of the CL proof they are
+//!    the same as the ones in the CL credential.
+//! 4. The holder unblinds and checks the signature with `MigrationProver::complete_migration`.
+//!
+//! The BBS+ messages are the attributes of both schemas in sorted order and each CL
+//! value is mapped to a message with `encode_value`. The same CL link secret always gives
+//! the same BBS+ link secret, so migrated credentials can still be proven to belong together.
+//!
+//! The proof does not show that the committed hidden values equal the hidden values of the
+//! CL credential, that would need a proof across the two groups. A holder that commits to
+//! something else only breaks the link between their own credentials.
+//! Revocation is not carried over, an issuer that supports revocation should check the
+//! status of the CL credential before re-issuing it.
+use bbs::prelude::{
+    BBSError, BlindSignature, BlindSignatureContext, HashElem, ProofNonce, Prover, PublicKey,
+    SecretKey, Signature, SignatureBlinding, SignatureMessage, ToVariableLengthBytes,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use ursa::bn::BigNumber;
+use ursa::cl::hash::get_hash_as_int;
+use ursa::cl::prover::Prover as CLProver;
+use ursa::cl::verifier::Verifier as CLVerifier;
+use ursa::cl::{
+    CredentialPublicKey, CredentialSchema, CredentialSignature, CredentialValue, CredentialValues,
+    NonCredentialSchema, Nonce, Proof, SubProofRequest,
+};
+use ursa::errors::prelude::*;
+
+const NONCE_DOMAIN: &[u8] = b"ursa cl to bbs+ migration v1";
+
+/// The attribute names in the order of the BBS+ messages
+pub fn message_layout(
+    credential_schema: &CredentialSchema,
+    non_credential_schema: &NonCredentialSchema,
+) -> Vec<String> {
+    credential_schema
+        .attrs()
+        .union(non_credential_schema.attrs())
+        .cloned()
+        .collect()
+}
+
+/// Map an encoded CL attribute value to a BBS+ message
+pub fn encode_value(value: &BigNumber) -> UrsaCryptoResult<SignatureMessage> {
+    Ok(SignatureMessage::hash(value.to_bytes()?))
+}
+
+/// The BBS+ messages of a migrated credential in the order of `message_layout`
+pub fn credential_messages(
+    credential_schema: &CredentialSchema,
+    non_credential_schema: &NonCredentialSchema,
+    credential_values: &CredentialValues,
+) -> UrsaCryptoResult<Vec<SignatureMessage>> {
+    message_layout(credential_schema, non_credential_schema)
+        .iter()
+        .map(|attr| encode_value(value_of(credential_values, attr)?.value()))
+        .collect()
+}
+
+/// Proof that a BBS+ blind signature context belongs to a valid CL credential
+/// whose schema attributes are revealed
+#[derive(Debug)]
+pub struct LinkingProof {
+    /// Proof of the CL credential revealing the credential schema attributes
+    pub cl_proof: Proof,
+    /// Commitment to and proof of the non credential schema attributes
+    pub blind_context: BlindSignatureContext,
+}
+
+impl LinkingProof {
+    /// Check the proof against the CL credential definition and the issuer's new BBS+ key.
+    /// Returns the messages to sign by their index.
+    pub fn verify(
+        &self,
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        credential_pub_key: &CredentialPublicKey,
+        public_key: &PublicKey,
+        nonce: &ProofNonce,
+    ) -> UrsaCryptoResult<BTreeMap<usize, SignatureMessage>> {
+        let layout = message_layout(credential_schema, non_credential_schema);
+        check_public_key(public_key, &layout)?;
+
+        if self.cl_proof.proofs.len() != 1 {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidStructure,
+                "Linking proof must contain exactly one CL sub proof",
+            ));
+        }
+
+        let mut verifier = CLVerifier::new_proof_verifier()?;
+        verifier.add_sub_proof_request(
+            &revealed_sub_proof_request(credential_schema)?,
+            credential_schema,
+            non_credential_schema,
+            credential_pub_key,
+            None,
+            None,
+        )?;
+        let cl_nonce = linking_nonce(&self.blind_context, public_key, nonce)?;
+        if !verifier.verify(&self.cl_proof, &cl_nonce)? {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::ProofRejected,
+                "Invalid CL proof in linking proof",
+            ));
+        }
+
+        let revealed_attrs = self.cl_proof.proofs[0].revealed_attrs()?;
+        let mut messages = BTreeMap::new();
+        for (i, attr) in layout.iter().enumerate() {
+            if let Some(value) = revealed_attrs.get(attr) {
+                messages.insert(i, encode_value(&BigNumber::from_dec(value)?)?);
+            }
+        }
+
+        let revealed: BTreeSet<usize> = messages.keys().copied().collect();
+        if !self
+            .blind_context
+            .verify(&revealed, public_key, nonce)
+            .map_err(from_bbs_error)?
+        {
+            return Err(UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::ProofRejected,
+                "Invalid proof of committed messages in linking proof",
+            ));
+        }
+        Ok(messages)
+    }
+}
+
+/// The holder of a CL credential migrating it to BBS+
+pub struct MigrationProver {}
+
+impl MigrationProver {
+    /// Create the linking proof to send to the issuer and the blinding factor
+    /// needed to complete the BBS+ signature
+    pub fn new_linking_proof(
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        credential_pub_key: &CredentialPublicKey,
+        credential_signature: &CredentialSignature,
+        credential_values: &CredentialValues,
+        public_key: &PublicKey,
+        nonce: &ProofNonce,
+    ) -> UrsaCryptoResult<(LinkingProof, SignatureBlinding)> {
+        let layout = message_layout(credential_schema, non_credential_schema);
+        check_public_key(public_key, &layout)?;
+
+        let mut hidden = BTreeMap::new();
+        for (i, attr) in layout.iter().enumerate() {
+            if !credential_schema.attrs().contains(attr) {
+                hidden.insert(i, encode_value(value_of(credential_values, attr)?.value())?);
+            }
+        }
+        let (blind_context, blinding) =
+            Prover::new_blind_signature_context(public_key, &hidden, nonce)
+                .map_err(from_bbs_error)?;
+
+        let mut proof_builder = CLProver::new_proof_builder()?;
+        proof_builder.add_sub_proof_request(
+            &revealed_sub_proof_request(credential_schema)?,
+            credential_schema,
+            non_credential_schema,
+            credential_signature,
+            credential_values,
+            credential_pub_key,
+            None,
+            None,
+        )?;
+        let cl_proof =
+            proof_builder.finalize(&linking_nonce(&blind_context, public_key, nonce)?)?;
+
+        Ok((
+            LinkingProof {
+                cl_proof,
+                blind_context,
+            },
+            blinding,
+        ))
+    }
+
+    /// Unblind the signature from the issuer and check it signs the CL credential values
+    pub fn complete_migration(
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        credential_values: &CredentialValues,
+        public_key: &PublicKey,
+        blind_signature: &BlindSignature,
+        blinding: &SignatureBlinding,
+    ) -> UrsaCryptoResult<Signature> {
+        let messages =
+            credential_messages(credential_schema, non_credential_schema, credential_values)?;
+        Prover::complete_signature(public_key, &messages, blind_signature, blinding)
+            .map_err(from_bbs_error)
+    }
+}
+
+/// The issuer re-issuing its CL credentials under a BBS+ key
+pub struct MigrationIssuer {}
+
+impl MigrationIssuer {
+    /// Create the nonce the holder must use in its linking proof
+    pub fn new_nonce() -> ProofNonce {
+        bbs::prelude::Issuer::generate_signing_nonce()
+    }
+
+    /// Check the linking proof and blindly sign the values of the CL credential
+    pub fn reissue(
+        linking_proof: &LinkingProof,
+        credential_schema: &CredentialSchema,
+        non_credential_schema: &NonCredentialSchema,
+        credential_pub_key: &CredentialPublicKey,
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+        nonce: &ProofNonce,
+    ) -> UrsaCryptoResult<BlindSignature> {
+        let messages = linking_proof.verify(
+            credential_schema,
+            non_credential_schema,
+            credential_pub_key,
+            public_key,
+            nonce,
+        )?;
+        BlindSignature::new(
+            &linking_proof.blind_context.commitment,
+            &messages,
+            secret_key,
+            public_key,
+        )
+        .map_err(from_bbs_error)
+    }
+}
+
+fn check_public_key(public_key: &PublicKey, layout: &[String]) -> UrsaCryptoResult<()> {
+    if public_key.message_count() != layout.len() {
+        return Err(UrsaCryptoError::from_msg(
+            UrsaCryptoErrorKind::InvalidStructure,
+            format!(
+                "BBS+ public key supports {} messages but the credential has {} attributes",
+                public_key.message_count(),
+                layout.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn value_of<'a>(
+    credential_values: &'a CredentialValues,
+    attr: &str,
+) -> UrsaCryptoResult<&'a CredentialValue> {
+    credential_values.get(attr).ok_or_else(|| {
+        UrsaCryptoError::from_msg(
+            UrsaCryptoErrorKind::InvalidStructure,
+            format!("Credential values are missing {}", attr),
+        )
+    })
+}
+
+fn revealed_sub_proof_request(
+    credential_schema: &CredentialSchema,
+) -> UrsaCryptoResult<SubProofRequest> {
+    let mut builder = CLVerifier::new_sub_proof_request_builder()?;
+    for attr in credential_schema.attrs() {
+        builder.add_revealed_attr(attr)?;
+    }
+    builder.finalize()
+}
+
+fn linking_nonce(
+    blind_context: &BlindSignatureContext,
+    public_key: &PublicKey,
+    nonce: &ProofNonce,
+) -> UrsaCryptoResult<Nonce> {
+    get_hash_as_int(&[
+        NONCE_DOMAIN.to_vec(),
+        public_key.to_bytes_compressed_form(),
+        blind_context.to_bytes_compressed_form(),
+        nonce.to_bytes_compressed_form().to_vec(),
+    ])
+}
+
+fn from_bbs_error(err: BBSError) -> UrsaCryptoError {
+    UrsaCryptoError::from_msg(UrsaCryptoErrorKind::InvalidStructure, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bbs::prelude::{HiddenMessage, Issuer, ProofMessage, ProofRequest, Verifier};
+    use ursa::cl::issuer::Issuer as CLIssuer;
+    use ursa::cl::{new_nonce, CredentialKeyCorrectnessProof, CredentialPrivateKey, MasterSecret};
+
+    struct Fixture {
+        schema: CredentialSchema,
+        non_schema: NonCredentialSchema,
+        cl_pub_key: CredentialPublicKey,
+        cl_signature: CredentialSignature,
+        values: CredentialValues,
+    }
+
+    fn cl_credential() -> Fixture {
+        let mut schema_builder = CLIssuer::new_credential_schema_builder().unwrap();
+        schema_builder.add_attr("name").unwrap();
+        schema_builder.add_attr("age").unwrap();
+        let schema = schema_builder.finalize().unwrap();
+        let mut non_schema_builder = CLIssuer::new_non_credential_schema_builder().unwrap();
+        non_schema_builder.add_attr("master_secret").unwrap();
+        let non_schema = non_schema_builder.finalize().unwrap();
+
+        let (cl_pub_key, cl_priv_key, key_proof): (
+            CredentialPublicKey,
+            CredentialPrivateKey,
+            CredentialKeyCorrectnessProof,
+        ) = CLIssuer::new_credential_def(&schema, &non_schema, false).unwrap();
+        let master_secret: MasterSecret = CLProver::new_master_secret().unwrap();
+
+        let mut values_builder = CLIssuer::new_credential_values_builder().unwrap();
+        values_builder
+            .add_value_hidden("master_secret", &master_secret.value().unwrap())
+            .unwrap();
+        values_builder
+            .add_dec_known("name", "1139481716457488690172217916278103335")
+            .unwrap();
+        values_builder.add_dec_known("age", "28").unwrap();
+        let values = values_builder.finalize().unwrap();
+
+        let key_nonce = new_nonce().unwrap();
+        let (blinded_secrets, blinding_factors, blinded_proof) =
+            CLProver::blind_credential_secrets(&cl_pub_key, &key_proof, &values, &key_nonce)
+                .unwrap();
+        let issuance_nonce = new_nonce().unwrap();
+        let (mut cl_signature, signature_proof) = CLIssuer::sign_credential(
+            "CnEDk9HrMnmiHXEV1WFgbVCRteYnPqsJwrTdcZaNhFVW",
+            &blinded_secrets,
+            &blinded_proof,
+            &key_nonce,
+            &issuance_nonce,
+            &values,
+            &cl_pub_key,
+            &cl_priv_key,
+        )
+        .unwrap();
+        CLProver::process_credential_signature(
+            &mut cl_signature,
+            &values,
+            &signature_proof,
+            &blinding_factors,
+            &cl_pub_key,
+            &issuance_nonce,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        Fixture {
+            schema,
+            non_schema,
+            cl_pub_key,
+            cl_signature,
+            values,
+        }
+    }
+
+    #[test]
+    fn migrate_cl_credential_to_bbs() {
+        let f = cl_credential();
+        let layout = message_layout(&f.schema, &f.non_schema);
+        assert_eq!(layout, vec!["age", "master_secret", "name"]);
+        let (public_key, secret_key) = Issuer::new_keys(layout.len()).unwrap();
+
+        let nonce = MigrationIssuer::new_nonce();
+        let (linking_proof, blinding) = MigrationProver::new_linking_proof(
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &f.cl_signature,
+            &f.values,
+            &public_key,
+            &nonce,
+        )
+        .unwrap();
+        let blind_signature = MigrationIssuer::reissue(
+            &linking_proof,
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &public_key,
+            &secret_key,
+            &nonce,
+        )
+        .unwrap();
+        let signature = MigrationProver::complete_migration(
+            &f.schema,
+            &f.non_schema,
+            &f.values,
+            &public_key,
+            &blind_signature,
+            &blinding,
+        )
+        .unwrap();
+
+        // The migrated credential can be presented like any other BBS+ credential
+        let messages = credential_messages(&f.schema, &f.non_schema, &f.values).unwrap();
+        assert!(signature.verify(&messages, &public_key).unwrap());
+        let request: ProofRequest = Verifier::new_proof_request(&[0, 2], &public_key).unwrap();
+        let proof_messages = vec![
+            ProofMessage::Revealed(messages[0]),
+            ProofMessage::Hidden(HiddenMessage::ProofSpecificBlinding(messages[1])),
+            ProofMessage::Revealed(messages[2]),
+        ];
+        let pok = Prover::commit_signature_pok(&request, &proof_messages, &signature).unwrap();
+        let proof_nonce = Verifier::generate_proof_nonce();
+        let challenge =
+            Prover::create_challenge_hash(std::slice::from_ref(&pok), None, &proof_nonce).unwrap();
+        let proof = Prover::generate_signature_pok(pok, &challenge).unwrap();
+        let revealed = Verifier::verify_signature_pok(&request, &proof, &proof_nonce).unwrap();
+        assert_eq!(
+            revealed,
+            vec![
+                encode_value(&BigNumber::from_dec("28").unwrap()).unwrap(),
+                encode_value(
+                    &BigNumber::from_dec("1139481716457488690172217916278103335").unwrap()
+                )
+                .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn linking_proof_is_bound_to_nonce_and_key() {
+        let f = cl_credential();
+        let (public_key, secret_key) = Issuer::new_keys(3).unwrap();
+        let nonce = MigrationIssuer::new_nonce();
+        let (linking_proof, _) = MigrationProver::new_linking_proof(
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &f.cl_signature,
+            &f.values,
+            &public_key,
+            &nonce,
+        )
+        .unwrap();
+
+        let res = MigrationIssuer::reissue(
+            &linking_proof,
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &public_key,
+            &secret_key,
+            &MigrationIssuer::new_nonce(),
+        );
+        assert!(res.is_err());
+
+        let (other_public_key, other_secret_key) = Issuer::new_keys(3).unwrap();
+        let res = MigrationIssuer::reissue(
+            &linking_proof,
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &other_public_key,
+            &other_secret_key,
+            &nonce,
+        );
+        assert!(res.is_err());
+
+        let (short_public_key, _) = Issuer::new_keys(2).unwrap();
+        let res = MigrationProver::new_linking_proof(
+            &f.schema,
+            &f.non_schema,
+            &f.cl_pub_key,
+            &f.cl_signature,
+            &f.values,
+            &short_public_key,
+            &nonce,
+        );
+        assert!(res.is_err());
+    }
+}
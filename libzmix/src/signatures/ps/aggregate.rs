@@ -0,0 +1,353 @@
+// Aggregation of signatures from several signers.
+//
+// Signatures from different signers over the same messages are aggregated by having every signer
+// use the same `sigma_1`, the hash of the messages. The aggregate is the sum of the `sigma_2` and
+// verifies as a normal signature against the sum of the signers' verkeys, so a credential issued by
+// several authorities is checked with a single pairing check.
+//
+// Sequential aggregate signatures are from section 5 of the paper. Each signer adds its own
+// messages to the aggregate of the previous signers and the result is checked against all the
+// messages and verkeys at once.
+
+use super::errors::{PSError, PSErrorKind};
+use super::keys::{Params, Sigkey, Verkey};
+use super::signature::Signature;
+use super::{ate_2_pairing, OtherGroup, OtherGroupVec, SignatureGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+impl Verkey {
+    /// Combine the verkeys of several signers over the same number of messages.
+    /// The signers must have proven possession of their signing keys, otherwise a signer can
+    /// pick its verkey to cancel out the others.
+    pub fn aggregate(verkeys: &[&Verkey]) -> Result<Verkey, PSError> {
+        let first = verkeys.first().ok_or_else(|| PSErrorKind::GeneralError {
+            msg: "No verkeys to aggregate".to_string(),
+        })?;
+        let mut X_tilde = OtherGroup::identity();
+        let mut Y_tilde = vec![OtherGroup::identity(); first.msg_count()];
+        for vk in verkeys {
+            if vk.msg_count() != first.msg_count() {
+                return Err(PSErrorKind::UnsupportedNoOfMessages {
+                    expected: first.msg_count(),
+                    given: vk.msg_count(),
+                }
+                .into());
+            }
+            X_tilde += &vk.X_tilde;
+            for (agg, y) in Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+                *agg += y;
+            }
+        }
+        Ok(Verkey { X_tilde, Y_tilde })
+    }
+}
+
+impl Signature {
+    /// Signer creates a signature that can be aggregated with signatures of other signers over the
+    /// same messages. `sigma_1` is derived from the messages instead of being random.
+    pub fn new_aggregatable(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let sigma_1 = Self::aggregatable_sigma_1(messages, params);
+        let mut exp = sigkey.x.clone();
+        for (y, m) in sigkey.y.iter().zip(messages.iter()) {
+            exp += y * m;
+        }
+        let sigma_2 = &sigma_1 * &exp;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Combine signatures of several signers over the same messages created with `new_aggregatable`.
+    /// The result verifies with `Signature::verify` against `Verkey::aggregate` of the signers' verkeys.
+    pub fn aggregate(signatures: &[Signature]) -> Result<Self, PSError> {
+        let first = signatures
+            .first()
+            .ok_or_else(|| PSErrorKind::GeneralError {
+                msg: "No signatures to aggregate".to_string(),
+            })?;
+        let mut sigma_2 = SignatureGroup::identity();
+        for sig in signatures {
+            if sig.sigma_1 != first.sigma_1 {
+                return Err(PSErrorKind::GeneralError {
+                    msg: "Signatures are not over the same messages".to_string(),
+                }
+                .into());
+            }
+            sigma_2 += &sig.sigma_2;
+        }
+        Ok(Self {
+            sigma_1: first.sigma_1.clone(),
+            sigma_2,
+        })
+    }
+
+    fn aggregatable_sigma_1(messages: &[FieldElement], params: &Params) -> SignatureGroup {
+        let mut bytes = params.g.to_bytes();
+        bytes.extend_from_slice(&(messages.len() as u64).to_be_bytes());
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        SignatureGroup::from_msg_hash(&[b"PS aggregatable : sigma_1".to_vec(), bytes].concat())
+    }
+}
+
+/// Public parameters for sequential aggregate signatures. `X` and `X_tilde` share a secret exponent
+/// that nobody should know, the party running the setup must discard it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SequentialParams {
+    pub params: Params,
+    pub X: SignatureGroup,
+    pub X_tilde: OtherGroup,
+}
+
+impl SequentialParams {
+    pub fn new(label: &[u8]) -> Self {
+        let params = Params::new(label);
+        let x = FieldElement::random();
+        let X = &params.g * &x;
+        let X_tilde = &params.g_tilde * &x;
+        Self { params, X, X_tilde }
+    }
+}
+
+/// Signing key of one signer in a sequential aggregate
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SequentialSigkey {
+    pub y: Vec<FieldElement>,
+}
+
+/// Verkey of one signer in a sequential aggregate
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SequentialVerkey {
+    pub Y_tilde: Vec<OtherGroup>,
+}
+
+impl SequentialVerkey {
+    pub fn msg_count(&self) -> usize {
+        self.Y_tilde.len()
+    }
+}
+
+pub fn sequential_keygen(
+    count_messages: usize,
+    params: &SequentialParams,
+) -> (SequentialVerkey, SequentialSigkey) {
+    let mut y = vec![];
+    let mut Y_tilde = vec![];
+    for _ in 0..count_messages {
+        let y_i = FieldElement::random();
+        Y_tilde.push(&params.params.g_tilde * &y_i);
+        y.push(y_i);
+    }
+    (SequentialVerkey { Y_tilde }, SequentialSigkey { y })
+}
+
+/// A signature by an ordered list of signers, each on its own messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SequentialAggregateSignature {
+    pub signature: Signature,
+}
+
+impl SequentialAggregateSignature {
+    /// The aggregate of no signers that the first signer extends
+    pub fn new(params: &SequentialParams) -> Self {
+        Self {
+            signature: Signature {
+                sigma_1: params.params.g.clone(),
+                sigma_2: params.X.clone(),
+            },
+        }
+    }
+
+    /// Check the aggregate of the previous signers and add a signature on `messages`.
+    /// `previous_messages` and `previous_verkeys` are in the order the previous signers signed.
+    pub fn extend(
+        &self,
+        previous_messages: &[&[FieldElement]],
+        previous_verkeys: &[&SequentialVerkey],
+        messages: &[FieldElement],
+        sigkey: &SequentialSigkey,
+        params: &SequentialParams,
+    ) -> Result<Self, PSError> {
+        if messages.len() != sigkey.y.len() {
+            return Err(PSErrorKind::UnsupportedNoOfMessages {
+                expected: messages.len(),
+                given: sigkey.y.len(),
+            }
+            .into());
+        }
+        let valid = if previous_verkeys.is_empty() {
+            self.signature.sigma_1 == params.params.g && self.signature.sigma_2 == params.X
+        } else {
+            self.verify(previous_messages, previous_verkeys, params)?
+        };
+        if !valid {
+            return Err(PSErrorKind::GeneralError {
+                msg: "Invalid aggregate signature from previous signers".to_string(),
+            }
+            .into());
+        }
+        let mut exp = FieldElement::zero();
+        for (y, m) in sigkey.y.iter().zip(messages.iter()) {
+            exp += y * m;
+        }
+        // (sigma_1^t, (sigma_2 * sigma_1^{y_1*m_1 + y_2*m_2 + ...})^t)
+        let t = FieldElement::random();
+        let sigma_1 = &self.signature.sigma_1 * &t;
+        let sigma_2 = &(&self.signature.sigma_2 + &(&self.signature.sigma_1 * &exp)) * &t;
+        Ok(Self {
+            signature: Signature { sigma_1, sigma_2 },
+        })
+    }
+
+    /// Verify the aggregate with a single pairing check.
+    /// `messages[i]` are the messages signed by the signer with `verkeys[i]`.
+    pub fn verify(
+        &self,
+        messages: &[&[FieldElement]],
+        verkeys: &[&SequentialVerkey],
+        params: &SequentialParams,
+    ) -> Result<bool, PSError> {
+        let sig = &self.signature;
+        if verkeys.is_empty() || sig.sigma_1.is_identity() || sig.sigma_2.is_identity() {
+            return Ok(false);
+        }
+        if messages.len() != verkeys.len() {
+            return Err(PSErrorKind::GeneralError {
+                msg: format!(
+                    "Given messages of {} signers but {} verkeys",
+                    messages.len(),
+                    verkeys.len()
+                ),
+            }
+            .into());
+        }
+        let mut Y_m_bases = OtherGroupVec::new(0);
+        let mut Y_m_exps = FieldElementVector::new(0);
+        for (msgs, vk) in messages.iter().zip(verkeys.iter()) {
+            if msgs.len() != vk.msg_count() {
+                return Err(PSErrorKind::UnsupportedNoOfMessages {
+                    expected: msgs.len(),
+                    given: vk.msg_count(),
+                }
+                .into());
+            }
+            for (m, Y) in msgs.iter().zip(vk.Y_tilde.iter()) {
+                Y_m_bases.push(Y.clone());
+                Y_m_exps.push(m.clone());
+            }
+        }
+        // Y_m = X_tilde * Y_tilde_1[1]^m_1_1 * Y_tilde_1[2]^m_1_2 * ... Y_tilde_2[1]^m_2_1 * ...
+        let Y_m = &params.X_tilde
+            + &(Y_m_bases
+                .multi_scalar_mul_var_time(Y_m_exps.as_slice())
+                .unwrap());
+        let e = ate_2_pairing(
+            &sig.sigma_1,
+            &Y_m,
+            &(sig.sigma_2.negation()),
+            &params.params.g_tilde,
+        );
+        Ok(e.is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::keys::keygen;
+    use super::*;
+
+    #[test]
+    fn test_aggregate_same_messages() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let keys: Vec<(Verkey, Sigkey)> = (0..3).map(|_| keygen(count_msgs, &params)).collect();
+        let msgs = FieldElementVector::random(count_msgs);
+        let msgs = msgs.as_slice();
+
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .map(|(vk, sk)| {
+                let sig = Signature::new_aggregatable(msgs, sk, &params).unwrap();
+                assert!(sig.verify(msgs, vk, &params).unwrap());
+                sig
+            })
+            .collect();
+        let agg_sig = Signature::aggregate(&sigs).unwrap();
+        let vks: Vec<&Verkey> = keys.iter().map(|(vk, _)| vk).collect();
+        let agg_vk = Verkey::aggregate(&vks).unwrap();
+        assert!(agg_sig.verify(msgs, &agg_vk, &params).unwrap());
+
+        // Missing a signer
+        let partial_vk = Verkey::aggregate(&vks[..2]).unwrap();
+        assert!(!agg_sig.verify(msgs, &partial_vk, &params).unwrap());
+
+        // Different messages
+        let other_msgs = FieldElementVector::random(count_msgs);
+        assert!(!agg_sig
+            .verify(other_msgs.as_slice(), &agg_vk, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_aggregate_rejects_mismatched_inputs() {
+        let params = Params::new("test".as_bytes());
+        let (vk_1, sk_1) = keygen(3, &params);
+        let (vk_2, sk_2) = keygen(3, &params);
+        let (vk_3, _) = keygen(4, &params);
+        let msgs = FieldElementVector::random(3);
+        let msgs = msgs.as_slice();
+
+        // Signatures with random sigma_1 can't be aggregated
+        let sig_1 = Signature::new(msgs, &sk_1, &params).unwrap();
+        let sig_2 = Signature::new(msgs, &sk_2, &params).unwrap();
+        assert!(Signature::aggregate(&[sig_1, sig_2]).is_err());
+        assert!(Signature::aggregate(&[]).is_err());
+
+        assert!(Verkey::aggregate(&[&vk_1, &vk_2, &vk_3]).is_err());
+        assert!(Verkey::aggregate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_sequential_aggregate() {
+        let params = SequentialParams::new("test".as_bytes());
+        let counts = [2, 4, 1];
+        let keys: Vec<(SequentialVerkey, SequentialSigkey)> = counts
+            .iter()
+            .map(|c| sequential_keygen(*c, &params))
+            .collect();
+        let msgs: Vec<FieldElementVector> = counts
+            .iter()
+            .map(|c| FieldElementVector::random(*c))
+            .collect();
+        let msgs: Vec<&[FieldElement]> = msgs.iter().map(|m| m.as_slice()).collect();
+        let vks: Vec<&SequentialVerkey> = keys.iter().map(|(vk, _)| vk).collect();
+
+        let mut agg = SequentialAggregateSignature::new(&params);
+        for (i, (_, sk)) in keys.iter().enumerate() {
+            agg = agg
+                .extend(&msgs[..i], &vks[..i], msgs[i], sk, &params)
+                .unwrap();
+            assert!(agg.verify(&msgs[..=i], &vks[..=i], &params).unwrap());
+        }
+
+        // Changing a message of any signer fails
+        let other = FieldElementVector::random(counts[1]);
+        let mut bad_msgs = msgs.clone();
+        bad_msgs[1] = other.as_slice();
+        assert!(!agg.verify(&bad_msgs, &vks, &params).unwrap());
+
+        // Dropping a signer fails
+        assert!(!agg.verify(&msgs[..2], &vks[..2], &params).unwrap());
+        assert!(agg.verify(&msgs[..2], &vks[..3], &params).is_err());
+
+        // A signer won't extend an invalid aggregate
+        assert!(agg
+            .extend(&bad_msgs, &vks, msgs[0], &keys[0].1, &params)
+            .is_err());
+    }
+}
@@ -45,6 +45,7 @@ pub(crate) fn ate_2_pairing(
 #[cfg(feature = "PS_Signature_G1")]
 pub const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
 
+pub mod aggregate;
 pub mod blind_signature;
 pub mod errors;
 pub mod keys;
@@ -52,6 +53,10 @@ pub mod pok_sig;
 pub mod signature;
 
 pub mod prelude {
+    pub use super::aggregate::{
+        sequential_keygen, SequentialAggregateSignature, SequentialParams, SequentialSigkey,
+        SequentialVerkey,
+    };
     pub use super::blind_signature::{
         BlindSignature, BlindingKey, ProofSignatureGroup, ProverCommittedSignatureGroup,
         ProverCommittingSignatureGroup,
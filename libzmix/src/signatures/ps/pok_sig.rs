@@ -91,8 +91,7 @@ impl PoKOfSignature {
         let t = FieldElement::random();
 
         // Transform signature to an aggregate signature on (messages, t)
-        let sigma_prime_1 = &sig.sigma_1 * &r;
-        let sigma_prime_2 = (&sig.sigma_2 + (&sig.sigma_1 * &t)) * &r;
+        let sigma_prime = sig.blind(&t).randomize_with(&r);
 
         // +1 for `t`
         let hidden_msg_count = vk.Y_tilde.len() - revealed_msg_indices.len() + 1;
@@ -121,10 +120,6 @@ impl PoKOfSignature {
         }
         let committed = committing.finish();
 
-        let sigma_prime = Signature {
-            sigma_1: sigma_prime_1,
-            sigma_2: sigma_prime_2,
-        };
         Ok(Self {
             secrets: exponents,
             sig: sigma_prime,
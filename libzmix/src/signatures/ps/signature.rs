@@ -89,6 +89,30 @@ impl Signature {
         Ok(e.is_one())
     }
 
+    /// Randomize the signature as in section 4.2 of the paper: (sigma_1^r, sigma_2^r) for a
+    /// random r is a fresh signature on the same messages, unlinkable to this one
+    pub fn randomize(&self) -> Self {
+        self.randomize_with(&FieldElement::random())
+    }
+
+    /// Randomize the signature with the given non-zero exponent `r`
+    pub fn randomize_with(&self, r: &FieldElement) -> Self {
+        Self {
+            sigma_1: &self.sigma_1 * r,
+            sigma_2: &self.sigma_2 * r,
+        }
+    }
+
+    /// Transform the signature to one on the messages and `t`, as in step 1 of section 6.2 of
+    /// the paper: (sigma_1, sigma_2 * sigma_1^t) verifies against the verkey extended with
+    /// g_tilde as the base of `t`. Randomize the result to show it
+    pub fn blind(&self, t: &FieldElement) -> Self {
+        Self {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2: &self.sigma_2 + &(&self.sigma_1 * t),
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![];
         bytes.append(&mut self.sigma_1.to_bytes());
@@ -168,4 +192,64 @@ mod tests {
         };
         assert!(!sig_bad.verify(msgs.as_slice(), &vk, &params).unwrap());
     }
+
+    #[test]
+    fn test_signature_randomize() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (vk, sk) = keygen(count_msgs, &params);
+        let msgs = FieldElementVector::random(count_msgs);
+        let msgs = msgs.as_slice();
+        let u = FieldElement::random();
+        let (sigma_1, sigma_2) =
+            Signature::sign_with_sigma_1_generated_from_given_exp(msgs, &sk, &u, 0, &params.g)
+                .unwrap();
+        let sig = Signature { sigma_1, sigma_2 };
+
+        let sig_1 = sig.randomize();
+        let sig_2 = sig.randomize();
+        assert!(sig_1.verify(msgs, &vk, &params).unwrap());
+        assert!(sig_2.verify(msgs, &vk, &params).unwrap());
+        assert_ne!(sig_1.to_bytes(), sig.to_bytes());
+        assert_ne!(sig_1.to_bytes(), sig_2.to_bytes());
+        assert_ne!(sig_1.sigma_1, sig.sigma_1);
+        assert_ne!(sig_1.sigma_1, sig_2.sigma_1);
+        assert!(!sig_1
+            .verify(
+                FieldElementVector::random(count_msgs).as_slice(),
+                &vk,
+                &params
+            )
+            .unwrap());
+
+        // A randomized signature is the signature the signer would have made with h = g^(u*r)
+        let r = FieldElement::random();
+        let (sigma_1, sigma_2) = Signature::sign_with_sigma_1_generated_from_given_exp(
+            msgs,
+            &sk,
+            &(&u * &r),
+            0,
+            &params.g,
+        )
+        .unwrap();
+        let sig_r = sig.randomize_with(&r);
+        assert_eq!(sig_r.sigma_1, sigma_1);
+        assert_eq!(sig_r.sigma_2, sigma_2);
+
+        // A blinded signature verifies on the messages and t against the extended verkey
+        let t = FieldElement::random();
+        let blinded = sig.blind(&t).randomize();
+        assert!(!blinded.verify(msgs, &vk, &params).unwrap());
+        let mut msgs_t = msgs.to_vec();
+        msgs_t.push(t);
+        let mut vk_t = vk.clone();
+        vk_t.Y_tilde.push(params.g_tilde.clone());
+        assert!(blinded.verify(msgs_t.as_slice(), &vk_t, &params).unwrap());
+
+        let identity = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: SignatureGroup::identity(),
+        };
+        assert!(!identity.randomize().verify(msgs, &vk, &params).unwrap());
+    }
 }
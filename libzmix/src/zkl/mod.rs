@@ -13,6 +13,12 @@ pub struct Witness {}
 
 pub struct WitnessBuilder {}
 
+impl Default for WitnessBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WitnessBuilder {
     pub fn new() -> WitnessBuilder {
         WitnessBuilder {}
@@ -38,7 +44,8 @@ pub enum ZKLError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use zkl::spec::ProofSpecBuilder;
+    use membership::MembershipSet;
+    use zkl::spec::{ProofSpecBuilder, ProofSpecClauseType, SetMembershipClause};
 
     #[test]
     #[ignore]
@@ -76,8 +83,14 @@ mod tests {
     #[test]
     #[ignore]
     fn parse_set_membership_clause() {
-        //TODO: Add set membership clause to the proof_spec
-        let pb = ProofSpecBuilder::new();
+        let set = MembershipSet::new(4).unwrap();
+        let mut pb = ProofSpecBuilder::new();
+        pb.set_attribute_count(1);
+        pb.add_clause(ProofSpecClauseType::SetMembership(SetMembershipClause {
+            attribute_index: 0,
+            depth: set.depth(),
+            root: set.root().clone(),
+        }));
         let proof_spec = pb.finalize();
         let witness = Witness {};
         assert!(Parser::parse(&proof_spec, &witness).is_ok());
@@ -1,3 +1,5 @@
+use amcl_wrapper::field_elem::FieldElement;
+
 pub struct ProofSpec {
     pub attribute_count: u32,
     pub disclosed_attributes: Vec<DisclosedAttribute>,
@@ -11,6 +13,12 @@ pub struct ProofSpecBuilder {
     clauses: Vec<ProofSpecClauseType>,
 }
 
+impl Default for ProofSpecBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ProofSpecBuilder {
     pub fn new() -> ProofSpecBuilder {
         ProofSpecBuilder {
@@ -47,11 +55,19 @@ impl ProofSpecBuilder {
 pub enum ProofSpecClauseType {
     Credential,
     Interval,
-    SetMembership,
+    SetMembership(SetMembershipClause),
     VerifiableEncryption,
     Nym,
 }
 
+/// A hidden attribute is an element of the set with `root`, proven with
+/// `membership::MembershipProof` on a tree of `depth`
+pub struct SetMembershipClause {
+    pub attribute_index: u32,
+    pub depth: usize,
+    pub root: FieldElement,
+}
+
 pub struct DisclosedAttribute {
     pub index: u32,
     pub value: String,
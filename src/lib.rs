@@ -15,4 +15,28 @@
 //! cryptography used by blockchains. There is nothing
 //! specific to any blockchain in Ursa and can be used
 //! outside of that context.
+//!
+//! # Stability
+//!
+//! [`prelude`] is the stable API of the crate. Nothing in it is removed or changed
+//! incompatibly outside a major release: `tests/prelude.rs` pins every item and fails
+//! to compile when one changes, and CI runs `cargo semver-checks` against the last
+//! release.
+//!
+//! Everything else is in `unstable`, which only exists with the `unstable` feature and
+//! can change in any release. That includes the MPC protocols, distributed key
+//! generation, proactive refresh, FROST and threshold ECDSA, and the number theoretic transforms meant
+//! for lattice based post-quantum schemes. Features that only add unstable code,
+//! `frost` and `threshold_ecdsa`, refuse to build without `unstable`, so depending on
+//! them is always a deliberate choice.
+#![cfg_attr(feature = "nightly", feature(doc_cfg))]
+
+#[cfg(all(feature = "frost", not(feature = "unstable")))]
+compile_error!("The 'frost' feature is unstable, enable 'unstable' to use it");
+#[cfg(all(feature = "threshold_ecdsa", not(feature = "unstable")))]
+compile_error!("The 'threshold_ecdsa' feature is unstable, enable 'unstable' to use it");
+
 pub mod prelude;
+#[cfg(feature = "unstable")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "unstable")))]
+pub mod unstable;
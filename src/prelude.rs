@@ -11,6 +11,37 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-//! Convenience re-export of common members
+//! The stable API of the crate.
+//!
+//! Everything here follows semver. Items are re-exported one by one, never with a
+//! glob, so that something new in a subcrate doesn't become part of the promise
+//! without a decision to add it here.
+#[cfg(feature = "ed25519")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ed25519")))]
+pub use ursa_sharing::ed25519::{Ed25519Point, Ed25519Scalar};
+#[cfg(feature = "ristretto")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ristretto")))]
+pub use ursa_sharing::ristretto::{Ristretto25519Point, Ristretto25519Scalar};
+#[cfg(feature = "secp256k1")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "secp256k1")))]
+pub use ursa_sharing::secp256k1::{Secp256k1Point, Secp256k1Scalar};
+#[cfg(feature = "secp256r1")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "secp256r1")))]
+pub use ursa_sharing::secp256r1::{Secp256r1Point, Secp256r1Scalar};
 #[cfg(feature = "sharing")]
+pub use ursa_sharing::{
+    error::{SharingError, SharingResult},
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    pedersen::{PedersenVerifier, PedersenVssResult, Scheme as PedersenScheme},
+    shamir::{Scheme as ShamirScheme, Share},
+    Field, Group, ShareVerifier, Strictness,
+};
+
+/// The whole of `ursa_sharing`, kept for one release while callers move to the
+/// items above or `ursa::unstable::sharing`
+#[cfg(feature = "sharing")]
+#[deprecated(
+    since = "0.5.0",
+    note = "use the items in `ursa::prelude` or `ursa::unstable::sharing`"
+)]
 pub use ursa_sharing as sharing;
@@ -0,0 +1,25 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! APIs that can change in any release, only built with the `unstable` feature.
+//!
+//! `sharing` is the whole of `ursa_sharing`, including what [`prelude`](crate::prelude)
+//! re-exports as stable. The modules below are the experimental parts of it.
+pub use ursa_sharing as sharing;
+#[cfg(feature = "frost")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "frost")))]
+pub use ursa_sharing::frost;
+#[cfg(feature = "threshold_ecdsa")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "threshold_ecdsa")))]
+pub use ursa_sharing::threshold_ecdsa;
+pub use ursa_sharing::{dkg, ntt, refresh};
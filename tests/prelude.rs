@@ -0,0 +1,66 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Pins the stable API of `ursa::prelude`. A change that stops this file from compiling
+//! breaks downstream code too and needs a major release.
+#![cfg(feature = "sharing")]
+
+use ursa::prelude::*;
+
+#[test]
+fn signatures() {
+    let _: fn(usize, usize) -> SharingResult<ShamirScheme> = ShamirScheme::new;
+    let _: fn(&ShamirScheme) -> usize = ShamirScheme::threshold;
+    let _: fn(&ShamirScheme) -> usize = ShamirScheme::limit;
+    let _: fn(usize, Vec<u8>) -> Share = Share::new::<Vec<u8>>;
+    let _: fn(&Share) -> Vec<u8> = Share::to_bytes;
+    let _: fn(&Share) -> u32 = Share::identifier;
+    let _: fn(&Share) -> &[u8] = Share::value;
+    let _: fn(usize, usize) -> SharingResult<FeldmanScheme> = FeldmanScheme::new;
+    let _: fn(usize, usize) -> SharingResult<PedersenScheme> = PedersenScheme::new;
+    let _: [Strictness; 2] = [Strictness::Strict, Strictness::default()];
+
+    match ShamirScheme::new(3, 2) {
+        Err(SharingError::ShareLimitLessThanThreshold) => {}
+        r => panic!("expected a limit error, found {:?}", r.map(|_| ())),
+    }
+    match ShamirScheme::new(1, 2) {
+        Err(SharingError::ShareMinThreshold) => {}
+        r => panic!("expected a threshold error, found {:?}", r.map(|_| ())),
+    }
+}
+
+#[cfg(feature = "ed25519")]
+#[test]
+fn split_and_combine() {
+    use rand::rngs::OsRng;
+
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let scheme = ShamirScheme::new(2, 3).unwrap();
+    let shares = scheme.split_secret(&mut OsRng, &secret).unwrap();
+    let combined = scheme
+        .combine_shares::<Ed25519Scalar, Ed25519Scalar>(&shares[1..])
+        .unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+
+    let scheme = FeldmanScheme::new(2, 3).unwrap();
+    let (verifier, shares): (FeldmanVerifier<Ed25519Scalar, Ed25519Point>, Vec<Share>) =
+        scheme.split_secret(&mut OsRng, &secret, None).unwrap();
+    for share in &shares {
+        verifier.verify_share(share).unwrap();
+    }
+    let combined = scheme
+        .combine_shares::<Ed25519Scalar, Ed25519Scalar>(&shares[..2])
+        .unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+}
@@ -0,0 +1,140 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[macro_use]
+extern crate criterion;
+
+use criterion::{BenchmarkId, Criterion};
+use ff::Field as FFField;
+use generic_array::{typenum::U32, GenericArray};
+use k256::{FieldBytes, Scalar};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use ursa_sharing::{error::*, shamir::Scheme, zeroize::Zeroize, Field, Group};
+
+struct K256Scalar(Scalar);
+
+impl Zeroize for K256Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Scalar::zero();
+    }
+}
+
+impl Field for K256Scalar {
+    fn one() -> Self {
+        Self(Scalar::one())
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(Scalar::from(value as u64))
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = Option::<Scalar>::from(rhs.0.invert()).ok_or(SharingError::DivisionByZero)?;
+        self.0 *= inverse;
+        Ok(())
+    }
+}
+
+impl Group for K256Scalar {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(Scalar::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() <= 32 {
+            let mut s = [0u8; 32];
+            s[..value.len()].copy_from_slice(value);
+            Ok(Self(Scalar::from_bytes_reduced(FieldBytes::from_slice(&s))))
+        } else {
+            Err(SharingError::ShareInvalidSecret)
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero().unwrap_u8() == 1
+    }
+
+    fn is_valid(&self) -> bool {
+        self.0.is_zero().unwrap_u8() == 0
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.negate()
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        self.0 *= rhs.0
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut c = [0u8; 32];
+        c.copy_from_slice(self.0.to_bytes().as_slice());
+        c.into()
+    }
+}
+
+/// Evaluate each point in turn with Horner's method
+fn evaluate_sequential(coefficients: &[K256Scalar], limit: usize) -> Vec<Vec<u8>> {
+    let degree = coefficients.len() - 1;
+    (1..=limit)
+        .map(|i| {
+            let x = K256Scalar::from_usize(i);
+            let mut y = K256Scalar::zero();
+            y.add_assign(&coefficients[degree]);
+            for c in coefficients[..degree].iter().rev() {
+                y.scalar_mul_assign(&x);
+                y.add_assign(c);
+            }
+            y.to_bytes().to_vec()
+        })
+        .collect()
+}
+
+fn share_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Share generation");
+    group.sample_size(10);
+    for limit in [100, 1000, 4000] {
+        let threshold = limit / 2 + 1;
+        let secret = K256Scalar::random(&mut OsRng);
+        let coefficients = (0..threshold)
+            .map(|_| K256Scalar::random(&mut OsRng))
+            .collect::<Vec<_>>();
+        let scheme = Scheme::new(threshold, limit).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("sequential", limit), &limit, |b, l| {
+            b.iter(|| evaluate_sequential(coefficients.as_slice(), *l))
+        });
+        group.bench_with_input(BenchmarkId::new("split_secret", limit), &limit, |b, _| {
+            b.iter(|| scheme.split_secret(&mut OsRng, &secret).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, share_generation);
+criterion_main!(benches);
@@ -0,0 +1,197 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{abe::*, error::SharingError};
+
+const POLICY: &str = "role=doctor AND (dept=oncology OR dept=radiology)";
+
+fn policies() {
+    let parsed = AttributePolicy::parse(POLICY).unwrap();
+    let built = AttributePolicy::all(vec![
+        AttributePolicy::attribute("role=doctor").unwrap(),
+        AttributePolicy::any(vec![
+            AttributePolicy::attribute("dept=oncology").unwrap(),
+            AttributePolicy::attribute("dept=radiology").unwrap(),
+        ])
+        .unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(parsed, built);
+    assert_eq!(
+        AttributePolicy::try_from(&parsed.to_bytes()[..]).unwrap(),
+        parsed
+    );
+
+    // AND binds tighter and chains are flattened
+    let precedence = AttributePolicy::parse("a OR b and c OR d").unwrap();
+    match &precedence {
+        AttributePolicy::Threshold { threshold, members } => {
+            assert_eq!(*threshold, 1);
+            assert_eq!(members.len(), 3);
+        }
+        _ => panic!("expected a threshold"),
+    }
+    assert!(precedence.is_satisfied_by(&["b", "c"]));
+    assert!(!precedence.is_satisfied_by(&["b"]));
+
+    assert!(parsed.is_satisfied_by(&["dept=radiology", "role=doctor"]));
+    assert!(!parsed.is_satisfied_by(&["role=doctor", "role=nurse"]));
+
+    for bad in ["", "a AND", "(a OR b", "a OR b)", "a b", "AND a", "()"] {
+        assert!(matches!(
+            AttributePolicy::parse(bad),
+            Err(SharingError::PolicyInvalid)
+        ));
+    }
+    let deep = format!("{}a{}", "(".repeat(10), ")".repeat(10));
+    assert!(AttributePolicy::parse(&deep).is_ok());
+    let deep = format!(
+        "{}a OR b{}",
+        "(c AND ".repeat(MAX_DEPTH),
+        ")".repeat(MAX_DEPTH)
+    );
+    assert!(matches!(
+        AttributePolicy::parse(&deep),
+        Err(SharingError::PolicyInvalid)
+    ));
+    let a = AttributePolicy::attribute("a").unwrap();
+    assert!(matches!(
+        AttributePolicy::threshold(2, vec![a.clone()]),
+        Err(SharingError::PolicyInvalid)
+    ));
+    assert!(matches!(
+        AttributePolicy::threshold(0, vec![a]),
+        Err(SharingError::PolicyInvalid)
+    ));
+    assert!(matches!(
+        AttributePolicy::attribute("a b"),
+        Err(SharingError::PolicyInvalid)
+    ));
+}
+
+fn round_trip() {
+    let (public_key, master_key) = setup(&mut OsRng);
+    let policy = AttributePolicy::parse(POLICY).unwrap();
+    let ciphertext = encrypt(&mut OsRng, &public_key, &policy, b"patient record").unwrap();
+    assert_eq!(ciphertext.policy(), &policy);
+
+    let oncologist = master_key.issue(&mut OsRng, &["role=doctor", "dept=oncology", "site=a"]);
+    assert_eq!(oncologist.decrypt(&ciphertext).unwrap(), b"patient record");
+    let radiologist = master_key.issue(&mut OsRng, &["dept=radiology", "role=doctor"]);
+    assert_eq!(radiologist.decrypt(&ciphertext).unwrap(), b"patient record");
+
+    for attributes in [
+        &["role=doctor"][..],
+        &["role=nurse", "dept=oncology"][..],
+        &[],
+    ] {
+        let key = master_key.issue(&mut OsRng, attributes);
+        assert!(matches!(
+            key.decrypt(&ciphertext),
+            Err(SharingError::PolicyNotSatisfied)
+        ));
+    }
+
+    // Thresholds of nested thresholds, with more members satisfied than needed
+    let policy = AttributePolicy::threshold(
+        2,
+        vec![
+            AttributePolicy::parse("a AND b").unwrap(),
+            AttributePolicy::attribute("c").unwrap(),
+            AttributePolicy::parse("d OR e").unwrap(),
+        ],
+    )
+    .unwrap();
+    let ciphertext = encrypt(&mut OsRng, &public_key, &policy, b"").unwrap();
+    for attributes in [&["a", "b", "c"][..], &["c", "e"], &["a", "b", "c", "d"]] {
+        let key = master_key.issue(&mut OsRng, attributes);
+        assert!(key.decrypt(&ciphertext).unwrap().is_empty());
+    }
+    let key = master_key.issue(&mut OsRng, &["a", "c"]);
+    assert!(matches!(
+        key.decrypt(&ciphertext),
+        Err(SharingError::PolicyNotSatisfied)
+    ));
+
+    // Another authority's key with the right attributes
+    let (_, other) = setup(&mut OsRng);
+    let key = other.issue(&mut OsRng, &["a", "b", "c"]);
+    assert!(matches!(
+        key.decrypt(&ciphertext),
+        Err(SharingError::AbeDecryptionFailed)
+    ));
+}
+
+fn tampering() {
+    let (public_key, master_key) = setup(&mut OsRng);
+    let policy = AttributePolicy::parse("a OR b").unwrap();
+    let ciphertext = encrypt(&mut OsRng, &public_key, &policy, b"secret").unwrap();
+    let key = master_key.issue(&mut OsRng, &["a"]);
+
+    let bytes = ciphertext.to_bytes();
+    for i in [bytes.len() - 1, bytes.len() - 20] {
+        let mut tampered = bytes.clone();
+        tampered[i] ^= 1;
+        let tampered = Ciphertext::try_from(&tampered[..]).unwrap();
+        assert!(matches!(
+            key.decrypt(&tampered),
+            Err(SharingError::AbeDecryptionFailed)
+        ));
+    }
+    // Widening the policy is caught by the associated data
+    let mut widened = bytes.clone();
+    let position = bytes.iter().position(|b| *b == b'a').unwrap();
+    widened[position] = b'c';
+    let widened = Ciphertext::try_from(&widened[..]).unwrap();
+    let other = master_key.issue(&mut OsRng, &["c"]);
+    assert!(matches!(
+        other.decrypt(&widened),
+        Err(SharingError::AbeDecryptionFailed)
+    ));
+    assert!(Ciphertext::try_from(&bytes[..bytes.len() - 1]).is_err());
+}
+
+fn serialization() {
+    let (public_key, master_key) = setup(&mut OsRng);
+    let public_key = PublicKey::try_from(&public_key.to_bytes()[..]).unwrap();
+    let master_key = MasterKey::try_from(&master_key.to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", master_key), "MasterKey(..)");
+
+    let policy = AttributePolicy::parse(POLICY).unwrap();
+    let ciphertext = encrypt(&mut OsRng, &public_key, &policy, b"record").unwrap();
+    let ciphertext = Ciphertext::try_from(&ciphertext.to_bytes()[..]).unwrap();
+    let key = master_key.issue(&mut OsRng, &["role=doctor", "dept=radiology"]);
+    let key = SecretKey::try_from(&key.to_bytes()[..]).unwrap();
+    assert_eq!(key.attributes(), vec!["dept=radiology", "role=doctor"]);
+    assert_eq!(key.decrypt(&ciphertext).unwrap(), b"record");
+
+    let mut bytes = ciphertext.to_bytes();
+    bytes[0] = 2;
+    assert!(matches!(
+        Ciphertext::try_from(&bytes[..]),
+        Err(SharingError::ShareUnsupportedVersion(2))
+    ));
+}
+
+fn main() {
+    println!("Policies");
+    policies();
+    println!("Round trip");
+    round_trip();
+    println!("Tampering");
+    tampering();
+    println!("Serialization");
+    serialization();
+}
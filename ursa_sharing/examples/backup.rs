@@ -0,0 +1,116 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::convert::TryFrom;
+use ursa_sharing::{
+    backup::{backup_secret, restore_secret, EncodedShare},
+    error::SharingError,
+    tests::backup_invalid_restore,
+};
+
+fn backup_invalid_args() {
+    assert!(backup_secret(b"secret", 3, 2, None).is_err());
+    assert!(backup_secret(b"secret", 1, 3, None).is_err());
+    assert!(backup_secret(b"secret", 2, 256, None).is_err());
+    assert!(backup_secret(b"", 2, 3, None).is_err());
+}
+
+fn backup_restore_plain() {
+    // Larger than any scalar field
+    let secret = (0..4096).map(|i| i as u8).collect::<Vec<u8>>();
+    let shares = backup_secret(&secret, 3, 5, None).unwrap();
+    assert_eq!(shares.len(), 5);
+
+    for i in 0..5 {
+        for j in 0..5 {
+            for k in 0..5 {
+                if i == j || j == k || i == k {
+                    continue;
+                }
+                let parts = [shares[i].clone(), shares[j].clone(), shares[k].clone()];
+                assert_eq!(restore_secret(&parts, None).unwrap(), secret);
+            }
+        }
+    }
+
+    // Not enough shares
+    assert!(restore_secret(&shares[..2], None).is_err());
+    // Duplicate shares
+    let parts = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+    assert!(restore_secret(&parts, None).is_err());
+}
+
+fn backup_restore_armored_with_passphrase() {
+    let secret = b"correct horse battery staple";
+    let passphrase = b"hunter2";
+    let shares = backup_secret(secret, 2, 3, Some(passphrase)).unwrap();
+    assert!(shares.iter().all(|s| s.is_encrypted()));
+
+    let armored = shares.iter().map(|s| s.to_armored()).collect::<Vec<_>>();
+    assert!(armored[0].starts_with("-----BEGIN URSA SECRET SHARE-----"));
+    let parsed = armored
+        .iter()
+        .map(|a| EncodedShare::from_armored(a).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        restore_secret(&parsed[1..], Some(passphrase)).unwrap(),
+        secret.to_vec()
+    );
+    match restore_secret(&parsed[1..], None) {
+        Err(SharingError::SharePassphraseRequired) => {}
+        r => panic!("expected a passphrase error, found {:?}", r),
+    }
+    match restore_secret(&parsed[1..], Some(b"hunter3")) {
+        Err(SharingError::ShareDecryptionFailed) => {}
+        r => panic!("expected a decryption error, found {:?}", r),
+    }
+}
+
+fn restore_detects_corruption() {
+    let secret = b"a secret worth keeping";
+    let shares = backup_secret(secret, 2, 3, None).unwrap();
+
+    // Flipped bit caught by the checksum
+    let mut bytes = shares[0].to_bytes();
+    bytes[14] ^= 1;
+    match EncodedShare::try_from(bytes.as_slice()) {
+        Err(SharingError::ShareChecksumMismatch) => {}
+        r => panic!("expected a checksum error, found {:?}", r),
+    }
+
+    // Truncated
+    let bytes = shares[0].to_bytes();
+    assert!(EncodedShare::try_from(&bytes[..10]).is_err());
+
+    // Shares from different backups
+    let other = backup_secret(secret, 2, 3, None).unwrap();
+    let parts = [shares[0].clone(), other[1].clone()];
+    match restore_secret(&parts, None) {
+        Err(SharingError::ShareMismatchedBackup) => {}
+        r => panic!("expected a mismatched backup error, found {:?}", r),
+    }
+}
+
+fn main() {
+    println!("Backup invalid args");
+    backup_invalid_args();
+    println!("Backup restore success");
+    backup_restore_plain();
+    println!("Backup restore armored with passphrase success");
+    backup_restore_armored_with_passphrase();
+    println!("Restore detects corruption");
+    restore_detects_corruption();
+    println!("Restore rejects invalid armor and passphrases");
+    backup_invalid_restore();
+}
@@ -13,21 +13,27 @@
 // limitations under the License.
 use ff_zeroize::{Field as FFField, PrimeField};
 use generic_array::{
-    typenum::{Unsigned, U32, U48, U96},
+    typenum::{Unsigned, U32, U48, U576, U96},
     GenericArray,
 };
 use pairing_plus::{
-    bls12_381::{Fr, FrRepr, G1, G2},
+    bls12_381::{Bls12, Fq12, Fr, FrRepr, G1, G2},
     hash_to_curve::HashToCurve,
     hash_to_field::{BaseFromRO, ExpandMsgXmd},
     serdes::SerDes,
-    CurveProjective,
+    CurveProjective, Engine,
 };
-use rand::{CryptoRng, RngCore};
-use ursa_sharing::{error::*, tests::*, Field, Group};
+use rand::RngCore;
+use ursa_sharing::{error::*, tests::*, zeroize::Zeroize, Field, Group};
 
 struct FrField(Fr);
 
+impl Zeroize for FrField {
+    fn zeroize(&mut self) {
+        self.0 = Fr::zero();
+    }
+}
+
 impl Field for FrField {
     fn one() -> Self {
         Self(Fr::from_repr(FrRepr::from(1u64)).unwrap())
@@ -37,8 +43,10 @@ impl Field for FrField {
         Self(Fr::from_repr(FrRepr::from(value as u64)).unwrap())
     }
 
-    fn scalar_div_assign(&mut self, rhs: &Self) {
-        self.0.mul_assign(&rhs.0.inverse().unwrap());
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = rhs.0.inverse().ok_or(SharingError::DivisionByZero)?;
+        self.0.mul_assign(&inverse);
+        Ok(())
     }
 }
 
@@ -54,9 +62,9 @@ impl Group for FrField {
         if value.len() < Self::Size::to_usize() {
             let mut s = [0u8; 48];
             s[..value.len()].copy_from_slice(value);
-            return Ok(Self(Fr::from_okm(GenericArray::from_slice(&s))));
+            Ok(Self(Fr::from_okm(GenericArray::from_slice(&s))))
         } else if value.len() == Self::Size::to_usize() {
-            let mut r = std::io::Cursor::new(value.as_ref());
+            let mut r = std::io::Cursor::new(value);
             match Fr::deserialize(&mut r, true) {
                 Ok(f) => Ok(Self(f)),
                 Err(_) => Err(SharingError::ShareInvalidSecret),
@@ -225,17 +233,112 @@ impl Group<FrField> for G2Field {
     }
 }
 
+struct GtField(Fq12);
+
+impl Group<FrField> for GtField {
+    type Size = U576;
+
+    fn zero() -> Self {
+        Self(Fq12::one())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() != Self::Size::to_usize() {
+            return Err(SharingError::InvalidPoint);
+        }
+        let mut c = std::io::Cursor::new(value);
+        match Fq12::deserialize(&mut c, true) {
+            Ok(p) => Ok(Self(p)),
+            Err(_) => Err(SharingError::InvalidPoint),
+        }
+    }
+
+    fn random(rng: &mut impl RngCore) -> Self {
+        Self(Bls12::pairing(G1::random(rng), G2::random(rng)))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == Fq12::one()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.0 != Fq12::one()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.inverse().unwrap();
+    }
+
+    fn add_assign(&mut self, rhs: &GtField) {
+        self.0.mul_assign(&rhs.0);
+    }
+
+    fn sub_assign(&mut self, rhs: &GtField) {
+        self.0.mul_assign(&rhs.0.inverse().unwrap());
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &FrField) {
+        self.0 = self.0.pow(rhs.0.into_repr());
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut r = [0u8; 576];
+        self.0.serialize(&mut r.as_mut(), true).unwrap();
+        GenericArray::clone_from_slice(&r)
+    }
+}
+
 fn main() {
     println!("Splitting");
     split_invalid_args::<FrField>();
     println!("Combine invalid fail");
     combine_invalid::<FrField>();
+    println!("Split many success");
+    split_many::<FrField>();
+    println!("Field division success");
+    field_division::<FrField>();
+    println!("Refresh shares success");
+    refresh_shares::<FrField>();
     println!("Combine single G1 success");
     combine_single::<FrField, G1Field>();
     println!("Combine combinations G1 success");
     combine_all_combinations::<FrField, G1Field>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<FrField, G1Field>();
+    println!("Observer events success");
+    observer_events::<FrField, G1Field>();
     println!("Combine single G2 success");
     combine_single::<FrField, G2Field>();
     println!("Combine combinations G2 success");
     combine_all_combinations::<FrField, G2Field>();
+    println!("Combine single Gt success");
+    combine_single::<FrField, GtField>();
+    println!("Combine in exponent G1 success");
+    combine_in_exponent::<FrField, G1Field>();
+    println!("Combine with context success");
+    combine_with_context::<FrField, G1Field>();
+    println!("Combine in exponent G2 success");
+    combine_in_exponent::<FrField, G2Field>();
+    println!("Combine with context success");
+    combine_with_context::<FrField, G2Field>();
+    println!("Combine in exponent Gt success");
+    combine_in_exponent::<FrField, GtField>();
+    println!("Combine with context success");
+    combine_with_context::<FrField, GtField>();
+    println!("Share verification success");
+    share_verification::<FrField, G1Field>();
+    println!("Cheater detection success");
+    cheater_detection::<FrField, G1Field>();
+    println!("DKG rounds success");
+    dkg_rounds::<FrField, G1Field>();
+    println!("Possession proofs success");
+    possession_proofs::<FrField, G1Field>();
+    println!("Escrow success");
+    escrow_shares::<FrField, G1Field>();
+    dleq_proofs::<FrField, G1Field>();
+    println!("Transcript challenges success");
+    transcript_challenges::<FrField, G1Field>();
+    println!("Strict deserialization success");
+    strict_deserialization::<FrField, G1Field>();
 }
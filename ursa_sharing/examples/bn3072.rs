@@ -11,7 +11,7 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use ursa_sharing::{error::*, tests::*, Field, Group};
+use ursa_sharing::{error::*, tests::*, zeroize::Zeroize, Field, Group};
 
 use generic_array::{typenum::U384, GenericArray};
 use openssl::bn::{BigNum, BigNumContext};
@@ -19,6 +19,12 @@ use rand::{CryptoRng, RngCore};
 
 struct Bn3072(BigNum);
 
+impl Zeroize for Bn3072 {
+    fn zeroize(&mut self) {
+        self.0.clear();
+    }
+}
+
 /// Safe prime generated by OpenSSL
 const MODULUS: &str= "2810648864918553692326414703540982236400161676089967413931080487336351664151951596142800952137837786949602780093696245504389122898007840882257958695803988348815090801641319246142910064654295656659531928562021941244973691275871456332653319544240941768988728044605685210468374028296470195771445893463172468596886296397514478881750748854380716952364198170004560459348379205872353140560333434262518706391341981993534904655587887145767376775247180777074746668503322468682710283049559264203107937893133977707072588631910572571691340287270684887753691017850014026114991899690570003338568598941699411176562161168853436661856010103788770425973503571121826245383549444250163796809725074965822253566072469306063871256250293519396973490875540895189911205333942452223362768239991669324715576323296472026485762324469371989244243246598338329148439857434726497797195444870550503807350157997765978494536147484675781002402359451435754948869927";
 
@@ -31,13 +37,15 @@ impl Field for Bn3072 {
         Self(BigNum::from_dec_str(value.to_string().as_str()).unwrap())
     }
 
-    fn scalar_div_assign(&mut self, rhs: &Self) {
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
         let n = BigNum::from_dec_str(MODULUS).unwrap();
         let r = BigNum::from_slice(self.0.to_vec().as_slice()).unwrap();
         let mut h = BigNum::new().unwrap();
         let mut ctx = BigNumContext::new().unwrap();
-        h.mod_inverse(&rhs.0, &n, &mut ctx).unwrap();
+        h.mod_inverse(&rhs.0, &n, &mut ctx)
+            .map_err(|_| SharingError::DivisionByZero)?;
         self.0.mod_mul(&r, &h, &n, &mut ctx).unwrap();
+        Ok(())
     }
 }
 
@@ -116,8 +124,37 @@ fn main() {
     split_invalid_args::<Bn3072>();
     println!("Combine invalid fail");
     combine_invalid::<Bn3072>();
+    println!("Split many success");
+    split_many::<Bn3072>();
+    println!("Field division success");
+    field_division::<Bn3072>();
+    println!("Refresh shares success");
+    refresh_shares::<Bn3072>();
     println!("Combine single success");
     combine_single::<Bn3072, Bn3072>();
     println!("Combine combinations success");
     combine_all_combinations::<Bn3072, Bn3072>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<Bn3072, Bn3072>();
+    println!("Observer events success");
+    observer_events::<Bn3072, Bn3072>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<Bn3072, Bn3072>();
+    println!("Combine with context success");
+    combine_with_context::<Bn3072, Bn3072>();
+    println!("Share verification success");
+    share_verification::<Bn3072, Bn3072>();
+    println!("Cheater detection success");
+    cheater_detection::<Bn3072, Bn3072>();
+    println!("DKG rounds success");
+    dkg_rounds::<Bn3072, Bn3072>();
+    println!("Possession proofs success");
+    possession_proofs::<Bn3072, Bn3072>();
+    println!("Escrow success");
+    escrow_shares::<Bn3072, Bn3072>();
+    dleq_proofs::<Bn3072, Bn3072>();
+    println!("Transcript challenges success");
+    transcript_challenges::<Bn3072, Bn3072>();
+    println!("Strict deserialization success");
+    strict_deserialization::<Bn3072, Bn3072>();
 }
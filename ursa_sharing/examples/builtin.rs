@@ -0,0 +1,68 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ursa_sharing::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    tests::*,
+    Field, Group,
+};
+
+/// Run every scheme test with the scalars `S` and the points `R`
+fn suite<S: Field, R: Group<S>>(name: &str) {
+    println!("{} Splitting", name);
+    split_invalid_args::<S>();
+    println!("{} Combine invalid fail", name);
+    combine_invalid::<S>();
+    println!("{} Split many success", name);
+    split_many::<S>();
+    println!("{} Field division success", name);
+    field_division::<S>();
+    println!("{} Refresh shares success", name);
+    refresh_shares::<S>();
+    println!("{} Combine single success", name);
+    combine_single::<S, R>();
+    println!("{} Combine combinations success", name);
+    combine_all_combinations::<S, R>();
+    println!("{} Combine with identifiers success", name);
+    combine_with_identifiers::<S, R>();
+    println!("{} Observer events success", name);
+    observer_events::<S, R>();
+    println!("{} Combine in exponent success", name);
+    combine_in_exponent::<S, R>();
+    println!("{} Combine with context success", name);
+    combine_with_context::<S, R>();
+    println!("{} Share verification success", name);
+    share_verification::<S, R>();
+    println!("{} Cheater detection success", name);
+    cheater_detection::<S, R>();
+    println!("{} DKG rounds success", name);
+    dkg_rounds::<S, R>();
+    println!("{} Possession proofs success", name);
+    possession_proofs::<S, R>();
+    println!("{} Escrow success", name);
+    escrow_shares::<S, R>();
+    dleq_proofs::<S, R>();
+    println!("{} Transcript challenges success", name);
+    transcript_challenges::<S, R>();
+    println!("{} Strict deserialization success", name);
+    strict_deserialization::<S, R>();
+}
+
+fn main() {
+    suite::<Ristretto25519Scalar, Ristretto25519Point>("Ristretto255");
+    suite::<Secp256r1Scalar, Secp256r1Point>("P-256");
+    suite::<Bls12381Scalar, Bls12381G1>("BLS12-381 G1");
+    suite::<Bls12381Scalar, Bls12381G2>("BLS12-381 G2");
+}
@@ -0,0 +1,120 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::convert::TryFrom;
+use ursa_sharing::{
+    ceremony::*,
+    error::SharingError,
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    shamir::Scheme,
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+fn inputs(label: &[u8]) -> CeremonyInputs {
+    let mut inputs = CeremonyInputs::new(label, THRESHOLD, LIMIT).unwrap();
+    for id in 1..=LIMIT as u32 {
+        inputs.add_seed(id, &[id as u8; 40]).unwrap();
+    }
+    inputs
+}
+
+fn run(inputs: &CeremonyInputs) -> CeremonyOutput<Secp256k1Scalar, Secp256k1Point> {
+    Ceremony::run(inputs, &Secp256k1Point::generator()).unwrap()
+}
+
+fn reproducible() {
+    let first = inputs(b"ceremony 1");
+    // The second machine gets the encoded inputs
+    let second = CeremonyInputs::try_from(first.to_bytes().as_slice()).unwrap();
+    assert_eq!(second.to_bytes(), first.to_bytes());
+    let a = run(&first);
+    let b = run(&second);
+
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.public_key().to_bytes(), b.public_key().to_bytes());
+    assert_eq!(a.transcript().to_bytes(), b.transcript().to_bytes());
+    for (x, y) in a.shares().iter().zip(b.shares().iter()) {
+        assert_eq!(x.to_bytes(), y.to_bytes());
+    }
+    assert!(a.transcript().blame().is_empty());
+
+    // The shares open the public key
+    let scheme = Scheme::new(THRESHOLD, LIMIT).unwrap();
+    let shares = a.shares().into_iter().cloned().collect::<Vec<_>>();
+    let secret: Secp256k1Scalar = scheme.combine_shares(&shares[1..4]).unwrap();
+    let mut public_key = Secp256k1Point::generator();
+    public_key.scalar_mul_assign(&secret);
+    assert_eq!(public_key.to_bytes(), a.public_key().to_bytes());
+    for (o, s) in a.outputs().iter().zip(shares.iter()) {
+        assert!(s.verify(o.verifier()).is_ok());
+    }
+
+    // Any other label or seed is another key
+    assert_ne!(run(&inputs(b"ceremony 2")).fingerprint(), a.fingerprint());
+    let mut other = CeremonyInputs::new(b"ceremony 1", THRESHOLD, LIMIT).unwrap();
+    for id in 1..=LIMIT as u32 {
+        other.add_seed(id, &[id as u8 + 1; 40]).unwrap();
+    }
+    let other = run(&other);
+    assert_ne!(other.fingerprint(), a.fingerprint());
+    assert_ne!(other.public_key().to_bytes(), a.public_key().to_bytes());
+}
+
+fn invalid() {
+    let mut inputs = CeremonyInputs::new(b"ceremony", THRESHOLD, LIMIT).unwrap();
+    inputs.add_seed(1, &[1u8; 32]).unwrap();
+    assert!(matches!(
+        Ceremony::run::<Secp256k1Scalar, Secp256k1Point>(&inputs, &Secp256k1Point::generator()),
+        Err(SharingError::ShareIdentifierCount(5, 1))
+    ));
+    assert!(matches!(
+        inputs.add_seed(1, &[2u8; 32]),
+        Err(SharingError::ShareDuplicateIdentifier)
+    ));
+    for id in [0, LIMIT as u32 + 1] {
+        assert!(matches!(
+            inputs.add_seed(id, &[2u8; 32]),
+            Err(SharingError::DkgInvalidParticipant)
+        ));
+    }
+    assert!(matches!(
+        inputs.add_seed(2, &[2u8; 31]),
+        Err(SharingError::EntropyTooShort(31))
+    ));
+    assert!(matches!(
+        CeremonyInputs::new(b"ceremony", 1, LIMIT),
+        Err(SharingError::ShareMinThreshold)
+    ));
+
+    let mut bytes = inputs.to_bytes();
+    bytes.push(0);
+    assert!(matches!(
+        CeremonyInputs::try_from(bytes.as_slice()),
+        Err(SharingError::ShareInvalidEncoding)
+    ));
+    bytes[0] = 2;
+    assert!(matches!(
+        CeremonyInputs::try_from(bytes.as_slice()),
+        Err(SharingError::ShareUnsupportedVersion(2))
+    ));
+}
+
+fn main() {
+    println!("Reproducible ceremonies");
+    reproducible();
+    println!("Invalid ceremony inputs");
+    invalid();
+}
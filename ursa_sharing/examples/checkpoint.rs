@@ -0,0 +1,251 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use ursa_sharing::{
+    checkpoint::{resume, serialize_encrypted, Checkpoint, CheckpointKey},
+    dkg::{DkgOutput, Participant},
+    error::{SharingError, SharingResult},
+    frost::{Coordinator, SignerRound1},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{dkg::Participant as Secp256k1Participant, Secp256k1Point, Secp256k1Scalar},
+    shamir::Share,
+    threshold_ecdsa::{
+        paillier::{DecryptionKey, MIN_MODULUS_BITS},
+        SignerRound1 as EcdsaSignerRound1,
+    },
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+type RistrettoParticipant = Participant<Ristretto25519Scalar, Ristretto25519Point>;
+
+/// Seal a state as if the process stopped, and resume it
+fn restart<T: Checkpoint>(key: &CheckpointKey, state: T) -> T {
+    let checkpoint = serialize_encrypted(&mut OsRng, &state, key);
+    drop(state);
+    resume(&checkpoint, key).unwrap()
+}
+
+fn rejected<T>(result: SharingResult<T>) {
+    match result {
+        Err(SharingError::CheckpointInvalid) => {}
+        Err(e) => panic!("expected an invalid checkpoint, found {:?}", e),
+        Ok(_) => panic!("expected an invalid checkpoint"),
+    }
+}
+
+/// A Ristretto DKG where every participant restarts between the rounds
+fn dkg(key: &CheckpointKey) -> Vec<DkgOutput<Ristretto25519Scalar, Ristretto25519Point>> {
+    let g = Ristretto25519Point::generator();
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(&mut OsRng, id, THRESHOLD, LIMIT, &g).unwrap();
+        participants.push(restart(key, p));
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    let mut resumed = Vec::new();
+    for mut p in participants {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        assert!(p.receive_round1(&broadcasts, &mine).unwrap().is_empty());
+        resumed.push(restart(key, p));
+    }
+    resumed
+        .iter_mut()
+        .map(|p| p.finalize(&[], &[]).unwrap())
+        .collect()
+}
+
+/// FROST signing where the signers and the coordinator restart between the rounds
+fn frost(key: &CheckpointKey) {
+    let outputs = dkg(key);
+    let verifier = outputs[0].verifier();
+    let message = b"block 1024";
+
+    let mut signers = Vec::new();
+    let mut commitments = Vec::new();
+    for output in &outputs[1..4] {
+        let (state, c) = SignerRound1::new(&mut OsRng, output.share(), verifier).unwrap();
+        signers.push(restart(key, state));
+        commitments.push(c);
+    }
+    let coordinator = restart(
+        key,
+        Coordinator::new(verifier, message, &commitments).unwrap(),
+    );
+    assert_eq!(coordinator.signers(), vec![2, 3, 4]);
+    let partials = signers
+        .into_iter()
+        .map(|s| s.sign(message, &commitments).unwrap())
+        .collect::<Vec<_>>();
+    let signature = coordinator.aggregate(&partials).unwrap();
+    assert!(signature.verify(&outputs[0].public_key(), message).is_ok());
+}
+
+/// A secp256k1 DKG then threshold ECDSA where every signer restarts after each round
+fn threshold_ecdsa(key: &CheckpointKey, decryption_keys: &[DecryptionKey]) {
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Secp256k1Participant::new(&mut OsRng, id, THRESHOLD, LIMIT).unwrap();
+        participants.push(restart(key, p));
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    let outputs = participants
+        .into_iter()
+        .map(|mut p| {
+            let mine = shares
+                .iter()
+                .filter(|s| s.receiver() == p.id())
+                .cloned()
+                .collect::<Vec<_>>();
+            assert!(p.receive_round1(&broadcasts, &mine).unwrap().is_empty());
+            restart(key, p).finalize(&[], &[]).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let shares = outputs
+        .iter()
+        .map(|o| o.share().clone())
+        .collect::<Vec<Share>>();
+    let public_key: Secp256k1Point = outputs[0].public_key();
+
+    let signers = [1, 3, 5];
+    let mut states = Vec::new();
+    let mut round1 = Vec::new();
+    for (id, decryption_key) in signers.iter().zip(decryption_keys) {
+        let share = shares.iter().find(|s| s.identifier() == *id).unwrap();
+        let (state, b) = EcdsaSignerRound1::<Secp256k1Scalar, _>::new(
+            &mut OsRng,
+            share,
+            &public_key,
+            &signers,
+            decryption_key,
+        )
+        .unwrap();
+        states.push(restart(key, state));
+        round1.push(b);
+    }
+    let mut states2 = Vec::new();
+    let mut round2 = Vec::new();
+    for state in states {
+        let (state, m) = state.receive(&mut OsRng, &round1).unwrap();
+        states2.push(restart(key, state));
+        round2.extend(m);
+    }
+    let mut states3 = Vec::new();
+    let mut round3 = Vec::new();
+    for state in states2 {
+        let mine = round2
+            .iter()
+            .filter(|m| m.receiver() == state.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        let (state, b) = state.receive(&mine).unwrap();
+        states3.push(restart(key, state));
+        round3.push(b);
+    }
+    let mut states4 = Vec::new();
+    let mut round4 = Vec::new();
+    for state in states3 {
+        let (state, b) = state.receive(&round3).unwrap();
+        states4.push(restart(key, state));
+        round4.push(b);
+    }
+    let mut states5 = Vec::new();
+    let mut round5 = Vec::new();
+    for state in states4 {
+        let (state, b) = state.receive(&round4).unwrap();
+        states5.push(restart(key, state));
+        round5.push(b);
+    }
+    let presignatures = states5
+        .into_iter()
+        .map(|s| restart(key, s.receive(&round5).unwrap()))
+        .collect::<Vec<_>>();
+
+    let hash = Sha256::digest(b"transfer 1 btc");
+    let mut states6 = Vec::new();
+    let mut partials = Vec::new();
+    for p in presignatures {
+        let (state, partial) = p.sign(&hash).unwrap();
+        states6.push(restart(key, state));
+        partials.push(partial);
+    }
+    for state in states6 {
+        let signature = state.receive(&partials).unwrap();
+        assert!(signature.verify(&public_key, &hash).is_ok());
+    }
+}
+
+/// Modified checkpoints, other keys and other kinds of state are rejected
+fn tampering(key: &CheckpointKey) {
+    let g = Ristretto25519Point::generator();
+    let (p, _, _) = Participant::new(&mut OsRng, 1, THRESHOLD, LIMIT, &g).unwrap();
+    let checkpoint = serialize_encrypted(&mut OsRng, &p, key);
+    assert!(resume::<RistrettoParticipant>(&checkpoint, key).is_ok());
+    let same = CheckpointKey::new(key.to_bytes());
+    assert_eq!(
+        resume::<RistrettoParticipant>(&checkpoint, &same)
+            .unwrap()
+            .id(),
+        1
+    );
+
+    // A fresh nonce for every checkpoint
+    assert_ne!(checkpoint, serialize_encrypted(&mut OsRng, &p, key));
+
+    for i in &[1, 13, checkpoint.len() - 1] {
+        let mut modified = checkpoint.clone();
+        modified[*i] ^= 1;
+        rejected(resume::<RistrettoParticipant>(&modified, key));
+    }
+    rejected(resume::<RistrettoParticipant>(
+        &checkpoint[..checkpoint.len() - 1],
+        key,
+    ));
+    rejected(resume::<RistrettoParticipant>(&checkpoint[..5], key));
+    rejected(resume::<RistrettoParticipant>(&[], key));
+    rejected(resume::<RistrettoParticipant>(
+        &checkpoint,
+        &CheckpointKey::random(&mut OsRng),
+    ));
+    rejected(resume::<Secp256k1Participant>(&checkpoint, key));
+
+    let mut future = checkpoint.clone();
+    future[0] = 2;
+    match resume::<RistrettoParticipant>(&future, key) {
+        Err(SharingError::CheckpointVersion(2)) => {}
+        r => panic!("expected an unknown version, found {:?}", r),
+    }
+}
+
+fn main() {
+    let key = CheckpointKey::random(&mut OsRng);
+    let decryption_keys = (0..3)
+        .map(|_| DecryptionKey::generate(&mut OsRng, MIN_MODULUS_BITS).unwrap())
+        .collect::<Vec<_>>();
+    tampering(&key);
+    frost(&key);
+    threshold_ecdsa(&key, &decryption_keys);
+}
@@ -0,0 +1,66 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use ursa_sharing::{
+    combine::CombineContext,
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    shamir::Scheme,
+    Group,
+};
+
+const MESSAGES: usize = 64;
+
+/// A validator receiving partial results `g^share` from the same quorum for every message
+fn quorum() {
+    let scheme = Scheme::new(3, 5).unwrap();
+    let quorum = [1u32, 3, 4];
+    let context = CombineContext::<Secp256r1Scalar>::new(scheme.threshold(), &quorum).unwrap();
+
+    let mut expected = Vec::with_capacity(MESSAGES);
+    let mut partials = Vec::with_capacity(MESSAGES);
+    for _ in 0..MESSAGES {
+        let g = Secp256r1Point::random(&mut OsRng);
+        let secret = Secp256r1Scalar::random(&mut OsRng);
+        let shares = scheme.split_secret(&mut OsRng, &secret).unwrap();
+        let mut e = g;
+        e.scalar_mul_assign(&secret);
+        expected.push(e);
+        partials.push(
+            quorum
+                .iter()
+                .map(|i| {
+                    shares[*i as usize - 1]
+                        .lift::<Secp256r1Scalar, Secp256r1Point>(&g)
+                        .and_then(|s| Secp256r1Point::from_bytes(s.value()))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let sequential = context.combine_many(&partials).unwrap();
+    let parallel = context.par_combine_many(&partials).unwrap();
+    for ((s, p), e) in sequential.iter().zip(parallel.iter()).zip(expected.iter()) {
+        assert_eq!(s.to_bytes(), e.to_bytes());
+        assert_eq!(p.to_bytes(), e.to_bytes());
+    }
+
+    // One bad set fails the batch
+    partials[MESSAGES / 2].pop();
+    assert!(context.par_combine_many(&partials).is_err());
+}
+
+fn main() {
+    quorum();
+}
@@ -0,0 +1,68 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ursa_sharing::{
+    ct_audit::*,
+    ed25519::{Ed25519Point, Ed25519Scalar},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+};
+
+type Audit = fn(usize) -> Report;
+
+/// Run in release mode. Set `CT_AUDIT_MEASUREMENTS` to take more measurements.
+fn main() {
+    let measurements = std::env::var("CT_AUDIT_MEASUREMENTS")
+        .ok()
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(DEFAULT_MEASUREMENTS);
+
+    // The harness must see a leak before a pass means anything
+    let control = leaky_comparison(measurements);
+    println!("leaky comparison (control): t = {:.2}", control.t);
+    assert!(control.leaks(), "the audits can't detect a known leak here");
+
+    let audits: Vec<(&str, Audit)> = vec![
+        (
+            "ed25519 scalar mul",
+            scalar_mul::<Ed25519Scalar, Ed25519Point>,
+        ),
+        (
+            "ristretto25519 scalar mul",
+            scalar_mul::<Ristretto25519Scalar, Ristretto25519Point>,
+        ),
+        (
+            "secp256k1 scalar mul",
+            scalar_mul::<Secp256k1Scalar, Secp256k1Point>,
+        ),
+        (
+            "secp256r1 scalar mul",
+            scalar_mul::<Secp256r1Scalar, Secp256r1Point>,
+        ),
+        ("ed25519 combine", combine_shares::<Ed25519Scalar>),
+        ("secp256k1 combine", combine_shares::<Secp256k1Scalar>),
+        ("secp256r1 combine", combine_shares::<Secp256r1Scalar>),
+        ("backup digest", backup_digest),
+        ("checkpoint tag", checkpoint_tag),
+    ];
+    let mut leaks = Vec::new();
+    for (name, audit) in audits {
+        let report = audit(measurements);
+        println!("{}: t = {:.2}", name, report.t);
+        if report.leaks() {
+            leaks.push(name);
+        }
+    }
+    assert!(leaks.is_empty(), "timing differences found in {:?}", leaks);
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use rand::{CryptoRng, RngCore};
-use ursa_sharing::{error::*, tests::*, Field, Group};
+use ursa_sharing::{error::*, tests::*, zeroize::Zeroize, Field, Group};
 
 use curve25519_dalek::{
     constants::ED25519_BASEPOINT_POINT,
@@ -26,6 +26,12 @@ use subtle::ConstantTimeEq;
 
 struct C25519Scalar(Scalar);
 
+impl Zeroize for C25519Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Scalar::zero();
+    }
+}
+
 impl Field for C25519Scalar {
     fn one() -> Self {
         Self(Scalar::one())
@@ -35,8 +41,12 @@ impl Field for C25519Scalar {
         Self(Scalar::from(value as u64))
     }
 
-    fn scalar_div_assign(&mut self, rhs: &Self) {
-        self.0 *= rhs.0.invert()
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        if rhs.is_zero() {
+            return Err(SharingError::DivisionByZero);
+        }
+        self.0 *= rhs.0.invert();
+        Ok(())
     }
 }
 
@@ -157,8 +167,37 @@ fn main() {
     split_invalid_args::<C25519Scalar>();
     println!("Combine invalid fail");
     combine_invalid::<C25519Scalar>();
+    println!("Split many success");
+    split_many::<C25519Scalar>();
+    println!("Field division success");
+    field_division::<C25519Scalar>();
+    println!("Refresh shares success");
+    refresh_shares::<C25519Scalar>();
     println!("Combine single success");
     combine_single::<C25519Scalar, C25519Point>();
     println!("Combine combinations success");
     combine_all_combinations::<C25519Scalar, C25519Point>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<C25519Scalar, C25519Point>();
+    println!("Observer events success");
+    observer_events::<C25519Scalar, C25519Point>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<C25519Scalar, C25519Point>();
+    println!("Combine with context success");
+    combine_with_context::<C25519Scalar, C25519Point>();
+    println!("Share verification success");
+    share_verification::<C25519Scalar, C25519Point>();
+    println!("Cheater detection success");
+    cheater_detection::<C25519Scalar, C25519Point>();
+    println!("DKG rounds success");
+    dkg_rounds::<C25519Scalar, C25519Point>();
+    println!("Possession proofs success");
+    possession_proofs::<C25519Scalar, C25519Point>();
+    println!("Escrow success");
+    escrow_shares::<C25519Scalar, C25519Point>();
+    dleq_proofs::<C25519Scalar, C25519Point>();
+    println!("Transcript challenges success");
+    transcript_challenges::<C25519Scalar, C25519Point>();
+    println!("Strict deserialization success");
+    strict_deserialization::<C25519Scalar, C25519Point>();
 }
@@ -0,0 +1,123 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    custody::{Custodian, Wallet},
+    ed25519::{Ed25519Point, Ed25519Scalar},
+    error::SharingError,
+    frost::FrostGroup,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    shamir::Scheme,
+    Field,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+/// Create a wallet, sign with a few quorums, rotate and recover a lost custodian
+fn lifecycle<S: Field, R: FrostGroup<S>>() {
+    let (wallet, custodians) = Wallet::<S, R>::create(&mut OsRng, THRESHOLD, LIMIT).unwrap();
+    assert_eq!(wallet.threshold(), THRESHOLD);
+    assert_eq!(wallet.limit(), LIMIT);
+    assert_eq!(wallet.epoch(), 0);
+    assert_eq!(custodians.len(), LIMIT);
+    let public_key = wallet.public_key();
+
+    // Any quorum signs
+    for quorum in &[[0, 1, 2], [2, 3, 4], [0, 2, 4]] {
+        let signers = quorum.iter().map(|i| &custodians[*i]).collect::<Vec<_>>();
+        let signature = wallet.sign(&mut OsRng, &signers, b"transfer 10").unwrap();
+        signature.verify(&public_key, b"transfer 10").unwrap();
+        assert!(signature.verify(&public_key, b"transfer 11").is_err());
+    }
+    let all = custodians.iter().collect::<Vec<_>>();
+    wallet.sign(&mut OsRng, &all, b"transfer 10").unwrap();
+    assert!(wallet
+        .sign(&mut OsRng, &all[..THRESHOLD - 1], b"transfer 10")
+        .is_err());
+
+    // The wallet is public and survives serialization
+    let parsed = Wallet::<S, R>::try_from(wallet.to_bytes().as_slice()).unwrap();
+    assert_eq!(parsed.to_bytes(), wallet.to_bytes());
+    let custodian = Custodian::try_from(custodians[0].to_bytes().as_slice()).unwrap();
+    parsed.check(&custodian).unwrap();
+
+    // Back up a custodian before and after rotating
+    let stale = custodians[1]
+        .backup(&mut OsRng, 2, 3, Some(b"guardians"))
+        .unwrap();
+    let (rotated, fresh) = wallet.rotate(&mut OsRng, &custodians).unwrap();
+    assert_eq!(rotated.epoch(), 1);
+    assert_eq!(
+        rotated.public_key().to_bytes(),
+        wallet.public_key().to_bytes()
+    );
+    let pieces = fresh[1]
+        .backup(&mut OsRng, 2, 3, Some(b"guardians"))
+        .unwrap();
+
+    // Custodians of the old epoch are refused and their shares don't mix with the new ones
+    let mixed = [&custodians[0], &fresh[1], &fresh[2]];
+    match rotated.sign(&mut OsRng, &mixed, b"transfer 10") {
+        Err(SharingError::CustodyWrongEpoch(0)) => {}
+        r => panic!("expected a wrong epoch error, found {:?}", r.map(|_| ())),
+    }
+    let scheme = Scheme::new(THRESHOLD, LIMIT).unwrap();
+    let secret = scheme
+        .combine_shares::<S, S>(&[
+            custodians[0].share().clone(),
+            custodians[1].share().clone(),
+            custodians[2].share().clone(),
+        ])
+        .unwrap();
+    let spliced = scheme
+        .combine_shares::<S, S>(&[
+            custodians[0].share().clone(),
+            fresh[1].share().clone(),
+            fresh[2].share().clone(),
+        ])
+        .unwrap();
+    assert_ne!(secret.to_bytes(), spliced.to_bytes());
+
+    // Custodian 2 is lost and recovered from its guardians' pieces
+    let recovered = rotated.recover(&pieces[1..], Some(b"guardians")).unwrap();
+    assert_eq!(recovered.id(), fresh[1].id());
+    let signers = [&fresh[0], &recovered, &fresh[4]];
+    let signature = rotated.sign(&mut OsRng, &signers, b"transfer 20").unwrap();
+    signature.verify(&public_key, b"transfer 20").unwrap();
+
+    // A backup from before the rotation or the wrong passphrase doesn't recover
+    match rotated.recover(&stale[..2], Some(b"guardians")) {
+        Err(SharingError::CustodyWrongEpoch(0)) => {}
+        r => panic!("expected a wrong epoch error, found {:?}", r),
+    }
+    match rotated.recover(&pieces[..2], Some(b"strangers")) {
+        Err(SharingError::ShareDecryptionFailed) => {}
+        r => panic!("expected a decryption error, found {:?}", r),
+    }
+
+    // Rotating needs every custodian of the current epoch
+    assert!(rotated.rotate(&mut OsRng, &fresh[1..]).is_err());
+    assert!(rotated.rotate(&mut OsRng, &custodians).is_err());
+    let (again, _) = rotated.rotate(&mut OsRng, &fresh).unwrap();
+    assert_eq!(again.epoch(), 2);
+}
+
+fn main() {
+    println!("Ed25519 wallet lifecycle");
+    lifecycle::<Ed25519Scalar, Ed25519Point>();
+    println!("Ristretto255 wallet lifecycle");
+    lifecycle::<Ristretto25519Scalar, Ristretto25519Point>();
+}
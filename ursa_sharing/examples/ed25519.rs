@@ -0,0 +1,144 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ed25519_dalek::{
+    ExpandedSecretKey as DalekExpandedSecretKey, Keypair, PublicKey, SecretKey, Signer, Verifier,
+};
+use rand::{rngs::OsRng, thread_rng};
+use std::convert::TryFrom;
+use ursa_sharing::{
+    ed25519::{Ed25519Point, Ed25519Scalar, ExpandedSecretKey, KeyShare, Scheme},
+    error::SharingError,
+    tests::*,
+    Group, Strictness,
+};
+
+fn combined_key_signs_identically() {
+    let mut rng = thread_rng();
+    let keypair = Keypair::generate(&mut OsRng);
+    let scheme = Scheme::new(3, 5).unwrap();
+
+    let shares = scheme
+        .split_secret_key(&mut rng, keypair.secret.as_bytes())
+        .unwrap();
+    assert_eq!(shares.len(), 5);
+
+    let msg = b"ursa sharing ed25519";
+    let expected = keypair.sign(msg);
+
+    for parts in &[&shares[..3], &shares[1..4], &shares[2..]] {
+        // Round trip through the byte form
+        let parts = parts
+            .iter()
+            .map(|s| KeyShare::try_from(s.to_bytes().as_slice()).unwrap())
+            .collect::<Vec<KeyShare>>();
+        let key = scheme.combine_shares(parts.as_slice()).unwrap();
+        assert_eq!(key.public_key(), keypair.public.to_bytes());
+
+        let dalek_key = DalekExpandedSecretKey::from_bytes(&key.to_bytes()).unwrap();
+        let public = PublicKey::from_bytes(&key.public_key()).unwrap();
+        let signature = dalek_key.sign(msg, &public);
+        assert_eq!(signature.to_bytes()[..], expected.to_bytes()[..]);
+        assert!(keypair.public.verify(msg, &signature).is_ok());
+    }
+
+    // Not enough shares
+    assert!(scheme.combine_shares(&shares[..2]).is_err());
+}
+
+fn expanded_key_matches_dalek() {
+    let secret = SecretKey::generate(&mut OsRng);
+    let dalek_key = DalekExpandedSecretKey::from(&secret);
+    let key = ExpandedSecretKey::from_secret_key(secret.as_bytes()).unwrap();
+    assert_eq!(&key.to_bytes()[32..], &dalek_key.to_bytes()[32..]);
+    assert_eq!(
+        key.public_key(),
+        PublicKey::from(&secret).to_bytes(),
+        "public keys differ"
+    );
+}
+
+fn strict_rejects_small_order_points() {
+    // The point of order 2, y = -1
+    let mut order2 = [0xffu8; 32];
+    order2[0] = 0xec;
+    order2[31] = 0x7f;
+    assert!(Ed25519Point::from_bytes_with(order2, Strictness::Lenient).is_ok());
+    assert!(matches!(
+        Ed25519Point::from_bytes_with(order2, Strictness::Strict),
+        Err(SharingError::InvalidSubgroup)
+    ));
+
+    // The identity encoded with y = p + 1
+    let mut identity = [0xffu8; 32];
+    identity[0] = 0xee;
+    identity[31] = 0x7f;
+    assert!(Ed25519Point::from_bytes_with(identity, Strictness::Lenient).is_ok());
+    assert!(matches!(
+        Ed25519Point::from_bytes_with(identity, Strictness::Strict),
+        Err(SharingError::NonCanonicalEncoding)
+    ));
+
+    // Scalars that aren't reduced
+    assert!(Ed25519Scalar::from_bytes_with([0xffu8; 32], Strictness::Lenient).is_ok());
+    assert!(matches!(
+        Ed25519Scalar::from_bytes_with([0xffu8; 32], Strictness::Strict),
+        Err(SharingError::NonCanonicalEncoding)
+    ));
+}
+
+fn main() {
+    println!("Splitting");
+    split_invalid_args::<Ed25519Scalar>();
+    println!("Combine invalid fail");
+    combine_invalid::<Ed25519Scalar>();
+    println!("Split many success");
+    split_many::<Ed25519Scalar>();
+    println!("Field division success");
+    field_division::<Ed25519Scalar>();
+    println!("Refresh shares success");
+    refresh_shares::<Ed25519Scalar>();
+    println!("Combine single success");
+    combine_single::<Ed25519Scalar, Ed25519Point>();
+    println!("Combine combinations success");
+    combine_all_combinations::<Ed25519Scalar, Ed25519Point>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<Ed25519Scalar, Ed25519Point>();
+    println!("Observer events success");
+    observer_events::<Ed25519Scalar, Ed25519Point>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<Ed25519Scalar, Ed25519Point>();
+    println!("Combine with context success");
+    combine_with_context::<Ed25519Scalar, Ed25519Point>();
+    println!("Share verification success");
+    share_verification::<Ed25519Scalar, Ed25519Point>();
+    println!("Cheater detection success");
+    cheater_detection::<Ed25519Scalar, Ed25519Point>();
+    println!("DKG rounds success");
+    dkg_rounds::<Ed25519Scalar, Ed25519Point>();
+    println!("Expanded key matches");
+    expanded_key_matches_dalek();
+    println!("Combined key signs identically");
+    combined_key_signs_identically();
+    println!("Possession proofs success");
+    possession_proofs::<Ed25519Scalar, Ed25519Point>();
+    println!("Escrow success");
+    escrow_shares::<Ed25519Scalar, Ed25519Point>();
+    dleq_proofs::<Ed25519Scalar, Ed25519Point>();
+    println!("Transcript challenges success");
+    transcript_challenges::<Ed25519Scalar, Ed25519Point>();
+    println!("Strict deserialization success");
+    strict_deserialization::<Ed25519Scalar, Ed25519Point>();
+    println!("Strict rejects small order points");
+    strict_rejects_small_order_points();
+}
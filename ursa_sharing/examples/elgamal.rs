@@ -0,0 +1,152 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    dleq::DleqProof,
+    elgamal,
+    error::SharingError,
+    ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar},
+    transcript::Transcript,
+    Group, Strictness,
+};
+
+type Ciphertext = elgamal::Ciphertext<Scalar, Point>;
+
+const PROOF_LABEL: &[u8] = b"elgamal rerandomization";
+
+fn keypair() -> (Scalar, Point) {
+    let x = Scalar::random(&mut OsRng);
+    let mut y = Point::generator();
+    y.scalar_mul_assign(&x);
+    (x, y)
+}
+
+fn sub(a: &Point, b: &Point) -> Point {
+    let mut d = *a;
+    d.sub_assign(b);
+    d
+}
+
+fn rerandomization() {
+    let g = Point::generator();
+    let (x, y) = keypair();
+    let message = Point::random(&mut OsRng);
+    let c = Ciphertext::encrypt(&mut OsRng, &g, &y, &message).unwrap();
+    assert_eq!(c.decrypt(&x).to_bytes(), message.to_bytes());
+
+    // Rerandomized ciphertexts decrypt to the same message but share no component
+    let c_1 = c.rerandomize(&mut OsRng, &g, &y).unwrap();
+    let c_2 = c_1.rerandomize(&mut OsRng, &g, &y).unwrap();
+    for (a, b) in [(&c, &c_1), (&c, &c_2), (&c_1, &c_2)] {
+        assert_ne!(a.c1().to_bytes(), b.c1().to_bytes());
+        assert_ne!(a.c2().to_bytes(), b.c2().to_bytes());
+    }
+    assert_eq!(c_2.decrypt(&x).to_bytes(), message.to_bytes());
+
+    // A rerandomized ciphertext is a fresh encryption with the randomness r + t
+    let r = Scalar::random(&mut OsRng);
+    let t = Scalar::random(&mut OsRng);
+    let mut r_t = r;
+    r_t.add_assign(&t);
+    let rerandomized = Ciphertext::encrypt_with(&g, &y, &message, &r)
+        .unwrap()
+        .rerandomize_with(&g, &y, &t)
+        .unwrap();
+    let fresh = Ciphertext::encrypt_with(&g, &y, &message, &r_t).unwrap();
+    assert_eq!(rerandomized.to_bytes(), fresh.to_bytes());
+
+    // Another key decrypts to something else and invalid keys are rejected
+    let (other, _) = keypair();
+    assert_ne!(c_1.decrypt(&other).to_bytes(), message.to_bytes());
+    assert!(matches!(
+        Ciphertext::encrypt(&mut OsRng, &g, &Point::zero(), &message),
+        Err(SharingError::ShareInvalidRecipientKey)
+    ));
+    assert!(c.rerandomize(&mut OsRng, &g, &Point::zero()).is_err());
+}
+
+fn proven_rerandomization() {
+    // A mix server proves that c_1 rerandomizes c without revealing t:
+    // c_1 / c = (g^t, y^t)
+    let g = Point::generator();
+    let (x, y) = keypair();
+    let message = Point::random(&mut OsRng);
+    let c = Ciphertext::encrypt(&mut OsRng, &g, &y, &message).unwrap();
+    let t = Scalar::random(&mut OsRng);
+    let c_1 = c.rerandomize_with(&g, &y, &t).unwrap();
+    let proof = DleqProof::prove(
+        &mut OsRng,
+        &mut Transcript::new(PROOF_LABEL),
+        &t,
+        (&g, &sub(c_1.c1(), c.c1())),
+        (&y, &sub(c_1.c2(), c.c2())),
+    )
+    .unwrap();
+    proof
+        .verify(
+            &mut Transcript::new(PROOF_LABEL),
+            (&g, &sub(c_1.c1(), c.c1())),
+            (&y, &sub(c_1.c2(), c.c2())),
+        )
+        .unwrap();
+    assert_eq!(c_1.decrypt(&x).to_bytes(), message.to_bytes());
+
+    // The proof doesn't hold for a ciphertext of another message
+    let swapped = Ciphertext::encrypt_with(
+        &g,
+        &y,
+        &Point::random(&mut OsRng),
+        &Scalar::random(&mut OsRng),
+    )
+    .unwrap();
+    assert!(proof
+        .verify(
+            &mut Transcript::new(PROOF_LABEL),
+            (&g, &sub(swapped.c1(), c.c1())),
+            (&y, &sub(swapped.c2(), c.c2())),
+        )
+        .is_err());
+}
+
+fn serialization() {
+    let g = Point::generator();
+    let (x, y) = keypair();
+    let message = Point::random(&mut OsRng);
+    let c = Ciphertext::encrypt(&mut OsRng, &g, &y, &message).unwrap();
+    let bytes = c.to_bytes();
+    assert_eq!(bytes.len(), 64);
+    let decoded = Ciphertext::try_from(&bytes[..]).unwrap();
+    assert_eq!(decoded.decrypt(&x).to_bytes(), message.to_bytes());
+    let strict = Ciphertext::from_bytes_with(&bytes, Strictness::Strict).unwrap();
+    assert_eq!(strict.clone().to_bytes(), bytes);
+
+    assert!(matches!(
+        Ciphertext::try_from(&bytes[1..]),
+        Err(SharingError::ShareInvalidEncoding)
+    ));
+    assert!(Ciphertext::try_from(&[bytes.clone(), vec![0]].concat()[..]).is_err());
+    let mut bad = bytes.clone();
+    bad[32..].copy_from_slice(&[0xffu8; 32]);
+    assert!(Ciphertext::try_from(&bad[..]).is_err());
+}
+
+fn main() {
+    println!("Rerandomization");
+    rerandomization();
+    println!("Proven rerandomization");
+    proven_rerandomization();
+    println!("Serialization");
+    serialization();
+}
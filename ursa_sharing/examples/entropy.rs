@@ -0,0 +1,145 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::RngCore;
+use ursa_sharing::{
+    entropy::*,
+    error::SharingError,
+    secp256k1::Secp256k1Scalar,
+    shamir::{Scheme, Share},
+    Group,
+};
+
+const ROLLS: &str = "1352 6413 2256 3411 6625 4123 5561 2346 1162 3544 \
+                     2631 5254 4416 3625 1213 5342 6611 2453 3164 5225 \
+                     4136 2514 6342 1153 3426";
+
+fn read(source: &dyn EntropySource, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    source.fill(&mut bytes).unwrap();
+    bytes
+}
+
+fn seeds() {
+    let seed = [7u8; MIN_SEED_BYTES];
+    let a = SeedEntropy::from_seed(&seed).unwrap();
+    let b = SeedEntropy::from_seed(&seed).unwrap();
+    // The same stream whichever way it is read
+    let whole = read(&a, 100);
+    let mut parts = read(&b, 33);
+    parts.extend(read(&b, 67));
+    assert_eq!(whole, parts);
+    assert_ne!(
+        read(&a, 32),
+        read(&SeedEntropy::from_seed(&[8u8; 32]).unwrap(), 32)
+    );
+    assert!(matches!(
+        SeedEntropy::from_seed(&seed[1..]),
+        Err(SharingError::EntropyTooShort(31))
+    ));
+
+    let dice = SeedEntropy::from_dice(ROLLS).unwrap();
+    let compact = ROLLS.split_whitespace().collect::<Vec<_>>().join(",");
+    assert_eq!(
+        read(&dice, 64),
+        read(&SeedEntropy::from_dice(&compact).unwrap(), 64)
+    );
+    // Dice rolls and a seed of the same digits are separate
+    let digits = ROLLS.split_whitespace().collect::<String>();
+    assert_ne!(
+        read(&SeedEntropy::from_dice(&digits).unwrap(), 32),
+        read(&SeedEntropy::from_seed(digits.as_bytes()).unwrap(), 32)
+    );
+    assert!(matches!(
+        SeedEntropy::from_dice(&digits[1..]),
+        Err(SharingError::EntropyTooShort(99))
+    ));
+    assert!(matches!(
+        SeedEntropy::from_dice(&format!("{}7", digits)),
+        Err(SharingError::EntropyInvalidDiceRoll)
+    ));
+
+    let path = std::env::temp_dir().join(format!("ursa_sharing_seed_{}", std::process::id()));
+    std::fs::write(&path, seed).unwrap();
+    let file = SeedEntropy::from_file(&path).unwrap();
+    assert_eq!(
+        read(&file, 48),
+        read(&SeedEntropy::from_seed(&seed).unwrap(), 48)
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        SeedEntropy::from_file(&path),
+        Err(SharingError::EntropySeedFile)
+    ));
+}
+
+fn mixing() {
+    let seeded = || -> Vec<Box<dyn EntropySource>> {
+        vec![
+            Box::new(SeedEntropy::from_seed(&[1u8; 32]).unwrap()),
+            Box::new(SeedEntropy::from_dice(ROLLS).unwrap()),
+        ]
+    };
+    let a = MixedEntropy::new(seeded());
+    let b = MixedEntropy::new(seeded());
+    assert_eq!(read(&a, 50), read(&b, 50));
+    assert_ne!(
+        read(&a, 32),
+        read(&SeedEntropy::from_dice(ROLLS).unwrap(), 32)
+    );
+
+    // The operating system makes the output unpredictable even with a known seed
+    let mut sources = seeded();
+    sources.push(Box::new(OsEntropy));
+    let with_os = MixedEntropy::new(sources);
+    assert_ne!(read(&with_os, 32), read(&MixedEntropy::new(seeded()), 32));
+
+    let mut bytes = [0u8; 8];
+    assert!(MixedEntropy::new(Vec::new()).fill(&mut bytes).is_err());
+}
+
+fn keygen() {
+    let scheme = Scheme::new(2, 3).unwrap();
+    let split = || {
+        let dice = SeedEntropy::from_dice(ROLLS).unwrap();
+        let mut rng = EntropyRng::new(&dice);
+        let secret = Secp256k1Scalar::random(&mut rng);
+        let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+        (secret, shares)
+    };
+    let (secret, shares) = split();
+    let (again, repeated) = split();
+    assert_eq!(secret.to_bytes(), again.to_bytes());
+    assert_eq!(
+        shares.iter().map(Share::to_bytes).collect::<Vec<_>>(),
+        repeated.iter().map(Share::to_bytes).collect::<Vec<_>>()
+    );
+    let combined: Secp256k1Scalar = scheme.combine_shares(&shares[1..]).unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+
+    // The one-shot APIs use the registered source
+    set_entropy_source(Box::new(SeedEntropy::from_dice(ROLLS).unwrap()));
+    let registered = SystemRng.next_u64();
+    set_entropy_source(Box::new(SeedEntropy::from_dice(ROLLS).unwrap()));
+    assert_eq!(SystemRng.next_u64(), registered);
+    clear_entropy_source();
+}
+
+fn main() {
+    println!("Seed and dice entropy");
+    seeds();
+    println!("Mixed entropy");
+    mixing();
+    println!("Key generation from chosen entropy");
+    keygen();
+}
@@ -0,0 +1,153 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ffi_support::{ByteBuffer, ExternError, FfiStr};
+use std::ffi::CStr;
+use ursa_sharing::ffi::*;
+
+const THRESHOLD: u32 = 2;
+const LIMIT: u32 = 3;
+
+/// Check the call succeeded as a C caller would
+fn ok(err: &ExternError) {
+    assert!(
+        err.get_code().is_success(),
+        "unexpected error {:?}",
+        err.get_message().as_opt_str()
+    );
+}
+
+/// Check the call failed with `code` and free the message
+fn failed(err: ExternError, code: i32) {
+    assert_eq!(err.get_code().code(), code);
+    unsafe { ursa_sharing_string_free(err.get_raw_message() as *mut _) };
+}
+
+fn take(buffer: ByteBuffer) -> Vec<u8> {
+    let bytes = buffer.as_slice().to_vec();
+    ursa_sharing_bytebuffer_free(buffer);
+    bytes
+}
+
+/// Copy the shares out as they would be stored by the caller
+fn export(shares: u64) -> Vec<Vec<u8>> {
+    let mut err = ExternError::success();
+    let len = ursa_sharing_shares_len(shares, &mut err);
+    ok(&err);
+    let exported = (0..len)
+        .map(|i| {
+            let share = take(ursa_sharing_shares_get(shares, i, &mut err));
+            ok(&err);
+            share
+        })
+        .collect();
+    ursa_sharing_shares_get(shares, len, &mut err);
+    failed(err, error_codes::INDEX_OUT_OF_RANGE);
+    exported
+}
+
+/// Load stored shares into a new set
+fn import(shares: &[&Vec<u8>]) -> u64 {
+    let mut err = ExternError::success();
+    let set = ursa_sharing_shares_new(&mut err);
+    ok(&err);
+    for s in shares {
+        ursa_sharing_shares_add(set, &ByteArray::from(s.as_slice()), &mut err);
+        ok(&err);
+    }
+    set
+}
+
+fn free(shares: u64) {
+    let mut err = ExternError::success();
+    ursa_sharing_shares_free(shares, &mut err);
+    ok(&err);
+}
+
+fn scalars(curve: &CStr, secret: &[u8]) {
+    let mut err = ExternError::success();
+    let handle = ursa_sharing_split(
+        FfiStr::from_cstr(curve),
+        &ByteArray::from(secret),
+        THRESHOLD,
+        LIMIT,
+        &mut err,
+    );
+    ok(&err);
+    let shares = export(handle);
+    free(handle);
+    for (i, s) in shares.iter().enumerate() {
+        let id = ursa_sharing_share_identifier(&ByteArray::from(s.as_slice()), &mut err);
+        ok(&err);
+        assert_eq!(id, i as u32 + 1);
+    }
+
+    let set = import(&[&shares[2], &shares[0]]);
+    let recovered = take(ursa_sharing_combine(
+        FfiStr::from_cstr(curve),
+        THRESHOLD,
+        set,
+        &mut err,
+    ));
+    ok(&err);
+    assert_eq!(recovered, secret);
+    free(set);
+
+    let set = import(&[&shares[1]]);
+    ursa_sharing_combine(FfiStr::from_cstr(curve), THRESHOLD, set, &mut err);
+    failed(err, error_codes::SHARING_ERROR);
+    free(set);
+}
+
+fn bytes() {
+    let seed = b"abandon ability able about above absent";
+    let mut err = ExternError::success();
+    let handle = ursa_sharing_split_bytes(&ByteArray::from(&seed[..]), 3, 5, &mut err);
+    ok(&err);
+    let shares = export(handle);
+    free(handle);
+    let set = import(&[&shares[4], &shares[1], &shares[3]]);
+    let recovered = take(ursa_sharing_combine_bytes(3, set, &mut err));
+    ok(&err);
+    assert_eq!(recovered, seed.to_vec());
+    free(set);
+}
+
+fn errors() {
+    let mut err = ExternError::success();
+    let curve = FfiStr::from_cstr(CStr::from_bytes_with_nul(b"curve448\0").unwrap());
+    let secret = [1u8; 32];
+    ursa_sharing_split(curve, &ByteArray::from(&secret[..]), 2, 3, &mut err);
+    failed(err, error_codes::UNSUPPORTED_CURVE);
+
+    let mut err = ExternError::success();
+    let curve = FfiStr::from_cstr(CStr::from_bytes_with_nul(b"secp256k1\0").unwrap());
+    ursa_sharing_split(curve, &ByteArray::from(&secret[..]), 3, 2, &mut err);
+    failed(err, error_codes::SHARING_ERROR);
+
+    // A freed handle is rejected rather than read
+    let set = import(&[]);
+    free(set);
+    let mut err = ExternError::success();
+    ursa_sharing_shares_len(set, &mut err);
+    assert!(!err.get_code().is_success());
+    unsafe { ursa_sharing_string_free(err.get_raw_message() as *mut _) };
+}
+
+fn main() {
+    let secret = [7u8; 32];
+    scalars(CStr::from_bytes_with_nul(b"ed25519\0").unwrap(), &secret);
+    scalars(CStr::from_bytes_with_nul(b"secp256k1\0").unwrap(), &secret);
+    bytes();
+    errors();
+}
@@ -0,0 +1,317 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ff_zeroize::{Field as FFField, PrimeField};
+use pairing_plus::{
+    bls12_381::{Fr, FrRepr},
+    serdes::SerDes,
+};
+use rand::{rngs::OsRng, RngCore};
+use ursa_sharing::{
+    error::SharingError,
+    field::{
+        batch_invert, lagrange_coefficients, Bls12381Fr, Bls12381FrParams, Bn254Fr, Bn254FrParams,
+        Domain, FieldParams, Fp,
+    },
+    tests::*,
+    Field, Group,
+};
+
+fn to_fr(a: &Bls12381Fr) -> Fr {
+    Fr::deserialize(&mut &a.to_bytes()[..], true).unwrap()
+}
+
+fn from_fr(a: &Fr) -> Bls12381Fr {
+    let mut bytes = Vec::new();
+    a.serialize(&mut bytes, true).unwrap();
+    Bls12381Fr::from_bytes(&bytes).unwrap()
+}
+
+/// The arithmetic matches the pairing-plus implementation
+fn matches_pairing_plus() {
+    for _ in 0..100 {
+        let a = Bls12381Fr::random(&mut OsRng);
+        let b = Bls12381Fr::random(&mut OsRng);
+        let (fa, fb) = (to_fr(&a), to_fr(&b));
+        assert_eq!(from_fr(&fa), a);
+
+        let mut r = fa;
+        r.add_assign(&fb);
+        assert_eq!(from_fr(&r), a + b);
+        let mut r = fa;
+        r.sub_assign(&fb);
+        assert_eq!(from_fr(&r), a - b);
+        let mut r = fa;
+        r.mul_assign(&fb);
+        assert_eq!(from_fr(&r), a * b);
+        let mut r = fa;
+        r.negate();
+        assert_eq!(from_fr(&r), -a);
+        assert_eq!(from_fr(&fa.inverse().unwrap()), a.inverse().unwrap());
+    }
+    assert_eq!(
+        from_fr(&Fr::from_repr(FrRepr::from(12345u64)).unwrap()),
+        Bls12381Fr::from_u64(12345)
+    );
+    assert_eq!(
+        from_fr(&Fr::root_of_unity()),
+        Bls12381Fr::root_of_unity(32).unwrap()
+    );
+    assert_eq!(
+        from_fr(&Fr::multiplicative_generator()),
+        Bls12381Fr::multiplicative_generator()
+    );
+}
+
+fn arithmetic<P: FieldParams>() {
+    let zero = Fp::<P>::zero();
+    let one = Fp::<P>::one();
+    let minus_one = -one;
+    assert_eq!(minus_one + one, zero);
+    assert_eq!(-zero, zero);
+    assert_eq!(zero.inverse(), None);
+    assert_eq!(minus_one.square(), one);
+    // 2^-1 = (p + 1) / 2
+    let two = Fp::<P>::from_u64(2);
+    assert_eq!(two.inverse().unwrap() * two, one);
+    assert_eq!(two.double(), Fp::from_u64(4));
+
+    for _ in 0..100 {
+        let a = Fp::<P>::random(&mut OsRng);
+        let b = Fp::<P>::random(&mut OsRng);
+        let c = Fp::<P>::random(&mut OsRng);
+        assert_eq!(a * (b + c), a * b + a * c);
+        assert_eq!((a - b) + b, a);
+        assert_eq!(a * a.inverse().unwrap(), one);
+        assert_eq!(Fp::<P>::from_bytes(a.to_bytes()).unwrap(), a);
+        assert_eq!(Fp::<P>::from_canonical(a.to_canonical()).unwrap(), a);
+        assert_eq!(a.pow(&[3]), a * a * a);
+    }
+
+    // 2^512 - 1 is reduced
+    let mut bytes = [0u8; 64];
+    bytes[31] = 1;
+    let two_256 = Fp::<P>::from_bytes_wide(&bytes);
+    assert_eq!(
+        Fp::<P>::from_bytes_wide(&[0xff; 64]),
+        two_256 * two_256 - one
+    );
+    // Shorter values are left padded
+    let mut bytes = [0u8; 64];
+    OsRng.fill_bytes(&mut bytes[33..]);
+    assert_eq!(
+        Fp::<P>::from_bytes(&bytes[33..]).unwrap(),
+        Fp::<P>::from_bytes_wide(&bytes)
+    );
+
+    // The modulus doesn't decode
+    let mut modulus = [0u8; 32];
+    for (c, l) in modulus.chunks_mut(8).zip(P::MODULUS.iter().rev()) {
+        c.copy_from_slice(&l.to_be_bytes());
+    }
+    assert!(matches!(
+        Fp::<P>::from_bytes(modulus),
+        Err(SharingError::ShareInvalidSecret)
+    ));
+    assert!(Fp::<P>::from_canonical(P::MODULUS).is_none());
+    assert!(Fp::<P>::from_bytes([0u8; 33]).is_err());
+
+    // The root of unity has exactly the order 2^TWO_ADICITY
+    let root = Fp::<P>::root_of_unity(P::TWO_ADICITY).unwrap();
+    let mut r = root;
+    for _ in 1..P::TWO_ADICITY {
+        r = r.square();
+    }
+    assert_eq!(r, minus_one);
+    assert_eq!(r.square(), one);
+    assert!(Fp::<P>::root_of_unity(P::TWO_ADICITY + 1).is_none());
+    assert_eq!(Fp::<P>::root_of_unity(0).unwrap(), one);
+}
+
+fn batch_inversion<P: FieldParams>() {
+    let mut elements = (0..50)
+        .map(|_| Fp::<P>::random(&mut OsRng))
+        .collect::<Vec<_>>();
+    let expected = elements
+        .iter()
+        .map(|e| e.inverse().unwrap())
+        .collect::<Vec<_>>();
+    batch_invert(&mut elements).unwrap();
+    assert_eq!(elements, expected);
+    batch_invert::<Fp<P>>(&mut []).unwrap();
+
+    let mut with_zero = expected.clone();
+    with_zero[17] = Fp::zero();
+    let unchanged = with_zero.clone();
+    assert!(matches!(
+        batch_invert(&mut with_zero),
+        Err(SharingError::DivisionByZero)
+    ));
+    assert_eq!(with_zero, unchanged);
+}
+
+fn lagrange<P: FieldParams>() {
+    let coefficients = (0..10)
+        .map(|_| Fp::<P>::random(&mut OsRng))
+        .collect::<Vec<_>>();
+    let mut points = (0..10)
+        .map(|_| Fp::<P>::random(&mut OsRng))
+        .collect::<Vec<_>>();
+    let weights = lagrange_coefficients(&points).unwrap();
+    let naive = points
+        .iter()
+        .enumerate()
+        .map(|(i, x_i)| {
+            points
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(Fp::one(), |acc, (_, x_j)| {
+                    acc * *x_j * (*x_j - *x_i).inverse().unwrap()
+                })
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(weights, naive);
+    let at_zero = points
+        .iter()
+        .zip(weights.iter())
+        .fold(Fp::zero(), |acc, (x, w)| {
+            acc + evaluate(&coefficients, x) * *w
+        });
+    assert_eq!(at_zero, coefficients[0]);
+
+    // Zero itself is weighed alone
+    points[3] = Fp::zero();
+    let weights = lagrange_coefficients(&points).unwrap();
+    for (i, w) in weights.iter().enumerate() {
+        assert_eq!(*w, if i == 3 { Fp::one() } else { Fp::zero() });
+    }
+
+    assert!(lagrange_coefficients::<Fp<P>>(&[]).unwrap().is_empty());
+    points[7] = points[1];
+    assert!(matches!(
+        lagrange_coefficients(&points),
+        Err(SharingError::DivisionByZero)
+    ));
+}
+
+fn evaluate<P: FieldParams>(coefficients: &[Fp<P>], x: &Fp<P>) -> Fp<P> {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fp::zero(), |acc, c| acc * *x + *c)
+}
+
+fn domains<P: FieldParams>() {
+    for log_n in 0..7 {
+        let n = 1 << log_n;
+        let domain = Domain::<P>::new(n).unwrap();
+        assert_eq!(domain.size(), n);
+        let elements = domain.elements();
+        for e in &elements {
+            assert_eq!(domain.evaluate_vanishing(e), Fp::zero());
+        }
+
+        let coefficients = (0..n)
+            .map(|_| Fp::<P>::random(&mut OsRng))
+            .collect::<Vec<_>>();
+        let mut values = coefficients.clone();
+        domain.fft(&mut values).unwrap();
+        for (v, e) in values.iter().zip(elements.iter()) {
+            assert_eq!(*v, evaluate(&coefficients, e));
+        }
+        domain.ifft(&mut values).unwrap();
+        assert_eq!(values, coefficients);
+
+        let g = Fp::<P>::multiplicative_generator();
+        domain.coset_fft(&mut values).unwrap();
+        for (v, e) in values.iter().zip(elements.iter()) {
+            assert_eq!(*v, evaluate(&coefficients, &(g * *e)));
+        }
+        domain.coset_ifft(&mut values).unwrap();
+        assert_eq!(values, coefficients);
+
+        assert!(matches!(
+            domain.fft(&mut vec![Fp::zero(); n + 1]),
+            Err(SharingError::FieldDomainSize(l)) if l == n + 1
+        ));
+    }
+
+    // Multiply two polynomials with the FFT
+    let a = (0..5)
+        .map(|_| Fp::<P>::random(&mut OsRng))
+        .collect::<Vec<_>>();
+    let b = (0..4)
+        .map(|_| Fp::<P>::random(&mut OsRng))
+        .collect::<Vec<_>>();
+    let mut expected = vec![Fp::<P>::zero(); 8];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            expected[i + j] += *x * *y;
+        }
+    }
+    let domain = Domain::<P>::new(8).unwrap();
+    let mut fa = a.clone();
+    fa.resize(8, Fp::zero());
+    let mut fb = b.clone();
+    fb.resize(8, Fp::zero());
+    domain.fft(&mut fa).unwrap();
+    domain.fft(&mut fb).unwrap();
+    let mut product = fa
+        .iter()
+        .zip(fb.iter())
+        .map(|(x, y)| *x * *y)
+        .collect::<Vec<_>>();
+    domain.ifft(&mut product).unwrap();
+    assert_eq!(product, expected);
+
+    for size in [0, 3, 12] {
+        assert!(matches!(
+            Domain::<P>::new(size),
+            Err(SharingError::FieldDomainSize(s)) if s == size
+        ));
+    }
+    let too_large = 1usize << (P::TWO_ADICITY + 1);
+    assert!(matches!(
+        Domain::<P>::new(too_large),
+        Err(SharingError::FieldDomainSize(s)) if s == too_large
+    ));
+}
+
+fn sharing<S: Field + Group>() {
+    split_invalid_args::<S>();
+    combine_invalid::<S>();
+    split_many::<S>();
+    field_division::<S>();
+    refresh_shares::<S>();
+}
+
+fn main() {
+    println!("BLS12-381 scalars match pairing-plus");
+    matches_pairing_plus();
+    println!("Field arithmetic");
+    arithmetic::<Bls12381FrParams>();
+    arithmetic::<Bn254FrParams>();
+    println!("Batch inversion");
+    batch_inversion::<Bls12381FrParams>();
+    batch_inversion::<Bn254FrParams>();
+    println!("Lagrange coefficients");
+    lagrange::<Bls12381FrParams>();
+    lagrange::<Bn254FrParams>();
+    println!("Evaluation domains");
+    domains::<Bls12381FrParams>();
+    domains::<Bn254FrParams>();
+    println!("Shamir over the fields");
+    sharing::<Bls12381Fr>();
+    sharing::<Bn254Fr>();
+}
@@ -0,0 +1,79 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    fips::{self, Algorithm},
+    secp256r1::Secp256r1Scalar,
+    shamir::Scheme,
+    tagged::{combine_secret, CurveTag, TaggedShare},
+    Group,
+};
+
+fn allow_list() {
+    for a in &Algorithm::APPROVED {
+        assert!(fips::check(*a).is_ok());
+    }
+    for a in &[
+        Algorithm::Secp256k1,
+        Algorithm::Ristretto25519,
+        Algorithm::ChaCha20Poly1305,
+        Algorithm::Custom,
+    ] {
+        assert!(!a.is_approved());
+        assert!(matches!(
+            fips::check(*a),
+            Err(SharingError::AlgorithmNotApproved(r)) if r == *a
+        ));
+    }
+    assert_eq!(
+        fips::check(Algorithm::Secp256k1).unwrap_err().to_string(),
+        "secp256k1 is not approved in FIPS mode"
+    );
+}
+
+fn tagged_shares() {
+    let secret = Secp256r1Scalar::random(&mut OsRng);
+    let shares = Scheme::new(2, 3)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+    let tagged = TaggedShare::tag_all::<Secp256r1Scalar>(2, &shares).unwrap();
+    let parsed = tagged
+        .iter()
+        .map(|t| TaggedShare::try_from(t.to_bytes().as_slice()).unwrap())
+        .collect::<Vec<_>>();
+    let recovered: Secp256r1Scalar = combine_secret(&parsed[1..]).unwrap();
+    assert_eq!(recovered.to_bytes(), secret.to_bytes());
+
+    // Shares of curves that aren't approved are refused when read back
+    for curve in &[
+        CurveTag::Secp256k1,
+        CurveTag::Ristretto25519,
+        CurveTag::Custom(0x80),
+    ] {
+        let mut bytes = tagged[0].to_bytes();
+        bytes[1] = curve.to_byte();
+        assert!(matches!(
+            TaggedShare::try_from(bytes.as_slice()),
+            Err(SharingError::AlgorithmNotApproved(a)) if a == curve.algorithm()
+        ));
+    }
+}
+
+fn main() {
+    allow_list();
+    tagged_shares();
+}
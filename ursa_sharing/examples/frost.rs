@@ -0,0 +1,244 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ed25519_dalek::{PublicKey as DalekPublicKey, Signature as DalekSignature, Verifier};
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    dkg::{DkgOutput, Participant, Round1Share},
+    ed25519::{Ed25519Point, Ed25519Scalar, ExpandedSecretKey},
+    error::{SharingError, SharingResult},
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    frost::{Coordinator, FrostGroup, Signature, SignatureShare, SignerRound1, SigningCommitments},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    shamir::Share,
+    Field, Group, Strictness,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+type Round1<S, R> = (Vec<SignerRound1<S, R>>, Vec<SigningCommitments<S, R>>);
+
+/// Run the first round with the shares of `signers`,
+/// passing the commitments through their byte encoding
+fn commit<S: Field, R: FrostGroup<S>>(
+    shares: &[Share],
+    verifier: &FeldmanVerifier<S, R>,
+    signers: &[u32],
+) -> SharingResult<Round1<S, R>> {
+    let mut states = Vec::new();
+    let mut commitments = Vec::new();
+    for id in signers {
+        let share = shares.iter().find(|s| s.identifier() == *id).unwrap();
+        let (state, c) = SignerRound1::new(&mut OsRng, share, verifier)?;
+        states.push(state);
+        commitments.push(SigningCommitments::try_from(c.to_bytes().as_slice())?);
+    }
+    Ok((states, commitments))
+}
+
+/// Run the second round and return the shares
+fn sign_shares<S: Field, R: FrostGroup<S>>(
+    states: Vec<SignerRound1<S, R>>,
+    message: &[u8],
+    commitments: &[SigningCommitments<S, R>],
+) -> SharingResult<Vec<SignatureShare<S>>> {
+    let mut shares = Vec::new();
+    for state in states {
+        let share = state.sign(message, commitments)?;
+        shares.push(SignatureShare::try_from(share.to_bytes().as_slice())?);
+    }
+    Ok(shares)
+}
+
+fn sign<S: Field, R: FrostGroup<S>>(
+    shares: &[Share],
+    verifier: &FeldmanVerifier<S, R>,
+    signers: &[u32],
+    message: &[u8],
+) -> SharingResult<Signature<S, R>> {
+    let (states, commitments) = commit(shares, verifier, signers)?;
+    let coordinator = Coordinator::new(verifier, message, &commitments)?;
+    assert_eq!(coordinator.signers(), signers);
+    let partials = sign_shares(states, message, &commitments)?;
+    let signature = coordinator.aggregate(&partials)?;
+    Signature::try_from(signature.to_bytes().as_slice())
+}
+
+/// Split an existing Ed25519 key and check the signatures with ed25519-dalek
+fn ed25519_interop() {
+    let mut seed = [0u8; 32];
+    rand::RngCore::fill_bytes(&mut OsRng, &mut seed);
+    let key = ExpandedSecretKey::from_secret_key(seed).unwrap();
+    let (verifier, shares) = FeldmanScheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .split_secret::<Ed25519Scalar, Ed25519Point>(
+            &mut OsRng,
+            key.scalar(),
+            Some(Ed25519Point::generator()),
+        )
+        .unwrap();
+    assert_eq!(
+        verifier.commitments[0].to_bytes().as_slice(),
+        &key.public_key()[..]
+    );
+    let public_key = Ed25519Point::from_bytes(&key.public_key()[..]).unwrap();
+    let dalek_key = DalekPublicKey::from_bytes(&key.public_key()).unwrap();
+
+    let message = b"block 1024";
+    for signers in &[vec![1, 2, 3], vec![2, 4, 5], vec![1, 2, 3, 4, 5]] {
+        let signature = sign(&shares, &verifier, signers, message).unwrap();
+        assert!(signature.verify(&public_key, message).is_ok());
+        assert!(signature.verify(&public_key, b"block 1025").is_err());
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), 64);
+        let dalek = DalekSignature::try_from(bytes.as_slice()).unwrap();
+        assert!(dalek_key.verify(message, &dalek).is_ok());
+        assert!(dalek_key.verify_strict(message, &dalek).is_ok());
+    }
+}
+
+fn ristretto_dkg() -> Vec<DkgOutput<Ristretto25519Scalar, Ristretto25519Point>> {
+    let g = Ristretto25519Point::generator();
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(&mut OsRng, id, THRESHOLD, LIMIT, &g).unwrap();
+        participants.push(p);
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    for p in participants.iter_mut() {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<Round1Share>>();
+        assert!(p.receive_round1(&broadcasts, &mine).unwrap().is_empty());
+    }
+    participants
+        .iter_mut()
+        .map(|p| p.finalize(&[], &[]).unwrap())
+        .collect()
+}
+
+/// Sign with a key from the DKG and check the failures
+fn ristretto_signing() {
+    let outputs = ristretto_dkg();
+    let verifier = outputs[0].verifier();
+    let public_key = outputs[0].public_key();
+    let shares = outputs
+        .iter()
+        .map(|o| o.share().clone())
+        .collect::<Vec<_>>();
+    let message = b"block 1024";
+
+    let signature = sign(&shares, verifier, &[1, 3, 5], message).unwrap();
+    assert!(signature.verify(&public_key, message).is_ok());
+    assert!(signature.verify(&public_key, b"block 1025").is_err());
+    let other = sign(&shares, verifier, &[2, 3, 4, 5], message).unwrap();
+    assert!(other.verify(&public_key, message).is_ok());
+    let bytes = other.to_bytes();
+    assert!(Signature::<Ristretto25519Scalar, Ristretto25519Point>::try_from(&bytes[1..]).is_err());
+
+    // Too few signers, or a signer whose commitments weren't chosen
+    let (states, commitments) = commit(&shares, verifier, &[1, 2, 3, 4]).unwrap();
+    match Coordinator::new(verifier, message, &commitments[..2]) {
+        Err(SharingError::FrostInvalidSigner) => {}
+        r => panic!("expected an invalid signer set, found {:?}", r.err()),
+    }
+    let dup = [
+        commitments[0].clone(),
+        commitments[0].clone(),
+        commitments[1].clone(),
+        commitments[2].clone(),
+    ];
+    assert!(Coordinator::new(verifier, message, &dup).is_err());
+    let first = states.into_iter().next().unwrap();
+    match first.sign(message, &commitments[1..]) {
+        Err(SharingError::FrostInvalidSigner) => {}
+        r => panic!("expected an invalid signer set, found {:?}", r),
+    }
+
+    // A signer signing another message is named, so is a missing share
+    let coordinator = Coordinator::new(verifier, message, &commitments[..3]).unwrap();
+    let (states, commitments) = commit(&shares, verifier, &[1, 2, 3]).unwrap();
+    let coordinator_2 = Coordinator::new(verifier, message, &commitments).unwrap();
+    let mut partials = Vec::new();
+    for state in states {
+        let m: &[u8] = if state.id() == 2 {
+            b"block 1025"
+        } else {
+            message
+        };
+        partials.push(state.sign(m, &commitments).unwrap());
+    }
+    match coordinator_2.aggregate(&partials) {
+        Err(SharingError::FrostAbort(2)) => {}
+        r => panic!("expected signer 2 to be blamed, found {:?}", r.err()),
+    }
+    assert!(coordinator_2.verify_share(&partials[0]).is_ok());
+    match coordinator_2.aggregate(&[partials[0].clone(), partials[2].clone()]) {
+        Err(SharingError::FrostAbort(2)) => {}
+        r => panic!("expected signer 2 to be missing, found {:?}", r.err()),
+    }
+    // Shares are bound to the commitments they were made with
+    assert!(coordinator.aggregate(&partials).is_err());
+
+    // Malformed messages
+    let bytes = commitments[0].to_bytes();
+    let mut zero = bytes.clone();
+    zero[..4].copy_from_slice(&[0u8; 4]);
+    assert!(
+        SigningCommitments::<Ristretto25519Scalar, Ristretto25519Point>::try_from(zero.as_slice())
+            .is_err()
+    );
+    let mut identity = bytes;
+    identity[4..36].copy_from_slice(&[0u8; 32]);
+    let identity = SigningCommitments::try_from(identity.as_slice()).unwrap();
+    assert!(
+        SigningCommitments::<Ristretto25519Scalar, Ristretto25519Point>::from_bytes_with(
+            &identity.to_bytes(),
+            Strictness::Strict
+        )
+        .is_err()
+    );
+    match Coordinator::new(
+        verifier,
+        message,
+        &[identity, commitments[1].clone(), commitments[2].clone()],
+    ) {
+        Err(SharingError::FrostAbort(1)) => {}
+        r => panic!("expected signer 1 to be blamed, found {:?}", r.err()),
+    }
+    let share = partials[0].to_bytes();
+    assert!(SignatureShare::<Ristretto25519Scalar>::try_from(&share[1..]).is_err());
+
+    // The commitments must use the standard generator
+    let secret = Ristretto25519Scalar::random(&mut OsRng);
+    let (verifier, shares) = FeldmanScheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .split_secret::<Ristretto25519Scalar, Ristretto25519Point>(&mut OsRng, &secret, None)
+        .unwrap();
+    match SignerRound1::new(&mut OsRng, &shares[0], &verifier) {
+        Err(SharingError::FrostInvalidGenerator) => {}
+        r => panic!("expected an invalid generator, found {:?}", r.err()),
+    }
+}
+
+fn main() {
+    ed25519_interop();
+    ristretto_signing();
+}
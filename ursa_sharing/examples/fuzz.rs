@@ -0,0 +1,114 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ursa_sharing::{
+    fuzz::{case, DkgSession, FrostSession, Message, Mutation, Mutator, Verdict},
+    ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar},
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+const CASES: u64 = 200;
+
+#[derive(Default)]
+struct Tally {
+    completed: usize,
+    rejected: usize,
+}
+
+impl Tally {
+    fn add(&mut self, verdict: Verdict, mutations: &[Mutation]) {
+        match verdict {
+            Verdict::Completed => self.completed += 1,
+            Verdict::Rejected(_) => self.rejected += 1,
+            Verdict::Violation(v) => panic!("{} after {:?}", v, mutations),
+        }
+    }
+}
+
+fn mutated(
+    mutator: &mut Mutator,
+    honest: &[Message],
+    count: usize,
+) -> (Vec<Message>, Vec<Mutation>) {
+    let mut messages = honest.to_vec();
+    let mutations = mutator.mutate(&mut messages, count);
+    (messages, mutations)
+}
+
+fn dkg() {
+    let session =
+        DkgSession::<Scalar, Point>::new(1, THRESHOLD, LIMIT, &Point::generator()).unwrap();
+    let replays =
+        DkgSession::<Scalar, Point>::new(2, THRESHOLD, LIMIT, &Point::generator()).unwrap();
+    assert!(matches!(
+        session.run(1, session.messages()),
+        Verdict::Completed
+    ));
+
+    let mut tally = Tally::default();
+    for seed in 0..CASES {
+        let mut mutator = Mutator::new(seed).with_replays(replays.messages());
+        let (messages, mutations) =
+            mutated(&mut mutator, session.messages(), 1 + seed as usize % 4);
+        for target in 1..=LIMIT as u32 {
+            tally.add(session.run(target, &messages), &mutations);
+        }
+    }
+    println!(
+        "DKG: {} participants completed and {} rejected their case",
+        tally.completed, tally.rejected
+    );
+}
+
+fn frost() {
+    let session = FrostSession::<Scalar, Point>::new(1, THRESHOLD, LIMIT, b"message").unwrap();
+    let replays = FrostSession::<Scalar, Point>::new(2, THRESHOLD, LIMIT, b"message").unwrap();
+    assert!(matches!(
+        session.run(session.messages()),
+        Verdict::Completed
+    ));
+
+    let mut tally = Tally::default();
+    for seed in 0..CASES {
+        let mut mutator = Mutator::new(seed).with_replays(replays.messages());
+        let (messages, mutations) =
+            mutated(&mut mutator, session.messages(), 1 + seed as usize % 4);
+        tally.add(session.run(&messages), &mutations);
+    }
+    println!(
+        "FROST: the coordinator completed {} and rejected {} cases",
+        tally.completed, tally.rejected
+    );
+}
+
+/// How a fuzzer's input becomes a case, e.g. in a cargo-fuzz target
+fn from_input(data: &[u8]) {
+    let session =
+        DkgSession::<Scalar, Point>::new(1, THRESHOLD, LIMIT, &Point::generator()).unwrap();
+    let (seed, mutations) = case(data);
+    let mut mutator = Mutator::new(seed);
+    let mut messages = session.messages().to_vec();
+    for mutation in &mutations {
+        mutator.apply(&mut messages, *mutation);
+    }
+    let verdict = session.run(1, &messages);
+    println!("{:?} gives {:?}", mutations, verdict);
+    Tally::default().add(verdict, &mutations);
+}
+
+fn main() {
+    dkg();
+    frost();
+    from_input(&[0, 0, 0, 0, 0, 0, 0, 42, 4, 6]);
+}
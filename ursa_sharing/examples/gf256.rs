@@ -0,0 +1,25 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ursa_sharing::tests::*;
+
+fn main() {
+    println!("GF(256) Splitting");
+    byte_split_invalid_args();
+    println!("GF(256) Combine invalid fail");
+    byte_combine_invalid();
+    println!("GF(256) Split many success");
+    byte_split_many();
+    println!("GF(256) Combine combinations success");
+    byte_combine_all_combinations();
+}
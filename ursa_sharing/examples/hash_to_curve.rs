@@ -0,0 +1,268 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use ff_zeroize::{PrimeField, PrimeFieldRepr};
+use generic_array::GenericArray;
+use pairing_plus::{
+    bls12_381::{Fq, G2},
+    hash_to_field::{BaseFromRO, ExpandMsgXmd},
+};
+use sha2::{Digest, Sha256, Sha512};
+use ursa_sharing::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    error::SharingError,
+    hash_to_curve::*,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    Field, Group, Strictness,
+};
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len() / 2)
+        .map(|i| u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap())
+        .collect()
+}
+
+fn expand<H: XmdHash>(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    expand_message_xmd::<H>(msg, dst, &mut out).unwrap();
+    out
+}
+
+/// The `expand_message_xmd` vectors of RFC 9380 Appendix K
+fn expand_vectors() {
+    let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+    assert_eq!(
+        expand::<Sha256>(b"", dst, 0x20),
+        hex("68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235")
+    );
+    assert_eq!(
+        expand::<Sha256>(b"abc", dst, 0x20),
+        hex("d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615")
+    );
+    assert_eq!(
+        expand::<Sha256>(b"abcdef0123456789", dst, 0x20),
+        hex("eff31487c770a893cfb36f912fbfcbff40d5661771ca4b2cb4eafe524333f5c1")
+    );
+
+    let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+    assert_eq!(
+        expand::<Sha512>(b"", dst, 0x20),
+        hex("6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba")
+    );
+    assert_eq!(
+        expand::<Sha512>(b"abc", dst, 0x20),
+        hex("0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc")
+    );
+}
+
+fn expand_lengths() {
+    // Long tags are replaced by their hash
+    let long = [b'1'; 256];
+    let hashed = Sha256::new()
+        .chain(b"H2C-OVERSIZE-DST-")
+        .chain(&long[..])
+        .result();
+    assert_eq!(
+        expand::<Sha256>(b"abc", &long, 100),
+        expand::<Sha256>(b"abc", &hashed, 100)
+    );
+    assert_ne!(
+        expand::<Sha256>(b"abc", &long[..255], 100),
+        expand::<Sha256>(b"abc", &hashed, 100)
+    );
+
+    // Shorter outputs aren't prefixes of longer ones
+    let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+    assert_ne!(
+        expand::<Sha256>(b"abc", dst, 32)[..],
+        expand::<Sha256>(b"abc", dst, 64)[..32]
+    );
+    assert_eq!(expand::<Sha256>(b"abc", dst, 255 * 32).len(), 255 * 32);
+    assert_eq!(expand::<Sha512>(b"abc", dst, 255 * 64).len(), 255 * 64);
+    assert!(expand::<Sha256>(b"abc", dst, 0).is_empty());
+    for (len, result) in [
+        (
+            255 * 32 + 1,
+            expand_message_xmd::<Sha256>(b"abc", dst, &mut vec![0u8; 255 * 32 + 1]),
+        ),
+        (
+            255 * 64 + 1,
+            expand_message_xmd::<Sha512>(b"abc", dst, &mut vec![0u8; 255 * 64 + 1]),
+        ),
+    ] {
+        assert!(matches!(result, Err(SharingError::HashToCurveLength(l)) if l == len));
+    }
+}
+
+/// The `P256_XMD:SHA-256_SSWU_RO_` vectors of RFC 9380 Appendix J
+fn p256_vectors() {
+    assert_eq!(Secp256r1Point::SUITE, "P256_XMD:SHA-256_SSWU_RO_");
+    let dst = b"QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_";
+    for (msg, x, y) in [
+        (
+            &b""[..],
+            "2c15230b26dbc6fc9a37051158c95b79656e17a1a920b11394ca91c44247d3e4",
+            "8a7a74985cc5c776cdfe4b1f19884970453912e9d31528c060be9ab5c43e8415",
+        ),
+        (
+            b"abc",
+            "0bb8b87485551aa43ed54f009230450b492fead5f1cc91658775dac4a3388a0f",
+            "5c41b3d0731a27a7b14bc0bf0ccded2d8751f83493404c84a88e71ffd424212e",
+        ),
+        (
+            b"abcdef0123456789",
+            "65038ac8f2b1def042a5df0b33b1f4eca6bff7cb0f9c6c1526811864e544ed80",
+            "cad44d40a656e7aff4002a8de287abc8ae0482b5ae825822bb870d6df9b56ca3",
+        ),
+    ] {
+        let y = hex(y);
+        let mut expected = vec![0x02 | (y[31] & 1)];
+        expected.extend_from_slice(&hex(x));
+        assert_eq!(
+            Secp256r1Point::hash_to_curve(msg, dst).to_bytes().to_vec(),
+            expected
+        );
+    }
+}
+
+/// The `hash_to_field` outputs of the `BLS12381G1_XMD:SHA-256_SSWU_RO_` vectors of
+/// RFC 9380 Appendix J. The points differ, see the module documentation
+fn bls12_381_field_vectors() {
+    let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+    let uniform = expand::<Sha256>(b"", dst, 128);
+    for (okm, u) in uniform.chunks(64).zip([
+        "0ba14bd907ad64a016293ee7c2d276b8eae71f25a4b941eece7b0d89f17f75cb\
+         3ae5438a614fb61d6835ad59f29c564f",
+        "019b9bd7979f12657976de2884c7cce192b82c177c80e0ec604436a7f538d231\
+         552f0d96d9f7babe5fa3b19b3ff25ac9",
+    ]) {
+        let mut bytes = Vec::new();
+        Fq::from_okm(GenericArray::from_slice(okm))
+            .into_repr()
+            .write_be(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, hex(u));
+    }
+}
+
+fn bls12_381_points() {
+    assert_eq!(Bls12381G1::SUITE, "BLS12381G1_XMD:SHA-256_SSWU_RO_");
+    assert_eq!(Bls12381G2::SUITE, "BLS12381G2_XMD:SHA-256_SSWU_RO_");
+    let dst = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+    // The same points as pairing-plus, so BLS signatures keep verifying
+    assert_eq!(
+        Bls12381G2::hash_to_curve(b"message", dst).to_bytes(),
+        Bls12381G2(<G2 as pairing_plus::hash_to_curve::HashToCurve<
+            ExpandMsgXmd<Sha256>,
+        >>::hash_to_curve(b"message", dst))
+        .to_bytes()
+    );
+}
+
+/// Points are in the prime order group and depend on the message and the tag
+fn points<S: Field, R: HashToCurve<S>>() {
+    let dst: &[u8] = b"URSA-V01-CS01-with-hash-to-curve";
+    let p = R::hash_to_curve(b"message", dst);
+    assert!(R::from_bytes_with(p.to_bytes(), Strictness::Strict).is_ok());
+    assert_eq!(p.to_bytes(), R::hash_to_curve(b"message", dst).to_bytes());
+    assert_ne!(p.to_bytes(), R::hash_to_curve(b"messagf", dst).to_bytes());
+    assert_ne!(
+        p.to_bytes(),
+        R::hash_to_curve(b"message", b"URSA-V01-CS01-with-hash-to-curvf").to_bytes()
+    );
+    assert!(!R::hash_to_curve(b"", &[]).is_zero());
+
+    let long = [0x5au8; 300];
+    let hashed = if R::SUITE.contains("SHA-512") {
+        Sha512::new()
+            .chain(b"H2C-OVERSIZE-DST-")
+            .chain(&long[..])
+            .result()
+            .to_vec()
+    } else {
+        Sha256::new()
+            .chain(b"H2C-OVERSIZE-DST-")
+            .chain(&long[..])
+            .result()
+            .to_vec()
+    };
+    assert_eq!(
+        R::hash_to_curve(b"message", &long).to_bytes(),
+        R::hash_to_curve(b"message", &hashed).to_bytes()
+    );
+}
+
+fn scalars<S: HashToScalar>() {
+    let dst: &[u8] = b"URSA-V01-CS01-with-hash-to-scalar";
+    let s = S::hash_to_scalar(b"message", dst);
+    assert!(s.is_valid());
+    assert_eq!(s.to_bytes(), S::hash_to_scalar(b"message", dst).to_bytes());
+    assert_ne!(s.to_bytes(), S::hash_to_scalar(b"messagf", dst).to_bytes());
+    assert_ne!(
+        s.to_bytes(),
+        S::hash_to_scalar(b"message", b"URSA-V01-CS01-with-hash-to-scalaq").to_bytes()
+    );
+}
+
+/// `hash_to_field` with 48 bytes big endian, for both the BLS12-381 and P-256 scalars
+fn scalar_reduction() {
+    let dst: &[u8] = b"URSA-V01-CS01-with-hash-to-scalar";
+    let uniform = expand::<Sha256>(b"message", dst, 48);
+    assert_eq!(
+        Bls12381Scalar::hash_to_scalar(b"message", dst).to_bytes(),
+        Bls12381Scalar::from_bytes(&uniform[..]).unwrap().to_bytes()
+    );
+
+    // hi * 2^256 + lo with the 16 and 32 byte halves
+    let hi = Secp256r1Scalar::from_bytes(&uniform[..16]).unwrap();
+    let lo = Secp256r1Scalar::from_bytes(&uniform[16..]).unwrap();
+    // 2^128
+    let mut shift = [0u8; 32];
+    shift[15] = 1;
+    let shift = Secp256r1Scalar::from_bytes(shift).unwrap();
+    assert_eq!(
+        Secp256r1Scalar::hash_to_scalar(b"message", dst).to_bytes(),
+        Secp256r1Scalar(hi.0 * shift.0 * shift.0 + lo.0).to_bytes()
+    );
+
+    // 64 bytes little endian for ristretto255
+    let uniform = expand::<Sha512>(b"message", dst, 64);
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&uniform);
+    assert_eq!(
+        Ristretto25519Scalar::hash_to_scalar(b"message", dst).0,
+        curve25519_dalek::scalar::Scalar::from_bytes_mod_order_wide(&wide)
+    );
+}
+
+fn main() {
+    println!("RFC 9380 expand_message_xmd test vectors");
+    expand_vectors();
+    expand_lengths();
+    println!("RFC 9380 P-256 test vectors");
+    p256_vectors();
+    println!("RFC 9380 BLS12-381 hash to field test vectors");
+    bls12_381_field_vectors();
+    bls12_381_points();
+    println!("Hash to curve");
+    points::<Secp256r1Scalar, Secp256r1Point>();
+    points::<Ristretto25519Scalar, Ristretto25519Point>();
+    points::<Bls12381Scalar, Bls12381G1>();
+    points::<Bls12381Scalar, Bls12381G2>();
+    println!("Hash to scalar");
+    scalars::<Secp256r1Scalar>();
+    scalars::<Ristretto25519Scalar>();
+    scalars::<Bls12381Scalar>();
+    scalar_reduction();
+}
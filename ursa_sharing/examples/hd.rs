@@ -0,0 +1,178 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::str::FromStr;
+use ursa_sharing::{
+    bls12_381::{Bls12381G1, Bls12381Scalar},
+    error::SharingError,
+    hd::{ChildIndex, DerivationPath, Eip2333, KeyDerivation, Ristretto255Hd},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    Group,
+};
+
+/// EIP-2333 test cases as seed, master key, child index and child key
+const EIP2333_VECTORS: [(&str, &str, u32, &str); 4] = [
+    (
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        "0d7359d57963ab8fbbde1852dcf553fedbc31f464d80ee7d40ae683122b45070",
+        0,
+        "2d18bd6c14e6d15bf8b5085c9b74f3daae3b03cc2014770a599d8c1539e50f8e",
+    ),
+    (
+        "3141592653589793238462643383279502884197169399375105820974944592",
+        "41c9e07822b092a93fd6797396338c3ada4170cc81829fdfce6b5d34bd5e7ec7",
+        3141592653,
+        "384843fad5f3d777ea39de3e47a8f999ae91f89e42bffa993d91d9782d152a0f",
+    ),
+    (
+        "0099ff991111002299dd7744ee3355bbdd8844115566cc55663355668888cc00",
+        "3cfa341ab3910a7d00d933d8f7c4fe87c91798a0397421d6b19fd5b815132e80",
+        4294967295,
+        "40e86285582f35b28821340f6a53b448588efa575bc4d88c32ef8567b8d9479b",
+    ),
+    (
+        "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+        "2a0e28ffa5fbbe2f8e7aad4ed94f745d6bf755c51182e119bb1694fe61d3afca",
+        42,
+        "455c0dc9fccb3395825d92a60d2672d69416be1c2578a87a7a3d3ced11ebb88d",
+    ),
+];
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn eip2333() {
+    for (seed, master, index, child) in EIP2333_VECTORS.iter() {
+        let seed = unhex(seed);
+        let master_key = Eip2333::master_key(&seed).unwrap();
+        assert_eq!(hex(&master_key.to_bytes()), *master);
+        let child_key = Eip2333::child_key(&master_key, ChildIndex::Normal(*index)).unwrap();
+        assert_eq!(hex(&child_key.to_bytes()), *child);
+        let path = DerivationPath::from_str(&format!("m/{}", index)).unwrap();
+        let derived = Eip2333::derive(&seed, &path).unwrap();
+        assert_eq!(hex(&derived.to_bytes()), *child);
+    }
+
+    // A validator signing key and its public key
+    let seed = [7u8; 32];
+    let path = DerivationPath::from_str("m/12381/3600/0/0/0").unwrap();
+    assert_eq!(path, DerivationPath::eip2334_signing(0));
+    let key: Bls12381Scalar = Eip2333::derive(&seed, &path).unwrap();
+    let mut public_key = Bls12381G1::generator();
+    public_key.scalar_mul_assign(&key);
+    assert!(public_key.is_valid());
+    let other = Eip2333::derive(&seed, &DerivationPath::eip2334_signing(1)).unwrap();
+    assert_ne!(key.to_bytes(), other.to_bytes());
+
+    // Hardened indices mean something else in BIP-32 style paths
+    let hardened = DerivationPath::from_str("m/12381'/3600'/0'/0'/0'").unwrap();
+    match Eip2333::derive(&seed, &hardened) {
+        Err(SharingError::DerivationIndexUnsupported) => {}
+        r => panic!("expected an unsupported index, found {:?}", r.err()),
+    }
+    match Eip2333::master_key(&seed[..31]) {
+        Err(SharingError::DerivationSeedTooShort) => {}
+        r => panic!("expected a short seed, found {:?}", r.err()),
+    }
+}
+
+fn ristretto() {
+    let seed = [9u8; 64];
+    let path = DerivationPath::from_str("m/44h/0'/7'").unwrap();
+    let key = Ristretto255Hd::derive(&seed, &path).unwrap();
+    let parent =
+        Ristretto255Hd::derive(&seed, &DerivationPath::from_str("m/44'/0'").unwrap()).unwrap();
+    let child = Ristretto255Hd::child_key(&parent, ChildIndex::Hardened(7)).unwrap();
+    assert_eq!(key.to_bytes(), child.to_bytes());
+    let sibling = Ristretto255Hd::child_key(&parent, ChildIndex::Hardened(8)).unwrap();
+    assert_ne!(key.to_bytes(), sibling.to_bytes());
+    let master = Ristretto255Hd::master_key(&seed).unwrap();
+    assert_ne!(master.to_bytes(), parent.to_bytes());
+    let mut public_key = Ristretto25519Point::generator();
+    public_key.scalar_mul_assign(&key);
+    assert!(public_key.is_valid());
+
+    // Deterministic, different seeds give different keys
+    let again: Ristretto25519Scalar = Ristretto255Hd::derive(&seed, &path).unwrap();
+    assert_eq!(key.to_bytes(), again.to_bytes());
+    let other = Ristretto255Hd::derive(&[8u8; 64], &path).unwrap();
+    assert_ne!(key.to_bytes(), other.to_bytes());
+
+    match Ristretto255Hd::derive(&seed, &DerivationPath::from_str("m/44'/0").unwrap()) {
+        Err(SharingError::DerivationIndexUnsupported) => {}
+        r => panic!("expected an unsupported index, found {:?}", r.err()),
+    }
+    assert!(Ristretto255Hd::master_key(&seed[..16]).is_err());
+}
+
+fn paths() {
+    let path = DerivationPath::from_str("m/44'/60h/0/2147483647'").unwrap();
+    assert_eq!(
+        path.indices(),
+        &[
+            ChildIndex::Hardened(44),
+            ChildIndex::Hardened(60),
+            ChildIndex::Normal(0),
+            ChildIndex::Hardened(2147483647),
+        ]
+    );
+    assert_eq!(path.to_string(), "m/44'/60'/0/2147483647'");
+    assert_eq!(DerivationPath::from_str(&path.to_string()).unwrap(), path);
+    assert_eq!(path.indices()[0].to_u32().unwrap(), 0x8000_002c);
+    assert!(ChildIndex::Normal(0x8000_0000).to_u32().is_err());
+    assert!(DerivationPath::from_str("m").unwrap().indices().is_empty());
+    assert_eq!(
+        DerivationPath::from_str("m/1")
+            .unwrap()
+            .child(ChildIndex::Hardened(2))
+            .unwrap()
+            .to_string(),
+        "m/1/2'"
+    );
+
+    for bad in &[
+        "",
+        "n/0",
+        "m/",
+        "m//0",
+        "m/0/",
+        "m/+1",
+        "m/-1",
+        "m/1''",
+        "m/0x1",
+        "m/4294967296",
+        "m/2147483648'",
+        "/0",
+        "m/ 1",
+    ] {
+        match DerivationPath::from_str(bad) {
+            Err(SharingError::DerivationPathInvalid) => {}
+            r => panic!("expected {:?} to be invalid, found {:?}", bad, r),
+        }
+    }
+    assert!(DerivationPath::new(vec![ChildIndex::Hardened(0x8000_0000)]).is_err());
+}
+
+fn main() {
+    paths();
+    eip2333();
+    ristretto();
+}
@@ -0,0 +1,142 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    bls12_381::Bls12381Scalar,
+    error::SharingError,
+    ibe::*,
+    shamir::Scheme,
+    threshold_bls::{PublicKey, PublicKeyShare},
+    Group,
+};
+
+const ALICE: &[u8] = b"alice@example.com";
+const BOB: &[u8] = b"did:example:bob";
+
+fn round_trip() {
+    let master_key = MasterKey::new(&mut OsRng);
+    let public_key = master_key.public_key();
+    let ciphertext = encrypt(&mut OsRng, &public_key, ALICE, b"hello alice").unwrap();
+
+    let alice = master_key.extract(ALICE);
+    alice.verify(&public_key).unwrap();
+    assert_eq!(alice.identity(), ALICE);
+    assert_eq!(alice.decrypt(&ciphertext).unwrap(), b"hello alice");
+
+    let bob = master_key.extract(BOB);
+    assert!(matches!(
+        bob.decrypt(&ciphertext),
+        Err(SharingError::IbeDecryptionFailed)
+    ));
+
+    // Another authority's key for the same identity
+    let other = MasterKey::new(&mut OsRng).extract(ALICE);
+    assert!(matches!(
+        other.verify(&public_key),
+        Err(SharingError::IbeInvalidKey)
+    ));
+    assert!(matches!(
+        other.decrypt(&ciphertext),
+        Err(SharingError::IbeDecryptionFailed)
+    ));
+
+    let empty = encrypt(&mut OsRng, &public_key, BOB, b"").unwrap();
+    assert!(bob.decrypt(&empty).unwrap().is_empty());
+}
+
+fn threshold_extraction() {
+    let secret = Bls12381Scalar::random(&mut OsRng);
+    let public_key = PublicKey::from_secret_key(&secret);
+    let shares = Scheme::new(3, 5)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+    let ciphertext = encrypt(&mut OsRng, &public_key, BOB, b"hello bob").unwrap();
+
+    let parts = shares
+        .iter()
+        .map(|s| extract_share(s, BOB).unwrap())
+        .collect::<Vec<_>>();
+    for (part, share) in parts.iter().zip(shares.iter()) {
+        part.verify_hashed(
+            &PublicKeyShare::from_share(share).unwrap(),
+            &hash_identity(BOB),
+        )
+        .unwrap();
+    }
+    let bob = IdentityKey::combine(BOB, 3, &parts[1..4]).unwrap();
+    bob.verify(&public_key).unwrap();
+    assert_eq!(bob.decrypt(&ciphertext).unwrap(), b"hello bob");
+    assert_eq!(
+        bob.to_bytes(),
+        MasterKey::from_secret_key(&secret)
+            .unwrap()
+            .extract(BOB)
+            .to_bytes()
+    );
+
+    // Too few parts give a wrong key
+    let wrong = IdentityKey::combine(BOB, 2, &parts[..2]).unwrap();
+    assert!(matches!(
+        wrong.verify(&public_key),
+        Err(SharingError::IbeInvalidKey)
+    ));
+}
+
+fn serialization() {
+    let master_key = MasterKey::new(&mut OsRng);
+    let master_key = MasterKey::try_from(&master_key.to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", master_key), "MasterKey(..)");
+    let public_key = master_key.public_key();
+    let ciphertext = encrypt(&mut OsRng, &public_key, ALICE, b"hello alice").unwrap();
+    let bytes = ciphertext.to_bytes();
+    let ciphertext = Ciphertext::try_from(&bytes[..]).unwrap();
+    let alice = IdentityKey::try_from(&master_key.extract(ALICE).to_bytes()[..]).unwrap();
+    assert_eq!(
+        format!("{:?}", alice),
+        "IdentityKey(\"alice@example.com\", ..)"
+    );
+    assert_eq!(alice.decrypt(&ciphertext).unwrap(), b"hello alice");
+
+    for i in [1, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[i] ^= 1;
+        if let Ok(tampered) = Ciphertext::try_from(&tampered[..]) {
+            assert!(matches!(
+                alice.decrypt(&tampered),
+                Err(SharingError::IbeDecryptionFailed)
+            ));
+        }
+    }
+    let mut bytes = bytes;
+    bytes[0] = 2;
+    assert!(matches!(
+        Ciphertext::try_from(&bytes[..]),
+        Err(SharingError::ShareUnsupportedVersion(2))
+    ));
+    assert!(matches!(
+        MasterKey::try_from(&[0u8; 32][..]),
+        Err(SharingError::ShareInvalidSecret)
+    ));
+}
+
+fn main() {
+    println!("Round trip");
+    round_trip();
+    println!("Threshold extraction");
+    threshold_extraction();
+    println!("Serialization");
+    serialization();
+}
@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use ff::Field as FFField;
-use generic_array::{typenum::U32, GenericArray};
+use generic_array::{
+    typenum::{U32, U33},
+    GenericArray,
+};
 use k256::{
     elliptic_curve::{
         sec1::{FromEncodedPoint, ToEncodedPoint},
@@ -21,10 +24,16 @@ use k256::{
     EncodedPoint, FieldBytes, ProjectivePoint, Scalar,
 };
 use rand::{CryptoRng, RngCore};
-use ursa_sharing::{error::*, tests::*, Field, Group};
+use ursa_sharing::{error::*, tests::*, zeroize::Zeroize, Field, Group};
 
 struct K256Scalar(Scalar);
 
+impl Zeroize for K256Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Scalar::zero();
+    }
+}
+
 impl Field for K256Scalar {
     fn one() -> Self {
         Self(Scalar::one())
@@ -34,8 +43,10 @@ impl Field for K256Scalar {
         Self(Scalar::from(value as u64))
     }
 
-    fn scalar_div_assign(&mut self, rhs: &Self) {
-        self.0 *= rhs.0.invert().unwrap()
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = Option::<Scalar>::from(rhs.0.invert()).ok_or(SharingError::DivisionByZero)?;
+        self.0 *= inverse;
+        Ok(())
     }
 }
 
@@ -95,7 +106,7 @@ impl Group for K256Scalar {
 struct K256Point(ProjectivePoint);
 
 impl Group<K256Scalar> for K256Point {
-    type Size = U32;
+    type Size = U33;
 
     fn zero() -> Self {
         Self(ProjectivePoint::identity())
@@ -144,7 +155,7 @@ impl Group<K256Scalar> for K256Point {
     }
 
     fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
-        let mut c = [0u8; 32];
+        let mut c = [0u8; 33];
         c.copy_from_slice(
             self.0
                 .to_affine()
@@ -152,7 +163,7 @@ impl Group<K256Scalar> for K256Point {
                 .to_bytes()
                 .as_ref(),
         );
-        c.into()
+        GenericArray::clone_from_slice(&c)
     }
 }
 
@@ -161,8 +172,37 @@ fn main() {
     split_invalid_args::<K256Scalar>();
     println!("Combine invalid fail");
     combine_invalid::<K256Scalar>();
+    println!("Split many success");
+    split_many::<K256Scalar>();
+    println!("Field division success");
+    field_division::<K256Scalar>();
+    println!("Refresh shares success");
+    refresh_shares::<K256Scalar>();
     println!("Combine single success");
     combine_single::<K256Scalar, K256Point>();
     println!("Combine combinations success");
     combine_all_combinations::<K256Scalar, K256Point>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<K256Scalar, K256Point>();
+    println!("Observer events success");
+    observer_events::<K256Scalar, K256Point>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<K256Scalar, K256Point>();
+    println!("Combine with context success");
+    combine_with_context::<K256Scalar, K256Point>();
+    println!("Share verification success");
+    share_verification::<K256Scalar, K256Point>();
+    println!("Cheater detection success");
+    cheater_detection::<K256Scalar, K256Point>();
+    println!("DKG rounds success");
+    dkg_rounds::<K256Scalar, K256Point>();
+    println!("Possession proofs success");
+    possession_proofs::<K256Scalar, K256Point>();
+    println!("Escrow success");
+    escrow_shares::<K256Scalar, K256Point>();
+    dleq_proofs::<K256Scalar, K256Point>();
+    println!("Transcript challenges success");
+    transcript_challenges::<K256Scalar, K256Point>();
+    println!("Strict deserialization success");
+    strict_deserialization::<K256Scalar, K256Point>();
 }
@@ -0,0 +1,164 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use ursa_sharing::{
+    error::SharingError,
+    field::{Bls12381FrParams, Bn254FrParams, Domain, FieldParams, Fp},
+    ntt::*,
+    Field, Group,
+};
+
+fn random<P: FieldParams>(len: usize) -> Vec<Fp<P>> {
+    (0..len).map(|_| Fp::<P>::random(&mut OsRng)).collect()
+}
+
+fn schoolbook<P: FieldParams>(a: &[Fp<P>], b: &[Fp<P>]) -> Vec<Fp<P>> {
+    let mut product = vec![Fp::<P>::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += *x * *y;
+        }
+    }
+    product
+}
+
+fn multiplication<P: FieldParams>() {
+    for (la, lb) in [(1, 1), (3, 5), (33, 33), (40, 100), (257, 300), (1000, 64)] {
+        let a = random::<P>(la);
+        let b = random::<P>(lb);
+        let product = multiply(&a, &b).unwrap();
+        assert_eq!(product, schoolbook(&a, &b));
+        assert_eq!(multiply(&b, &a).unwrap(), product);
+    }
+    assert!(multiply::<P>(&[], &random(10)).unwrap().is_empty());
+    assert!(multiply::<P>(&random(10), &[]).unwrap().is_empty());
+}
+
+fn division<P: FieldParams>() {
+    for (la, lb) in [
+        (1, 1),
+        (10, 3),
+        (70, 35),
+        (100, 40),
+        (300, 50),
+        (500, 250),
+        (20, 40),
+    ] {
+        let a = random::<P>(la);
+        let b = random::<P>(lb);
+        let (q, r) = divide(&a, &b).unwrap();
+        assert_eq!(q.len(), (la + 1).saturating_sub(lb));
+        assert_eq!(r.len(), lb - 1);
+        // a = q * b + r
+        let mut expected = if q.is_empty() {
+            vec![Fp::<P>::zero(); la.max(lb - 1)]
+        } else {
+            multiply(&q, &b).unwrap()
+        };
+        expected
+            .iter_mut()
+            .zip(r.iter())
+            .for_each(|(e, r)| *e += *r);
+        expected.resize(la, Fp::zero());
+        assert_eq!(expected, a);
+    }
+
+    // Zero leading coefficients of the divisor are ignored
+    let a = random::<P>(50);
+    let b = random::<P>(5);
+    let mut padded = b.clone();
+    padded.extend_from_slice(&[Fp::zero(); 3]);
+    assert_eq!(divide(&a, &padded).unwrap(), divide(&a, &b).unwrap());
+
+    // Dividing by x - c leaves the value at c
+    let c = Fp::<P>::random(&mut OsRng);
+    let (_, r) = divide(&a, &[-c, Fp::one()]).unwrap();
+    assert_eq!(r, vec![evaluate(&a, &c)]);
+
+    for zero in [&[][..], &[Fp::<P>::zero(); 4][..]] {
+        assert!(matches!(
+            divide(&a, zero),
+            Err(SharingError::DivisionByZero)
+        ));
+    }
+}
+
+fn trees<P: FieldParams>() {
+    for n in [1usize, 2, 7, 64, 100, 257] {
+        let points = (1..=n as u64).map(Fp::<P>::from_u64).collect::<Vec<_>>();
+        let tree = SubproductTree::new(&points).unwrap();
+        assert_eq!(tree.len(), n);
+
+        let vanishing = tree.vanishing();
+        assert_eq!(vanishing.len(), n + 1);
+        assert_eq!(vanishing[n], Fp::one());
+        for x in &points {
+            assert_eq!(evaluate(&vanishing, x), Fp::zero());
+        }
+
+        // Polynomials of degree below, at and above the number of points
+        for len in [n / 2 + 1, n, 2 * n + 3] {
+            let coefficients = random::<P>(len);
+            let values = tree.evaluate(&coefficients).unwrap();
+            let expected = points
+                .iter()
+                .map(|x| evaluate(&coefficients, x))
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected);
+        }
+
+        let coefficients = random::<P>(n);
+        let values = tree.evaluate(&coefficients).unwrap();
+        assert_eq!(tree.interpolate(&values).unwrap(), coefficients);
+
+        assert!(matches!(
+            tree.interpolate(&values[1..]),
+            Err(SharingError::PolynomialValueCount(e, f)) if e == n && f == n - 1
+        ));
+    }
+
+    // Arbitrary points agree with the FFT on the roots of unity
+    let domain = Domain::<P>::new(128).unwrap();
+    let tree = SubproductTree::new(&domain.elements()).unwrap();
+    let coefficients = random::<P>(128);
+    let mut values = coefficients.clone();
+    domain.fft(&mut values).unwrap();
+    assert_eq!(tree.evaluate(&coefficients).unwrap(), values);
+    assert_eq!(tree.interpolate(&values).unwrap(), coefficients);
+
+    let empty = SubproductTree::<P>::new(&[]).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.vanishing(), vec![Fp::one()]);
+    assert!(empty.evaluate(&random(5)).unwrap().is_empty());
+    assert!(empty.interpolate(&[]).unwrap().is_empty());
+
+    let mut points = random::<P>(20);
+    points[13] = points[4];
+    assert!(matches!(
+        SubproductTree::new(&points),
+        Err(SharingError::ShareDuplicateIdentifier)
+    ));
+}
+
+fn main() {
+    println!("NTT multiplication");
+    multiplication::<Bls12381FrParams>();
+    multiplication::<Bn254FrParams>();
+    println!("Division");
+    division::<Bls12381FrParams>();
+    division::<Bn254FrParams>();
+    println!("Subproduct trees");
+    trees::<Bls12381FrParams>();
+    trees::<Bn254FrParams>();
+}
@@ -0,0 +1,302 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    hash_to_curve::HashToScalar,
+    oprf::*,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    Group, Strictness,
+};
+
+type RistrettoKey = ServerKey<Ristretto25519Scalar, Ristretto25519Point>;
+type P256Key = ServerKey<Secp256r1Scalar, Secp256r1Point>;
+
+const SEED: [u8; 32] = [0xa3; 32];
+const KEY_INFO: &[u8] = b"test key";
+const INPUTS: [&str; 2] = ["00", "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"];
+
+/// Returns the given bytes so the test vectors' random scalars can be replayed
+struct FixedRng(Vec<u8>);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let n = dest.len();
+        dest.copy_from_slice(&self.0[..n]);
+        self.0.drain(..n);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len() / 2)
+        .map(|i| u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The vectors of RFC 9497 Appendix A for OPRF(ristretto255, SHA-512)
+fn ristretto_vectors() {
+    let key = RistrettoKey::derive(Mode::Oprf, &SEED, KEY_INFO).unwrap();
+    assert_eq!(
+        key.to_bytes(),
+        hex("5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e")
+    );
+    let server = OprfServer::new(key);
+    let outputs = [
+        "527759c3d9366f277d8c6020418d96bb393ba2afb20ff90df23fb7708264e2f3\
+         ab9135e3bd69955851de4b1f9fe8a0973396719b7912ba9ee8aa7d0b5e24bcf6",
+        "f4a74c9c592497375e796aa837e907b1a045d34306a749db9f34221f7e750cb4\
+         f2a6413a6bf6fa5e19ba6348eb673934a722a7ede2e7621306d18951e7cf2c73",
+    ];
+    let client = OprfClient::new();
+    for (input, output) in INPUTS.iter().zip(outputs.iter()) {
+        let input = hex(input);
+        let (blind, blinded) = client.blind(&mut OsRng, &input).unwrap();
+        let evaluated = server.blind_evaluate(&blinded);
+        assert_eq!(
+            client.finalize(&input, &blind, &evaluated).unwrap(),
+            hex(output)
+        );
+        assert_eq!(server.evaluate(&input).unwrap(), hex(output));
+    }
+
+    let key = RistrettoKey::derive(Mode::Voprf, &SEED, KEY_INFO).unwrap();
+    assert_eq!(
+        key.to_bytes(),
+        hex("e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909")
+    );
+    assert_eq!(
+        key.public_key().to_bytes().to_vec(),
+        hex("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e")
+    );
+    assert_eq!(
+        VoprfServer::new(key).evaluate(&[0]).unwrap(),
+        hex(
+            "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+             a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c"
+        )
+    );
+
+    let key = RistrettoKey::derive(Mode::Poprf, &SEED, KEY_INFO).unwrap();
+    assert_eq!(
+        key.to_bytes(),
+        hex("145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07")
+    );
+    assert_eq!(
+        key.public_key().to_bytes().to_vec(),
+        hex("c647bef38497bc6ec077c22af65b696efa43bff3b4a1975a3e8e0a1c5a79d631")
+    );
+}
+
+/// The vectors of RFC 9497 Appendix A for OPRF(P-256, SHA-256)
+fn p256_vectors() {
+    let key = P256Key::derive(Mode::Oprf, &SEED, KEY_INFO).unwrap();
+    assert_eq!(
+        key.to_bytes(),
+        hex("159749d750713afe245d2d39ccfaae8381c53ce92d098a9375ee70739c7ac0bf")
+    );
+    let server = OprfServer::new(key);
+    let client = OprfClient::<Secp256r1Scalar, Secp256r1Point>::new();
+    let mut rng = FixedRng(hex(
+        "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+    ));
+    let (blind, blinded) = client.blind(&mut rng, &[0]).unwrap();
+    assert_eq!(
+        blinded.to_bytes().to_vec(),
+        hex("03723a1e5c09b8b9c18d1dcbca29e8007e95f14f4732d9346d490ffc195110368d")
+    );
+    let evaluated = server.blind_evaluate(&blinded);
+    assert_eq!(
+        evaluated.to_bytes().to_vec(),
+        hex("030de02ffec47a1fd53efcdd1c6faf5bdc270912b8749e783c7ca75bb412958832")
+    );
+    let output = hex("a0b34de5fa4c5b6da07e72af73cc507cceeb48981b97b7285fc375345fe495dd");
+    assert_eq!(client.finalize(&[0], &blind, &evaluated).unwrap(), output);
+    assert_eq!(server.evaluate(&[0]).unwrap(), output);
+}
+
+fn parse<S: HashToScalar, R: OprfGroup<S>>(element: &R) -> R {
+    R::from_bytes_with(element.to_bytes(), Strictness::Strict).unwrap()
+}
+
+fn oprf<S: HashToScalar, R: OprfGroup<S>>() {
+    let server = OprfServer::new(ServerKey::<S, R>::random(&mut OsRng));
+    let client = OprfClient::<S, R>::new();
+    let (blind, blinded) = client.blind(&mut OsRng, b"password").unwrap();
+    let evaluated = server.blind_evaluate(&parse(&blinded));
+    let output = client.finalize(b"password", &blind, &evaluated).unwrap();
+    assert_eq!(output, server.evaluate(b"password").unwrap());
+    assert_ne!(output, server.evaluate(b"passw0rd").unwrap());
+
+    // Another key gives another function
+    let other = OprfServer::new(ServerKey::<S, R>::random(&mut OsRng));
+    assert_ne!(output, other.evaluate(b"password").unwrap());
+
+    // Keys round trip
+    let key = ServerKey::<S, R>::random(&mut OsRng);
+    let parsed = ServerKey::<S, R>::try_from(key.to_bytes().as_slice()).unwrap();
+    assert_eq!(parsed.public_key().to_bytes(), key.public_key().to_bytes());
+    assert!(matches!(
+        ServerKey::<S, R>::try_from(&vec![0u8; S::zero().to_bytes().len()][..]),
+        Err(SharingError::ShareInvalidSecret)
+    ));
+    assert!(ServerKey::<S, R>::try_from(&[1u8][..]).is_err());
+
+    // Derived keys are separated by mode and info
+    let oprf = ServerKey::<S, R>::derive(Mode::Oprf, &SEED, KEY_INFO).unwrap();
+    let voprf = ServerKey::<S, R>::derive(Mode::Voprf, &SEED, KEY_INFO).unwrap();
+    let other = ServerKey::<S, R>::derive(Mode::Oprf, &SEED, b"other key").unwrap();
+    assert_ne!(oprf.to_bytes(), voprf.to_bytes());
+    assert_ne!(oprf.to_bytes(), other.to_bytes());
+}
+
+fn voprf<S: HashToScalar, R: OprfGroup<S>>() {
+    let server = VoprfServer::new(ServerKey::<S, R>::random(&mut OsRng));
+    let client = VoprfClient::new(parse(server.public_key()));
+    let (blind, blinded) = client.blind(&mut OsRng, b"password").unwrap();
+    let (evaluated, proof) = server.blind_evaluate(&mut OsRng, &blinded);
+    let proof = Proof::<S>::try_from(proof.to_bytes().as_slice()).unwrap();
+    let output = client
+        .finalize(b"password", &blind, &evaluated, &blinded, &proof)
+        .unwrap();
+    assert_eq!(output, server.evaluate(b"password").unwrap());
+
+    // A proof for another evaluation or from another server doesn't verify
+    let (_, other_blinded) = client.blind(&mut OsRng, b"password").unwrap();
+    let (_, other_proof) = server.blind_evaluate(&mut OsRng, &other_blinded);
+    assert!(matches!(
+        client.finalize(b"password", &blind, &evaluated, &blinded, &other_proof),
+        Err(SharingError::OprfProofInvalid)
+    ));
+    let tracking = VoprfServer::new(ServerKey::<S, R>::random(&mut OsRng));
+    let (tracked, tracked_proof) = tracking.blind_evaluate(&mut OsRng, &blinded);
+    assert!(matches!(
+        client.finalize(b"password", &blind, &tracked, &blinded, &tracked_proof),
+        Err(SharingError::OprfProofInvalid)
+    ));
+
+    // Batches share one proof
+    let inputs: [&[u8]; 3] = [b"alice", b"bob", b"carol"];
+    let (blinds, blinded): (Vec<_>, Vec<_>) = inputs
+        .iter()
+        .map(|i| client.blind(&mut OsRng, i).unwrap())
+        .unzip();
+    let (evaluated, proof) = server.blind_evaluate_batch(&mut OsRng, &blinded).unwrap();
+    let outputs = client
+        .finalize_batch(&inputs, &blinds, &evaluated, &blinded, &proof)
+        .unwrap();
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        assert_eq!(*output, server.evaluate(input).unwrap());
+    }
+    let mut swapped = evaluated.iter().map(parse).collect::<Vec<_>>();
+    swapped.swap(0, 1);
+    assert!(matches!(
+        client.finalize_batch(&inputs, &blinds, &swapped, &blinded, &proof),
+        Err(SharingError::OprfProofInvalid)
+    ));
+    assert!(matches!(
+        client.finalize_batch(&inputs[..2], &blinds, &evaluated, &blinded, &proof),
+        Err(SharingError::OprfBatchInvalid)
+    ));
+    assert!(matches!(
+        server.blind_evaluate_batch(&mut OsRng, &[]),
+        Err(SharingError::OprfBatchInvalid)
+    ));
+
+    // Proof scalars must be reduced
+    let size = proof.to_bytes().len();
+    assert!(matches!(
+        Proof::<S>::try_from(&vec![0xff; size][..]),
+        Err(SharingError::NonCanonicalEncoding)
+    ));
+    assert!(Proof::<S>::try_from(&proof.to_bytes()[1..]).is_err());
+}
+
+fn poprf<S: HashToScalar, R: OprfGroup<S>>() {
+    let server = PoprfServer::new(ServerKey::<S, R>::random(&mut OsRng));
+    let client = PoprfClient::new(parse(server.public_key()));
+    let info = b"2020-11-01";
+    let (blind, blinded) = client.blind(&mut OsRng, b"password", info).unwrap();
+    let (evaluated, proof) = server.blind_evaluate(&mut OsRng, &blinded, info).unwrap();
+    let output = client
+        .finalize(b"password", info, &blind, &evaluated, &blinded, &proof)
+        .unwrap();
+    assert_eq!(output, server.evaluate(b"password", info).unwrap());
+    assert_ne!(output, server.evaluate(b"password", b"2020-11-02").unwrap());
+
+    // The proof is bound to the info
+    assert!(matches!(
+        client.finalize(
+            b"password",
+            b"2020-11-02",
+            &blind,
+            &evaluated,
+            &blinded,
+            &proof
+        ),
+        Err(SharingError::OprfProofInvalid)
+    ));
+
+    // Batches share one proof
+    let inputs: [&[u8]; 2] = [b"alice", b"bob"];
+    let (blinds, blinded): (Vec<_>, Vec<_>) = inputs
+        .iter()
+        .map(|i| client.blind(&mut OsRng, i, info).unwrap())
+        .unzip();
+    let (evaluated, proof) = server
+        .blind_evaluate_batch(&mut OsRng, &blinded, info)
+        .unwrap();
+    let outputs = client
+        .finalize_batch(&inputs, info, &blinds, &evaluated, &blinded, &proof)
+        .unwrap();
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        assert_eq!(*output, server.evaluate(input, info).unwrap());
+    }
+    assert!(matches!(
+        server.blind_evaluate_batch(&mut OsRng, &[], info),
+        Err(SharingError::OprfBatchInvalid)
+    ));
+}
+
+fn main() {
+    println!("RFC 9497 ristretto255 test vectors");
+    ristretto_vectors();
+    println!("RFC 9497 P-256 test vectors");
+    p256_vectors();
+    println!("OPRF");
+    oprf::<Ristretto25519Scalar, Ristretto25519Point>();
+    oprf::<Secp256r1Scalar, Secp256r1Point>();
+    println!("VOPRF");
+    voprf::<Ristretto25519Scalar, Ristretto25519Point>();
+    voprf::<Secp256r1Scalar, Secp256r1Point>();
+    println!("POPRF");
+    poprf::<Ristretto25519Scalar, Ristretto25519Point>();
+    poprf::<Secp256r1Scalar, Secp256r1Point>();
+}
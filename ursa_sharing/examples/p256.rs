@@ -11,9 +11,12 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use generic_array::{typenum::U32, GenericArray};
+use generic_array::{
+    typenum::{U32, U33},
+    GenericArray,
+};
 use rand::{CryptoRng, RngCore};
-use ursa_sharing::{error::*, tests::*, Field, Group};
+use ursa_sharing::{error::*, tests::*, zeroize::Zeroize, Field, Group};
 
 use ff::Field as FFField;
 use p256::elliptic_curve::ops::Neg;
@@ -27,6 +30,12 @@ use p256::{
 
 struct P256Scalar(Scalar);
 
+impl Zeroize for P256Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Scalar::zero();
+    }
+}
+
 impl Group for P256Scalar {
     type Size = U32;
 
@@ -89,15 +98,17 @@ impl Field for P256Scalar {
         Self(Scalar::from(value as u64))
     }
 
-    fn scalar_div_assign(&mut self, rhs: &Self) {
-        self.0 *= rhs.0.invert().unwrap()
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = Option::<Scalar>::from(rhs.0.invert()).ok_or(SharingError::DivisionByZero)?;
+        self.0 *= inverse;
+        Ok(())
     }
 }
 
 struct P256Point(ProjectivePoint);
 
 impl Group<P256Scalar> for P256Point {
-    type Size = U32;
+    type Size = U33;
 
     fn zero() -> Self {
         Self(ProjectivePoint::identity())
@@ -146,8 +157,8 @@ impl Group<P256Scalar> for P256Point {
         self.0 *= rhs.0;
     }
 
-    fn to_bytes(&self) -> GenericArray<u8, U32> {
-        let mut c = [0u8; 32];
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut c = [0u8; 33];
         c.copy_from_slice(
             self.0
                 .to_affine()
@@ -155,7 +166,7 @@ impl Group<P256Scalar> for P256Point {
                 .to_bytes()
                 .as_ref(),
         );
-        c.into()
+        GenericArray::clone_from_slice(&c)
     }
 }
 
@@ -164,8 +175,37 @@ fn main() {
     split_invalid_args::<P256Scalar>();
     println!("Combine invalid fail");
     combine_invalid::<P256Scalar>();
+    println!("Split many success");
+    split_many::<P256Scalar>();
+    println!("Field division success");
+    field_division::<P256Scalar>();
+    println!("Refresh shares success");
+    refresh_shares::<P256Scalar>();
     println!("Combine single success");
     combine_single::<P256Scalar, P256Point>();
     println!("Combine combinations success");
     combine_all_combinations::<P256Scalar, P256Point>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<P256Scalar, P256Point>();
+    println!("Observer events success");
+    observer_events::<P256Scalar, P256Point>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<P256Scalar, P256Point>();
+    println!("Combine with context success");
+    combine_with_context::<P256Scalar, P256Point>();
+    println!("Share verification success");
+    share_verification::<P256Scalar, P256Point>();
+    println!("Cheater detection success");
+    cheater_detection::<P256Scalar, P256Point>();
+    println!("DKG rounds success");
+    dkg_rounds::<P256Scalar, P256Point>();
+    println!("Possession proofs success");
+    possession_proofs::<P256Scalar, P256Point>();
+    println!("Escrow success");
+    escrow_shares::<P256Scalar, P256Point>();
+    dleq_proofs::<P256Scalar, P256Point>();
+    println!("Transcript challenges success");
+    transcript_challenges::<P256Scalar, P256Point>();
+    println!("Strict deserialization success");
+    strict_deserialization::<P256Scalar, P256Point>();
 }
@@ -0,0 +1,70 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{error::SharingError, peks::*};
+
+fn search() {
+    let secret_key = SecretKey::new(&mut OsRng);
+    let public_key = secret_key.public_key();
+    let tags = [b"urgent".as_ref(), b"invoice", b"urgent", b"personal"]
+        .iter()
+        .map(|w| KeywordTag::new(&mut OsRng, &public_key, w).unwrap())
+        .collect::<Vec<_>>();
+
+    let urgent = secret_key.trapdoor(b"urgent");
+    assert_eq!(urgent.filter(&tags), vec![0, 2]);
+    assert_eq!(secret_key.trapdoor(b"personal").filter(&tags), vec![3]);
+    assert!(secret_key.trapdoor(b"Urgent").filter(&tags).is_empty());
+
+    // Tags of the same keyword don't look alike
+    assert_ne!(tags[0].to_bytes(), tags[2].to_bytes());
+
+    // Another receiver's trapdoor matches nothing
+    let other = SecretKey::new(&mut OsRng).trapdoor(b"urgent");
+    assert!(other.filter(&tags).is_empty());
+}
+
+fn serialization() {
+    let secret_key = SecretKey::new(&mut OsRng);
+    let secret_key = SecretKey::try_from(&secret_key.to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", secret_key), "SecretKey(..)");
+    let public_key = PublicKey::try_from(&secret_key.public_key().to_bytes()[..]).unwrap();
+    let tag = KeywordTag::new(&mut OsRng, &public_key, b"urgent").unwrap();
+    let bytes = tag.to_bytes();
+    let tag = KeywordTag::try_from(&bytes[..]).unwrap();
+    let trapdoor = Trapdoor::try_from(&secret_key.trapdoor(b"urgent").to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", trapdoor), "Trapdoor(..)");
+    assert!(trapdoor.matches(&tag));
+
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(!trapdoor.matches(&KeywordTag::try_from(&tampered[..]).unwrap()));
+    assert!(matches!(
+        KeywordTag::try_from(&bytes[1..]),
+        Err(SharingError::ShareInvalidEncoding)
+    ));
+    assert!(matches!(
+        SecretKey::try_from(&[0u8; 32][..]),
+        Err(SharingError::ShareInvalidSecret)
+    ));
+}
+
+fn main() {
+    println!("Search");
+    search();
+    println!("Serialization");
+    serialization();
+}
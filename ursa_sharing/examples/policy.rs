@@ -0,0 +1,173 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    policy::{Policy, PolicyShare},
+    ristretto::Ristretto25519Scalar,
+    secp256k1::Secp256k1Scalar,
+    Field,
+};
+
+const ADMINS: [u32; 3] = [1, 2, 3];
+const OPERATORS: [u32; 4] = [10, 11, 12, 13];
+
+/// 2 of 3 admins, or 1 admin and 3 of 4 operators
+fn admins_or_operators() -> Policy {
+    let admins = || ADMINS.iter().map(|id| Policy::holder(*id)).collect();
+    let operators = OPERATORS.iter().map(|id| Policy::holder(*id)).collect();
+    Policy::threshold(
+        1,
+        vec![
+            Policy::threshold(2, admins()).unwrap(),
+            Policy::threshold(
+                2,
+                vec![
+                    Policy::threshold(1, admins()).unwrap(),
+                    Policy::threshold(3, operators).unwrap(),
+                ],
+            )
+            .unwrap(),
+        ],
+    )
+    .unwrap()
+}
+
+fn select(shares: &[PolicyShare], holders: &[u32]) -> Vec<PolicyShare> {
+    shares
+        .iter()
+        .filter(|s| holders.contains(&s.holder()))
+        .cloned()
+        .collect()
+}
+
+fn hierarchy<S: Field>() {
+    let policy = admins_or_operators();
+    let secret = S::random(&mut OsRng);
+    let shares = policy.split_secret(&mut OsRng, &secret).unwrap();
+    assert_eq!(shares.len(), ADMINS.len() + OPERATORS.len());
+    // Admins are in both branches
+    assert_eq!(shares[0].points(), 2);
+    assert_eq!(shares[3].points(), 1);
+
+    for holders in &[&[1, 3][..], &[2, 10, 12, 13], &[1, 2, 3, 10, 11, 12, 13]] {
+        assert!(policy.is_satisfied_by(holders));
+        let recovered = policy
+            .combine_shares::<S>(&select(&shares, holders))
+            .unwrap();
+        assert_eq!(recovered.to_bytes(), secret.to_bytes());
+    }
+    for holders in &[&[1][..], &[2, 10, 11], &[10, 11, 12, 13]] {
+        assert!(!policy.is_satisfied_by(holders));
+        assert!(matches!(
+            policy.combine_shares::<S>(&select(&shares, holders)),
+            Err(SharingError::PolicyNotSatisfied)
+        ));
+    }
+
+    // A holder can't pass off another holder's points as its own
+    let mut forged = shares[0].to_bytes();
+    forged[32..36].copy_from_slice(&2u32.to_be_bytes());
+    let forged = PolicyShare::try_from(forged.as_slice()).unwrap();
+    assert!(matches!(
+        policy.combine_shares::<S>(&[forged, shares[2].clone()]),
+        Err(SharingError::PolicyMismatch)
+    ));
+}
+
+fn weighted<S: Field>() {
+    // The owner alone, or any two of three trustees
+    let policy = Policy::threshold(
+        2,
+        vec![
+            Policy::weighted(1, 2),
+            Policy::holder(2),
+            Policy::holder(3),
+            Policy::holder(4),
+        ],
+    )
+    .unwrap();
+    let secret = S::random(&mut OsRng);
+    let shares = policy.split_secret(&mut OsRng, &secret).unwrap();
+    assert_eq!(shares[0].points(), 2);
+    for holders in &[&[1][..], &[2, 4], &[3, 1]] {
+        let recovered = policy
+            .combine_shares::<S>(&select(&shares, holders))
+            .unwrap();
+        assert_eq!(recovered.to_bytes(), secret.to_bytes());
+    }
+    assert!(policy.combine_shares::<S>(&select(&shares, &[3])).is_err());
+}
+
+fn serialization<S: Field>() {
+    let policy = admins_or_operators();
+    let secret = S::random(&mut OsRng);
+    let shares = policy.split_secret(&mut OsRng, &secret).unwrap();
+
+    // The policy is stored with the shares
+    let stored = policy.to_bytes();
+    let policy = Policy::try_from(stored.as_slice()).unwrap();
+    assert_eq!(policy, admins_or_operators());
+    let shares = shares
+        .iter()
+        .map(|s| PolicyShare::try_from(s.to_bytes().as_slice()).unwrap())
+        .collect::<Vec<_>>();
+    let recovered = policy
+        .combine_shares::<S>(&select(&shares, &[3, 11, 12, 13]))
+        .unwrap();
+    assert_eq!(recovered.to_bytes(), secret.to_bytes());
+
+    // Shares only combine under the policy they were split with
+    let other =
+        Policy::threshold(2, ADMINS.iter().map(|id| Policy::holder(*id)).collect()).unwrap();
+    assert_ne!(other.digest(), policy.digest());
+    assert!(matches!(
+        other.combine_shares::<S>(&select(&shares, &[1, 2])),
+        Err(SharingError::PolicyMismatch)
+    ));
+
+    assert!(Policy::try_from(&stored[..stored.len() - 1]).is_err());
+    let encoded = shares[0].to_bytes();
+    assert!(PolicyShare::try_from(&encoded[..encoded.len() - 1]).is_err());
+}
+
+fn invalid() {
+    assert!(matches!(
+        Policy::threshold(3, vec![Policy::holder(1), Policy::holder(2)]),
+        Err(SharingError::PolicyInvalid)
+    ));
+    assert!(Policy::threshold(0, vec![Policy::holder(1)]).is_err());
+    assert!(Policy::threshold(1, vec![]).is_err());
+    assert!(Policy::threshold(1, vec![Policy::weighted(1, 0)]).is_err());
+    assert!(Policy::threshold(1, vec![Policy::holder(0)]).is_err());
+    let mut deep = Policy::threshold(1, vec![Policy::holder(1)]).unwrap();
+    for _ in 0..ursa_sharing::policy::MAX_DEPTH {
+        deep = Policy::Threshold {
+            threshold: 1,
+            members: vec![deep],
+        };
+    }
+    assert!(Policy::try_from(deep.to_bytes().as_slice()).is_err());
+}
+
+fn main() {
+    hierarchy::<Ristretto25519Scalar>();
+    hierarchy::<Secp256k1Scalar>();
+    weighted::<Ristretto25519Scalar>();
+    weighted::<Secp256k1Scalar>();
+    serialization::<Ristretto25519Scalar>();
+    serialization::<Secp256k1Scalar>();
+    invalid();
+}
@@ -0,0 +1,196 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    pre::*,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    Field, Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+fn key_pair<S: Field, R: Group<S>>(g: &R) -> (S, R) {
+    let secret = S::random(&mut OsRng);
+    let mut public = R::from_bytes(g.to_bytes()).unwrap();
+    public.scalar_mul_assign(&secret);
+    (secret, public)
+}
+
+fn reencryption<S: Field, R: Group<S>>(g: R) {
+    let (alice, alice_public) = key_pair::<S, R>(&g);
+    let (bob, bob_public) = key_pair::<S, R>(&g);
+    let ciphertext = encrypt(&mut OsRng, &g, &alice_public, b"shared file").unwrap();
+    assert_eq!(ciphertext.decrypt(&g, &alice).unwrap(), b"shared file");
+    assert!(matches!(
+        ciphertext.decrypt(&g, &bob),
+        Err(SharingError::PreDecryptionFailed)
+    ));
+
+    let key_fragments =
+        generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, THRESHOLD, LIMIT).unwrap();
+    assert_eq!(key_fragments.len(), LIMIT);
+    let fragments = key_fragments
+        .iter()
+        .map(|k| k.reencrypt(&mut OsRng, &g, ciphertext.capsule()).unwrap())
+        .collect::<Vec<_>>();
+    for (fragment, key) in fragments.iter().zip(key_fragments.iter()) {
+        fragment.verify(&g, ciphertext.capsule()).unwrap();
+        assert_eq!(fragment.identifier(), key.identifier());
+        assert_eq!(
+            fragment.commitment().to_bytes(),
+            key.commitment().to_bytes()
+        );
+    }
+
+    // Any threshold of the fragments, in any order
+    for subset in [&[0, 1, 2][..], &[4, 2, 0], &[1, 3, 4, 0], &[0, 1, 2, 3, 4]] {
+        let chosen = subset
+            .iter()
+            .map(|i| fragments[*i].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decrypt_reencrypted(&g, &bob, &ciphertext, &chosen).unwrap(),
+            b"shared file"
+        );
+    }
+    assert!(matches!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &fragments[..THRESHOLD - 1]),
+        Err(SharingError::PreDecryptionFailed)
+    ));
+    assert!(matches!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &[]),
+        Err(SharingError::PreDecryptionFailed)
+    ));
+    let duplicated = vec![
+        fragments[0].clone(),
+        fragments[1].clone(),
+        fragments[1].clone(),
+    ];
+    assert!(matches!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &duplicated),
+        Err(SharingError::ShareDuplicateIdentifier)
+    ));
+
+    // Only Bob can open the fragments
+    let (carol, _) = key_pair::<S, R>(&g);
+    assert!(matches!(
+        decrypt_reencrypted(&g, &carol, &ciphertext, &fragments),
+        Err(SharingError::PreDecryptionFailed)
+    ));
+
+    // Fragments of another delegation can't be mixed in
+    let other = generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, THRESHOLD, LIMIT)
+        .unwrap()
+        .remove(3)
+        .reencrypt(&mut OsRng, &g, ciphertext.capsule())
+        .unwrap();
+    let mixed = vec![fragments[0].clone(), fragments[1].clone(), other];
+    assert!(matches!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &mixed),
+        Err(SharingError::PreInvalidFragment)
+    ));
+
+    // A fragment of another capsule doesn't verify
+    let second = encrypt(&mut OsRng, &g, &alice_public, b"other file").unwrap();
+    let wrong = key_fragments[2]
+        .reencrypt(&mut OsRng, &g, second.capsule())
+        .unwrap();
+    assert!(matches!(
+        wrong.verify(&g, ciphertext.capsule()),
+        Err(SharingError::PreInvalidFragment)
+    ));
+    let mixed = vec![fragments[0].clone(), fragments[1].clone(), wrong];
+    assert!(matches!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &mixed),
+        Err(SharingError::PreInvalidFragment)
+    ));
+
+    assert!(matches!(
+        generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, 4, 3),
+        Err(SharingError::ShareLimitLessThanThreshold)
+    ));
+}
+
+fn serialization<S: Field, R: Group<S>>(g: R) {
+    let (alice, alice_public) = key_pair::<S, R>(&g);
+    let (bob, bob_public) = key_pair::<S, R>(&g);
+    let ciphertext = encrypt(&mut OsRng, &g, &alice_public, b"record").unwrap();
+    let bytes = ciphertext.to_bytes();
+    let ciphertext = Ciphertext::<S, R>::try_from(&bytes[..]).unwrap();
+    assert_eq!(ciphertext.to_bytes(), bytes);
+
+    let fragments = generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, 2, 2)
+        .unwrap()
+        .iter()
+        .map(|k| KeyFragment::<S, R>::try_from(&k.to_bytes()[..]).unwrap())
+        .map(|k| {
+            let fragment = k.reencrypt(&mut OsRng, &g, ciphertext.capsule()).unwrap();
+            CapsuleFragment::try_from(&fragment.to_bytes()[..]).unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        decrypt_reencrypted(&g, &bob, &ciphertext, &fragments).unwrap(),
+        b"record"
+    );
+    let key = generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, 2, 2)
+        .unwrap()
+        .remove(0);
+    assert_eq!(format!("{:?}", key), "KeyFragment(1, ..)");
+
+    // The sealed data and the capsule are both authenticated
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    let tampered = Ciphertext::<S, R>::try_from(&tampered[..]).unwrap();
+    assert!(matches!(
+        tampered.decrypt(&g, &alice),
+        Err(SharingError::PreDecryptionFailed)
+    ));
+    let mut tampered = bytes.clone();
+    let s = 1 + 2 * R::from_bytes(g.to_bytes()).unwrap().to_bytes().len();
+    tampered[s + 5] ^= 1;
+    if let Ok(tampered) = Ciphertext::<S, R>::try_from(&tampered[..]) {
+        assert!(matches!(
+            tampered.decrypt(&g, &alice),
+            Err(SharingError::PreInvalidCapsule)
+        ));
+        let key = generate_key_fragments(&mut OsRng, &g, &alice, &bob_public, 1, 1)
+            .unwrap()
+            .remove(0);
+        assert!(matches!(
+            key.reencrypt(&mut OsRng, &g, tampered.capsule()),
+            Err(SharingError::PreInvalidCapsule)
+        ));
+    }
+
+    let mut bytes = bytes;
+    bytes[0] = 2;
+    assert!(matches!(
+        Ciphertext::<S, R>::try_from(&bytes[..]),
+        Err(SharingError::ShareUnsupportedVersion(2))
+    ));
+}
+
+fn main() {
+    println!("Re-encryption");
+    reencryption::<Secp256k1Scalar, Secp256k1Point>(Secp256k1Point::generator());
+    reencryption::<Ristretto25519Scalar, Ristretto25519Point>(Ristretto25519Point::generator());
+    println!("Serialization");
+    serialization::<Secp256k1Scalar, Secp256k1Point>(Secp256k1Point::generator());
+    serialization::<Ristretto25519Scalar, Ristretto25519Point>(Ristretto25519Point::generator());
+}
@@ -0,0 +1,121 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    receipt::{assignment, Dealer, Opening, Receipt},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    Field, Group, Strictness,
+};
+
+const SESSION: &[u8] = b"example dealing 7";
+
+fn assignments() {
+    // The assignment is fixed by the session and is a permutation of the payloads
+    let a = assignment(SESSION, 5);
+    assert_eq!(a, assignment(SESSION, 5));
+    let mut indices = a.indices().to_vec();
+    indices.sort_unstable();
+    assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+}
+
+fn disputes<S: Field, R: Group<S>>() {
+    // Any generator works, the dealer only needs the discrete log of its public key
+    let g = R::random(&mut OsRng);
+    let dealer = Dealer::<S, R>::random(&mut OsRng, &g);
+    let recipients: Vec<&[u8]> = vec![b"alice", b"bob", b"carol", b"dave"];
+    let shares: Vec<Vec<u8>> = (0..recipients.len())
+        .map(|i| format!("share {}", i).into_bytes())
+        .collect();
+    let receipts = dealer
+        .distribute(&mut OsRng, SESSION, &recipients, &shares)
+        .unwrap();
+    let order = assignment(SESSION, recipients.len());
+    assert_eq!(
+        receipts.iter().map(|r| r.0).collect::<Vec<_>>(),
+        order.indices()
+    );
+
+    // Every recipient can prove what it was given
+    for (recipient, (index, receipt, opening)) in recipients.iter().zip(&receipts) {
+        assert_eq!(receipt.recipient(), *recipient);
+        assert_eq!(receipt.session(), SESSION);
+        assert_eq!(receipt.index(), *index);
+        receipt.verify(&g, dealer.public_key()).unwrap();
+        receipt.verify_opening(&shares[*index], opening).unwrap();
+        receipt.verify_assignment(&recipients).unwrap();
+    }
+
+    // A recipient can't claim another share, and receipts can't be moved or forged
+    let (index, receipt, opening) = &receipts[0];
+    let other = (index + 1) % shares.len();
+    assert!(matches!(
+        receipt.verify_opening(&shares[other], opening),
+        Err(SharingError::ReceiptOpeningInvalid)
+    ));
+    let wrong = Opening::try_from(&[0u8; 32][..]).unwrap();
+    assert!(receipt.verify_opening(&shares[*index], &wrong).is_err());
+    let impostor = Dealer::<S, R>::random(&mut OsRng, &g);
+    assert!(matches!(
+        receipt.verify(&g, impostor.public_key()),
+        Err(SharingError::ReceiptSignatureInvalid)
+    ));
+    let mut swapped = recipients.clone();
+    swapped.swap(0, 1);
+    assert!(matches!(
+        receipt.verify_assignment(&swapped),
+        Err(SharingError::ReceiptAssignmentInvalid)
+    ));
+    let mut bytes = receipt.to_bytes();
+    bytes[4 + SESSION.len() + 4] ^= 1;
+    let moved = Receipt::<S, R>::try_from(&bytes[..]).unwrap();
+    assert!(moved.verify(&g, dealer.public_key()).is_err());
+
+    // A dealer can't hand out receipts for an assignment it chose
+    let (forced, _) = dealer
+        .issue(&mut OsRng, SESSION, b"alice", other, &shares[other])
+        .unwrap();
+    forced.verify(&g, dealer.public_key()).unwrap();
+    if order.indices()[0] != other {
+        assert!(forced.verify_assignment(&recipients).is_err());
+    }
+
+    // Bad distributions are rejected
+    assert!(matches!(
+        dealer.distribute(&mut OsRng, SESSION, &[b"alice", b"alice"], &shares[..2]),
+        Err(SharingError::ReceiptAssignmentInvalid)
+    ));
+    assert!(dealer
+        .distribute(&mut OsRng, SESSION, &recipients, &shares[..2])
+        .is_err());
+
+    // Receipts and openings survive encoding
+    let bytes = receipt.to_bytes();
+    let decoded = Receipt::<S, R>::from_bytes_with(&bytes, Strictness::Strict).unwrap();
+    assert_eq!(decoded.clone().to_bytes(), bytes);
+    decoded.verify(&g, dealer.public_key()).unwrap();
+    let opening = Opening::try_from(&opening.to_bytes()[..]).unwrap();
+    decoded.verify_opening(&shares[*index], &opening).unwrap();
+    assert!(Receipt::<S, R>::try_from(&bytes[1..]).is_err());
+    assert!(Receipt::<S, R>::try_from(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Opening::try_from(&[0u8; 31][..]).is_err());
+}
+
+fn main() {
+    assignments();
+    disputes::<Secp256k1Scalar, Secp256k1Point>();
+    disputes::<Ristretto25519Scalar, Ristretto25519Point>();
+}
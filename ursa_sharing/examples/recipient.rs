@@ -0,0 +1,138 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    ed25519::{Ed25519Point, Ed25519Scalar},
+    error::SharingError,
+    feldman::Scheme as FeldmanScheme,
+    recipient::{EncryptedShare, RecipientPublicKey, RecipientSecretKey},
+    shamir::{Scheme, Share},
+    Group,
+};
+
+fn recipients() -> Vec<RecipientSecretKey> {
+    vec![
+        RecipientSecretKey::X25519(x25519_dalek::StaticSecret::new(OsRng)),
+        RecipientSecretKey::P256(p256::SecretKey::random(OsRng)),
+        RecipientSecretKey::X25519(x25519_dalek::StaticSecret::new(OsRng)),
+    ]
+}
+
+fn split_encrypted_combine() {
+    let keys = recipients();
+    let public_keys = keys
+        .iter()
+        .map(|k| k.public_key())
+        .collect::<Vec<RecipientPublicKey>>();
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+
+    assert!(scheme
+        .split_encrypted(&mut OsRng, &secret, &public_keys[..2])
+        .is_err());
+
+    let encrypted = scheme
+        .split_encrypted(&mut OsRng, &secret, public_keys.as_slice())
+        .unwrap();
+    let shares = encrypted
+        .iter()
+        .zip(keys.iter())
+        .map(|(e, k)| {
+            let bytes = e.to_bytes();
+            EncryptedShare::try_from(bytes.as_slice())
+                .unwrap()
+                .decrypt(k)
+                .unwrap()
+        })
+        .collect::<Vec<Share>>();
+    let res = scheme.combine_shares::<Ed25519Scalar, Ed25519Scalar>(&shares[1..]);
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+}
+
+fn feldman_split_encrypted_verify() {
+    let keys = recipients();
+    let public_keys = keys
+        .iter()
+        .map(|k| k.public_key())
+        .collect::<Vec<RecipientPublicKey>>();
+    let scheme = FeldmanScheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+
+    let (verifier, encrypted) = scheme
+        .split_encrypted::<Ed25519Scalar, Ed25519Point>(
+            &mut OsRng,
+            &secret,
+            None,
+            public_keys.as_slice(),
+        )
+        .unwrap();
+    for (e, k) in encrypted.iter().zip(keys.iter()) {
+        let share = e.decrypt(k).unwrap();
+        assert!(scheme.verify_share(&share, &verifier).is_ok());
+    }
+}
+
+fn decrypt_rejects_tampering() {
+    let keys = recipients();
+    let public_keys = keys
+        .iter()
+        .map(|k| k.public_key())
+        .collect::<Vec<RecipientPublicKey>>();
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let encrypted = scheme
+        .split_encrypted(&mut OsRng, &secret, public_keys.as_slice())
+        .unwrap();
+
+    // Another recipient of the same key type
+    match encrypted[0].decrypt(&keys[2]) {
+        Err(SharingError::ShareDecryptionFailed) => {}
+        r => panic!("expected a decryption error, found {:?}", r),
+    }
+    // A key of the wrong type
+    match encrypted[0].decrypt(&keys[1]) {
+        Err(SharingError::ShareInvalidRecipientKey) => {}
+        r => panic!("expected a recipient key error, found {:?}", r),
+    }
+
+    for e in &encrypted {
+        // Flipped ciphertext bit
+        let mut bytes = e.to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let tampered = EncryptedShare::try_from(bytes.as_slice()).unwrap();
+        assert!(tampered.decrypt(&keys[0]).is_err());
+        assert!(tampered.decrypt(&keys[1]).is_err());
+
+        // Relabelled identifier
+        let mut bytes = e.to_bytes();
+        bytes[4] ^= 7;
+        let relabelled = EncryptedShare::try_from(bytes.as_slice()).unwrap();
+        assert!(keys.iter().all(|k| relabelled.decrypt(k).is_err()));
+
+        // Truncated
+        let bytes = e.to_bytes();
+        assert!(EncryptedShare::try_from(&bytes[..20]).is_err());
+    }
+}
+
+fn main() {
+    println!("Split encrypted combine success");
+    split_encrypted_combine();
+    println!("Feldman split encrypted verify success");
+    feldman_split_encrypted_verify();
+    println!("Decrypt rejects tampering");
+    decrypt_rejects_tampering();
+}
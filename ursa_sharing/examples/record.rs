@@ -0,0 +1,272 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    dkg::{Complaint, DkgOutput, Participant, Round1Share, Transcript},
+    error::{SharingError, SharingResult},
+    frost::{Coordinator, SignatureShare, SignerRound1, SigningCommitments},
+    record::{Recordable, Recording},
+    ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar},
+    shamir::Scheme as ShamirScheme,
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+type Output = DkgOutput<Scalar, Point>;
+
+/// Run a DKG where participant 2's share to 4 is corrupted in transit,
+/// recording every public message
+fn dkg(recording: &mut Recording) -> Vec<Output> {
+    let g = Point::generator();
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(&mut OsRng, id, THRESHOLD, LIMIT, &g).unwrap();
+        recording.record(&b);
+        participants.push(p);
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    let i = shares
+        .iter()
+        .position(|s| s.sender() == 2 && s.receiver() == 4)
+        .unwrap();
+    let mut bytes = shares[i].to_bytes();
+    bytes[12] ^= 0xff;
+    shares[i] = Round1Share::try_from(bytes.as_slice()).unwrap();
+
+    let mut complaints = Vec::new();
+    for p in participants.iter_mut() {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        complaints.extend(p.receive_round1(&broadcasts, &mine).unwrap());
+    }
+    assert_eq!(
+        complaints,
+        vec![Complaint {
+            accuser: 4,
+            accused: 2
+        }]
+    );
+    complaints.iter().for_each(|c| recording.record(c));
+    let justifications = participants
+        .iter()
+        .flat_map(|p| p.justify(&complaints))
+        .collect::<Vec<_>>();
+    justifications.iter().for_each(|j| recording.record(j));
+
+    participants
+        .iter_mut()
+        .map(|p| p.finalize(&complaints, &justifications).unwrap())
+        .collect()
+}
+
+/// Sign with signers 1, 3 and 5, recording the commitments and signature shares
+fn frost(outputs: &[Output], message: &[u8], recording: &mut Recording) {
+    let verifier = outputs[0].verifier();
+    let mut states = Vec::new();
+    let mut commitments = Vec::new();
+    for o in outputs.iter().filter(|o| o.share().identifier() % 2 == 1) {
+        let (state, c) = SignerRound1::new(&mut OsRng, o.share(), verifier).unwrap();
+        recording.record(&c);
+        states.push(state);
+        commitments.push(c);
+    }
+    for state in states {
+        let share = state.sign(message, &commitments).unwrap();
+        recording.record(&share);
+    }
+}
+
+/// A server's partial decryption `c1^s_i` of an ElGamal ciphertext
+struct PartialDecryption {
+    identifier: u32,
+    point: Point,
+}
+
+impl Recordable for PartialDecryption {
+    const KIND: &'static str = "elgamal partial decryption";
+
+    fn sender(&self) -> u32 {
+        self.identifier
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        let mut o = self.identifier.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.point.to_bytes());
+        o
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        if data.len() < 4 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&data[..4]);
+        Ok(Self {
+            identifier: u32::from_be_bytes(identifier),
+            point: Point::from_bytes(&data[4..])?,
+        })
+    }
+}
+
+/// Decrypt `(c1, c2)` with servers 2, 3 and 4, recording their partial decryptions
+fn decrypt(outputs: &[Output], c1: &Point, recording: &mut Recording) {
+    for o in &outputs[1..4] {
+        let mut point = *c1;
+        point.scalar_mul_assign(&Scalar::from_bytes(o.share().value()).unwrap());
+        recording.record(&PartialDecryption {
+            identifier: o.share().identifier(),
+            point,
+        });
+    }
+}
+
+/// Decode, change and re-encode an exported recording
+fn tamper(exported: &[u8], f: impl FnOnce(&mut Vec<Value>)) -> Vec<u8> {
+    let mut value = serde_cbor::from_slice::<Value>(exported).unwrap();
+    if let Value::Map(map) = &mut value {
+        if let Some(Value::Array(entries)) = map.get_mut(&Value::Text("entries".to_string())) {
+            f(entries);
+        }
+    }
+    serde_cbor::to_vec(&value).unwrap()
+}
+
+fn main() {
+    let mut dkg_recording = Recording::new(b"ceremony 7 dkg");
+    let outputs = dkg(&mut dkg_recording);
+    let public_key = outputs[0].public_key();
+    // Only the broadcasts, the complaint and the justification are recorded
+    assert_eq!(dkg_recording.entries().len(), LIMIT + 2);
+
+    let message = b"block 1024";
+    let mut signing_recording = Recording::new(b"ceremony 7 signing block 1024");
+    frost(&outputs, message, &mut signing_recording);
+
+    let plaintext = Point::random(&mut OsRng);
+    let r = Scalar::random(&mut OsRng);
+    let mut c1 = Point::generator();
+    c1.scalar_mul_assign(&r);
+    let mut c2 = public_key;
+    c2.scalar_mul_assign(&r);
+    c2.add_assign(&plaintext);
+    let mut decryption_recording = Recording::new(b"ceremony 7 decryption");
+    decrypt(&outputs, &c1, &mut decryption_recording);
+
+    // The auditor imports the recordings and checks them against the published heads
+    let dkg_export = dkg_recording.to_cbor();
+    let audited = Recording::from_cbor(&dkg_export).unwrap();
+    assert_eq!(audited, dkg_recording);
+    assert_eq!(audited.head(), dkg_recording.head());
+    assert_eq!(audited.entries()[LIMIT + 1].receiver(), Some(4));
+
+    // and replays the DKG verdict and the joint key
+    let transcript =
+        Transcript::<Scalar, Point>::replay(Point::generator(), THRESHOLD, LIMIT, &audited)
+            .unwrap();
+    assert!(transcript.blame().is_empty());
+    assert_eq!(
+        transcript.public_key().unwrap().to_bytes(),
+        public_key.to_bytes()
+    );
+    let verifier = transcript.verifier().unwrap();
+
+    // the signature
+    let audited = Recording::from_cbor(&signing_recording.to_cbor()).unwrap();
+    let commitments = audited
+        .messages::<SigningCommitments<Scalar, Point>>()
+        .unwrap();
+    let shares = audited.messages::<SignatureShare<Scalar>>().unwrap();
+    assert_eq!(
+        shares.iter().map(|s| s.sender()).collect::<Vec<_>>(),
+        vec![1, 3, 5]
+    );
+    let coordinator = Coordinator::new(&verifier, message, &commitments).unwrap();
+    let signature = coordinator.aggregate(&shares).unwrap();
+    assert!(signature.verify(&public_key, message).is_ok());
+
+    // and the decryption
+    let audited = Recording::from_cbor(&decryption_recording.to_cbor()).unwrap();
+    let points = audited
+        .messages::<PartialDecryption>()
+        .unwrap()
+        .iter()
+        .map(|p| (p.identifier, p.point))
+        .collect::<Vec<_>>();
+    let shared = ShamirScheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .combine_commitments(&points)
+        .unwrap();
+    let mut decrypted = c2;
+    decrypted.sub_assign(&shared);
+    assert_eq!(decrypted.to_bytes(), plaintext.to_bytes());
+
+    // A changed, reordered or renumbered entry breaks the chain
+    let changed = tamper(&dkg_export, |entries| {
+        if let Value::Map(entry) = &mut entries[LIMIT] {
+            entry.insert(Value::Text("sender".to_string()), Value::Integer(3));
+        }
+    });
+    let reordered = tamper(&dkg_export, |entries| entries.swap(0, 1));
+    let renumbered = tamper(&dkg_export, |entries| {
+        if let Value::Map(entry) = &mut entries[0] {
+            entry.insert(Value::Text("sequence".to_string()), Value::Integer(1));
+        }
+    });
+    for data in &[changed, reordered, renumbered] {
+        match Recording::from_cbor(data) {
+            Err(SharingError::RecordingInvalid) => {}
+            r => panic!("expected an invalid recording, found {:?}", r),
+        }
+    }
+    // A truncated recording is valid but no longer matches the head
+    let truncated = tamper(&dkg_export, |entries| {
+        entries.pop();
+    });
+    let truncated = Recording::from_cbor(&truncated).unwrap();
+    assert_ne!(truncated.head(), dkg_recording.head());
+    // so is a recording of another session
+    let mut other = Recording::new(b"ceremony 8 dkg");
+    let mut relabeled = Recording::new(b"ceremony 7 dkg");
+    assert_ne!(other.head(), relabeled.head());
+    other.record(&Complaint {
+        accuser: 4,
+        accused: 2,
+    });
+    relabeled.record(&Complaint {
+        accuser: 4,
+        accused: 2,
+    });
+    assert_ne!(other.head(), relabeled.head());
+
+    // Messages are only replayed as their own kind
+    assert!(audited.messages::<Complaint>().unwrap().is_empty());
+    assert!(Recording::from_cbor(&dkg_export[1..]).is_err());
+    let mut newer = dkg_export.clone();
+    let i = newer.windows(8).position(|w| w == b"version\x01").unwrap();
+    newer[i + 7] = 2;
+    match Recording::from_cbor(&newer) {
+        Err(SharingError::RecordingVersion(2)) => {}
+        r => panic!("expected an unsupported version, found {:?}", r),
+    }
+}
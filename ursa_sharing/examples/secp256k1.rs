@@ -0,0 +1,314 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::{rngs::OsRng, Rng};
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    secp256k1::{
+        dkg::{
+            Blame, Complaint, Justification, Misbehavior, Participant, Round1Broadcast,
+            Round1Share, Transcript,
+        },
+        Secp256k1Point, Secp256k1Scalar,
+    },
+    shamir::{Scheme, Share},
+    tests::*,
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+/// Round one messages for every participant
+fn round1() -> (Vec<Participant>, Vec<Round1Broadcast>, Vec<Round1Share>) {
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(&mut OsRng, id, THRESHOLD, LIMIT).unwrap();
+        participants.push(p);
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    (participants, broadcasts, shares)
+}
+
+/// Replace the value of a private share with garbage
+fn corrupt_share(share: &Round1Share) -> Round1Share {
+    let mut bytes = share.to_bytes();
+    bytes[12] ^= 0xff;
+    Round1Share::try_from(bytes.as_slice()).unwrap()
+}
+
+/// Run the remaining rounds delivering every message
+fn complete(
+    participants: &mut [Participant],
+    broadcasts: &[Round1Broadcast],
+    shares: &[Round1Share],
+) -> (Vec<Complaint>, Vec<Justification>) {
+    let mut complaints = Vec::new();
+    for p in participants.iter_mut() {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        complaints.extend(p.receive_round1(broadcasts, mine.as_slice()).unwrap());
+    }
+    let justifications = participants
+        .iter()
+        .flat_map(|p| p.justify(complaints.as_slice()))
+        .collect::<Vec<_>>();
+    (complaints, justifications)
+}
+
+fn dkg_honest() {
+    let (mut participants, broadcasts, shares) = round1();
+    let (complaints, justifications) = complete(&mut participants, &broadcasts, &shares);
+    assert!(complaints.is_empty());
+
+    let outputs = participants
+        .iter_mut()
+        .map(|p| p.finalize(&complaints, &justifications).unwrap())
+        .collect::<Vec<_>>();
+    let public_key = outputs[0].public_key();
+    for o in &outputs {
+        assert_eq!(o.public_key().to_bytes(), public_key.to_bytes());
+        let mut vs = Secp256k1Point::generator();
+        vs.scalar_mul_assign(&Secp256k1Scalar::from_bytes(o.share().value()).unwrap());
+        assert_eq!(vs.to_bytes(), o.verification_share().to_bytes());
+    }
+
+    // Any threshold of shares gives the secret key of the public key
+    let scheme = Scheme::new(THRESHOLD, LIMIT).unwrap();
+    let shares = outputs
+        .iter()
+        .map(|o| o.share().clone())
+        .collect::<Vec<Share>>();
+    for start in 0..=LIMIT - THRESHOLD {
+        let secret = scheme
+            .combine_shares::<Secp256k1Scalar, Secp256k1Scalar>(&shares[start..start + THRESHOLD])
+            .unwrap();
+        let mut pk = Secp256k1Point::generator();
+        pk.scalar_mul_assign(&secret);
+        assert_eq!(pk.to_bytes(), public_key.to_bytes());
+    }
+    // Or combined in the exponent without the secret
+    let lifted = shares
+        .iter()
+        .map(|s| s.lift::<Secp256k1Scalar, Secp256k1Point>(&Secp256k1Point::generator()))
+        .collect::<Result<Vec<Share>, _>>()
+        .unwrap();
+    let pk = scheme
+        .combine_shares::<Secp256k1Scalar, Secp256k1Point>(&lifted[..THRESHOLD])
+        .unwrap();
+    assert_eq!(pk.to_bytes(), public_key.to_bytes());
+}
+
+fn dkg_resolved_complaint() {
+    let (mut participants, broadcasts, mut shares) = round1();
+    // Participant 2's share to 4 is lost in transit and replaced
+    let i = shares
+        .iter()
+        .position(|s| s.sender() == 2 && s.receiver() == 4)
+        .unwrap();
+    shares[i] = corrupt_share(&shares[i]);
+    let (complaints, justifications) = complete(&mut participants, &broadcasts, &shares);
+    assert_eq!(
+        complaints,
+        vec![Complaint {
+            accuser: 4,
+            accused: 2
+        }]
+    );
+    assert_eq!(justifications.len(), 1);
+
+    let outputs = participants
+        .iter_mut()
+        .map(|p| p.finalize(&complaints, &justifications).unwrap())
+        .collect::<Vec<_>>();
+    let scheme = Scheme::new(THRESHOLD, LIMIT).unwrap();
+    let shares = outputs
+        .iter()
+        .map(|o| o.share().clone())
+        .collect::<Vec<Share>>();
+    let secret = scheme
+        .combine_shares::<Secp256k1Scalar, Secp256k1Scalar>(&shares[2..])
+        .unwrap();
+    let mut pk = Secp256k1Point::generator();
+    pk.scalar_mul_assign(&secret);
+    assert_eq!(pk.to_bytes(), outputs[0].public_key().to_bytes());
+}
+
+fn dkg_blames_cheaters() {
+    let (mut participants, broadcasts, mut shares) = round1();
+    // Participant 3 deals a bad share to 1 then refuses to justify it
+    let i = shares
+        .iter()
+        .position(|s| s.sender() == 3 && s.receiver() == 1)
+        .unwrap();
+    shares[i] = corrupt_share(&shares[i]);
+    let (complaints, justifications) = complete(&mut participants, &broadcasts, &shares);
+    let justifications = justifications
+        .into_iter()
+        .filter(|j| j.sender() != 3)
+        .collect::<Vec<_>>();
+    for p in participants.iter_mut() {
+        match p.finalize(&complaints, &justifications) {
+            Err(SharingError::DkgAbort(3)) => {}
+            r => panic!("expected participant 3 to be blamed, found {:?}", r),
+        }
+        assert_eq!(
+            p.transcript().blame(),
+            vec![Blame {
+                participant: 3,
+                misbehavior: Misbehavior::MissingJustification
+            }]
+        );
+    }
+
+    // Participant 5 justifies with another bad share
+    let (mut participants, broadcasts, mut shares) = round1();
+    let i = shares
+        .iter()
+        .position(|s| s.sender() == 5 && s.receiver() == 2)
+        .unwrap();
+    shares[i] = corrupt_share(&shares[i]);
+    let (complaints, justifications) = complete(&mut participants, &broadcasts, &shares);
+    let justifications = justifications
+        .iter()
+        .map(|j| {
+            let mut bytes = j.to_bytes();
+            bytes[20] ^= 1;
+            Justification::try_from(bytes.as_slice()).unwrap()
+        })
+        .collect::<Vec<_>>();
+    match participants[0].finalize(&complaints, &justifications) {
+        Err(SharingError::DkgAbort(5)) => {}
+        r => panic!("expected participant 5 to be blamed, found {:?}", r),
+    }
+    assert_eq!(
+        participants[0].transcript().blame()[0].misbehavior,
+        Misbehavior::InvalidJustification
+    );
+
+    // Participant 1 sends a broadcast with an invalid proof and 4 sends nothing
+    let (mut participants, mut broadcasts, shares) = round1();
+    let mut bytes = broadcasts[0].to_bytes();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    broadcasts[0] = Round1Broadcast::try_from(bytes.as_slice()).unwrap();
+    broadcasts.remove(3);
+    // Participants other than 1 and 4 never saw their real broadcasts
+    let mut p = participants.remove(1);
+    let mine = shares
+        .iter()
+        .filter(|s| s.receiver() == 2)
+        .cloned()
+        .collect::<Vec<_>>();
+    let complaints = p.receive_round1(&broadcasts, &mine).unwrap();
+    assert!(complaints.is_empty());
+    assert!(p.finalize(&complaints, &[]).is_err());
+    let blame = p.transcript().blame();
+    assert_eq!(
+        blame,
+        vec![
+            Blame {
+                participant: 1,
+                misbehavior: Misbehavior::InvalidProof
+            },
+            Blame {
+                participant: 4,
+                misbehavior: Misbehavior::MissingBroadcast
+            }
+        ]
+    );
+}
+
+fn dkg_transcript_replay() {
+    let (mut participants, broadcasts, mut shares) = round1();
+    let i = OsRng.gen_range(0, shares.len());
+    shares[i] = corrupt_share(&shares[i]);
+    let (complaints, justifications) = complete(&mut participants, &broadcasts, &shares);
+    let output = participants[0]
+        .finalize(&complaints, &justifications)
+        .unwrap();
+
+    let bytes = participants[0].transcript().to_bytes();
+    let transcript = Transcript::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(transcript.to_bytes(), bytes);
+    assert!(transcript.blame().is_empty());
+    assert_eq!(transcript.complaints(), complaints);
+    assert_eq!(
+        transcript.public_key().unwrap().to_bytes(),
+        output.public_key().to_bytes()
+    );
+    assert_eq!(
+        transcript.verification_share(1).unwrap().to_bytes(),
+        output.verification_share().to_bytes()
+    );
+
+    assert!(Transcript::try_from(&bytes[..bytes.len() - 1]).is_err());
+    let mut bytes = bytes;
+    bytes[0] = 2;
+    assert!(Transcript::try_from(bytes.as_slice()).is_err());
+}
+
+fn main() {
+    println!("Splitting");
+    split_invalid_args::<Secp256k1Scalar>();
+    println!("Combine invalid fail");
+    combine_invalid::<Secp256k1Scalar>();
+    println!("Split many success");
+    split_many::<Secp256k1Scalar>();
+    println!("Field division success");
+    field_division::<Secp256k1Scalar>();
+    println!("Refresh shares success");
+    refresh_shares::<Secp256k1Scalar>();
+    println!("Combine single success");
+    combine_single::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Combine combinations success");
+    combine_all_combinations::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Combine with identifiers success");
+    combine_with_identifiers::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Observer events success");
+    observer_events::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Combine in exponent success");
+    combine_in_exponent::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Combine with context success");
+    combine_with_context::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Share verification success");
+    share_verification::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Cheater detection success");
+    cheater_detection::<Secp256k1Scalar, Secp256k1Point>();
+    println!("DKG rounds success");
+    dkg_rounds::<Secp256k1Scalar, Secp256k1Point>();
+    println!("DKG honest success");
+    dkg_honest();
+    println!("DKG resolved complaint success");
+    dkg_resolved_complaint();
+    println!("DKG blames cheaters");
+    dkg_blames_cheaters();
+    println!("DKG transcript replay success");
+    dkg_transcript_replay();
+    println!("Possession proofs success");
+    possession_proofs::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Escrow success");
+    escrow_shares::<Secp256k1Scalar, Secp256k1Point>();
+    dleq_proofs::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Transcript challenges success");
+    transcript_challenges::<Secp256k1Scalar, Secp256k1Point>();
+    println!("Strict deserialization success");
+    strict_deserialization::<Secp256k1Scalar, Secp256k1Point>();
+}
@@ -0,0 +1,192 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::{SharingError, SharingResult},
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    shuffle::{shuffle, Permutation, PermutationCommitment, PermutationOpening, ShuffleProof},
+    transcript::Transcript,
+    Field, Group,
+};
+
+const TICKETS: usize = 8;
+
+fn transcript(draw: u64) -> Transcript {
+    let mut t = Transcript::new(b"example lottery");
+    t.append_u64(b"draw", draw);
+    t
+}
+
+/// Pedersen commitments `g^r h^m` to the ticket numbers
+fn tickets<S: Field, R: Group<S>>(g: &R, h: &R) -> Vec<R> {
+    (0..TICKETS)
+        .map(|m| {
+            let mut c = R::zero();
+            c.add_assign(g);
+            c.scalar_mul_assign(&S::random(&mut OsRng));
+            let mut v = R::zero();
+            v.add_assign(h);
+            v.scalar_mul_assign(&S::from_usize(m + 1));
+            c.add_assign(&v);
+            c
+        })
+        .collect()
+}
+
+fn rejected(result: SharingResult<()>) {
+    match result {
+        Err(SharingError::ShuffleProofInvalid) => {}
+        r => panic!("expected an invalid proof, found {:?}", r),
+    }
+}
+
+fn permutations() {
+    let p = Permutation::from_seed(b"drawn after the tickets are sold", TICKETS);
+    assert_eq!(
+        p,
+        Permutation::from_seed(b"drawn after the tickets are sold", TICKETS)
+    );
+    assert_ne!(p, Permutation::from_seed(b"another seed", TICKETS));
+    let mut sorted = p.indices().to_vec();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..TICKETS).collect::<Vec<usize>>());
+
+    let items = (0..TICKETS).collect::<Vec<usize>>();
+    let permuted = p.apply(&items).unwrap();
+    assert_eq!(permuted, p.indices());
+    assert_eq!(p.inverse().apply(&permuted).unwrap(), items);
+    assert!(p.apply(&items[1..]).is_err());
+
+    assert!(Permutation::new(vec![1, 0, 2]).is_ok());
+    assert!(Permutation::new(vec![1, 1, 2]).is_err());
+    assert!(Permutation::new(vec![0, 3, 1]).is_err());
+}
+
+fn lottery<S: Field, R: Group<S>>() {
+    // Generators from a trusted setup, random here
+    let g = R::random(&mut OsRng);
+    let h = (0..TICKETS)
+        .map(|_| R::random(&mut OsRng))
+        .collect::<Vec<R>>();
+    let value_generator = R::random(&mut OsRng);
+
+    // The organizer commits to the order before the tickets are known
+    let permutation = Permutation::from_seed(b"organizer's seed", TICKETS);
+    let (commitment, opening) =
+        PermutationCommitment::<S, R>::commit(&mut OsRng, (&g, &h), &permutation).unwrap();
+    let commitment =
+        PermutationCommitment::<S, R>::try_from(commitment.to_bytes().as_slice()).unwrap();
+
+    let inputs = tickets::<S, R>(&g, &value_generator);
+    let (outputs, rerandomization) = shuffle(&mut OsRng, &g, &inputs, &permutation).unwrap();
+    for (i, j) in permutation.indices().iter().enumerate() {
+        assert_ne!(outputs[i].to_bytes(), inputs[*j].to_bytes());
+    }
+
+    let proof = ShuffleProof::prove(
+        &mut OsRng,
+        &mut transcript(1),
+        (&g, &h),
+        &commitment,
+        &opening,
+        &inputs,
+        &outputs,
+        &rerandomization,
+    )
+    .unwrap();
+    let bytes = proof.to_bytes();
+    assert_eq!(bytes.len(), ShuffleProof::<S, R>::size(TICKETS));
+    let proof = ShuffleProof::<S, R>::try_from(bytes.as_slice()).unwrap();
+    proof
+        .verify(&mut transcript(1), (&g, &h), &commitment, &inputs, &outputs)
+        .unwrap();
+
+    // Another draw, reordered outputs or another commitment are rejected
+    rejected(proof.verify(&mut transcript(2), (&g, &h), &commitment, &inputs, &outputs));
+    let mut reordered = outputs.iter().map(|o| o.to_bytes()).collect::<Vec<_>>();
+    reordered.swap(0, 1);
+    let reordered = reordered
+        .iter()
+        .map(|b| R::from_bytes(b).unwrap())
+        .collect::<Vec<R>>();
+    rejected(proof.verify(
+        &mut transcript(1),
+        (&g, &h),
+        &commitment,
+        &inputs,
+        &reordered,
+    ));
+    let (other, _) = PermutationCommitment::<S, R>::commit(
+        &mut OsRng,
+        (&g, &h),
+        &Permutation::random(&mut OsRng, TICKETS),
+    )
+    .unwrap();
+    rejected(proof.verify(&mut transcript(1), (&g, &h), &other, &inputs, &outputs));
+    rejected(proof.verify(
+        &mut transcript(1),
+        (&g, &h[1..]),
+        &commitment,
+        &inputs[1..],
+        &outputs[1..],
+    ));
+
+    // A proof for another permutation than the committed one doesn't verify
+    let (wrong, wrong_t) = shuffle(
+        &mut OsRng,
+        &g,
+        &inputs,
+        &Permutation::random(&mut OsRng, TICKETS),
+    )
+    .unwrap();
+    let proof = ShuffleProof::prove(
+        &mut OsRng,
+        &mut transcript(1),
+        (&g, &h),
+        &commitment,
+        &opening,
+        &inputs,
+        &wrong,
+        &wrong_t,
+    )
+    .unwrap();
+    rejected(proof.verify(&mut transcript(1), (&g, &h), &commitment, &inputs, &wrong));
+
+    // Revealing the opening lets anyone check the order
+    let revealed = PermutationOpening::<S>::try_from(opening.to_bytes().as_slice()).unwrap();
+    assert_eq!(revealed.permutation(), &permutation);
+    commitment.verify_opening((&g, &h), &revealed).unwrap();
+    assert!(other.verify_opening((&g, &h), &revealed).is_err());
+
+    // Malformed encodings
+    let bytes = commitment.to_bytes();
+    assert!(PermutationCommitment::<S, R>::try_from(&bytes[..bytes.len() - 1]).is_err());
+    assert!(PermutationCommitment::<S, R>::try_from(&[0u8, 0, 0, 0][..]).is_err());
+    let bytes = proof.to_bytes();
+    assert!(ShuffleProof::<S, R>::try_from(&bytes[..bytes.len() - 1]).is_err());
+    let mut bytes = opening.to_bytes();
+    bytes.copy_within(8..12, 4);
+    assert!(PermutationOpening::<S>::try_from(bytes.as_slice()).is_err());
+    assert!(
+        PermutationCommitment::<S, R>::commit(&mut OsRng, (&g, &h[1..]), &permutation).is_err()
+    );
+}
+
+fn main() {
+    permutations();
+    lottery::<Secp256k1Scalar, Secp256k1Point>();
+    lottery::<Ristretto25519Scalar, Ristretto25519Point>();
+}
@@ -0,0 +1,293 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    dkg::{
+        Blame, Complaint, DkgOutput, Justification, Misbehavior, Participant, Round1Broadcast,
+        Round1Share,
+    },
+    error::{SharingError, SharingResult},
+    ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar},
+    sim::{Behavior, Context, Link, Node, Simulation},
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+/// How long a participant waits for the messages of a round
+const ROUND: u64 = 50;
+
+#[derive(Clone)]
+enum Message {
+    Broadcast(Box<Round1Broadcast<Scalar, Point>>),
+    Share(Round1Share),
+    Complaint(Complaint),
+    Justification(Justification),
+}
+
+/// A DKG participant that ends each round when its timer fires,
+/// using whatever messages arrived by then
+struct DkgNode {
+    id: u32,
+    participant: Option<Participant<Scalar, Point>>,
+    broadcasts: Vec<Round1Broadcast<Scalar, Point>>,
+    shares: Vec<Round1Share>,
+    complaints: Vec<Complaint>,
+    justifications: Vec<Justification>,
+    output: Option<SharingResult<DkgOutput<Scalar, Point>>>,
+}
+
+impl DkgNode {
+    fn new(id: u32) -> Self {
+        Self {
+            id,
+            participant: None,
+            broadcasts: Vec::new(),
+            shares: Vec::new(),
+            complaints: Vec::new(),
+            justifications: Vec::new(),
+            output: None,
+        }
+    }
+}
+
+impl Node for DkgNode {
+    type Message = Message;
+
+    fn start(&mut self, ctx: &mut Context<Message>) {
+        let (participant, broadcast, shares) =
+            Participant::new(ctx.rng(), self.id, THRESHOLD, LIMIT, &Point::generator()).unwrap();
+        self.participant = Some(participant);
+        self.broadcasts.push(broadcast.clone());
+        ctx.broadcast(Message::Broadcast(Box::new(broadcast)));
+        for share in shares {
+            ctx.send(share.receiver(), Message::Share(share));
+        }
+        ctx.set_timer(ROUND, 1);
+    }
+
+    fn receive(&mut self, _ctx: &mut Context<Message>, _from: u32, message: Message) {
+        match message {
+            Message::Broadcast(b) => self.broadcasts.push(*b),
+            Message::Share(s) => self.shares.push(s),
+            Message::Complaint(c) => self.complaints.push(c),
+            Message::Justification(j) => self.justifications.push(j),
+        }
+    }
+
+    fn timeout(&mut self, ctx: &mut Context<Message>, round: u64) {
+        let participant = self.participant.as_mut().unwrap();
+        match round {
+            1 => match participant.receive_round1(&self.broadcasts, &self.shares) {
+                Ok(complaints) => {
+                    for c in complaints {
+                        self.complaints.push(c);
+                        ctx.broadcast(Message::Complaint(c));
+                    }
+                    ctx.set_timer(ROUND, 2);
+                }
+                Err(e) => self.output = Some(Err(e)),
+            },
+            2 => {
+                for j in participant.justify(&self.complaints) {
+                    self.justifications.push(j.clone());
+                    ctx.broadcast(Message::Justification(j));
+                }
+                ctx.set_timer(ROUND, 3);
+            }
+            _ => {
+                self.output = Some(participant.finalize(&self.complaints, &self.justifications));
+            }
+        }
+    }
+}
+
+fn simulation(seed: u64) -> Simulation<DkgNode> {
+    let mut sim = Simulation::new(seed);
+    sim.set_default_link(Link {
+        min_latency: 1,
+        max_latency: 20,
+        drop_rate: 0.0,
+    });
+    sim
+}
+
+/// Run the DKG with `behavior` for participant `faulty` and the others honest
+fn run(
+    mut sim: Simulation<DkgNode>,
+    faulty: u32,
+    behavior: Behavior<Message>,
+) -> Simulation<DkgNode> {
+    let mut behavior = Some(behavior);
+    for id in 1..=LIMIT as u32 {
+        let b = if id == faulty {
+            behavior.take().unwrap()
+        } else {
+            Behavior::Honest
+        };
+        sim.add_node(id, DkgNode::new(id), b);
+    }
+    assert!(sim.run(10 * ROUND));
+    sim
+}
+
+fn public_key(sim: &Simulation<DkgNode>, id: u32) -> Vec<u8> {
+    let output = sim.node(id).unwrap().output.as_ref().unwrap();
+    output.as_ref().unwrap().public_key().to_bytes().to_vec()
+}
+
+fn honest() {
+    let sim = run(simulation(1), 0, Behavior::Honest);
+    let key = public_key(&sim, 1);
+    for id in 2..=LIMIT as u32 {
+        assert_eq!(public_key(&sim, id), key);
+    }
+    let stats = sim.stats();
+    // A broadcast and a share to every other participant each
+    assert_eq!(stats.sent, 2 * LIMIT * (LIMIT - 1));
+    assert_eq!(stats.delivered, stats.sent);
+    assert_eq!(stats.dropped, 0);
+    assert_eq!(sim.now(), 3 * ROUND);
+
+    // The same seed replays the same session, another seed doesn't
+    assert_eq!(public_key(&run(simulation(1), 0, Behavior::Honest), 1), key);
+    assert_ne!(public_key(&run(simulation(2), 0, Behavior::Honest), 1), key);
+}
+
+/// Participant 3 deals a bad share to 2, which complains, and 3 justifies it
+fn bad_share() {
+    let tamper = |to: u32, message: Message| match message {
+        Message::Share(s) if to == 2 => {
+            let mut bytes = s.to_bytes();
+            bytes[12] ^= 0xff;
+            Some(Message::Share(
+                Round1Share::try_from(bytes.as_slice()).unwrap(),
+            ))
+        }
+        m => Some(m),
+    };
+    let sim = run(simulation(3), 3, Behavior::Byzantine(Box::new(tamper)));
+    let key = public_key(&sim, 1);
+    for (id, node) in sim.nodes() {
+        assert_eq!(public_key(&sim, id), key);
+        assert_eq!(
+            node.complaints,
+            vec![Complaint {
+                accuser: 2,
+                accused: 3
+            }]
+        );
+        assert_eq!(node.justifications.len(), 1);
+    }
+}
+
+/// Participant 5 crashes before sending anything and everyone names it
+fn crash() {
+    let sim = run(simulation(4), 5, Behavior::Crash(0));
+    for id in 1..LIMIT as u32 {
+        let node = sim.node(id).unwrap();
+        match node.output {
+            Some(Err(SharingError::DkgAbort(5))) => {}
+            _ => panic!("expected participant {} to blame 5", id),
+        }
+        assert_eq!(
+            node.participant.as_ref().unwrap().transcript().blame(),
+            vec![Blame {
+                participant: 5,
+                misbehavior: Misbehavior::MissingBroadcast
+            }]
+        );
+    }
+    assert!(sim.node(5).unwrap().output.is_none());
+}
+
+/// Participant 4 equivocates by sending 1 a broadcast with another secret
+fn equivocation() {
+    let mut rng = ChaChaRng::seed_from_u64(7);
+    let (_, other, _) =
+        Participant::<Scalar, Point>::new(&mut rng, 4, THRESHOLD, LIMIT, &Point::generator())
+            .unwrap();
+    let tamper = move |to: u32, message: Message| match message {
+        Message::Broadcast(_) if to == 1 => Some(Message::Broadcast(Box::new(other.clone()))),
+        m => Some(m),
+    };
+    let sim = run(simulation(5), 4, Behavior::Byzantine(Box::new(tamper)));
+    // 1 complains as 4's share doesn't match the broadcast it saw and rejects the
+    // justification, while the others accept it. The participants disagree on the
+    // outcome, which is why the DKG needs a reliable broadcast channel
+    let key = public_key(&sim, 2);
+    for id in 3..=LIMIT as u32 {
+        assert_eq!(public_key(&sim, id), key);
+    }
+    let node = sim.node(1).unwrap();
+    assert_eq!(
+        node.complaints,
+        vec![Complaint {
+            accuser: 1,
+            accused: 4
+        }]
+    );
+    match node.output {
+        Some(Err(SharingError::DkgAbort(4))) => {}
+        _ => panic!("expected participant 1 to blame 4"),
+    }
+}
+
+/// A message that arrives after its round ended counts as missing
+fn slow_link() {
+    let mut sim = simulation(6);
+    sim.set_link(
+        4,
+        1,
+        Link {
+            min_latency: 4 * ROUND,
+            max_latency: 4 * ROUND,
+            drop_rate: 0.0,
+        },
+    );
+    let sim = run(sim, 0, Behavior::Honest);
+    match sim.node(1).unwrap().output {
+        Some(Err(SharingError::DkgAbort(4))) => {}
+        _ => panic!("expected participant 1 to blame 4"),
+    }
+    let key = public_key(&sim, 2);
+    for id in 3..=LIMIT as u32 {
+        assert_eq!(public_key(&sim, id), key);
+    }
+
+    // Partitioning 4 from 1 loses the messages instead
+    let mut sim = simulation(6);
+    sim.set_link(
+        4,
+        1,
+        Link {
+            min_latency: 1,
+            max_latency: 1,
+            drop_rate: 1.0,
+        },
+    );
+    let sim = run(sim, 0, Behavior::Honest);
+    assert_eq!(sim.stats().dropped, 2);
+    assert_eq!(sim.stats().delivered, sim.stats().sent - 2);
+}
+
+fn main() {
+    honest();
+    bad_share();
+    crash();
+    equivocation();
+    slow_link();
+}
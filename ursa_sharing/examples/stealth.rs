@@ -0,0 +1,113 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::SharingError,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256k1::{Secp256k1Point, Secp256k1Scalar},
+    stealth::{
+        blind_public_key, blind_secret_key, OneTimeKey, ScanKey, StealthAddress, StealthKeys,
+    },
+    Field, Group, Strictness,
+};
+
+fn public_key<S: Field, R: Group<S>>(g: &R, secret: &S) -> R {
+    let mut p = R::zero();
+    p.add_assign(g);
+    p.scalar_mul_assign(secret);
+    p
+}
+
+/// Pay two receivers, find the payments and spend them
+fn payments<S: Field, R: Group<S>>(g: &R) {
+    let alice = StealthKeys::<S>::random(&mut OsRng);
+    let bob = StealthKeys::<S>::random(&mut OsRng);
+    let keys = StealthKeys::<S>::try_from(alice.to_bytes().as_slice()).unwrap();
+    assert_eq!(keys.to_bytes(), alice.to_bytes());
+
+    let address = alice.address(g);
+    let address = StealthAddress::<S, R>::try_from(address.to_bytes().as_slice()).unwrap();
+    let bob_address = bob.address(g);
+
+    let mut published = Vec::new();
+    for i in 0..6 {
+        let to = if i % 3 == 0 { &address } else { &bob_address };
+        let key = to.derive(&mut OsRng, g).unwrap();
+        published.push(OneTimeKey::<S, R>::try_from(key.to_bytes().as_slice()).unwrap());
+    }
+    // Payments to the same address are different keys
+    assert_ne!(published[0].key().to_bytes(), published[3].key().to_bytes());
+    assert_ne!(published[0].key().to_bytes(), address.spend().to_bytes());
+
+    // A server with the scan key finds the payments
+    let scan_key = alice.scan_key(g);
+    let scan_key = ScanKey::<S, R>::try_from(scan_key.to_bytes().as_slice()).unwrap();
+    assert_eq!(scan_key.scan(g, &published), vec![0, 3]);
+    assert_eq!(bob.scan_key(g).scan(g, &published), vec![1, 2, 4, 5]);
+
+    // Only the owner gets the one time secret key
+    for i in &[0, 3] {
+        let secret = alice.one_time_secret(g, &published[*i]).unwrap();
+        assert_eq!(
+            public_key(g, &secret).to_bytes(),
+            published[*i].key().to_bytes()
+        );
+        match bob.one_time_secret(g, &published[*i]) {
+            Err(SharingError::StealthKeyNotOwned) => {}
+            r => panic!("expected a key of another address, found {:?}", r.err()),
+        }
+    }
+
+    // Malformed keys
+    let bytes = published[0].to_bytes();
+    assert!(OneTimeKey::<S, R>::try_from(&bytes[1..]).is_err());
+    let mut identity = bytes.clone();
+    let half = bytes.len() / 2;
+    identity[..half].copy_from_slice(&R::zero().to_bytes());
+    if let Ok(k) = OneTimeKey::<S, R>::try_from(identity.as_slice()) {
+        assert!(!scan_key.detect(g, &k));
+        assert!(alice.one_time_secret(g, &k).is_err());
+    }
+    assert!(OneTimeKey::<S, R>::from_bytes_with(&identity, Strictness::Strict).is_err());
+    assert!(StealthKeys::<S>::try_from(&alice.to_bytes()[1..]).is_err());
+}
+
+/// Blind a key for different contexts
+fn blinding<S: Field, R: Group<S>>(g: &R) {
+    let secret = S::random(&mut OsRng);
+    let key = public_key(g, &secret);
+    let day_1 = blind_public_key::<S, R>(&key, b"2020-06-01").unwrap();
+    let day_2 = blind_public_key::<S, R>(&key, b"2020-06-02").unwrap();
+    assert_ne!(day_1.to_bytes(), day_2.to_bytes());
+    assert_ne!(day_1.to_bytes(), key.to_bytes());
+    assert_eq!(
+        blind_public_key::<S, R>(&key, b"2020-06-01")
+            .unwrap()
+            .to_bytes(),
+        day_1.to_bytes()
+    );
+
+    let blinded = blind_secret_key(&secret, &key, b"2020-06-01").unwrap();
+    assert_eq!(public_key(g, &blinded).to_bytes(), day_1.to_bytes());
+    assert!(blind_public_key::<S, R>(&R::zero(), b"2020-06-01").is_err());
+    assert!(blind_secret_key(&S::zero(), &key, b"2020-06-01").is_err());
+}
+
+fn main() {
+    payments::<Secp256k1Scalar, Secp256k1Point>(&Secp256k1Point::generator());
+    blinding::<Secp256k1Scalar, Secp256k1Point>(&Secp256k1Point::generator());
+    payments::<Ristretto25519Scalar, Ristretto25519Point>(&Ristretto25519Point::generator());
+    blinding::<Ristretto25519Scalar, Ristretto25519Point>(&Ristretto25519Point::generator());
+}
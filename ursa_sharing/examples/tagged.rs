@@ -0,0 +1,153 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    ed25519::Ed25519Scalar,
+    error::SharingError,
+    secp256k1::Secp256k1Scalar,
+    shamir::{Scheme, Share},
+    tagged::{combine_secret, CurveTag, TaggedShare, VERSION},
+    Group,
+};
+
+fn split() -> (Secp256k1Scalar, Vec<TaggedShare>) {
+    let secret = Secp256k1Scalar::random(&mut OsRng);
+    let shares = Scheme::new(3, 5)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+    (
+        secret,
+        TaggedShare::tag_all::<Secp256k1Scalar>(3, &shares).unwrap(),
+    )
+}
+
+fn tagged_roundtrip() {
+    let (secret, shares) = split();
+    assert_eq!(shares[0].curve(), CurveTag::Secp256k1);
+    assert_eq!(shares[0].threshold(), 3);
+    assert_eq!(shares[4].identifier(), 5);
+
+    let bytes = shares[0].to_bytes();
+    assert_eq!(bytes[0], VERSION);
+    assert_eq!(bytes[1], CurveTag::Secp256k1.to_byte());
+    assert_eq!(bytes.len(), 10 + 32);
+
+    let parsed = shares
+        .iter()
+        .map(|s| TaggedShare::from_base64(&s.to_base64()).unwrap())
+        .collect::<Vec<_>>();
+    let combined: Secp256k1Scalar = combine_secret(&parsed[1..4]).unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+
+    let parsed = shares
+        .iter()
+        .map(|s| TaggedShare::try_from(s.to_bytes().as_slice()).unwrap())
+        .collect::<Vec<_>>();
+    let combined: Secp256k1Scalar = combine_secret(&parsed[..3]).unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+    assert!(combine_secret::<Secp256k1Scalar>(&parsed[..2]).is_err());
+}
+
+fn tagged_serde() {
+    let (secret, shares) = split();
+
+    let json = serde_json::to_string(&shares).unwrap();
+    assert!(json.starts_with("[\""));
+    let parsed: Vec<TaggedShare> = serde_json::from_str(&json).unwrap();
+    let combined: Secp256k1Scalar = combine_secret(&parsed[2..]).unwrap();
+    assert_eq!(combined.to_bytes(), secret.to_bytes());
+
+    let cbor = serde_cbor::to_vec(&shares).unwrap();
+    let parsed: Vec<TaggedShare> = serde_cbor::from_slice(&cbor).unwrap();
+    assert_eq!(parsed[0].to_bytes(), shares[0].to_bytes());
+
+    // Malformed shares are rejected while deserializing
+    let truncated = shares[0].to_base64()[..12].to_string();
+    assert!(serde_json::from_str::<TaggedShare>(&format!("\"{}\"", truncated)).is_err());
+}
+
+fn tagged_rejects_mismatches() {
+    let (_, shares) = split();
+
+    match combine_secret::<Ed25519Scalar>(&shares[..3]) {
+        Err(SharingError::ShareMismatchedCurve) => {}
+        r => panic!("expected a curve mismatch, found {:?}", r.map(|_| ())),
+    }
+    assert!(shares[0].to_share::<Ed25519Scalar>().is_err());
+
+    // Same shares tagged with another threshold
+    let plain = shares[0].to_share::<Secp256k1Scalar>().unwrap();
+    let mixed = vec![
+        TaggedShare::new::<Secp256k1Scalar>(2, &plain).unwrap(),
+        shares[1].clone(),
+        shares[2].clone(),
+    ];
+    match combine_secret::<Secp256k1Scalar>(&mixed) {
+        Err(SharingError::ShareMismatchedThreshold) => {}
+        r => panic!("expected a threshold mismatch, found {:?}", r.map(|_| ())),
+    }
+}
+
+fn tagged_rejects_malformed() {
+    let (_, shares) = split();
+    let bytes = shares[0].to_bytes();
+
+    let mut version = bytes.clone();
+    version[0] = VERSION + 1;
+    match TaggedShare::try_from(version.as_slice()) {
+        Err(SharingError::ShareUnsupportedVersion(v)) => assert_eq!(v, VERSION + 1),
+        r => panic!("expected a version error, found {:?}", r),
+    }
+
+    let mut curve = bytes.clone();
+    curve[1] = 0x7f;
+    match TaggedShare::try_from(curve.as_slice()) {
+        Err(SharingError::ShareUnknownCurve(0x7f)) => {}
+        r => panic!("expected an unknown curve, found {:?}", r),
+    }
+
+    assert!(TaggedShare::try_from(&bytes[..10]).is_err());
+    assert!(TaggedShare::try_from(&[][..]).is_err());
+
+    // Parses but doesn't hold a whole scalar
+    let truncated = TaggedShare::try_from(&bytes[..bytes.len() - 1]).unwrap();
+    assert!(truncated.to_share::<Secp256k1Scalar>().is_err());
+    let mut parsed = shares[1..3]
+        .iter()
+        .map(|s| TaggedShare::try_from(s.to_bytes().as_slice()).unwrap())
+        .collect::<Vec<_>>();
+    parsed.push(truncated);
+    assert!(combine_secret::<Secp256k1Scalar>(&parsed).is_err());
+
+    // Zero identifiers and thresholds below two
+    let mut identifier = bytes.clone();
+    identifier[6..10].copy_from_slice(&[0u8; 4]);
+    assert!(TaggedShare::try_from(identifier.as_slice()).is_err());
+    let mut threshold = bytes;
+    threshold[2..6].copy_from_slice(&1u32.to_be_bytes());
+    assert!(TaggedShare::try_from(threshold.as_slice()).is_err());
+
+    // Values above the group order aren't canonical
+    let share = Share::new(1, [0xffu8; 32]);
+    assert!(TaggedShare::new::<Secp256k1Scalar>(3, &share).is_err());
+}
+
+fn main() {
+    tagged_roundtrip();
+    tagged_serde();
+    tagged_rejects_mismatches();
+    tagged_rejects_malformed();
+}
@@ -0,0 +1,169 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use amcl_wrapper::{
+    extension_field_gt::GT, field_elem::FieldElement, group_elem::GroupElement,
+    group_elem_g1::G1 as AmclG1, group_elem_g2::G2 as AmclG2,
+};
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    bls12_381::{Bls12381G1, Bls12381Scalar},
+    error::SharingError,
+    feldman::Scheme as FeldmanScheme,
+    shamir::{Scheme as ShamirScheme, Share},
+    threshold_bls::{
+        g2_from_ursa_bytes, secret_key_from_ursa_bytes, PartialSignature, PublicKey,
+        PublicKeyShare, Signature,
+    },
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+/// The context Ursa's BLS prepends to messages before hashing
+const URSA_MESSAGE_CONTEXT: &[u8] = b"for signing messages";
+
+fn threshold_sign() {
+    let secret = Bls12381Scalar::random(&mut OsRng);
+    let scheme = FeldmanScheme::new(THRESHOLD, LIMIT).unwrap();
+    let (verifier, shares) = scheme
+        .split_secret::<Bls12381Scalar, Bls12381G1>(
+            &mut OsRng,
+            &secret,
+            Some(Bls12381G1::generator()),
+        )
+        .unwrap();
+    let public_key = PublicKey::from_verifier(&verifier).unwrap();
+    assert_eq!(
+        public_key.to_bytes(),
+        PublicKey::from_secret_key(&secret).to_bytes()
+    );
+
+    let message = b"transfer 10 tokens";
+    let partials = shares
+        .iter()
+        .map(|s| PartialSignature::new(s, message).unwrap())
+        .collect::<Vec<_>>();
+    for (share, partial) in shares.iter().zip(partials.iter()) {
+        let key = PublicKeyShare::from_verifier(&verifier, share.identifier()).unwrap();
+        assert_eq!(
+            key.key().to_bytes(),
+            PublicKeyShare::from_share(share).unwrap().key().to_bytes()
+        );
+        assert!(partial.verify(&key, message).is_ok());
+        assert!(partial.verify(&key, b"another message").is_err());
+    }
+    let key = PublicKeyShare::from_verifier(&verifier, 2).unwrap();
+    match partials[0].verify(&key, message) {
+        Err(SharingError::BlsPartialSignatureInvalid(1)) => {}
+        r => panic!("expected an invalid partial signature, found {:?}", r),
+    }
+
+    // Any threshold of partial signatures give the same signature
+    let signature = Signature::combine(THRESHOLD, &partials[..3]).unwrap();
+    assert!(signature.verify(&public_key, message).is_ok());
+    assert!(signature.verify(&public_key, b"another message").is_err());
+    let other = Signature::combine(THRESHOLD, &partials[2..]).unwrap();
+    assert_eq!(signature.to_bytes(), other.to_bytes());
+    let parsed = Signature::try_from(signature.to_bytes().as_slice()).unwrap();
+    assert!(parsed.verify(&public_key, message).is_ok());
+
+    // Too few or duplicated partial signatures
+    assert!(Signature::combine(THRESHOLD, &partials[..2]).is_err());
+    let dup = [partials[0], partials[0], partials[1]];
+    assert!(Signature::combine(THRESHOLD, &dup).is_err());
+
+    // A partial signature of another message spoils the signature
+    let bad = PartialSignature::new(&shares[3], b"another message").unwrap();
+    let mixed = [partials[0], partials[1], bad];
+    let signature = Signature::combine(THRESHOLD, &mixed).unwrap();
+    assert!(signature.verify(&public_key, message).is_err());
+
+    // Serialization
+    let bytes = partials[1].to_bytes();
+    assert_eq!(bytes.len(), 100);
+    let parsed = PartialSignature::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(parsed.identifier(), 2);
+    assert!(PartialSignature::try_from(&bytes[1..]).is_err());
+    let mut zero = bytes;
+    zero[..4].copy_from_slice(&[0u8; 4]);
+    assert!(PartialSignature::try_from(zero.as_slice()).is_err());
+
+    // The public key shares need the standard generator
+    let (verifier, _) = scheme
+        .split_secret::<Bls12381Scalar, Bls12381G1>(&mut OsRng, &secret, None)
+        .unwrap();
+    assert!(PublicKey::from_verifier(&verifier).is_err());
+}
+
+/// Split an existing Ursa BLS key and produce a signature Ursa's verification accepts
+fn ursa_interop() {
+    let ursa_secret = FieldElement::random();
+    let ursa_public = AmclG1::generator() * &ursa_secret;
+
+    let secret = secret_key_from_ursa_bytes(&ursa_secret.to_bytes()).unwrap();
+    let public_key = PublicKey::from_ursa_bytes(&ursa_public.to_bytes(false)).unwrap();
+    assert_eq!(
+        public_key.to_bytes(),
+        PublicKey::from_secret_key(&secret).to_bytes()
+    );
+    assert_eq!(public_key.to_ursa_bytes(), ursa_public.to_bytes(false));
+
+    let shares = ShamirScheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+
+    // Hash the message like Ursa does
+    let message = b"transfer 10 tokens";
+    let mut input = URSA_MESSAGE_CONTEXT.to_vec();
+    input.extend_from_slice(message);
+    let ursa_hash = AmclG2::from_msg_hash(&input);
+    let hashed = g2_from_ursa_bytes(&ursa_hash.to_bytes(false)).unwrap();
+
+    let partials = shares[1..4]
+        .iter()
+        .map(|s| PartialSignature::new_hashed(s, &hashed).unwrap())
+        .collect::<Vec<_>>();
+    for (share, partial) in shares[1..4].iter().zip(partials.iter()) {
+        let key = PublicKeyShare::from_share(share).unwrap();
+        assert!(partial.verify_hashed(&key, &hashed).is_ok());
+    }
+    let signature = Signature::combine(THRESHOLD, &partials).unwrap();
+    assert!(signature.verify_hashed(&public_key, &hashed).is_ok());
+
+    // Ursa's own signature matches, and the combined one passes its pairing check
+    let ursa_signature = &ursa_hash * &ursa_secret;
+    assert_eq!(signature.to_ursa_bytes(), ursa_signature.to_bytes(false));
+    let parsed = AmclG2::from_bytes(&signature.to_ursa_bytes()).unwrap();
+    assert!(GT::ate_2_pairing(&-AmclG1::generator(), &parsed, &ursa_public, &ursa_hash).is_one());
+    let roundtrip = Signature::from_ursa_bytes(&signature.to_ursa_bytes()).unwrap();
+    assert_eq!(roundtrip.to_bytes(), signature.to_bytes());
+
+    // Malformed keys
+    assert!(secret_key_from_ursa_bytes(&[0u8; 48]).is_err());
+    assert!(secret_key_from_ursa_bytes(&[0xffu8; 48]).is_err());
+    let mut bad = ursa_public.to_bytes(false);
+    bad[0] = 0x02;
+    assert!(PublicKey::from_ursa_bytes(&bad).is_err());
+    let mut bad = ursa_public.to_bytes(false);
+    bad[40] ^= 1;
+    assert!(PublicKey::from_ursa_bytes(&bad).is_err());
+    assert!(PartialSignature::new(&Share::new(0, secret.to_bytes()), message).is_err());
+}
+
+fn main() {
+    threshold_sign();
+    ursa_interop();
+}
@@ -0,0 +1,483 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey, EcPoint},
+    ecdsa::EcdsaSig,
+    nid::Nid,
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::{SharingError, SharingResult},
+    secp256k1::{dkg::Participant, Secp256k1Point, Secp256k1Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    shamir::{Scheme, Share},
+    threshold_ecdsa::{
+        paillier::{DecryptionKey, EncryptionKey, MIN_MODULUS_BITS},
+        EcdsaGroup, PartialSignature, Presignature, Round1Broadcast, Round2Message,
+        Round3Broadcast, Round4Broadcast, Round5Broadcast, Signature, SignerRound1, SignerRound2,
+        SignerRound3, SignerRound4, SignerRound5, SignerRound6,
+    },
+    Field, Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+type Round1<S, R> = (Vec<SignerRound1<S, R>>, Vec<Round1Broadcast>);
+type Round2<S, R> = (Vec<SignerRound2<S, R>>, Vec<Round2Message>);
+type Round3<S, R> = (Vec<SignerRound3<S, R>>, Vec<Round3Broadcast<S>>);
+type Round4<S, R> = (Vec<SignerRound4<S, R>>, Vec<Round4Broadcast<S, R>>);
+type Round5<S, R> = (Vec<SignerRound5<S, R>>, Vec<Round5Broadcast<S, R>>);
+type Round6<S, R> = (Vec<SignerRound6<S, R>>, Vec<PartialSignature<S>>);
+
+/// Start signing with the shares of `signers`, using the Paillier keys in order
+fn round1<S: Field, R: EcdsaGroup<S>>(
+    shares: &[Share],
+    public_key: &R,
+    signers: &[u32],
+    keys: &[DecryptionKey],
+) -> SharingResult<Round1<S, R>> {
+    let mut states = Vec::new();
+    let mut broadcasts = Vec::new();
+    for (id, key) in signers.iter().zip(keys.iter()) {
+        let share = shares.iter().find(|s| s.identifier() == *id).unwrap();
+        let (state, broadcast) = SignerRound1::new(&mut OsRng, share, public_key, signers, key)?;
+        states.push(state);
+        broadcasts.push(Round1Broadcast::try_from(broadcast.to_bytes().as_slice())?);
+    }
+    Ok((states, broadcasts))
+}
+
+fn round2<S: Field, R: EcdsaGroup<S>>(
+    (states, broadcasts): Round1<S, R>,
+) -> SharingResult<Round2<S, R>> {
+    let mut next = Vec::new();
+    let mut messages = Vec::new();
+    for state in states {
+        let (state, m) = state.receive(&mut OsRng, &broadcasts)?;
+        next.push(state);
+        for m in m {
+            messages.push(Round2Message::try_from(m.to_bytes().as_slice())?);
+        }
+    }
+    Ok((next, messages))
+}
+
+fn round3<S: Field, R: EcdsaGroup<S>>(
+    (states, messages): Round2<S, R>,
+) -> SharingResult<Round3<S, R>> {
+    let mut next = Vec::new();
+    let mut broadcasts = Vec::new();
+    for state in states {
+        let mine = messages
+            .iter()
+            .filter(|m| m.receiver() == state.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        let (state, b) = state.receive(&mine)?;
+        next.push(state);
+        broadcasts.push(Round3Broadcast::try_from(b.to_bytes().as_slice())?);
+    }
+    Ok((next, broadcasts))
+}
+
+fn round4<S: Field, R: EcdsaGroup<S>>(
+    (states, broadcasts): Round3<S, R>,
+) -> SharingResult<Round4<S, R>> {
+    let mut next = Vec::new();
+    let mut out = Vec::new();
+    for state in states {
+        let (state, b) = state.receive(&broadcasts)?;
+        next.push(state);
+        out.push(Round4Broadcast::try_from(b.to_bytes().as_slice())?);
+    }
+    Ok((next, out))
+}
+
+fn round5<S: Field, R: EcdsaGroup<S>>(
+    (states, broadcasts): Round4<S, R>,
+) -> SharingResult<Round5<S, R>> {
+    let mut next = Vec::new();
+    let mut out = Vec::new();
+    for state in states {
+        let (state, b) = state.receive(&broadcasts)?;
+        next.push(state);
+        out.push(Round5Broadcast::try_from(b.to_bytes().as_slice())?);
+    }
+    Ok((next, out))
+}
+
+fn presign<S: Field, R: EcdsaGroup<S>>(
+    (states, broadcasts): Round5<S, R>,
+) -> SharingResult<Vec<Presignature<S, R>>> {
+    states.into_iter().map(|s| s.receive(&broadcasts)).collect()
+}
+
+fn sign<S: Field, R: EcdsaGroup<S>>(
+    presignatures: Vec<Presignature<S, R>>,
+    message_hash: &[u8],
+) -> SharingResult<Round6<S, R>> {
+    let mut next = Vec::new();
+    let mut partials = Vec::new();
+    for p in presignatures {
+        let (state, partial) = p.sign(message_hash)?;
+        next.push(state);
+        partials.push(PartialSignature::try_from(partial.to_bytes().as_slice())?);
+    }
+    Ok((next, partials))
+}
+
+fn combine<S: Field, R: EcdsaGroup<S>>(
+    (states, partials): Round6<S, R>,
+) -> SharingResult<Vec<Signature<S>>> {
+    states.into_iter().map(|s| s.receive(&partials)).collect()
+}
+
+fn run_presign<S: Field, R: EcdsaGroup<S>>(
+    shares: &[Share],
+    public_key: &R,
+    signers: &[u32],
+    keys: &[DecryptionKey],
+) -> SharingResult<Vec<Presignature<S, R>>> {
+    let r1 = round1(shares, public_key, signers, keys)?;
+    presign(round5(round4(round3(round2(r1)?)?)?)?)
+}
+
+fn threshold_sign<S: Field, R: EcdsaGroup<S>>(
+    shares: &[Share],
+    public_key: &R,
+    signers: &[u32],
+    keys: &[DecryptionKey],
+    message_hash: &[u8],
+) -> SharingResult<Signature<S>> {
+    let presignatures = run_presign(shares, public_key, signers, keys)?;
+    let r = presignatures[0].r().to_bytes();
+    assert!(presignatures.iter().all(|p| p.r().to_bytes() == r));
+    let signatures = combine(sign(presignatures, message_hash)?)?;
+    let bytes = signatures[0].to_bytes();
+    assert!(signatures.iter().all(|s| s.to_bytes() == bytes));
+    Signature::try_from(bytes.as_slice())
+}
+
+/// Verify with OpenSSL
+fn openssl_verify<S: Field, R: EcdsaGroup<S>>(
+    curve: Nid,
+    public_key: &R,
+    message_hash: &[u8],
+    signature: &Signature<S>,
+) -> bool {
+    let group = EcGroup::from_curve_name(curve).unwrap();
+    let mut ctx = BigNumContext::new().unwrap();
+    let point = EcPoint::from_bytes(&group, &public_key.to_bytes(), &mut ctx).unwrap();
+    let key = EcKey::from_public_key(&group, &point).unwrap();
+    let sig = EcdsaSig::from_private_components(
+        BigNum::from_slice(&signature.r().to_bytes()).unwrap(),
+        BigNum::from_slice(&signature.s().to_bytes()).unwrap(),
+    )
+    .unwrap();
+    sig.verify(message_hash, &key).unwrap()
+}
+
+/// Key shares from the secp256k1 DKG
+fn secp256k1_dkg() -> (Vec<Share>, Secp256k1Point) {
+    let mut participants = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(&mut OsRng, id, THRESHOLD, LIMIT).unwrap();
+        participants.push(p);
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    for p in participants.iter_mut() {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        assert!(p.receive_round1(&broadcasts, &mine).unwrap().is_empty());
+    }
+    let outputs = participants
+        .iter_mut()
+        .map(|p| p.finalize(&[], &[]).unwrap())
+        .collect::<Vec<_>>();
+    (
+        outputs.iter().map(|o| o.share().clone()).collect(),
+        outputs[0].public_key(),
+    )
+}
+
+/// Key shares of a dealt P-256 key
+fn secp256r1_split() -> (Vec<Share>, Secp256r1Point) {
+    let secret = Secp256r1Scalar::random(&mut OsRng);
+    let shares = Scheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+    let mut public_key = Secp256r1Point::generator();
+    public_key.scalar_mul_assign(&secret);
+    (shares, public_key)
+}
+
+fn sign_secp256k1(keys: &[DecryptionKey]) {
+    let (shares, public_key) = secp256k1_dkg();
+    let hash = Sha256::digest(b"transfer 1 btc");
+    for signers in &[[1, 3, 5], [2, 3, 4], [1, 2, 3]] {
+        let signature: Signature<Secp256k1Scalar> =
+            threshold_sign(&shares, &public_key, signers, keys, &hash).unwrap();
+        assert!(signature.verify(&public_key, &hash).is_ok());
+        assert!(openssl_verify(
+            Nid::SECP256K1,
+            &public_key,
+            &hash,
+            &signature
+        ));
+        let other = Sha256::digest(b"transfer 2 btc");
+        assert!(signature.verify(&public_key, &other).is_err());
+        assert!(!openssl_verify(
+            Nid::SECP256K1,
+            &public_key,
+            &other,
+            &signature
+        ));
+
+        // Low s
+        let mut negated = *signature.s();
+        negated.negate();
+        assert!(signature.s().to_bytes() < negated.to_bytes());
+    }
+
+    // More signers than the threshold
+    let signature: Signature<Secp256k1Scalar> =
+        threshold_sign(&shares, &public_key, &[1, 2, 3, 4], keys, &hash).unwrap();
+    assert!(openssl_verify(
+        Nid::SECP256K1,
+        &public_key,
+        &hash,
+        &signature
+    ));
+}
+
+fn sign_secp256r1(keys: &[DecryptionKey]) {
+    let (shares, public_key) = secp256r1_split();
+    let hash = Sha256::digest(b"open the vault");
+    let signature: Signature<Secp256r1Scalar> =
+        threshold_sign(&shares, &public_key, &[2, 4, 5], keys, &hash).unwrap();
+    assert!(signature.verify(&public_key, &hash).is_ok());
+    assert!(openssl_verify(
+        Nid::X9_62_PRIME256V1,
+        &public_key,
+        &hash,
+        &signature
+    ));
+    assert!(signature.verify(&public_key, &hash[1..]).is_err());
+
+    // Serialization
+    let bytes = signature.to_bytes();
+    assert_eq!(bytes.len(), 64);
+    let parsed = Signature::<Secp256r1Scalar>::try_from(bytes.as_slice()).unwrap();
+    assert!(parsed.verify(&public_key, &hash).is_ok());
+    assert!(Signature::<Secp256r1Scalar>::try_from(&bytes[1..]).is_err());
+    assert!(Signature::<Secp256r1Scalar>::try_from(&[0u8; 64][..]).is_err());
+}
+
+fn presign_ahead(keys: &[DecryptionKey]) {
+    let (shares, public_key) = secp256r1_split();
+    let signers = [1, 2, 3];
+    let presignatures =
+        run_presign::<Secp256r1Scalar, Secp256r1Point>(&shares, &public_key, &signers, keys)
+            .unwrap();
+
+    // The message is only needed for the last round
+    let hash = Sha256::digest(b"signed later");
+    let signatures = combine(sign(presignatures, &hash).unwrap()).unwrap();
+    assert!(openssl_verify(
+        Nid::X9_62_PRIME256V1,
+        &public_key,
+        &hash,
+        &signatures[0]
+    ));
+
+    let presignatures =
+        run_presign::<Secp256r1Scalar, Secp256r1Point>(&shares, &public_key, &signers, keys)
+            .unwrap();
+    match sign(presignatures, &hash[..31]) {
+        Err(SharingError::EcdsaInvalidMessageHash) => {}
+        r => panic!("expected an invalid hash, found {:?}", r.err()),
+    }
+}
+
+fn identifiable_aborts(keys: &[DecryptionKey]) {
+    let (shares, public_key) = secp256k1_dkg();
+    let signers = [1, 2, 4];
+    let hash = Sha256::digest(b"transfer 1 btc");
+
+    // A missing broadcast names its sender
+    let (states, mut broadcasts) = round3(
+        round2(round1::<Secp256k1Scalar, _>(&shares, &public_key, &signers, keys).unwrap())
+            .unwrap(),
+    )
+    .unwrap();
+    broadcasts.remove(1);
+    match round4((states, broadcasts)) {
+        Err(SharingError::EcdsaAbort(2)) => {}
+        r => panic!("expected signer 2 to abort, found {:?}", r.err()),
+    }
+
+    // A commitment that doesn't open
+    let r4 = round4(
+        round3(
+            round2(round1::<Secp256k1Scalar, _>(&shares, &public_key, &signers, keys).unwrap())
+                .unwrap(),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let (states, mut broadcasts) = r4;
+    let mut bytes = broadcasts[2].to_bytes();
+    bytes[4 + 33] ^= 1;
+    broadcasts[2] = Round4Broadcast::try_from(bytes.as_slice()).unwrap();
+    match round5((states, broadcasts)) {
+        Err(SharingError::EcdsaAbort(4)) => {}
+        r => panic!("expected signer 4 to abort, found {:?}", r.err()),
+    }
+
+    // An invalid partial signature
+    let presignatures =
+        run_presign::<Secp256k1Scalar, _>(&shares, &public_key, &signers, keys).unwrap();
+    let (states, mut partials) = sign(presignatures, &hash).unwrap();
+    let mut bytes = partials[0].to_bytes();
+    bytes[35] ^= 1;
+    partials[0] = PartialSignature::try_from(bytes.as_slice()).unwrap();
+    match combine((states, partials)) {
+        Err(SharingError::EcdsaAbort(1)) => {}
+        r => panic!("expected signer 1 to abort, found {:?}", r.err()),
+    }
+
+    // A wrong share of delta gives the wrong nonce point, which is only detected
+    let (states, mut broadcasts) = round3(
+        round2(round1::<Secp256k1Scalar, _>(&shares, &public_key, &signers, keys).unwrap())
+            .unwrap(),
+    )
+    .unwrap();
+    let mut bytes = broadcasts[1].to_bytes();
+    bytes[35] ^= 1;
+    broadcasts[1] = Round3Broadcast::try_from(bytes.as_slice()).unwrap();
+    match presign(round5(round4((states, broadcasts)).unwrap()).unwrap()) {
+        Err(SharingError::EcdsaConsistencyCheck) => {}
+        r => panic!("expected a consistency failure, found {:?}", r.err()),
+    }
+
+    // Fewer signers than the threshold
+    match run_presign::<Secp256k1Scalar, _>(&shares, &public_key, &[3, 5], keys) {
+        Err(SharingError::EcdsaConsistencyCheck) => {}
+        r => panic!("expected a consistency failure, found {:?}", r.err()),
+    }
+}
+
+fn invalid_signers(keys: &[DecryptionKey]) {
+    let (shares, public_key) = secp256k1_dkg();
+    let new = |signers: &[u32]| {
+        SignerRound1::<Secp256k1Scalar, _>::new(
+            &mut OsRng,
+            &shares[0],
+            &public_key,
+            signers,
+            &keys[0],
+        )
+        .map(|_| ())
+    };
+    assert!(new(&[1, 2, 3]).is_ok());
+    for signers in &[&[2, 3][..], &[1, 1, 2], &[1], &[0, 1, 2]] {
+        match new(signers) {
+            Err(SharingError::EcdsaInvalidSigner) => {}
+            r => panic!("expected an invalid signer set, found {:?}", r),
+        }
+    }
+
+    // Messages from signers outside the session
+    let (states, mut broadcasts) =
+        round1::<Secp256k1Scalar, _>(&shares, &public_key, &[1, 2, 3], keys).unwrap();
+    let (_, outsider) = round1::<Secp256k1Scalar, _>(&shares, &public_key, &[1, 4], keys).unwrap();
+    broadcasts.push(outsider[1].clone());
+    match round2((states, broadcasts)) {
+        Err(SharingError::EcdsaInvalidSigner) => {}
+        r => panic!("expected an invalid signer, found {:?}", r.err()),
+    }
+
+    // Round two messages for someone else
+    let (states, messages) =
+        round2(round1::<Secp256k1Scalar, _>(&shares, &public_key, &[1, 2, 3], keys).unwrap())
+            .unwrap();
+    let mut states = states.into_iter();
+    match states.next().unwrap().receive(&messages) {
+        Err(SharingError::EcdsaInvalidSigner) => {}
+        r => panic!("expected an invalid signer, found {:?}", r.err()),
+    }
+}
+
+fn paillier_keys(keys: &[DecryptionKey]) {
+    assert!(DecryptionKey::generate(&mut OsRng, MIN_MODULUS_BITS / 2).is_err());
+    assert_eq!(keys[0].encryption_key().modulus_bits(), MIN_MODULUS_BITS);
+
+    let bytes = keys[0].to_bytes();
+    let parsed = DecryptionKey::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(parsed.encryption_key(), keys[0].encryption_key());
+    assert!(DecryptionKey::try_from(&bytes[1..]).is_err());
+    let mut composite = bytes;
+    let last = composite.len() - 1;
+    composite[last] ^= 2;
+    assert!(DecryptionKey::try_from(composite.as_slice()).is_err());
+
+    let bytes = keys[0].encryption_key().to_bytes();
+    assert_eq!(
+        &EncryptionKey::try_from(bytes.as_slice()).unwrap(),
+        keys[0].encryption_key()
+    );
+    assert!(EncryptionKey::try_from(&bytes[1..]).is_err());
+    let mut even = bytes.clone();
+    let last = even.len() - 1;
+    even[last] ^= 1;
+    assert!(EncryptionKey::try_from(even.as_slice()).is_err());
+    let mut padded = vec![0u8];
+    padded.extend_from_slice(&bytes);
+    assert!(EncryptionKey::try_from(padded.as_slice()).is_err());
+
+    // Round one broadcasts with a nonce that isn't a valid ciphertext
+    let (shares, public_key) = secp256r1_split();
+    let (_, broadcasts) =
+        round1::<Secp256r1Scalar, Secp256r1Point>(&shares, &public_key, &[1, 2], keys).unwrap();
+    let mut zero = broadcasts[0].to_bytes();
+    // sender, commitment, key length and the key
+    let nonce = 4 + 32 + 4 + MIN_MODULUS_BITS / 8;
+    for b in &mut zero[nonce..] {
+        *b = 0;
+    }
+    assert!(Round1Broadcast::try_from(zero.as_slice()).is_err());
+}
+
+fn main() {
+    let keys = (0..THRESHOLD + 1)
+        .map(|_| DecryptionKey::generate(&mut OsRng, MIN_MODULUS_BITS).unwrap())
+        .collect::<Vec<_>>();
+    sign_secp256k1(&keys);
+    sign_secp256r1(&keys);
+    presign_ahead(&keys);
+    identifiable_aborts(&keys);
+    invalid_signers(&keys);
+    paillier_keys(&keys);
+}
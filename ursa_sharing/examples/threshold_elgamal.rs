@@ -0,0 +1,160 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use ursa_sharing::{
+    dleq::DleqProof,
+    elgamal,
+    error::SharingError,
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar},
+    shamir::{lagrange_interpolate_points, Scheme as ShamirScheme, Share},
+    transcript::Transcript,
+    Group,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+const PROOF_LABEL: &[u8] = b"threshold elgamal partial decryption";
+
+type Ciphertext = elgamal::Ciphertext<Scalar, Point>;
+
+/// A server's partial decryption `c1^s_i` and the proof that it used its share
+struct PartialDecryption {
+    identifier: u32,
+    point: Point,
+    proof: DleqProof<Scalar>,
+}
+
+fn transcript(identifier: u32) -> Transcript {
+    let mut transcript = Transcript::new(PROOF_LABEL);
+    transcript.append_u64(b"server", identifier as u64);
+    transcript
+}
+
+fn partial_decrypt(
+    share: &Share,
+    verifier: &FeldmanVerifier<Scalar, Point>,
+    c: &Ciphertext,
+) -> PartialDecryption {
+    let value = Scalar::from_bytes(share.value()).unwrap();
+    let mut point = *c.c1();
+    point.scalar_mul_assign(&value);
+    let key = verifier.verification_share(share.identifier()).unwrap();
+    let proof = DleqProof::prove(
+        &mut OsRng,
+        &mut transcript(share.identifier()),
+        &value,
+        (&verifier.g, &key),
+        (c.c1(), &point),
+    )
+    .unwrap();
+    PartialDecryption {
+        identifier: share.identifier(),
+        point,
+        proof,
+    }
+}
+
+/// Check the partial decryptions and combine them into the plaintext
+fn decrypt(
+    scheme: &ShamirScheme,
+    verifier: &FeldmanVerifier<Scalar, Point>,
+    c: &Ciphertext,
+    partials: &[PartialDecryption],
+) -> Result<Point, SharingError> {
+    let mut points = Vec::new();
+    for p in partials {
+        let key = verifier.verification_share(p.identifier)?;
+        p.proof.verify(
+            &mut transcript(p.identifier),
+            (&verifier.g, &key),
+            (c.c1(), &p.point),
+        )?;
+        points.push((p.identifier, p.point));
+    }
+    // c1^x without x ever being reconstructed
+    let shared = scheme.combine_commitments(&points)?;
+    Ok(c.decrypt_with(&shared))
+}
+
+fn main() {
+    let secret = Scalar::random(&mut OsRng);
+    let (verifier, shares) = FeldmanScheme::new(THRESHOLD, LIMIT)
+        .unwrap()
+        .split_secret::<Scalar, Point>(&mut OsRng, &secret, Some(Point::generator()))
+        .unwrap();
+    let scheme = ShamirScheme::new(THRESHOLD, LIMIT).unwrap();
+    let public_key = verifier.commitments[0].to_bytes();
+
+    // The verification shares interpolate to the public key
+    let keys = (1..=LIMIT as u32)
+        .map(|i| (i, verifier.verification_share(i).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lagrange_interpolate_points(&keys[1..4]).unwrap().to_bytes(),
+        public_key
+    );
+    assert_eq!(
+        scheme.combine_commitments(&keys[..]).unwrap().to_bytes(),
+        public_key
+    );
+    let public_key = Point::from_bytes(public_key).unwrap();
+
+    let message = Point::random(&mut OsRng);
+    let c = Ciphertext::encrypt(&mut OsRng, &Point::generator(), &public_key, &message).unwrap();
+    let partials = shares
+        .iter()
+        .map(|s| partial_decrypt(s, &verifier, &c))
+        .collect::<Vec<_>>();
+
+    // Any threshold of servers decrypts
+    for range in &[0..3, 2..5, 0..5] {
+        let decrypted = decrypt(&scheme, &verifier, &c, &partials[range.clone()]).unwrap();
+        assert_eq!(decrypted.to_bytes(), message.to_bytes());
+    }
+
+    // Too few or repeated partial decryptions
+    match decrypt(&scheme, &verifier, &c, &partials[..2]) {
+        Err(SharingError::ShareMinThreshold) => {}
+        r => panic!("expected too few partial decryptions, found {:?}", r.err()),
+    }
+    let dup = [
+        (1, partials[0].point),
+        (1, partials[0].point),
+        (2, partials[1].point),
+    ];
+    match scheme.combine_commitments(&dup) {
+        Err(SharingError::ShareDuplicateIdentifier) => {}
+        r => panic!("expected a duplicate identifier, found {:?}", r.err()),
+    }
+    assert!(lagrange_interpolate_points::<Scalar, Point>(&[]).is_err());
+    assert!(lagrange_interpolate_points::<Scalar, Point>(&[(0, partials[0].point)]).is_err());
+    assert!(lagrange_interpolate_points::<Scalar, Point>(&[(1, Point::zero())]).is_err());
+
+    // A server that doesn't use its share is caught by its proof
+    let mut bad = partial_decrypt(&shares[1], &verifier, &c);
+    bad.point.add_assign(&Point::generator());
+    let mixed = [
+        partial_decrypt(&shares[0], &verifier, &c),
+        bad,
+        partial_decrypt(&shares[2], &verifier, &c),
+    ];
+    assert!(decrypt(&scheme, &verifier, &c, &mixed).is_err());
+    let unchecked = mixed
+        .iter()
+        .map(|p| (p.identifier, p.point))
+        .collect::<Vec<_>>();
+    let wrong = c.decrypt_with(&scheme.combine_commitments(&unchecked).unwrap());
+    assert_ne!(wrong.to_bytes(), message.to_bytes());
+}
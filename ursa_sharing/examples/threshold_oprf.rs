@@ -0,0 +1,224 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::{DetectionError, SharingError},
+    feldman::Scheme as FeldmanScheme,
+    hash_to_curve::HashToScalar,
+    oprf::{OprfGroup, ServerKey, VoprfServer},
+    refresh::Reshare,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    shamir::Share,
+    threshold_oprf::{
+        KeyRefresh, PartialEvaluation, RefreshDealing, ThresholdOprfClient, ThresholdOprfServer,
+    },
+    Strictness,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+fn threshold_oprf<S: HashToScalar, R: OprfGroup<S>>() {
+    let key = ServerKey::<S, R>::random(&mut OsRng);
+    let secret = S::from_bytes(key.to_bytes()).unwrap();
+    let scheme = FeldmanScheme::new(THRESHOLD, LIMIT).unwrap();
+    let (verifier, shares) = scheme
+        .split_secret::<S, R>(&mut OsRng, &secret, Some(R::generator()))
+        .unwrap();
+    let servers = shares
+        .iter()
+        .map(|s| ThresholdOprfServer::new(&verifier, s).unwrap())
+        .collect::<Vec<_>>();
+    let client = ThresholdOprfClient::new(&verifier).unwrap();
+    assert_eq!(client.threshold(), THRESHOLD);
+    let whole = VoprfServer::new(key);
+    assert_eq!(
+        client.public_key().to_bytes(),
+        whole.public_key().to_bytes()
+    );
+
+    // Any threshold of servers give the VOPRF output of the whole key
+    let password = b"correct horse battery staple";
+    let expected = whole.evaluate(password).unwrap();
+    let (blind, blinded) = client.blind(&mut OsRng, password).unwrap();
+    let partials = servers
+        .iter()
+        .map(|s| s.blind_evaluate(&mut OsRng, &blinded))
+        .collect::<Vec<_>>();
+    for partial in &partials {
+        assert!(client.verify_partial(&blinded, partial).is_ok());
+    }
+    let output = client
+        .finalize(password, &blind, &blinded, &partials[..3])
+        .unwrap();
+    assert_eq!(output, expected);
+    let output = client
+        .finalize(password, &blind, &blinded, &partials[2..])
+        .unwrap();
+    assert_eq!(output, expected);
+    let mut many = partials.clone();
+    many.insert(1, partials[0].clone());
+    assert_eq!(
+        client.finalize(password, &blind, &blinded, &many).unwrap(),
+        expected
+    );
+
+    // Bad servers are named, and too few evaluations don't finalize
+    let (_, other) = client.blind(&mut OsRng, password).unwrap();
+    let wrong = servers[3].blind_evaluate(&mut OsRng, &other);
+    let forged = PartialEvaluation::<S, R>::try_from(
+        {
+            let mut bytes = partials[4].to_bytes();
+            bytes[..4].copy_from_slice(&2u32.to_be_bytes());
+            bytes
+        }
+        .as_slice(),
+    )
+    .unwrap();
+    let mixed = [partials[0].clone(), wrong, forged, partials[2].clone()];
+    assert!(matches!(
+        client.finalize(password, &blind, &blinded, &mixed),
+        Err(DetectionError::Cheaters(ref ids)) if ids == &[4, 2]
+    ));
+    assert!(matches!(
+        client.verify_partial(&blinded, &mixed[1]),
+        Err(SharingError::OprfPartialEvaluationInvalid(4))
+    ));
+    assert!(matches!(
+        client.finalize(password, &blind, &blinded, &partials[..2]),
+        Err(DetectionError::Sharing(SharingError::ShareMinThreshold))
+    ));
+    let unrelated = Share::new(1, S::random(&mut OsRng).to_bytes());
+    assert!(ThresholdOprfServer::new(&verifier, &unrelated).is_err());
+
+    // Serialization
+    let bytes = partials[1].to_bytes();
+    assert_eq!(bytes.len(), PartialEvaluation::<S, R>::size());
+    let parsed = PartialEvaluation::<S, R>::from_bytes_with(&bytes, Strictness::Strict).unwrap();
+    assert!(client.verify_partial(&blinded, &parsed).is_ok());
+    assert!(PartialEvaluation::<S, R>::try_from(&bytes[1..]).is_err());
+    let mut zero = bytes.clone();
+    zero[..4].copy_from_slice(&[0u8; 4]);
+    assert!(PartialEvaluation::<S, R>::try_from(zero.as_slice()).is_err());
+    let mut tampered = bytes;
+    tampered[PartialEvaluation::<S, R>::size() - 1] ^= 1;
+    assert!(PartialEvaluation::<S, R>::try_from(tampered.as_slice())
+        .map_or(true, |p| client.verify_partial(&blinded, &p).is_err()));
+
+    // The commitments must use the suite's generator
+    let (random, _) = scheme
+        .split_secret::<S, R>(&mut OsRng, &secret, None)
+        .unwrap();
+    assert!(matches!(
+        ThresholdOprfClient::new(&random),
+        Err(SharingError::OprfInvalidGenerator)
+    ));
+
+    // Proactive refresh of the shares to three of the old servers and two new ones
+    let holders = [1, 2, 4, 6, 7];
+    let refresh = KeyRefresh::new(&verifier, &holders).unwrap();
+    assert_eq!(refresh.identifiers(), &holders);
+    let mut dealings = Vec::new();
+    let mut reshares = Vec::new();
+    for share in &shares[1..4] {
+        let (dealing, r) = refresh.deal(&mut OsRng, share).unwrap();
+        assert_eq!(r.len(), holders.len());
+        dealings.push(dealing);
+        reshares.extend(r);
+    }
+    let new_verifier = refresh.verifier(&dealings).unwrap();
+    assert_eq!(
+        new_verifier.commitments[0].to_bytes(),
+        verifier.commitments[0].to_bytes()
+    );
+    let new_shares = holders
+        .iter()
+        .map(|id| refresh.combine(*id, &dealings, &reshares).unwrap())
+        .collect::<Vec<_>>();
+    for (share, old) in new_shares.iter().zip(shares.iter()) {
+        assert!(new_verifier.verify_share(share).is_ok());
+        if share.identifier() == old.identifier() {
+            assert_ne!(share.value(), old.value());
+        }
+    }
+    let client = ThresholdOprfClient::new(&new_verifier).unwrap();
+    let (blind, blinded) = client.blind(&mut OsRng, password).unwrap();
+    let partials = new_shares[2..]
+        .iter()
+        .map(|s| {
+            ThresholdOprfServer::new(&new_verifier, s)
+                .unwrap()
+                .blind_evaluate(&mut OsRng, &blinded)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        client
+            .finalize(password, &blind, &blinded, &partials)
+            .unwrap(),
+        expected
+    );
+    // Old shares don't verify against the new commitments
+    assert!(ThresholdOprfServer::new(&new_verifier, &shares[0]).is_err());
+
+    // Dealings that don't commit to the dealer's key share or don't match the reshares
+    let (dishonest, bad_reshares) = refresh.deal(&mut OsRng, &shares[0]).unwrap();
+    let mut bad_dealings = dealings.clone();
+    bad_dealings[0] = RefreshDealing::try_from(
+        {
+            let mut bytes = dishonest.to_bytes();
+            bytes[..4].copy_from_slice(&2u32.to_be_bytes());
+            bytes
+        }
+        .as_slice(),
+    )
+    .unwrap();
+    assert!(matches!(
+        refresh.verifier(&bad_dealings),
+        Err(DetectionError::Cheaters(ref ids)) if ids == &[2]
+    ));
+    let mut swapped = reshares
+        .iter()
+        .filter(|r| r.dealer() != 3)
+        .cloned()
+        .collect::<Vec<_>>();
+    swapped.extend(bad_reshares.iter().map(|r| {
+        let mut bytes = r.to_bytes();
+        bytes[..4].copy_from_slice(&3u32.to_be_bytes());
+        Reshare::try_from(bytes.as_slice()).unwrap()
+    }));
+    assert!(matches!(
+        refresh.combine(6, &bad_dealings, &swapped),
+        Err(DetectionError::Cheaters(ref ids)) if ids == &[2, 3]
+    ));
+    assert!(matches!(
+        refresh.combine(6, &dealings[..2], &reshares),
+        Err(DetectionError::Sharing(SharingError::ShareMinThreshold))
+    ));
+    assert!(refresh.combine(3, &dealings, &reshares).is_err());
+    let bytes = dealings[1].to_bytes();
+    let parsed = RefreshDealing::<S, R>::from_bytes_with(&bytes, Strictness::Strict).unwrap();
+    assert_eq!(parsed.dealer(), 3);
+    assert!(RefreshDealing::<S, R>::try_from(&bytes[..3]).is_err());
+    assert!(KeyRefresh::new(&verifier, &[1, 2]).is_err());
+    assert!(KeyRefresh::new(&verifier, &[1, 2, 2]).is_err());
+}
+
+fn main() {
+    println!("ristretto255");
+    threshold_oprf::<Ristretto25519Scalar, Ristretto25519Point>();
+    println!("P-256");
+    threshold_oprf::<Secp256r1Scalar, Secp256r1Point>();
+}
@@ -0,0 +1,141 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+use std::convert::TryFrom;
+use ursa_sharing::{
+    error::{DetectionError, SharingError},
+    feldman::Scheme as FeldmanScheme,
+    hash_to_curve::HashToCurve,
+    ristretto::{Ristretto25519Point, Ristretto25519Scalar},
+    secp256r1::{Secp256r1Point, Secp256r1Scalar},
+    shamir::Share,
+    threshold_vrf::{hash_input, PartialEvaluation, ThresholdVrf, VrfProof},
+    Field, Group, Strictness,
+};
+
+const THRESHOLD: usize = 3;
+const LIMIT: usize = 5;
+
+fn threshold_vrf<S: Field, R: HashToCurve<S>>(generator: R) {
+    let secret = S::random(&mut OsRng);
+    let scheme = FeldmanScheme::new(THRESHOLD, LIMIT).unwrap();
+    let (verifier, shares) = scheme
+        .split_secret::<S, R>(&mut OsRng, &secret, Some(generator))
+        .unwrap();
+    let vrf = ThresholdVrf::new(&verifier).unwrap();
+    assert_eq!(vrf.threshold(), THRESHOLD);
+
+    let input = b"epoch 42 leader";
+    let partials = shares
+        .iter()
+        .map(|s| vrf.evaluate(&mut OsRng, s, input).unwrap())
+        .collect::<Vec<_>>();
+    for partial in &partials {
+        assert!(vrf.verify_partial(input, partial).is_ok());
+        assert!(vrf.verify_partial(b"another input", partial).is_err());
+    }
+
+    // Any threshold of nodes give the output of the whole key
+    let (output, proof) = vrf.combine(input, &partials[..3]).unwrap();
+    let (other, _) = vrf.combine(input, &partials[2..]).unwrap();
+    assert_eq!(output, other);
+    let mut gamma = hash_input::<S, R>(input);
+    gamma.scalar_mul_assign(&secret);
+    let mut hasher = Sha512::new();
+    hasher.input(b"ursa threshold vrf v1 output");
+    hasher.input((input.len() as u64).to_be_bytes());
+    hasher.input(input);
+    hasher.input(gamma.to_bytes());
+    assert_eq!(output, hasher.result().to_vec());
+    assert_eq!(proof.partials().len(), THRESHOLD);
+    assert_eq!(vrf.verify(input, &proof).unwrap(), output);
+    assert!(vrf.verify(b"another input", &proof).is_err());
+
+    // Extra and duplicated partial evaluations are dropped
+    let mut many = partials.clone();
+    many.insert(1, partials[0].clone());
+    let (same, proof) = vrf.combine(input, &many).unwrap();
+    assert_eq!(same, output);
+    assert_eq!(
+        proof
+            .partials()
+            .iter()
+            .map(|p| p.identifier())
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+
+    // Bad nodes are named, and too few evaluations don't combine
+    let wrong = vrf
+        .evaluate(&mut OsRng, &shares[3], b"another input")
+        .unwrap();
+    let forged = PartialEvaluation::<S, R>::try_from(
+        {
+            let mut bytes = partials[4].to_bytes();
+            bytes[..4].copy_from_slice(&2u32.to_be_bytes());
+            bytes
+        }
+        .as_slice(),
+    )
+    .unwrap();
+    let mixed = [partials[0].clone(), wrong, forged, partials[2].clone()];
+    assert!(matches!(
+        vrf.combine(input, &mixed),
+        Err(DetectionError::Cheaters(ref ids)) if ids == &[4, 2]
+    ));
+    assert!(matches!(
+        vrf.verify_partial(input, &mixed[1]),
+        Err(SharingError::VrfPartialEvaluationInvalid(4))
+    ));
+    assert!(matches!(
+        vrf.combine(input, &partials[..2]),
+        Err(DetectionError::Sharing(SharingError::ShareMinThreshold))
+    ));
+    let unrelated = Share::new(1, S::random(&mut OsRng).to_bytes());
+    let bad = vrf.evaluate(&mut OsRng, &unrelated, input).unwrap();
+    assert!(vrf.verify_partial(input, &bad).is_err());
+
+    // Serialization
+    let bytes = proof.to_bytes();
+    assert_eq!(bytes.len(), THRESHOLD * PartialEvaluation::<S, R>::size());
+    let parsed = VrfProof::<S, R>::from_bytes_with(&bytes, Strictness::Strict).unwrap();
+    assert_eq!(vrf.verify(input, &parsed).unwrap(), output);
+    let short =
+        VrfProof::<S, R>::try_from(&bytes[..2 * PartialEvaluation::<S, R>::size()]).unwrap();
+    assert!(matches!(
+        vrf.verify(input, &short),
+        Err(SharingError::ShareMinThreshold)
+    ));
+    assert!(VrfProof::<S, R>::try_from(&bytes[1..]).is_err());
+    assert!(VrfProof::<S, R>::try_from(&[][..]).is_err());
+    let mut tampered = bytes;
+    tampered[10] ^= 1;
+    assert!(VrfProof::<S, R>::try_from(tampered.as_slice())
+        .map_or(true, |p| vrf.verify(input, &p).is_err()));
+    let mut zero = partials[0].to_bytes();
+    zero[..4].copy_from_slice(&[0u8; 4]);
+    assert!(PartialEvaluation::<S, R>::try_from(zero.as_slice()).is_err());
+}
+
+fn main() {
+    println!("ristretto255");
+    threshold_vrf::<Ristretto25519Scalar, Ristretto25519Point>(Ristretto25519Point::generator());
+    // Any generator works, the public keys just have to use the same one
+    threshold_vrf::<Ristretto25519Scalar, Ristretto25519Point>(Ristretto25519Point::random(
+        &mut OsRng,
+    ));
+    println!("P-256");
+    threshold_vrf::<Secp256r1Scalar, Secp256r1Point>(Secp256r1Point::generator());
+}
@@ -0,0 +1,184 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    bls12_381::Bls12381Scalar,
+    error::SharingError,
+    ibe,
+    shamir::{Scheme, Share},
+    threshold_bls::{PublicKey, PublicKeyShare},
+    timelock::*,
+    Group,
+};
+
+const DEADLINE: u64 = 1_000;
+
+fn committee() -> (PublicKey, Vec<Share>) {
+    let secret = Bls12381Scalar::random(&mut OsRng);
+    let shares = Scheme::new(3, 5)
+        .unwrap()
+        .split_secret(&mut OsRng, &secret)
+        .unwrap();
+    (PublicKey::from_secret_key(&secret), shares)
+}
+
+fn release(shares: &[Share], epoch: u64) -> EpochKey {
+    let parts = shares
+        .iter()
+        .map(|s| release_share(s, epoch).unwrap())
+        .collect::<Vec<_>>();
+    for (part, share) in parts.iter().zip(shares.iter()) {
+        part.verify_hashed(
+            &PublicKeyShare::from_share(share).unwrap(),
+            &hash_epoch(epoch),
+        )
+        .unwrap();
+    }
+    EpochKey::combine(epoch, 3, &parts).unwrap()
+}
+
+fn sealed_bids() {
+    let (public_key, shares) = committee();
+    let bids: [&[u8]; 3] = [b"alice bids 120", b"bob bids 95", b""];
+    let sealed = bids
+        .iter()
+        .map(|bid| encrypt(&mut OsRng, &public_key, DEADLINE, bid).unwrap())
+        .collect::<Vec<_>>();
+
+    // Before the deadline only earlier epochs are released
+    let early = release(&shares[..3], DEADLINE - 1);
+    early.verify(&public_key).unwrap();
+    for (ciphertext, _) in &sealed {
+        assert_eq!(ciphertext.epoch(), DEADLINE);
+        assert!(matches!(
+            ciphertext.decrypt(&early),
+            Err(SharingError::TimelockWrongEpoch(e)) if e == DEADLINE - 1
+        ));
+    }
+
+    // Any three nodes open every bid at the deadline
+    let key = release(&shares[2..], DEADLINE);
+    key.verify(&public_key).unwrap();
+    assert_eq!(key.epoch(), DEADLINE);
+    for ((ciphertext, _), bid) in sealed.iter().zip(bids.iter()) {
+        assert_eq!(ciphertext.decrypt(&key).unwrap(), *bid);
+    }
+    let same = release(&shares[..3], DEADLINE);
+    assert_eq!(same.to_bytes(), key.to_bytes());
+
+    // Too few nodes or another committee give keys that don't verify or decrypt
+    let parts = shares[..2]
+        .iter()
+        .map(|s| release_share(s, DEADLINE).unwrap())
+        .collect::<Vec<_>>();
+    let wrong = EpochKey::combine(DEADLINE, 2, &parts).unwrap();
+    assert!(matches!(
+        wrong.verify(&public_key),
+        Err(SharingError::TimelockInvalidEpochKey)
+    ));
+    assert!(matches!(
+        sealed[0].0.decrypt(&wrong),
+        Err(SharingError::TimelockDecryptionFailed)
+    ));
+    let (_, others) = committee();
+    let other = release(&others[..3], DEADLINE);
+    assert!(other.verify(&public_key).is_err());
+    assert!(sealed[0].0.decrypt(&other).is_err());
+}
+
+fn openings() {
+    let (public_key, shares) = committee();
+    let (ciphertext, opening) = encrypt(&mut OsRng, &public_key, DEADLINE, b"bid 42").unwrap();
+
+    // The bidder opens the bid before the deadline, to the same plaintext it decrypts to
+    ciphertext
+        .verify_opening(&public_key, &opening, b"bid 42")
+        .unwrap();
+    assert!(matches!(
+        ciphertext.verify_opening(&public_key, &opening, b"bid 43"),
+        Err(SharingError::TimelockInvalidOpening)
+    ));
+    let (other_ciphertext, other_opening) =
+        encrypt(&mut OsRng, &public_key, DEADLINE, b"bid 42").unwrap();
+    assert_ne!(other_ciphertext.to_bytes(), ciphertext.to_bytes());
+    assert!(ciphertext
+        .verify_opening(&public_key, &other_opening, b"bid 42")
+        .is_err());
+    let (other_key, _) = committee();
+    assert!(ciphertext
+        .verify_opening(&other_key, &opening, b"bid 42")
+        .is_err());
+    let key = release(&shares[1..4], DEADLINE);
+    assert_eq!(ciphertext.decrypt(&key).unwrap(), b"bid 42");
+
+    // Plain identity-based encryption to the epoch decrypts, but isn't a commitment
+    let uncommitted = ibe::encrypt(
+        &mut OsRng,
+        &public_key,
+        &epoch_identity(DEADLINE),
+        &[&opening.to_bytes()[..], b"bid 42"].concat(),
+    )
+    .unwrap();
+    let uncommitted =
+        Ciphertext::try_from(&[&DEADLINE.to_be_bytes()[..], &uncommitted.to_bytes()].concat()[..])
+            .unwrap();
+    assert!(matches!(
+        uncommitted.decrypt(&key),
+        Err(SharingError::TimelockDecryptionFailed)
+    ));
+    assert!(uncommitted
+        .verify_opening(&public_key, &opening, b"bid 42")
+        .is_err());
+}
+
+fn serialization() {
+    let (public_key, shares) = committee();
+    let (ciphertext, opening) = encrypt(&mut OsRng, &public_key, 7, b"sealed").unwrap();
+    let bytes = ciphertext.to_bytes();
+    assert_eq!(&bytes[..8], &7u64.to_be_bytes());
+    let ciphertext = Ciphertext::try_from(&bytes[..]).unwrap();
+    let opening = Opening::try_from(&opening.to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", opening), "Opening(..)");
+    ciphertext
+        .verify_opening(&public_key, &opening, b"sealed")
+        .unwrap();
+    let key = EpochKey::try_from(&release(&shares[..3], 7).to_bytes()[..]).unwrap();
+    assert_eq!(format!("{:?}", key), "EpochKey(7, ..)");
+    key.verify(&public_key).unwrap();
+    assert_eq!(ciphertext.decrypt(&key).unwrap(), b"sealed");
+
+    for i in [0, 9, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[i] ^= 1;
+        if let Ok(tampered) = Ciphertext::try_from(&tampered[..]) {
+            assert!(tampered.decrypt(&key).is_err());
+            assert!(tampered
+                .verify_opening(&public_key, &opening, b"sealed")
+                .is_err());
+        }
+    }
+    assert!(Ciphertext::try_from(&bytes[..7]).is_err());
+    assert!(EpochKey::try_from(&key.to_bytes()[1..]).is_err());
+    assert!(Opening::try_from(&[0u8; 31][..]).is_err());
+}
+
+fn main() {
+    println!("Sealed bids");
+    sealed_bids();
+    println!("Openings");
+    openings();
+    println!("Serialization");
+    serialization();
+}
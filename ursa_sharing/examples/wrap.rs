@@ -0,0 +1,157 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rand::rngs::OsRng;
+use std::convert::TryFrom;
+use ursa_sharing::{
+    ed25519::Ed25519Scalar,
+    error::SharingError,
+    shamir::Scheme,
+    wrap::{wrap_share, WrapParams, WrappedShare, MAX_MEMORY_KIB},
+    Group,
+};
+
+/// Cheaper than the defaults to keep the example fast
+const PARAMS: WrapParams = WrapParams {
+    memory_kib: 1024,
+    iterations: 1,
+    parallelism: 1,
+};
+
+fn split_wrapped_combine() {
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let passphrase = b"correct horse battery staple";
+
+    let wrapped = scheme
+        .split_wrapped(&mut OsRng, &secret, passphrase, PARAMS)
+        .unwrap();
+    // Same passphrase, different salts
+    assert_ne!(wrapped[0].to_bytes()[13..29], wrapped[1].to_bytes()[13..29]);
+
+    let parsed = wrapped
+        .iter()
+        .map(|w| WrappedShare::try_from(w.to_bytes().as_slice()).unwrap())
+        .collect::<Vec<WrappedShare>>();
+    assert_eq!(parsed[2].identifier(), 3);
+    assert_eq!(parsed[2].params(), PARAMS);
+    let res = scheme.combine_wrapped::<Ed25519Scalar, Ed25519Scalar>(&parsed[1..], passphrase);
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+
+    let share = parsed[0].unwrap(passphrase).unwrap();
+    assert_eq!(share.identifier(), 1);
+
+    // The default parameters are used when none are chosen
+    let shares = scheme.split_secret(&mut OsRng, &secret).unwrap();
+    let wrapped = wrap_share(&mut OsRng, &shares[0], passphrase, WrapParams::default()).unwrap();
+    assert_eq!(
+        wrapped.unwrap(passphrase).unwrap().value(),
+        shares[0].value()
+    );
+}
+
+fn wrong_passphrase() {
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let wrapped = scheme
+        .split_wrapped(&mut OsRng, &secret, b"hunter2", PARAMS)
+        .unwrap();
+    match scheme.combine_wrapped::<Ed25519Scalar, Ed25519Scalar>(&wrapped, b"hunter3") {
+        Err(SharingError::ShareDecryptionFailed) => {}
+        r => panic!("expected a decryption error, found {:?}", r),
+    }
+    // Shares wrapped under different passphrases are unwrapped one by one
+    let other = wrap_share(
+        &mut OsRng,
+        &wrapped[1].unwrap(b"hunter2").unwrap(),
+        b"hunter3",
+        PARAMS,
+    )
+    .unwrap();
+    let shares = [
+        wrapped[0].unwrap(b"hunter2").unwrap(),
+        other.unwrap(b"hunter3").unwrap(),
+    ];
+    let res = scheme.combine_shares::<Ed25519Scalar, Ed25519Scalar>(&shares);
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+}
+
+fn combine_wrapped_checks_before_unwrapping() {
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let wrapped = scheme
+        .split_wrapped(&mut OsRng, &secret, b"hunter2", PARAMS)
+        .unwrap();
+    match scheme.combine_wrapped::<Ed25519Scalar, Ed25519Scalar>(&wrapped[..1], b"hunter2") {
+        Err(SharingError::ShareMinThreshold) => {}
+        r => panic!("expected a threshold error, found {:?}", r),
+    }
+    let dups = [wrapped[0].clone(), wrapped[0].clone()];
+    match scheme.combine_wrapped::<Ed25519Scalar, Ed25519Scalar>(&dups, b"hunter2") {
+        Err(SharingError::ShareDuplicateIdentifier) => {}
+        r => panic!("expected a duplicate error, found {:?}", r),
+    }
+}
+
+fn envelope_tampering() {
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut OsRng);
+    let wrapped = scheme
+        .split_wrapped(&mut OsRng, &secret, b"hunter2", PARAMS)
+        .unwrap();
+    let bytes = wrapped[0].to_bytes();
+
+    // Every byte of the envelope is authenticated
+    for i in [0, 4, 8, 12, 20, 35, 44, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[i] ^= 1;
+        let res = WrappedShare::try_from(tampered.as_slice()).and_then(|w| w.unwrap(b"hunter2"));
+        assert!(res.is_err(), "byte {} is not authenticated", i);
+    }
+
+    match WrappedShare::try_from(&bytes[..40]) {
+        Err(SharingError::ShareInvalidEncoding) => {}
+        r => panic!("expected an encoding error, found {:?}", r),
+    }
+    let mut other_version = bytes.clone();
+    other_version[0] = 2;
+    match WrappedShare::try_from(other_version.as_slice()) {
+        Err(SharingError::ShareUnsupportedVersion(2)) => {}
+        r => panic!("expected a version error, found {:?}", r),
+    }
+
+    // A forged envelope can't ask for unbounded memory
+    let mut greedy = bytes;
+    greedy[1..5].copy_from_slice(&(MAX_MEMORY_KIB + 1).to_be_bytes());
+    match WrappedShare::try_from(greedy.as_slice()) {
+        Err(SharingError::ShareWrapParamsInvalid) => {}
+        r => panic!("expected a parameter error, found {:?}", r),
+    }
+    let share = scheme.split_secret(&mut OsRng, &secret).unwrap();
+    let no_passes = WrapParams {
+        iterations: 0,
+        ..PARAMS
+    };
+    assert!(wrap_share(&mut OsRng, &share[0], b"hunter2", no_passes).is_err());
+}
+
+fn main() {
+    println!("Split wrapped combine");
+    split_wrapped_combine();
+    println!("Wrong passphrase");
+    wrong_passphrase();
+    println!("Combine wrapped checks before unwrapping");
+    combine_wrapped_checks_before_unwrapping();
+    println!("Envelope tampering");
+    envelope_tampering();
+}
@@ -0,0 +1,84 @@
+#ifndef __ursa__sharing__included__
+#define __ursa__sharing__included__
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum {
+    URSA_SHARING_SUCCESS = 0,
+    URSA_SHARING_INVALID_PARAM = 1,
+    URSA_SHARING_UNSUPPORTED_CURVE = 2,
+    URSA_SHARING_SHARING_ERROR = 3,
+    URSA_SHARING_INDEX_OUT_OF_RANGE = 4,
+    URSA_SHARING_PANIC = -1,
+    URSA_SHARING_INVALID_HANDLE = -1000,
+} ursa_sharing_error_t;
+
+/* Input bytes owned by the caller */
+struct ByteArray {
+    size_t length;
+    const uint8_t *data;
+};
+
+/* Output bytes owned by the library, free with ursa_sharing_bytebuffer_free */
+struct ByteBuffer {
+    int64_t len;
+    uint8_t *data;
+};
+
+/* Set by every call, free message with ursa_sharing_string_free */
+struct ExternError {
+    int32_t code;
+    char *message; /* note: nullable */
+};
+
+extern void ursa_sharing_bytebuffer_free(struct ByteBuffer buffer);
+extern void ursa_sharing_string_free(char *s);
+
+/* Curves are "ed25519", "ristretto25519", "secp256k1" or "secp256r1" */
+extern uint64_t ursa_sharing_split(const char *curve,
+                                   const struct ByteArray *secret,
+                                   uint32_t threshold,
+                                   uint32_t limit,
+                                   struct ExternError *err);
+
+extern struct ByteBuffer ursa_sharing_combine(const char *curve,
+                                              uint32_t threshold,
+                                              uint64_t shares,
+                                              struct ExternError *err);
+
+extern uint64_t ursa_sharing_split_bytes(const struct ByteArray *secret,
+                                         uint32_t threshold,
+                                         uint32_t limit,
+                                         struct ExternError *err);
+
+extern struct ByteBuffer ursa_sharing_combine_bytes(uint32_t threshold,
+                                                    uint64_t shares,
+                                                    struct ExternError *err);
+
+extern uint64_t ursa_sharing_shares_new(struct ExternError *err);
+
+extern void ursa_sharing_shares_add(uint64_t shares,
+                                    const struct ByteArray *share,
+                                    struct ExternError *err);
+
+extern void ursa_sharing_shares_free(uint64_t shares, struct ExternError *err);
+
+extern uint32_t ursa_sharing_shares_len(uint64_t shares, struct ExternError *err);
+
+extern struct ByteBuffer ursa_sharing_shares_get(uint64_t shares,
+                                                 uint32_t index,
+                                                 struct ExternError *err);
+
+extern uint32_t ursa_sharing_share_identifier(const struct ByteArray *share,
+                                              struct ExternError *err);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
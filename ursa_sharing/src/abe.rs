@@ -0,0 +1,794 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Ciphertext-policy attribute-based encryption over BLS12-381, a prototype.
+//!
+//! Data is encrypted under an [`AttributePolicy`], a tree of thresholds over
+//! attributes like `role=doctor`, and any [`SecretKey`] whose attributes satisfy the
+//! policy decrypts it. Keys are issued by the holder of the [`MasterKey`] and keys
+//! can't be combined: two users with `role=doctor` and `dept=oncology` each can't
+//! decrypt under `role=doctor AND dept=oncology` together.
+//!
+//! ```ignore
+//! let (public_key, master_key) = setup(&mut rng);
+//! let policy = AttributePolicy::parse("role=doctor AND (dept=oncology OR dept=radiology)")?;
+//! let ciphertext = encrypt(&mut rng, &public_key, &policy, b"patient record")?;
+//!
+//! let key = master_key.issue(&mut rng, &["role=doctor", "dept=oncology"]);
+//! assert_eq!(key.decrypt(&ciphertext)?, b"patient record");
+//! ```
+//!
+//! The scheme is Bethencourt, Sahai and Waters' CP-ABE
+//! (see <https://doi.org/10.1109/SP.2007.11>) moved to the asymmetric pairing with
+//! ciphertext components in G1 and key components in G2. As there, the secret is
+//! split with Shamir at every threshold of the policy and recombined in the target
+//! group. The scheme is a KEM: `e(g1, g2)^(alpha s)` is hashed with SHA-256 to a
+//! ChaCha20-Poly1305 key that seals the plaintext, with the policy and the ciphertext
+//! components as associated data. Attributes are hashed to G2 with the suite in
+//! [`DST`]. The policy of a ciphertext is public.
+//!
+//! This is a prototype: BSW07 is proven secure in the generic group model only and
+//! keys can't be revoked or delegated.
+
+use crate::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    dkg::Reader,
+    error::{SharingError, SharingResult},
+    field::lagrange_coefficients,
+    hash_to_curve::HashToCurve,
+    Field, Group, Polynomial,
+};
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use ff_zeroize::{Field as _, PrimeField};
+use pairing_plus::{
+    bls12_381::{Bls12, Fq12},
+    serdes::SerDes,
+    Engine,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// The version of the policy and ciphertext encodings
+pub const VERSION: u8 = 1;
+/// The deepest nesting of thresholds allowed
+pub const MAX_DEPTH: usize = 16;
+/// The hash to curve suite used for attributes
+pub const DST: &[u8] = b"URSA_CPABE_BLS12381G2_XMD:SHA-256_SSWU_RO_";
+/// The size of an encoded element of the target group
+pub const GT_BYTES: usize = 576;
+
+const KEY_DOMAIN: &[u8] = b"ursa_sharing cpabe key";
+
+const ATTRIBUTE: u8 = 0;
+const THRESHOLD: u8 = 1;
+
+/// A tree of thresholds over attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttributePolicy {
+    /// Satisfied by a key with this attribute
+    Attribute(String),
+    /// Satisfied when at least `threshold` of the members are
+    Threshold {
+        /// The number of members that must be satisfied
+        threshold: u32,
+        /// The attributes and nested thresholds
+        members: Vec<AttributePolicy>,
+    },
+}
+
+impl AttributePolicy {
+    /// A single attribute. Fails if it is empty or contains whitespace or parentheses
+    pub fn attribute(name: &str) -> SharingResult<Self> {
+        let policy = AttributePolicy::Attribute(name.to_string());
+        policy.check(1)?;
+        Ok(policy)
+    }
+
+    /// Satisfied when all of `members` are
+    pub fn all(members: Vec<AttributePolicy>) -> SharingResult<Self> {
+        Self::threshold(members.len() as u32, members)
+    }
+
+    /// Satisfied when one of `members` is
+    pub fn any(members: Vec<AttributePolicy>) -> SharingResult<Self> {
+        Self::threshold(1, members)
+    }
+
+    /// Satisfied when `threshold` of `members` are.
+    /// Fails if the threshold is zero or greater than the number of members,
+    /// or if a member is invalid
+    pub fn threshold(threshold: u32, members: Vec<AttributePolicy>) -> SharingResult<Self> {
+        let policy = AttributePolicy::Threshold { threshold, members };
+        policy.check(1)?;
+        Ok(policy)
+    }
+
+    /// Parse attributes joined with `AND` and `OR`, where `AND` binds tighter and
+    /// parentheses group, like `role=doctor AND (dept=oncology OR dept=radiology)`.
+    /// Fails with `SharingError::PolicyInvalid` if the expression is malformed
+    pub fn parse(expression: &str) -> SharingResult<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expression),
+            position: 0,
+        };
+        let policy = parser.or(1)?;
+        if parser.position != parser.tokens.len() {
+            return Err(SharingError::PolicyInvalid);
+        }
+        policy.check(1)?;
+        Ok(policy)
+    }
+
+    fn check(&self, depth: usize) -> SharingResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(SharingError::PolicyInvalid);
+        }
+        match self {
+            AttributePolicy::Attribute(name) => {
+                if name.is_empty()
+                    || name
+                        .chars()
+                        .any(|c| c.is_whitespace() || c == '(' || c == ')')
+                {
+                    return Err(SharingError::PolicyInvalid);
+                }
+            }
+            AttributePolicy::Threshold { threshold, members } => {
+                if *threshold == 0 || *threshold as usize > members.len() {
+                    return Err(SharingError::PolicyInvalid);
+                }
+                for member in members {
+                    member.check(depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// True if a key with `attributes` can decrypt under this policy
+    pub fn is_satisfied_by(&self, attributes: &[&str]) -> bool {
+        match self {
+            AttributePolicy::Attribute(name) => attributes.contains(&name.as_str()),
+            AttributePolicy::Threshold { threshold, members } => {
+                members
+                    .iter()
+                    .filter(|m| m.is_satisfied_by(attributes))
+                    .count()
+                    >= *threshold as usize
+            }
+        }
+    }
+
+    /// The number of attributes in the tree, counting repeats
+    fn leaves(&self) -> usize {
+        match self {
+            AttributePolicy::Attribute(_) => 1,
+            AttributePolicy::Threshold { members, .. } => members.iter().map(|m| m.leaves()).sum(),
+        }
+    }
+
+    /// Encode the policy
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            AttributePolicy::Attribute(name) => {
+                out.push(ATTRIBUTE);
+                out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+                out.extend_from_slice(name.as_bytes());
+            }
+            AttributePolicy::Threshold { threshold, members } => {
+                out.push(THRESHOLD);
+                out.extend_from_slice(&threshold.to_be_bytes());
+                out.extend_from_slice(&(members.len() as u32).to_be_bytes());
+                members.iter().for_each(|m| m.write(out));
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'_>, depth: usize) -> SharingResult<Self> {
+        match reader.take(1)?[0] {
+            ATTRIBUTE => {
+                let name = core::str::from_utf8(reader.field()?)
+                    .map_err(|_| SharingError::PolicyInvalid)?;
+                Ok(AttributePolicy::Attribute(name.to_string()))
+            }
+            THRESHOLD if depth <= MAX_DEPTH => {
+                let threshold = reader.u32()?;
+                let count = reader.u32()?;
+                let mut members = Vec::new();
+                for _ in 0..count {
+                    members.push(AttributePolicy::read(reader, depth + 1)?);
+                }
+                Ok(AttributePolicy::Threshold { threshold, members })
+            }
+            _ => Err(SharingError::PolicyInvalid),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for AttributePolicy {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        match value.first() {
+            Some(&VERSION) => {}
+            Some(v) => return Err(SharingError::ShareUnsupportedVersion(*v)),
+            None => return Err(SharingError::ShareInvalidEncoding),
+        }
+        let mut reader = Reader { data: &value[1..] };
+        let policy = AttributePolicy::read(&mut reader, 1)?;
+        reader.finish()?;
+        policy.check(1)?;
+        Ok(policy)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    Attribute(&'a str),
+}
+
+fn tokenize(expression: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '(' => {
+                tokens.push(Token::Open);
+                1
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                1
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .unwrap_or(rest.len());
+                let word = &rest[..len];
+                tokens.push(if word.eq_ignore_ascii_case("and") {
+                    Token::And
+                } else if word.eq_ignore_ascii_case("or") {
+                    Token::Or
+                } else {
+                    Token::Attribute(word)
+                });
+                len
+            }
+        };
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+/// Recursive descent over `or := and (OR and)*`, `and := atom (AND atom)*` and
+/// `atom := attribute | ( or )`
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn or(&mut self, depth: usize) -> SharingResult<AttributePolicy> {
+        let mut members = vec![self.and(depth)?];
+        while self.accept(&Token::Or) {
+            members.push(self.and(depth)?);
+        }
+        Ok(Self::join(1, members))
+    }
+
+    fn and(&mut self, depth: usize) -> SharingResult<AttributePolicy> {
+        let mut members = vec![self.atom(depth)?];
+        while self.accept(&Token::And) {
+            members.push(self.atom(depth)?);
+        }
+        let threshold = members.len() as u32;
+        Ok(Self::join(threshold, members))
+    }
+
+    fn atom(&mut self, depth: usize) -> SharingResult<AttributePolicy> {
+        if depth > MAX_DEPTH {
+            return Err(SharingError::PolicyInvalid);
+        }
+        match self.tokens.get(self.position) {
+            Some(Token::Attribute(name)) => {
+                self.position += 1;
+                Ok(AttributePolicy::Attribute(name.to_string()))
+            }
+            Some(Token::Open) => {
+                self.position += 1;
+                let policy = self.or(depth + 1)?;
+                if !self.accept(&Token::Close) {
+                    return Err(SharingError::PolicyInvalid);
+                }
+                Ok(policy)
+            }
+            _ => Err(SharingError::PolicyInvalid),
+        }
+    }
+
+    fn accept(&mut self, token: &Token<'_>) -> bool {
+        if self.tokens.get(self.position) == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn join(threshold: u32, mut members: Vec<AttributePolicy>) -> AttributePolicy {
+        if members.len() == 1 {
+            members.remove(0)
+        } else {
+            AttributePolicy::Threshold { threshold, members }
+        }
+    }
+}
+
+/// The public parameters anyone encrypts with
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+    /// g1^beta
+    h: Bls12381G1,
+    /// e(g1, g2)^alpha
+    egg_alpha: Fq12,
+}
+
+impl PublicKey {
+    /// Serialize the public key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.h.to_bytes().to_vec();
+        self.egg_alpha.serialize(&mut out, true).unwrap();
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        let h = Bls12381G1::from_bytes(reader.take(G1_BYTES)?)?;
+        let egg_alpha = Fq12::deserialize(&mut reader.take(GT_BYTES)?, true)
+            .map_err(|_| SharingError::InvalidPoint)?;
+        reader.finish()?;
+        if h.is_zero() || egg_alpha.is_zero() {
+            return Err(SharingError::InvalidPoint);
+        }
+        Ok(Self { h, egg_alpha })
+    }
+}
+
+/// The secret of the key authority that issues user keys, cleared on drop
+pub struct MasterKey {
+    beta: Bls12381Scalar,
+    /// g2^alpha
+    g2_alpha: Bls12381G2,
+}
+
+/// Create the public key and the master key of a new authority
+pub fn setup(rng: &mut (impl RngCore + CryptoRng)) -> (PublicKey, MasterKey) {
+    let mut alpha = Bls12381Scalar::random(rng);
+    let beta = nonzero(rng);
+    let g2_alpha = mul(&Bls12381G2::generator(), &alpha);
+    alpha.zeroize();
+    let public_key = PublicKey {
+        h: mul(&Bls12381G1::generator(), &beta),
+        egg_alpha: Bls12::pairing(Bls12381G1::generator().0, g2_alpha.0),
+    };
+    (public_key, MasterKey { beta, g2_alpha })
+}
+
+impl MasterKey {
+    /// Issue a key for `attributes`. Repeated attributes are ignored
+    pub fn issue(&self, rng: &mut (impl RngCore + CryptoRng), attributes: &[&str]) -> SecretKey {
+        let mut r = Bls12381Scalar::random(rng);
+        let g2_r = mul(&Bls12381G2::generator(), &r);
+        // D = g2^((alpha + r) / beta)
+        let mut d = self.g2_alpha;
+        d.add_assign(&g2_r);
+        let mut beta_inverse = self.beta.invert().unwrap();
+        d.scalar_mul_assign(&beta_inverse);
+
+        let mut components = BTreeMap::new();
+        for attribute in attributes {
+            if components.contains_key(*attribute) {
+                continue;
+            }
+            let mut r_j = Bls12381Scalar::random(rng);
+            // D_j = g2^r H(j)^r_j, D'_j = g1^r_j
+            let mut d_j = mul(&hash_attribute(attribute), &r_j);
+            d_j.add_assign(&g2_r);
+            let d_j_prime = mul(&Bls12381G1::generator(), &r_j);
+            r_j.zeroize();
+            components.insert(attribute.to_string(), (d_j, d_j_prime));
+        }
+        r.zeroize();
+        beta_inverse.zeroize();
+        SecretKey { d, components }
+    }
+
+    /// Serialize the master key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.beta.to_bytes().to_vec();
+        out.extend_from_slice(&self.g2_alpha.to_bytes());
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for MasterKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        let beta = Bls12381Scalar::from_bytes(reader.take(SCALAR_BYTES)?)?;
+        let g2_alpha = Bls12381G2::from_bytes(reader.take(G2_BYTES)?)?;
+        reader.finish()?;
+        if beta.is_zero() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        Ok(Self { beta, g2_alpha })
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.beta.zeroize();
+        self.g2_alpha.clear();
+    }
+}
+
+impl Debug for MasterKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "MasterKey(..)")
+    }
+}
+
+/// A user's key for a set of attributes, cleared on drop
+pub struct SecretKey {
+    /// g2^((alpha + r) / beta)
+    d: Bls12381G2,
+    /// D_j and D'_j by attribute
+    components: BTreeMap<String, (Bls12381G2, Bls12381G1)>,
+}
+
+impl SecretKey {
+    /// The attributes of the key, in ascending order
+    pub fn attributes(&self) -> Vec<&str> {
+        self.components.keys().map(|a| a.as_str()).collect()
+    }
+
+    /// Decrypt `ciphertext`. Fails with `SharingError::PolicyNotSatisfied` if the
+    /// attributes of the key don't satisfy its policy and with
+    /// `SharingError::AbeDecryptionFailed` if it was modified or encrypted under
+    /// another authority
+    pub fn decrypt(&self, ciphertext: &Ciphertext) -> SharingResult<Vec<u8>> {
+        if !ciphertext.policy.is_satisfied_by(&self.attributes()) {
+            return Err(SharingError::PolicyNotSatisfied);
+        }
+        let mut position = 0;
+        // e(g1, g2)^(r s)
+        let blinding = self
+            .recover(&ciphertext.policy, &ciphertext.leaves, &mut position)?
+            .ok_or(SharingError::PolicyNotSatisfied)?;
+        // e(C, D) = e(g1, g2)^((alpha + r) s)
+        let mut shared = Bls12::pairing(ciphertext.c.0, self.d.0);
+        shared.mul_assign(
+            &blinding
+                .inverse()
+                .ok_or(SharingError::AbeDecryptionFailed)?,
+        );
+        let cipher = cipher(&shared);
+        cipher
+            .decrypt(
+                Nonce::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: &ciphertext.sealed,
+                    aad: &ciphertext.header(),
+                },
+            )
+            .map_err(|_| SharingError::AbeDecryptionFailed)
+    }
+
+    /// e(g1, g2)^(r q(0)) for the polynomial `q` of `policy`, consuming its leaves
+    /// from `position`, or `None` if the key doesn't satisfy it
+    fn recover(
+        &self,
+        policy: &AttributePolicy,
+        leaves: &[(Bls12381G1, Bls12381G2)],
+        position: &mut usize,
+    ) -> SharingResult<Option<Fq12>> {
+        match policy {
+            AttributePolicy::Attribute(name) => {
+                let (c_y, c_y_prime) = leaves[*position];
+                *position += 1;
+                Ok(self.components.get(name).map(|(d_j, d_j_prime)| {
+                    // e(C_y, D_j) / e(D'_j, C'_y)
+                    let mut negated = *d_j_prime;
+                    negated.negate();
+                    Bls12::pairing_product(c_y.0, d_j.0, negated.0, c_y_prime.0)
+                }))
+            }
+            AttributePolicy::Threshold { threshold, members } => {
+                let mut x_coordinates = Vec::with_capacity(*threshold as usize);
+                let mut values = Vec::with_capacity(*threshold as usize);
+                for (i, member) in members.iter().enumerate() {
+                    if values.len() == *threshold as usize {
+                        *position += member.leaves();
+                        continue;
+                    }
+                    if let Some(value) = self.recover(member, leaves, position)? {
+                        x_coordinates.push(Bls12381Scalar::from_usize(i + 1));
+                        values.push(value);
+                    }
+                }
+                if values.len() < *threshold as usize {
+                    return Ok(None);
+                }
+                // Interpolate at zero in the exponent
+                let coefficients = lagrange_coefficients(&x_coordinates)?;
+                let mut result = Fq12::one();
+                for (value, coefficient) in values.iter().zip(coefficients.iter()) {
+                    result.mul_assign(&value.pow(coefficient.0.into_repr()));
+                }
+                Ok(Some(result))
+            }
+        }
+    }
+
+    /// Serialize the secret key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.d.to_bytes().to_vec();
+        out.extend_from_slice(&(self.components.len() as u32).to_be_bytes());
+        for (attribute, (d_j, d_j_prime)) in &self.components {
+            out.extend_from_slice(&(attribute.len() as u32).to_be_bytes());
+            out.extend_from_slice(attribute.as_bytes());
+            out.extend_from_slice(&d_j.to_bytes());
+            out.extend_from_slice(&d_j_prime.to_bytes());
+        }
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for SecretKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        let d = Bls12381G2::from_bytes(reader.take(G2_BYTES)?)?;
+        let mut components = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            let attribute = core::str::from_utf8(reader.field()?)
+                .map_err(|_| SharingError::ShareInvalidEncoding)?
+                .to_string();
+            let d_j = Bls12381G2::from_bytes(reader.take(G2_BYTES)?)?;
+            let d_j_prime = Bls12381G1::from_bytes(reader.take(G1_BYTES)?)?;
+            if components.insert(attribute, (d_j, d_j_prime)).is_some() {
+                return Err(SharingError::ShareInvalidEncoding);
+            }
+        }
+        reader.finish()?;
+        Ok(Self { d, components })
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.d.clear();
+        for (d_j, d_j_prime) in self.components.values_mut() {
+            d_j.clear();
+            d_j_prime.clear();
+        }
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "SecretKey({:?})", self.attributes())
+    }
+}
+
+/// Data encrypted under an attribute policy
+#[derive(Clone, Debug)]
+pub struct Ciphertext {
+    policy: AttributePolicy,
+    /// h^s
+    c: Bls12381G1,
+    /// C_y = g1^q_y(0) and C'_y = H(att(y))^q_y(0) of every attribute in the policy,
+    /// depth first
+    leaves: Vec<(Bls12381G1, Bls12381G2)>,
+    sealed: Vec<u8>,
+}
+
+/// Encrypt `plaintext` so keys with attributes satisfying `policy` decrypt it
+pub fn encrypt(
+    rng: &mut (impl RngCore + CryptoRng),
+    public_key: &PublicKey,
+    policy: &AttributePolicy,
+    plaintext: &[u8],
+) -> SharingResult<Ciphertext> {
+    policy.check(1)?;
+    let mut s = Bls12381Scalar::random(rng);
+    let mut leaves = Vec::with_capacity(policy.leaves());
+    split(rng, policy, &s, &mut leaves);
+    let mut ciphertext = Ciphertext {
+        policy: policy.clone(),
+        c: mul(&public_key.h, &s),
+        leaves,
+        sealed: Vec::new(),
+    };
+
+    // e(g1, g2)^(alpha s)
+    let shared = public_key.egg_alpha.pow(s.0.into_repr());
+    s.zeroize();
+    let cipher = cipher(&shared);
+    // The key is only used once so a fixed nonce is safe
+    ciphertext.sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: plaintext,
+                aad: &ciphertext.header(),
+            },
+        )
+        .map_err(|_| SharingError::ShareInvalidValue)?;
+    Ok(ciphertext)
+}
+
+/// Share `value` down the tree, pushing the components of every attribute
+fn split(
+    rng: &mut (impl RngCore + CryptoRng),
+    policy: &AttributePolicy,
+    value: &Bls12381Scalar,
+    leaves: &mut Vec<(Bls12381G1, Bls12381G2)>,
+) {
+    match policy {
+        AttributePolicy::Attribute(name) => {
+            leaves.push((
+                mul(&Bls12381G1::generator(), value),
+                mul(&hash_attribute(name), value),
+            ));
+        }
+        AttributePolicy::Threshold { threshold, members } => {
+            let polynomial = Polynomial::new(rng, value, *threshold as usize);
+            for (i, member) in members.iter().enumerate() {
+                let mut share = polynomial.evaluate(&Bls12381Scalar::from_usize(i + 1));
+                split(rng, member, &share, leaves);
+                share.zeroize();
+            }
+        }
+    }
+}
+
+impl Ciphertext {
+    /// The policy the data is encrypted under
+    pub fn policy(&self) -> &AttributePolicy {
+        &self.policy
+    }
+
+    /// Everything but the sealed data, authenticated with it
+    fn header(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        let policy = self.policy.to_bytes();
+        out.extend_from_slice(&(policy.len() as u32).to_be_bytes());
+        out.extend_from_slice(&policy);
+        out.extend_from_slice(&self.c.to_bytes());
+        out.extend_from_slice(&(self.leaves.len() as u32).to_be_bytes());
+        for (c_y, c_y_prime) in &self.leaves {
+            out.extend_from_slice(&c_y.to_bytes());
+            out.extend_from_slice(&c_y_prime.to_bytes());
+        }
+        out
+    }
+
+    /// Serialize the ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header();
+        out.extend_from_slice(&(self.sealed.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.sealed);
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for Ciphertext {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(version));
+        }
+        let policy = AttributePolicy::try_from(reader.field()?)?;
+        let c = Bls12381G1::from_bytes(reader.take(G1_BYTES)?)?;
+        let count = reader.u32()? as usize;
+        if count != policy.leaves() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut leaves = Vec::with_capacity(count);
+        for _ in 0..count {
+            let c_y = Bls12381G1::from_bytes(reader.take(G1_BYTES)?)?;
+            let c_y_prime = Bls12381G2::from_bytes(reader.take(G2_BYTES)?)?;
+            leaves.push((c_y, c_y_prime));
+        }
+        let sealed = reader.field()?.to_vec();
+        reader.finish()?;
+        Ok(Self {
+            policy,
+            c,
+            leaves,
+            sealed,
+        })
+    }
+}
+
+const SCALAR_BYTES: usize = 32;
+const G1_BYTES: usize = 48;
+const G2_BYTES: usize = 96;
+
+/// The AEAD keyed with the hash of the shared element of the target group
+fn cipher(shared: &Fq12) -> ChaCha20Poly1305 {
+    let mut bytes = Vec::with_capacity(GT_BYTES);
+    shared.serialize(&mut bytes, true).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(KEY_DOMAIN);
+    hasher.input(&bytes);
+    bytes.zeroize();
+    let mut key = hasher.result();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    cipher
+}
+
+fn hash_attribute(attribute: &str) -> Bls12381G2 {
+    Bls12381G2::hash_to_curve(attribute.as_bytes(), DST)
+}
+
+fn nonzero(rng: &mut (impl RngCore + CryptoRng)) -> Bls12381Scalar {
+    loop {
+        let s = Bls12381Scalar::random(rng);
+        if !s.is_zero() {
+            return s;
+        }
+    }
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<R: Group<Bls12381Scalar> + Copy>(p: &R, s: &Bls12381Scalar) -> R {
+    let mut r = *p;
+    r.scalar_mul_assign(s);
+    r
+}
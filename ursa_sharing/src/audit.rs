@@ -0,0 +1,130 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Observer hooks for sharing operations.
+//!
+//! An [`Observer`] registered with [`set_observer`] is notified whenever
+//! shares are split, combined, refreshed, fail verification, or a DKG
+//! participant files a complaint. Events only carry public metadata
+//! like thresholds and identifiers, never secrets, shares or blinding factors,
+//! so observers can forward them directly to an audit log.
+//! Registering an observer needs the `std` feature.
+
+use super::error::SharingError;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+
+/// The sharing scheme that emitted an event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SchemeKind {
+    /// Shamir's simple secret sharing
+    Shamir,
+    /// Feldman's verifiable secret sharing
+    Feldman,
+    /// Pedersen's verifiable secret sharing
+    Pedersen,
+    /// Publicly verifiable escrow
+    Escrow,
+}
+
+/// Non-secret metadata about a sharing operation
+#[derive(Clone, Debug)]
+pub enum Event<'a> {
+    /// A secret was split into shares
+    Split {
+        /// The scheme used to split
+        scheme: SchemeKind,
+        /// The minimum number of shares needed to combine
+        threshold: usize,
+        /// The total number of shares created
+        limit: usize,
+        /// The identifiers of the created shares
+        identifiers: &'a [u32],
+    },
+    /// A share failed verification against its verifier
+    VerifyFailed {
+        /// The scheme used to verify
+        scheme: SchemeKind,
+        /// The identifier of the rejected share
+        identifier: u32,
+        /// The reason the share was rejected
+        error: SharingError,
+    },
+    /// Shares were combined
+    Combine {
+        /// The minimum number of shares needed to combine
+        threshold: usize,
+        /// The identifiers of the shares supplied
+        identifiers: &'a [u32],
+        /// The error if combining failed
+        error: Option<SharingError>,
+    },
+    /// Shares were refreshed or reshared to a new access structure
+    Refresh {
+        /// The threshold of the new shares
+        threshold: usize,
+        /// The total number of new shares
+        limit: usize,
+        /// The identifiers of the new shares
+        identifiers: &'a [u32],
+    },
+    /// A DKG participant complained about another participant's share
+    DkgComplaint {
+        /// The participant filing the complaint
+        accuser: u32,
+        /// The participant complained about
+        accused: u32,
+    },
+}
+
+/// Receives events for sharing operations
+pub trait Observer: Send + Sync {
+    /// Called after each sharing operation
+    fn notify(&self, event: &Event);
+}
+
+#[cfg(feature = "std")]
+static OBSERVER: RwLock<Option<Box<dyn Observer>>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "std")))]
+/// Register the observer notified of all sharing operations.
+/// Replaces any previously registered observer.
+pub fn set_observer(observer: Box<dyn Observer>) {
+    if let Ok(mut o) = OBSERVER.write() {
+        *o = Some(observer);
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "std")))]
+/// Remove the registered observer
+pub fn clear_observer() {
+    if let Ok(mut o) = OBSERVER.write() {
+        *o = None;
+    }
+}
+
+/// Notify the registered observer if there is one
+#[cfg(feature = "std")]
+pub(crate) fn notify(event: Event) {
+    if let Ok(o) = OBSERVER.read() {
+        if let Some(observer) = o.as_ref() {
+            observer.notify(&event);
+        }
+    }
+}
+
+/// Without `std` there is no registry so nothing is notified
+#[cfg(not(feature = "std"))]
+pub(crate) fn notify(_event: Event) {}
@@ -0,0 +1,395 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! One-shot secret backup.
+//!
+//! [`backup_secret`] splits an arbitrary length secret into armored shares and
+//! [`restore_secret`] reverses it. No [`Field`](crate::Field) implementation is needed.
+//!
+//! Each share carries a format version, a random backup id so shares
+//! from different backups cannot be mixed, the threshold, its identifier and
+//! a checksum to detect corruption. The secret is shared together with a digest
+//! so a wrong or tampered combination is detected when restoring.
+//!
+//! When a passphrase is supplied each share is encrypted with ChaCha20-Poly1305
+//! under a key derived from the passphrase with Argon2id and a per-share salt.
+//! The share header is authenticated as associated data.
+
+use super::{
+    entropy::SystemRng,
+    error::{SharingError, SharingResult},
+    gf256,
+};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeSet, convert::TryFrom};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// The current encoded share format version
+pub const VERSION: u8 = 1;
+/// The maximum number of shares in a backup
+pub const MAX_SHARES: usize = gf256::MAX_SHARES;
+
+const FLAG_ENCRYPTED: u8 = 1;
+const ID_BYTES: usize = 8;
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const DIGEST_BYTES: usize = 8;
+const CHECKSUM_BYTES: usize = 4;
+/// version, flags, backup id, threshold, identifier
+const HEADER_BYTES: usize = 2 + ID_BYTES + 2;
+
+const ARMOR_BEGIN: &str = "-----BEGIN URSA SECRET SHARE-----";
+const ARMOR_END: &str = "-----END URSA SECRET SHARE-----";
+const ARMOR_LINE: usize = 64;
+
+/// A self-contained share of a backed up secret
+#[derive(Clone, Debug, Zeroize)]
+#[zeroize(drop)]
+pub struct EncodedShare {
+    backup_id: [u8; ID_BYTES],
+    threshold: u8,
+    identifier: u8,
+    /// The salt and nonce if the share is encrypted
+    encryption: Option<([u8; SALT_BYTES], [u8; NONCE_BYTES])>,
+    /// The share value or its ciphertext
+    pub(crate) payload: Vec<u8>,
+}
+
+impl EncodedShare {
+    /// Get the identifier
+    pub fn identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    /// Get the minimum number of shares needed to restore
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    /// Get the random id shared by all shares in the same backup
+    pub fn backup_id(&self) -> [u8; ID_BYTES] {
+        self.backup_id
+    }
+
+    /// True if the share is protected by a passphrase
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    fn header(&self) -> [u8; HEADER_BYTES] {
+        let mut h = [0u8; HEADER_BYTES];
+        h[0] = VERSION;
+        h[1] = if self.is_encrypted() {
+            FLAG_ENCRYPTED
+        } else {
+            0
+        };
+        h[2..2 + ID_BYTES].copy_from_slice(&self.backup_id);
+        h[2 + ID_BYTES] = self.threshold;
+        h[3 + ID_BYTES] = self.identifier;
+        h
+    }
+
+    /// Serialize to the binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.header().to_vec();
+        if let Some((salt, nonce)) = &self.encryption {
+            o.extend_from_slice(salt);
+            o.extend_from_slice(nonce);
+        }
+        o.extend_from_slice(self.payload.as_slice());
+        let checksum = checksum(o.as_slice());
+        o.extend_from_slice(&checksum);
+        o
+    }
+
+    /// Serialize to the armored text form suitable for printing or email
+    pub fn to_armored(&self) -> String {
+        let encoded = base64::encode(self.to_bytes());
+        let mut o = String::with_capacity(encoded.len() + 128);
+        o.push_str(ARMOR_BEGIN);
+        o.push('\n');
+        for line in encoded.as_bytes().chunks(ARMOR_LINE) {
+            // base64 output is always ascii
+            o.push_str(std::str::from_utf8(line).unwrap());
+            o.push('\n');
+        }
+        o.push_str(ARMOR_END);
+        o.push('\n');
+        o
+    }
+
+    /// Parse the armored text form
+    pub fn from_armored(value: &str) -> SharingResult<Self> {
+        // The markers share their dashes, so strip them in turn instead of slicing
+        let body = value
+            .trim()
+            .strip_prefix(ARMOR_BEGIN)
+            .and_then(|v| v.strip_suffix(ARMOR_END))
+            .ok_or(SharingError::ShareInvalidEncoding)?
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        let bytes = base64::decode(body).map_err(|_| SharingError::ShareInvalidEncoding)?;
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+impl TryFrom<&[u8]> for EncodedShare {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() < HEADER_BYTES + CHECKSUM_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let (data, check) = value.split_at(value.len() - CHECKSUM_BYTES);
+        if checksum(data) != check {
+            return Err(SharingError::ShareChecksumMismatch);
+        }
+        if data[0] != VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(data[0]));
+        }
+        let encrypted = match data[1] {
+            0 => false,
+            FLAG_ENCRYPTED => true,
+            _ => return Err(SharingError::ShareInvalidEncoding),
+        };
+        let mut backup_id = [0u8; ID_BYTES];
+        backup_id.copy_from_slice(&data[2..2 + ID_BYTES]);
+        let threshold = data[2 + ID_BYTES];
+        let identifier = data[3 + ID_BYTES];
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+
+        let mut offset = HEADER_BYTES;
+        let encryption = if encrypted {
+            if data.len() < offset + SALT_BYTES + NONCE_BYTES + TAG_BYTES {
+                return Err(SharingError::ShareInvalidEncoding);
+            }
+            let mut salt = [0u8; SALT_BYTES];
+            salt.copy_from_slice(&data[offset..offset + SALT_BYTES]);
+            offset += SALT_BYTES;
+            let mut nonce = [0u8; NONCE_BYTES];
+            nonce.copy_from_slice(&data[offset..offset + NONCE_BYTES]);
+            offset += NONCE_BYTES;
+            Some((salt, nonce))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            backup_id,
+            threshold,
+            identifier,
+            encryption,
+            payload: data[offset..].to_vec(),
+        })
+    }
+}
+
+/// Split `secret` into `limit` shares of which `threshold` are needed to restore it.
+/// If `passphrase` is supplied each share is encrypted with a key derived from it.
+pub fn backup_secret<B: AsRef<[u8]>>(
+    secret: B,
+    threshold: usize,
+    limit: usize,
+    passphrase: Option<&[u8]>,
+) -> SharingResult<Vec<EncodedShare>> {
+    backup_secret_with_rng(&mut SystemRng, secret, threshold, limit, passphrase)
+}
+
+/// Same as [`backup_secret`] but using the supplied random number generator
+pub fn backup_secret_with_rng<B: AsRef<[u8]>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    secret: B,
+    threshold: usize,
+    limit: usize,
+    passphrase: Option<&[u8]>,
+) -> SharingResult<Vec<EncodedShare>> {
+    let secret = secret.as_ref();
+    if limit < threshold {
+        return Err(SharingError::ShareLimitLessThanThreshold);
+    }
+    if threshold < 2 {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    if limit > MAX_SHARES {
+        return Err(SharingError::ShareIdentifierCount(MAX_SHARES, limit));
+    }
+    if secret.is_empty() {
+        return Err(SharingError::ShareInvalidSecret);
+    }
+
+    let mut backup_id = [0u8; ID_BYTES];
+    rng.fill_bytes(&mut backup_id);
+
+    // Share the secret with its digest to detect bad combinations when restoring
+    let mut plaintext = Vec::with_capacity(secret.len() + DIGEST_BYTES);
+    plaintext.extend_from_slice(secret);
+    plaintext.extend_from_slice(&secret_digest(&backup_id, secret));
+
+    let identifiers = (1..=limit as u8).collect::<Vec<u8>>();
+    let values = gf256::split(rng, plaintext.as_slice(), threshold, identifiers.as_slice());
+    plaintext.zeroize();
+
+    let mut shares = Vec::with_capacity(limit);
+    for (identifier, mut value) in identifiers.into_iter().zip(values) {
+        let mut share = EncodedShare {
+            backup_id,
+            threshold: threshold as u8,
+            identifier,
+            encryption: None,
+            payload: Vec::new(),
+        };
+        match passphrase {
+            Some(passphrase) => {
+                let mut salt = [0u8; SALT_BYTES];
+                let mut nonce = [0u8; NONCE_BYTES];
+                rng.fill_bytes(&mut salt);
+                rng.fill_bytes(&mut nonce);
+                share.encryption = Some((salt, nonce));
+                let cipher = derive_cipher(passphrase, &salt)?;
+                let header = share.header();
+                share.payload = cipher
+                    .encrypt(
+                        Nonce::from_slice(&nonce),
+                        Payload {
+                            msg: value.as_slice(),
+                            aad: &header,
+                        },
+                    )
+                    .map_err(|_| SharingError::ShareInvalidSecret)?;
+                value.zeroize();
+            }
+            None => share.payload = value,
+        }
+        shares.push(share);
+    }
+    Ok(shares)
+}
+
+/// Restore the secret from shares created by [`backup_secret`].
+/// The same `passphrase` used to create the backup must be supplied,
+/// and none if the backup isn't encrypted.
+pub fn restore_secret(
+    shares: &[EncodedShare],
+    passphrase: Option<&[u8]>,
+) -> SharingResult<Vec<u8>> {
+    let first = shares.first().ok_or(SharingError::ShareMinThreshold)?;
+    if shares.len() < first.threshold() {
+        return Err(SharingError::ShareMinThreshold);
+    }
+
+    // A backup is encrypted as a whole, check before asking for a passphrase
+    if shares
+        .iter()
+        .any(|s| s.is_encrypted() != first.is_encrypted())
+    {
+        return Err(SharingError::ShareMismatchedBackup);
+    }
+
+    let mut dups = BTreeSet::new();
+    let mut identifiers = Vec::with_capacity(shares.len());
+    let mut values: Vec<Vec<u8>> = Vec::with_capacity(shares.len());
+    for share in shares {
+        if share.backup_id != first.backup_id || share.threshold != first.threshold {
+            return Err(SharingError::ShareMismatchedBackup);
+        }
+        if !dups.insert(share.identifier) {
+            return Err(SharingError::ShareDuplicateIdentifier);
+        }
+        let value = match (&share.encryption, passphrase) {
+            (Some((salt, nonce)), Some(passphrase)) => {
+                let cipher = derive_cipher(passphrase, salt)?;
+                let header = share.header();
+                cipher
+                    .decrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: share.payload.as_slice(),
+                            aad: &header,
+                        },
+                    )
+                    .map_err(|_| SharingError::ShareDecryptionFailed)?
+            }
+            (Some(_), None) => return Err(SharingError::SharePassphraseRequired),
+            (None, Some(_)) => return Err(SharingError::SharePassphraseUnexpected),
+            (None, None) => share.payload.clone(),
+        };
+        if value.len() <= DIGEST_BYTES {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        if let Some(v) = values.first() {
+            if v.len() != value.len() {
+                return Err(SharingError::ShareInvalidValue);
+            }
+        }
+        identifiers.push(share.identifier);
+        values.push(value);
+    }
+
+    let refs = values.iter().map(|v| v.as_slice()).collect::<Vec<&[u8]>>();
+    let mut plaintext = gf256::combine(identifiers.as_slice(), refs.as_slice());
+    for v in values.iter_mut() {
+        v.zeroize();
+    }
+
+    let (secret, digest) = plaintext.split_at(plaintext.len() - DIGEST_BYTES);
+    let expected = secret_digest(&first.backup_id, secret);
+    let valid = digest.ct_eq(&expected[..]).unwrap_u8() == 1;
+    let secret = secret.to_vec();
+    plaintext.zeroize();
+    if valid {
+        Ok(secret)
+    } else {
+        Err(SharingError::ShareInvalidValue)
+    }
+}
+
+fn derive_cipher(passphrase: &[u8], salt: &[u8]) -> SharingResult<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|_| SharingError::ShareInvalidSecret)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    Ok(cipher)
+}
+
+fn secret_digest(backup_id: &[u8], secret: &[u8]) -> [u8; DIGEST_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.input(b"ursa_sharing backup secret digest");
+    hasher.input(backup_id);
+    hasher.input(secret);
+    let mut o = [0u8; DIGEST_BYTES];
+    o.copy_from_slice(&hasher.result()[..DIGEST_BYTES]);
+    o
+}
+
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    let mut o = [0u8; CHECKSUM_BYTES];
+    o.copy_from_slice(&Sha256::digest(data)[..CHECKSUM_BYTES]);
+    o
+}
@@ -0,0 +1,49 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::process;
+use ursa_sharing::vectors::generate;
+
+/// Used when no seed is given so the published vectors can be reproduced
+const DEFAULT_SEED: [u8; 32] = [0u8; 32];
+
+fn parse_seed(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut seed = [0u8; 32];
+    for (i, b) in seed.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(seed)
+}
+
+fn main() {
+    let seed = match std::env::args().nth(1) {
+        None => DEFAULT_SEED,
+        Some(arg) => match parse_seed(&arg) {
+            Some(seed) => seed,
+            None => {
+                eprintln!("usage: test_vectors [seed as 64 hex characters]");
+                process::exit(2);
+            }
+        },
+    };
+    match generate(seed) {
+        Ok(json) => print!("{}", json),
+        Err(e) => {
+            eprintln!("failed to generate test vectors: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! BLS12-381 scalars and points in G1 and G2 for use with the sharing schemes.
+//!
+//! Points use the compressed encoding of the pairing-friendly curves draft, which is
+//! also what BLS signatures use, and decoding checks subgroup membership.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+use ff_zeroize::{Field as _, PrimeField};
+use generic_array::{
+    typenum::{U32, U48, U96},
+    GenericArray,
+};
+use pairing_plus::{
+    bls12_381::{Fr, FrRepr, G1, G2},
+    hash_to_field::BaseFromRO,
+    serdes::SerDes,
+    CurveProjective,
+};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// A scalar modulo the order of the BLS12-381 groups
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Bls12381Scalar(pub Fr);
+
+impl Zeroize for Bls12381Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Fr::zero();
+    }
+}
+
+impl Field for Bls12381Scalar {
+    fn one() -> Self {
+        Self(Fr::one())
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(Fr::from_repr(FrRepr::from(value as u64)).unwrap())
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = rhs.0.inverse().ok_or(SharingError::DivisionByZero)?;
+        self.0.mul_assign(&inverse);
+        Ok(())
+    }
+}
+
+impl Group for Bls12381Scalar {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(Fr::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        match value.len() {
+            // Wide values are reduced like the output of hash to field
+            48 => Ok(Self(Fr::from_okm(GenericArray::from_slice(value)))),
+            l if l <= 32 => {
+                // Big endian so shorter values are left padded
+                let mut s = [0u8; 32];
+                s[32 - l..].copy_from_slice(value);
+                let r = Fr::deserialize(&mut &s[..], true)
+                    .map_err(|_| SharingError::ShareInvalidSecret);
+                s.zeroize();
+                r.map(Self)
+            }
+            _ => Err(SharingError::ShareInvalidSecret),
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(Fr::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0.negate();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0.add_assign(&rhs.0);
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0.sub_assign(&rhs.0);
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        self.0.mul_assign(&rhs.0);
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut r = GenericArray::default();
+        self.0.serialize(&mut r.as_mut_slice(), true).unwrap();
+        r
+    }
+
+    fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// A point in G1 of BLS12-381
+#[derive(Copy, Clone, Debug)]
+pub struct Bls12381G1(pub G1);
+
+impl Bls12381G1 {
+    /// The standard generator
+    pub fn generator() -> Self {
+        Self(G1::one())
+    }
+}
+
+impl Group<Bls12381Scalar> for Bls12381G1 {
+    type Size = U48;
+
+    fn zero() -> Self {
+        Self(G1::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let mut value = value.as_ref();
+        if value.len() != 48 {
+            return Err(SharingError::InvalidPoint);
+        }
+        // Checks that the point is on the curve and in the prime order subgroup
+        G1::deserialize(&mut value, true)
+            .map(Self)
+            .map_err(|_| SharingError::InvalidPoint)
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(G1::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0.negate();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0.add_assign(&rhs.0);
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0.sub_assign(&rhs.0);
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Bls12381Scalar) {
+        self.0.mul_assign(rhs.0);
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut r = GenericArray::default();
+        self.0.serialize(&mut r.as_mut_slice(), true).unwrap();
+        r
+    }
+}
+
+/// A point in G2 of BLS12-381
+#[derive(Copy, Clone, Debug)]
+pub struct Bls12381G2(pub G2);
+
+impl Bls12381G2 {
+    /// The standard generator
+    pub fn generator() -> Self {
+        Self(G2::one())
+    }
+}
+
+impl Group<Bls12381Scalar> for Bls12381G2 {
+    type Size = U96;
+
+    fn zero() -> Self {
+        Self(G2::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let mut value = value.as_ref();
+        if value.len() != 96 {
+            return Err(SharingError::InvalidPoint);
+        }
+        // Checks that the point is on the curve and in the prime order subgroup
+        G2::deserialize(&mut value, true)
+            .map(Self)
+            .map_err(|_| SharingError::InvalidPoint)
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(G2::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0.negate();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0.add_assign(&rhs.0);
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0.sub_assign(&rhs.0);
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Bls12381Scalar) {
+        self.0.mul_assign(rhs.0);
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut r = GenericArray::default();
+        self.0.serialize(&mut r.as_mut_slice(), true).unwrap();
+        r
+    }
+}
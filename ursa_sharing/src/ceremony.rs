@@ -0,0 +1,292 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Reproducible key generation ceremonies for dual-control verification.
+//!
+//! A [`Ceremony`] runs every participant of a DKG on one machine from recorded
+//! entropy, a seed per participant such as the contents of a seed file or the digits
+//! of dice rolls. Each participant's randomness is the seed expanded with SHAKE256
+//! together with the ceremony label and the participant's identifier, so the same
+//! [`CeremonyInputs`] always produce byte-identical keys, shares and transcripts.
+//! Two independent, air-gapped machines given the same inputs confirm the outcome by
+//! comparing [`CeremonyOutput::fingerprint`] before the shares are handed out:
+//!
+//! ```ignore
+//! let mut inputs = CeremonyInputs::new(b"root key 2020-11", 2, 3)?;
+//! for (id, seed) in seeds {
+//!     inputs.add_seed(id, &seed)?;
+//! }
+//! let output = Ceremony::run::<Secp256k1Scalar, Secp256k1Point>(&inputs, &generator)?;
+//! // Read aloud and checked against the second machine
+//! println!("{:02x?}", output.fingerprint());
+//! ```
+//!
+//! The inputs are the secret of every participant. They should only exist on the
+//! ceremony machines and be destroyed with them, [`CeremonyInputs::to_bytes`] moves
+//! them between the machines. A deterministic ceremony is only as unpredictable as
+//! its seeds, which must be fresh for every ceremony.
+
+use crate::{
+    dkg::{DkgOutput, Participant, Reader, Round1Broadcast, Round1Share, Transcript},
+    entropy::{EntropyRng, SeedEntropy, MIN_SEED_BYTES},
+    error::{SharingError, SharingResult},
+    shamir::{Scheme as ShamirScheme, Share},
+    Field, Group,
+};
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, convert::TryFrom};
+use zeroize::Zeroize;
+
+/// The current format version of encoded ceremony inputs
+pub const INPUTS_VERSION: u8 = 1;
+/// The size of a ceremony fingerprint
+pub const FINGERPRINT_BYTES: usize = 32;
+
+const FINGERPRINT_DOMAIN: &[u8] = b"ursa_sharing ceremony fingerprint";
+
+/// The recorded entropy of every participant of a ceremony, cleared on drop
+pub struct CeremonyInputs {
+    label: Vec<u8>,
+    threshold: usize,
+    limit: usize,
+    seeds: BTreeMap<u32, Vec<u8>>,
+}
+
+impl CeremonyInputs {
+    /// Start recording the inputs of the ceremony named `label` for a `threshold`
+    /// out of `limit` key
+    pub fn new(label: &[u8], threshold: usize, limit: usize) -> SharingResult<Self> {
+        ShamirScheme::new(threshold, limit)?;
+        Ok(Self {
+            label: label.to_vec(),
+            threshold,
+            limit,
+            seeds: BTreeMap::new(),
+        })
+    }
+
+    /// Record the seed of participant `id`, which must be one of `1..=limit`, have
+    /// at least `MIN_SEED_BYTES` bytes and not already have a seed
+    pub fn add_seed(&mut self, id: u32, seed: &[u8]) -> SharingResult<()> {
+        if id == 0 || id as usize > self.limit {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        if seed.len() < MIN_SEED_BYTES {
+            return Err(SharingError::EntropyTooShort(seed.len()));
+        }
+        if self.seeds.contains_key(&id) {
+            return Err(SharingError::ShareDuplicateIdentifier);
+        }
+        self.seeds.insert(id, seed.to_vec());
+        Ok(())
+    }
+
+    /// The name of the ceremony
+    pub fn label(&self) -> &[u8] {
+        self.label.as_slice()
+    }
+
+    /// The number of participants needed to use the key
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of participants
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Serialize the inputs, including every seed in the clear
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = vec![INPUTS_VERSION];
+        o.extend_from_slice(&(self.label.len() as u32).to_be_bytes());
+        o.extend_from_slice(&self.label);
+        o.extend_from_slice(&(self.threshold as u32).to_be_bytes());
+        o.extend_from_slice(&(self.limit as u32).to_be_bytes());
+        o.extend_from_slice(&(self.seeds.len() as u32).to_be_bytes());
+        for (id, seed) in &self.seeds {
+            o.extend_from_slice(&id.to_be_bytes());
+            o.extend_from_slice(&(seed.len() as u32).to_be_bytes());
+            o.extend_from_slice(seed);
+        }
+        o
+    }
+
+    /// The randomness of participant `id`
+    fn entropy(&self, id: u32, seed: &[u8]) -> SeedEntropy {
+        let mut input = (self.label.len() as u32).to_be_bytes().to_vec();
+        input.extend_from_slice(&self.label);
+        input.extend_from_slice(&id.to_be_bytes());
+        input.extend_from_slice(seed);
+        let entropy = SeedEntropy::expand(b"ceremony", &input);
+        input.zeroize();
+        entropy
+    }
+}
+
+impl TryFrom<&[u8]> for CeremonyInputs {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let version = reader.take(1)?[0];
+        if version != INPUTS_VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(version));
+        }
+        let label = reader.field()?;
+        let threshold = reader.u32()? as usize;
+        let limit = reader.u32()? as usize;
+        let mut inputs = Self::new(label, threshold, limit)?;
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            inputs.add_seed(id, reader.field()?)?;
+        }
+        reader.finish()?;
+        Ok(inputs)
+    }
+}
+
+impl Drop for CeremonyInputs {
+    fn drop(&mut self) {
+        self.seeds.values_mut().for_each(|s| s.zeroize());
+    }
+}
+
+impl Debug for CeremonyInputs {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "CeremonyInputs {{ label: {:?}, threshold: {}, limit: {}, seeds: {:?} }}",
+            self.label,
+            self.threshold,
+            self.limit,
+            self.seeds.keys().collect::<Vec<_>>()
+        )
+    }
+}
+
+/// A DKG run by every participant from recorded entropy
+#[derive(Copy, Clone, Debug)]
+pub struct Ceremony;
+
+impl Ceremony {
+    /// Run the DKG for all participants of `inputs` committing with `generator`.
+    /// Fails with `SharingError::ShareIdentifierCount` unless every participant has a seed
+    pub fn run<S: Field, R: Group<S>>(
+        inputs: &CeremonyInputs,
+        generator: &R,
+    ) -> SharingResult<CeremonyOutput<S, R>> {
+        if inputs.seeds.len() != inputs.limit {
+            return Err(SharingError::ShareIdentifierCount(
+                inputs.limit,
+                inputs.seeds.len(),
+            ));
+        }
+
+        let mut participants = Vec::with_capacity(inputs.limit);
+        let mut broadcasts: Vec<Round1Broadcast<S, R>> = Vec::with_capacity(inputs.limit);
+        let mut shares: Vec<Round1Share> = Vec::new();
+        for (id, seed) in &inputs.seeds {
+            let entropy = inputs.entropy(*id, seed);
+            let (p, b, s) = Participant::new(
+                &mut EntropyRng::new(&entropy),
+                *id,
+                inputs.threshold,
+                inputs.limit,
+                generator,
+            )?;
+            participants.push(p);
+            broadcasts.push(b);
+            shares.extend(s);
+        }
+
+        let mut complaints = Vec::new();
+        for p in participants.iter_mut() {
+            let mine = shares
+                .iter()
+                .filter(|s| s.receiver() == p.id())
+                .cloned()
+                .collect::<Vec<_>>();
+            complaints.extend(p.receive_round1(&broadcasts, &mine)?);
+        }
+        let justifications = participants
+            .iter()
+            .flat_map(|p| p.justify(&complaints))
+            .collect::<Vec<_>>();
+        let outputs = participants
+            .iter_mut()
+            .map(|p| p.finalize(&complaints, &justifications))
+            .collect::<SharingResult<Vec<_>>>()?;
+
+        // Every participant saw the same messages
+        let transcript = Transcript::try_from(participants[0].transcript().to_bytes().as_slice())?;
+        Ok(CeremonyOutput {
+            label: inputs.label.clone(),
+            outputs,
+            transcript,
+        })
+    }
+}
+
+/// The keys, shares and transcript of a ceremony
+#[derive(Debug)]
+pub struct CeremonyOutput<S: Field, R: Group<S>> {
+    label: Vec<u8>,
+    outputs: Vec<DkgOutput<S, R>>,
+    transcript: Transcript<S, R>,
+}
+
+impl<S: Field, R: Group<S>> CeremonyOutput<S, R> {
+    /// The joint public key
+    pub fn public_key(&self) -> R {
+        self.outputs[0].public_key()
+    }
+
+    /// The share of every participant, ordered by identifier
+    pub fn shares(&self) -> Vec<&Share> {
+        self.outputs.iter().map(|o| o.share()).collect()
+    }
+
+    /// The result of every participant, ordered by identifier
+    pub fn outputs(&self) -> &[DkgOutput<S, R>] {
+        self.outputs.as_slice()
+    }
+
+    /// The public messages of the ceremony
+    pub fn transcript(&self) -> &Transcript<S, R> {
+        &self.transcript
+    }
+
+    /// SHA-256 of the label, the public key, the transcript and every share.
+    /// Equal fingerprints on two machines mean they produced the same artifacts
+    pub fn fingerprint(&self) -> [u8; FINGERPRINT_BYTES] {
+        let mut hasher = Sha256::new();
+        hasher.input(FINGERPRINT_DOMAIN);
+        hasher.input((self.label.len() as u32).to_be_bytes());
+        hasher.input(&self.label);
+        hasher.input(self.public_key().to_bytes());
+        let transcript = self.transcript.to_bytes();
+        hasher.input((transcript.len() as u32).to_be_bytes());
+        hasher.input(&transcript);
+        for share in self.shares() {
+            let mut bytes = share.to_bytes();
+            hasher.input((bytes.len() as u32).to_be_bytes());
+            hasher.input(&bytes);
+            bytes.zeroize();
+        }
+        let mut fingerprint = [0u8; FINGERPRINT_BYTES];
+        fingerprint.copy_from_slice(&hasher.result());
+        fingerprint
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Encrypted checkpoints of multi-round protocol sessions.
+//!
+//! The participants of a DKG and the signers of FROST and threshold ECDSA hold secrets
+//! between rounds that are lost when the process restarts. [`serialize_encrypted`] seals
+//! any [`Checkpoint`] state with ChaCha20-Poly1305 under a [`CheckpointKey`] and [`resume`]
+//! opens it again. The checkpoint is `version | nonce | ciphertext` and the version and
+//! the kind of state are authenticated with it, so a modified checkpoint, another key
+//! or a checkpoint of another kind of state is rejected.
+//!
+//! A checkpoint only protects the state, not the order of the session. Resuming an older
+//! checkpoint of a signer whose nonces were already used signs twice with the same nonces
+//! and reveals its share, so delete the checkpoint of a signer before sending its
+//! signature share and only ever keep the latest checkpoint of a session.
+//!
+//! ```
+//! # use ursa_sharing::{checkpoint::*, dkg::Participant, Field, Group, error::SharingResult};
+//! # fn example<S: Field, R: Group<S>>(participant: Participant<S, R>) -> SharingResult<()> {
+//! let mut rng = rand::thread_rng();
+//! let key = CheckpointKey::random(&mut rng);
+//! let checkpoint = serialize_encrypted(&mut rng, &participant, &key);
+//! // After a restart
+//! let participant = resume::<Participant<S, R>>(&checkpoint, &key)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::error::{SharingError, SharingResult};
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The current checkpoint format version
+pub const VERSION: u8 = 1;
+/// The size of a [`CheckpointKey`]
+pub const KEY_BYTES: usize = 32;
+
+const DOMAIN: &[u8] = b"ursa_sharing checkpoint";
+const NONCE_BYTES: usize = 12;
+
+/// Protocol state that can be saved and resumed
+pub trait Checkpoint: Sized {
+    /// Names the kind of state, a checkpoint of one kind can't be resumed as another
+    const KIND: &'static [u8];
+
+    /// Serialize the state. The result holds its secrets in the clear
+    fn to_checkpoint_bytes(&self) -> Vec<u8>;
+
+    /// Deserialize a state from [`Checkpoint::to_checkpoint_bytes`]
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self>;
+}
+
+/// The key sealing checkpoints, cleared on drop
+#[derive(Clone, Zeroize)]
+#[zeroize(drop)]
+pub struct CheckpointKey([u8; KEY_BYTES]);
+
+impl CheckpointKey {
+    /// Create a key from its bytes, e.g. a key derived or unwrapped by a KMS
+    pub fn new(key: [u8; KEY_BYTES]) -> Self {
+        Self(key)
+    }
+
+    /// Generate a new key
+    pub fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut key = [0u8; KEY_BYTES];
+        rng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// The key's bytes
+    pub fn to_bytes(&self) -> [u8; KEY_BYTES] {
+        self.0
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+impl Debug for CheckpointKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "CheckpointKey(..)")
+    }
+}
+
+/// Seal `state` under `key`
+pub fn serialize_encrypted<T: Checkpoint>(
+    rng: &mut (impl RngCore + CryptoRng),
+    state: &T,
+    key: &CheckpointKey,
+) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_BYTES];
+    rng.fill_bytes(&mut nonce);
+    let mut plaintext = state.to_checkpoint_bytes();
+    let ciphertext = key
+        .cipher()
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext.as_slice(),
+                aad: &aad::<T>(VERSION),
+            },
+        )
+        .expect("ChaCha20-Poly1305 encrypts any length a state can have");
+    plaintext.zeroize();
+
+    let mut o = Vec::with_capacity(1 + NONCE_BYTES + ciphertext.len());
+    o.push(VERSION);
+    o.extend_from_slice(&nonce);
+    o.extend_from_slice(&ciphertext);
+    o
+}
+
+/// Open a checkpoint sealed with [`serialize_encrypted`] and restore its state
+pub fn resume<T: Checkpoint>(data: &[u8], key: &CheckpointKey) -> SharingResult<T> {
+    match data.first() {
+        None => return Err(SharingError::CheckpointInvalid),
+        Some(&VERSION) => {}
+        Some(&v) => return Err(SharingError::CheckpointVersion(v)),
+    }
+    if data.len() < 1 + NONCE_BYTES {
+        return Err(SharingError::CheckpointInvalid);
+    }
+    let mut plaintext = key
+        .cipher()
+        .decrypt(
+            Nonce::from_slice(&data[1..1 + NONCE_BYTES]),
+            Payload {
+                msg: &data[1 + NONCE_BYTES..],
+                aad: &aad::<T>(VERSION),
+            },
+        )
+        .map_err(|_| SharingError::CheckpointInvalid)?;
+    let state = T::from_checkpoint_bytes(&plaintext);
+    plaintext.zeroize();
+    state
+}
+
+fn aad<T: Checkpoint>(version: u8) -> Vec<u8> {
+    let mut aad = DOMAIN.to_vec();
+    aad.push(version);
+    aad.extend_from_slice(T::KIND);
+    aad
+}
+
+/// Append `data` prefixed with its length
+pub(crate) fn put(o: &mut Vec<u8>, data: &[u8]) {
+    o.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    o.extend_from_slice(data);
+}
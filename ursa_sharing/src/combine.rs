@@ -0,0 +1,200 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Combining many sets of shares from the same participants.
+//!
+//! Interpolating at zero weighs each share by a Lagrange coefficient that only depends
+//! on the identifiers of the shares, not their values. A validator combining partial
+//! signatures from the same quorum every time computes the same coefficients over and
+//! over. A [`CombineContext`] computes them once for a fixed set of identifiers, so each
+//! combine is only a weighted sum:
+//!
+//! ```ignore
+//! let context = CombineContext::<Secp256r1Scalar>::new(threshold, &[1, 3, 4])?;
+//! for partials in received {
+//!     // One partial result per participant, in the order given to the context
+//!     let signature: Secp256r1Point = context.combine(&partials)?;
+//! }
+//! ```
+//!
+//! With the `parallel` feature, `CombineContext::par_combine_many` spreads a batch
+//! over rayon's thread pool.
+
+use crate::{
+    audit::{notify, Event},
+    error::{SharingError, SharingResult},
+    field::lagrange_coefficients,
+    shamir::Share,
+    Field, Group,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// The Lagrange coefficients at zero for a fixed set of share identifiers
+#[derive(Debug)]
+pub struct CombineContext<S: Field> {
+    threshold: usize,
+    identifiers: Vec<u32>,
+    positions: BTreeMap<u32, usize>,
+    coefficients: Vec<S>,
+}
+
+impl<S: Field> CombineContext<S> {
+    /// Compute the coefficients for combining shares with `identifiers` in a `threshold`
+    /// out of n scheme. Fails if there are fewer identifiers than the threshold,
+    /// or an identifier is zero or repeated.
+    pub fn new(threshold: usize, identifiers: &[u32]) -> SharingResult<Self> {
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        if identifiers.len() < threshold {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let mut positions = BTreeMap::new();
+        for (i, identifier) in identifiers.iter().enumerate() {
+            if *identifier == 0 {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+            if positions.insert(*identifier, i).is_some() {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        let x_coordinates = identifiers
+            .iter()
+            .map(|i| S::from_usize(*i as usize))
+            .collect::<Vec<S>>();
+        let coefficients = lagrange_coefficients(&x_coordinates)?;
+        Ok(Self {
+            threshold,
+            identifiers: identifiers.to_vec(),
+            positions,
+            coefficients,
+        })
+    }
+
+    /// The identifiers the coefficients are for, in the order values are given to [`Self::combine`]
+    pub fn identifiers(&self) -> &[u32] {
+        self.identifiers.as_slice()
+    }
+
+    /// The Lagrange coefficient of the share with `identifier`
+    pub fn coefficient(&self, identifier: u32) -> Option<&S> {
+        self.positions
+            .get(&identifier)
+            .map(|i| &self.coefficients[*i])
+    }
+
+    /// Combine one value per identifier, in the order of [`Self::identifiers`],
+    /// such as partial signatures in the exponent.
+    /// Fails if the number of values is wrong or a value isn't valid.
+    pub fn combine<R: Group<S>>(&self, values: &[R]) -> SharingResult<R> {
+        let res = self.weighted_sum(values);
+        self.notify(&res);
+        res
+    }
+
+    /// Combine shares with the identifiers of the context, given in any order
+    pub fn combine_shares<R: Group<S>>(&self, shares: &[Share]) -> SharingResult<R> {
+        let res = self.sort_shares(shares).and_then(|shares| {
+            let mut values = shares
+                .iter()
+                .map(|s| R::from_bytes(s.value()))
+                .collect::<SharingResult<Vec<R>>>()?;
+            let res = self.weighted_sum(values.as_slice());
+            // The share values are secret when combining a secret, not commitments
+            values.iter_mut().for_each(|v| v.clear());
+            res
+        });
+        self.notify(&res);
+        res
+    }
+
+    /// Combine each set of values as [`Self::combine`] does
+    pub fn combine_many<R: Group<S>, B: AsRef<[R]>>(&self, batch: &[B]) -> SharingResult<Vec<R>> {
+        batch
+            .iter()
+            .map(|values| self.combine(values.as_ref()))
+            .collect()
+    }
+
+    /// Combine each set of values as [`Self::combine`] does on rayon's thread pool
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(feature = "nightly", doc(cfg(feature = "parallel")))]
+    pub fn par_combine_many<R, B>(&self, batch: &[B]) -> SharingResult<Vec<R>>
+    where
+        S: Send + Sync,
+        R: Group<S> + Send,
+        B: AsRef<[R]> + Sync,
+    {
+        batch
+            .par_iter()
+            .map(|values| self.combine(values.as_ref()))
+            .collect()
+    }
+
+    /// Put the shares in the order of the identifiers
+    fn sort_shares<'a>(&self, shares: &'a [Share]) -> SharingResult<Vec<&'a Share>> {
+        if shares.len() != self.identifiers.len() {
+            return Err(SharingError::ShareIdentifierCount(
+                self.identifiers.len(),
+                shares.len(),
+            ));
+        }
+        let mut sorted = alloc::vec![None; shares.len()];
+        for share in shares {
+            let i = *self
+                .positions
+                .get(&share.identifier())
+                .ok_or(SharingError::ShareInvalidIdentifier)?;
+            if sorted[i].replace(share).is_some() {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        Ok(sorted.into_iter().flatten().collect())
+    }
+
+    fn weighted_sum<R: Group<S>>(&self, values: &[R]) -> SharingResult<R> {
+        if values.len() != self.coefficients.len() {
+            return Err(SharingError::ShareIdentifierCount(
+                self.coefficients.len(),
+                values.len(),
+            ));
+        }
+        // Only the identifiers are public so validity is checked without exiting early
+        let mut valid = true;
+        let mut result = R::zero();
+        for (value, coefficient) in values.iter().zip(self.coefficients.iter()) {
+            valid &= value.is_valid();
+            let mut term = R::zero();
+            term.add_assign(value);
+            term.scalar_mul_assign(coefficient);
+            result.add_assign(&term);
+            term.clear();
+        }
+        if valid {
+            Ok(result)
+        } else {
+            result.clear();
+            Err(SharingError::ShareInvalidValue)
+        }
+    }
+
+    fn notify<R>(&self, res: &SharingResult<R>) {
+        notify(Event::Combine {
+            threshold: self.threshold,
+            identifiers: self.identifiers.as_slice(),
+            error: res.as_ref().err().copied(),
+        });
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Statistical timing tests of the paths that must be constant time.
+//!
+//! Following dudect (see <https://eprint.iacr.org/2016/1123>), each audit times an
+//! operation on two classes of inputs, a fixed input and random inputs, interleaved at
+//! random. If the time doesn't depend on the input the two distributions are the same.
+//! Welch's t-test compares them, on all measurements and with the slowest cropped at
+//! a range of percentiles to remove interruptions, and the largest |t| is reported.
+//!
+//! dudect reads |t| above 4.5 as a likely leak. The audits of a backend that's meant
+//! to be constant time fail above [`T_THRESHOLD`], which leaves room for noisy CI machines
+//! while leaks like an early exit give |t| in the hundreds. [`leaky_comparison`] is such a
+//! leak, to check the machine running the audits can see one.
+//!
+//! A passing audit doesn't prove an operation is constant time, only that no difference
+//! was measured between these classes on this machine. The audits are run by the
+//! `ct_audit` example in release mode.
+
+#[cfg(feature = "backup")]
+use crate::backup::{backup_secret_with_rng, restore_secret};
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{resume, serialize_encrypted, Checkpoint, CheckpointKey};
+use crate::{error::SharingResult, shamir::Scheme, Field, Group};
+use rand::prelude::*;
+use std::time::Instant;
+
+/// The |t| above which an audit is considered to have found a leak
+pub const T_THRESHOLD: f64 = 10.0;
+/// The number of measurements the audits default to
+pub const DEFAULT_MEASUREMENTS: usize = 20_000;
+
+/// The number of percentiles the measurements are cropped at
+const CROPS: usize = 100;
+/// Tests with fewer measurements in a class are ignored
+const MIN_CLASS_SIZE: usize = 100;
+
+/// The result of an audit
+#[derive(Copy, Clone, Debug)]
+pub struct Report {
+    /// The number of measurements taken
+    pub measurements: usize,
+    /// The largest Welch's t statistic between the fixed and random classes
+    pub t: f64,
+}
+
+impl Report {
+    /// True if the timing of the classes differs by more than [`T_THRESHOLD`]
+    pub fn leaks(&self) -> bool {
+        self.t.abs() > T_THRESHOLD
+    }
+}
+
+/// Running mean and variance of one class
+#[derive(Copy, Clone, Default)]
+struct Moments {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.n - 1.0)
+    }
+}
+
+/// Welch's t statistic over the measurements faster than `limit`, if there are enough
+fn welch(samples: &[(bool, f64)], limit: f64) -> Option<f64> {
+    let mut classes = [Moments::default(); 2];
+    for (fixed, x) in samples.iter().filter(|(_, x)| *x <= limit) {
+        classes[*fixed as usize].push(*x);
+    }
+    if classes.iter().any(|c| c.n < MIN_CLASS_SIZE as f64) {
+        return None;
+    }
+    let [random, fixed] = classes;
+    let denom = (fixed.variance() / fixed.n + random.variance() / random.n).sqrt();
+    if denom == 0.0 {
+        return Some(0.0);
+    }
+    Some((fixed.mean - random.mean) / denom)
+}
+
+/// Time `op` on `measurements` inputs from `input`, which is told whether to return
+/// the fixed input, and compare the two classes
+pub fn measure<I>(
+    measurements: usize,
+    mut input: impl FnMut(&mut ThreadRng, bool) -> I,
+    mut op: impl FnMut(&I),
+) -> Report {
+    let mut rng = thread_rng();
+    let inputs = (0..measurements)
+        .map(|_| {
+            let fixed = rng.gen::<bool>();
+            (fixed, input(&mut rng, fixed))
+        })
+        .collect::<Vec<_>>();
+    // Warm up caches and branch predictors
+    for (_, i) in inputs.iter().take(measurements / 100) {
+        op(i);
+    }
+    let mut samples = inputs
+        .iter()
+        .map(|(fixed, i)| {
+            let start = Instant::now();
+            op(i);
+            (*fixed, start.elapsed().as_nanos() as f64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut sorted = samples.iter().map(|(_, x)| *x).collect::<Vec<f64>>();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("durations are not NaN"));
+    let mut t = welch(&samples, f64::INFINITY).unwrap_or(0.0);
+    for k in 0..CROPS {
+        let p = 1.0 - 0.5f64.powf(10.0 * (k + 1) as f64 / CROPS as f64);
+        let limit = sorted[((sorted.len() - 1) as f64 * p) as usize];
+        if let Some(t_k) = welch(&samples, limit) {
+            if t_k.abs() > t.abs() {
+                t = t_k;
+            }
+        }
+    }
+    samples.clear();
+    Report { measurements, t }
+}
+
+/// Audit `Group::scalar_mul_assign` of `R` with the scalar one against random scalars
+pub fn scalar_mul<S: Field, R: Group<S>>(measurements: usize) -> Report {
+    let base = R::random(&mut thread_rng());
+    measure(
+        measurements,
+        |rng, fixed| {
+            if fixed {
+                S::one()
+            } else {
+                S::random(rng)
+            }
+        },
+        |scalar| {
+            let mut point = R::zero();
+            point.add_assign(&base);
+            point.scalar_mul_assign(scalar);
+            std::hint::black_box(&point);
+        },
+    )
+}
+
+/// Audit combining 3 out of 5 shares over `S` with the same shares against shares of random secrets
+pub fn combine_shares<S: Field>(measurements: usize) -> Report {
+    let scheme = Scheme::new(3, 5).expect("3 out of 5 is a valid scheme");
+    let fixed_shares = scheme
+        .split_secret(&mut thread_rng(), &S::one())
+        .expect("one is a valid secret");
+    measure(
+        measurements,
+        |rng, fixed| {
+            if fixed {
+                fixed_shares.clone()
+            } else {
+                let secret = S::random(rng);
+                scheme
+                    .split_secret(rng, &secret)
+                    .expect("random secrets are valid")
+            }
+        },
+        |shares| {
+            let secret: SharingResult<S> = scheme.combine_shares::<S, S>(&shares[1..4]);
+            std::hint::black_box(&secret);
+        },
+    )
+}
+
+/// Audit the digest check when restoring a `backup`, with the digest wrong in its
+/// first byte against wrong in a random byte
+#[cfg(feature = "backup")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "backup")))]
+pub fn backup_digest(measurements: usize) -> Report {
+    let secret = [7u8; 32];
+    let shares = backup_secret_with_rng(&mut thread_rng(), secret, 2, 3, None)
+        .expect("the backup parameters are valid");
+    let digest_bytes = shares[0].payload.len() - secret.len();
+    measure(
+        measurements,
+        |rng, fixed| {
+            // Changing a share changes the reconstructed digest at the same position
+            let position = if fixed {
+                0
+            } else {
+                rng.gen_range(0, digest_bytes)
+            };
+            let mut corrupted = shares[..2].to_vec();
+            corrupted[0].payload[secret.len() + position] ^= 1;
+            corrupted
+        },
+        |shares| {
+            let secret = restore_secret(shares, None);
+            debug_assert!(secret.is_err());
+            std::hint::black_box(&secret);
+        },
+    )
+}
+
+/// A checkpoint of opaque bytes
+#[cfg(feature = "checkpoint")]
+struct Blob(Vec<u8>);
+
+#[cfg(feature = "checkpoint")]
+impl Checkpoint for Blob {
+    const KIND: &'static [u8] = b"ct_audit blob";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        Ok(Self(data.to_vec()))
+    }
+}
+
+/// Audit the ChaCha20-Poly1305 tag check when resuming a `checkpoint`, with the tag
+/// wrong in its first byte against wrong in a random byte
+#[cfg(feature = "checkpoint")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "checkpoint")))]
+pub fn checkpoint_tag(measurements: usize) -> Report {
+    const TAG_BYTES: usize = 16;
+    let mut rng = thread_rng();
+    let key = CheckpointKey::random(&mut rng);
+    let checkpoint = serialize_encrypted(&mut rng, &Blob(vec![7u8; 256]), &key);
+    let tag = checkpoint.len() - TAG_BYTES;
+    measure(
+        measurements,
+        |rng, fixed| {
+            let position = if fixed {
+                0
+            } else {
+                rng.gen_range(0, TAG_BYTES)
+            };
+            let mut corrupted = checkpoint.clone();
+            corrupted[tag + position] ^= 1;
+            corrupted
+        },
+        |checkpoint| {
+            let state = resume::<Blob>(checkpoint, &key);
+            debug_assert!(state.is_err());
+            std::hint::black_box(&state);
+        },
+    )
+}
+
+/// A comparison that exits at the first differing byte, with the difference in the first
+/// byte against a random byte. This is expected to leak, to check the audits can see it.
+pub fn leaky_comparison(measurements: usize) -> Report {
+    const LEN: usize = 4096;
+    let expected = vec![0u8; LEN];
+    measure(
+        measurements,
+        |rng, fixed| {
+            let position = if fixed { 0 } else { rng.gen_range(0, LEN) };
+            let mut data = vec![0u8; LEN];
+            data[position] = 1;
+            data
+        },
+        |data| {
+            std::hint::black_box(std::hint::black_box(data) == &expected);
+        },
+    )
+}
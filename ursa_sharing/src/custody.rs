@@ -0,0 +1,414 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Split-custody signing wallets built from the DKG, FROST, refresh and backup modules.
+//!
+//! A [`Wallet`] is the public half of a FROST key held by `limit` [`Custodian`]s of
+//! which `threshold` are needed to sign. It goes through four steps:
+//!
+//! 1. [`Wallet::create`] runs the `dkg` between the custodians so the key never exists
+//!    in one place. Custodians that ran the DKG on their own machines each build the
+//!    wallet and their custodian with [`Wallet::from_dkg`] instead.
+//! 2. [`Wallet::sign`] runs both `frost` rounds with at least `threshold` custodians and
+//!    returns the aggregated signature.
+//! 3. [`Wallet::rotate`] refreshes every share with `refresh` and moves the wallet to the
+//!    next epoch. The public key stays the same but shares from before the rotation no
+//!    longer combine with the new ones, so a share stolen before it is useless.
+//! 4. [`Custodian::backup`] splits a custodian's share into passphrase protected pieces
+//!    with `backup`, e.g. for the custodian's own guardians, and [`Wallet::recover`]
+//!    restores the custodian from them.
+//!
+//! Custodians and their backups belong to one epoch and are refused with
+//! `CustodyWrongEpoch` by the wallet of another, so backups must be taken again after
+//! every rotation. Rotating needs every custodian: recover a lost one first.
+//!
+//! [`Wallet::create`], [`Wallet::sign`] and [`Wallet::rotate`] run every custodian in
+//! one process, for custodians kept in separate enclaves or HSM sessions of one host.
+//! Custodians on separate machines run the same steps with the messages of `dkg`,
+//! `frost` and `refresh` over their own transport.
+
+use crate::{
+    backup::{backup_secret_with_rng, restore_secret, EncodedShare},
+    dkg::{DkgOutput, Participant, Round1Broadcast, Round1Share, Transcript},
+    error::{SharingError, SharingResult},
+    feldman::FeldmanVerifier,
+    frost::{Coordinator, FrostGroup, Signature, SignerRound1},
+    refresh::Resharing,
+    shamir::{Scheme as ShamirScheme, Share},
+    Field, Group, ShareVerifier,
+};
+use core::marker::PhantomData;
+use rand::{CryptoRng, RngCore};
+use std::{collections::BTreeSet, convert::TryFrom};
+use zeroize::Zeroize;
+
+/// The current wallet format version
+pub const WALLET_VERSION: u8 = 1;
+
+/// The public key of a split-custody wallet and the commitments to its shares.
+/// Every custodian and anyone checking signatures keeps a copy
+#[derive(Debug)]
+pub struct Wallet<S: Field, R: FrostGroup<S>> {
+    epoch: u64,
+    limit: usize,
+    verifier: FeldmanVerifier<S, R>,
+}
+
+/// One custodian's share of a wallet's key, cleared on drop
+#[derive(Clone, Debug)]
+pub struct Custodian {
+    epoch: u64,
+    share: Share,
+}
+
+impl<S: Field, R: FrostGroup<S>> Wallet<S, R> {
+    /// Create a wallet held by `limit` custodians of which `threshold` are needed to
+    /// sign, running the DKG for all of them
+    pub fn create(
+        rng: &mut (impl RngCore + CryptoRng),
+        threshold: usize,
+        limit: usize,
+    ) -> SharingResult<(Self, Vec<Custodian>)> {
+        let generator = R::generator();
+        let mut participants = Vec::with_capacity(limit);
+        let mut broadcasts: Vec<Round1Broadcast<S, R>> = Vec::with_capacity(limit);
+        let mut shares: Vec<Round1Share> = Vec::new();
+        for id in 1..=limit as u32 {
+            let (p, b, s) = Participant::new(rng, id, threshold, limit, &generator)?;
+            participants.push(p);
+            broadcasts.push(b);
+            shares.extend(s);
+        }
+
+        let mut complaints = Vec::new();
+        for p in participants.iter_mut() {
+            let mine = shares
+                .iter()
+                .filter(|s| s.receiver() == p.id())
+                .cloned()
+                .collect::<Vec<_>>();
+            complaints.extend(p.receive_round1(&broadcasts, &mine)?);
+        }
+        let justifications = participants
+            .iter()
+            .flat_map(|p| p.justify(&complaints))
+            .collect::<Vec<_>>();
+
+        let mut wallet = None;
+        let mut custodians = Vec::with_capacity(limit);
+        for p in participants.iter_mut() {
+            let output = p.finalize(&complaints, &justifications)?;
+            let (w, custodian) = Self::from_dkg(p.transcript(), &output)?;
+            wallet.get_or_insert(w);
+            custodians.push(custodian);
+        }
+        let wallet = wallet.ok_or(SharingError::ShareMinThreshold)?;
+        Ok((wallet, custodians))
+    }
+
+    /// The wallet and the custodian of a participant that finished a DKG session with
+    /// `output`, whose messages are in `transcript`. Fails with `FrostInvalidGenerator`
+    /// unless the session committed with the ciphersuite's base point
+    pub fn from_dkg(
+        transcript: &Transcript<S, R>,
+        output: &DkgOutput<S, R>,
+    ) -> SharingResult<(Self, Custodian)> {
+        if output.verifier().g.to_bytes() != R::generator().to_bytes() {
+            return Err(SharingError::FrostInvalidGenerator);
+        }
+        if transcript.verifier()?.to_bytes() != output.verifier().to_bytes() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        let wallet = Self {
+            epoch: 0,
+            limit: transcript.limit(),
+            verifier: copy_verifier(output.verifier()),
+        };
+        let custodian = Custodian {
+            epoch: 0,
+            share: output.share().clone(),
+        };
+        wallet.check(&custodian)?;
+        Ok((wallet, custodian))
+    }
+
+    /// The public key signatures verify against
+    pub fn public_key(&self) -> R {
+        copy(&self.verifier.commitments[0].value)
+    }
+
+    /// The number of custodians needed to sign
+    pub fn threshold(&self) -> usize {
+        self.verifier.commitments.len()
+    }
+
+    /// The number of custodians
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The number of rotations since the wallet was created
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The commitments the custodians' shares verify against
+    pub fn verifier(&self) -> &FeldmanVerifier<S, R> {
+        &self.verifier
+    }
+
+    /// Check `custodian` holds a valid share of this epoch
+    pub fn check(&self, custodian: &Custodian) -> SharingResult<()> {
+        if custodian.epoch != self.epoch {
+            return Err(SharingError::CustodyWrongEpoch(custodian.epoch));
+        }
+        if custodian.share.identifier() == 0 || custodian.share.identifier() as usize > self.limit {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        self.verifier.verify_share(&custodian.share)
+    }
+
+    /// Sign `message` with `custodians`, at least the threshold of them
+    pub fn sign(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        custodians: &[&Custodian],
+        message: &[u8],
+    ) -> SharingResult<Signature<S, R>> {
+        if custodians.len() < self.threshold() {
+            return Err(SharingError::FrostInvalidSigner);
+        }
+        let mut signers = Vec::with_capacity(custodians.len());
+        let mut commitments = Vec::with_capacity(custodians.len());
+        for custodian in custodians {
+            self.check(custodian)?;
+            let (signer, c) = SignerRound1::new(rng, &custodian.share, &self.verifier)?;
+            signers.push(signer);
+            commitments.push(c);
+        }
+        let coordinator = Coordinator::new(&self.verifier, message, &commitments)?;
+        let shares = signers
+            .into_iter()
+            .map(|signer| signer.sign(message, &commitments))
+            .collect::<SharingResult<Vec<_>>>()?;
+        coordinator.aggregate(&shares)
+    }
+
+    /// Refresh the shares of every custodian and move to the next epoch.
+    /// Returns the new wallet and custodians, the old ones should be destroyed.
+    /// Fails with `RefreshAbort` naming a custodian whose reshares are invalid
+    pub fn rotate(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        custodians: &[Custodian],
+    ) -> SharingResult<(Self, Vec<Custodian>)> {
+        if custodians.len() != self.limit {
+            return Err(SharingError::ShareIdentifierCount(
+                self.limit,
+                custodians.len(),
+            ));
+        }
+        let mut identifiers = BTreeSet::new();
+        for custodian in custodians {
+            self.check(custodian)?;
+            if !identifiers.insert(custodian.id()) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        let identifiers = identifiers.into_iter().collect::<Vec<_>>();
+        let scheme = ShamirScheme::new(self.threshold(), self.limit)?;
+        let resharing = Resharing::with_identifiers(scheme, scheme, identifiers.as_slice())?;
+
+        let generator = R::generator();
+        let mut verifiers = Vec::with_capacity(custodians.len());
+        let mut reshares = Vec::new();
+        for custodian in custodians {
+            let (verifier, dealt) =
+                resharing.deal_verifiable::<S, R>(rng, &custodian.share, &generator)?;
+            verifiers.push((custodian.id(), verifier));
+            reshares.extend(dealt);
+        }
+
+        // Every dealer must reshare the share the wallet committed to
+        for (dealer, verifier) in &verifiers {
+            let expected = self.verifier.verification_share(*dealer)?;
+            if verifier.commitments.len() != self.threshold()
+                || verifier.commitments[0].value.to_bytes() != expected.to_bytes()
+            {
+                return Err(SharingError::RefreshAbort(*dealer));
+            }
+        }
+        let verifier = resharing.combine_verifiers(verifiers.as_slice())?;
+        if verifier.commitments[0].value.to_bytes() != self.verifier.commitments[0].value.to_bytes()
+        {
+            return Err(SharingError::ShareInvalidValue);
+        }
+
+        let wallet = Self {
+            epoch: self.epoch + 1,
+            limit: self.limit,
+            verifier,
+        };
+        let mut rotated = Vec::with_capacity(identifiers.len());
+        for id in identifiers {
+            let mine = reshares
+                .iter()
+                .filter(|r| r.receiver() == id)
+                .cloned()
+                .collect::<Vec<_>>();
+            for reshare in &mine {
+                let (_, dealer) = verifiers
+                    .iter()
+                    .find(|(dealer, _)| *dealer == reshare.dealer())
+                    .ok_or(SharingError::RefreshAbort(reshare.dealer()))?;
+                dealer
+                    .verify_share(&reshare.share)
+                    .map_err(|_| SharingError::RefreshAbort(reshare.dealer()))?;
+            }
+            let custodian = Custodian {
+                epoch: wallet.epoch,
+                share: resharing.combine::<S>(id, mine.as_slice())?,
+            };
+            wallet.check(&custodian)?;
+            rotated.push(custodian);
+        }
+        Ok((wallet, rotated))
+    }
+
+    /// Restore a custodian of this epoch from the pieces of a [`Custodian::backup`],
+    /// with the passphrase the backup was made with
+    pub fn recover(
+        &self,
+        pieces: &[EncodedShare],
+        passphrase: Option<&[u8]>,
+    ) -> SharingResult<Custodian> {
+        let bytes = restore_secret(pieces, passphrase)?;
+        let custodian = Custodian::try_from(bytes.as_slice())?;
+        self.check(&custodian)?;
+        Ok(custodian)
+    }
+
+    /// Serialize as the version, the epoch, the limit and the verifier
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = vec![WALLET_VERSION];
+        o.extend_from_slice(&self.epoch.to_be_bytes());
+        o.extend_from_slice(&(self.limit as u32).to_be_bytes());
+        o.extend_from_slice(&self.verifier.to_bytes());
+        o
+    }
+}
+
+impl<S: Field, R: FrostGroup<S>> TryFrom<&[u8]> for Wallet<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() < 13 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        if data[0] != WALLET_VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(data[0]));
+        }
+        let mut epoch = [0u8; 8];
+        epoch.copy_from_slice(&data[1..9]);
+        let mut limit = [0u8; 4];
+        limit.copy_from_slice(&data[9..13]);
+        let verifier = FeldmanVerifier::<S, R>::try_from(&data[13..])?;
+        if verifier.g.to_bytes() != R::generator().to_bytes() {
+            return Err(SharingError::FrostInvalidGenerator);
+        }
+        let limit = u32::from_be_bytes(limit) as usize;
+        ShamirScheme::new(verifier.commitments.len(), limit)?;
+        Ok(Self {
+            epoch: u64::from_be_bytes(epoch),
+            limit,
+            verifier,
+        })
+    }
+}
+
+impl Custodian {
+    /// The identifier of the custodian's share
+    pub fn id(&self) -> u32 {
+        self.share.identifier()
+    }
+
+    /// The epoch of the wallet the share belongs to
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The custodian's share of the wallet's key
+    pub fn share(&self) -> &Share {
+        &self.share
+    }
+
+    /// Split this custodian into `limit` pieces of which `threshold` restore it with
+    /// [`Wallet::recover`]. If `passphrase` is supplied each piece is encrypted with a
+    /// key derived from it
+    pub fn backup(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        threshold: usize,
+        limit: usize,
+        passphrase: Option<&[u8]>,
+    ) -> SharingResult<Vec<EncodedShare>> {
+        let mut bytes = self.to_bytes();
+        let pieces = backup_secret_with_rng(rng, &bytes, threshold, limit, passphrase);
+        bytes.zeroize();
+        pieces
+    }
+
+    /// Serialize as the epoch followed by the share.
+    /// The result is secret and must only be stored encrypted
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.epoch.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.share.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Custodian {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() < 8 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut epoch = [0u8; 8];
+        epoch.copy_from_slice(&data[..8]);
+        Ok(Self {
+            epoch: u64::from_be_bytes(epoch),
+            share: Share::try_from(&data[8..])?,
+        })
+    }
+}
+
+fn copy_verifier<S: Field, R: Group<S>>(verifier: &FeldmanVerifier<S, R>) -> FeldmanVerifier<S, R> {
+    FeldmanVerifier {
+        g: copy(&verifier.g),
+        commitments: verifier
+            .commitments
+            .iter()
+            .map(|c| ShareVerifier {
+                value: copy(&c.value),
+                phantom: PhantomData,
+            })
+            .collect(),
+    }
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
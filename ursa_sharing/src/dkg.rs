@@ -0,0 +1,956 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Dealer-less distributed key generation over any `Field` and `Group`.
+//!
+//! Every participant deals a random secret with Feldman's scheme and sums the
+//! shares it receives. The joint secret is the sum of the dealt secrets and
+//! is never known to anyone. Each participant moves through four steps:
+//!
+//! 1. [`Participant::new`] generates the commitments. The [`Round1Broadcast`] holds the
+//!    commitments to the participant's polynomial and a proof of knowledge of its secret
+//!    and goes to everyone, each [`Round1Share`] goes to one other participant over a
+//!    private channel.
+//! 2. [`Participant::receive_round1`] checks the exchanged shares and returns a
+//!    [`Complaint`] to broadcast against every dealer whose share is missing or
+//!    doesn't match its commitments.
+//! 3. [`Participant::justify`] answers the complaints against the participant with a
+//!    [`Justification`] revealing each disputed share.
+//! 4. [`Participant::finalize`] decides the outcome and sums the shares.
+//!
+//! All participants must use the same generator, broadcasts committing with another
+//! generator are rejected. Every decision is made from broadcast messages alone, so anyone
+//! holding the [`Transcript`] reaches the same verdict as the participants. If anyone
+//! misbehaved the protocol aborts and [`Transcript::blame`] says who and why.
+//! The transport must provide a reliable broadcast: equivocation, sending different
+//! broadcasts to different participants, is not detected here.
+//!
+//! Participants are identified by `1..=limit` which are also the identifiers of the
+//! resulting shares, so they can be combined with `shamir::Scheme::combine_shares`
+//! and checked with the joint [`FeldmanVerifier`].
+//! `secp256k1::dkg` is the same protocol fixed to secp256k1 and its standard generator.
+
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{put, Checkpoint};
+#[cfg(feature = "record")]
+use crate::record::{Recordable, Recording};
+use crate::{
+    audit::{notify, Event},
+    error::{SharingError, SharingResult},
+    evaluate_commitments,
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    shamir::{Scheme as ShamirScheme, Share},
+    transcript, Field, Group, ShareVerifier, Strictness,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "checkpoint")]
+use zeroize::Zeroize;
+
+/// The current transcript format version
+pub const TRANSCRIPT_VERSION: u8 = 1;
+
+const PROOF_DOMAIN: &[u8] = b"ursa_sharing dkg proof of knowledge";
+
+/// Commitments to a participant's polynomial and a proof of knowledge of its secret
+#[derive(Debug)]
+pub struct Round1Broadcast<S: Field, R: Group<S>> {
+    sender: u32,
+    verifier: FeldmanVerifier<S, R>,
+    proof_commitment: R,
+    proof_response: S,
+}
+
+impl<S: Field, R: Group<S>> Round1Broadcast<S, R> {
+    /// The participant that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The generator and the commitments to the sender's polynomial coefficients
+    pub fn verifier(&self) -> &FeldmanVerifier<S, R> {
+        &self.verifier
+    }
+
+    /// Serialize this broadcast
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let verifier = self.verifier.to_bytes();
+        let mut o =
+            Vec::with_capacity(8 + verifier.len() + R::Size::to_usize() + S::Size::to_usize());
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&(verifier.len() as u32).to_be_bytes());
+        o.extend_from_slice(&verifier);
+        o.extend_from_slice(&self.proof_commitment.to_bytes());
+        o.extend_from_slice(&self.proof_response.to_bytes());
+        o
+    }
+
+    /// Deserialize a broadcast using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let sender = reader.u32()?;
+        let len = reader.u32()? as usize;
+        let verifier = FeldmanVerifier::from_bytes_with(reader.take(len)?, strictness)?;
+        let proof_commitment = R::from_bytes_with(reader.take(R::Size::to_usize())?, strictness)?;
+        let proof_response = S::from_bytes_with(reader.take(S::Size::to_usize())?, strictness)?;
+        if !reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            sender,
+            verifier,
+            proof_commitment,
+            proof_response,
+        })
+    }
+
+    fn challenge(sender: u32, g: &R, secret_commitment: &R, r: &R) -> SharingResult<S> {
+        let mut transcript = transcript::Transcript::new(PROOF_DOMAIN);
+        transcript.append_u64(b"sender", sender as u64);
+        transcript.append_point(b"generator", g);
+        transcript.append_point(b"secret commitment", secret_commitment);
+        transcript.append_point(b"commitment", r);
+        transcript.challenge_scalar(b"challenge")
+    }
+
+    /// Check the Schnorr proof of knowledge of the committed secret
+    fn verify_proof(&self) -> bool {
+        let g = &self.verifier.g;
+        let secret_commitment = &self.verifier.commitments[0].value;
+        let c = match Self::challenge(self.sender, g, secret_commitment, &self.proof_commitment) {
+            Ok(c) => c,
+            Err(_) => return false,
+        };
+        // g^z == R * C_0^c
+        let mut lhs = copy(g);
+        lhs.scalar_mul_assign(&self.proof_response);
+        let mut rhs = copy(secret_commitment);
+        rhs.scalar_mul_assign(&c);
+        rhs.add_assign(&self.proof_commitment);
+        lhs.sub_assign(&rhs);
+        lhs.is_zero()
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for Round1Broadcast<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender,
+            verifier: FeldmanVerifier {
+                g: copy(&self.verifier.g),
+                commitments: self.verifier.commitments.clone(),
+            },
+            proof_commitment: copy(&self.proof_commitment),
+            proof_response: copy(&self.proof_response),
+        }
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for Round1Broadcast<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S: Field, R: Group<S>> Recordable for Round1Broadcast<S, R> {
+    const KIND: &'static str = "dkg round1 broadcast";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// A share sent privately from its dealer to a single receiver
+#[derive(Clone, Debug)]
+pub struct Round1Share {
+    sender: u32,
+    share: Share,
+}
+
+impl Round1Share {
+    /// The participant that dealt this share
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The participant this share must be delivered to
+    pub fn receiver(&self) -> u32 {
+        self.share.identifier
+    }
+
+    /// Serialize this share. The result is secret and must only be sent encrypted
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.share.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Round1Share {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let (sender, share) = read_sent_share(data)?;
+        Ok(Self { sender, share })
+    }
+}
+
+/// A broadcast claim that the share from `accused` to `accuser` is missing or invalid
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Complaint {
+    /// The participant filing the complaint
+    pub accuser: u32,
+    /// The dealer complained about
+    pub accused: u32,
+}
+
+#[cfg(feature = "record")]
+impl Recordable for Complaint {
+    const KIND: &'static str = "dkg complaint";
+
+    fn sender(&self) -> u32 {
+        self.accuser
+    }
+
+    /// `accuser (4) | accused (4)`
+    fn to_record_bytes(&self) -> Vec<u8> {
+        let mut o = self.accuser.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.accused.to_be_bytes());
+        o
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let accuser = reader.u32()?;
+        let accused = reader.u32()?;
+        reader.finish()?;
+        Ok(Self { accuser, accused })
+    }
+}
+
+/// A disputed share revealed by its dealer in response to a [`Complaint`]
+#[derive(Clone, Debug)]
+pub struct Justification {
+    sender: u32,
+    share: Share,
+}
+
+impl Justification {
+    /// The dealer revealing the share
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The participant that complained
+    pub fn receiver(&self) -> u32 {
+        self.share.identifier
+    }
+
+    /// Serialize this justification
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.share.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Justification {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let (sender, share) = read_sent_share(data)?;
+        Ok(Self { sender, share })
+    }
+}
+
+#[cfg(feature = "record")]
+impl Recordable for Justification {
+    const KIND: &'static str = "dkg justification";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn receiver(&self) -> Option<u32> {
+        Some(self.share.identifier)
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// The ways a participant can cause the protocol to abort
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Misbehavior {
+    /// No round one broadcast was received
+    MissingBroadcast,
+    /// The round one broadcast has the wrong number of commitments, commits to zero
+    /// or uses another generator
+    InvalidBroadcast,
+    /// The proof of knowledge of the committed secret is invalid
+    InvalidProof,
+    /// A complaint names a participant that doesn't exist or the accuser itself
+    InvalidComplaint,
+    /// A complaint was not answered with a justification
+    MissingJustification,
+    /// A justification reveals a share that doesn't match the dealer's commitments
+    InvalidJustification,
+}
+
+/// A participant identified as misbehaving
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Blame {
+    /// The misbehaving participant
+    pub participant: u32,
+    /// What the participant did wrong
+    pub misbehavior: Misbehavior,
+}
+
+/// All public messages of a DKG session in a canonical order.
+/// Exported for external audit, anyone can replay the verdict with [`Transcript::blame`]
+/// and recompute the joint verifier.
+#[derive(Debug)]
+pub struct Transcript<S: Field, R: Group<S>> {
+    generator: R,
+    threshold: usize,
+    limit: usize,
+    broadcasts: BTreeMap<u32, Round1Broadcast<S, R>>,
+    complaints: BTreeSet<Complaint>,
+    justifications: Vec<Justification>,
+}
+
+impl<S: Field, R: Group<S>> Transcript<S, R> {
+    fn new(generator: R, threshold: usize, limit: usize) -> Self {
+        Self {
+            generator,
+            threshold,
+            limit,
+            broadcasts: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            justifications: Vec::new(),
+        }
+    }
+
+    /// The generator all participants commit with
+    pub fn generator(&self) -> &R {
+        &self.generator
+    }
+
+    /// The threshold of the generated key
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of participants
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The round one broadcasts ordered by sender
+    pub fn broadcasts(&self) -> Vec<&Round1Broadcast<S, R>> {
+        self.broadcasts.values().collect()
+    }
+
+    /// The complaints ordered by accuser then accused
+    pub fn complaints(&self) -> Vec<Complaint> {
+        self.complaints.iter().copied().collect()
+    }
+
+    /// The justifications in the order they were received
+    pub fn justifications(&self) -> &[Justification] {
+        self.justifications.as_slice()
+    }
+
+    fn is_participant(&self, id: u32) -> bool {
+        id != 0 && id as usize <= self.limit
+    }
+
+    fn add_broadcast(&mut self, broadcast: &Round1Broadcast<S, R>) -> SharingResult<()> {
+        if !self.is_participant(broadcast.sender) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        // The first broadcast from each sender is kept, the transport must prevent equivocation
+        self.broadcasts
+            .entry(broadcast.sender)
+            .or_insert_with(|| broadcast.clone());
+        Ok(())
+    }
+
+    /// What is wrong with the broadcast from `id` if anything
+    fn check_broadcast(&self, id: u32) -> Option<Misbehavior> {
+        match self.broadcasts.get(&id) {
+            None => Some(Misbehavior::MissingBroadcast),
+            Some(b)
+                if b.verifier.commitments.len() != self.threshold
+                    || b.verifier.commitments[0].value.is_zero()
+                    || b.verifier.g.to_bytes() != self.generator.to_bytes() =>
+            {
+                Some(Misbehavior::InvalidBroadcast)
+            }
+            Some(b) if !b.verify_proof() => Some(Misbehavior::InvalidProof),
+            Some(_) => None,
+        }
+    }
+
+    /// Find everyone who misbehaved. An empty result means the session succeeded
+    pub fn blame(&self) -> Vec<Blame> {
+        let mut blame = BTreeSet::new();
+        let mut qualified = BTreeSet::new();
+        for id in 1..=self.limit as u32 {
+            match self.check_broadcast(id) {
+                Some(misbehavior) => {
+                    blame.insert(Blame {
+                        participant: id,
+                        misbehavior,
+                    });
+                }
+                None => {
+                    qualified.insert(id);
+                }
+            }
+        }
+
+        for complaint in &self.complaints {
+            if !self.is_participant(complaint.accuser)
+                || !self.is_participant(complaint.accused)
+                || complaint.accuser == complaint.accused
+            {
+                blame.insert(Blame {
+                    participant: complaint.accuser,
+                    misbehavior: Misbehavior::InvalidComplaint,
+                });
+                continue;
+            }
+            // Already disqualified
+            if !qualified.contains(&complaint.accused) {
+                continue;
+            }
+            let verifier = &self.broadcasts[&complaint.accused].verifier;
+            let mut answered = false;
+            let mut valid = true;
+            for j in self.justifications_for(complaint) {
+                answered = true;
+                valid &= verifier.verify(&j.share).is_ok();
+            }
+            let misbehavior = if !answered {
+                Misbehavior::MissingJustification
+            } else if !valid {
+                Misbehavior::InvalidJustification
+            } else {
+                continue;
+            };
+            blame.insert(Blame {
+                participant: complaint.accused,
+                misbehavior,
+            });
+        }
+        blame.into_iter().collect()
+    }
+
+    fn justifications_for<'a>(
+        &'a self,
+        complaint: &'a Complaint,
+    ) -> impl Iterator<Item = &'a Justification> {
+        self.justifications.iter().filter(move |j| {
+            j.sender == complaint.accused && j.share.identifier == complaint.accuser
+        })
+    }
+
+    fn check(&self) -> SharingResult<()> {
+        match self.blame().first() {
+            Some(b) => Err(SharingError::DkgAbort(b.participant)),
+            None => Ok(()),
+        }
+    }
+
+    /// The commitments to the joint polynomial, the sum of everyone's commitments.
+    /// The first commitment is the public key and the resulting shares verify against it.
+    pub fn verifier(&self) -> SharingResult<FeldmanVerifier<S, R>> {
+        self.check()?;
+        let commitments = (0..self.threshold)
+            .map(|i| {
+                let mut value = R::zero();
+                for b in self.broadcasts.values() {
+                    value.add_assign(&b.verifier.commitments[i].value);
+                }
+                ShareVerifier {
+                    value,
+                    phantom: Default::default(),
+                }
+            })
+            .collect();
+        Ok(FeldmanVerifier {
+            g: copy(&self.generator),
+            commitments,
+        })
+    }
+
+    /// The jointly generated public key, the generator raised to the joint secret
+    pub fn public_key(&self) -> SharingResult<R> {
+        Ok(copy(&self.verifier()?.commitments[0].value))
+    }
+
+    /// The public key of the share held by participant `identifier`,
+    /// used to verify its contributions to threshold protocols
+    pub fn verification_share(&self, identifier: u32) -> SharingResult<R> {
+        let verifier = self.verifier()?;
+        if !self.is_participant(identifier) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        Ok(evaluate_commitments(&verifier.commitments, identifier))
+    }
+
+    /// Serialize the transcript
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = vec![TRANSCRIPT_VERSION];
+        o.extend_from_slice(&self.generator.to_bytes());
+        o.extend_from_slice(&(self.threshold as u32).to_be_bytes());
+        o.extend_from_slice(&(self.limit as u32).to_be_bytes());
+        o.extend_from_slice(&(self.broadcasts.len() as u32).to_be_bytes());
+        for b in self.broadcasts.values() {
+            let bytes = b.to_bytes();
+            o.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            o.extend_from_slice(&bytes);
+        }
+        o.extend_from_slice(&(self.complaints.len() as u32).to_be_bytes());
+        for c in &self.complaints {
+            o.extend_from_slice(&c.accuser.to_be_bytes());
+            o.extend_from_slice(&c.accused.to_be_bytes());
+        }
+        o.extend_from_slice(&(self.justifications.len() as u32).to_be_bytes());
+        for j in &self.justifications {
+            o.extend_from_slice(&j.to_bytes());
+        }
+        o
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S: Field, R: Group<S>> Transcript<S, R> {
+    /// Rebuild the transcript of a session from its [`Recording`] so an auditor can
+    /// replay the verdict. The first broadcast recorded from each sender is kept
+    pub fn replay(
+        generator: R,
+        threshold: usize,
+        limit: usize,
+        recording: &Recording,
+    ) -> SharingResult<Self> {
+        ShamirScheme::new(threshold, limit)?;
+        let mut transcript = Self::new(generator, threshold, limit);
+        for broadcast in recording.messages::<Round1Broadcast<S, R>>()? {
+            transcript.add_broadcast(&broadcast)?;
+        }
+        transcript
+            .complaints
+            .extend(recording.messages::<Complaint>()?);
+        transcript
+            .justifications
+            .extend(recording.messages::<Justification>()?);
+        Ok(transcript)
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for Transcript<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let version = reader.take(1)?[0];
+        if version != TRANSCRIPT_VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(version));
+        }
+        let generator = R::from_bytes(reader.take(R::Size::to_usize())?)?;
+        let threshold = reader.u32()? as usize;
+        let limit = reader.u32()? as usize;
+        ShamirScheme::new(threshold, limit)?;
+        let mut transcript = Self::new(generator, threshold, limit);
+
+        for _ in 0..reader.u32()? {
+            let len = reader.u32()? as usize;
+            let broadcast = Round1Broadcast::try_from(reader.take(len)?)?;
+            if transcript.broadcasts.contains_key(&broadcast.sender) {
+                return Err(SharingError::ShareInvalidEncoding);
+            }
+            transcript.add_broadcast(&broadcast)?;
+        }
+        for _ in 0..reader.u32()? {
+            let accuser = reader.u32()?;
+            let accused = reader.u32()?;
+            transcript.complaints.insert(Complaint { accuser, accused });
+        }
+        for _ in 0..reader.u32()? {
+            let justification = Justification::try_from(reader.take(8 + S::Size::to_usize())?)?;
+            transcript.justifications.push(justification);
+        }
+        if !reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(transcript)
+    }
+}
+
+/// The result of a successful DKG session for one participant
+#[derive(Debug)]
+pub struct DkgOutput<S: Field, R: Group<S>> {
+    share: Share,
+    verifier: FeldmanVerifier<S, R>,
+}
+
+impl<S: Field, R: Group<S>> DkgOutput<S, R> {
+    /// This participant's share of the joint secret
+    pub fn share(&self) -> &Share {
+        &self.share
+    }
+
+    /// The commitments to the joint polynomial that every share verifies against
+    pub fn verifier(&self) -> &FeldmanVerifier<S, R> {
+        &self.verifier
+    }
+
+    /// The joint public key
+    pub fn public_key(&self) -> R {
+        copy(&self.verifier.commitments[0].value)
+    }
+
+    /// The public key of this participant's share
+    pub fn verification_share(&self) -> R {
+        evaluate_commitments(&self.verifier.commitments, self.share.identifier)
+    }
+}
+
+/// One participant's state in a DKG session
+#[derive(Debug)]
+pub struct Participant<S: Field, R: Group<S>> {
+    id: u32,
+    /// The shares this participant dealt, by receiver
+    dealt: BTreeMap<u32, Share>,
+    /// The shares this participant received, by dealer
+    received: BTreeMap<u32, Share>,
+    transcript: Transcript<S, R>,
+}
+
+impl<S: Field, R: Group<S>> Participant<S, R> {
+    /// Start a session as participant `id` of `limit` where `threshold` participants
+    /// are needed to use the key and everyone commits with `generator`.
+    /// Returns the new state, the message to broadcast and the shares
+    /// to send privately to each of the other participants.
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        id: u32,
+        threshold: usize,
+        limit: usize,
+        generator: &R,
+    ) -> SharingResult<(Self, Round1Broadcast<S, R>, Vec<Round1Share>)> {
+        let feldman = FeldmanScheme::new(threshold, limit)?;
+        let mut transcript = Transcript::new(copy(generator), threshold, limit);
+        if !transcript.is_participant(id) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+
+        let secret = S::random(rng);
+        let (verifier, shares) = feldman.split_secret(rng, &secret, Some(copy(generator)))?;
+
+        // Schnorr proof of knowledge of the secret prevents rogue key attacks
+        let k = S::random(rng);
+        let mut proof_commitment = copy(generator);
+        proof_commitment.scalar_mul_assign(&k);
+        let c = Round1Broadcast::<S, R>::challenge(
+            id,
+            generator,
+            &verifier.commitments[0].value,
+            &proof_commitment,
+        )?;
+        let mut proof_response = secret;
+        proof_response.scalar_mul_assign(&c);
+        proof_response.add_assign(&k);
+
+        let broadcast = Round1Broadcast {
+            sender: id,
+            verifier,
+            proof_commitment,
+            proof_response,
+        };
+        transcript.add_broadcast(&broadcast)?;
+
+        let dealt = shares
+            .into_iter()
+            .map(|s| (s.identifier, s))
+            .collect::<BTreeMap<u32, Share>>();
+        let mut received = BTreeMap::new();
+        received.insert(id, dealt[&id].clone());
+        let outgoing = dealt
+            .iter()
+            .filter(|(receiver, _)| **receiver != id)
+            .map(|(_, share)| Round1Share {
+                sender: id,
+                share: share.clone(),
+            })
+            .collect();
+
+        Ok((
+            Self {
+                id,
+                dealt,
+                received,
+                transcript,
+            },
+            broadcast,
+            outgoing,
+        ))
+    }
+
+    /// This participant's identifier
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Process the round one messages from the other participants.
+    /// Returns the complaints to broadcast, one for each dealer whose share
+    /// is missing or doesn't match its commitments.
+    pub fn receive_round1(
+        &mut self,
+        broadcasts: &[Round1Broadcast<S, R>],
+        shares: &[Round1Share],
+    ) -> SharingResult<Vec<Complaint>> {
+        for broadcast in broadcasts {
+            self.transcript.add_broadcast(broadcast)?;
+        }
+        for share in shares {
+            if share.receiver() != self.id || !self.transcript.is_participant(share.sender) {
+                return Err(SharingError::DkgInvalidParticipant);
+            }
+            if share.sender != self.id {
+                self.received
+                    .entry(share.sender)
+                    .or_insert_with(|| share.share.clone());
+            }
+        }
+
+        let mut complaints = Vec::new();
+        for (sender, broadcast) in &self.transcript.broadcasts {
+            // Bad broadcasts are blamed from the transcript without a complaint
+            if *sender == self.id || self.transcript.check_broadcast(*sender).is_some() {
+                continue;
+            }
+            let valid = self
+                .received
+                .get(sender)
+                .map(|s| broadcast.verifier.verify(s).is_ok())
+                .unwrap_or(false);
+            if !valid {
+                self.received.remove(sender);
+                complaints.push(Complaint {
+                    accuser: self.id,
+                    accused: *sender,
+                });
+                notify(Event::DkgComplaint {
+                    accuser: self.id,
+                    accused: *sender,
+                });
+            }
+        }
+        Ok(complaints)
+    }
+
+    /// Answer the complaints against this participant by revealing the disputed shares
+    pub fn justify(&self, complaints: &[Complaint]) -> Vec<Justification> {
+        let mut receivers = complaints
+            .iter()
+            .filter(|c| c.accused == self.id)
+            .map(|c| c.accuser)
+            .collect::<Vec<u32>>();
+        receivers.sort_unstable();
+        receivers.dedup();
+        receivers
+            .iter()
+            .filter_map(|r| {
+                self.dealt.get(r).map(|share| Justification {
+                    sender: self.id,
+                    share: share.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Complete the session with all broadcast complaints and justifications.
+    /// Fails with `DkgAbort` naming a misbehaving participant if anyone misbehaved,
+    /// `transcript().blame()` lists all of them.
+    pub fn finalize(
+        &mut self,
+        complaints: &[Complaint],
+        justifications: &[Justification],
+    ) -> SharingResult<DkgOutput<S, R>> {
+        self.transcript
+            .complaints
+            .extend(complaints.iter().copied());
+        self.transcript
+            .justifications
+            .extend(justifications.iter().cloned());
+        let verifier = self.transcript.verifier()?;
+
+        for j in justifications {
+            if j.share.identifier == self.id {
+                self.received.insert(j.sender, j.share.clone());
+            }
+        }
+        let mut secret = S::zero();
+        for sender in self.transcript.broadcasts.keys() {
+            let share = self
+                .received
+                .get(sender)
+                .ok_or(SharingError::DkgAbort(*sender))?;
+            secret.add_assign(&S::from_bytes(share.value())?);
+        }
+
+        Ok(DkgOutput {
+            share: Share {
+                identifier: self.id,
+                value: secret.to_bytes().to_vec(),
+            },
+            verifier,
+        })
+    }
+
+    /// The public messages of this session so far
+    pub fn transcript(&self) -> &Transcript<S, R> {
+        &self.transcript
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl<S: Field, R: Group<S>> Checkpoint for Participant<S, R> {
+    const KIND: &'static [u8] = b"dkg participant";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = self.id.to_be_bytes().to_vec();
+        put(&mut o, &self.transcript.to_bytes());
+        for shares in &[&self.dealt, &self.received] {
+            o.extend_from_slice(&(shares.len() as u32).to_be_bytes());
+            for (participant, share) in shares.iter() {
+                o.extend_from_slice(&participant.to_be_bytes());
+                let mut bytes = share.to_bytes();
+                put(&mut o, &bytes);
+                bytes.zeroize();
+            }
+        }
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let id = reader.u32()?;
+        let transcript = Transcript::try_from(reader.field()?)?;
+        if !transcript.is_participant(id) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        let dealt = read_shares(&mut reader)?;
+        let received = read_shares(&mut reader)?;
+        reader.finish()?;
+        Ok(Self {
+            id,
+            dealt,
+            received,
+            transcript,
+        })
+    }
+}
+
+/// Read shares by participant written by `Participant::to_checkpoint_bytes`
+#[cfg(feature = "checkpoint")]
+fn read_shares(reader: &mut Reader) -> SharingResult<BTreeMap<u32, Share>> {
+    let mut shares = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let participant = reader.u32()?;
+        shares.insert(participant, Share::try_from(reader.field()?)?);
+    }
+    Ok(shares)
+}
+
+/// Copy an element of a group that isn't `Clone`
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+/// Read a sender followed by a share
+fn read_sent_share(data: &[u8]) -> SharingResult<(u32, Share)> {
+    // The sender, the identifier and at least one byte of value
+    if data.len() < 9 {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let share = Share {
+        identifier: read_u32(&data[4..8]),
+        value: data[8..].to_vec(),
+    };
+    Ok((read_u32(&data[..4]), share))
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> SharingResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u32(&mut self) -> SharingResult<u32> {
+        self.take(4).map(read_u32)
+    }
+
+    /// Read bytes prefixed with their length
+    pub(crate) fn field(&mut self) -> SharingResult<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    /// Fail unless everything was read
+    pub(crate) fn finish(&self) -> SharingResult<()> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(SharingError::ShareInvalidEncoding)
+        }
+    }
+}
+
+pub(crate) fn read_u32(data: &[u8]) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&data[..4]);
+    u32::from_be_bytes(b)
+}
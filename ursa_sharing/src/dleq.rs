@@ -0,0 +1,179 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Chaum-Pedersen proofs of discrete log equality.
+//!
+//! A [`DleqProof`] shows that `h1 = g1^x` and `h2 = g2^x` for the same secret `x`
+//! without revealing it. The two bases can be in different groups as long as they
+//! share the scalar field `S`, like the two source groups of a pairing.
+//! This is the proof behind verifiable decryption shares, VRF outputs and
+//! verifiable OPRF evaluations.
+//!
+//! The proof is made non-interactive with a [`Transcript`] supplied by the caller.
+//! The caller starts it with its protocol label and appends any context the proof
+//! must be bound to, e.g. a share identifier or a session id, and the verifier
+//! must rebuild the same transcript. The proof then appends `g1, h1, g2, h2` and
+//! its commitments, each labeled `point`, and draws the challenge labeled `challenge`.
+//!
+//! ```
+//! use ursa_sharing::{dleq::DleqProof, transcript::Transcript};
+//! # use ursa_sharing::{Field, Group, error::SharingResult};
+//! # fn example<S: Field, R: Group<S>>(x: &S, (g1, h1): (&R, &R), (g2, h2): (&R, &R)) -> SharingResult<()> {
+//! let mut transcript = Transcript::new(b"my protocol v1");
+//! transcript.append_message(b"session", b"1234");
+//! let proof = DleqProof::prove(&mut rand::thread_rng(), &mut transcript, x, (g1, h1), (g2, h2))?;
+//!
+//! let mut transcript = Transcript::new(b"my protocol v1");
+//! transcript.append_message(b"session", b"1234");
+//! proof.verify(&mut transcript, (g1, h1), (g2, h2))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    error::{SharingError, SharingResult},
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+
+/// Chaum-Pedersen proof that `log_{g1} h1 == log_{g2} h2`
+#[derive(Debug)]
+pub struct DleqProof<S: Field> {
+    challenge: S,
+    response: S,
+}
+
+impl<S: Field> DleqProof<S> {
+    /// Prove that `h1 = g1^x` and `h2 = g2^x`.
+    /// If they don't the proof is created but doesn't verify
+    pub fn prove<R1: Group<S>, R2: Group<S>>(
+        rng: &mut (impl RngCore + CryptoRng),
+        transcript: &mut Transcript,
+        x: &S,
+        (g1, h1): (&R1, &R1),
+        (g2, h2): (&R2, &R2),
+    ) -> SharingResult<Self> {
+        let w = S::random(rng);
+        let a1 = mul(g1, &w);
+        let a2 = mul(g2, &w);
+        let challenge = dleq_challenge(transcript, (g1, h1, &a1), (g2, h2, &a2))?;
+
+        // z = w - c * x
+        let mut cx = S::zero();
+        cx.add_assign(&challenge);
+        cx.scalar_mul_assign(x);
+        let mut response = w;
+        response.sub_assign(&cx);
+        Ok(Self {
+            challenge,
+            response,
+        })
+    }
+
+    /// Check the proof against a transcript in the same state as the prover's
+    pub fn verify<R1: Group<S>, R2: Group<S>>(
+        &self,
+        transcript: &mut Transcript,
+        (g1, h1): (&R1, &R1),
+        (g2, h2): (&R2, &R2),
+    ) -> SharingResult<()> {
+        // a = g^z * h^c
+        let mut a1 = mul(g1, &self.response);
+        a1.add_assign(&mul(h1, &self.challenge));
+        let mut a2 = mul(g2, &self.response);
+        a2.add_assign(&mul(h2, &self.challenge));
+        let challenge = dleq_challenge::<S, R1, R2>(transcript, (g1, h1, &a1), (g2, h2, &a2))
+            .map_err(|_| SharingError::DleqProofInvalid)?;
+        if challenge.to_bytes() == self.challenge.to_bytes() {
+            Ok(())
+        } else {
+            Err(SharingError::DleqProofInvalid)
+        }
+    }
+
+    /// The size of an encoded proof
+    pub fn size() -> usize {
+        2 * S::Size::to_usize()
+    }
+
+    /// Convert this proof to a byte array
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(Self::size());
+        self.append_bytes(&mut o);
+        o
+    }
+
+    pub(crate) fn append_bytes(&self, o: &mut Vec<u8>) {
+        o.extend_from_slice(self.challenge.to_bytes().as_ref());
+        o.extend_from_slice(self.response.to_bytes().as_ref());
+    }
+
+    /// Convert a byte array to a proof using the acceptance rules of `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        if value.len() != 2 * s_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            challenge: S::from_bytes_with(&value[..s_size], strictness)?,
+            response: S::from_bytes_with(&value[s_size..], strictness)?,
+        })
+    }
+}
+
+impl<S: Field> Clone for DleqProof<S> {
+    fn clone(&self) -> Self {
+        let mut challenge = S::zero();
+        challenge.add_assign(&self.challenge);
+        let mut response = S::zero();
+        response.add_assign(&self.response);
+        Self {
+            challenge,
+            response,
+        }
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for DleqProof<S> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+fn dleq_challenge<S: Field, R1: Group<S>, R2: Group<S>>(
+    transcript: &mut Transcript,
+    (g1, h1, a1): (&R1, &R1, &R1),
+    (g2, h2, a2): (&R2, &R2, &R2),
+) -> SharingResult<S> {
+    transcript.append_point(b"point", g1);
+    transcript.append_point(b"point", h1);
+    transcript.append_point(b"point", g2);
+    transcript.append_point(b"point", h2);
+    transcript.append_point(b"point", a1);
+    transcript.append_point(b"point", a2);
+    transcript.challenge_scalar(b"challenge")
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r.scalar_mul_assign(s);
+    r
+}
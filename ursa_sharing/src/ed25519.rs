@@ -0,0 +1,442 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Sharing of Ed25519 secret keys.
+//!
+//! An Ed25519 secret key is a 32 byte seed that is hashed with SHA-512.
+//! The lower half is clamped to become the signing scalar and the upper half
+//! is the nonce prefix used to derive deterministic signing nonces.
+//! Sharing the seed as if it were a scalar breaks the key because the seed is
+//! reduced modulo the group order and the hash cannot be inverted.
+//!
+//! This module expands the key first, then shares the signing scalar and
+//! the nonce prefix separately. Combining yields the expanded secret key
+//! which produces signatures identical to the original key.
+//! The seed itself cannot be recovered.
+
+use super::{
+    error::{SharingError, SharingResult},
+    shamir::{Scheme as ShamirScheme, Share},
+    Field, Group,
+};
+use alloc::vec::Vec;
+use core::{convert::TryFrom, ops::Neg};
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_POINT,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::{Identity, IsIdentity},
+};
+use generic_array::{typenum::U32, GenericArray};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// The length of an Ed25519 secret key seed
+pub const SECRET_KEY_BYTES: usize = 32;
+/// The length of an expanded Ed25519 secret key
+pub const EXPANDED_SECRET_KEY_BYTES: usize = 64;
+/// The length of an Ed25519 public key
+pub const PUBLIC_KEY_BYTES: usize = 32;
+/// The length of a serialized key share
+pub const KEY_SHARE_BYTES: usize = 4 + 3 * 32;
+
+/// The nonce prefix is shared as two scalars of this many bytes each
+const NONCE_HALF_BYTES: usize = 16;
+
+/// A scalar modulo the order of the Ed25519 prime order subgroup
+#[derive(Copy, Clone, Debug, Zeroize)]
+pub struct Ed25519Scalar(pub Scalar);
+
+impl Field for Ed25519Scalar {
+    fn one() -> Self {
+        Self(Scalar::one())
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(Scalar::from(value as u64))
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        if rhs.is_zero() {
+            return Err(SharingError::DivisionByZero);
+        }
+        self.0 *= rhs.0.invert();
+        Ok(())
+    }
+}
+
+impl Group for Ed25519Scalar {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(Scalar::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        match value.len() {
+            64 => {
+                let mut s = [0u8; 64];
+                s.copy_from_slice(value);
+                let r = Self(Scalar::from_bytes_mod_order_wide(&s));
+                s.zeroize();
+                Ok(r)
+            }
+            l if l <= 32 => {
+                let mut s = [0u8; 32];
+                s[..l].copy_from_slice(value);
+                let r = Self(Scalar::from_bytes_mod_order(s));
+                s.zeroize();
+                Ok(r)
+            }
+            _ => Err(SharingError::ShareInvalidSecret),
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.ct_eq(&Scalar::zero()).unwrap_u8() == 1
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.neg();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        self.0.to_bytes().into()
+    }
+
+    fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// A point on the Ed25519 curve
+#[derive(Copy, Clone, Debug)]
+pub struct Ed25519Point(pub EdwardsPoint);
+
+impl Group<Ed25519Scalar> for Ed25519Point {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(EdwardsPoint::identity())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() != 32 {
+            return Err(SharingError::InvalidPoint);
+        }
+        CompressedEdwardsY::from_slice(value)
+            .decompress()
+            .map(Self)
+            .ok_or(SharingError::InvalidPoint)
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(ED25519_BASEPOINT_POINT * Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_identity()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.0.is_identity()
+    }
+
+    fn is_torsion_free(&self) -> bool {
+        self.0.is_torsion_free()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.neg();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Ed25519Scalar) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        self.0.compress().to_bytes().into()
+    }
+}
+
+/// An expanded Ed25519 secret key.
+/// The byte form matches the 64 byte expanded secret key
+/// accepted by common Ed25519 implementations.
+#[derive(Clone, Zeroize)]
+#[zeroize(drop)]
+pub struct ExpandedSecretKey {
+    /// The signing scalar reduced modulo the group order
+    scalar: Ed25519Scalar,
+    /// The prefix used to derive signing nonces
+    nonce: [u8; 32],
+}
+
+impl ExpandedSecretKey {
+    /// Expand an Ed25519 secret key seed by hashing and clamping it
+    pub fn from_secret_key<B: AsRef<[u8]>>(secret_key: B) -> SharingResult<Self> {
+        let secret_key = secret_key.as_ref();
+        if secret_key.len() != SECRET_KEY_BYTES {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        let mut h = [0u8; EXPANDED_SECRET_KEY_BYTES];
+        h.copy_from_slice(Sha512::digest(secret_key).as_slice());
+        h[0] &= 248;
+        h[31] &= 127;
+        h[31] |= 64;
+        let r = Self::try_from(&h[..]);
+        h.zeroize();
+        r
+    }
+
+    /// The signing scalar
+    pub fn scalar(&self) -> &Ed25519Scalar {
+        &self.scalar
+    }
+
+    /// The nonce prefix
+    pub fn nonce(&self) -> &[u8; 32] {
+        &self.nonce
+    }
+
+    /// Compute the public key for this secret key
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        (ED25519_BASEPOINT_POINT * self.scalar.0)
+            .compress()
+            .to_bytes()
+    }
+
+    /// Serialize to the 64 byte expanded secret key form
+    pub fn to_bytes(&self) -> [u8; EXPANDED_SECRET_KEY_BYTES] {
+        let mut o = [0u8; EXPANDED_SECRET_KEY_BYTES];
+        o[..32].copy_from_slice(self.scalar.0.as_bytes());
+        o[32..].copy_from_slice(&self.nonce);
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for ExpandedSecretKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != EXPANDED_SECRET_KEY_BYTES {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&value[..32]);
+        let scalar = Ed25519Scalar(Scalar::from_bits(s).reduce());
+        s.zeroize();
+        if !scalar.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        let mut nonce = [0u8; 32];
+        nonce.copy_from_slice(&value[32..]);
+        Ok(Self { scalar, nonce })
+    }
+}
+
+/// A share of an expanded Ed25519 secret key
+#[derive(Clone, Debug)]
+pub struct KeyShare {
+    /// The share of the signing scalar
+    scalar: Share,
+    /// The share of the lower half of the nonce prefix
+    nonce_lo: Share,
+    /// The share of the upper half of the nonce prefix
+    nonce_hi: Share,
+}
+
+impl KeyShare {
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.scalar.identifier()
+    }
+
+    /// The share of the signing scalar which can be used
+    /// with the Feldman and Pedersen verifiers
+    pub fn scalar_share(&self) -> &Share {
+        &self.scalar
+    }
+
+    /// Output the identifier followed by the three share values
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(KEY_SHARE_BYTES);
+        o.extend_from_slice(&self.identifier().to_be_bytes());
+        o.extend_from_slice(self.scalar.value());
+        o.extend_from_slice(self.nonce_lo.value());
+        o.extend_from_slice(self.nonce_hi.value());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for KeyShare {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != KEY_SHARE_BYTES {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&value[..4]);
+        let identifier = u32::from_be_bytes(identifier) as usize;
+        Ok(Self {
+            scalar: Share::new(identifier, &value[4..36]),
+            nonce_lo: Share::new(identifier, &value[36..68]),
+            nonce_hi: Share::new(identifier, &value[68..]),
+        })
+    }
+}
+
+/// Splits Ed25519 secret keys so the combined key signs identically to the original
+#[derive(Copy, Clone, Debug)]
+pub struct Scheme {
+    threshold: usize,
+    limit: usize,
+}
+
+impl Scheme {
+    /// Create a new Ed25519 key sharing scheme
+    pub fn new(threshold: usize, limit: usize) -> SharingResult<Self> {
+        ShamirScheme::new(threshold, limit)?;
+        Ok(Self { threshold, limit })
+    }
+
+    /// Create shares from a 32 byte Ed25519 secret key seed
+    pub fn split_secret_key<B: AsRef<[u8]>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret_key: B,
+    ) -> SharingResult<Vec<KeyShare>> {
+        let expanded = ExpandedSecretKey::from_secret_key(secret_key)?;
+        self.split_expanded_secret_key(rng, &expanded)
+    }
+
+    /// Create shares from an expanded Ed25519 secret key
+    pub fn split_expanded_secret_key(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret_key: &ExpandedSecretKey,
+    ) -> SharingResult<Vec<KeyShare>> {
+        let shamir = ShamirScheme::new(self.threshold, self.limit)?;
+        let scalar = shamir.split_secret(rng, &secret_key.scalar)?;
+
+        let mut lo = nonce_half_to_scalar(&secret_key.nonce[..NONCE_HALF_BYTES]);
+        let mut hi = nonce_half_to_scalar(&secret_key.nonce[NONCE_HALF_BYTES..]);
+        let nonce_lo = shamir.split_secret(rng, &lo);
+        let nonce_hi = shamir.split_secret(rng, &hi);
+        lo.zeroize();
+        hi.zeroize();
+
+        Ok(scalar
+            .into_iter()
+            .zip(nonce_lo?)
+            .zip(nonce_hi?)
+            .map(|((scalar, nonce_lo), nonce_hi)| KeyShare {
+                scalar,
+                nonce_lo,
+                nonce_hi,
+            })
+            .collect())
+    }
+
+    /// Reconstruct the expanded secret key from shares created from `split_secret_key`
+    pub fn combine_shares(&self, shares: &[KeyShare]) -> SharingResult<ExpandedSecretKey> {
+        let shamir = ShamirScheme::new(self.threshold, self.limit)?;
+        for s in shares {
+            if s.nonce_lo.identifier() != s.identifier()
+                || s.nonce_hi.identifier() != s.identifier()
+            {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+        }
+
+        let scalar_shares = shares.iter().map(|s| s.scalar.clone()).collect::<Vec<_>>();
+        let lo_shares = shares
+            .iter()
+            .map(|s| s.nonce_lo.clone())
+            .collect::<Vec<_>>();
+        let hi_shares = shares
+            .iter()
+            .map(|s| s.nonce_hi.clone())
+            .collect::<Vec<_>>();
+
+        let scalar =
+            shamir.combine_shares::<Ed25519Scalar, Ed25519Scalar>(scalar_shares.as_slice())?;
+        let lo = shamir.combine_shares::<Ed25519Scalar, Ed25519Scalar>(lo_shares.as_slice())?;
+        let hi = shamir.combine_shares::<Ed25519Scalar, Ed25519Scalar>(hi_shares.as_slice())?;
+
+        let mut nonce = [0u8; 32];
+        scalar_to_nonce_half(&lo, &mut nonce[..NONCE_HALF_BYTES])?;
+        scalar_to_nonce_half(&hi, &mut nonce[NONCE_HALF_BYTES..])?;
+        Ok(ExpandedSecretKey { scalar, nonce })
+    }
+}
+
+/// Encode half of the nonce prefix as a scalar.
+/// A marker bit above the half is set so the scalar is never zero.
+fn nonce_half_to_scalar(half: &[u8]) -> Ed25519Scalar {
+    let mut s = [0u8; 32];
+    s[..NONCE_HALF_BYTES].copy_from_slice(half);
+    s[NONCE_HALF_BYTES] = 1;
+    let r = Ed25519Scalar(Scalar::from_bits(s));
+    s.zeroize();
+    r
+}
+
+/// Decode half of the nonce prefix from a scalar created by `nonce_half_to_scalar`
+fn scalar_to_nonce_half(scalar: &Ed25519Scalar, half: &mut [u8]) -> SharingResult<()> {
+    let mut s = scalar.0.to_bytes();
+    // The marker byte must be 1 and the rest 0, checked without exiting early
+    let mut marker = [0u8; 32 - NONCE_HALF_BYTES];
+    marker[0] = 1;
+    let valid = s[NONCE_HALF_BYTES..].ct_eq(&marker[..]).unwrap_u8() == 1;
+    half.copy_from_slice(&s[..NONCE_HALF_BYTES]);
+    s.zeroize();
+    if valid {
+        Ok(())
+    } else {
+        half.zeroize();
+        Err(SharingError::ShareInvalidValue)
+    }
+}
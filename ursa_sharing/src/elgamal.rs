@@ -0,0 +1,169 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! ElGamal encryption of group elements with rerandomizable ciphertexts.
+//!
+//! A [`Ciphertext`] of `m` under the public key `y = g^x` is `(g^r, m * y^r)`.
+//! [`Ciphertext::rerandomize`] multiplies in an encryption of the identity, giving
+//! `(g^(r + t), m * y^(r + t))`: a fresh ciphertext of the same message that nobody
+//! without `x` can link to the original, as mix nets and voting protocols need.
+//! [`Ciphertext::encrypt_with`] and [`Ciphertext::rerandomize_with`] take the
+//! randomness from the caller for protocols that prove statements about it, such as a
+//! DLEQ proof that a ciphertext was rerandomized.
+//!
+//! Threshold decryption raises `c1` to each shareholder's share and combines the
+//! results with `shamir::Scheme::combine_commitments`, then removes `c1^x` with
+//! [`Ciphertext::decrypt_with`], so the key is never reconstructed.
+//!
+//! The ciphertext is encoded as `c1 | c2`.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group, Strictness,
+};
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+
+/// An ElGamal ciphertext `(g^r, m * y^r)`
+#[derive(Debug)]
+pub struct Ciphertext<S: Field, R: Group<S>> {
+    c1: R,
+    c2: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: Group<S>> Ciphertext<S, R> {
+    /// Encrypt `message` to `public_key = g^x`
+    pub fn encrypt(
+        rng: &mut (impl RngCore + CryptoRng),
+        g: &R,
+        public_key: &R,
+        message: &R,
+    ) -> SharingResult<Self> {
+        let mut r = S::random(rng);
+        let c = Self::encrypt_with(g, public_key, message, &r);
+        r.zeroize();
+        c
+    }
+
+    /// Encrypt `message` to `public_key = g^x` with the randomness `r`.
+    /// Fails with `SharingError::ShareInvalidRecipientKey` if the key isn't valid
+    pub fn encrypt_with(g: &R, public_key: &R, message: &R, r: &S) -> SharingResult<Self> {
+        if !public_key.is_valid() {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        let mut c2 = mul(public_key, r);
+        c2.add_assign(message);
+        Ok(Self {
+            c1: mul(g, r),
+            c2,
+            _scalar: PhantomData,
+        })
+    }
+
+    /// A fresh ciphertext of the same message under the same key
+    pub fn rerandomize(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        g: &R,
+        public_key: &R,
+    ) -> SharingResult<Self> {
+        let mut t = S::random(rng);
+        let c = self.rerandomize_with(g, public_key, &t);
+        t.zeroize();
+        c
+    }
+
+    /// Rerandomize with `t`, giving `(c1 * g^t, c2 * y^t)`.
+    /// Fails with `SharingError::ShareInvalidRecipientKey` if the key isn't valid
+    pub fn rerandomize_with(&self, g: &R, public_key: &R, t: &S) -> SharingResult<Self> {
+        let mut c = Self::encrypt_with(g, public_key, &R::zero(), t)?;
+        c.c1.add_assign(&self.c1);
+        c.c2.add_assign(&self.c2);
+        Ok(c)
+    }
+
+    /// `g^r`
+    pub fn c1(&self) -> &R {
+        &self.c1
+    }
+
+    /// `m * y^r`
+    pub fn c2(&self) -> &R {
+        &self.c2
+    }
+
+    /// Decrypt with the secret key `x`
+    pub fn decrypt(&self, secret_key: &S) -> R {
+        self.decrypt_with(&mul(&self.c1, secret_key))
+    }
+
+    /// Decrypt with `c1^x` computed elsewhere, e.g. combined from shareholders
+    pub fn decrypt_with(&self, shared: &R) -> R {
+        let mut m = copy(&self.c2);
+        m.sub_assign(shared);
+        m
+    }
+
+    /// Encode the ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.c1.to_bytes().to_vec();
+        out.extend_from_slice(&self.c2.to_bytes());
+        out
+    }
+
+    /// Decode a ciphertext using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let size = R::Size::to_usize();
+        if data.len() != 2 * size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            c1: R::from_bytes_with(&data[..size], strictness)?,
+            c2: R::from_bytes_with(&data[size..], strictness)?,
+            _scalar: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for Ciphertext<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for Ciphertext<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            c1: copy(&self.c1),
+            c2: copy(&self.c2),
+            _scalar: PhantomData,
+        }
+    }
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
@@ -0,0 +1,295 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Entropy for operations that don't take a caller supplied RNG.
+//!
+//! Every scheme in this crate accepts an RNG from the caller and never
+//! draws randomness on its own. The one-shot APIs like
+//! `backup::backup_secret` use [`SystemRng`] instead which reads from
+//! the operating system through `getrandom`. This works on all tier one
+//! platforms, WASI and, with the `wasm` feature, `wasm32-unknown-unknown`
+//! in browsers and node.js via `crypto.getRandomValues`.
+//!
+//! Targets where none of these are available can register their own
+//! [`EntropySource`] with [`set_entropy_source`] which [`SystemRng`]
+//! will use in place of the operating system.
+//!
+//! Air-gapped key ceremonies that must control every entropy input can
+//! choose the source when generating keys. [`OsEntropy`] is the operating
+//! system, [`SeedEntropy`] expands dice rolls or a seed file with SHAKE256
+//! and [`MixedEntropy`] hashes the output of several sources together so it
+//! is unpredictable as long as any one of them is. [`EntropyRng`] turns a
+//! source into the RNG every scheme takes:
+//!
+//! ```ignore
+//! let dice = SeedEntropy::from_dice(&rolls)?;
+//! let source = MixedEntropy::new(vec![Box::new(OsEntropy), Box::new(dice)]);
+//! let shares = scheme.split_secret(&mut EntropyRng::new(&source), &secret);
+//! ```
+
+use crate::error::{SharingError, SharingResult};
+use rand::{rngs::OsRng, CryptoRng, Error, RngCore};
+use sha3::{
+    digest::{ExtendableOutput, Input, XofReader},
+    Sha3XofReader, Shake256,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    path::Path,
+    sync::{Mutex, RwLock},
+};
+use zeroize::Zeroize;
+
+/// The fewest bytes [`SeedEntropy::from_seed`] accepts, 256 bits
+pub const MIN_SEED_BYTES: usize = 32;
+/// The fewest rolls of a six sided die [`SeedEntropy::from_dice`] accepts, 258 bits
+pub const MIN_DICE_ROLLS: usize = 100;
+
+/// Bytes drawn from every source of a [`MixedEntropy`] for each fill
+const MIX_BYTES: usize = 64;
+
+/// A cryptographically secure source of random bytes
+pub trait EntropySource: Send + Sync {
+    /// Fill `dest` entirely with random bytes or return an error
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Error>;
+}
+
+static SOURCE: RwLock<Option<Box<dyn EntropySource>>> = RwLock::new(None);
+
+/// Register the entropy source used by [`SystemRng`].
+/// Replaces any previously registered source.
+pub fn set_entropy_source(source: Box<dyn EntropySource>) {
+    if let Ok(mut s) = SOURCE.write() {
+        *s = Some(source);
+    }
+}
+
+/// Remove the registered entropy source so [`SystemRng`] uses the operating system
+pub fn clear_entropy_source() {
+    if let Ok(mut s) = SOURCE.write() {
+        *s = None;
+    }
+}
+
+/// A random number generator that reads from the registered [`EntropySource`]
+/// or the operating system if there isn't one
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemRng;
+
+impl RngCore for SystemRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("Error: {}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        let s = SOURCE
+            .read()
+            .map_err(|_| Error::new("entropy source lock poisoned"))?;
+        match s.as_ref() {
+            Some(source) => source.fill(dest),
+            None => OsRng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl CryptoRng for SystemRng {}
+
+/// The operating system's random number generator
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Error> {
+        OsRng.try_fill_bytes(dest)
+    }
+}
+
+/// Entropy expanded with SHAKE256 from a seed the caller controls, like dice rolls
+/// or a seed file. The output is as unpredictable as the seed and repeats for the
+/// same seed, so a seed must not be reused for another ceremony
+pub struct SeedEntropy {
+    reader: Mutex<Sha3XofReader>,
+}
+
+impl SeedEntropy {
+    /// Expand `seed`, which must have at least [`MIN_SEED_BYTES`] bytes
+    pub fn from_seed(seed: &[u8]) -> SharingResult<Self> {
+        if seed.len() < MIN_SEED_BYTES {
+            return Err(SharingError::EntropyTooShort(seed.len()));
+        }
+        Ok(Self::expand(b"seed", seed))
+    }
+
+    /// Expand the rolls of a six sided die written as the digits 1 to 6.
+    /// Whitespace and commas between rolls are ignored and there must be at least
+    /// [`MIN_DICE_ROLLS`] rolls
+    pub fn from_dice(rolls: &str) -> SharingResult<Self> {
+        let mut digits = Vec::with_capacity(rolls.len());
+        for c in rolls.chars() {
+            match c {
+                '1'..='6' => digits.push(c as u8),
+                ',' => {}
+                c if c.is_whitespace() => {}
+                _ => {
+                    digits.zeroize();
+                    return Err(SharingError::EntropyInvalidDiceRoll);
+                }
+            }
+        }
+        if digits.len() < MIN_DICE_ROLLS {
+            let rolls = digits.len();
+            digits.zeroize();
+            return Err(SharingError::EntropyTooShort(rolls));
+        }
+        let source = Self::expand(b"dice", &digits);
+        digits.zeroize();
+        Ok(source)
+    }
+
+    /// Expand the contents of the seed file at `path` like [`Self::from_seed`]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> SharingResult<Self> {
+        let mut seed = std::fs::read(path).map_err(|_| SharingError::EntropySeedFile)?;
+        let source = Self::from_seed(&seed);
+        seed.zeroize();
+        source
+    }
+
+    pub(crate) fn expand(kind: &[u8], input: &[u8]) -> Self {
+        let mut xof = Shake256::default();
+        xof.input(b"ursa_sharing entropy ");
+        xof.input(kind);
+        xof.input((input.len() as u64).to_be_bytes());
+        xof.input(input);
+        Self {
+            reader: Mutex::new(xof.xof_result()),
+        }
+    }
+}
+
+impl EntropySource for SeedEntropy {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Error> {
+        let mut reader = self
+            .reader
+            .lock()
+            .map_err(|_| Error::new("seed entropy lock poisoned"))?;
+        reader.read(dest);
+        Ok(())
+    }
+}
+
+impl Debug for SeedEntropy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "SeedEntropy(..)")
+    }
+}
+
+/// Entropy from hashing the output of several sources together with SHAKE256.
+/// It is as unpredictable as the best of them, so a ceremony can combine the
+/// operating system with dice rolls without trusting either alone
+pub struct MixedEntropy {
+    sources: Vec<Box<dyn EntropySource>>,
+}
+
+impl MixedEntropy {
+    /// Mix `sources`, in this order. Filling fails if there are none
+    pub fn new(sources: Vec<Box<dyn EntropySource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl EntropySource for MixedEntropy {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Error> {
+        if self.sources.is_empty() {
+            return Err(Error::new("no entropy sources to mix"));
+        }
+        let mut xof = Shake256::default();
+        xof.input(b"ursa_sharing entropy mix");
+        xof.input((dest.len() as u64).to_be_bytes());
+        let mut bytes = [0u8; MIX_BYTES];
+        for source in &self.sources {
+            if let Err(e) = source.fill(&mut bytes) {
+                bytes.zeroize();
+                return Err(e);
+            }
+            xof.input(bytes);
+        }
+        bytes.zeroize();
+        xof.xof_result().read(dest);
+        Ok(())
+    }
+}
+
+impl Debug for MixedEntropy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "MixedEntropy({} sources)", self.sources.len())
+    }
+}
+
+/// A random number generator that reads from an [`EntropySource`],
+/// to pick the source of a single key generation
+pub struct EntropyRng<'a> {
+    source: &'a dyn EntropySource,
+}
+
+impl<'a> EntropyRng<'a> {
+    /// Read from `source`
+    pub fn new(source: &'a dyn EntropySource) -> Self {
+        Self { source }
+    }
+}
+
+impl RngCore for EntropyRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("Error: {}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.source.fill(dest)
+    }
+}
+
+impl CryptoRng for EntropyRng<'_> {}
+
+impl Debug for EntropyRng<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "EntropyRng(..)")
+    }
+}
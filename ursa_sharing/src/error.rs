@@ -15,10 +15,9 @@
 //!
 //! Uses a kind enum for the error type
 
-use std::{
-    error::Error,
-    fmt::{Display, Formatter, Result as FmtResult},
-};
+use crate::fips::Algorithm;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter, Result as FmtResult};
 
 /// A specialized [`Result`] type for Sharing operations.
 pub type SharingResult<T> = Result<T, SharingError>;
@@ -38,6 +37,8 @@ pub enum SharingError {
     ShareInvalidIdentifier,
     /// More than one secret share identifier is duplicated when recombining
     ShareDuplicateIdentifier,
+    /// The number of supplied share identifiers does not match the share limit
+    ShareIdentifierCount(usize, usize),
     /// The secret share value is corrupted or invalid
     ShareInvalidValue,
     /// Deserializing less than the minimum size for a pedersen verifier
@@ -46,6 +47,174 @@ pub enum SharingError {
     PedersenBlindShareInvalid,
     /// Deserializing an invalid ECC point
     InvalidPoint,
+    /// Strict deserialization of an element that isn't in its canonical encoding
+    NonCanonicalEncoding,
+    /// Strict deserialization of the identity element
+    IdentityElement,
+    /// Strict deserialization of an element outside the prime order group
+    InvalidSubgroup,
+    /// Division by or inversion of the zero element
+    DivisionByZero,
+    /// The encoded share is malformed or truncated
+    ShareInvalidEncoding,
+    /// The encoded share checksum does not match its contents
+    ShareChecksumMismatch,
+    /// The encoded share format version is not supported
+    ShareUnsupportedVersion(u8),
+    /// The shares do not belong to the same backup
+    ShareMismatchedBackup,
+    /// The share is encrypted but no passphrase was supplied
+    SharePassphraseRequired,
+    /// A passphrase was supplied but the shares aren't encrypted
+    SharePassphraseUnexpected,
+    /// The share could not be decrypted with the supplied passphrase
+    ShareDecryptionFailed,
+    /// The number of recipients does not match the share limit
+    ShareRecipientCount(usize, usize),
+    /// The recipient key is malformed or of the wrong type
+    ShareInvalidRecipientKey,
+    /// The passphrase key derivation parameters of a wrapped share are out of range
+    ShareWrapParamsInvalid,
+    /// The DKG aborted because the participant with this identifier misbehaved
+    DkgAbort(u32),
+    /// A DKG message names a participant that isn't part of the session
+    DkgInvalidParticipant,
+    /// The share possession proof does not verify
+    SharePossessionProofInvalid,
+    /// The escrow transcript doesn't match the scheme's threshold or limit
+    EscrowTranscriptMismatch,
+    /// The proof for the escrowed share with this identifier is not valid
+    EscrowProofInvalid(u32),
+    /// The encoded share names a curve tag that isn't known
+    ShareUnknownCurve(u8),
+    /// The share belongs to a different curve
+    ShareMismatchedCurve,
+    /// The shares were tagged with different thresholds
+    ShareMismatchedThreshold,
+    /// The discrete log equality proof does not verify
+    DleqProofInvalid,
+    /// The partial BLS signature from the signer with this identifier is not valid
+    BlsPartialSignatureInvalid(u32),
+    /// The BLS signature is not valid
+    BlsSignatureInvalid,
+    /// The commitments don't use the standard generator of the BLS public keys
+    BlsInvalidGenerator,
+    /// Threshold ECDSA signing aborted because the signer with this identifier misbehaved
+    EcdsaAbort(u32),
+    /// The signer set is invalid or a signing message names a signer that isn't part of it
+    EcdsaInvalidSigner,
+    /// The signers' nonce or key shares are inconsistent but the culprit isn't known
+    EcdsaConsistencyCheck,
+    /// The message hash is not as long as a scalar
+    EcdsaInvalidMessageHash,
+    /// The ECDSA signature is not valid
+    EcdsaSignatureInvalid,
+    /// The Paillier key is malformed or too small
+    PaillierInvalidKey,
+    /// The Paillier ciphertext is not a unit modulo the key's modulus squared
+    PaillierInvalidCiphertext,
+    /// FROST signing aborted because the signer with this identifier misbehaved
+    FrostAbort(u32),
+    /// The FROST signer set is too small or doesn't include this signer's commitments
+    FrostInvalidSigner,
+    /// The FROST signature is not valid
+    FrostSignatureInvalid,
+    /// The commitments don't use the base point of the FROST ciphersuite
+    FrostInvalidGenerator,
+    /// The one time key wasn't derived for this stealth address
+    StealthKeyNotOwned,
+    /// The derivation path is malformed
+    DerivationPathInvalid,
+    /// The derivation scheme doesn't support this kind of child index
+    DerivationIndexUnsupported,
+    /// The seed is shorter than the derivation scheme allows
+    DerivationSeedTooShort,
+    /// The permutation is malformed, has the wrong length or doesn't open the commitment
+    PermutationInvalid,
+    /// The shuffle proof is not valid
+    ShuffleProofInvalid,
+    /// The receipt wasn't signed by the dealer
+    ReceiptSignatureInvalid,
+    /// The opening doesn't open the receipt to the payload
+    ReceiptOpeningInvalid,
+    /// The payload wasn't assigned to the recipient, or the recipients are empty,
+    /// repeated or don't match the payloads
+    ReceiptAssignmentInvalid,
+    /// The checkpoint was written by an unsupported format version
+    CheckpointVersion(u8),
+    /// The checkpoint was modified, is for another kind of state or was sealed with another key
+    CheckpointInvalid,
+    /// The access policy has an empty or unreachable threshold, a zero weight or identifier,
+    /// or is nested too deeply
+    PolicyInvalid,
+    /// The holders of the shares don't satisfy the access policy
+    PolicyNotSatisfied,
+    /// The share was split under another access policy or holds points of another holder
+    PolicyMismatch,
+    /// The algorithm isn't on the FIPS mode allow-list
+    AlgorithmNotApproved(Algorithm),
+    /// The recording was exported by an unsupported format version
+    RecordingVersion(u8),
+    /// The recording is malformed, an entry doesn't match the hash chain
+    /// or holds a message that doesn't parse or doesn't match its sender
+    RecordingInvalid,
+    /// The evaluation domain size or the number of values isn't a power of two
+    /// the field supports
+    FieldDomainSize(usize),
+    /// The OPRF input hashes to the identity or is longer than 65535 bytes
+    OprfInvalidInput,
+    /// The POPRF info tweaks the server key to zero or is longer than 65535 bytes
+    OprfInvalidInfo,
+    /// No OPRF key could be derived from the seed
+    OprfDeriveKeyPair,
+    /// The OPRF evaluation proof is not valid
+    OprfProofInvalid,
+    /// The OPRF batch is empty, too large or its lists have different lengths
+    OprfBatchInvalid,
+    /// The commitments of a threshold OPRF key don't use the ciphersuite's generator
+    OprfInvalidGenerator,
+    /// The partial OPRF evaluation from the server with this identifier is not valid
+    OprfPartialEvaluationInvalid(u32),
+    /// The requested `expand_message_xmd` output is longer than 255 hash blocks
+    /// or 65535 bytes
+    HashToCurveLength(usize),
+    /// The number of values to interpolate doesn't match the number of points
+    PolynomialValueCount(usize, usize),
+    /// The entropy seed has this many bytes or dice rolls, fewer than the minimum
+    EntropyTooShort(usize),
+    /// The dice rolls contain something other than the digits 1 to 6
+    EntropyInvalidDiceRoll,
+    /// The entropy seed file could not be read
+    EntropySeedFile,
+    /// The attribute-based ciphertext was modified or encrypted under another authority
+    AbeDecryptionFailed,
+    /// The capsule of a re-encryptable ciphertext was modified
+    PreInvalidCapsule,
+    /// The capsule fragment doesn't re-encrypt the capsule or is from another delegation
+    PreInvalidFragment,
+    /// The re-encryptable ciphertext was modified, encrypted to another key or there are
+    /// too few capsule fragments
+    PreDecryptionFailed,
+    /// The identity key wasn't extracted for its identity by this authority
+    IbeInvalidKey,
+    /// The identity-based ciphertext was modified or encrypted to another identity or authority
+    IbeDecryptionFailed,
+    /// The epoch key wasn't released by this committee
+    TimelockInvalidEpochKey,
+    /// The epoch key is for another epoch than the ciphertext
+    TimelockWrongEpoch(u64),
+    /// The timelock ciphertext was modified or not encrypted to the epoch's committee
+    TimelockDecryptionFailed,
+    /// The opening doesn't open the timelock ciphertext to the plaintext
+    TimelockInvalidOpening,
+    /// The partial VRF evaluation from the node with this identifier is not valid
+    VrfPartialEvaluationInvalid(u32),
+    /// The VRF proof is not valid for the input
+    VrfProofInvalid,
+    /// The reshares or commitments from the dealer with this identifier are not valid
+    RefreshAbort(u32),
+    /// The custodian's share belongs to this epoch instead of the wallet's
+    CustodyWrongEpoch(u64),
 }
 
 impl Display for SharingError {
@@ -62,6 +231,11 @@ impl Display for SharingError {
                 f,
                 "Duplicate shares cannot be used to reconstruct the secret"
             ),
+            ShareIdentifierCount(expected, found) => write!(
+                f,
+                "Share identifiers count mismatch: expected {}, found {}",
+                expected, found
+            ),
             ShareInvalidValue => write!(f, "Share is not valid"),
             PedersenVerifierMinSize(expected, found) => write!(
                 f,
@@ -70,8 +244,156 @@ impl Display for SharingError {
             ),
             PedersenBlindShareInvalid => write!(f, "Blind share is not valid"),
             InvalidPoint => write!(f, "Invalid curve point"),
+            NonCanonicalEncoding => write!(f, "Element is not canonically encoded"),
+            IdentityElement => write!(f, "Element is the identity"),
+            InvalidSubgroup => write!(f, "Element is not in the prime order group"),
+            DivisionByZero => write!(f, "Division by zero"),
+            ShareInvalidEncoding => write!(f, "Share encoding is malformed"),
+            ShareChecksumMismatch => write!(f, "Share checksum does not match"),
+            ShareUnsupportedVersion(version) => {
+                write!(f, "Share format version {} is not supported", version)
+            }
+            ShareMismatchedBackup => write!(f, "Shares belong to different backups"),
+            SharePassphraseRequired => write!(f, "Share is encrypted and requires a passphrase"),
+            SharePassphraseUnexpected => {
+                write!(f, "Share is not encrypted but a passphrase was supplied")
+            }
+            ShareDecryptionFailed => write!(f, "Share could not be decrypted"),
+            ShareRecipientCount(expected, found) => write!(
+                f,
+                "Share recipients count mismatch: expected {}, found {}",
+                expected, found
+            ),
+            ShareInvalidRecipientKey => write!(f, "Recipient key is not valid"),
+            ShareWrapParamsInvalid => write!(
+                f,
+                "Wrapped share key derivation parameters are out of range"
+            ),
+            DkgAbort(participant) => write!(
+                f,
+                "DKG aborted because participant {} misbehaved",
+                participant
+            ),
+            DkgInvalidParticipant => write!(f, "DKG message names an unknown participant"),
+            SharePossessionProofInvalid => write!(f, "Share possession proof is not valid"),
+            EscrowTranscriptMismatch => {
+                write!(
+                    f,
+                    "Escrow transcript does not match the threshold and limit"
+                )
+            }
+            EscrowProofInvalid(identifier) => {
+                write!(f, "Escrowed share {} proof is not valid", identifier)
+            }
+            ShareUnknownCurve(tag) => write!(f, "Share curve tag {} is not known", tag),
+            ShareMismatchedCurve => write!(f, "Share belongs to a different curve"),
+            ShareMismatchedThreshold => write!(f, "Shares have different thresholds"),
+            DleqProofInvalid => write!(f, "Discrete log equality proof is not valid"),
+            BlsPartialSignatureInvalid(identifier) => {
+                write!(f, "Partial signature {} is not valid", identifier)
+            }
+            BlsSignatureInvalid => write!(f, "Signature is not valid"),
+            BlsInvalidGenerator => write!(f, "Commitments do not use the standard generator"),
+            EcdsaAbort(signer) => write!(f, "Signing aborted because signer {} misbehaved", signer),
+            EcdsaInvalidSigner => write!(f, "Signing message names an unknown signer"),
+            EcdsaConsistencyCheck => write!(f, "Signing shares are inconsistent"),
+            EcdsaInvalidMessageHash => write!(f, "Message hash has the wrong length"),
+            EcdsaSignatureInvalid => write!(f, "ECDSA signature is not valid"),
+            PaillierInvalidKey => write!(f, "Paillier key is not valid"),
+            PaillierInvalidCiphertext => write!(f, "Paillier ciphertext is not valid"),
+            FrostAbort(signer) => write!(f, "Signing aborted because signer {} misbehaved", signer),
+            FrostInvalidSigner => write!(f, "Signing commitments don't form a valid signer set"),
+            FrostSignatureInvalid => write!(f, "Schnorr signature is not valid"),
+            FrostInvalidGenerator => write!(f, "Commitments do not use the standard generator"),
+            StealthKeyNotOwned => write!(f, "One time key belongs to another address"),
+            DerivationPathInvalid => write!(f, "Derivation path is malformed"),
+            DerivationIndexUnsupported => {
+                write!(f, "Child index is not supported by the derivation scheme")
+            }
+            DerivationSeedTooShort => write!(f, "Seed is too short for key derivation"),
+            PermutationInvalid => write!(f, "Permutation is invalid for these elements"),
+            ShuffleProofInvalid => write!(f, "Shuffle proof is not valid"),
+            ReceiptSignatureInvalid => write!(f, "Receipt signature is not valid"),
+            ReceiptOpeningInvalid => write!(f, "Receipt opening is not valid"),
+            ReceiptAssignmentInvalid => write!(f, "Payload was not assigned to the recipient"),
+            CheckpointVersion(v) => write!(f, "Checkpoint version {} is not supported", v),
+            CheckpointInvalid => write!(f, "Checkpoint could not be opened"),
+            PolicyInvalid => write!(f, "Access policy is invalid"),
+            PolicyNotSatisfied => write!(f, "Shares don't satisfy the access policy"),
+            PolicyMismatch => write!(f, "Share doesn't belong to the access policy"),
+            AlgorithmNotApproved(a) => write!(f, "{} is not approved in FIPS mode", a),
+            RecordingVersion(v) => write!(f, "Recording version {} is not supported", v),
+            RecordingInvalid => write!(f, "Recording is invalid"),
+            FieldDomainSize(n) => write!(f, "{} is not a valid evaluation domain size", n),
+            OprfInvalidInput => write!(f, "OPRF input is invalid"),
+            OprfInvalidInfo => write!(f, "POPRF info is invalid for this key"),
+            OprfDeriveKeyPair => write!(f, "Could not derive an OPRF key from the seed"),
+            OprfProofInvalid => write!(f, "OPRF evaluation proof is not valid"),
+            OprfBatchInvalid => write!(f, "OPRF batch is empty or has mismatched lengths"),
+            OprfInvalidGenerator => {
+                write!(f, "OPRF key commitments must use the suite's generator")
+            }
+            OprfPartialEvaluationInvalid(identifier) => {
+                write!(f, "Partial OPRF evaluation {} is not valid", identifier)
+            }
+            HashToCurveLength(n) => write!(f, "Cannot expand a message to {} bytes", n),
+            PolynomialValueCount(e, a) => {
+                write!(f, "Expected {} values to interpolate, found {}", e, a)
+            }
+            EntropyTooShort(n) => write!(f, "Entropy seed of {} bytes or rolls is too short", n),
+            EntropyInvalidDiceRoll => write!(f, "Dice rolls must be the digits 1 to 6"),
+            EntropySeedFile => write!(f, "Entropy seed file could not be read"),
+            AbeDecryptionFailed => write!(f, "Attribute-based ciphertext could not be decrypted"),
+            PreInvalidCapsule => write!(f, "Capsule is not valid"),
+            PreInvalidFragment => write!(f, "Capsule fragment is not valid"),
+            PreDecryptionFailed => write!(f, "Re-encrypted ciphertext could not be decrypted"),
+            IbeInvalidKey => write!(f, "Identity key is not valid"),
+            IbeDecryptionFailed => write!(f, "Identity-based ciphertext could not be decrypted"),
+            TimelockInvalidEpochKey => write!(f, "Epoch key is not valid"),
+            TimelockWrongEpoch(epoch) => write!(f, "Epoch key is for epoch {}", epoch),
+            TimelockDecryptionFailed => write!(f, "Timelock ciphertext could not be decrypted"),
+            TimelockInvalidOpening => write!(f, "Timelock opening is not valid"),
+            VrfPartialEvaluationInvalid(identifier) => {
+                write!(f, "Partial VRF evaluation {} is not valid", identifier)
+            }
+            VrfProofInvalid => write!(f, "VRF proof is not valid"),
+            RefreshAbort(dealer) => {
+                write!(f, "Resharing aborted because dealer {} misbehaved", dealer)
+            }
+            CustodyWrongEpoch(epoch) => write!(f, "Custodian share is from epoch {}", epoch),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SharingError {}
+
+/// The error type for combining with cheater detection.
+/// Identifies the parties whose shares failed verification so they can be excluded
+#[derive(Clone, Debug)]
+pub enum DetectionError {
+    /// The shares with these identifiers failed verification, in the order given
+    Cheaters(Vec<u32>),
+    /// Combining failed for a reason not attributable to a share
+    Sharing(SharingError),
+}
+
+impl From<SharingError> for DetectionError {
+    fn from(e: SharingError) -> Self {
+        DetectionError::Sharing(e)
+    }
+}
+
+impl Display for DetectionError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            DetectionError::Cheaters(ids) => {
+                write!(f, "Shares failed verification: {:?}", ids)
+            }
+            DetectionError::Sharing(e) => write!(f, "{}", e),
         }
     }
 }
 
-impl Error for SharingError {}
+#[cfg(feature = "std")]
+impl std::error::Error for DetectionError {}
@@ -0,0 +1,425 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Publicly verifiable escrow of a secret with a set of escrow agents.
+//!
+//! Implements Schoenmakers' publicly verifiable secret sharing
+//! (see <https://www.win.tue.nl/~berry/papers/crypto99.pdf>).
+//!
+//! Each agent has a key pair `x_i`, `y_i = h^{x_i}`. The dealer commits to the sharing
+//! polynomial with generator `g` like Feldman, encrypts share `s_i` as `Y_i = y_i^{s_i}`
+//! and proves `log_g X_i == log_{y_i} Y_i` where `X_i` is the commitments evaluated at
+//! the agent's identifier. The resulting [`EscrowTranscript`] can be checked by anyone
+//! without any secrets.
+//!
+//! When recovery is required `threshold` agents decrypt `h^{s_i} = Y_i^{1/x_i}`, each
+//! with a proof of correct decryption, and the shares are combined in the exponent to
+//! give `h^secret`. The secret itself is never recovered so the escrowed value is
+//! `h^secret`, typically hashed to derive a key.
+
+use super::{
+    audit::SchemeKind,
+    dleq::DleqProof,
+    error::{SharingError, SharingResult},
+    evaluate_commitments,
+    shamir::{Scheme as ShamirScheme, Share as ShamirShare},
+    transcript::Transcript,
+    Field, Group, ShareVerifier, Strictness,
+};
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+
+const DOMAIN: &[u8] = b"ursa_sharing escrow v1";
+const ENCRYPT: u8 = 1;
+const DECRYPT: u8 = 2;
+
+/// Publicly verifiable escrow of a secret with `limit` agents
+/// where any `threshold` of them can recover it.
+#[derive(Copy, Clone, Debug)]
+pub struct Scheme {
+    threshold: usize,
+    limit: usize,
+}
+
+impl Scheme {
+    /// Create a new escrow scheme
+    pub fn new(threshold: usize, limit: usize) -> SharingResult<Self> {
+        if limit < threshold {
+            return Err(SharingError::ShareLimitLessThanThreshold);
+        }
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        Ok(Self { threshold, limit })
+    }
+
+    /// Escrow `secret` with the `agents` public keys which are multiples of `h`.
+    /// Agent `i` in the slice receives the share with identifier `i + 1`.
+    /// Caller can optionally supply the generator for the commitments.
+    /// If [`None`] is passed as the parameter then the `R::random()` is used.
+    pub fn escrow<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        g: Option<R>,
+        h: &R,
+        agents: &[R],
+    ) -> SharingResult<EscrowTranscript<S, R>> {
+        if agents.len() != self.limit {
+            return Err(SharingError::ShareRecipientCount(self.limit, agents.len()));
+        }
+        if agents.iter().any(|y| !y.is_valid()) || !h.is_valid() {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        let g = g.unwrap_or_else(|| R::random(rng));
+
+        let shamir = ShamirScheme::new(self.threshold, self.limit)?;
+        let (shares, polynomial) = shamir.get_shares_and_polynomial(rng, secret)?;
+        shamir.notify_split(SchemeKind::Escrow, shares.as_slice());
+
+        let commitments = polynomial
+            .coefficients
+            .iter()
+            .map(|c| ShareVerifier {
+                value: mul(&g, c),
+                phantom: PhantomData,
+            })
+            .collect::<Vec<ShareVerifier<S, R>>>();
+
+        let mut encrypted = Vec::with_capacity(self.limit);
+        for (share, y) in shares.iter().zip(agents.iter()) {
+            let s = S::from_bytes(&share.value)?;
+            let x = evaluate_commitments(&commitments, share.identifier);
+            let value = mul(y, &s);
+            let proof = DleqProof::prove(
+                rng,
+                &mut proof_transcript(ENCRYPT, share.identifier),
+                &s,
+                (&g, &x),
+                (y, &value),
+            )?;
+            encrypted.push(EncryptedShare { value, proof });
+        }
+
+        Ok(EscrowTranscript {
+            g,
+            h: copy(h),
+            agents: agents.iter().map(copy).collect(),
+            commitments,
+            shares: encrypted,
+        })
+    }
+
+    /// Check that every encrypted share in the transcript is consistent with the
+    /// commitments. Needs no secrets so anyone can audit the escrow.
+    pub fn verify_transcript<S: Field, R: Group<S>>(
+        &self,
+        transcript: &EscrowTranscript<S, R>,
+    ) -> SharingResult<()> {
+        self.check_parameters(transcript)?;
+        for identifier in 1..=self.limit as u32 {
+            transcript.verify_share(identifier)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the share for `identifier` with the agent's `secret_key`.
+    /// The result includes a proof of correct decryption so it can be
+    /// handed to whoever combines the shares.
+    pub fn decrypt_share<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        transcript: &EscrowTranscript<S, R>,
+        identifier: u32,
+        secret_key: &S,
+    ) -> SharingResult<DecryptedShare<S, R>> {
+        self.check_parameters(transcript)?;
+        let index = self.index(identifier)?;
+        let y = &transcript.agents[index];
+        if !secret_key.is_valid() || mul(&transcript.h, secret_key).to_bytes() != y.to_bytes() {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        transcript.verify_share(identifier)?;
+
+        let encrypted = &transcript.shares[index].value;
+        let inverse = secret_key.invert()?;
+        let value = mul(encrypted, &inverse);
+        let proof = DleqProof::prove(
+            rng,
+            &mut proof_transcript(DECRYPT, identifier),
+            secret_key,
+            (&transcript.h, y),
+            (&value, encrypted),
+        )?;
+        Ok(DecryptedShare {
+            identifier,
+            value,
+            proof,
+        })
+    }
+
+    /// Check an agent decrypted its share correctly
+    pub fn verify_decrypted_share<S: Field, R: Group<S>>(
+        &self,
+        transcript: &EscrowTranscript<S, R>,
+        share: &DecryptedShare<S, R>,
+    ) -> SharingResult<()> {
+        self.check_parameters(transcript)?;
+        let index = self.index(share.identifier)?;
+        share
+            .proof
+            .verify(
+                &mut proof_transcript(DECRYPT, share.identifier),
+                (&transcript.h, &transcript.agents[index]),
+                (&share.value, &transcript.shares[index].value),
+            )
+            .map_err(|_| SharingError::EscrowProofInvalid(share.identifier))
+    }
+
+    /// Verify the decrypted shares and combine them to recover `h^secret`
+    pub fn recover<S: Field, R: Group<S>>(
+        &self,
+        transcript: &EscrowTranscript<S, R>,
+        shares: &[DecryptedShare<S, R>],
+    ) -> SharingResult<R> {
+        let mut lifted = Vec::with_capacity(shares.len());
+        for share in shares {
+            self.verify_decrypted_share(transcript, share)?;
+            lifted.push(ShamirShare {
+                identifier: share.identifier,
+                value: share.value.to_bytes().to_vec(),
+            });
+        }
+        ShamirScheme::new(self.threshold, self.limit)?.combine_shares::<S, R>(&lifted)
+    }
+
+    fn check_parameters<S: Field, R: Group<S>>(
+        &self,
+        transcript: &EscrowTranscript<S, R>,
+    ) -> SharingResult<()> {
+        if transcript.commitments.len() != self.threshold
+            || transcript.agents.len() != self.limit
+            || transcript.shares.len() != self.limit
+        {
+            return Err(SharingError::EscrowTranscriptMismatch);
+        }
+        Ok(())
+    }
+
+    fn index(&self, identifier: u32) -> SharingResult<usize> {
+        if identifier == 0 || identifier as usize > self.limit {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(identifier as usize - 1)
+    }
+}
+
+/// The public record of an escrow.
+/// Contains the commitments, the agents' public keys and the encrypted shares.
+#[derive(Debug)]
+pub struct EscrowTranscript<S: Field, R: Group<S>> {
+    g: R,
+    h: R,
+    agents: Vec<R>,
+    commitments: Vec<ShareVerifier<S, R>>,
+    shares: Vec<EncryptedShare<S, R>>,
+}
+
+impl<S: Field, R: Group<S>> EscrowTranscript<S, R> {
+    /// The commitment to the escrowed value `g^secret`
+    pub fn public_commitment(&self) -> R {
+        copy(&self.commitments[0].value)
+    }
+
+    /// Convert this transcript to a byte array
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.g.to_bytes().to_vec();
+        o.extend_from_slice(self.h.to_bytes().as_ref());
+        o.extend_from_slice(&(self.commitments.len() as u32).to_be_bytes());
+        o.extend_from_slice(&(self.agents.len() as u32).to_be_bytes());
+        for c in &self.commitments {
+            o.extend_from_slice(c.to_bytes().as_ref());
+        }
+        for y in &self.agents {
+            o.extend_from_slice(y.to_bytes().as_ref());
+        }
+        for s in &self.shares {
+            o.extend_from_slice(s.value.to_bytes().as_ref());
+            s.proof.append_bytes(&mut o);
+        }
+        o
+    }
+
+    fn verify_share(&self, identifier: u32) -> SharingResult<()> {
+        let index = identifier as usize - 1;
+        let share = &self.shares[index];
+        let x = evaluate_commitments(&self.commitments, identifier);
+        share
+            .proof
+            .verify(
+                &mut proof_transcript(ENCRYPT, identifier),
+                (&self.g, &x),
+                (&self.agents[index], &share.value),
+            )
+            .map_err(|_| SharingError::EscrowProofInvalid(identifier))
+    }
+
+    /// Deserialize a transcript using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        let mut reader = Reader(value);
+        let g = R::from_bytes_with(reader.read(r_size)?, strictness)?;
+        let h = R::from_bytes_with(reader.read(r_size)?, strictness)?;
+        let threshold = reader.read_u32()? as usize;
+        let limit = reader.read_u32()? as usize;
+        let share_size = r_size + DleqProof::<S>::size();
+        if reader.0.len() != (threshold + limit) * r_size + limit * share_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut commitments = Vec::with_capacity(threshold);
+        for _ in 0..threshold {
+            commitments.push(ShareVerifier::from_bytes_with(
+                reader.read(r_size)?,
+                strictness,
+            )?);
+        }
+        let mut agents = Vec::with_capacity(limit);
+        for _ in 0..limit {
+            agents.push(R::from_bytes_with(reader.read(r_size)?, strictness)?);
+        }
+        let mut shares = Vec::with_capacity(limit);
+        for _ in 0..limit {
+            let value = R::from_bytes_with(reader.read(r_size)?, strictness)?;
+            let proof =
+                DleqProof::from_bytes_with(reader.read(DleqProof::<S>::size())?, strictness)?;
+            shares.push(EncryptedShare { value, proof });
+        }
+        Ok(Self {
+            g,
+            h,
+            agents,
+            commitments,
+            shares,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for EscrowTranscript<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// A share decrypted by an escrow agent with a proof the decryption is correct
+#[derive(Debug)]
+pub struct DecryptedShare<S: Field, R: Group<S>> {
+    identifier: u32,
+    value: R,
+    proof: DleqProof<S>,
+}
+
+impl<S: Field, R: Group<S>> DecryptedShare<S, R> {
+    /// The identifier of the agent that decrypted this share
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// Convert this share to a byte array
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.identifier.to_be_bytes().to_vec();
+        o.extend_from_slice(self.value.to_bytes().as_ref());
+        self.proof.append_bytes(&mut o);
+        o
+    }
+
+    /// Deserialize a decrypted share using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        if value.len() != 4 + r_size + DleqProof::<S>::size() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut reader = Reader(value);
+        let identifier = reader.read_u32()?;
+        let value = R::from_bytes_with(reader.read(r_size)?, strictness)?;
+        let proof = DleqProof::from_bytes_with(reader.0, strictness)?;
+        Ok(Self {
+            identifier,
+            value,
+            proof,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for DecryptedShare<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+#[derive(Debug)]
+struct EncryptedShare<S: Field, R: Group<S>> {
+    value: R,
+    proof: DleqProof<S>,
+}
+
+/// The proof context is the operation followed by the share identifier
+fn context(operation: u8, identifier: u32) -> [u8; 5] {
+    let mut c = [operation, 0, 0, 0, 0];
+    c[1..].copy_from_slice(&identifier.to_be_bytes());
+    c
+}
+
+/// Start the transcript of the proof for `operation` on the share `identifier`
+fn proof_transcript(operation: u8, identifier: u32) -> Transcript {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_message(b"context", &context(operation, identifier));
+    transcript
+}
+
+fn copy<S: Field, R: Group<S>>(p: &R) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> SharingResult<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> SharingResult<u32> {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(self.read(4)?);
+        Ok(u32::from_be_bytes(b))
+    }
+}
@@ -11,14 +11,20 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+#[cfg(feature = "recipient")]
+use super::recipient::{encrypt_shares, EncryptedShare, RecipientPublicKey};
 use super::{
-    error::{SharingError, SharingResult},
+    audit::{notify, Event, SchemeKind},
+    error::{DetectionError, SharingError, SharingResult},
+    evaluate_commitments,
+    possession::PossessionProof,
     shamir::{Scheme as ShamirScheme, Share as ShamirShare},
-    Field, Group, ShareVerifier,
+    Field, Group, Polynomial, ShareVerifier, Strictness,
 };
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
 use generic_array::typenum::Unsigned;
 use rand::{CryptoRng, RngCore};
-use std::{convert::TryFrom, marker::PhantomData};
 
 /// Feldman's Verifiable secret sharing scheme.
 /// (see <https://www.cs.umd.edu/~gasarch/TOPICS/secretsharing/feldmanVSS.pdf>
@@ -50,9 +56,61 @@ impl Scheme {
         secret: &S,
         g: Option<R>,
     ) -> SharingResult<(FeldmanVerifier<S, R>, Vec<ShamirShare>)> {
-        let (shares, polynomial) = ShamirScheme::new(self.threshold, self.limit)?
-            .get_shares_and_polynomial(rng, secret)?;
+        let shamir = ShamirScheme::new(self.threshold, self.limit)?;
+        let (shares, polynomial) = shamir.get_shares_and_polynomial(rng, secret)?;
+        shamir.notify_split(SchemeKind::Feldman, shares.as_slice());
+        Ok(self.create_verifier(rng, g, shares, polynomial))
+    }
+
+    /// Create Shares from a secret using the caller supplied identifiers
+    /// as the x-coordinates. See [`ShamirScheme::split_secret_with_identifiers`].
+    /// Caller can optionally supply a random generator for use
+    /// when computing share verifiers.
+    /// If [`None`] is passed as the parameter then the `R::random()` is used.
+    pub fn split_secret_with_identifiers<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        identifiers: &[u32],
+        g: Option<R>,
+    ) -> SharingResult<(FeldmanVerifier<S, R>, Vec<ShamirShare>)> {
+        let shamir = ShamirScheme::new(self.threshold, self.limit)?;
+        let (shares, polynomial) =
+            shamir.get_shares_and_polynomial_with_identifiers(rng, secret, identifiers)?;
+        shamir.notify_split(SchemeKind::Feldman, shares.as_slice());
+        Ok(self.create_verifier(rng, g, shares, polynomial))
+    }
+
+    /// Create Shares from a secret and encrypt each one to the recipient
+    /// at the same position. See [`ShamirScheme::split_encrypted`].
+    /// The verifier is public and returned in the clear.
+    #[cfg(feature = "recipient")]
+    #[cfg_attr(feature = "nightly", doc(cfg(feature = "recipient")))]
+    pub fn split_encrypted<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        g: Option<R>,
+        recipients: &[RecipientPublicKey],
+    ) -> SharingResult<(FeldmanVerifier<S, R>, Vec<EncryptedShare>)> {
+        if recipients.len() != self.limit {
+            return Err(SharingError::ShareRecipientCount(
+                self.limit,
+                recipients.len(),
+            ));
+        }
+        let (verifier, shares) = self.split_secret(rng, secret, g)?;
+        let encrypted = encrypt_shares(rng, shares.as_slice(), recipients)?;
+        Ok((verifier, encrypted))
+    }
 
+    fn create_verifier<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        g: Option<R>,
+        shares: Vec<ShamirShare>,
+        polynomial: Polynomial<S>,
+    ) -> (FeldmanVerifier<S, R>, Vec<ShamirShare>) {
         let g = g.unwrap_or_else(|| R::random(rng));
 
         // Generate the verifiable commitments to the polynomial for the shares
@@ -69,7 +127,7 @@ impl Scheme {
             });
         }
 
-        Ok((FeldmanVerifier { g, commitments: vs }, shares))
+        (FeldmanVerifier { g, commitments: vs }, shares)
     }
 
     /// Checks if the share is valid according to verifier set
@@ -77,49 +135,62 @@ impl Scheme {
         &self,
         share: &ShamirShare,
         verifier: &FeldmanVerifier<S, R>,
+    ) -> SharingResult<()> {
+        let res = self.verify(share, verifier);
+        if let Err(error) = res {
+            notify(Event::VerifyFailed {
+                scheme: SchemeKind::Feldman,
+                identifier: share.identifier,
+                error,
+            });
+        }
+        res
+    }
+
+    fn verify<S: Field, R: Group<S>>(
+        &self,
+        share: &ShamirShare,
+        verifier: &FeldmanVerifier<S, R>,
     ) -> SharingResult<()> {
         if verifier.commitments.len() < self.threshold {
             return Err(SharingError::ShareMinThreshold);
         }
-        let s = S::from_bytes(&share.value)?;
-        if !s.is_valid() {
-            return Err(SharingError::ShareInvalidValue);
-        }
-
-        let x = S::from_usize(share.identifier as usize);
-        let mut i = S::one();
-
-        // FUTURE: execute this sum of products
-        // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
-        // as a constant time operation using <https://cr.yp.to/papers/pippenger.pdf>
-        // or Guide to Elliptic Curve Cryptography book,
-        // "Algorithm 3.48 Simultaneous multiple point multiplication"
-        // without precomputing the addition but still reduces doublings
+        verifier.verify(share)
+    }
 
-        // c_0
-        let mut rhs = R::zero();
-        rhs.add_assign(&verifier.commitments[0].value);
-        for v in &verifier.commitments[1..] {
-            // i *= x
-            i.scalar_mul_assign(&x);
+    /// Prove possession of `share` without revealing it.
+    /// The share is checked against the verifier first so a bad share is caught
+    /// by its holder. `context` should be a fresh value from whoever checks the proof.
+    pub fn prove_possession<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &ShamirShare,
+        verifier: &FeldmanVerifier<S, R>,
+        context: &[u8],
+    ) -> SharingResult<PossessionProof<S, R>> {
+        self.verify_share(share, verifier)?;
+        let s = S::from_bytes(&share.value)?;
+        let y = evaluate_commitments(&verifier.commitments, share.identifier);
+        PossessionProof::feldman(rng, share.identifier, &s, &verifier.g, &y, context)
+    }
 
-            // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
-            let mut c = R::zero();
-            c.add_assign(&v.value);
-            c.scalar_mul_assign(&i);
-            rhs.add_assign(&c);
+    /// Check a proof from the holder of the share with `identifier`
+    /// created by `prove_possession` with the same `context`
+    pub fn verify_possession<S: Field, R: Group<S>>(
+        &self,
+        identifier: u32,
+        proof: &PossessionProof<S, R>,
+        verifier: &FeldmanVerifier<S, R>,
+        context: &[u8],
+    ) -> SharingResult<()> {
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
         }
-        let mut lhs = R::zero();
-        lhs.add_assign(&verifier.g);
-        lhs.negate();
-        lhs.scalar_mul_assign(&s);
-        rhs.add_assign(&lhs);
-
-        if rhs.is_zero() {
-            Ok(())
-        } else {
-            Err(SharingError::ShareInvalidValue)
+        if verifier.commitments.len() < self.threshold {
+            return Err(SharingError::ShareMinThreshold);
         }
+        let y = evaluate_commitments(&verifier.commitments, identifier);
+        proof.verify_feldman(identifier, &verifier.g, &y, context)
     }
 
     /// Reconstruct a secret from shares created from `split_secret`.
@@ -132,6 +203,29 @@ impl Scheme {
     ) -> SharingResult<R> {
         ShamirScheme::new(self.threshold, self.limit)?.combine_shares::<S, R>(shares)
     }
+
+    /// Verify every share then reconstruct the secret.
+    /// Fails with [`DetectionError::Cheaters`] naming every share that doesn't match
+    /// the verifier, instead of returning a wrong secret, so the holders can be excluded
+    /// and the secret combined from the remaining shares.
+    pub fn combine_with_detection<S: Field, R: Group<S>>(
+        &self,
+        shares: &[ShamirShare],
+        verifier: &FeldmanVerifier<S, R>,
+    ) -> Result<S, DetectionError> {
+        if verifier.commitments.len() < self.threshold {
+            return Err(SharingError::ShareMinThreshold.into());
+        }
+        let cheaters = shares
+            .iter()
+            .filter(|share| self.verify_share(share, verifier).is_err())
+            .map(|share| share.identifier)
+            .collect::<Vec<u32>>();
+        if !cheaters.is_empty() {
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        Ok(self.combine_shares::<S, S>(shares)?)
+    }
 }
 
 /// A Feldman verifier is used to provide integrity checking of shamir shares
@@ -144,6 +238,55 @@ pub struct FeldmanVerifier<S: Field, R: Group<S>> {
 }
 
 impl<S: Field, R: Group<S>> FeldmanVerifier<S, R> {
+    /// Checks if the share is valid according to these commitments.
+    /// The threshold is the number of commitments,
+    /// use `Scheme::verify_share` to also check it matches the scheme
+    pub fn verify_share(&self, share: &ShamirShare) -> SharingResult<()> {
+        let res = self.verify(share);
+        if let Err(error) = res {
+            notify(Event::VerifyFailed {
+                scheme: SchemeKind::Feldman,
+                identifier: share.identifier,
+                error,
+            });
+        }
+        res
+    }
+
+    /// The generator raised to the share with `identifier`, computed from the commitments.
+    /// Used to verify the share holder's contributions to threshold protocols
+    pub fn verification_share(&self, identifier: u32) -> SharingResult<R> {
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        if self.commitments.is_empty() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        Ok(evaluate_commitments(&self.commitments, identifier))
+    }
+
+    pub(crate) fn verify(&self, share: &ShamirShare) -> SharingResult<()> {
+        if self.commitments.is_empty() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let s = S::from_bytes(&share.value)?;
+        // Checked at the end so the time doesn't depend on the share value
+        let valid = s.is_valid();
+
+        let mut rhs = evaluate_commitments(&self.commitments, share.identifier);
+        let mut lhs = R::zero();
+        lhs.add_assign(&self.g);
+        lhs.negate();
+        lhs.scalar_mul_assign(&s);
+        rhs.add_assign(&lhs);
+
+        if valid & rhs.is_zero() {
+            Ok(())
+        } else {
+            Err(SharingError::ShareInvalidValue)
+        }
+    }
+
     /// Convert this verifier to a byte array
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut o = self.g.to_bytes().to_vec();
@@ -153,12 +296,9 @@ impl<S: Field, R: Group<S>> FeldmanVerifier<S, R> {
         }
         o
     }
-}
-
-impl<S: Field, R: Group<S>> TryFrom<&[u8]> for FeldmanVerifier<S, R> {
-    type Error = SharingError;
 
-    fn try_from(value: &[u8]) -> SharingResult<Self> {
+    /// Deserialize a verifier using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
         if value.len() < R::Size::to_usize() * 2 + 4 {
             return Err(SharingError::PedersenVerifierMinSize(
                 R::Size::to_usize() * 2 + 4,
@@ -167,7 +307,7 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for FeldmanVerifier<S, R> {
         }
         let mut offset = 0;
         let mut end = R::Size::to_usize();
-        let g = R::from_bytes(&value[offset..end])?;
+        let g = R::from_bytes_with(&value[offset..end], strictness)?;
 
         offset = end;
         end += 4;
@@ -175,11 +315,17 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for FeldmanVerifier<S, R> {
         let mut c_size = [0u8; 4];
         c_size.copy_from_slice(&value[offset..end]);
         let cs = u32::from_be_bytes(c_size) as usize;
+        if (value.len() - end) / R::Size::to_usize() < cs {
+            return Err(SharingError::PedersenVerifierMinSize(
+                end + cs * R::Size::to_usize(),
+                value.len(),
+            ));
+        }
         let mut commitments = Vec::with_capacity(cs);
-        offset = end;
-        end += R::Size::to_usize();
         for _ in 0..cs {
-            let c = R::from_bytes(&value[offset..end])?;
+            offset = end;
+            end += R::Size::to_usize();
+            let c = R::from_bytes_with(&value[offset..end], strictness)?;
             commitments.push(ShareVerifier {
                 value: c,
                 phantom: PhantomData,
@@ -188,3 +334,11 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for FeldmanVerifier<S, R> {
         Ok(Self { g, commitments })
     }
 }
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for FeldmanVerifier<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! C bindings for splitting and combining secrets.
+//!
+//! The functions are exported by the `ursa` library when built with its `ffi` feature
+//! and declared in `include/ursa_sharing.h`, which binding generators for Go, Python
+//! and others can read. They follow the FFI of the main ursa crate:
+//!
+//! * Inputs are [`ByteArray`]s pointing to memory owned by the caller.
+//! * Outputs are `ByteBuffer`s owned by Rust, freed with [`ursa_sharing_bytebuffer_free`]
+//!   which also clears them.
+//! * Every function takes an `ExternError` it sets to the error code in [`error_codes`]
+//!   and a message the caller frees with `ursa_sharing_string_free`, or to zero on success.
+//!
+//! Shares live in Rust behind opaque `u64` handles to a set of shares. [`ursa_sharing_split`]
+//! returns a new set and [`ursa_sharing_shares_get`] copies a share out to store or send it.
+//! To combine, create a set with [`ursa_sharing_shares_new`], add the shares with
+//! [`ursa_sharing_shares_add`] and pass it to [`ursa_sharing_combine`]. Free sets with
+//! [`ursa_sharing_shares_free`]. Handles are checked so using one after it's freed
+//! returns an error instead of reading freed memory.
+//!
+//! Curves are named as in the `wasm` bindings, one of `"ed25519"`, `"ristretto25519"`,
+//! `"secp256k1"` or `"secp256r1"` when its feature is enabled.
+//! The `_bytes` variants split byte strings of any length over GF(256).
+//!
+//! ```c
+//! struct ExternError err = {0};
+//! struct ByteArray secret = {32, key};
+//! uint64_t shares = ursa_sharing_split("secp256k1", &secret, 2, 3, &err);
+//! struct ByteBuffer share = ursa_sharing_shares_get(shares, 0, &err);
+//! ```
+
+// Without a curve only the byte string bindings are used
+#![cfg_attr(
+    not(any(
+        feature = "ed25519",
+        feature = "ristretto",
+        feature = "secp256k1",
+        feature = "secp256r1"
+    )),
+    allow(dead_code)
+)]
+
+use crate::{entropy::SystemRng, error::SharingError, gf256, shamir::Scheme, shamir::Share, Field};
+use ffi_support::{
+    call_with_result, define_string_destructor, ByteBuffer, ConcurrentHandleMap, ErrorCode,
+    ExternError, FfiStr,
+};
+use lazy_static::lazy_static;
+use std::convert::TryFrom;
+use zeroize::Zeroize;
+
+/// The error codes set in `ExternError`. `ffi_support` also uses `-1` for a
+/// panic and `-1000` and below for invalid handles.
+pub mod error_codes {
+    /// A null pointer was passed where data was expected, or the curve name isn't UTF-8
+    pub const INVALID_PARAM: i32 = 1;
+    /// The curve isn't known or its feature isn't enabled
+    pub const UNSUPPORTED_CURVE: i32 = 2;
+    /// Splitting or combining failed, the message holds the `SharingError`
+    pub const SHARING_ERROR: i32 = 3;
+    /// The index is past the end of the set of shares
+    pub const INDEX_OUT_OF_RANGE: i32 = 4;
+}
+
+/// Bytes passed in from C, owned by the caller
+#[repr(C)]
+#[derive(Debug)]
+pub struct ByteArray {
+    length: usize,
+    data: *const u8,
+}
+
+impl ByteArray {
+    fn as_slice(&self) -> Result<&[u8], ExternError> {
+        if self.length == 0 {
+            Ok(&[])
+        } else if self.data.is_null() {
+            Err(invalid_param("Null data pointer"))
+        } else {
+            Ok(unsafe { std::slice::from_raw_parts(self.data, self.length) })
+        }
+    }
+}
+
+impl From<&[u8]> for ByteArray {
+    /// Point at `input`, which must outlive the `ByteArray`
+    fn from(input: &[u8]) -> Self {
+        Self {
+            length: input.len(),
+            data: input.as_ptr(),
+        }
+    }
+}
+
+/// A set of encoded shares, cleared when removed from the map
+struct Shares(Vec<Vec<u8>>);
+
+impl Drop for Shares {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+lazy_static! {
+    static ref SHARES: ConcurrentHandleMap<Shares> = ConcurrentHandleMap::new();
+}
+
+define_string_destructor!(ursa_sharing_string_free);
+
+impl From<SharingError> for ExternError {
+    fn from(e: SharingError) -> Self {
+        ExternError::new_error(ErrorCode::new(error_codes::SHARING_ERROR), e.to_string())
+    }
+}
+
+/// Free a buffer returned by these functions, clearing it first
+#[no_mangle]
+pub extern "C" fn ursa_sharing_bytebuffer_free(buffer: ByteBuffer) {
+    ffi_support::abort_on_panic::with_abort_on_panic(|| buffer.destroy_into_vec().zeroize())
+}
+
+/// Split a secret scalar of `curve` into `limit` shares, `threshold` of which recover it.
+/// Returns the handle to the set of shares, or zero on error.
+#[no_mangle]
+pub extern "C" fn ursa_sharing_split(
+    curve: FfiStr<'_>,
+    secret: &ByteArray,
+    threshold: u32,
+    limit: u32,
+    err: &mut ExternError,
+) -> u64 {
+    SHARES.insert_with_result(err, || {
+        let secret = secret.as_slice()?;
+        with_curve!(
+            curve_name(&curve)?,
+            split(secret, threshold as usize, limit as usize),
+            unsupported
+        )
+    })
+}
+
+/// Recover a secret scalar of `curve` from at least `threshold` shares in the set
+/// `shares` from [`ursa_sharing_split`]
+#[no_mangle]
+pub extern "C" fn ursa_sharing_combine(
+    curve: FfiStr<'_>,
+    threshold: u32,
+    shares: u64,
+    err: &mut ExternError,
+) -> ByteBuffer {
+    SHARES.call_with_result(err, shares, |shares| {
+        with_curve!(
+            curve_name(&curve)?,
+            combine(threshold as usize, &shares.0),
+            unsupported
+        )
+    })
+}
+
+/// Split a byte string into `limit` shares, `threshold` of which recover it.
+/// Returns the handle to the set of shares, or zero on error.
+#[no_mangle]
+pub extern "C" fn ursa_sharing_split_bytes(
+    secret: &ByteArray,
+    threshold: u32,
+    limit: u32,
+    err: &mut ExternError,
+) -> u64 {
+    SHARES.insert_with_result(err, || -> Result<_, ExternError> {
+        let scheme = gf256::Scheme::new(threshold as usize, limit as usize)?;
+        let shares = scheme.split_secret(&mut SystemRng, secret.as_slice()?)?;
+        Ok(Shares(shares.iter().map(|s| s.to_bytes()).collect()))
+    })
+}
+
+/// Recover a byte string from at least `threshold` shares in the set `shares`
+/// from [`ursa_sharing_split_bytes`]
+#[no_mangle]
+pub extern "C" fn ursa_sharing_combine_bytes(
+    threshold: u32,
+    shares: u64,
+    err: &mut ExternError,
+) -> ByteBuffer {
+    SHARES.call_with_result(err, shares, |shares| -> Result<_, ExternError> {
+        let shares = shares
+            .0
+            .iter()
+            .map(|s| gf256::Share::try_from(s.as_slice()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let scheme = gf256::Scheme::new(threshold as usize, threshold as usize)?;
+        Ok(ByteBuffer::from_vec(scheme.combine_shares(&shares)?))
+    })
+}
+
+/// Create an empty set of shares to add to for combining.
+/// Returns the handle to the set.
+#[no_mangle]
+pub extern "C" fn ursa_sharing_shares_new(err: &mut ExternError) -> u64 {
+    SHARES.insert_with_output(err, || Shares(Vec::new()))
+}
+
+/// Add a copy of an encoded share to the set `shares`
+#[no_mangle]
+pub extern "C" fn ursa_sharing_shares_add(shares: u64, share: &ByteArray, err: &mut ExternError) {
+    SHARES.call_with_result_mut(err, shares, |shares| -> Result<_, ExternError> {
+        shares.0.push(share.as_slice()?.to_vec());
+        Ok(())
+    })
+}
+
+/// Free the set of shares `shares`, clearing them
+#[no_mangle]
+pub extern "C" fn ursa_sharing_shares_free(shares: u64, err: &mut ExternError) {
+    call_with_result(err, || SHARES.delete_u64(shares))
+}
+
+/// The number of shares in the set `shares`
+#[no_mangle]
+pub extern "C" fn ursa_sharing_shares_len(shares: u64, err: &mut ExternError) -> u32 {
+    SHARES.call_with_output(err, shares, |shares| shares.0.len() as u32)
+}
+
+/// Copy the share at `index` in the set `shares` out to store or send it
+#[no_mangle]
+pub extern "C" fn ursa_sharing_shares_get(
+    shares: u64,
+    index: u32,
+    err: &mut ExternError,
+) -> ByteBuffer {
+    SHARES.call_with_result(err, shares, |shares| {
+        shares
+            .0
+            .get(index as usize)
+            .map(|s| ByteBuffer::from_vec(s.clone()))
+            .ok_or_else(|| {
+                ExternError::new_error(
+                    ErrorCode::new(error_codes::INDEX_OUT_OF_RANGE),
+                    format!("Index {} is out of range", index),
+                )
+            })
+    })
+}
+
+/// The identifier of an encoded share from [`ursa_sharing_split`].
+/// Returns zero on error, identifiers start at one.
+#[no_mangle]
+pub extern "C" fn ursa_sharing_share_identifier(share: &ByteArray, err: &mut ExternError) -> u32 {
+    call_with_result(err, || -> Result<_, ExternError> {
+        Ok(Share::try_from(share.as_slice()?)?.identifier())
+    })
+}
+
+fn split<S: Field>(secret: &[u8], threshold: usize, limit: usize) -> Result<Shares, ExternError> {
+    let mut secret = S::from_bytes(secret)?;
+    let shares =
+        Scheme::new(threshold, limit).and_then(|s| s.split_secret(&mut SystemRng, &secret));
+    secret.zeroize();
+    Ok(Shares(shares?.iter().map(|s| s.to_bytes()).collect()))
+}
+
+fn combine<S: Field>(threshold: usize, shares: &[Vec<u8>]) -> Result<ByteBuffer, ExternError> {
+    let shares = shares
+        .iter()
+        .map(|s| Share::try_from(s.as_slice()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut secret = Scheme::new(threshold, threshold)?.combine_shares::<S, S>(&shares)?;
+    let bytes = secret.to_bytes().to_vec();
+    secret.zeroize();
+    Ok(ByteBuffer::from_vec(bytes))
+}
+
+fn curve_name<'a>(curve: &FfiStr<'a>) -> Result<&'a str, ExternError> {
+    curve
+        .as_opt_str()
+        .ok_or_else(|| invalid_param("The curve name is null or not UTF-8"))
+}
+
+fn invalid_param(message: &str) -> ExternError {
+    ExternError::new_error(ErrorCode::new(error_codes::INVALID_PARAM), message)
+}
+
+fn unsupported(curve: &str) -> ExternError {
+    ExternError::new_error(
+        ErrorCode::new(error_codes::UNSUPPORTED_CURVE),
+        format!("Curve {} is not supported", curve),
+    )
+}
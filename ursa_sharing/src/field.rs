@@ -0,0 +1,783 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Arithmetic in the scalar fields of BLS12-381 and BN254 for polynomial protocols.
+//!
+//! [`Fp`] is an element of a prime field below 2^256 kept in Montgomery form in four
+//! 64 bit limbs, with the modulus and constants given by [`FieldParams`]. Addition,
+//! multiplication and inversion don't branch on or index memory with the values.
+//! [`Bls12381Fr`] and [`Bn254Fr`] are the fields of the curves' group orders and
+//! implement [`Field`] so they work with the sharing schemes, while [`batch_invert`]
+//! and [`lagrange_coefficients`] work for any [`Field`].
+//!
+//! Both fields have large power of two roots of unity, 2^32 for BLS12-381 and 2^28 for
+//! BN254. A [`Domain`] is the subgroup of the `n`-th roots of unity for a power of two
+//! `n`, with the FFTs moving a polynomial between its coefficients and its evaluations
+//! on the domain or a coset of it, as used by PLONK and other polynomial IOPs.
+//!
+//! Elements encode as 32 big endian bytes, the same as
+//! [`Bls12381Scalar`](crate::bls12_381::Bls12381Scalar), and `from_bytes` also reduces
+//! 64 byte inputs like the output of a hash.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+use generic_array::{typenum::U32, GenericArray};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The constants of a prime field below 2^256 with 2^256 as the Montgomery radix R
+pub trait FieldParams: 'static + Copy + Send + Sync {
+    /// The modulus in little endian limbs
+    const MODULUS: [u64; 4];
+    /// -MODULUS^-1 mod 2^64
+    const INV: u64;
+    /// R mod MODULUS, one in Montgomery form
+    const R: [u64; 4];
+    /// R^2 mod MODULUS
+    const R2: [u64; 4];
+    /// R^3 mod MODULUS
+    const R3: [u64; 4];
+    /// The largest `s` where 2^s divides MODULUS - 1
+    const TWO_ADICITY: u32;
+    /// A primitive 2^TWO_ADICITY-th root of unity
+    const ROOT_OF_UNITY: [u64; 4];
+    /// A generator of the multiplicative group, which offsets cosets of a [`Domain`]
+    const GENERATOR: u64;
+}
+
+/// The parameters of [`Bls12381Fr`]
+#[derive(Copy, Clone, Debug)]
+pub struct Bls12381FrParams;
+
+impl FieldParams for Bls12381FrParams {
+    const MODULUS: [u64; 4] = [
+        0xffff_ffff_0000_0001,
+        0x53bd_a402_fffe_5bfe,
+        0x3339_d808_09a1_d805,
+        0x73ed_a753_299d_7d48,
+    ];
+    const INV: u64 = 0xffff_fffe_ffff_ffff;
+    const R: [u64; 4] = [
+        0x0000_0001_ffff_fffe,
+        0x5884_b7fa_0003_4802,
+        0x998c_4fef_ecbc_4ff5,
+        0x1824_b159_acc5_056f,
+    ];
+    const R2: [u64; 4] = [
+        0xc999_e990_f3f2_9c6d,
+        0x2b6c_edcb_8792_5c23,
+        0x05d3_1496_7254_398f,
+        0x0748_d9d9_9f59_ff11,
+    ];
+    const R3: [u64; 4] = [
+        0xc62c_1807_439b_73af,
+        0x1b3e_0d18_8cf0_6990,
+        0x73d1_3c71_c7b5_f418,
+        0x6e2a_5bb9_c8db_33e9,
+    ];
+    const TWO_ADICITY: u32 = 32;
+    // 7^((MODULUS - 1) / 2^32)
+    const ROOT_OF_UNITY: [u64; 4] = [
+        0x3829_971f_439f_0d2b,
+        0xb636_8350_8c22_80b9,
+        0xd09b_6819_22c8_13b4,
+        0x16a2_a19e_dfe8_1f20,
+    ];
+    const GENERATOR: u64 = 7;
+}
+
+/// The parameters of [`Bn254Fr`]
+#[derive(Copy, Clone, Debug)]
+pub struct Bn254FrParams;
+
+impl FieldParams for Bn254FrParams {
+    const MODULUS: [u64; 4] = [
+        0x43e1_f593_f000_0001,
+        0x2833_e848_79b9_7091,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ];
+    const INV: u64 = 0xc2e1_f593_efff_ffff;
+    const R: [u64; 4] = [
+        0xac96_341c_4fff_fffb,
+        0x36fc_7695_9f60_cd29,
+        0x666e_a36f_7879_462e,
+        0x0e0a_77c1_9a07_df2f,
+    ];
+    const R2: [u64; 4] = [
+        0x1bb8_e645_ae21_6da7,
+        0x53fe_3ab1_e35c_59e3,
+        0x8c49_833d_53bb_8085,
+        0x0216_d0b1_7f4e_44a5,
+    ];
+    const R3: [u64; 4] = [
+        0x5e94_d8e1_b4bf_0040,
+        0x2a48_9cbe_1cfb_b6b8,
+        0x893c_c664_a19f_cfed,
+        0x0cf8_594b_7fcc_657c,
+    ];
+    const TWO_ADICITY: u32 = 28;
+    // 5^((MODULUS - 1) / 2^28)
+    const ROOT_OF_UNITY: [u64; 4] = [
+        0x9bd6_1b6e_725b_19f0,
+        0x402d_111e_4111_2ed4,
+        0x00e0_a7eb_8ef6_2abc,
+        0x2a3c_09f0_a58a_7e85,
+    ];
+    const GENERATOR: u64 = 5;
+}
+
+/// The scalar field of BLS12-381
+pub type Bls12381Fr = Fp<Bls12381FrParams>;
+
+/// The scalar field of BN254, also known as alt_bn128
+pub type Bn254Fr = Fp<Bn254FrParams>;
+
+/// Compute a + b * c + carry, returning the result and the new carry
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let r = (a as u128) + (b as u128) * (c as u128) + (carry as u128);
+    (r as u64, (r >> 64) as u64)
+}
+
+/// Compute a + b + carry, returning the result and the new carry
+#[inline(always)]
+fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let r = (a as u128) + (b as u128) + (carry as u128);
+    (r as u64, (r >> 64) as u64)
+}
+
+/// Compute a - (b + borrow), returning the result and the new borrow, which is
+/// all ones if the subtraction underflowed and zero otherwise
+#[inline(always)]
+fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let r = (a as u128).wrapping_sub((b as u128) + ((borrow >> 63) as u128));
+    (r as u64, (r >> 64) as u64)
+}
+
+/// An element of the prime field given by `P`
+pub struct Fp<P: FieldParams> {
+    /// Montgomery form, a * R mod MODULUS
+    limbs: [u64; 4],
+    _params: PhantomData<P>,
+}
+
+impl<P: FieldParams> Fp<P> {
+    const fn from_montgomery(limbs: [u64; 4]) -> Self {
+        Self {
+            limbs,
+            _params: PhantomData,
+        }
+    }
+
+    /// Return the element from the given number
+    pub fn from_u64(value: u64) -> Self {
+        Self::from_montgomery([value, 0, 0, 0]) * Self::from_montgomery(P::R2)
+    }
+
+    /// Return the element from little endian limbs, `None` if they're not less than the modulus
+    pub fn from_canonical(limbs: [u64; 4]) -> Option<Self> {
+        let (_, borrow) = sbb(limbs[0], P::MODULUS[0], 0);
+        let (_, borrow) = sbb(limbs[1], P::MODULUS[1], borrow);
+        let (_, borrow) = sbb(limbs[2], P::MODULUS[2], borrow);
+        let (_, borrow) = sbb(limbs[3], P::MODULUS[3], borrow);
+        // The subtraction underflows only if the limbs are less than the modulus
+        if borrow == 0 {
+            return None;
+        }
+        Some(Self::from_montgomery(limbs) * Self::from_montgomery(P::R2))
+    }
+
+    /// The element as little endian limbs less than the modulus
+    pub fn to_canonical(&self) -> [u64; 4] {
+        let l = &self.limbs;
+        Self::montgomery_reduce(l[0], l[1], l[2], l[3], 0, 0, 0, 0).limbs
+    }
+
+    /// Return the element congruent to 64 bytes read in big endian, which is
+    /// as good as uniform when the bytes are
+    pub fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+        let mut limbs = [0u64; 8];
+        for (l, c) in limbs.iter_mut().zip(bytes.chunks(8).rev()) {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(c);
+            *l = u64::from_be_bytes(b);
+        }
+        // lo * R^2 / R + hi * R^3 / R = lo + hi * R mod MODULUS
+        let lo = Self::from_montgomery([limbs[0], limbs[1], limbs[2], limbs[3]]);
+        let hi = Self::from_montgomery([limbs[4], limbs[5], limbs[6], limbs[7]]);
+        limbs.zeroize();
+        lo * Self::from_montgomery(P::R2) + hi * Self::from_montgomery(P::R3)
+    }
+
+    /// Return a multiplicative generator of the field
+    pub fn multiplicative_generator() -> Self {
+        Self::from_u64(P::GENERATOR)
+    }
+
+    /// Return a primitive `2^log_n`-th root of unity, `None` if `log_n` exceeds the
+    /// field's two-adicity
+    pub fn root_of_unity(log_n: u32) -> Option<Self> {
+        if log_n > P::TWO_ADICITY {
+            return None;
+        }
+        let mut root = Self::from_canonical(P::ROOT_OF_UNITY)?;
+        for _ in log_n..P::TWO_ADICITY {
+            root = root.square();
+        }
+        Some(root)
+    }
+
+    /// Compute `2 * self`
+    pub fn double(&self) -> Self {
+        *self + *self
+    }
+
+    /// Compute `self^2`
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// Raise to the power of little endian limbs. The time taken depends on the exponent,
+    /// which must be public
+    pub fn pow(&self, exponent: &[u64]) -> Self {
+        let mut r = Self::from_montgomery(P::R);
+        for e in exponent.iter().rev() {
+            for i in (0..64).rev() {
+                r = r.square();
+                if (e >> i) & 1 == 1 {
+                    r *= *self;
+                }
+            }
+        }
+        r
+    }
+
+    /// Return the multiplicative inverse, `None` if this element is zero
+    pub fn inverse(&self) -> Option<Self> {
+        // Fermat's little theorem, self^(MODULUS - 2)
+        let m = P::MODULUS;
+        let (e0, borrow) = sbb(m[0], 2, 0);
+        let (e1, borrow) = sbb(m[1], 0, borrow);
+        let (e2, borrow) = sbb(m[2], 0, borrow);
+        let (e3, _) = sbb(m[3], 0, borrow);
+        let inverse = self.pow(&[e0, e1, e2, e3]);
+        if self.is_zero() {
+            None
+        } else {
+            Some(inverse)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn montgomery_reduce(
+        r0: u64,
+        r1: u64,
+        r2: u64,
+        r3: u64,
+        r4: u64,
+        r5: u64,
+        r6: u64,
+        r7: u64,
+    ) -> Self {
+        let m = &P::MODULUS;
+
+        let k = r0.wrapping_mul(P::INV);
+        let (_, carry) = mac(r0, k, m[0], 0);
+        let (r1, carry) = mac(r1, k, m[1], carry);
+        let (r2, carry) = mac(r2, k, m[2], carry);
+        let (r3, carry) = mac(r3, k, m[3], carry);
+        let (r4, carry2) = adc(r4, 0, carry);
+
+        let k = r1.wrapping_mul(P::INV);
+        let (_, carry) = mac(r1, k, m[0], 0);
+        let (r2, carry) = mac(r2, k, m[1], carry);
+        let (r3, carry) = mac(r3, k, m[2], carry);
+        let (r4, carry) = mac(r4, k, m[3], carry);
+        let (r5, carry2) = adc(r5, carry2, carry);
+
+        let k = r2.wrapping_mul(P::INV);
+        let (_, carry) = mac(r2, k, m[0], 0);
+        let (r3, carry) = mac(r3, k, m[1], carry);
+        let (r4, carry) = mac(r4, k, m[2], carry);
+        let (r5, carry) = mac(r5, k, m[3], carry);
+        let (r6, carry2) = adc(r6, carry2, carry);
+
+        let k = r3.wrapping_mul(P::INV);
+        let (_, carry) = mac(r3, k, m[0], 0);
+        let (r4, carry) = mac(r4, k, m[1], carry);
+        let (r5, carry) = mac(r5, k, m[2], carry);
+        let (r6, carry) = mac(r6, k, m[3], carry);
+        let (r7, r8) = adc(r7, carry2, carry);
+
+        // The result is less than 2 * MODULUS
+        Self::subtract_modulus([r4, r5, r6, r7], r8)
+    }
+
+    /// Subtract the modulus if `limbs` plus `carry * 2^256` is at least the modulus
+    #[inline(always)]
+    fn subtract_modulus(limbs: [u64; 4], carry: u64) -> Self {
+        let m = &P::MODULUS;
+        let (d0, borrow) = sbb(limbs[0], m[0], 0);
+        let (d1, borrow) = sbb(limbs[1], m[1], borrow);
+        let (d2, borrow) = sbb(limbs[2], m[2], borrow);
+        let (d3, borrow) = sbb(limbs[3], m[3], borrow);
+        let (_, borrow) = sbb(carry, 0, borrow);
+        // Add the modulus back if the subtraction underflowed
+        let (d0, carry) = adc(d0, m[0] & borrow, 0);
+        let (d1, carry) = adc(d1, m[1] & borrow, carry);
+        let (d2, carry) = adc(d2, m[2] & borrow, carry);
+        let (d3, _) = adc(d3, m[3] & borrow, carry);
+        Self::from_montgomery([d0, d1, d2, d3])
+    }
+}
+
+impl<P: FieldParams> Copy for Fp<P> {}
+
+impl<P: FieldParams> Clone for Fp<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: FieldParams> Default for Fp<P> {
+    fn default() -> Self {
+        Self::from_montgomery([0; 4])
+    }
+}
+
+impl<P: FieldParams> Debug for Fp<P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Fp(0x")?;
+        for l in self.to_canonical().iter().rev() {
+            write!(f, "{:016x}", l)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<P: FieldParams> PartialEq for Fp<P> {
+    fn eq(&self, other: &Self) -> bool {
+        let d = self
+            .limbs
+            .iter()
+            .zip(other.limbs.iter())
+            .fold(0u64, |d, (a, b)| d | (a ^ b));
+        d == 0
+    }
+}
+
+impl<P: FieldParams> Eq for Fp<P> {}
+
+impl<P: FieldParams> Zeroize for Fp<P> {
+    fn zeroize(&mut self) {
+        self.limbs.zeroize();
+    }
+}
+
+impl<P: FieldParams> Add for Fp<P> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (a, b) = (&self.limbs, &rhs.limbs);
+        let (d0, carry) = adc(a[0], b[0], 0);
+        let (d1, carry) = adc(a[1], b[1], carry);
+        let (d2, carry) = adc(a[2], b[2], carry);
+        let (d3, carry) = adc(a[3], b[3], carry);
+        Self::subtract_modulus([d0, d1, d2, d3], carry)
+    }
+}
+
+impl<P: FieldParams> Sub for Fp<P> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let (a, b, m) = (&self.limbs, &rhs.limbs, &P::MODULUS);
+        let (d0, borrow) = sbb(a[0], b[0], 0);
+        let (d1, borrow) = sbb(a[1], b[1], borrow);
+        let (d2, borrow) = sbb(a[2], b[2], borrow);
+        let (d3, borrow) = sbb(a[3], b[3], borrow);
+        let (d0, carry) = adc(d0, m[0] & borrow, 0);
+        let (d1, carry) = adc(d1, m[1] & borrow, carry);
+        let (d2, carry) = adc(d2, m[2] & borrow, carry);
+        let (d3, _) = adc(d3, m[3] & borrow, carry);
+        Self::from_montgomery([d0, d1, d2, d3])
+    }
+}
+
+impl<P: FieldParams> Neg for Fp<P> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::default() - self
+    }
+}
+
+impl<P: FieldParams> Mul for Fp<P> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (&self.limbs, &rhs.limbs);
+
+        let (r0, carry) = mac(0, a[0], b[0], 0);
+        let (r1, carry) = mac(0, a[0], b[1], carry);
+        let (r2, carry) = mac(0, a[0], b[2], carry);
+        let (r3, r4) = mac(0, a[0], b[3], carry);
+
+        let (r1, carry) = mac(r1, a[1], b[0], 0);
+        let (r2, carry) = mac(r2, a[1], b[1], carry);
+        let (r3, carry) = mac(r3, a[1], b[2], carry);
+        let (r4, r5) = mac(r4, a[1], b[3], carry);
+
+        let (r2, carry) = mac(r2, a[2], b[0], 0);
+        let (r3, carry) = mac(r3, a[2], b[1], carry);
+        let (r4, carry) = mac(r4, a[2], b[2], carry);
+        let (r5, r6) = mac(r5, a[2], b[3], carry);
+
+        let (r3, carry) = mac(r3, a[3], b[0], 0);
+        let (r4, carry) = mac(r4, a[3], b[1], carry);
+        let (r5, carry) = mac(r5, a[3], b[2], carry);
+        let (r6, r7) = mac(r6, a[3], b[3], carry);
+
+        Self::montgomery_reduce(r0, r1, r2, r3, r4, r5, r6, r7)
+    }
+}
+
+impl<P: FieldParams> AddAssign for Fp<P> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<P: FieldParams> SubAssign for Fp<P> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<P: FieldParams> MulAssign for Fp<P> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<P: FieldParams> Field for Fp<P> {
+    fn one() -> Self {
+        Self::from_montgomery(P::R)
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self::from_u64(value as u64)
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = rhs.inverse().ok_or(SharingError::DivisionByZero)?;
+        *self *= inverse;
+        Ok(())
+    }
+}
+
+impl<P: FieldParams> Group for Fp<P> {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        match value.len() {
+            64 => {
+                let mut wide = [0u8; 64];
+                wide.copy_from_slice(value);
+                let r = Self::from_bytes_wide(&wide);
+                wide.zeroize();
+                Ok(r)
+            }
+            l if l <= 32 => {
+                // Big endian so shorter values are left padded
+                let mut s = [0u8; 32];
+                s[32 - l..].copy_from_slice(value);
+                let mut limbs = [0u64; 4];
+                for (l, c) in limbs.iter_mut().zip(s.chunks(8).rev()) {
+                    let mut b = [0u8; 8];
+                    b.copy_from_slice(c);
+                    *l = u64::from_be_bytes(b);
+                }
+                let r = Self::from_canonical(limbs).ok_or(SharingError::ShareInvalidSecret);
+                s.zeroize();
+                limbs.zeroize();
+                r
+            }
+            _ => Err(SharingError::ShareInvalidSecret),
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut wide = [0u8; 64];
+        rng.fill_bytes(&mut wide);
+        let r = Self::from_bytes_wide(&wide);
+        wide.zeroize();
+        r
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.iter().fold(0u64, |d, l| d | l) == 0
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        *self = -*self;
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        *self += *rhs;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        *self -= *rhs;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        *self *= *rhs;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        let mut r = GenericArray::default();
+        for (c, l) in r.chunks_mut(8).zip(self.to_canonical().iter().rev()) {
+            c.copy_from_slice(&l.to_be_bytes());
+        }
+        r
+    }
+
+    fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Invert every element with one inversion and three multiplications per element.
+/// Fails with `SharingError::DivisionByZero` and leaves the elements unchanged if any is zero
+pub fn batch_invert<F: Field>(elements: &mut [F]) -> SharingResult<()> {
+    // products[i] is the product of the elements before i
+    let mut products = Vec::with_capacity(elements.len());
+    let mut acc = F::one();
+    for e in elements.iter() {
+        products.push(copy(&acc));
+        acc.scalar_mul_assign(e);
+    }
+    let mut inverse = acc.invert()?;
+    for (e, p) in elements.iter_mut().zip(products.iter_mut()).rev() {
+        // inverse is the inverse of the product up to and including e
+        let mut next = copy(&inverse);
+        next.scalar_mul_assign(e);
+        p.scalar_mul_assign(&inverse);
+        core::mem::swap(e, p);
+        inverse = next;
+    }
+    products.iter_mut().for_each(|p| p.clear());
+    acc.clear();
+    inverse.clear();
+    Ok(())
+}
+
+/// The Lagrange coefficients at zero of the points with `x_coordinates`, the weight of
+/// each point's value in the value at zero of the polynomial through them, with a single
+/// inversion. Fails with `SharingError::DivisionByZero` if two coordinates are the same
+pub fn lagrange_coefficients<F: Field>(x_coordinates: &[F]) -> SharingResult<Vec<F>> {
+    // The coefficient of x_i is the product of x_j / (x_j - x_i) over j != i
+    let mut denominators = Vec::with_capacity(x_coordinates.len());
+    for (i, x_i) in x_coordinates.iter().enumerate() {
+        let mut denom = F::one();
+        for (j, x_j) in x_coordinates.iter().enumerate() {
+            if i != j {
+                let mut d = copy(x_j);
+                d.sub_assign(x_i);
+                denom.scalar_mul_assign(&d);
+            }
+        }
+        denominators.push(denom);
+    }
+    batch_invert(&mut denominators)?;
+
+    // The numerators from the products of the coordinates before and after each one
+    let mut suffix = F::one();
+    for (d, x) in denominators.iter_mut().zip(x_coordinates.iter()).rev() {
+        d.scalar_mul_assign(&suffix);
+        suffix.scalar_mul_assign(x);
+    }
+    let mut prefix = F::one();
+    for (d, x) in denominators.iter_mut().zip(x_coordinates.iter()) {
+        d.scalar_mul_assign(&prefix);
+        prefix.scalar_mul_assign(x);
+    }
+    suffix.clear();
+    prefix.clear();
+    Ok(denominators)
+}
+
+fn copy<F: Field>(value: &F) -> F {
+    let mut c = F::zero();
+    c.add_assign(value);
+    c
+}
+
+/// The multiplicative subgroup of the `n`-th roots of unity for a power of two `n`
+#[derive(Clone, Debug)]
+pub struct Domain<P: FieldParams> {
+    log_size: u32,
+    generator: Fp<P>,
+    size_inverse: Fp<P>,
+    /// The first `n / 2` powers of the generator
+    twiddles: Vec<Fp<P>>,
+}
+
+impl<P: FieldParams> Domain<P> {
+    /// Create the domain of `size` elements, which must be a power of two no larger
+    /// than 2^TWO_ADICITY
+    pub fn new(size: usize) -> SharingResult<Self> {
+        if !size.is_power_of_two() {
+            return Err(SharingError::FieldDomainSize(size));
+        }
+        let log_size = size.trailing_zeros();
+        let generator = Fp::root_of_unity(log_size).ok_or(SharingError::FieldDomainSize(size))?;
+        let size_inverse = Fp::from_u64(size as u64)
+            .inverse()
+            .ok_or(SharingError::FieldDomainSize(size))?;
+        let mut twiddles = Vec::with_capacity(size / 2);
+        let mut w = Fp::one();
+        for _ in 0..size / 2 {
+            twiddles.push(w);
+            w *= generator;
+        }
+        Ok(Self {
+            log_size,
+            generator,
+            size_inverse,
+            twiddles,
+        })
+    }
+
+    /// The number of elements
+    pub fn size(&self) -> usize {
+        1 << self.log_size
+    }
+
+    /// The primitive root of unity generating the domain
+    pub fn generator(&self) -> Fp<P> {
+        self.generator
+    }
+
+    /// The elements in the order the FFT evaluates at, the powers of the generator
+    pub fn elements(&self) -> Vec<Fp<P>> {
+        let mut elements = Vec::with_capacity(self.size());
+        let mut w = Fp::one();
+        for _ in 0..self.size() {
+            elements.push(w);
+            w *= self.generator;
+        }
+        elements
+    }
+
+    /// Evaluate the polynomial vanishing on the domain, `x^n - 1`
+    pub fn evaluate_vanishing(&self, x: &Fp<P>) -> Fp<P> {
+        let mut r = *x;
+        for _ in 0..self.log_size {
+            r = r.square();
+        }
+        r - Fp::one()
+    }
+
+    /// Replace the coefficients of a polynomial of degree less than `n` with its
+    /// evaluations at the elements of the domain. Fails unless there are `n` values
+    pub fn fft(&self, values: &mut [Fp<P>]) -> SharingResult<()> {
+        if values.len() != self.size() {
+            return Err(SharingError::FieldDomainSize(values.len()));
+        }
+        let n = values.len();
+        if n == 1 {
+            return Ok(());
+        }
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - self.log_size);
+            if i < j {
+                values.swap(i, j);
+            }
+        }
+        let mut m = 1;
+        while m < n {
+            // The twiddles of this stage are every (n / 2m)-th power
+            let step = n / (2 * m);
+            for k in (0..n).step_by(2 * m) {
+                for j in 0..m {
+                    let t = values[k + j + m] * self.twiddles[j * step];
+                    let u = values[k + j];
+                    values[k + j] = u + t;
+                    values[k + j + m] = u - t;
+                }
+            }
+            m *= 2;
+        }
+        Ok(())
+    }
+
+    /// Replace the evaluations at the elements of the domain with the coefficients of
+    /// the polynomial of degree less than `n`. Fails unless there are `n` values
+    pub fn ifft(&self, values: &mut [Fp<P>]) -> SharingResult<()> {
+        // Evaluating at the inverse roots reverses all but the first output
+        self.fft(values)?;
+        values[1..].reverse();
+        for v in values.iter_mut() {
+            *v *= self.size_inverse;
+        }
+        Ok(())
+    }
+
+    /// Like `fft` but evaluating at the coset `g * w^i` for the multiplicative generator `g`,
+    /// which doesn't intersect the domain
+    pub fn coset_fft(&self, values: &mut [Fp<P>]) -> SharingResult<()> {
+        if values.len() != self.size() {
+            return Err(SharingError::FieldDomainSize(values.len()));
+        }
+        Self::scale(values, Fp::multiplicative_generator());
+        self.fft(values)
+    }
+
+    /// The inverse of `coset_fft`
+    pub fn coset_ifft(&self, values: &mut [Fp<P>]) -> SharingResult<()> {
+        self.ifft(values)?;
+        // The generator is not zero
+        Self::scale(values, Fp::multiplicative_generator().inverse().unwrap());
+        Ok(())
+    }
+
+    /// Multiply the i-th value by `g^i`
+    fn scale(values: &mut [Fp<P>], g: Fp<P>) {
+        let mut s = Fp::one();
+        for v in values.iter_mut() {
+            *v *= s;
+            s *= g;
+        }
+    }
+}
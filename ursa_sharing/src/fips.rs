@@ -0,0 +1,135 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! The allow-list of algorithms for FIPS mode.
+//!
+//! The `fips` feature restricts the crate to the algorithms approved by FIPS 186-5,
+//! SP 800-56A and FIPS 180-4: the secp256r1 (P-256) and Ed25519 backends and
+//! SHA-256, SHA-512 and HKDF. It's enforced twice:
+//!
+//! * At compile time, enabling `fips` with a feature that needs another algorithm is
+//!   an error. These are `bls12_381`, `ristretto` and `secp256k1` for their curves,
+//!   `backup`, `checkpoint`, `recipient` and `wrap` for ChaCha20-Poly1305, Argon2 and X25519,
+//!   and `threshold_ecdsa` for Paillier, along with the features enabling them.
+//! * At runtime, algorithms chosen by data such as the curve of a parsed
+//!   `tagged::TaggedShare` are checked with [`check`], which fails with
+//!   [`SharingError::AlgorithmNotApproved`]. External code can call it for its own choices.
+//!
+//! The secret sharing schemes and proofs aren't FIPS algorithms and are generic over
+//! the backend, so they're allowed over the approved curves. Fields and groups
+//! implemented outside this crate can't be vetted and are rejected wherever the crate
+//! learns of them. FIPS mode keeps unapproved algorithms out, it doesn't make the
+//! build a validated module.
+
+use crate::error::{SharingError, SharingResult};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+/// True when the crate is built with the `fips` feature
+pub const ENABLED: bool = cfg!(feature = "fips");
+
+/// The algorithms used by the crate, approved or not
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// EdDSA and scalars over Curve25519 in Edwards form, approved by FIPS 186-5
+    Ed25519,
+    /// The secp256r1 (P-256) curve, approved by FIPS 186-5
+    Secp256r1,
+    /// SHA-256 from FIPS 180-4
+    Sha256,
+    /// SHA-512 from FIPS 180-4
+    Sha512,
+    /// HKDF with SHA-256 or SHA-512 from SP 800-56C
+    Hkdf,
+    /// The BLS12-381 pairing friendly curve
+    Bls12381,
+    /// The Ristretto group over Curve25519
+    Ristretto25519,
+    /// The secp256k1 curve, including Schnorr signatures over it
+    Secp256k1,
+    /// The ChaCha20-Poly1305 AEAD
+    ChaCha20Poly1305,
+    /// The Argon2id password hash
+    Argon2id,
+    /// X25519 key agreement
+    X25519,
+    /// Paillier's additively homomorphic encryption
+    Paillier,
+    /// A field or group implemented outside this crate
+    Custom,
+}
+
+impl Algorithm {
+    /// The algorithms allowed in FIPS mode
+    pub const APPROVED: [Algorithm; 5] = [
+        Algorithm::Ed25519,
+        Algorithm::Secp256r1,
+        Algorithm::Sha256,
+        Algorithm::Sha512,
+        Algorithm::Hkdf,
+    ];
+
+    /// True if the algorithm is on the allow-list
+    pub fn is_approved(self) -> bool {
+        Self::APPROVED.contains(&self)
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match self {
+            Algorithm::Ed25519 => "Ed25519",
+            Algorithm::Secp256r1 => "secp256r1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Hkdf => "HKDF",
+            Algorithm::Bls12381 => "BLS12-381",
+            Algorithm::Ristretto25519 => "Ristretto25519",
+            Algorithm::Secp256k1 => "secp256k1",
+            Algorithm::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+            Algorithm::Argon2id => "Argon2id",
+            Algorithm::X25519 => "X25519",
+            Algorithm::Paillier => "Paillier",
+            Algorithm::Custom => "A custom field or group",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Fail if FIPS mode is enabled and `algorithm` isn't approved
+pub fn check(algorithm: Algorithm) -> SharingResult<()> {
+    if ENABLED && !algorithm.is_approved() {
+        Err(SharingError::AlgorithmNotApproved(algorithm))
+    } else {
+        Ok(())
+    }
+}
+
+macro_rules! reject_features {
+    ($($feature:literal),+) => {
+        $(
+            #[cfg(all(feature = "fips", feature = $feature, not(doc)))]
+            compile_error!(concat!("Cannot compile both features 'fips' and '", $feature, "'"));
+        )+
+    };
+}
+
+reject_features!(
+    "backup",
+    "bls12_381",
+    "checkpoint",
+    "recipient",
+    "ristretto",
+    "secp256k1",
+    "threshold_ecdsa",
+    "wrap"
+);
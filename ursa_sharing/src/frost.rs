@@ -0,0 +1,719 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! FROST threshold Schnorr signatures over Ed25519 and Ristretto255.
+//!
+//! The signers hold Feldman shares of the secret key, e.g. from `dkg` or
+//! `feldman::Scheme`, committed with the standard base point. A quorum of them
+//! jointly produces a Schnorr signature `(R, z)` with `g^z = R * Y^c` under the
+//! public key `Y` without reconstructing the key. Over Ed25519 the challenge is the
+//! one of RFC 8032 so the result is a standard Ed25519 signature.
+//!
+//! Signing takes two rounds, following Komlo and Goldberg's FROST as in RFC 9591:
+//!
+//! 1. [`SignerRound1::new`] picks two nonces and returns the [`SigningCommitments`]
+//!    to them. This doesn't depend on the message or the other signers so it can
+//!    be done ahead of time.
+//! 2. A [`Coordinator`], which may be one of the signers, chooses the message and
+//!    the commitments of at least `threshold` signers and sends them to each of them.
+//!    [`SignerRound1::sign`] returns the signer's [`SignatureShare`].
+//!
+//! [`Coordinator::aggregate`] checks every share against the signer's verification
+//! share from the Feldman commitments and combines them into a [`Signature`].
+//! An invalid or missing share aborts with `FrostAbort` naming the signer.
+//! Each signer binds its nonces to the message and the whole set of commitments, so
+//! commitments chosen after seeing the others can't bias the nonce point.
+//!
+//! A set of nonces must never sign twice, [`SignerRound1::sign`] consumes them for
+//! that reason. The signers must check that the message is one they intend to sign,
+//! the coordinator is trusted only for liveness.
+
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{put, Checkpoint};
+#[cfg(feature = "record")]
+use crate::record::Recordable;
+use crate::{
+    dkg::Reader,
+    error::{SharingError, SharingResult},
+    evaluate_commitments,
+    feldman::FeldmanVerifier,
+    shamir::Share,
+    Field, Group, ShareVerifier, Strictness,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+const NONCE_RANDOM_BYTES: usize = 32;
+
+/// A prime order group with a FROST ciphersuite.
+/// The scalars must accept 64 byte inputs reduced modulo the group order
+pub trait FrostGroup<S: Field>: Group<S> {
+    /// The context string prefixed to the ciphersuite's hashes
+    const CONTEXT: &'static [u8];
+    /// The standard base point
+    fn generator() -> Self;
+    /// The challenge of the signature with nonce point `R` under `public_key`
+    fn challenge(nonce_point: &Self, public_key: &Self, message: &[u8]) -> S;
+}
+
+#[cfg(feature = "ed25519")]
+impl FrostGroup<crate::ed25519::Ed25519Scalar> for crate::ed25519::Ed25519Point {
+    const CONTEXT: &'static [u8] = b"FROST-ED25519-SHA512-v1";
+
+    fn generator() -> Self {
+        Self(curve25519_dalek::constants::ED25519_BASEPOINT_POINT)
+    }
+
+    /// `SHA-512(R || A || M)` like RFC 8032
+    fn challenge(
+        nonce_point: &Self,
+        public_key: &Self,
+        message: &[u8],
+    ) -> crate::ed25519::Ed25519Scalar {
+        hash_to_scalar(
+            &[],
+            &[&nonce_point.to_bytes(), &public_key.to_bytes(), message],
+        )
+    }
+}
+
+#[cfg(feature = "ristretto")]
+impl FrostGroup<crate::ristretto::Ristretto25519Scalar> for crate::ristretto::Ristretto25519Point {
+    const CONTEXT: &'static [u8] = b"FROST-RISTRETTO255-SHA512-v1";
+
+    fn generator() -> Self {
+        crate::ristretto::Ristretto25519Point::generator()
+    }
+
+    fn challenge(
+        nonce_point: &Self,
+        public_key: &Self,
+        message: &[u8],
+    ) -> crate::ristretto::Ristretto25519Scalar {
+        hash_to_scalar(
+            &[Self::CONTEXT, b"chal"],
+            &[&nonce_point.to_bytes(), &public_key.to_bytes(), message],
+        )
+    }
+}
+
+/// The first round broadcast: commitments to the sender's hiding and binding nonces
+#[derive(Clone, Debug)]
+pub struct SigningCommitments<S: Field, R: FrostGroup<S>> {
+    sender: u32,
+    hiding: R,
+    binding: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: FrostGroup<S>> SigningCommitments<S, R> {
+    /// The signer that sent these commitments
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | hiding | binding`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.hiding.to_bytes());
+        o.extend_from_slice(&self.binding.to_bytes());
+        o
+    }
+
+    /// Deserialize commitments using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        let (sender, data) = read_sender(data, 2 * r_size)?;
+        if sender == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(Self {
+            sender,
+            hiding: R::from_bytes_with(&data[..r_size], strictness)?,
+            binding: R::from_bytes_with(&data[r_size..], strictness)?,
+            _scalar: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: FrostGroup<S>> TryFrom<&[u8]> for SigningCommitments<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S: Field, R: FrostGroup<S>> Recordable for SigningCommitments<S, R> {
+    const KIND: &'static str = "frost signing commitments";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// A signer's share of the signature
+#[derive(Clone, Debug)]
+pub struct SignatureShare<S: Field> {
+    sender: u32,
+    value: S,
+}
+
+impl<S: Field> SignatureShare<S> {
+    /// The signer that created this share
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | value`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.value.to_bytes());
+        o
+    }
+
+    /// Deserialize a signature share using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let (sender, data) = read_sender(data, S::Size::to_usize())?;
+        Ok(Self {
+            sender,
+            value: S::from_bytes_with(data, strictness)?,
+        })
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for SignatureShare<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S: Field> Recordable for SignatureShare<S> {
+    const KIND: &'static str = "frost signature share";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// A Schnorr signature `(R, z)`. Over Ed25519 its bytes are a standard Ed25519 signature
+#[derive(Clone, Debug)]
+pub struct Signature<S: Field, R: FrostGroup<S>> {
+    nonce_point: R,
+    z: S,
+}
+
+impl<S: Field, R: FrostGroup<S>> Signature<S, R> {
+    /// The nonce point
+    pub fn nonce_point(&self) -> &R {
+        &self.nonce_point
+    }
+
+    /// The signature proof
+    pub fn z(&self) -> &S {
+        &self.z
+    }
+
+    /// Check the signature of `message`
+    pub fn verify(&self, public_key: &R, message: &[u8]) -> SharingResult<()> {
+        if public_key.is_zero() || !self.nonce_point.is_torsion_free() {
+            return Err(SharingError::FrostSignatureInvalid);
+        }
+        // g^z = R * Y^c
+        let c = R::challenge(&self.nonce_point, public_key, message);
+        let mut expected = mul(public_key, &c);
+        expected.add_assign(&self.nonce_point);
+        if !equal(&mul(&R::generator(), &self.z), &expected) {
+            return Err(SharingError::FrostSignatureInvalid);
+        }
+        Ok(())
+    }
+
+    /// Serialize as `R | z`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.nonce_point.to_bytes().to_vec();
+        o.extend_from_slice(&self.z.to_bytes());
+        o
+    }
+
+    /// Deserialize a signature using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        if data.len() != r_size + S::Size::to_usize() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            nonce_point: R::from_bytes_with(&data[..r_size], strictness)?,
+            z: S::from_bytes_with(&data[r_size..], strictness)?,
+        })
+    }
+}
+
+impl<S: Field, R: FrostGroup<S>> TryFrom<&[u8]> for Signature<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The hiding and binding nonces of a signer, cleared on drop
+struct Nonces<S: Field> {
+    hiding: S,
+    binding: S,
+}
+
+impl<S: Field> Debug for Nonces<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Nonces(..)")
+    }
+}
+
+impl<S: Field> Drop for Nonces<S> {
+    fn drop(&mut self) {
+        self.hiding.zeroize();
+        self.binding.zeroize();
+    }
+}
+
+/// A signer holding nonces committed to in [`SigningCommitments`]
+#[derive(Debug)]
+pub struct SignerRound1<S: Field, R: FrostGroup<S>> {
+    share: Share,
+    verifier: FeldmanVerifier<S, R>,
+    nonces: Nonces<S>,
+    commitments: SigningCommitments<S, R>,
+}
+
+impl<S: Field, R: FrostGroup<S>> SignerRound1<S, R> {
+    /// Start signing with `share` of the secret key committed to by `verifier`.
+    /// Returns the commitments to send to the coordinator
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+        verifier: &FeldmanVerifier<S, R>,
+    ) -> SharingResult<(Self, SigningCommitments<S, R>)> {
+        check_generator(verifier)?;
+        if share.identifier() == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let value = S::from_bytes(share.value())?;
+        if !value.is_valid() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        verifier.verify_share(share)?;
+        let nonces = Nonces {
+            hiding: generate_nonce::<S, R>(rng, &value),
+            binding: generate_nonce::<S, R>(rng, &value),
+        };
+        let commitments = SigningCommitments {
+            sender: share.identifier(),
+            hiding: mul(&R::generator(), &nonces.hiding),
+            binding: mul(&R::generator(), &nonces.binding),
+            _scalar: PhantomData,
+        };
+        let own = SigningCommitments {
+            sender: commitments.sender,
+            hiding: copy(&commitments.hiding),
+            binding: copy(&commitments.binding),
+            _scalar: PhantomData,
+        };
+        Ok((
+            Self {
+                share: share.clone(),
+                verifier: copy_verifier(verifier),
+                nonces,
+                commitments: own,
+            },
+            commitments,
+        ))
+    }
+
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.share.identifier()
+    }
+
+    /// Sign `message` with the nonces committed to, given the commitments the
+    /// coordinator chose which must include this signer's own.
+    /// Returns the [`SignatureShare`] to send to the coordinator
+    pub fn sign(
+        self,
+        message: &[u8],
+        commitments: &[SigningCommitments<S, R>],
+    ) -> SharingResult<SignatureShare<S>> {
+        let package = Package::new(&self.verifier, message, commitments)?;
+        let id = self.id();
+        match package.commitments.get(&id) {
+            Some((hiding, binding))
+                if equal(hiding, &self.commitments.hiding)
+                    && equal(binding, &self.commitments.binding) => {}
+            _ => return Err(SharingError::FrostInvalidSigner),
+        }
+        // z_i = d_i + e_i * rho_i + lambda_i * s_i * c
+        let lambda = lagrange::<S>(id, &package.signers())?;
+        let mut key = S::from_bytes(self.share.value())?;
+        key.scalar_mul_assign(&lambda);
+        key.scalar_mul_assign(&package.challenge);
+        let mut value = copy(&self.nonces.binding);
+        value.scalar_mul_assign(&package.binding_factors[&id]);
+        value.add_assign(&self.nonces.hiding);
+        value.add_assign(&key);
+        Ok(SignatureShare { sender: id, value })
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl<S: Field, R: FrostGroup<S>> Checkpoint for SignerRound1<S, R> {
+    const KIND: &'static [u8] = b"frost signer round 1";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        let mut share = self.share.to_bytes();
+        put(&mut o, &share);
+        share.zeroize();
+        put(&mut o, &self.verifier.to_bytes());
+        for nonce in &[&self.nonces.hiding, &self.nonces.binding] {
+            let mut bytes = nonce.to_bytes();
+            o.extend_from_slice(&bytes);
+            bytes.zeroize();
+        }
+        o.extend_from_slice(&self.commitments.to_bytes());
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        let mut reader = Reader { data };
+        let share = Share::try_from(reader.field()?)?;
+        let verifier = FeldmanVerifier::try_from(reader.field()?)?;
+        let nonces = Nonces {
+            hiding: S::from_bytes(reader.take(s_size)?)?,
+            binding: S::from_bytes(reader.take(s_size)?)?,
+        };
+        let commitments = SigningCommitments::try_from(reader.data)?;
+        check_generator(&verifier)?;
+        verifier.verify_share(&share)?;
+        if commitments.sender != share.identifier()
+            || !equal(&mul(&R::generator(), &nonces.hiding), &commitments.hiding)
+            || !equal(&mul(&R::generator(), &nonces.binding), &commitments.binding)
+        {
+            return Err(SharingError::CheckpointInvalid);
+        }
+        Ok(Self {
+            share,
+            verifier,
+            nonces,
+            commitments,
+        })
+    }
+}
+
+/// Chooses the signers and the message, then checks and combines their shares.
+/// Holds no secrets
+#[derive(Debug)]
+pub struct Coordinator<S: Field, R: FrostGroup<S>> {
+    verifier: FeldmanVerifier<S, R>,
+    package: Package<S, R>,
+}
+
+impl<S: Field, R: FrostGroup<S>> Coordinator<S, R> {
+    /// Sign `message` with the signers that sent `commitments`, at least the threshold
+    /// of the key committed to by `verifier`. Send the message and the same commitments
+    /// to each of them
+    pub fn new(
+        verifier: &FeldmanVerifier<S, R>,
+        message: &[u8],
+        commitments: &[SigningCommitments<S, R>],
+    ) -> SharingResult<Self> {
+        Ok(Self {
+            package: Package::new(verifier, message, commitments)?,
+            verifier: copy_verifier(verifier),
+        })
+    }
+
+    /// The identifiers of the signers
+    pub fn signers(&self) -> Vec<u32> {
+        self.package.signers()
+    }
+
+    /// Check one signer's share against its verification share
+    pub fn verify_share(&self, share: &SignatureShare<S>) -> SharingResult<()> {
+        let id = share.sender;
+        let (hiding, binding) = self
+            .package
+            .commitments
+            .get(&id)
+            .ok_or(SharingError::FrostAbort(id))?;
+        // g^z_i = D_i * E_i^rho_i * Y_i^(lambda_i * c)
+        let mut e = lagrange::<S>(id, &self.package.signers())?;
+        e.scalar_mul_assign(&self.package.challenge);
+        let mut expected = mul(&evaluate_commitments(&self.verifier.commitments, id), &e);
+        expected.add_assign(&mul(binding, &self.package.binding_factors[&id]));
+        expected.add_assign(hiding);
+        if !equal(&mul(&R::generator(), &share.value), &expected) {
+            return Err(SharingError::FrostAbort(id));
+        }
+        Ok(())
+    }
+
+    /// Check the signers' shares and combine them into a signature
+    pub fn aggregate(&self, shares: &[SignatureShare<S>]) -> SharingResult<Signature<S, R>> {
+        let mut collected = BTreeMap::new();
+        for s in shares {
+            if !self.package.commitments.contains_key(&s.sender) {
+                return Err(SharingError::FrostInvalidSigner);
+            }
+            collected.entry(s.sender).or_insert(s);
+        }
+        let mut z = S::zero();
+        for id in self.package.commitments.keys() {
+            let share = collected.get(id).ok_or(SharingError::FrostAbort(*id))?;
+            self.verify_share(share)?;
+            z.add_assign(&share.value);
+        }
+        let signature = Signature {
+            nonce_point: copy(&self.package.nonce_point),
+            z,
+        };
+        signature.verify(&self.package.public_key, &self.package.message)?;
+        Ok(signature)
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl<S: Field, R: FrostGroup<S>> Checkpoint for Coordinator<S, R> {
+    const KIND: &'static [u8] = b"frost coordinator";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put(&mut o, &self.verifier.to_bytes());
+        put(&mut o, &self.package.message);
+        o.extend_from_slice(&(self.package.commitments.len() as u32).to_be_bytes());
+        for (id, (hiding, binding)) in &self.package.commitments {
+            o.extend_from_slice(&id.to_be_bytes());
+            o.extend_from_slice(&hiding.to_bytes());
+            o.extend_from_slice(&binding.to_bytes());
+        }
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let size = 4 + 2 * R::Size::to_usize();
+        let mut reader = Reader { data };
+        let verifier = FeldmanVerifier::try_from(reader.field()?)?;
+        let message = reader.field()?;
+        let commitments = (0..reader.u32()?)
+            .map(|_| SigningCommitments::try_from(reader.take(size)?))
+            .collect::<SharingResult<Vec<_>>>()?;
+        reader.finish()?;
+        Self::new(&verifier, message, &commitments)
+    }
+}
+
+/// The values every participant derives from the message and the commitments
+#[derive(Debug)]
+struct Package<S: Field, R: FrostGroup<S>> {
+    public_key: R,
+    message: Vec<u8>,
+    /// The hiding and binding commitments by signer
+    commitments: BTreeMap<u32, (R, R)>,
+    binding_factors: BTreeMap<u32, S>,
+    nonce_point: R,
+    challenge: S,
+}
+
+impl<S: Field, R: FrostGroup<S>> Package<S, R> {
+    fn new(
+        verifier: &FeldmanVerifier<S, R>,
+        message: &[u8],
+        commitments: &[SigningCommitments<S, R>],
+    ) -> SharingResult<Self> {
+        check_generator(verifier)?;
+        let public_key = copy(&verifier.commitments[0].value);
+        let mut collected = BTreeMap::new();
+        for c in commitments {
+            if c.sender == 0 || collected.contains_key(&c.sender) {
+                return Err(SharingError::FrostInvalidSigner);
+            }
+            for point in &[&c.hiding, &c.binding] {
+                if !point.is_valid() || !point.is_torsion_free() {
+                    return Err(SharingError::FrostAbort(c.sender));
+                }
+            }
+            collected.insert(c.sender, (copy(&c.hiding), copy(&c.binding)));
+        }
+        if collected.len() < verifier.commitments.len() {
+            return Err(SharingError::FrostInvalidSigner);
+        }
+
+        // rho_i = H1(Y || H4(m) || H5(commitments) || i)
+        let mut encoded = Vec::new();
+        for (id, (hiding, binding)) in &collected {
+            encoded.extend_from_slice(&identifier::<S>(*id));
+            encoded.extend_from_slice(&hiding.to_bytes());
+            encoded.extend_from_slice(&binding.to_bytes());
+        }
+        let mut prefix = public_key.to_bytes().to_vec();
+        prefix.extend_from_slice(&hash(&[R::CONTEXT, b"msg"], &[message]));
+        prefix.extend_from_slice(&hash(&[R::CONTEXT, b"com"], &[&encoded]));
+        let mut binding_factors = BTreeMap::new();
+        let mut nonce_point = R::zero();
+        for (id, (hiding, binding)) in &collected {
+            let rho: S = hash_to_scalar(&[R::CONTEXT, b"rho"], &[&prefix, &identifier::<S>(*id)]);
+            nonce_point.add_assign(hiding);
+            nonce_point.add_assign(&mul(binding, &rho));
+            binding_factors.insert(*id, rho);
+        }
+        let challenge = R::challenge(&nonce_point, &public_key, message);
+        Ok(Self {
+            public_key,
+            message: message.to_vec(),
+            commitments: collected,
+            binding_factors,
+            nonce_point,
+            challenge,
+        })
+    }
+
+    fn signers(&self) -> Vec<u32> {
+        self.commitments.keys().copied().collect()
+    }
+}
+
+/// `H3(random || secret)`, hedged against a weak random number generator
+fn generate_nonce<S: Field, R: FrostGroup<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    secret: &S,
+) -> S {
+    let mut random = [0u8; NONCE_RANDOM_BYTES];
+    rng.fill_bytes(&mut random);
+    let nonce = hash_to_scalar(&[R::CONTEXT, b"nonce"], &[&random, &secret.to_bytes()]);
+    random.zeroize();
+    nonce
+}
+
+fn hash(prefix: &[&[u8]], parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for p in prefix.iter().chain(parts.iter()) {
+        hasher.input(p);
+    }
+    let mut o = [0u8; 64];
+    o.copy_from_slice(&hasher.result());
+    o
+}
+
+fn hash_to_scalar<S: Field>(prefix: &[&[u8]], parts: &[&[u8]]) -> S {
+    S::from_bytes(&hash(prefix, parts)[..]).unwrap_or_else(|_| S::zero())
+}
+
+/// The identifier serialized as a scalar
+fn identifier<S: Field>(id: u32) -> Vec<u8> {
+    S::from_usize(id as usize).to_bytes().to_vec()
+}
+
+/// `prod(j / (j - i))` over the other signers `j`
+fn lagrange<S: Field>(id: u32, signers: &[u32]) -> SharingResult<S> {
+    let x = S::from_usize(id as usize);
+    let mut num = S::one();
+    let mut den = S::one();
+    for j in signers.iter().filter(|j| **j != id) {
+        let xj = S::from_usize(*j as usize);
+        let mut d = copy(&xj);
+        d.sub_assign(&x);
+        num.scalar_mul_assign(&xj);
+        den.scalar_mul_assign(&d);
+    }
+    num.scalar_div_assign(&den)?;
+    Ok(num)
+}
+
+fn check_generator<S: Field, R: FrostGroup<S>>(
+    verifier: &FeldmanVerifier<S, R>,
+) -> SharingResult<()> {
+    if !equal(&verifier.g, &R::generator()) {
+        return Err(SharingError::FrostInvalidGenerator);
+    }
+    if verifier.commitments.is_empty() {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    Ok(())
+}
+
+fn copy_verifier<S: Field, R: FrostGroup<S>>(
+    verifier: &FeldmanVerifier<S, R>,
+) -> FeldmanVerifier<S, R> {
+    FeldmanVerifier {
+        g: copy(&verifier.g),
+        commitments: verifier
+            .commitments
+            .iter()
+            .map(|c| ShareVerifier {
+                value: copy(&c.value),
+                phantom: PhantomData,
+            })
+            .collect(),
+    }
+}
+
+fn read_sender(data: &[u8], len: usize) -> SharingResult<(u32, &[u8])> {
+    if data.len() != 4 + len {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let mut reader = Reader { data };
+    Ok((reader.u32()?, reader.data))
+}
+
+fn equal<E: ?Sized, R: Group<E>>(a: &R, b: &R) -> bool {
+    a.to_bytes() == b.to_bytes()
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
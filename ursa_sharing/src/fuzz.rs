@@ -0,0 +1,584 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Structurally valid but mutated protocol messages for fuzzing state machines.
+//!
+//! Byte level fuzzing mostly exercises the parsers since nearly every input it produces
+//! fails to decode. The bugs that matter in a multi-party protocol are in what a
+//! participant does with messages that decode fine but arrive twice, in the wrong round,
+//! from the wrong sender or stitched together from two sessions. A [`DkgSession`] or
+//! [`FrostSession`] records an honest session as [`Message`]s, a [`Mutator`] applies
+//! [`Mutation`]s that keep every message well formed, and the session drives one state
+//! machine through the result.
+//!
+//! Rejecting a case is a correct outcome. The [`Verdict`] is a [`Verdict::Violation`]
+//! when the state machine finished with an output that contradicts what it accepted,
+//! and that is the finding. Sessions and mutations are generated from seeds so a fuzzer
+//! can turn its input into a case with [`case`] and every finding replays exactly.
+//! The seeded RNGs must only be used for testing.
+//!
+//! ```
+//! # use ursa_sharing::{fuzz::*, ristretto::{Ristretto25519Point as Point, Ristretto25519Scalar as Scalar}, Group};
+//! let session = DkgSession::<Scalar, Point>::new(1, 2, 3, &Point::generator()).unwrap();
+//! let replays = DkgSession::<Scalar, Point>::new(2, 2, 3, &Point::generator()).unwrap();
+//! let mut mutator = Mutator::new(7).with_replays(replays.messages());
+//! for _ in 0..10 {
+//!     let mut messages = session.messages().to_vec();
+//!     mutator.mutate(&mut messages, 2);
+//!     assert!(!matches!(session.run(1, &messages), Verdict::Violation(_)));
+//! }
+//! assert!(matches!(session.run(1, session.messages()), Verdict::Completed));
+//! ```
+
+use crate::{
+    dkg::{Complaint, Justification, Participant, Round1Broadcast, Round1Share},
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+#[cfg(feature = "frost")]
+use crate::{
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    frost::{Coordinator, FrostGroup, SignatureShare, SignerRound1, SigningCommitments},
+};
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::Range;
+use generic_array::typenum::Unsigned;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+/// A message of a recorded session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// The round the message belongs to, starting from 1
+    pub round: u8,
+    /// The participant that sent the message
+    pub sender: u32,
+    /// The participant a private message is for, `None` for broadcasts
+    pub receiver: Option<u32>,
+    /// The encoded message, which starts with the sender
+    pub bytes: Vec<u8>,
+    /// Where the group elements and scalars are in `bytes`
+    elements: Vec<Range<usize>>,
+    /// Whether `bytes` has the receiver after the sender
+    encodes_receiver: bool,
+}
+
+impl Message {
+    fn new(round: u8, sender: u32, receiver: Option<u32>, bytes: Vec<u8>) -> Self {
+        Self {
+            round,
+            sender,
+            receiver,
+            bytes,
+            elements: Vec::new(),
+            encodes_receiver: false,
+        }
+    }
+
+    fn with_elements(mut self, elements: Vec<Range<usize>>) -> Self {
+        self.elements = elements;
+        self
+    }
+
+    /// A private message whose receiver is encoded after the sender
+    fn encoding_receiver(mut self) -> Self {
+        self.encodes_receiver = true;
+        self
+    }
+}
+
+/// A change to the messages of a session that keeps each of them well formed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Remove a message
+    Drop,
+    /// Deliver a message twice
+    Duplicate,
+    /// Swap two messages of the same round
+    Reorder,
+    /// Move a message to the round before or after its own, where it's decoded as a
+    /// message of that round
+    WrongRound,
+    /// Claim a message comes from another participant
+    Spoof,
+    /// Send a private message to another participant, or a broadcast to a single one
+    Redirect,
+    /// Replace a group element or scalar with one of the same size from another
+    /// message of the round
+    Splice,
+    /// Replace a message with the one from the same sender to the same receiver in the
+    /// replayed session
+    Replay,
+}
+
+impl Mutation {
+    /// Every mutation
+    pub const ALL: [Mutation; 8] = [
+        Mutation::Drop,
+        Mutation::Duplicate,
+        Mutation::Reorder,
+        Mutation::WrongRound,
+        Mutation::Spoof,
+        Mutation::Redirect,
+        Mutation::Splice,
+        Mutation::Replay,
+    ];
+}
+
+/// Turn fuzzer input into the seed of a [`Mutator`] and the mutations to apply,
+/// the first 8 bytes are the seed and every following byte picks a mutation
+pub fn case(data: &[u8]) -> (u64, Vec<Mutation>) {
+    let mut seed = [0u8; 8];
+    let len = data.len().min(8);
+    seed[..len].copy_from_slice(&data[..len]);
+    let mutations = data[len..]
+        .iter()
+        .map(|b| Mutation::ALL[*b as usize % Mutation::ALL.len()])
+        .collect();
+    (u64::from_be_bytes(seed), mutations)
+}
+
+/// Applies mutations to recorded messages
+pub struct Mutator {
+    rng: ChaChaRng,
+    replays: Vec<Message>,
+}
+
+impl Mutator {
+    /// Create a mutator where every random choice comes from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaChaRng::seed_from_u64(seed),
+            replays: Vec::new(),
+        }
+    }
+
+    /// Take replayed and spliced messages from `messages` too, the messages of another
+    /// session of the same protocol between the same participants
+    pub fn with_replays(mut self, messages: &[Message]) -> Self {
+        self.replays = messages.to_vec();
+        self
+    }
+
+    /// Apply `count` random mutations to `messages`. Returns the ones that applied
+    pub fn mutate(&mut self, messages: &mut Vec<Message>, count: usize) -> Vec<Mutation> {
+        (0..count)
+            .filter_map(|_| {
+                let mutation = *Mutation::ALL.choose(&mut self.rng).expect("not empty");
+                if self.apply(messages, mutation) {
+                    Some(mutation)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Apply `mutation` to a random message of `messages`.
+    /// Returns false if it doesn't apply to the message chosen
+    pub fn apply(&mut self, messages: &mut Vec<Message>, mutation: Mutation) -> bool {
+        if messages.is_empty() {
+            return false;
+        }
+        let i = self.rng.gen_range(0, messages.len());
+        match mutation {
+            Mutation::Drop => {
+                messages.remove(i);
+            }
+            Mutation::Duplicate => {
+                let message = messages[i].clone();
+                messages.insert(self.rng.gen_range(i, messages.len() + 1), message);
+            }
+            Mutation::Reorder => {
+                let round = messages[i].round;
+                let others = (0..messages.len())
+                    .filter(|j| *j != i && messages[*j].round == round)
+                    .collect::<Vec<_>>();
+                match others.choose(&mut self.rng) {
+                    Some(j) => messages.swap(i, *j),
+                    None => return false,
+                }
+            }
+            Mutation::WrongRound => {
+                let message = &mut messages[i];
+                message.round = if message.round > 1 && self.rng.gen() {
+                    message.round - 1
+                } else {
+                    message.round + 1
+                };
+            }
+            Mutation::Spoof => {
+                let sender = self.other_participant(messages, messages[i].sender);
+                let message = &mut messages[i];
+                message.sender = sender;
+                message.bytes[..4].copy_from_slice(&sender.to_be_bytes());
+            }
+            Mutation::Redirect => {
+                let current = messages[i].receiver.unwrap_or(messages[i].sender);
+                let receiver = self.other_participant(messages, current);
+                let message = &mut messages[i];
+                message.receiver = Some(receiver);
+                if message.encodes_receiver {
+                    message.bytes[4..8].copy_from_slice(&receiver.to_be_bytes());
+                }
+            }
+            Mutation::Splice => {
+                let message = &messages[i];
+                if message.elements.is_empty() {
+                    return false;
+                }
+                let target =
+                    message.elements[self.rng.gen_range(0, message.elements.len())].clone();
+                let donors = messages
+                    .iter()
+                    .chain(self.replays.iter())
+                    .filter(|m| m.round == message.round)
+                    .flat_map(|m| {
+                        m.elements
+                            .iter()
+                            .filter(|e| e.len() == target.len())
+                            .map(move |e| m.bytes[e.clone()].to_vec())
+                    })
+                    .filter(|e| *e != message.bytes[target.clone()])
+                    .collect::<Vec<_>>();
+                match donors.choose(&mut self.rng) {
+                    Some(element) => messages[i].bytes[target].copy_from_slice(element),
+                    None => return false,
+                }
+            }
+            Mutation::Replay => {
+                let message = &messages[i];
+                match self.replays.iter().find(|m| {
+                    m.round == message.round
+                        && m.sender == message.sender
+                        && m.receiver == message.receiver
+                }) {
+                    Some(replay) => messages[i] = replay.clone(),
+                    None => return false,
+                }
+            }
+        }
+        true
+    }
+
+    /// A random participant of the session other than `id`, or `id` if there are none
+    fn other_participant(&mut self, messages: &[Message], id: u32) -> u32 {
+        let mut participants = messages
+            .iter()
+            .flat_map(|m| m.receiver.into_iter().chain(Some(m.sender)))
+            .filter(|p| *p != id)
+            .collect::<Vec<_>>();
+        participants.sort_unstable();
+        participants.dedup();
+        participants.choose(&mut self.rng).copied().unwrap_or(id)
+    }
+}
+
+/// What the state machine under test did with a case
+#[derive(Copy, Clone, Debug)]
+pub enum Verdict {
+    /// It finished with an output consistent with the messages it accepted
+    Completed,
+    /// It stopped with an error
+    Rejected(SharingError),
+    /// It finished with an output that breaks an invariant of the protocol
+    Violation(&'static str),
+}
+
+impl<T> From<SharingResult<T>> for Verdict {
+    fn from(result: SharingResult<T>) -> Self {
+        match result {
+            Ok(_) => Verdict::Completed,
+            Err(e) => Verdict::Rejected(e),
+        }
+    }
+}
+
+/// An honest distributed key generation session to mutate.
+///
+/// Round 1 holds the broadcasts and shares, round 2 the complaints as
+/// `accuser (4) | accused (4)` and round 3 the justifications. Honest sessions have
+/// no complaints, those of the participant under test are added when it runs.
+pub struct DkgSession<S: Field, R: Group<S>> {
+    seed: u64,
+    threshold: usize,
+    limit: usize,
+    generator: R,
+    messages: Vec<Message>,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: Group<S>> DkgSession<S, R> {
+    /// Record a session of `limit` participants with the given `threshold` where all
+    /// randomness comes from `seed`
+    pub fn new(seed: u64, threshold: usize, limit: usize, generator: &R) -> SharingResult<Self> {
+        let mut session = Self {
+            seed,
+            threshold,
+            limit,
+            generator: copy(generator),
+            messages: Vec::new(),
+            _scalar: PhantomData,
+        };
+        let mut messages = Vec::new();
+        session.participants(Some(&mut messages))?;
+        session.messages = messages;
+        Ok(session)
+    }
+
+    /// The messages of the honest session
+    pub fn messages(&self) -> &[Message] {
+        self.messages.as_slice()
+    }
+
+    /// Drive participant `target` through `messages`, delivering the broadcasts and the
+    /// private messages sent to it, and check its output
+    pub fn run(&self, target: u32, messages: &[Message]) -> Verdict {
+        match self.try_run(target, messages) {
+            Ok(verdict) => verdict,
+            Err(e) => Verdict::Rejected(e),
+        }
+    }
+
+    fn try_run(&self, target: u32, messages: &[Message]) -> SharingResult<Verdict> {
+        let mut participant = self
+            .participants(None)?
+            .into_iter()
+            .find(|p| p.id() == target)
+            .ok_or(SharingError::DkgInvalidParticipant)?;
+        let delivered = |round: u8| {
+            messages
+                .iter()
+                .filter(move |m| m.round == round && m.receiver.is_none_or(|r| r == target))
+        };
+
+        let mut broadcasts = Vec::new();
+        let mut shares = Vec::new();
+        for message in delivered(1) {
+            if message.receiver.is_some() {
+                shares.push(Round1Share::try_from(message.bytes.as_slice())?);
+            } else {
+                broadcasts.push(Round1Broadcast::<S, R>::try_from(message.bytes.as_slice())?);
+            }
+        }
+        let mut complaints = participant.receive_round1(&broadcasts, &shares)?;
+        for message in delivered(2) {
+            complaints.push(read_complaint(&message.bytes)?);
+        }
+        let mut justifications = participant.justify(&complaints);
+        for message in delivered(3) {
+            justifications.push(Justification::try_from(message.bytes.as_slice())?);
+        }
+        let output = participant.finalize(&complaints, &justifications)?;
+
+        let transcript = participant.transcript();
+        if !transcript.blame().is_empty() {
+            return Ok(Verdict::Violation(
+                "finished although the transcript blames a participant",
+            ));
+        }
+        if output.verifier().verify(output.share()).is_err() {
+            return Ok(Verdict::Violation(
+                "the share doesn't match the joint commitments",
+            ));
+        }
+        if transcript.verifier()?.to_bytes() != output.verifier().to_bytes() {
+            return Ok(Verdict::Violation(
+                "the joint commitments differ from the transcript's",
+            ));
+        }
+        Ok(Verdict::Completed)
+    }
+
+    /// Create the participants in order, recording their round 1 messages if asked
+    fn participants(
+        &self,
+        mut messages: Option<&mut Vec<Message>>,
+    ) -> SharingResult<Vec<Participant<S, R>>> {
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let (r_size, s_size) = (R::Size::to_usize(), S::Size::to_usize());
+        let mut participants = Vec::with_capacity(self.limit);
+        for id in 1..=self.limit as u32 {
+            let (participant, broadcast, shares) =
+                Participant::new(&mut rng, id, self.threshold, self.limit, &self.generator)?;
+            if let Some(messages) = messages.as_mut() {
+                // sender | length | generator | count | commitments | proof commitment | response
+                let mut elements = Vec::with_capacity(self.threshold + 3);
+                elements.push(8..8 + r_size);
+                let mut offset = 12 + r_size;
+                for _ in 0..=self.threshold {
+                    elements.push(offset..offset + r_size);
+                    offset += r_size;
+                }
+                elements.push(offset..offset + s_size);
+                messages
+                    .push(Message::new(1, id, None, broadcast.to_bytes()).with_elements(elements));
+                for share in shares {
+                    let bytes = share.to_bytes();
+                    let value = 8..bytes.len();
+                    messages.push(
+                        Message::new(1, id, Some(share.receiver()), bytes)
+                            .with_elements(vec![value])
+                            .encoding_receiver(),
+                    );
+                }
+            }
+            participants.push(participant);
+        }
+        Ok(participants)
+    }
+}
+
+#[cfg(feature = "frost")]
+type Round1<S, R> = (Vec<SignerRound1<S, R>>, Vec<SigningCommitments<S, R>>);
+
+/// An honest FROST signing session to mutate, where every holder of a share signs.
+///
+/// Round 1 holds the signing commitments and round 2 the signature shares, all sent to
+/// the coordinator.
+#[cfg(feature = "frost")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "frost")))]
+pub struct FrostSession<S: Field, R: FrostGroup<S>> {
+    seed: u64,
+    threshold: usize,
+    limit: usize,
+    message: Vec<u8>,
+    verifier: FeldmanVerifier<S, R>,
+    messages: Vec<Message>,
+}
+
+#[cfg(feature = "frost")]
+impl<S: Field, R: FrostGroup<S>> FrostSession<S, R> {
+    /// Record the signing of `message` with a key split between `limit` signers with the
+    /// given `threshold` where all randomness comes from `seed`
+    pub fn new(seed: u64, threshold: usize, limit: usize, message: &[u8]) -> SharingResult<Self> {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        let (verifier, _) = Self::split(&mut rng, threshold, limit)?;
+        let mut session = Self {
+            seed,
+            threshold,
+            limit,
+            message: message.to_vec(),
+            verifier,
+            messages: Vec::new(),
+        };
+        let (signers, commitments) = session.signers()?;
+        let (r_size, s_size) = (R::Size::to_usize(), S::Size::to_usize());
+        for c in &commitments {
+            session.messages.push(
+                Message::new(1, c.sender(), None, c.to_bytes())
+                    .with_elements(vec![4..4 + r_size, 4 + r_size..4 + 2 * r_size]),
+            );
+        }
+        for signer in signers {
+            let share = signer.sign(message, &commitments)?;
+            // The only element is the range of the response, not a range of values
+            let response = 4..4 + s_size;
+            session.messages.push(
+                Message::new(2, share.sender(), None, share.to_bytes())
+                    .with_elements(vec![response]),
+            );
+        }
+        Ok(session)
+    }
+
+    /// The messages of the honest session
+    pub fn messages(&self) -> &[Message] {
+        self.messages.as_slice()
+    }
+
+    /// Drive the coordinator through `messages` and check the signature it aggregates.
+    /// Each signer also signs with the commitments the coordinator chose, and a share
+    /// the coordinator rejects for the same commitments is a violation
+    pub fn run(&self, messages: &[Message]) -> Verdict {
+        match self.try_run(messages) {
+            Ok(verdict) => verdict,
+            Err(e) => Verdict::Rejected(e),
+        }
+    }
+
+    fn try_run(&self, messages: &[Message]) -> SharingResult<Verdict> {
+        let delivered = |round: u8| {
+            messages
+                .iter()
+                .filter(move |m| m.round == round && m.receiver.is_none())
+        };
+        let commitments = delivered(1)
+            .map(|m| SigningCommitments::<S, R>::try_from(m.bytes.as_slice()))
+            .collect::<SharingResult<Vec<_>>>()?;
+        let coordinator = Coordinator::new(&self.verifier, &self.message, &commitments)?;
+
+        for signer in self.signers()?.0 {
+            if let Ok(share) = signer.sign(&self.message, &commitments) {
+                if coordinator.verify_share(&share).is_err() {
+                    return Ok(Verdict::Violation(
+                        "the coordinator rejects a share signed with its commitments",
+                    ));
+                }
+            }
+        }
+
+        let shares = delivered(2)
+            .map(|m| SignatureShare::<S>::try_from(m.bytes.as_slice()))
+            .collect::<SharingResult<Vec<_>>>()?;
+        let signature = coordinator.aggregate(&shares)?;
+        if signature
+            .verify(&self.verifier.commitments[0].value, &self.message)
+            .is_err()
+        {
+            return Ok(Verdict::Violation("aggregated an invalid signature"));
+        }
+        Ok(Verdict::Completed)
+    }
+
+    fn split(
+        rng: &mut ChaChaRng,
+        threshold: usize,
+        limit: usize,
+    ) -> SharingResult<(FeldmanVerifier<S, R>, Vec<crate::shamir::Share>)> {
+        let secret = S::random(rng);
+        FeldmanScheme::new(threshold, limit)?.split_secret(rng, &secret, Some(R::generator()))
+    }
+
+    /// Deal the key again and start every signer, the same way every time
+    fn signers(&self) -> SharingResult<Round1<S, R>> {
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let (verifier, shares) = Self::split(&mut rng, self.threshold, self.limit)?;
+        let mut signers = Vec::with_capacity(shares.len());
+        let mut commitments = Vec::with_capacity(shares.len());
+        for share in &shares {
+            let (signer, c) = SignerRound1::new(&mut rng, share, &verifier)?;
+            signers.push(signer);
+            commitments.push(c);
+        }
+        Ok((signers, commitments))
+    }
+}
+
+fn read_complaint(data: &[u8]) -> SharingResult<Complaint> {
+    if data.len() != 8 {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    Ok(Complaint {
+        accuser: crate::dkg::read_u32(&data[..4]),
+        accused: crate::dkg::read_u32(&data[4..]),
+    })
+}
+
+/// Copy an element of a group that isn't `Clone`
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
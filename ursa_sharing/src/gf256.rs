@@ -0,0 +1,318 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Shamir secret sharing over GF(2^8) using the AES polynomial x^8 + x^4 + x^3 + x + 1.
+//!
+//! Each byte of the secret is shared independently so secrets can be any length,
+//! unlike [`shamir::Scheme`](crate::shamir::Scheme) which shares a single field element.
+//! A share of an `n` byte secret is `n + 1` bytes, the share value followed by the
+//! identifier, the layout used by HashiCorp Vault. Because identifiers are field
+//! elements there can be at most 255 shares.
+//! Arithmetic avoids lookup tables and secret dependent branches.
+
+use super::{
+    audit::{notify, Event, SchemeKind},
+    error::{SharingError, SharingResult},
+};
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::convert::TryFrom;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The maximum number of shares
+pub const MAX_SHARES: usize = 255;
+
+/// A share of a byte string secret
+/// provides no integrity checking
+#[derive(Clone, Debug, Zeroize)]
+#[zeroize(drop)]
+pub struct Share {
+    /// X-coordinate
+    identifier: u8,
+    /// Y-coordinate of each byte of the secret
+    value: Vec<u8>,
+}
+
+impl Share {
+    /// Create a new share
+    pub fn new<B: AsRef<[u8]>>(identifier: u8, value: B) -> Self {
+        Self {
+            identifier,
+            value: value.as_ref().to_vec(),
+        }
+    }
+
+    /// Output the share value and the identifier.
+    /// The identifier is the last byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.value.clone();
+        o.push(self.identifier);
+        o
+    }
+
+    /// Get the identifier
+    pub fn identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    /// Get the current value of the share
+    pub fn value(&self) -> &[u8] {
+        self.value.as_slice()
+    }
+}
+
+impl TryFrom<&[u8]> for Share {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        match value.split_last() {
+            Some((identifier, value)) if !value.is_empty() => Ok(Self::new(*identifier, value)),
+            _ => Err(SharingError::ShareSecretMinSize),
+        }
+    }
+}
+
+/// Shamir's simple secret sharing scheme for byte strings.
+#[derive(Copy, Clone, Debug)]
+pub struct Scheme {
+    threshold: usize,
+    limit: usize,
+}
+
+impl Scheme {
+    /// Create a new scheme, `limit` can be at most [`MAX_SHARES`]
+    pub fn new(threshold: usize, limit: usize) -> SharingResult<Self> {
+        if limit < threshold {
+            return Err(SharingError::ShareLimitLessThanThreshold);
+        }
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        if limit > MAX_SHARES {
+            return Err(SharingError::ShareIdentifierCount(MAX_SHARES, limit));
+        }
+        Ok(Self { threshold, limit })
+    }
+
+    /// The minimum number of shares needed to combine
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of shares created
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Create Shares from a non-empty secret of any length
+    pub fn split_secret(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &[u8],
+    ) -> SharingResult<Vec<Share>> {
+        let identifiers = (1..=self.limit as u8).collect::<Vec<u8>>();
+        self.split_secret_with_identifiers(rng, secret, identifiers.as_slice())
+    }
+
+    /// Create Shares from a secret using the caller supplied identifiers
+    /// as the x-coordinates instead of the default `1..=limit`.
+    /// Exactly `limit` identifiers must be supplied and each must be non-zero and unique.
+    pub fn split_secret_with_identifiers(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &[u8],
+        identifiers: &[u8],
+    ) -> SharingResult<Vec<Share>> {
+        if secret.is_empty() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        self.check_identifiers(identifiers)?;
+        let shares = split(rng, secret, self.threshold, identifiers)
+            .into_iter()
+            .zip(identifiers.iter())
+            .map(|(value, identifier)| Share {
+                identifier: *identifier,
+                value,
+            })
+            .collect::<Vec<Share>>();
+        let identifiers = identifiers.iter().map(|i| *i as u32).collect::<Vec<u32>>();
+        notify(Event::Split {
+            scheme: SchemeKind::Shamir,
+            threshold: self.threshold,
+            limit: self.limit,
+            identifiers: identifiers.as_slice(),
+        });
+        Ok(shares)
+    }
+
+    /// Reconstruct a secret from shares created from `split_secret`
+    pub fn combine_shares(&self, shares: &[Share]) -> SharingResult<Vec<u8>> {
+        let res = self.combine(shares);
+        let identifiers = shares
+            .iter()
+            .map(|s| s.identifier as u32)
+            .collect::<Vec<u32>>();
+        notify(Event::Combine {
+            threshold: self.threshold,
+            identifiers: identifiers.as_slice(),
+            error: res.as_ref().err().copied(),
+        });
+        res
+    }
+
+    fn check_identifiers(&self, identifiers: &[u8]) -> SharingResult<()> {
+        if identifiers.len() != self.limit {
+            return Err(SharingError::ShareIdentifierCount(
+                self.limit,
+                identifiers.len(),
+            ));
+        }
+        let mut dups = BTreeSet::new();
+        for identifier in identifiers {
+            if *identifier == 0 {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+            if !dups.insert(*identifier) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        Ok(())
+    }
+
+    fn combine(&self, shares: &[Share]) -> SharingResult<Vec<u8>> {
+        if shares.len() < self.threshold {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let len = shares[0].value.len();
+        let mut dups = BTreeSet::new();
+        for share in shares {
+            if share.identifier == 0 {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+            if !dups.insert(share.identifier) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+            if share.value.is_empty() || share.value.len() != len {
+                return Err(SharingError::ShareInvalidValue);
+            }
+        }
+        let identifiers = shares.iter().map(|s| s.identifier).collect::<Vec<u8>>();
+        let values = shares
+            .iter()
+            .map(|s| s.value.as_slice())
+            .collect::<Vec<&[u8]>>();
+        Ok(combine(identifiers.as_slice(), values.as_slice()))
+    }
+}
+
+/// Multiply two elements
+pub(crate) fn mul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut b = b;
+    let mut r = 0u8;
+    for _ in 0..8 {
+        // r ^= a if the low bit of b is set
+        r ^= a & (b & 1).wrapping_neg();
+        // a *= x reducing by the polynomial if the high bit was set
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    r
+}
+
+/// Compute the multiplicative inverse as a^254. The inverse of zero is zero.
+pub(crate) fn inv(a: u8) -> u8 {
+    // a^2
+    let a2 = mul(a, a);
+    // a^3
+    let a3 = mul(a2, a);
+    // a^6
+    let a6 = mul(a3, a3);
+    // a^12
+    let a12 = mul(a6, a6);
+    // a^15
+    let a15 = mul(a12, a3);
+    // a^30
+    let a30 = mul(a15, a15);
+    // a^60
+    let a60 = mul(a30, a30);
+    // a^120
+    let a120 = mul(a60, a60);
+    // a^126
+    let a126 = mul(a120, a6);
+    // a^252
+    let a252 = mul(a126, a126);
+    // a^254
+    mul(a252, a2)
+}
+
+/// Split each byte of `secret` using a random polynomial of degree `threshold - 1`
+/// evaluated at each of the `identifiers`.
+/// Returns the share values in the same order as the `identifiers`.
+pub(crate) fn split(
+    rng: &mut (impl RngCore + CryptoRng),
+    secret: &[u8],
+    threshold: usize,
+    identifiers: &[u8],
+) -> Vec<Vec<u8>> {
+    let mut values = vec![Vec::with_capacity(secret.len()); identifiers.len()];
+    let mut coefficients = vec![0u8; threshold];
+    for b in secret {
+        coefficients[0] = *b;
+        rng.fill_bytes(&mut coefficients[1..]);
+        for (x, v) in identifiers.iter().zip(values.iter_mut()) {
+            // Horner's method
+            let mut y = 0u8;
+            for c in coefficients.iter().rev() {
+                y = mul(y, *x) ^ *c;
+            }
+            v.push(y);
+        }
+    }
+    coefficients.zeroize();
+    values
+}
+
+/// Reconstruct the secret from the share values at x-coordinates `identifiers`
+/// using lagrange interpolation at zero.
+/// The caller must ensure identifiers are non-zero and unique
+/// and the values all have the same length.
+pub(crate) fn combine(identifiers: &[u8], values: &[&[u8]]) -> Vec<u8> {
+    debug_assert_eq!(identifiers.len(), values.len());
+    let len = values.first().map(|v| v.len()).unwrap_or(0);
+
+    // The basis polynomials are the same for every byte
+    let mut basis = Vec::with_capacity(identifiers.len());
+    for (i, x_i) in identifiers.iter().enumerate() {
+        let mut num = 1u8;
+        let mut den = 1u8;
+        for (j, x_j) in identifiers.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            // x_j / (x_j - x_i), subtraction is xor
+            num = mul(num, *x_j);
+            den = mul(den, *x_j ^ *x_i);
+        }
+        basis.push(mul(num, inv(den)));
+    }
+
+    let mut secret = vec![0u8; len];
+    for (b, v) in basis.iter().zip(values.iter()) {
+        for (s, y) in secret.iter_mut().zip(v.iter()) {
+            *s ^= mul(*b, *y);
+        }
+    }
+    secret
+}
@@ -0,0 +1,394 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Hashing to curves and scalar fields as in RFC 9380.
+//!
+//! [`HashToCurve`] hashes a message to a group element with the random oracle
+//! variant of a suite, `hash_to_curve` from the RFC, and [`HashToScalar`] hashes a
+//! message to a scalar like `hash_to_field`. Both take the application's
+//! domain separation tag, tags longer than 255 bytes are hashed first as the RFC
+//! requires. [`expand_message_xmd`] is public for protocols that need uniform bytes
+//! bound to a tag the same way.
+//!
+//! The suites are
+//!
+//! * `P256_XMD:SHA-256_SSWU_RO_` for [`Secp256r1Point`](crate::secp256r1::Secp256r1Point)
+//! * `ristretto255_XMD:SHA-512_R255MAP_RO_` for
+//!   [`Ristretto25519Point`](crate::ristretto::Ristretto25519Point)
+//! * `BLS12381G1_XMD:SHA-256_SSWU_RO_` and `BLS12381G2_XMD:SHA-256_SSWU_RO_` for
+//!   [`Bls12381G1`](crate::bls12_381::Bls12381G1) and
+//!   [`Bls12381G2`](crate::bls12_381::Bls12381G2)
+//!
+//! The BLS12-381 points come from pairing-plus, whose map predates the final RFC and
+//! picks the sign of `y` with the earlier `sgn0` that compares against `(p - 1) / 2`
+//! instead of the parity. Its `hash_to_field` matches the RFC but the points don't
+//! match the RFC's test vectors, so they only interoperate with other users of
+//! pairing-plus like the BLS signatures of the main Ursa crate.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+use generic_array::typenum::Unsigned;
+use sha2::Digest;
+#[cfg(any(feature = "bls12_381", feature = "ristretto", feature = "secp256r1"))]
+use zeroize::Zeroize;
+
+/// The longest domain separation tag used as is, longer tags are hashed
+const MAX_DST_LEN: usize = 255;
+/// The prefix of hashed domain separation tags
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+/// A hash function for `expand_message_xmd`
+pub trait XmdHash: Digest {
+    /// The input block size in bytes
+    const BLOCK_SIZE: usize;
+}
+
+impl XmdHash for sha2::Sha256 {
+    const BLOCK_SIZE: usize = 64;
+}
+
+impl XmdHash for sha2::Sha512 {
+    const BLOCK_SIZE: usize = 128;
+}
+
+/// A group with a hash to curve suite from RFC 9380
+pub trait HashToCurve<S: Field>: Group<S> {
+    /// The suite identifier
+    const SUITE: &'static str;
+    /// Hash `msg` to the group with the domain separation tag `dst`
+    fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Self;
+}
+
+/// A scalar field with a hash from messages
+pub trait HashToScalar: Field {
+    /// Hash `msg` to a scalar with the domain separation tag `dst`
+    fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Self;
+}
+
+/// `expand_message_xmd` from RFC 9380, fill `out` with bytes derived from `msg`
+/// and the domain separation tag `dst`.
+/// Fails if `out` is longer than 255 hash outputs or 65535 bytes
+pub fn expand_message_xmd<H: XmdHash>(msg: &[u8], dst: &[u8], out: &mut [u8]) -> SharingResult<()> {
+    let blocks = out.len().div_ceil(H::OutputSize::to_usize());
+    if blocks > 255 || out.len() > u16::MAX as usize {
+        return Err(SharingError::HashToCurveLength(out.len()));
+    }
+    expand::<H>(&[msg], dst, out);
+    Ok(())
+}
+
+/// `expand_message_xmd` of the concatenated `input`, for outputs known to be short enough
+pub(crate) fn expand<H: XmdHash>(input: &[&[u8]], dst: &[u8], out: &mut [u8]) {
+    with_dst::<H, _>(dst, |dst| {
+        let hash_size = H::OutputSize::to_usize();
+        debug_assert!(out.len() <= 255 * hash_size);
+        let dst_suffix = [dst.len() as u8];
+
+        let mut hasher = H::new();
+        hasher.input(&[0u8; 128][..H::BLOCK_SIZE]);
+        for i in input {
+            hasher.input(i);
+        }
+        hasher.input((out.len() as u16).to_be_bytes());
+        hasher.input([0u8]);
+        hasher.input(dst);
+        hasher.input(dst_suffix);
+        let b0 = hasher.result();
+
+        let mut bi = H::new()
+            .chain(&b0)
+            .chain([1u8])
+            .chain(dst)
+            .chain(dst_suffix)
+            .result();
+        for (i, chunk) in out.chunks_mut(hash_size).enumerate() {
+            if i > 0 {
+                let mut xored = b0.clone();
+                xored.iter_mut().zip(bi.iter()).for_each(|(x, b)| *x ^= b);
+                bi = H::new()
+                    .chain(&xored)
+                    .chain([i as u8 + 1])
+                    .chain(dst)
+                    .chain(dst_suffix)
+                    .result();
+            }
+            chunk.copy_from_slice(&bi[..chunk.len()]);
+        }
+    })
+}
+
+/// Call `f` with `dst`, or its hash if it is too long to use as is
+fn with_dst<H: XmdHash, T>(dst: &[u8], f: impl FnOnce(&[u8]) -> T) -> T {
+    if dst.len() > MAX_DST_LEN {
+        f(&H::new().chain(OVERSIZE_DST_PREFIX).chain(dst).result())
+    } else {
+        f(dst)
+    }
+}
+
+#[cfg(feature = "ristretto")]
+impl HashToCurve<crate::ristretto::Ristretto25519Scalar> for crate::ristretto::Ristretto25519Point {
+    const SUITE: &'static str = "ristretto255_XMD:SHA-512_R255MAP_RO_";
+
+    /// `hash_to_ristretto255`, the one-way map of 64 bytes from `expand_message_xmd`
+    fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Self {
+        let mut uniform = [0u8; 64];
+        expand::<sha2::Sha512>(&[msg], dst, &mut uniform);
+        let r = Self(curve25519_dalek::ristretto::RistrettoPoint::from_uniform_bytes(&uniform));
+        uniform.zeroize();
+        r
+    }
+}
+
+#[cfg(feature = "ristretto")]
+impl HashToScalar for crate::ristretto::Ristretto25519Scalar {
+    /// 64 bytes from `expand_message_xmd` with SHA-512 read in little endian modulo
+    /// the group order, like the ristretto255 ciphersuites of RFC 9497
+    fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Self {
+        let mut uniform = [0u8; 64];
+        expand::<sha2::Sha512>(&[msg], dst, &mut uniform);
+        let r = Self(curve25519_dalek::scalar::Scalar::from_bytes_mod_order_wide(
+            &uniform,
+        ));
+        uniform.zeroize();
+        r
+    }
+}
+
+#[cfg(feature = "secp256r1")]
+impl HashToCurve<crate::secp256r1::Secp256r1Scalar> for crate::secp256r1::Secp256r1Point {
+    const SUITE: &'static str = "P256_XMD:SHA-256_SSWU_RO_";
+
+    fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Self {
+        let mut uniform = [0u8; 96];
+        expand::<sha2::Sha256>(&[msg], dst, &mut uniform);
+        let q0 = p256_sswu::map_to_curve(&uniform[..48]);
+        let q1 = p256_sswu::map_to_curve(&uniform[48..]);
+        uniform.zeroize();
+        Self(q0 + q1)
+    }
+}
+
+#[cfg(feature = "secp256r1")]
+impl HashToScalar for crate::secp256r1::Secp256r1Scalar {
+    /// `hash_to_field` with SHA-256 and 48 bytes per element, over the group order
+    fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Self {
+        use p256::{FieldBytes, Scalar};
+
+        // 2^256 mod the group order
+        const R: [u8; 32] = [
+            0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x43, 0x19, 0x05, 0x52, 0x58, 0xe8, 0x61, 0x7b, 0x0c, 0x46, 0x35, 0x3d,
+            0x03, 0x9c, 0xda, 0xaf,
+        ];
+
+        let mut uniform = [0u8; 64];
+        expand::<sha2::Sha256>(&[msg], dst, &mut uniform[16..]);
+        // hi * 2^256 + lo where both halves are below 2^256 and reduce with one subtraction
+        let hi = Scalar::from_bytes_reduced(FieldBytes::from_slice(&uniform[..32]));
+        let lo = Scalar::from_bytes_reduced(FieldBytes::from_slice(&uniform[32..]));
+        uniform.zeroize();
+        Self(hi * Scalar::from_bytes_reduced(FieldBytes::from_slice(&R)) + lo)
+    }
+}
+
+#[cfg(feature = "bls12_381")]
+impl HashToCurve<crate::bls12_381::Bls12381Scalar> for crate::bls12_381::Bls12381G1 {
+    const SUITE: &'static str = "BLS12381G1_XMD:SHA-256_SSWU_RO_";
+
+    /// The pairing-plus map, see the module documentation
+    fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Self {
+        use pairing_plus::{
+            bls12_381::G1, hash_to_curve::HashToCurve, hash_to_field::ExpandMsgXmd,
+        };
+
+        with_dst::<sha2::Sha256, _>(dst, |dst| {
+            Self(<G1 as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(msg, dst))
+        })
+    }
+}
+
+#[cfg(feature = "bls12_381")]
+impl HashToCurve<crate::bls12_381::Bls12381Scalar> for crate::bls12_381::Bls12381G2 {
+    const SUITE: &'static str = "BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+    /// The pairing-plus map, see the module documentation
+    fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Self {
+        use pairing_plus::{
+            bls12_381::G2, hash_to_curve::HashToCurve, hash_to_field::ExpandMsgXmd,
+        };
+
+        with_dst::<sha2::Sha256, _>(dst, |dst| {
+            Self(<G2 as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(msg, dst))
+        })
+    }
+}
+
+#[cfg(feature = "bls12_381")]
+impl HashToScalar for crate::bls12_381::Bls12381Scalar {
+    /// `hash_to_field` with SHA-256 and 48 bytes per element, over the group order
+    fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Self {
+        let mut uniform = [0u8; 48];
+        expand::<sha2::Sha256>(&[msg], dst, &mut uniform);
+        let r = Self::from_bytes(&uniform[..]).expect("48 bytes are reduced");
+        uniform.zeroize();
+        r
+    }
+}
+
+/// The simplified SWU map to P-256 from RFC 9380
+#[cfg(feature = "secp256r1")]
+mod p256_sswu {
+    use crate::field::{FieldParams, Fp};
+    use crate::{Field, Group};
+    use p256::{
+        elliptic_curve::sec1::FromEncodedPoint, AffinePoint, EncodedPoint, FieldBytes,
+        ProjectivePoint,
+    };
+
+    /// The field of P-256 coordinates
+    #[derive(Copy, Clone, Debug)]
+    struct FqParams;
+
+    impl FieldParams for FqParams {
+        const MODULUS: [u64; 4] = [
+            0xffff_ffff_ffff_ffff,
+            0x0000_0000_ffff_ffff,
+            0x0000_0000_0000_0000,
+            0xffff_ffff_0000_0001,
+        ];
+        const INV: u64 = 0x0000_0000_0000_0001;
+        const R: [u64; 4] = [
+            0x0000_0000_0000_0001,
+            0xffff_ffff_0000_0000,
+            0xffff_ffff_ffff_ffff,
+            0x0000_0000_ffff_fffe,
+        ];
+        const R2: [u64; 4] = [
+            0x0000_0000_0000_0003,
+            0xffff_fffb_ffff_ffff,
+            0xffff_ffff_ffff_fffe,
+            0x0000_0004_ffff_fffd,
+        ];
+        const R3: [u64; 4] = [
+            0xffff_fffd_0000_000a,
+            0xffff_ffed_ffff_fff7,
+            0x0000_0005_ffff_fffc,
+            0x0000_0018_0000_0001,
+        ];
+        const TWO_ADICITY: u32 = 1;
+        const ROOT_OF_UNITY: [u64; 4] = [
+            0xffff_ffff_ffff_fffe,
+            0x0000_0000_ffff_ffff,
+            0x0000_0000_0000_0000,
+            0xffff_ffff_0000_0001,
+        ];
+        const GENERATOR: u64 = 6;
+    }
+
+    type Fq = Fp<FqParams>;
+
+    /// MODULUS - 2, for inversion that maps zero to zero
+    const INVERT: [u64; 4] = [
+        0xffff_ffff_ffff_fffd,
+        0x0000_0000_ffff_ffff,
+        0x0000_0000_0000_0000,
+        0xffff_ffff_0000_0001,
+    ];
+    /// (MODULUS - 1) / 2, Euler's criterion
+    const LEGENDRE: [u64; 4] = [
+        0xffff_ffff_ffff_ffff,
+        0x0000_0000_7fff_ffff,
+        0x8000_0000_0000_0000,
+        0x7fff_ffff_8000_0000,
+    ];
+    /// (MODULUS + 1) / 4, square roots as MODULUS = 3 mod 4
+    const SQRT: [u64; 4] = [
+        0x0000_0000_0000_0000,
+        0x0000_0000_4000_0000,
+        0x4000_0000_0000_0000,
+        0x3fff_ffff_c000_0000,
+    ];
+    /// The curve constant b
+    const B: [u64; 4] = [
+        0x3bce_3c3e_27d2_604b,
+        0x651d_06b0_cc53_b0f6,
+        0xb3eb_bd55_7698_86bc,
+        0x5ac6_35d8_aa3a_93e7,
+    ];
+    /// -b / a with a = -3
+    const MINUS_B_OVER_A: [u64; 4] = [
+        0x6944_bebf_629b_756e,
+        0xcc5f_023b_441b_e5a7,
+        0x3bf9_3f1c_7cdd_823e,
+        0x7397_6747_e368_dbf8,
+    ];
+    /// b / (Z * a) with Z = -10
+    const B_OVER_ZA: [u64; 4] = [
+        0xbdba_1313_2375_f224,
+        0x146f_e6a0_2069_3090,
+        0x6c65_b982_d949_59d3,
+        0xa528_bd86_96bd_af99,
+    ];
+
+    fn constant(limbs: [u64; 4]) -> Fq {
+        Fq::from_canonical(limbs).expect("constants are reduced")
+    }
+
+    /// `b` when `choice` is set and `a` otherwise, without branching on `choice`
+    fn select(a: Fq, b: Fq, choice: bool) -> Fq {
+        a + (b - a) * Fq::from_u64(choice as u64)
+    }
+
+    fn sgn0(x: &Fq) -> bool {
+        x.to_canonical()[0] & 1 == 1
+    }
+
+    /// Map 48 uniform bytes to a point
+    pub(super) fn map_to_curve(uniform: &[u8]) -> ProjectivePoint {
+        let mut wide = [0u8; 64];
+        wide[16..].copy_from_slice(uniform);
+        let u = Fq::from_bytes_wide(&wide);
+
+        let b = constant(B);
+        let three = Fq::from_u64(3);
+        let zu2 = -Fq::from_u64(10) * u.square();
+        let tv1 = (zu2.square() + zu2).pow(&INVERT);
+        let x1 = select(
+            constant(MINUS_B_OVER_A) * (Fq::one() + tv1),
+            constant(B_OVER_ZA),
+            tv1.is_zero(),
+        );
+        let gx1 = (x1.square() - three) * x1 + b;
+        let x2 = zu2 * x1;
+        let gx2 = (x2.square() - three) * x2 + b;
+
+        let legendre = gx1.pow(&LEGENDRE);
+        let is_square = (legendre == Fq::one()) | legendre.is_zero();
+        let x = select(x2, x1, is_square);
+        let y = select(gx2, gx1, is_square).pow(&SQRT);
+        let y = select(-y, y, sgn0(&u) == sgn0(&y));
+
+        let encoded = EncodedPoint::from_affine_coordinates(
+            FieldBytes::from_slice(&x.to_bytes()),
+            FieldBytes::from_slice(&y.to_bytes()),
+            false,
+        );
+        let point = AffinePoint::from_encoded_point(&encoded);
+        ProjectivePoint::from(
+            Option::<AffinePoint>::from(point).expect("the map is onto the curve"),
+        )
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Hierarchical deterministic derivation of secret keys from a seed.
+//!
+//! A [`DerivationPath`] such as `m/12381/3600/0/0` or `m/44'/0'` is parsed once and
+//! used with any [`KeyDerivation`] scheme. Each index is either normal or hardened,
+//! written with a trailing `'` or `h`, and each scheme rejects the kind it doesn't
+//! support instead of silently deriving another key.
+//!
+//! - [`Eip2333`] derives BLS12-381 keys as EIP-2333 does, the scheme used by Ethereum
+//!   validators. Its indices are normal and can be any `u32`.
+//! - [`Ristretto255Hd`] derives Ristretto255 keys with HKDF-SHA512. Only hardened
+//!   indices are supported, a child key reveals nothing about its parent or siblings.
+//!
+//! There is no public key derivation, the public key of a derived secret key is
+//! computed from it as usual.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field,
+};
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+#[cfg(feature = "bls12_381")]
+use super::bls12_381::Bls12381Scalar;
+#[cfg(feature = "ristretto")]
+use super::ristretto::Ristretto25519Scalar;
+#[cfg(any(feature = "bls12_381", feature = "ristretto"))]
+use super::Group;
+#[cfg(any(feature = "bls12_381", feature = "ristretto"))]
+use hkdf::Hkdf;
+#[cfg(feature = "ristretto")]
+use sha2::Sha512;
+#[cfg(feature = "bls12_381")]
+use sha2::{Digest, Sha256};
+#[cfg(any(feature = "bls12_381", feature = "ristretto"))]
+use zeroize::Zeroize;
+
+/// The smallest seed accepted by the derivation schemes
+pub const MIN_SEED_SIZE: usize = 32;
+/// The offset hardened indices are encoded with in 32 bits
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// One step of a derivation path
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChildIndex {
+    /// A normal index
+    Normal(u32),
+    /// A hardened index, less than [`HARDENED_OFFSET`]
+    Hardened(u32),
+}
+
+impl ChildIndex {
+    /// The index as 32 bits with hardened indices offset by [`HARDENED_OFFSET`]
+    /// like BIP-32. Fails for normal indices that can't be told apart from hardened ones
+    pub fn to_u32(self) -> SharingResult<u32> {
+        match self {
+            ChildIndex::Normal(i) if i < HARDENED_OFFSET => Ok(i),
+            ChildIndex::Hardened(i) if i < HARDENED_OFFSET => Ok(i | HARDENED_OFFSET),
+            _ => Err(SharingError::DerivationIndexUnsupported),
+        }
+    }
+}
+
+impl Display for ChildIndex {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ChildIndex::Normal(i) => write!(f, "{}", i),
+            ChildIndex::Hardened(i) => write!(f, "{}'", i),
+        }
+    }
+}
+
+/// A path of child indices from the master key
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<ChildIndex>);
+
+impl DerivationPath {
+    /// Create a path from its indices
+    pub fn new(indices: Vec<ChildIndex>) -> SharingResult<Self> {
+        for index in &indices {
+            if let ChildIndex::Hardened(i) = index {
+                if *i >= HARDENED_OFFSET {
+                    return Err(SharingError::DerivationPathInvalid);
+                }
+            }
+        }
+        Ok(Self(indices))
+    }
+
+    /// The EIP-2334 path of an Ethereum validator's signing key, `m/12381/3600/account/0/0`
+    pub fn eip2334_signing(account: u32) -> Self {
+        Self(vec![
+            ChildIndex::Normal(12381),
+            ChildIndex::Normal(3600),
+            ChildIndex::Normal(account),
+            ChildIndex::Normal(0),
+            ChildIndex::Normal(0),
+        ])
+    }
+
+    /// The indices from the master key
+    pub fn indices(&self) -> &[ChildIndex] {
+        self.0.as_slice()
+    }
+
+    /// The path extended by `index`
+    pub fn child(&self, index: ChildIndex) -> SharingResult<Self> {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        Self::new(indices)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = SharingError;
+
+    /// Parse `m` followed by `/index` for each index, with a trailing `'` or `h`
+    /// for hardened indices
+    fn from_str(path: &str) -> SharingResult<Self> {
+        let mut parts = path.split('/');
+        if parts.next() != Some("m") {
+            return Err(SharingError::DerivationPathInvalid);
+        }
+        let mut indices = Vec::new();
+        for part in parts {
+            let (digits, hardened) = match part.strip_suffix(|c| c == '\'' || c == 'h') {
+                Some(digits) => (digits, true),
+                None => (part, false),
+            };
+            // Reject signs and empty parts which u32::from_str would accept or misreport
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(SharingError::DerivationPathInvalid);
+            }
+            let value = u32::from_str(digits).map_err(|_| SharingError::DerivationPathInvalid)?;
+            indices.push(if hardened {
+                ChildIndex::Hardened(value)
+            } else {
+                ChildIndex::Normal(value)
+            });
+        }
+        Self::new(indices)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// A scheme deriving secret keys from a seed along a path
+pub trait KeyDerivation {
+    /// The secret keys derived
+    type Scalar: Field;
+
+    /// Derive the master key from a seed of at least [`MIN_SEED_SIZE`] bytes
+    fn master_key(seed: &[u8]) -> SharingResult<Self::Scalar>;
+
+    /// Derive the child key at `index` of `parent`
+    fn child_key(parent: &Self::Scalar, index: ChildIndex) -> SharingResult<Self::Scalar>;
+
+    /// Derive the key at `path` from the master key of `seed`
+    fn derive(seed: &[u8], path: &DerivationPath) -> SharingResult<Self::Scalar> {
+        let mut key = Self::master_key(seed)?;
+        for index in path.indices() {
+            key = Self::child_key(&key, *index)?;
+        }
+        Ok(key)
+    }
+}
+
+/// EIP-2333 derivation of BLS12-381 keys
+#[cfg(feature = "bls12_381")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "bls12_381")))]
+#[derive(Copy, Clone, Debug)]
+pub struct Eip2333;
+
+#[cfg(feature = "bls12_381")]
+impl Eip2333 {
+    const SALT: &'static [u8] = b"BLS-SIG-KEYGEN-SALT-";
+    const LAMPORT_CHUNKS: usize = 255;
+
+    /// HKDF_mod_r of the key generation draft with empty key info
+    fn hkdf_mod_r(ikm: &[u8]) -> SharingResult<Bls12381Scalar> {
+        let mut salt = Sha256::digest(Self::SALT);
+        let mut ikm = ikm.to_vec();
+        ikm.push(0);
+        loop {
+            let mut okm = [0u8; 48];
+            Hkdf::<Sha256>::new(Some(salt.as_slice()), &ikm)
+                .expand(&[0, 48], &mut okm)
+                .map_err(|_| SharingError::ShareInvalidSecret)?;
+            let key = Bls12381Scalar::from_bytes(&okm[..]);
+            okm.zeroize();
+            let key = key?;
+            if !key.is_zero() {
+                ikm.zeroize();
+                return Ok(key);
+            }
+            salt = Sha256::digest(salt.as_slice());
+        }
+    }
+
+    /// The compressed Lamport public key of the key generation draft, hashing the
+    /// 255 chunks of a key derived from the parent and from its complement
+    fn parent_to_lamport_pk(parent: &Bls12381Scalar, index: u32) -> SharingResult<[u8; 32]> {
+        let mut ikm = parent.to_bytes().to_vec();
+        let mut lamport_pk = Sha256::new();
+        let mut okm = vec![0u8; 32 * Self::LAMPORT_CHUNKS];
+        for _ in 0..2 {
+            Hkdf::<Sha256>::new(Some(&index.to_be_bytes()), &ikm)
+                .expand(&[], &mut okm)
+                .map_err(|_| SharingError::ShareInvalidSecret)?;
+            for chunk in okm.chunks(32) {
+                lamport_pk.input(Sha256::digest(chunk));
+            }
+            // The second key is derived from the flipped bits
+            ikm.iter_mut().for_each(|b| *b = !*b);
+        }
+        okm.zeroize();
+        ikm.zeroize();
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&lamport_pk.result());
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "bls12_381")]
+impl KeyDerivation for Eip2333 {
+    type Scalar = Bls12381Scalar;
+
+    fn master_key(seed: &[u8]) -> SharingResult<Bls12381Scalar> {
+        if seed.len() < MIN_SEED_SIZE {
+            return Err(SharingError::DerivationSeedTooShort);
+        }
+        Self::hkdf_mod_r(seed)
+    }
+
+    /// Only normal indices are supported, EIP-2333 children are always hardened
+    fn child_key(parent: &Bls12381Scalar, index: ChildIndex) -> SharingResult<Bls12381Scalar> {
+        match index {
+            ChildIndex::Normal(i) => Self::hkdf_mod_r(&Self::parent_to_lamport_pk(parent, i)?),
+            ChildIndex::Hardened(_) => Err(SharingError::DerivationIndexUnsupported),
+        }
+    }
+}
+
+/// Hardened derivation of Ristretto255 keys with HKDF-SHA512
+#[cfg(feature = "ristretto")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ristretto")))]
+#[derive(Copy, Clone, Debug)]
+pub struct Ristretto255Hd;
+
+#[cfg(feature = "ristretto")]
+impl Ristretto255Hd {
+    const SALT: &'static [u8] = b"ursa_sharing ristretto255 hd v1";
+
+    fn expand(ikm: &[u8], info: &[u8]) -> SharingResult<Ristretto25519Scalar> {
+        let mut okm = [0u8; 64];
+        Hkdf::<Sha512>::new(Some(Self::SALT), ikm)
+            .expand(info, &mut okm)
+            .map_err(|_| SharingError::ShareInvalidSecret)?;
+        let key = Ristretto25519Scalar::from_bytes(&okm[..]);
+        okm.zeroize();
+        let key = key?;
+        if key.is_zero() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(feature = "ristretto")]
+impl KeyDerivation for Ristretto255Hd {
+    type Scalar = Ristretto25519Scalar;
+
+    fn master_key(seed: &[u8]) -> SharingResult<Ristretto25519Scalar> {
+        if seed.len() < MIN_SEED_SIZE {
+            return Err(SharingError::DerivationSeedTooShort);
+        }
+        Self::expand(seed, b"master")
+    }
+
+    /// Only hardened indices are supported
+    fn child_key(
+        parent: &Ristretto25519Scalar,
+        index: ChildIndex,
+    ) -> SharingResult<Ristretto25519Scalar> {
+        if let ChildIndex::Normal(_) = index {
+            return Err(SharingError::DerivationIndexUnsupported);
+        }
+        let mut info = b"child".to_vec();
+        info.extend_from_slice(&index.to_u32()?.to_be_bytes());
+        let mut ikm = parent.to_bytes().to_vec();
+        let key = Self::expand(&ikm, &info);
+        ikm.zeroize();
+        key
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Identity-based encryption over BLS12-381.
+//!
+//! Anyone can encrypt to an identity, like an email address or a DID, with only the
+//! [`PublicKey`] of the key authority. The authority's [`MasterKey`] extracts the
+//! [`IdentityKey`] that decrypts.
+//!
+//! The scheme is Boneh and Franklin's (see <https://crypto.stanford.edu/~dabo/papers/bfibe.pdf>)
+//! as a KEM: the identity key is `H(id)^s`, which is a BLS signature on the identity
+//! under `g1^s`, and a ciphertext is `g1^r` with the data sealed with ChaCha20-Poly1305
+//! under a key hashed from `e(g1^s, H(id))^r`. An identity key can be checked against
+//! the public key before it is used, and there is no key escrow beyond the authority
+//! itself. Identities are hashed to G2 with the suite in [`DST`].
+//!
+//! Because identity keys are BLS signatures, the master key can be shared like a
+//! [`threshold_bls`](crate::threshold_bls) signing key, e.g. generated with the DKG.
+//! Each node extracts a [`PartialSignature`] with [`extract_share`] and the user
+//! combines `threshold` of them with [`IdentityKey::combine`], so no single node can
+//! decrypt for every identity.
+
+use super::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    error::{SharingError, SharingResult},
+    hash_to_curve::HashToCurve,
+    shamir::Share,
+    threshold_bls::{PartialSignature, PublicKey, Signature},
+    Group,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use pairing_plus::{
+    bls12_381::{Bls12, Fq12},
+    serdes::SerDes,
+    Engine,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use zeroize::Zeroize;
+
+/// The version of the ciphertext encoding
+pub const VERSION: u8 = 1;
+/// The hash to curve suite used for identities
+pub const DST: &[u8] = b"URSA_IBE_BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+const KEY_DOMAIN: &[u8] = b"ursa_sharing ibe key v1";
+const G1_BYTES: usize = 48;
+
+/// The key authority's secret, cleared on drop
+pub struct MasterKey(Bls12381Scalar);
+
+impl MasterKey {
+    /// Create a new master key
+    pub fn new(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        loop {
+            let s = Bls12381Scalar::random(rng);
+            if s.is_valid() {
+                return Self(s);
+            }
+        }
+    }
+
+    /// Use an existing BLS signing key as the master key.
+    /// Fails with `SharingError::ShareInvalidSecret` if it is zero
+    pub fn from_secret_key(secret_key: &Bls12381Scalar) -> SharingResult<Self> {
+        if !secret_key.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        Ok(Self(*secret_key))
+    }
+
+    /// The public key everyone encrypts with
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(&self.0)
+    }
+
+    /// Extract the key of `identity`
+    pub fn extract(&self, identity: &[u8]) -> IdentityKey {
+        let mut key = hash_identity(identity);
+        key.scalar_mul_assign(&self.0);
+        IdentityKey {
+            identity: identity.to_vec(),
+            key,
+        }
+    }
+
+    /// Encode the master key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for MasterKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != 32 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut s = Bls12381Scalar::from_bytes(value)?;
+        let key = Self::from_secret_key(&s);
+        s.zeroize();
+        key
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for MasterKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "MasterKey(..)")
+    }
+}
+
+/// A node's part of the key of `identity`, extracted with its share of the master key
+pub fn extract_share(share: &Share, identity: &[u8]) -> SharingResult<PartialSignature> {
+    PartialSignature::new_hashed(share, &hash_identity(identity))
+}
+
+/// The key that decrypts data encrypted to one identity, cleared on drop
+pub struct IdentityKey {
+    identity: Vec<u8>,
+    /// H(id)^s
+    key: Bls12381G2,
+}
+
+impl IdentityKey {
+    pub(crate) fn new(identity: &[u8], key: Bls12381G2) -> Self {
+        Self {
+            identity: identity.to_vec(),
+            key,
+        }
+    }
+
+    /// Combine at least `threshold` parts of the key of `identity` from distinct nodes.
+    /// Check the parts with `PartialSignature::verify_hashed` and [`hash_identity`] first
+    /// to find bad nodes, or the combined key with [`IdentityKey::verify`]
+    pub fn combine(
+        identity: &[u8],
+        threshold: usize,
+        parts: &[PartialSignature],
+    ) -> SharingResult<Self> {
+        let signature = Signature::combine(threshold, parts)?;
+        Ok(Self {
+            identity: identity.to_vec(),
+            key: signature.0,
+        })
+    }
+
+    pub(crate) fn key(&self) -> &Bls12381G2 {
+        &self.key
+    }
+
+    /// The identity of the key
+    pub fn identity(&self) -> &[u8] {
+        &self.identity
+    }
+
+    /// Check that the key was extracted for its identity by the authority of `public_key`.
+    /// Fails with `SharingError::IbeInvalidKey` if it wasn't
+    pub fn verify(&self, public_key: &PublicKey) -> SharingResult<()> {
+        Signature(self.key)
+            .verify_hashed(public_key, &hash_identity(&self.identity))
+            .map_err(|_| SharingError::IbeInvalidKey)
+    }
+
+    /// Decrypt data encrypted to the identity of the key.
+    /// Fails with `SharingError::IbeDecryptionFailed` if it was encrypted to another
+    /// identity or authority, or modified
+    pub fn decrypt(&self, ciphertext: &Ciphertext) -> SharingResult<Vec<u8>> {
+        // e(g1^r, H(id)^s)
+        let shared = Bls12::pairing(ciphertext.u.0, self.key.0);
+        let aad = ciphertext.header();
+        cipher(&ciphertext.u, &self.identity, &shared)
+            .decrypt(
+                Nonce::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: &ciphertext.sealed,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| SharingError::IbeDecryptionFailed)
+    }
+
+    /// Encode the identity key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = (self.identity.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(&self.identity);
+        out.extend_from_slice(&self.key.to_bytes());
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for IdentityKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() < 4 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&value[..4]);
+        let len = u32::from_be_bytes(len) as usize;
+        if value.len() - 4 < len || value.len() - 4 - len != 96 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            identity: value[4..4 + len].to_vec(),
+            key: Bls12381G2::from_bytes(&value[4 + len..])?,
+        })
+    }
+}
+
+impl Drop for IdentityKey {
+    fn drop(&mut self) {
+        self.key.clear();
+    }
+}
+
+impl Debug for IdentityKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "IdentityKey({:?}, ..)",
+            String::from_utf8_lossy(&self.identity)
+        )
+    }
+}
+
+/// Data encrypted to an identity
+#[derive(Clone, Debug)]
+pub struct Ciphertext {
+    /// g1^r
+    pub(crate) u: Bls12381G1,
+    sealed: Vec<u8>,
+}
+
+/// Encrypt `plaintext` to `identity` under the authority of `public_key`
+pub fn encrypt(
+    rng: &mut (impl RngCore + CryptoRng),
+    public_key: &PublicKey,
+    identity: &[u8],
+    plaintext: &[u8],
+) -> SharingResult<Ciphertext> {
+    let mut r = Bls12381Scalar::random(rng);
+    let ciphertext = encrypt_with(&r, public_key, identity, plaintext);
+    r.zeroize();
+    ciphertext
+}
+
+/// Encrypt with the randomness `r`, which must not be used again
+pub(crate) fn encrypt_with(
+    r: &Bls12381Scalar,
+    public_key: &PublicKey,
+    identity: &[u8],
+    plaintext: &[u8],
+) -> SharingResult<Ciphertext> {
+    if !public_key.0.is_valid() {
+        return Err(SharingError::ShareInvalidRecipientKey);
+    }
+    let mut u = Bls12381G1::generator();
+    u.scalar_mul_assign(r);
+    // e(g1^s, H(id))^r, with the exponent applied in G1 where it is cheaper
+    let mut blinded = public_key.0;
+    blinded.scalar_mul_assign(r);
+    let shared = Bls12::pairing(blinded.0, hash_identity(identity).0);
+    blinded.clear();
+
+    let mut ciphertext = Ciphertext {
+        u,
+        sealed: Vec::new(),
+    };
+    let aad = ciphertext.header();
+    // The key is only used once so a fixed nonce is safe
+    ciphertext.sealed = cipher(&u, identity, &shared)
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| SharingError::ShareInvalidValue)?;
+    Ok(ciphertext)
+}
+
+impl Ciphertext {
+    fn header(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        out.extend_from_slice(&self.u.to_bytes());
+        out
+    }
+
+    /// Encode the ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header();
+        out.extend_from_slice(&self.sealed);
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for Ciphertext {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        match value.first() {
+            Some(&VERSION) => {}
+            Some(v) => return Err(SharingError::ShareUnsupportedVersion(*v)),
+            None => return Err(SharingError::ShareInvalidEncoding),
+        }
+        if value.len() < 1 + G1_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let u = Bls12381G1::from_bytes(&value[1..1 + G1_BYTES])?;
+        if u.is_zero() {
+            return Err(SharingError::InvalidPoint);
+        }
+        Ok(Self {
+            u,
+            sealed: value[1 + G1_BYTES..].to_vec(),
+        })
+    }
+}
+
+/// Hash an identity to G2 with the suite in [`DST`]
+pub fn hash_identity(identity: &[u8]) -> Bls12381G2 {
+    Bls12381G2::hash_to_curve(identity, DST)
+}
+
+/// The AEAD keyed with the hash of the ciphertext, the identity and the shared element
+fn cipher(u: &Bls12381G1, identity: &[u8], shared: &Fq12) -> ChaCha20Poly1305 {
+    let mut bytes = Vec::with_capacity(576);
+    shared.serialize(&mut bytes, true).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(KEY_DOMAIN);
+    hasher.input(u.to_bytes());
+    hasher.input((identity.len() as u32).to_be_bytes());
+    hasher.input(identity);
+    hasher.input(&bytes);
+    bytes.zeroize();
+    let mut key = hasher.result();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    cipher
+}
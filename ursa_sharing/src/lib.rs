@@ -35,23 +35,50 @@
 //!
 //! Feldman reveals the public value of the verifier whereas Pedersen's hides it.
 //!
+//! # Constant time
+//!
+//! Shares are often held by servers where an attacker can measure how long requests take.
+//! Splitting and combining only branch on public values: the threshold, the limit,
+//! the share identifiers and the encoded lengths. Checks on secret values such as
+//! rejecting a zero share are computed in full and only acted on after the rest of
+//! the work is done. The field and group arithmetic is provided by the `Field` and
+//! `Group` implementations so the guarantee is only as strong as the backend used.
+//! The README lists the status of the backends used in the examples.
+//!
+//! # no_std
+//!
+//! The crate is `no_std` with `alloc` when the default `std` feature is disabled, for
+//! firmware like HSMs and TrustZone applets. The schemes, proofs and the `ed25519`,
+//! `ristretto`, `secp256k1` and `secp256r1` backends work without `std` as every
+//! operation takes an RNG from the caller. `std` adds the `Error` trait for `SharingError`,
+//! the audit observer registry and the `entropy` module, and is needed by the features
+//! relying on it.
+//!
 //! FUTURE: Adept secret sharing as described by Phillip Rogaway
 //! (see <https://eprint.iacr.org/2020/800>
 //!
 //! Future work would be to use reed-solomon
 //! codes to check for corrupted shares.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(feature = "nightly", feature(doc_cfg))]
 
+extern crate alloc;
+
 pub use generic_array::{self, typenum};
+pub use zeroize;
 
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
 use error::{SharingError, SharingResult};
 use generic_array::{ArrayLength, GenericArray};
 use rand::{CryptoRng, RngCore};
-use std::{convert::TryFrom, marker::PhantomData};
+use zeroize::Zeroize;
 
 /// Represents a prime-order cyclic group where the exponent is a finite field.
-/// `Exp` is the type used for the finite field operations
+/// `Exp` is the type used for the finite field operations.
+/// Implementations should not branch on or index memory with the value of an element
+/// for any operation other than `from_bytes` on malformed input.
 pub trait Group<Exp: ?Sized = Self> {
     /// The group size in bytes
     type Size: ArrayLength<u8>;
@@ -62,12 +89,39 @@ pub trait Group<Exp: ?Sized = Self> {
     fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self>
     where
         Self: Sized;
+    /// Return the group element from the specified bytes using the acceptance
+    /// rules of `strictness`. `Lenient` is the same as `from_bytes`.
+    fn from_bytes_with<B: AsRef<[u8]>>(value: B, strictness: Strictness) -> SharingResult<Self>
+    where
+        Self: Sized,
+    {
+        let value = value.as_ref();
+        let element = Self::from_bytes(value)?;
+        if strictness == Strictness::Strict {
+            if element.to_bytes().as_slice() != value {
+                return Err(SharingError::NonCanonicalEncoding);
+            }
+            if element.is_zero() {
+                return Err(SharingError::IdentityElement);
+            }
+            if !element.is_torsion_free() {
+                return Err(SharingError::InvalidSubgroup);
+            }
+        }
+        Ok(element)
+    }
     /// Return the element chosen uniformly at random using the user-provided RNG
     fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self;
     /// True iff this element is zero
     fn is_zero(&self) -> bool;
     /// True iff this element is not zero and less than the modulus
     fn is_valid(&self) -> bool;
+    /// True iff this element is in the prime order group.
+    /// Backends whose encoding can represent other elements, like points
+    /// on a curve with a cofactor, must override this.
+    fn is_torsion_free(&self) -> bool {
+        true
+    }
     /// Field negation
     fn negate(&mut self);
     /// Add another element to this element
@@ -78,19 +132,56 @@ pub trait Group<Exp: ?Sized = Self> {
     fn scalar_mul_assign(&mut self, rhs: &Exp);
     /// Serialize this element to bytes
     fn to_bytes(&self) -> GenericArray<u8, Self::Size>;
+    /// Overwrite an element that may hold secret data, like a Lagrange term of a
+    /// secret being combined. The default sets it to zero, secret types should
+    /// override it to use `Zeroize` so the write can't be optimized away
+    fn clear(&mut self)
+    where
+        Self: Sized,
+    {
+        *self = Self::zero();
+    }
 }
 
-/// Represents the finite field methods used by Sharing Schemes
-pub trait Field: Group {
+/// The rules used to accept encoded field and group elements.
+///
+/// Backends differ in what `from_bytes` accepts, some reduce scalars that are
+/// too large or accept points outside the prime order group. Systems where every
+/// party must accept exactly the same messages, like consensus protocols,
+/// should parse with `Strict`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Only accept the canonical encoding of a non-zero element in the prime order group
+    Strict,
+    /// Accept anything the backend can decode
+    #[default]
+    Lenient,
+}
+
+/// Represents the finite field methods used by Sharing Schemes.
+/// Field elements are usually secret so implementations must clear them in `zeroize`
+/// and should forward `Group::clear` to it
+pub trait Field: Group + Zeroize {
     /// Return the one element of the field, the multiplicative identity
     fn one() -> Self;
     /// Return the element from the given number
     fn from_usize(value: usize) -> Self;
-    /// Multiply the inverse of another element with this element
-    fn scalar_div_assign(&mut self, rhs: &Self);
+    /// Multiply the inverse of another element with this element.
+    /// Fails with `SharingError::DivisionByZero` and leaves this element unchanged if `rhs` is zero
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()>;
+    /// Return the multiplicative inverse of this element.
+    /// Fails with `SharingError::DivisionByZero` if this element is zero
+    fn invert(&self) -> SharingResult<Self>
+    where
+        Self: Sized,
+    {
+        let mut inverse = Self::one();
+        inverse.scalar_div_assign(self)?;
+        Ok(inverse)
+    }
 }
 
-/// The polynomial used for generating the shares
+/// The polynomial used for generating the shares, cleared on drop
 #[derive(Debug)]
 pub(crate) struct Polynomial<S: Field> {
     pub(crate) coefficients: Vec<S>,
@@ -129,6 +220,54 @@ impl<S: Field> Polynomial<S> {
         }
         out
     }
+
+    /// Compute the values of the polynomial at `start, start + 1, ..., start + count - 1`.
+    ///
+    /// The first `degree + 1` values are found with Horner's method and converted to
+    /// the forward differences of the polynomial. Every value after that only needs
+    /// `degree` additions instead of `degree` multiplications which makes generating
+    /// large numbers of shares at consecutive identifiers much cheaper.
+    pub fn evaluate_consecutive(&self, start: usize, count: usize) -> Vec<S> {
+        let degree = self.coefficients.len() - 1;
+
+        // p(start + i) for i in 0..=degree
+        let mut deltas = (0..=degree)
+            .take(count)
+            .map(|i| self.evaluate(&S::from_usize(start + i)))
+            .collect::<Vec<S>>();
+        if count <= degree + 1 {
+            return deltas;
+        }
+        // Convert in place to Delta^i p(start)
+        for k in 1..=degree {
+            for i in (k..=degree).rev() {
+                let (lo, hi) = deltas.split_at_mut(i);
+                hi[0].sub_assign(&lo[i - 1]);
+            }
+        }
+
+        let mut out = Vec::with_capacity(count);
+        for n in 0..count {
+            if n > 0 {
+                // Step every difference forward, the degree-th difference is constant
+                for i in 0..degree {
+                    let (lo, hi) = deltas.split_at_mut(i + 1);
+                    lo[i].add_assign(&hi[0]);
+                }
+            }
+            let mut y = S::zero();
+            y.add_assign(&deltas[0]);
+            out.push(y);
+        }
+        deltas.zeroize();
+        out
+    }
+}
+
+impl<S: Field> Drop for Polynomial<S> {
+    fn drop(&mut self) {
+        self.coefficients.zeroize();
+    }
 }
 
 /// A share verifier is used to provide integrity checking of shamir shares
@@ -143,36 +282,258 @@ impl<S: Field, R: Group<S>> ShareVerifier<S, R> {
     pub fn to_bytes(&self) -> GenericArray<u8, R::Size> {
         self.value.to_bytes()
     }
+
+    /// Deserialize the share verifier commitment using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        Ok(Self {
+            value: R::from_bytes_with(value, strictness)?,
+            phantom: PhantomData,
+        })
+    }
 }
 
 impl<S: Field, R: Group<S>> TryFrom<&[u8]> for ShareVerifier<S, R> {
     type Error = SharingError;
 
     fn try_from(value: &[u8]) -> SharingResult<Self> {
-        Ok(Self {
-            value: R::from_bytes(value)?,
-            phantom: PhantomData,
-        })
+        Self::from_bytes_with(value, Strictness::Lenient)
     }
 }
 
 impl<S: Field, R: Group<S>> Clone for ShareVerifier<S, R> {
     fn clone(&self) -> Self {
         Self {
-            value: R::from_bytes(&self.value.to_bytes()).unwrap(),
+            value: R::from_bytes(self.value.to_bytes()).unwrap(),
             phantom: PhantomData,
         }
     }
 }
 
+/// Evaluate the commitments in the exponent at `identifier`
+/// giving the verification point for the share with that identifier
+pub(crate) fn evaluate_commitments<S: Field, R: Group<S>>(
+    commitments: &[ShareVerifier<S, R>],
+    identifier: u32,
+) -> R {
+    let x = S::from_usize(identifier as usize);
+    let mut i = S::one();
+
+    // FUTURE: execute this sum of products
+    // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
+    // as a constant time operation using <https://cr.yp.to/papers/pippenger.pdf>
+    // or Guide to Elliptic Curve Cryptography book,
+    // "Algorithm 3.48 Simultaneous multiple point multiplication"
+    // without precomputing the addition but still reduces doublings
+
+    // c_0
+    let mut rhs = R::zero();
+    rhs.add_assign(&commitments[0].value);
+    for v in &commitments[1..] {
+        // i *= x
+        i.scalar_mul_assign(&x);
+
+        // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
+        let mut c = R::zero();
+        c.add_assign(&v.value);
+        c.scalar_mul_assign(&i);
+        rhs.add_assign(&c);
+    }
+    rhs
+}
+
+/// Call `$f::<Scalar>($args)` for the curve named by `$curve`,
+/// or return `Err($unsupported(curve))` when it isn't enabled
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+macro_rules! with_curve {
+    ($curve:expr, $f:ident($($args:expr),*), $unsupported:expr) => {
+        match $curve {
+            #[cfg(feature = "ed25519")]
+            "ed25519" => $f::<crate::ed25519::Ed25519Scalar>($($args),*),
+            #[cfg(feature = "ristretto")]
+            "ristretto25519" => $f::<crate::ristretto::Ristretto25519Scalar>($($args),*),
+            #[cfg(feature = "secp256k1")]
+            "secp256k1" => $f::<crate::secp256k1::Secp256k1Scalar>($($args),*),
+            #[cfg(feature = "secp256r1")]
+            "secp256r1" => $f::<crate::secp256r1::Secp256r1Scalar>($($args),*),
+            c => {
+                $(let _ = &$args;)*
+                Err($unsupported(c))
+            }
+        }
+    };
+}
+
+/// Ciphertext-policy attribute-based encryption over BLS12-381
+#[cfg(feature = "abe")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "abe")))]
+pub mod abe;
+/// Observer hooks for auditing sharing operations
+pub mod audit;
+/// One-shot secret backup and restore
+#[cfg(feature = "backup")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "backup")))]
+pub mod backup;
+/// BLS12-381 scalars and points
+#[cfg(feature = "bls12_381")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "bls12_381")))]
+pub mod bls12_381;
+/// Reproducible key generation ceremonies from recorded entropy
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "std")))]
+pub mod ceremony;
+/// Encrypted checkpoints of multi-round protocol sessions
+#[cfg(feature = "checkpoint")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "checkpoint")))]
+pub mod checkpoint;
+/// Combining many sets of shares from the same participants
+pub mod combine;
+/// Statistical timing tests of the constant time paths
+#[cfg(feature = "ct_audit")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ct_audit")))]
+pub mod ct_audit;
+/// Split-custody signing wallets from DKG, FROST, refresh and backup
+#[cfg(feature = "custody")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "custody")))]
+pub mod custody;
+/// Dealer-less distributed key generation
+pub mod dkg;
+/// Chaum-Pedersen proofs of discrete log equality
+pub mod dleq;
+/// Sharing of Ed25519 secret keys
+#[cfg(feature = "ed25519")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ed25519")))]
+pub mod ed25519;
+/// ElGamal encryption with rerandomizable ciphertexts
+pub mod elgamal;
+/// Entropy sources for one-shot operations and air-gapped key generation
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "std")))]
+pub mod entropy;
 /// Sharing Errors and Results
 pub mod error;
+/// Publicly verifiable escrow of shares with escrow agents
+pub mod escrow;
 /// Feldman's verifiable secret sharing scheme
 pub mod feldman;
+/// C bindings for splitting and combining secrets
+#[cfg(feature = "ffi")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ffi")))]
+pub mod ffi;
+/// Scalar field arithmetic and FFTs for BLS12-381 and BN254
+pub mod field;
+/// The allow-list of algorithms for FIPS mode
+pub mod fips;
+/// FROST threshold Schnorr signatures over Ed25519 and Ristretto255
+#[cfg(feature = "frost")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "frost")))]
+pub mod frost;
+/// Mutated protocol sessions for fuzzing state machines
+#[cfg(feature = "fuzz")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "fuzz")))]
+pub mod fuzz;
+/// Shamir secret sharing of byte strings over GF(2^8)
+pub mod gf256;
+/// Hashing to curves and scalar fields from RFC 9380
+pub mod hash_to_curve;
+/// Hierarchical deterministic key derivation
+#[cfg(feature = "hd")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "hd")))]
+pub mod hd;
+/// Identity-based encryption over BLS12-381
+#[cfg(feature = "ibe")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ibe")))]
+pub mod ibe;
+/// Polynomial multiplication, division, evaluation and interpolation with NTTs
+pub mod ntt;
+/// Oblivious pseudorandom functions from RFC 9497
+#[cfg(feature = "oprf")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "oprf")))]
+pub mod oprf;
 /// Pedersen's verifiable secret sharing scheme
 pub mod pedersen;
+/// Public-key encryption with keyword search over BLS12-381
+#[cfg(feature = "peks")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "peks")))]
+pub mod peks;
+/// Weighted and hierarchical access structures
+pub mod policy;
+/// Proofs of share possession
+pub mod possession;
+/// Umbral-style threshold proxy re-encryption
+#[cfg(feature = "pre")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "pre")))]
+pub mod pre;
+/// Signed receipts of the shares a dealer distributes
+pub mod receipt;
+/// Encryption of shares to recipient public keys
+#[cfg(feature = "recipient")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "recipient")))]
+pub mod recipient;
+/// Auditable recordings of the public messages of protocol sessions
+#[cfg(feature = "record")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "record")))]
+pub mod record;
+/// Proactive share refresh and resharing to a new access structure
+pub mod refresh;
+/// Ristretto255 scalars and points
+#[cfg(feature = "ristretto")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "ristretto")))]
+pub mod ristretto;
+/// Secp256k1 backend and distributed key generation
+#[cfg(feature = "secp256k1")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "secp256k1")))]
+pub mod secp256k1;
+/// NIST P-256 scalars and points
+#[cfg(feature = "secp256r1")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "secp256r1")))]
+pub mod secp256r1;
 /// Shamir secret sharing scheme
 pub mod shamir;
+/// Permutation commitments and proofs of shuffles
+pub mod shuffle;
+/// Deterministic simulated networks for testing multi-party protocols
+#[cfg(feature = "sim")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "sim")))]
+pub mod sim;
+/// Stealth addresses and key blinding
+pub mod stealth;
+/// Versioned share encoding with curve and threshold tags
+#[cfg(feature = "tagged")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "tagged")))]
+pub mod tagged;
+/// Threshold BLS signatures over BLS12-381
+#[cfg(feature = "threshold_bls")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "threshold_bls")))]
+pub mod threshold_bls;
+/// Threshold ECDSA signing over secp256k1 and P-256
+#[cfg(feature = "threshold_ecdsa")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "threshold_ecdsa")))]
+pub mod threshold_ecdsa;
+
+#[cfg(feature = "threshold_oprf")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "threshold_oprf")))]
+pub mod threshold_oprf;
+#[cfg(feature = "threshold_vrf")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "threshold_vrf")))]
+pub mod threshold_vrf;
+/// Encryption to the future epochs of a threshold committee
+#[cfg(feature = "timelock")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "timelock")))]
+pub mod timelock;
+/// Transcripts for Fiat-Shamir challenges
+pub mod transcript;
+/// Deterministic test vectors
+#[cfg(feature = "test_vectors")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "test_vectors")))]
+pub mod vectors;
+/// JavaScript bindings for WebAssembly
+#[cfg(feature = "wasm")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "wasm")))]
+pub mod wasm;
+/// Passphrase-wrapped shares for storage at rest
+#[cfg(feature = "wrap")]
+#[cfg_attr(feature = "nightly", doc(cfg(feature = "wrap")))]
+pub mod wrap;
 
 /// Provide a suite of tests for implementers to run for their implementations
 #[cfg(feature = "impl_tests")]
@@ -0,0 +1,297 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Polynomial arithmetic over the fields of [`field`](crate::field) with number
+//! theoretic transforms.
+//!
+//! Polynomials are slices of coefficients, lowest degree first. [`multiply`] uses the
+//! NTT of a [`Domain`] for a product in `O(n log n)` and [`divide`] inverts the divisor
+//! as a power series with Newton's iteration to divide in the same time. Small inputs
+//! use the schoolbook algorithms, which are faster below a few dozen coefficients.
+//!
+//! A [`SubproductTree`] over `n` arbitrary points, like the identifiers of a large
+//! committee, evaluates a polynomial at all of them and interpolates the polynomial
+//! through values at them in `O(n log^2 n)` instead of the `O(n^2)` of Horner's method
+//! and Lagrange interpolation. The tree is built once and reused for every
+//! polynomial over the same points, such as every dealer's polynomial in a PVSS or the
+//! shares being repaired.
+//!
+//! The algorithms branch only on lengths, besides [`divide`] dropping zero leading
+//! coefficients of the divisor. Copies of the inputs are cleared before returning.
+
+use super::{
+    error::{SharingError, SharingResult},
+    field::{batch_invert, Domain, FieldParams, Fp},
+    Field, Group,
+};
+use alloc::{vec, vec::Vec};
+use zeroize::Zeroize;
+
+/// Below this many coefficients in the smaller input the schoolbook algorithms are faster
+const SCHOOLBOOK_THRESHOLD: usize = 32;
+
+/// The product of two polynomials, with `a.len() + b.len() - 1` coefficients, or none if
+/// either is empty. Fails with `SharingError::FieldDomainSize` if the product is larger
+/// than the field's largest domain
+pub fn multiply<P: FieldParams>(a: &[Fp<P>], b: &[Fp<P>]) -> SharingResult<Vec<Fp<P>>> {
+    if a.is_empty() || b.is_empty() {
+        return Ok(Vec::new());
+    }
+    let len = a.len() + b.len() - 1;
+    if a.len().min(b.len()) <= SCHOOLBOOK_THRESHOLD {
+        let mut product = vec![Fp::zero(); len];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                product[i + j] += *x * *y;
+            }
+        }
+        return Ok(product);
+    }
+
+    let domain = Domain::<P>::new(len.next_power_of_two())?;
+    let mut fa = padded(a, domain.size());
+    let mut fb = padded(b, domain.size());
+    domain.fft(&mut fa)?;
+    domain.fft(&mut fb)?;
+    fa.iter_mut().zip(fb.iter()).for_each(|(x, y)| *x *= *y);
+    domain.ifft(&mut fa)?;
+    fb.zeroize();
+    fa.truncate(len);
+    Ok(fa)
+}
+
+/// The quotient and the remainder of a division
+pub type QuotientRemainder<P> = (Vec<Fp<P>>, Vec<Fp<P>>);
+
+/// The quotient and remainder of dividing `a` by `b`. The remainder has one coefficient
+/// fewer than `b` without its zero leading coefficients, and the quotient is empty if
+/// `a` is shorter than that. Fails with `SharingError::DivisionByZero` if `b` is zero
+pub fn divide<P: FieldParams>(a: &[Fp<P>], b: &[Fp<P>]) -> SharingResult<QuotientRemainder<P>> {
+    let b = &b[..b.len() - b.iter().rev().take_while(|c| c.is_zero()).count()];
+    // The last coefficient is not zero
+    let lead_inverse = b
+        .last()
+        .ok_or(SharingError::DivisionByZero)?
+        .inverse()
+        .unwrap();
+    if a.len() < b.len() {
+        let mut r = a.to_vec();
+        r.resize(b.len() - 1, Fp::zero());
+        return Ok((Vec::new(), r));
+    }
+
+    let q_len = a.len() - b.len() + 1;
+    let quotient = if q_len.min(b.len()) <= SCHOOLBOOK_THRESHOLD {
+        let mut r = a.to_vec();
+        let mut q = vec![Fp::zero(); q_len];
+        for i in (0..q_len).rev() {
+            let c = r[i + b.len() - 1] * lead_inverse;
+            for (j, d) in b.iter().enumerate() {
+                r[i + j] -= c * *d;
+            }
+            q[i] = c;
+        }
+        r.zeroize();
+        q
+    } else {
+        // The reversed quotient is the reversed `a` over the reversed `b` modulo x^q_len
+        let mut ra = a.iter().rev().take(q_len).copied().collect::<Vec<_>>();
+        let rb = b.iter().rev().copied().collect::<Vec<_>>();
+        let inverse = series_inverse(&rb, q_len)?;
+        let mut q = multiply(&ra, &inverse)?;
+        ra.zeroize();
+        q.truncate(q_len);
+        q.reverse();
+        q
+    };
+
+    let mut product = multiply(&quotient, b)?;
+    let remainder = a[..b.len() - 1]
+        .iter()
+        .zip(product.iter())
+        .map(|(x, y)| *x - *y)
+        .collect();
+    product.zeroize();
+    Ok((quotient, remainder))
+}
+
+/// The value of the polynomial at `x` with Horner's method
+pub fn evaluate<P: FieldParams>(coefficients: &[Fp<P>], x: &Fp<P>) -> Fp<P> {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fp::zero(), |acc, c| acc * *x + *c)
+}
+
+/// The inverse of `f` as a power series modulo x^len. `f[0]` must not be zero
+fn series_inverse<P: FieldParams>(f: &[Fp<P>], len: usize) -> SharingResult<Vec<Fp<P>>> {
+    let mut g = vec![f[0].inverse().ok_or(SharingError::DivisionByZero)?];
+    let mut k = 1;
+    while k < len {
+        k = (2 * k).min(len);
+        // g = g * (2 - f * g) mod x^k
+        let mut fg = multiply(&f[..f.len().min(k)], &g)?;
+        fg.truncate(k);
+        fg.iter_mut().for_each(|c| *c = -*c);
+        fg[0] += Fp::from_u64(2);
+        let mut next = multiply(&g, &fg)?;
+        next.truncate(k);
+        g = next;
+    }
+    Ok(g)
+}
+
+/// `values` followed by zeros up to `len`
+fn padded<P: FieldParams>(values: &[Fp<P>], len: usize) -> Vec<Fp<P>> {
+    let mut p = Vec::with_capacity(len);
+    p.extend_from_slice(values);
+    p.resize(len, Fp::zero());
+    p
+}
+
+/// The products of `x - x_i` over ever larger groups of the points, to evaluate and
+/// interpolate at all of them at once
+#[derive(Clone, Debug)]
+pub struct SubproductTree<P: FieldParams> {
+    /// `levels[0]` are the linear factors and every node of the next level is
+    /// the product of two nodes, or the last node when there is an odd number
+    levels: Vec<Vec<Vec<Fp<P>>>>,
+    /// The inverses of the derivative of the root at the points, the Lagrange weights
+    weights: Vec<Fp<P>>,
+}
+
+impl<P: FieldParams> SubproductTree<P> {
+    /// Build the tree of `points`.
+    /// Fails with `SharingError::ShareDuplicateIdentifier` if a point repeats
+    pub fn new(points: &[Fp<P>]) -> SharingResult<Self> {
+        let mut levels = vec![points
+            .iter()
+            .map(|x| vec![-*x, Fp::one()])
+            .collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => multiply(a, b),
+                    [a] => Ok(a.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<SharingResult<Vec<_>>>()?;
+            levels.push(next);
+        }
+        let mut tree = Self {
+            levels,
+            weights: Vec::new(),
+        };
+
+        let root = tree.vanishing();
+        let derivative = root
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, c)| Fp::from_u64(i as u64) * *c)
+            .collect::<Vec<_>>();
+        let mut weights = tree.evaluate(&derivative)?;
+        batch_invert(&mut weights).map_err(|_| SharingError::ShareDuplicateIdentifier)?;
+        tree.weights = weights;
+        Ok(tree)
+    }
+
+    /// The number of points
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// True if the tree has no points
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// The polynomial vanishing at every point, the product of `x - x_i`
+    pub fn vanishing(&self) -> Vec<Fp<P>> {
+        self.levels
+            .last()
+            .and_then(|l| l.first())
+            .cloned()
+            .unwrap_or_else(|| vec![Fp::one()])
+    }
+
+    /// The values of the polynomial at the points in their order
+    pub fn evaluate(&self, coefficients: &[Fp<P>]) -> SharingResult<Vec<Fp<P>>> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Reduce the polynomial modulo every node from the root down to the leaves
+        let mut remainders = vec![divide(coefficients, &self.vanishing())?.1];
+        for level in self.levels.iter().rev().skip(1) {
+            let mut next = Vec::with_capacity(level.len());
+            for (pair, r) in level.chunks(2).zip(remainders.iter_mut()) {
+                match pair {
+                    [a, b] => {
+                        next.push(divide(r, a)?.1);
+                        next.push(divide(r, b)?.1);
+                    }
+                    [_] => next.push(r.clone()),
+                    _ => unreachable!(),
+                }
+                r.zeroize();
+            }
+            remainders = next;
+        }
+        Ok(remainders.into_iter().map(|r| r[0]).collect())
+    }
+
+    /// The polynomial of degree less than the number of points with the given values
+    /// at the points. Fails with `SharingError::PolynomialValueCount` unless there is
+    /// one value per point
+    pub fn interpolate(&self, values: &[Fp<P>]) -> SharingResult<Vec<Fp<P>>> {
+        if values.len() != self.len() {
+            return Err(SharingError::PolynomialValueCount(self.len(), values.len()));
+        }
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Combine `sum_i y_i / M'(x_i) * M(x) / (x - x_i)` from the leaves up, where a
+        // node is the sum of each child times the other child's product
+        let mut sums = values
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(y, w)| vec![*y * *w])
+            .collect::<Vec<_>>();
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            for (pair, sum) in level.chunks(2).zip(sums.chunks_mut(2)) {
+                match (pair, sum) {
+                    ([a, b], [sa, sb]) => {
+                        let mut left = multiply(sa, b)?;
+                        let mut right = multiply(sb, a)?;
+                        left.iter_mut()
+                            .zip(right.iter())
+                            .for_each(|(l, r)| *l += *r);
+                        right.zeroize();
+                        sa.zeroize();
+                        sb.zeroize();
+                        next.push(left);
+                    }
+                    ([_], [s]) => next.push(core::mem::take(s)),
+                    _ => unreachable!(),
+                }
+            }
+            sums = next;
+        }
+        let mut result = sums.pop().unwrap();
+        result.resize(self.len(), Fp::zero());
+        Ok(result)
+    }
+}
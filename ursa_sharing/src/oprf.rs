@@ -0,0 +1,843 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Oblivious pseudorandom functions as in RFC 9497, over ristretto255 with SHA-512
+//! and P-256 with SHA-256.
+//!
+//! A server holding a [`ServerKey`] evaluates a PRF on the client's input without
+//! learning the input or the output. The client blinds its input, the server
+//! evaluates the blinded element and the client unblinds and hashes the result:
+//!
+//! 1. `blind` hashes the input to the group and returns the [`Blind`] to keep and
+//!    the blinded element to send.
+//! 2. `blind_evaluate` on the server returns the evaluated element, along with a
+//!    [`Proof`] in the verifiable modes.
+//! 3. `finalize` checks the proof and returns the PRF output. A server can compute the
+//!    same output for inputs it knows with `evaluate`.
+//!
+//! The three modes of the RFC each have their own client and server:
+//!
+//! * [`OprfClient`] and [`OprfServer`]: the base protocol.
+//! * [`VoprfClient`] and [`VoprfServer`]: the server proves the evaluation used the
+//!   key of its public key, so the client can tell it isn't being tracked with a
+//!   key used only for it.
+//! * [`PoprfClient`] and [`PoprfServer`]: verifiable, and both sides also bind a public
+//!   `info` string to the output, such as a date or a tenant.
+//!
+//! The verifiable servers evaluate batches of blinded elements with one proof.
+//! The hashes and encodings are the RFC's, so either side can interoperate with
+//! other implementations of the ciphersuites. Messages should be parsed with
+//! `Strictness::Strict`, which rejects the identity and non-canonical encodings
+//! like the RFC's `DeserializeElement`.
+
+use super::{
+    error::{SharingError, SharingResult},
+    hash_to_curve::{HashToCurve, HashToScalar},
+    Field, Group,
+};
+use alloc::vec::Vec;
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The protocol variants of RFC 9497
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// The base OPRF
+    Oprf = 0,
+    /// The verifiable OPRF
+    Voprf = 1,
+    /// The partially oblivious PRF with public info
+    Poprf = 2,
+}
+
+/// A prime order group with an RFC 9497 ciphersuite, hashing to the group and
+/// scalars with its RFC 9380 suite
+pub trait OprfGroup<S: HashToScalar>: HashToCurve<S> {
+    /// The ciphersuite identifier in the context string
+    const IDENTIFIER: &'static [u8];
+    /// The standard base point
+    fn generator() -> Self;
+    /// The ciphersuite's hash of the concatenated `input`
+    fn hash(input: &[&[u8]]) -> Vec<u8>;
+}
+
+#[cfg(feature = "ristretto")]
+impl OprfGroup<crate::ristretto::Ristretto25519Scalar> for crate::ristretto::Ristretto25519Point {
+    const IDENTIFIER: &'static [u8] = b"ristretto255-SHA512";
+
+    fn generator() -> Self {
+        crate::ristretto::Ristretto25519Point::generator()
+    }
+
+    fn hash(input: &[&[u8]]) -> Vec<u8> {
+        digest::<sha2::Sha512>(input)
+    }
+}
+
+#[cfg(feature = "secp256r1")]
+impl OprfGroup<crate::secp256r1::Secp256r1Scalar> for crate::secp256r1::Secp256r1Point {
+    const IDENTIFIER: &'static [u8] = b"P256-SHA256";
+
+    fn generator() -> Self {
+        crate::secp256r1::Secp256r1Point::generator()
+    }
+
+    fn hash(input: &[&[u8]]) -> Vec<u8> {
+        digest::<sha2::Sha256>(input)
+    }
+}
+
+#[cfg(any(feature = "ristretto", feature = "secp256r1"))]
+fn digest<D: sha2::Digest>(input: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::new();
+    for i in input {
+        hasher.input(i);
+    }
+    hasher.result().to_vec()
+}
+
+/// `"OPRFV1-" || mode || "-" || identifier`
+fn context<S: HashToScalar, R: OprfGroup<S>>(mode: Mode) -> Vec<u8> {
+    let mut c = b"OPRFV1-".to_vec();
+    c.push(mode as u8);
+    c.push(b'-');
+    c.extend_from_slice(R::IDENTIFIER);
+    c
+}
+
+fn dst<S: HashToScalar, R: OprfGroup<S>>(prefix: &[u8], mode: Mode) -> Vec<u8> {
+    let mut d = prefix.to_vec();
+    d.extend_from_slice(&context::<S, R>(mode));
+    d
+}
+
+fn hash_to_group<S: HashToScalar, R: OprfGroup<S>>(mode: Mode, input: &[u8]) -> SharingResult<R> {
+    let element = R::hash_to_curve(input, &dst::<S, R>(b"HashToGroup-", mode));
+    if element.is_zero() {
+        return Err(SharingError::OprfInvalidInput);
+    }
+    Ok(element)
+}
+
+fn hash_to_scalar<S: HashToScalar, R: OprfGroup<S>>(mode: Mode, input: &[&[u8]]) -> S {
+    hash_concat(input, &dst::<S, R>(b"HashToScalar-", mode))
+}
+
+/// Hash the concatenated `input`, which may hold secrets, to a scalar
+fn hash_concat<S: HashToScalar>(input: &[&[u8]], dst: &[u8]) -> S {
+    let mut message = input.concat();
+    let r = S::hash_to_scalar(&message, dst);
+    message.zeroize();
+    r
+}
+
+/// The key of a server, the secret `k` and public key `g^k`
+pub struct ServerKey<S: HashToScalar, R: OprfGroup<S>> {
+    secret: S,
+    public: R,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> ServerKey<S, R> {
+    /// Generate a new key
+    pub fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self::from_secret(random_scalar(rng))
+    }
+
+    /// Derive the key for `mode` from a seed of at least 32 uniform bytes and public
+    /// `info`, `DeriveKeyPair` from the RFC
+    pub fn derive(mode: Mode, seed: &[u8], info: &[u8]) -> SharingResult<Self> {
+        if info.len() > u16::MAX as usize {
+            return Err(SharingError::OprfInvalidInput);
+        }
+        let dst = dst::<S, R>(b"DeriveKeyPair", mode);
+        let info_len = (info.len() as u16).to_be_bytes();
+        for counter in 0..=255u8 {
+            let secret: S = hash_concat(&[seed, &info_len, info, &[counter]], &dst);
+            if !secret.is_zero() {
+                return Ok(Self::from_secret(secret));
+            }
+        }
+        Err(SharingError::OprfDeriveKeyPair)
+    }
+
+    /// The public key `g^k`
+    pub fn public_key(&self) -> &R {
+        &self.public
+    }
+
+    /// Serialize the secret
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.secret.to_bytes().to_vec()
+    }
+
+    fn from_secret(secret: S) -> Self {
+        let public = mul(&R::generator(), &secret);
+        Self { secret, public }
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> TryFrom<&[u8]> for ServerKey<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() != S::Size::to_usize() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let secret = read_scalar::<S>(data)?;
+        if !secret.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        Ok(Self::from_secret(secret))
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Debug for ServerKey<S, R> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "ServerKey(..)")
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Drop for ServerKey<S, R> {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The client's secret blinding factor for one input, needed to finalize it
+pub struct Blind<S: Field>(S);
+
+impl<S: Field> Debug for Blind<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Blind(..)")
+    }
+}
+
+impl<S: Field> Drop for Blind<S> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// A proof that evaluated elements used the key of a public key,
+/// a batched discrete log equality proof
+#[derive(Debug)]
+pub struct Proof<S: Field> {
+    challenge: S,
+    response: S,
+}
+
+impl<S: Field> Proof<S> {
+    /// Serialize as `challenge | response`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.challenge.to_bytes().to_vec();
+        o.extend_from_slice(&self.response.to_bytes());
+        o
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for Proof<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        if data.len() != 2 * s_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            challenge: read_scalar(&data[..s_size])?,
+            response: read_scalar(&data[s_size..])?,
+        })
+    }
+}
+
+impl<S: Field> Clone for Proof<S> {
+    fn clone(&self) -> Self {
+        Self {
+            challenge: copy(&self.challenge),
+            response: copy(&self.response),
+        }
+    }
+}
+
+/// The client of the base OPRF
+#[derive(Debug)]
+pub struct OprfClient<S: HashToScalar, R: OprfGroup<S>> {
+    _group: PhantomData<(S, R)>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> OprfClient<S, R> {
+    /// Create a client
+    pub fn new() -> Self {
+        Self {
+            _group: PhantomData,
+        }
+    }
+
+    /// Blind `input`, returning the blind to keep and the blinded element to send.
+    /// Fails with `OprfInvalidInput` if the input hashes to the identity
+    pub fn blind(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        input: &[u8],
+    ) -> SharingResult<(Blind<S>, R)> {
+        blind::<S, R>(rng, Mode::Oprf, input)
+    }
+
+    /// Unblind the server's evaluation of `input` and return the PRF output
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        blind: &Blind<S>,
+        evaluated: &R,
+    ) -> SharingResult<Vec<u8>> {
+        finalize(input, None, blind, evaluated)
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Default for OprfClient<S, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The server of the base OPRF
+#[derive(Debug)]
+pub struct OprfServer<S: HashToScalar, R: OprfGroup<S>> {
+    key: ServerKey<S, R>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> OprfServer<S, R> {
+    /// Create a server with `key`
+    pub fn new(key: ServerKey<S, R>) -> Self {
+        Self { key }
+    }
+
+    /// Evaluate a client's blinded element
+    pub fn blind_evaluate(&self, blinded: &R) -> R {
+        mul(blinded, &self.key.secret)
+    }
+
+    /// The PRF output for an `input` known to the server
+    pub fn evaluate(&self, input: &[u8]) -> SharingResult<Vec<u8>> {
+        let element = hash_to_group::<S, R>(Mode::Oprf, input)?;
+        output(input, None, &mul(&element, &self.key.secret))
+    }
+}
+
+/// The client of the verifiable OPRF
+#[derive(Debug)]
+pub struct VoprfClient<S: HashToScalar, R: OprfGroup<S>> {
+    public_key: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> VoprfClient<S, R> {
+    /// Create a client for the server with `public_key`
+    pub fn new(public_key: R) -> Self {
+        Self {
+            public_key,
+            _scalar: PhantomData,
+        }
+    }
+
+    /// Blind `input`, returning the blind to keep and the blinded element to send.
+    /// Fails with `OprfInvalidInput` if the input hashes to the identity
+    pub fn blind(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        input: &[u8],
+    ) -> SharingResult<(Blind<S>, R)> {
+        blind::<S, R>(rng, Mode::Voprf, input)
+    }
+
+    /// Check the proof of the server's evaluation of `input`, unblind it and
+    /// return the PRF output. Fails with `OprfProofInvalid` if the proof doesn't verify
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        blind: &Blind<S>,
+        evaluated: &R,
+        blinded: &R,
+        proof: &Proof<S>,
+    ) -> SharingResult<Vec<u8>> {
+        Ok(self
+            .finalize_batch(
+                &[input],
+                core::slice::from_ref(blind),
+                core::slice::from_ref(evaluated),
+                core::slice::from_ref(blinded),
+                proof,
+            )?
+            .remove(0))
+    }
+
+    /// Check the proof of a batch evaluation and return the PRF output of each input.
+    /// Fails with `OprfBatchInvalid` if the batch is empty or the lengths differ
+    pub fn finalize_batch(
+        &self,
+        inputs: &[&[u8]],
+        blinds: &[Blind<S>],
+        evaluated: &[R],
+        blinded: &[R],
+        proof: &Proof<S>,
+    ) -> SharingResult<Vec<Vec<u8>>> {
+        check_batch(
+            inputs.len(),
+            &[blinds.len(), evaluated.len(), blinded.len()],
+        )?;
+        verify_proof(Mode::Voprf, &self.public_key, blinded, evaluated, proof)?;
+        inputs
+            .iter()
+            .zip(blinds.iter().zip(evaluated.iter()))
+            .map(|(input, (blind, evaluated))| finalize(input, None, blind, evaluated))
+            .collect()
+    }
+}
+
+/// The server of the verifiable OPRF
+#[derive(Debug)]
+pub struct VoprfServer<S: HashToScalar, R: OprfGroup<S>> {
+    key: ServerKey<S, R>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> VoprfServer<S, R> {
+    /// Create a server with `key`. The key should be derived for `Mode::Voprf`
+    pub fn new(key: ServerKey<S, R>) -> Self {
+        Self { key }
+    }
+
+    /// The public key clients verify evaluations with
+    pub fn public_key(&self) -> &R {
+        &self.key.public
+    }
+
+    /// Evaluate a client's blinded element and prove it used this server's key
+    pub fn blind_evaluate(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        blinded: &R,
+    ) -> (R, Proof<S>) {
+        let evaluated = mul(blinded, &self.key.secret);
+        let proof = generate_proof(
+            rng,
+            Mode::Voprf,
+            &self.key.secret,
+            &self.key.public,
+            core::slice::from_ref(blinded),
+            core::slice::from_ref(&evaluated),
+        );
+        (evaluated, proof)
+    }
+
+    /// Evaluate many blinded elements with one proof.
+    /// Fails with `OprfBatchInvalid` if there are none
+    pub fn blind_evaluate_batch(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        blinded: &[R],
+    ) -> SharingResult<(Vec<R>, Proof<S>)> {
+        check_batch(blinded.len(), &[])?;
+        let evaluated = blinded
+            .iter()
+            .map(|b| mul(b, &self.key.secret))
+            .collect::<Vec<_>>();
+        let proof = generate_proof(
+            rng,
+            Mode::Voprf,
+            &self.key.secret,
+            &self.key.public,
+            blinded,
+            &evaluated,
+        );
+        Ok((evaluated, proof))
+    }
+
+    /// The PRF output for an `input` known to the server
+    pub fn evaluate(&self, input: &[u8]) -> SharingResult<Vec<u8>> {
+        let element = hash_to_group::<S, R>(Mode::Voprf, input)?;
+        output(input, None, &mul(&element, &self.key.secret))
+    }
+}
+
+/// The client of the partially oblivious PRF
+#[derive(Debug)]
+pub struct PoprfClient<S: HashToScalar, R: OprfGroup<S>> {
+    public_key: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> PoprfClient<S, R> {
+    /// Create a client for the server with `public_key`
+    pub fn new(public_key: R) -> Self {
+        Self {
+            public_key,
+            _scalar: PhantomData,
+        }
+    }
+
+    /// Blind `input` to be evaluated with `info`, returning the blind to keep and the
+    /// blinded element to send. Fails with `OprfInvalidInput` if the input hashes to the
+    /// identity and `OprfInvalidInfo` if `info` tweaks the public key to the identity
+    pub fn blind(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        input: &[u8],
+        info: &[u8],
+    ) -> SharingResult<(Blind<S>, R)> {
+        self.tweaked_key(info)?;
+        blind::<S, R>(rng, Mode::Poprf, input)
+    }
+
+    /// Check the proof of the server's evaluation of `input` with `info`, unblind it and
+    /// return the PRF output. Fails with `OprfProofInvalid` if the proof doesn't verify
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        info: &[u8],
+        blind: &Blind<S>,
+        evaluated: &R,
+        blinded: &R,
+        proof: &Proof<S>,
+    ) -> SharingResult<Vec<u8>> {
+        Ok(self
+            .finalize_batch(
+                &[input],
+                info,
+                core::slice::from_ref(blind),
+                core::slice::from_ref(evaluated),
+                core::slice::from_ref(blinded),
+                proof,
+            )?
+            .remove(0))
+    }
+
+    /// Check the proof of a batch evaluation with `info` and return the PRF output of
+    /// each input. Fails with `OprfBatchInvalid` if the batch is empty or the lengths differ
+    pub fn finalize_batch(
+        &self,
+        inputs: &[&[u8]],
+        info: &[u8],
+        blinds: &[Blind<S>],
+        evaluated: &[R],
+        blinded: &[R],
+        proof: &Proof<S>,
+    ) -> SharingResult<Vec<Vec<u8>>> {
+        check_batch(
+            inputs.len(),
+            &[blinds.len(), evaluated.len(), blinded.len()],
+        )?;
+        let tweaked_key = self.tweaked_key(info)?;
+        // The server proves blinded = evaluated^t for the tweaked key g^t
+        verify_proof(Mode::Poprf, &tweaked_key, evaluated, blinded, proof)?;
+        inputs
+            .iter()
+            .zip(blinds.iter().zip(evaluated.iter()))
+            .map(|(input, (blind, evaluated))| finalize(input, Some(info), blind, evaluated))
+            .collect()
+    }
+
+    /// `g^m * public_key`
+    fn tweaked_key(&self, info: &[u8]) -> SharingResult<R> {
+        let mut key = mul(&R::generator(), &info_scalar::<S, R>(info)?);
+        key.add_assign(&self.public_key);
+        if key.is_zero() {
+            return Err(SharingError::OprfInvalidInfo);
+        }
+        Ok(key)
+    }
+}
+
+/// The server of the partially oblivious PRF
+#[derive(Debug)]
+pub struct PoprfServer<S: HashToScalar, R: OprfGroup<S>> {
+    key: ServerKey<S, R>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> PoprfServer<S, R> {
+    /// Create a server with `key`. The key should be derived for `Mode::Poprf`
+    pub fn new(key: ServerKey<S, R>) -> Self {
+        Self { key }
+    }
+
+    /// The public key clients verify evaluations with
+    pub fn public_key(&self) -> &R {
+        &self.key.public
+    }
+
+    /// Evaluate a client's blinded element with `info` and prove it used this
+    /// server's key. Fails with `OprfInvalidInfo` if `info` tweaks the key to zero
+    pub fn blind_evaluate(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        blinded: &R,
+        info: &[u8],
+    ) -> SharingResult<(R, Proof<S>)> {
+        let (mut evaluated, proof) =
+            self.blind_evaluate_batch(rng, core::slice::from_ref(blinded), info)?;
+        Ok((evaluated.remove(0), proof))
+    }
+
+    /// Evaluate many blinded elements with `info` and one proof.
+    /// Fails with `OprfBatchInvalid` if there are none
+    pub fn blind_evaluate_batch(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        blinded: &[R],
+        info: &[u8],
+    ) -> SharingResult<(Vec<R>, Proof<S>)> {
+        check_batch(blinded.len(), &[])?;
+        let mut t = self.tweaked_secret(info)?;
+        let result = t.invert().map(|mut inverse| {
+            let evaluated = blinded.iter().map(|b| mul(b, &inverse)).collect::<Vec<_>>();
+            inverse.zeroize();
+            let tweaked_key = mul(&R::generator(), &t);
+            let proof = generate_proof(rng, Mode::Poprf, &t, &tweaked_key, &evaluated, blinded);
+            (evaluated, proof)
+        });
+        t.zeroize();
+        result
+    }
+
+    /// The PRF output for an `input` known to the server with `info`
+    pub fn evaluate(&self, input: &[u8], info: &[u8]) -> SharingResult<Vec<u8>> {
+        let element = hash_to_group::<S, R>(Mode::Poprf, input)?;
+        let mut t = self.tweaked_secret(info)?;
+        let inverse = t.invert();
+        t.zeroize();
+        let mut inverse = inverse?;
+        let evaluated = mul(&element, &inverse);
+        inverse.zeroize();
+        output(input, Some(info), &evaluated)
+    }
+
+    /// `k + m`
+    fn tweaked_secret(&self, info: &[u8]) -> SharingResult<S> {
+        let mut t = info_scalar::<S, R>(info)?;
+        t.add_assign(&self.key.secret);
+        if t.is_zero() {
+            return Err(SharingError::OprfInvalidInfo);
+        }
+        Ok(t)
+    }
+}
+
+/// `m = HashToScalar("Info" || len(info) || info)`
+fn info_scalar<S: HashToScalar, R: OprfGroup<S>>(info: &[u8]) -> SharingResult<S> {
+    Ok(hash_to_scalar::<S, R>(
+        Mode::Poprf,
+        &[b"Info", &length(info)?, info],
+    ))
+}
+
+fn random_scalar<S: Field>(rng: &mut (impl RngCore + CryptoRng)) -> S {
+    loop {
+        let s = S::random(rng);
+        if s.is_valid() {
+            return s;
+        }
+    }
+}
+
+pub(crate) fn blind<S: HashToScalar, R: OprfGroup<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    mode: Mode,
+    input: &[u8],
+) -> SharingResult<(Blind<S>, R)> {
+    let element = hash_to_group::<S, R>(mode, input)?;
+    let blind = Blind(random_scalar(rng));
+    let blinded = mul(&element, &blind.0);
+    Ok((blind, blinded))
+}
+
+pub(crate) fn finalize<S: HashToScalar, R: OprfGroup<S>>(
+    input: &[u8],
+    info: Option<&[u8]>,
+    blind: &Blind<S>,
+    evaluated: &R,
+) -> SharingResult<Vec<u8>> {
+    let mut inverse = blind.0.invert()?;
+    let unblinded = mul(evaluated, &inverse);
+    inverse.zeroize();
+    output(input, info, &unblinded)
+}
+
+/// `Hash(len(input) || input || [len(info) || info] || len(element) || element || "Finalize")`
+fn output<S: HashToScalar, R: OprfGroup<S>>(
+    input: &[u8],
+    info: Option<&[u8]>,
+    element: &R,
+) -> SharingResult<Vec<u8>> {
+    let element = element.to_bytes();
+    let input_len = length(input)?;
+    let element_len = length(&element)?;
+    Ok(match info {
+        Some(info) => R::hash(&[
+            &input_len,
+            input,
+            &length(info)?,
+            info,
+            &element_len,
+            &element,
+            b"Finalize",
+        ]),
+        None => R::hash(&[&input_len, input, &element_len, &element, b"Finalize"]),
+    })
+}
+
+/// Compute the composite elements `M = sum d_i * C_i` and `Z = sum d_i * D_i`,
+/// or `Z = k * M` when the prover knows `k`
+fn composites<S: HashToScalar, R: OprfGroup<S>>(
+    mode: Mode,
+    key: Option<&S>,
+    b: &R,
+    c: &[R],
+    d: &[R],
+) -> (R, R) {
+    let b = b.to_bytes();
+    let seed_dst = dst::<S, R>(b"Seed-", mode);
+    let seed = R::hash(&[
+        &(b.len() as u16).to_be_bytes(),
+        &b,
+        &(seed_dst.len() as u16).to_be_bytes(),
+        &seed_dst,
+    ]);
+    let seed_len = (seed.len() as u16).to_be_bytes();
+
+    let mut m = R::zero();
+    let mut z = R::zero();
+    for (i, (ci, di)) in c.iter().zip(d.iter()).enumerate() {
+        let ci_bytes = ci.to_bytes();
+        let di_bytes = di.to_bytes();
+        let weight = hash_to_scalar::<S, R>(
+            mode,
+            &[
+                &seed_len,
+                &seed,
+                &(i as u16).to_be_bytes(),
+                &(ci_bytes.len() as u16).to_be_bytes(),
+                &ci_bytes,
+                &(di_bytes.len() as u16).to_be_bytes(),
+                &di_bytes,
+                b"Composite",
+            ],
+        );
+        m.add_assign(&mul(ci, &weight));
+        if key.is_none() {
+            z.add_assign(&mul(di, &weight));
+        }
+    }
+    if let Some(k) = key {
+        z = mul(&m, k);
+    }
+    (m, z)
+}
+
+fn challenge<S: HashToScalar, R: OprfGroup<S>>(mode: Mode, elements: [&R; 5]) -> S {
+    let encoded = elements.iter().map(|e| e.to_bytes()).collect::<Vec<_>>();
+    let lengths = encoded
+        .iter()
+        .map(|e| (e.len() as u16).to_be_bytes())
+        .collect::<Vec<_>>();
+    let mut transcript = Vec::<&[u8]>::with_capacity(11);
+    for (l, e) in lengths.iter().zip(encoded.iter()) {
+        transcript.push(l);
+        transcript.push(e);
+    }
+    transcript.push(b"Challenge");
+    hash_to_scalar::<S, R>(mode, &transcript)
+}
+
+/// Prove `B = g^k` and `D_i = C_i^k` for every `i`
+pub(crate) fn generate_proof<S: HashToScalar, R: OprfGroup<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    mode: Mode,
+    key: &S,
+    b: &R,
+    c: &[R],
+    d: &[R],
+) -> Proof<S> {
+    let (m, z) = composites(mode, Some(key), b, c, d);
+    let mut r = random_scalar::<S>(rng);
+    let t2 = mul(&R::generator(), &r);
+    let t3 = mul(&m, &r);
+    let challenge = challenge::<S, R>(mode, [b, &m, &z, &t2, &t3]);
+    // s = r - c * k
+    let mut ck = copy(&challenge);
+    ck.scalar_mul_assign(key);
+    r.sub_assign(&ck);
+    ck.zeroize();
+    Proof {
+        challenge,
+        response: r,
+    }
+}
+
+pub(crate) fn verify_proof<S: HashToScalar, R: OprfGroup<S>>(
+    mode: Mode,
+    b: &R,
+    c: &[R],
+    d: &[R],
+    proof: &Proof<S>,
+) -> SharingResult<()> {
+    let (m, z) = composites(mode, None, b, c, d);
+    let mut t2 = mul(&R::generator(), &proof.response);
+    t2.add_assign(&mul(b, &proof.challenge));
+    let mut t3 = mul(&m, &proof.response);
+    t3.add_assign(&mul(&z, &proof.challenge));
+    let expected = challenge::<S, R>(mode, [b, &m, &z, &t2, &t3]);
+    if expected.to_bytes() != proof.challenge.to_bytes() {
+        return Err(SharingError::OprfProofInvalid);
+    }
+    Ok(())
+}
+
+fn check_batch(len: usize, others: &[usize]) -> SharingResult<()> {
+    if len == 0 || len > u16::MAX as usize || others.iter().any(|l| *l != len) {
+        return Err(SharingError::OprfBatchInvalid);
+    }
+    Ok(())
+}
+
+fn length(value: &[u8]) -> SharingResult<[u8; 2]> {
+    if value.len() > u16::MAX as usize {
+        return Err(SharingError::OprfInvalidInput);
+    }
+    Ok((value.len() as u16).to_be_bytes())
+}
+
+/// Parse a scalar, rejecting encodings that aren't reduced
+fn read_scalar<S: Field>(data: &[u8]) -> SharingResult<S> {
+    let s = S::from_bytes(data)?;
+    if s.to_bytes().as_slice() != data {
+        return Err(SharingError::NonCanonicalEncoding);
+    }
+    Ok(s)
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
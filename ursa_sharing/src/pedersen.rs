@@ -12,13 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use super::{
-    error::{SharingError, SharingResult},
+    audit::{notify, Event, SchemeKind},
+    error::{DetectionError, SharingError, SharingResult},
+    evaluate_commitments,
+    possession::PossessionProof,
     shamir::{Scheme as ShamirScheme, Share as ShamirShare},
-    Field, Group, ShareVerifier,
+    Field, Group, ShareVerifier, Strictness,
 };
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
 use generic_array::typenum::Unsigned;
 use rand::{CryptoRng, RngCore};
-use std::{convert::TryFrom, marker::PhantomData};
 
 /// Implements Pedersen's Verifiable secret sharing scheme.
 /// (see <https://www.cs.cornell.edu/courses/cs754/2001fa/129.PDF>)
@@ -51,15 +55,34 @@ impl Scheme {
         secret: &S,
         g: Option<R>,
         h: Option<R>,
+    ) -> SharingResult<PedersenVssResult<S, R>> {
+        // x coordinates are incremental from [1, total+1). 0 is reserved for the secret.
+        let identifiers = (1..=self.limit as u32).collect::<Vec<u32>>();
+        self.split_secret_with_identifiers(rng, secret, identifiers.as_slice(), g, h)
+    }
+
+    /// Create shares from a secret using the caller supplied identifiers
+    /// as the x-coordinates. See [`ShamirScheme::split_secret_with_identifiers`].
+    /// The blinding factor shares use the same identifiers.
+    /// If [`None`] is passed for `g` or `h` then `R::random()` is used.
+    pub fn split_secret_with_identifiers<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        identifiers: &[u32],
+        g: Option<R>,
+        h: Option<R>,
     ) -> SharingResult<PedersenVssResult<S, R>> {
         let g = g.unwrap_or_else(|| R::random(rng));
         let h = h.unwrap_or_else(|| R::random(rng));
         let blinding = S::random(rng);
 
         let shamir = ShamirScheme::new(self.threshold, self.limit)?;
-        let (secret_shares, secret_polynomial) = shamir.get_shares_and_polynomial(rng, secret)?;
+        let (secret_shares, secret_polynomial) =
+            shamir.get_shares_and_polynomial_with_identifiers(rng, secret, identifiers)?;
         let (blinding_shares, blinding_polynomial) =
-            shamir.get_shares_and_polynomial(rng, &blinding)?;
+            shamir.get_shares_and_polynomial_with_identifiers(rng, &blinding, identifiers)?;
+        shamir.notify_split(SchemeKind::Pedersen, secret_shares.as_slice());
 
         let mut commitments = Vec::with_capacity(self.limit);
         // {(g^p0 h^r0), (g^p1, h^r1), ..., (g^pn, h^rn)}
@@ -95,55 +118,68 @@ impl Scheme {
         blind_share: &ShamirShare,
         verifier: &PedersenVerifier<S, R>,
     ) -> SharingResult<()> {
-        let s = S::from_bytes(&share.value)?;
-        if !s.is_valid() {
-            return Err(SharingError::ShareInvalidValue);
-        }
-        let t = S::from_bytes(&blind_share.value)?;
-        if !t.is_valid() {
-            return Err(SharingError::PedersenBlindShareInvalid);
-        }
-
-        let x = S::from_usize(share.identifier as usize);
-        let mut i = S::one();
-
-        // FUTURE: execute this sum of products
-        // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
-        // as a constant time operation using <https://cr.yp.to/papers/pippenger.pdf>
-        // or Guide to Elliptic Curve Cryptography book,
-        // "Algorithm 3.48 Simultaneous multiple point multiplication"
-        // without precomputing the addition but still reduces doublings
-
-        // c_0
-        let mut rhs = R::zero();
-        rhs.add_assign(&verifier.commitments[0].value);
-        for v in &verifier.commitments[1..] {
-            // i *= x
-            i.scalar_mul_assign(&x);
-            // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
-            let mut c = R::zero();
-            c.add_assign(&v.value);
-            c.scalar_mul_assign(&i);
-            rhs.add_assign(&c);
+        let res = self.verify(share, blind_share, verifier);
+        if let Err(error) = res {
+            notify(Event::VerifyFailed {
+                scheme: SchemeKind::Pedersen,
+                identifier: share.identifier,
+                error,
+            });
         }
+        res
+    }
 
-        let mut g = R::zero();
-        g.add_assign(&verifier.g);
-        g.negate();
-        g.scalar_mul_assign(&s);
-        rhs.add_assign(&g);
+    fn verify<S: Field, R: Group<S>>(
+        &self,
+        share: &ShamirShare,
+        blind_share: &ShamirShare,
+        verifier: &PedersenVerifier<S, R>,
+    ) -> SharingResult<()> {
+        verifier.verify(share, blind_share)
+    }
 
-        let mut h = R::zero();
-        h.add_assign(&verifier.h);
-        h.negate();
-        h.scalar_mul_assign(&t);
-        rhs.add_assign(&h);
+    /// Prove possession of `share` and `blind_share` without revealing them.
+    /// The shares are checked against the verifier first so bad shares are caught
+    /// by their holder. `context` should be a fresh value from whoever checks the proof.
+    pub fn prove_possession<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &ShamirShare,
+        blind_share: &ShamirShare,
+        verifier: &PedersenVerifier<S, R>,
+        context: &[u8],
+    ) -> SharingResult<PossessionProof<S, R>> {
+        self.verify_share(share, blind_share, verifier)?;
+        let s = S::from_bytes(&share.value)?;
+        let t = S::from_bytes(&blind_share.value)?;
+        let y = evaluate_commitments(&verifier.commitments, share.identifier);
+        PossessionProof::pedersen(
+            rng,
+            share.identifier,
+            (&s, &t),
+            (&verifier.g, &verifier.h),
+            &y,
+            context,
+        )
+    }
 
-        if rhs.is_zero() {
-            Ok(())
-        } else {
-            Err(SharingError::ShareInvalidValue)
+    /// Check a proof from the holder of the share with `identifier`
+    /// created by `prove_possession` with the same `context`
+    pub fn verify_possession<S: Field, R: Group<S>>(
+        &self,
+        identifier: u32,
+        proof: &PossessionProof<S, R>,
+        verifier: &PedersenVerifier<S, R>,
+        context: &[u8],
+    ) -> SharingResult<()> {
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        if verifier.commitments.len() < self.threshold {
+            return Err(SharingError::ShareMinThreshold);
         }
+        let y = evaluate_commitments(&verifier.commitments, identifier);
+        proof.verify_pedersen(identifier, (&verifier.g, &verifier.h), &y, context)
     }
 
     /// Reconstruct a secret from shares created from `split_secret`.
@@ -156,6 +192,36 @@ impl Scheme {
     ) -> SharingResult<R> {
         ShamirScheme::new(self.threshold, self.limit)?.combine_shares::<S, R>(shares)
     }
+
+    /// Verify every share with the blinding share at the same position then reconstruct
+    /// the secret. Fails with [`DetectionError::Cheaters`] naming every share that doesn't
+    /// match the verifier, instead of returning a wrong secret, so the holders can be
+    /// excluded and the secret combined from the remaining shares.
+    pub fn combine_with_detection<S: Field, R: Group<S>>(
+        &self,
+        shares: &[ShamirShare],
+        blind_shares: &[ShamirShare],
+        verifier: &PedersenVerifier<S, R>,
+    ) -> Result<S, DetectionError> {
+        if blind_shares.len() != shares.len() {
+            return Err(
+                SharingError::ShareIdentifierCount(shares.len(), blind_shares.len()).into(),
+            );
+        }
+        let cheaters = shares
+            .iter()
+            .zip(blind_shares.iter())
+            .filter(|(share, blind_share)| {
+                share.identifier != blind_share.identifier
+                    || self.verify_share(share, blind_share, verifier).is_err()
+            })
+            .map(|(share, _)| share.identifier)
+            .collect::<Vec<u32>>();
+        if !cheaters.is_empty() {
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        Ok(self.combine_shares::<S, S>(shares)?)
+    }
 }
 
 /// A Pedersen verifier is used to provide integrity checking of shamir shares
@@ -170,6 +236,58 @@ pub struct PedersenVerifier<S: Field, R: Group<S>> {
 }
 
 impl<S: Field, R: Group<S>> PedersenVerifier<S, R> {
+    /// Checks if the share and its blinding share are valid according to these commitments
+    pub fn verify_share(
+        &self,
+        share: &ShamirShare,
+        blind_share: &ShamirShare,
+    ) -> SharingResult<()> {
+        let res = self.verify(share, blind_share);
+        if let Err(error) = res {
+            notify(Event::VerifyFailed {
+                scheme: SchemeKind::Pedersen,
+                identifier: share.identifier,
+                error,
+            });
+        }
+        res
+    }
+
+    fn verify(&self, share: &ShamirShare, blind_share: &ShamirShare) -> SharingResult<()> {
+        if self.commitments.is_empty() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let s = S::from_bytes(&share.value)?;
+        let t = S::from_bytes(&blind_share.value)?;
+        // Checked at the end so the time doesn't depend on the share values
+        let s_valid = s.is_valid();
+        let t_valid = t.is_valid();
+
+        let mut rhs = evaluate_commitments(&self.commitments, share.identifier);
+
+        let mut g = R::zero();
+        g.add_assign(&self.g);
+        g.negate();
+        g.scalar_mul_assign(&s);
+        rhs.add_assign(&g);
+
+        let mut h = R::zero();
+        h.add_assign(&self.h);
+        h.negate();
+        h.scalar_mul_assign(&t);
+        rhs.add_assign(&h);
+
+        if !s_valid {
+            Err(SharingError::ShareInvalidValue)
+        } else if !t_valid {
+            Err(SharingError::PedersenBlindShareInvalid)
+        } else if rhs.is_zero() {
+            Ok(())
+        } else {
+            Err(SharingError::ShareInvalidValue)
+        }
+    }
+
     /// Convert this verifier to a byte array
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut o = self.g.to_bytes().to_vec();
@@ -180,12 +298,9 @@ impl<S: Field, R: Group<S>> PedersenVerifier<S, R> {
         }
         o
     }
-}
-
-impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PedersenVerifier<S, R> {
-    type Error = SharingError;
 
-    fn try_from(value: &[u8]) -> SharingResult<Self> {
+    /// Deserialize a verifier using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
         if value.len() < R::Size::to_usize() * 2 + 4 {
             return Err(SharingError::PedersenVerifierMinSize(
                 R::Size::to_usize() * 2 + 4,
@@ -194,12 +309,12 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PedersenVerifier<S, R> {
         }
         let mut offset = 0;
         let mut end = R::Size::to_usize();
-        let g = R::from_bytes(&value[offset..end])?;
+        let g = R::from_bytes_with(&value[offset..end], strictness)?;
 
         offset = end;
         end += R::Size::to_usize();
 
-        let h = R::from_bytes(&value[offset..end])?;
+        let h = R::from_bytes_with(&value[offset..end], strictness)?;
 
         offset = end;
         end += 4;
@@ -207,11 +322,17 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PedersenVerifier<S, R> {
         let mut c_size = [0u8; 4];
         c_size.copy_from_slice(&value[offset..end]);
         let cs = u32::from_be_bytes(c_size) as usize;
+        if (value.len() - end) / R::Size::to_usize() < cs {
+            return Err(SharingError::PedersenVerifierMinSize(
+                end + cs * R::Size::to_usize(),
+                value.len(),
+            ));
+        }
         let mut commitments = Vec::with_capacity(cs);
-        offset = end;
-        end += R::Size::to_usize();
         for _ in 0..cs {
-            let c = R::from_bytes(&value[offset..end])?;
+            offset = end;
+            end += R::Size::to_usize();
+            let c = R::from_bytes_with(&value[offset..end], strictness)?;
             commitments.push(ShareVerifier {
                 value: c,
                 phantom: PhantomData,
@@ -221,6 +342,14 @@ impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PedersenVerifier<S, R> {
     }
 }
 
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PedersenVerifier<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
 /// A Pedersen result returned when calling `split_secret`
 #[derive(Debug, Clone)]
 pub struct PedersenVssResult<S: Field, R: Group<S>> {
@@ -0,0 +1,261 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Public-key encryption with keyword search over BLS12-381.
+//!
+//! A sender attaches a [`KeywordTag`] for each keyword of a message, like `urgent`,
+//! made with only the receiver's [`PublicKey`]. The receiver gives a gateway the
+//! [`Trapdoor`] of a keyword and the gateway can then tell which tags are for that
+//! keyword, e.g. to route urgent mail, without learning anything else about the tags.
+//!
+//! The scheme is Boneh, Di Crescenzo, Ostrovsky and Persiano's
+//! (see <https://crypto.stanford.edu/~dabo/papers/encsearch.pdf>), the
+//! [`ibe`](crate::ibe) construction with keywords as identities: a tag is
+//! `(g1^r, H(e(pk^r, H(w))))` and the trapdoor is `H(w)^sk`. Tags are randomized so
+//! two tags for the same keyword can't be linked without its trapdoor. Keywords are
+//! hashed to G2 with the suite in [`DST`].
+//!
+//! Whoever holds a trapdoor can test it against tags it makes for guessed keywords,
+//! so trapdoors of low-entropy keywords reveal the keyword to the gateway. Trapdoors
+//! must be sent to the gateway over an authenticated and confidential channel.
+
+use super::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    error::{SharingError, SharingResult},
+    hash_to_curve::HashToCurve,
+    Group,
+};
+use pairing_plus::{
+    bls12_381::{Bls12, Fq12},
+    serdes::SerDes,
+    Engine,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// The hash to curve suite used for keywords
+pub const DST: &[u8] = b"URSA_PEKS_BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+const TAG_DOMAIN: &[u8] = b"ursa_sharing peks tag v1";
+const G1_BYTES: usize = 48;
+const G2_BYTES: usize = 96;
+const HASH_BYTES: usize = 32;
+
+/// The receiver's key, cleared on drop
+pub struct SecretKey(Bls12381Scalar);
+
+impl SecretKey {
+    /// Create a new secret key
+    pub fn new(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        loop {
+            let s = Bls12381Scalar::random(rng);
+            if s.is_valid() {
+                return Self(s);
+            }
+        }
+    }
+
+    /// The public key senders make tags with
+    pub fn public_key(&self) -> PublicKey {
+        let mut key = Bls12381G1::generator();
+        key.scalar_mul_assign(&self.0);
+        PublicKey(key)
+    }
+
+    /// The trapdoor that matches the tags of `keyword`
+    pub fn trapdoor(&self, keyword: &[u8]) -> Trapdoor {
+        let mut point = hash_keyword(keyword);
+        point.scalar_mul_assign(&self.0);
+        Trapdoor(point)
+    }
+
+    /// Encode the secret key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for SecretKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != 32 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let s = Bls12381Scalar::from_bytes(value)?;
+        if !s.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        Ok(Self(s))
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "SecretKey(..)")
+    }
+}
+
+/// The receiver's public key, `g1^sk`
+#[derive(Copy, Clone, Debug)]
+pub struct PublicKey(pub Bls12381G1);
+
+impl PublicKey {
+    /// The compressed encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let key = Bls12381G1::from_bytes(value)?;
+        if !key.is_valid() {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        Ok(Self(key))
+    }
+}
+
+/// An encrypted keyword attached to a message
+#[derive(Clone, Debug)]
+pub struct KeywordTag {
+    /// g1^r
+    a: Bls12381G1,
+    /// H(e(pk^r, H(w)))
+    b: [u8; HASH_BYTES],
+}
+
+impl KeywordTag {
+    /// Tag `keyword` for the receiver with `public_key`
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        public_key: &PublicKey,
+        keyword: &[u8],
+    ) -> SharingResult<Self> {
+        if !public_key.0.is_valid() {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        let mut r = Bls12381Scalar::random(rng);
+        let mut a = Bls12381G1::generator();
+        a.scalar_mul_assign(&r);
+        let mut blinded = public_key.0;
+        blinded.scalar_mul_assign(&r);
+        r.zeroize();
+        let b = tag_hash(&a, &Bls12::pairing(blinded.0, hash_keyword(keyword).0));
+        Ok(Self { a, b })
+    }
+
+    /// Encode the tag
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.a.to_bytes().to_vec();
+        out.extend_from_slice(&self.b);
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for KeywordTag {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != G1_BYTES + HASH_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut b = [0u8; HASH_BYTES];
+        b.copy_from_slice(&value[G1_BYTES..]);
+        Ok(Self {
+            a: Bls12381G1::from_bytes(&value[..G1_BYTES])?,
+            b,
+        })
+    }
+}
+
+/// Lets a gateway find the tags of one keyword, cleared on drop
+pub struct Trapdoor(Bls12381G2);
+
+impl Trapdoor {
+    /// True if `tag` is for the keyword of this trapdoor
+    pub fn matches(&self, tag: &KeywordTag) -> bool {
+        // e(g1^r, H(w)^sk)
+        let expected = tag_hash(&tag.a, &Bls12::pairing(tag.a.0, self.0 .0));
+        expected.ct_eq(&tag.b).into()
+    }
+
+    /// The positions of the tags for the keyword of this trapdoor
+    pub fn filter(&self, tags: &[KeywordTag]) -> Vec<usize> {
+        tags.iter()
+            .enumerate()
+            .filter(|(_, t)| self.matches(t))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Encode the trapdoor
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for Trapdoor {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != G2_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Bls12381G2::from_bytes(value).map(Self)
+    }
+}
+
+impl Drop for Trapdoor {
+    fn drop(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl Debug for Trapdoor {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Trapdoor(..)")
+    }
+}
+
+/// Hash a keyword to G2 with the suite in [`DST`]
+pub fn hash_keyword(keyword: &[u8]) -> Bls12381G2 {
+    Bls12381G2::hash_to_curve(keyword, DST)
+}
+
+fn tag_hash(a: &Bls12381G1, shared: &Fq12) -> [u8; HASH_BYTES] {
+    let mut bytes = Vec::with_capacity(576);
+    shared.serialize(&mut bytes, true).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(TAG_DOMAIN);
+    hasher.input(a.to_bytes());
+    hasher.input(&bytes);
+    let mut out = [0u8; HASH_BYTES];
+    out.copy_from_slice(&hasher.result());
+    out
+}
@@ -0,0 +1,495 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Weighted and hierarchical access structures.
+//!
+//! A [`Policy`] is a tree of thresholds whose leaves are shareholders. A threshold is
+//! satisfied by at least `threshold` points from its members, where a holder with weight
+//! `w` gives `w` points and a nested threshold gives one point when it's satisfied.
+//! "2 of 3 admins, or 1 admin and 3 operators" is
+//!
+//! ```
+//! use ursa_sharing::policy::Policy;
+//!
+//! let admins = || (1..=3).map(Policy::holder).collect::<Vec<_>>();
+//! let operators = (10..=13).map(Policy::holder).collect::<Vec<_>>();
+//! let policy = Policy::threshold(1, vec![
+//!     Policy::threshold(2, admins())?,
+//!     Policy::threshold(2, vec![
+//!         Policy::threshold(1, admins())?,
+//!         Policy::threshold(3, operators)?,
+//!     ])?,
+//! ])?;
+//! assert!(policy.is_satisfied_by(&[3, 10, 11, 12]));
+//! assert!(!policy.is_satisfied_by(&[10, 11, 12, 13]));
+//! # Ok::<(), ursa_sharing::error::SharingError>(())
+//! ```
+//!
+//! The secret is split with Shamir at every threshold, the member at position `x`
+//! receiving the point `f(x)` and passing it down to its own members if it's a threshold.
+//! A holder can appear under several thresholds and its [`PolicyShare`] holds all of its
+//! points, each with the path of x-coordinates leading to it from the root.
+//!
+//! Shares are bound to the SHA-256 hash of the policy encoding, so combining needs the
+//! policy, stored with the shares as [`Policy::to_bytes`], and fails unless the holders
+//! of the shares satisfy it. Holders are identified by non-zero numbers which are public.
+
+use super::{
+    dkg::Reader,
+    error::{SharingError, SharingResult},
+    shamir::Scheme,
+    Field, Polynomial,
+};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::convert::TryFrom;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// The version of the policy encoding
+pub const POLICY_VERSION: u8 = 1;
+/// The deepest nesting of thresholds allowed
+pub const MAX_DEPTH: usize = 16;
+
+const HOLDER: u8 = 0;
+const THRESHOLD: u8 = 1;
+
+/// A tree of thresholds over weighted shareholders
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// A shareholder giving `weight` points to the threshold it's a member of
+    Holder {
+        /// The non-zero identifier of the shareholder
+        id: u32,
+        /// The number of points the holder receives
+        weight: u32,
+    },
+    /// Satisfied by at least `threshold` points of the members
+    Threshold {
+        /// The number of points needed
+        threshold: u32,
+        /// The holders and nested thresholds
+        members: Vec<Policy>,
+    },
+}
+
+impl Policy {
+    /// A shareholder with one point
+    pub fn holder(id: u32) -> Self {
+        Self::weighted(id, 1)
+    }
+
+    /// A shareholder with `weight` points
+    pub fn weighted(id: u32, weight: u32) -> Self {
+        Policy::Holder { id, weight }
+    }
+
+    /// A threshold of `threshold` points over `members`.
+    /// Fails if the threshold is zero or greater than the points of the members,
+    /// or if a member is invalid.
+    pub fn threshold(threshold: u32, members: Vec<Policy>) -> SharingResult<Self> {
+        let policy = Policy::Threshold { threshold, members };
+        policy.check(1)?;
+        Ok(policy)
+    }
+
+    /// Check the policy is a valid threshold no deeper than `MAX_DEPTH` from `depth`
+    fn check(&self, depth: usize) -> SharingResult<()> {
+        let (threshold, members) = match self {
+            Policy::Threshold { threshold, members } => (*threshold, members),
+            Policy::Holder { .. } => return Err(SharingError::PolicyInvalid),
+        };
+        if depth > MAX_DEPTH {
+            return Err(SharingError::PolicyInvalid);
+        }
+        let mut points = 0u32;
+        for member in members {
+            let weight = match member {
+                Policy::Holder { id, weight } => {
+                    if *id == 0 || *weight == 0 {
+                        return Err(SharingError::PolicyInvalid);
+                    }
+                    *weight
+                }
+                Policy::Threshold { .. } => {
+                    member.check(depth + 1)?;
+                    1
+                }
+            };
+            points = points
+                .checked_add(weight)
+                .ok_or(SharingError::PolicyInvalid)?;
+        }
+        if threshold == 0 || threshold > points {
+            return Err(SharingError::PolicyInvalid);
+        }
+        Ok(())
+    }
+
+    /// The identifiers of the holders, in ascending order
+    pub fn holders(&self) -> Vec<u32> {
+        let mut holders = BTreeSet::new();
+        self.visit_holders(&mut |id| {
+            holders.insert(id);
+        });
+        holders.into_iter().collect()
+    }
+
+    fn visit_holders(&self, f: &mut impl FnMut(u32)) {
+        match self {
+            Policy::Holder { id, .. } => f(*id),
+            Policy::Threshold { members, .. } => members.iter().for_each(|m| m.visit_holders(f)),
+        }
+    }
+
+    /// True if shares from the holders `holders` can recover the secret
+    pub fn is_satisfied_by(&self, holders: &[u32]) -> bool {
+        match self {
+            Policy::Holder { id, .. } => holders.contains(id),
+            Policy::Threshold { threshold, members } => {
+                let points: u64 = members
+                    .iter()
+                    .map(|m| match m {
+                        Policy::Holder { id, weight } if holders.contains(id) => *weight as u64,
+                        Policy::Threshold { .. } if m.is_satisfied_by(holders) => 1,
+                        _ => 0,
+                    })
+                    .sum();
+                points >= *threshold as u64
+            }
+        }
+    }
+
+    /// Split `secret` into one share for every holder
+    pub fn split_secret<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+    ) -> SharingResult<Vec<PolicyShare>> {
+        if !secret.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        let mut points = BTreeMap::new();
+        self.split_node(rng, secret, &mut Vec::new(), &mut points);
+        let policy = self.digest();
+        Ok(points
+            .into_iter()
+            .map(|(holder, points)| PolicyShare {
+                policy,
+                holder,
+                points,
+            })
+            .collect())
+    }
+
+    fn split_node<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        path: &mut Vec<u32>,
+        points: &mut BTreeMap<u32, Vec<PolicyPoint>>,
+    ) {
+        let (threshold, members) = match self {
+            Policy::Threshold { threshold, members } => (*threshold, members),
+            Policy::Holder { .. } => return,
+        };
+        let polynomial = Polynomial::new(rng, secret, threshold as usize);
+        let mut x = 1u32;
+        for member in members {
+            let weight = match member {
+                Policy::Holder { weight, .. } => *weight,
+                Policy::Threshold { .. } => 1,
+            };
+            for _ in 0..weight {
+                path.push(x);
+                let mut y = polynomial.evaluate(&S::from_usize(x as usize));
+                match member {
+                    Policy::Holder { id, .. } => points.entry(*id).or_default().push(PolicyPoint {
+                        path: path.clone(),
+                        value: y.to_bytes().to_vec(),
+                    }),
+                    Policy::Threshold { .. } => member.split_node(rng, &y, path, points),
+                }
+                y.zeroize();
+                path.pop();
+                x += 1;
+            }
+        }
+    }
+
+    /// Recover the secret from the shares of holders satisfying the policy
+    pub fn combine_shares<S: Field>(&self, shares: &[PolicyShare]) -> SharingResult<S> {
+        let digest = self.digest();
+        let mut owners = BTreeMap::new();
+        self.positions(&mut Vec::new(), &mut owners);
+
+        let mut holders = BTreeSet::new();
+        let mut points = BTreeMap::new();
+        for share in shares {
+            if share.policy != digest {
+                return Err(SharingError::PolicyMismatch);
+            }
+            if !holders.insert(share.holder) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+            for point in &share.points {
+                // Only take the points the policy gave to this holder
+                if owners.get(&point.path) != Some(&share.holder) {
+                    return Err(SharingError::PolicyMismatch);
+                }
+                points.insert(point.path.as_slice(), point.value.as_slice());
+            }
+        }
+        self.combine_node(&mut Vec::new(), &points)?
+            .ok_or(SharingError::PolicyNotSatisfied)
+    }
+
+    /// Map the path of every point to the holder it's given to
+    fn positions(&self, path: &mut Vec<u32>, owners: &mut BTreeMap<Vec<u32>, u32>) {
+        if let Policy::Threshold { members, .. } = self {
+            let mut x = 1u32;
+            for member in members {
+                let weight = match member {
+                    Policy::Holder { weight, .. } => *weight,
+                    Policy::Threshold { .. } => 1,
+                };
+                for _ in 0..weight {
+                    path.push(x);
+                    match member {
+                        Policy::Holder { id, .. } => {
+                            owners.insert(path.clone(), *id);
+                        }
+                        Policy::Threshold { .. } => member.positions(path, owners),
+                    }
+                    path.pop();
+                    x += 1;
+                }
+            }
+        }
+    }
+
+    /// Interpolate the value at this threshold, or `None` if it isn't satisfied.
+    /// Only which points are present is branched on, not their values.
+    fn combine_node<S: Field>(
+        &self,
+        path: &mut Vec<u32>,
+        points: &BTreeMap<&[u32], &[u8]>,
+    ) -> SharingResult<Option<S>> {
+        let (threshold, members) = match self {
+            Policy::Threshold { threshold, members } => (*threshold as usize, members),
+            Policy::Holder { .. } => return Ok(None),
+        };
+        let mut x_coordinates = Vec::with_capacity(threshold);
+        let mut y_coordinates = Vec::with_capacity(threshold);
+        let mut valid = true;
+        let mut x = 1u32;
+        let mut result = Ok(());
+        for member in members {
+            let weight = match member {
+                Policy::Holder { weight, .. } => *weight,
+                Policy::Threshold { .. } => 1,
+            };
+            for _ in 0..weight {
+                if x_coordinates.len() < threshold && result.is_ok() {
+                    path.push(x);
+                    let y = match member {
+                        Policy::Holder { .. } => points
+                            .get(path.as_slice())
+                            .map(|v| S::from_bytes(v))
+                            .transpose(),
+                        Policy::Threshold { .. } => member.combine_node(path, points),
+                    };
+                    path.pop();
+                    match y {
+                        Ok(Some(y)) => {
+                            valid &= y.is_valid();
+                            x_coordinates.push(S::from_usize(x as usize));
+                            y_coordinates.push(y);
+                        }
+                        Ok(None) => {}
+                        Err(e) => result = Err(e),
+                    }
+                }
+                x += 1;
+            }
+        }
+        let secret = if result.is_ok() && x_coordinates.len() == threshold {
+            Some(Scheme::interpolate(
+                x_coordinates.as_slice(),
+                y_coordinates.iter().collect::<Vec<&S>>().as_slice(),
+            ))
+        } else {
+            None
+        };
+        y_coordinates.iter_mut().for_each(|y| y.zeroize());
+        result?;
+        let secret = secret.transpose()?;
+        if secret.is_some() && !valid {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        Ok(secret)
+    }
+
+    /// The SHA-256 hash of the encoding the shares are bound to
+    pub fn digest(&self) -> [u8; 32] {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&Sha256::digest(&self.to_bytes()));
+        digest
+    }
+
+    /// Encode the policy to store it with the shares
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![POLICY_VERSION];
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Policy::Holder { id, weight } => {
+                out.push(HOLDER);
+                out.extend_from_slice(&id.to_be_bytes());
+                out.extend_from_slice(&weight.to_be_bytes());
+            }
+            Policy::Threshold { threshold, members } => {
+                out.push(THRESHOLD);
+                out.extend_from_slice(&threshold.to_be_bytes());
+                out.extend_from_slice(&(members.len() as u32).to_be_bytes());
+                members.iter().for_each(|m| m.write(out));
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'_>, depth: usize) -> SharingResult<Self> {
+        match reader.take(1)?[0] {
+            HOLDER => Ok(Policy::Holder {
+                id: reader.u32()?,
+                weight: reader.u32()?,
+            }),
+            THRESHOLD if depth <= MAX_DEPTH => {
+                let threshold = reader.u32()?;
+                let count = reader.u32()?;
+                let mut members = Vec::new();
+                for _ in 0..count {
+                    members.push(Policy::read(reader, depth + 1)?);
+                }
+                Ok(Policy::Threshold { threshold, members })
+            }
+            _ => Err(SharingError::PolicyInvalid),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Policy {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        match value.first() {
+            Some(&POLICY_VERSION) => {}
+            Some(v) => return Err(SharingError::ShareUnsupportedVersion(*v)),
+            None => return Err(SharingError::ShareInvalidEncoding),
+        }
+        let mut reader = Reader { data: &value[1..] };
+        let policy = Policy::read(&mut reader, 1)?;
+        reader.finish()?;
+        policy.check(1)?;
+        Ok(policy)
+    }
+}
+
+/// A point of a holder, the value for the path of x-coordinates from the root
+#[derive(Clone, Debug, Zeroize)]
+#[zeroize(drop)]
+struct PolicyPoint {
+    path: Vec<u32>,
+    value: Vec<u8>,
+}
+
+/// The points of one holder under a [`Policy`]
+#[derive(Clone, Debug)]
+pub struct PolicyShare {
+    policy: [u8; 32],
+    holder: u32,
+    points: Vec<PolicyPoint>,
+}
+
+impl PolicyShare {
+    /// The identifier of the holder
+    pub fn holder(&self) -> u32 {
+        self.holder
+    }
+
+    /// The [`Policy::digest`] of the policy the share was split under
+    pub fn policy_digest(&self) -> &[u8; 32] {
+        &self.policy
+    }
+
+    /// The number of points of the holder, its weight summed over the thresholds it's in
+    pub fn points(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Encode the share as the policy digest, the holder and the points
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.policy.to_vec();
+        out.extend_from_slice(&self.holder.to_be_bytes());
+        out.extend_from_slice(&(self.points.len() as u32).to_be_bytes());
+        for point in &self.points {
+            out.extend_from_slice(&(point.path.len() as u32).to_be_bytes());
+            point
+                .path
+                .iter()
+                .for_each(|x| out.extend_from_slice(&x.to_be_bytes()));
+            out.extend_from_slice(&(point.value.len() as u32).to_be_bytes());
+            out.extend_from_slice(&point.value);
+        }
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for PolicyShare {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        let mut policy = [0u8; 32];
+        policy.copy_from_slice(reader.take(32)?);
+        let holder = reader.u32()?;
+        let count = reader.u32()?;
+        let mut points = Vec::new();
+        for _ in 0..count {
+            let depth = reader.u32()? as usize;
+            if depth == 0 || depth > MAX_DEPTH {
+                return Err(SharingError::ShareInvalidEncoding);
+            }
+            let path = (0..depth)
+                .map(|_| reader.u32())
+                .collect::<SharingResult<Vec<_>>>()?;
+            let value = reader.field()?.to_vec();
+            points.push(PolicyPoint { path, value });
+        }
+        reader.finish()?;
+        if holder == 0 || points.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            policy,
+            holder,
+            points,
+        })
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Proofs that a shareholder holds a share consistent with the published
+//! verifiable secret sharing commitments without revealing the share.
+//!
+//! The commitments evaluated at the share identifier give the shareholder's
+//! verification point `c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}`.
+//! The proof is a Schnorr proof of knowledge of its discrete log
+//! (or of its opening for Pedersen) made non-interactive with Fiat-Shamir.
+//!
+//! The caller-supplied `context` is bound into the challenge.
+//! Coordinators checking liveness before a ceremony should send a fresh
+//! nonce as the context so an old proof can't be replayed.
+//!
+//! Proofs are created and checked through
+//! [`feldman::Scheme::prove_possession`](crate::feldman::Scheme::prove_possession) and
+//! [`pedersen::Scheme::prove_possession`](crate::pedersen::Scheme::prove_possession).
+
+use super::{
+    error::{SharingError, SharingResult},
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+
+const DOMAIN: &[u8] = b"ursa_sharing share possession v1";
+const FELDMAN: u8 = 1;
+const PEDERSEN: u8 = 2;
+
+/// A non-interactive proof that the holder of the share with
+/// `identifier` knows its value
+#[derive(Clone, Debug)]
+pub struct PossessionProof<S: Field, R: Group<S>> {
+    commitment: R,
+    response: S,
+    blind_response: Option<S>,
+}
+
+impl<S: Field, R: Group<S>> PossessionProof<S, R> {
+    /// Convert this proof to a byte array
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.commitment.to_bytes().to_vec();
+        o.extend_from_slice(self.response.to_bytes().as_ref());
+        if let Some(t) = &self.blind_response {
+            o.extend_from_slice(t.to_bytes().as_ref());
+        }
+        o
+    }
+
+    /// Prove knowledge of `value` where `g * value` is the verification point `y`
+    pub(crate) fn feldman(
+        rng: &mut (impl RngCore + CryptoRng),
+        identifier: u32,
+        value: &S,
+        g: &R,
+        y: &R,
+        context: &[u8],
+    ) -> SharingResult<Self> {
+        let mut k = S::random(rng);
+        let mut commitment = R::zero();
+        commitment.add_assign(g);
+        commitment.scalar_mul_assign(&k);
+
+        let c = challenge::<S, R>(FELDMAN, identifier, &[g], y, &commitment, context);
+        let proof = c.map(|c| Self {
+            commitment,
+            response: respond(&k, &c, value),
+            blind_response: None,
+        });
+        k.zeroize();
+        proof
+    }
+
+    /// Prove knowledge of `value` and `blind` where `g * value + h * blind`
+    /// is the verification point `y`
+    pub(crate) fn pedersen(
+        rng: &mut (impl RngCore + CryptoRng),
+        identifier: u32,
+        (value, blind): (&S, &S),
+        (g, h): (&R, &R),
+        y: &R,
+        context: &[u8],
+    ) -> SharingResult<Self> {
+        let mut k = S::random(rng);
+        let mut l = S::random(rng);
+        let mut commitment = R::zero();
+        commitment.add_assign(g);
+        commitment.scalar_mul_assign(&k);
+        let mut b = R::zero();
+        b.add_assign(h);
+        b.scalar_mul_assign(&l);
+        commitment.add_assign(&b);
+
+        let c = challenge::<S, R>(PEDERSEN, identifier, &[g, h], y, &commitment, context);
+        let proof = c.map(|c| Self {
+            commitment,
+            response: respond(&k, &c, value),
+            blind_response: Some(respond(&l, &c, blind)),
+        });
+        k.zeroize();
+        l.zeroize();
+        proof
+    }
+
+    /// Check the proof for identifier against the verification point `y`
+    pub(crate) fn verify_feldman(
+        &self,
+        identifier: u32,
+        g: &R,
+        y: &R,
+        context: &[u8],
+    ) -> SharingResult<()> {
+        if self.blind_response.is_some() {
+            return Err(SharingError::SharePossessionProofInvalid);
+        }
+        let c = challenge::<S, R>(FELDMAN, identifier, &[g], y, &self.commitment, context)?;
+
+        // g * z - y * c - commitment == 0
+        let mut lhs = R::zero();
+        lhs.add_assign(g);
+        lhs.scalar_mul_assign(&self.response);
+        self.check(lhs, y, &c)
+    }
+
+    /// Check the proof for identifier against the verification point `y`
+    pub(crate) fn verify_pedersen(
+        &self,
+        identifier: u32,
+        (g, h): (&R, &R),
+        y: &R,
+        context: &[u8],
+    ) -> SharingResult<()> {
+        let t = self
+            .blind_response
+            .as_ref()
+            .ok_or(SharingError::SharePossessionProofInvalid)?;
+        let c = challenge::<S, R>(PEDERSEN, identifier, &[g, h], y, &self.commitment, context)?;
+
+        // g * z + h * u - y * c - commitment == 0
+        let mut lhs = R::zero();
+        lhs.add_assign(g);
+        lhs.scalar_mul_assign(&self.response);
+        let mut b = R::zero();
+        b.add_assign(h);
+        b.scalar_mul_assign(t);
+        lhs.add_assign(&b);
+        self.check(lhs, y, &c)
+    }
+
+    fn check(&self, mut lhs: R, y: &R, c: &S) -> SharingResult<()> {
+        let mut rhs = R::zero();
+        rhs.add_assign(y);
+        rhs.scalar_mul_assign(c);
+        lhs.sub_assign(&rhs);
+        lhs.sub_assign(&self.commitment);
+        if lhs.is_zero() {
+            Ok(())
+        } else {
+            Err(SharingError::SharePossessionProofInvalid)
+        }
+    }
+
+    /// Deserialize a proof using the given acceptance rules
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        let s_size = S::Size::to_usize();
+        let blinded = if value.len() == r_size + s_size {
+            false
+        } else if value.len() == r_size + 2 * s_size {
+            true
+        } else {
+            return Err(SharingError::ShareInvalidEncoding);
+        };
+        let commitment = R::from_bytes_with(&value[..r_size], strictness)?;
+        let response = S::from_bytes_with(&value[r_size..r_size + s_size], strictness)?;
+        let blind_response = if blinded {
+            Some(S::from_bytes_with(&value[r_size + s_size..], strictness)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            commitment,
+            response,
+            blind_response,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PossessionProof<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// Compute `k + c * value`
+fn respond<S: Field>(k: &S, c: &S, value: &S) -> S {
+    let mut z = S::zero();
+    z.add_assign(c);
+    z.scalar_mul_assign(value);
+    z.add_assign(k);
+    z
+}
+
+fn challenge<S: Field, R: Group<S>>(
+    scheme: u8,
+    identifier: u32,
+    generators: &[&R],
+    y: &R,
+    commitment: &R,
+    context: &[u8],
+) -> SharingResult<S> {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_message(b"scheme", &[scheme]);
+    transcript.append_u64(b"identifier", identifier as u64);
+    transcript.append_message(b"context", context);
+    for g in generators {
+        transcript.append_point(b"generator", *g);
+    }
+    transcript.append_point(b"verification point", y);
+    transcript.append_point(b"commitment", commitment);
+    transcript.challenge_scalar(b"challenge")
+}
@@ -0,0 +1,560 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Threshold proxy re-encryption, so proxies can turn data encrypted to one key into
+//! data for another key without learning it.
+//!
+//! The scheme follows Umbral (see <https://github.com/nucypher/umbral-doc>). Alice
+//! encrypts data to her own public key with [`encrypt`], and the resulting
+//! [`Ciphertext`] holds a [`Capsule`], an ElGamal-style encapsulation of the
+//! ChaCha20-Poly1305 key. To share the data with Bob she creates `limit`
+//! [`KeyFragment`]s with [`generate_key_fragments`] and gives one to each proxy. Each
+//! proxy turns the capsule into a [`CapsuleFragment`] with its key fragment, and Bob
+//! opens the ciphertext with [`decrypt_reencrypted`] once he holds `threshold` of them.
+//! Fewer than `threshold` proxies learn nothing about Alice's key or the data, and
+//! the delegation only goes from Alice to Bob.
+//!
+//! The re-encryption key `a / d` is split with Shamir, where `d` is derived from a
+//! Diffie-Hellman exchange between a fresh key of Alice's and Bob's key so only Bob
+//! can undo it. Capsules carry a Schnorr-like proof of their randomness that proxies
+//! check, and every capsule fragment has a DLEQ proof against the commitment `g^rk_i`
+//! of its key fragment. Bob can check the proofs with [`CapsuleFragment::verify`] and
+//! must compare [`CapsuleFragment::commitment`] to the commitments Alice published to
+//! know which proxy misbehaved. Unlike Umbral the key fragments aren't signed, so their
+//! commitments must reach Bob authenticated by Alice.
+//!
+//! All keys are multiples of a generator `g` the caller chooses and every party must
+//! use the same one.
+
+use super::{
+    dkg::Reader,
+    dleq::DleqProof,
+    error::{SharingError, SharingResult},
+    field::lagrange_coefficients,
+    transcript::Transcript,
+    Field, Group, Polynomial,
+};
+use alloc::{vec, vec::Vec};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// The version of the ciphertext and fragment encodings
+pub const VERSION: u8 = 1;
+
+const PROTOCOL: &[u8] = b"ursa_sharing pre v1";
+const KEY_DOMAIN: &[u8] = b"ursa_sharing pre key v1";
+
+/// The encapsulated data key of a ciphertext
+#[derive(Debug)]
+pub struct Capsule<S: Field, R: Group<S>> {
+    /// g^r
+    e: R,
+    /// g^u
+    v: R,
+    /// u + r H(E, V)
+    s: S,
+}
+
+impl<S: Field, R: Group<S>> Capsule<S, R> {
+    /// Encapsulate a fresh key to `public_key`, returning the capsule and the point
+    /// the key is derived from
+    fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        g: &R,
+        public_key: &R,
+    ) -> SharingResult<(Self, R)> {
+        let mut r = S::random(rng);
+        let mut u = S::random(rng);
+        let e = mul(g, &r);
+        let v = mul(g, &u);
+        let mut s = capsule_challenge::<S, R>(&e, &v)?;
+        s.scalar_mul_assign(&r);
+        s.add_assign(&u);
+
+        // pk^(r + u)
+        r.add_assign(&u);
+        let shared = mul(public_key, &r);
+        r.zeroize();
+        u.zeroize();
+        Ok((Self { e, v, s }, shared))
+    }
+
+    /// Check that `g^s = V E^H(E, V)`.
+    /// Fails with `SharingError::PreInvalidCapsule` if the capsule was modified
+    pub fn verify(&self, g: &R) -> SharingResult<()> {
+        let h = capsule_challenge::<S, R>(&self.e, &self.v)?;
+        let mut rhs = mul(&self.e, &h);
+        rhs.add_assign(&self.v);
+        if mul(g, &self.s).to_bytes() == rhs.to_bytes() && !self.e.is_zero() {
+            Ok(())
+        } else {
+            Err(SharingError::PreInvalidCapsule)
+        }
+    }
+
+    /// E V, the point raised to the secret key or the re-encryption key
+    fn base(&self) -> R {
+        let mut base = copy(&self.e);
+        base.add_assign(&self.v);
+        base
+    }
+
+    fn append_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.e.to_bytes());
+        out.extend_from_slice(&self.v.to_bytes());
+        out.extend_from_slice(&self.s.to_bytes());
+    }
+
+    fn read(reader: &mut Reader<'_>) -> SharingResult<Self> {
+        Ok(Self {
+            e: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            v: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            s: S::from_bytes(reader.take(S::Size::to_usize())?)?,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for Capsule<S, R> {
+    fn clone(&self) -> Self {
+        let mut s = S::zero();
+        s.add_assign(&self.s);
+        Self {
+            e: copy(&self.e),
+            v: copy(&self.v),
+            s,
+        }
+    }
+}
+
+/// Data encrypted to a public key that proxies can re-encrypt
+#[derive(Debug)]
+pub struct Ciphertext<S: Field, R: Group<S>> {
+    capsule: Capsule<S, R>,
+    sealed: Vec<u8>,
+}
+
+impl<S: Field, R: Group<S>> Clone for Ciphertext<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            capsule: self.capsule.clone(),
+            sealed: self.sealed.clone(),
+        }
+    }
+}
+
+/// Encrypt `plaintext` to `public_key = g^a`
+pub fn encrypt<S: Field, R: Group<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    g: &R,
+    public_key: &R,
+    plaintext: &[u8],
+) -> SharingResult<Ciphertext<S, R>> {
+    if !public_key.is_valid() {
+        return Err(SharingError::ShareInvalidRecipientKey);
+    }
+    let (capsule, shared) = Capsule::new(rng, g, public_key)?;
+    let mut aad = Vec::new();
+    capsule.append_bytes(&mut aad);
+    // The key is only used once so a fixed nonce is safe
+    let sealed = cipher::<S, R>(&shared)
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| SharingError::ShareInvalidValue)?;
+    Ok(Ciphertext { capsule, sealed })
+}
+
+impl<S: Field, R: Group<S>> Ciphertext<S, R> {
+    /// The capsule proxies re-encrypt
+    pub fn capsule(&self) -> &Capsule<S, R> {
+        &self.capsule
+    }
+
+    /// Decrypt with the secret key `a` the data was encrypted to.
+    /// Fails with `SharingError::PreDecryptionFailed` if it was encrypted to another key
+    /// or modified
+    pub fn decrypt(&self, g: &R, secret_key: &S) -> SharingResult<Vec<u8>> {
+        self.capsule.verify(g)?;
+        self.open(&mul(&self.capsule.base(), secret_key))
+    }
+
+    fn open(&self, shared: &R) -> SharingResult<Vec<u8>> {
+        let mut aad = Vec::new();
+        self.capsule.append_bytes(&mut aad);
+        cipher::<S, R>(shared)
+            .decrypt(
+                Nonce::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: &self.sealed,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| SharingError::PreDecryptionFailed)
+    }
+
+    /// Encode the ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        self.capsule.append_bytes(&mut out);
+        out.extend_from_slice(&(self.sealed.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.sealed);
+        out
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for Ciphertext<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        read_version(&mut reader)?;
+        let capsule = Capsule::read(&mut reader)?;
+        let sealed = reader.field()?.to_vec();
+        reader.finish()?;
+        Ok(Self { capsule, sealed })
+    }
+}
+
+/// A proxy's share of the re-encryption key from Alice to Bob, cleared on drop
+pub struct KeyFragment<S: Field, R: Group<S>> {
+    identifier: u32,
+    /// rk_i, the share of a / d
+    key: S,
+    /// g^rk_i
+    commitment: R,
+    /// Alice's fresh key X = g^x of the delegation, Bob derives d from Bob^x
+    precursor: R,
+}
+
+/// Split the re-encryption key from `delegating_key` to `receiving_public_key` into
+/// `limit` fragments, any `threshold` of which re-encrypt. Fragment `i` in the result
+/// has identifier `i + 1`
+pub fn generate_key_fragments<S: Field, R: Group<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    g: &R,
+    delegating_key: &S,
+    receiving_public_key: &R,
+    threshold: usize,
+    limit: usize,
+) -> SharingResult<Vec<KeyFragment<S, R>>> {
+    if threshold < 1 {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    if limit < threshold {
+        return Err(SharingError::ShareLimitLessThanThreshold);
+    }
+    if !receiving_public_key.is_valid() {
+        return Err(SharingError::ShareInvalidRecipientKey);
+    }
+    let mut x = S::random(rng);
+    let precursor = mul(g, &x);
+    let exchanged = mul(receiving_public_key, &x);
+    x.zeroize();
+    let d = delegation_secret::<S, R>(&precursor, receiving_public_key, &exchanged)?;
+
+    // f(0) = a / d
+    let mut intercept = d.invert()?;
+    intercept.scalar_mul_assign(delegating_key);
+    let polynomial = Polynomial::new(rng, &intercept, threshold);
+    intercept.zeroize();
+    Ok((1..=limit as u32)
+        .map(|identifier| {
+            let key = polynomial.evaluate(&S::from_usize(identifier as usize));
+            KeyFragment {
+                identifier,
+                commitment: mul(g, &key),
+                key,
+                precursor: copy(&precursor),
+            }
+        })
+        .collect())
+}
+
+impl<S: Field, R: Group<S>> KeyFragment<S, R> {
+    /// The identifier of the fragment
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// The commitment `g^rk_i` Alice publishes so Bob can check the capsule fragments
+    pub fn commitment(&self) -> &R {
+        &self.commitment
+    }
+
+    /// Re-encrypt the capsule of a ciphertext for Bob.
+    /// Fails with `SharingError::PreInvalidCapsule` if the capsule is malformed
+    pub fn reencrypt(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        g: &R,
+        capsule: &Capsule<S, R>,
+    ) -> SharingResult<CapsuleFragment<S, R>> {
+        capsule.verify(g)?;
+        let base = capsule.base();
+        let value = mul(&base, &self.key);
+        let proof = DleqProof::prove(
+            rng,
+            &mut fragment_transcript(self.identifier, &self.precursor),
+            &self.key,
+            (g, &self.commitment),
+            (&base, &value),
+        )?;
+        Ok(CapsuleFragment {
+            identifier: self.identifier,
+            value,
+            commitment: copy(&self.commitment),
+            precursor: copy(&self.precursor),
+            proof,
+        })
+    }
+
+    /// Encode the key fragment
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        out.extend_from_slice(&self.identifier.to_be_bytes());
+        out.extend_from_slice(&self.key.to_bytes());
+        out.extend_from_slice(&self.commitment.to_bytes());
+        out.extend_from_slice(&self.precursor.to_bytes());
+        out
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for KeyFragment<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        read_version(&mut reader)?;
+        let fragment = Self {
+            identifier: read_identifier(&mut reader)?,
+            key: S::from_bytes(reader.take(S::Size::to_usize())?)?,
+            commitment: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            precursor: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+        };
+        reader.finish()?;
+        Ok(fragment)
+    }
+}
+
+impl<S: Field, R: Group<S>> Drop for KeyFragment<S, R> {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl<S: Field, R: Group<S>> Debug for KeyFragment<S, R> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "KeyFragment({}, ..)", self.identifier)
+    }
+}
+
+/// A capsule re-encrypted by one proxy
+#[derive(Debug)]
+pub struct CapsuleFragment<S: Field, R: Group<S>> {
+    identifier: u32,
+    /// (E V)^rk_i
+    value: R,
+    commitment: R,
+    precursor: R,
+    proof: DleqProof<S>,
+}
+
+impl<S: Field, R: Group<S>> CapsuleFragment<S, R> {
+    /// The identifier of the key fragment that made it
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// The commitment of the key fragment that made it
+    pub fn commitment(&self) -> &R {
+        &self.commitment
+    }
+
+    /// Check that the fragment re-encrypts `capsule` with the key of its commitment.
+    /// Fails with `SharingError::PreInvalidFragment` if it doesn't
+    pub fn verify(&self, g: &R, capsule: &Capsule<S, R>) -> SharingResult<()> {
+        let base = capsule.base();
+        self.proof
+            .verify(
+                &mut fragment_transcript(self.identifier, &self.precursor),
+                (g, &self.commitment),
+                (&base, &self.value),
+            )
+            .map_err(|_| SharingError::PreInvalidFragment)
+    }
+
+    /// Encode the capsule fragment
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VERSION];
+        out.extend_from_slice(&self.identifier.to_be_bytes());
+        out.extend_from_slice(&self.value.to_bytes());
+        out.extend_from_slice(&self.commitment.to_bytes());
+        out.extend_from_slice(&self.precursor.to_bytes());
+        self.proof.append_bytes(&mut out);
+        out
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for CapsuleFragment<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            identifier: self.identifier,
+            value: copy(&self.value),
+            commitment: copy(&self.commitment),
+            precursor: copy(&self.precursor),
+            proof: DleqProof::try_from(&self.proof.to_bytes()[..]).unwrap(),
+        }
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for CapsuleFragment<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data: value };
+        read_version(&mut reader)?;
+        let fragment = Self {
+            identifier: read_identifier(&mut reader)?,
+            value: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            commitment: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            precursor: R::from_bytes(reader.take(R::Size::to_usize())?)?,
+            proof: DleqProof::try_from(reader.take(DleqProof::<S>::size())?)?,
+        };
+        reader.finish()?;
+        Ok(fragment)
+    }
+}
+
+/// Decrypt a ciphertext Alice encrypted to `delegating_public_key` with Bob's
+/// `receiving_key` and the capsule fragments of at least `threshold` proxies.
+/// Fails with `SharingError::PreInvalidFragment` if a fragment doesn't verify or is
+/// from another delegation, and with `SharingError::PreDecryptionFailed` if there are
+/// too few fragments or the ciphertext was modified
+pub fn decrypt_reencrypted<S: Field, R: Group<S>>(
+    g: &R,
+    receiving_key: &S,
+    ciphertext: &Ciphertext<S, R>,
+    fragments: &[CapsuleFragment<S, R>],
+) -> SharingResult<Vec<u8>> {
+    let precursor = &fragments
+        .first()
+        .ok_or(SharingError::PreDecryptionFailed)?
+        .precursor;
+    ciphertext.capsule.verify(g)?;
+    for fragment in fragments {
+        if fragment.precursor.to_bytes() != precursor.to_bytes() {
+            return Err(SharingError::PreInvalidFragment);
+        }
+        fragment.verify(g, &ciphertext.capsule)?;
+    }
+    let x_coordinates = fragments
+        .iter()
+        .map(|f| S::from_usize(f.identifier as usize))
+        .collect::<Vec<_>>();
+    let coefficients = lagrange_coefficients(&x_coordinates)
+        .map_err(|_| SharingError::ShareDuplicateIdentifier)?;
+
+    // (E V)^(a / d) interpolated in the exponent
+    let mut combined = R::zero();
+    for (fragment, coefficient) in fragments.iter().zip(coefficients.iter()) {
+        combined.add_assign(&mul(&fragment.value, coefficient));
+    }
+    let receiving_public_key = mul(g, receiving_key);
+    let exchanged = mul(precursor, receiving_key);
+    let mut d = delegation_secret::<S, R>(precursor, &receiving_public_key, &exchanged)?;
+    combined.scalar_mul_assign(&d);
+    d.zeroize();
+    let result = ciphertext.open(&combined);
+    combined.clear();
+    result
+}
+
+/// H(E, V), the challenge of a capsule's proof
+fn capsule_challenge<S: Field, R: Group<S>>(e: &R, v: &R) -> SharingResult<S> {
+    let mut transcript = Transcript::new(PROTOCOL);
+    transcript.append_point::<S, R>(b"e", e);
+    transcript.append_point::<S, R>(b"v", v);
+    transcript.challenge_scalar(b"capsule")
+}
+
+/// d = H(X, Bob, Bob^x), known only to Alice and Bob
+fn delegation_secret<S: Field, R: Group<S>>(
+    precursor: &R,
+    receiving_public_key: &R,
+    exchanged: &R,
+) -> SharingResult<S> {
+    let mut transcript = Transcript::new(PROTOCOL);
+    transcript.append_point::<S, R>(b"precursor", precursor);
+    transcript.append_point::<S, R>(b"receiving key", receiving_public_key);
+    transcript.append_point::<S, R>(b"exchanged", exchanged);
+    transcript.challenge_scalar(b"delegation")
+}
+
+fn fragment_transcript<S: Field, R: Group<S>>(identifier: u32, precursor: &R) -> Transcript {
+    let mut transcript = Transcript::new(PROTOCOL);
+    transcript.append_u64(b"identifier", identifier as u64);
+    transcript.append_point::<S, R>(b"precursor", precursor);
+    transcript
+}
+
+/// The AEAD keyed with the hash of the shared point
+fn cipher<S: Field, R: Group<S>>(shared: &R) -> ChaCha20Poly1305 {
+    let mut hasher = Sha256::new();
+    hasher.input(KEY_DOMAIN);
+    hasher.input(shared.to_bytes());
+    let mut key = hasher.result();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    cipher
+}
+
+fn read_version(reader: &mut Reader<'_>) -> SharingResult<()> {
+    match reader.take(1)?[0] {
+        VERSION => Ok(()),
+        v => Err(SharingError::ShareUnsupportedVersion(v)),
+    }
+}
+
+fn read_identifier(reader: &mut Reader<'_>) -> SharingResult<u32> {
+    match reader.u32()? {
+        0 => Err(SharingError::ShareInvalidIdentifier),
+        identifier => Ok(identifier),
+    }
+}
+
+fn copy<S: Field, R: Group<S>>(p: &R) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
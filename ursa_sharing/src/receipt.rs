@@ -0,0 +1,441 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Signed receipts of the shares a dealer hands out.
+//!
+//! A dealer who sends shares, or encrypted shares, to a list of recipients can later be
+//! accused of sending someone the wrong share, and a recipient can claim it was sent a
+//! different one. [`Dealer::distribute`] settles both. It assigns the payloads to the
+//! recipients with the [`Permutation`] derived from the session id, so the dealer can't
+//! choose who gets which payload and anyone can recompute who should have, and gives
+//! each recipient a [`Receipt`]: a hiding commitment to its payload signed by the dealer
+//! with a Schnorr signature, and the [`Opening`] of the commitment.
+//!
+//! In a dispute the recipient reveals its payload and opening. Anyone with the dealer's
+//! public key checks the signature with [`Receipt::verify`], that the payload is the one
+//! committed to with [`Receipt::verify_opening`], and that it was due to the recipient
+//! with [`Receipt::verify_assignment`]. The dealer can't disown a receipt it signed and
+//! the recipient can't open one to another payload. Until it is opened a receipt
+//! reveals nothing about the payload, so it can be published or escrowed.
+//!
+//! The receipt is encoded as
+//!
+//! ```text
+//! session length (4, big endian) | session | recipient length (4, big endian) |
+//! recipient | index (4, big endian) | commitment (32) | challenge | response
+//! ```
+
+use super::{
+    error::{SharingError, SharingResult},
+    shuffle::Permutation,
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+const ASSIGNMENT_DOMAIN: &[u8] = b"ursa_sharing receipt assignment v1";
+const COMMITMENT_DOMAIN: &[u8] = b"ursa_sharing receipt commitment v1";
+const SIGNATURE_DOMAIN: &[u8] = b"ursa_sharing receipt signature v1";
+const COMMITMENT_BYTES: usize = 32;
+const OPENING_BYTES: usize = 32;
+
+/// The permutation assigning payloads to `recipients` recipients in `session`:
+/// recipient `i` gets payload `indices()[i]`
+pub fn assignment(session: &[u8], recipients: usize) -> Permutation {
+    let mut transcript = Transcript::new(ASSIGNMENT_DOMAIN);
+    transcript.append_message(b"session", session);
+    let mut seed = [0u8; 32];
+    transcript.challenge_bytes(b"seed", &mut seed);
+    Permutation::from_seed(&seed, recipients)
+}
+
+/// What a recipient is sent: the index of its payload, its receipt and the opening
+pub type Delivery<S, R> = (usize, Receipt<S, R>, Opening);
+
+/// A dealer's key for signing receipts, cleared on drop
+pub struct Dealer<S: Field, R: Group<S>> {
+    g: R,
+    secret: S,
+    public_key: R,
+}
+
+impl<S: Field, R: Group<S>> Dealer<S, R> {
+    /// A dealer with the signing key `secret` for the generator `g`
+    pub fn new(g: &R, secret: S) -> Self {
+        Self {
+            g: copy(g),
+            public_key: mul(g, &secret),
+            secret,
+        }
+    }
+
+    /// A dealer with a random signing key for the generator `g`
+    pub fn random(rng: &mut (impl RngCore + CryptoRng), g: &R) -> Self {
+        Self::new(g, S::random(rng))
+    }
+
+    /// The public key receipts are checked with, `g^secret`
+    pub fn public_key(&self) -> &R {
+        &self.public_key
+    }
+
+    /// Assign `payloads` to `recipients`, which must be distinct and as many as the
+    /// payloads. Returns, for each recipient in order, the index of its payload, its
+    /// receipt and the opening to send along with the payload.
+    /// Fails with `SharingError::ReceiptAssignmentInvalid` if the recipients are invalid
+    pub fn distribute<P: AsRef<[u8]>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        session: &[u8],
+        recipients: &[&[u8]],
+        payloads: &[P],
+    ) -> SharingResult<Vec<Delivery<S, R>>> {
+        check_recipients(recipients)?;
+        if recipients.len() != payloads.len() {
+            return Err(SharingError::ReceiptAssignmentInvalid);
+        }
+        let assignment = assignment(session, recipients.len());
+        recipients
+            .iter()
+            .zip(assignment.indices())
+            .map(|(recipient, index)| {
+                let (receipt, opening) =
+                    self.issue(rng, session, recipient, *index, payloads[*index].as_ref())?;
+                Ok((*index, receipt, opening))
+            })
+            .collect()
+    }
+
+    /// Sign a receipt of `payload`, the one at `index`, sent to `recipient` in `session`.
+    /// [`Dealer::distribute`] assigns and issues them for a whole session
+    pub fn issue(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        session: &[u8],
+        recipient: &[u8],
+        index: usize,
+        payload: &[u8],
+    ) -> SharingResult<(Receipt<S, R>, Opening)> {
+        if index > u32::MAX as usize {
+            return Err(SharingError::ReceiptAssignmentInvalid);
+        }
+        let mut opening = Opening([0u8; OPENING_BYTES]);
+        rng.fill_bytes(&mut opening.0);
+        let commitment = commit(session, recipient, index as u32, payload, &opening);
+
+        // s = k - c * x
+        let mut k = S::random(rng);
+        let nonce = mul(&self.g, &k);
+        let challenge = signature_challenge::<S, R>(
+            &self.public_key,
+            session,
+            recipient,
+            index as u32,
+            &commitment,
+            &nonce,
+        );
+        let challenge = match challenge {
+            Ok(challenge) => challenge,
+            Err(e) => {
+                k.zeroize();
+                return Err(e);
+            }
+        };
+        let mut cx = copy(&challenge);
+        cx.scalar_mul_assign(&self.secret);
+        let mut response = copy(&k);
+        response.sub_assign(&cx);
+        k.zeroize();
+        cx.zeroize();
+        let receipt = Receipt {
+            session: session.to_vec(),
+            recipient: recipient.to_vec(),
+            index: index as u32,
+            commitment,
+            challenge,
+            response,
+            _group: PhantomData,
+        };
+        Ok((receipt, opening))
+    }
+}
+
+impl<S: Field, R: Group<S>> Drop for Dealer<S, R> {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl<S: Field, R: Group<S>> Debug for Dealer<S, R> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Dealer(..)")
+    }
+}
+
+/// The secret that opens a receipt's commitment to its payload, cleared on drop
+pub struct Opening([u8; OPENING_BYTES]);
+
+impl Opening {
+    /// Encode the opening
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for Opening {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != OPENING_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut opening = [0u8; OPENING_BYTES];
+        opening.copy_from_slice(value);
+        Ok(Self(opening))
+    }
+}
+
+impl Drop for Opening {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for Opening {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Opening(..)")
+    }
+}
+
+/// A dealer's signed commitment to the payload it sent a recipient
+#[derive(Debug)]
+pub struct Receipt<S: Field, R: Group<S>> {
+    session: Vec<u8>,
+    recipient: Vec<u8>,
+    index: u32,
+    commitment: [u8; COMMITMENT_BYTES],
+    challenge: S,
+    response: S,
+    _group: PhantomData<R>,
+}
+
+impl<S: Field, R: Group<S>> Receipt<S, R> {
+    /// The session the payload was sent in
+    pub fn session(&self) -> &[u8] {
+        &self.session
+    }
+
+    /// Who the payload was sent to
+    pub fn recipient(&self) -> &[u8] {
+        &self.recipient
+    }
+
+    /// The index of the payload among the session's payloads
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Check that the dealer with `public_key` for the generator `g` signed the receipt.
+    /// Fails with `SharingError::ReceiptSignatureInvalid` if it didn't
+    pub fn verify(&self, g: &R, public_key: &R) -> SharingResult<()> {
+        // k = s + c * x, so g^k = g^s * y^c
+        let mut nonce = mul(g, &self.response);
+        nonce.add_assign(&mul(public_key, &self.challenge));
+        let challenge = signature_challenge::<S, R>(
+            public_key,
+            &self.session,
+            &self.recipient,
+            self.index,
+            &self.commitment,
+            &nonce,
+        )
+        .map_err(|_| SharingError::ReceiptSignatureInvalid)?;
+        if public_key.is_valid() && challenge.to_bytes() == self.challenge.to_bytes() {
+            Ok(())
+        } else {
+            Err(SharingError::ReceiptSignatureInvalid)
+        }
+    }
+
+    /// Check that `opening` opens the receipt to `payload`.
+    /// Fails with `SharingError::ReceiptOpeningInvalid` if it doesn't
+    pub fn verify_opening(&self, payload: &[u8], opening: &Opening) -> SharingResult<()> {
+        let commitment = commit(&self.session, &self.recipient, self.index, payload, opening);
+        if commitment == self.commitment {
+            Ok(())
+        } else {
+            Err(SharingError::ReceiptOpeningInvalid)
+        }
+    }
+
+    /// Check that the payload was the one the session's assignment gives the recipient
+    /// among `recipients`.
+    /// Fails with `SharingError::ReceiptAssignmentInvalid` if it wasn't
+    pub fn verify_assignment(&self, recipients: &[&[u8]]) -> SharingResult<()> {
+        check_recipients(recipients)?;
+        let position = recipients
+            .iter()
+            .position(|r| *r == &self.recipient[..])
+            .ok_or(SharingError::ReceiptAssignmentInvalid)?;
+        if assignment(&self.session, recipients.len()).indices()[position] == self.index() {
+            Ok(())
+        } else {
+            Err(SharingError::ReceiptAssignmentInvalid)
+        }
+    }
+
+    /// Encode the receipt
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.session.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.session);
+        out.extend_from_slice(&(self.recipient.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.recipient);
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&self.commitment);
+        out.extend_from_slice(self.challenge.to_bytes().as_ref());
+        out.extend_from_slice(self.response.to_bytes().as_ref());
+        out
+    }
+
+    /// Decode a receipt using the acceptance rules of `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let mut rest = value;
+        let session = read_bytes(&mut rest)?;
+        let recipient = read_bytes(&mut rest)?;
+        let s_size = S::Size::to_usize();
+        if rest.len() != 4 + COMMITMENT_BYTES + 2 * s_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut commitment = [0u8; COMMITMENT_BYTES];
+        commitment.copy_from_slice(&rest[4..4 + COMMITMENT_BYTES]);
+        let scalars = &rest[4 + COMMITMENT_BYTES..];
+        Ok(Self {
+            session,
+            recipient,
+            index: read_u32(rest),
+            commitment,
+            challenge: S::from_bytes_with(&scalars[..s_size], strictness)?,
+            response: S::from_bytes_with(&scalars[s_size..], strictness)?,
+            _group: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for Receipt<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for Receipt<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            recipient: self.recipient.clone(),
+            index: self.index,
+            commitment: self.commitment,
+            challenge: copy(&self.challenge),
+            response: copy(&self.response),
+            _group: PhantomData,
+        }
+    }
+}
+
+fn check_recipients(recipients: &[&[u8]]) -> SharingResult<()> {
+    let distinct = recipients.iter().collect::<BTreeSet<_>>();
+    if recipients.is_empty()
+        || recipients.len() > u32::MAX as usize
+        || distinct.len() != recipients.len()
+    {
+        return Err(SharingError::ReceiptAssignmentInvalid);
+    }
+    Ok(())
+}
+
+fn commit(
+    session: &[u8],
+    recipient: &[u8],
+    index: u32,
+    payload: &[u8],
+    opening: &Opening,
+) -> [u8; COMMITMENT_BYTES] {
+    let mut transcript = Transcript::new(COMMITMENT_DOMAIN);
+    transcript.append_message(b"session", session);
+    transcript.append_message(b"recipient", recipient);
+    transcript.append_u64(b"index", u64::from(index));
+    transcript.append_message(b"payload", payload);
+    transcript.append_message(b"opening", &opening.0);
+    let mut commitment = [0u8; COMMITMENT_BYTES];
+    transcript.challenge_bytes(b"commitment", &mut commitment);
+    commitment
+}
+
+fn signature_challenge<S: Field, R: Group<S>>(
+    public_key: &R,
+    session: &[u8],
+    recipient: &[u8],
+    index: u32,
+    commitment: &[u8],
+    nonce: &R,
+) -> SharingResult<S> {
+    let mut transcript = Transcript::new(SIGNATURE_DOMAIN);
+    transcript.append_point(b"public key", public_key);
+    transcript.append_message(b"session", session);
+    transcript.append_message(b"recipient", recipient);
+    transcript.append_u64(b"index", u64::from(index));
+    transcript.append_message(b"commitment", commitment);
+    transcript.append_point(b"nonce", nonce);
+    transcript.challenge_scalar(b"challenge")
+}
+
+fn read_bytes(rest: &mut &[u8]) -> SharingResult<Vec<u8>> {
+    if rest.len() < 4 {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let len = read_u32(rest) as usize;
+    if rest.len() - 4 < len {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let bytes = rest[4..4 + len].to_vec();
+    *rest = &rest[4 + len..];
+    Ok(bytes)
+}
+
+fn read_u32(value: &[u8]) -> u32 {
+    let mut n = [0u8; 4];
+    n.copy_from_slice(&value[..4]);
+    u32::from_be_bytes(n)
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
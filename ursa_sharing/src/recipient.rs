@@ -0,0 +1,311 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Encrypt shares to their recipients.
+//!
+//! Each share is encrypted to a recipient's X25519 or P-256 public key
+//! so a dealer never has to store or send a plaintext share. The dealer
+//! generates an ephemeral key per share and derives a ChaCha20-Poly1305 key with
+//! HKDF-SHA256 from the Diffie-Hellman shared secret, the ephemeral public key and
+//! the recipient's public key. The share identifier and key type are authenticated
+//! as associated data so a ciphertext can't be relabelled for another share.
+//!
+//! Use `shamir::Scheme::split_encrypted` or `feldman::Scheme::split_encrypted`
+//! to split and encrypt in one step, or [`encrypt_share`] for shares created elsewhere.
+
+use super::{
+    error::{SharingError, SharingResult},
+    shamir::Share,
+};
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::convert::TryFrom;
+use hkdf::Hkdf;
+use p256::{
+    elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint},
+    AffinePoint, EncodedPoint, ProjectivePoint,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const KIND_X25519: u8 = 1;
+const KIND_P256: u8 = 2;
+const X25519_KEY_BYTES: usize = 32;
+const P256_KEY_BYTES: usize = 33;
+const TAG_BYTES: usize = 16;
+/// kind, identifier
+const HEADER_BYTES: usize = 1 + 4;
+const KDF_INFO: &[u8] = b"ursa_sharing recipient share v1";
+
+/// The public key of a share recipient
+#[derive(Clone, Debug)]
+pub enum RecipientPublicKey {
+    /// An X25519 public key
+    X25519(x25519_dalek::PublicKey),
+    /// A P-256 public key
+    P256(EncodedPoint),
+}
+
+impl RecipientPublicKey {
+    fn kind(&self) -> u8 {
+        match self {
+            Self::X25519(_) => KIND_X25519,
+            Self::P256(_) => KIND_P256,
+        }
+    }
+
+    /// The compressed encoding used for key derivation
+    fn to_bytes(&self) -> SharingResult<Vec<u8>> {
+        match self {
+            Self::X25519(pk) => Ok(pk.as_bytes().to_vec()),
+            Self::P256(pk) => Ok(p256_point(pk)?
+                .to_affine()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec()),
+        }
+    }
+}
+
+/// The secret key a recipient uses to decrypt their share
+pub enum RecipientSecretKey {
+    /// An X25519 secret key
+    X25519(x25519_dalek::StaticSecret),
+    /// A P-256 secret key
+    P256(p256::SecretKey),
+}
+
+impl RecipientSecretKey {
+    /// Get the public key for this secret key
+    pub fn public_key(&self) -> RecipientPublicKey {
+        match self {
+            Self::X25519(sk) => RecipientPublicKey::X25519(sk.into()),
+            Self::P256(sk) => RecipientPublicKey::P256(
+                (ProjectivePoint::generator() * sk.secret_scalar())
+                    .to_affine()
+                    .to_encoded_point(true),
+            ),
+        }
+    }
+}
+
+/// A share encrypted to a single recipient
+#[derive(Clone, Debug)]
+pub struct EncryptedShare {
+    kind: u8,
+    identifier: u32,
+    ephemeral: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedShare {
+    /// Get the identifier of the encrypted share
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// Decrypt the share with the recipient's secret key
+    pub fn decrypt(&self, secret_key: &RecipientSecretKey) -> SharingResult<Share> {
+        let recipient = secret_key.public_key();
+        if recipient.kind() != self.kind {
+            return Err(SharingError::ShareInvalidRecipientKey);
+        }
+        let mut shared = match secret_key {
+            RecipientSecretKey::X25519(sk) => {
+                let mut epk = [0u8; X25519_KEY_BYTES];
+                epk.copy_from_slice(&self.ephemeral);
+                sk.diffie_hellman(&epk.into()).as_bytes().to_vec()
+            }
+            RecipientSecretKey::P256(sk) => {
+                let epk = EncodedPoint::from_bytes(&self.ephemeral)
+                    .map_err(|_| SharingError::ShareInvalidEncoding)?;
+                p256_diffie_hellman(sk.secret_scalar(), &p256_point(&epk)?)
+            }
+        };
+        let cipher = derive_cipher(&shared, &self.ephemeral, &recipient.to_bytes()?);
+        shared.zeroize();
+        let cipher = cipher?;
+
+        let header = self.header();
+        let res = cipher
+            .decrypt(
+                Nonce::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| SharingError::ShareDecryptionFailed);
+        let mut value = res?;
+        let share = Share {
+            identifier: self.identifier,
+            value: value.clone(),
+        };
+        value.zeroize();
+        Ok(share)
+    }
+
+    /// Serialize the encrypted share
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.header().to_vec();
+        o.extend_from_slice(&self.ephemeral);
+        o.extend_from_slice(&self.ciphertext);
+        o
+    }
+
+    fn header(&self) -> [u8; HEADER_BYTES] {
+        let mut h = [0u8; HEADER_BYTES];
+        h[0] = self.kind;
+        h[1..].copy_from_slice(&self.identifier.to_be_bytes());
+        h
+    }
+}
+
+impl TryFrom<&[u8]> for EncryptedShare {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() < HEADER_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let kind = data[0];
+        let key_bytes = match kind {
+            KIND_X25519 => X25519_KEY_BYTES,
+            KIND_P256 => P256_KEY_BYTES,
+            _ => return Err(SharingError::ShareInvalidEncoding),
+        };
+        if data.len() < HEADER_BYTES + key_bytes + TAG_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&data[1..HEADER_BYTES]);
+        let identifier = u32::from_be_bytes(identifier);
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(Self {
+            kind,
+            identifier,
+            ephemeral: data[HEADER_BYTES..HEADER_BYTES + key_bytes].to_vec(),
+            ciphertext: data[HEADER_BYTES + key_bytes..].to_vec(),
+        })
+    }
+}
+
+/// Encrypt `share` to `recipient` using a fresh ephemeral key
+pub fn encrypt_share(
+    rng: &mut (impl RngCore + CryptoRng),
+    share: &Share,
+    recipient: &RecipientPublicKey,
+) -> SharingResult<EncryptedShare> {
+    let (ephemeral, mut shared) = match recipient {
+        RecipientPublicKey::X25519(pk) => {
+            let esk = x25519_dalek::EphemeralSecret::new(&mut *rng);
+            let epk = x25519_dalek::PublicKey::from(&esk);
+            let shared = esk.diffie_hellman(pk);
+            // Reject low order public keys that give a known shared secret
+            if shared.as_bytes().ct_eq(&[0u8; 32]).unwrap_u8() == 1 {
+                return Err(SharingError::ShareInvalidRecipientKey);
+            }
+            (epk.as_bytes().to_vec(), shared.as_bytes().to_vec())
+        }
+        RecipientPublicKey::P256(pk) => {
+            let point = p256_point(pk)?;
+            let esk = p256::SecretKey::random(&mut *rng);
+            let epk = (ProjectivePoint::generator() * esk.secret_scalar())
+                .to_affine()
+                .to_encoded_point(true);
+            let shared = p256_diffie_hellman(esk.secret_scalar(), &point);
+            (epk.as_bytes().to_vec(), shared)
+        }
+    };
+    let cipher = derive_cipher(&shared, &ephemeral, &recipient.to_bytes()?);
+    shared.zeroize();
+    let cipher = cipher?;
+
+    let mut encrypted = EncryptedShare {
+        kind: recipient.kind(),
+        identifier: share.identifier,
+        ephemeral,
+        ciphertext: Vec::new(),
+    };
+    let header = encrypted.header();
+    // The key is only used once so a fixed nonce is safe
+    let res = cipher.encrypt(
+        Nonce::from_slice(&[0u8; 12]),
+        Payload {
+            msg: &share.value,
+            aad: &header,
+        },
+    );
+    encrypted.ciphertext = res.map_err(|_| SharingError::ShareInvalidValue)?;
+    Ok(encrypted)
+}
+
+/// Encrypt each share to the recipient at the same position
+pub fn encrypt_shares(
+    rng: &mut (impl RngCore + CryptoRng),
+    shares: &[Share],
+    recipients: &[RecipientPublicKey],
+) -> SharingResult<Vec<EncryptedShare>> {
+    if shares.len() != recipients.len() {
+        return Err(SharingError::ShareRecipientCount(
+            shares.len(),
+            recipients.len(),
+        ));
+    }
+    shares
+        .iter()
+        .zip(recipients.iter())
+        .map(|(share, recipient)| encrypt_share(rng, share, recipient))
+        .collect()
+}
+
+fn p256_point(pk: &EncodedPoint) -> SharingResult<ProjectivePoint> {
+    let point = AffinePoint::from_encoded_point(pk);
+    if point.is_some().unwrap_u8() == 1 {
+        Ok(ProjectivePoint::from(point.unwrap()))
+    } else {
+        Err(SharingError::ShareInvalidRecipientKey)
+    }
+}
+
+/// The x-coordinate of the shared point
+fn p256_diffie_hellman(scalar: &p256::Scalar, point: &ProjectivePoint) -> Vec<u8> {
+    let mut shared = (point * scalar).to_affine().to_encoded_point(false);
+    let x = shared.x().to_vec();
+    shared.zeroize();
+    x
+}
+
+fn derive_cipher(
+    shared: &[u8],
+    ephemeral: &[u8],
+    recipient: &[u8],
+) -> SharingResult<ChaCha20Poly1305> {
+    let mut info = KDF_INFO.to_vec();
+    info.extend_from_slice(ephemeral);
+    info.extend_from_slice(recipient);
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, shared)
+        .expand(&info, &mut key)
+        .map_err(|_| SharingError::ShareDecryptionFailed)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    Ok(cipher)
+}
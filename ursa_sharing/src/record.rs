@@ -0,0 +1,332 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Recordings of the public messages of multi-party protocols for external audit.
+//!
+//! A [`Recording`] keeps the public messages of a DKG, FROST or threshold decryption
+//! session in the order they were seen, e.g. by the coordinator or a bulletin board,
+//! so a third party can audit the ceremony and replay it. Every [`Entry`] holds its
+//! sequence number, the kind of message, the sender, the receiver and the encoded
+//! message, and a SHA-256 hash chained over the label of the session and all earlier
+//! entries. [`Recording::head`] commits to the whole session: when the participants sign
+//! or publish it, a recording with a changed, dropped or reordered entry is detected.
+//!
+//! Only messages implementing [`Recordable`] are recorded. The round one shares of a DKG
+//! are private and don't implement it, justifications reveal their share on purpose.
+//! Protocols built by the application, like the partial decryptions of threshold
+//! ElGamal, implement [`Recordable`] for their own messages.
+//!
+//! [`Recording::to_cbor`] exports the CBOR map
+//! `{"version", "label", "entries": [{"sequence", "kind", "sender", "receiver", "payload", "hash"}]}`
+//! where `receiver` is null for broadcasts. [`Recording::from_cbor`] checks the hash chain
+//! and [`Recording::messages`] replays the messages of one kind.
+//!
+//! ```
+//! # use ursa_sharing::{dkg::Round1Broadcast, record::Recording, Field, Group, error::SharingResult};
+//! # fn example<S: Field, R: Group<S>>(broadcasts: &[Round1Broadcast<S, R>]) -> SharingResult<()> {
+//! let mut recording = Recording::new(b"dkg ceremony 7");
+//! for b in broadcasts {
+//!     recording.record(b);
+//! }
+//! let exported = recording.to_cbor();
+//! // The auditor
+//! let imported = Recording::from_cbor(&exported)?;
+//! assert_eq!(imported.head(), recording.head());
+//! let broadcasts = imported.messages::<Round1Broadcast<S, R>>()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::error::{SharingError, SharingResult};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// The current recording format version
+pub const VERSION: u8 = 1;
+/// The size of the hash of an entry
+pub const HASH_BYTES: usize = 32;
+
+const DOMAIN: &[u8] = b"ursa_sharing recording";
+
+/// A public protocol message that can be recorded and replayed
+pub trait Recordable: Sized {
+    /// Names the kind of message, messages are only replayed as their own kind
+    const KIND: &'static str;
+
+    /// The participant that sent the message
+    fn sender(&self) -> u32;
+
+    /// The participant the message was sent to, `None` for broadcasts
+    fn receiver(&self) -> Option<u32> {
+        None
+    }
+
+    /// Serialize the message
+    fn to_record_bytes(&self) -> Vec<u8>;
+
+    /// Deserialize a message from [`Recordable::to_record_bytes`]
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self>;
+}
+
+/// A recorded message and the hash chaining it to the earlier entries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    sequence: u64,
+    kind: String,
+    sender: u32,
+    receiver: Option<u32>,
+    payload: Vec<u8>,
+    hash: [u8; HASH_BYTES],
+}
+
+impl Entry {
+    /// The position of the entry in the recording, starting at zero
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The kind of message
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// The participant that sent the message
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The participant the message was sent to, `None` for broadcasts
+    pub fn receiver(&self) -> Option<u32> {
+        self.receiver
+    }
+
+    /// The encoded message
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// `SHA-256(domain | previous hash | sequence | kind | sender | receiver | payload)`
+    pub fn hash(&self) -> [u8; HASH_BYTES] {
+        self.hash
+    }
+
+    fn chain(&self, previous: &[u8; HASH_BYTES]) -> [u8; HASH_BYTES] {
+        let mut hasher = Sha256::new();
+        hasher.input(DOMAIN);
+        hasher.input(previous);
+        hasher.input(self.sequence.to_be_bytes());
+        hasher.input((self.kind.len() as u32).to_be_bytes());
+        hasher.input(self.kind.as_bytes());
+        hasher.input(self.sender.to_be_bytes());
+        match self.receiver {
+            None => hasher.input([0u8]),
+            Some(r) => {
+                hasher.input([1u8]);
+                hasher.input(r.to_be_bytes());
+            }
+        }
+        hasher.input((self.payload.len() as u32).to_be_bytes());
+        hasher.input(&self.payload);
+        let mut o = [0u8; HASH_BYTES];
+        o.copy_from_slice(&hasher.result());
+        o
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(text("sequence"), Value::Integer(self.sequence.into()));
+        map.insert(text("kind"), text(&self.kind));
+        map.insert(text("sender"), Value::Integer(self.sender.into()));
+        map.insert(
+            text("receiver"),
+            self.receiver
+                .map_or(Value::Null, |r| Value::Integer(r.into())),
+        );
+        map.insert(text("payload"), Value::Bytes(self.payload.clone()));
+        map.insert(text("hash"), Value::Bytes(self.hash.to_vec()));
+        Value::Map(map)
+    }
+
+    fn from_value(value: &Value) -> SharingResult<Self> {
+        let map = read_map(value, 6)?;
+        let receiver = match get(map, "receiver")? {
+            Value::Null => None,
+            v => Some(read_integer(v)?),
+        };
+        let hash = read_bytes(get(map, "hash")?)?;
+        if hash.len() != HASH_BYTES {
+            return Err(SharingError::RecordingInvalid);
+        }
+        let mut entry = Self {
+            sequence: read_integer(get(map, "sequence")?)?,
+            kind: match get(map, "kind")? {
+                Value::Text(t) => t.clone(),
+                _ => return Err(SharingError::RecordingInvalid),
+            },
+            sender: read_integer(get(map, "sender")?)?,
+            receiver,
+            payload: read_bytes(get(map, "payload")?)?.to_vec(),
+            hash: [0u8; HASH_BYTES],
+        };
+        entry.hash.copy_from_slice(hash);
+        Ok(entry)
+    }
+}
+
+/// The ordered public messages of one protocol session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    label: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl Recording {
+    /// Start recording the session named by `label`, e.g. the ceremony identifier
+    pub fn new(label: &[u8]) -> Self {
+        Self {
+            label: label.to_vec(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// The label of the session
+    pub fn label(&self) -> &[u8] {
+        &self.label
+    }
+
+    /// The recorded entries in order
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The hash of the last entry, or of the label when nothing was recorded.
+    /// It commits to the label and every entry
+    pub fn head(&self) -> [u8; HASH_BYTES] {
+        match self.entries.last() {
+            Some(e) => e.hash,
+            None => self.genesis(),
+        }
+    }
+
+    /// Append `message` to the recording
+    pub fn record<T: Recordable>(&mut self, message: &T) {
+        let previous = self.head();
+        let mut entry = Entry {
+            sequence: self.entries.len() as u64,
+            kind: T::KIND.to_string(),
+            sender: message.sender(),
+            receiver: message.receiver(),
+            payload: message.to_record_bytes(),
+            hash: [0u8; HASH_BYTES],
+        };
+        entry.hash = entry.chain(&previous);
+        self.entries.push(entry);
+    }
+
+    /// Decode the messages of kind `T` in the order they were recorded.
+    /// Fails if a message doesn't parse or doesn't match the sender
+    /// and receiver of its entry
+    pub fn messages<T: Recordable>(&self) -> SharingResult<Vec<T>> {
+        self.entries
+            .iter()
+            .filter(|e| e.kind == T::KIND)
+            .map(|e| {
+                let message = T::from_record_bytes(&e.payload)?;
+                if message.sender() != e.sender || message.receiver() != e.receiver {
+                    return Err(SharingError::RecordingInvalid);
+                }
+                Ok(message)
+            })
+            .collect()
+    }
+
+    /// Export the recording as CBOR
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut map = BTreeMap::new();
+        map.insert(text("version"), Value::Integer(VERSION.into()));
+        map.insert(text("label"), Value::Bytes(self.label.clone()));
+        map.insert(
+            text("entries"),
+            Value::Array(self.entries.iter().map(Entry::to_value).collect()),
+        );
+        serde_cbor::to_vec(&Value::Map(map)).expect("CBOR values always serialize")
+    }
+
+    /// Import a recording exported with [`Recording::to_cbor`] and check its hash chain
+    pub fn from_cbor(data: &[u8]) -> SharingResult<Self> {
+        let value =
+            serde_cbor::from_slice::<Value>(data).map_err(|_| SharingError::RecordingInvalid)?;
+        let map = read_map(&value, 3)?;
+        let version = read_integer::<u8>(get(map, "version")?)?;
+        if version != VERSION {
+            return Err(SharingError::RecordingVersion(version));
+        }
+        let mut recording = Self::new(read_bytes(get(map, "label")?)?);
+        let entries = match get(map, "entries")? {
+            Value::Array(a) => a,
+            _ => return Err(SharingError::RecordingInvalid),
+        };
+        for value in entries {
+            let entry = Entry::from_value(value)?;
+            if entry.sequence != recording.entries.len() as u64
+                || entry.hash != entry.chain(&recording.head())
+            {
+                return Err(SharingError::RecordingInvalid);
+            }
+            recording.entries.push(entry);
+        }
+        Ok(recording)
+    }
+
+    fn genesis(&self) -> [u8; HASH_BYTES] {
+        let mut hasher = Sha256::new();
+        hasher.input(DOMAIN);
+        hasher.input([VERSION]);
+        hasher.input((self.label.len() as u32).to_be_bytes());
+        hasher.input(&self.label);
+        let mut o = [0u8; HASH_BYTES];
+        o.copy_from_slice(&hasher.result());
+        o
+    }
+}
+
+fn text(s: &str) -> Value {
+    Value::Text(s.to_string())
+}
+
+fn get<'a>(map: &'a BTreeMap<Value, Value>, key: &str) -> SharingResult<&'a Value> {
+    map.get(&text(key)).ok_or(SharingError::RecordingInvalid)
+}
+
+/// A map with exactly `len` keys, unknown keys are rejected
+fn read_map(value: &Value, len: usize) -> SharingResult<&BTreeMap<Value, Value>> {
+    match value {
+        Value::Map(m) if m.len() == len => Ok(m),
+        _ => Err(SharingError::RecordingInvalid),
+    }
+}
+
+fn read_integer<T: TryFrom<i128>>(value: &Value) -> SharingResult<T> {
+    match value {
+        Value::Integer(i) => T::try_from(*i).map_err(|_| SharingError::RecordingInvalid),
+        _ => Err(SharingError::RecordingInvalid),
+    }
+}
+
+fn read_bytes(value: &Value) -> SharingResult<&[u8]> {
+    match value {
+        Value::Bytes(b) => Ok(b),
+        _ => Err(SharingError::RecordingInvalid),
+    }
+}
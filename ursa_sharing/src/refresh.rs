@@ -0,0 +1,298 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Proactive refresh and resharing of Shamir shares without reconstructing the secret.
+//!
+//! Each of at least `threshold` current shareholders splits its own share for the new
+//! access structure and sends one [`Reshare`] to every new shareholder. A new shareholder
+//! interpolates the reshares it received at zero, weighting each dealer's reshare by its
+//! Lagrange coefficient, which gives a share of the same secret on a fresh polynomial.
+//! Old and new shares can't be mixed, so shares stolen before a refresh become useless.
+//!
+//! Every new shareholder must combine reshares from the same set of dealers,
+//! otherwise the new shares belong to different polynomials and don't combine.
+//! The dealers should agree on the set before dealing.
+//!
+//! [`Resharing::refresh`] keeps the threshold and limit, [`Resharing::new`] and
+//! [`Resharing::with_identifiers`] move to another threshold, limit or set of shareholders.
+//!
+//! When the old shares have Feldman commitments, [`Resharing::deal_verifiable`] also
+//! commits to each dealer's reshares. Its first commitment is the dealer's old share in
+//! the exponent, which the new shareholders check against the old commitments, and
+//! [`Resharing::combine_verifiers`] gives the commitments the new shares verify against.
+
+use super::{
+    audit::{notify, Event},
+    error::{SharingError, SharingResult},
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    shamir::{lagrange_interpolate_points, Scheme as ShamirScheme, Share},
+    Field, Group, ShareVerifier,
+};
+use alloc::vec::Vec;
+use core::{convert::TryFrom, marker::PhantomData};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// A share of an existing share, sent privately from its dealer to one new shareholder
+#[derive(Clone, Debug, Zeroize)]
+#[zeroize(drop)]
+pub struct Reshare {
+    pub(crate) dealer: u32,
+    pub(crate) share: Share,
+}
+
+impl Reshare {
+    /// The identifier of the old share that was split
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    /// The identifier of the new share this reshare is for
+    pub fn receiver(&self) -> u32 {
+        self.share.identifier
+    }
+
+    /// Serialize this reshare. The result is secret and must only be sent encrypted
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.dealer.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.share.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Reshare {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        // The dealer, the receiver and at least one byte of value
+        if data.len() < 9 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut dealer = [0u8; 4];
+        dealer.copy_from_slice(&data[..4]);
+        Ok(Self {
+            dealer: u32::from_be_bytes(dealer),
+            share: Share::try_from(&data[4..])?,
+        })
+    }
+}
+
+/// Moves a secret from shares of one access structure to another
+#[derive(Clone, Debug)]
+pub struct Resharing {
+    old: ShamirScheme,
+    new: ShamirScheme,
+    identifiers: Vec<u32>,
+}
+
+impl Resharing {
+    /// Reshare from shares of `old` to shares of `new` with identifiers `1..=limit`
+    pub fn new(old: ShamirScheme, new: ShamirScheme) -> Self {
+        Self {
+            old,
+            new,
+            identifiers: (1..=new.limit() as u32).collect(),
+        }
+    }
+
+    /// Reshare from shares of `old` to shares of `new` with the caller supplied
+    /// identifiers. Exactly `limit` of the new scheme must be supplied,
+    /// see [`ShamirScheme::split_secret_with_identifiers`]
+    pub fn with_identifiers(
+        old: ShamirScheme,
+        new: ShamirScheme,
+        identifiers: &[u32],
+    ) -> SharingResult<Self> {
+        new.check_identifiers(identifiers)?;
+        Ok(Self {
+            old,
+            new,
+            identifiers: identifiers.to_vec(),
+        })
+    }
+
+    /// Replace the shares of `scheme` with fresh ones for the same identifiers
+    pub fn refresh(scheme: ShamirScheme) -> Self {
+        Self::new(scheme, scheme)
+    }
+
+    /// The identifiers of the new shares
+    pub fn identifiers(&self) -> &[u32] {
+        self.identifiers.as_slice()
+    }
+
+    /// Split an old share for the new shareholders, run by each dealer.
+    /// Returns one reshare for each new identifier
+    pub fn deal<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+    ) -> SharingResult<Vec<Reshare>> {
+        let reshares = self.split::<S>(rng, share)?;
+        self.notify_refresh();
+        Ok(reshares)
+    }
+
+    /// Split an old share for the new shareholders like [`Resharing::deal`] and commit
+    /// to the reshares with `generator`. Returns the commitments to broadcast and one
+    /// reshare for each new identifier
+    pub fn deal_verifiable<S: Field, R: Group<S>>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+        generator: &R,
+    ) -> SharingResult<(FeldmanVerifier<S, R>, Vec<Reshare>)> {
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let dealer = share.identifier;
+        let value = S::from_bytes(&share.value)?;
+        let mut g = R::zero();
+        g.add_assign(generator);
+        let (verifier, shares) = FeldmanScheme::new(self.new.threshold(), self.new.limit())?
+            .split_secret_with_identifiers(rng, &value, self.identifiers.as_slice(), Some(g))?;
+        self.notify_refresh();
+        Ok((
+            verifier,
+            shares
+                .into_iter()
+                .map(|share| Reshare { dealer, share })
+                .collect(),
+        ))
+    }
+
+    /// Compute the commitments to the new shares from the commitments of at least
+    /// `threshold` dealers from [`Resharing::deal_verifiable`], by dealer.
+    /// The dealers must be the ones whose reshares are combined
+    pub fn combine_verifiers<S: Field, R: Group<S>>(
+        &self,
+        verifiers: &[(u32, FeldmanVerifier<S, R>)],
+    ) -> SharingResult<FeldmanVerifier<S, R>> {
+        if verifiers.len() < self.old.threshold() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let g = &verifiers[0].1.g;
+        if verifiers.iter().any(|(_, v)| {
+            v.commitments.len() != self.new.threshold() || v.g.to_bytes() != g.to_bytes()
+        }) {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        // The new polynomial is the dealers' polynomials weighted by their Lagrange
+        // coefficients, so each commitment interpolates like the shares do
+        let mut commitments = Vec::with_capacity(self.new.threshold());
+        for i in 0..self.new.threshold() {
+            let points = verifiers
+                .iter()
+                .map(|(dealer, v)| {
+                    let mut point = R::zero();
+                    point.add_assign(&v.commitments[i].value);
+                    (*dealer, point)
+                })
+                .collect::<Vec<_>>();
+            commitments.push(ShareVerifier {
+                value: lagrange_interpolate_points::<S, R>(points.as_slice())?,
+                phantom: PhantomData,
+            });
+        }
+        let mut generator = R::zero();
+        generator.add_assign(g);
+        Ok(FeldmanVerifier {
+            g: generator,
+            commitments,
+        })
+    }
+
+    /// Compute the new share with identifier `receiver` from the reshares
+    /// of at least `threshold` old shareholders, run by each new shareholder
+    pub fn combine<S: Field>(&self, receiver: u32, reshares: &[Reshare]) -> SharingResult<Share> {
+        if !self.identifiers.contains(&receiver) {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        if reshares.iter().any(|r| r.share.identifier != receiver) {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        // Interpolating the reshares at zero as if they were the dealers' old shares
+        // gives the new share
+        let points = reshares
+            .iter()
+            .map(|r| Share {
+                identifier: r.dealer,
+                value: r.share.value.clone(),
+            })
+            .collect::<Vec<Share>>();
+        let value = self.old.combine::<S, S>(points.as_slice())?;
+        Ok(Share {
+            identifier: receiver,
+            value: value.to_bytes().to_vec(),
+        })
+    }
+
+    /// Reshare in a single place, for when one party already holds
+    /// at least `threshold` of the old shares. Returns the new shares
+    pub fn reshare<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        shares: &[Share],
+    ) -> SharingResult<Vec<Share>> {
+        if shares.len() < self.old.threshold() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let mut reshares = Vec::with_capacity(shares.len() * self.identifiers.len());
+        for share in shares {
+            reshares.extend(self.split::<S>(rng, share)?);
+        }
+        let new_shares = self
+            .identifiers
+            .iter()
+            .map(|id| {
+                let mine = reshares
+                    .iter()
+                    .filter(|r| r.share.identifier == *id)
+                    .cloned()
+                    .collect::<Vec<Reshare>>();
+                self.combine::<S>(*id, mine.as_slice())
+            })
+            .collect::<SharingResult<Vec<Share>>>()?;
+        self.notify_refresh();
+        Ok(new_shares)
+    }
+
+    fn split<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+    ) -> SharingResult<Vec<Reshare>> {
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let dealer = share.identifier;
+        let value = S::from_bytes(&share.value)?;
+        let (shares, _) = self.new.get_shares_and_polynomial_with_identifiers(
+            rng,
+            &value,
+            self.identifiers.as_slice(),
+        )?;
+        Ok(shares
+            .into_iter()
+            .map(|share| Reshare { dealer, share })
+            .collect())
+    }
+
+    fn notify_refresh(&self) {
+        notify(Event::Refresh {
+            threshold: self.new.threshold(),
+            limit: self.new.limit(),
+            identifiers: self.identifiers.as_slice(),
+        });
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Ristretto255 scalars and points for use with the sharing schemes.
+//!
+//! Ristretto is a prime order group built from Curve25519, so unlike Ed25519 points
+//! every valid encoding is canonical and in the group and no cofactor checks are needed.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+use core::ops::Neg;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{Identity, IsIdentity},
+};
+use generic_array::{typenum::U32, GenericArray};
+use rand::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// A scalar modulo the order of the Ristretto255 group
+#[derive(Copy, Clone, Debug, Zeroize)]
+pub struct Ristretto25519Scalar(pub Scalar);
+
+impl Field for Ristretto25519Scalar {
+    fn one() -> Self {
+        Self(Scalar::one())
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(Scalar::from(value as u64))
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        if rhs.is_zero() {
+            return Err(SharingError::DivisionByZero);
+        }
+        self.0 *= rhs.0.invert();
+        Ok(())
+    }
+}
+
+impl Group for Ristretto25519Scalar {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(Scalar::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        match value.len() {
+            64 => {
+                let mut s = [0u8; 64];
+                s.copy_from_slice(value);
+                let r = Self(Scalar::from_bytes_mod_order_wide(&s));
+                s.zeroize();
+                Ok(r)
+            }
+            l if l <= 32 => {
+                // Little endian so shorter values are right padded
+                let mut s = [0u8; 32];
+                s[..l].copy_from_slice(value);
+                let r = Self(Scalar::from_bytes_mod_order(s));
+                s.zeroize();
+                Ok(r)
+            }
+            _ => Err(SharingError::ShareInvalidSecret),
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.ct_eq(&Scalar::zero()).unwrap_u8() == 1
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.neg();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        self.0.to_bytes().into()
+    }
+
+    fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// An element of the Ristretto255 group
+#[derive(Copy, Clone, Debug)]
+pub struct Ristretto25519Point(pub RistrettoPoint);
+
+impl Ristretto25519Point {
+    /// The standard base point
+    pub fn generator() -> Self {
+        Self(RISTRETTO_BASEPOINT_POINT)
+    }
+}
+
+impl Group<Ristretto25519Scalar> for Ristretto25519Point {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(RistrettoPoint::identity())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() != 32 {
+            return Err(SharingError::InvalidPoint);
+        }
+        CompressedRistretto::from_slice(value)
+            .decompress()
+            .map(Self)
+            .ok_or(SharingError::InvalidPoint)
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(RISTRETTO_BASEPOINT_POINT * Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_identity()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.neg();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Ristretto25519Scalar) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        self.0.compress().to_bytes().into()
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Secp256k1 scalars and points for use with the sharing schemes
+//! and a Pedersen distributed key generation with identifiable aborts
+//! for threshold ECDSA.
+
+use super::{
+    error::{SharingError, SharingResult},
+    Field, Group,
+};
+use generic_array::{
+    typenum::{U32, U33},
+    GenericArray,
+};
+use k256::{
+    elliptic_curve::{
+        sec1::{FromEncodedPoint, ToEncodedPoint},
+        Field as _, Group as _,
+    },
+    AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar,
+};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// Pedersen distributed key generation with identifiable aborts
+pub mod dkg;
+
+/// A scalar modulo the order of the secp256k1 group
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Secp256k1Scalar(pub Scalar);
+
+impl Zeroize for Secp256k1Scalar {
+    fn zeroize(&mut self) {
+        self.0 = Scalar::zero();
+    }
+}
+
+impl Field for Secp256k1Scalar {
+    fn one() -> Self {
+        Self(Scalar::one())
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(Scalar::from(value as u64))
+    }
+
+    fn scalar_div_assign(&mut self, rhs: &Self) -> SharingResult<()> {
+        let inverse = Option::<Scalar>::from(rhs.0.invert()).ok_or(SharingError::DivisionByZero)?;
+        self.0 *= inverse;
+        Ok(())
+    }
+}
+
+impl Group for Secp256k1Scalar {
+    type Size = U32;
+
+    fn zero() -> Self {
+        Self(Scalar::zero())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() > 32 {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        // Big endian so shorter values are left padded
+        let mut s = [0u8; 32];
+        s[32 - value.len()..].copy_from_slice(value);
+        let r = Self(Scalar::from_bytes_reduced(FieldBytes::from_slice(&s)));
+        s.zeroize();
+        Ok(r)
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero().unwrap_u8() == 1
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0 = self.0.negate();
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Self) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        GenericArray::clone_from_slice(self.0.to_bytes().as_slice())
+    }
+
+    fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// A point on the secp256k1 curve
+#[derive(Copy, Clone, Debug)]
+pub struct Secp256k1Point(pub ProjectivePoint);
+
+impl Secp256k1Point {
+    /// The standard base point
+    pub fn generator() -> Self {
+        Self(ProjectivePoint::generator())
+    }
+}
+
+impl Group<Secp256k1Scalar> for Secp256k1Point {
+    type Size = U33;
+
+    fn zero() -> Self {
+        Self(ProjectivePoint::identity())
+    }
+
+    fn from_bytes<B: AsRef<[u8]>>(value: B) -> SharingResult<Self> {
+        let value = value.as_ref();
+        if value.len() != 33 {
+            return Err(SharingError::InvalidPoint);
+        }
+        if value.iter().all(|b| *b == 0) {
+            return Ok(Self::zero());
+        }
+        let ept = EncodedPoint::from_bytes(value).map_err(|_| SharingError::InvalidPoint)?;
+        let apt = AffinePoint::from_encoded_point(&ept);
+        if apt.is_some().unwrap_u8() == 1 {
+            Ok(Self(ProjectivePoint::from(apt.unwrap())))
+        } else {
+            Err(SharingError::InvalidPoint)
+        }
+    }
+
+    fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self(ProjectivePoint::generator() * Scalar::random(rng))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_identity().unwrap_u8() == 1
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn negate(&mut self) {
+        self.0 = -self.0;
+    }
+
+    fn add_assign(&mut self, rhs: &Self) {
+        self.0 += rhs.0;
+    }
+
+    fn sub_assign(&mut self, rhs: &Self) {
+        self.0 -= rhs.0;
+    }
+
+    fn scalar_mul_assign(&mut self, rhs: &Secp256k1Scalar) {
+        self.0 *= rhs.0;
+    }
+
+    fn to_bytes(&self) -> GenericArray<u8, Self::Size> {
+        // The identity has no compressed encoding so use all zeros
+        if self.is_zero() {
+            return GenericArray::default();
+        }
+        GenericArray::clone_from_slice(self.0.to_affine().to_encoded_point(true).as_bytes())
+    }
+}
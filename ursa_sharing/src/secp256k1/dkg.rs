@@ -0,0 +1,848 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Pedersen distributed key generation over secp256k1 with identifiable aborts.
+//!
+//! Every participant deals a random secret with Feldman's scheme and sums the
+//! shares it receives. The joint secret key is the sum of the dealt secrets and
+//! is never known to anyone. The protocol has three rounds:
+//!
+//! 1. Each participant broadcasts a [`Round1Broadcast`] with the commitments to its
+//!    polynomial and a proof of knowledge of its secret, and sends a [`Round1Share`]
+//!    to every other participant over a private channel.
+//! 2. Each participant checks the shares it received and broadcasts a [`Complaint`]
+//!    against every dealer whose share is missing or doesn't match its commitments.
+//! 3. Each accused dealer broadcasts a [`Justification`] revealing the disputed share.
+//!
+//! Every decision is made from broadcast messages alone, so anyone holding the
+//! [`Transcript`] reaches the same verdict as the participants. If anyone misbehaved
+//! the protocol aborts and [`Transcript::blame`] says who and why, instead of only
+//! reporting that something failed. The transport must provide a reliable broadcast:
+//! equivocation, sending different broadcasts to different participants, is not detected here.
+//!
+//! Participants are identified by `1..=limit` which are also the identifiers of the
+//! resulting shares, so they can be combined with `shamir::Scheme::combine_shares`
+//! or lifted with `Share::lift` for threshold signing.
+
+use super::{Secp256k1Point, Secp256k1Scalar};
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{put, Checkpoint};
+#[cfg(feature = "record")]
+use crate::record::{Recordable, Recording};
+use crate::{
+    audit::{notify, Event},
+    dkg::{read_u32, Reader},
+    error::{SharingError, SharingResult},
+    shamir::{Scheme as ShamirScheme, Share},
+    transcript, Field, Group, Strictness,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use k256::{FieldBytes, Scalar};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+pub use crate::dkg::{Blame, Complaint, Misbehavior};
+
+/// The current transcript format version
+pub const TRANSCRIPT_VERSION: u8 = 1;
+
+const SCALAR_BYTES: usize = 32;
+const POINT_BYTES: usize = 33;
+const PROOF_DOMAIN: &[u8] = b"ursa_sharing secp256k1 dkg proof of knowledge";
+
+/// Commitments to a participant's polynomial and a proof of knowledge of its secret
+#[derive(Clone, Debug)]
+pub struct Round1Broadcast {
+    sender: u32,
+    commitments: Vec<Secp256k1Point>,
+    proof_commitment: Secp256k1Point,
+    proof_response: Secp256k1Scalar,
+}
+
+impl Round1Broadcast {
+    /// The participant that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The commitments to the sender's polynomial coefficients, constant term first
+    pub fn commitments(&self) -> &[Secp256k1Point] {
+        self.commitments.as_slice()
+    }
+
+    /// Serialize this broadcast
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(8 + (self.commitments.len() + 1) * POINT_BYTES + 32);
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&(self.commitments.len() as u32).to_be_bytes());
+        for c in &self.commitments {
+            o.extend_from_slice(&c.to_bytes());
+        }
+        o.extend_from_slice(&self.proof_commitment.to_bytes());
+        o.extend_from_slice(&self.proof_response.to_bytes());
+        o
+    }
+
+    fn challenge(sender: u32, secret_commitment: &Secp256k1Point, r: &Secp256k1Point) -> Scalar {
+        let mut transcript = transcript::Transcript::new(PROOF_DOMAIN);
+        transcript.append_u64(b"sender", sender as u64);
+        transcript.append_point(b"secret commitment", secret_commitment);
+        transcript.append_point(b"commitment", r);
+        let mut c = [0u8; SCALAR_BYTES];
+        transcript.challenge_bytes(b"challenge", &mut c);
+        Scalar::from_bytes_reduced(FieldBytes::from_slice(&c))
+    }
+
+    /// Check the Schnorr proof of knowledge of the committed secret
+    fn verify_proof(&self) -> bool {
+        let c = Self::challenge(self.sender, &self.commitments[0], &self.proof_commitment);
+        // g^z == R * C_0^c
+        let lhs = Secp256k1Point::generator().0 * self.proof_response.0;
+        let rhs = self.proof_commitment.0 + self.commitments[0].0 * c;
+        lhs == rhs
+    }
+
+    /// Check a share dealt to `receiver` against the commitments
+    fn verify_share(&self, receiver: u32, value: &Secp256k1Scalar) -> bool {
+        let x = Secp256k1Scalar::from_usize(receiver as usize);
+        let mut i = Secp256k1Scalar::one();
+        // c_0 * c_1^i * c_2^{i^2} ... c_t^{i^t}
+        let mut rhs = Secp256k1Point::zero();
+        for c in &self.commitments {
+            let mut t = *c;
+            t.scalar_mul_assign(&i);
+            rhs.add_assign(&t);
+            i.scalar_mul_assign(&x);
+        }
+        let lhs = Secp256k1Point::generator().0 * value.0;
+        lhs == rhs.0
+    }
+
+    /// Deserialize a broadcast using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        if data.len() < 8 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let sender = read_u32(&data[..4]);
+        let count = read_u32(&data[4..8]) as usize;
+        let expected = count
+            .checked_add(1)
+            .and_then(|c| c.checked_mul(POINT_BYTES))
+            .and_then(|c| c.checked_add(8 + SCALAR_BYTES));
+        if expected != Some(data.len()) {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut points = data[8..data.len() - SCALAR_BYTES]
+            .chunks(POINT_BYTES)
+            .map(|p| Secp256k1Point::from_bytes_with(p, strictness))
+            .collect::<SharingResult<Vec<Secp256k1Point>>>()?;
+        let proof_commitment = points.pop().ok_or(SharingError::ShareInvalidEncoding)?;
+        let proof_response =
+            Secp256k1Scalar::from_bytes_with(&data[data.len() - SCALAR_BYTES..], strictness)?;
+        Ok(Self {
+            sender,
+            commitments: points,
+            proof_commitment,
+            proof_response,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for Round1Broadcast {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+#[cfg(feature = "record")]
+impl Recordable for Round1Broadcast {
+    const KIND: &'static str = "secp256k1 dkg round1 broadcast";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// A share sent privately from its dealer to a single receiver
+#[derive(Clone, Debug)]
+pub struct Round1Share {
+    sender: u32,
+    receiver: u32,
+    value: Secp256k1Scalar,
+}
+
+impl Round1Share {
+    /// The participant that dealt this share
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The participant this share must be delivered to
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+
+    /// Serialize this share. The result is secret and must only be sent encrypted
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(8 + SCALAR_BYTES);
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&self.receiver.to_be_bytes());
+        o.extend_from_slice(&self.value.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Round1Share {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() != 8 + SCALAR_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            sender: read_u32(&data[..4]),
+            receiver: read_u32(&data[4..8]),
+            value: Secp256k1Scalar::from_bytes(&data[8..])?,
+        })
+    }
+}
+
+impl Drop for Round1Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// A disputed share revealed by its dealer in response to a [`Complaint`]
+#[derive(Clone, Debug)]
+pub struct Justification {
+    sender: u32,
+    receiver: u32,
+    value: Secp256k1Scalar,
+}
+
+impl Justification {
+    /// The dealer revealing the share
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The participant that complained
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+
+    /// Serialize this justification
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(8 + SCALAR_BYTES);
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&self.receiver.to_be_bytes());
+        o.extend_from_slice(&self.value.to_bytes());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Justification {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() != 8 + SCALAR_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            sender: read_u32(&data[..4]),
+            receiver: read_u32(&data[4..8]),
+            value: Secp256k1Scalar::from_bytes(&data[8..])?,
+        })
+    }
+}
+
+#[cfg(feature = "record")]
+impl Recordable for Justification {
+    const KIND: &'static str = "secp256k1 dkg justification";
+
+    fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn receiver(&self) -> Option<u32> {
+        Some(self.receiver)
+    }
+
+    fn to_record_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_record_bytes(data: &[u8]) -> SharingResult<Self> {
+        Self::try_from(data)
+    }
+}
+
+/// All public messages of a DKG session in a canonical order.
+/// Exported for external audit, anyone can replay the verdict with [`Transcript::blame`]
+/// and recompute the public key and verification shares.
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    threshold: usize,
+    limit: usize,
+    broadcasts: BTreeMap<u32, Round1Broadcast>,
+    complaints: BTreeSet<Complaint>,
+    justifications: Vec<Justification>,
+}
+
+impl Transcript {
+    fn new(threshold: usize, limit: usize) -> Self {
+        Self {
+            threshold,
+            limit,
+            broadcasts: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            justifications: Vec::new(),
+        }
+    }
+
+    /// The threshold of the generated key
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of participants
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The round one broadcasts ordered by sender
+    pub fn broadcasts(&self) -> Vec<&Round1Broadcast> {
+        self.broadcasts.values().collect()
+    }
+
+    /// The complaints ordered by accuser then accused
+    pub fn complaints(&self) -> Vec<Complaint> {
+        self.complaints.iter().copied().collect()
+    }
+
+    /// The justifications in the order they were received
+    pub fn justifications(&self) -> &[Justification] {
+        self.justifications.as_slice()
+    }
+
+    fn is_participant(&self, id: u32) -> bool {
+        id != 0 && id as usize <= self.limit
+    }
+
+    fn add_broadcast(&mut self, broadcast: &Round1Broadcast) -> SharingResult<()> {
+        if !self.is_participant(broadcast.sender) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        // The first broadcast from each sender is kept, the transport must prevent equivocation
+        self.broadcasts
+            .entry(broadcast.sender)
+            .or_insert_with(|| broadcast.clone());
+        Ok(())
+    }
+
+    /// Find everyone who misbehaved. An empty result means the session succeeded
+    pub fn blame(&self) -> Vec<Blame> {
+        let mut blame = BTreeSet::new();
+        let mut qualified = BTreeSet::new();
+        for id in 1..=self.limit as u32 {
+            let misbehavior = match self.broadcasts.get(&id) {
+                None => Some(Misbehavior::MissingBroadcast),
+                Some(b) if b.commitments.len() != self.threshold || b.commitments[0].is_zero() => {
+                    Some(Misbehavior::InvalidBroadcast)
+                }
+                Some(b) if !b.verify_proof() => Some(Misbehavior::InvalidProof),
+                Some(_) => None,
+            };
+            match misbehavior {
+                Some(misbehavior) => {
+                    blame.insert(Blame {
+                        participant: id,
+                        misbehavior,
+                    });
+                }
+                None => {
+                    qualified.insert(id);
+                }
+            }
+        }
+
+        for complaint in &self.complaints {
+            if !self.is_participant(complaint.accuser)
+                || !self.is_participant(complaint.accused)
+                || complaint.accuser == complaint.accused
+            {
+                blame.insert(Blame {
+                    participant: complaint.accuser,
+                    misbehavior: Misbehavior::InvalidComplaint,
+                });
+                continue;
+            }
+            // Already disqualified
+            if !qualified.contains(&complaint.accused) {
+                continue;
+            }
+            let broadcast = &self.broadcasts[&complaint.accused];
+            let mut answered = false;
+            let mut valid = true;
+            for j in self.justifications_for(complaint) {
+                answered = true;
+                valid &= broadcast.verify_share(j.receiver, &j.value);
+            }
+            let misbehavior = if !answered {
+                Misbehavior::MissingJustification
+            } else if !valid {
+                Misbehavior::InvalidJustification
+            } else {
+                continue;
+            };
+            blame.insert(Blame {
+                participant: complaint.accused,
+                misbehavior,
+            });
+        }
+        blame.into_iter().collect()
+    }
+
+    fn justifications_for<'a>(
+        &'a self,
+        complaint: &'a Complaint,
+    ) -> impl Iterator<Item = &'a Justification> {
+        self.justifications
+            .iter()
+            .filter(move |j| j.sender == complaint.accused && j.receiver == complaint.accuser)
+    }
+
+    fn check(&self) -> SharingResult<()> {
+        match self.blame().first() {
+            Some(b) => Err(SharingError::DkgAbort(b.participant)),
+            None => Ok(()),
+        }
+    }
+
+    /// The jointly generated public key
+    pub fn public_key(&self) -> SharingResult<Secp256k1Point> {
+        self.check()?;
+        let mut pk = Secp256k1Point::zero();
+        for b in self.broadcasts.values() {
+            pk.add_assign(&b.commitments[0]);
+        }
+        Ok(pk)
+    }
+
+    /// The public key of the share held by participant `identifier`,
+    /// used to verify its contributions to threshold signatures
+    pub fn verification_share(&self, identifier: u32) -> SharingResult<Secp256k1Point> {
+        self.check()?;
+        if !self.is_participant(identifier) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        let x = Secp256k1Scalar::from_usize(identifier as usize);
+        let mut vs = Secp256k1Point::zero();
+        for b in self.broadcasts.values() {
+            let mut i = Secp256k1Scalar::one();
+            for c in &b.commitments {
+                let mut t = *c;
+                t.scalar_mul_assign(&i);
+                vs.add_assign(&t);
+                i.scalar_mul_assign(&x);
+            }
+        }
+        Ok(vs)
+    }
+
+    /// Serialize the transcript
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = vec![TRANSCRIPT_VERSION];
+        o.extend_from_slice(&(self.threshold as u32).to_be_bytes());
+        o.extend_from_slice(&(self.limit as u32).to_be_bytes());
+        o.extend_from_slice(&(self.broadcasts.len() as u32).to_be_bytes());
+        for b in self.broadcasts.values() {
+            let bytes = b.to_bytes();
+            o.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            o.extend_from_slice(&bytes);
+        }
+        o.extend_from_slice(&(self.complaints.len() as u32).to_be_bytes());
+        for c in &self.complaints {
+            o.extend_from_slice(&c.accuser.to_be_bytes());
+            o.extend_from_slice(&c.accused.to_be_bytes());
+        }
+        o.extend_from_slice(&(self.justifications.len() as u32).to_be_bytes());
+        for j in &self.justifications {
+            o.extend_from_slice(&j.to_bytes());
+        }
+        o
+    }
+}
+
+#[cfg(feature = "record")]
+impl Transcript {
+    /// Rebuild the transcript of a session from its [`Recording`] so an auditor can
+    /// replay the verdict. The first broadcast recorded from each sender is kept
+    pub fn replay(threshold: usize, limit: usize, recording: &Recording) -> SharingResult<Self> {
+        ShamirScheme::new(threshold, limit)?;
+        let mut transcript = Self::new(threshold, limit);
+        for broadcast in recording.messages::<Round1Broadcast>()? {
+            transcript.add_broadcast(&broadcast)?;
+        }
+        transcript
+            .complaints
+            .extend(recording.messages::<Complaint>()?);
+        transcript
+            .justifications
+            .extend(recording.messages::<Justification>()?);
+        Ok(transcript)
+    }
+}
+
+impl TryFrom<&[u8]> for Transcript {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let version = reader.take(1)?[0];
+        if version != TRANSCRIPT_VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(version));
+        }
+        let threshold = reader.u32()? as usize;
+        let limit = reader.u32()? as usize;
+        ShamirScheme::new(threshold, limit)?;
+        let mut transcript = Self::new(threshold, limit);
+
+        for _ in 0..reader.u32()? {
+            let len = reader.u32()? as usize;
+            let broadcast = Round1Broadcast::try_from(reader.take(len)?)?;
+            if transcript.broadcasts.contains_key(&broadcast.sender) {
+                return Err(SharingError::ShareInvalidEncoding);
+            }
+            transcript.add_broadcast(&broadcast)?;
+        }
+        for _ in 0..reader.u32()? {
+            let accuser = reader.u32()?;
+            let accused = reader.u32()?;
+            transcript.complaints.insert(Complaint { accuser, accused });
+        }
+        for _ in 0..reader.u32()? {
+            let justification = Justification::try_from(reader.take(8 + SCALAR_BYTES)?)?;
+            transcript.justifications.push(justification);
+        }
+        if !reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(transcript)
+    }
+}
+
+/// The result of a successful DKG session for one participant
+#[derive(Debug)]
+pub struct DkgOutput {
+    share: Share,
+    public_key: Secp256k1Point,
+    verification_share: Secp256k1Point,
+}
+
+impl DkgOutput {
+    /// This participant's share of the joint secret key
+    pub fn share(&self) -> &Share {
+        &self.share
+    }
+
+    /// The joint public key
+    pub fn public_key(&self) -> Secp256k1Point {
+        self.public_key
+    }
+
+    /// The public key of this participant's share
+    pub fn verification_share(&self) -> Secp256k1Point {
+        self.verification_share
+    }
+}
+
+/// One participant's state in a DKG session
+#[derive(Debug)]
+pub struct Participant {
+    id: u32,
+    /// The shares this participant dealt, by receiver
+    dealt: BTreeMap<u32, Secp256k1Scalar>,
+    /// The shares this participant received, by dealer
+    received: BTreeMap<u32, Secp256k1Scalar>,
+    transcript: Transcript,
+}
+
+impl Participant {
+    /// Start a session as participant `id` of `limit` where `threshold` participants
+    /// are needed to use the key. Returns the new state, the message to broadcast
+    /// and the shares to send privately to each of the other participants.
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        id: u32,
+        threshold: usize,
+        limit: usize,
+    ) -> SharingResult<(Self, Round1Broadcast, Vec<Round1Share>)> {
+        let shamir = ShamirScheme::new(threshold, limit)?;
+        let mut transcript = Transcript::new(threshold, limit);
+        if !transcript.is_participant(id) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+
+        let secret = Secp256k1Scalar::random(rng);
+        let (shares, polynomial) = shamir.get_shares_and_polynomial(rng, &secret)?;
+        let commitments = polynomial
+            .coefficients
+            .iter()
+            .map(|c| Secp256k1Point(Secp256k1Point::generator().0 * c.0))
+            .collect::<Vec<Secp256k1Point>>();
+
+        // Schnorr proof of knowledge of the secret prevents rogue key attacks
+        let mut k = Secp256k1Scalar::random(rng);
+        let proof_commitment = Secp256k1Point(Secp256k1Point::generator().0 * k.0);
+        let c = Round1Broadcast::challenge(id, &commitments[0], &proof_commitment);
+        let proof_response = Secp256k1Scalar(k.0 + c * secret.0);
+        k.zeroize();
+
+        let broadcast = Round1Broadcast {
+            sender: id,
+            commitments,
+            proof_commitment,
+            proof_response,
+        };
+        transcript.add_broadcast(&broadcast)?;
+
+        let mut dealt = BTreeMap::new();
+        for share in &shares {
+            dealt.insert(
+                share.identifier(),
+                Secp256k1Scalar::from_bytes(share.value())?,
+            );
+        }
+        let mut received = BTreeMap::new();
+        received.insert(id, dealt[&id]);
+        let outgoing = dealt
+            .iter()
+            .filter(|(receiver, _)| **receiver != id)
+            .map(|(receiver, value)| Round1Share {
+                sender: id,
+                receiver: *receiver,
+                value: *value,
+            })
+            .collect();
+
+        Ok((
+            Self {
+                id,
+                dealt,
+                received,
+                transcript,
+            },
+            broadcast,
+            outgoing,
+        ))
+    }
+
+    /// This participant's identifier
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Process the round one messages from the other participants.
+    /// Returns the complaints to broadcast, one for each dealer whose share
+    /// is missing or doesn't match its commitments.
+    pub fn receive_round1(
+        &mut self,
+        broadcasts: &[Round1Broadcast],
+        shares: &[Round1Share],
+    ) -> SharingResult<Vec<Complaint>> {
+        for broadcast in broadcasts {
+            self.transcript.add_broadcast(broadcast)?;
+        }
+        for share in shares {
+            if share.receiver != self.id || !self.transcript.is_participant(share.sender) {
+                return Err(SharingError::DkgInvalidParticipant);
+            }
+            if share.sender != self.id {
+                self.received.entry(share.sender).or_insert(share.value);
+            }
+        }
+
+        let mut complaints = Vec::new();
+        for (sender, broadcast) in &self.transcript.broadcasts {
+            if *sender == self.id
+                || broadcast.commitments.len() != self.transcript.threshold
+                || !broadcast.verify_proof()
+            {
+                // Bad broadcasts are blamed from the transcript without a complaint
+                continue;
+            }
+            let valid = self
+                .received
+                .get(sender)
+                .map(|v| broadcast.verify_share(self.id, v))
+                .unwrap_or(false);
+            if !valid {
+                self.received.remove(sender);
+                complaints.push(Complaint {
+                    accuser: self.id,
+                    accused: *sender,
+                });
+                notify(Event::DkgComplaint {
+                    accuser: self.id,
+                    accused: *sender,
+                });
+            }
+        }
+        Ok(complaints)
+    }
+
+    /// Answer the complaints against this participant by revealing the disputed shares
+    pub fn justify(&self, complaints: &[Complaint]) -> Vec<Justification> {
+        let mut receivers = complaints
+            .iter()
+            .filter(|c| c.accused == self.id)
+            .map(|c| c.accuser)
+            .collect::<Vec<u32>>();
+        receivers.sort_unstable();
+        receivers.dedup();
+        receivers
+            .iter()
+            .filter_map(|r| {
+                self.dealt.get(r).map(|value| Justification {
+                    sender: self.id,
+                    receiver: *r,
+                    value: *value,
+                })
+            })
+            .collect()
+    }
+
+    /// Complete the session with all broadcast complaints and justifications.
+    /// Fails with `DkgAbort` naming a misbehaving participant if anyone misbehaved,
+    /// `transcript().blame()` lists all of them.
+    pub fn finalize(
+        &mut self,
+        complaints: &[Complaint],
+        justifications: &[Justification],
+    ) -> SharingResult<DkgOutput> {
+        self.transcript
+            .complaints
+            .extend(complaints.iter().copied());
+        self.transcript
+            .justifications
+            .extend(justifications.iter().cloned());
+        self.transcript.check()?;
+
+        for j in justifications {
+            if j.receiver == self.id {
+                self.received.insert(j.sender, j.value);
+            }
+        }
+        let mut secret = Secp256k1Scalar::zero();
+        for sender in self.transcript.broadcasts.keys() {
+            let value = self
+                .received
+                .get(sender)
+                .ok_or(SharingError::DkgAbort(*sender))?;
+            secret.add_assign(value);
+        }
+
+        let share = Share {
+            identifier: self.id,
+            value: secret.to_bytes().to_vec(),
+        };
+        secret.zeroize();
+        Ok(DkgOutput {
+            share,
+            public_key: self.transcript.public_key()?,
+            verification_share: self.transcript.verification_share(self.id)?,
+        })
+    }
+
+    /// The public messages of this session so far
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl Checkpoint for Participant {
+    const KIND: &'static [u8] = b"secp256k1 dkg participant";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = self.id.to_be_bytes().to_vec();
+        put(&mut o, &self.transcript.to_bytes());
+        for scalars in &[&self.dealt, &self.received] {
+            o.extend_from_slice(&(scalars.len() as u32).to_be_bytes());
+            for (participant, value) in scalars.iter() {
+                o.extend_from_slice(&participant.to_be_bytes());
+                let mut bytes = value.to_bytes();
+                o.extend_from_slice(&bytes);
+                bytes.zeroize();
+            }
+        }
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let id = reader.u32()?;
+        let transcript = Transcript::try_from(reader.field()?)?;
+        if !transcript.is_participant(id) {
+            return Err(SharingError::DkgInvalidParticipant);
+        }
+        let dealt = read_scalars(&mut reader)?;
+        let received = read_scalars(&mut reader)?;
+        reader.finish()?;
+        Ok(Self {
+            id,
+            dealt,
+            received,
+            transcript,
+        })
+    }
+}
+
+/// Read scalars by participant written by `Participant::to_checkpoint_bytes`
+#[cfg(feature = "checkpoint")]
+fn read_scalars(reader: &mut Reader) -> SharingResult<BTreeMap<u32, Secp256k1Scalar>> {
+    let mut scalars = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let participant = reader.u32()?;
+        scalars.insert(
+            participant,
+            Secp256k1Scalar::from_bytes(reader.take(SCALAR_BYTES)?)?,
+        );
+    }
+    Ok(scalars)
+}
+
+impl Drop for Participant {
+    fn drop(&mut self) {
+        for v in self.dealt.values_mut() {
+            v.zeroize();
+        }
+        for v in self.received.values_mut() {
+            v.zeroize();
+        }
+    }
+}
@@ -11,12 +11,22 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+#[cfg(feature = "recipient")]
+use super::recipient::{encrypt_shares, EncryptedShare, RecipientPublicKey};
+#[cfg(feature = "wrap")]
+use super::wrap::{unwrap_shares, wrap_shares, WrapParams, WrappedShare};
 use super::{
+    audit::{notify, Event, SchemeKind},
     error::{SharingError, SharingResult},
+    feldman::FeldmanVerifier,
+    field::lagrange_coefficients,
+    pedersen::PedersenVerifier,
     Field, Group, Polynomial,
 };
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::convert::TryFrom;
 use rand::{CryptoRng, RngCore};
-use std::{collections::BTreeSet, convert::TryFrom};
+use sha2::{Digest, Sha256};
 use zeroize::Zeroize;
 
 /// A Shamir simple secret share
@@ -43,7 +53,7 @@ impl Share {
     /// The identifier is the first 4 bytes
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut o = self.identifier.to_be_bytes().to_vec();
-        o.append(&mut self.to_bytes());
+        o.extend_from_slice(self.value.as_slice());
         o
     }
 
@@ -56,6 +66,44 @@ impl Share {
     pub fn value(&self) -> &[u8] {
         self.value.as_slice()
     }
+
+    /// Lift this share into the exponent of `generator` i.e. compute `generator^value`.
+    /// The result has the same identifier so shares lifted into any group
+    /// can be combined in the exponent using `Scheme::combine_shares::<S, R>`
+    /// without reconstructing the secret.
+    pub fn lift<S: Field, R: Group<S>>(&self, generator: &R) -> SharingResult<Self> {
+        let s = S::from_bytes(&self.value)?;
+        if !s.is_valid() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        let mut r = R::zero();
+        r.add_assign(generator);
+        r.scalar_mul_assign(&s);
+        Ok(Self {
+            identifier: self.identifier,
+            value: r.to_bytes().to_vec(),
+        })
+    }
+
+    /// Check this share against the commitments a Feldman dealer published with it
+    /// so a shareholder can detect an inconsistent share without other shares.
+    /// Same as `feldman::Scheme::verify_share` with the threshold given by the commitments.
+    pub fn verify<S: Field, R: Group<S>>(
+        &self,
+        commitments: &FeldmanVerifier<S, R>,
+    ) -> SharingResult<()> {
+        commitments.verify_share(self)
+    }
+
+    /// Check this share and its blinding share against the commitments a Pedersen dealer
+    /// published with them. Same as `pedersen::Scheme::verify_share`.
+    pub fn verify_blinded<S: Field, R: Group<S>>(
+        &self,
+        blind_share: &Share,
+        commitments: &PedersenVerifier<S, R>,
+    ) -> SharingResult<()> {
+        commitments.verify_share(self, blind_share)
+    }
 }
 
 impl TryFrom<&[u8]> for Share {
@@ -102,6 +150,16 @@ impl Scheme {
         Ok(Self { threshold, limit })
     }
 
+    /// The minimum number of shares needed to combine
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of shares created
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
     /// Create Shares from a secret
     pub fn split_secret<S: Field>(
         &self,
@@ -109,37 +167,225 @@ impl Scheme {
         secret: &S,
     ) -> SharingResult<Vec<Share>> {
         let (shares, _) = self.get_shares_and_polynomial(rng, secret)?;
+        self.notify_split(SchemeKind::Shamir, shares.as_slice());
+        Ok(shares)
+    }
+
+    /// Create Shares from a secret using the caller supplied identifiers
+    /// as the x-coordinates instead of the default `1..=limit`.
+    /// This allows shares to be bound to existing participant ids.
+    /// Exactly `limit` identifiers must be supplied and each must be non-zero and unique.
+    pub fn split_secret_with_identifiers<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        identifiers: &[u32],
+    ) -> SharingResult<Vec<Share>> {
+        let (shares, _) =
+            self.get_shares_and_polynomial_with_identifiers(rng, secret, identifiers)?;
+        self.notify_split(SchemeKind::Shamir, shares.as_slice());
         Ok(shares)
     }
 
+    /// Create Shares from a secret and encrypt each one to the recipient
+    /// at the same position so no plaintext share leaves this function.
+    /// Exactly `limit` recipients must be supplied.
+    #[cfg(feature = "recipient")]
+    #[cfg_attr(feature = "nightly", doc(cfg(feature = "recipient")))]
+    pub fn split_encrypted<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        recipients: &[RecipientPublicKey],
+    ) -> SharingResult<Vec<EncryptedShare>> {
+        if recipients.len() != self.limit {
+            return Err(SharingError::ShareRecipientCount(
+                self.limit,
+                recipients.len(),
+            ));
+        }
+        let shares = self.split_secret(rng, secret)?;
+        encrypt_shares(rng, shares.as_slice(), recipients)
+    }
+
+    /// Create Shares from a secret and wrap each one under `passphrase`
+    /// for storage at rest
+    #[cfg(feature = "wrap")]
+    #[cfg_attr(feature = "nightly", doc(cfg(feature = "wrap")))]
+    pub fn split_wrapped<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        passphrase: &[u8],
+        params: WrapParams,
+    ) -> SharingResult<Vec<WrappedShare>> {
+        let shares = self.split_secret(rng, secret)?;
+        wrap_shares(rng, shares.as_slice(), passphrase, params)
+    }
+
+    /// Unwrap shares created by `split_wrapped` or `wrap::wrap_share`
+    /// with `passphrase` and reconstruct the secret
+    #[cfg(feature = "wrap")]
+    #[cfg_attr(feature = "nightly", doc(cfg(feature = "wrap")))]
+    pub fn combine_wrapped<S: Field, R: Group<S>>(
+        &self,
+        shares: &[WrappedShare],
+        passphrase: &[u8],
+    ) -> SharingResult<R> {
+        // Check what's public before spending a key derivation on each share
+        if shares.len() < self.threshold {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let mut dups = BTreeSet::new();
+        if !shares.iter().all(|s| dups.insert(s.identifier())) {
+            return Err(SharingError::ShareDuplicateIdentifier);
+        }
+        let shares = unwrap_shares(shares, passphrase)?;
+        self.combine_shares::<S, R>(shares.as_slice())
+    }
+
+    /// Tell the registered observer which shares were created
+    pub(crate) fn notify_split(&self, scheme: SchemeKind, shares: &[Share]) {
+        let identifiers = shares.iter().map(|s| s.identifier).collect::<Vec<u32>>();
+        notify(Event::Split {
+            scheme,
+            threshold: self.threshold,
+            limit: self.limit,
+            identifiers: identifiers.as_slice(),
+        });
+    }
+
+    /// Generate `limit` unique, non-zero identifiers chosen at random
+    /// that can be passed to `split_secret_with_identifiers`
+    pub fn random_identifiers(&self, rng: &mut (impl RngCore + CryptoRng)) -> Vec<u32> {
+        let mut dups = BTreeSet::new();
+        let mut identifiers = Vec::with_capacity(self.limit);
+        while identifiers.len() < self.limit {
+            let identifier = rng.next_u32();
+            if identifier == 0 || !dups.insert(identifier) {
+                continue;
+            }
+            identifiers.push(identifier);
+        }
+        identifiers
+    }
+
+    /// Map `limit` stable participant ids, such as account numbers, to identifiers
+    /// that can be passed to `split_secret_with_identifiers` with [`participant_identifier`].
+    /// The same id always maps to the same identifier so no table from ids to positions
+    /// has to be kept. Fails if ids repeat or, with a chance of about `limit^2 / 2^33`,
+    /// two ids map to the same identifier.
+    pub fn participant_identifiers(&self, ids: &[u64]) -> SharingResult<Vec<u32>> {
+        let identifiers = ids
+            .iter()
+            .map(|id| participant_identifier(*id))
+            .collect::<Vec<u32>>();
+        self.check_identifiers(identifiers.as_slice())?;
+        Ok(identifiers)
+    }
+
+    /// Check that the identifiers can be used as x-coordinates for this scheme
+    pub(crate) fn check_identifiers(&self, identifiers: &[u32]) -> SharingResult<()> {
+        if identifiers.len() != self.limit {
+            return Err(SharingError::ShareIdentifierCount(
+                self.limit,
+                identifiers.len(),
+            ));
+        }
+        let mut dups = BTreeSet::new();
+        for identifier in identifiers {
+            if *identifier == 0 {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+            if !dups.insert(*identifier) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn get_shares_and_polynomial<S: Field>(
         &self,
         rng: &mut (impl RngCore + CryptoRng),
         secret: &S,
+    ) -> SharingResult<(Vec<Share>, Polynomial<S>)> {
+        // x coordinates are incremental from [1, total+1). 0 is reserved for the secret.
+        let identifiers = (1..=self.limit as u32).collect::<Vec<u32>>();
+        self.get_shares_and_polynomial_with_identifiers(rng, secret, identifiers.as_slice())
+    }
+
+    pub(crate) fn get_shares_and_polynomial_with_identifiers<S: Field>(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        secret: &S,
+        identifiers: &[u32],
     ) -> SharingResult<(Vec<Share>, Polynomial<S>)> {
         if !secret.is_valid() {
             return Err(SharingError::ShareInvalidSecret);
         }
+        self.check_identifiers(identifiers)?;
 
         let polynomial = Polynomial::new(rng, secret, self.threshold);
 
         // Generate the shares of (x, y) coordinates
-        // x coordinates are incremental from [1, total+1). 0 is reserved for the secret.
-        let mut shares = Vec::with_capacity(self.limit);
-        for i in 0..self.limit {
-            let identifier = i + 1;
-            let x = S::from_usize(identifier);
-            let y = polynomial.evaluate(&x);
-            shares.push(Share {
-                identifier: identifier as u32,
+        let consecutive = identifiers
+            .windows(2)
+            .all(|w| w[0].checked_add(1) == Some(w[1]));
+        let mut y_coordinates = if consecutive {
+            polynomial.evaluate_consecutive(identifiers[0] as usize, identifiers.len())
+        } else {
+            identifiers
+                .iter()
+                .map(|i| polynomial.evaluate(&S::from_usize(*i as usize)))
+                .collect::<Vec<S>>()
+        };
+        let shares = identifiers
+            .iter()
+            .zip(y_coordinates.iter())
+            .map(|(identifier, y)| Share {
+                identifier: *identifier,
                 value: y.to_bytes().to_vec(),
-            });
-        }
+            })
+            .collect::<Vec<Share>>();
+        y_coordinates.zeroize();
         Ok((shares, polynomial))
     }
 
     /// Reconstruct a secret from shares created from `split_secret`
     pub fn combine_shares<S: Field, R: Group<S>>(&self, shares: &[Share]) -> SharingResult<R> {
+        let res = self.combine::<S, R>(shares);
+        let identifiers = shares.iter().map(|s| s.identifier).collect::<Vec<u32>>();
+        notify(Event::Combine {
+            threshold: self.threshold,
+            identifiers: identifiers.as_slice(),
+            error: res.as_ref().err().copied(),
+        });
+        res
+    }
+
+    /// Combine points computed from at least `threshold` shares, each paired with
+    /// the identifier of its share, into the point for the secret.
+    /// For example `g^share_i` gives `g^secret`, and the partial decryptions `c^share_i`
+    /// of threshold ElGamal give `c^secret`, without the secret ever being reconstructed
+    pub fn combine_commitments<S: Field, R: Group<S>>(
+        &self,
+        points: &[(u32, R)],
+    ) -> SharingResult<R> {
+        let res = if points.len() < self.threshold {
+            Err(SharingError::ShareMinThreshold)
+        } else {
+            lagrange_interpolate_points::<S, R>(points)
+        };
+        let identifiers = points.iter().map(|(i, _)| *i).collect::<Vec<u32>>();
+        notify(Event::Combine {
+            threshold: self.threshold,
+            identifiers: identifiers.as_slice(),
+            error: res.as_ref().err().copied(),
+        });
+        res
+    }
+
+    pub(crate) fn combine<S: Field, R: Group<S>>(&self, shares: &[Share]) -> SharingResult<R> {
         // Verify minimum shares
         if shares.len() < self.threshold {
             return Err(SharingError::ShareMinThreshold);
@@ -149,6 +395,10 @@ impl Scheme {
         let mut dups = BTreeSet::new();
         let mut x_coordinates = Vec::with_capacity(shares.len());
         let mut y_coordinates = Vec::with_capacity(shares.len());
+        // Only identifiers and lengths are public so the share values
+        // are checked without exiting early and the result is only used
+        // after interpolating
+        let mut valid = true;
 
         for share in shares {
             if share.identifier == 0 {
@@ -159,49 +409,90 @@ impl Scheme {
             }
 
             let y = R::from_bytes(&share.value)?;
-            if !y.is_valid() {
-                return Err(SharingError::ShareInvalidValue);
-            }
+            valid &= y.is_valid();
             let x = S::from_usize(share.identifier as usize);
             dups.insert(share.identifier);
             x_coordinates.push(x);
             y_coordinates.push(y);
         }
-        let secret = Self::interpolate(x_coordinates.as_slice(), y_coordinates.as_slice());
-        Ok(secret)
+        let secret = Self::interpolate(
+            x_coordinates.as_slice(),
+            y_coordinates.iter().collect::<Vec<&R>>().as_slice(),
+        );
+        // The share values are secret when combining a secret, not commitments
+        y_coordinates.iter_mut().for_each(|y| y.clear());
+        let secret = secret?;
+        if valid {
+            Ok(secret)
+        } else {
+            Err(SharingError::ShareInvalidValue)
+        }
     }
 
-    /// Calculate lagrange interpolation
-    fn interpolate<S: Field, R: Group<S>>(x_coordinates: &[S], y_coordinates: &[R]) -> R {
+    /// Calculate lagrange interpolation.
+    /// Fails if two x coordinates are the same in the field
+    pub(crate) fn interpolate<S: Field, R: Group<S>>(
+        x_coordinates: &[S],
+        y_coordinates: &[&R],
+    ) -> SharingResult<R> {
         debug_assert_eq!(x_coordinates.len(), y_coordinates.len());
 
-        let limit = x_coordinates.len();
+        let mut coefficients = lagrange_coefficients(x_coordinates)?;
         // Initialize to zero
         let mut result = R::zero();
-
-        for i in 0..limit {
-            let mut basis = S::one();
-            for j in 0..limit {
-                if i == j {
-                    continue;
-                }
-
-                // x_m
-                let mut x_m = S::zero();
-                x_m.add_assign(&x_coordinates[j]);
-                // x_m - x_j
-                let mut denom = S::zero();
-                denom.add_assign(&x_coordinates[j]);
-                denom.sub_assign(&x_coordinates[i]);
-                // x_m / (x_m - x_j) * ...
-                x_m.scalar_div_assign(&denom);
-                basis.scalar_mul_assign(&x_m);
-            }
+        for (y, basis) in y_coordinates.iter().zip(coefficients.iter()) {
             let mut group = R::zero();
-            group.add_assign(&y_coordinates[i]);
-            group.scalar_mul_assign(&basis);
+            group.add_assign(y);
+            group.scalar_mul_assign(basis);
             result.add_assign(&group);
+            group.clear();
+        }
+        coefficients.iter_mut().for_each(|c| c.clear());
+        Ok(result)
+    }
+}
+
+/// The non-zero share identifier for the participant id `id`, from SHA-256 of the id.
+/// Identifiers stay 32 bits so shares keep their encoding and can be combined in any order.
+pub fn participant_identifier(id: u64) -> u32 {
+    let mut counter = 0u8;
+    loop {
+        let mut hasher = Sha256::new();
+        hasher.input(b"ursa_sharing participant identifier");
+        hasher.input(id.to_be_bytes());
+        hasher.input([counter]);
+        let digest = hasher.result();
+        let identifier = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        if identifier != 0 {
+            return identifier;
+        }
+        counter = counter.wrapping_add(1);
+    }
+}
+
+/// Interpolate `(identifier, point)` pairs at zero in the exponent, the point for the
+/// secret when each point is a fixed point raised to a share.
+/// Fails if there are no points, an identifier is zero or repeated, or a point is the identity.
+/// The threshold isn't checked, [`Scheme::combine_commitments`] also checks it
+pub fn lagrange_interpolate_points<S: Field, R: Group<S>>(points: &[(u32, R)]) -> SharingResult<R> {
+    if points.is_empty() {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    let mut dups = BTreeSet::new();
+    let mut x_coordinates = Vec::with_capacity(points.len());
+    let mut y_coordinates = Vec::with_capacity(points.len());
+    for (identifier, point) in points {
+        if *identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        if !dups.insert(*identifier) {
+            return Err(SharingError::ShareDuplicateIdentifier);
+        }
+        if !point.is_valid() {
+            return Err(SharingError::ShareInvalidValue);
         }
-        result
+        x_coordinates.push(S::from_usize(*identifier as usize));
+        y_coordinates.push(point);
     }
+    Scheme::interpolate(x_coordinates.as_slice(), y_coordinates.as_slice())
 }
@@ -0,0 +1,712 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Permutation commitments and proofs that a committed permutation was applied
+//! to a vector of commitments, following the Terelius-Wikström proof of a shuffle.
+//!
+//! A [`Permutation`] maps output `i` to input `indices()[i]`. It can be drawn at
+//! random or derived from a seed, so a lottery can commit to the seed's permutation
+//! before the tickets are known and reveal the seed afterwards.
+//!
+//! A [`PermutationCommitment`] commits to a permutation with the generators `g` and
+//! `h_0, ..., h_{n-1}`. [`shuffle`] permutes `n` commitments and rerandomizes each by
+//! adding `g^t`, e.g. Pedersen commitments `g^r h^m` whose blinding generator is `g`.
+//! A [`ShuffleProof`] then shows the outputs are the inputs permuted by the committed
+//! permutation and rerandomized, without revealing the permutation.
+//! The proof has `2n + 4` points and `2n + 4` scalars.
+//!
+//! The generators must be independent, nobody may know the discrete log of one to
+//! another, or the commitment isn't binding. Use generators from a trusted setup or
+//! hashed to the group, not `R::random` by one of the parties.
+//! Like [`DleqProof`](crate::dleq::DleqProof) the proof is bound to a [`Transcript`]
+//! supplied by the caller, which the verifier must rebuild in the same state.
+//!
+//! Shuffles of ElGamal ciphertexts need the same proof over pairs of points and are
+//! not covered here.
+
+use super::{
+    dkg::Reader,
+    error::{SharingError, SharingResult},
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+const SEED_DOMAIN: &[u8] = b"ursa_sharing permutation seed v1";
+
+/// A permutation of `n` elements, output `i` is input `indices()[i]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permutation(Vec<usize>);
+
+impl Permutation {
+    /// Create a permutation from its indices, each of `0..n` exactly once
+    pub fn new(indices: Vec<usize>) -> SharingResult<Self> {
+        let mut seen = BTreeSet::new();
+        for i in &indices {
+            if *i >= indices.len() || !seen.insert(*i) {
+                return Err(SharingError::PermutationInvalid);
+            }
+        }
+        Ok(Self(indices))
+    }
+
+    /// Draw a uniformly random permutation of `len` elements
+    pub fn random(rng: &mut (impl RngCore + CryptoRng), len: usize) -> Self {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let permutation = Self::from_seed(&seed, len);
+        seed.zeroize();
+        permutation
+    }
+
+    /// Derive the permutation of `len` elements for `seed` with a Fisher-Yates
+    /// shuffle. The same seed and length always give the same permutation
+    pub fn from_seed(seed: &[u8], len: usize) -> Self {
+        let mut transcript = Transcript::new(SEED_DOMAIN);
+        transcript.append_message(b"seed", seed);
+        transcript.append_u64(b"length", len as u64);
+        let mut indices = (0..len).collect::<Vec<usize>>();
+        for i in (1..len).rev() {
+            let j = uniform(&mut transcript, i as u64 + 1);
+            indices.swap(i, j as usize);
+        }
+        Self(indices)
+    }
+
+    /// The input index of each output
+    pub fn indices(&self) -> &[usize] {
+        self.0.as_slice()
+    }
+
+    /// The number of elements permuted
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// True iff this permutes no elements
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The permutation undoing this one
+    pub fn inverse(&self) -> Self {
+        let mut inverse = vec![0; self.0.len()];
+        for (i, j) in self.0.iter().enumerate() {
+            inverse[*j] = i;
+        }
+        Self(inverse)
+    }
+
+    /// Permute `items` which must have the same length
+    pub fn apply<T: Clone>(&self, items: &[T]) -> SharingResult<Vec<T>> {
+        if items.len() != self.0.len() {
+            return Err(SharingError::PermutationInvalid);
+        }
+        Ok(self.0.iter().map(|i| items[*i].clone()).collect())
+    }
+}
+
+/// A commitment to a permutation, `c_j = g^{r_j} h_i` where output `i` is input `j`
+#[derive(Debug)]
+pub struct PermutationCommitment<S: Field, R: Group<S>> {
+    commitments: Vec<R>,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: Group<S>> PermutationCommitment<S, R> {
+    /// Commit to `permutation` with a generator `h_i` per element.
+    /// The opening is secret until the permutation is revealed
+    pub fn commit(
+        rng: &mut (impl RngCore + CryptoRng),
+        (g, h): (&R, &[R]),
+        permutation: &Permutation,
+    ) -> SharingResult<(Self, PermutationOpening<S>)> {
+        if permutation.is_empty() || permutation.len() != h.len() {
+            return Err(SharingError::PermutationInvalid);
+        }
+        let randomness = (0..h.len()).map(|_| S::random(rng)).collect::<Vec<S>>();
+        let opening = PermutationOpening {
+            permutation: permutation.clone(),
+            randomness,
+        };
+        let commitment = Self {
+            commitments: opening.commitments(g, h),
+            _scalar: PhantomData,
+        };
+        Ok((commitment, opening))
+    }
+
+    /// The commitment of each input
+    pub fn commitments(&self) -> &[R] {
+        self.commitments.as_slice()
+    }
+
+    /// Check that `opening` opens this commitment
+    pub fn verify_opening(
+        &self,
+        (g, h): (&R, &[R]),
+        opening: &PermutationOpening<S>,
+    ) -> SharingResult<()> {
+        if opening.permutation.len() != self.commitments.len() || h.len() != self.commitments.len()
+        {
+            return Err(SharingError::PermutationInvalid);
+        }
+        let expected = opening.commitments(g, h);
+        if expected
+            .iter()
+            .zip(self.commitments.iter())
+            .all(|(a, b)| equal(a, b))
+        {
+            Ok(())
+        } else {
+            Err(SharingError::PermutationInvalid)
+        }
+    }
+
+    /// Serialize as the number of elements followed by the commitments
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(4 + self.commitments.len() * R::Size::to_usize());
+        o.extend_from_slice(&(self.commitments.len() as u32).to_be_bytes());
+        for c in &self.commitments {
+            o.extend_from_slice(&c.to_bytes());
+        }
+        o
+    }
+
+    /// Deserialize a commitment using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let n = reader.u32()? as usize;
+        let commitments = read_points(&mut reader, n, strictness)?;
+        if n == 0 || !reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            commitments,
+            _scalar: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PermutationCommitment<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The permutation and randomness of a [`PermutationCommitment`], cleared on drop
+pub struct PermutationOpening<S: Field> {
+    permutation: Permutation,
+    randomness: Vec<S>,
+}
+
+impl<S: Field> PermutationOpening<S> {
+    /// The committed permutation
+    pub fn permutation(&self) -> &Permutation {
+        &self.permutation
+    }
+
+    /// Serialize as the number of elements, the indices and the randomness
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let n = self.randomness.len();
+        let mut o = Vec::with_capacity(4 + n * (4 + S::Size::to_usize()));
+        o.extend_from_slice(&(n as u32).to_be_bytes());
+        for i in self.permutation.indices() {
+            o.extend_from_slice(&(*i as u32).to_be_bytes());
+        }
+        for r in &self.randomness {
+            o.extend_from_slice(&r.to_bytes());
+        }
+        o
+    }
+
+    /// Deserialize an opening using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let n = reader.u32()? as usize;
+        let mut indices = Vec::with_capacity(n.min(reader.data.len() / 4));
+        for _ in 0..n {
+            indices.push(reader.u32()? as usize);
+        }
+        let permutation = Permutation::new(indices)?;
+        let randomness = read_scalars(&mut reader, n, strictness)?;
+        if n == 0 || !reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            permutation,
+            randomness,
+        })
+    }
+
+    fn commitments<R: Group<S>>(&self, g: &R, h: &[R]) -> Vec<R> {
+        let mut commitments = self
+            .randomness
+            .iter()
+            .map(|r| mul(g, r))
+            .collect::<Vec<R>>();
+        for (i, j) in self.permutation.indices().iter().enumerate() {
+            commitments[*j].add_assign(&h[i]);
+        }
+        commitments
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for PermutationOpening<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+impl<S: Field> Debug for PermutationOpening<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "PermutationOpening(..)")
+    }
+}
+
+impl<S: Field> Drop for PermutationOpening<S> {
+    fn drop(&mut self) {
+        self.permutation.0.zeroize();
+        self.randomness.zeroize();
+    }
+}
+
+/// Permute `inputs` and rerandomize output `i` by adding `g^{t_i}`.
+/// Returns the outputs and the secret `t_i` needed to prove the shuffle
+pub fn shuffle<S: Field, R: Group<S>>(
+    rng: &mut (impl RngCore + CryptoRng),
+    g: &R,
+    inputs: &[R],
+    permutation: &Permutation,
+) -> SharingResult<(Vec<R>, Vec<S>)> {
+    if inputs.len() != permutation.len() {
+        return Err(SharingError::PermutationInvalid);
+    }
+    let rerandomization = (0..inputs.len())
+        .map(|_| S::random(rng))
+        .collect::<Vec<S>>();
+    let outputs = permutation
+        .indices()
+        .iter()
+        .zip(rerandomization.iter())
+        .map(|(j, t)| {
+            let mut w = mul(g, t);
+            w.add_assign(&inputs[*j]);
+            w
+        })
+        .collect();
+    Ok((outputs, rerandomization))
+}
+
+/// Proof that the outputs of a shuffle are the inputs permuted by a committed
+/// permutation and rerandomized by `g`
+#[derive(Debug)]
+pub struct ShuffleProof<S: Field, R: Group<S>> {
+    /// Commitments to the running products of the permuted exponents
+    chain: Vec<R>,
+    /// Schnorr commitments of the four aggregate relations and the chain
+    t: [R; 4],
+    t_chain: Vec<R>,
+    /// Responses of the four aggregate relations, the chain and the permuted exponents
+    k: [S; 4],
+    k_chain: Vec<S>,
+    k_exponents: Vec<S>,
+}
+
+impl<S: Field, R: Group<S>> ShuffleProof<S, R> {
+    /// Prove that `outputs` are `inputs` shuffled with the permutation of `opening`
+    /// and `rerandomization` as returned by [`shuffle`].
+    /// If they aren't the proof is created but doesn't verify
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove(
+        rng: &mut (impl RngCore + CryptoRng),
+        transcript: &mut Transcript,
+        (g, h): (&R, &[R]),
+        commitment: &PermutationCommitment<S, R>,
+        opening: &PermutationOpening<S>,
+        inputs: &[R],
+        outputs: &[R],
+        rerandomization: &[S],
+    ) -> SharingResult<Self> {
+        let n = commitment.commitments.len();
+        if [
+            h.len(),
+            opening.permutation.len(),
+            inputs.len(),
+            outputs.len(),
+            rerandomization.len(),
+        ]
+        .iter()
+        .any(|l| *l != n)
+        {
+            return Err(SharingError::PermutationInvalid);
+        }
+        let e = exponents(transcript, (g, h), commitment, inputs, outputs)?;
+        let mut e_perm = opening
+            .permutation
+            .indices()
+            .iter()
+            .map(|j| copy(&e[*j]))
+            .collect::<Vec<S>>();
+
+        // c^_i = g^{r^_i} c^_{i-1}^{e'_i} with c^_{-1} = h_0
+        let mut r_chain = random_scalars(rng, n);
+        let mut chain = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut c = mul(g, &r_chain[i]);
+            c.add_assign(&mul(chain.last().unwrap_or(&h[0]), &e_perm[i]));
+            chain.push(c);
+        }
+
+        let mut w = random_scalars(rng, 4);
+        let mut w_chain = random_scalars(rng, n);
+        let mut w_exponents = random_scalars(rng, n);
+        let mut t3 = mul(g, &w[2]);
+        t3.add_assign(&multi_mul(h, &w_exponents));
+        let mut t4 = multi_mul(outputs, &w_exponents);
+        t4.sub_assign(&mul(g, &w[3]));
+        let t = [mul(g, &w[0]), mul(g, &w[1]), t3, t4];
+        let t_chain = (0..n)
+            .map(|i| {
+                let mut t = mul(g, &w_chain[i]);
+                t.add_assign(&mul(
+                    if i == 0 { &h[0] } else { &chain[i - 1] },
+                    &w_exponents[i],
+                ));
+                t
+            })
+            .collect::<Vec<R>>();
+        let v = challenge(transcript, &chain, &t, &t_chain)?;
+
+        // The committed randomness aggregated as each relation needs it
+        let mut r_sum = S::zero();
+        let mut r_inner = S::zero();
+        for (r, e) in opening.randomness.iter().zip(e.iter()) {
+            r_sum.add_assign(r);
+            r_inner.add_assign(&scalar_mul(r, e));
+        }
+        let mut r_chain_total = S::zero();
+        for (r, e) in r_chain.iter().zip(e_perm.iter()) {
+            r_chain_total.scalar_mul_assign(e);
+            r_chain_total.add_assign(r);
+        }
+        let mut t_inner = S::zero();
+        for (t, e) in rerandomization.iter().zip(e_perm.iter()) {
+            t_inner.add_assign(&scalar_mul(t, e));
+        }
+        let mut witnesses = [r_sum, r_chain_total, r_inner, t_inner];
+
+        let proof = Self {
+            chain,
+            t,
+            t_chain,
+            k: [
+                respond(&w[0], &v, &witnesses[0]),
+                respond(&w[1], &v, &witnesses[1]),
+                respond(&w[2], &v, &witnesses[2]),
+                respond(&w[3], &v, &witnesses[3]),
+            ],
+            k_chain: (0..n)
+                .map(|i| respond(&w_chain[i], &v, &r_chain[i]))
+                .collect(),
+            k_exponents: (0..n)
+                .map(|i| respond(&w_exponents[i], &v, &e_perm[i]))
+                .collect(),
+        };
+        // The permuted exponents reveal the permutation
+        e_perm.zeroize();
+        r_chain.zeroize();
+        w.zeroize();
+        w_chain.zeroize();
+        w_exponents.zeroize();
+        witnesses.iter_mut().for_each(|x| x.zeroize());
+        Ok(proof)
+    }
+
+    /// Check the proof against a transcript in the same state as the prover's
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        (g, h): (&R, &[R]),
+        commitment: &PermutationCommitment<S, R>,
+        inputs: &[R],
+        outputs: &[R],
+    ) -> SharingResult<()> {
+        let c = commitment.commitments();
+        let n = c.len();
+        if [
+            h.len(),
+            inputs.len(),
+            outputs.len(),
+            self.chain.len(),
+            self.t_chain.len(),
+            self.k_chain.len(),
+            self.k_exponents.len(),
+        ]
+        .iter()
+        .any(|l| *l != n)
+        {
+            return Err(SharingError::ShuffleProofInvalid);
+        }
+        let e = exponents(transcript, (g, h), commitment, inputs, outputs)
+            .map_err(|_| SharingError::ShuffleProofInvalid)?;
+        let v = challenge(transcript, &self.chain, &self.t, &self.t_chain)
+            .map_err(|_| SharingError::ShuffleProofInvalid)?;
+
+        // The commitment is to a matrix with rows summing to one
+        let mut u = R::zero();
+        for (c, h) in c.iter().zip(h.iter()) {
+            u.add_assign(c);
+            u.sub_assign(h);
+        }
+        let mut valid = check(&v, &u, &self.t[0], mul(g, &self.k[0]));
+
+        // The permuted exponents have the same product as the exponents
+        let mut product = S::one();
+        e.iter().for_each(|e| product.scalar_mul_assign(e));
+        let mut last = copy(&self.chain[n - 1]);
+        last.sub_assign(&mul(&h[0], &product));
+        valid &= check(&v, &last, &self.t[1], mul(g, &self.k[1]));
+
+        // The committed matrix maps the exponents to the permuted exponents
+        let mut rhs = mul(g, &self.k[2]);
+        rhs.add_assign(&multi_mul(h, &self.k_exponents));
+        valid &= check(&v, &multi_mul(c, &e), &self.t[2], rhs);
+
+        // The outputs with the permuted exponents match the inputs with the exponents
+        let mut lhs = multi_mul(inputs, &e);
+        lhs.scalar_mul_assign(&v);
+        lhs.add_assign(&self.t[3]);
+        lhs.add_assign(&mul(g, &self.k[3]));
+        valid &= equal(&lhs, &multi_mul(outputs, &self.k_exponents));
+
+        for i in 0..n {
+            let mut rhs = mul(g, &self.k_chain[i]);
+            rhs.add_assign(&mul(
+                if i == 0 { &h[0] } else { &self.chain[i - 1] },
+                &self.k_exponents[i],
+            ));
+            valid &= check(&v, &self.chain[i], &self.t_chain[i], rhs);
+        }
+        if valid {
+            Ok(())
+        } else {
+            Err(SharingError::ShuffleProofInvalid)
+        }
+    }
+
+    /// The size of an encoded proof of `n` elements
+    pub fn size(n: usize) -> usize {
+        4 + (2 * n + 4) * (R::Size::to_usize() + S::Size::to_usize())
+    }
+
+    /// Serialize as the number of elements, the points and the scalars
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let n = self.chain.len();
+        let mut o = Vec::with_capacity(Self::size(n));
+        o.extend_from_slice(&(n as u32).to_be_bytes());
+        for p in self
+            .chain
+            .iter()
+            .chain(self.t.iter())
+            .chain(self.t_chain.iter())
+        {
+            o.extend_from_slice(&p.to_bytes());
+        }
+        for s in self
+            .k
+            .iter()
+            .chain(self.k_chain.iter())
+            .chain(self.k_exponents.iter())
+        {
+            o.extend_from_slice(&s.to_bytes());
+        }
+        o
+    }
+
+    /// Deserialize a proof using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let n = reader.u32()? as usize;
+        if n == 0 || reader.data.len() != Self::size(n) - 4 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let chain = read_points(&mut reader, n, strictness)?;
+        let mut t = read_points(&mut reader, 4, strictness)?.into_iter();
+        let t_chain = read_points(&mut reader, n, strictness)?;
+        let mut k = read_scalars(&mut reader, 4, strictness)?.into_iter();
+        let k_chain = read_scalars(&mut reader, n, strictness)?;
+        let k_exponents = read_scalars(&mut reader, n, strictness)?;
+        let mut next_t = || t.next().ok_or(SharingError::ShareInvalidEncoding);
+        let t = [next_t()?, next_t()?, next_t()?, next_t()?];
+        let mut next_k = || k.next().ok_or(SharingError::ShareInvalidEncoding);
+        let k = [next_k()?, next_k()?, next_k()?, next_k()?];
+        Ok(Self {
+            chain,
+            t,
+            t_chain,
+            k,
+            k_chain,
+            k_exponents,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for ShuffleProof<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// Bind the statement to the transcript and draw an exponent per input
+fn exponents<S: Field, R: Group<S>>(
+    transcript: &mut Transcript,
+    (g, h): (&R, &[R]),
+    commitment: &PermutationCommitment<S, R>,
+    inputs: &[R],
+    outputs: &[R],
+) -> SharingResult<Vec<S>> {
+    transcript.append_u64(b"length", inputs.len() as u64);
+    transcript.append_point(b"generator", g);
+    for p in h {
+        transcript.append_point(b"generator", p);
+    }
+    for p in commitment.commitments() {
+        transcript.append_point(b"permutation", p);
+    }
+    for p in inputs {
+        transcript.append_point(b"input", p);
+    }
+    for p in outputs {
+        transcript.append_point(b"output", p);
+    }
+    (0..inputs.len())
+        .map(|_| transcript.challenge_scalar(b"exponent"))
+        .collect()
+}
+
+fn challenge<S: Field, R: Group<S>>(
+    transcript: &mut Transcript,
+    chain: &[R],
+    t: &[R],
+    t_chain: &[R],
+) -> SharingResult<S> {
+    for p in chain {
+        transcript.append_point(b"chain", p);
+    }
+    for p in t.iter().chain(t_chain.iter()) {
+        transcript.append_point(b"commitment", p);
+    }
+    transcript.challenge_scalar(b"challenge")
+}
+
+/// Draw a number below `bound` without bias
+fn uniform(transcript: &mut Transcript, bound: u64) -> u64 {
+    let zone = u64::MAX - u64::MAX % bound;
+    loop {
+        let mut buf = [0u8; 8];
+        transcript.challenge_bytes(b"index", &mut buf);
+        let value = u64::from_be_bytes(buf);
+        if value < zone {
+            return value % bound;
+        }
+    }
+}
+
+/// `x^v * t == rhs`
+fn check<S: Field, R: Group<S>>(v: &S, x: &R, t: &R, rhs: R) -> bool {
+    let mut lhs = mul(x, v);
+    lhs.add_assign(t);
+    equal(&lhs, &rhs)
+}
+
+/// `w + v * x`
+fn respond<S: Field>(w: &S, v: &S, x: &S) -> S {
+    let mut r = scalar_mul(v, x);
+    r.add_assign(w);
+    r
+}
+
+fn random_scalars<S: Field>(rng: &mut (impl RngCore + CryptoRng), n: usize) -> Vec<S> {
+    (0..n).map(|_| S::random(rng)).collect()
+}
+
+fn read_points<S: Field, R: Group<S>>(
+    reader: &mut Reader,
+    n: usize,
+    strictness: Strictness,
+) -> SharingResult<Vec<R>> {
+    let size = R::Size::to_usize();
+    let mut points = Vec::with_capacity(n.min(reader.data.len() / size));
+    for _ in 0..n {
+        points.push(R::from_bytes_with(reader.take(size)?, strictness)?);
+    }
+    Ok(points)
+}
+
+fn read_scalars<S: Field>(
+    reader: &mut Reader,
+    n: usize,
+    strictness: Strictness,
+) -> SharingResult<Vec<S>> {
+    let size = S::Size::to_usize();
+    let mut scalars = Vec::with_capacity(n.min(reader.data.len() / size));
+    for _ in 0..n {
+        scalars.push(S::from_bytes_with(reader.take(size)?, strictness)?);
+    }
+    Ok(scalars)
+}
+
+fn multi_mul<S: Field, R: Group<S>>(points: &[R], scalars: &[S]) -> R {
+    let mut r = R::zero();
+    for (p, s) in points.iter().zip(scalars.iter()) {
+        r.add_assign(&mul(p, s));
+    }
+    r
+}
+
+fn scalar_mul<S: Field>(a: &S, b: &S) -> S {
+    let mut r = copy(a);
+    r.scalar_mul_assign(b);
+    r
+}
+
+fn equal<E: ?Sized, R: Group<E>>(a: &R, b: &R) -> bool {
+    a.to_bytes() == b.to_bytes()
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
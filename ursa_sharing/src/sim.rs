@@ -0,0 +1,375 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! A deterministic in-process network for testing multi-party protocols.
+//!
+//! A [`Simulation`] wires participants implementing [`Node`] together and delivers
+//! their messages in virtual time, so a DKG, FROST or refresh session can be tested
+//! with slow, lossy links and misbehaving participants without real infrastructure.
+//! Every [`Link`] has a latency range and a drop rate, and a participant's [`Behavior`]
+//! makes it go silent, crash at a point in time or change, drop or equivocate on any
+//! message it sends. Timers let nodes end a round when messages don't arrive.
+//!
+//! Everything random, the latencies, the drops and the RNG every node is given, comes
+//! from the seed of the simulation, so a seed that finds a bug replays it exactly.
+//! The node RNGs are seeded deterministically and must only be used for testing.
+//!
+//! ```
+//! # use ursa_sharing::sim::*;
+//! /// Counts the pings it receives
+//! struct Pinger(usize);
+//!
+//! impl Node for Pinger {
+//!     type Message = ();
+//!
+//!     fn start(&mut self, ctx: &mut Context<()>) {
+//!         ctx.broadcast(());
+//!     }
+//!
+//!     fn receive(&mut self, _ctx: &mut Context<()>, _from: u32, _message: ()) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let mut sim = Simulation::new(7);
+//! sim.set_default_link(Link { min_latency: 1, max_latency: 5, drop_rate: 0.0 });
+//! for id in 1..=3 {
+//!     sim.add_node(id, Pinger(0), Behavior::Honest);
+//! }
+//! sim.add_node(4, Pinger(0), Behavior::Silent);
+//! sim.run(100);
+//! // Node 4 hears everyone but nobody hears it
+//! assert_eq!(sim.node(1).unwrap().0, 2);
+//! assert_eq!(sim.node(4).unwrap().0, 3);
+//! assert_eq!(sim.stats().delivered, 9);
+//! ```
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use std::collections::BTreeMap;
+
+/// A participant of a simulated protocol
+pub trait Node {
+    /// The messages the participants exchange
+    type Message: Clone;
+
+    /// Called once when the simulation starts
+    fn start(&mut self, ctx: &mut Context<Self::Message>);
+
+    /// Handle `message` sent by `from`
+    fn receive(&mut self, ctx: &mut Context<Self::Message>, from: u32, message: Self::Message);
+
+    /// Handle a timer set with [`Context::set_timer`]
+    fn timeout(&mut self, _ctx: &mut Context<Self::Message>, _timer: u64) {}
+}
+
+/// What a node can see and do while it handles an event
+pub struct Context<'a, M> {
+    id: u32,
+    now: u64,
+    peers: &'a [u32],
+    rng: &'a mut ChaChaRng,
+    outbox: Vec<(Option<u32>, M)>,
+    timers: Vec<(u64, u64)>,
+}
+
+impl<'a, M> Context<'a, M> {
+    /// The identifier of the node
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The current virtual time
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// The identifiers of all the other nodes
+    pub fn peers(&self) -> &[u32] {
+        self.peers
+    }
+
+    /// The node's RNG, seeded from the simulation seed
+    pub fn rng(&mut self) -> &mut ChaChaRng {
+        self.rng
+    }
+
+    /// Send `message` to the node `to`
+    pub fn send(&mut self, to: u32, message: M) {
+        self.outbox.push((Some(to), message));
+    }
+
+    /// Send `message` to every other node
+    pub fn broadcast(&mut self, message: M) {
+        self.outbox.push((None, message));
+    }
+
+    /// Call [`Node::timeout`] with `timer` after `delay`
+    pub fn set_timer(&mut self, delay: u64, timer: u64) {
+        self.timers.push((delay, timer));
+    }
+}
+
+/// The latency and reliability of the messages from one node to another
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Link {
+    /// The shortest time a message takes
+    pub min_latency: u64,
+    /// The longest time a message takes
+    pub max_latency: u64,
+    /// The probability a message is lost, `1.0` partitions the nodes
+    pub drop_rate: f64,
+}
+
+impl Default for Link {
+    /// Every message arrives after one time unit
+    fn default() -> Self {
+        Self {
+            min_latency: 1,
+            max_latency: 1,
+            drop_rate: 0.0,
+        }
+    }
+}
+
+/// How a node deviates from the protocol
+pub enum Behavior<M> {
+    /// Follows the protocol
+    Honest,
+    /// Receives but never sends
+    Silent,
+    /// Stops sending, receiving and handling timers at the given time
+    Crash(u64),
+    /// Every message it sends is passed with its receiver to the function,
+    /// which returns the message to send instead or `None` to drop it. Returning
+    /// different messages to different receivers equivocates
+    Byzantine(Box<dyn FnMut(u32, M) -> Option<M>>),
+}
+
+/// Counts of the messages in a simulation
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Messages put on a link
+    pub sent: usize,
+    /// Messages handled by their receiver
+    pub delivered: usize,
+    /// Messages lost on a link or sent to a node that doesn't exist or crashed
+    pub dropped: usize,
+}
+
+enum Event<M> {
+    Deliver { from: u32, to: u32, message: M },
+    Timer { node: u32, timer: u64 },
+}
+
+struct Slot<N: Node> {
+    node: N,
+    behavior: Behavior<N::Message>,
+    rng: ChaChaRng,
+}
+
+/// A network of nodes driven in virtual time
+pub struct Simulation<N: Node> {
+    now: u64,
+    sequence: u64,
+    started: bool,
+    rng: ChaChaRng,
+    default_link: Link,
+    links: BTreeMap<(u32, u32), Link>,
+    nodes: BTreeMap<u32, Slot<N>>,
+    /// Pending events by time, ties broken by the order they were scheduled
+    events: BTreeMap<(u64, u64), Event<N::Message>>,
+    stats: Stats,
+}
+
+impl<N: Node> Simulation<N> {
+    /// Create an empty network where all randomness comes from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            now: 0,
+            sequence: 0,
+            started: false,
+            rng: ChaChaRng::seed_from_u64(seed),
+            default_link: Link::default(),
+            links: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+            events: BTreeMap::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    /// Use `link` between nodes without a link of their own
+    pub fn set_default_link(&mut self, link: Link) {
+        self.default_link = link;
+    }
+
+    /// Use `link` for the messages from `from` to `to`
+    pub fn set_link(&mut self, from: u32, to: u32, link: Link) {
+        self.links.insert((from, to), link);
+    }
+
+    /// Add `node` as `id`, replacing any node with the same identifier
+    pub fn add_node(&mut self, id: u32, node: N, behavior: Behavior<N::Message>) {
+        let rng = ChaChaRng::from_seed(self.rng.gen());
+        self.nodes.insert(
+            id,
+            Slot {
+                node,
+                behavior,
+                rng,
+            },
+        );
+    }
+
+    /// The node `id`
+    pub fn node(&self, id: u32) -> Option<&N> {
+        self.nodes.get(&id).map(|s| &s.node)
+    }
+
+    /// All nodes in order of their identifiers
+    pub fn nodes(&self) -> impl Iterator<Item = (u32, &N)> {
+        self.nodes.iter().map(|(id, s)| (*id, &s.node))
+    }
+
+    /// The current virtual time
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// The messages sent, delivered and dropped so far
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// True if no messages or timers are pending
+    pub fn is_idle(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Start the nodes if they weren't yet and handle every event up to time `until`.
+    /// Returns true if the network became idle
+    pub fn run(&mut self, until: u64) -> bool {
+        if !self.started {
+            self.started = true;
+            let ids = self.nodes.keys().copied().collect::<Vec<_>>();
+            for id in ids {
+                self.handle(id, |node, ctx| node.start(ctx));
+            }
+        }
+        while let Some((time, sequence)) = self.events.keys().next().copied() {
+            if time > until {
+                self.now = until;
+                return false;
+            }
+            self.now = time;
+            match self.events.remove(&(time, sequence)) {
+                Some(Event::Deliver { from, to, message }) => {
+                    if self.is_running(to) {
+                        self.stats.delivered += 1;
+                        self.handle(to, |node, ctx| node.receive(ctx, from, message));
+                    } else {
+                        self.stats.dropped += 1;
+                    }
+                }
+                Some(Event::Timer { node, timer }) => {
+                    self.handle(node, |n, ctx| n.timeout(ctx, timer));
+                }
+                None => {}
+            }
+        }
+        true
+    }
+
+    fn is_running(&self, id: u32) -> bool {
+        match self.nodes.get(&id) {
+            Some(Slot {
+                behavior: Behavior::Crash(at),
+                ..
+            }) => self.now < *at,
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Let node `id` handle an event and send what it produced
+    fn handle(&mut self, id: u32, f: impl FnOnce(&mut N, &mut Context<N::Message>)) {
+        if !self.is_running(id) {
+            return;
+        }
+        let peers = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|p| *p != id)
+            .collect::<Vec<_>>();
+        let slot = match self.nodes.get_mut(&id) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let mut ctx = Context {
+            id,
+            now: self.now,
+            peers: &peers,
+            rng: &mut slot.rng,
+            outbox: Vec::new(),
+            timers: Vec::new(),
+        };
+        f(&mut slot.node, &mut ctx);
+        let Context { outbox, timers, .. } = ctx;
+
+        let mut messages = Vec::new();
+        for (to, message) in outbox {
+            let receivers = match to {
+                Some(to) => vec![to],
+                None => peers.clone(),
+            };
+            for to in receivers {
+                let message = match &mut slot.behavior {
+                    Behavior::Honest | Behavior::Crash(_) => Some(message.clone()),
+                    Behavior::Silent => None,
+                    Behavior::Byzantine(tamper) => tamper(to, message.clone()),
+                };
+                if let Some(message) = message {
+                    messages.push((to, message));
+                }
+            }
+        }
+        for (to, message) in messages {
+            self.transmit(id, to, message);
+        }
+        for (delay, timer) in timers {
+            self.schedule(delay, Event::Timer { node: id, timer });
+        }
+    }
+
+    fn transmit(&mut self, from: u32, to: u32, message: N::Message) {
+        self.stats.sent += 1;
+        let link = *self.links.get(&(from, to)).unwrap_or(&self.default_link);
+        if !self.nodes.contains_key(&to) || self.rng.gen::<f64>() < link.drop_rate {
+            self.stats.dropped += 1;
+            return;
+        }
+        let latency = if link.max_latency > link.min_latency {
+            self.rng.gen_range(link.min_latency, link.max_latency + 1)
+        } else {
+            link.min_latency
+        };
+        self.schedule(latency, Event::Deliver { from, to, message });
+    }
+
+    fn schedule(&mut self, delay: u64, event: Event<N::Message>) {
+        self.events
+            .insert((self.now.saturating_add(delay), self.sequence), event);
+        self.sequence += 1;
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Stealth addresses and key blinding over any `Field` and `Group`,
+//! e.g. secp256k1 or Ristretto255.
+//!
+//! A receiver holds [`StealthKeys`], a scan secret `a` and a spend secret `b`, and
+//! publishes the [`StealthAddress`] `(A = g^a, B = g^b)`. A sender picks `r` and
+//! derives a [`OneTimeKey`] `P = B * g^t` where `t = H(R, A^r)` and `R = g^r` is
+//! published next to it. Only the receiver can tell that `P` belongs to it, by computing
+//! the same `t` from `R^a`, and only the receiver can sign for it with `b + t`.
+//! Different one time keys of the same address can't be linked to each other.
+//!
+//! Detection needs only the scan secret, so the receiver can hand a [`ScanKey`] to a
+//! server that watches for its payments without being able to spend them.
+//!
+//! [`blind_public_key`] and [`blind_secret_key`] derive a key for an identity or
+//! context string, like Tor's onion service key blinding: `Y' = Y^h` and `x' = x * h`
+//! with `h = H(Y, context)`. Anyone knowing `Y` and the context can compute `Y'`,
+//! but the blinded keys of different contexts can't be linked without knowing `Y`.
+
+use super::{
+    error::{SharingError, SharingResult},
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::vec::Vec;
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+
+const STEALTH_DOMAIN: &[u8] = b"ursa_sharing stealth address v1";
+const BLIND_DOMAIN: &[u8] = b"ursa_sharing key blinding v1";
+
+/// The receiver's scan and spend secret keys
+pub struct StealthKeys<S: Field> {
+    scan: S,
+    spend: S,
+}
+
+impl<S: Field> StealthKeys<S> {
+    /// Generate new keys
+    pub fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self {
+            scan: S::random(rng),
+            spend: S::random(rng),
+        }
+    }
+
+    /// The address to publish, `(g^a, g^b)`
+    pub fn address<R: Group<S>>(&self, g: &R) -> StealthAddress<S, R> {
+        StealthAddress {
+            scan: mul(g, &self.scan),
+            spend: mul(g, &self.spend),
+            _scalar: PhantomData,
+        }
+    }
+
+    /// The key that detects one time keys of this address but can't spend them
+    pub fn scan_key<R: Group<S>>(&self, g: &R) -> ScanKey<S, R> {
+        ScanKey {
+            scan: copy(&self.scan),
+            spend: mul(g, &self.spend),
+        }
+    }
+
+    /// The secret key `b + t` of a one time key derived for this address.
+    /// Fails with `StealthKeyNotOwned` if it was derived for another address
+    pub fn one_time_secret<R: Group<S>>(
+        &self,
+        g: &R,
+        one_time_key: &OneTimeKey<S, R>,
+    ) -> SharingResult<S> {
+        let scan_key = self.scan_key(g);
+        let mut secret = scan_key.tweak(one_time_key)?;
+        if !equal(&scan_key.expected(g, &secret), &one_time_key.key) {
+            return Err(SharingError::StealthKeyNotOwned);
+        }
+        secret.add_assign(&self.spend);
+        Ok(secret)
+    }
+
+    /// Serialize as `scan | spend`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.scan.to_bytes().to_vec();
+        o.extend_from_slice(&self.spend.to_bytes());
+        o
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for StealthKeys<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        if data.len() != 2 * s_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let keys = Self {
+            scan: S::from_bytes(&data[..s_size])?,
+            spend: S::from_bytes(&data[s_size..])?,
+        };
+        if !keys.scan.is_valid() || !keys.spend.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        Ok(keys)
+    }
+}
+
+impl<S: Field> Debug for StealthKeys<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "StealthKeys(..)")
+    }
+}
+
+impl<S: Field> Drop for StealthKeys<S> {
+    fn drop(&mut self) {
+        self.scan.zeroize();
+        self.spend.zeroize();
+    }
+}
+
+/// The public scan and spend keys of a receiver
+#[derive(Clone, Debug)]
+pub struct StealthAddress<S: Field, R: Group<S>> {
+    scan: R,
+    spend: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: Group<S>> StealthAddress<S, R> {
+    /// The scan public key
+    pub fn scan(&self) -> &R {
+        &self.scan
+    }
+
+    /// The spend public key
+    pub fn spend(&self) -> &R {
+        &self.spend
+    }
+
+    /// Derive a fresh one time key for this address
+    pub fn derive(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        g: &R,
+    ) -> SharingResult<OneTimeKey<S, R>> {
+        if !self.scan.is_valid() || !self.spend.is_valid() {
+            return Err(SharingError::InvalidPoint);
+        }
+        let mut r = S::random(rng);
+        let ephemeral = mul(g, &r);
+        let t = tweak(&ephemeral, &mul(&self.scan, &r), &self.spend);
+        r.zeroize();
+        let mut t = t?;
+        let mut key = mul(g, &t);
+        t.zeroize();
+        key.add_assign(&self.spend);
+        Ok(OneTimeKey {
+            ephemeral,
+            key,
+            _scalar: PhantomData,
+        })
+    }
+
+    /// Serialize as `scan | spend`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.scan.to_bytes().to_vec();
+        o.extend_from_slice(&self.spend.to_bytes());
+        o
+    }
+
+    /// Deserialize an address using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let (scan, spend) = read_points(data, strictness)?;
+        Ok(Self {
+            scan,
+            spend,
+            _scalar: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for StealthAddress<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// A one time public key `P` and the ephemeral key `R` published with it
+#[derive(Clone, Debug)]
+pub struct OneTimeKey<S: Field, R: Group<S>> {
+    ephemeral: R,
+    key: R,
+    _scalar: PhantomData<S>,
+}
+
+impl<S: Field, R: Group<S>> OneTimeKey<S, R> {
+    /// The ephemeral public key `R`
+    pub fn ephemeral(&self) -> &R {
+        &self.ephemeral
+    }
+
+    /// The one time public key `P`
+    pub fn key(&self) -> &R {
+        &self.key
+    }
+
+    /// Serialize as `ephemeral | key`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.ephemeral.to_bytes().to_vec();
+        o.extend_from_slice(&self.key.to_bytes());
+        o
+    }
+
+    /// Deserialize a one time key using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let (ephemeral, key) = read_points(data, strictness)?;
+        Ok(Self {
+            ephemeral,
+            key,
+            _scalar: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for OneTimeKey<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The scan secret and spend public key, enough to detect one time keys
+/// of an address but not to spend them
+pub struct ScanKey<S: Field, R: Group<S>> {
+    scan: S,
+    spend: R,
+}
+
+impl<S: Field, R: Group<S>> ScanKey<S, R> {
+    /// True iff `one_time_key` was derived for this address
+    pub fn detect(&self, g: &R, one_time_key: &OneTimeKey<S, R>) -> bool {
+        match self.tweak(one_time_key) {
+            Ok(t) => equal(&self.expected(g, &t), &one_time_key.key),
+            Err(_) => false,
+        }
+    }
+
+    /// The indices of the one time keys derived for this address
+    pub fn scan(&self, g: &R, one_time_keys: &[OneTimeKey<S, R>]) -> Vec<usize> {
+        one_time_keys
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| self.detect(g, k))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Serialize as `scan | spend`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.scan.to_bytes().to_vec();
+        o.extend_from_slice(&self.spend.to_bytes());
+        o
+    }
+
+    /// `t = H(R, R^a)`
+    fn tweak(&self, one_time_key: &OneTimeKey<S, R>) -> SharingResult<S> {
+        let ephemeral = &one_time_key.ephemeral;
+        if !ephemeral.is_valid() || !ephemeral.is_torsion_free() {
+            return Err(SharingError::InvalidPoint);
+        }
+        tweak(ephemeral, &mul(ephemeral, &self.scan), &self.spend)
+    }
+
+    /// `B * g^t`
+    fn expected(&self, g: &R, t: &S) -> R {
+        let mut key = mul(g, t);
+        key.add_assign(&self.spend);
+        key
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for ScanKey<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        if data.len() != s_size + R::Size::to_usize() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let scan = S::from_bytes(&data[..s_size])?;
+        if !scan.is_valid() {
+            return Err(SharingError::ShareInvalidSecret);
+        }
+        let spend = R::from_bytes(&data[s_size..])?;
+        if !spend.is_valid() {
+            return Err(SharingError::InvalidPoint);
+        }
+        Ok(Self { scan, spend })
+    }
+}
+
+impl<S: Field, R: Group<S>> Debug for ScanKey<S, R> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "ScanKey(..)")
+    }
+}
+
+impl<S: Field, R: Group<S>> Drop for ScanKey<S, R> {
+    fn drop(&mut self) {
+        self.scan.zeroize();
+    }
+}
+
+/// Blind `public_key` for `context`, `Y^h` with `h = H(Y, context)`
+pub fn blind_public_key<S: Field, R: Group<S>>(public_key: &R, context: &[u8]) -> SharingResult<R> {
+    if !public_key.is_valid() {
+        return Err(SharingError::InvalidPoint);
+    }
+    Ok(mul(public_key, &blinding_factor(public_key, context)?))
+}
+
+/// Blind the secret key of `public_key` for `context`, `x * h` with `h = H(Y, context)`.
+/// The result is the secret key of `blind_public_key(public_key, context)`
+pub fn blind_secret_key<S: Field, R: Group<S>>(
+    secret_key: &S,
+    public_key: &R,
+    context: &[u8],
+) -> SharingResult<S> {
+    if !public_key.is_valid() {
+        return Err(SharingError::InvalidPoint);
+    }
+    if !secret_key.is_valid() {
+        return Err(SharingError::ShareInvalidSecret);
+    }
+    let mut key = blinding_factor(public_key, context)?;
+    key.scalar_mul_assign(secret_key);
+    Ok(key)
+}
+
+fn blinding_factor<S: Field, R: Group<S>>(public_key: &R, context: &[u8]) -> SharingResult<S> {
+    let mut transcript = Transcript::new(BLIND_DOMAIN);
+    transcript.append_point(b"public key", public_key);
+    transcript.append_message(b"context", context);
+    transcript.challenge_scalar(b"blinding factor")
+}
+
+fn tweak<S: Field, R: Group<S>>(ephemeral: &R, shared: &R, spend: &R) -> SharingResult<S> {
+    let mut transcript = Transcript::new(STEALTH_DOMAIN);
+    transcript.append_point(b"ephemeral", ephemeral);
+    transcript.append_point(b"shared", shared);
+    transcript.append_point(b"spend", spend);
+    transcript.challenge_scalar(b"tweak")
+}
+
+fn read_points<S: Field, R: Group<S>>(
+    data: &[u8],
+    strictness: Strictness,
+) -> SharingResult<(R, R)> {
+    let r_size = R::Size::to_usize();
+    if data.len() != 2 * r_size {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    Ok((
+        R::from_bytes_with(&data[..r_size], strictness)?,
+        R::from_bytes_with(&data[r_size..], strictness)?,
+    ))
+}
+
+fn equal<E: ?Sized, R: Group<E>>(a: &R, b: &R) -> bool {
+    a.to_bytes() == b.to_bytes()
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
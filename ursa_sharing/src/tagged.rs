@@ -0,0 +1,345 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! A versioned, self-describing encoding for Shamir shares.
+//!
+//! [`Share::to_bytes`] is only the identifier and the field bytes, so a share
+//! read back from storage can't tell which curve or threshold it belongs to and
+//! is happily combined with shares of another scheme. A [`TaggedShare`] adds a
+//! format version, a curve tag and the threshold:
+//!
+//! ```text
+//! version (1) | curve (1) | threshold (4) | identifier (4) | value
+//! ```
+//!
+//! All integers are big endian. Parsing is strict: unknown versions and curves,
+//! truncated data and, when the field is known, values of the wrong length or in
+//! a non-canonical encoding are rejected. [`combine_secret`] also refuses shares
+//! of different curves or thresholds. With the `fips` feature, shares of curves
+//! that aren't approved are refused when tagging and parsing.
+//!
+//! Tagged shares implement serde's traits. Human readable formats like JSON get
+//! the URL safe base64 encoding without padding, binary formats get the bytes.
+
+use super::{
+    error::{SharingError, SharingResult},
+    fips::{self, Algorithm},
+    shamir::{Scheme as ShamirScheme, Share},
+    Field,
+};
+use serde::{
+    de::{Error as DeError, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    convert::TryFrom,
+    fmt::{Formatter, Result as FmtResult},
+};
+use zeroize::Zeroize;
+
+/// The current tagged share format version
+pub const VERSION: u8 = 1;
+/// The smallest tag available to [`CurveTag::Custom`]
+pub const CUSTOM_TAG_START: u8 = 0x80;
+
+/// version, curve, threshold, identifier
+const HEADER_BYTES: usize = 2 + 4 + 4;
+
+/// The curve whose scalar field the share values are in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CurveTag {
+    /// Curve25519 in Edwards form
+    Ed25519,
+    /// The Ristretto group over Curve25519, which has the same scalars as Ed25519
+    /// but is kept apart so shares aren't mixed between protocols
+    Ristretto25519,
+    /// The curve secp256k1
+    Secp256k1,
+    /// The NIST curve P-256
+    Secp256r1,
+    /// The pairing friendly curve BLS12-381
+    Bls12381,
+    /// An application defined field, the tag must be at least `CUSTOM_TAG_START`
+    Custom(u8),
+}
+
+impl CurveTag {
+    /// The encoded tag
+    pub fn to_byte(self) -> u8 {
+        match self {
+            CurveTag::Ed25519 => 1,
+            CurveTag::Ristretto25519 => 2,
+            CurveTag::Secp256k1 => 3,
+            CurveTag::Secp256r1 => 4,
+            CurveTag::Bls12381 => 5,
+            CurveTag::Custom(tag) => tag,
+        }
+    }
+
+    /// Parse an encoded tag
+    pub fn from_byte(tag: u8) -> SharingResult<Self> {
+        match tag {
+            1 => Ok(CurveTag::Ed25519),
+            2 => Ok(CurveTag::Ristretto25519),
+            3 => Ok(CurveTag::Secp256k1),
+            4 => Ok(CurveTag::Secp256r1),
+            5 => Ok(CurveTag::Bls12381),
+            t if t >= CUSTOM_TAG_START => Ok(CurveTag::Custom(t)),
+            t => Err(SharingError::ShareUnknownCurve(t)),
+        }
+    }
+
+    /// The algorithm checked against the FIPS mode allow-list
+    pub fn algorithm(self) -> Algorithm {
+        match self {
+            CurveTag::Ed25519 => Algorithm::Ed25519,
+            CurveTag::Ristretto25519 => Algorithm::Ristretto25519,
+            CurveTag::Secp256k1 => Algorithm::Secp256k1,
+            CurveTag::Secp256r1 => Algorithm::Secp256r1,
+            CurveTag::Bls12381 => Algorithm::Bls12381,
+            CurveTag::Custom(_) => Algorithm::Custom,
+        }
+    }
+}
+
+/// A field whose shares can be tagged
+pub trait TaggedField: Field {
+    /// The tag written into the shares of this field
+    const CURVE: CurveTag;
+}
+
+#[cfg(feature = "bls12_381")]
+impl TaggedField for super::bls12_381::Bls12381Scalar {
+    const CURVE: CurveTag = CurveTag::Bls12381;
+}
+
+#[cfg(feature = "ed25519")]
+impl TaggedField for super::ed25519::Ed25519Scalar {
+    const CURVE: CurveTag = CurveTag::Ed25519;
+}
+
+#[cfg(feature = "ristretto")]
+impl TaggedField for super::ristretto::Ristretto25519Scalar {
+    const CURVE: CurveTag = CurveTag::Ristretto25519;
+}
+
+#[cfg(feature = "secp256k1")]
+impl TaggedField for super::secp256k1::Secp256k1Scalar {
+    const CURVE: CurveTag = CurveTag::Secp256k1;
+}
+
+#[cfg(feature = "secp256r1")]
+impl TaggedField for super::secp256r1::Secp256r1Scalar {
+    const CURVE: CurveTag = CurveTag::Secp256r1;
+}
+
+/// A Shamir share with its format version, curve and threshold
+#[derive(Clone, Debug, Zeroize)]
+#[zeroize(drop)]
+pub struct TaggedShare {
+    #[zeroize(skip)]
+    curve: CurveTag,
+    threshold: u32,
+    share: Share,
+}
+
+impl TaggedShare {
+    /// Tag a share of a `threshold` out of n scheme over the field `S`
+    pub fn new<S: TaggedField>(threshold: usize, share: &Share) -> SharingResult<Self> {
+        fips::check(S::CURVE.algorithm())?;
+        if let CurveTag::Custom(tag) = S::CURVE {
+            if tag < CUSTOM_TAG_START {
+                return Err(SharingError::ShareUnknownCurve(tag));
+            }
+        }
+        let threshold = u32::try_from(threshold).map_err(|_| SharingError::ShareInvalidEncoding)?;
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        check_value::<S>(share.value.as_slice())?;
+        Ok(Self {
+            curve: S::CURVE,
+            threshold,
+            share: share.clone(),
+        })
+    }
+
+    /// Tag every share returned by a `threshold` out of n split over the field `S`
+    pub fn tag_all<S: TaggedField>(threshold: usize, shares: &[Share]) -> SharingResult<Vec<Self>> {
+        shares
+            .iter()
+            .map(|s| Self::new::<S>(threshold, s))
+            .collect()
+    }
+
+    /// Get the curve
+    pub fn curve(&self) -> CurveTag {
+        self.curve
+    }
+
+    /// Get the minimum number of shares needed to combine
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.share.identifier
+    }
+
+    /// Return the share after checking it belongs to the field `S`
+    pub fn to_share<S: TaggedField>(&self) -> SharingResult<Share> {
+        if self.curve != S::CURVE {
+            return Err(SharingError::ShareMismatchedCurve);
+        }
+        check_value::<S>(self.share.value.as_slice())?;
+        Ok(self.share.clone())
+    }
+
+    /// Serialize to the binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(HEADER_BYTES + self.share.value.len());
+        o.push(VERSION);
+        o.push(self.curve.to_byte());
+        o.extend_from_slice(&self.threshold.to_be_bytes());
+        o.extend_from_slice(&self.share.to_bytes());
+        o
+    }
+
+    /// Serialize to URL safe base64 without padding
+    pub fn to_base64(&self) -> String {
+        let mut bytes = self.to_bytes();
+        let encoded = base64::encode_config(bytes.as_slice(), base64::URL_SAFE_NO_PAD);
+        bytes.zeroize();
+        encoded
+    }
+
+    /// Parse the base64 form
+    pub fn from_base64(value: &str) -> SharingResult<Self> {
+        let mut bytes = base64::decode_config(value.trim(), base64::URL_SAFE_NO_PAD)
+            .map_err(|_| SharingError::ShareInvalidEncoding)?;
+        let res = Self::try_from(bytes.as_slice());
+        bytes.zeroize();
+        res
+    }
+}
+
+impl TryFrom<&[u8]> for TaggedShare {
+    type Error = SharingError;
+
+    /// Parse the binary form. The value is only checked against its field
+    /// by [`TaggedShare::to_share`] and [`combine_secret`]
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        if data[0] != VERSION {
+            return Err(SharingError::ShareUnsupportedVersion(data[0]));
+        }
+        // At least one byte of value
+        if data.len() <= HEADER_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let curve = CurveTag::from_byte(data[1])?;
+        fips::check(curve.algorithm())?;
+        let mut threshold = [0u8; 4];
+        threshold.copy_from_slice(&data[2..6]);
+        let threshold = u32::from_be_bytes(threshold);
+        if threshold < 2 {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let share = Share::try_from(&data[6..])?;
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(Self {
+            curve,
+            threshold,
+            share,
+        })
+    }
+}
+
+impl Serialize for TaggedShare {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&self.to_base64())
+        } else {
+            let mut bytes = self.to_bytes();
+            let res = s.serialize_bytes(bytes.as_slice());
+            bytes.zeroize();
+            res
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TaggedShare {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct TaggedShareVisitor;
+
+        impl<'de> Visitor<'de> for TaggedShareVisitor {
+            type Value = TaggedShare;
+
+            fn expecting(&self, f: &mut Formatter) -> FmtResult {
+                write!(f, "a tagged share in base64 or bytes")
+            }
+
+            fn visit_str<E: DeError>(self, value: &str) -> Result<TaggedShare, E> {
+                TaggedShare::from_base64(value).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: DeError>(self, value: &[u8]) -> Result<TaggedShare, E> {
+                TaggedShare::try_from(value).map_err(E::custom)
+            }
+        }
+
+        if d.is_human_readable() {
+            d.deserialize_str(TaggedShareVisitor)
+        } else {
+            d.deserialize_bytes(TaggedShareVisitor)
+        }
+    }
+}
+
+/// Reconstruct a secret of the field `S` from tagged shares. Every share must be
+/// tagged with the curve of `S` and the same threshold
+pub fn combine_secret<S: TaggedField>(shares: &[TaggedShare]) -> SharingResult<S> {
+    let threshold = match shares.first() {
+        Some(s) => s.threshold(),
+        None => return Err(SharingError::ShareMinThreshold),
+    };
+    if shares.iter().any(|s| s.threshold() != threshold) {
+        return Err(SharingError::ShareMismatchedThreshold);
+    }
+    let plain = shares
+        .iter()
+        .map(|s| s.to_share::<S>())
+        .collect::<SharingResult<Vec<Share>>>()?;
+    let scheme = ShamirScheme::new(threshold, threshold.max(plain.len()))?;
+    scheme.combine_shares::<S, S>(plain.as_slice())
+}
+
+fn check_value<S: Field>(value: &[u8]) -> SharingResult<()> {
+    // Truncated or padded values could otherwise be accepted by lenient backends
+    if value.len() != S::zero().to_bytes().len() {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let element = S::from_bytes(value)?;
+    if element.to_bytes().as_slice() != value {
+        return Err(SharingError::NonCanonicalEncoding);
+    }
+    Ok(())
+}
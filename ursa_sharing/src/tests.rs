@@ -12,13 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use super::{
-    error::SharingResult,
+    audit::{clear_observer, set_observer, Event, Observer},
+    combine::CombineContext,
+    dkg::{Blame, Complaint, Misbehavior, Participant, Round1Broadcast, Round1Share},
+    dleq::DleqProof,
+    error::{DetectionError, SharingError, SharingResult},
+    escrow::{DecryptedShare, EscrowTranscript, Scheme as EscrowScheme},
     feldman::{FeldmanVerifier, Scheme as FeldmanVss},
+    gf256::{Scheme as ByteScheme, Share as ByteShare},
     pedersen::{PedersenVssResult, Scheme as PedersenVss},
-    shamir::{Scheme, Share},
-    Field, Group,
+    possession::PossessionProof,
+    refresh::{Reshare, Resharing},
+    shamir::{participant_identifier, Scheme, Share},
+    transcript::Transcript,
+    Field, Group, Strictness,
 };
+use generic_array::typenum::Unsigned;
 use rand::prelude::*;
+use std::{
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 /// Test invalid split arguments
 pub fn split_invalid_args<S: Field>() {
@@ -28,7 +45,7 @@ pub fn split_invalid_args<S: Field>() {
     let scheme = Scheme::new(2, 3).unwrap();
     let mut rng = thread_rng();
     assert!(scheme.split_secret(&mut rng, &S::zero()).is_err());
-    assert!(S::from_bytes(&[65u8; 1000]).is_err());
+    assert!(S::from_bytes([65u8; 1000]).is_err());
 }
 
 /// Test that combining should fail
@@ -39,7 +56,7 @@ pub fn combine_invalid<S: Field>() {
     assert!(scheme.combine_shares::<S, S>(shares.as_slice()).is_err());
 
     // No secret
-    let shares = vec![Share::new(1, &[]), Share::new(2, &[])];
+    let shares = vec![Share::new(1, []), Share::new(2, [])];
     assert!(scheme.combine_shares::<S, S>(shares.as_slice()).is_err());
 
     // Zero identifier
@@ -51,6 +68,50 @@ pub fn combine_invalid<S: Field>() {
     assert!(scheme.combine_shares::<S, S>(shares.as_slice()).is_err());
 }
 
+/// Test splitting into many shares with consecutive and arbitrary identifiers
+pub fn split_many<S: Field>() {
+    let mut rng = thread_rng();
+    let secret = S::random(&mut rng);
+    for (threshold, limit) in [(2, 3), (3, 3), (5, 64), (32, 64)] {
+        let scheme = Scheme::new(threshold, limit).unwrap();
+        let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+        for window in shares.chunks(threshold).filter(|c| c.len() == threshold) {
+            let res = scheme.combine_shares::<S, S>(window);
+            assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+        }
+        let mut identifiers = (100..100 + limit as u32).collect::<Vec<u32>>();
+        identifiers[0] = 1;
+        let shares = scheme
+            .split_secret_with_identifiers(&mut rng, &secret, identifiers.as_slice())
+            .unwrap();
+        let res = scheme.combine_shares::<S, S>(&shares[limit - threshold..]);
+        assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+    }
+}
+
+/// Test dividing by and inverting zero fails without changing the element
+pub fn field_division<S: Field>() {
+    let mut rng = thread_rng();
+    let value = S::random(&mut rng);
+    let inverse = value.invert().unwrap();
+    let mut one = S::zero();
+    one.add_assign(&value);
+    one.scalar_mul_assign(&inverse);
+    assert_eq!(one.to_bytes(), S::one().to_bytes());
+
+    let mut quotient = S::zero();
+    quotient.add_assign(&value);
+    assert!(matches!(
+        quotient.scalar_div_assign(&S::zero()),
+        Err(SharingError::DivisionByZero)
+    ));
+    assert_eq!(quotient.to_bytes(), value.to_bytes());
+    assert!(matches!(
+        S::zero().invert(),
+        Err(SharingError::DivisionByZero)
+    ));
+}
+
 /// Test recombining for a single set of shares
 pub fn combine_single<S: Field, R: Group<S>>() {
     let scheme = Scheme::new(2, 3).unwrap();
@@ -96,7 +157,7 @@ pub fn combine_single<S: Field, R: Group<S>>() {
     assert!(res.is_ok());
     let pedersen_res = res.unwrap();
 
-    for i in 0..(&pedersen_res.secret_shares).len() {
+    for i in 0..pedersen_res.secret_shares.len() {
         assert!(scheme
             .verify_share(
                 &pedersen_res.secret_shares[i],
@@ -210,3 +271,1029 @@ pub fn combine_all_combinations<S: Field, R: Group<S>>() {
         }
     }
 }
+
+/// Test splitting and recombining with caller supplied identifiers
+pub fn combine_with_identifiers<S: Field, R: Group<S>>() {
+    let secret = S::from_bytes(b"hello").unwrap();
+    let scheme = Scheme::new(3, 5).unwrap();
+    let feldman_vss = FeldmanVss::new(3, 5).unwrap();
+    let pedersen_vss = PedersenVss::new(3, 5).unwrap();
+
+    let mut rng = thread_rng();
+
+    // Wrong number of identifiers
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, &secret, &[7, 11, 13])
+        .is_err());
+    // Zero identifier
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, &secret, &[0, 7, 11, 13, 17])
+        .is_err());
+    // Colliding identifiers
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, &secret, &[7, 11, 7, 13, 17])
+        .is_err());
+
+    let identifiers = scheme.random_identifiers(&mut rng);
+    assert_eq!(identifiers.len(), 5);
+
+    let res = scheme.split_secret_with_identifiers(&mut rng, &secret, identifiers.as_slice());
+    assert!(res.is_ok());
+    let shares = res.unwrap();
+    for (s, i) in shares.iter().zip(identifiers.iter()) {
+        assert_eq!(s.identifier(), *i);
+    }
+    let res = scheme.combine_shares::<S, S>(&shares[1..4]);
+    assert!(res.is_ok());
+    assert_eq!(secret.to_bytes(), res.unwrap().to_bytes());
+
+    // Stable participant ids, combined in any order
+    let ids = [u64::MAX, 0, 42, 1 << 40, 7];
+    let identifiers = scheme.participant_identifiers(&ids).unwrap();
+    assert_eq!(identifiers, scheme.participant_identifiers(&ids).unwrap());
+    assert_eq!(identifiers[2], participant_identifier(42));
+    assert!(identifiers.iter().all(|i| *i != 0));
+    assert!(scheme.participant_identifiers(&[1, 2, 3, 2, 5]).is_err());
+    assert!(scheme.participant_identifiers(&ids[..4]).is_err());
+    let shares = scheme
+        .split_secret_with_identifiers(&mut rng, &secret, identifiers.as_slice())
+        .unwrap();
+    let res =
+        scheme.combine_shares::<S, S>(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]);
+    assert_eq!(secret.to_bytes(), res.unwrap().to_bytes());
+
+    let identifiers = [1000u32, 42, 7, 123_456, 99];
+    let res: SharingResult<(FeldmanVerifier<S, R>, Vec<Share>)> =
+        feldman_vss.split_secret_with_identifiers(&mut rng, &secret, &identifiers, None);
+    assert!(res.is_ok());
+    let (verifier, shares) = res.unwrap();
+    for s in &shares {
+        assert!(feldman_vss.verify_share(s, &verifier).is_ok());
+    }
+    let res = feldman_vss.combine_shares::<S, S>(&shares[2..]);
+    assert!(res.is_ok());
+    assert_eq!(secret.to_bytes(), res.unwrap().to_bytes());
+
+    let res: SharingResult<PedersenVssResult<S, R>> =
+        pedersen_vss.split_secret_with_identifiers(&mut rng, &secret, &identifiers, None, None);
+    assert!(res.is_ok());
+    let pedersen_res = res.unwrap();
+    for (s, b) in pedersen_res
+        .secret_shares
+        .iter()
+        .zip(pedersen_res.blinding_shares.iter())
+    {
+        assert_eq!(s.identifier(), b.identifier());
+        assert!(pedersen_vss
+            .verify_share(s, b, &pedersen_res.verifier)
+            .is_ok());
+    }
+    let res = pedersen_vss.combine_shares::<S, S>(&pedersen_res.secret_shares[..3]);
+    assert!(res.is_ok());
+    assert_eq!(secret.to_bytes(), res.unwrap().to_bytes());
+}
+
+#[derive(Default)]
+struct EventCounter {
+    splits: AtomicUsize,
+    verify_failures: AtomicUsize,
+    combines: AtomicUsize,
+}
+
+struct CountingObserver(Arc<EventCounter>);
+
+impl Observer for CountingObserver {
+    fn notify(&self, event: &Event) {
+        let counter = match event {
+            Event::Split { .. } => &self.0.splits,
+            Event::VerifyFailed { .. } => &self.0.verify_failures,
+            Event::Combine { .. } => &self.0.combines,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Test the registered observer is notified of sharing operations
+pub fn observer_events<S: Field, R: Group<S>>() {
+    let counter = Arc::new(EventCounter::default());
+    set_observer(Box::new(CountingObserver(counter.clone())));
+
+    let secret = S::from_bytes(b"hello").unwrap();
+    let scheme = Scheme::new(2, 3).unwrap();
+    let feldman_vss = FeldmanVss::new(2, 3).unwrap();
+    let mut rng = thread_rng();
+
+    let shamir_shares = scheme.split_secret(&mut rng, &secret).unwrap();
+    let res: SharingResult<(FeldmanVerifier<S, R>, Vec<Share>)> =
+        feldman_vss.split_secret(&mut rng, &secret, None);
+    let (verifier, feldman_shares) = res.unwrap();
+    assert_eq!(counter.splits.load(Ordering::SeqCst), 2);
+
+    assert!(feldman_vss
+        .verify_share(&feldman_shares[0], &verifier)
+        .is_ok());
+    assert!(feldman_vss
+        .verify_share(&shamir_shares[0], &verifier)
+        .is_err());
+    assert_eq!(counter.verify_failures.load(Ordering::SeqCst), 1);
+
+    assert!(scheme.combine_shares::<S, S>(&shamir_shares[..2]).is_ok());
+    assert!(scheme.combine_shares::<S, S>(&shamir_shares[..1]).is_err());
+    assert_eq!(counter.combines.load(Ordering::SeqCst), 2);
+
+    clear_observer();
+    assert!(scheme.split_secret(&mut rng, &secret).is_ok());
+    assert_eq!(counter.splits.load(Ordering::SeqCst), 2);
+}
+
+/// Test combining shares lifted into the group `R` yields the secret in the exponent
+pub fn combine_in_exponent<S: Field, R: Group<S>>() {
+    let secret = S::from_bytes(b"hello").unwrap();
+    let scheme = Scheme::new(3, 5).unwrap();
+    let mut rng = thread_rng();
+
+    let g = R::random(&mut rng);
+    let mut expected = R::zero();
+    expected.add_assign(&g);
+    expected.scalar_mul_assign(&secret);
+
+    let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+    let lifted = shares
+        .iter()
+        .map(|s| s.lift::<S, R>(&g).unwrap())
+        .collect::<Vec<Share>>();
+    for (s, l) in shares.iter().zip(lifted.iter()) {
+        assert_eq!(s.identifier(), l.identifier());
+    }
+
+    let res = scheme.combine_shares::<S, R>(&lifted[..3]);
+    assert!(res.is_ok());
+    assert_eq!(expected.to_bytes(), res.unwrap().to_bytes());
+
+    let res = scheme.combine_shares::<S, R>(&lifted[2..]);
+    assert!(res.is_ok());
+    assert_eq!(expected.to_bytes(), res.unwrap().to_bytes());
+
+    // Not enough shares
+    let res = scheme.combine_shares::<S, R>(&lifted[..2]);
+    assert!(res.is_err());
+}
+
+/// Test combining many sets of shares with precomputed coefficients
+pub fn combine_with_context<S: Field, R: Group<S>>() {
+    let scheme = Scheme::new(3, 5).unwrap();
+    let mut rng = thread_rng();
+    let g = R::random(&mut rng);
+
+    assert!(CombineContext::<S>::new(3, &[1, 2]).is_err());
+    assert!(CombineContext::<S>::new(1, &[1]).is_err());
+    assert!(CombineContext::<S>::new(2, &[1, 0]).is_err());
+    assert!(CombineContext::<S>::new(2, &[3, 3]).is_err());
+
+    let context = CombineContext::<S>::new(3, &[5, 2, 4]).unwrap();
+    assert_eq!(context.identifiers(), &[5, 2, 4]);
+    assert!(context.coefficient(2).is_some());
+    assert!(context.coefficient(1).is_none());
+
+    let mut secrets = Vec::new();
+    let mut batch = Vec::new();
+    for _ in 0..4 {
+        let secret = S::random(&mut rng);
+        let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+        // The same result as combining with the scheme, with shares in any order
+        let res = context
+            .combine_shares::<S>(&[shares[3].clone(), shares[4].clone(), shares[1].clone()])
+            .unwrap();
+        assert_eq!(res.to_bytes(), secret.to_bytes());
+        let res = scheme.combine_shares::<S, S>(&shares[1..]).unwrap();
+        assert_eq!(res.to_bytes(), secret.to_bytes());
+
+        let mut expected = R::zero();
+        expected.add_assign(&g);
+        expected.scalar_mul_assign(&secret);
+        secrets.push(expected);
+        // Partial results in the exponent, in the order of the context
+        batch.push(
+            [4usize, 1, 3]
+                .iter()
+                .map(|i| {
+                    let mut partial = R::zero();
+                    partial.add_assign(&g);
+                    partial.scalar_mul_assign(&S::from_bytes(shares[*i].value()).unwrap());
+                    partial
+                })
+                .collect::<Vec<R>>(),
+        );
+    }
+    let combined = context.combine_many(&batch).unwrap();
+    for (c, e) in combined.iter().zip(secrets.iter()) {
+        assert_eq!(c.to_bytes(), e.to_bytes());
+    }
+
+    // Wrong number of values or shares of other participants
+    assert!(context.combine(&batch[0][..2]).is_err());
+    let secret = S::random(&mut rng);
+    let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+    assert!(context.combine_shares::<S>(&shares[2..]).is_err());
+    assert!(context.combine_shares::<S>(&shares[..3]).is_err());
+    assert!(context
+        .combine_shares::<S>(&[shares[1].clone(), shares[1].clone(), shares[3].clone()])
+        .is_err());
+}
+
+/// Test shareholders can verify their shares against the dealer commitments alone
+pub fn share_verification<S: Field, R: Group<S>>() {
+    let secret = S::from_bytes(b"hello").unwrap();
+    let feldman_vss = FeldmanVss::new(3, 5).unwrap();
+    let pedersen_vss = PedersenVss::new(3, 5).unwrap();
+    let mut rng = thread_rng();
+
+    let res: SharingResult<(FeldmanVerifier<S, R>, Vec<Share>)> =
+        feldman_vss.split_secret(&mut rng, &secret, None);
+    let (verifier, shares) = res.unwrap();
+    let bytes = verifier.to_bytes();
+    let received = FeldmanVerifier::<S, R>::try_from(bytes.as_slice()).unwrap();
+    for share in &shares {
+        assert!(share.verify(&received).is_ok());
+        assert!(received.verify_share(share).is_ok());
+    }
+    // Wrong identifier
+    let moved = Share::new(6, shares[0].value());
+    assert!(moved.verify(&received).is_err());
+    // Commitments for another polynomial
+    let res: SharingResult<(FeldmanVerifier<S, R>, Vec<Share>)> =
+        feldman_vss.split_secret(&mut rng, &secret, None);
+    let (other, _) = res.unwrap();
+    assert!(shares[0].verify(&other).is_err());
+
+    let res: SharingResult<PedersenVssResult<S, R>> =
+        pedersen_vss.split_secret(&mut rng, &secret, None, None);
+    let res = res.unwrap();
+    for (s, b) in res.secret_shares.iter().zip(res.blinding_shares.iter()) {
+        assert!(s.verify_blinded(b, &res.verifier).is_ok());
+        assert!(res.verifier.verify_share(s, b).is_ok());
+    }
+    assert!(res.secret_shares[0]
+        .verify_blinded(&res.blinding_shares[1], &res.verifier)
+        .is_err());
+}
+
+/// Test combining with cheater detection names the corrupted shares
+pub fn cheater_detection<S: Field, R: Group<S>>() {
+    let secret = S::random(&mut thread_rng());
+    let feldman_vss = FeldmanVss::new(3, 5).unwrap();
+    let pedersen_vss = PedersenVss::new(3, 5).unwrap();
+    let mut rng = thread_rng();
+
+    let res: SharingResult<(FeldmanVerifier<S, R>, Vec<Share>)> =
+        feldman_vss.split_secret(&mut rng, &secret, None);
+    let (verifier, mut shares) = res.unwrap();
+    let res = feldman_vss.combine_with_detection(&shares[..3], &verifier);
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+
+    // Shares 2 and 4 are replaced by other values
+    let other = S::random(&mut rng).to_bytes();
+    shares[1] = Share::new(2, other.as_slice());
+    shares[3] = Share::new(4, other.as_slice());
+    let res = feldman_vss.combine_with_detection(&shares, &verifier);
+    assert!(matches!(res, Err(DetectionError::Cheaters(ref ids)) if ids == &[2, 4]));
+    let honest = [shares[0].clone(), shares[2].clone(), shares[4].clone()];
+    let res = feldman_vss.combine_with_detection(&honest, &verifier);
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+    let res = feldman_vss.combine_with_detection(&honest[..2], &verifier);
+    assert!(matches!(
+        res,
+        Err(DetectionError::Sharing(SharingError::ShareMinThreshold))
+    ));
+
+    let res: SharingResult<PedersenVssResult<S, R>> =
+        pedersen_vss.split_secret(&mut rng, &secret, None, None);
+    let mut res = res.unwrap();
+    let combined = pedersen_vss.combine_with_detection(
+        &res.secret_shares,
+        &res.blinding_shares,
+        &res.verifier,
+    );
+    assert_eq!(combined.unwrap().to_bytes(), secret.to_bytes());
+    res.secret_shares[2] = Share::new(3, other.as_slice());
+    let combined = pedersen_vss.combine_with_detection(
+        &res.secret_shares,
+        &res.blinding_shares,
+        &res.verifier,
+    );
+    assert!(matches!(combined, Err(DetectionError::Cheaters(ref ids)) if ids == &[3]));
+    let combined = pedersen_vss.combine_with_detection(
+        &res.secret_shares,
+        &res.blinding_shares[1..],
+        &res.verifier,
+    );
+    assert!(matches!(combined, Err(DetectionError::Sharing(_))));
+}
+
+/// Test share possession proofs for feldman and pedersen
+pub fn possession_proofs<S: Field, R: Group<S>>() {
+    let secret = S::from_bytes(b"hello").unwrap();
+    let mut rng = thread_rng();
+    let context = b"ceremony 1";
+
+    // Feldman
+    let scheme = FeldmanVss::new(3, 5).unwrap();
+    let (verifier, shares) = scheme
+        .split_secret::<S, R>(&mut rng, &secret, None)
+        .unwrap();
+    for s in &shares {
+        let proof = scheme
+            .prove_possession(&mut rng, s, &verifier, context)
+            .unwrap();
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &verifier, context)
+            .is_ok());
+
+        let bytes = proof.to_bytes();
+        let proof = PossessionProof::<S, R>::try_from(bytes.as_slice()).unwrap();
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &verifier, context)
+            .is_ok());
+
+        // Replayed under a different context
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &verifier, b"ceremony 2")
+            .is_err());
+        // Claimed for another shareholder
+        let other = s.identifier % 5 + 1;
+        assert!(scheme
+            .verify_possession(other, &proof, &verifier, context)
+            .is_err());
+        assert!(scheme
+            .verify_possession(0, &proof, &verifier, context)
+            .is_err());
+    }
+
+    // A share that doesn't match the commitments can't be proven
+    let mut bad = shares[0].clone();
+    bad.identifier = shares[1].identifier;
+    assert!(scheme
+        .prove_possession(&mut rng, &bad, &verifier, context)
+        .is_err());
+
+    // Pedersen
+    let scheme = PedersenVss::new(3, 5).unwrap();
+    let res = scheme
+        .split_secret::<S, R>(&mut rng, &secret, None, None)
+        .unwrap();
+    for (s, t) in res.secret_shares.iter().zip(res.blinding_shares.iter()) {
+        let proof = scheme
+            .prove_possession(&mut rng, s, t, &res.verifier, context)
+            .unwrap();
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &res.verifier, context)
+            .is_ok());
+
+        let bytes = proof.to_bytes();
+        let proof = PossessionProof::<S, R>::try_from(bytes.as_slice()).unwrap();
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &res.verifier, context)
+            .is_ok());
+        assert!(scheme
+            .verify_possession(s.identifier, &proof, &res.verifier, b"ceremony 2")
+            .is_err());
+
+        // A pedersen proof isn't accepted as a feldman proof
+        let feldman = FeldmanVerifier {
+            g: R::from_bytes(res.verifier.g.to_bytes()).unwrap(),
+            commitments: res.verifier.commitments.clone(),
+        };
+        assert!(FeldmanVss::new(3, 5)
+            .unwrap()
+            .verify_possession(s.identifier, &proof, &feldman, context)
+            .is_err());
+    }
+
+    // Truncated proofs are rejected
+    let proof = scheme
+        .prove_possession(
+            &mut rng,
+            &res.secret_shares[0],
+            &res.blinding_shares[0],
+            &res.verifier,
+            context,
+        )
+        .unwrap();
+    let bytes = proof.to_bytes();
+    assert!(PossessionProof::<S, R>::try_from(&bytes[..bytes.len() - 1]).is_err());
+}
+
+/// Test publicly verifiable escrow with agents
+pub fn escrow_shares<S: Field, R: Group<S>>() {
+    let secret = S::from_bytes(b"hello").unwrap();
+    let mut rng = thread_rng();
+    let scheme = EscrowScheme::new(3, 5).unwrap();
+
+    let h = R::random(&mut rng);
+    let keys = (0..5).map(|_| S::random(&mut rng)).collect::<Vec<S>>();
+    let agents = keys
+        .iter()
+        .map(|x| {
+            let mut y = R::zero();
+            y.add_assign(&h);
+            y.scalar_mul_assign(x);
+            y
+        })
+        .collect::<Vec<R>>();
+    let mut expected = R::zero();
+    expected.add_assign(&h);
+    expected.scalar_mul_assign(&secret);
+
+    assert!(scheme
+        .escrow(&mut rng, &secret, None, &h, &agents[..4])
+        .is_err());
+    let transcript = scheme.escrow(&mut rng, &secret, None, &h, &agents).unwrap();
+    assert!(scheme.verify_transcript(&transcript).is_ok());
+    assert!(EscrowScheme::new(2, 5)
+        .unwrap()
+        .verify_transcript(&transcript)
+        .is_err());
+
+    // Anyone can audit the serialized transcript
+    let bytes = transcript.to_bytes();
+    let transcript = EscrowTranscript::<S, R>::try_from(bytes.as_slice()).unwrap();
+    assert!(scheme.verify_transcript(&transcript).is_ok());
+    assert!(EscrowTranscript::<S, R>::try_from(&bytes[..bytes.len() - 1]).is_err());
+
+    // Tampering with an encrypted share is caught
+    let r_size = R::Size::to_usize();
+    let offset = 2 * r_size + 8 + (3 + 5) * r_size;
+    let mut tampered = bytes.clone();
+    let share_size = tampered.len() - offset;
+    let (first, second) = tampered[offset..].split_at_mut(share_size / 5);
+    first[..r_size].swap_with_slice(&mut second[..r_size]);
+    let tampered = EscrowTranscript::<S, R>::try_from(tampered.as_slice()).unwrap();
+    assert!(scheme.verify_transcript(&tampered).is_err());
+
+    // Any threshold of agents recover h^secret
+    let decrypted = keys
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            scheme
+                .decrypt_share(&mut rng, &transcript, i as u32 + 1, x)
+                .unwrap()
+        })
+        .collect::<Vec<DecryptedShare<S, R>>>();
+    for d in &decrypted {
+        assert!(scheme.verify_decrypted_share(&transcript, d).is_ok());
+    }
+    let res = scheme.recover(&transcript, &decrypted[..3]);
+    assert_eq!(res.unwrap().to_bytes(), expected.to_bytes());
+    let res = scheme.recover(&transcript, &decrypted[2..]);
+    assert_eq!(res.unwrap().to_bytes(), expected.to_bytes());
+    assert!(scheme.recover(&transcript, &decrypted[..2]).is_err());
+
+    let bytes = decrypted[0].to_bytes();
+    let d = DecryptedShare::<S, R>::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(d.identifier(), 1);
+    assert!(scheme.verify_decrypted_share(&transcript, &d).is_ok());
+
+    // A share claimed by the wrong agent is rejected
+    let mut bytes = decrypted[1].to_bytes();
+    bytes[..4].copy_from_slice(&3u32.to_be_bytes());
+    let d = DecryptedShare::<S, R>::try_from(bytes.as_slice()).unwrap();
+    assert!(scheme.verify_decrypted_share(&transcript, &d).is_err());
+    assert!(scheme
+        .recover(&transcript, &[d, decrypted.into_iter().next().unwrap()])
+        .is_err());
+
+    // Only the agent's key decrypts
+    assert!(scheme
+        .decrypt_share(&mut rng, &transcript, 1, &keys[1])
+        .is_err());
+    assert!(scheme
+        .decrypt_share(&mut rng, &transcript, 6, &keys[0])
+        .is_err());
+}
+
+/// Test discrete log equality proofs within a group and across groups
+pub fn dleq_proofs<S: Field, R: Group<S>>() {
+    let mut rng = thread_rng();
+    let x = S::random(&mut rng);
+    let g1 = R::random(&mut rng);
+    let g2 = R::random(&mut rng);
+    let mul = |g: &R, s: &S| {
+        let mut r = R::zero();
+        r.add_assign(g);
+        r.scalar_mul_assign(s);
+        r
+    };
+    let (h1, h2) = (mul(&g1, &x), mul(&g2, &x));
+    let transcript = |session: &[u8]| {
+        let mut t = Transcript::new(b"dleq test");
+        t.append_message(b"session", session);
+        t
+    };
+
+    let proof =
+        DleqProof::prove(&mut rng, &mut transcript(b"1"), &x, (&g1, &h1), (&g2, &h2)).unwrap();
+    assert!(proof
+        .verify(&mut transcript(b"1"), (&g1, &h1), (&g2, &h2))
+        .is_ok());
+    // Bound to the transcript and the statement
+    assert!(proof
+        .verify(&mut transcript(b"2"), (&g1, &h1), (&g2, &h2))
+        .is_err());
+    assert!(proof
+        .verify(&mut transcript(b"1"), (&g2, &h2), (&g1, &h1))
+        .is_err());
+    assert!(proof
+        .verify(&mut transcript(b"1"), (&g1, &h1), (&g2, &h1))
+        .is_err());
+
+    // A different exponent doesn't verify
+    let y = S::random(&mut rng);
+    let h3 = mul(&g2, &y);
+    let proof =
+        DleqProof::prove(&mut rng, &mut transcript(b"1"), &x, (&g1, &h1), (&g2, &h3)).unwrap();
+    assert!(proof
+        .verify(&mut transcript(b"1"), (&g1, &h1), (&g2, &h3))
+        .is_err());
+
+    // The second base in another group with the same scalars
+    let g3 = S::random(&mut rng);
+    let mut h4 = S::zero();
+    h4.add_assign(&g3);
+    h4.scalar_mul_assign(&x);
+    let proof =
+        DleqProof::prove(&mut rng, &mut transcript(b"1"), &x, (&g1, &h1), (&g3, &h4)).unwrap();
+    let proof = DleqProof::<S>::from_bytes_with(&proof.to_bytes(), Strictness::Strict).unwrap();
+    assert!(proof
+        .verify(&mut transcript(b"1"), (&g1, &h1), (&g3, &h4))
+        .is_ok());
+    assert_eq!(proof.to_bytes().len(), DleqProof::<S>::size());
+    assert!(DleqProof::<S>::try_from(&proof.to_bytes()[1..]).is_err());
+}
+
+/// Test transcript challenges are deterministic and domain separated
+pub fn transcript_challenges<S: Field, R: Group<S>>() {
+    let mut rng = thread_rng();
+    let point = R::random(&mut rng);
+    let scalar = S::random(&mut rng);
+    let challenge = |protocol: &'static [u8], label: &'static [u8], message: &[u8]| {
+        let mut t = Transcript::new(protocol);
+        t.append_point::<S, R>(b"point", &point);
+        t.append_scalar(b"scalar", &scalar);
+        t.append_message(label, message);
+        t.challenge_scalar::<S>(b"challenge")
+            .unwrap()
+            .to_bytes()
+            .to_vec()
+    };
+
+    let c = challenge(b"protocol 1", b"message", b"hello");
+    assert_eq!(c, challenge(b"protocol 1", b"message", b"hello"));
+    assert_ne!(c, challenge(b"protocol 2", b"message", b"hello"));
+    assert_ne!(c, challenge(b"protocol 1", b"other", b"hello"));
+    assert_ne!(c, challenge(b"protocol 1", b"message", b"hellp"));
+
+    // Moving bytes between a label and its message changes the challenge
+    let mut t1 = Transcript::new(b"protocol 1");
+    t1.append_message(b"ab", b"c");
+    let mut t2 = Transcript::new(b"protocol 1");
+    t2.append_message(b"a", b"bc");
+    let mut c1 = [0u8; 48];
+    let mut c2 = [0u8; 48];
+    t1.challenge_bytes(b"challenge", &mut c1);
+    t2.challenge_bytes(b"challenge", &mut c2);
+    assert_ne!(c1.to_vec(), c2.to_vec());
+
+    // Later challenges depend on earlier ones
+    let mut c3 = [0u8; 48];
+    t1.challenge_bytes(b"challenge", &mut c3);
+    assert_ne!(c1.to_vec(), c3.to_vec());
+}
+
+/// Test strict and lenient deserialization
+pub fn strict_deserialization<S: Field, R: Group<S>>() {
+    let mut rng = thread_rng();
+
+    // Canonical encodings are accepted by both
+    let point = R::random(&mut rng);
+    let scalar = S::random(&mut rng);
+    for strictness in [Strictness::Strict, Strictness::Lenient].iter() {
+        let p = R::from_bytes_with(point.to_bytes(), *strictness).unwrap();
+        assert_eq!(p.to_bytes(), point.to_bytes());
+        let s = S::from_bytes_with(scalar.to_bytes(), *strictness).unwrap();
+        assert_eq!(s.to_bytes(), scalar.to_bytes());
+    }
+
+    // Short encodings are only accepted when lenient
+    assert!(S::from_bytes_with(b"hello", Strictness::Lenient).is_ok());
+    assert!(S::from_bytes_with(b"hello", Strictness::Strict).is_err());
+
+    // The identity is only accepted when lenient
+    assert!(S::from_bytes_with(S::zero().to_bytes(), Strictness::Strict).is_err());
+    let zero = R::zero().to_bytes();
+    // Some backends have no encoding of the identity
+    let has_identity = R::from_bytes(&zero).map(|z| z.is_zero()).unwrap_or(false);
+    if has_identity {
+        assert!(R::from_bytes_with(&zero, Strictness::Lenient).is_ok());
+        assert!(R::from_bytes_with(&zero, Strictness::Strict).is_err());
+    }
+
+    // Verifiers and proofs made by the schemes parse strictly
+    let secret = S::from_bytes(b"hello").unwrap();
+    let scheme = FeldmanVss::new(2, 3).unwrap();
+    let (verifier, shares) = scheme
+        .split_secret::<S, R>(&mut rng, &secret, None)
+        .unwrap();
+    let bytes = verifier.to_bytes();
+    assert!(FeldmanVerifier::<S, R>::from_bytes_with(&bytes, Strictness::Strict).is_ok());
+    let proof = scheme
+        .prove_possession(&mut rng, &shares[0], &verifier, b"context")
+        .unwrap();
+    let proof =
+        PossessionProof::<S, R>::from_bytes_with(&proof.to_bytes(), Strictness::Strict).unwrap();
+    assert!(scheme
+        .verify_possession(shares[0].identifier, &proof, &verifier, b"context")
+        .is_ok());
+
+    // But not with the identity as the generator
+    if has_identity {
+        let mut bad = zero.to_vec();
+        bad.extend_from_slice(&bytes[zero.len()..]);
+        assert!(FeldmanVerifier::<S, R>::from_bytes_with(&bad, Strictness::Lenient).is_ok());
+        assert!(FeldmanVerifier::<S, R>::from_bytes_with(&bad, Strictness::Strict).is_err());
+    }
+}
+
+/// Test distributed key generation with honest and misbehaving participants
+pub fn dkg_rounds<S: Field, R: Group<S>>() {
+    const THRESHOLD: usize = 3;
+    const LIMIT: usize = 5;
+    let mut rng = thread_rng();
+    let g = R::random(&mut rng);
+
+    let mut run = |tamper: &dyn Fn(&mut Vec<Round1Share>)| {
+        let mut participants = Vec::new();
+        let mut broadcasts: Vec<Round1Broadcast<S, R>> = Vec::new();
+        let mut shares = Vec::new();
+        for id in 1..=LIMIT as u32 {
+            let (p, b, s) = Participant::new(&mut rng, id, THRESHOLD, LIMIT, &g).unwrap();
+            participants.push(p);
+            broadcasts.push(b);
+            shares.extend(s);
+        }
+        tamper(&mut shares);
+        let mut complaints = Vec::new();
+        for p in participants.iter_mut() {
+            let mine = shares
+                .iter()
+                .filter(|s| s.receiver() == p.id())
+                .cloned()
+                .collect::<Vec<Round1Share>>();
+            complaints.extend(p.receive_round1(&broadcasts, &mine).unwrap());
+        }
+        (participants, complaints)
+    };
+
+    // Honest session
+    let (mut participants, complaints) = run(&|_| {});
+    assert!(complaints.is_empty());
+    let outputs = participants
+        .iter_mut()
+        .map(|p| p.finalize(&[], &[]).unwrap())
+        .collect::<Vec<_>>();
+    let public_key = outputs[0].public_key().to_bytes();
+    for o in &outputs {
+        assert_eq!(o.public_key().to_bytes(), public_key);
+        assert!(o.share().verify(o.verifier()).is_ok());
+        let lifted = o.share().lift::<S, R>(&g).unwrap();
+        let mut expected = R::from_bytes(lifted.value()).unwrap();
+        expected.sub_assign(&o.verification_share());
+        assert!(expected.is_zero());
+    }
+    // Any threshold of shares gives the secret key of the public key
+    let scheme = Scheme::new(THRESHOLD, LIMIT).unwrap();
+    let shares = outputs
+        .iter()
+        .map(|o| o.share().clone())
+        .collect::<Vec<Share>>();
+    for start in 0..=LIMIT - THRESHOLD {
+        let secret = scheme
+            .combine_shares::<S, S>(&shares[start..start + THRESHOLD])
+            .unwrap();
+        let mut pk = R::zero();
+        pk.add_assign(&g);
+        pk.scalar_mul_assign(&secret);
+        assert_eq!(pk.to_bytes(), public_key);
+    }
+    // Anyone can replay the session from its transcript
+    let bytes = participants[0].transcript().to_bytes();
+    let replayed = crate::dkg::Transcript::<S, R>::try_from(bytes.as_slice()).unwrap();
+    assert!(replayed.blame().is_empty());
+    assert_eq!(replayed.public_key().unwrap().to_bytes(), public_key);
+    assert_eq!(
+        replayed.verification_share(2).unwrap().to_bytes(),
+        outputs[1].verification_share().to_bytes()
+    );
+
+    // A corrupted share is complained about and resolved by its dealer
+    let corrupt = |shares: &mut Vec<Round1Share>| {
+        let i = shares
+            .iter()
+            .position(|s| s.sender() == 2 && s.receiver() == 4)
+            .unwrap();
+        let mut bytes = shares[i].to_bytes();
+        bytes.truncate(8);
+        bytes.extend_from_slice(&S::random(&mut thread_rng()).to_bytes());
+        shares[i] = Round1Share::try_from(bytes.as_slice()).unwrap();
+    };
+    let (mut participants, complaints) = run(&corrupt);
+    assert_eq!(
+        complaints,
+        vec![Complaint {
+            accuser: 4,
+            accused: 2
+        }]
+    );
+    let justifications = participants
+        .iter()
+        .flat_map(|p| p.justify(&complaints))
+        .collect::<Vec<_>>();
+    assert_eq!(justifications.len(), 1);
+    let shares = participants
+        .iter_mut()
+        .map(|p| {
+            p.finalize(&complaints, &justifications)
+                .unwrap()
+                .share()
+                .clone()
+        })
+        .collect::<Vec<Share>>();
+    let first = scheme.combine_shares::<S, S>(&shares[..THRESHOLD]).unwrap();
+    let last = scheme
+        .combine_shares::<S, S>(&shares[LIMIT - THRESHOLD..])
+        .unwrap();
+    assert_eq!(first.to_bytes(), last.to_bytes());
+
+    // A dealer that doesn't answer the complaint is blamed by everyone
+    let (mut participants, complaints) = run(&corrupt);
+    for p in participants.iter_mut() {
+        assert!(p.finalize(&complaints, &[]).is_err());
+        assert_eq!(
+            p.transcript().blame(),
+            vec![Blame {
+                participant: 2,
+                misbehavior: Misbehavior::MissingJustification
+            }]
+        );
+    }
+
+    // Participants outside the session are rejected
+    assert!(Participant::<S, R>::new(&mut rng, 0, THRESHOLD, LIMIT, &g).is_err());
+    assert!(Participant::<S, R>::new(&mut rng, 6, THRESHOLD, LIMIT, &g).is_err());
+}
+
+/// Test refreshing and resharing keeps the secret
+pub fn refresh_shares<S: Field>() {
+    let mut rng = thread_rng();
+    let secret = S::random(&mut rng);
+    let old = Scheme::new(3, 5).unwrap();
+    let shares = old.split_secret(&mut rng, &secret).unwrap();
+
+    // Refresh, the new shares combine to the secret but not with the old ones
+    let refresh = Resharing::refresh(old);
+    let refreshed = refresh.reshare::<S>(&mut rng, &shares[1..4]).unwrap();
+    assert_eq!(refreshed.len(), 5);
+    for (old_share, new_share) in shares.iter().zip(refreshed.iter()) {
+        assert_eq!(old_share.identifier(), new_share.identifier());
+        assert_ne!(old_share.value(), new_share.value());
+    }
+    let res = old.combine_shares::<S, S>(&refreshed[2..]).unwrap();
+    assert_eq!(res.to_bytes(), secret.to_bytes());
+    let mixed = vec![
+        shares[0].clone(),
+        refreshed[1].clone(),
+        refreshed[2].clone(),
+    ];
+    let res = old.combine_shares::<S, S>(mixed.as_slice()).unwrap();
+    assert_ne!(res.to_bytes(), secret.to_bytes());
+
+    // Reshare to a larger threshold and new shareholders, one step at a time
+    let new = Scheme::new(4, 7).unwrap();
+    let identifiers = (10..17).collect::<Vec<u32>>();
+    let resharing = Resharing::with_identifiers(old, new, identifiers.as_slice()).unwrap();
+    let dealt = shares[..3]
+        .iter()
+        .map(|s| {
+            let reshares = resharing.deal::<S>(&mut rng, s).unwrap();
+            // Reshares are sent over the wire
+            reshares
+                .iter()
+                .map(|r| Reshare::try_from(r.to_bytes().as_slice()).unwrap())
+                .collect::<Vec<Reshare>>()
+        })
+        .collect::<Vec<_>>();
+    let reshared = identifiers
+        .iter()
+        .map(|id| {
+            let mine = dealt
+                .iter()
+                .flatten()
+                .filter(|r| r.receiver() == *id)
+                .cloned()
+                .collect::<Vec<Reshare>>();
+            assert_eq!(mine.len(), 3);
+            resharing.combine::<S>(*id, mine.as_slice()).unwrap()
+        })
+        .collect::<Vec<Share>>();
+    for window in reshared.windows(4) {
+        let res = new.combine_shares::<S, S>(window).unwrap();
+        assert_eq!(res.to_bytes(), secret.to_bytes());
+    }
+    // The new threshold applies
+    assert!(new.combine_shares::<S, S>(&reshared[..3]).is_err());
+
+    // Reshare down to 2 of 3
+    let smaller = Scheme::new(2, 3).unwrap();
+    let reshared = Resharing::new(old, smaller)
+        .reshare::<S>(&mut rng, &shares[2..])
+        .unwrap();
+    let res = smaller.combine_shares::<S, S>(&reshared[1..]).unwrap();
+    assert_eq!(res.to_bytes(), secret.to_bytes());
+
+    // Too few dealers or reshares for another shareholder are rejected
+    assert!(refresh.reshare::<S>(&mut rng, &shares[..2]).is_err());
+    let reshares = dealt.iter().map(|d| d[0].clone()).collect::<Vec<Reshare>>();
+    assert!(resharing.combine::<S>(11, reshares.as_slice()).is_err());
+    assert!(resharing.combine::<S>(1, reshares.as_slice()).is_err());
+    assert!(resharing.combine::<S>(10, &reshares[..2]).is_err());
+}
+
+/// Test invalid byte split arguments
+pub fn byte_split_invalid_args() {
+    assert!(ByteScheme::new(0, 0).is_err());
+    assert!(ByteScheme::new(3, 2).is_err());
+    assert!(ByteScheme::new(1, 10).is_err());
+    assert!(ByteScheme::new(2, 256).is_err());
+    assert!(ByteScheme::new(255, 255).is_ok());
+    let scheme = ByteScheme::new(2, 3).unwrap();
+    let mut rng = thread_rng();
+    assert!(scheme.split_secret(&mut rng, &[]).is_err());
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, b"hello", &[1, 2])
+        .is_err());
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, b"hello", &[0, 1, 2])
+        .is_err());
+    assert!(scheme
+        .split_secret_with_identifiers(&mut rng, b"hello", &[1, 2, 1])
+        .is_err());
+}
+
+/// Test that combining byte shares should fail
+pub fn byte_combine_invalid() {
+    let scheme = ByteScheme::new(2, 3).unwrap();
+    // No shares
+    assert!(scheme.combine_shares(&[]).is_err());
+
+    // No secret
+    let shares = vec![ByteShare::new(1, []), ByteShare::new(2, [])];
+    assert!(scheme.combine_shares(shares.as_slice()).is_err());
+
+    // Zero identifier
+    let shares = vec![ByteShare::new(0, b"abc"), ByteShare::new(2, b"abc")];
+    assert!(scheme.combine_shares(shares.as_slice()).is_err());
+
+    // Duplicate shares
+    let shares = vec![ByteShare::new(1, b"abc"), ByteShare::new(1, b"abc")];
+    assert!(scheme.combine_shares(shares.as_slice()).is_err());
+
+    // Different lengths
+    let shares = vec![ByteShare::new(1, b"abc"), ByteShare::new(2, b"abcd")];
+    assert!(scheme.combine_shares(shares.as_slice()).is_err());
+
+    // Too few shares
+    let mut rng = thread_rng();
+    let shares = scheme.split_secret(&mut rng, b"hello").unwrap();
+    assert!(scheme.combine_shares(&shares[..1]).is_err());
+
+    // Truncated encoding
+    assert!(ByteShare::try_from(&[1u8][..]).is_err());
+    assert!(ByteShare::try_from(&[][..]).is_err());
+}
+
+/// Test splitting byte secrets of many lengths into many shares
+pub fn byte_split_many() {
+    let mut rng = thread_rng();
+    for len in [1, 31, 32, 33, 4096] {
+        let mut secret = vec![0u8; len];
+        rng.fill_bytes(&mut secret);
+        for (threshold, limit) in [(2, 3), (3, 3), (5, 64), (32, 64), (2, 255)] {
+            let scheme = ByteScheme::new(threshold, limit).unwrap();
+            let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+            assert_eq!(shares.len(), limit);
+            for share in &shares {
+                let bytes = share.to_bytes();
+                assert_eq!(bytes.len(), len + 1);
+                assert_eq!(bytes[len], share.identifier());
+            }
+            for window in shares.chunks(threshold).filter(|c| c.len() == threshold) {
+                let res = scheme.combine_shares(window);
+                assert_eq!(res.unwrap(), secret);
+            }
+            // Interpolating fewer shares than the threshold gives something else
+            if len > 8 && threshold > 2 {
+                let short = ByteScheme::new(threshold - 1, limit).unwrap();
+                let res = short.combine_shares(&shares[..threshold - 1]).unwrap();
+                assert_ne!(res, secret);
+            }
+            let mut identifiers = (1..=limit as u8).rev().collect::<Vec<u8>>();
+            identifiers.rotate_left(limit / 2);
+            let shares = scheme
+                .split_secret_with_identifiers(&mut rng, &secret, identifiers.as_slice())
+                .unwrap();
+            let parsed = shares[limit - threshold..]
+                .iter()
+                .map(|s| ByteShare::try_from(s.to_bytes().as_slice()).unwrap())
+                .collect::<Vec<ByteShare>>();
+            assert_eq!(scheme.combine_shares(parsed.as_slice()).unwrap(), secret);
+        }
+    }
+}
+
+/// Test recombining byte shares with all possible subsets of shares
+pub fn byte_combine_all_combinations() {
+    let secret = b"a secret longer than any scalar, split byte by byte";
+    let scheme = ByteScheme::new(3, 5).unwrap();
+    let mut rng = thread_rng();
+    let shares = scheme.split_secret(&mut rng, secret).unwrap();
+
+    for i in 0..5 {
+        for j in 0..5 {
+            if i == j {
+                continue;
+            }
+            for k in 0..5 {
+                if k == i || k == j {
+                    continue;
+                }
+                let parts = &[shares[i].clone(), shares[j].clone(), shares[k].clone()];
+                let res = scheme.combine_shares(parts);
+                assert_eq!(res.unwrap().as_slice(), &secret[..]);
+            }
+        }
+    }
+}
+
+/// Test that malformed armor and mismatched passphrases are rejected when restoring a backup
+#[cfg(feature = "backup")]
+pub fn backup_invalid_restore() {
+    use super::{
+        backup::{backup_secret, restore_secret, EncodedShare},
+        error::SharingError,
+    };
+    use sha2::{Digest, Sha256};
+    use std::convert::TryFrom;
+
+    // The markers overlap when the body is missing
+    for armor in [
+        "-----BEGIN URSA SECRET SHARE-----END URSA SECRET SHARE-----",
+        "-----BEGIN URSA SECRET SHARE-----",
+        "-----END URSA SECRET SHARE-----",
+        "",
+    ] {
+        match EncodedShare::from_armored(armor) {
+            Err(SharingError::ShareInvalidEncoding) => {}
+            r => panic!("expected an encoding error, found {:?}", r),
+        }
+    }
+
+    let plain = backup_secret(b"secret", 2, 3, None).unwrap();
+    match restore_secret(&plain[..2], Some(b"hunter2")) {
+        Err(SharingError::SharePassphraseUnexpected) => {}
+        r => panic!("expected a passphrase error, found {:?}", r),
+    }
+
+    // Shares of one backup are either all encrypted or none are. Give an encrypted
+    // share the id of the plain backup, with a valid checksum, to mix them
+    let encrypted = backup_secret(b"secret", 2, 3, Some(b"hunter2")).unwrap();
+    let mut bytes = encrypted[0].to_bytes();
+    bytes[2..10].copy_from_slice(&plain[0].backup_id());
+    let checksum_at = bytes.len() - 4;
+    let checksum = Sha256::digest(&bytes[..checksum_at]);
+    bytes[checksum_at..].copy_from_slice(&checksum[..4]);
+    let mixed = [
+        EncodedShare::try_from(bytes.as_slice()).unwrap(),
+        plain[1].clone(),
+    ];
+    for passphrase in [None, Some(&b"hunter2"[..])] {
+        match restore_secret(&mixed, passphrase) {
+            Err(SharingError::ShareMismatchedBackup) => {}
+            r => panic!("expected a mismatched backup error, found {:?}", r),
+        }
+    }
+    assert_eq!(
+        restore_secret(&plain[..2], None).unwrap(),
+        b"secret".to_vec()
+    );
+}
@@ -0,0 +1,383 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Threshold BLS signatures over BLS12-381.
+//!
+//! The signing key is shared with Shamir or Feldman over [`Bls12381Scalar`], or generated
+//! without a dealer by the DKG. Each node signs with its share to produce a
+//! [`PartialSignature`] `H(m)^{s_i}` and anyone holding `threshold` partial signatures
+//! interpolates them in the exponent to get `H(m)^{sk}`, a standard BLS signature under
+//! the public key `g1^{sk}`. The secret key is never reconstructed.
+//!
+//! A partial signature is checked against the signer's [`PublicKeyShare`] `g1^{s_i}`
+//! which can be computed from the Feldman commitments, so a bad partial signature is
+//! attributed to its signer before it spoils the combined signature.
+//!
+//! Public keys are in G1 and signatures in G2 like `bls::normal` of the main Ursa crate.
+//! Keys convert to and from its encodings with `from_ursa_bytes` and `to_ursa_bytes`,
+//! so an existing Ursa BLS key can be split and the combined public key matches.
+//! Messages are hashed to G2 with the hash to curve suite in [`DST`].
+//! Ursa's BLS hashes messages with a different map, to make signatures its
+//! `verify` accepts hash the message with it and sign the point with [`PartialSignature::new_hashed`].
+
+use super::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    error::{SharingError, SharingResult},
+    evaluate_commitments,
+    feldman::FeldmanVerifier,
+    hash_to_curve::HashToCurve,
+    shamir::{Scheme as ShamirScheme, Share},
+    Group,
+};
+use ff_zeroize::Field as _;
+use pairing_plus::{
+    bls12_381::{Bls12, Fq12, G1, G2},
+    serdes::SerDes,
+    CurveProjective, Engine,
+};
+use std::convert::TryFrom;
+
+/// The domain separation tag of the hash to curve suite used for messages
+pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// The length of an uncompressed coordinate
+const FP_BYTES: usize = 48;
+/// The tag of uncompressed points in Ursa's encoding
+const URSA_UNCOMPRESSED: u8 = 0x04;
+
+/// The public key that verifies the combined signatures
+#[derive(Copy, Clone, Debug)]
+pub struct PublicKey(pub Bls12381G1);
+
+impl PublicKey {
+    /// The public key of `secret_key`
+    pub fn from_secret_key(secret_key: &Bls12381Scalar) -> Self {
+        Self(mul(&Bls12381G1::generator(), secret_key))
+    }
+
+    /// The public key of a key shared with Feldman, the first commitment.
+    /// The commitments must use the standard generator of G1
+    pub fn from_verifier(
+        verifier: &FeldmanVerifier<Bls12381Scalar, Bls12381G1>,
+    ) -> SharingResult<Self> {
+        check_generator(verifier)?;
+        Ok(Self(copy(&verifier.commitments[0].value)))
+    }
+
+    /// The compressed encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    /// The uncompressed encoding used by Ursa's BLS public keys
+    pub fn to_ursa_bytes(&self) -> Vec<u8> {
+        g1_to_ursa_bytes(&self.0)
+    }
+
+    /// Parse Ursa's BLS public key encoding
+    pub fn from_ursa_bytes(value: &[u8]) -> SharingResult<Self> {
+        g1_from_ursa_bytes(value).map(Self)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        let key = Bls12381G1::from_bytes(value)?;
+        if key.is_zero() {
+            return Err(SharingError::InvalidPoint);
+        }
+        Ok(Self(key))
+    }
+}
+
+/// The public key of one signer's share, `g1^{s_i}`
+#[derive(Copy, Clone, Debug)]
+pub struct PublicKeyShare {
+    identifier: u32,
+    key: Bls12381G1,
+}
+
+impl PublicKeyShare {
+    /// The public key of a share, computed by its holder
+    pub fn from_share(share: &Share) -> SharingResult<Self> {
+        let value = Bls12381Scalar::from_bytes(share.value())?;
+        Ok(Self {
+            identifier: share.identifier(),
+            key: mul(&Bls12381G1::generator(), &value),
+        })
+    }
+
+    /// The public key of share `identifier` from the Feldman commitments, computed
+    /// by anyone. The commitments must use the standard generator of G1
+    pub fn from_verifier(
+        verifier: &FeldmanVerifier<Bls12381Scalar, Bls12381G1>,
+        identifier: u32,
+    ) -> SharingResult<Self> {
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        check_generator(verifier)?;
+        Ok(Self {
+            identifier,
+            key: evaluate_commitments(&verifier.commitments, identifier),
+        })
+    }
+
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// Get the public key
+    pub fn key(&self) -> &Bls12381G1 {
+        &self.key
+    }
+}
+
+/// One signer's signature `H(m)^{s_i}` with the share identifier
+#[derive(Copy, Clone, Debug)]
+pub struct PartialSignature {
+    identifier: u32,
+    point: Bls12381G2,
+}
+
+impl PartialSignature {
+    /// Sign `message` with a share of the signing key
+    pub fn new(share: &Share, message: &[u8]) -> SharingResult<Self> {
+        Self::new_hashed(share, &hash_message(message))
+    }
+
+    /// Sign a message already hashed to G2
+    pub fn new_hashed(share: &Share, hashed: &Bls12381G2) -> SharingResult<Self> {
+        if share.identifier() == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let value = Bls12381Scalar::from_bytes(share.value())?;
+        if !value.is_valid() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        Ok(Self {
+            identifier: share.identifier(),
+            point: mul(hashed, &value),
+        })
+    }
+
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// Check this partial signature on `message` against its signer's public key share
+    pub fn verify(&self, key: &PublicKeyShare, message: &[u8]) -> SharingResult<()> {
+        self.verify_hashed(key, &hash_message(message))
+    }
+
+    /// Check this partial signature on a message already hashed to G2
+    pub fn verify_hashed(&self, key: &PublicKeyShare, hashed: &Bls12381G2) -> SharingResult<()> {
+        if key.identifier != self.identifier || !pairing_check(&key.key, hashed, &self.point) {
+            return Err(SharingError::BlsPartialSignatureInvalid(self.identifier));
+        }
+        Ok(())
+    }
+
+    /// The identifier followed by the compressed point
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.identifier.to_be_bytes().to_vec();
+        o.extend_from_slice(self.point.to_bytes().as_ref());
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for PartialSignature {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != 4 + 96 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&value[..4]);
+        let identifier = u32::from_be_bytes(identifier);
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(Self {
+            identifier,
+            point: Bls12381G2::from_bytes(&value[4..])?,
+        })
+    }
+}
+
+/// A standard BLS signature
+#[derive(Copy, Clone, Debug)]
+pub struct Signature(pub Bls12381G2);
+
+impl Signature {
+    /// Interpolate at least `threshold` partial signatures with distinct identifiers.
+    /// Partial signatures aren't checked here, verify them first to find bad signers
+    pub fn combine(threshold: usize, partials: &[PartialSignature]) -> SharingResult<Self> {
+        let shares = partials
+            .iter()
+            .map(|p| Share::new(p.identifier as usize, p.point.to_bytes()))
+            .collect::<Vec<Share>>();
+        let scheme = ShamirScheme::new(threshold, threshold.max(shares.len()))?;
+        scheme
+            .combine_shares::<Bls12381Scalar, Bls12381G2>(shares.as_slice())
+            .map(Self)
+    }
+
+    /// Check this signature on `message`
+    pub fn verify(&self, key: &PublicKey, message: &[u8]) -> SharingResult<()> {
+        self.verify_hashed(key, &hash_message(message))
+    }
+
+    /// Check this signature on a message already hashed to G2
+    pub fn verify_hashed(&self, key: &PublicKey, hashed: &Bls12381G2) -> SharingResult<()> {
+        if !pairing_check(&key.0, hashed, &self.0) {
+            return Err(SharingError::BlsSignatureInvalid);
+        }
+        Ok(())
+    }
+
+    /// The compressed encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    /// The uncompressed encoding used by Ursa's BLS signatures
+    pub fn to_ursa_bytes(&self) -> Vec<u8> {
+        g2_to_ursa_bytes(&self.0)
+    }
+
+    /// Parse Ursa's BLS signature encoding
+    pub fn from_ursa_bytes(value: &[u8]) -> SharingResult<Self> {
+        g2_from_ursa_bytes(value).map(Self)
+    }
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Bls12381G2::from_bytes(value).map(Self)
+    }
+}
+
+/// Hash a message to G2 with the suite in [`DST`]
+pub fn hash_message(message: &[u8]) -> Bls12381G2 {
+    Bls12381G2::hash_to_curve(message, DST)
+}
+
+/// Parse an Ursa BLS private key, which is a 48 byte big endian scalar
+pub fn secret_key_from_ursa_bytes(value: &[u8]) -> SharingResult<Bls12381Scalar> {
+    if value.len() != FP_BYTES || value[..FP_BYTES - 32].iter().any(|b| *b != 0) {
+        return Err(SharingError::ShareInvalidSecret);
+    }
+    let key = Bls12381Scalar::from_bytes(&value[FP_BYTES - 32..])?;
+    if !key.is_valid() {
+        return Err(SharingError::ShareInvalidSecret);
+    }
+    Ok(key)
+}
+
+/// Encode a point in G1 like Ursa: `0x04 || x || y`
+pub fn g1_to_ursa_bytes(point: &Bls12381G1) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(2 * FP_BYTES);
+    point.0.serialize(&mut raw, false).unwrap();
+    let mut o = vec![URSA_UNCOMPRESSED];
+    o.extend_from_slice(&raw);
+    o
+}
+
+/// Parse a point in G1 in Ursa's uncompressed encoding
+pub fn g1_from_ursa_bytes(value: &[u8]) -> SharingResult<Bls12381G1> {
+    if value.len() != 1 + 2 * FP_BYTES || value[0] != URSA_UNCOMPRESSED {
+        return Err(SharingError::InvalidPoint);
+    }
+    // Checks that the point is on the curve and in the prime order subgroup
+    let point = G1::deserialize(&mut &value[1..], false).map_err(|_| SharingError::InvalidPoint)?;
+    if point.is_zero() {
+        return Err(SharingError::InvalidPoint);
+    }
+    Ok(Bls12381G1(point))
+}
+
+/// Encode a point in G2 like Ursa: `0x04 || x.c0 || x.c1 || y.c0 || y.c1`.
+/// The usual encoding has the coefficients of each coordinate the other way around
+pub fn g2_to_ursa_bytes(point: &Bls12381G2) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(4 * FP_BYTES);
+    point.0.serialize(&mut raw, false).unwrap();
+    let mut o = vec![URSA_UNCOMPRESSED];
+    for c in [1, 0, 3, 2].iter() {
+        o.extend_from_slice(&raw[c * FP_BYTES..(c + 1) * FP_BYTES]);
+    }
+    o
+}
+
+/// Parse a point in G2 in Ursa's uncompressed encoding
+pub fn g2_from_ursa_bytes(value: &[u8]) -> SharingResult<Bls12381G2> {
+    if value.len() != 1 + 4 * FP_BYTES || value[0] != URSA_UNCOMPRESSED {
+        return Err(SharingError::InvalidPoint);
+    }
+    let mut raw = Vec::with_capacity(4 * FP_BYTES);
+    for c in [1, 0, 3, 2].iter() {
+        raw.extend_from_slice(&value[1 + c * FP_BYTES..1 + (c + 1) * FP_BYTES]);
+    }
+    // Checks that the point is on the curve and in the prime order subgroup
+    let point =
+        G2::deserialize(&mut raw.as_slice(), false).map_err(|_| SharingError::InvalidPoint)?;
+    if point.is_zero() {
+        return Err(SharingError::InvalidPoint);
+    }
+    Ok(Bls12381G2(point))
+}
+
+/// e(key, hashed) == e(g1, signature)
+fn pairing_check(key: &Bls12381G1, hashed: &Bls12381G2, signature: &Bls12381G2) -> bool {
+    if key.is_zero() || signature.is_zero() {
+        return false;
+    }
+    let mut g = G1::one();
+    g.negate();
+    Bls12::pairing_product(
+        key.0.into_affine(),
+        hashed.0.into_affine(),
+        g.into_affine(),
+        signature.0.into_affine(),
+    ) == Fq12::one()
+}
+
+fn check_generator(verifier: &FeldmanVerifier<Bls12381Scalar, Bls12381G1>) -> SharingResult<()> {
+    if verifier.g.to_bytes() != Bls12381G1::generator().to_bytes() {
+        return Err(SharingError::BlsInvalidGenerator);
+    }
+    if verifier.commitments.is_empty() {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    Ok(())
+}
+
+fn copy<R: Group<Bls12381Scalar>>(p: &R) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<R: Group<Bls12381Scalar>>(p: &R, s: &Bls12381Scalar) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
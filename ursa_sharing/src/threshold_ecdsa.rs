@@ -0,0 +1,1122 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Threshold ECDSA signing over secp256k1 and P-256 in the style of
+//! Gennaro and Goldfeder's GG18 and GG20 protocols.
+//!
+//! The signers hold Shamir shares of the secret key, e.g. from `secp256k1::dkg` or
+//! `dkg`, and jointly sign without anyone reconstructing the key. Each signer
+//! converts its share into an additive share with its Lagrange coefficient, picks
+//! a nonce share `k_i` and a mask `gamma_i`, and turns the products of the other
+//! signers' shares into additive shares with Paillier based multiplicative to
+//! additive (MtA) conversions. Each step is an explicit state which consumes the
+//! messages of the previous round and returns the messages to send, so the
+//! protocol can be driven over any transport:
+//!
+//! 1. [`SignerRound1::new`] broadcasts a [`Round1Broadcast`] with a commitment to
+//!    `g^gamma_i`, the signer's Paillier key and its encrypted nonce share.
+//! 2. [`SignerRound1::receive`] answers every other signer's nonce with a
+//!    [`Round2Message`] sent to it privately, holding the MtA responses for
+//!    `k_j * gamma_i` and `k_j * w_i`.
+//! 3. [`SignerRound2::receive`] decrypts the responses and broadcasts a
+//!    [`Round3Broadcast`] with the signer's share of `delta = k * gamma`.
+//! 4. [`SignerRound3::receive`] opens the commitment with a [`Round4Broadcast`]
+//!    and a proof of knowledge of `gamma_i`. Everyone computes `R = g^(1/k)`.
+//! 5. [`SignerRound4::receive`] broadcasts a [`Round5Broadcast`] with `R^k_i` and
+//!    `R^sigma_i` which must sum to `g` and the public key.
+//! 6. [`SignerRound5::receive`] returns a [`Presignature`] that doesn't depend on
+//!    the message so it can be computed ahead of time. [`Presignature::sign`] gives
+//!    the signer's [`PartialSignature`] and [`SignerRound6::receive`] checks and
+//!    combines them into an ECDSA [`Signature`].
+//!
+//! All signers must agree on the set of signer identifiers, which must hold at
+//! least the threshold of shares. A missing or malformed message, a commitment that
+//! doesn't open or an invalid partial signature aborts with `EcdsaAbort` naming the
+//! signer. The sums of `R^k_i` and `R^sigma_i` only show that someone deviated and
+//! abort with `EcdsaConsistencyCheck`, like when too few shares take part.
+//! The transport must provide a reliable broadcast, equivocation isn't detected here.
+//!
+//! This doesn't include the zero knowledge range proofs of GG20 for the Paillier
+//! ciphertexts and MtA responses, nor proofs that the Paillier moduli are well formed.
+//! Without them a signer that deviates in the MtA conversions may learn information
+//! about the other signers' key shares even though the signing aborts. Only sign with
+//! parties trusted to follow the protocol. A presignature must never be used twice,
+//! [`Presignature::sign`] consumes it for that reason.
+
+#[cfg(feature = "checkpoint")]
+mod checkpoint;
+/// Paillier encryption for the MtA conversions
+pub mod paillier;
+
+use self::paillier::{Ciphertext, DecryptionKey, EncryptionKey};
+use crate::{
+    dkg::Reader,
+    dleq::DleqProof,
+    error::{SharingError, SharingResult},
+    shamir::Share,
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use generic_array::typenum::Unsigned;
+use num_bigint::{BigUint, RandBigInt};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+
+const COMMITMENT_DOMAIN: &[u8] = b"ursa_sharing threshold ecdsa commitment";
+const PROOF_DOMAIN: &[u8] = b"ursa_sharing threshold ecdsa proof of knowledge";
+const COMMITMENT_BYTES: usize = 32;
+const BLINDING_BYTES: usize = 32;
+
+/// A prime order group used for ECDSA.
+/// The scalars must encode in big endian like SEC1
+pub trait EcdsaGroup<S: Field>: Group<S> {
+    /// The standard base point
+    fn generator() -> Self;
+    /// The affine x coordinate reduced modulo the group order
+    fn x_coordinate(&self) -> S;
+}
+
+#[cfg(feature = "secp256k1")]
+impl EcdsaGroup<crate::secp256k1::Secp256k1Scalar> for crate::secp256k1::Secp256k1Point {
+    fn generator() -> Self {
+        crate::secp256k1::Secp256k1Point::generator()
+    }
+
+    fn x_coordinate(&self) -> crate::secp256k1::Secp256k1Scalar {
+        compressed_x(self)
+    }
+}
+
+#[cfg(feature = "secp256r1")]
+impl EcdsaGroup<crate::secp256r1::Secp256r1Scalar> for crate::secp256r1::Secp256r1Point {
+    fn generator() -> Self {
+        crate::secp256r1::Secp256r1Point::generator()
+    }
+
+    fn x_coordinate(&self) -> crate::secp256r1::Secp256r1Scalar {
+        compressed_x(self)
+    }
+}
+
+/// The first round broadcast: a commitment to `g^gamma_i`, the sender's
+/// Paillier key and its encrypted nonce share
+#[derive(Clone, Debug)]
+pub struct Round1Broadcast {
+    sender: u32,
+    commitment: [u8; COMMITMENT_BYTES],
+    encryption_key: EncryptionKey,
+    nonce: Ciphertext,
+}
+
+impl Round1Broadcast {
+    /// The signer that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The sender's Paillier key
+    pub fn encryption_key(&self) -> &EncryptionKey {
+        &self.encryption_key
+    }
+
+    /// Serialize as `sender (4) | commitment (32) | key length (4) | key | nonce`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let key = self.encryption_key.to_bytes();
+        let nonce = self.nonce.to_bytes();
+        let mut o = Vec::with_capacity(8 + COMMITMENT_BYTES + key.len() + nonce.len());
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&self.commitment);
+        o.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        o.extend_from_slice(&key);
+        o.extend_from_slice(&nonce);
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Round1Broadcast {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let sender = reader.u32()?;
+        let mut commitment = [0u8; COMMITMENT_BYTES];
+        commitment.copy_from_slice(reader.take(COMMITMENT_BYTES)?);
+        let len = reader.u32()? as usize;
+        let encryption_key = EncryptionKey::try_from(reader.take(len)?)?;
+        let nonce = Ciphertext::from_bytes(reader.data);
+        encryption_key.check(&nonce)?;
+        Ok(Self {
+            sender,
+            commitment,
+            encryption_key,
+            nonce,
+        })
+    }
+}
+
+/// The MtA responses from the sender to the receiver's nonce,
+/// encrypted under the receiver's Paillier key. Sent privately
+#[derive(Clone, Debug)]
+pub struct Round2Message {
+    sender: u32,
+    receiver: u32,
+    gamma: Ciphertext,
+    key: Ciphertext,
+}
+
+impl Round2Message {
+    /// The signer that sent this message
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// The signer this message is for
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+
+    /// Serialize as `sender (4) | receiver (4) | length (4) | gamma response | key response`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let gamma = self.gamma.to_bytes();
+        let key = self.key.to_bytes();
+        let mut o = Vec::with_capacity(12 + gamma.len() + key.len());
+        o.extend_from_slice(&self.sender.to_be_bytes());
+        o.extend_from_slice(&self.receiver.to_be_bytes());
+        o.extend_from_slice(&(gamma.len() as u32).to_be_bytes());
+        o.extend_from_slice(&gamma);
+        o.extend_from_slice(&key);
+        o
+    }
+}
+
+impl TryFrom<&[u8]> for Round2Message {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let sender = reader.u32()?;
+        let receiver = reader.u32()?;
+        let len = reader.u32()? as usize;
+        let gamma = Ciphertext::from_bytes(reader.take(len)?);
+        if reader.data.is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            sender,
+            receiver,
+            gamma,
+            key: Ciphertext::from_bytes(reader.data),
+        })
+    }
+}
+
+/// The third round broadcast: the sender's additive share of `k * gamma`
+#[derive(Clone, Debug)]
+pub struct Round3Broadcast<S: Field> {
+    sender: u32,
+    delta: S,
+}
+
+impl<S: Field> Round3Broadcast<S> {
+    /// The signer that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | delta`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.delta.to_bytes());
+        o
+    }
+
+    /// Deserialize a broadcast using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let (sender, data) = read_sender(data, S::Size::to_usize())?;
+        Ok(Self {
+            sender,
+            delta: S::from_bytes_with(data, strictness)?,
+        })
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for Round3Broadcast<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The fourth round broadcast: the opening of the sender's commitment to
+/// `g^gamma_i` and a proof of knowledge of `gamma_i`
+#[derive(Clone, Debug)]
+pub struct Round4Broadcast<S: Field, R: EcdsaGroup<S>> {
+    sender: u32,
+    gamma_point: R,
+    blinding: [u8; BLINDING_BYTES],
+    proof: DleqProof<S>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Round4Broadcast<S, R> {
+    /// The signer that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | g^gamma_i | blinding (32) | proof`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.gamma_point.to_bytes());
+        o.extend_from_slice(&self.blinding);
+        self.proof.append_bytes(&mut o);
+        o
+    }
+
+    /// Deserialize a broadcast using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        let (sender, data) = read_sender(data, r_size + BLINDING_BYTES + DleqProof::<S>::size())?;
+        let gamma_point = R::from_bytes_with(&data[..r_size], strictness)?;
+        let mut blinding = [0u8; BLINDING_BYTES];
+        blinding.copy_from_slice(&data[r_size..r_size + BLINDING_BYTES]);
+        Ok(Self {
+            sender,
+            gamma_point,
+            blinding,
+            proof: DleqProof::from_bytes_with(&data[r_size + BLINDING_BYTES..], strictness)?,
+        })
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> TryFrom<&[u8]> for Round4Broadcast<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The fifth round broadcast: `R^k_i` and `R^sigma_i`
+#[derive(Clone, Debug)]
+pub struct Round5Broadcast<S: Field, R: EcdsaGroup<S>> {
+    sender: u32,
+    nonce_point: R,
+    key_point: R,
+    phantom: PhantomData<S>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Round5Broadcast<S, R> {
+    /// The signer that sent this broadcast
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | R^k_i | R^sigma_i`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.nonce_point.to_bytes());
+        o.extend_from_slice(&self.key_point.to_bytes());
+        o
+    }
+
+    /// Deserialize a broadcast using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let r_size = R::Size::to_usize();
+        let (sender, data) = read_sender(data, 2 * r_size)?;
+        Ok(Self {
+            sender,
+            nonce_point: R::from_bytes_with(&data[..r_size], strictness)?,
+            key_point: R::from_bytes_with(&data[r_size..], strictness)?,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> TryFrom<&[u8]> for Round5Broadcast<S, R> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// A signer's share of the signature
+#[derive(Clone, Debug)]
+pub struct PartialSignature<S: Field> {
+    sender: u32,
+    value: S,
+}
+
+impl<S: Field> PartialSignature<S> {
+    /// The signer that created this partial signature
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Serialize as `sender (4) | value`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.sender.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.value.to_bytes());
+        o
+    }
+
+    /// Deserialize a partial signature using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let (sender, data) = read_sender(data, S::Size::to_usize())?;
+        Ok(Self {
+            sender,
+            value: S::from_bytes_with(data, strictness)?,
+        })
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for PartialSignature<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// An ECDSA signature. Combined signatures use the smaller of `s` and `-s`
+/// as Bitcoin requires, verification accepts both
+#[derive(Clone, Debug)]
+pub struct Signature<S: Field> {
+    r: S,
+    s: S,
+}
+
+impl<S: Field> Signature<S> {
+    /// The x coordinate of the nonce point reduced modulo the group order
+    pub fn r(&self) -> &S {
+        &self.r
+    }
+
+    /// The signature proof
+    pub fn s(&self) -> &S {
+        &self.s
+    }
+
+    /// Check the signature of a message hash, which must be as long as a scalar
+    pub fn verify<R: EcdsaGroup<S>>(
+        &self,
+        public_key: &R,
+        message_hash: &[u8],
+    ) -> SharingResult<()> {
+        let m = hash_to_scalar::<S>(message_hash)?;
+        if self.r.is_zero() || self.s.is_zero() || public_key.is_zero() {
+            return Err(SharingError::EcdsaSignatureInvalid);
+        }
+        let w = self.s.invert()?;
+        // g^(m / s) * y^(r / s)
+        let mut u1 = m;
+        u1.scalar_mul_assign(&w);
+        let mut u2 = copy(&self.r);
+        u2.scalar_mul_assign(&w);
+        let mut point = mul(&R::generator(), &u1);
+        point.add_assign(&mul(public_key, &u2));
+        if point.is_zero() || !equal(&point.x_coordinate(), &self.r) {
+            return Err(SharingError::EcdsaSignatureInvalid);
+        }
+        Ok(())
+    }
+
+    /// Serialize as `r | s`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.r.to_bytes().to_vec();
+        o.extend_from_slice(&self.s.to_bytes());
+        o
+    }
+
+    /// Deserialize a signature using the given acceptance rules
+    pub fn from_bytes_with(data: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let s_size = S::Size::to_usize();
+        if data.len() != 2 * s_size {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let r = S::from_bytes_with(&data[..s_size], strictness)?;
+        let s = S::from_bytes_with(&data[s_size..], strictness)?;
+        if r.is_zero() || s.is_zero() {
+            return Err(SharingError::EcdsaSignatureInvalid);
+        }
+        Ok(Self { r, s })
+    }
+}
+
+impl<S: Field> TryFrom<&[u8]> for Signature<S> {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(data, Strictness::Lenient)
+    }
+}
+
+/// The identifiers of the session
+#[derive(Clone, Debug)]
+struct Session {
+    id: u32,
+    signers: Vec<u32>,
+}
+
+impl Session {
+    /// Collect one message from each other signer, ignoring this signer's own
+    fn collect<'a, M>(
+        &self,
+        messages: &'a [M],
+        sender: impl Fn(&M) -> u32,
+    ) -> SharingResult<BTreeMap<u32, &'a M>> {
+        let mut collected = BTreeMap::new();
+        for m in messages {
+            let s = sender(m);
+            if self.signers.binary_search(&s).is_err() {
+                return Err(SharingError::EcdsaInvalidSigner);
+            }
+            if s != self.id {
+                collected.entry(s).or_insert(m);
+            }
+        }
+        match self
+            .signers
+            .iter()
+            .find(|s| **s != self.id && !collected.contains_key(*s))
+        {
+            Some(missing) => Err(SharingError::EcdsaAbort(*missing)),
+            None => Ok(collected),
+        }
+    }
+
+    fn proof_transcript(&self, sender: u32) -> Transcript {
+        let mut transcript = Transcript::new(PROOF_DOMAIN);
+        for s in &self.signers {
+            transcript.append_u64(b"signer", *s as u64);
+        }
+        transcript.append_u64(b"sender", sender as u64);
+        transcript
+    }
+}
+
+/// The secret values of a signer, cleared on drop
+struct Secrets<S: Field> {
+    nonce: S,
+    gamma: S,
+    key: S,
+    sigma: S,
+    /// The negated MtA masks for `k_j * gamma_i` and `k_j * w_i`, by signer
+    masks: BTreeMap<u32, (S, S)>,
+}
+
+impl<S: Field> Debug for Secrets<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Secrets(..)")
+    }
+}
+
+impl<S: Field> Drop for Secrets<S> {
+    fn drop(&mut self) {
+        self.nonce.zeroize();
+        self.gamma.zeroize();
+        self.key.zeroize();
+        self.sigma.zeroize();
+        for (a, b) in self.masks.values_mut() {
+            a.zeroize();
+            b.zeroize();
+        }
+    }
+}
+
+/// A signer waiting for the other signers' [`Round1Broadcast`]s
+#[derive(Debug)]
+pub struct SignerRound1<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    decryption_key: DecryptionKey,
+    opening: Round4Broadcast<S, R>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound1<S, R> {
+    /// Start signing with `share` of the secret key behind `public_key`.
+    /// `signers` are the identifiers of the shares taking part including this one,
+    /// `decryption_key` is this signer's Paillier key which can be reused across sessions.
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+        public_key: &R,
+        signers: &[u32],
+        decryption_key: &DecryptionKey,
+    ) -> SharingResult<(Self, Round1Broadcast)> {
+        let id = share.identifier();
+        let mut ids = signers.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() != signers.len()
+            || ids.len() < 2
+            || ids[0] == 0
+            || ids.binary_search(&id).is_err()
+        {
+            return Err(SharingError::EcdsaInvalidSigner);
+        }
+        if !public_key.is_valid() {
+            return Err(SharingError::InvalidPoint);
+        }
+        let value = S::from_bytes(share.value())?;
+        if !value.is_valid() {
+            return Err(SharingError::ShareInvalidValue);
+        }
+        let mut key = lagrange::<S>(id, &ids)?;
+        key.scalar_mul_assign(&value);
+        let session = Session { id, signers: ids };
+
+        let nonce = S::random(rng);
+        let gamma = S::random(rng);
+        let gamma_point = mul(&R::generator(), &gamma);
+        let mut blinding = [0u8; BLINDING_BYTES];
+        rng.fill_bytes(&mut blinding);
+        let proof = DleqProof::prove(
+            rng,
+            &mut session.proof_transcript(id),
+            &gamma,
+            (&R::generator(), &gamma_point),
+            (&R::generator(), &gamma_point),
+        )?;
+        let encryption_key = decryption_key.encryption_key().clone();
+        let broadcast = Round1Broadcast {
+            sender: id,
+            commitment: commitment(id, &gamma_point, &blinding),
+            nonce: encryption_key.encrypt(rng, &to_biguint(&nonce))?,
+            encryption_key,
+        };
+        Ok((
+            Self {
+                session,
+                public_key: copy(public_key),
+                secrets: Secrets {
+                    nonce,
+                    gamma,
+                    key,
+                    sigma: S::zero(),
+                    masks: BTreeMap::new(),
+                },
+                decryption_key: decryption_key.clone(),
+                opening: Round4Broadcast {
+                    sender: id,
+                    gamma_point,
+                    blinding,
+                    proof,
+                },
+            },
+            broadcast,
+        ))
+    }
+
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Process the other signers' round one broadcasts.
+    /// Returns the [`Round2Message`]s to send to each of them
+    pub fn receive(
+        mut self,
+        rng: &mut (impl RngCore + CryptoRng),
+        broadcasts: &[Round1Broadcast],
+    ) -> SharingResult<(SignerRound2<S, R>, Vec<Round2Message>)> {
+        let broadcasts = self.session.collect(broadcasts, |b| b.sender)?;
+        let mut messages = Vec::with_capacity(broadcasts.len());
+        let mut commitments = BTreeMap::new();
+        for (sender, b) in &broadcasts {
+            let key = &b.encryption_key;
+            if key.modulus_bits() < paillier::MIN_MODULUS_BITS || key.check(&b.nonce).is_err() {
+                return Err(SharingError::EcdsaAbort(*sender));
+            }
+            let (gamma, gamma_mask) = mta_respond(rng, key, &b.nonce, &self.secrets.gamma)?;
+            let (key_response, key_mask) = mta_respond(rng, key, &b.nonce, &self.secrets.key)?;
+            self.secrets.masks.insert(*sender, (gamma_mask, key_mask));
+            commitments.insert(*sender, b.commitment);
+            messages.push(Round2Message {
+                sender: self.session.id,
+                receiver: *sender,
+                gamma,
+                key: key_response,
+            });
+        }
+        Ok((
+            SignerRound2 {
+                session: self.session,
+                public_key: self.public_key,
+                secrets: self.secrets,
+                decryption_key: self.decryption_key,
+                opening: self.opening,
+                commitments,
+            },
+            messages,
+        ))
+    }
+}
+
+/// A signer waiting for the [`Round2Message`]s sent to it
+#[derive(Debug)]
+pub struct SignerRound2<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    decryption_key: DecryptionKey,
+    opening: Round4Broadcast<S, R>,
+    commitments: BTreeMap<u32, [u8; COMMITMENT_BYTES]>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound2<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Process the MtA responses from the other signers.
+    /// Returns the [`Round3Broadcast`] to send to everyone
+    pub fn receive(
+        mut self,
+        messages: &[Round2Message],
+    ) -> SharingResult<(SignerRound3<S, R>, Round3Broadcast<S>)> {
+        if messages.iter().any(|m| m.receiver != self.session.id) {
+            return Err(SharingError::EcdsaInvalidSigner);
+        }
+        let messages = self.session.collect(messages, |m| m.sender)?;
+        let key = self.decryption_key.encryption_key();
+
+        // delta_i = k_i * gamma_i + sum(alpha_ij + beta_ji)
+        // sigma_i = k_i * w_i + sum(mu_ij + nu_ji)
+        let secrets = &mut self.secrets;
+        let mut delta = copy(&secrets.nonce);
+        delta.scalar_mul_assign(&secrets.gamma);
+        let mut sigma = copy(&secrets.nonce);
+        sigma.scalar_mul_assign(&secrets.key);
+        for (sender, m) in &messages {
+            if key.check(&m.gamma).is_err() || key.check(&m.key).is_err() {
+                return Err(SharingError::EcdsaAbort(*sender));
+            }
+            let (gamma_mask, key_mask) = &secrets.masks[sender];
+            delta.add_assign(&from_biguint(&self.decryption_key.decrypt(&m.gamma))?);
+            delta.add_assign(gamma_mask);
+            sigma.add_assign(&from_biguint(&self.decryption_key.decrypt(&m.key))?);
+            sigma.add_assign(key_mask);
+        }
+        secrets.sigma = sigma;
+
+        let broadcast = Round3Broadcast {
+            sender: self.session.id,
+            delta: copy(&delta),
+        };
+        Ok((
+            SignerRound3 {
+                session: self.session,
+                public_key: self.public_key,
+                secrets: self.secrets,
+                opening: self.opening,
+                commitments: self.commitments,
+                delta,
+            },
+            broadcast,
+        ))
+    }
+}
+
+/// A signer waiting for the other signers' [`Round3Broadcast`]s
+#[derive(Debug)]
+pub struct SignerRound3<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    opening: Round4Broadcast<S, R>,
+    commitments: BTreeMap<u32, [u8; COMMITMENT_BYTES]>,
+    delta: S,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound3<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Process the other signers' shares of `delta`.
+    /// Returns the [`Round4Broadcast`] to send to everyone
+    pub fn receive(
+        self,
+        broadcasts: &[Round3Broadcast<S>],
+    ) -> SharingResult<(SignerRound4<S, R>, Round4Broadcast<S, R>)> {
+        let broadcasts = self.session.collect(broadcasts, |b| b.sender)?;
+        let mut delta = self.delta;
+        for b in broadcasts.values() {
+            delta.add_assign(&b.delta);
+        }
+        if delta.is_zero() {
+            return Err(SharingError::EcdsaConsistencyCheck);
+        }
+        let delta_inverse = delta.invert()?;
+        Ok((
+            SignerRound4 {
+                session: self.session,
+                public_key: self.public_key,
+                secrets: self.secrets,
+                gamma_point: copy(&self.opening.gamma_point),
+                commitments: self.commitments,
+                delta_inverse,
+            },
+            self.opening,
+        ))
+    }
+}
+
+/// A signer waiting for the other signers' [`Round4Broadcast`]s
+#[derive(Debug)]
+pub struct SignerRound4<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    gamma_point: R,
+    commitments: BTreeMap<u32, [u8; COMMITMENT_BYTES]>,
+    delta_inverse: S,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound4<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Check the openings of the other signers' commitments and compute the nonce point.
+    /// Returns the [`Round5Broadcast`] to send to everyone
+    pub fn receive(
+        self,
+        broadcasts: &[Round4Broadcast<S, R>],
+    ) -> SharingResult<(SignerRound5<S, R>, Round5Broadcast<S, R>)> {
+        let broadcasts = self.session.collect(broadcasts, |b| b.sender)?;
+        let mut gamma = self.gamma_point;
+        for (sender, b) in &broadcasts {
+            let g = R::generator();
+            let opens =
+                commitment(*sender, &b.gamma_point, &b.blinding) == self.commitments[sender];
+            if !opens
+                || b.proof
+                    .verify(
+                        &mut self.session.proof_transcript(*sender),
+                        (&g, &b.gamma_point),
+                        (&g, &b.gamma_point),
+                    )
+                    .is_err()
+            {
+                return Err(SharingError::EcdsaAbort(*sender));
+            }
+            gamma.add_assign(&b.gamma_point);
+        }
+
+        // R = g^(gamma / (k * gamma)) = g^(1 / k)
+        let nonce_point = mul(&gamma, &self.delta_inverse);
+        let r = nonce_point.x_coordinate();
+        if nonce_point.is_zero() || r.is_zero() {
+            return Err(SharingError::EcdsaConsistencyCheck);
+        }
+        let own = Round5Broadcast {
+            sender: self.session.id,
+            nonce_point: mul(&nonce_point, &self.secrets.nonce),
+            key_point: mul(&nonce_point, &self.secrets.sigma),
+            phantom: PhantomData,
+        };
+        let broadcast = Round5Broadcast {
+            sender: own.sender,
+            nonce_point: copy(&own.nonce_point),
+            key_point: copy(&own.key_point),
+            phantom: PhantomData,
+        };
+        Ok((
+            SignerRound5 {
+                session: self.session,
+                public_key: self.public_key,
+                secrets: self.secrets,
+                nonce_point,
+                r,
+                own,
+            },
+            broadcast,
+        ))
+    }
+}
+
+/// A signer waiting for the other signers' [`Round5Broadcast`]s
+#[derive(Debug)]
+pub struct SignerRound5<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    nonce_point: R,
+    r: S,
+    own: Round5Broadcast<S, R>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound5<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Check that the shares of `k` and the secret key are consistent with the nonce
+    /// point and the public key. Returns the presignature
+    pub fn receive(
+        self,
+        broadcasts: &[Round5Broadcast<S, R>],
+    ) -> SharingResult<Presignature<S, R>> {
+        let broadcasts = self.session.collect(broadcasts, |b| b.sender)?;
+        // sum(R^k_i) = R^k = g and sum(R^sigma_i) = R^(k * x) = y
+        let mut nonce_sum = copy(&self.own.nonce_point);
+        let mut key_sum = copy(&self.own.key_point);
+        for b in broadcasts.values() {
+            nonce_sum.add_assign(&b.nonce_point);
+            key_sum.add_assign(&b.key_point);
+        }
+        if !equal(&nonce_sum, &R::generator()) || !equal(&key_sum, &self.public_key) {
+            return Err(SharingError::EcdsaConsistencyCheck);
+        }
+        let mut points = broadcasts
+            .into_iter()
+            .map(|(s, b)| (s, (copy(&b.nonce_point), copy(&b.key_point))))
+            .collect::<BTreeMap<u32, (R, R)>>();
+        points.insert(self.session.id, (self.own.nonce_point, self.own.key_point));
+        Ok(Presignature {
+            session: self.session,
+            public_key: self.public_key,
+            secrets: self.secrets,
+            nonce_point: self.nonce_point,
+            r: self.r,
+            points,
+        })
+    }
+}
+
+/// The message independent result of the first five rounds.
+/// Must be used for a single signature
+#[derive(Debug)]
+pub struct Presignature<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    secrets: Secrets<S>,
+    nonce_point: R,
+    r: S,
+    /// `R^k_i` and `R^sigma_i` by signer
+    points: BTreeMap<u32, (R, R)>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Presignature<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// The `r` value of the signature this presignature makes
+    pub fn r(&self) -> &S {
+        &self.r
+    }
+
+    /// Sign a message hash, which must be as long as a scalar.
+    /// Returns the [`PartialSignature`] to send to everyone
+    pub fn sign(
+        self,
+        message_hash: &[u8],
+    ) -> SharingResult<(SignerRound6<S, R>, PartialSignature<S>)> {
+        let m = hash_to_scalar::<S>(message_hash)?;
+        // s_i = m * k_i + r * sigma_i
+        let mut value = copy(&m);
+        value.scalar_mul_assign(&self.secrets.nonce);
+        let mut t = copy(&self.r);
+        t.scalar_mul_assign(&self.secrets.sigma);
+        value.add_assign(&t);
+        let partial = PartialSignature {
+            sender: self.session.id,
+            value: copy(&value),
+        };
+        Ok((
+            SignerRound6 {
+                session: self.session,
+                public_key: self.public_key,
+                nonce_point: self.nonce_point,
+                r: self.r,
+                points: self.points,
+                message: m,
+                own: PartialSignature {
+                    sender: partial.sender,
+                    value,
+                },
+            },
+            partial,
+        ))
+    }
+}
+
+/// A signer waiting for the other signers' [`PartialSignature`]s.
+/// Holds no secrets
+#[derive(Debug)]
+pub struct SignerRound6<S: Field, R: EcdsaGroup<S>> {
+    session: Session,
+    public_key: R,
+    nonce_point: R,
+    r: S,
+    points: BTreeMap<u32, (R, R)>,
+    message: S,
+    own: PartialSignature<S>,
+}
+
+impl<S: Field, R: EcdsaGroup<S>> SignerRound6<S, R> {
+    /// This signer's identifier
+    pub fn id(&self) -> u32 {
+        self.session.id
+    }
+
+    /// Check the other signers' partial signatures and combine them
+    pub fn receive(self, partials: &[PartialSignature<S>]) -> SharingResult<Signature<S>> {
+        let mut partials = self.session.collect(partials, |p| p.sender)?;
+        partials.insert(self.session.id, &self.own);
+        let mut s = S::zero();
+        for (sender, p) in &partials {
+            // R^s_i = (R^k_i)^m * (R^sigma_i)^r
+            let (nonce_point, key_point) = &self.points[sender];
+            let mut expected = mul(nonce_point, &self.message);
+            expected.add_assign(&mul(key_point, &self.r));
+            if !equal(&mul(&self.nonce_point, &p.value), &expected) {
+                return Err(SharingError::EcdsaAbort(*sender));
+            }
+            s.add_assign(&p.value);
+        }
+        if s.is_zero() {
+            return Err(SharingError::EcdsaConsistencyCheck);
+        }
+        let mut negated = copy(&s);
+        negated.negate();
+        if negated.to_bytes() < s.to_bytes() {
+            s = negated;
+        }
+        let signature = Signature { r: self.r, s };
+        signature.verify(&self.public_key, &self.message.to_bytes())?;
+        Ok(signature)
+    }
+}
+
+/// Bob's side of an MtA conversion of `a * b` where Alice sent `Enc(a)`.
+/// Returns `Enc(a * b + beta')` and Bob's additive share `-beta'`
+fn mta_respond<S: Field>(
+    rng: &mut (impl RngCore + CryptoRng),
+    key: &EncryptionKey,
+    encrypted: &Ciphertext,
+    b: &S,
+) -> SharingResult<(Ciphertext, S)> {
+    // beta' < q^5 hides a * b < q^2 and the sum can't wrap around n
+    let bound = order::<S>().pow(5);
+    let mask = rng.gen_biguint_below(&bound);
+    let response = key.add(
+        &key.mul(encrypted, &to_biguint(b)),
+        &key.encrypt(rng, &mask)?,
+    );
+    let mut share = from_biguint::<S>(&mask)?;
+    share.negate();
+    Ok((response, share))
+}
+
+/// `prod(j / (j - i))` over the other signers `j`
+fn lagrange<S: Field>(id: u32, signers: &[u32]) -> SharingResult<S> {
+    let x = S::from_usize(id as usize);
+    let mut num = S::one();
+    let mut den = S::one();
+    for j in signers.iter().filter(|j| **j != id) {
+        let xj = S::from_usize(*j as usize);
+        let mut d = copy(&xj);
+        d.sub_assign(&x);
+        num.scalar_mul_assign(&xj);
+        den.scalar_mul_assign(&d);
+    }
+    num.scalar_div_assign(&den)?;
+    Ok(num)
+}
+
+fn commitment<S: Field, R: Group<S>>(
+    sender: u32,
+    point: &R,
+    blinding: &[u8; BLINDING_BYTES],
+) -> [u8; COMMITMENT_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.input(COMMITMENT_DOMAIN);
+    hasher.input(sender.to_be_bytes());
+    hasher.input(point.to_bytes());
+    hasher.input(blinding);
+    let mut o = [0u8; COMMITMENT_BYTES];
+    o.copy_from_slice(&hasher.result());
+    o
+}
+
+fn hash_to_scalar<S: Field>(message_hash: &[u8]) -> SharingResult<S> {
+    if message_hash.len() != S::Size::to_usize() {
+        return Err(SharingError::EcdsaInvalidMessageHash);
+    }
+    S::from_bytes(message_hash)
+}
+
+/// The group order of `S`
+fn order<S: Field>() -> BigUint {
+    let mut minus_one = S::zero();
+    minus_one.sub_assign(&S::one());
+    to_biguint(&minus_one) + 1u32
+}
+
+fn to_biguint<S: Field>(s: &S) -> BigUint {
+    BigUint::from_bytes_be(&s.to_bytes())
+}
+
+fn from_biguint<S: Field>(n: &BigUint) -> SharingResult<S> {
+    let bytes = (n % order::<S>()).to_bytes_be();
+    let mut padded = vec![0u8; S::Size::to_usize() - bytes.len()];
+    padded.extend_from_slice(&bytes);
+    S::from_bytes(padded)
+}
+
+/// The x coordinate of a point in SEC1 compressed form
+#[cfg(any(feature = "secp256k1", feature = "secp256r1"))]
+fn compressed_x<S: Field, R: Group<S>>(point: &R) -> S {
+    S::from_bytes(&point.to_bytes()[1..]).unwrap_or_else(|_| S::zero())
+}
+
+fn read_sender(data: &[u8], len: usize) -> SharingResult<(u32, &[u8])> {
+    if data.len() != 4 + len {
+        return Err(SharingError::ShareInvalidEncoding);
+    }
+    let mut reader = Reader { data };
+    Ok((reader.u32()?, reader.data))
+}
+
+fn equal<E: ?Sized, R: Group<E>>(a: &R, b: &R) -> bool {
+    a.to_bytes() == b.to_bytes()
+}
+
+fn copy<E: ?Sized, R: Group<E>>(value: &R) -> R {
+    let mut c = R::zero();
+    c.add_assign(value);
+    c
+}
+
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
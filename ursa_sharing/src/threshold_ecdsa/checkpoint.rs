@@ -0,0 +1,336 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! [`Checkpoint`] encodings of the signer states.
+//!
+//! Each state is written as its fields in declaration order. Variable length fields
+//! are prefixed with their length and maps with their number of entries.
+
+use super::*;
+use crate::checkpoint::{put, Checkpoint};
+use zeroize::Zeroize;
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound1<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 1";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        put_decryption_key(&mut o, &self.decryption_key);
+        put(&mut o, &self.opening.to_bytes());
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let state = Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            decryption_key: DecryptionKey::try_from(reader.field()?)?,
+            opening: Round4Broadcast::try_from(reader.field()?)?,
+        };
+        reader.finish()?;
+        Ok(state)
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound2<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 2";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        put_decryption_key(&mut o, &self.decryption_key);
+        put(&mut o, &self.opening.to_bytes());
+        put_commitments(&mut o, &self.commitments);
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let state = Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            decryption_key: DecryptionKey::try_from(reader.field()?)?,
+            opening: Round4Broadcast::try_from(reader.field()?)?,
+            commitments: read_commitments(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(state)
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound3<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 3";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        put(&mut o, &self.opening.to_bytes());
+        put_commitments(&mut o, &self.commitments);
+        put_scalar(&mut o, &self.delta);
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let state = Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            opening: Round4Broadcast::try_from(reader.field()?)?,
+            commitments: read_commitments(&mut reader)?,
+            delta: read_scalar(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(state)
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound4<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 4";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        o.extend_from_slice(&self.gamma_point.to_bytes());
+        put_commitments(&mut o, &self.commitments);
+        put_scalar(&mut o, &self.delta_inverse);
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let state = Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            gamma_point: read_point(&mut reader)?,
+            commitments: read_commitments(&mut reader)?,
+            delta_inverse: read_scalar(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(state)
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound5<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 5";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        o.extend_from_slice(&self.nonce_point.to_bytes());
+        put_scalar(&mut o, &self.r);
+        o.extend_from_slice(&self.own.to_bytes());
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        Ok(Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            nonce_point: read_point(&mut reader)?,
+            r: read_scalar(&mut reader)?,
+            own: Round5Broadcast::try_from(reader.data)?,
+        })
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for Presignature<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa presignature";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        put_secrets(&mut o, &self.secrets);
+        o.extend_from_slice(&self.nonce_point.to_bytes());
+        put_scalar(&mut o, &self.r);
+        put_points(&mut o, &self.points);
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        let state = Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            secrets: read_secrets(&mut reader)?,
+            nonce_point: read_point(&mut reader)?,
+            r: read_scalar(&mut reader)?,
+            points: read_points(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(state)
+    }
+}
+
+impl<S: Field, R: EcdsaGroup<S>> Checkpoint for SignerRound6<S, R> {
+    const KIND: &'static [u8] = b"threshold ecdsa signer round 6";
+
+    fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::new();
+        put_session(&mut o, &self.session);
+        o.extend_from_slice(&self.public_key.to_bytes());
+        o.extend_from_slice(&self.nonce_point.to_bytes());
+        put_scalar(&mut o, &self.r);
+        put_points(&mut o, &self.points);
+        put_scalar(&mut o, &self.message);
+        o.extend_from_slice(&self.own.to_bytes());
+        o
+    }
+
+    fn from_checkpoint_bytes(data: &[u8]) -> SharingResult<Self> {
+        let mut reader = Reader { data };
+        Ok(Self {
+            session: read_session(&mut reader)?,
+            public_key: read_point(&mut reader)?,
+            nonce_point: read_point(&mut reader)?,
+            r: read_scalar(&mut reader)?,
+            points: read_points(&mut reader)?,
+            message: read_scalar(&mut reader)?,
+            own: PartialSignature::try_from(reader.data)?,
+        })
+    }
+}
+
+fn put_session(o: &mut Vec<u8>, session: &Session) {
+    o.extend_from_slice(&session.id.to_be_bytes());
+    o.extend_from_slice(&(session.signers.len() as u32).to_be_bytes());
+    for s in &session.signers {
+        o.extend_from_slice(&s.to_be_bytes());
+    }
+}
+
+/// Read a session, checked like in [`SignerRound1::new`]
+fn read_session(reader: &mut Reader) -> SharingResult<Session> {
+    let id = reader.u32()?;
+    let signers = (0..reader.u32()?)
+        .map(|_| reader.u32())
+        .collect::<SharingResult<Vec<u32>>>()?;
+    if signers.len() < 2
+        || signers[0] == 0
+        || signers.windows(2).any(|w| w[0] >= w[1])
+        || signers.binary_search(&id).is_err()
+    {
+        return Err(SharingError::EcdsaInvalidSigner);
+    }
+    Ok(Session { id, signers })
+}
+
+fn put_scalar<S: Field>(o: &mut Vec<u8>, s: &S) {
+    let mut bytes = s.to_bytes();
+    o.extend_from_slice(&bytes);
+    bytes.zeroize();
+}
+
+fn read_scalar<S: Field>(reader: &mut Reader) -> SharingResult<S> {
+    S::from_bytes(reader.take(S::Size::to_usize())?)
+}
+
+fn read_point<S: Field, R: EcdsaGroup<S>>(reader: &mut Reader) -> SharingResult<R> {
+    let point = R::from_bytes(reader.take(R::Size::to_usize())?)?;
+    if !point.is_valid() {
+        return Err(SharingError::InvalidPoint);
+    }
+    Ok(point)
+}
+
+fn put_secrets<S: Field>(o: &mut Vec<u8>, secrets: &Secrets<S>) {
+    for s in &[&secrets.nonce, &secrets.gamma, &secrets.key, &secrets.sigma] {
+        put_scalar(o, *s);
+    }
+    o.extend_from_slice(&(secrets.masks.len() as u32).to_be_bytes());
+    for (signer, (a, b)) in &secrets.masks {
+        o.extend_from_slice(&signer.to_be_bytes());
+        put_scalar(o, a);
+        put_scalar(o, b);
+    }
+}
+
+fn read_secrets<S: Field>(reader: &mut Reader) -> SharingResult<Secrets<S>> {
+    let mut secrets = Secrets {
+        nonce: read_scalar(reader)?,
+        gamma: read_scalar(reader)?,
+        key: read_scalar(reader)?,
+        sigma: read_scalar(reader)?,
+        masks: BTreeMap::new(),
+    };
+    for _ in 0..reader.u32()? {
+        let signer = reader.u32()?;
+        let masks = (read_scalar(reader)?, read_scalar(reader)?);
+        secrets.masks.insert(signer, masks);
+    }
+    Ok(secrets)
+}
+
+fn put_decryption_key(o: &mut Vec<u8>, key: &DecryptionKey) {
+    let mut bytes = key.to_bytes();
+    put(o, &bytes);
+    bytes.zeroize();
+}
+
+fn put_commitments(o: &mut Vec<u8>, commitments: &BTreeMap<u32, [u8; COMMITMENT_BYTES]>) {
+    o.extend_from_slice(&(commitments.len() as u32).to_be_bytes());
+    for (signer, c) in commitments {
+        o.extend_from_slice(&signer.to_be_bytes());
+        o.extend_from_slice(c);
+    }
+}
+
+fn read_commitments(reader: &mut Reader) -> SharingResult<BTreeMap<u32, [u8; COMMITMENT_BYTES]>> {
+    let mut commitments = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let signer = reader.u32()?;
+        let mut c = [0u8; COMMITMENT_BYTES];
+        c.copy_from_slice(reader.take(COMMITMENT_BYTES)?);
+        commitments.insert(signer, c);
+    }
+    Ok(commitments)
+}
+
+fn put_points<S: Field, R: EcdsaGroup<S>>(o: &mut Vec<u8>, points: &BTreeMap<u32, (R, R)>) {
+    o.extend_from_slice(&(points.len() as u32).to_be_bytes());
+    for (signer, (nonce_point, key_point)) in points {
+        o.extend_from_slice(&signer.to_be_bytes());
+        o.extend_from_slice(&nonce_point.to_bytes());
+        o.extend_from_slice(&key_point.to_bytes());
+    }
+}
+
+fn read_points<S: Field, R: EcdsaGroup<S>>(
+    reader: &mut Reader,
+) -> SharingResult<BTreeMap<u32, (R, R)>> {
+    let mut points = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let signer = reader.u32()?;
+        let pair = (read_point(reader)?, read_point(reader)?);
+        points.insert(signer, pair);
+    }
+    Ok(points)
+}
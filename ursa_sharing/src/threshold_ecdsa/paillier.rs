@@ -0,0 +1,243 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Paillier encryption for the multiplicative to additive share conversions.
+//!
+//! Every signer owns a long lived [`DecryptionKey`] and announces its
+//! [`EncryptionKey`] in the first signing round. The others use it to multiply
+//! their secrets into the signer's encrypted nonce. Only key handling is public,
+//! the homomorphic operations are used internally by the signing rounds.
+
+use crate::error::{SharingError, SharingResult};
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand::{CryptoRng, RngCore};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+/// The smallest modulus accepted, in bits
+pub const MIN_MODULUS_BITS: usize = 2048;
+
+/// A Paillier public key with generator `n + 1`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptionKey {
+    n: BigUint,
+    nn: BigUint,
+}
+
+impl EncryptionKey {
+    fn new(n: BigUint) -> Self {
+        let nn = &n * &n;
+        Self { n, nn }
+    }
+
+    /// The size of the modulus in bits
+    pub fn modulus_bits(&self) -> usize {
+        self.n.bits() as usize
+    }
+
+    /// Serialize the modulus in big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.n.to_bytes_be()
+    }
+
+    /// Encrypt `m < n` with fresh randomness
+    pub(crate) fn encrypt(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        m: &BigUint,
+    ) -> SharingResult<Ciphertext> {
+        if m >= &self.n {
+            return Err(SharingError::PaillierInvalidCiphertext);
+        }
+        let r = loop {
+            let r = rng.gen_biguint_range(&BigUint::one(), &self.n);
+            if r.gcd(&self.n).is_one() {
+                break r;
+            }
+        };
+        // (1 + n)^m * r^n = (1 + m * n) * r^n mod n^2
+        let gm = (BigUint::one() + m * &self.n) % &self.nn;
+        Ok(Ciphertext(gm * r.modpow(&self.n, &self.nn) % &self.nn))
+    }
+
+    /// The encryption of the sum of the plaintexts
+    pub(crate) fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        Ciphertext(&a.0 * &b.0 % &self.nn)
+    }
+
+    /// The encryption of the plaintext multiplied by `k`
+    pub(crate) fn mul(&self, a: &Ciphertext, k: &BigUint) -> Ciphertext {
+        Ciphertext(a.0.modpow(k, &self.nn))
+    }
+
+    /// Check that a ciphertext is a unit modulo `n^2`
+    pub(crate) fn check(&self, c: &Ciphertext) -> SharingResult<()> {
+        if c.0.is_zero() || c.0 >= self.nn || !c.0.gcd(&self.n).is_one() {
+            return Err(SharingError::PaillierInvalidCiphertext);
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for EncryptionKey {
+    type Error = SharingError;
+
+    /// Parse a modulus of at least `MIN_MODULUS_BITS` bits.
+    /// Knowing its factorization isn't proven so the owner must be trusted to generate it honestly
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.first().map(|b| *b == 0).unwrap_or(true) {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        let n = BigUint::from_bytes_be(data);
+        if (n.bits() as usize) < MIN_MODULUS_BITS || n.is_even() {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        Ok(Self::new(n))
+    }
+}
+
+/// A Paillier secret key
+#[derive(Clone)]
+pub struct DecryptionKey {
+    encryption_key: EncryptionKey,
+    p: BigUint,
+    q: BigUint,
+    phi: BigUint,
+    mu: BigUint,
+}
+
+impl DecryptionKey {
+    /// Generate a key with a modulus of `bits` bits, at least `MIN_MODULUS_BITS`
+    pub fn generate(rng: &mut (impl RngCore + CryptoRng), bits: usize) -> SharingResult<Self> {
+        if bits < MIN_MODULUS_BITS || bits & 1 == 1 {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        loop {
+            let p = prime(rng, bits / 2)?;
+            let q = prime(rng, bits / 2)?;
+            if p != q && (&p * &q).bits() as usize == bits {
+                return Self::from_primes(p, q);
+            }
+        }
+    }
+
+    fn from_primes(p: BigUint, q: BigUint) -> SharingResult<Self> {
+        let one = BigUint::one();
+        let n = &p * &q;
+        let phi = (&p - &one) * (&q - &one);
+        let mu = invert(&phi, &n).ok_or(SharingError::PaillierInvalidKey)?;
+        Ok(Self {
+            encryption_key: EncryptionKey::new(n),
+            p,
+            q,
+            phi,
+            mu,
+        })
+    }
+
+    /// The public key
+    pub fn encryption_key(&self) -> &EncryptionKey {
+        &self.encryption_key
+    }
+
+    /// Serialize the primes as `len(p) (4) | p | q`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let p = self.p.to_bytes_be();
+        let mut o = Vec::with_capacity(4 + 2 * p.len());
+        o.extend_from_slice(&(p.len() as u32).to_be_bytes());
+        o.extend_from_slice(&p);
+        o.extend_from_slice(&self.q.to_bytes_be());
+        o
+    }
+
+    /// Decrypt a ciphertext checked with [`EncryptionKey::check`]
+    pub(crate) fn decrypt(&self, c: &Ciphertext) -> BigUint {
+        let ek = &self.encryption_key;
+        let u = c.0.modpow(&self.phi, &ek.nn);
+        // L(u) = (u - 1) / n
+        let l = (u - BigUint::one()) / &ek.n;
+        l * &self.mu % &ek.n
+    }
+}
+
+impl TryFrom<&[u8]> for DecryptionKey {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        if data.len() < 4 {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&data[..4]);
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || data.len() != 4 + 2 * len {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        let p = BigUint::from_bytes_be(&data[4..4 + len]);
+        let q = BigUint::from_bytes_be(&data[4 + len..]);
+        if p == q || !glass_pumpkin::prime::check(&p) || !glass_pumpkin::prime::check(&q) {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        let key = Self::from_primes(p, q)?;
+        if key.encryption_key.modulus_bits() < MIN_MODULUS_BITS {
+            return Err(SharingError::PaillierInvalidKey);
+        }
+        Ok(key)
+    }
+}
+
+impl Debug for DecryptionKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "DecryptionKey(..)")
+    }
+}
+
+/// A Paillier ciphertext
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Ciphertext(BigUint);
+
+impl Ciphertext {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes_be()
+    }
+
+    pub(crate) fn from_bytes(data: &[u8]) -> Self {
+        Self(BigUint::from_bytes_be(data))
+    }
+}
+
+/// A prime of exactly `bits` bits
+fn prime(rng: &mut (impl RngCore + CryptoRng), bits: usize) -> SharingResult<BigUint> {
+    loop {
+        let p = glass_pumpkin::prime::from_rng(bits, rng)
+            .map_err(|_| SharingError::PaillierInvalidKey)?;
+        if p.bits() as usize == bits {
+            return Ok(p);
+        }
+    }
+}
+
+/// `a^-1 mod m` if it exists
+fn invert(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    let a = BigInt::from(a.clone());
+    let m = BigInt::from(m.clone());
+    let e = a.extended_gcd(&m);
+    if !e.gcd.is_one() {
+        return None;
+    }
+    e.x.mod_floor(&m).to_biguint()
+}
@@ -0,0 +1,568 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Threshold OPRFs, for password hardening services run by `n` servers of which any
+//! `t` answer.
+//!
+//! The key of an RFC 9497 VOPRF is shared with Feldman, or generated by the DKG, with
+//! the suite's standard generator. Every [`ThresholdOprfServer`] evaluates the client's
+//! blinded element with its share and proves with the VOPRF [`Proof`] that it used the
+//! share of its public key share, computed by the client from the commitments. The
+//! [`ThresholdOprfClient`] checks the [`PartialEvaluation`]s, names the servers whose
+//! evaluations are invalid, interpolates any `threshold` of them in the exponent and
+//! finalizes. The output is the VOPRF output of the whole key, so it is the same
+//! whichever servers answer, and no coalition of fewer than `threshold` servers can
+//! compute it or test password guesses offline.
+//!
+//! [`KeyRefresh`] proactively replaces the key shares without changing the key, so
+//! shares stolen before a refresh are useless after it. Each of at least `threshold`
+//! servers reshares its share with Feldman and broadcasts the commitments as a
+//! [`RefreshDealing`], which must commit to its old public key share. Every new
+//! shareholder checks its reshares against them before combining, and everyone computes
+//! the new commitments from the same dealings. The public key stays the same, so
+//! clients don't notice the refresh. As in [`refresh`](crate::refresh) every
+//! shareholder must combine the dealings of the same dealers.
+//!
+//! ```
+//! use ursa_sharing::{feldman::FeldmanVerifier, shamir::Share, threshold_oprf::*};
+//! # use ursa_sharing::{error::{DetectionError, SharingResult}, hash_to_curve::HashToScalar, oprf::OprfGroup};
+//! # fn example<S: HashToScalar, R: OprfGroup<S>>(verifier: &FeldmanVerifier<S, R>, shares: &[Share]) -> Result<(), DetectionError> {
+//! let mut rng = rand::thread_rng();
+//! let client = ThresholdOprfClient::new(verifier)?;
+//! let (blind, blinded) = client.blind(&mut rng, b"password")?;
+//! // Each server
+//! let partials = shares
+//!     .iter()
+//!     .map(|s| Ok(ThresholdOprfServer::new(verifier, s)?.blind_evaluate(&mut rng, &blinded)))
+//!     .collect::<SharingResult<Vec<_>>>()?;
+//! let output = client.finalize(b"password", &blind, &blinded, &partials)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    audit::{notify, Event},
+    error::{DetectionError, SharingError, SharingResult},
+    evaluate_commitments,
+    feldman::{FeldmanVerifier, Scheme as FeldmanScheme},
+    field::lagrange_coefficients,
+    hash_to_curve::HashToScalar,
+    oprf::{blind, finalize, generate_proof, verify_proof, Blind, Mode, OprfGroup, Proof},
+    refresh::Reshare,
+    shamir::{lagrange_interpolate_points, Scheme as ShamirScheme, Share},
+    Field, Group, ShareVerifier, Strictness,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// A server holding one share of the OPRF key
+pub struct ThresholdOprfServer<S: HashToScalar, R: OprfGroup<S>> {
+    identifier: u32,
+    secret: S,
+    public: R,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> ThresholdOprfServer<S, R> {
+    /// Create the server of `share`, checked against the key's commitments
+    pub fn new(verifier: &FeldmanVerifier<S, R>, share: &Share) -> SharingResult<Self> {
+        check_generator(verifier)?;
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        verifier.verify_share(share)?;
+        let secret = S::from_bytes(&share.value)?;
+        Ok(Self {
+            identifier: share.identifier,
+            public: mul(&R::generator(), &secret),
+            secret,
+        })
+    }
+
+    /// The identifier of the share
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// The public key share `g^{k_i}` clients verify evaluations with
+    pub fn public_key_share(&self) -> &R {
+        &self.public
+    }
+
+    /// Evaluate a client's blinded element with the share and prove it
+    pub fn blind_evaluate(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        blinded: &R,
+    ) -> PartialEvaluation<S, R> {
+        let evaluated = mul(blinded, &self.secret);
+        let proof = generate_proof(
+            rng,
+            Mode::Voprf,
+            &self.secret,
+            &self.public,
+            core::slice::from_ref(blinded),
+            core::slice::from_ref(&evaluated),
+        );
+        PartialEvaluation {
+            identifier: self.identifier,
+            evaluated,
+            proof,
+        }
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Debug for ThresholdOprfServer<S, R> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "ThresholdOprfServer {{ identifier: {} }}",
+            self.identifier
+        )
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Drop for ThresholdOprfServer<S, R> {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The client of a threshold OPRF
+#[derive(Debug)]
+pub struct ThresholdOprfClient<'a, S: HashToScalar, R: OprfGroup<S>> {
+    verifier: &'a FeldmanVerifier<S, R>,
+}
+
+impl<'a, S: HashToScalar, R: OprfGroup<S>> ThresholdOprfClient<'a, S, R> {
+    /// Create a client for the key with these commitments. The threshold is the number
+    /// of commitments
+    pub fn new(verifier: &'a FeldmanVerifier<S, R>) -> SharingResult<Self> {
+        check_generator(verifier)?;
+        Ok(Self { verifier })
+    }
+
+    /// The number of partial evaluations the output needs
+    pub fn threshold(&self) -> usize {
+        self.verifier.commitments.len()
+    }
+
+    /// The public key of the whole key, the same as a VOPRF server's
+    pub fn public_key(&self) -> &R {
+        &self.verifier.commitments[0].value
+    }
+
+    /// Blind `input`, returning the blind to keep and the blinded element to send to
+    /// every server. Fails with `OprfInvalidInput` if the input hashes to the identity
+    pub fn blind(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        input: &[u8],
+    ) -> SharingResult<(Blind<S>, R)> {
+        blind::<S, R>(rng, Mode::Voprf, input)
+    }
+
+    /// Check one server's evaluation of `blinded` against its public key share.
+    /// Fails with `OprfPartialEvaluationInvalid` naming the server
+    pub fn verify_partial(
+        &self,
+        blinded: &R,
+        partial: &PartialEvaluation<S, R>,
+    ) -> SharingResult<()> {
+        let invalid = SharingError::OprfPartialEvaluationInvalid(partial.identifier);
+        if partial.evaluated.is_zero() {
+            return Err(invalid);
+        }
+        let key_share = evaluate_commitments(&self.verifier.commitments, partial.identifier);
+        verify_proof(
+            Mode::Voprf,
+            &key_share,
+            core::slice::from_ref(blinded),
+            core::slice::from_ref(&partial.evaluated),
+            &partial.proof,
+        )
+        .map_err(|_| invalid)
+    }
+
+    /// Check the servers' evaluations, combine the first `threshold` of them and return
+    /// the PRF output of `input`. Fails with `DetectionError::Cheaters` naming every
+    /// server whose evaluation is invalid, which can be left out to try again
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        blind: &Blind<S>,
+        blinded: &R,
+        partials: &[PartialEvaluation<S, R>],
+    ) -> Result<Vec<u8>, DetectionError> {
+        let cheaters = partials
+            .iter()
+            .filter(|p| self.verify_partial(blinded, p).is_err())
+            .map(|p| p.identifier)
+            .collect::<Vec<u32>>();
+        if !cheaters.is_empty() {
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        let mut identifiers = BTreeSet::new();
+        let points = partials
+            .iter()
+            .filter(|p| identifiers.insert(p.identifier))
+            .take(self.threshold())
+            .map(|p| (p.identifier, copy(&p.evaluated)))
+            .collect::<Vec<_>>();
+        if points.len() < self.threshold() {
+            return Err(SharingError::ShareMinThreshold.into());
+        }
+        let evaluated = lagrange_interpolate_points::<S, R>(&points)?;
+        Ok(finalize(input, None, blind, &evaluated)?)
+    }
+}
+
+/// One server's evaluation of a blinded element with its proof
+#[derive(Debug)]
+pub struct PartialEvaluation<S: HashToScalar, R: OprfGroup<S>> {
+    identifier: u32,
+    evaluated: R,
+    proof: Proof<S>,
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> PartialEvaluation<S, R> {
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// The size of an encoded partial evaluation
+    pub fn size() -> usize {
+        4 + R::Size::to_usize() + 2 * S::Size::to_usize()
+    }
+
+    /// The identifier followed by the evaluated element and the proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(Self::size());
+        o.extend_from_slice(&self.identifier.to_be_bytes());
+        o.extend_from_slice(self.evaluated.to_bytes().as_ref());
+        o.extend_from_slice(&self.proof.to_bytes());
+        o
+    }
+
+    /// Convert a byte array to a partial evaluation using the acceptance rules of
+    /// `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        if value.len() != Self::size() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&value[..4]);
+        let identifier = u32::from_be_bytes(identifier);
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let r_size = R::Size::to_usize();
+        Ok(Self {
+            identifier,
+            evaluated: R::from_bytes_with(&value[4..4 + r_size], strictness)?,
+            proof: Proof::try_from(&value[4 + r_size..])?,
+        })
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> Clone for PartialEvaluation<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            identifier: self.identifier,
+            evaluated: copy(&self.evaluated),
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+impl<S: HashToScalar, R: OprfGroup<S>> TryFrom<&[u8]> for PartialEvaluation<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// A dealer's public commitments to the reshares of its key share
+#[derive(Debug)]
+pub struct RefreshDealing<S: Field, R: Group<S>> {
+    dealer: u32,
+    verifier: FeldmanVerifier<S, R>,
+}
+
+impl<S: Field, R: Group<S>> RefreshDealing<S, R> {
+    /// The identifier of the key share that was reshared
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    /// The commitments to the reshares
+    pub fn verifier(&self) -> &FeldmanVerifier<S, R> {
+        &self.verifier
+    }
+
+    /// The dealer followed by the commitments
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.dealer.to_be_bytes().to_vec();
+        o.extend_from_slice(&self.verifier.to_bytes());
+        o
+    }
+
+    /// Convert a byte array to a dealing using the acceptance rules of `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        if value.len() < 4 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut dealer = [0u8; 4];
+        dealer.copy_from_slice(&value[..4]);
+        Ok(Self {
+            dealer: u32::from_be_bytes(dealer),
+            verifier: FeldmanVerifier::from_bytes_with(&value[4..], strictness)?,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for RefreshDealing<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            dealer: self.dealer,
+            verifier: FeldmanVerifier {
+                g: copy(&self.verifier.g),
+                commitments: self.verifier.commitments.clone(),
+            },
+        }
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for RefreshDealing<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// Proactively refreshes the shares of an OPRF key for a set of shareholders,
+/// keeping the threshold and the public key
+#[derive(Debug)]
+pub struct KeyRefresh<'a, S: Field, R: Group<S>> {
+    verifier: &'a FeldmanVerifier<S, R>,
+    scheme: FeldmanScheme,
+    identifiers: Vec<u32>,
+}
+
+impl<'a, S: Field, R: Group<S>> KeyRefresh<'a, S, R> {
+    /// Refresh the shares of the key with these commitments for the new shareholders
+    /// `identifiers`, at least `threshold` of them
+    pub fn new(verifier: &'a FeldmanVerifier<S, R>, identifiers: &[u32]) -> SharingResult<Self> {
+        let threshold = verifier.commitments.len();
+        let scheme = FeldmanScheme::new(threshold, identifiers.len())?;
+        ShamirScheme::new(threshold, identifiers.len())?.check_identifiers(identifiers)?;
+        Ok(Self {
+            verifier,
+            scheme,
+            identifiers: identifiers.to_vec(),
+        })
+    }
+
+    /// The identifiers of the new shares
+    pub fn identifiers(&self) -> &[u32] {
+        self.identifiers.as_slice()
+    }
+
+    /// Reshare a key share, run by each dealer. Returns the dealing to broadcast and one
+    /// reshare for each new identifier, to send privately
+    pub fn deal(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+    ) -> SharingResult<(RefreshDealing<S, R>, Vec<Reshare>)> {
+        if share.identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        self.verifier.verify_share(share)?;
+        let mut value = S::from_bytes(&share.value)?;
+        let split = self.scheme.split_secret_with_identifiers(
+            rng,
+            &value,
+            &self.identifiers,
+            Some(copy(&self.verifier.g)),
+        );
+        value.zeroize();
+        let (verifier, shares) = split?;
+        notify(Event::Refresh {
+            threshold: verifier.commitments.len(),
+            limit: self.identifiers.len(),
+            identifiers: self.identifiers.as_slice(),
+        });
+        let dealer = share.identifier;
+        Ok((
+            RefreshDealing { dealer, verifier },
+            shares
+                .into_iter()
+                .map(|share| Reshare { dealer, share })
+                .collect(),
+        ))
+    }
+
+    /// Compute the new share with identifier `receiver` from the dealings of at least
+    /// `threshold` dealers and the reshares they sent it, run by each new shareholder.
+    /// Fails with `DetectionError::Cheaters` naming every dealer whose dealing doesn't
+    /// commit to its key share or whose reshare is missing or doesn't match its dealing
+    pub fn combine(
+        &self,
+        receiver: u32,
+        dealings: &[RefreshDealing<S, R>],
+        reshares: &[Reshare],
+    ) -> Result<Share, DetectionError> {
+        if !self.identifiers.contains(&receiver) {
+            return Err(SharingError::ShareInvalidIdentifier.into());
+        }
+        self.check_dealers(dealings)?;
+        let mut cheaters = Vec::new();
+        let mut values = Vec::with_capacity(dealings.len());
+        for dealing in dealings {
+            let reshare = reshares
+                .iter()
+                .find(|r| r.dealer == dealing.dealer && r.share.identifier == receiver);
+            match reshare {
+                Some(r)
+                    if self.check_dealing(dealing) && dealing.verifier.verify(&r.share).is_ok() =>
+                {
+                    values.push(S::from_bytes(&r.share.value)?)
+                }
+                _ => cheaters.push(dealing.dealer),
+            }
+        }
+        if !cheaters.is_empty() {
+            values.iter_mut().for_each(Zeroize::zeroize);
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        let coefficients = self.coefficients(dealings)?;
+        let mut value = S::zero();
+        for (v, c) in values.iter_mut().zip(coefficients.iter()) {
+            v.scalar_mul_assign(c);
+            value.add_assign(v);
+            v.zeroize();
+        }
+        let share = Share {
+            identifier: receiver,
+            value: value.to_bytes().to_vec(),
+        };
+        value.zeroize();
+        Ok(share)
+    }
+
+    /// The commitments of the new shares, computed from the same dealings every
+    /// shareholder combined. Fails with `DetectionError::Cheaters` naming every dealer
+    /// whose dealing doesn't commit to its key share
+    pub fn verifier(
+        &self,
+        dealings: &[RefreshDealing<S, R>],
+    ) -> Result<FeldmanVerifier<S, R>, DetectionError> {
+        self.check_dealers(dealings)?;
+        let cheaters = dealings
+            .iter()
+            .filter(|d| !self.check_dealing(d))
+            .map(|d| d.dealer)
+            .collect::<Vec<u32>>();
+        if !cheaters.is_empty() {
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        let coefficients = self.coefficients(dealings)?;
+        let commitments = (0..self.verifier.commitments.len())
+            .map(|j| {
+                let mut value = R::zero();
+                for (dealing, c) in dealings.iter().zip(coefficients.iter()) {
+                    value.add_assign(&mul(&dealing.verifier.commitments[j].value, c));
+                }
+                ShareVerifier {
+                    value,
+                    phantom: PhantomData,
+                }
+            })
+            .collect();
+        Ok(FeldmanVerifier {
+            g: copy(&self.verifier.g),
+            commitments,
+        })
+    }
+
+    /// At least `threshold` distinct dealers
+    fn check_dealers(&self, dealings: &[RefreshDealing<S, R>]) -> SharingResult<()> {
+        if dealings.len() < self.verifier.commitments.len() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let mut dealers = BTreeSet::new();
+        for dealing in dealings {
+            if dealing.dealer == 0 {
+                return Err(SharingError::ShareInvalidIdentifier);
+            }
+            if !dealers.insert(dealing.dealer) {
+                return Err(SharingError::ShareDuplicateIdentifier);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a dealing reshares its dealer's key share with the same generator
+    /// and threshold
+    fn check_dealing(&self, dealing: &RefreshDealing<S, R>) -> bool {
+        let commitments = &dealing.verifier.commitments;
+        commitments.len() == self.verifier.commitments.len()
+            && dealing.verifier.g.to_bytes() == self.verifier.g.to_bytes()
+            && commitments[0].value.to_bytes()
+                == evaluate_commitments(&self.verifier.commitments, dealing.dealer).to_bytes()
+    }
+
+    fn coefficients(&self, dealings: &[RefreshDealing<S, R>]) -> SharingResult<Vec<S>> {
+        let x_coordinates = dealings
+            .iter()
+            .map(|d| S::from_usize(d.dealer as usize))
+            .collect::<Vec<S>>();
+        lagrange_coefficients(&x_coordinates)
+    }
+}
+
+/// The commitments must use the suite's generator, which the proofs are made with
+fn check_generator<S: HashToScalar, R: OprfGroup<S>>(
+    verifier: &FeldmanVerifier<S, R>,
+) -> SharingResult<()> {
+    if verifier.commitments.is_empty() {
+        return Err(SharingError::ShareMinThreshold);
+    }
+    if verifier.g.to_bytes() != R::generator().to_bytes() {
+        return Err(SharingError::OprfInvalidGenerator);
+    }
+    Ok(())
+}
+
+fn copy<S: Field, R: Group<S>>(p: &R) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
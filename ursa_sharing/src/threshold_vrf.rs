@@ -0,0 +1,368 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Threshold verifiable random functions.
+//!
+//! The VRF key `sk` is shared with Feldman, or generated without a dealer by the DKG,
+//! and its output on an input `x` is `SHA-512(x, H(x)^sk)` with `H` the group's hash to
+//! curve suite. Each node evaluates `H(x)^{s_i}` with its share and proves with a
+//! [`DleqProof`] that it used the share of its public key share `g^{s_i}`, computed by
+//! anyone from the Feldman commitments. Any `threshold` valid [`PartialEvaluation`]s
+//! interpolate in the exponent to `H(x)^sk`, so the output is the same whichever nodes
+//! answer, and nobody learns it before `threshold` nodes have evaluated the input. This
+//! gives committees a randomness beacon or a leader election no minority can bias.
+//!
+//! The partial evaluations that were combined are the [`VrfProof`] of the output: anyone
+//! holding the commitments checks it with [`ThresholdVrf::verify`], which is the only way
+//! to check an output of a group without a pairing. Messages should be parsed with
+//! `Strictness::Strict` so points outside the prime order group are rejected.
+//!
+//! ```
+//! use ursa_sharing::{feldman::FeldmanVerifier, shamir::Share, threshold_vrf::ThresholdVrf};
+//! # use ursa_sharing::{error::{DetectionError, SharingResult}, hash_to_curve::HashToCurve, Field};
+//! # fn example<S: Field, R: HashToCurve<S>>(verifier: &FeldmanVerifier<S, R>, shares: &[Share]) -> Result<(), DetectionError> {
+//! let vrf = ThresholdVrf::new(verifier)?;
+//! // Each node
+//! let partials = shares
+//!     .iter()
+//!     .map(|s| vrf.evaluate(&mut rand::thread_rng(), s, b"round 7"))
+//!     .collect::<SharingResult<Vec<_>>>()?;
+//! // Aggregator
+//! let (output, proof) = vrf.combine(b"round 7", &partials)?;
+//! // Anyone
+//! assert_eq!(vrf.verify(b"round 7", &proof)?, output);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    dleq::DleqProof,
+    error::{DetectionError, SharingError, SharingResult},
+    evaluate_commitments,
+    feldman::FeldmanVerifier,
+    hash_to_curve::HashToCurve,
+    shamir::{lagrange_interpolate_points, Share},
+    transcript::Transcript,
+    Field, Group, Strictness,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::convert::TryFrom;
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+/// The prefix of the domain separation tag that inputs are hashed to the group with,
+/// followed by the suite of the group
+pub const DST_PREFIX: &[u8] = b"URSA-THRESHOLD-VRF-V01-CS01-with-";
+
+const TRANSCRIPT_LABEL: &[u8] = b"ursa threshold vrf v1";
+const OUTPUT_LABEL: &[u8] = b"ursa threshold vrf v1 output";
+
+/// The VRF of a key shared with Feldman
+#[derive(Debug)]
+pub struct ThresholdVrf<'a, S: Field, R: HashToCurve<S>> {
+    verifier: &'a FeldmanVerifier<S, R>,
+}
+
+impl<'a, S: Field, R: HashToCurve<S>> ThresholdVrf<'a, S, R> {
+    /// The VRF of the key with these commitments. The threshold is the number of
+    /// commitments
+    pub fn new(verifier: &'a FeldmanVerifier<S, R>) -> SharingResult<Self> {
+        if verifier.commitments.is_empty() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        Ok(Self { verifier })
+    }
+
+    /// The number of partial evaluations an output needs
+    pub fn threshold(&self) -> usize {
+        self.verifier.commitments.len()
+    }
+
+    /// The VRF public key `g^sk`
+    pub fn public_key(&self) -> &R {
+        &self.verifier.commitments[0].value
+    }
+
+    /// The public key share `g^{s_i}` of share `identifier`
+    pub fn key_share(&self, identifier: u32) -> SharingResult<R> {
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(evaluate_commitments(&self.verifier.commitments, identifier))
+    }
+
+    /// Evaluate `input` with a share of the key and prove it
+    pub fn evaluate(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        share: &Share,
+        input: &[u8],
+    ) -> SharingResult<PartialEvaluation<S, R>> {
+        if share.identifier() == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let mut value = S::from_bytes(share.value())?;
+        if !value.is_valid() {
+            value.zeroize();
+            return Err(SharingError::ShareInvalidValue);
+        }
+        let hashed = hash_input::<S, R>(input);
+        let point = mul(&hashed, &value);
+        let key_share = mul(&self.verifier.g, &value);
+        let mut transcript = self.transcript(share.identifier(), input);
+        let proof = DleqProof::prove(
+            rng,
+            &mut transcript,
+            &value,
+            (&self.verifier.g, &key_share),
+            (&hashed, &point),
+        );
+        value.zeroize();
+        Ok(PartialEvaluation {
+            identifier: share.identifier(),
+            point,
+            proof: proof?,
+        })
+    }
+
+    /// Check a partial evaluation of `input` against its node's public key share.
+    /// Fails with `VrfPartialEvaluationInvalid` naming the node
+    pub fn verify_partial(
+        &self,
+        input: &[u8],
+        partial: &PartialEvaluation<S, R>,
+    ) -> SharingResult<()> {
+        self.verify_hashed(&hash_input::<S, R>(input), input, partial)
+    }
+
+    /// Check the partial evaluations of `input` and combine the first `threshold` of
+    /// them into the output and its proof. Fails with `DetectionError::Cheaters` naming
+    /// every node whose evaluation is invalid, which can be left out to try again
+    pub fn combine(
+        &self,
+        input: &[u8],
+        partials: &[PartialEvaluation<S, R>],
+    ) -> Result<(Vec<u8>, VrfProof<S, R>), DetectionError> {
+        let hashed = hash_input::<S, R>(input);
+        let cheaters = partials
+            .iter()
+            .filter(|p| self.verify_hashed(&hashed, input, p).is_err())
+            .map(|p| p.identifier)
+            .collect::<Vec<u32>>();
+        if !cheaters.is_empty() {
+            return Err(DetectionError::Cheaters(cheaters));
+        }
+        let mut identifiers = BTreeSet::new();
+        let partials = partials
+            .iter()
+            .filter(|p| identifiers.insert(p.identifier))
+            .take(self.threshold())
+            .cloned()
+            .collect::<Vec<_>>();
+        let proof = VrfProof { partials };
+        let output = self.output(input, &proof)?;
+        Ok((output, proof))
+    }
+
+    /// Check a proof of an output of `input` and return the output
+    pub fn verify(&self, input: &[u8], proof: &VrfProof<S, R>) -> SharingResult<Vec<u8>> {
+        let hashed = hash_input::<S, R>(input);
+        for partial in &proof.partials {
+            self.verify_hashed(&hashed, input, partial)
+                .map_err(|_| SharingError::VrfProofInvalid)?;
+        }
+        self.output(input, proof)
+    }
+
+    /// The output of checked partial evaluations with distinct identifiers
+    fn output(&self, input: &[u8], proof: &VrfProof<S, R>) -> SharingResult<Vec<u8>> {
+        if proof.partials.len() < self.threshold() {
+            return Err(SharingError::ShareMinThreshold);
+        }
+        let points = proof
+            .partials
+            .iter()
+            .map(|p| (p.identifier, copy(&p.point)))
+            .collect::<Vec<_>>();
+        let gamma = lagrange_interpolate_points::<S, R>(&points)?;
+        Ok(output(input, &gamma))
+    }
+
+    fn verify_hashed(
+        &self,
+        hashed: &R,
+        input: &[u8],
+        partial: &PartialEvaluation<S, R>,
+    ) -> SharingResult<()> {
+        let invalid = SharingError::VrfPartialEvaluationInvalid(partial.identifier);
+        if partial.point.is_zero() {
+            return Err(invalid);
+        }
+        let key_share = self.key_share(partial.identifier)?;
+        let mut transcript = self.transcript(partial.identifier, input);
+        partial
+            .proof
+            .verify(
+                &mut transcript,
+                (&self.verifier.g, &key_share),
+                (hashed, &partial.point),
+            )
+            .map_err(|_| invalid)
+    }
+
+    fn transcript(&self, identifier: u32, input: &[u8]) -> Transcript {
+        let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+        transcript.append_point(b"generator", &self.verifier.g);
+        transcript.append_point(b"public key", self.public_key());
+        transcript.append_u64(b"identifier", identifier as u64);
+        transcript.append_message(b"input", input);
+        transcript
+    }
+}
+
+/// One node's evaluation `H(x)^{s_i}` with its proof
+#[derive(Debug)]
+pub struct PartialEvaluation<S: Field, R: Group<S>> {
+    identifier: u32,
+    point: R,
+    proof: DleqProof<S>,
+}
+
+impl<S: Field, R: Group<S>> PartialEvaluation<S, R> {
+    /// Get the identifier
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// The size of an encoded partial evaluation
+    pub fn size() -> usize {
+        4 + R::Size::to_usize() + DleqProof::<S>::size()
+    }
+
+    /// The identifier followed by the point and the proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = Vec::with_capacity(Self::size());
+        o.extend_from_slice(&self.identifier.to_be_bytes());
+        o.extend_from_slice(self.point.to_bytes().as_ref());
+        self.proof.append_bytes(&mut o);
+        o
+    }
+
+    /// Convert a byte array to a partial evaluation using the acceptance rules of
+    /// `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        if value.len() != Self::size() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut identifier = [0u8; 4];
+        identifier.copy_from_slice(&value[..4]);
+        let identifier = u32::from_be_bytes(identifier);
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        let r_size = R::Size::to_usize();
+        Ok(Self {
+            identifier,
+            point: R::from_bytes_with(&value[4..4 + r_size], strictness)?,
+            proof: DleqProof::from_bytes_with(&value[4 + r_size..], strictness)?,
+        })
+    }
+}
+
+impl<S: Field, R: Group<S>> Clone for PartialEvaluation<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            identifier: self.identifier,
+            point: copy(&self.point),
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for PartialEvaluation<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// The partial evaluations an output was combined from
+#[derive(Clone, Debug)]
+pub struct VrfProof<S: Field, R: Group<S>> {
+    partials: Vec<PartialEvaluation<S, R>>,
+}
+
+impl<S: Field, R: Group<S>> VrfProof<S, R> {
+    /// The partial evaluations
+    pub fn partials(&self) -> &[PartialEvaluation<S, R>] {
+        &self.partials
+    }
+
+    /// The encoded partial evaluations, one after the other
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.partials.iter().flat_map(|p| p.to_bytes()).collect()
+    }
+
+    /// Convert a byte array to a proof using the acceptance rules of `strictness`
+    pub fn from_bytes_with(value: &[u8], strictness: Strictness) -> SharingResult<Self> {
+        let size = PartialEvaluation::<S, R>::size();
+        let chunks = value.chunks_exact(size);
+        if value.is_empty() || !chunks.remainder().is_empty() {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let partials = chunks
+            .map(|p| PartialEvaluation::from_bytes_with(p, strictness))
+            .collect::<SharingResult<Vec<_>>>()?;
+        Ok(Self { partials })
+    }
+}
+
+impl<S: Field, R: Group<S>> TryFrom<&[u8]> for VrfProof<S, R> {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        Self::from_bytes_with(value, Strictness::Lenient)
+    }
+}
+
+/// Hash an input to the group with [`DST_PREFIX`] and the suite
+pub fn hash_input<S: Field, R: HashToCurve<S>>(input: &[u8]) -> R {
+    let mut dst = DST_PREFIX.to_vec();
+    dst.extend_from_slice(R::SUITE.as_bytes());
+    R::hash_to_curve(input, &dst)
+}
+
+/// `SHA-512(label || len(input) || input || gamma)`
+fn output<S: Field, R: Group<S>>(input: &[u8], gamma: &R) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    hasher.input(OUTPUT_LABEL);
+    hasher.input((input.len() as u64).to_be_bytes());
+    hasher.input(input);
+    hasher.input(gamma.to_bytes());
+    hasher.result().to_vec()
+}
+
+fn copy<S: Field, R: Group<S>>(p: &R) -> R {
+    let mut r = R::zero();
+    r.add_assign(p);
+    r
+}
+
+/// Compute `p^s` without consuming `p`
+fn mul<S: Field, R: Group<S>>(p: &R, s: &S) -> R {
+    let mut r = copy(p);
+    r.scalar_mul_assign(s);
+    r
+}
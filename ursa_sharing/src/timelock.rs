@@ -0,0 +1,304 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Encryption to the future epochs of a threshold committee over BLS12-381.
+//!
+//! A committee that shares a [`threshold_bls`](crate::threshold_bls) key, e.g. from the
+//! DKG, signs each epoch number when the epoch starts, like a randomness beacon. Data
+//! [`encrypt`]ed to an epoch only decrypts with that signature, the [`EpochKey`], so no
+//! one, including the sender, can read it before `threshold` nodes release their
+//! [`PartialSignature`]s for the epoch with [`release_share`]. This orders transactions
+//! before anyone can see them, or collects sealed bids that all open at the deadline.
+//!
+//! Epochs are identities of the [`ibe`](crate::ibe) scheme, so the epoch key is the
+//! identity key of [`epoch_identity`]. The ciphertext is also a commitment to the data:
+//! its randomness is hashed from the data and a random [`Opening`], which decryption
+//! checks, so it only opens to one plaintext. The sender keeps the opening, and can
+//! reveal it with the plaintext to open the ciphertext before the epoch, or to prove
+//! what it holds after; anyone checks an opening with [`Ciphertext::verify_opening`]
+//! with only the committee's public key. An opening must stay secret until then because
+//! it and a guess of the plaintext, like a bid, can be checked against the ciphertext.
+//!
+//! The ciphertext is encoded as
+//!
+//! ```text
+//! epoch (8, big endian) | identity-based ciphertext
+//! ```
+
+use super::{
+    bls12_381::{Bls12381G1, Bls12381G2, Bls12381Scalar},
+    error::{SharingError, SharingResult},
+    hash_to_curve::HashToScalar,
+    ibe::{self, IdentityKey},
+    shamir::Share,
+    threshold_bls::{PartialSignature, PublicKey},
+    Group,
+};
+use rand::{CryptoRng, RngCore};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use zeroize::Zeroize;
+
+/// The domain separation tag of the randomness of ciphertexts
+pub const DST: &[u8] = b"URSA_TIMELOCK_BLS12381_XMD:SHA-256_R_";
+
+const EPOCH_DOMAIN: &[u8] = b"ursa_sharing timelock epoch v1";
+const OPENING_BYTES: usize = 32;
+const G2_BYTES: usize = 96;
+
+/// The identity that data is encrypted to for `epoch`
+pub fn epoch_identity(epoch: u64) -> Vec<u8> {
+    let mut identity = EPOCH_DOMAIN.to_vec();
+    identity.extend_from_slice(&epoch.to_be_bytes());
+    identity
+}
+
+/// [`epoch_identity`] hashed, to check the parts of an epoch key with
+/// `PartialSignature::verify_hashed`
+pub fn hash_epoch(epoch: u64) -> Bls12381G2 {
+    ibe::hash_identity(&epoch_identity(epoch))
+}
+
+/// A node's part of the key of `epoch`, signed with its share of the committee's key
+/// once the epoch starts
+pub fn release_share(share: &Share, epoch: u64) -> SharingResult<PartialSignature> {
+    ibe::extract_share(share, &epoch_identity(epoch))
+}
+
+/// The key that decrypts the data encrypted to one epoch, the committee's signature on it
+pub struct EpochKey {
+    epoch: u64,
+    key: IdentityKey,
+}
+
+impl EpochKey {
+    /// Combine at least `threshold` parts of the key of `epoch` from distinct nodes.
+    /// Check the combined key with [`EpochKey::verify`] before it is published
+    pub fn combine(
+        epoch: u64,
+        threshold: usize,
+        parts: &[PartialSignature],
+    ) -> SharingResult<Self> {
+        Ok(Self {
+            epoch,
+            key: IdentityKey::combine(&epoch_identity(epoch), threshold, parts)?,
+        })
+    }
+
+    /// The epoch of the key
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Check that the key was released by the committee of `public_key`.
+    /// Fails with `SharingError::TimelockInvalidEpochKey` if it wasn't
+    pub fn verify(&self, public_key: &PublicKey) -> SharingResult<()> {
+        self.key
+            .verify(public_key)
+            .map_err(|_| SharingError::TimelockInvalidEpochKey)
+    }
+
+    /// Encode the epoch key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.epoch.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.key.key().to_bytes());
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for EpochKey {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != 8 + G2_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let epoch = read_epoch(value);
+        Ok(Self {
+            epoch,
+            key: IdentityKey::new(&epoch_identity(epoch), Bls12381G2::from_bytes(&value[8..])?),
+        })
+    }
+}
+
+impl Debug for EpochKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "EpochKey({}, ..)", self.epoch)
+    }
+}
+
+/// The secret that opens a ciphertext without the epoch key, cleared on drop
+pub struct Opening([u8; OPENING_BYTES]);
+
+impl Opening {
+    /// Encode the opening
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for Opening {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() != OPENING_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let mut opening = [0u8; OPENING_BYTES];
+        opening.copy_from_slice(value);
+        Ok(Self(opening))
+    }
+}
+
+impl Drop for Opening {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for Opening {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Opening(..)")
+    }
+}
+
+/// Data encrypted to an epoch
+#[derive(Clone, Debug)]
+pub struct Ciphertext {
+    epoch: u64,
+    inner: ibe::Ciphertext,
+}
+
+/// Encrypt `plaintext` to `epoch` of the committee of `public_key`.
+/// Returns the ciphertext and the opening the sender keeps
+pub fn encrypt(
+    rng: &mut (impl RngCore + CryptoRng),
+    public_key: &PublicKey,
+    epoch: u64,
+    plaintext: &[u8],
+) -> SharingResult<(Ciphertext, Opening)> {
+    let mut opening = Opening([0u8; OPENING_BYTES]);
+    rng.fill_bytes(&mut opening.0);
+    let ciphertext = seal(public_key, epoch, &opening, plaintext)?;
+    Ok((ciphertext, opening))
+}
+
+impl Ciphertext {
+    /// The epoch the data is encrypted to
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Decrypt the data with the key of its epoch.
+    /// Fails with `SharingError::TimelockWrongEpoch` if the key is for another epoch and
+    /// `SharingError::TimelockDecryptionFailed` if the ciphertext was modified or is for
+    /// another committee
+    pub fn decrypt(&self, key: &EpochKey) -> SharingResult<Vec<u8>> {
+        if key.epoch != self.epoch {
+            return Err(SharingError::TimelockWrongEpoch(key.epoch));
+        }
+        let mut sealed = key
+            .key
+            .decrypt(&self.inner)
+            .map_err(|_| SharingError::TimelockDecryptionFailed)?;
+        if sealed.len() < OPENING_BYTES {
+            sealed.zeroize();
+            return Err(SharingError::TimelockDecryptionFailed);
+        }
+        let plaintext = sealed[OPENING_BYTES..].to_vec();
+        let opening = Opening::try_from(&sealed[..OPENING_BYTES])?;
+        sealed.zeroize();
+        // The ciphertext must be the one the opening commits to
+        let mut r = randomness(self.epoch, &opening, &plaintext);
+        let mut u = Bls12381G1::generator();
+        u.scalar_mul_assign(&r);
+        r.zeroize();
+        if u.to_bytes() != self.inner.u.to_bytes() {
+            return Err(SharingError::TimelockDecryptionFailed);
+        }
+        Ok(plaintext)
+    }
+
+    /// Check that `opening` opens the ciphertext to `plaintext` under the committee of
+    /// `public_key`, without the epoch key.
+    /// Fails with `SharingError::TimelockInvalidOpening` if it doesn't
+    pub fn verify_opening(
+        &self,
+        public_key: &PublicKey,
+        opening: &Opening,
+        plaintext: &[u8],
+    ) -> SharingResult<()> {
+        let expected = seal(public_key, self.epoch, opening, plaintext)?;
+        if expected.to_bytes() != self.to_bytes() {
+            return Err(SharingError::TimelockInvalidOpening);
+        }
+        Ok(())
+    }
+
+    /// Encode the ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.epoch.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.inner.to_bytes());
+        out
+    }
+}
+
+impl TryFrom<&[u8]> for Ciphertext {
+    type Error = SharingError;
+
+    fn try_from(value: &[u8]) -> SharingResult<Self> {
+        if value.len() < 8 {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        Ok(Self {
+            epoch: read_epoch(value),
+            inner: ibe::Ciphertext::try_from(&value[8..])?,
+        })
+    }
+}
+
+/// The ciphertext of `opening | plaintext` with the randomness hashed from them
+fn seal(
+    public_key: &PublicKey,
+    epoch: u64,
+    opening: &Opening,
+    plaintext: &[u8],
+) -> SharingResult<Ciphertext> {
+    let mut r = randomness(epoch, opening, plaintext);
+    let mut sealed = opening.0.to_vec();
+    sealed.extend_from_slice(plaintext);
+    let inner = ibe::encrypt_with(&r, public_key, &epoch_identity(epoch), &sealed);
+    r.zeroize();
+    sealed.zeroize();
+    Ok(Ciphertext {
+        epoch,
+        inner: inner?,
+    })
+}
+
+fn randomness(epoch: u64, opening: &Opening, plaintext: &[u8]) -> Bls12381Scalar {
+    let mut msg = epoch.to_be_bytes().to_vec();
+    msg.extend_from_slice(&opening.0);
+    msg.extend_from_slice(plaintext);
+    let r = Bls12381Scalar::hash_to_scalar(&msg, DST);
+    msg.zeroize();
+    r
+}
+
+fn read_epoch(value: &[u8]) -> u64 {
+    let mut epoch = [0u8; 8];
+    epoch.copy_from_slice(&value[..8]);
+    u64::from_be_bytes(epoch)
+}
@@ -0,0 +1,115 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Transcripts for deriving Fiat-Shamir challenges in the style of
+//! [Merlin](https://merlin.cool).
+//!
+//! A transcript is started with a protocol label and the prover and verifier
+//! append the same labeled messages to it in the same order.
+//! Every message is framed with its label and length so two different
+//! sequences of messages never produce the same challenge, and the protocol
+//! label keeps a proof made for one protocol from verifying in another.
+//! Drawing a challenge also feeds it back into the transcript so later
+//! challenges depend on earlier ones.
+//!
+//! ```
+//! use ursa_sharing::transcript::Transcript;
+//!
+//! let mut prover = Transcript::new(b"example protocol");
+//! prover.append_message(b"commitment", b"some commitment");
+//! let mut challenge = [0u8; 16];
+//! prover.challenge_bytes(b"challenge", &mut challenge);
+//!
+//! let mut verifier = Transcript::new(b"example protocol");
+//! verifier.append_message(b"commitment", b"some commitment");
+//! let mut expected = [0u8; 16];
+//! verifier.challenge_bytes(b"challenge", &mut expected);
+//! assert_eq!(challenge, expected);
+//! ```
+
+use super::{error::SharingResult, Field, Group};
+use sha2::{Digest, Sha256};
+
+const VERSION: &[u8] = b"ursa_sharing transcript v1";
+const PROTOCOL: u8 = 1;
+const MESSAGE: u8 = 2;
+const CHALLENGE: u8 = 3;
+/// Challenges are truncated so they are always below the order of a 248-bit field
+const CHALLENGE_SIZE: usize = 31;
+
+/// A running transcript of a protocol
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// Start a transcript for the protocol named by `label`
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut t = Self {
+            hasher: Sha256::new(),
+        };
+        t.hasher.input(VERSION);
+        t.frame(PROTOCOL, label, label.len());
+        t
+    }
+
+    /// Append a message with its label
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.frame(MESSAGE, label, message.len());
+        self.hasher.input(message);
+    }
+
+    /// Append a number with its label
+    pub fn append_u64(&mut self, label: &'static [u8], value: u64) {
+        self.append_message(label, &value.to_be_bytes());
+    }
+
+    /// Append a field element with its label
+    pub fn append_scalar<S: Field>(&mut self, label: &'static [u8], value: &S) {
+        self.append_message(label, value.to_bytes().as_ref());
+    }
+
+    /// Append a group element with its label
+    pub fn append_point<S: Field, R: Group<S>>(&mut self, label: &'static [u8], value: &R) {
+        self.append_message(label, value.to_bytes().as_ref());
+    }
+
+    /// Fill `dest` with challenge bytes that depend on everything appended so far
+    pub fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.frame(CHALLENGE, label, dest.len());
+        let seed = self.hasher.clone().result();
+        for (i, chunk) in dest.chunks_mut(32).enumerate() {
+            let mut hasher = Sha256::new();
+            hasher.input(seed);
+            hasher.input((i as u32).to_be_bytes());
+            let block = hasher.result();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.hasher.input(seed);
+    }
+
+    /// Draw a challenge field element
+    pub fn challenge_scalar<S: Field>(&mut self, label: &'static [u8]) -> SharingResult<S> {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        self.challenge_bytes(label, &mut buf);
+        S::from_bytes(buf)
+    }
+
+    fn frame(&mut self, op: u8, label: &[u8], len: usize) {
+        self.hasher.input([op]);
+        self.hasher.input((label.len() as u32).to_be_bytes());
+        self.hasher.input(label);
+        self.hasher.input((len as u64).to_be_bytes());
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Deterministic test vectors for every scheme in this crate.
+//!
+//! All randomness is drawn from ChaCha20 seeded with the caller's seed so the
+//! same seed always gives the same vectors. Other implementations and auditors
+//! can check they accept the shares, commitments and proofs and recover the
+//! same secrets. Byte strings are hex encoded using the `to_bytes` format of
+//! each type.
+//!
+//! Run `cargo run -p ursa_sharing --features test_vectors --bin test_vectors -- <seed hex>`
+//! to print them.
+//!
+//! ```
+//! use ursa_sharing::vectors::generate;
+//!
+//! let seed = [7u8; 32];
+//! assert_eq!(generate(seed).unwrap(), generate(seed).unwrap());
+//! ```
+
+use super::{
+    backup::backup_secret_with_rng,
+    ed25519::{Ed25519Point, Ed25519Scalar, Scheme as Ed25519Scheme},
+    error::SharingResult,
+    escrow::Scheme as EscrowScheme,
+    feldman::Scheme as FeldmanVss,
+    pedersen::Scheme as PedersenVss,
+    recipient::RecipientSecretKey,
+    secp256k1::{dkg::Participant, Secp256k1Point, Secp256k1Scalar},
+    shamir::Scheme as ShamirScheme,
+    Field, Group,
+};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The version of the vector format
+pub const VECTORS_VERSION: u64 = 1;
+
+const THRESHOLD: usize = 2;
+const LIMIT: usize = 3;
+const CONTEXT: &[u8] = b"ursa_sharing test vectors";
+const PASSPHRASE: &[u8] = b"correct horse battery staple";
+
+/// Generate the test vectors for `seed` as a JSON document
+pub fn generate(seed: [u8; 32]) -> SharingResult<String> {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    let mut vectors = Vec::new();
+    group_vectors::<Secp256k1Scalar, Secp256k1Point>("secp256k1", &mut rng, &mut vectors)?;
+    group_vectors::<Ed25519Scalar, Ed25519Point>("ed25519", &mut rng, &mut vectors)?;
+    vectors.push(ed25519_keys(&mut rng)?);
+    vectors.push(recipient(&mut rng)?);
+    vectors.push(backup(&mut rng)?);
+    vectors.push(dkg(&mut rng)?);
+
+    let doc = Value::Object(vec![
+        ("version", Value::Number(VECTORS_VERSION)),
+        ("seed", Value::Hex(seed.to_vec())),
+        ("threshold", Value::Number(THRESHOLD as u64)),
+        ("limit", Value::Number(LIMIT as u64)),
+        ("context", Value::Hex(CONTEXT.to_vec())),
+        ("vectors", Value::List(vectors)),
+    ]);
+    let mut out = String::new();
+    doc.write(&mut out, 0);
+    out.push('\n');
+    Ok(out)
+}
+
+/// Vectors for the schemes that are generic over the field and group
+fn group_vectors<S: Field, R: Group<S>>(
+    group: &'static str,
+    rng: &mut (impl RngCore + CryptoRng),
+    vectors: &mut Vec<Value>,
+) -> SharingResult<()> {
+    let secret = S::random(rng);
+
+    let shares = ShamirScheme::new(THRESHOLD, LIMIT)?.split_secret(rng, &secret)?;
+    vectors.push(Value::Object(vec![
+        ("scheme", Value::Str("shamir")),
+        ("group", Value::Str(group)),
+        ("secret", Value::Hex(secret.to_bytes().to_vec())),
+        ("shares", hex_list(shares.iter().map(|s| s.to_bytes()))),
+    ]));
+
+    let feldman = FeldmanVss::new(THRESHOLD, LIMIT)?;
+    let (verifier, shares) = feldman.split_secret::<S, R>(rng, &secret, None)?;
+    let proof = feldman.prove_possession(rng, &shares[0], &verifier, CONTEXT)?;
+    vectors.push(Value::Object(vec![
+        ("scheme", Value::Str("feldman")),
+        ("group", Value::Str(group)),
+        ("secret", Value::Hex(secret.to_bytes().to_vec())),
+        ("verifier", Value::Hex(verifier.to_bytes())),
+        ("shares", hex_list(shares.iter().map(|s| s.to_bytes()))),
+        ("possession_proof_share", Value::Number(1)),
+        ("possession_proof", Value::Hex(proof.to_bytes())),
+    ]));
+
+    let pedersen = PedersenVss::new(THRESHOLD, LIMIT)?;
+    let result = pedersen.split_secret::<S, R>(rng, &secret, None, None)?;
+    let proof = pedersen.prove_possession(
+        rng,
+        &result.secret_shares[0],
+        &result.blinding_shares[0],
+        &result.verifier,
+        CONTEXT,
+    )?;
+    vectors.push(Value::Object(vec![
+        ("scheme", Value::Str("pedersen")),
+        ("group", Value::Str(group)),
+        ("secret", Value::Hex(secret.to_bytes().to_vec())),
+        ("blinding", Value::Hex(result.blinding.to_bytes().to_vec())),
+        ("verifier", Value::Hex(result.verifier.to_bytes())),
+        (
+            "secret_shares",
+            hex_list(result.secret_shares.iter().map(|s| s.to_bytes())),
+        ),
+        (
+            "blinding_shares",
+            hex_list(result.blinding_shares.iter().map(|s| s.to_bytes())),
+        ),
+        ("possession_proof_share", Value::Number(1)),
+        ("possession_proof", Value::Hex(proof.to_bytes())),
+    ]));
+
+    let escrow = EscrowScheme::new(THRESHOLD, LIMIT)?;
+    let h = R::random(rng);
+    let agent_keys = (0..LIMIT).map(|_| S::random(rng)).collect::<Vec<S>>();
+    let agents = agent_keys
+        .iter()
+        .map(|x| {
+            let mut y = R::zero();
+            y.add_assign(&h);
+            y.scalar_mul_assign(x);
+            y
+        })
+        .collect::<Vec<R>>();
+    let transcript = escrow.escrow(rng, &secret, None, &h, agents.as_slice())?;
+    let decrypted = (0..THRESHOLD)
+        .map(|i| escrow.decrypt_share(rng, &transcript, i as u32 + 1, &agent_keys[i]))
+        .collect::<SharingResult<Vec<_>>>()?;
+    let escrowed = escrow.recover(&transcript, decrypted.as_slice())?;
+    vectors.push(Value::Object(vec![
+        ("scheme", Value::Str("escrow")),
+        ("group", Value::Str(group)),
+        ("secret", Value::Hex(secret.to_bytes().to_vec())),
+        (
+            "agent_secret_keys",
+            hex_list(agent_keys.iter().map(|x| x.to_bytes().to_vec())),
+        ),
+        ("transcript", Value::Hex(transcript.to_bytes())),
+        (
+            "decrypted_shares",
+            hex_list(decrypted.iter().map(|s| s.to_bytes())),
+        ),
+        ("escrowed", Value::Hex(escrowed.to_bytes().to_vec())),
+    ]));
+    Ok(())
+}
+
+fn ed25519_keys(rng: &mut (impl RngCore + CryptoRng)) -> SharingResult<Value> {
+    let mut secret_key = [0u8; 32];
+    rng.fill_bytes(&mut secret_key);
+    let scheme = Ed25519Scheme::new(THRESHOLD, LIMIT)?;
+    let shares = scheme.split_secret_key(rng, secret_key)?;
+    let expanded = scheme.combine_shares(&shares[..THRESHOLD])?;
+    Ok(Value::Object(vec![
+        ("scheme", Value::Str("ed25519_key")),
+        ("secret_key", Value::Hex(secret_key.to_vec())),
+        ("public_key", Value::Hex(expanded.public_key().to_vec())),
+        ("shares", hex_list(shares.iter().map(|s| s.to_bytes()))),
+    ]))
+}
+
+fn recipient(rng: &mut (impl RngCore + CryptoRng)) -> SharingResult<Value> {
+    let secret = Secp256k1Scalar::random(rng);
+    let mut secret_keys = Vec::with_capacity(LIMIT);
+    let mut public_keys = Vec::with_capacity(LIMIT);
+    for _ in 0..LIMIT {
+        let mut sk = [0u8; 32];
+        rng.fill_bytes(&mut sk);
+        let key = RecipientSecretKey::X25519(x25519_dalek::StaticSecret::from(sk));
+        public_keys.push(key.public_key());
+        secret_keys.push(sk.to_vec());
+    }
+    let encrypted = ShamirScheme::new(THRESHOLD, LIMIT)?.split_encrypted(
+        rng,
+        &secret,
+        public_keys.as_slice(),
+    )?;
+    Ok(Value::Object(vec![
+        ("scheme", Value::Str("recipient_x25519")),
+        ("group", Value::Str("secp256k1")),
+        ("secret", Value::Hex(secret.to_bytes().to_vec())),
+        ("recipient_secret_keys", hex_list(secret_keys.into_iter())),
+        (
+            "encrypted_shares",
+            hex_list(encrypted.iter().map(|s| s.to_bytes())),
+        ),
+    ]))
+}
+
+fn backup(rng: &mut (impl RngCore + CryptoRng)) -> SharingResult<Value> {
+    let mut secret = [0u8; 32];
+    rng.fill_bytes(&mut secret);
+    let plain = backup_secret_with_rng(rng, secret, THRESHOLD, LIMIT, None)?;
+    let encrypted = backup_secret_with_rng(rng, secret, THRESHOLD, LIMIT, Some(PASSPHRASE))?;
+    Ok(Value::Object(vec![
+        ("scheme", Value::Str("backup")),
+        ("secret", Value::Hex(secret.to_vec())),
+        ("passphrase", Value::Hex(PASSPHRASE.to_vec())),
+        ("shares", hex_list(plain.iter().map(|s| s.to_bytes()))),
+        (
+            "encrypted_shares",
+            hex_list(encrypted.iter().map(|s| s.to_bytes())),
+        ),
+    ]))
+}
+
+fn dkg(rng: &mut (impl RngCore + CryptoRng)) -> SharingResult<Value> {
+    let mut participants = Vec::with_capacity(LIMIT);
+    let mut broadcasts = Vec::with_capacity(LIMIT);
+    let mut shares = Vec::new();
+    for id in 1..=LIMIT as u32 {
+        let (p, b, s) = Participant::new(rng, id, THRESHOLD, LIMIT)?;
+        participants.push(p);
+        broadcasts.push(b);
+        shares.extend(s);
+    }
+    for p in participants.iter_mut() {
+        let mine = shares
+            .iter()
+            .filter(|s| s.receiver() == p.id())
+            .cloned()
+            .collect::<Vec<_>>();
+        p.receive_round1(broadcasts.as_slice(), mine.as_slice())?;
+    }
+    let output = participants[0].finalize(&[], &[])?;
+    Ok(Value::Object(vec![
+        ("scheme", Value::Str("secp256k1_dkg")),
+        (
+            "round1_broadcasts",
+            hex_list(broadcasts.iter().map(|b| b.to_bytes())),
+        ),
+        (
+            "round1_shares",
+            hex_list(shares.iter().map(|s| s.to_bytes())),
+        ),
+        (
+            "transcript",
+            Value::Hex(participants[0].transcript().to_bytes()),
+        ),
+        (
+            "public_key",
+            Value::Hex(output.public_key().to_bytes().to_vec()),
+        ),
+    ]))
+}
+
+fn hex_list(items: impl Iterator<Item = Vec<u8>>) -> Value {
+    Value::List(items.map(Value::Hex).collect())
+}
+
+/// Just enough JSON for the vectors, keys are written in the given order
+enum Value {
+    Number(u64),
+    Str(&'static str),
+    Hex(Vec<u8>),
+    List(Vec<Value>),
+    Object(Vec<(&'static str, Value)>),
+}
+
+impl Value {
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Self::Number(n) => out.push_str(&n.to_string()),
+            Self::Str(s) => {
+                out.push('"');
+                out.push_str(s);
+                out.push('"');
+            }
+            Self::Hex(bytes) => {
+                out.push('"');
+                for b in bytes {
+                    out.push_str(&format!("{:02x}", b));
+                }
+                out.push('"');
+            }
+            Self::List(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    item.write(out, indent + 1);
+                }
+                if !items.is_empty() {
+                    newline(out, indent);
+                }
+                out.push(']');
+            }
+            Self::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    out.push('"');
+                    out.push_str(key);
+                    out.push_str("\": ");
+                    value.write(out, indent + 1);
+                }
+                if !fields.is_empty() {
+                    newline(out, indent);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
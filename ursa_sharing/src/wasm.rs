@@ -0,0 +1,154 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! JavaScript bindings for `wasm32-unknown-unknown`.
+//!
+//! Shares cross the boundary as `Uint8Array`s holding [`Share::to_bytes`] so they can be
+//! stored or encoded by the caller as is. Secrets are the byte encodings of the curve's
+//! scalars. Curves are named by strings, one of `"ed25519"`, `"ristretto25519"`,
+//! `"secp256k1"` or `"secp256r1"` when its feature is enabled.
+//! Randomness comes from [`SystemRng`], i.e. `crypto.getRandomValues`.
+//! The `ursa` crate builds them into a package with e.g.
+//! `wasm-pack build -- --features wasm,ursa_sharing/secp256k1`.
+//!
+//! ```js
+//! import { combineShares, splitSecret, randomSecret } from "ursa";
+//!
+//! const secret = randomSecret("secp256k1");
+//! const shares = splitSecret("secp256k1", secret, 2, 3);
+//! const recovered = combineShares("secp256k1", 2, [shares[0], shares[2]]);
+//! ```
+//!
+//! Byte strings of any length, like a wallet seed, are split with [`split_bytes`].
+//! Errors are thrown as JavaScript `Error`s with the message of the [`SharingError`].
+//!
+//! [`SharingError`]: crate::error::SharingError
+
+// Without a curve only the byte string bindings are used
+#![cfg_attr(
+    not(any(
+        feature = "ed25519",
+        feature = "ristretto",
+        feature = "secp256k1",
+        feature = "secp256r1"
+    )),
+    allow(dead_code)
+)]
+
+use crate::{entropy::SystemRng, gf256, shamir::Scheme, shamir::Share, Field};
+use js_sys::Uint8Array;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+/// Generate a random secret scalar for `curve`
+#[wasm_bindgen(js_name = randomSecret)]
+pub fn random_secret(curve: &str) -> Result<Vec<u8>, JsError> {
+    with_curve!(curve, random(), unsupported)
+}
+
+/// Split a secret scalar of `curve` into `limit` shares, `threshold` of which recover it
+#[wasm_bindgen(js_name = splitSecret)]
+pub fn split_secret(
+    curve: &str,
+    secret: &[u8],
+    threshold: usize,
+    limit: usize,
+) -> Result<Vec<Uint8Array>, JsError> {
+    with_curve!(curve, split(secret, threshold, limit), unsupported)
+}
+
+/// Recover a secret scalar of `curve` from at least `threshold` shares from [`split_secret`]
+#[wasm_bindgen(js_name = combineShares)]
+pub fn combine_shares(
+    curve: &str,
+    threshold: usize,
+    shares: Vec<Uint8Array>,
+) -> Result<Vec<u8>, JsError> {
+    with_curve!(curve, combine(threshold, &shares), unsupported)
+}
+
+/// The identifier of a share from [`split_secret`]
+#[wasm_bindgen(js_name = shareIdentifier)]
+pub fn share_identifier(share: &[u8]) -> Result<u32, JsError> {
+    Ok(Share::try_from(share)?.identifier())
+}
+
+/// Split a byte string into `limit` shares, `threshold` of which recover it
+#[wasm_bindgen(js_name = splitBytes)]
+pub fn split_bytes(
+    secret: &[u8],
+    threshold: usize,
+    limit: usize,
+) -> Result<Vec<Uint8Array>, JsError> {
+    let shares = gf256::Scheme::new(threshold, limit)?.split_secret(&mut SystemRng, secret)?;
+    Ok(shares.iter().map(|s| to_array(s.to_bytes())).collect())
+}
+
+/// Recover a byte string from at least `threshold` shares from [`split_bytes`]
+#[wasm_bindgen(js_name = combineBytes)]
+pub fn combine_bytes(threshold: usize, shares: Vec<Uint8Array>) -> Result<Vec<u8>, JsError> {
+    let shares = shares
+        .iter()
+        .map(|s| from_array(s, |b| gf256::Share::try_from(b)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(gf256::Scheme::new(threshold, threshold)?.combine_shares(&shares)?)
+}
+
+fn random<S: Field>() -> Result<Vec<u8>, JsError> {
+    let mut secret = S::random(&mut SystemRng);
+    let bytes = secret.to_bytes().to_vec();
+    secret.zeroize();
+    Ok(bytes)
+}
+
+fn split<S: Field>(
+    secret: &[u8],
+    threshold: usize,
+    limit: usize,
+) -> Result<Vec<Uint8Array>, JsError> {
+    let mut secret = S::from_bytes(secret)?;
+    let shares = Scheme::new(threshold, limit)?.split_secret(&mut SystemRng, &secret);
+    secret.zeroize();
+    Ok(shares?.iter().map(|s| to_array(s.to_bytes())).collect())
+}
+
+fn combine<S: Field>(threshold: usize, shares: &[Uint8Array]) -> Result<Vec<u8>, JsError> {
+    let shares = shares
+        .iter()
+        .map(|s| from_array(s, |b| Share::try_from(b)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut secret = Scheme::new(threshold, threshold)?.combine_shares::<S, S>(&shares)?;
+    let bytes = secret.to_bytes().to_vec();
+    secret.zeroize();
+    Ok(bytes)
+}
+
+fn unsupported(curve: &str) -> JsError {
+    JsError::new(&format!("Curve {} is not supported", curve))
+}
+
+/// Copy into a JavaScript array, clearing the Rust copy
+fn to_array(mut bytes: Vec<u8>) -> Uint8Array {
+    let array = Uint8Array::from(bytes.as_slice());
+    bytes.zeroize();
+    array
+}
+
+/// Decode a copy of a JavaScript array, clearing the copy
+fn from_array<T>(array: &Uint8Array, decode: impl FnOnce(&[u8]) -> T) -> T {
+    let mut bytes = array.to_vec();
+    let decoded = decode(&bytes);
+    bytes.zeroize();
+    decoded
+}
@@ -0,0 +1,272 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Passphrase-wrapped shares for storage at rest.
+//!
+//! Shares written to paper backups or kept on user devices should not be readable by
+//! whoever finds them. [`wrap_share`] encrypts a share with ChaCha20-Poly1305 under a key
+//! derived from a passphrase with Argon2id and a random salt, and [`WrappedShare::unwrap`]
+//! reverses it. Each share gets its own salt, so shares wrapped with the same passphrase
+//! don't share a key.
+//!
+//! The envelope is self-describing:
+//! `version | memory | iterations | parallelism | salt | nonce | identifier | ciphertext`,
+//! so a share can be unwrapped with only the passphrase even after the defaults change.
+//! Everything before the ciphertext is authenticated as associated data. The identifier
+//! stays in the clear so duplicate shares are caught before any key is derived.
+//!
+//! Since the Argon2id parameters are read from the envelope, unwrapping refuses
+//! parameters above [`MAX_MEMORY_KIB`], [`MAX_ITERATIONS`] and [`MAX_PARALLELISM`] so a
+//! forged envelope can't make a device spend unbounded memory or time.
+//!
+//! Use `shamir::Scheme::split_wrapped` and `shamir::Scheme::combine_wrapped` to wrap and
+//! unwrap as part of splitting and combining.
+
+use super::{
+    error::{SharingError, SharingResult},
+    shamir::Share,
+};
+use alloc::vec::Vec;
+use argon2::{Argon2, Params};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::convert::TryFrom;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The current wrapped share format version
+pub const VERSION: u8 = 1;
+/// The most memory in KiB an envelope may ask Argon2id to use, 1 GiB
+pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+/// The most Argon2id passes an envelope may ask for
+pub const MAX_ITERATIONS: u32 = 64;
+/// The most Argon2id lanes an envelope may ask for
+pub const MAX_PARALLELISM: u32 = 64;
+
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const KEY_BYTES: usize = 32;
+/// version, memory, iterations, parallelism, salt, nonce, identifier
+const HEADER_BYTES: usize = 1 + 12 + SALT_BYTES + NONCE_BYTES + 4;
+
+/// The Argon2id cost parameters used to derive the wrapping key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WrapParams {
+    /// Memory in KiB
+    pub memory_kib: u32,
+    /// Number of passes over the memory
+    pub iterations: u32,
+    /// Number of lanes
+    pub parallelism: u32,
+}
+
+impl Default for WrapParams {
+    /// The Argon2id defaults recommended by OWASP, 19 MiB and 2 passes over 1 lane
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl WrapParams {
+    fn argon2(&self) -> SharingResult<Argon2<'static>> {
+        if self.memory_kib > MAX_MEMORY_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(SharingError::ShareWrapParamsInvalid);
+        }
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_BYTES),
+        )
+        .map_err(|_| SharingError::ShareWrapParamsInvalid)?;
+        Ok(Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            params,
+        ))
+    }
+
+    fn derive_cipher(&self, passphrase: &[u8], salt: &[u8]) -> SharingResult<ChaCha20Poly1305> {
+        let mut key = [0u8; KEY_BYTES];
+        self.argon2()?
+            .hash_password_into(passphrase, salt, &mut key)
+            .map_err(|_| SharingError::ShareWrapParamsInvalid)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        key.zeroize();
+        Ok(cipher)
+    }
+}
+
+/// A share encrypted under a passphrase
+#[derive(Clone, Debug)]
+pub struct WrappedShare {
+    params: WrapParams,
+    salt: [u8; SALT_BYTES],
+    nonce: [u8; NONCE_BYTES],
+    identifier: u32,
+    ciphertext: Vec<u8>,
+}
+
+impl WrappedShare {
+    /// Get the identifier of the wrapped share
+    pub fn identifier(&self) -> u32 {
+        self.identifier
+    }
+
+    /// Get the Argon2id parameters the share was wrapped with
+    pub fn params(&self) -> WrapParams {
+        self.params
+    }
+
+    /// Decrypt the share with the passphrase it was wrapped with
+    pub fn unwrap(&self, passphrase: &[u8]) -> SharingResult<Share> {
+        let cipher = self.params.derive_cipher(passphrase, &self.salt)?;
+        let header = self.header();
+        let mut value = cipher
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| SharingError::ShareDecryptionFailed)?;
+        let share = Share {
+            identifier: self.identifier,
+            value: value.clone(),
+        };
+        value.zeroize();
+        Ok(share)
+    }
+
+    /// Serialize the wrapped share
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut o = self.header().to_vec();
+        o.extend_from_slice(&self.ciphertext);
+        o
+    }
+
+    fn header(&self) -> [u8; HEADER_BYTES] {
+        let mut h = [0u8; HEADER_BYTES];
+        h[0] = VERSION;
+        h[1..5].copy_from_slice(&self.params.memory_kib.to_be_bytes());
+        h[5..9].copy_from_slice(&self.params.iterations.to_be_bytes());
+        h[9..13].copy_from_slice(&self.params.parallelism.to_be_bytes());
+        h[13..13 + SALT_BYTES].copy_from_slice(&self.salt);
+        h[13 + SALT_BYTES..13 + SALT_BYTES + NONCE_BYTES].copy_from_slice(&self.nonce);
+        h[HEADER_BYTES - 4..].copy_from_slice(&self.identifier.to_be_bytes());
+        h
+    }
+}
+
+impl TryFrom<&[u8]> for WrappedShare {
+    type Error = SharingError;
+
+    fn try_from(data: &[u8]) -> SharingResult<Self> {
+        match data.first() {
+            None => return Err(SharingError::ShareInvalidEncoding),
+            Some(&VERSION) => {}
+            Some(&v) => return Err(SharingError::ShareUnsupportedVersion(v)),
+        }
+        if data.len() < HEADER_BYTES + TAG_BYTES {
+            return Err(SharingError::ShareInvalidEncoding);
+        }
+        let u32_at = |offset: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_be_bytes(b)
+        };
+        let params = WrapParams {
+            memory_kib: u32_at(1),
+            iterations: u32_at(5),
+            parallelism: u32_at(9),
+        };
+        params.argon2()?;
+        let mut salt = [0u8; SALT_BYTES];
+        salt.copy_from_slice(&data[13..13 + SALT_BYTES]);
+        let mut nonce = [0u8; NONCE_BYTES];
+        nonce.copy_from_slice(&data[13 + SALT_BYTES..13 + SALT_BYTES + NONCE_BYTES]);
+        let identifier = u32_at(HEADER_BYTES - 4);
+        if identifier == 0 {
+            return Err(SharingError::ShareInvalidIdentifier);
+        }
+        Ok(Self {
+            params,
+            salt,
+            nonce,
+            identifier,
+            ciphertext: data[HEADER_BYTES..].to_vec(),
+        })
+    }
+}
+
+/// Encrypt `share` under `passphrase` with a fresh salt
+pub fn wrap_share(
+    rng: &mut (impl RngCore + CryptoRng),
+    share: &Share,
+    passphrase: &[u8],
+    params: WrapParams,
+) -> SharingResult<WrappedShare> {
+    let mut wrapped = WrappedShare {
+        params,
+        salt: [0u8; SALT_BYTES],
+        nonce: [0u8; NONCE_BYTES],
+        identifier: share.identifier,
+        ciphertext: Vec::new(),
+    };
+    rng.fill_bytes(&mut wrapped.salt);
+    rng.fill_bytes(&mut wrapped.nonce);
+    let cipher = params.derive_cipher(passphrase, &wrapped.salt)?;
+    let header = wrapped.header();
+    wrapped.ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&wrapped.nonce),
+            Payload {
+                msg: &share.value,
+                aad: &header,
+            },
+        )
+        .map_err(|_| SharingError::ShareInvalidValue)?;
+    Ok(wrapped)
+}
+
+/// Encrypt each share under `passphrase`, each with its own salt
+pub fn wrap_shares(
+    rng: &mut (impl RngCore + CryptoRng),
+    shares: &[Share],
+    passphrase: &[u8],
+    params: WrapParams,
+) -> SharingResult<Vec<WrappedShare>> {
+    shares
+        .iter()
+        .map(|share| wrap_share(rng, share, passphrase, params))
+        .collect()
+}
+
+/// Decrypt each share with `passphrase`
+pub fn unwrap_shares(shares: &[WrappedShare], passphrase: &[u8]) -> SharingResult<Vec<Share>> {
+    shares
+        .iter()
+        .map(|share| share.unwrap(passphrase))
+        .collect()
+}
@@ -0,0 +1,52 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// The bindings call into JavaScript so only run on wasm32-unknown-unknown
+#![cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+
+use ursa_sharing::wasm::{
+    combine_bytes, combine_shares, random_secret, share_identifier, split_bytes, split_secret,
+};
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+#[test]
+fn split_combine_secret() {
+    let secret = random_secret("ed25519").unwrap();
+    assert_eq!(secret.len(), 32);
+    assert_ne!(secret, random_secret("ed25519").unwrap());
+
+    let shares = split_secret("ed25519", &secret, 2, 3).unwrap();
+    assert_eq!(shares.len(), 3);
+    for (i, s) in shares.iter().enumerate() {
+        assert_eq!(share_identifier(&s.to_vec()).unwrap(), i as u32 + 1);
+    }
+    let res = combine_shares("ed25519", 2, vec![shares[0].clone(), shares[2].clone()]);
+    assert_eq!(res.unwrap(), secret);
+    assert!(combine_shares("ed25519", 2, vec![shares[1].clone()]).is_err());
+    assert!(split_secret("ed25519", &secret, 3, 2).is_err());
+}
+
+#[test]
+fn unsupported_curve() {
+    assert!(random_secret("secp256k1").is_err());
+    assert!(split_secret("curve448", &[1u8; 32], 2, 3).is_err());
+}
+
+#[test]
+fn split_combine_bytes() {
+    let seed = b"abandon ability able about above absent";
+    let shares = split_bytes(seed, 3, 5).unwrap();
+    let res = combine_bytes(3, shares[1..4].to_vec());
+    assert_eq!(res.unwrap(), seed.to_vec());
+    assert!(combine_bytes(3, shares[..2].to_vec()).is_err());
+}
@@ -0,0 +1,75 @@
+// Copyright 2020 Hyperledger Ursa Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+use rand::{Error, RngCore};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ursa_sharing::{
+    backup::{backup_secret, restore_secret},
+    ed25519::Ed25519Scalar,
+    entropy::{clear_entropy_source, set_entropy_source, EntropySource, SystemRng},
+    shamir::Scheme,
+    Group,
+};
+
+#[test]
+fn system_rng_works() {
+    let mut a = [0u8; 32];
+    let mut b = [0u8; 32];
+    SystemRng.fill_bytes(&mut a);
+    SystemRng.fill_bytes(&mut b);
+    assert_ne!(a, [0u8; 32]);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn split_combine() {
+    let mut rng = SystemRng;
+    let scheme = Scheme::new(2, 3).unwrap();
+    let secret = Ed25519Scalar::random(&mut rng);
+    let shares = scheme.split_secret(&mut rng, &secret).unwrap();
+    let res = scheme.combine_shares::<Ed25519Scalar, Ed25519Scalar>(&shares[1..]);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().to_bytes(), secret.to_bytes());
+}
+
+#[test]
+fn backup_restore() {
+    let secret = b"a secret backed up on wasm";
+    let shares = backup_secret(secret, 2, 3, Some(b"passphrase")).unwrap();
+    let res = restore_secret(&shares[..2], Some(b"passphrase"));
+    assert_eq!(res.unwrap(), secret.to_vec());
+}
+
+static FILLS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingSource;
+
+impl EntropySource for CountingSource {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Error> {
+        FILLS.fetch_add(1, Ordering::SeqCst);
+        rand::rngs::OsRng.try_fill_bytes(dest)
+    }
+}
+
+#[test]
+fn injected_entropy_source() {
+    set_entropy_source(Box::new(CountingSource));
+    let before = FILLS.load(Ordering::SeqCst);
+    let mut bytes = [0u8; 16];
+    SystemRng.fill_bytes(&mut bytes);
+    clear_entropy_source();
+    assert!(FILLS.load(Ordering::SeqCst) > before);
+}